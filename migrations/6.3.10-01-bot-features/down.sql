@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Bot Features
+-- ============================================
+
+DROP TABLE IF EXISTS bot_features;
@@ -0,0 +1,15 @@
+-- ============================================
+-- Bot Features
+-- Version: 6.3.10
+-- ============================================
+-- Runtime per-bot overrides for capabilities compiled into the server.
+-- A bot without a row for a feature gets the default (enabled).
+
+CREATE TABLE IF NOT EXISTS bot_features (
+    bot_id UUID NOT NULL REFERENCES bots(id) ON DELETE CASCADE,
+    feature VARCHAR(32) NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (bot_id, feature)
+);
@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Key Rotations
+-- ============================================
+
+DROP TABLE IF EXISTS encryption_key_rotations;
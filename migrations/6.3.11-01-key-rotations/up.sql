@@ -0,0 +1,26 @@
+-- ============================================
+-- Key Rotations
+-- Version: 6.3.11
+-- ============================================
+-- Progress of master key rotations. A rotation stays 'running' until every
+-- stored secret is re-encrypted under to_version; an interrupted or failed
+-- rotation is resumed by starting a rotation again.
+
+CREATE TABLE IF NOT EXISTS encryption_key_rotations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    from_version INTEGER NOT NULL,
+    to_version INTEGER NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'running',
+    total BIGINT NOT NULL DEFAULT 0,
+    processed BIGINT NOT NULL DEFAULT 0,
+    skipped BIGINT NOT NULL DEFAULT 0,
+    failed BIGINT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    started_by UUID,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_encryption_key_rotations_started
+    ON encryption_key_rotations(started_at DESC);
@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Default Bot
+-- ============================================
+
+ALTER TABLE bots DROP COLUMN IF EXISTS is_default;
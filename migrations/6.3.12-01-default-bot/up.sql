@@ -0,0 +1,10 @@
+-- ============================================
+-- Default Bot
+-- Version: 6.3.12
+-- ============================================
+-- Bot served when a request does not name one. At most one bot is marked; without a
+-- mark the bot named 'default' is used. The index enforcing a single mark needs
+-- `bots.deleted_at` and lives in 6.3.6-02, which runs after the soft-delete columns.
+
+ALTER TABLE bots
+    ADD COLUMN IF NOT EXISTS is_default BOOLEAN NOT NULL DEFAULT false;
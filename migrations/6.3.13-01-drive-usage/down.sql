@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Drive Usage
+-- ============================================
+
+DROP TABLE IF EXISTS drive_usage;
@@ -0,0 +1,13 @@
+-- ============================================
+-- Drive Usage
+-- Version: 6.3.13
+-- ============================================
+-- Bytes stored per drive bucket, updated on every write made through the quota
+-- checks, and an optional per-bucket quota overriding DRIVE_QUOTA_MB.
+
+CREATE TABLE IF NOT EXISTS drive_usage (
+    bucket VARCHAR(255) PRIMARY KEY,
+    used_bytes BIGINT NOT NULL DEFAULT 0,
+    quota_bytes BIGINT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
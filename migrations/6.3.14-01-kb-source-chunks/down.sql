@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback KB Source Chunks
+-- ============================================
+
+DROP TABLE IF EXISTS kb_source_chunks;
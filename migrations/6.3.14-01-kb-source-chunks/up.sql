@@ -0,0 +1,14 @@
+-- ============================================
+-- KB Source Chunks
+-- Version: 6.3.14
+-- ============================================
+-- Vector point ids written for each ingested source document, per collection, so
+-- re-ingesting a changed document deletes exactly the chunks it no longer has.
+
+CREATE TABLE IF NOT EXISTS kb_source_chunks (
+    collection_name VARCHAR(255) NOT NULL,
+    source TEXT NOT NULL,
+    chunk_ids UUID[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (collection_name, source)
+);
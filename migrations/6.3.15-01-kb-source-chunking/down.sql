@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback KB Source Chunking
+-- ============================================
+
+ALTER TABLE kb_source_chunks DROP COLUMN IF EXISTS chunking;
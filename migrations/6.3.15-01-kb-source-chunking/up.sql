@@ -0,0 +1,8 @@
+-- ============================================
+-- KB Source Chunking
+-- Version: 6.3.15
+-- ============================================
+-- Chunking strategy (e.g. `markdown:1000/200`) each source was last ingested with.
+-- When it changes, the source's points are cleared instead of diffed by chunk id.
+
+ALTER TABLE kb_source_chunks ADD COLUMN IF NOT EXISTS chunking VARCHAR(64);
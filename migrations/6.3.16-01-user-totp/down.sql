@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback User TOTP
+-- ============================================
+
+DROP TABLE IF EXISTS user_totp;
@@ -0,0 +1,20 @@
+-- ============================================
+-- User TOTP
+-- Version: 6.3.16
+-- ============================================
+-- Authenticator-app second factor for directory users. The secret is sealed with the
+-- master key; recovery codes are stored as SHA-256 hashes. last_step is the last
+-- accepted time step, so a code cannot be replayed within its validity window.
+
+CREATE TABLE IF NOT EXISTS user_totp (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id VARCHAR(255) NOT NULL UNIQUE,
+    secret_encrypted TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    recovery_codes JSONB NOT NULL DEFAULT '[]'::jsonb,
+    last_step BIGINT,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Bot Quick Replies
+-- ============================================
+
+DROP TABLE IF EXISTS bot_quick_replies;
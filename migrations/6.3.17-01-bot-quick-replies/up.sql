@@ -0,0 +1,23 @@
+-- ============================================
+-- Bot Quick Replies
+-- Version: 6.3.17
+-- ============================================
+-- Canned responses returned instead of calling the LLM when a message
+-- matches the trigger. Higher priority is checked first.
+
+CREATE TABLE IF NOT EXISTS bot_quick_replies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    bot_id UUID NOT NULL REFERENCES bots(id) ON DELETE CASCADE,
+    trigger_kind VARCHAR(16) NOT NULL,
+    pattern TEXT NOT NULL,
+    response TEXT NOT NULL,
+    priority INTEGER NOT NULL DEFAULT 0,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT bot_quick_replies_kind_check CHECK (trigger_kind IN ('exact', 'prefix', 'regex'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_bot_quick_replies_bot
+    ON bot_quick_replies (bot_id, priority DESC);
@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Sheet Saved Queries
+-- ============================================
+
+DROP TABLE IF EXISTS sheet_saved_queries;
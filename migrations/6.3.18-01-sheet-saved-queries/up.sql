@@ -0,0 +1,21 @@
+-- ============================================
+-- Sheet Saved Queries
+-- Version: 6.3.18
+-- ============================================
+-- SELECT statements registered by admins that users can run to fill a
+-- worksheet. Users pick a query by id and supply typed parameters; the SQL
+-- itself is never taken from the import request.
+
+CREATE TABLE IF NOT EXISTS sheet_saved_queries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(128) NOT NULL,
+    description TEXT,
+    sql_text TEXT NOT NULL,
+    params JSONB NOT NULL DEFAULT '[]',
+    max_rows INTEGER NOT NULL DEFAULT 1000,
+    created_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT sheet_saved_queries_name_unique UNIQUE (name),
+    CONSTRAINT sheet_saved_queries_max_rows_check CHECK (max_rows > 0)
+);
@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Sheet Inbound Webhooks
+-- ============================================
+
+DROP TABLE IF EXISTS sheet_webhooks;
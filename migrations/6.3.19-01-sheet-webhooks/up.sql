@@ -0,0 +1,15 @@
+-- ============================================
+-- Sheet Inbound Webhooks
+-- Version: 6.3.19
+-- ============================================
+-- One signing secret per sheet. External systems sign pushes to
+-- /webhook/sheet/:sheet_id with it; owner_id locates the sheet in the drive.
+
+CREATE TABLE IF NOT EXISTS sheet_webhooks (
+    sheet_id VARCHAR(64) PRIMARY KEY,
+    owner_id VARCHAR(255) NOT NULL,
+    secret TEXT NOT NULL,
+    created_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_received_at TIMESTAMPTZ
+);
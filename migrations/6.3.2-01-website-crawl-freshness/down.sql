@@ -0,0 +1,10 @@
+-- ============================================
+-- Rollback Website Crawl Freshness Tracking
+-- ============================================
+
+ALTER TABLE website_crawls
+    DROP COLUMN IF EXISTS last_changed,
+    DROP COLUMN IF EXISTS change_detected;
+
+DROP INDEX IF EXISTS idx_website_crawl_pages_crawl;
+DROP TABLE IF EXISTS website_crawl_pages;
@@ -0,0 +1,23 @@
+-- ============================================
+-- Website Crawl Freshness Tracking
+-- Version: 6.3.2
+-- ============================================
+-- Stores a content hash per crawled page so unchanged pages
+-- are not re-embedded on recrawl, and exposes change detection
+-- per source for the sources status endpoint
+
+CREATE TABLE IF NOT EXISTS website_crawl_pages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    crawl_id UUID NOT NULL REFERENCES website_crawls(id) ON DELETE CASCADE,
+    page_url TEXT NOT NULL,
+    content_hash VARCHAR(64) NOT NULL,
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(crawl_id, page_url)
+);
+
+CREATE INDEX IF NOT EXISTS idx_website_crawl_pages_crawl ON website_crawl_pages(crawl_id);
+
+ALTER TABLE website_crawls
+    ADD COLUMN IF NOT EXISTS last_changed TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS change_detected BOOLEAN NOT NULL DEFAULT FALSE;
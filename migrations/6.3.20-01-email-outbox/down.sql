@@ -0,0 +1,6 @@
+-- ============================================
+-- Rollback Outbound Email Queue
+-- ============================================
+
+ALTER TABLE user_email_accounts DROP COLUMN IF EXISTS send_rate_per_minute;
+DROP TABLE IF EXISTS email_outbox;
@@ -0,0 +1,34 @@
+-- ============================================
+-- Outbound Email Queue
+-- Version: 6.3.20
+-- ============================================
+-- /api/email/send stores messages here and returns right away; a worker
+-- sends them at each account's rate and retries transient SMTP failures.
+
+CREATE TABLE IF NOT EXISTS email_outbox (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    account_id UUID NOT NULL REFERENCES user_email_accounts(id) ON DELETE CASCADE,
+    to_address TEXT NOT NULL,
+    cc TEXT,
+    bcc TEXT,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    tracking_id UUID,
+    status VARCHAR(16) NOT NULL DEFAULT 'queued',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    claimed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ,
+    CONSTRAINT email_outbox_status_check
+        CHECK (status IN ('queued', 'sending', 'sent', 'failed'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_outbox_due
+    ON email_outbox (next_attempt_at) WHERE status = 'queued';
+CREATE INDEX IF NOT EXISTS idx_email_outbox_account
+    ON email_outbox (account_id, created_at DESC);
+
+-- Overrides the provider default send rate for one account.
+ALTER TABLE user_email_accounts ADD COLUMN IF NOT EXISTS send_rate_per_minute INTEGER;
@@ -0,0 +1,8 @@
+-- ============================================
+-- Rollback Session Forks
+-- ============================================
+
+DROP INDEX IF EXISTS idx_user_sessions_parent;
+
+ALTER TABLE user_sessions DROP COLUMN IF EXISTS forked_at_index;
+ALTER TABLE user_sessions DROP COLUMN IF EXISTS parent_session_id;
@@ -0,0 +1,12 @@
+-- ============================================
+-- Session Forks
+-- Version: 6.3.21
+-- ============================================
+-- A fork is a session whose history starts as a copy of its parent's, up to a message
+
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS parent_session_id UUID
+    REFERENCES user_sessions(id) ON DELETE SET NULL;
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS forked_at_index INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_user_sessions_parent
+    ON user_sessions(parent_session_id) WHERE parent_session_id IS NOT NULL;
@@ -0,0 +1,6 @@
+-- ============================================
+-- Rollback Email Message Index
+-- ============================================
+
+DROP TABLE IF EXISTS email_message_index;
+DROP TABLE IF EXISTS email_folder_sync;
@@ -0,0 +1,33 @@
+-- ============================================
+-- Email Message Index
+-- Version: 6.3.22
+-- ============================================
+-- Local copy of each folder's messages, keyed by IMAP UID. /api/email/list
+-- fetches only UIDs above highest_uid and serves pages from this table. A
+-- UIDVALIDITY change discards the folder's rows and rebuilds them.
+
+CREATE TABLE IF NOT EXISTS email_folder_sync (
+    account_id UUID NOT NULL REFERENCES user_email_accounts(id) ON DELETE CASCADE,
+    folder TEXT NOT NULL,
+    uid_validity BIGINT NOT NULL,
+    highest_uid BIGINT NOT NULL DEFAULT 0,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (account_id, folder)
+);
+
+CREATE TABLE IF NOT EXISTS email_message_index (
+    account_id UUID NOT NULL REFERENCES user_email_accounts(id) ON DELETE CASCADE,
+    folder TEXT NOT NULL,
+    uid BIGINT NOT NULL,
+    from_name TEXT NOT NULL DEFAULT '',
+    from_email TEXT NOT NULL DEFAULT '',
+    to_addrs TEXT NOT NULL DEFAULT '',
+    subject TEXT NOT NULL DEFAULT '',
+    preview TEXT NOT NULL DEFAULT '',
+    body TEXT NOT NULL DEFAULT '',
+    date_header TEXT NOT NULL DEFAULT '',
+    has_attachments BOOLEAN NOT NULL DEFAULT false,
+    seen BOOLEAN NOT NULL DEFAULT false,
+    indexed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (account_id, folder, uid)
+);
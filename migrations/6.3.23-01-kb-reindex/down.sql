@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback KB Reindex Jobs
+-- ============================================
+
+DROP TABLE IF EXISTS kb_reindex_jobs;
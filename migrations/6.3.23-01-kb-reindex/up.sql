@@ -0,0 +1,15 @@
+-- ============================================
+-- KB Reindex Jobs
+-- Version: 6.3.23
+-- ============================================
+-- Full rebuilds of a knowledge base that have started but not yet been swapped
+-- in. Running the reindex again continues into the same shadow collection; the
+-- documents it already holds are listed in kb_source_chunks under its name.
+
+CREATE TABLE IF NOT EXISTS kb_reindex_jobs (
+    bot_id UUID NOT NULL,
+    kb_name VARCHAR(255) NOT NULL,
+    shadow_collection VARCHAR(255) NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (bot_id, kb_name)
+);
@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Session Variables
+-- ============================================
+
+DROP TABLE IF EXISTS session_variables;
@@ -0,0 +1,13 @@
+-- ============================================
+-- Session Variables
+-- Version: 6.3.24
+-- ============================================
+-- Typed values a bot keeps for one conversation across turns, such as form
+-- fields collected so far. One JSON object per session; its serialized size
+-- is capped by the server.
+
+CREATE TABLE IF NOT EXISTS session_variables (
+    session_id UUID PRIMARY KEY REFERENCES user_sessions(id) ON DELETE CASCADE,
+    variables JSONB NOT NULL DEFAULT '{}'::jsonb,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
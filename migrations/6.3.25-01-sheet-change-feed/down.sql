@@ -0,0 +1,6 @@
+-- ============================================
+-- Rollback Sheet Change Feed
+-- ============================================
+
+DROP TABLE IF EXISTS sheet_changes;
+DROP TABLE IF EXISTS sheet_change_feeds;
@@ -0,0 +1,24 @@
+-- ============================================
+-- Sheet Change Feed
+-- Version: 6.3.25
+-- ============================================
+-- Append-only log of sheet edits, read by integrations that mirror sheets.
+-- sheet_change_feeds holds each sheet's latest sequence; entries up to
+-- compacted_through were dropped and replaced by snapshot snapshot_id.
+
+CREATE TABLE IF NOT EXISTS sheet_change_feeds (
+    sheet_id VARCHAR(64) PRIMARY KEY,
+    last_seq BIGINT NOT NULL DEFAULT 0,
+    compacted_through BIGINT NOT NULL DEFAULT 0,
+    snapshot_id VARCHAR(64),
+    snapshot_seq BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS sheet_changes (
+    sheet_id VARCHAR(64) NOT NULL REFERENCES sheet_change_feeds(sheet_id) ON DELETE CASCADE,
+    seq BIGINT NOT NULL,
+    actor VARCHAR(255) NOT NULL,
+    change JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (sheet_id, seq)
+);
@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Shared Email Send Slots
+-- ============================================
+
+ALTER TABLE user_email_accounts DROP COLUMN IF EXISTS outbox_next_send_at;
@@ -0,0 +1,8 @@
+-- ============================================
+-- Shared Email Send Slots
+-- Version: 6.3.26
+-- ============================================
+-- The outbox worker reserves each account's next send slot on the account
+-- row, so several servers sharing the database keep to one send rate.
+
+ALTER TABLE user_email_accounts ADD COLUMN IF NOT EXISTS outbox_next_send_at TIMESTAMPTZ;
@@ -0,0 +1,7 @@
+-- ============================================
+-- Rollback Drive Usage per Owner
+-- ============================================
+
+DELETE FROM drive_usage WHERE owner LIKE 'user:%';
+
+ALTER TABLE drive_usage RENAME COLUMN owner TO bucket;
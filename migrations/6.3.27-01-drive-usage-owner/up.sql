@@ -0,0 +1,12 @@
+-- ============================================
+-- Drive Usage per Owner
+-- Version: 6.3.27
+-- ============================================
+-- Usage is now kept per owner: `user:{id}` for files under a user's area, the
+-- bucket (the bot, for `{bot}.gbai`) for everything else. Counters of buckets
+-- that held user areas mixed every user together, so they start over; recount
+-- an owner with POST /api/admin/drive/usage/:owner/recalculate.
+
+ALTER TABLE drive_usage RENAME COLUMN bucket TO owner;
+
+UPDATE drive_usage SET used_bytes = 0, updated_at = NOW() WHERE owner NOT LIKE '%.gbai';
@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Revoked Session Tokens
+-- ============================================
+
+DROP TABLE IF EXISTS revoked_session_tokens;
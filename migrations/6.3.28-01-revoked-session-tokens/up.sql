@@ -0,0 +1,16 @@
+-- ============================================
+-- Revoked Session Tokens
+-- Version: 6.3.28
+-- ============================================
+-- Access tokens ended by logout or session revocation, as SHA-256 hashes. Every
+-- instance loads them at startup and re-reads them every few seconds, so a revoked
+-- token stays refused after a restart and on other instances. Rows older than the
+-- retention window are deleted as they are read.
+
+CREATE TABLE IF NOT EXISTS revoked_session_tokens (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_revoked_session_tokens_revoked_at
+    ON revoked_session_tokens (revoked_at);
@@ -0,0 +1,6 @@
+-- ============================================
+-- Rollback Analytics Rollups
+-- ============================================
+
+DROP INDEX IF EXISTS idx_analytics_rollups_lookup;
+DROP TABLE IF EXISTS analytics_rollups;
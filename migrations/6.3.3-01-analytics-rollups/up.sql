@@ -0,0 +1,19 @@
+-- ============================================
+-- Analytics Rollups
+-- Version: 6.3.3
+-- ============================================
+-- Per-minute/hour/day aggregates of key metrics used by
+-- the analytics time-series query endpoint
+
+CREATE TABLE IF NOT EXISTS analytics_rollups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    metric VARCHAR(64) NOT NULL,
+    granularity VARCHAR(16) NOT NULL,
+    bucket_start TIMESTAMPTZ NOT NULL,
+    value DOUBLE PRECISION NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(metric, granularity, bucket_start)
+);
+
+CREATE INDEX IF NOT EXISTS idx_analytics_rollups_lookup
+    ON analytics_rollups(metric, granularity, bucket_start);
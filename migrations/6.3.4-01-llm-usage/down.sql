@@ -0,0 +1,8 @@
+-- ============================================
+-- Rollback LLM Usage Accounting
+-- ============================================
+
+DROP INDEX IF EXISTS idx_llm_usage_created;
+DROP INDEX IF EXISTS idx_llm_usage_session;
+DROP INDEX IF EXISTS idx_llm_usage_bot_created;
+DROP TABLE IF EXISTS llm_usage;
@@ -0,0 +1,22 @@
+-- ============================================
+-- LLM Usage Accounting
+-- Version: 6.3.4
+-- ============================================
+-- One row per LLM call with token counts and the priced cost,
+-- keyed by bot and session for cost reports and analytics rollups
+
+CREATE TABLE IF NOT EXISTS llm_usage (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    bot_id UUID NOT NULL,
+    session_id UUID NOT NULL,
+    model VARCHAR(255) NOT NULL DEFAULT '',
+    prompt_tokens BIGINT NOT NULL DEFAULT 0,
+    completion_tokens BIGINT NOT NULL DEFAULT 0,
+    cost DOUBLE PRECISION NOT NULL DEFAULT 0,
+    cached BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_llm_usage_bot_created ON llm_usage(bot_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_llm_usage_session ON llm_usage(session_id);
+CREATE INDEX IF NOT EXISTS idx_llm_usage_created ON llm_usage(created_at);
@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback LLM Usage Provider
+-- ============================================
+
+ALTER TABLE llm_usage DROP COLUMN IF EXISTS provider;
@@ -0,0 +1,7 @@
+-- ============================================
+-- LLM Usage Provider
+-- Version: 6.3.5
+-- ============================================
+-- Records which provider in a bot's fallback chain served each call
+
+ALTER TABLE llm_usage ADD COLUMN IF NOT EXISTS provider VARCHAR(64) NOT NULL DEFAULT 'primary';
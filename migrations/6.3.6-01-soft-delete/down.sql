@@ -0,0 +1,12 @@
+-- ============================================
+-- Rollback Soft Delete
+-- ============================================
+
+DROP INDEX IF EXISTS idx_user_sessions_deleted_at;
+DROP INDEX IF EXISTS idx_bots_deleted_at;
+
+ALTER TABLE user_sessions DROP COLUMN IF EXISTS deleted_by;
+ALTER TABLE user_sessions DROP COLUMN IF EXISTS deleted_at;
+
+ALTER TABLE bots DROP COLUMN IF EXISTS deleted_by;
+ALTER TABLE bots DROP COLUMN IF EXISTS deleted_at;
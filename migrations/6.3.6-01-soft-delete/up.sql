@@ -0,0 +1,14 @@
+-- ============================================
+-- Soft Delete
+-- Version: 6.3.6
+-- ============================================
+-- Deleted bots and sessions keep their rows until purged, so they can be restored
+
+ALTER TABLE bots ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE bots ADD COLUMN IF NOT EXISTS deleted_by UUID;
+
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE user_sessions ADD COLUMN IF NOT EXISTS deleted_by UUID;
+
+CREATE INDEX IF NOT EXISTS idx_bots_deleted_at ON bots(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_user_sessions_deleted_at ON user_sessions(deleted_at) WHERE deleted_at IS NOT NULL;
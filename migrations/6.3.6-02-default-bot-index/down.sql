@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Single Default Bot
+-- ============================================
+
+DROP INDEX IF EXISTS idx_bots_single_default;
@@ -0,0 +1,11 @@
+-- ============================================
+-- Single Default Bot
+-- Version: 6.3.6-02
+-- ============================================
+-- At most one live bot carries the default mark; a deleted bot keeps its mark without
+-- blocking a new one. Migrations run in string order of their versions ("6.3.1201" <
+-- "6.3.601" < "6.3.602"), so this comes after both 6.3.12-01, which adds `is_default`,
+-- and 6.3.6-01, which adds `deleted_at`.
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_bots_single_default
+    ON bots (is_default) WHERE is_default AND deleted_at IS NULL;
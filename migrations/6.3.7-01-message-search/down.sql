@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Message Search
+-- ============================================
+
+DROP INDEX IF EXISTS idx_message_history_content_fts;
@@ -0,0 +1,10 @@
+-- ============================================
+-- Message Search
+-- Version: 6.3.7
+-- ============================================
+-- Full-text index over message bodies for /api/sessions/search.
+-- The 'simple' configuration avoids language-specific stemming, since bots
+-- converse in many languages. Queries must use the same expression.
+
+CREATE INDEX IF NOT EXISTS idx_message_history_content_fts
+    ON message_history USING GIN (to_tsvector('simple', content_encrypted));
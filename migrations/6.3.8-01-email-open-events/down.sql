@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Email Open Events
+-- ============================================
+
+DROP TABLE IF EXISTS email_open_events;
@@ -0,0 +1,22 @@
+-- ============================================
+-- Email Open Events
+-- Version: 6.3.8
+-- ============================================
+-- One row per tracking pixel request for a known tracking id. Only rows with
+-- counted = true (a human open, first in its dedup window) update read_count
+-- and feed the tracking stats; proxy prefetches and bots are kept for auditing.
+
+CREATE TABLE IF NOT EXISTS email_open_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tracking_id UUID NOT NULL REFERENCES sent_email_tracking(tracking_id) ON DELETE CASCADE,
+    opened_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    client_ip VARCHAR(45),
+    user_agent TEXT,
+    source VARCHAR(16) NOT NULL,
+    counted BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_open_events_tracking
+    ON email_open_events(tracking_id, opened_at DESC);
+CREATE INDEX IF NOT EXISTS idx_email_open_events_counted
+    ON email_open_events(tracking_id) WHERE counted;
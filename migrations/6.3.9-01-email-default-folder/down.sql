@@ -0,0 +1,5 @@
+-- ============================================
+-- Rollback Email Default Folder
+-- ============================================
+
+ALTER TABLE user_email_accounts DROP COLUMN IF EXISTS default_folder;
@@ -0,0 +1,8 @@
+-- ============================================
+-- Email Default Folder
+-- Version: 6.3.9
+-- ============================================
+-- Folder opened by /api/email/list when the request does not name one.
+
+ALTER TABLE user_email_accounts
+    ADD COLUMN IF NOT EXISTS default_folder VARCHAR(255) NOT NULL DEFAULT 'INBOX';
@@ -14,6 +14,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::shared::schema::{okr_checkins, okr_key_results, okr_objectives, okr_templates};
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 fn get_bot_context() -> (Uuid, Uuid) {
@@ -613,7 +614,7 @@ pub async fn list_objectives(
 pub async fn create_objective(
     State(state): State<Arc<AppState>>,
     user: crate::security::auth::AuthenticatedUser,
-    Json(req): Json<CreateObjectiveRequest>,
+    ApiJson(req): ApiJson<CreateObjectiveRequest>,
 ) -> Result<Json<Objective>, GoalsError> {
     let pool = state.conn.clone();
     let (org_id, bot_id) = get_bot_context();
@@ -688,7 +689,7 @@ pub async fn get_objective(
 pub async fn update_objective(
     State(state): State<Arc<AppState>>,
     Path(objective_id): Path<Uuid>,
-    Json(req): Json<UpdateObjectiveRequest>,
+    ApiJson(req): ApiJson<UpdateObjectiveRequest>,
 ) -> Result<Json<Objective>, GoalsError> {
     let pool = state.conn.clone();
 
@@ -789,7 +790,7 @@ pub async fn create_key_result(
     State(state): State<Arc<AppState>>,
     user: crate::security::auth::AuthenticatedUser,
     Path(objective_id): Path<Uuid>,
-    Json(req): Json<CreateKeyResultRequest>,
+    ApiJson(req): ApiJson<CreateKeyResultRequest>,
 ) -> Result<Json<KeyResult>, GoalsError> {
     let pool = state.conn.clone();
     let (org_id, bot_id) = get_bot_context();
@@ -839,7 +840,7 @@ pub async fn create_key_result(
 pub async fn update_key_result(
     State(state): State<Arc<AppState>>,
     Path(key_result_id): Path<Uuid>,
-    Json(req): Json<UpdateKeyResultRequest>,
+    ApiJson(req): ApiJson<UpdateKeyResultRequest>,
 ) -> Result<Json<KeyResult>, GoalsError> {
     let pool = state.conn.clone();
 
@@ -919,7 +920,7 @@ pub async fn create_check_in(
     State(state): State<Arc<AppState>>,
     user: crate::security::auth::AuthenticatedUser,
     Path(key_result_id): Path<Uuid>,
-    Json(req): Json<CreateCheckInRequest>,
+    ApiJson(req): ApiJson<CreateCheckInRequest>,
 ) -> Result<Json<CheckIn>, GoalsError> {
     let pool = state.conn.clone();
     let (org_id, bot_id) = get_bot_context();
@@ -1122,7 +1123,7 @@ pub async fn get_alignment(
 }
 
 pub async fn ai_suggest(
-    Json(_req): Json<AISuggestRequest>,
+    ApiJson(_req): ApiJson<AISuggestRequest>,
 ) -> Result<Json<Vec<AISuggestion>>, GoalsError> {
     let suggestions = vec![
         AISuggestion {
@@ -13,6 +13,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::core::shared::utils::DbPool;
 
@@ -705,7 +706,7 @@ impl IntoResponse for InsightsError {
 
 pub async fn handle_track_usage(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<TrackUsageRequest>,
+    ApiJson(req): ApiJson<TrackUsageRequest>,
 ) -> Result<Json<AppUsage>, InsightsError> {
     let service = InsightsService::new(_state.conn.clone());
     let user_id = Uuid::nil();
@@ -767,7 +768,7 @@ pub async fn handle_get_settings(
 
 pub async fn handle_update_settings(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<UpdateSettingsRequest>,
+    ApiJson(req): ApiJson<UpdateSettingsRequest>,
 ) -> Result<Json<InsightsSettings>, InsightsError> {
     let service = InsightsService::new(_state.conn.clone());
     let user_id = Uuid::nil();
@@ -777,7 +778,7 @@ pub async fn handle_update_settings(
 
 pub async fn handle_update_focus_mode(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<UpdateFocusModeRequest>,
+    ApiJson(req): ApiJson<UpdateFocusModeRequest>,
 ) -> Result<Json<FocusMode>, InsightsError> {
     let service = InsightsService::new(_state.conn.clone());
     let user_id = Uuid::nil();
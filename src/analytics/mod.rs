@@ -3,14 +3,18 @@ pub mod goals;
 #[cfg(feature = "goals")]
 pub mod goals_ui;
 pub mod insights;
+pub mod rollups;
+#[cfg(feature = "sheet")]
+pub mod sheet_export;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::urls::ApiUrls;
 use crate::core::shared::state::AppState;
 use axum::{
     extract::State,
     response::{Html, IntoResponse},
     routing::{get, post},
-    Json, Router,
+    Router,
 };
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -134,12 +138,26 @@ pub fn configure_analytics_routes() -> Router<Arc<AppState>> {
             get(handle_recent_activity),
         )
         .route(ApiUrls::ANALYTICS_QUERIES_TOP, get(handle_top_queries))
-        .route(ApiUrls::ANALYTICS_CHAT, post(handle_analytics_chat));
+        .route(ApiUrls::ANALYTICS_CHAT, post(handle_analytics_chat))
+        .route(
+            ApiUrls::ANALYTICS_TIMESERIES,
+            get(rollups::handle_timeseries_query),
+        );
+
+    #[cfg(feature = "sheet")]
+    let router: Router<Arc<AppState>> = router.route(
+        ApiUrls::ANALYTICS_EXPORT_SHEET,
+        post(sheet_export::handle_export_analytics_sheet),
+    );
 
     #[cfg(feature = "llm")]
     let router: Router<Arc<AppState>> = router
         .route(ApiUrls::ANALYTICS_LLM_STATS, get(handle_llm_stats))
-        .route(ApiUrls::ANALYTICS_BUDGET_STATUS, get(handle_budget_status));
+        .route(ApiUrls::ANALYTICS_BUDGET_STATUS, get(handle_budget_status))
+        .route(
+            ApiUrls::ANALYTICS_LLM_COST,
+            get(crate::llm::usage::handle_cost_report),
+        );
 
     router
 }
@@ -780,7 +798,7 @@ pub async fn handle_top_queries(State(state): State<Arc<AppState>>) -> impl Into
 
 pub async fn handle_analytics_chat(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<AnalyticsQuery>,
+    ApiJson(payload): ApiJson<AnalyticsQuery>,
 ) -> impl IntoResponse {
     let query = payload.query.unwrap_or_default();
 
@@ -0,0 +1,420 @@
+use crate::core::shared::state::AppState;
+use crate::core::shared::utils::DbPool;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use diesel::prelude::*;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+const DEFAULT_MAX_POINTS: usize = 500;
+const MAX_POINTS_CAP: usize = 2000;
+/// Minute buckets are re-computed for this many minutes back to absorb late writes.
+const MINUTE_LOOKBACK: i64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl Granularity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Minute => Duration::minutes(1),
+            Self::Hour => Duration::hours(1),
+            Self::Day => Duration::days(1),
+        }
+    }
+
+    pub fn bucket_start(&self, ts: DateTime<Utc>) -> DateTime<Utc> {
+        ts.duration_trunc(self.duration()).unwrap_or(ts)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RollupMetric {
+    MessageVolume,
+    ActiveSessions,
+    LlmCost,
+}
+
+/// How two adjacent buckets are combined when coarsening or downsampling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combine {
+    Sum,
+    Max,
+}
+
+impl RollupMetric {
+    pub const ALL: [RollupMetric; 3] = [Self::MessageVolume, Self::ActiveSessions, Self::LlmCost];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MessageVolume => "message_volume",
+            Self::ActiveSessions => "active_sessions",
+            Self::LlmCost => "llm_cost",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    pub fn combine(&self) -> Combine {
+        match self {
+            Self::ActiveSessions => Combine::Max,
+            Self::MessageVolume | Self::LlmCost => Combine::Sum,
+        }
+    }
+
+    /// Raw-table aggregation each metric is recomputed from.
+    /// `$1` is the `date_trunc` unit, `$2`/`$3` the half-open time window.
+    fn source_sql(&self) -> &'static str {
+        match self {
+            Self::MessageVolume => {
+                "SELECT date_trunc($1, created_at) AS bucket, COUNT(*)::float8 AS value
+                 FROM message_history
+                 WHERE created_at >= $2 AND created_at < $3
+                 GROUP BY 1"
+            }
+            Self::ActiveSessions => {
+                "SELECT date_trunc($1, created_at) AS bucket, COUNT(DISTINCT session_id)::float8 AS value
+                 FROM message_history
+                 WHERE created_at >= $2 AND created_at < $3
+                 GROUP BY 1"
+            }
+            Self::LlmCost => {
+                "SELECT date_trunc($1, created_at) AS bucket, COALESCE(SUM(cost), 0)::float8 AS value
+                 FROM llm_usage
+                 WHERE created_at >= $2 AND created_at < $3
+                 GROUP BY 1"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SeriesPoint {
+    pub t: DateTime<Utc>,
+    pub v: f64,
+}
+
+/// Groups raw samples into `granularity` buckets inside `[from, to)`.
+/// A sample exactly on a bucket boundary belongs to the bucket that starts there.
+pub fn aggregate_samples(
+    samples: &[(DateTime<Utc>, f64)],
+    granularity: Granularity,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    combine: Combine,
+) -> Vec<SeriesPoint> {
+    let mut buckets: BTreeMap<DateTime<Utc>, f64> = BTreeMap::new();
+
+    for (ts, value) in samples {
+        if *ts < from || *ts >= to {
+            continue;
+        }
+        let bucket = granularity.bucket_start(*ts);
+        let entry = buckets.entry(bucket).or_insert(match combine {
+            Combine::Sum => 0.0,
+            Combine::Max => f64::MIN,
+        });
+        *entry = match combine {
+            Combine::Sum => *entry + value,
+            Combine::Max => entry.max(*value),
+        };
+    }
+
+    buckets
+        .into_iter()
+        .map(|(t, v)| SeriesPoint { t, v })
+        .collect()
+}
+
+/// Reduces a series to at most `max_points` by merging consecutive points.
+pub fn downsample(points: Vec<SeriesPoint>, max_points: usize, combine: Combine) -> Vec<SeriesPoint> {
+    if max_points == 0 || points.len() <= max_points {
+        return points;
+    }
+
+    let group = points.len().div_ceil(max_points);
+    points
+        .chunks(group)
+        .map(|chunk| {
+            let v = match combine {
+                Combine::Sum => chunk.iter().map(|p| p.v).sum(),
+                Combine::Max => chunk.iter().map(|p| p.v).fold(f64::MIN, f64::max),
+            };
+            SeriesPoint { t: chunk[0].t, v }
+        })
+        .collect()
+}
+
+#[derive(QueryableByName)]
+struct BucketRow {
+    #[diesel(sql_type = diesel::sql_types::Timestamptz)]
+    bucket: DateTime<Utc>,
+    #[diesel(sql_type = diesel::sql_types::Double)]
+    value: f64,
+}
+
+fn upsert_points(
+    conn: &mut PgConnection,
+    metric: RollupMetric,
+    granularity: Granularity,
+    points: &[SeriesPoint],
+) -> Result<(), diesel::result::Error> {
+    for point in points {
+        diesel::sql_query(
+            "INSERT INTO analytics_rollups (metric, granularity, bucket_start, value, updated_at)
+             VALUES ($1, $2, $3, $4, NOW())
+             ON CONFLICT (metric, granularity, bucket_start)
+             DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()",
+        )
+        .bind::<diesel::sql_types::Text, _>(metric.name())
+        .bind::<diesel::sql_types::Text, _>(granularity.as_str())
+        .bind::<diesel::sql_types::Timestamptz, _>(point.t)
+        .bind::<diesel::sql_types::Double, _>(point.v)
+        .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn rollup_window(granularity: Granularity, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let current = granularity.bucket_start(now);
+    let lookback = match granularity {
+        Granularity::Minute => granularity.duration() * MINUTE_LOOKBACK as i32,
+        Granularity::Hour | Granularity::Day => granularity.duration(),
+    };
+    // The current bucket is included so charts show partial progress.
+    (current - lookback, current + granularity.duration())
+}
+
+fn rollup_from_raw(
+    conn: &mut PgConnection,
+    metric: RollupMetric,
+    granularity: Granularity,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<SeriesPoint>, diesel::result::Error> {
+    let rows: Vec<BucketRow> = diesel::sql_query(metric.source_sql())
+        .bind::<diesel::sql_types::Text, _>(granularity.as_str())
+        .bind::<diesel::sql_types::Timestamptz, _>(from)
+        .bind::<diesel::sql_types::Timestamptz, _>(to)
+        .load(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| SeriesPoint { t: r.bucket, v: r.value })
+        .collect())
+}
+
+pub async fn run_rollups(pool: &DbPool, now: DateTime<Utc>) {
+    let pool = pool.clone();
+    let result = tokio::task::spawn_blocking(move || -> Result<(), diesel::result::Error> {
+        let mut conn = match pool.get() {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Analytics rollup skipped, no DB connection: {}", e);
+                return Ok(());
+            }
+        };
+
+        for granularity in [Granularity::Minute, Granularity::Hour, Granularity::Day] {
+            let (from, to) = rollup_window(granularity, now);
+
+            for metric in RollupMetric::ALL {
+                let points = rollup_from_raw(&mut conn, metric, granularity, from, to)?;
+                upsert_points(&mut conn, metric, granularity, &points)?;
+            }
+        }
+
+        Ok(())
+    })
+    .await;
+
+    match result {
+        Ok(Ok(())) => trace!("Analytics rollups updated"),
+        Ok(Err(e)) => warn!("Analytics rollup failed: {}", e),
+        Err(e) => warn!("Analytics rollup task failed: {}", e),
+    }
+}
+
+pub fn spawn_rollup_worker(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+
+        loop {
+            interval.tick().await;
+            run_rollups(&state.conn, Utc::now()).await;
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TimeSeriesQuery {
+    pub metric: String,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub granularity: Option<Granularity>,
+    pub max_points: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeSeriesResponse {
+    pub metric: String,
+    pub granularity: Granularity,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub points: Vec<SeriesPoint>,
+}
+
+pub fn load_series(
+    conn: &mut PgConnection,
+    metric: RollupMetric,
+    granularity: Granularity,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<SeriesPoint>, diesel::result::Error> {
+    let rows: Vec<BucketRow> = diesel::sql_query(
+        "SELECT bucket_start AS bucket, value
+         FROM analytics_rollups
+         WHERE metric = $1 AND granularity = $2 AND bucket_start >= $3 AND bucket_start < $4
+         ORDER BY bucket_start",
+    )
+    .bind::<diesel::sql_types::Text, _>(metric.name())
+    .bind::<diesel::sql_types::Text, _>(granularity.as_str())
+    .bind::<diesel::sql_types::Timestamptz, _>(from)
+    .bind::<diesel::sql_types::Timestamptz, _>(to)
+    .load(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| SeriesPoint { t: r.bucket, v: r.value })
+        .collect())
+}
+
+pub async fn handle_timeseries_query(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TimeSeriesQuery>,
+) -> Result<Json<TimeSeriesResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let Some(metric) = RollupMetric::from_name(&query.metric) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": format!("Unknown metric '{}'", query.metric),
+                "available": RollupMetric::ALL.iter().map(|m| m.name()).collect::<Vec<_>>(),
+            })),
+        ));
+    };
+
+    let granularity = query.granularity.unwrap_or(Granularity::Hour);
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::hours(24));
+    if from >= to {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "'from' must be before 'to'" })),
+        ));
+    }
+    let max_points = query
+        .max_points
+        .unwrap_or(DEFAULT_MAX_POINTS)
+        .clamp(1, MAX_POINTS_CAP);
+
+    let pool = state.read_pool().clone();
+    let points = tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        load_series(&mut conn, metric, granularity, from, to).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r)
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        )
+    })?;
+
+    Ok(Json(TimeSeriesResponse {
+        metric: metric.name().to_string(),
+        granularity,
+        from,
+        to,
+        points: downsample(points, max_points, metric.combine()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 10, h, m, s).unwrap()
+    }
+
+    #[test]
+    fn test_bucket_boundaries() {
+        let from = at(9, 0, 0);
+        let to = at(11, 0, 0);
+        let samples = vec![
+            (at(9, 59, 59), 1.0),
+            (at(10, 0, 0), 2.0),
+            (at(10, 59, 59), 3.0),
+            (at(11, 0, 0), 100.0),
+        ];
+
+        let points = aggregate_samples(&samples, Granularity::Hour, from, to, Combine::Sum);
+        assert_eq!(
+            points,
+            vec![
+                SeriesPoint { t: at(9, 0, 0), v: 1.0 },
+                SeriesPoint { t: at(10, 0, 0), v: 5.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_minute_and_day_truncation() {
+        assert_eq!(Granularity::Minute.bucket_start(at(10, 15, 59)), at(10, 15, 0));
+        assert_eq!(Granularity::Day.bucket_start(at(23, 59, 59)), at(0, 0, 0));
+    }
+
+    #[test]
+    fn test_max_combine_for_gauges() {
+        let samples = vec![(at(10, 1, 0), 4.0), (at(10, 2, 0), 7.0), (at(10, 3, 0), 5.0)];
+        let points = aggregate_samples(&samples, Granularity::Hour, at(10, 0, 0), at(11, 0, 0), Combine::Max);
+        assert_eq!(points[0].v, 7.0);
+    }
+
+    #[test]
+    fn test_downsample_caps_points() {
+        let points: Vec<SeriesPoint> = (0..10)
+            .map(|i| SeriesPoint { t: at(10, i, 0), v: 1.0 })
+            .collect();
+
+        let reduced = downsample(points, 3, Combine::Sum);
+        assert_eq!(reduced.len(), 3);
+        assert_eq!(reduced.iter().map(|p| p.v).sum::<f64>(), 10.0);
+        assert_eq!(reduced[0].t, at(10, 0, 0));
+    }
+}
@@ -0,0 +1,472 @@
+//! Conversation analytics exported as a spreadsheet.
+//!
+//! Sessions and messages per day come from the daily rollups; the per-bot, response time
+//! and cache figures are aggregated from the raw tables for the same range. The workbook
+//! is either downloaded as `.xlsx` or saved to the caller's sheets.
+
+use super::rollups::{load_series, Granularity, RollupMetric, SeriesPoint};
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::export::export_to_xlsx;
+use crate::sheet::sort_filter::set_header;
+use crate::sheet::storage::{create_new_spreadsheet, get_current_user_id, save_sheet_to_drive};
+use crate::sheet::types::{CellData, CellStyle, SaveResponse, Spreadsheet, Worksheet};
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::Engine;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use log::info;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+const MAX_RANGE_DAYS: i64 = 366;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportDestination {
+    #[default]
+    Download,
+    Drive,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsSheetRequest {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub destination: ExportDestination,
+}
+
+#[derive(Debug, Clone, PartialEq, QueryableByName)]
+pub struct BotMessages {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub bot_name: String,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub messages: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub sessions: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, QueryableByName)]
+pub struct DailyResponseTime {
+    #[diesel(sql_type = diesel::sql_types::Date)]
+    pub day: NaiveDate,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub responses: i64,
+    #[diesel(sql_type = diesel::sql_types::Double)]
+    pub avg_seconds: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, QueryableByName)]
+pub struct DailyCache {
+    #[diesel(sql_type = diesel::sql_types::Date)]
+    pub day: NaiveDate,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub requests: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub cache_hits: i64,
+}
+
+/// Everything the workbook is built from.
+#[derive(Debug, Clone, Default)]
+pub struct ConversationReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub sessions_per_day: Vec<SeriesPoint>,
+    pub messages_per_day: Vec<SeriesPoint>,
+    pub messages_per_bot: Vec<BotMessages>,
+    pub response_times: Vec<DailyResponseTime>,
+    pub cache: Vec<DailyCache>,
+}
+
+impl ConversationReport {
+    /// Response time averaged over all responses, weighting each day by its count.
+    fn avg_response_seconds(&self) -> Option<f64> {
+        let responses: i64 = self.response_times.iter().map(|d| d.responses).sum();
+        (responses > 0).then(|| {
+            self.response_times
+                .iter()
+                .map(|d| d.avg_seconds * d.responses as f64)
+                .sum::<f64>()
+                / responses as f64
+        })
+    }
+
+    fn cache_hit_rate(&self) -> Option<f64> {
+        let requests: i64 = self.cache.iter().map(|d| d.requests).sum();
+        let hits: i64 = self.cache.iter().map(|d| d.cache_hits).sum();
+        (requests > 0).then(|| hits as f64 / requests as f64)
+    }
+}
+
+pub fn load_report(
+    conn: &mut PgConnection,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<ConversationReport, diesel::result::Error> {
+    let sessions_per_day = load_series(conn, RollupMetric::ActiveSessions, Granularity::Day, from, to)?;
+    let messages_per_day = load_series(conn, RollupMetric::MessageVolume, Granularity::Day, from, to)?;
+
+    let messages_per_bot = diesel::sql_query(
+        "SELECT b.name AS bot_name, COUNT(mh.id) AS messages,
+                COUNT(DISTINCT mh.session_id) AS sessions
+         FROM message_history mh
+         JOIN sessions s ON s.id = mh.session_id
+         JOIN bots b ON b.id = s.bot_id
+         WHERE mh.created_at >= $1 AND mh.created_at < $2
+         GROUP BY b.id, b.name
+         ORDER BY messages DESC",
+    )
+    .bind::<diesel::sql_types::Timestamptz, _>(from)
+    .bind::<diesel::sql_types::Timestamptz, _>(to)
+    .load(conn)?;
+
+    // Same definition as the dashboard's average response card, per day.
+    let response_times = diesel::sql_query(
+        "SELECT (created_at AT TIME ZONE 'UTC')::date AS day, COUNT(*) AS responses,
+                COALESCE(AVG(EXTRACT(EPOCH FROM (updated_at - created_at))), 0)::float8 AS avg_seconds
+         FROM message_history
+         WHERE role = 1 AND created_at >= $1 AND created_at < $2
+         GROUP BY 1
+         ORDER BY 1",
+    )
+    .bind::<diesel::sql_types::Timestamptz, _>(from)
+    .bind::<diesel::sql_types::Timestamptz, _>(to)
+    .load(conn)?;
+
+    let cache = diesel::sql_query(
+        "SELECT (created_at AT TIME ZONE 'UTC')::date AS day, COUNT(*) AS requests,
+                COUNT(*) FILTER (WHERE cached) AS cache_hits
+         FROM llm_usage
+         WHERE created_at >= $1 AND created_at < $2
+         GROUP BY 1
+         ORDER BY 1",
+    )
+    .bind::<diesel::sql_types::Timestamptz, _>(from)
+    .bind::<diesel::sql_types::Timestamptz, _>(to)
+    .load(conn)?;
+
+    Ok(ConversationReport {
+        from,
+        to,
+        sessions_per_day,
+        messages_per_day,
+        messages_per_bot,
+        response_times,
+        cache,
+    })
+}
+
+enum Value {
+    Text(String),
+    Number(f64),
+    Percent(f64),
+    Empty,
+}
+
+fn cell(value: Value) -> Option<CellData> {
+    let (value, format) = match value {
+        Value::Text(text) => (text, None),
+        Value::Number(n) => (n.to_string(), None),
+        Value::Percent(p) => (p.to_string(), Some("0.0%".to_string())),
+        Value::Empty => return None,
+    };
+    Some(CellData {
+        value: Some(value),
+        formula: None,
+        style: None,
+        format,
+        note: None,
+        locked: None,
+        has_comment: None,
+        array_formula_id: None,
+    })
+}
+
+fn worksheet(name: &str, headers: &[&str], rows: Vec<Vec<Value>>) -> Worksheet {
+    let mut data = HashMap::new();
+    for (col, label) in headers.iter().enumerate() {
+        if let Some(mut header) = cell(Value::Text((*label).to_string())) {
+            header.style = Some(CellStyle {
+                font_weight: Some("bold".to_string()),
+                ..CellStyle::default()
+            });
+            data.insert(format!("0,{col}"), header);
+        }
+    }
+    for (row, values) in rows.into_iter().enumerate() {
+        for (col, value) in values.into_iter().enumerate() {
+            if let Some(cell) = cell(value) {
+                data.insert(format!("{},{col}", row + 1), cell);
+            }
+        }
+    }
+
+    let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+    worksheet.name = name.to_string();
+    worksheet.data = data;
+    set_header(&mut worksheet, true);
+    worksheet
+}
+
+fn day_text(day: NaiveDate) -> Value {
+    Value::Text(day.format("%Y-%m-%d").to_string())
+}
+
+/// Lays the report out as a summary sheet followed by one sheet per metric.
+pub fn build_workbook(report: &ConversationReport) -> Spreadsheet {
+    let sessions: f64 = report.sessions_per_day.iter().map(|p| p.v).sum();
+    let messages: f64 = report.messages_per_day.iter().map(|p| p.v).sum();
+    let summary = worksheet(
+        "Summary",
+        &["Metric", "Value"],
+        vec![
+            vec![Value::Text("From".into()), Value::Text(report.from.to_rfc3339())],
+            vec![Value::Text("To".into()), Value::Text(report.to.to_rfc3339())],
+            vec![Value::Text("Sessions".into()), Value::Number(sessions)],
+            vec![Value::Text("Messages".into()), Value::Number(messages)],
+            vec![
+                Value::Text("Average response time (s)".into()),
+                report.avg_response_seconds().map_or(Value::Empty, Value::Number),
+            ],
+            vec![
+                Value::Text("Cache hit rate".into()),
+                report.cache_hit_rate().map_or(Value::Empty, Value::Percent),
+            ],
+        ],
+    );
+
+    let mut daily: BTreeMap<NaiveDate, (f64, f64)> = BTreeMap::new();
+    for point in &report.sessions_per_day {
+        daily.entry(point.t.date_naive()).or_default().0 += point.v;
+    }
+    for point in &report.messages_per_day {
+        daily.entry(point.t.date_naive()).or_default().1 += point.v;
+    }
+    let sessions_sheet = worksheet(
+        "Sessions per Day",
+        &["Date", "Sessions", "Messages"],
+        daily
+            .into_iter()
+            .map(|(day, (sessions, messages))| {
+                vec![day_text(day), Value::Number(sessions), Value::Number(messages)]
+            })
+            .collect(),
+    );
+
+    let bots_sheet = worksheet(
+        "Messages per Bot",
+        &["Bot", "Messages", "Sessions"],
+        report
+            .messages_per_bot
+            .iter()
+            .map(|b| {
+                vec![
+                    Value::Text(b.bot_name.clone()),
+                    Value::Number(b.messages as f64),
+                    Value::Number(b.sessions as f64),
+                ]
+            })
+            .collect(),
+    );
+
+    let response_sheet = worksheet(
+        "Response Time",
+        &["Date", "Responses", "Average response time (s)"],
+        report
+            .response_times
+            .iter()
+            .map(|d| {
+                vec![
+                    day_text(d.day),
+                    Value::Number(d.responses as f64),
+                    Value::Number((d.avg_seconds * 1000.0).round() / 1000.0),
+                ]
+            })
+            .collect(),
+    );
+
+    let cache_sheet = worksheet(
+        "Cache",
+        &["Date", "LLM requests", "Cache hits", "Hit rate"],
+        report
+            .cache
+            .iter()
+            .map(|d| {
+                let rate = if d.requests > 0 {
+                    Value::Percent(d.cache_hits as f64 / d.requests as f64)
+                } else {
+                    Value::Empty
+                };
+                vec![
+                    day_text(d.day),
+                    Value::Number(d.requests as f64),
+                    Value::Number(d.cache_hits as f64),
+                    rate,
+                ]
+            })
+            .collect(),
+    );
+
+    let mut sheet = create_new_spreadsheet();
+    sheet.name = format!(
+        "Conversation analytics {} to {}",
+        report.from.format("%Y-%m-%d"),
+        report.to.format("%Y-%m-%d")
+    );
+    sheet.worksheets = vec![summary, sessions_sheet, bots_sheet, response_sheet, cache_sheet];
+    sheet
+}
+
+pub async fn handle_export_analytics_sheet(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<AnalyticsSheetRequest>,
+) -> Result<Response, ApiError> {
+    if !user.is_admin() {
+        return Err(ApiError::forbidden("Administrator role required"));
+    }
+
+    let to = req.to.unwrap_or_else(Utc::now);
+    let from = req.from.unwrap_or(to - Duration::days(30));
+    if from >= to {
+        return Err(ApiError::bad_request("'from' must be before 'to'"));
+    }
+    if to - from > Duration::days(MAX_RANGE_DAYS) {
+        return Err(ApiError::bad_request(format!(
+            "The range may span at most {MAX_RANGE_DAYS} days"
+        )));
+    }
+
+    let pool = state.read_pool().clone();
+    let report = tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        load_report(&mut conn, from, to).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r)
+    .map_err(ApiError::internal)?;
+
+    let mut sheet = build_workbook(&report);
+    sheet.owner_id = get_current_user_id();
+    sheet.created_by = Some(user.user_id.to_string());
+
+    match req.destination {
+        ExportDestination::Drive => {
+            save_sheet_to_drive(&state, &sheet.owner_id, &sheet).await?;
+            info!("Analytics sheet {} saved for {}", sheet.id, user.user_id);
+            Ok(Json(SaveResponse {
+                id: sheet.id,
+                success: true,
+                message: Some(sheet.name),
+            })
+            .into_response())
+        }
+        ExportDestination::Download => {
+            let encoded = export_to_xlsx(&sheet).map_err(ApiError::internal)?;
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(encoded)
+                .map_err(|e| ApiError::internal(e.to_string()))?;
+            Ok((
+                [
+                    (
+                        header::CONTENT_TYPE,
+                        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                            .to_string(),
+                    ),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"{}.xlsx\"", sheet.name),
+                    ),
+                ],
+                bytes,
+            )
+                .into_response())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::load_xlsx_from_bytes;
+    use chrono::TimeZone;
+
+    fn report() -> ConversationReport {
+        let day = |d| Utc.with_ymd_and_hms(2026, 10, d, 0, 0, 0).unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(2026, 10, d).unwrap();
+        ConversationReport {
+            from: day(1),
+            to: day(3),
+            sessions_per_day: vec![SeriesPoint { t: day(1), v: 4.0 }, SeriesPoint { t: day(2), v: 6.0 }],
+            messages_per_day: vec![SeriesPoint { t: day(1), v: 40.0 }, SeriesPoint { t: day(2), v: 52.0 }],
+            messages_per_bot: vec![BotMessages {
+                bot_name: "support".to_string(),
+                messages: 92,
+                sessions: 10,
+            }],
+            response_times: vec![
+                DailyResponseTime { day: date(1), responses: 20, avg_seconds: 1.0 },
+                DailyResponseTime { day: date(2), responses: 20, avg_seconds: 2.0 },
+            ],
+            cache: vec![DailyCache { day: date(1), requests: 8, cache_hits: 2 }],
+        }
+    }
+
+    fn row(worksheet: &Worksheet, row: u32, cols: u32) -> Vec<String> {
+        (0..cols)
+            .map(|col| {
+                worksheet
+                    .data
+                    .get(&format!("{row},{col}"))
+                    .and_then(|c| c.value.clone())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_workbook_has_expected_worksheets_and_headers() {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(export_to_xlsx(&build_workbook(&report())).unwrap())
+            .unwrap();
+        let (sheet, _) = load_xlsx_from_bytes(&bytes, "u1", "analytics.xlsx").unwrap();
+
+        let names: Vec<&str> = sheet.worksheets.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["Summary", "Sessions per Day", "Messages per Bot", "Response Time", "Cache"]
+        );
+        assert_eq!(row(&sheet.worksheets[0], 0, 2), ["Metric", "Value"]);
+        assert_eq!(row(&sheet.worksheets[1], 0, 3), ["Date", "Sessions", "Messages"]);
+        assert_eq!(row(&sheet.worksheets[2], 0, 3), ["Bot", "Messages", "Sessions"]);
+        assert_eq!(
+            row(&sheet.worksheets[3], 0, 3),
+            ["Date", "Responses", "Average response time (s)"]
+        );
+        assert_eq!(
+            row(&sheet.worksheets[4], 0, 4),
+            ["Date", "LLM requests", "Cache hits", "Hit rate"]
+        );
+
+        assert_eq!(row(&sheet.worksheets[1], 2, 3), ["2026-10-02", "6", "52"]);
+        assert_eq!(row(&sheet.worksheets[2], 1, 3), ["support", "92", "10"]);
+    }
+
+    #[test]
+    fn test_summary_weights_response_time_and_cache_rate() {
+        let report = report();
+        assert_eq!(report.avg_response_seconds(), Some(1.5));
+        assert_eq!(report.cache_hit_rate(), Some(0.25));
+        assert_eq!(ConversationReport::default().cache_hit_rate(), None);
+    }
+}
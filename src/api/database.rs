@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 // Note: Replace AppState with your actual shared state struct
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 pub fn configure_database_routes() -> Router<Arc<AppState>> {
@@ -72,7 +73,7 @@ pub struct QueryRequest {
 
 pub async fn execute_query(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<QueryRequest>,
+    ApiJson(payload): ApiJson<QueryRequest>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
     if payload.query.trim().is_empty() {
         return Err(axum::http::StatusCode::BAD_REQUEST);
@@ -90,7 +91,7 @@ pub async fn execute_query(
 pub async fn insert_or_update_row(
     State(_state): State<Arc<AppState>>,
     Path(name): Path<String>,
-    Json(_payload): Json<serde_json::Value>,
+    ApiJson(_payload): ApiJson<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
     Ok(Json(serde_json::json!({
         "status": "success",
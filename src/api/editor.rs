@@ -9,6 +9,7 @@ use std::sync::Arc;
 use tokio::fs;
 
 // Note: Replace AppState with your actual shared state struct
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 pub fn configure_editor_routes() -> Router<Arc<AppState>> {
@@ -65,7 +66,7 @@ pub struct SaveFileRequest {
 pub async fn save_file(
     State(_state): State<Arc<AppState>>,
     Path(path): Path<String>,
-    Json(_payload): Json<SaveFileRequest>,
+    ApiJson(_payload): ApiJson<SaveFileRequest>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
     let safe_path = path.replace("..", "");
     
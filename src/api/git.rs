@@ -7,6 +7,7 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 pub fn configure_git_routes() -> Router<Arc<AppState>> {
@@ -59,7 +60,7 @@ pub struct CommitRequest {
 
 pub async fn git_commit(
     State(_state): State<Arc<AppState>>,
-    Json(_payload): Json<CommitRequest>,
+    ApiJson(_payload): ApiJson<CommitRequest>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
     Ok(Json(serde_json::json!({ "status": "success", "message": "Committed successfully" })))
 }
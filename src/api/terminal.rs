@@ -23,6 +23,7 @@ use tokio::{
     sync::{mpsc, Mutex, RwLock},
 };
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::core::urls::ApiUrls;
 use crate::security::command_guard::SafeCommand;
@@ -441,7 +442,7 @@ pub struct CreateTerminalRequest {
 
 pub async fn create_terminal(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<CreateTerminalRequest>,
+    ApiJson(payload): ApiJson<CreateTerminalRequest>,
 ) -> impl IntoResponse {
     let session_id = payload.session_id.unwrap_or_else(|| {
         use std::time::{SystemTime, UNIX_EPOCH};
@@ -473,7 +474,7 @@ pub async fn create_terminal(
 
 pub async fn kill_terminal(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<serde_json::Value>,
+    ApiJson(payload): ApiJson<serde_json::Value>,
 ) -> impl IntoResponse {
     let session_id = payload
         .get("session_id")
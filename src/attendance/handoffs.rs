@@ -0,0 +1,642 @@
+//! The attendant side of a handoff: listing escalated sessions, claiming one, talking to
+//! the user and giving the session back to the bot.
+//!
+//! A claim is kept in the session's `context_data` (`claimed_by`) and taken under the
+//! session's row lock, so two attendants can never hold the same session. Only the
+//! claimant can send messages into the session or release it. The attendant is the
+//! authenticated user, identified by their user id. Presence is tracked per instance from
+//! the attendant websocket and `POST /api/attendance/presence`.
+
+use super::queue::AttendantStatus;
+use super::save_message_to_history;
+use crate::core::bot::channels::telegram::TelegramAdapter;
+use crate::core::bot::channels::whatsapp::WhatsAppAdapter;
+use crate::core::bot::channels::ChannelAdapter;
+use crate::core::bot::handoff::{self, needs_human};
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::message_bus::notify_attendants;
+use crate::core::shared::message_types::MessageType;
+use crate::core::shared::models::{BotResponse, UserSession};
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::{AuthenticatedUser, Role};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Nullable, Text};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, LazyLock, RwLock};
+use uuid::Uuid;
+
+const LIST_LIMIT: i64 = 100;
+
+static PRESENCE: LazyLock<RwLock<HashMap<String, AttendantPresence>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClaimError {
+    /// The session is not waiting for an attendant.
+    NotHandedOff,
+    /// Another attendant holds the session.
+    AlreadyClaimed(String),
+    /// The attendant does not hold the session.
+    NotClaimant,
+    Database(String),
+}
+
+impl fmt::Display for ClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotHandedOff => write!(f, "Session is not handed off to attendants"),
+            Self::AlreadyClaimed(by) => write!(f, "Session is already claimed by {}", by),
+            Self::NotClaimant => write!(f, "Session is not claimed by this attendant"),
+            Self::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<diesel::result::Error> for ClaimError {
+    fn from(e: diesel::result::Error) -> Self {
+        Self::Database(e.to_string())
+    }
+}
+
+impl ClaimError {
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::NotHandedOff | Self::AlreadyClaimed(_) => StatusCode::CONFLICT,
+            Self::NotClaimant => StatusCode::FORBIDDEN,
+            Self::Database(e) if e.contains("not found") => StatusCode::NOT_FOUND,
+            Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn reply(self) -> (StatusCode, Json<Value>) {
+        (
+            self.status(),
+            Json(json!({ "success": false, "error": self.to_string() })),
+        )
+    }
+}
+
+fn claimed_by(context: &Value) -> Option<&str> {
+    context.get("claimed_by").and_then(Value::as_str)
+}
+
+/// Gives the handed-off session to `attendant_id`. Claiming a session the attendant
+/// already holds succeeds.
+pub fn claim(context: &mut Value, attendant_id: &str) -> Result<(), ClaimError> {
+    if !needs_human(context) {
+        return Err(ClaimError::NotHandedOff);
+    }
+    match claimed_by(context) {
+        Some(current) if current == attendant_id => return Ok(()),
+        Some(current) => return Err(ClaimError::AlreadyClaimed(current.to_string())),
+        None => {}
+    }
+    context["claimed_by"] = json!(attendant_id);
+    context["claimed_at"] = json!(Utc::now().to_rfc3339());
+    context["assigned_to"] = json!(attendant_id);
+    context["status"] = json!("active");
+    Ok(())
+}
+
+pub fn ensure_claimant(context: &Value, attendant_id: &str) -> Result<(), ClaimError> {
+    if !needs_human(context) {
+        return Err(ClaimError::NotHandedOff);
+    }
+    if claimed_by(context) != Some(attendant_id) {
+        return Err(ClaimError::NotClaimant);
+    }
+    Ok(())
+}
+
+/// Drops the claim and gives the session back to the bot.
+pub fn release_claim(context: &mut Value, attendant_id: &str) -> Result<(), ClaimError> {
+    ensure_claimant(context, attendant_id)?;
+    if let Some(map) = context.as_object_mut() {
+        map.remove("claimed_by");
+        map.remove("claimed_at");
+        map.insert("assigned_to".to_string(), Value::Null);
+    }
+    context["status"] = json!("released");
+    handoff::release(context);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AttendantPresence {
+    pub attendant_id: String,
+    pub status: AttendantStatus,
+    /// Open attendant websockets on this instance.
+    pub connections: u32,
+    pub last_seen: DateTime<Utc>,
+}
+
+fn update_presence(attendant_id: &str, change: impl FnOnce(&mut AttendantPresence)) {
+    let mut presence = PRESENCE.write().unwrap_or_else(|e| e.into_inner());
+    let entry = presence
+        .entry(attendant_id.to_string())
+        .or_insert_with(|| AttendantPresence {
+            attendant_id: attendant_id.to_string(),
+            status: AttendantStatus::Offline,
+            connections: 0,
+            last_seen: Utc::now(),
+        });
+    change(entry);
+    entry.last_seen = Utc::now();
+}
+
+pub fn attendant_connected(attendant_id: &str) {
+    update_presence(attendant_id, |p| {
+        p.connections += 1;
+        if p.status == AttendantStatus::Offline {
+            p.status = AttendantStatus::Online;
+        }
+    });
+}
+
+/// The attendant goes offline when their last websocket closes.
+pub fn attendant_disconnected(attendant_id: &str) {
+    update_presence(attendant_id, |p| {
+        p.connections = p.connections.saturating_sub(1);
+        if p.connections == 0 {
+            p.status = AttendantStatus::Offline;
+        }
+    });
+}
+
+pub fn set_presence(attendant_id: &str, status: AttendantStatus) {
+    update_presence(attendant_id, |p| p.status = status);
+}
+
+pub fn presence(attendant_id: &str) -> AttendantStatus {
+    PRESENCE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(attendant_id)
+        .map_or(AttendantStatus::Offline, |p| p.status)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HandoffFilters {
+    pub claimed: Option<bool>,
+    pub channel: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HandoffItem {
+    pub session_id: Uuid,
+    pub bot_id: Uuid,
+    pub user_id: Uuid,
+    pub user_name: Option<String>,
+    pub channel: String,
+    pub priority: i32,
+    pub trigger: Option<String>,
+    pub reason: Option<String>,
+    pub handed_off_at: Option<String>,
+    pub claimed_by: Option<String>,
+    pub claimed_by_status: Option<AttendantStatus>,
+}
+
+impl From<&UserSession> for HandoffItem {
+    fn from(session: &UserSession) -> Self {
+        let context = &session.context_data;
+        let text = |key: &str| context.get(key).and_then(Value::as_str).map(str::to_string);
+        let claimed_by = text("claimed_by");
+        Self {
+            session_id: session.id,
+            bot_id: session.bot_id,
+            user_id: session.user_id,
+            user_name: text("name"),
+            channel: text("channel").unwrap_or_else(|| "web".to_string()),
+            priority: handoff::priority(context),
+            trigger: text("handoff_trigger"),
+            reason: text("handoff_reason").or_else(|| text("transfer_reason")),
+            handed_off_at: text("handoff_at").or_else(|| text("transfer_requested_at")),
+            claimed_by_status: claimed_by.as_deref().map(presence),
+            claimed_by,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HandoffMessageRequest {
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PresenceRequest {
+    pub status: AttendantStatus,
+}
+
+/// Roles allowed to work the handoff queue, as for the `/api/attendant` routes.
+const ATTENDANT_ROLES: [Role; 3] = [Role::Moderator, Role::Admin, Role::SuperAdmin];
+
+/// The calling attendant's id, or the reply refusing an anonymous caller or one
+/// without an attendant role.
+fn attendant_of(user: &AuthenticatedUser) -> Result<String, (StatusCode, Json<Value>)> {
+    if !user.is_authenticated() {
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            Json(json!({ "success": false, "error": "Authentication required" })),
+        ));
+    }
+    if !user.has_any_role(&ATTENDANT_ROLES) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "success": false, "error": "Attendant role required" })),
+        ));
+    }
+    Ok(user.user_id.to_string())
+}
+
+/// Applies `change` to the session's context under its row lock and returns the session.
+async fn change_session(
+    state: &Arc<AppState>,
+    session_id: Uuid,
+    change: impl FnOnce(&mut Value) -> Result<(), ClaimError> + Send + 'static,
+) -> Result<UserSession, ClaimError> {
+    let conn = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut db_conn = conn
+            .get()
+            .map_err(|e| ClaimError::Database(format!("Database connection error: {}", e)))?;
+        handoff::try_update_context(&mut db_conn, session_id, change)?;
+
+        use crate::core::shared::models::schema::user_sessions;
+        Ok(user_sessions::table
+            .find(session_id)
+            .select(UserSession::as_select())
+            .first(&mut db_conn)?)
+    })
+    .await
+    .map_err(|e| ClaimError::Database(e.to_string()))?
+}
+
+/// Tells every attendant console, not just the claimant, that the session changed hands.
+async fn broadcast_claim(state: &AppState, session: &UserSession, kind: &str, by: &str) {
+    let mut notification = handoff::notification(session, kind, by);
+    notification.assigned_to = None;
+    notify_attendants(state, &notification).await;
+}
+
+/// Sends `content` to the user through the session's channel. Web sessions are written to
+/// the chat websocket held in `response_channels`, on this instance or the owning one.
+/// Returns whether the message reached the user.
+async fn deliver(state: &Arc<AppState>, session: &UserSession, content: &str) -> bool {
+    let context = &session.context_data;
+    let text = |key: &str| context.get(key).and_then(Value::as_str).unwrap_or_default();
+    let channel = Some(text("channel"))
+        .filter(|c| !c.is_empty())
+        .unwrap_or("web");
+
+    let (adapter, recipient): (Box<dyn ChannelAdapter>, &str) = match channel {
+        "whatsapp" => (
+            Box::new(WhatsAppAdapter::new(state, session.bot_id)),
+            text("phone"),
+        ),
+        "telegram" => (
+            Box::new(TelegramAdapter::new(state.conn.clone(), session.bot_id)),
+            text("chat_id"),
+        ),
+        _ => {
+            let user_id = session.user_id.to_string();
+            return state
+                .ws_router
+                .deliver(response(session, &user_id, channel, content))
+                .await;
+        }
+    };
+    if recipient.is_empty() {
+        error!("Session {} has no {} recipient", session.id, channel);
+        return false;
+    }
+    match adapter
+        .send_message(response(session, recipient, channel, content))
+        .await
+    {
+        Ok(()) => true,
+        Err(e) => {
+            error!(
+                "Failed to send attendant message to session {}: {}",
+                session.id, e
+            );
+            false
+        }
+    }
+}
+
+fn response(session: &UserSession, recipient: &str, channel: &str, content: &str) -> BotResponse {
+    BotResponse {
+        bot_id: session.bot_id.to_string(),
+        user_id: recipient.to_string(),
+        session_id: session.id.to_string(),
+        channel: channel.to_string(),
+        content: content.to_string(),
+        message_type: MessageType::BOT_RESPONSE,
+        stream_token: None,
+        is_complete: true,
+        suggestions: Vec::new(),
+        context_name: None,
+        context_length: 0,
+        context_max_length: 0,
+    }
+}
+
+fn bad_request(error: &str) -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "success": false, "error": error })),
+    )
+}
+
+/// Sessions waiting for or held by an attendant, most urgent and oldest first.
+pub async fn list_handoffs(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Query(filters): Query<HandoffFilters>,
+) -> impl IntoResponse {
+    if let Err(reply) = attendant_of(&user) {
+        return reply;
+    }
+    let conn = state.conn.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut db_conn = conn.get().map_err(|e| e.to_string())?;
+
+        use crate::core::shared::models::schema::user_sessions;
+        let mut query = user_sessions::table
+            .filter(user_sessions::deleted_at.is_null())
+            .filter(
+                user_sessions::context_data
+                    .retrieve_as_text("needs_human")
+                    .eq("true"),
+            )
+            .into_boxed();
+        if let Some(channel) = filters.channel {
+            query = query.filter(
+                user_sessions::context_data
+                    .retrieve_as_text("channel")
+                    .eq(channel),
+            );
+        }
+        let claimed_by = user_sessions::context_data.retrieve_as_text("claimed_by");
+        query = match filters.claimed {
+            Some(true) => query.filter(claimed_by.is_not_null()),
+            Some(false) => query.filter(claimed_by.is_null()),
+            None => query,
+        };
+        // Same order as `handoff::priority` and `HandoffItem::handed_off_at`
+        let priority = format!(
+            "CASE WHEN jsonb_typeof(context_data->'transfer_priority') = 'number' \
+             THEN (context_data->>'transfer_priority')::numeric::int ELSE {} END",
+            handoff::DEFAULT_PRIORITY
+        );
+        query
+            .order(sql::<Integer>(&priority).desc())
+            .then_order_by(
+                sql::<Nullable<Text>>(
+                    "COALESCE(context_data->>'handoff_at', \
+                     context_data->>'transfer_requested_at')",
+                )
+                .asc(),
+            )
+            .then_order_by(user_sessions::updated_at.asc())
+            .limit(LIST_LIMIT)
+            .select(UserSession::as_select())
+            .load(&mut db_conn)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+
+    match result {
+        Ok(sessions) => {
+            let items: Vec<HandoffItem> = sessions.iter().map(HandoffItem::from).collect();
+            (StatusCode::OK, Json(json!(items)))
+        }
+        Err(e) => {
+            error!("Failed to list handoffs: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "success": false, "error": e })),
+            )
+        }
+    }
+}
+
+pub async fn claim_handoff(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(session_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let attendant_id = match attendant_of(&user) {
+        Ok(id) => id,
+        Err(reply) => return reply,
+    };
+
+    let claimant = attendant_id.clone();
+    match change_session(&state, session_id, move |ctx| claim(ctx, &claimant)).await {
+        Ok(session) => {
+            info!(
+                "Session {} claimed by attendant {}",
+                session_id, attendant_id
+            );
+            broadcast_claim(&state, &session, "claimed", &attendant_id).await;
+            (
+                StatusCode::OK,
+                Json(json!({
+                    "success": true,
+                    "session_id": session_id,
+                    "claimed_by": attendant_id,
+                    "item": HandoffItem::from(&session),
+                })),
+            )
+        }
+        Err(e) => e.reply(),
+    }
+}
+
+/// Sends the claimant's message to the user and records it in the history.
+pub async fn send_handoff_message(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(session_id): Path<Uuid>,
+    ApiJson(request): ApiJson<HandoffMessageRequest>,
+) -> impl IntoResponse {
+    let attendant_id = match attendant_of(&user) {
+        Ok(id) => id,
+        Err(reply) => return reply,
+    };
+    if request.content.trim().is_empty() {
+        return bad_request("content is required");
+    }
+
+    let claimant = attendant_id.clone();
+    let session = match change_session(&state, session_id, move |ctx| {
+        ensure_claimant(ctx, &claimant)?;
+        ctx["attendant_last_message_at"] = json!(Utc::now().to_rfc3339());
+        Ok(())
+    })
+    .await
+    {
+        Ok(session) => session,
+        Err(e) => return e.reply(),
+    };
+
+    if let Err(e) = save_message_to_history(&state, &session, &request.content, "attendant").await {
+        error!("Failed to save attendant message: {}", e);
+    }
+    let delivered = deliver(&state, &session, &request.content).await;
+    notify_attendants(
+        &state,
+        &handoff::notification(&session, "attendant_response", &request.content),
+    )
+    .await;
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "success": true,
+            "session_id": session_id,
+            "delivered": delivered,
+        })),
+    )
+}
+
+/// Drops the claim; the bot answers the session's next message.
+pub async fn release_handoff(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(session_id): Path<Uuid>,
+) -> impl IntoResponse {
+    let attendant_id = match attendant_of(&user) {
+        Ok(id) => id,
+        Err(reply) => return reply,
+    };
+    let claimant = attendant_id.clone();
+    match change_session(&state, session_id, move |ctx| release_claim(ctx, &claimant)).await {
+        Ok(session) => {
+            info!(
+                "Session {} released to the bot by {}",
+                session_id, attendant_id
+            );
+            broadcast_claim(&state, &session, "released", &attendant_id).await;
+            (
+                StatusCode::OK,
+                Json(json!({ "success": true, "session_id": session_id })),
+            )
+        }
+        Err(e) => e.reply(),
+    }
+}
+
+pub async fn list_presence(user: AuthenticatedUser) -> impl IntoResponse {
+    if let Err(reply) = attendant_of(&user) {
+        return reply.into_response();
+    }
+    let mut attendants: Vec<AttendantPresence> = PRESENCE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .cloned()
+        .collect();
+    attendants.sort_by(|a, b| a.attendant_id.cmp(&b.attendant_id));
+    Json(attendants).into_response()
+}
+
+pub async fn update_presence_status(
+    user: AuthenticatedUser,
+    ApiJson(request): ApiJson<PresenceRequest>,
+) -> impl IntoResponse {
+    let attendant_id = match attendant_of(&user) {
+        Ok(id) => id,
+        Err(reply) => return reply,
+    };
+    set_presence(&attendant_id, request.status);
+    (
+        StatusCode::OK,
+        Json(json!({ "success": true, "attendant_id": attendant_id, "status": request.status })),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bot::handoff::HandoffTrigger;
+
+    fn handed_off() -> Value {
+        let mut context = json!({ "channel": "web" });
+        handoff::mark_escalated(&mut context, HandoffTrigger::Keyword, "agent");
+        context
+    }
+
+    #[test]
+    fn test_claimed_session_cannot_be_claimed_by_second_attendant() {
+        let mut context = json!({ "channel": "web" });
+        assert_eq!(claim(&mut context, "ana"), Err(ClaimError::NotHandedOff));
+
+        let mut context = handed_off();
+        claim(&mut context, "ana").unwrap();
+        assert_eq!(
+            claim(&mut context, "bruno"),
+            Err(ClaimError::AlreadyClaimed("ana".to_string()))
+        );
+        assert_eq!(claim(&mut context, "ana"), Ok(()));
+        assert_eq!(context["assigned_to"], "ana");
+
+        assert_eq!(
+            ensure_claimant(&context, "bruno"),
+            Err(ClaimError::NotClaimant)
+        );
+        assert_eq!(
+            release_claim(&mut context, "bruno"),
+            Err(ClaimError::NotClaimant)
+        );
+        release_claim(&mut context, "ana").unwrap();
+        assert!(!needs_human(&context));
+        assert_eq!(claim(&mut context, "bruno"), Err(ClaimError::NotHandedOff));
+    }
+
+    #[test]
+    fn test_attendant_is_the_authenticated_user() {
+        let (status, _) = attendant_of(&AuthenticatedUser::anonymous()).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let user = AuthenticatedUser::new(Uuid::new_v4(), "ana".to_string());
+        let (status, _) = attendant_of(&user).unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        for role in ATTENDANT_ROLES {
+            let attendant = user.clone().with_role(role);
+            assert_eq!(
+                attendant_of(&attendant).ok(),
+                Some(user.user_id.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_presence_follows_websockets() {
+        let id = format!("presence-{}", Uuid::new_v4());
+        assert_eq!(presence(&id), AttendantStatus::Offline);
+        attendant_connected(&id);
+        attendant_connected(&id);
+        assert_eq!(presence(&id), AttendantStatus::Online);
+        set_presence(&id, AttendantStatus::Busy);
+        attendant_disconnected(&id);
+        assert_eq!(presence(&id), AttendantStatus::Busy);
+        attendant_disconnected(&id);
+        assert_eq!(presence(&id), AttendantStatus::Offline);
+    }
+}
@@ -1,6 +1,7 @@
 use super::llm_assist_types::*;
 use super::llm_assist_helpers::*;
 use super::llm_assist_handlers::*;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use log::info;
 use std::fmt::Write;
@@ -380,7 +381,7 @@ async fn handle_tips_command(
         history,
     };
 
-    let (_, Json(tip_response)) = generate_tips(State(state.clone()), Json(request)).await;
+    let (_, Json(tip_response)) = generate_tips(State(state.clone()), ApiJson(request)).await;
 
     if tip_response.tips.is_empty() {
         return Ok(" No specific tips for this conversation yet.".to_string());
@@ -420,7 +421,7 @@ async fn handle_polish_command(
         tone: "professional".to_string(),
     };
 
-    let (_, Json(polish_response)) = polish_message(State(state.clone()), Json(request)).await;
+    let (_, Json(polish_response)) = polish_message(State(state.clone()), ApiJson(request)).await;
 
     if !polish_response.success {
         return Err(polish_response
@@ -459,7 +460,7 @@ async fn handle_replies_command(
     };
 
     let (_, Json(replies_response)) =
-        generate_smart_replies(State(state.clone()), Json(request)).await;
+        generate_smart_replies(State(state.clone()), ApiJson(request)).await;
 
     if replies_response.replies.is_empty() {
         return Ok(" No reply suggestions available.".to_string());
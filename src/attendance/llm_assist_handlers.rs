@@ -2,6 +2,7 @@ use super::llm_assist_types::*;
 use super::llm_assist_config::get_bot_system_prompt;
 use super::llm_assist_helpers::*;
 use crate::core::config::ConfigManager;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use axum::{
     extract::{Path, State},
@@ -15,7 +16,7 @@ use uuid::Uuid;
 
 pub async fn generate_tips(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<TipRequest>,
+    ApiJson(request): ApiJson<TipRequest>,
 ) -> (StatusCode, Json<TipResponse>) {
     info!("Generating tips for session {}", request.session_id);
 
@@ -120,7 +121,7 @@ Provide tips for the attendant."#,
 
 pub async fn polish_message(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<PolishRequest>,
+    ApiJson(request): ApiJson<PolishRequest>,
 ) -> (StatusCode, Json<PolishResponse>) {
     info!("Polishing message for session {}", request.session_id);
 
@@ -216,7 +217,7 @@ Respond in JSON format:
 
 pub async fn generate_smart_replies(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<SmartRepliesRequest>,
+    ApiJson(request): ApiJson<SmartRepliesRequest>,
 ) -> (StatusCode, Json<SmartRepliesResponse>) {
     info!(
         "Generating smart replies for session {}",
@@ -446,7 +447,7 @@ Respond in JSON format:
 
 pub async fn analyze_sentiment(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<SentimentRequest>,
+    ApiJson(request): ApiJson<SentimentRequest>,
 ) -> impl IntoResponse {
     info!("Analyzing sentiment for session {}", request.session_id);
 
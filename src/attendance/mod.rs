@@ -1,4 +1,5 @@
 pub mod drive;
+pub mod handoffs;
 pub mod keyword_services;
 pub mod sla;
 pub mod webhooks;
@@ -20,6 +21,7 @@ pub mod llm_assist_helpers;
 pub mod llm_parser;
 pub mod queue;
 
+use crate::core::shared::api_json::ApiJson;
 pub use drive::{AttendanceDriveConfig, AttendanceDriveService, RecordMetadata, SyncResult};
 pub use keyword_services::{
     AttendanceCommand, AttendanceRecord, AttendanceResponse, AttendanceService, KeywordConfig,
@@ -44,6 +46,7 @@ pub use queue::{
 use crate::core::bot::channels::whatsapp::WhatsAppAdapter;
 use crate::core::bot::channels::ChannelAdapter;
 use crate::core::urls::ApiUrls;
+use crate::core::shared::message_bus::{notify_attendants, ATTENDANT_TOPIC};
 use crate::core::shared::models::{BotResponse, UserSession};
 use crate::core::shared::state::{AppState, AttendantNotification};
 use axum::{
@@ -80,6 +83,17 @@ pub fn configure_attendance_routes() -> Router<Arc<AppState>> {
         .route(ApiUrls::ATTENDANCE_INSIGHTS, get(queue::get_insights))
         .route(ApiUrls::ATTENDANCE_KANBAN, get(queue::get_kanban))
         .route(ApiUrls::ATTENDANCE_RESPOND, post(attendant_respond))
+        .route(ApiUrls::ATTENDANCE_HANDOFFS, get(handoffs::list_handoffs))
+        .route(ApiUrls::ATTENDANCE_HANDOFF_CLAIM, post(handoffs::claim_handoff))
+        .route(
+            ApiUrls::ATTENDANCE_HANDOFF_MESSAGES,
+            post(handoffs::send_handoff_message),
+        )
+        .route(ApiUrls::ATTENDANCE_HANDOFF_RELEASE, post(handoffs::release_handoff))
+        .route(
+            ApiUrls::ATTENDANCE_PRESENCE,
+            get(handoffs::list_presence).post(handoffs::update_presence_status),
+        )
         .route(ApiUrls::WS_ATTENDANT, get(attendant_websocket_handler))
         .route("/api/attendance/webhooks", get(webhooks::list_webhooks).post(webhooks::create_webhook))
         .route("/api/attendance/webhooks/:id", get(webhooks::get_webhook).put(webhooks::update_webhook).delete(webhooks::delete_webhook))
@@ -126,7 +140,7 @@ pub struct AttendantRespondResponse {
 
 pub async fn attendant_respond(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<AttendantRespondRequest>,
+    ApiJson(request): ApiJson<AttendantRespondRequest>,
 ) -> impl IntoResponse {
     info!(
         "Attendant {} responding to session {}",
@@ -238,30 +252,21 @@ pub async fn attendant_respond(
             }
         }
         _ => {
-            let sent = if let Some(tx) = state
-                .response_channels
-                .lock()
-                .await
-                .get(&session.id.to_string())
-            {
-                let response = BotResponse {
-                    bot_id: session.bot_id.to_string(),
-                    session_id: session.id.to_string(),
-                    user_id: session.user_id.to_string(),
-                    channel: channel.to_string(),
-                    content: request.message.clone(),
-                    message_type: botlib::MessageType::BOT_RESPONSE,
-                    stream_token: None,
-                    is_complete: true,
-                    suggestions: vec![],
-                    context_name: None,
-                    context_length: 0,
-                    context_max_length: 0,
-                };
-                tx.send(response).await.is_ok()
-            } else {
-                false
+            let response = BotResponse {
+                bot_id: session.bot_id.to_string(),
+                session_id: session.id.to_string(),
+                user_id: session.user_id.to_string(),
+                channel: channel.to_string(),
+                content: request.message.clone(),
+                message_type: botlib::MessageType::BOT_RESPONSE,
+                stream_token: None,
+                is_complete: true,
+                suggestions: vec![],
+                context_name: None,
+                context_length: 0,
+                context_max_length: 0,
             };
+            let sent = state.ws_router.deliver(response).await;
 
             broadcast_attendant_action(&state, &session, &request, "attendant_response").await;
 
@@ -332,37 +337,33 @@ async fn broadcast_attendant_action(
     request: &AttendantRespondRequest,
     action_type: &str,
 ) {
-    if let Some(broadcast_tx) = state.attendant_broadcast.as_ref() {
-        let notification = AttendantNotification {
-            notification_type: action_type.to_string(),
-            session_id: session.id.to_string(),
-            user_id: session.user_id.to_string(),
-            user_name: session
-                .context_data
-                .get("name")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string()),
-            user_phone: session
-                .context_data
-                .get("phone")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string()),
-            channel: session
-                .context_data
-                .get("channel")
-                .and_then(|v| v.as_str())
-                .unwrap_or("web")
-                .to_string(),
-            content: request.message.clone(),
-            timestamp: Utc::now().to_rfc3339(),
-            assigned_to: Some(request.attendant_id.clone()),
-            priority: 0,
-        };
-
-        if let Err(e) = broadcast_tx.send(notification) {
-            debug!("No attendants listening for broadcast: {}", e);
-        }
-    }
+    let notification = AttendantNotification {
+        notification_type: action_type.to_string(),
+        session_id: session.id.to_string(),
+        user_id: session.user_id.to_string(),
+        user_name: session
+            .context_data
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        user_phone: session
+            .context_data
+            .get("phone")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+        channel: session
+            .context_data
+            .get("channel")
+            .and_then(|v| v.as_str())
+            .unwrap_or("web")
+            .to_string(),
+        content: request.message.clone(),
+        timestamp: Utc::now().to_rfc3339(),
+        assigned_to: Some(request.attendant_id.clone()),
+        priority: 0,
+    };
+
+    notify_attendants(state, &notification).await;
 }
 
 pub async fn attendant_websocket_handler(
@@ -392,6 +393,7 @@ async fn handle_attendant_websocket(socket: WebSocket, state: Arc<AppState>, att
     let (mut sender, mut receiver) = socket.split();
 
     info!("Attendant WebSocket connected: {}", attendant_id);
+    handoffs::attendant_connected(&attendant_id);
 
     let welcome = serde_json::json!({
         "type": "connected",
@@ -407,18 +409,24 @@ async fn handle_attendant_websocket(socket: WebSocket, state: Arc<AppState>, att
         }
     }
 
-    let mut broadcast_rx = if let Some(broadcast_tx) = state.attendant_broadcast.as_ref() {
-        broadcast_tx.subscribe()
-    } else {
-        warn!("No broadcast channel available for attendants");
-        return;
+    let mut broadcast_rx = match state.message_bus.subscribe(ATTENDANT_TOPIC).await {
+        Ok(receiver) => receiver,
+        Err(e) => {
+            warn!("No notification channel available for attendants: {}", e);
+            return;
+        }
     };
 
     let attendant_id_clone = attendant_id.clone();
     let mut send_task = tokio::spawn(async move {
         loop {
             match broadcast_rx.recv().await {
-                Ok(notification) => {
+                Ok(message) => {
+                    let Ok(notification) =
+                        serde_json::from_value::<AttendantNotification>(message.payload)
+                    else {
+                        continue;
+                    };
                     let should_send = notification.assigned_to.is_none()
                         || notification.assigned_to.as_ref() == Some(&attendant_id_clone);
 
@@ -489,6 +497,7 @@ async fn handle_attendant_websocket(socket: WebSocket, state: Arc<AppState>, att
         }
     }
 
+    handoffs::attendant_disconnected(&attendant_id);
     info!("Attendant WebSocket disconnected: {}", attendant_id);
 }
 
@@ -506,6 +515,10 @@ async fn handle_attendant_message(
         "status_update" => {
             if let Some(status) = message.get("status").and_then(|v| v.as_str()) {
                 info!("Attendant {} status update: {}", attendant_id, status);
+                match serde_json::from_value::<AttendantStatus>(serde_json::json!(status)) {
+                    Ok(status) => handoffs::set_presence(attendant_id, status),
+                    Err(_) => debug!("Unknown status {} from attendant {}", status, attendant_id),
+                }
             }
         }
         "typing" => {
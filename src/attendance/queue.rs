@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::models::UserSession;
 use crate::core::shared::state::AppState;
 use axum::{
@@ -68,7 +69,7 @@ pub struct AttendantCSV {
     pub google: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AttendantStatus {
     Online,
@@ -446,7 +447,7 @@ pub async fn list_attendants(
 
 pub async fn assign_conversation(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<AssignRequest>,
+    ApiJson(request): ApiJson<AssignRequest>,
 ) -> impl IntoResponse {
     info!(
         "Assigning session {} to attendant {}",
@@ -542,7 +543,7 @@ pub struct SkillBasedAssignRequest {
 
 pub async fn assign_by_skill(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<SkillBasedAssignRequest>,
+    ApiJson(request): ApiJson<SkillBasedAssignRequest>,
 ) -> impl IntoResponse {
     info!(
         "Skill-based assignment for session {} with skills {:?}",
@@ -723,7 +724,7 @@ pub async fn assign_by_skill(
 
 pub async fn transfer_conversation(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<TransferRequest>,
+    ApiJson(request): ApiJson<TransferRequest>,
 ) -> impl IntoResponse {
     info!(
         "Transferring session {} from {} to {}",
@@ -818,7 +819,7 @@ pub async fn transfer_conversation(
 
 pub async fn resolve_conversation(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<serde_json::Value>,
+    ApiJson(payload): ApiJson<serde_json::Value>,
 ) -> impl IntoResponse {
     let session_id = payload
         .get("session_id")
@@ -844,6 +845,7 @@ pub async fn resolve_conversation(
                 .map_err(|e| format!("Session not found: {}", e))?;
 
             let mut ctx = session.context_data;
+            crate::core::bot::handoff::release(&mut ctx);
             ctx["status"] = serde_json::json!("resolved");
             ctx["resolved_at"] = serde_json::json!(Utc::now().to_rfc3339());
             ctx["resolved"] = serde_json::json!(true);
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::attendance_webhooks;
 use crate::core::shared::state::AppState;
 
@@ -84,7 +85,7 @@ pub async fn list_webhooks(
 
 pub async fn create_webhook(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateWebhookRequest>,
+    ApiJson(req): ApiJson<CreateWebhookRequest>,
 ) -> Result<Json<AttendanceWebhook>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -133,7 +134,7 @@ pub async fn get_webhook(
 pub async fn update_webhook(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateWebhookRequest>,
+    ApiJson(req): ApiJson<UpdateWebhookRequest>,
 ) -> Result<Json<AttendanceWebhook>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -216,7 +217,7 @@ pub async fn test_webhook(
         (StatusCode::INTERNAL_SERVER_ERROR, format!("Serialization error: {e}"))
     })?;
 
-    let client = reqwest::Client::new();
+    let client = crate::core::shared::outbound_proxy::http_client();
     let mut request = client.post(&webhook.webhook_url);
 
     if let Some(ref secret) = webhook.secret_key {
@@ -289,7 +290,7 @@ pub fn emit_webhook_event(
 
         let payload_json = serde_json::to_string(&payload).unwrap_or_default();
 
-        let mut request = reqwest::Client::new()
+        let mut request = crate::core::shared::outbound_proxy::http_client()
             .post(&webhook_url)
             .header("Content-Type", "application/json")
             .timeout(std::time::Duration::from_secs(5))
@@ -1,5 +1,6 @@
 pub mod ui;
 
+use crate::core::shared::api_json::ApiJson;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -313,7 +314,7 @@ fn generate_session_number(conn: &mut diesel::PgConnection, org_id: Uuid) -> Str
 
 pub async fn create_queue(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateQueueRequest>,
+    ApiJson(req): ApiJson<CreateQueueRequest>,
 ) -> Result<Json<AttendantQueue>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -426,7 +427,7 @@ pub async fn delete_queue(
 pub async fn add_queue_agent(
     State(state): State<Arc<AppState>>,
     Path(queue_id): Path<Uuid>,
-    Json(req): Json<AddQueueAgentRequest>,
+    ApiJson(req): ApiJson<AddQueueAgentRequest>,
 ) -> Result<Json<QueueAgent>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -475,7 +476,7 @@ pub async fn remove_queue_agent(
 
 pub async fn create_session(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateSessionRequest>,
+    ApiJson(req): ApiJson<CreateSessionRequest>,
 ) -> Result<Json<AttendantSession>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -595,7 +596,7 @@ pub async fn get_session(
 pub async fn assign_session(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<AssignSessionRequest>,
+    ApiJson(req): ApiJson<AssignSessionRequest>,
 ) -> Result<Json<AttendantSession>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -631,7 +632,7 @@ pub async fn assign_session(
 pub async fn transfer_session(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<TransferSessionRequest>,
+    ApiJson(req): ApiJson<TransferSessionRequest>,
 ) -> Result<Json<AttendantSession>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -691,7 +692,7 @@ pub async fn transfer_session(
 pub async fn end_session(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(_req): Json<EndSessionRequest>,
+    ApiJson(_req): ApiJson<EndSessionRequest>,
 ) -> Result<Json<AttendantSession>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -728,7 +729,7 @@ pub async fn end_session(
 pub async fn rate_session(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<RateSessionRequest>,
+    ApiJson(req): ApiJson<RateSessionRequest>,
 ) -> Result<Json<AttendantSession>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -753,7 +754,7 @@ pub async fn rate_session(
 pub async fn send_message(
     State(state): State<Arc<AppState>>,
     Path(session_id): Path<Uuid>,
-    Json(req): Json<SendMessageRequest>,
+    ApiJson(req): ApiJson<SendMessageRequest>,
 ) -> Result<Json<SessionMessage>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -798,7 +799,7 @@ pub async fn send_message(
 pub async fn update_agent_status(
     State(state): State<Arc<AppState>>,
     Path(agent_id): Path<Uuid>,
-    Json(req): Json<UpdateAgentStatusRequest>,
+    ApiJson(req): ApiJson<UpdateAgentStatusRequest>,
 ) -> Result<Json<AgentStatus>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -916,7 +917,7 @@ pub async fn list_canned_responses(
 
 pub async fn create_canned_response(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateCannedResponseRequest>,
+    ApiJson(req): ApiJson<CreateCannedResponseRequest>,
 ) -> Result<Json<CannedResponse>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -4,6 +4,7 @@ use crate::auto_task::task_types::{
 };
 use crate::auto_task::intent_classifier::IntentClassifier;
 use crate::auto_task::intent_compiler::IntentCompiler;
+use crate::core::shared::api_json::ApiJson;
 use crate::auto_task::safety_layer::{SafetyLayer, SimulationResult};
 use crate::core::shared::state::AppState;
 use axum::{
@@ -290,7 +291,7 @@ pub struct RecommendationResponse {
 
 pub async fn create_and_execute_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<CreateAndExecuteRequest>,
+    ApiJson(request): ApiJson<CreateAndExecuteRequest>,
 ) -> impl IntoResponse {
     info!(
         "Create and execute: {}",
@@ -403,7 +404,7 @@ pub async fn create_and_execute_handler(
 
 pub async fn classify_intent_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ClassifyIntentRequest>,
+    ApiJson(request): ApiJson<ClassifyIntentRequest>,
 ) -> impl IntoResponse {
     info!(
         "Classifying intent: {}",
@@ -530,7 +531,7 @@ pub async fn classify_intent_handler(
 
 pub async fn compile_intent_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<CompileIntentRequest>,
+    ApiJson(request): ApiJson<CompileIntentRequest>,
 ) -> impl IntoResponse {
     info!(
         "Compiling intent: {}",
@@ -677,7 +678,7 @@ pub async fn compile_intent_handler(
 
 pub async fn execute_plan_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ExecutePlanRequest>,
+    ApiJson(request): ApiJson<ExecutePlanRequest>,
 ) -> impl IntoResponse {
     info!("Executing plan: {}", request.plan_id);
 
@@ -1108,7 +1109,7 @@ pub async fn get_decisions_handler(
 pub async fn submit_decision_handler(
     State(state): State<Arc<AppState>>,
     Path(task_id): Path<String>,
-    Json(request): Json<DecisionRequest>,
+    ApiJson(request): ApiJson<DecisionRequest>,
 ) -> impl IntoResponse {
     match submit_decision(&state, &task_id, &request) {
         Ok(_) => (
@@ -1149,7 +1150,7 @@ pub async fn get_approvals_handler(
 pub async fn submit_approval_handler(
     State(state): State<Arc<AppState>>,
     Path(task_id): Path<String>,
-    Json(request): Json<ApprovalRequest>,
+    ApiJson(request): ApiJson<ApprovalRequest>,
 ) -> impl IntoResponse {
     match submit_approval(&state, &task_id, &request) {
         Ok(_) => (
@@ -2289,7 +2290,7 @@ pub struct SubmitPendingItemRequest {
 pub async fn submit_pending_item_handler(
     State(state): State<Arc<AppState>>,
     Path(item_id): Path<String>,
-    Json(request): Json<SubmitPendingItemRequest>,
+    ApiJson(request): ApiJson<SubmitPendingItemRequest>,
 ) -> impl IntoResponse {
     info!("Submitting pending item {item_id}: {}", request.value);
 
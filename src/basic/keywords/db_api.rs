@@ -1,6 +1,7 @@
 use super::table_access::{
     check_field_write_access, check_table_access, filter_fields_by_role, AccessType, UserRoles,
 };
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::core::shared::sanitize_identifier;
 use crate::core::urls::ApiUrls;
@@ -313,7 +314,7 @@ pub async fn create_record_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Path(table): Path<String>,
-    Json(payload): Json<Value>,
+    ApiJson(payload): ApiJson<Value>,
 ) -> impl IntoResponse {
     let table_name = sanitize_identifier(&table);
     let user_roles = user_roles_from_headers(&headers);
@@ -436,7 +437,7 @@ pub async fn update_record_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Path((table, id)): Path<(String, String)>,
-    Json(payload): Json<Value>,
+    ApiJson(payload): ApiJson<Value>,
 ) -> impl IntoResponse {
     let table_name = sanitize_identifier(&table);
     let user_roles = user_roles_from_headers(&headers);
@@ -745,7 +746,7 @@ pub async fn search_records_handler(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Path(table): Path<String>,
-    Json(payload): Json<SearchRequest>,
+    ApiJson(payload): ApiJson<SearchRequest>,
 ) -> impl IntoResponse {
     let table_name = sanitize_identifier(&table);
     let user_roles = user_roles_from_headers(&headers);
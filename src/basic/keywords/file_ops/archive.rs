@@ -31,6 +31,7 @@
 use crate::core::shared::models::schema::bots::dsl::*;
 use crate::core::shared::models::UserSession;
 use crate::core::shared::state::AppState;
+use crate::drive::quota;
 use diesel::prelude::*;
 use flate2::read::GzDecoder;
 use log::{error, trace};
@@ -85,6 +86,14 @@ pub async fn execute_compress(
     let bucket_name = format!("{bot_name}.gbai");
     let key = format!("{bot_name}.gbdrive/{archive_name}");
 
+    let delta = quota::check_s3_put(
+        state,
+        client,
+        &bucket_name,
+        &key,
+        archive_content.len() as u64,
+    )
+    .await?;
     client
         .put_object()
         .bucket(&bucket_name)
@@ -93,6 +102,7 @@ pub async fn execute_compress(
         .send()
         .await
         .map_err(|e| format!("S3 put failed: {e}"))?;
+    quota::record_change(state, &bucket_name, &key, delta).await;
 
     fs::remove_file(&archive_path).ok();
 
@@ -175,6 +185,9 @@ pub async fn execute_extract(
             let dest_path = format!("{}/{file_name}", destination.trim_end_matches('/'));
 
             let dest_key = format!("{bot_name}.gbdrive/{dest_path}");
+            let delta =
+                quota::check_s3_put(state, client, &bucket_name, &dest_key, content.len() as u64)
+                    .await?;
             client
                 .put_object()
                 .bucket(&bucket_name)
@@ -183,6 +196,7 @@ pub async fn execute_extract(
                 .send()
                 .await
                 .map_err(|e| format!("S3 put failed: {e}"))?;
+            quota::record_change(state, &bucket_name, &dest_key, delta).await;
 
             extracted_files.push(dest_path);
         }
@@ -201,6 +215,9 @@ pub async fn execute_extract(
             let dest_path = format!("{}/{file_name}", destination.trim_end_matches('/'));
 
             let dest_key = format!("{bot_name}.gbdrive/{dest_path}");
+            let delta =
+                quota::check_s3_put(state, client, &bucket_name, &dest_key, content.len() as u64)
+                    .await?;
             client
                 .put_object()
                 .bucket(&bucket_name)
@@ -209,6 +226,7 @@ pub async fn execute_extract(
                 .send()
                 .await
                 .map_err(|e| format!("S3 put failed: {e}"))?;
+            quota::record_change(state, &bucket_name, &dest_key, delta).await;
 
             extracted_files.push(dest_path);
         }
@@ -31,6 +31,7 @@
 use crate::core::shared::models::schema::bots::dsl::*;
 use crate::core::shared::models::UserSession;
 use crate::core::shared::state::AppState;
+use crate::drive::quota;
 use diesel::prelude::*;
 use log::{error, trace};
 use std::error::Error;
@@ -94,6 +95,8 @@ pub async fn execute_write(
     let bucket_name = format!("{bot_name}.gbai");
     let key = format!("{bot_name}.gbdrive/{path}");
 
+    let delta =
+        quota::check_s3_put(state, client, &bucket_name, &key, content.len() as u64).await?;
     client
         .put_object()
         .bucket(&bucket_name)
@@ -102,6 +105,7 @@ pub async fn execute_write(
         .send()
         .await
         .map_err(|e| format!("S3 put failed: {e}"))?;
+    quota::record_change(state, &bucket_name, &key, delta).await;
 
     trace!("WRITE successful: {} bytes to {path}", content.len());
     Ok(())
@@ -128,6 +132,7 @@ pub async fn execute_delete_file(
     let bucket_name = format!("{bot_name}.gbai");
     let key = format!("{bot_name}.gbdrive/{path}");
 
+    let size = quota::s3_object_size(client, &bucket_name, &key).await;
     client
         .delete_object()
         .bucket(&bucket_name)
@@ -135,6 +140,7 @@ pub async fn execute_delete_file(
         .send()
         .await
         .map_err(|e| format!("S3 delete failed: {e}"))?;
+    quota::record_change(state, &bucket_name, &key, -(size as i64)).await;
 
     trace!("DELETE_FILE successful: {path}");
     Ok(())
@@ -34,6 +34,7 @@ use crate::basic::keywords::use_account::{
 use crate::core::shared::models::schema::bots::dsl::*;
 use crate::core::shared::models::UserSession;
 use crate::core::shared::state::AppState;
+use crate::drive::quota;
 use diesel::prelude::*;
 use log::trace;
 use std::error::Error;
@@ -71,6 +72,8 @@ pub async fn execute_copy(
     let dest_key = format!("{bot_name}.gbdrive/{destination}");
 
     let copy_source = format!("{bucket_name}/{source_key}");
+    let size = quota::s3_object_size(client, &bucket_name, &source_key).await;
+    let delta = quota::check_s3_put(state, client, &bucket_name, &dest_key, size).await?;
 
     client
         .copy_object()
@@ -80,6 +83,7 @@ pub async fn execute_copy(
         .send()
         .await
         .map_err(|e| format!("S3 copy failed: {e}"))?;
+    quota::record_change(state, &bucket_name, &dest_key, delta).await;
 
     trace!("COPY successful: {source} -> {destination}");
     Ok(())
@@ -244,6 +248,8 @@ pub async fn write_to_local(
     let bucket_name = format!("{bot_name}.gbai");
     let key = format!("{bot_name}.gbdrive/{path}");
 
+    let delta =
+        quota::check_s3_put(state, client, &bucket_name, &key, content.len() as u64).await?;
     client
         .put_object()
         .bucket(&bucket_name)
@@ -251,6 +257,7 @@ pub async fn write_to_local(
         .body(content.to_vec().into())
         .send()
         .await?;
+    quota::record_change(state, &bucket_name, &key, delta).await;
     Ok(())
 }
 
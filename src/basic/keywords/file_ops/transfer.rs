@@ -31,6 +31,7 @@
 use crate::core::shared::models::schema::bots::dsl::*;
 use crate::core::shared::models::UserSession;
 use crate::core::shared::state::AppState;
+use crate::drive::quota;
 use diesel::prelude::*;
 use log::{error, trace};
 use std::error::Error;
@@ -72,6 +73,14 @@ pub async fn execute_upload(
         file_data.content.len()
     );
 
+    let delta = quota::check_s3_put(
+        state,
+        client,
+        &bucket_name,
+        &key,
+        file_data.content.len() as u64,
+    )
+    .await?;
     client
         .put_object()
         .bucket(&bucket_name)
@@ -81,6 +90,7 @@ pub async fn execute_upload(
         .send()
         .await
         .map_err(|e| format!("S3 put failed: {e}"))?;
+    quota::record_change(state, &bucket_name, &key, delta).await;
 
     let url = format!("s3://{bucket_name}/{key}");
     trace!(
@@ -0,0 +1,51 @@
+use crate::basic::keywords::session_variables::json_to_dynamic;
+use crate::core::config::ConfigManager;
+use crate::core::session::forms::{load_form, start_form, FormSchema};
+use crate::core::shared::models::UserSession;
+use crate::core::shared::state::AppState;
+use diesel::prelude::*;
+use log::{trace, warn};
+use rhai::{Engine, EvalAltResult, Map};
+use std::sync::Arc;
+
+/// `START_FORM(name)` makes the bot's `form-<name>` the session's active form and returns
+/// its first question; `GET_FORM(name)` returns the values collected so far.
+pub fn forms_keyword(state: Arc<AppState>, user: UserSession, engine: &mut Engine) {
+    let start_state = Arc::clone(&state);
+    let start_user = user.clone();
+    engine.register_fn(
+        "START_FORM",
+        move |name: &str| -> Result<String, Box<EvalAltResult>> {
+            let config = ConfigManager::new(start_state.conn.clone());
+            let schema = FormSchema::for_bot(&config, start_user.bot_id, name)
+                .map_err(|e| format!("START_FORM {name}: {e}"))?;
+            let mut conn = start_state
+                .conn
+                .get()
+                .map_err(|e| format!("START_FORM {name}: no database connection: {e}"))?;
+            let question = conn
+                .transaction(|conn| start_form(conn, start_user.id, &schema))
+                .map_err(|e| format!("START_FORM {name}: {e}"))?;
+            trace!("Session {} started form {}", start_user.id, name);
+            Ok(question)
+        },
+    );
+
+    engine.register_fn("GET_FORM", move |name: &str| -> Map {
+        let Ok(mut conn) = state.conn.get() else {
+            warn!("GET_FORM {name}: no database connection");
+            return Map::new();
+        };
+        match load_form(&mut *conn, user.id, name) {
+            Ok(form) => form
+                .values
+                .iter()
+                .map(|(k, v)| (k.clone().into(), json_to_dynamic(v)))
+                .collect(),
+            Err(e) => {
+                warn!("GET_FORM {name} failed: {e}");
+                Map::new()
+            }
+        }
+    });
+}
@@ -40,6 +40,7 @@ pub mod products;
 pub mod search;
 pub mod for_next;
 pub mod format;
+pub mod forms;
 pub mod get;
 pub mod hear_talk;
 pub mod hearing;
@@ -65,6 +66,7 @@ pub mod procedures;
 pub mod qrcode;
 #[cfg(feature = "security")]
 pub mod security_protection;
+pub mod session_variables;
 pub mod set;
 pub mod set_context;
 pub mod set_user;
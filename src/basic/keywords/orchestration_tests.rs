@@ -79,5 +79,9 @@ fn create_mock_user_session() -> UserSession {
         current_tool: None,
         created_at: chrono::Utc::now(),
         updated_at: chrono::Utc::now(),
+        deleted_at: None,
+        deleted_by: None,
+        parent_session_id: None,
+        forked_at_index: None,
     }
 }
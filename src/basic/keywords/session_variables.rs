@@ -0,0 +1,132 @@
+use crate::core::session::variables::{get_variable, set_variable_locked};
+use crate::core::shared::models::UserSession;
+use crate::core::shared::state::AppState;
+use log::{trace, warn};
+use rhai::{Dynamic, Engine, EvalAltResult, Map};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// `SET SESSION key, value` and `SET_SESSION`, `GET_SESSION` and `CLEAR_SESSION` for
+/// values kept across the turns of the current session.
+pub fn session_variables_keyword(state: Arc<AppState>, user: UserSession, engine: &mut Engine) {
+    let set_state = Arc::clone(&state);
+    let set_user = user.clone();
+    engine
+        .register_custom_syntax(
+            ["SET", "SESSION", "$expr$", ",", "$expr$"],
+            false,
+            move |context, inputs| {
+                let key = context.eval_expression_tree(&inputs[0])?.to_string();
+                let value = context.eval_expression_tree(&inputs[1])?;
+                set_session(&set_state, &set_user, &key, &value)?;
+                Ok(Dynamic::UNIT)
+            },
+        )
+        .expect("valid syntax registration");
+
+    let fn_state = Arc::clone(&state);
+    let fn_user = user.clone();
+    engine.register_fn(
+        "SET_SESSION",
+        move |key: &str, value: Dynamic| -> Result<(), Box<EvalAltResult>> {
+            set_session(&fn_state, &fn_user, key, &value)
+        },
+    );
+
+    let clear_state = Arc::clone(&state);
+    let clear_user = user.clone();
+    engine.register_fn(
+        "CLEAR_SESSION",
+        move |key: &str| -> Result<(), Box<EvalAltResult>> {
+            set_session(&clear_state, &clear_user, key, &Dynamic::UNIT)
+        },
+    );
+
+    engine.register_fn("GET_SESSION", move |key: &str| -> Dynamic {
+        let Ok(mut conn) = state.conn.get() else {
+            warn!("GET_SESSION {key}: no database connection");
+            return Dynamic::UNIT;
+        };
+        match get_variable(&mut *conn, user.id, key) {
+            Ok(Some(value)) => json_to_dynamic(&value),
+            Ok(None) => Dynamic::UNIT,
+            Err(e) => {
+                warn!("GET_SESSION {key} failed: {e}");
+                Dynamic::UNIT
+            }
+        }
+    });
+}
+
+/// Written synchronously so a `GET_SESSION` later in the same script sees the value.
+fn set_session(
+    state: &AppState,
+    user: &UserSession,
+    key: &str,
+    value: &Dynamic,
+) -> Result<(), Box<EvalAltResult>> {
+    let mut conn = state
+        .conn
+        .get()
+        .map_err(|e| format!("SET SESSION {key}: no database connection: {e}"))?;
+    let variables = set_variable_locked(&mut conn, user.id, key, dynamic_to_json(value))
+        .map_err(|e| format!("SET SESSION {key}: {e}"))?;
+    trace!(
+        "Session {} variable {} set ({} bytes in use)",
+        user.id,
+        key,
+        variables.size_bytes()
+    );
+    Ok(())
+}
+
+pub fn dynamic_to_json(value: &Dynamic) -> Value {
+    if value.is_unit() {
+        Value::Null
+    } else if value.is_bool() {
+        Value::Bool(value.as_bool().unwrap_or(false))
+    } else if value.is_int() {
+        Value::Number(value.as_int().unwrap_or(0).into())
+    } else if value.is_float() {
+        value
+            .as_float()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map_or(Value::Null, Value::Number)
+    } else if value.is_array() {
+        let arr = value.clone().into_array().unwrap_or_default();
+        Value::Array(arr.iter().map(dynamic_to_json).collect())
+    } else if value.is_map() {
+        let map = value.clone().try_cast::<Map>().unwrap_or_default();
+        Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.to_string(), dynamic_to_json(v)))
+                .collect(),
+        )
+    } else {
+        Value::String(value.to_string())
+    }
+}
+
+pub fn json_to_dynamic(value: &Value) -> Dynamic {
+    match value {
+        Value::Null => Dynamic::UNIT,
+        Value::Bool(b) => Dynamic::from(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Dynamic::from(i)
+            } else {
+                n.as_f64().map_or(Dynamic::UNIT, Dynamic::from)
+            }
+        }
+        Value::String(s) => Dynamic::from(s.clone()),
+        Value::Array(arr) => Dynamic::from(arr.iter().map(json_to_dynamic).collect::<rhai::Array>()),
+        Value::Object(obj) => {
+            let map: Map = obj
+                .iter()
+                .map(|(k, v)| (k.clone().into(), json_to_dynamic(v)))
+                .collect();
+            Dynamic::from(map)
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::bot::handoff::{self, HandoffTrigger};
 use crate::core::shared::models::UserSession;
 use crate::core::shared::state::AppState;
 use chrono::Utc;
@@ -334,23 +335,36 @@ pub async fn execute_transfer(
     let session_id = session.id;
     let conn = state.conn.clone();
     let ctx_data = transfer_context.clone();
+    let reason = request.reason.clone().unwrap_or_default();
 
     let update_result = tokio::task::spawn_blocking(move || {
         let mut db_conn = conn
             .get()
             .map_err(|e| format!("DB connection error: {}", e))?;
 
-        use crate::core::shared::models::schema::user_sessions;
-
-        diesel::update(user_sessions::table.filter(user_sessions::id.eq(session_id)))
-            .set(user_sessions::context_data.eq(ctx_data))
-            .execute(&mut db_conn)
-            .map_err(|e| format!("Failed to update session: {}", e))
+        handoff::update_context(&mut db_conn, session_id, |ctx| {
+            handoff::mark_escalated(ctx, HandoffTrigger::Bot, &reason);
+            if let Some(transfer) = ctx_data.as_object() {
+                for (key, value) in transfer {
+                    ctx[key] = value.clone();
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to update session: {}", e))
     })
     .await;
 
     match update_result {
-        Ok(Ok(_)) => {
+        Ok(Ok(context)) => {
+            let mut transferred = session.clone();
+            transferred.context_data = context;
+            handoff::announce(
+                state.message_bus.as_ref(),
+                &transferred,
+                request.reason.as_deref().unwrap_or_default(),
+            )
+            .await;
+
             if let Some(att) = attendant {
                 info!(
                     "Transfer: Session {} assigned to {} ({})",
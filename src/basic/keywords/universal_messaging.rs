@@ -1,5 +1,9 @@
 use crate::core::bot::channels::{
-    instagram::InstagramAdapter, teams::TeamsAdapter, whatsapp::WhatsAppAdapter, ChannelAdapter,
+    instagram::InstagramAdapter,
+    rich::{ResponseAttachment, RichResponse},
+    teams::TeamsAdapter,
+    whatsapp::WhatsAppAdapter,
+    ChannelAdapter,
 };
 use crate::core::shared::message_types::MessageType;
 use crate::core::shared::models::UserSession;
@@ -325,16 +329,21 @@ async fn send_file_with_caption_to_recipient(
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let (channel, recipient_id) = parse_recipient(state.clone(), recipient).await?;
 
-    let file_data = if file.is_string() {
+    let (file_name, file_data) = if file.is_string() {
         let file_path = file.to_string();
-        std::fs::read(&file_path)?
+        let file_name = std::path::Path::new(&file_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "file".to_string());
+        (file_name, std::fs::read(&file_path)?)
     } else {
         return Err("File must be a string path".into());
     };
 
     match channel.as_str() {
         "whatsapp" => {
-            send_whatsapp_file(state, user, &recipient_id, file_data, caption).await?;
+            send_whatsapp_file(state, user, &recipient_id, &file_name, file_data, caption)
+                .await?;
         }
         "instagram" => {
             #[cfg(feature = "drive")]
@@ -343,10 +352,10 @@ async fn send_file_with_caption_to_recipient(
             return Err("Drive feature not enabled".into());
         }
         "teams" => {
-            send_teams_file(state, user, &recipient_id, file_data, caption).await?;
+            send_teams_file(state, user, &recipient_id, &file_name, file_data, caption).await?;
         }
         "web" => {
-            send_web_file(state, &recipient_id, file_data, caption).await?;
+            send_web_file(state, &recipient_id, &file_name, file_data, caption).await?;
         }
         "email" => {
             send_email_attachment(state, user.bot_id, &recipient_id, file_data, caption)?;
@@ -448,59 +457,13 @@ async fn send_whatsapp_file(
     state: Arc<AppState>,
     user: &UserSession,
     recipient: &str,
+    file_name: &str,
     file_data: Vec<u8>,
     caption: &str,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    use reqwest::Client;
-
-    let _adapter = WhatsAppAdapter::new(&state, user.bot_id);
-
-    let phone_number_id = "";
-    let upload_url = format!("https://graph.facebook.com/v17.0/{}/media", phone_number_id);
-
-    let client = Client::new();
-    let form = reqwest::multipart::Form::new()
-        .text("messaging_product", "whatsapp")
-        .part("file", reqwest::multipart::Part::bytes(file_data));
-
-    let access_token = "";
-    let upload_response = client
-        .post(&upload_url)
-        .bearer_auth(access_token)
-        .multipart(form)
-        .send()
-        .await?;
-
-    if !upload_response.status().is_success() {
-        return Err("Failed to upload file to WhatsApp".into());
-    }
-
-    let upload_result: serde_json::Value = upload_response.json().await?;
-    let media_id = upload_result["id"].as_str().ok_or("No media ID returned")?;
-
-    let send_url = format!(
-        "https://graph.facebook.com/v17.0/{}/messages",
-        phone_number_id
-    );
-
-    let payload = json!({
-        "messaging_product": "whatsapp",
-        "to": recipient,
-        "type": "document",
-        "document": {
-            "id": media_id,
-            "caption": caption
-        }
-    });
-
-    client
-        .post(&send_url)
-        .bearer_auth(access_token)
-        .json(&payload)
-        .send()
-        .await?;
-
-    Ok(())
+    WhatsAppAdapter::new(&state, user.bot_id)
+        .send_file(recipient, file_data, file_name, caption)
+        .await
 }
 
 #[cfg(feature = "drive")]
@@ -555,64 +518,38 @@ async fn send_teams_file(
     state: Arc<AppState>,
     user: &UserSession,
     recipient_id: &str,
+    file_name: &str,
     file_data: Vec<u8>,
     caption: &str,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let _adapter = TeamsAdapter::new(state.conn.clone(), user.bot_id);
-
-    let conversation_id = get_teams_conversation_id(&state, recipient_id).await?;
+    use base64::{engine::general_purpose::STANDARD, Engine};
 
-    let access_token = "";
-    let service_url = "https://smba.trafficmanager.net/apis".to_string();
+    let adapter = TeamsAdapter::new(state.conn.clone(), user.bot_id);
     let url = format!(
-        "{}/v3/conversations/{}/activities",
-        service_url.trim_end_matches('/'),
-        conversation_id
+        "data:application/octet-stream;base64,{}",
+        STANDARD.encode(&file_data)
     );
-
-    use base64::{engine::general_purpose::STANDARD, Engine};
-    let attachment = json!({
-        "contentType": "application/octet-stream",
-        "contentUrl": format!("data:application/octet-stream;base64,{}", STANDARD.encode(&file_data)),
-        "name": "attachment"
-    });
-
-    let activity = json!({
-        "type": "message",
-        "text": caption,
-        "from": {
-            "id": "",
-            "name": "Bot"
-        },
-        "conversation": {
-            "id": conversation_id
-        },
-        "recipient": {
-            "id": recipient_id
-        },
-        "attachments": [attachment]
-    });
-
-    use reqwest::Client;
-    let client = Client::new();
-    client
-        .post(&url)
-        .bearer_auth(access_token)
-        .json(&activity)
-        .send()
-        .await?;
-
-    Ok(())
+    let response = crate::core::shared::models::BotResponse {
+        bot_id: "default".to_string(),
+        session_id: user.id.to_string(),
+        user_id: recipient_id.to_string(),
+        channel: "teams".to_string(),
+        content: caption.to_string(),
+        message_type: MessageType::EXTERNAL,
+        stream_token: None,
+        is_complete: true,
+        suggestions: vec![],
+        context_name: None,
+        context_length: 0,
+        context_max_length: 0,
+    };
+    let rich =
+        RichResponse::new(response).with_attachment(ResponseAttachment::file(url, file_name));
+    adapter.send_rich(rich).await
 }
 
-async fn send_web_message(
-    state: Arc<AppState>,
-    session_id: &str,
-    message: &str,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let web_adapter = Arc::clone(&state.web_adapter);
-
-    let response = crate::core::shared::models::BotResponse {
+fn web_response(session_id: &str, message: &str) -> crate::core::shared::models::BotResponse {
+    crate::core::shared::models::BotResponse {
         bot_id: "system".to_string(),
         user_id: session_id.to_string(),
         session_id: session_id.to_string(),
@@ -625,10 +562,18 @@ async fn send_web_message(
         context_name: None,
         context_length: 0,
         context_max_length: 0,
-    };
+    }
+}
+
+async fn send_web_message(
+    state: Arc<AppState>,
+    session_id: &str,
+    message: &str,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let web_adapter = Arc::clone(&state.web_adapter);
 
     web_adapter
-        .send_message_to_session(session_id, response)
+        .send_message_to_session(session_id, web_response(session_id, message))
         .await?;
 
     Ok(())
@@ -637,6 +582,7 @@ async fn send_web_message(
 async fn send_web_file(
     state: Arc<AppState>,
     session_id: &str,
+    file_name: &str,
     file_data: Vec<u8>,
     caption: &str,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -656,13 +602,13 @@ async fn send_web_file(
             .await?;
     }
 
-    let message = if caption.is_empty() {
-        format!("[File: {}]", file_url)
-    } else {
-        format!("{}\n[File: {}]", caption, file_url)
-    };
+    let rich = RichResponse::new(web_response(session_id, caption))
+        .with_attachment(ResponseAttachment::file(file_url, file_name));
 
-    send_web_message(state, session_id, &message).await
+    state
+        .web_adapter
+        .send_rich_to_session(session_id, rich)
+        .await
 }
 
 fn send_email(
@@ -718,23 +664,3 @@ fn send_email_attachment(
         Err("Email feature not enabled".into())
     }
 }
-
-async fn get_teams_conversation_id(
-    state: &Arc<AppState>,
-    user_id: &str,
-) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-    if let Some(redis_client) = &state.cache {
-        let mut conn = redis_client.get_multiplexed_async_connection().await?;
-        let key = format!("teams_conversation:{}", user_id);
-
-        if let Ok(conversation) = redis::cmd("GET")
-            .arg(&key)
-            .query_async::<String>(&mut conn)
-            .await
-        {
-            return Ok(conversation);
-        }
-    }
-
-    Ok(user_id.to_string())
-}
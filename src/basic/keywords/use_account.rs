@@ -1,5 +1,6 @@
 use crate::core::shared::models::UserSession;
 use crate::core::shared::state::AppState;
+use crate::security::master_key::{self, LegacyEncoding};
 use diesel::prelude::*;
 use log::{error, info};
 use rhai::{Dynamic, Engine, EvalAltResult};
@@ -202,11 +203,19 @@ pub async fn get_account_credentials(
     .get_result(&mut conn)
     .map_err(|e| format!("Account not found: {}", e))?;
 
+    let access_token = master_key::reveal(&creds.access_token, LegacyEncoding::Plain)
+        .map_err(|e| format!("Failed to decrypt access token: {}", e))?;
+    let refresh_token = creds
+        .refresh_token
+        .map(|token| master_key::reveal(&token, LegacyEncoding::Plain))
+        .transpose()
+        .map_err(|e| format!("Failed to decrypt refresh token: {}", e))?;
+
     Ok(AccountCredentials {
         account_id: creds.id,
         provider: creds.provider,
-        access_token: creds.access_token,
-        refresh_token: creds.refresh_token,
+        access_token,
+        refresh_token,
     })
 }
 
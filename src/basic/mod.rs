@@ -59,6 +59,8 @@ use self::keywords::detect::detect_keyword;
 use self::keywords::on::on_keyword;
 use self::keywords::print::print_keyword;
 use self::keywords::set::set_keyword;
+use self::keywords::forms::forms_keyword;
+use self::keywords::session_variables::session_variables_keyword;
 use self::keywords::set_context::set_context_keyword;
 use self::keywords::wait::wait_keyword;
 
@@ -156,6 +158,8 @@ impl ScriptService {
         hear_keyword(state.clone(), user.clone(), &mut engine);
         talk_keyword(state.clone(), user.clone(), &mut engine);
         set_context_keyword(state.clone(), user.clone(), &mut engine);
+        session_variables_keyword(state.clone(), user.clone(), &mut engine);
+        forms_keyword(state.clone(), user.clone(), &mut engine);
         set_user_keyword(state.clone(), user.clone(), &mut engine);
         #[cfg(feature = "chat")]
         clear_suggestions_keyword(state.clone(), user.clone(), &mut engine);
@@ -15,6 +15,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::{
     billing_invoice_items, billing_invoices, billing_payments, billing_quote_items,
     billing_quotes, billing_recurring, billing_tax_rates,
@@ -325,7 +326,7 @@ fn bd_to_f64(val: &BigDecimal) -> f64 {
 
 pub async fn create_invoice(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateInvoiceRequest>,
+    ApiJson(req): ApiJson<CreateInvoiceRequest>,
 ) -> Result<Json<BillingInvoice>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -497,7 +498,7 @@ pub async fn get_invoice(
 pub async fn update_invoice(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateInvoiceRequest>,
+    ApiJson(req): ApiJson<UpdateInvoiceRequest>,
 ) -> Result<Json<BillingInvoice>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -610,7 +611,7 @@ pub async fn delete_invoice(
 
 pub async fn record_payment(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<RecordPaymentRequest>,
+    ApiJson(req): ApiJson<RecordPaymentRequest>,
 ) -> Result<Json<BillingPayment>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -731,7 +732,7 @@ pub async fn get_payment(
 
 pub async fn create_quote(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateQuoteRequest>,
+    ApiJson(req): ApiJson<CreateQuoteRequest>,
 ) -> Result<Json<BillingQuote>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
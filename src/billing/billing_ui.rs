@@ -13,6 +13,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::{billing_invoices, billing_payments, billing_quotes};
 use crate::core::shared::state::AppState;
 
@@ -187,7 +188,7 @@ struct UpgradeRequest {
 
 async fn handle_subscription_upgrade(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<UpgradeRequest>,
+    ApiJson(req): ApiJson<UpgradeRequest>,
 ) -> impl IntoResponse {
     Json(serde_json::json!({
         "success": true,
@@ -204,7 +205,7 @@ struct CancelRequest {
 
 async fn handle_subscription_cancel(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<CancelRequest>,
+    ApiJson(req): ApiJson<CancelRequest>,
 ) -> impl IntoResponse {
     Json(serde_json::json!({
         "success": true,
@@ -216,7 +217,7 @@ async fn handle_subscription_cancel(
 
 async fn handle_admin_billing_quotas(
     State(_state): State<Arc<AppState>>,
-    Json(quotas): Json<serde_json::Value>,
+    ApiJson(quotas): ApiJson<serde_json::Value>,
 ) -> impl IntoResponse {
     Json(serde_json::json!({
         "success": true,
@@ -227,7 +228,7 @@ async fn handle_admin_billing_quotas(
 
 async fn handle_admin_billing_alerts(
     State(_state): State<Arc<AppState>>,
-    Json(settings): Json<serde_json::Value>,
+    ApiJson(settings): ApiJson<serde_json::Value>,
 ) -> impl IntoResponse {
     Json(serde_json::json!({
         "success": true,
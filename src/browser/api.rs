@@ -7,6 +7,7 @@ use axum::{
 use serde::Deserialize;
 use std::sync::Arc;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 pub fn configure_browser_routes() -> Router<Arc<AppState>> {
@@ -26,7 +27,7 @@ pub struct CreateSessionRequest {
 
 pub async fn create_session(
     State(_state): State<Arc<AppState>>,
-    Json(_payload): Json<CreateSessionRequest>,
+    ApiJson(_payload): ApiJson<CreateSessionRequest>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
     Ok(Json(serde_json::json!({
         "id": "mock-session-id-1234",
@@ -43,7 +44,7 @@ pub struct ExecuteActionRequest {
 pub async fn run_action(
     State(_state): State<Arc<AppState>>,
     Path(id): Path<String>,
-    Json(_payload): Json<ExecuteActionRequest>,
+    ApiJson(_payload): ApiJson<ExecuteActionRequest>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
     Ok(Json(serde_json::json!({ "status": "success", "session": id })))
 }
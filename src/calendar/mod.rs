@@ -17,6 +17,9 @@ use uuid::Uuid;
 
 use crate::core::shared::schema::{calendar_event_attendees, calendar_events, calendar_shares, calendars};
 use crate::core::urls::ApiUrls;
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::bot_features::{self, BotFeature};
 use crate::core::shared::state::AppState;
 
 pub mod caldav;
@@ -335,7 +338,7 @@ pub fn import_from_ical(ical_str: &str, organizer: &str, calendar_id: Uuid) -> V
 
 pub async fn create_calendar(
     State(state): State<Arc<AppState>>,
-    Json(input): Json<CreateCalendarRequest>,
+    ApiJson(input): ApiJson<CreateCalendarRequest>,
 ) -> Result<Json<CalendarRecord>, StatusCode> {
     let pool = state.conn.clone();
     let (org_id, bot_id) = get_bot_context();
@@ -420,7 +423,7 @@ pub async fn get_calendar(
 pub async fn update_calendar(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(input): Json<UpdateCalendarRequest>,
+    ApiJson(input): ApiJson<UpdateCalendarRequest>,
 ) -> Result<Json<CalendarRecord>, StatusCode> {
     let pool = state.conn.clone();
 
@@ -558,7 +561,7 @@ pub async fn get_event(
 
 pub async fn create_event(
     State(state): State<Arc<AppState>>,
-    Json(input): Json<CalendarEventInput>,
+    ApiJson(input): ApiJson<CalendarEventInput>,
 ) -> Result<Json<CalendarEvent>, StatusCode> {
     let pool = state.conn.clone();
     let (org_id, bot_id) = get_bot_context();
@@ -622,7 +625,7 @@ pub async fn create_event(
 pub async fn update_event(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(input): Json<CalendarEventInput>,
+    ApiJson(input): ApiJson<CalendarEventInput>,
 ) -> Result<Json<CalendarEvent>, StatusCode> {
     let pool = state.conn.clone();
 
@@ -698,7 +701,7 @@ pub async fn delete_event(
 pub async fn share_calendar(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(input): Json<ShareCalendarRequest>,
+    ApiJson(input): ApiJson<ShareCalendarRequest>,
 ) -> Result<Json<CalendarShareRecord>, StatusCode> {
     let pool = state.conn.clone();
 
@@ -1019,6 +1022,23 @@ pub async fn new_calendar_form() -> Html<String> {
     "##.to_string())
 }
 
+/// Rejects calendar requests for bots that have the calendar feature switched off. The
+/// bot comes from the caller's session, otherwise the default bot context.
+pub async fn require_calendar_feature(
+    State(state): State<Arc<AppState>>,
+    req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, ApiError> {
+    let bot_id = match bot_features::session_bot_id(&state, &req).await? {
+        Some(bot_id) => bot_id,
+        None => tokio::task::spawn_blocking(|| get_bot_context().1)
+            .await
+            .map_err(|e| ApiError::internal(e.to_string()))?,
+    };
+    bot_features::ensure_enabled(&state, bot_id, BotFeature::Calendar).await?;
+    Ok(next.run(req).await)
+}
+
 pub fn configure_calendar_routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/api/calendar/calendars", get(list_calendars_db).post(create_calendar))
@@ -1,5 +1,6 @@
 pub mod ui;
 
+use crate::core::shared::api_json::ApiJson;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -443,7 +444,7 @@ async fn list_canvases(
 
 async fn create_canvas(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateCanvasRequest>,
+    ApiJson(req): ApiJson<CreateCanvasRequest>,
 ) -> Result<Json<Canvas>, (StatusCode, String)> {
     let mut conn = state
         .conn
@@ -510,7 +511,7 @@ async fn get_canvas(
 async fn update_canvas(
     State(state): State<Arc<AppState>>,
     Path(canvas_id): Path<Uuid>,
-    Json(req): Json<UpdateCanvasRequest>,
+    ApiJson(req): ApiJson<UpdateCanvasRequest>,
 ) -> Result<Json<Canvas>, (StatusCode, String)> {
     let mut conn = state
         .conn
@@ -627,7 +628,7 @@ async fn list_elements(
 async fn create_element(
     State(state): State<Arc<AppState>>,
     Path(canvas_id): Path<Uuid>,
-    Json(req): Json<CreateElementRequest>,
+    ApiJson(req): ApiJson<CreateElementRequest>,
 ) -> Result<Json<CanvasElement>, (StatusCode, String)> {
     let mut conn = state
         .conn
@@ -689,7 +690,7 @@ async fn create_element(
 async fn update_element(
     State(state): State<Arc<AppState>>,
     Path((canvas_id, element_id)): Path<(Uuid, Uuid)>,
-    Json(req): Json<UpdateElementRequest>,
+    ApiJson(req): ApiJson<UpdateElementRequest>,
 ) -> Result<Json<CanvasElement>, (StatusCode, String)> {
     let mut conn = state
         .conn
@@ -774,7 +775,7 @@ async fn delete_element(
 async fn export_canvas(
     State(state): State<Arc<AppState>>,
     Path(canvas_id): Path<Uuid>,
-    Json(req): Json<ExportRequest>,
+    ApiJson(req): ApiJson<ExportRequest>,
 ) -> Result<Json<ExportResponse>, (StatusCode, String)> {
     let mut conn = state
         .conn
@@ -941,7 +942,7 @@ async fn list_collaborators(
 async fn add_collaborator(
     State(state): State<Arc<AppState>>,
     Path(canvas_id): Path<Uuid>,
-    Json(req): Json<AddCollaboratorRequest>,
+    ApiJson(req): ApiJson<AddCollaboratorRequest>,
 ) -> Result<Json<DbCanvasCollaborator>, (StatusCode, String)> {
     let mut conn = state
         .conn
@@ -1016,7 +1017,7 @@ async fn list_comments(
 async fn create_comment(
     State(state): State<Arc<AppState>>,
     Path(canvas_id): Path<Uuid>,
-    Json(req): Json<CreateCommentRequest>,
+    ApiJson(req): ApiJson<CreateCommentRequest>,
 ) -> Result<Json<DbCanvasComment>, (StatusCode, String)> {
     let mut conn = state
         .conn
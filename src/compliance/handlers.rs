@@ -8,10 +8,12 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::{
     compliance_audit_log, compliance_checks, compliance_issues, compliance_training_records,
 };
 use crate::core::shared::state::AppState;
+use crate::security::upload_policy::check_upload;
 
 use super::storage::{
     db_audit_to_entry, db_check_to_result, db_issue_to_result, DbAuditLog, DbComplianceCheck,
@@ -81,7 +83,7 @@ pub async fn handle_list_checks(
 
 pub async fn handle_run_check(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<RunCheckRequest>,
+    ApiJson(req): ApiJson<RunCheckRequest>,
 ) -> Result<Json<Vec<ComplianceCheckResult>>, ComplianceError> {
     let pool = state.conn.clone();
 
@@ -226,7 +228,7 @@ pub async fn handle_list_issues(
 
 pub async fn handle_create_issue(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateIssueRequest>,
+    ApiJson(req): ApiJson<CreateIssueRequest>,
 ) -> Result<Json<ComplianceIssueResult>, ComplianceError> {
     let pool = state.conn.clone();
 
@@ -273,7 +275,7 @@ pub async fn handle_create_issue(
 pub async fn handle_update_issue(
     State(state): State<Arc<AppState>>,
     Path(issue_id): Path<Uuid>,
-    Json(req): Json<UpdateIssueRequest>,
+    ApiJson(req): ApiJson<UpdateIssueRequest>,
 ) -> Result<Json<ComplianceIssueResult>, ComplianceError> {
     let pool = state.conn.clone();
 
@@ -387,7 +389,7 @@ pub async fn handle_list_audit_logs(
 
 pub async fn handle_create_audit_log(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateAuditLogRequest>,
+    ApiJson(req): ApiJson<CreateAuditLogRequest>,
 ) -> Result<Json<AuditLogEntry>, ComplianceError> {
     let pool = state.conn.clone();
 
@@ -432,7 +434,7 @@ pub async fn handle_create_audit_log(
 
 pub async fn handle_create_training(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateTrainingRequest>,
+    ApiJson(req): ApiJson<CreateTrainingRequest>,
 ) -> Result<Json<TrainingRecord>, ComplianceError> {
     let pool = state.conn.clone();
 
@@ -594,7 +596,11 @@ mut multipart: axum::extract::Multipart,
         match name.as_str() {
             "file" => {
                 file_name = field.file_name().unwrap_or("unknown").to_string();
+                let content_type = field.content_type().unwrap_or_default().to_string();
                 let data = field.bytes().await.map_err(|e| ComplianceError::Internal(e.to_string()))?;
+                check_upload("compliance-evidence", &file_name, &content_type, &data)
+                    .await
+                    .map_err(ComplianceError::UploadRejected)?;
                 file_size = data.len();
             }
             "category" => {
@@ -53,6 +53,8 @@ pub enum ComplianceError {
     Database(String),
     #[error("Internal error: {0}")]
     Internal(String),
+    #[error("{0}")]
+    UploadRejected(crate::security::upload_policy::UploadRejection),
 }
 
 impl IntoResponse for ComplianceError {
@@ -64,6 +66,7 @@ impl IntoResponse for ComplianceError {
             Self::Database(msg) | Self::Internal(msg) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, msg.clone())
             }
+            Self::UploadRejected(rejection) => (rejection.status(), rejection.to_string()),
         };
         (status, Json(serde_json::json!({ "error": message }))).into_response()
     }
@@ -12,6 +12,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::shared::schema::{calendar_events, crm_contacts};
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::core::shared::utils::DbPool;
 
@@ -1070,7 +1071,7 @@ async fn get_event_contacts_handler(
 async fn link_contact_handler(
     State(state): State<Arc<AppState>>,
     Path(event_id): Path<Uuid>,
-    Json(request): Json<LinkContactRequest>,
+    ApiJson(request): ApiJson<LinkContactRequest>,
 ) -> impl IntoResponse {
     let service = CalendarIntegrationService::new(state.conn.clone());
     let org_id = Uuid::new_v4();
@@ -1084,7 +1085,7 @@ async fn link_contact_handler(
 async fn bulk_link_contacts_handler(
     State(state): State<Arc<AppState>>,
     Path(event_id): Path<Uuid>,
-    Json(request): Json<BulkLinkContactsRequest>,
+    ApiJson(request): ApiJson<BulkLinkContactsRequest>,
 ) -> impl IntoResponse {
     let service = CalendarIntegrationService::new(state.conn.clone());
     let org_id = Uuid::new_v4();
@@ -1111,7 +1112,7 @@ async fn unlink_contact_handler(
 async fn update_event_contact_handler(
     State(state): State<Arc<AppState>>,
     Path((event_id, contact_id)): Path<(Uuid, Uuid)>,
-    Json(request): Json<UpdateEventContactRequest>,
+    ApiJson(request): ApiJson<UpdateEventContactRequest>,
 ) -> impl IntoResponse {
     let service = CalendarIntegrationService::new(state.conn.clone());
     let org_id = Uuid::new_v4();
@@ -1166,7 +1167,7 @@ async fn find_contacts_handler(
 async fn create_contacts_from_attendees_handler(
     State(state): State<Arc<AppState>>,
     Path(event_id): Path<Uuid>,
-    Json(attendees): Json<Vec<AttendeeInfo>>,
+    ApiJson(attendees): ApiJson<Vec<AttendeeInfo>>,
 ) -> impl IntoResponse {
     let service = CalendarIntegrationService::new(state.conn.clone());
     let org_id = Uuid::new_v4();
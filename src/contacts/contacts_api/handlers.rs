@@ -10,6 +10,7 @@ use axum::{
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 pub fn contacts_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
@@ -36,7 +37,7 @@ pub async fn list_contacts_handler(
 
 pub async fn create_contact_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<CreateContactRequest>,
+    ApiJson(request): ApiJson<CreateContactRequest>,
 ) -> Result<Json<Contact>, ContactsError> {
     let organization_id = Uuid::nil();
     let service = ContactsService::new(Arc::new(state.conn.clone()));
@@ -57,7 +58,7 @@ pub async fn get_contact_handler(
 pub async fn update_contact_handler(
     State(state): State<Arc<AppState>>,
     Path(contact_id): Path<Uuid>,
-    Json(request): Json<UpdateContactRequest>,
+    ApiJson(request): ApiJson<UpdateContactRequest>,
 ) -> Result<Json<Contact>, ContactsError> {
     let organization_id = Uuid::nil();
     let service = ContactsService::new(Arc::new(state.conn.clone()));
@@ -77,7 +78,7 @@ pub async fn delete_contact_handler(
 
 pub async fn import_contacts_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ImportRequest>,
+    ApiJson(request): ApiJson<ImportRequest>,
 ) -> Result<Json<ImportResult>, ContactsError> {
     let organization_id = Uuid::nil();
     let service = ContactsService::new(Arc::new(state.conn.clone()));
@@ -87,7 +88,7 @@ pub async fn import_contacts_handler(
 
 pub async fn export_contacts_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ExportRequest>,
+    ApiJson(request): ApiJson<ExportRequest>,
 ) -> Result<Json<ExportResult>, ContactsError> {
     let organization_id = Uuid::nil();
     let service = ContactsService::new(Arc::new(state.conn.clone()));
@@ -13,6 +13,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::{
     crm_accounts, crm_activities, crm_contacts, crm_deals, crm_leads,
     crm_notes, crm_opportunities, crm_pipeline_stages,
@@ -472,7 +473,7 @@ fn get_bot_context(state: &AppState) -> (Uuid, Uuid) {
 
 pub async fn create_contact(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateContactRequest>,
+    ApiJson(req): ApiJson<CreateContactRequest>,
 ) -> Result<Json<CrmContact>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -584,7 +585,7 @@ pub async fn get_contact(
 pub async fn update_contact(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateContactRequest>,
+    ApiJson(req): ApiJson<UpdateContactRequest>,
 ) -> Result<Json<CrmContact>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -652,7 +653,7 @@ pub async fn delete_contact(
 
 pub async fn create_account(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateAccountRequest>,
+    ApiJson(req): ApiJson<CreateAccountRequest>,
 ) -> Result<Json<CrmAccount>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -777,7 +778,7 @@ pub struct CreateLeadForm {
 
 pub async fn create_lead_form(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateLeadForm>,
+    ApiJson(req): ApiJson<CreateLeadForm>,
 ) -> Result<Json<CrmDeal>, (StatusCode, String)> {
     log::info!("create_lead_form JSON: {:?}", req);
     
@@ -855,7 +856,7 @@ pub async fn create_lead_form(
 
 pub async fn create_lead(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateLeadRequest>,
+    ApiJson(req): ApiJson<CreateLeadRequest>,
 ) -> Result<Json<CrmDeal>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -975,7 +976,7 @@ pub async fn get_lead(
 pub async fn update_lead(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateLeadRequest>,
+    ApiJson(req): ApiJson<UpdateLeadRequest>,
 ) -> Result<Json<CrmDeal>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -1183,7 +1184,7 @@ pub async fn convert_lead_to_opportunity(
 
 pub async fn create_opportunity(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateOpportunityRequest>,
+    ApiJson(req): ApiJson<CreateOpportunityRequest>,
 ) -> Result<Json<CrmDeal>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -1313,7 +1314,7 @@ pub async fn get_opportunity(
 pub async fn update_opportunity(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateOpportunityRequest>,
+    ApiJson(req): ApiJson<UpdateOpportunityRequest>,
 ) -> Result<Json<CrmDeal>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -1358,7 +1359,7 @@ pub async fn update_opportunity(
 pub async fn close_opportunity(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<CloseOpportunityRequest>,
+    ApiJson(req): ApiJson<CloseOpportunityRequest>,
 ) -> Result<Json<CrmDeal>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -1469,7 +1470,7 @@ pub async fn list_deals(
 
 pub async fn create_deal(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateDealRequest>,
+    ApiJson(req): ApiJson<CreateDealRequest>,
 ) -> Result<Json<CrmDeal>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -1564,7 +1565,7 @@ pub async fn get_deal(
 pub async fn update_deal(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateDealRequest>,
+    ApiJson(req): ApiJson<UpdateDealRequest>,
 ) -> Result<Json<CrmDeal>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -1697,7 +1698,7 @@ pub async fn delete_deal(
 
 pub async fn create_activity(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateActivityRequest>,
+    ApiJson(req): ApiJson<CreateActivityRequest>,
 ) -> Result<Json<CrmActivity>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -1847,7 +1848,7 @@ pub async fn get_crm_stats(
 
 pub async fn import_from_postgres(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ImportPostgresRequest>,
+    ApiJson(req): ApiJson<ImportPostgresRequest>,
 ) -> Result<Json<serde_json::Value>, crate::security::error_sanitizer::SafeErrorResponse> {
     use crate::security::error_sanitizer::log_and_sanitize;
     let mut conn = state.conn.get().map_err(|e| {
@@ -0,0 +1,1056 @@
+//! Whole-stack backup and restore (`botserver backup`, `botserver restore`).
+//!
+//! A backup is a single `botserver-backup-<timestamp>.tar.gz` holding a plain SQL dump of
+//! the database, every drive object under `drive/<bucket>/<key>`, and the Vault secrets
+//! encrypted with `BACKUP_PASSPHRASE`. `manifest.json` records the SHA-256 of every entry
+//! and `<archive>.sha256` the hash of the archive itself; restore checks both before it
+//! changes anything.
+
+use crate::core::secrets::{SecretPaths, SecretsManager};
+use crate::core::shared::utils::{get_secrets_manager, get_stack_path};
+use crate::security::command_guard::SafeCommand;
+use crate::security::encryption::{
+    decrypt_field, derive_key_from_password, encrypt_field, generate_salt,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub mod quiesce;
+pub mod reset;
+
+pub const DIR_ENV: &str = "BACKUP_DIR";
+pub const PASSPHRASE_ENV: &str = "BACKUP_PASSPHRASE";
+pub const KEEP_ENV: &str = "BACKUP_KEEP";
+
+const DEFAULT_KEEP: usize = 7;
+const FORMAT_VERSION: u32 = 1;
+const ARCHIVE_PREFIX: &str = "botserver-backup-";
+const ARCHIVE_SUFFIX: &str = ".tar.gz";
+const MANIFEST_ENTRY: &str = "manifest.json";
+const DATABASE_ENTRY: &str = "postgres/database.sql";
+const VAULT_ENTRY: &str = "vault/secrets.enc";
+const DRIVE_PREFIX: &str = "drive/";
+
+/// Vault paths the stack itself writes. Per-organization paths are not listed by the
+/// server's token, so they are not part of a backup.
+const VAULT_PATHS: &[&str] = &[
+    SecretPaths::DIRECTORY,
+    SecretPaths::TABLES,
+    SecretPaths::DRIVE,
+    SecretPaths::CACHE,
+    SecretPaths::EMAIL,
+    SecretPaths::LLM,
+    SecretPaths::ENCRYPTION,
+    SecretPaths::JWT,
+    SecretPaths::MEET,
+    SecretPaths::ALM,
+    SecretPaths::VECTORDB,
+    SecretPaths::OBSERVABILITY,
+    SecretPaths::SECURITY,
+    SecretPaths::CLOUD,
+    SecretPaths::APP,
+    SecretPaths::MODELS,
+    "gbo/stripe",
+    "gbo/custom",
+];
+
+#[cfg(feature = "drive")]
+pub type DriveClient = aws_sdk_s3::Client;
+#[cfg(not(feature = "drive"))]
+pub type DriveClient = crate::core::shared::state::NoDrive;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Components {
+    pub database: bool,
+    pub drive: bool,
+    pub vault: bool,
+}
+
+impl Components {
+    pub const ALL: Self = Self {
+        database: true,
+        drive: true,
+        vault: true,
+    };
+    pub const NONE: Self = Self {
+        database: false,
+        drive: false,
+        vault: false,
+    };
+
+    /// Parses a comma-separated list such as `database,vault`.
+    pub fn parse(list: &str) -> Result<Self> {
+        let mut components = Self::NONE;
+        for name in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match name {
+                "database" | "postgres" => components.database = true,
+                "drive" => components.drive = true,
+                "vault" => components.vault = true,
+                other => bail!("Unknown component '{}' (database, drive, vault)", other),
+            }
+        }
+        if components == Self::NONE {
+            bail!("No components selected");
+        }
+        Ok(components)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    /// Encrypts the Vault export; required when Vault is backed up or restored.
+    pub passphrase: Option<String>,
+    /// Archives kept in `dir` after a backup; 0 keeps all.
+    pub keep: usize,
+    pub components: Components,
+}
+
+impl BackupConfig {
+    pub fn from_env() -> Self {
+        Self {
+            dir: std::env::var(DIR_ENV)
+                .ok()
+                .filter(|d| !d.trim().is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| Path::new(&get_stack_path()).join("backups")),
+            passphrase: std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()),
+            keep: std::env::var(KEEP_ENV)
+                .ok()
+                .and_then(|k| k.trim().parse().ok())
+                .unwrap_or(DEFAULT_KEEP),
+            components: Components::ALL,
+        }
+    }
+
+    /// Fails when the selected components cannot be backed up with this configuration.
+    pub fn check(&self) -> Result<()> {
+        if self.components.vault && self.passphrase.is_none() {
+            bail!(
+                "Set {} to encrypt the Vault export, or leave Vault out of the backup",
+                PASSPHRASE_ENV
+            );
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub created_at: DateTime<Utc>,
+    pub botserver_version: String,
+    pub entries: Vec<ManifestEntry>,
+    /// Hex salt for the Vault export key, when Vault was backed up.
+    pub vault_salt: Option<String>,
+    /// Drive objects (`bucket/key`) that changed while the backup ran.
+    #[serde(default)]
+    pub drive_changed: Vec<String>,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn sha256_file(path: &Path) -> Result<(String, u64)> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        size += read as u64;
+    }
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+fn archive_name(at: DateTime<Utc>) -> String {
+    format!(
+        "{}{}{}",
+        ARCHIVE_PREFIX,
+        at.format("%Y%m%d-%H%M%S"),
+        ARCHIVE_SUFFIX
+    )
+}
+
+fn sidecar_path(archive: &Path) -> PathBuf {
+    let mut name = archive.as_os_str().to_owned();
+    name.push(".sha256");
+    PathBuf::from(name)
+}
+
+/// Tar.gz writer that hashes each entry for the manifest.
+struct ArchiveWriter {
+    builder: tar::Builder<GzEncoder<File>>,
+    entries: Vec<ManifestEntry>,
+}
+
+impl ArchiveWriter {
+    fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("create {}", path.display()))?;
+        Ok(Self {
+            builder: tar::Builder::new(GzEncoder::new(file, Compression::default())),
+            entries: Vec::new(),
+        })
+    }
+
+    fn header(size: u64) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(size);
+        header.set_mode(0o600);
+        header.set_mtime(Utc::now().timestamp().max(0) as u64);
+        header
+    }
+
+    fn add(&mut self, path: &str, data: &[u8]) -> Result<()> {
+        let mut header = Self::header(data.len() as u64);
+        self.builder.append_data(&mut header, path, data)?;
+        self.entries.push(ManifestEntry {
+            path: path.to_string(),
+            size: data.len() as u64,
+            sha256: sha256_hex(data),
+        });
+        Ok(())
+    }
+
+    fn add_file(&mut self, path: &str, source: &Path) -> Result<()> {
+        let (sha256, size) = sha256_file(source)?;
+        let mut header = Self::header(size);
+        self.builder
+            .append_data(&mut header, path, File::open(source)?)?;
+        self.entries.push(ManifestEntry {
+            path: path.to_string(),
+            size,
+            sha256,
+        });
+        Ok(())
+    }
+
+    fn finish(mut self, mut manifest: Manifest) -> Result<Manifest> {
+        manifest.entries = std::mem::take(&mut self.entries);
+        let json = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = Self::header(json.len() as u64);
+        self.builder
+            .append_data(&mut header, MANIFEST_ENTRY, json.as_slice())?;
+        self.builder.into_inner()?.finish()?.sync_all()?;
+        Ok(manifest)
+    }
+}
+
+async fn secrets_manager() -> Result<SecretsManager> {
+    let manager = get_secrets_manager()
+        .await
+        .ok_or_else(|| anyhow!("Secrets manager not initialized; is the stack bootstrapped?"))?;
+    if !manager.is_enabled() {
+        bail!("Vault is not configured");
+    }
+    Ok(manager)
+}
+
+struct Database {
+    host: String,
+    port: String,
+    name: String,
+    user: String,
+    password: String,
+}
+
+impl Database {
+    async fn from_vault() -> Result<Self> {
+        let (host, port, name, user, password) =
+            secrets_manager().await?.get_database_config().await?;
+        Ok(Self {
+            host,
+            port: port.to_string(),
+            name,
+            user,
+            password,
+        })
+    }
+
+    fn command(&self, program: &str) -> Result<SafeCommand> {
+        SafeCommand::new(program)
+            .and_then(|c| {
+                c.args(&[
+                    "-h", &self.host, "-p", &self.port, "-U", &self.user, "-d", &self.name,
+                ])
+            })
+            .and_then(|c| c.env("PGPASSWORD", &self.password))
+            .map_err(|e| anyhow!("{}: {}", program, e))
+    }
+
+    fn run(command: SafeCommand, what: &str) -> Result<()> {
+        let output = command
+            .execute()
+            .map_err(|e| anyhow!("{} failed: {}", what, e))?;
+        if !output.status.success() {
+            bail!(
+                "{} failed: {}",
+                what,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// Dumps from one serializable snapshot, so the dump is consistent even while the
+    /// server keeps writing.
+    fn dump(&self, target: &Path) -> Result<()> {
+        let command = self
+            .command("pg_dump")?
+            .args(&[
+                "--clean",
+                "--if-exists",
+                "--no-owner",
+                "--serializable-deferrable",
+                "-f",
+            ])
+            .and_then(|c| c.arg(target.to_string_lossy()))
+            .map_err(|e| anyhow!("pg_dump: {}", e))?;
+        Self::run(command, "pg_dump")
+    }
+
+    fn restore(&self, dump: &Path) -> Result<()> {
+        let command = self
+            .command("psql")?
+            .args(&["-q", "-v", "ON_ERROR_STOP=1", "-f"])
+            .and_then(|c| c.arg(dump.to_string_lossy()))
+            .map_err(|e| anyhow!("psql: {}", e))?;
+        Self::run(command, "psql")
+    }
+}
+
+fn encrypt_vault(
+    secrets: &BTreeMap<String, HashMap<String, String>>,
+    passphrase: &str,
+) -> Result<(Vec<u8>, String)> {
+    let salt = generate_salt();
+    let key = derive_key_from_password(passphrase, &salt)?;
+    let sealed = encrypt_field(&serde_json::to_string(secrets)?, &key)?;
+    Ok((sealed.into_bytes(), hex::encode(salt)))
+}
+
+fn decrypt_vault(
+    sealed: &[u8],
+    salt_hex: &str,
+    passphrase: &str,
+) -> Result<BTreeMap<String, HashMap<String, String>>> {
+    let salt = hex::decode(salt_hex).context("invalid vault salt in manifest")?;
+    let key = derive_key_from_password(passphrase, &salt)?;
+    let json = decrypt_field(std::str::from_utf8(sealed)?, &key)
+        .map_err(|_| anyhow!("Cannot decrypt Vault export; wrong {}?", PASSPHRASE_ENV))?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+async fn export_vault(passphrase: &str) -> Result<(Vec<u8>, String, usize)> {
+    let manager = secrets_manager().await?;
+    let mut secrets = BTreeMap::new();
+    for path in VAULT_PATHS {
+        match manager.get_secret(path).await {
+            Ok(values) if !values.is_empty() => {
+                secrets.insert(path.to_string(), values);
+            }
+            _ => {}
+        }
+    }
+    let count = secrets.len();
+    let (sealed, salt) = encrypt_vault(&secrets, passphrase)?;
+    Ok((sealed, salt, count))
+}
+
+#[cfg(feature = "drive")]
+async fn list_objects(drive: &DriveClient, bucket: &str) -> Result<BTreeMap<String, String>> {
+    let mut objects = BTreeMap::new();
+    let mut token = None;
+    loop {
+        let page = drive
+            .list_objects_v2()
+            .bucket(bucket)
+            .set_continuation_token(token)
+            .send()
+            .await
+            .with_context(|| format!("list s3://{}", bucket))?;
+        for object in page.contents() {
+            if let Some(key) = object.key() {
+                objects.insert(
+                    key.to_string(),
+                    object.e_tag().unwrap_or_default().to_string(),
+                );
+            }
+        }
+        match page.next_continuation_token() {
+            Some(next) => token = Some(next.to_string()),
+            None => return Ok(objects),
+        }
+    }
+}
+
+#[cfg(feature = "drive")]
+async fn list_buckets(drive: &DriveClient) -> Result<Vec<String>> {
+    Ok(drive
+        .list_buckets()
+        .send()
+        .await
+        .context("list buckets")?
+        .buckets()
+        .iter()
+        .filter_map(|b| b.name().map(str::to_string))
+        .collect())
+}
+
+/// Downloads `bucket/key` into `spool` chunk by chunk, so large objects are never held
+/// in memory.
+#[cfg(feature = "drive")]
+async fn download_object(drive: &DriveClient, bucket: &str, key: &str, spool: &Path) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut body = drive
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .with_context(|| format!("get s3://{}/{}", bucket, key))?
+        .body;
+    let mut file = tokio::fs::File::create(spool)
+        .await
+        .with_context(|| format!("create {}", spool.display()))?;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.with_context(|| format!("read s3://{}/{}", bucket, key))?;
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(())
+}
+
+/// Copies every object through `spool`, then lists again and reports objects that
+/// changed meanwhile. Only scheduled backups hold API writes back, and the drive
+/// monitors keep writing even then, so those copies may not match the database dump.
+#[cfg(feature = "drive")]
+async fn backup_drive(
+    drive: &DriveClient,
+    archive: &mut ArchiveWriter,
+    spool: &Path,
+) -> Result<(usize, Vec<String>)> {
+    let mut copied = 0;
+    let mut changed = Vec::new();
+    for bucket in list_buckets(drive).await? {
+        let before = list_objects(drive, &bucket).await?;
+        for key in before.keys() {
+            let entry = format!("{}{}/{}", DRIVE_PREFIX, bucket, key);
+            if key.ends_with('/') || key.split('/').any(|part| part == "..") {
+                warn!(
+                    "Skipping drive object with unsafe name s3://{}/{}",
+                    bucket, key
+                );
+                continue;
+            }
+            download_object(drive, &bucket, key, spool).await?;
+            archive.add_file(&entry, spool)?;
+            copied += 1;
+        }
+        let after = list_objects(drive, &bucket).await?;
+        changed.extend(
+            after
+                .iter()
+                .filter(|(key, etag)| before.get(*key) != Some(etag))
+                .chain(before.iter().filter(|(key, _)| !after.contains_key(*key)))
+                .map(|(key, _)| format!("{}/{}", bucket, key)),
+        );
+    }
+    Ok((copied, changed))
+}
+
+#[cfg(feature = "drive")]
+async fn restore_drive(
+    drive: &DriveClient,
+    root: &Path,
+    entries: &[ManifestEntry],
+) -> Result<usize> {
+    use aws_sdk_s3::primitives::ByteStream;
+
+    let mut restored = 0;
+    let mut buckets: Vec<String> = list_buckets(drive).await?;
+    for entry in entries {
+        let Some((bucket, key)) = entry
+            .path
+            .strip_prefix(DRIVE_PREFIX)
+            .and_then(|rest| rest.split_once('/'))
+        else {
+            continue;
+        };
+        if !buckets.iter().any(|b| b == bucket) {
+            drive
+                .create_bucket()
+                .bucket(bucket)
+                .send()
+                .await
+                .with_context(|| format!("create bucket {}", bucket))?;
+            buckets.push(bucket.to_string());
+        }
+        let body = ByteStream::from_path(root.join(&entry.path))
+            .await
+            .with_context(|| format!("read {}", entry.path))?;
+        drive
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("put s3://{}/{}", bucket, key))?;
+        restored += 1;
+    }
+    Ok(restored)
+}
+
+#[derive(Debug)]
+pub struct BackupReport {
+    pub path: PathBuf,
+    pub size: u64,
+    pub entries: usize,
+    pub drive_changed: Vec<String>,
+    pub pruned: Vec<PathBuf>,
+}
+
+/// Writes a backup of the selected components into `config.dir` and prunes old archives.
+pub async fn create_backup(
+    config: &BackupConfig,
+    drive: Option<&DriveClient>,
+) -> Result<BackupReport> {
+    let components = config.components;
+    config.check()?;
+    std::fs::create_dir_all(&config.dir)
+        .with_context(|| format!("create {}", config.dir.display()))?;
+    let dir = config.dir.canonicalize()?;
+    let started = Utc::now();
+    let name = archive_name(started);
+    let partial = dir.join(format!(".{}.partial", name));
+    let dump = dir.join(format!(".{}.sql", name));
+    let spool = dir.join(format!(".{}.object", name));
+
+    let result = async {
+        let mut archive = ArchiveWriter::create(&partial)?;
+        let mut manifest = Manifest {
+            format: FORMAT_VERSION,
+            created_at: started,
+            botserver_version: env!("CARGO_PKG_VERSION").to_string(),
+            entries: Vec::new(),
+            vault_salt: None,
+            drive_changed: Vec::new(),
+        };
+
+        if components.database {
+            let database = Database::from_vault().await?;
+            let target = dump.clone();
+            tokio::task::spawn_blocking(move || database.dump(&target)).await??;
+            archive.add_file(DATABASE_ENTRY, &dump)?;
+            info!("Backup: database dumped");
+        }
+        if components.vault {
+            let passphrase = config.passphrase.as_deref().unwrap_or_default();
+            let (sealed, salt, count) = export_vault(passphrase).await?;
+            archive.add(VAULT_ENTRY, &sealed)?;
+            manifest.vault_salt = Some(salt);
+            info!("Backup: {} Vault path(s) exported", count);
+        }
+        if components.drive {
+            #[cfg(feature = "drive")]
+            match drive {
+                Some(drive) => {
+                    let (copied, changed) = backup_drive(drive, &mut archive, &spool).await?;
+                    if !changed.is_empty() {
+                        warn!(
+                            "Backup: {} drive object(s) changed during the backup",
+                            changed.len()
+                        );
+                    }
+                    manifest.drive_changed = changed;
+                    info!("Backup: {} drive object(s) copied", copied);
+                }
+                None => bail!("Drive is not available"),
+            }
+            #[cfg(not(feature = "drive"))]
+            {
+                let _ = drive;
+                warn!("Backup: built without drive support, drive skipped");
+            }
+        }
+        archive.finish(manifest)
+    }
+    .await;
+    let _ = std::fs::remove_file(&dump);
+    let _ = std::fs::remove_file(&spool);
+    let manifest = match result {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(e);
+        }
+    };
+
+    let path = dir.join(&name);
+    std::fs::rename(&partial, &path)?;
+    let (checksum, size) = sha256_file(&path)?;
+    std::fs::write(sidecar_path(&path), format!("{}  {}\n", checksum, name))?;
+    info!("Backup written to {} ({} bytes)", path.display(), size);
+
+    let pruned = prune_backups(&dir, config.keep)?;
+    for removed in &pruned {
+        info!("Removed old backup {}", removed.display());
+    }
+    Ok(BackupReport {
+        path,
+        size,
+        entries: manifest.entries.len(),
+        drive_changed: manifest.drive_changed,
+        pruned,
+    })
+}
+
+/// Deletes all but the newest `keep` archives (and their checksum files).
+pub fn prune_backups(dir: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    if keep == 0 {
+        return Ok(Vec::new());
+    }
+    let mut archives: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(ARCHIVE_PREFIX) && n.ends_with(ARCHIVE_SUFFIX))
+        })
+        .collect();
+    // Timestamped names sort chronologically.
+    archives.sort();
+    let excess = archives.len().saturating_sub(keep);
+    let removed: Vec<PathBuf> = archives.into_iter().take(excess).collect();
+    for path in &removed {
+        std::fs::remove_file(path)?;
+        let _ = std::fs::remove_file(sidecar_path(path));
+    }
+    Ok(removed)
+}
+
+/// Compares the archive with its `.sha256` file. A missing file only warns, so archives
+/// copied without it can still be restored.
+fn verify_archive_checksum(archive: &Path) -> Result<()> {
+    let sidecar = sidecar_path(archive);
+    let Ok(content) = std::fs::read_to_string(&sidecar) else {
+        warn!("No {} found; skipping archive checksum", sidecar.display());
+        return Ok(());
+    };
+    let expected = content.split_whitespace().next().unwrap_or_default();
+    let (actual, _) = sha256_file(archive)?;
+    if !expected.eq_ignore_ascii_case(&actual) {
+        bail!(
+            "Archive checksum mismatch: expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+/// Checks every manifest entry against the extracted files and rejects files the
+/// manifest does not list.
+fn verify_entries(root: &Path, manifest: &Manifest) -> Result<()> {
+    let mut problems = Vec::new();
+    for entry in &manifest.entries {
+        match sha256_file(&root.join(&entry.path)) {
+            Ok((sha256, size)) if sha256 == entry.sha256 && size == entry.size => {}
+            Ok(_) => problems.push(format!("{}: checksum mismatch", entry.path)),
+            Err(_) => problems.push(format!("{}: missing", entry.path)),
+        }
+    }
+    for file in walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let relative = file
+            .path()
+            .strip_prefix(root)
+            .unwrap_or(file.path())
+            .to_string_lossy()
+            .replace('\\', "/");
+        if relative != MANIFEST_ENTRY && !manifest.entries.iter().any(|e| e.path == relative) {
+            problems.push(format!("{}: not in manifest", relative));
+        }
+    }
+    if !problems.is_empty() {
+        bail!("Backup failed verification:\n  {}", problems.join("\n  "));
+    }
+    Ok(())
+}
+
+/// Unpacks `archive` into `staging` and verifies it. Returns the manifest.
+pub fn unpack_and_verify(archive: &Path, staging: &Path) -> Result<Manifest> {
+    verify_archive_checksum(archive)?;
+    std::fs::create_dir_all(staging)?;
+    tar::Archive::new(GzDecoder::new(File::open(archive)?))
+        .unpack(staging)
+        .context("unpack archive")?;
+    let manifest: Manifest = serde_json::from_slice(
+        &std::fs::read(staging.join(MANIFEST_ENTRY)).context("read manifest")?,
+    )?;
+    if manifest.format > FORMAT_VERSION {
+        bail!(
+            "Backup format {} is newer than this botserver supports",
+            manifest.format
+        );
+    }
+    verify_entries(staging, &manifest)?;
+    Ok(manifest)
+}
+
+/// Restores the selected components from a verified archive. Nothing is restored unless
+/// every checksum matches.
+pub async fn restore_backup(
+    archive: &Path,
+    config: &BackupConfig,
+    drive: Option<&DriveClient>,
+) -> Result<()> {
+    let archive = archive
+        .canonicalize()
+        .with_context(|| format!("open {}", archive.display()))?;
+    let staging = archive
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(format!(".restore-{}", Utc::now().format("%Y%m%d-%H%M%S")));
+    let result = restore_from(&archive, &staging, config, drive).await;
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+async fn restore_from(
+    archive: &Path,
+    staging: &Path,
+    config: &BackupConfig,
+    drive: Option<&DriveClient>,
+) -> Result<()> {
+    let unpack = (archive.to_path_buf(), staging.to_path_buf());
+    let manifest =
+        tokio::task::spawn_blocking(move || unpack_and_verify(&unpack.0, &unpack.1)).await??;
+    info!(
+        "Restore: {} verified ({} entries, created {})",
+        archive.display(),
+        manifest.entries.len(),
+        manifest.created_at
+    );
+    let has = |path: &str| manifest.entries.iter().any(|e| e.path == path);
+    let components = config.components;
+
+    // Decrypt first so a wrong passphrase fails before anything is overwritten, but write
+    // secrets last: the database is restored with the credentials in use now.
+    let vault = match (components.vault && has(VAULT_ENTRY), &manifest.vault_salt) {
+        (true, Some(salt)) => {
+            let passphrase = config
+                .passphrase
+                .as_deref()
+                .ok_or_else(|| anyhow!("Set {} to restore Vault secrets", PASSPHRASE_ENV))?;
+            let sealed = std::fs::read(staging.join(VAULT_ENTRY))?;
+            Some(decrypt_vault(&sealed, salt, passphrase)?)
+        }
+        _ => None,
+    };
+
+    if components.database && has(DATABASE_ENTRY) {
+        let database = Database::from_vault().await?;
+        let dump = staging.join(DATABASE_ENTRY);
+        tokio::task::spawn_blocking(move || database.restore(&dump)).await??;
+        info!("Restore: database restored");
+    }
+    if components.drive {
+        #[cfg(feature = "drive")]
+        if let Some(drive) = drive {
+            let count = restore_drive(drive, staging, &manifest.entries).await?;
+            info!("Restore: {} drive object(s) written", count);
+        } else {
+            bail!("Drive is not available");
+        }
+        #[cfg(not(feature = "drive"))]
+        {
+            let _ = drive;
+            warn!("Restore: built without drive support, drive skipped");
+        }
+    }
+    if let Some(secrets) = vault {
+        let manager = secrets_manager().await?;
+        for (path, values) in &secrets {
+            manager
+                .put_secret(path, values.clone())
+                .await
+                .with_context(|| format!("write {}", path))?;
+        }
+        info!("Restore: {} Vault path(s) written", secrets.len());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "drive")]
+async fn cli_drive() -> Option<DriveClient> {
+    let config = tokio::task::spawn_blocking(|| {
+        crate::core::shared::utils::create_conn()
+            .ok()
+            .and_then(|pool| crate::core::config::AppConfig::from_database(&pool).ok())
+            .or_else(|| crate::core::config::AppConfig::from_env().ok())
+    })
+    .await
+    .ok()
+    .flatten()?;
+    match crate::core::shared::utils::create_s3_operator(&config.drive).await {
+        Ok(client) => Some(client),
+        Err(e) => {
+            eprintln!("Drive unavailable: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "drive"))]
+async fn cli_drive() -> Option<DriveClient> {
+    None
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+fn config_from_args(args: &[String]) -> Result<BackupConfig> {
+    let mut config = BackupConfig::from_env();
+    if let Some(dir) = flag_value(args, "--output") {
+        config.dir = PathBuf::from(dir);
+    }
+    if let Some(keep) = flag_value(args, "--keep") {
+        config.keep = keep
+            .parse()
+            .map_err(|_| anyhow!("--keep expects a number"))?;
+    }
+    if let Some(only) = flag_value(args, "--only") {
+        config.components = Components::parse(only)?;
+    }
+    Ok(config)
+}
+
+fn print_usage() {
+    println!("Usage: botserver backup [--output <dir>] [--keep <n>] [--only <components>]");
+    println!("       botserver restore <archive> [--only <components>] [--yes]");
+    println!("       botserver reset --confirm [--dry-run] [--yes]  (see reset --help)");
+    println!();
+    println!("Components: database, drive, vault (default: all)");
+    println!("Environment:");
+    println!(
+        "  {:<18} Archive directory (default <stack>/backups)",
+        DIR_ENV
+    );
+    println!(
+        "  {:<18} Encrypts the Vault export (required for vault)",
+        PASSPHRASE_ENV
+    );
+    println!(
+        "  {:<18} Archives to keep, 0 keeps all (default {})",
+        KEEP_ENV, DEFAULT_KEEP
+    );
+}
+
+/// Entry point for `botserver backup` and `botserver restore`; `args` starts at the
+/// subcommand. Returns the process exit code.
+pub async fn run_cli(args: &[String]) -> i32 {
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        print_usage();
+        return 0;
+    }
+    let config = match config_from_args(args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            print_usage();
+            return 1;
+        }
+    };
+    let drive = if config.components.drive {
+        cli_drive().await
+    } else {
+        None
+    };
+
+    let result = match args.first().map(String::as_str) {
+        Some("backup") => create_backup(&config, drive.as_ref()).await.map(|report| {
+            println!(
+                "Backup written to {} ({} entries, {} bytes)",
+                report.path.display(),
+                report.entries,
+                report.size
+            );
+            if !report.drive_changed.is_empty() {
+                println!(
+                    "Warning: {} drive object(s) changed during the backup and may not match \
+                     the database dump:",
+                    report.drive_changed.len()
+                );
+                for object in report.drive_changed.iter().take(20) {
+                    println!("  {}", object);
+                }
+            }
+            for removed in &report.pruned {
+                println!("Removed old backup {}", removed.display());
+            }
+        }),
+        Some("restore") => {
+            let Some(archive) = args.get(1).filter(|a| !a.starts_with("--")) else {
+                print_usage();
+                return 1;
+            };
+            if !args.iter().any(|a| a == "--yes") && !confirm_restore(archive) {
+                println!("Aborted");
+                return 1;
+            }
+            restore_backup(Path::new(archive), &config, drive.as_ref())
+                .await
+                .map(|()| println!("Restore complete. Restart botserver to pick it up."))
+        }
+        _ => {
+            print_usage();
+            return 1;
+        }
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            1
+        }
+    }
+}
+
+fn confirm_restore(archive: &str) -> bool {
+    print!(
+        "Restoring {} overwrites the current database, drive objects and secrets. \
+         Continue? [y/N]: ",
+        archive
+    );
+    let _ = std::io::stdout().flush();
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input).is_ok() && input.trim().eq_ignore_ascii_case("y")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_archive(dir: &Path, files: &[(&str, &[u8])]) -> PathBuf {
+        let path = dir.join(archive_name(Utc::now()));
+        let mut archive = ArchiveWriter::create(&path).unwrap();
+        for (name, data) in files {
+            archive.add(name, data).unwrap();
+        }
+        archive
+            .finish(Manifest {
+                format: FORMAT_VERSION,
+                created_at: Utc::now(),
+                botserver_version: "test".to_string(),
+                entries: Vec::new(),
+                vault_salt: None,
+                drive_changed: Vec::new(),
+            })
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_restore_verifies_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = write_archive(
+            dir.path(),
+            &[
+                (DATABASE_ENTRY, b"SELECT 1;"),
+                ("drive/default.gbai/a.txt", b"hello"),
+            ],
+        );
+        let manifest = unpack_and_verify(&archive, &dir.path().join("ok")).unwrap();
+        assert_eq!(manifest.entries.len(), 2);
+        assert_eq!(manifest.entries[1].sha256, sha256_hex(b"hello"));
+
+        let tampered = dir.path().join("tampered");
+        unpack_and_verify(&archive, &tampered).unwrap();
+        std::fs::write(tampered.join("drive/default.gbai/a.txt"), b"HELLO").unwrap();
+        std::fs::write(tampered.join("extra.sql"), b"DROP TABLE x;").unwrap();
+        let err = verify_entries(&tampered, &manifest)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("a.txt: checksum mismatch"));
+        assert!(err.contains("extra.sql: not in manifest"));
+
+        std::fs::write(sidecar_path(&archive), "0000  wrong\n").unwrap();
+        assert!(unpack_and_verify(&archive, &dir.path().join("bad")).is_err());
+    }
+
+    #[test]
+    fn test_vault_export_needs_passphrase() {
+        let mut secrets = BTreeMap::new();
+        secrets.insert(
+            SecretPaths::TABLES.to_string(),
+            HashMap::from([("password".to_string(), "pg-secret".to_string())]),
+        );
+        let (sealed, salt) = encrypt_vault(&secrets, "correct horse").unwrap();
+        assert!(!String::from_utf8_lossy(&sealed).contains("pg-secret"));
+        assert_eq!(
+            decrypt_vault(&sealed, &salt, "correct horse").unwrap(),
+            secrets
+        );
+        assert!(decrypt_vault(&sealed, &salt, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let dir = tempfile::tempdir().unwrap();
+        for stamp in ["20260101-000000", "20260102-000000", "20260103-000000"] {
+            let path = dir
+                .path()
+                .join(format!("{}{}{}", ARCHIVE_PREFIX, stamp, ARCHIVE_SUFFIX));
+            std::fs::write(&path, b"x").unwrap();
+            std::fs::write(sidecar_path(&path), b"x").unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), b"keep me").unwrap();
+
+        let removed = prune_backups(dir.path(), 2).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(removed[0].to_string_lossy().contains("20260101"));
+        assert!(!sidecar_path(&removed[0]).exists());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 5);
+        assert!(prune_backups(dir.path(), 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_components_parse() {
+        let only = Components::parse("database, vault").unwrap();
+        assert!(only.database && only.vault && !only.drive);
+        assert!(Components::parse("redis").is_err());
+        assert!(Components::parse("").is_err());
+    }
+}
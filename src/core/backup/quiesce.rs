@@ -0,0 +1,187 @@
+//! Holding writes back while a backup copies the stack.
+//!
+//! [`write_gate_middleware`] lets reads through at all times. Requests that can change
+//! data (anything but `GET`, `HEAD` and `OPTIONS`) hold the gate open while they run;
+//! once a backup closes it, new ones get `503` with a `Retry-After` header until the
+//! backup is done, so the database dump and the drive copy describe the same moment.
+
+use crate::core::shared::api_error::ApiError;
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use log::warn;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{RwLock, RwLockWriteGuard};
+
+pub const DRAIN_ENV: &str = "BACKUP_DRAIN_SECS";
+
+const DEFAULT_DRAIN_SECS: u64 = 60;
+const RETRY_AFTER_SECS: u64 = 60;
+
+/// Writes that may still go through during a backup. Signing in only touches sessions.
+const ALWAYS_ALLOWED: [&str; 1] = ["/api/auth/"];
+
+pub struct WriteGate {
+    closed: AtomicBool,
+    lock: RwLock<()>,
+}
+
+/// Keeps the gate closed until dropped.
+pub struct Quiesced<'a> {
+    gate: &'a WriteGate,
+    _guard: Option<RwLockWriteGuard<'a, ()>>,
+}
+
+impl Drop for Quiesced<'_> {
+    fn drop(&mut self) {
+        self.gate.closed.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Default for WriteGate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WriteGate {
+    pub const fn new() -> Self {
+        Self {
+            closed: AtomicBool::new(false),
+            lock: RwLock::const_new(()),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Refuses new writes, then waits up to `drain` for those already running to finish.
+    /// Writes still running after that are logged and left to complete.
+    pub async fn quiesce(&self, drain: Duration) -> Quiesced<'_> {
+        self.closed.store(true, Ordering::SeqCst);
+        let guard = tokio::time::timeout(drain, self.lock.write()).await.ok();
+        if guard.is_none() {
+            warn!(
+                "Writes still running after {}s, backing up without waiting for them",
+                drain.as_secs()
+            );
+        }
+        Quiesced {
+            gate: self,
+            _guard: guard,
+        }
+    }
+}
+
+static WRITE_GATE: WriteGate = WriteGate::new();
+
+/// The process-wide gate closed by scheduled backups.
+pub fn write_gate() -> &'static WriteGate {
+    &WRITE_GATE
+}
+
+pub async fn write_gate_middleware(request: Request<Body>, next: Next) -> Response {
+    write_gate_middleware_with(write_gate(), request, next).await
+}
+
+pub async fn write_gate_middleware_with(
+    gate: &WriteGate,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) || ALWAYS_ALLOWED.iter().any(|prefix| path.starts_with(prefix))
+    {
+        return next.run(request).await;
+    }
+    if gate.is_closed() {
+        return backing_up();
+    }
+    match gate.lock.try_read() {
+        Ok(_guard) => next.run(request).await,
+        Err(_) => backing_up(),
+    }
+}
+
+/// Built directly rather than through `ApiError`'s `IntoResponse`, which logs every 5xx.
+fn backing_up() -> Response {
+    let body = ApiError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "BACKUP_IN_PROGRESS",
+        "A backup is running; changes are accepted again when it finishes",
+    )
+    .envelope(crate::security::current_request_id());
+    let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER_SECS));
+    response
+}
+
+/// How long a backup waits for running writes, from `BACKUP_DRAIN_SECS`.
+pub fn drain_timeout() -> Duration {
+    Duration::from_secs(
+        std::env::var(DRAIN_ENV)
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_DRAIN_SECS),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn app(gate: Arc<WriteGate>) -> Router {
+        Router::new()
+            .route(
+                "/api/bots",
+                get(|| async { "bots" }).post(|| async { "saved" }),
+            )
+            .layer(axum::middleware::from_fn(move |req, next| {
+                let gate = Arc::clone(&gate);
+                async move { write_gate_middleware_with(&gate, req, next).await }
+            }))
+    }
+
+    async fn status(gate: &Arc<WriteGate>, method: Method) -> StatusCode {
+        let request = Request::builder()
+            .method(method)
+            .uri("/api/bots")
+            .body(Body::empty())
+            .unwrap();
+        app(Arc::clone(gate))
+            .oneshot(request)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_backup_holds_writes_but_not_reads() {
+        let gate = Arc::new(WriteGate::new());
+        assert_eq!(status(&gate, Method::POST).await, StatusCode::OK);
+
+        let quiesced = gate.quiesce(Duration::from_secs(1)).await;
+        assert_eq!(status(&gate, Method::GET).await, StatusCode::OK);
+        assert_eq!(
+            status(&gate, Method::POST).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        drop(quiesced);
+        assert_eq!(status(&gate, Method::POST).await, StatusCode::OK);
+    }
+}
@@ -0,0 +1,358 @@
+//! Factory reset (`botserver reset --confirm`).
+//!
+//! The reset backs up the stack with the same code as `botserver backup`, stops every
+//! stack component, then deletes the stack directory, the data directory and `.env`. The
+//! plan is worked out before anything changes, because removing `.env` changes where
+//! [`get_stack_path`] points. A stack that holds the running `botserver` binary (the
+//! `/opt/gbo` layout) keeps the directory containing it.
+
+use super::{cli_drive, config_from_args, create_backup, BackupConfig, Components, DIR_ENV};
+use crate::core::bootstrap::bootstrap_utils::{get_processes_to_kill, safe_pkill};
+use crate::core::bootstrap::instance::running_instance;
+use crate::core::shared::utils::get_stack_path;
+use crate::security::command_guard::SafeCommand;
+use anyhow::{bail, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Typed at the prompt to go ahead.
+const CONFIRM_WORD: &str = "reset";
+/// Where the backup goes when `BACKUP_DIR` lies inside a directory being removed.
+const FALLBACK_BACKUP_DIR: &str = "botserver-backups";
+const STOP_GRACE: Duration = Duration::from_secs(5);
+const ENV_FILES: &[&str] = &["./.env", "/opt/gbo/bin/.env"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResetPlan {
+    pub stack: PathBuf,
+    /// Directories and files deleted, in order.
+    pub remove: Vec<PathBuf>,
+    /// Stack entries left in place because they hold the running binary.
+    pub keep: Vec<PathBuf>,
+    pub backup_dir: PathBuf,
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// The filesystem root and the directories around the working directory are never
+/// removed, whatever `DATA_DIR` says.
+fn is_protected(path: &Path, cwd: &Path) -> bool {
+    path.parent().is_none() || cwd.starts_with(path)
+}
+
+/// Works out what a reset deletes. Only paths that exist are listed.
+pub fn plan_reset(
+    stack: &Path,
+    data_dir: &Path,
+    env_files: &[PathBuf],
+    exe: Option<&Path>,
+    backup_dir: &Path,
+    cwd: &Path,
+) -> Result<ResetPlan> {
+    let stack = absolute(stack);
+    let mut remove = Vec::new();
+    let mut keep = Vec::new();
+
+    if is_protected(&stack, cwd) {
+        bail!(
+            "Refusing to remove {}: it contains the working directory",
+            stack.display()
+        );
+    }
+    match exe.map(absolute).filter(|exe| exe.starts_with(&stack)) {
+        Some(exe) => {
+            for entry in std::fs::read_dir(&stack)? {
+                let path = entry?.path();
+                if exe.starts_with(&path) {
+                    keep.push(path);
+                } else {
+                    remove.push(path);
+                }
+            }
+            remove.sort();
+        }
+        None if stack.exists() => remove.push(stack.clone()),
+        None => {}
+    }
+
+    let data_dir = absolute(data_dir);
+    if data_dir.exists() && !remove.iter().any(|r| data_dir.starts_with(r)) {
+        if is_protected(&data_dir, cwd) || keep.iter().any(|k| data_dir.starts_with(k)) {
+            bail!("Refusing to remove data directory {}", data_dir.display());
+        }
+        remove.push(data_dir);
+    }
+    for env_file in env_files.iter().map(|f| absolute(f)) {
+        if env_file.is_file() && !remove.iter().any(|r| env_file.starts_with(r)) {
+            remove.push(env_file);
+        }
+    }
+
+    let mut backup_dir = absolute(backup_dir);
+    if remove.iter().any(|r| backup_dir.starts_with(r)) {
+        backup_dir = stack
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join(FALLBACK_BACKUP_DIR);
+    }
+
+    Ok(ResetPlan {
+        stack,
+        remove,
+        keep,
+        backup_dir,
+    })
+}
+
+fn current_plan(config: &BackupConfig) -> Result<ResetPlan> {
+    let stack = PathBuf::from(get_stack_path());
+    let data_dir = std::env::var("DATA_DIR")
+        .ok()
+        .filter(|d| !d.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| stack.join("data"));
+    let env_files: Vec<PathBuf> = ENV_FILES.iter().map(PathBuf::from).collect();
+    let exe = std::env::current_exe().ok();
+    let cwd = std::env::current_dir()?;
+    plan_reset(
+        &stack,
+        &data_dir,
+        &env_files,
+        exe.as_deref(),
+        &config.dir,
+        &cwd,
+    )
+}
+
+fn print_plan(plan: &ResetPlan) {
+    println!("Factory reset of {}", plan.stack.display());
+    println!("  1. Back up the stack to {}", plan.backup_dir.display());
+    println!("  2. Stop all stack components");
+    println!("  3. Delete:");
+    if plan.remove.is_empty() {
+        println!("       (nothing to delete)");
+    }
+    for path in &plan.remove {
+        println!("       {}", path.display());
+    }
+    for path in &plan.keep {
+        println!("     Keep {} (holds the running binary)", path.display());
+    }
+}
+
+fn print_usage() {
+    println!("Usage: botserver reset --confirm [--yes] [--dry-run] [--output <dir>]");
+    println!();
+    println!("Backs up the stack, stops every component and deletes the stack directory,");
+    println!("the data directory and .env.");
+    println!("  --confirm   Required to change anything");
+    println!("  --dry-run   Print what would be backed up and deleted, then exit");
+    println!("  --yes       Skip the interactive prompt");
+    println!(
+        "  --output    Backup directory (default {}, moved out of the stack)",
+        DIR_ENV
+    );
+}
+
+fn confirm_reset(plan: &ResetPlan) -> bool {
+    println!();
+    println!("!!! FACTORY RESET !!!");
+    println!(
+        "This deletes {} path(s) listed above, including every bot, conversation, file \
+         and secret in this stack. Only the backup in {} will remain.",
+        plan.remove.len(),
+        plan.backup_dir.display()
+    );
+    print!("Type '{}' to continue: ", CONFIRM_WORD);
+    let _ = std::io::stdout().flush();
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input).is_ok() && input.trim() == CONFIRM_WORD
+}
+
+/// Asks every stack component to exit, then kills whatever is left after a grace period.
+async fn stop_components() {
+    let processes = get_processes_to_kill();
+    for (name, _) in &processes {
+        safe_pkill(&[name.as_str()], &["-f"]);
+    }
+    tokio::time::sleep(STOP_GRACE).await;
+    for (name, args) in &processes {
+        safe_pkill(&[name.as_str()], args);
+    }
+}
+
+/// Another running botserver, found through the instance lock or, when it holds none, by
+/// process name.
+fn other_server_pid() -> Option<u32> {
+    running_instance().or_else(|| {
+        let output = SafeCommand::new("pgrep")
+            .and_then(|c| c.args(&["-x", "botserver"]))
+            .ok()?
+            .execute()
+            .ok()?;
+        let me = std::process::id();
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .find(|pid| *pid != me)
+    })
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// Entry point for `botserver reset`; `args` starts at the subcommand. Returns the process
+/// exit code.
+pub async fn run_cli(args: &[String]) -> i32 {
+    let has = |flag: &str| args.iter().any(|a| a == flag);
+    if has("--help") || has("-h") {
+        print_usage();
+        return 0;
+    }
+    if has("--only") {
+        eprintln!("reset always backs up the database, drive and vault; --only is not accepted");
+        return 1;
+    }
+    let mut config = match config_from_args(args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            print_usage();
+            return 1;
+        }
+    };
+    // Everything is deleted, so everything is backed up first.
+    config.components = Components::ALL;
+    let plan = match current_plan(&config) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            return 1;
+        }
+    };
+    print_plan(&plan);
+
+    if has("--dry-run") {
+        println!("Dry run: nothing was changed");
+        return 0;
+    }
+    if !has("--confirm") {
+        eprintln!("Refusing to reset without --confirm");
+        return 1;
+    }
+    if let Some(pid) = other_server_pid() {
+        eprintln!(
+            "botserver is running on this stack (PID {}). Stop it before resetting.",
+            pid
+        );
+        return 1;
+    }
+    if !has("--yes") && !confirm_reset(&plan) {
+        println!("Aborted");
+        return 1;
+    }
+
+    config.dir = plan.backup_dir.clone();
+    let drive = cli_drive().await;
+    match create_backup(&config, drive.as_ref()).await {
+        Ok(report) => println!(
+            "Backup written to {} ({} entries, {} bytes)",
+            report.path.display(),
+            report.entries,
+            report.size
+        ),
+        Err(e) => {
+            eprintln!("Backup failed, nothing was deleted: {:#}", e);
+            return 1;
+        }
+    }
+
+    println!("Stopping stack components...");
+    stop_components().await;
+
+    let mut failed = 0;
+    for path in &plan.remove {
+        match remove_path(path) {
+            Ok(()) => println!("Deleted {}", path.display()),
+            Err(e) => {
+                eprintln!("Failed to delete {}: {}", path.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        eprintln!("Reset incomplete: {} path(s) could not be deleted", failed);
+        return 1;
+    }
+    println!("Reset complete. Run botserver to install a fresh stack.");
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_removes_stack_data_and_env() {
+        let root = tempfile::tempdir().unwrap();
+        let stack = root.path().join("botserver-stack");
+        let data = root.path().join("data");
+        let env = root.path().join(".env");
+        std::fs::create_dir_all(stack.join("bin/tables")).unwrap();
+        std::fs::create_dir_all(&data).unwrap();
+        std::fs::write(&env, "VAULT_ADDR=x").unwrap();
+        let cwd = root.path().join("work");
+
+        let plan = plan_reset(
+            &stack,
+            &data,
+            &[env.clone(), root.path().join("missing.env")],
+            None,
+            &stack.join("backups"),
+            &cwd,
+        )
+        .unwrap();
+        assert_eq!(plan.remove, vec![stack.clone(), data.clone(), env]);
+        assert!(plan.keep.is_empty());
+        // The default backup directory would be deleted with the stack.
+        assert_eq!(plan.backup_dir, root.path().join(FALLBACK_BACKUP_DIR));
+
+        // Running from inside the stack.
+        assert!(plan_reset(&stack, &data, &[], None, &cwd, &stack.join("bin/tables")).is_err());
+    }
+
+    #[test]
+    fn test_plan_keeps_running_binary() {
+        let root = tempfile::tempdir().unwrap();
+        let stack = root.path().join("gbo");
+        for dir in ["bin", "conf", "data", "logs"] {
+            std::fs::create_dir_all(stack.join(dir)).unwrap();
+        }
+        let exe = stack.join("bin/botserver");
+        let backups = root.path().join("backups");
+
+        let plan = plan_reset(
+            &stack,
+            &stack.join("data"),
+            &[],
+            Some(&exe),
+            &backups,
+            &root.path().join("work"),
+        )
+        .unwrap();
+        assert_eq!(
+            plan.remove,
+            vec![stack.join("conf"), stack.join("data"), stack.join("logs")]
+        );
+        assert_eq!(plan.keep, vec![stack.join("bin")]);
+        assert_eq!(plan.backup_dir, backups);
+    }
+}
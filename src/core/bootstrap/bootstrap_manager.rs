@@ -361,17 +361,54 @@ impl BootstrapManager {
         Ok(())
     }
 
-    /// Sync templates to database
+    /// Create a bot for every template that has none yet and sync the config of the new bots
     pub fn sync_templates_to_database(&self) -> anyhow::Result<()> {
+        use crate::core::bootstrap::template_sync::{resync_requested, sync_template_bots};
+        use crate::core::bootstrap::template_upload::templates_dir;
+
         info!("Syncing templates to database...");
-        // TODO: Implement actual template sync
+        let pool = crate::core::shared::utils::create_conn()?;
+        let synced = sync_template_bots(&pool, &templates_dir(), resync_requested())
+            .map_err(anyhow::Error::msg)?;
+        info!("Synced {} template bots", synced);
         Ok(())
     }
 
-    /// Upload templates to drive
+    /// Upload templates to drive. Files already in the drive are skipped, so a run cut
+    /// short by the boot timeout continues where it stopped on the next start.
+    #[cfg(feature = "drive")]
+    pub async fn upload_templates_to_drive(&self, cfg: &AppConfig) -> anyhow::Result<()> {
+        use crate::core::bootstrap::template_upload::{
+            collect_template_files, templates_dir, upload_templates, S3TemplateStore,
+        };
+        use crate::drive::object_store::DriveBackend;
+
+        if let Ok(DriveBackend::Filesystem(_)) = DriveBackend::from_env() {
+            info!("Filesystem drive backend selected, skipping template upload to S3");
+            return Ok(());
+        }
+
+        let root = templates_dir();
+        let files = collect_template_files(&root);
+        if files.is_empty() {
+            info!("No templates found in {}, skipping drive upload", root.display());
+            return Ok(());
+        }
+        info!("Uploading {} template files to drive...", files.len());
+
+        let client = crate::core::shared::utils::create_s3_operator(&cfg.drive)
+            .await
+            .map_err(|e| anyhow::anyhow!("Drive unavailable: {}", e))?;
+        let summary = upload_templates(&S3TemplateStore { client }, &files).await;
+        if summary.failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Template upload incomplete: {}", summary))
+        }
+    }
+
+    #[cfg(not(feature = "drive"))]
     pub async fn upload_templates_to_drive(&self, _cfg: &AppConfig) -> anyhow::Result<()> {
-        info!("Uploading templates to drive...");
-        // TODO: Implement actual template upload
         Ok(())
     }
 }
@@ -1,51 +1,345 @@
 //! Instance locking functions for bootstrap
 //!
 //! Extracted from mod.rs
+//!
+//! The lock file `<stack>/.lock` records the owner's PID, the process start time and a
+//! heartbeat the owner refreshes every `HEARTBEAT_INTERVAL`. A lock is stale, and taken
+//! over, when its process is gone, when the PID now belongs to a process that started at
+//! another time (the PID was recycled after a crash), or when the heartbeat is older than
+//! `STALE_AFTER`. Lock files from older versions hold only a PID; their modification time
+//! stands in for the heartbeat.
 
 use crate::core::shared::utils::get_stack_path;
 use crate::security::command_guard::SafeCommand;
-use log::warn;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-/// Check if another instance is already running
-pub fn check_single_instance() -> Result<bool, Box<dyn std::error::Error>> {
-    let stack_path = get_stack_path();
-    let lock_file = PathBuf::from(&stack_path).join(".lock");
-    if lock_file.exists() {
-        if let Ok(pid_str) = fs::read_to_string(&lock_file) {
-            if let Ok(pid) = pid_str.trim().parse::<i32>() {
-                let pid_str = pid.to_string();
-                if let Some(output) = SafeCommand::new("kill")
-                    .and_then(|c| c.args(&["-0", &pid_str]))
-                    .ok()
-                    .and_then(|cmd| cmd.execute().ok())
-                {
-                    if output.status.success() {
-                        warn!(
-                            "Another botserver process (PID {}) is already running on this stack",
-                            pid
-                        );
-                        return Ok(false);
-                    }
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// Three missed heartbeats.
+const STALE_AFTER: Duration = Duration::from_secs(90);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstanceLock {
+    pub pid: u32,
+    /// Start time of the owning process, in clock ticks since boot, where the platform
+    /// exposes it.
+    pub started_at: Option<u64>,
+    /// Unix seconds of the last heartbeat.
+    pub heartbeat: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Gone,
+    Running { started_at: Option<u64> },
+}
+
+fn lock_path() -> PathBuf {
+    PathBuf::from(get_stack_path()).join(".lock")
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Start time from `/proc/<pid>/stat` (field 22). The command name in field 2 may
+/// contain spaces, so fields are counted after its closing parenthesis.
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let after_name = &stat[stat.rfind(')')? + 1..];
+    after_name.split_whitespace().nth(19)?.parse().ok()
+}
+
+fn probe_process(pid: u32) -> ProcessState {
+    if Path::new("/proc/self/stat").exists() {
+        return match process_start_time(pid) {
+            Some(started_at) => ProcessState::Running {
+                started_at: Some(started_at),
+            },
+            None => ProcessState::Gone,
+        };
+    }
+    let pid_str = pid.to_string();
+    let alive = SafeCommand::new("kill")
+        .and_then(|c| c.args(&["-0", &pid_str]))
+        .ok()
+        .and_then(|cmd| cmd.execute().ok())
+        .is_some_and(|output| output.status.success());
+    if alive {
+        ProcessState::Running { started_at: None }
+    } else {
+        ProcessState::Gone
+    }
+}
+
+/// Reads a lock file, accepting the bare PID written by older versions.
+fn read_lock(path: &Path) -> Option<InstanceLock> {
+    let content = fs::read_to_string(path).ok()?;
+    if let Ok(lock) = serde_json::from_str::<InstanceLock>(&content) {
+        return Some(lock);
+    }
+    let pid = content.trim().parse().ok()?;
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    Some(InstanceLock {
+        pid,
+        started_at: None,
+        heartbeat: modified,
+    })
+}
+
+/// Why `lock` no longer protects a running instance, or `None` while it does.
+pub fn stale_reason(
+    lock: &InstanceLock,
+    process: ProcessState,
+    now: u64,
+    stale_after: Duration,
+) -> Option<&'static str> {
+    let ProcessState::Running { started_at } = process else {
+        return Some("process is gone");
+    };
+    if matches!((lock.started_at, started_at), (Some(a), Some(b)) if a != b) {
+        return Some("PID belongs to another process");
+    }
+    if now.saturating_sub(lock.heartbeat) > stale_after.as_secs() {
+        return Some("heartbeat expired");
+    }
+    None
+}
+
+fn write_new(path: &Path, lock: &InstanceLock) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    file.write_all(serde_json::to_string(lock)?.as_bytes())
+}
+
+/// Removes the lock file if it still holds `stale`. The file is first moved aside, which
+/// only one process can do, so a lock another instance has just taken is put back.
+fn remove_if_unchanged(path: &Path, stale: &InstanceLock, pid: u32) -> io::Result<bool> {
+    let aside = path.with_extension(format!("stale.{pid}"));
+    match fs::rename(path, &aside) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    }
+    if read_lock(&aside).is_some_and(|lock| lock.pid == stale.pid) {
+        fs::remove_file(&aside).ok();
+        Ok(true)
+    } else {
+        fs::rename(&aside, path)?;
+        Ok(false)
+    }
+}
+
+/// Takes the lock at `path` for `me` unless a live instance holds it. Creation is atomic,
+/// so when two processes start together only one of them wins.
+fn acquire(
+    path: &Path,
+    me: &InstanceLock,
+    stale_after: Duration,
+    probe: impl Fn(u32) -> ProcessState,
+) -> io::Result<bool> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    for _ in 0..2 {
+        match write_new(path, me) {
+            Ok(()) => return Ok(true),
+            Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e),
+            Err(_) => {}
+        }
+        let Some(held) = read_lock(path) else {
+            if !path.exists() {
+                continue;
+            }
+            // Unreadable or half-written; only reclaim it once it is old enough.
+            let age = fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.elapsed().ok())
+                .unwrap_or_default();
+            if age <= stale_after {
+                return Ok(false);
+            }
+            fs::remove_file(path).ok();
+            continue;
+        };
+        if held.pid == me.pid && held.started_at == me.started_at {
+            return Ok(true);
+        }
+        match stale_reason(&held, probe(held.pid), me.heartbeat, stale_after) {
+            Some(reason) => {
+                warn!(
+                    "Reclaiming stale instance lock of PID {}: {}",
+                    held.pid, reason
+                );
+                if !remove_if_unchanged(path, &held, me.pid)? {
+                    return Ok(false);
                 }
             }
+            None => {
+                warn!(
+                    "Another botserver process (PID {}) is already running on this stack",
+                    held.pid
+                );
+                return Ok(false);
+            }
         }
     }
+    Ok(false)
+}
 
+/// Refreshes the heartbeat while `path` still names this process; stops once the lock is
+/// released or taken over.
+fn spawn_heartbeat(path: PathBuf, mut lock: InstanceLock) {
+    let spawned = std::thread::Builder::new()
+        .name("instance-lock".to_string())
+        .spawn(move || loop {
+            std::thread::sleep(HEARTBEAT_INTERVAL);
+            match read_lock(&path) {
+                Some(held) if held.pid == lock.pid && held.started_at == lock.started_at => {}
+                _ => break,
+            }
+            lock.heartbeat = unix_now();
+            let tmp = path.with_extension("lock.tmp");
+            let written = serde_json::to_string(&lock)
+                .map_err(io::Error::from)
+                .and_then(|json| fs::write(&tmp, json))
+                .and_then(|()| fs::rename(&tmp, &path));
+            if let Err(e) = written {
+                warn!("Failed to refresh instance lock heartbeat: {}", e);
+            }
+        });
+    if let Err(e) = spawned {
+        warn!("Failed to start instance lock heartbeat: {}", e);
+    }
+}
+
+/// Check if another instance is already running
+pub fn check_single_instance() -> Result<bool, Box<dyn std::error::Error>> {
+    let path = lock_path();
     let pid = std::process::id();
-    if let Some(parent) = lock_file.parent() {
-        fs::create_dir_all(parent).ok();
+    let me = InstanceLock {
+        pid,
+        started_at: process_start_time(pid),
+        heartbeat: unix_now(),
+    };
+    if !acquire(&path, &me, STALE_AFTER, probe_process)? {
+        return Ok(false);
     }
-    fs::write(&lock_file, pid.to_string()).ok();
+    info!("Acquired instance lock {} for PID {}", path.display(), pid);
+    spawn_heartbeat(path, me);
     Ok(true)
 }
 
+/// PID of another live botserver holding the stack's lock, if any.
+pub fn running_instance() -> Option<u32> {
+    let held = read_lock(&lock_path())?;
+    if held.pid == std::process::id() {
+        return None;
+    }
+    stale_reason(&held, probe_process(held.pid), unix_now(), STALE_AFTER)
+        .is_none()
+        .then_some(held.pid)
+}
+
 /// Release the instance lock
 pub fn release_instance_lock() {
-    let stack_path = get_stack_path();
-    let lock_file = PathBuf::from(&stack_path).join(".lock");
-    if lock_file.exists() {
-        fs::remove_file(&lock_file).ok();
+    let path = lock_path();
+    let pid = std::process::id();
+    if read_lock(&path).is_some_and(|held| held.pid == pid) {
+        fs::remove_file(&path).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock(pid: u32, started_at: Option<u64>, heartbeat: u64) -> InstanceLock {
+        InstanceLock {
+            pid,
+            started_at,
+            heartbeat,
+        }
+    }
+
+    #[test]
+    fn test_stale_lock_is_reclaimed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".lock");
+        let now = 10_000;
+        let me = lock(200, Some(77), now);
+
+        // PID 100 was recycled: a process runs under it, but it started at another time.
+        fs::write(
+            &path,
+            serde_json::to_string(&lock(100, Some(5), now)).unwrap(),
+        )
+        .unwrap();
+        let recycled = |_| ProcessState::Running {
+            started_at: Some(9),
+        };
+        assert!(acquire(&path, &me, STALE_AFTER, recycled).unwrap());
+        assert_eq!(read_lock(&path), Some(me.clone()));
+
+        // The owner is running but stopped refreshing its heartbeat.
+        fs::remove_file(&path).unwrap();
+        fs::write(
+            &path,
+            serde_json::to_string(&lock(100, Some(5), now - 91)).unwrap(),
+        )
+        .unwrap();
+        let hung = |_| ProcessState::Running {
+            started_at: Some(5),
+        };
+        assert!(acquire(&path, &me, STALE_AFTER, hung).unwrap());
+
+        // A bare PID from an older version whose process is gone.
+        fs::remove_file(&path).unwrap();
+        fs::write(&path, "100").unwrap();
+        assert!(acquire(&path, &me, STALE_AFTER, |_| ProcessState::Gone).unwrap());
+        assert_eq!(read_lock(&path).map(|l| l.pid), Some(200));
+    }
+
+    #[test]
+    fn test_live_lock_blocks_second_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".lock");
+        let now = 10_000;
+        let held = lock(100, Some(5), now - 20);
+        fs::write(&path, serde_json::to_string(&held).unwrap()).unwrap();
+
+        let owner = |_| ProcessState::Running {
+            started_at: Some(5),
+        };
+        assert!(!acquire(&path, &lock(200, Some(77), now), STALE_AFTER, owner).unwrap());
+        assert_eq!(read_lock(&path), Some(held));
+    }
+
+    #[test]
+    fn test_reads_own_process_start_time() {
+        if Path::new("/proc/self/stat").exists() {
+            let pid = std::process::id();
+            assert!(process_start_time(pid).is_some());
+            assert_eq!(
+                probe_process(pid),
+                ProcessState::Running {
+                    started_at: process_start_time(pid)
+                }
+            );
+        }
     }
 }
@@ -3,6 +3,8 @@ pub mod bootstrap_types;
 pub mod bootstrap_utils;
 pub mod bootstrap_manager;
 pub mod instance;
+pub mod template_sync;
+pub mod template_upload;
 pub mod vault;
 
 // Re-export for backward compatibility
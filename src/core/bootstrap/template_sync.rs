@@ -0,0 +1,294 @@
+//! Creation of bots from the bundled templates (`<name>.gbai` directories).
+//!
+//! Every template becomes a bot named after its directory, created if it does not exist
+//! yet. A newly created bot gets the template's `config.csv` synced into
+//! `bot_configuration`; bots that already exist keep the configuration operators gave
+//! them, unless `TEMPLATE_CONFIG_RESYNC` is set to resync every template.
+//!
+//! The `default` template, or the first one when there is none, is marked as the default
+//! bot unless another bot already is.
+
+use crate::core::config::ConfigManager;
+use crate::core::shared::utils::DbPool;
+use diesel::prelude::*;
+use diesel::sql_types::{Text, Uuid as DieselUuid};
+use diesel::PgConnection;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+const DEFAULT_TEMPLATE: &str = "default";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateBot {
+    pub bot_name: String,
+    pub template_dir: PathBuf,
+    pub is_default: bool,
+}
+
+impl TemplateBot {
+    /// `config.csv` of the template's `.gbot` package, if it has one.
+    pub fn config_csv(&self) -> Option<PathBuf> {
+        let mut packages: Vec<PathBuf> = std::fs::read_dir(&self.template_dir)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir() && path.extension().is_some_and(|ext| ext == "gbot"))
+            .collect();
+        packages.sort();
+        packages
+            .into_iter()
+            .map(|package| package.join("config.csv"))
+            .find(|path| path.is_file())
+    }
+}
+
+/// Bot name for a template directory name: lowercase, with anything but letters, digits,
+/// `-` and `_` replaced by `-`.
+fn bot_name_for(template: &str) -> String {
+    template
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// One bot per `<name>.gbai` directory under `root`, in name order. Templates whose names
+/// normalize to the same bot name get `-2`, `-3`, ... suffixes.
+pub fn plan_template_bots(root: &Path) -> Vec<TemplateBot> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return vec![];
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && path.extension().is_some_and(|ext| ext == "gbai"))
+        .collect();
+    dirs.sort();
+
+    let mut bots: Vec<TemplateBot> = Vec::with_capacity(dirs.len());
+    for template_dir in dirs {
+        let base = template_dir
+            .file_stem()
+            .map(|stem| bot_name_for(&stem.to_string_lossy()))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "bot".to_string());
+        let mut bot_name = base.clone();
+        let mut n = 2;
+        while bots.iter().any(|b| b.bot_name == bot_name) {
+            bot_name = format!("{}-{}", base, n);
+            n += 1;
+        }
+        if bot_name != base {
+            warn!(
+                "Template {} collides with another template, creating it as bot '{}'",
+                template_dir.display(),
+                bot_name
+            );
+        }
+        bots.push(TemplateBot {
+            bot_name,
+            template_dir,
+            is_default: false,
+        });
+    }
+
+    let default_idx = bots
+        .iter()
+        .position(|b| b.bot_name == DEFAULT_TEMPLATE)
+        .unwrap_or(0);
+    if let Some(bot) = bots.get_mut(default_idx) {
+        bot.is_default = true;
+    }
+    bots
+}
+
+#[derive(QueryableByName)]
+struct IdRow {
+    #[diesel(sql_type = DieselUuid)]
+    id: Uuid,
+}
+
+/// Id of the bot named `bot_name`, creating it in the default organization if needed.
+/// Returns whether it was created.
+fn ensure_bot(conn: &mut PgConnection, bot_name: &str) -> QueryResult<(Uuid, bool)> {
+    use crate::core::shared::models::schema::bots::dsl::*;
+
+    if let Some(existing) = bots
+        .filter(name.eq(bot_name))
+        .filter(deleted_at.is_null())
+        .select(id)
+        .first::<Uuid>(conn)
+        .optional()?
+    {
+        return Ok((existing, false));
+    }
+
+    diesel::sql_query(
+        "INSERT INTO tenants (id, name, slug, created_at) \
+         VALUES ('00000000-0000-0000-0000-000000000001', 'Default Tenant', 'default', NOW()) \
+         ON CONFLICT (slug) DO NOTHING",
+    )
+    .execute(conn)?;
+    diesel::sql_query(
+        "INSERT INTO organizations (org_id, tenant_id, name, slug, created_at) \
+         VALUES ('00000000-0000-0000-0000-000000000001', '00000000-0000-0000-0000-000000000001', 'Default Organization', 'default', NOW()) \
+         ON CONFLICT (slug) DO NOTHING",
+    )
+    .execute(conn)?;
+
+    let org: IdRow =
+        diesel::sql_query("SELECT org_id AS id FROM organizations WHERE slug = 'default' LIMIT 1")
+            .get_result(conn)?;
+    let bot_id = Uuid::new_v4();
+    diesel::sql_query(
+        "INSERT INTO bots (id, name, slug, org_id, is_active, created_at, llm_provider, llm_config, context_provider, context_config) \
+         VALUES ($1, $2, $2, $3, true, NOW(), 'openai', '{}', 'openai', '{}')",
+    )
+    .bind::<DieselUuid, _>(bot_id)
+    .bind::<Text, _>(bot_name)
+    .bind::<DieselUuid, _>(org.id)
+    .execute(conn)?;
+    Ok((bot_id, true))
+}
+
+/// Marks `bot_id` as the default bot if no other bot is marked yet, so an administrator's
+/// choice survives restarts.
+fn mark_default_if_unset(conn: &mut PgConnection, bot_id: Uuid) -> QueryResult<bool> {
+    diesel::sql_query(
+        "UPDATE bots SET is_default = true, updated_at = NOW() \
+         WHERE id = $1 AND NOT EXISTS \
+         (SELECT 1 FROM bots WHERE is_default AND deleted_at IS NULL)",
+    )
+    .bind::<DieselUuid, _>(bot_id)
+    .execute(conn)
+    .map(|rows| rows > 0)
+}
+
+/// Whether `TEMPLATE_CONFIG_RESYNC` asks to resync the config of existing template bots.
+pub fn resync_requested() -> bool {
+    std::env::var("TEMPLATE_CONFIG_RESYNC")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false)
+}
+
+/// Creates a bot for every template under `root` and syncs the configuration of the bots
+/// it created, or of every template bot with `resync`. A failing template is logged and
+/// the others are still processed.
+pub fn sync_template_bots(pool: &DbPool, root: &Path, resync: bool) -> Result<usize, String> {
+    let templates = plan_template_bots(root);
+    if templates.is_empty() {
+        info!(
+            "No templates found in {}, no bots to create",
+            root.display()
+        );
+        return Ok(0);
+    }
+
+    let config = ConfigManager::new(pool.clone());
+    let mut failures = 0;
+    for template in &templates {
+        let result = pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| {
+                let (bot_id, created) =
+                    ensure_bot(&mut conn, &template.bot_name).map_err(|e| e.to_string())?;
+                if created {
+                    info!(
+                        "Created bot '{}' from template {}",
+                        template.bot_name,
+                        template.template_dir.display()
+                    );
+                }
+                if template.is_default
+                    && mark_default_if_unset(&mut conn, bot_id).map_err(|e| e.to_string())?
+                {
+                    info!("Bot '{}' marked as default", template.bot_name);
+                }
+                Ok((bot_id, created))
+            })
+            .and_then(|(bot_id, created)| {
+                if !created && !resync {
+                    return Ok(());
+                }
+                let Some(csv) = template.config_csv() else {
+                    return Ok(());
+                };
+                let content = std::fs::read_to_string(&csv)
+                    .map_err(|e| format!("Failed to read {}: {}", csv.display(), e))?;
+                let diff = config.sync_gbot_config(&bot_id, &content)?;
+                info!("Synced config for bot '{}': {}", template.bot_name, diff);
+                Ok(())
+            });
+        if let Err(e) = result {
+            failures += 1;
+            warn!("Failed to sync template bot '{}': {}", template.bot_name, e);
+        }
+    }
+
+    if failures == templates.len() {
+        return Err(format!("none of {} templates could be synced", failures));
+    }
+    Ok(templates.len() - failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(root: &Path, dir: &str, config: Option<&str>) {
+        let path = root.join(dir);
+        std::fs::create_dir_all(&path).unwrap();
+        if let Some(config) = config {
+            let stem = dir.trim_end_matches(".gbai");
+            let package = path.join(format!("{stem}.gbot"));
+            std::fs::create_dir_all(&package).unwrap();
+            std::fs::write(package.join("config.csv"), config).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_two_templates_produce_two_bots() {
+        let root = tempfile::tempdir().unwrap();
+        template(
+            root.path(),
+            "sales.gbai",
+            Some("name,value\ntheme-color1,#000\n"),
+        );
+        template(root.path(), "support.gbai", None);
+        std::fs::write(root.path().join("notes.gbai"), "not a directory").unwrap();
+
+        let bots = plan_template_bots(root.path());
+        let names: Vec<&str> = bots.iter().map(|b| b.bot_name.as_str()).collect();
+        assert_eq!(names, ["sales", "support"]);
+        assert!(bots[0].is_default && !bots[1].is_default);
+        assert!(bots[0].config_csv().is_some());
+        assert!(bots[1].config_csv().is_none());
+    }
+
+    #[test]
+    fn test_default_template_and_name_collisions() {
+        let root = tempfile::tempdir().unwrap();
+        template(root.path(), "Sales Team.gbai", None);
+        template(root.path(), "default.gbai", None);
+        template(root.path(), "sales-team.gbai", None);
+
+        let bots = plan_template_bots(root.path());
+        let names: Vec<&str> = bots.iter().map(|b| b.bot_name.as_str()).collect();
+        assert_eq!(names, ["sales-team", "default", "sales-team-2"]);
+        let defaults: Vec<&str> = bots
+            .iter()
+            .filter(|b| b.is_default)
+            .map(|b| b.bot_name.as_str())
+            .collect();
+        assert_eq!(defaults, ["default"]);
+    }
+}
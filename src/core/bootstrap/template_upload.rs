@@ -0,0 +1,443 @@
+//! Upload of bundled bot templates (`<name>.gbai` directories) to the drive.
+//!
+//! Every file is uploaded on its own: a failure is recorded and the rest continue. Files
+//! whose drive copy already has the same size and content hash are skipped, so an upload
+//! interrupted by the boot timeout resumes where it stopped on the next start instead of
+//! sending everything again.
+//!
+//! Each upload records the SHA-256 of what was sent in the object's metadata. A newer
+//! template only replaces a drive copy whose content still matches that record; copies an
+//! operator edited in the drive, or that carry no record, are left alone.
+
+use async_trait::async_trait;
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Object metadata key holding the SHA-256 of the last uploaded template file.
+pub const CONTENT_HASH_METADATA: &str = "template-sha256";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteObject {
+    pub size: u64,
+    /// Hash recorded by the last template upload, if this object came from one.
+    pub content_hash: Option<String>,
+}
+
+#[async_trait]
+pub trait TemplateStore: Send + Sync {
+    async fn ensure_bucket(&self, bucket: &str) -> Result<(), String>;
+    async fn head(&self, bucket: &str, key: &str) -> Result<Option<RemoteObject>, String>;
+    async fn get(&self, bucket: &str, key: &str) -> Result<Vec<u8>, String>;
+    async fn put(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_hash: &str,
+    ) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateFile {
+    pub bucket: String,
+    pub key: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Default)]
+pub struct TemplateUploadSummary {
+    pub uploaded: usize,
+    pub skipped: usize,
+    /// Files whose drive copy was edited since the last upload and was kept as is.
+    pub kept: usize,
+    /// `bucket/key` and the error for each file that could not be uploaded.
+    pub failed: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for TemplateUploadSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} uploaded, {} already present, {} edited in drive, {} failed",
+            self.uploaded,
+            self.skipped,
+            self.kept,
+            self.failed.len()
+        )
+    }
+}
+
+/// Templates directory: `BOTSERVER_TEMPLATES_DIR`, or `templates` next to the binary's
+/// working directory.
+pub fn templates_dir() -> PathBuf {
+    std::env::var("BOTSERVER_TEMPLATES_DIR")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("templates"))
+}
+
+/// Files of every `<name>.gbai` directory under `root`, mapped to bucket `<name>.gbai`
+/// and their path inside it. Sorted so uploads progress in a stable order.
+pub fn collect_template_files(root: &Path) -> Vec<TemplateFile> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return vec![];
+    };
+    let mut files: Vec<TemplateFile> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir() && path.extension().is_some_and(|ext| ext == "gbai"))
+        .flat_map(|bot_dir| {
+            let bucket = bot_dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            walkdir::WalkDir::new(&bot_dir)
+                .into_iter()
+                .flatten()
+                .filter(|e| e.file_type().is_file())
+                .filter_map(move |e| {
+                    let key = e
+                        .path()
+                        .strip_prefix(&bot_dir)
+                        .ok()?
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    Some(TemplateFile {
+                        bucket: bucket.clone(),
+                        key,
+                        path: e.path().to_path_buf(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    files.sort_by(|a, b| (&a.bucket, &a.key).cmp(&(&b.bucket, &b.key)));
+    files
+}
+
+fn is_current(remote: &RemoteObject, size: u64, content_hash: &str) -> bool {
+    remote.size == size && remote.content_hash.as_deref() == Some(content_hash)
+}
+
+/// Whether the drive copy is still exactly what the last upload recorded. Objects without
+/// a record were written by someone else and count as edited.
+async fn is_unedited(
+    store: &dyn TemplateStore,
+    file: &TemplateFile,
+    remote: &RemoteObject,
+) -> Result<bool, String> {
+    let Some(recorded) = remote.content_hash.as_deref() else {
+        return Ok(false);
+    };
+    let body = store.get(&file.bucket, &file.key).await?;
+    Ok(hex::encode(Sha256::digest(&body)) == recorded)
+}
+
+pub async fn upload_templates(
+    store: &dyn TemplateStore,
+    files: &[TemplateFile],
+) -> TemplateUploadSummary {
+    let mut summary = TemplateUploadSummary::default();
+    let mut ready_buckets: Vec<String> = Vec::new();
+
+    for file in files {
+        let name = format!("{}/{}", file.bucket, file.key);
+        if !ready_buckets.contains(&file.bucket) {
+            if let Err(e) = store.ensure_bucket(&file.bucket).await {
+                summary.failed.push((name, e));
+                continue;
+            }
+            ready_buckets.push(file.bucket.clone());
+        }
+
+        let body = match tokio::fs::read(&file.path).await {
+            Ok(body) => body,
+            Err(e) => {
+                summary.failed.push((name, e.to_string()));
+                continue;
+            }
+        };
+        let content_hash = hex::encode(Sha256::digest(&body));
+
+        match store.head(&file.bucket, &file.key).await {
+            Ok(Some(remote)) if is_current(&remote, body.len() as u64, &content_hash) => {
+                summary.skipped += 1;
+                continue;
+            }
+            Ok(Some(remote)) => match is_unedited(store, file, &remote).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    info!("Keeping {}: edited in drive since the last upload", name);
+                    summary.kept += 1;
+                    continue;
+                }
+                Err(e) => {
+                    summary.failed.push((name, e));
+                    continue;
+                }
+            },
+            Ok(None) => {}
+            Err(e) => warn!("Could not check {} in drive, uploading: {}", name, e),
+        }
+
+        match store
+            .put(&file.bucket, &file.key, body, &content_hash)
+            .await
+        {
+            Ok(()) => summary.uploaded += 1,
+            Err(e) => summary.failed.push((name, e)),
+        }
+    }
+
+    info!("Template upload: {}", summary);
+    for (name, error) in &summary.failed {
+        warn!("Template upload failed for {}: {}", name, error);
+    }
+    summary
+}
+
+#[cfg(feature = "drive")]
+pub struct S3TemplateStore {
+    pub client: aws_sdk_s3::Client,
+}
+
+#[cfg(feature = "drive")]
+#[async_trait]
+impl TemplateStore for S3TemplateStore {
+    async fn ensure_bucket(&self, bucket: &str) -> Result<(), String> {
+        if self
+            .client
+            .head_bucket()
+            .bucket(bucket)
+            .send()
+            .await
+            .is_ok()
+        {
+            return Ok(());
+        }
+        self.client
+            .create_bucket()
+            .bucket(bucket)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Failed to create bucket {}: {}", bucket, e))
+    }
+
+    async fn head(&self, bucket: &str, key: &str) -> Result<Option<RemoteObject>, String> {
+        match self
+            .client
+            .head_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(head) => Ok(Some(RemoteObject {
+                size: head.content_length().unwrap_or(0).max(0) as u64,
+                content_hash: head
+                    .metadata()
+                    .and_then(|m| m.get(CONTENT_HASH_METADATA))
+                    .cloned(),
+            })),
+            Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn get(&self, bucket: &str, key: &str) -> Result<Vec<u8>, String> {
+        let object = self
+            .client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        object
+            .body
+            .collect()
+            .await
+            .map(|data| data.into_bytes().to_vec())
+            .map_err(|e| e.to_string())
+    }
+
+    async fn put(
+        &self,
+        bucket: &str,
+        key: &str,
+        body: Vec<u8>,
+        content_hash: &str,
+    ) -> Result<(), String> {
+        self.client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .metadata(CONTENT_HASH_METADATA, content_hash)
+            .body(aws_sdk_s3::primitives::ByteStream::from(body))
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        objects: Mutex<HashMap<String, (RemoteObject, Vec<u8>)>>,
+        puts: Mutex<Vec<String>>,
+        fail_keys: Vec<String>,
+    }
+
+    #[async_trait]
+    impl TemplateStore for MemoryStore {
+        async fn ensure_bucket(&self, _bucket: &str) -> Result<(), String> {
+            Ok(())
+        }
+
+        async fn head(&self, bucket: &str, key: &str) -> Result<Option<RemoteObject>, String> {
+            let objects = self.objects.lock().unwrap();
+            Ok(objects
+                .get(&format!("{bucket}/{key}"))
+                .map(|(remote, _)| remote.clone()))
+        }
+
+        async fn get(&self, bucket: &str, key: &str) -> Result<Vec<u8>, String> {
+            let objects = self.objects.lock().unwrap();
+            objects
+                .get(&format!("{bucket}/{key}"))
+                .map(|(_, body)| body.clone())
+                .ok_or_else(|| "not found".to_string())
+        }
+
+        async fn put(
+            &self,
+            bucket: &str,
+            key: &str,
+            body: Vec<u8>,
+            content_hash: &str,
+        ) -> Result<(), String> {
+            if self.fail_keys.iter().any(|k| k == key) {
+                return Err("connection reset".to_string());
+            }
+            let name = format!("{bucket}/{key}");
+            self.puts.lock().unwrap().push(name.clone());
+            self.objects.lock().unwrap().insert(
+                name,
+                (
+                    RemoteObject {
+                        size: body.len() as u64,
+                        content_hash: Some(content_hash.to_string()),
+                    },
+                    body,
+                ),
+            );
+            Ok(())
+        }
+    }
+
+    impl MemoryStore {
+        /// Writes an object the way a drive client would, optionally keeping the recorded hash.
+        fn edit(&self, name: &str, body: &str, keep_record: bool) {
+            let mut objects = self.objects.lock().unwrap();
+            let record = objects
+                .get(name)
+                .and_then(|(remote, _)| remote.content_hash.clone())
+                .filter(|_| keep_record);
+            objects.insert(
+                name.to_string(),
+                (
+                    RemoteObject {
+                        size: body.len() as u64,
+                        content_hash: record,
+                    },
+                    body.as_bytes().to_vec(),
+                ),
+            );
+        }
+    }
+
+    fn template_tree() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let bot = root.path().join("sales.gbai");
+        std::fs::create_dir_all(bot.join("sales.gbdialog")).unwrap();
+        std::fs::write(bot.join("sales.gbdialog/start.bas"), "TALK \"hi\"").unwrap();
+        std::fs::write(bot.join("sales.gbot"), "name,value\n").unwrap();
+        std::fs::write(root.path().join("README.md"), "not a template").unwrap();
+        root
+    }
+
+    #[tokio::test]
+    async fn test_second_run_skips_existing_files() {
+        let root = template_tree();
+        let files = collect_template_files(root.path());
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].bucket, "sales.gbai");
+        assert_eq!(files[0].key, "sales.gbdialog/start.bas");
+
+        let store = MemoryStore::default();
+        let first = upload_templates(&store, &files).await;
+        assert_eq!((first.uploaded, first.skipped), (2, 0));
+
+        let second = upload_templates(&store, &files).await;
+        assert_eq!((second.uploaded, second.skipped), (0, 2));
+        assert_eq!(store.puts.lock().unwrap().len(), 2);
+
+        // A changed file is uploaded again, unchanged ones are not.
+        std::fs::write(&files[1].path, "name,value\ntheme,dark\n").unwrap();
+        let third = upload_templates(&store, &files).await;
+        assert_eq!((third.uploaded, third.skipped), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_files_edited_in_drive_are_left_alone() {
+        let root = template_tree();
+        let files = collect_template_files(root.path());
+        let store = MemoryStore::default();
+        upload_templates(&store, &files).await;
+
+        // One copy rewritten by a drive client (record dropped), one edited in place.
+        store.edit(
+            "sales.gbai/sales.gbdialog/start.bas",
+            "TALK \"hello\"",
+            false,
+        );
+        store.edit("sales.gbai/sales.gbot", "name,value\ntheme,light\n", true);
+        std::fs::write(&files[0].path, "TALK \"hi there\"").unwrap();
+        std::fs::write(&files[1].path, "name,value\ntheme,dark\n").unwrap();
+
+        let summary = upload_templates(&store, &files).await;
+        assert_eq!((summary.uploaded, summary.kept), (0, 2));
+        assert_eq!(store.puts.lock().unwrap().len(), 2);
+        let objects = store.objects.lock().unwrap();
+        assert_eq!(
+            objects["sales.gbai/sales.gbot"].1,
+            b"name,value\ntheme,light\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failures_do_not_stop_other_files() {
+        let root = template_tree();
+        let files = collect_template_files(root.path());
+        let store = MemoryStore {
+            fail_keys: vec!["sales.gbdialog/start.bas".to_string()],
+            ..Default::default()
+        };
+
+        let summary = upload_templates(&store, &files).await;
+        assert_eq!(summary.uploaded, 1);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, "sales.gbai/sales.gbdialog/start.bas");
+    }
+}
@@ -5,9 +5,151 @@
 use crate::core::shared::utils::get_stack_path;
 use anyhow::Result;
 use log::info;
+use serde_json::Value;
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Where `init.json` lives under a stack.
+pub fn vault_init_path(stack_path: &Path) -> PathBuf {
+    stack_path.join("conf/vault/init.json")
+}
+
+/// The parts of Vault's `init.json` the stack needs to unseal and log in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultInit {
+    pub unseal_keys_b64: Vec<String>,
+    pub root_token: String,
+}
+
+#[derive(Debug)]
+pub enum VaultInitError {
+    /// Vault has never been initialized for this stack.
+    Missing(PathBuf),
+    Unreadable(PathBuf, std::io::Error),
+    /// The file exists but cannot be used; Vault is initialized and its keys are lost
+    /// unless the file is restored.
+    Corrupt(PathBuf, String),
+}
+
+impl std::fmt::Display for VaultInitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing(path) => write!(
+                f,
+                "Missing {}: Vault has not been initialized for this stack and needs `vault operator init` (run the bootstrap)",
+                path.display()
+            ),
+            Self::Unreadable(path, e) => write!(f, "Cannot read {}: {e}", path.display()),
+            Self::Corrupt(path, reason) => write!(
+                f,
+                "Corrupt {} ({reason}): restore it from a backup in {}; re-initializing Vault would lose the secrets it holds",
+                path.display(),
+                backup_dir(path).display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VaultInitError {}
+
+/// Checks `content` has everything unsealing needs, describing the first problem found.
+pub fn parse_vault_init(content: &str) -> Result<VaultInit, String> {
+    if content.trim().is_empty() {
+        return Err("file is empty".to_string());
+    }
+    let json: Value = serde_json::from_str(content).map_err(|e| format!("not valid JSON: {e}"))?;
+    let Value::Object(fields) = json else {
+        return Err("expected a JSON object".to_string());
+    };
+
+    let keys = fields
+        .get("unseal_keys_b64")
+        .ok_or("unseal_keys_b64 is missing")?
+        .as_array()
+        .ok_or("unseal_keys_b64 is not a list")?;
+    let unseal_keys_b64 = keys
+        .iter()
+        .map(|k| match k.as_str() {
+            Some(k) if !k.trim().is_empty() => Ok(k.to_string()),
+            _ => Err("unseal_keys_b64 contains an empty or non-string key".to_string()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if unseal_keys_b64.is_empty() {
+        return Err("unseal_keys_b64 is empty".to_string());
+    }
+    if let Some(threshold) = fields.get("unseal_threshold").and_then(Value::as_u64) {
+        if (unseal_keys_b64.len() as u64) < threshold {
+            return Err(format!(
+                "{} unseal keys, fewer than the threshold of {threshold}",
+                unseal_keys_b64.len()
+            ));
+        }
+    }
+
+    let root_token = match fields.get("root_token").and_then(Value::as_str) {
+        Some(token) if !token.trim().is_empty() => token.to_string(),
+        Some(_) => return Err("root_token is empty".to_string()),
+        None => return Err("root_token is missing".to_string()),
+    };
+
+    Ok(VaultInit {
+        unseal_keys_b64,
+        root_token,
+    })
+}
+
+pub fn read_vault_init(path: &Path) -> Result<VaultInit, VaultInitError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(VaultInitError::Missing(path.to_path_buf()))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            return Err(VaultInitError::Corrupt(path.to_path_buf(), "not UTF-8 text".to_string()))
+        }
+        Err(e) => return Err(VaultInitError::Unreadable(path.to_path_buf(), e)),
+    };
+    parse_vault_init(&content).map_err(|reason| VaultInitError::Corrupt(path.to_path_buf(), reason))
+}
+
+fn backup_dir(init_path: &Path) -> PathBuf {
+    init_path
+        .parent()
+        .map_or_else(|| PathBuf::from("backups"), |dir| dir.join("backups"))
+}
+
+/// Writes Vault's init output to `path`, replacing the file atomically, and keeps a
+/// timestamped copy in `backups/` next to it. Output that could not unseal Vault later
+/// is refused before anything is written.
+pub fn write_vault_init(path: &Path, init: &Value) -> Result<PathBuf> {
+    let content = serde_json::to_string_pretty(init)?;
+    parse_vault_init(&content)
+        .map_err(|reason| anyhow::anyhow!("Refusing to save Vault init output: {reason}"))?;
+
+    let dir = backup_dir(path);
+    fs::create_dir_all(&dir)?;
+    let backup = dir.join(format!(
+        "init-{}.json",
+        chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ")
+    ));
+    write_private(&backup, &content)?;
+
+    let tmp = path.with_extension("json.tmp");
+    write_private(&tmp, &content)?;
+    fs::rename(&tmp, path)?;
+    Ok(backup)
+}
+
+fn write_private(path: &Path, content: &str) -> std::io::Result<()> {
+    fs::write(path, content)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
 
 /// Check if stack has been installed
 pub fn has_installed_stack() -> bool {
@@ -78,3 +220,72 @@ pub fn get_db_password_from_vault() -> Option<String> {
         Some(output.trim().to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: &str = r#"{"unseal_keys_b64": ["a1", "b2", "c3"], "unseal_threshold": 3, "root_token": "hvs.x"}"#;
+
+    fn reason(content: &str) -> String {
+        parse_vault_init(content).unwrap_err()
+    }
+
+    #[test]
+    fn test_malformed_init_json_is_reported_as_corrupt() {
+        assert_eq!(
+            parse_vault_init(VALID).unwrap(),
+            VaultInit {
+                unseal_keys_b64: vec!["a1".into(), "b2".into(), "c3".into()],
+                root_token: "hvs.x".into(),
+            }
+        );
+
+        assert_eq!(reason(""), "file is empty");
+        assert!(reason(&VALID[..40]).starts_with("not valid JSON"));
+        assert_eq!(reason("[1, 2]"), "expected a JSON object");
+        assert_eq!(reason(r#"{"root_token": "t"}"#), "unseal_keys_b64 is missing");
+        assert_eq!(reason(r#"{"unseal_keys_b64": [], "root_token": "t"}"#), "unseal_keys_b64 is empty");
+        assert_eq!(
+            reason(r#"{"unseal_keys_b64": ["a", ""], "root_token": "t"}"#),
+            "unseal_keys_b64 contains an empty or non-string key"
+        );
+        assert_eq!(
+            reason(r#"{"unseal_keys_b64": ["a"], "unseal_threshold": 3, "root_token": "t"}"#),
+            "1 unseal keys, fewer than the threshold of 3"
+        );
+        assert_eq!(reason(r#"{"unseal_keys_b64": ["a"], "root_token": ""}"#), "root_token is empty");
+    }
+
+    #[test]
+    fn test_missing_and_corrupt_files_are_told_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_init_path(dir.path());
+
+        let missing = read_vault_init(&path).unwrap_err();
+        assert!(matches!(missing, VaultInitError::Missing(_)));
+        assert!(missing.to_string().contains("needs `vault operator init`"));
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &VALID[..25]).unwrap();
+        let corrupt = read_vault_init(&path).unwrap_err();
+        assert!(matches!(corrupt, VaultInitError::Corrupt(_, _)));
+        assert!(corrupt.to_string().contains("restore it from a backup"));
+    }
+
+    #[test]
+    fn test_write_keeps_a_backup_and_refuses_bad_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_init_path(dir.path());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        let init: Value = serde_json::from_str(VALID).unwrap();
+        let backup = write_vault_init(&path, &init).unwrap();
+        assert_eq!(read_vault_init(&path).unwrap().root_token, "hvs.x");
+        assert_eq!(parse_vault_init(&fs::read_to_string(backup).unwrap()).unwrap().root_token, "hvs.x");
+
+        let bad = serde_json::json!({"root_token": "t"});
+        assert!(write_vault_init(&path, &bad).is_err());
+        assert_eq!(read_vault_init(&path).unwrap().root_token, "hvs.x");
+    }
+}
@@ -0,0 +1,120 @@
+//! Bots available to clients, and validation of the bot a session is bound to.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::bot_features::{enabled_features, load_overrides, BotFeature};
+use crate::core::shared::state::AppState;
+use axum::{extract::State, Json};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BotSummary {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub active: bool,
+    pub features: Vec<BotFeature>,
+}
+
+fn summarize(
+    (id, name, description, is_active): (Uuid, String, Option<String>, Option<bool>),
+    overrides: &HashMap<BotFeature, bool>,
+) -> BotSummary {
+    let active = is_active.unwrap_or(false);
+    BotSummary {
+        id,
+        name,
+        description,
+        active,
+        features: if active {
+            enabled_features(overrides)
+        } else {
+            vec![]
+        },
+    }
+}
+
+/// Every bot that has not been deleted, active ones first, then by name.
+pub fn list_bots(conn: &mut PgConnection) -> QueryResult<Vec<BotSummary>> {
+    use crate::core::shared::models::schema::bots::dsl::*;
+
+    let rows = bots
+        .filter(deleted_at.is_null())
+        .select((id, name, description, is_active))
+        .order(name.asc())
+        .load::<(Uuid, String, Option<String>, Option<bool>)>(conn)?;
+
+    let mut summaries = rows
+        .into_iter()
+        .map(|row| {
+            let overrides = load_overrides(conn, row.0)?;
+            Ok(summarize(row, &overrides))
+        })
+        .collect::<QueryResult<Vec<_>>>()?;
+    summaries.sort_by_key(|bot| !bot.active);
+    Ok(summaries)
+}
+
+/// Name of the bot if it exists and is active; sessions may only be bound to such bots.
+pub fn require_active_bot(conn: &mut PgConnection, bot_id: Uuid) -> Result<String, ApiError> {
+    use crate::core::shared::models::schema::bots::dsl::*;
+
+    let found = bots
+        .filter(id.eq(bot_id))
+        .filter(deleted_at.is_null())
+        .select((name, is_active))
+        .first::<(String, Option<bool>)>(conn)
+        .optional()
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+
+    match found {
+        Some((bot_name, Some(true))) => Ok(bot_name),
+        Some(_) => Err(ApiError::conflict(format!("Bot {} is not active", bot_id))),
+        None => Err(ApiError::not_found(format!("Bot {} not found", bot_id))),
+    }
+}
+
+pub async fn handle_list_bots(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<BotSummary>>, ApiError> {
+    let pool = state.read_pool().clone();
+    let summaries = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        list_bots(&mut conn).map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??;
+
+    Ok(Json(summaries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_reports_only_enabled_features_of_active_bots() {
+        let overrides = HashMap::from([(BotFeature::Kb, false)]);
+
+        let active = summarize(
+            (Uuid::new_v4(), "sales".into(), None, Some(true)),
+            &overrides,
+        );
+        assert!(active.active);
+        assert!(!active.features.contains(&BotFeature::Kb));
+        assert_eq!(
+            active.features.contains(&BotFeature::Calendar),
+            BotFeature::Calendar.compiled_in()
+        );
+
+        let inactive = summarize((Uuid::new_v4(), "old".into(), None, None), &overrides);
+        assert!(!inactive.active);
+        assert!(inactive.features.is_empty());
+    }
+}
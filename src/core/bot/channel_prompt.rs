@@ -0,0 +1,266 @@
+//! Per-channel reply rules, so answers fit where they are read: short plain text for SMS
+//! and WhatsApp, full Markdown on the web. The rules are added to the bot's system prompt
+//! and enforced on the answer when it is sent.
+//!
+//! Resolved from the bot's config for the inbound channel; without configuration a
+//! channel has no rules:
+//! - `channel-prompt-<channel>`: extra instructions appended to the base prompt
+//! - `channel-max-length-<channel>`: longest reply, in characters; `0` means no limit
+//! - `channel-markdown-<channel>`: whether the reply may use Markdown (default `true`)
+
+use super::normalize::strip_markdown;
+use crate::core::config::ConfigManager;
+use crate::core::shared::message_types::MessageType;
+use crate::core::shared::models::BotResponse;
+use log::warn;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// Responses buffered between the pipeline and the enforcing task.
+const OUTPUT_BUFFER: usize = 100;
+/// Appended to a reply cut at the length limit.
+const ELLIPSIS: char = '…';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelPrompt {
+    pub instructions: Option<String>,
+    pub max_length: Option<usize>,
+    pub markdown: bool,
+}
+
+impl Default for ChannelPrompt {
+    fn default() -> Self {
+        Self {
+            instructions: None,
+            max_length: None,
+            markdown: true,
+        }
+    }
+}
+
+impl ChannelPrompt {
+    /// Reads the rules for `channel` through `lookup`. Invalid values are logged and
+    /// ignored.
+    pub fn from_lookup(channel: &str, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let mut prompt = Self::default();
+        let get = |key: &str| {
+            lookup(&format!("{key}-{channel}"))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        if let Some(instructions) = get("channel-prompt") {
+            prompt.instructions = Some(instructions);
+        }
+        if let Some(value) = get("channel-max-length") {
+            match value.parse::<usize>() {
+                Ok(0) => prompt.max_length = None,
+                Ok(n) => prompt.max_length = Some(n),
+                Err(_) => warn!("Ignoring invalid channel-max-length-{channel} {value:?}"),
+            }
+        }
+        if let Some(value) = get("channel-markdown") {
+            match value.to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" => prompt.markdown = true,
+                "false" | "0" | "no" => prompt.markdown = false,
+                _ => warn!("Ignoring invalid channel-markdown-{channel} {value:?}"),
+            }
+        }
+        prompt
+    }
+
+    /// The bot's overrides for `channel`. Blocking.
+    pub fn for_channel(config: &ConfigManager, bot_id: Uuid, channel: &str) -> Self {
+        Self::from_lookup(channel, |key| config.get_config(&bot_id, key, None).ok())
+    }
+
+    /// `base` with this channel's rules appended; unchanged when there are none.
+    pub fn apply(&self, base: &str) -> String {
+        let mut rules = Vec::new();
+        if !self.markdown {
+            rules.push(
+                "Write plain text only: no Markdown headings, tables, bold or italic markers, or code fences."
+                    .to_string(),
+            );
+        }
+        if let Some(max) = self.max_length {
+            rules.push(format!("Keep each reply under {max} characters."));
+        }
+        if let Some(instructions) = &self.instructions {
+            rules.push(instructions.clone());
+        }
+        if rules.is_empty() {
+            return base.to_string();
+        }
+        format!("{}\n\n## Reply format\n{}", base.trim_end(), rules.join("\n"))
+    }
+
+    /// Whether answers must be rewritten before they are sent.
+    pub fn enforced(&self) -> bool {
+        self.max_length.is_some() || !self.markdown
+    }
+
+    /// `answer` as it may be sent: without Markdown when it is not allowed, and cut at the
+    /// last word that fits the length limit.
+    pub fn enforce(&self, answer: &str) -> String {
+        let text = if self.markdown {
+            answer.to_string()
+        } else {
+            strip_markdown(answer)
+        };
+        match self.max_length {
+            Some(max) => truncate(&text, max),
+            None => text,
+        }
+    }
+
+    /// A sender whose answers reach `tx` as [`Self::enforce`] makes them. Streamed text is
+    /// held until the answer is complete, then sent in one chunk; `tx` itself is returned
+    /// when there is nothing to enforce.
+    pub fn enforce_output(&self, tx: mpsc::Sender<BotResponse>) -> mpsc::Sender<BotResponse> {
+        if !self.enforced() {
+            return tx;
+        }
+        let (enforced_tx, mut rx) = mpsc::channel::<BotResponse>(OUTPUT_BUFFER);
+        let rules = self.clone();
+        tokio::spawn(async move {
+            let mut answer = String::new();
+            let mut last_text: Option<BotResponse> = None;
+            while let Some(mut response) = rx.recv().await {
+                if response.message_type == MessageType::BOT_RESPONSE {
+                    answer.push_str(&response.content);
+                    response.content = String::new();
+                    if !response.is_complete {
+                        last_text = Some(response);
+                        continue;
+                    }
+                    response.content = rules.enforce(&answer);
+                    answer.clear();
+                } else if let Some(mut held) = last_text.take().filter(|_| !answer.is_empty()) {
+                    // Text before another kind of message goes out first
+                    held.content = rules.enforce(&answer);
+                    answer.clear();
+                    if tx.send(held).await.is_err() {
+                        return;
+                    }
+                }
+                if tx.send(response).await.is_err() {
+                    return;
+                }
+            }
+            if let Some(mut held) = last_text.filter(|_| !answer.is_empty()) {
+                held.content = rules.enforce(&answer);
+                let _ = tx.send(held).await;
+            }
+        });
+        enforced_tx
+    }
+}
+
+/// `text` in at most `max` characters, cut at a word boundary when one is close enough
+/// and marked with an ellipsis.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let kept: String = text.chars().take(max.saturating_sub(1)).collect();
+    let cut = match kept.rfind(char::is_whitespace) {
+        Some(at) if at >= kept.len() / 2 => &kept[..at],
+        _ => kept.as_str(),
+    };
+    format!("{}{ELLIPSIS}", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const BASE: &str = "You are the Acme support assistant.";
+
+    fn config(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_whatsapp_and_web_assemble_different_prompts() {
+        let config = config(&[
+            ("channel-prompt-whatsapp", "Answer in one or two sentences."),
+            ("channel-max-length-whatsapp", "500"),
+            ("channel-markdown-whatsapp", "false"),
+        ]);
+        let lookup = |key: &str| config.get(key).cloned();
+
+        let web = ChannelPrompt::from_lookup("web", lookup).apply(BASE);
+        let whatsapp = ChannelPrompt::from_lookup("whatsapp", lookup).apply(BASE);
+
+        assert_eq!(web, BASE);
+        assert_ne!(web, whatsapp);
+        assert!(whatsapp.starts_with(BASE));
+        assert!(whatsapp.contains("no Markdown"));
+        assert!(whatsapp.contains("under 500 characters"));
+        assert!(whatsapp.ends_with("Answer in one or two sentences."));
+    }
+
+    #[test]
+    fn test_overrides_replace_channel_defaults() {
+        let config = config(&[
+            ("channel-markdown-sms", "yes"),
+            ("channel-max-length-sms", "0"),
+            ("channel-markdown-web", "false"),
+            ("channel-max-length-telegram", "lots"),
+        ]);
+        let lookup = |key: &str| config.get(key).cloned();
+
+        assert_eq!(ChannelPrompt::from_lookup("sms", lookup).apply(BASE), BASE);
+        assert!(!ChannelPrompt::from_lookup("web", lookup).markdown);
+        assert_eq!(
+            ChannelPrompt::from_lookup("telegram", lookup).max_length,
+            None
+        );
+        assert!(!ChannelPrompt::from_lookup("whatsapp", lookup).enforced());
+    }
+
+    fn chunk(content: &str, is_complete: bool) -> BotResponse {
+        BotResponse {
+            bot_id: String::new(),
+            user_id: String::new(),
+            session_id: String::new(),
+            channel: "sms".to_string(),
+            content: content.to_string(),
+            message_type: MessageType::BOT_RESPONSE,
+            stream_token: None,
+            is_complete,
+            suggestions: Vec::new(),
+            context_name: None,
+            context_length: 0,
+            context_max_length: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sent_answer_follows_the_rules() {
+        let rules = ChannelPrompt {
+            instructions: None,
+            max_length: Some(24),
+            markdown: false,
+        };
+        let (tx, mut rx) = mpsc::channel(OUTPUT_BUFFER);
+        let enforced = rules.enforce_output(tx);
+        enforced.send(chunk("Your **order** ships", false)).await.unwrap();
+        enforced.send(chunk(" tomorrow morning.", false)).await.unwrap();
+        enforced.send(chunk("", true)).await.unwrap();
+        drop(enforced);
+
+        let mut sent = String::new();
+        while let Some(response) = rx.recv().await {
+            sent.push_str(&response.content);
+        }
+        assert_eq!(sent, "Your order ships…");
+        assert!(sent.chars().count() <= 24);
+        assert_eq!(ChannelPrompt::default().enforce("**hi**"), "**hi**");
+    }
+}
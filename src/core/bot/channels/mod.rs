@@ -1,4 +1,6 @@
 pub mod instagram;
+pub mod pacing;
+pub mod rich;
 pub mod teams;
 pub mod telegram;
 pub mod whatsapp;
@@ -6,6 +8,7 @@ pub mod whatsapp_queue;
 pub mod whatsapp_rate_limiter;
 
 use crate::core::shared::models::BotResponse;
+use rich::RichResponse;
 use async_trait::async_trait;
 use log::{debug, info};
 use std::collections::HashMap;
@@ -26,6 +29,23 @@ pub trait ChannelAdapter: Send + Sync {
         response: BotResponse,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
 
+    /// Sends a response with attachments. Adapters render what their channel supports;
+    /// by default every attachment is sent as its text fallback.
+    async fn send_rich(
+        &self,
+        rich: RichResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.send_message(rich.into_text()).await
+    }
+
+    /// Shows the recipient that a reply is being typed, where the channel supports it.
+    async fn send_typing(
+        &self,
+        _recipient: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
     async fn receive_message(
         &self,
         _payload: serde_json::Value,
@@ -43,9 +63,15 @@ pub trait ChannelAdapter: Send + Sync {
         }))
     }
 }
+/// Prefix of the `stream_token` that marks a queued rich response; see
+/// [`WebChannelAdapter::frame`].
+const RICH_TOKEN_PREFIX: &str = "rich:";
+
 #[derive(Debug)]
 pub struct WebChannelAdapter {
     connections: Arc<Mutex<HashMap<String, mpsc::Sender<BotResponse>>>>,
+    /// Rich responses waiting for their marker to reach the websocket, by session and token.
+    rich: Arc<Mutex<HashMap<String, HashMap<String, RichResponse>>>>,
 }
 impl Default for WebChannelAdapter {
     fn default() -> Self {
@@ -57,6 +83,7 @@ impl WebChannelAdapter {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(Mutex::new(HashMap::new())),
+            rich: Arc::new(Mutex::new(HashMap::new())),
         }
     }
     pub async fn add_connection(&self, session_id: String, tx: mpsc::Sender<BotResponse>) {
@@ -64,6 +91,51 @@ impl WebChannelAdapter {
     }
     pub async fn remove_connection(&self, session_id: &str) {
         self.connections.lock().await.remove(session_id);
+        self.rich.lock().await.remove(session_id);
+    }
+    /// Queues a marker through the session's ordered channel; the websocket writes
+    /// the response with its attachments as one JSON frame when the marker comes up.
+    pub async fn send_rich_to_session(
+        &self,
+        session_id: &str,
+        rich: RichResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let token = format!("{RICH_TOKEN_PREFIX}{}", uuid::Uuid::new_v4());
+        let mut marker = rich.response.clone();
+        marker.stream_token = Some(token.clone());
+        self.rich
+            .lock()
+            .await
+            .entry(session_id.to_string())
+            .or_default()
+            .insert(token.clone(), rich);
+        if let Err(e) = self.send_message_to_session(session_id, marker).await {
+            if let Some(pending) = self.rich.lock().await.get_mut(session_id) {
+                pending.remove(&token);
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+    /// Serializes `response` for the websocket, swapping a rich marker for the
+    /// rich response it stands for.
+    pub async fn frame(&self, session_id: &str, response: &BotResponse) -> serde_json::Result<String> {
+        if let Some(token) = response
+            .stream_token
+            .as_deref()
+            .filter(|token| token.starts_with(RICH_TOKEN_PREFIX))
+        {
+            let rich = self
+                .rich
+                .lock()
+                .await
+                .get_mut(session_id)
+                .and_then(|pending| pending.remove(token));
+            if let Some(rich) = rich {
+                return serde_json::to_string(&rich);
+            }
+        }
+        serde_json::to_string(response)
     }
     pub async fn send_message_to_session(
         &self,
@@ -110,6 +182,14 @@ impl ChannelAdapter for WebChannelAdapter {
         }
         Ok(())
     }
+
+    async fn send_rich(
+        &self,
+        rich: RichResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let session_id = rich.response.session_id.clone();
+        self.send_rich_to_session(&session_id, rich).await
+    }
 }
 #[derive(Debug)]
 pub struct VoiceAdapter {
@@ -0,0 +1,186 @@
+//! Human-like pacing for outbound replies: a typing indicator, then a pause that grows
+//! with the length of the reply, before the reply is sent.
+//!
+//! Off by default. Read from the bot's config, where a `-<channel>` suffix overrides the
+//! bot-wide value:
+//! - `message-pacing`: `true` to enable
+//! - `message-pacing-ms-per-char`: pause per character of the reply
+//! - `message-pacing-min-ms` / `message-pacing-max-ms`: bounds for the pause
+
+use crate::core::bot::channels::ChannelAdapter;
+use crate::core::config::ConfigManager;
+use crate::core::shared::models::BotResponse;
+use log::{debug, error, warn};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+const DEFAULT_MS_PER_CHAR: u64 = 30;
+const DEFAULT_MIN_MS: u64 = 800;
+const DEFAULT_MAX_MS: u64 = 6000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessagePacing {
+    pub enabled: bool,
+    pub ms_per_char: u64,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl Default for MessagePacing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ms_per_char: DEFAULT_MS_PER_CHAR,
+            min: Duration::from_millis(DEFAULT_MIN_MS),
+            max: Duration::from_millis(DEFAULT_MAX_MS),
+        }
+    }
+}
+
+impl MessagePacing {
+    /// Reads the settings for `channel` through `lookup`. Invalid values are logged and
+    /// the default kept.
+    pub fn from_lookup(channel: &str, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let get = |key: &str| {
+            lookup(&format!("{key}-{channel}"))
+                .or_else(|| lookup(key))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let millis = |key: &str, default: u64| match get(key) {
+            Some(value) => value.parse::<u64>().unwrap_or_else(|_| {
+                warn!("Ignoring invalid {key} {value:?} for {channel}");
+                default
+            }),
+            None => default,
+        };
+
+        let enabled = get("message-pacing")
+            .is_some_and(|v| matches!(v.to_ascii_lowercase().as_str(), "true" | "1" | "yes"));
+        let min = millis("message-pacing-min-ms", DEFAULT_MIN_MS);
+        let max = millis("message-pacing-max-ms", DEFAULT_MAX_MS).max(min);
+        Self {
+            enabled,
+            ms_per_char: millis("message-pacing-ms-per-char", DEFAULT_MS_PER_CHAR),
+            min: Duration::from_millis(min),
+            max: Duration::from_millis(max),
+        }
+    }
+
+    /// The bot's settings for `channel`. Blocking.
+    pub fn for_channel(config: &ConfigManager, bot_id: Uuid, channel: &str) -> Self {
+        Self::from_lookup(channel, |key| config.get_config(&bot_id, key, None).ok())
+    }
+
+    /// Pause before sending `text`; zero when pacing is off.
+    pub fn delay_for(&self, text: &str) -> Duration {
+        if !self.enabled {
+            return Duration::ZERO;
+        }
+        let chars = text.chars().count() as u64;
+        Duration::from_millis(chars.saturating_mul(self.ms_per_char)).clamp(self.min, self.max)
+    }
+}
+
+/// Last paced send per recipient, so the next one starts after it.
+type PendingSends = Mutex<HashMap<String, (u64, JoinHandle<()>)>>;
+
+fn pending_sends() -> &'static PendingSends {
+    static PENDING: OnceLock<PendingSends> = OnceLock::new();
+    PENDING.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Sends `response` through `adapter`, paced when `pacing` is enabled. The pause runs on
+/// a spawned task, so the caller and other sessions are not held up; replies to the same
+/// recipient still go out in the order they were handed in.
+pub async fn send_paced(
+    adapter: Arc<dyn ChannelAdapter>,
+    pacing: &MessagePacing,
+    response: BotResponse,
+) {
+    if !pacing.enabled {
+        if let Err(e) = adapter.send_message(response).await {
+            error!("Failed to send {} message: {}", adapter.name(), e);
+        }
+        return;
+    }
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let key = format!("{}:{}", adapter.name(), response.user_id);
+    let delay = pacing.delay_for(&response.content);
+
+    let mut pending = pending_sends().lock().unwrap_or_else(|e| e.into_inner());
+    let previous = pending.remove(&key).map(|(_, handle)| handle);
+    let task_key = key.clone();
+    let handle = tokio::spawn(async move {
+        if let Some(previous) = previous {
+            let _ = previous.await;
+        }
+        if let Err(e) = adapter.send_typing(&response.user_id).await {
+            debug!("{} typing indicator failed: {}", adapter.name(), e);
+        }
+        tokio::time::sleep(delay).await;
+        if let Err(e) = adapter.send_message(response).await {
+            error!("Failed to send {} message: {}", adapter.name(), e);
+        }
+        let mut pending = pending_sends().lock().unwrap_or_else(|e| e.into_inner());
+        if pending
+            .get(&task_key)
+            .is_some_and(|(current, _)| *current == id)
+        {
+            pending.remove(&task_key);
+        }
+    });
+    pending.insert(key, (id, handle));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_is_proportional_and_clamped() {
+        let config: HashMap<&str, &str> = HashMap::from([
+            ("message-pacing", "true"),
+            ("message-pacing-ms-per-char", "20"),
+            ("message-pacing-min-ms", "500"),
+            ("message-pacing-max-ms", "3000"),
+            ("message-pacing-web", "false"),
+            ("message-pacing-max-ms-sms", "1000"),
+        ]);
+        let lookup = |key: &str| config.get(key).map(|v| v.to_string());
+
+        let whatsapp = MessagePacing::from_lookup("whatsapp", lookup);
+        assert_eq!(whatsapp.delay_for("hi"), Duration::from_millis(500));
+        assert_eq!(
+            whatsapp.delay_for(&"a".repeat(100)),
+            Duration::from_millis(2000)
+        );
+        assert_eq!(
+            whatsapp.delay_for(&"a".repeat(1000)),
+            Duration::from_millis(3000)
+        );
+        // Characters, not bytes.
+        assert_eq!(
+            whatsapp.delay_for(&"é".repeat(100)),
+            Duration::from_millis(2000)
+        );
+
+        let sms = MessagePacing::from_lookup("sms", lookup);
+        assert_eq!(sms.delay_for(&"a".repeat(100)), Duration::from_millis(1000));
+
+        let web = MessagePacing::from_lookup("web", lookup);
+        assert!(!web.enabled);
+        assert_eq!(web.delay_for(&"a".repeat(100)), Duration::ZERO);
+
+        assert_eq!(
+            MessagePacing::from_lookup("telegram", |_| None),
+            MessagePacing::default()
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Attachments on bot responses. A [`RichResponse`] is a [`BotResponse`] with images, files
+//! and buttons attached. Each [`ChannelAdapter`](super::ChannelAdapter) renders the
+//! attachments its channel supports and appends the text fallback of the others to the
+//! message, so nothing is silently dropped.
+
+use crate::core::shared::models::BotResponse;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseAttachment {
+    Image {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+    },
+    /// A file the user can download, such as a drive object behind a signed URL.
+    File {
+        url: String,
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mime_type: Option<String>,
+    },
+    Buttons {
+        buttons: Vec<ResponseButton>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseButton {
+    pub title: String,
+    /// Sent back as the user's message when the button is pressed.
+    pub value: String,
+}
+
+impl ResponseAttachment {
+    pub fn image(url: impl Into<String>, caption: Option<&str>) -> Self {
+        Self::Image {
+            url: url.into(),
+            caption: caption.map(str::to_string),
+        }
+    }
+
+    pub fn file(url: impl Into<String>, name: impl Into<String>) -> Self {
+        Self::File {
+            url: url.into(),
+            name: name.into(),
+            mime_type: None,
+        }
+    }
+
+    /// How the attachment reads on a text-only channel.
+    pub fn fallback_text(&self) -> String {
+        match self {
+            Self::Image {
+                url,
+                caption: Some(caption),
+            } => format!("{caption}: {url}"),
+            Self::Image { url, caption: None } => url.clone(),
+            Self::File { url, name, .. } => format!("{name}: {url}"),
+            Self::Buttons { buttons } => buttons
+                .iter()
+                .enumerate()
+                .map(|(i, button)| format!("{}. {}", i + 1, button.title))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// On the web channel this is sent as is: the usual response fields plus `attachments`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RichResponse {
+    #[serde(flatten)]
+    pub response: BotResponse,
+    pub attachments: Vec<ResponseAttachment>,
+}
+
+impl RichResponse {
+    pub fn new(response: BotResponse) -> Self {
+        Self {
+            response,
+            attachments: Vec::new(),
+        }
+    }
+
+    pub fn with_attachment(mut self, attachment: ResponseAttachment) -> Self {
+        self.attachments.push(attachment);
+        self
+    }
+
+    /// Keeps the attachments `renders` accepts and appends the fallback text of the rest
+    /// to the content.
+    pub fn degrade(mut self, renders: impl Fn(&ResponseAttachment) -> bool) -> Self {
+        let (kept, dropped): (Vec<_>, Vec<_>) = self.attachments.into_iter().partition(renders);
+        for attachment in dropped {
+            if !self.response.content.is_empty() {
+                self.response.content.push_str("\n\n");
+            }
+            self.response.content.push_str(&attachment.fallback_text());
+        }
+        self.attachments = kept;
+        self
+    }
+
+    /// The response as plain text, for channels that render no attachments.
+    pub fn into_text(self) -> BotResponse {
+        self.degrade(|_| false).response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bot::channels::{teams, whatsapp};
+    use crate::core::shared::models::MessageType;
+
+    fn response(content: &str) -> BotResponse {
+        BotResponse {
+            bot_id: "bot".to_string(),
+            user_id: "5511999990000".to_string(),
+            session_id: "session".to_string(),
+            channel: "web".to_string(),
+            content: content.to_string(),
+            message_type: MessageType::BOT_RESPONSE,
+            stream_token: None,
+            is_complete: true,
+            suggestions: Vec::new(),
+            context_name: None,
+            context_length: 0,
+            context_max_length: 0,
+        }
+    }
+
+    #[test]
+    fn test_image_attachment_per_channel() {
+        let url = "https://drive.example.com/default.gbai/chart.png";
+        let rich = RichResponse::new(response("Sales this month"))
+            .with_attachment(ResponseAttachment::image(url, Some("Chart")));
+
+        let web = serde_json::to_value(&rich).unwrap();
+        assert_eq!(web["content"], "Sales this month");
+        assert_eq!(
+            web["attachments"],
+            serde_json::json!([{"type": "image", "url": url, "caption": "Chart"}])
+        );
+
+        let media =
+            whatsapp::attachment_payload("5511999990000", &rich.attachments[0], "").unwrap();
+        assert_eq!(media["type"], "image");
+        assert_eq!(media["image"]["link"], url);
+        assert_eq!(media["image"]["caption"], "Chart");
+
+        let activity = serde_json::to_value(teams::rich_activity(&rich)).unwrap();
+        assert_eq!(activity["text"], "Sales this month");
+        let card = &activity["attachments"][0];
+        assert_eq!(card["contentType"], "application/vnd.microsoft.card.hero");
+        assert_eq!(card["content"]["images"][0]["url"], url);
+
+        // Telegram, Instagram and voice take the text fallback.
+        let text = rich.into_text();
+        assert_eq!(text.content, format!("Sales this month\n\nChart: {url}"));
+    }
+
+    #[test]
+    fn test_degrade_keeps_rendered_attachments() {
+        let buttons = ResponseAttachment::Buttons {
+            buttons: vec![
+                ResponseButton {
+                    title: "Yes".to_string(),
+                    value: "yes".to_string(),
+                },
+                ResponseButton {
+                    title: "No".to_string(),
+                    value: "no".to_string(),
+                },
+            ],
+        };
+        let rich = RichResponse::new(response("Confirm?"))
+            .with_attachment(ResponseAttachment::file(
+                "https://x/report.pdf",
+                "report.pdf",
+            ))
+            .with_attachment(buttons.clone())
+            .degrade(|a| matches!(a, ResponseAttachment::File { .. }));
+
+        assert_eq!(rich.attachments.len(), 1);
+        assert_eq!(rich.response.content, "Confirm?\n\n1. Yes\n2. No");
+        assert_eq!(
+            whatsapp::attachment_payload("1", &buttons, "Confirm?").unwrap()["interactive"]["type"],
+            "button"
+        );
+    }
+}
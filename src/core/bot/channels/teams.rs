@@ -3,6 +3,7 @@ use log::{error, info};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::core::bot::channels::rich::{ResponseAttachment, RichResponse};
 use crate::core::bot::channels::ChannelAdapter;
 use crate::core::config::ConfigManager;
 use crate::core::shared::models::BotResponse;
@@ -126,10 +127,10 @@ impl TeamsAdapter {
         let activity = TeamsActivity {
             activity_type: "message".to_string(),
             text: None,
-            attachments: Some(vec![TeamsAttachment {
-                content_type: "application/vnd.microsoft.card.adaptive".to_string(),
-                content: card,
-            }]),
+            attachments: Some(vec![TeamsAttachment::card(
+                "application/vnd.microsoft.card.adaptive",
+                card,
+            )]),
             ..Default::default()
         };
 
@@ -163,10 +164,10 @@ impl TeamsAdapter {
         let activity = TeamsActivity {
             activity_type: "message".to_string(),
             text: None,
-            attachments: Some(vec![TeamsAttachment {
-                content_type: "application/vnd.microsoft.card.hero".to_string(),
-                content: serde_json::to_value(hero_card)?,
-            }]),
+            attachments: Some(vec![TeamsAttachment::card(
+                "application/vnd.microsoft.card.hero",
+                serde_json::to_value(hero_card)?,
+            )]),
             ..Default::default()
         };
 
@@ -359,6 +360,38 @@ impl ChannelAdapter for TeamsAdapter {
         Ok(())
     }
 
+    async fn send_typing(
+        &self,
+        recipient: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let conversation_id = self.create_conversation(recipient).await?;
+        self.send_typing_indicator(&conversation_id).await
+    }
+
+    async fn send_rich(
+        &self,
+        rich: RichResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !self.is_configured() {
+            error!("Teams adapter not configured. Please set teams-app-id and teams-app-password in config.csv");
+            return Err("Teams not configured".into());
+        }
+
+        let conversation_id = self.create_conversation(&rich.response.user_id).await?;
+        let message_id = self
+            .send_teams_message(&conversation_id, rich_activity(&rich))
+            .await?;
+
+        info!(
+            "Teams message with {} attachment(s) sent to conversation {} (message_id: {})",
+            rich.attachments.len(),
+            conversation_id,
+            message_id
+        );
+
+        Ok(())
+    }
+
     async fn receive_message(
         &self,
         payload: serde_json::Value,
@@ -425,7 +458,24 @@ pub struct TeamsActivity {
 pub struct TeamsAttachment {
     #[serde(rename = "contentType")]
     pub content_type: String,
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
     pub content: serde_json::Value,
+    /// Where a file attachment is downloaded from; cards carry `content` instead.
+    #[serde(rename = "contentUrl", default, skip_serializing_if = "Option::is_none")]
+    pub content_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl TeamsAttachment {
+    pub fn card(content_type: &str, content: serde_json::Value) -> Self {
+        Self {
+            content_type: content_type.to_string(),
+            content,
+            content_url: None,
+            name: None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -482,6 +532,62 @@ pub struct TeamsConversationAccount {
     pub is_group: Option<bool>,
 }
 
+/// The activity for a rich response. Images become hero cards, files are attached by URL
+/// and buttons become suggested actions.
+pub fn rich_activity(rich: &RichResponse) -> TeamsActivity {
+    let hero_card = |card: TeamsHeroCard| {
+        TeamsAttachment::card(
+            "application/vnd.microsoft.card.hero",
+            serde_json::to_value(card).unwrap_or_default(),
+        )
+    };
+    let mut attachments = Vec::new();
+    let mut actions = Vec::new();
+    for attachment in &rich.attachments {
+        match attachment {
+            ResponseAttachment::Image { url, caption } => attachments.push(hero_card(TeamsHeroCard {
+                title: None,
+                subtitle: None,
+                text: caption.clone(),
+                images: vec![TeamsCardImage {
+                    url: url.clone(),
+                    alt: caption.clone(),
+                }],
+                buttons: None,
+            })),
+            ResponseAttachment::File {
+                url,
+                name,
+                mime_type,
+            } => attachments.push(TeamsAttachment {
+                content_type: mime_type
+                    .clone()
+                    .unwrap_or_else(|| "application/octet-stream".to_string()),
+                content: serde_json::Value::Null,
+                content_url: Some(url.clone()),
+                name: Some(name.clone()),
+            }),
+            ResponseAttachment::Buttons { buttons } => {
+                actions.extend(buttons.iter().map(|b| TeamsCardAction {
+                    action_type: "imBack".to_string(),
+                    title: b.title.clone(),
+                    value: Some(b.value.clone()),
+                    url: None,
+                }))
+            }
+        }
+    }
+
+    TeamsActivity {
+        activity_type: "message".to_string(),
+        text: Some(rich.response.content.clone()).filter(|t| !t.is_empty()),
+        attachments: Some(attachments).filter(|a| !a.is_empty()),
+        suggested_actions: Some(TeamsSuggestedActions { actions })
+            .filter(|s| !s.actions.is_empty()),
+        ..Default::default()
+    }
+}
+
 pub fn create_adaptive_card(
     title: &str,
     body: Vec<serde_json::Value>,
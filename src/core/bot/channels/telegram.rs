@@ -311,6 +311,15 @@ impl ChannelAdapter for TelegramAdapter {
         Ok(())
     }
 
+    async fn send_typing(
+        &self,
+        recipient: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let payload = serde_json::json!({ "chat_id": recipient, "action": "typing" });
+        self.send_telegram_request("sendChatAction", &payload).await?;
+        Ok(())
+    }
+
     async fn get_user_info(
         &self,
         user_id: &str,
@@ -3,16 +3,36 @@ use log::{error, info};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::core::bot::channels::rich::{ResponseAttachment, RichResponse};
 use crate::core::bot::channels::ChannelAdapter;
 use crate::core::bot::channels::whatsapp_queue::{QueuedWhatsAppMessage, WhatsAppMessageQueue};
 use crate::core::config::ConfigManager;
 use crate::core::shared::models::BotResponse;
+use crate::core::shared::outbound_proxy::client_builder;
 use crate::core::shared::state::AppState;
 use std::sync::Arc;
 
 /// Global WhatsApp message queue (shared across all adapters)
 static WHATSAPP_QUEUE: std::sync::OnceLock<Option<Arc<WhatsAppMessageQueue>>> = std::sync::OnceLock::new();
 
+/// How long an inbound message id is kept for the typing indicator.
+const INBOUND_TTL: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Last inbound message id per sender, with when it arrived. WhatsApp's typing
+/// indicator is attached to it.
+type InboundIds = std::collections::HashMap<String, (std::time::Instant, String)>;
+static LAST_INBOUND: std::sync::OnceLock<std::sync::Mutex<InboundIds>> = std::sync::OnceLock::new();
+
+/// Records the id of a message received from `from`, for [`ChannelAdapter::send_typing`].
+/// Ids older than [`INBOUND_TTL`] are dropped.
+pub fn remember_inbound_message(from: &str, message_id: &str) {
+    let map = LAST_INBOUND.get_or_init(Default::default);
+    let mut map = map.lock().unwrap_or_else(|e| e.into_inner());
+    let now = std::time::Instant::now();
+    map.retain(|_, (at, _)| now.duration_since(*at) < INBOUND_TTL);
+    map.insert(from.to_string(), (now, message_id.to_string()));
+}
+
 #[derive(Debug, Clone)]
 pub struct WhatsAppAdapter {
     api_key: String,
@@ -146,6 +166,7 @@ impl WhatsAppAdapter {
             api_key: self.api_key.clone(),
             phone_number_id: self.phone_number_id.clone(),
             api_version: self.api_version.clone(),
+            payload: None,
         };
 
         let queue = self.queue.ok_or_else(|| {
@@ -218,13 +239,6 @@ impl WhatsAppAdapter {
         media_type: &str,
         caption: Option<&str>,
     ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
-        let client = reqwest::Client::new();
-
-        let url = format!(
-            "https://graph.facebook.com/{}/{}/messages",
-            self.api_version, self.phone_number_id
-        );
-
         let media_object = match media_type {
             "image" | "video" => {
                 let mut obj = serde_json::json!({
@@ -257,24 +271,101 @@ impl WhatsAppAdapter {
             media_type: media_object
         });
 
+        self.enqueue_payload(to, payload).await
+    }
+
+    /// Queues a complete message payload behind the recipient's text messages, so media
+    /// and buttons arrive in the order they were sent.
+    async fn enqueue_payload(
+        &self,
+        to: &str,
+        payload: serde_json::Value,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let kind = payload["type"].as_str().unwrap_or("message").to_string();
+        let queued_msg = QueuedWhatsAppMessage {
+            to: to.to_string(),
+            message: format!("[{kind}]"),
+            api_key: self.api_key.clone(),
+            phone_number_id: self.phone_number_id.clone(),
+            api_version: self.api_version.clone(),
+            payload: Some(payload),
+        };
+
+        let queue = self.queue.ok_or_else(|| {
+            error!("WhatsApp queue not available (was initialization failed?)");
+            "WhatsApp queue not available"
+        })?;
+        queue
+            .enqueue(queued_msg)
+            .await
+            .map_err(|e| format!("Failed to enqueue WhatsApp {kind}: {e}"))?;
+
+        info!("WhatsApp {} enqueued for {}", kind, to);
+        Ok("queued".to_string())
+    }
+
+    /// Uploads `data` to WhatsApp and returns the media id to send it with.
+    pub async fn upload_media(
+        &self,
+        data: Vec<u8>,
+        file_name: &str,
+        mime_type: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let client = client_builder().build()?;
+        let url = format!(
+            "https://graph.facebook.com/{}/{}/media",
+            self.api_version, self.phone_number_id
+        );
+        let part = reqwest::multipart::Part::bytes(data)
+            .file_name(file_name.to_string())
+            .mime_str(mime_type)?;
+        let form = reqwest::multipart::Form::new()
+            .text("messaging_product", "whatsapp")
+            .part("file", part);
+
         let response = client
             .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&payload)
+            .bearer_auth(&self.api_key)
+            .multipart(form)
             .send()
             .await?;
-
-        if response.status().is_success() {
-            let result: serde_json::Value = response.json().await?;
-            Ok(result["messages"][0]["id"]
-                .as_str()
-                .unwrap_or("")
-                .to_string())
-        } else {
+        if !response.status().is_success() {
             let error_text = response.text().await?;
-            Err(format!("WhatsApp API error: {}", error_text).into())
+            return Err(format!("WhatsApp media upload failed: {}", error_text).into());
         }
+        let result: serde_json::Value = response.json().await?;
+        result["id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "WhatsApp media upload returned no id".into())
+    }
+
+    /// Uploads a file and sends it as a document, queued behind earlier messages.
+    pub async fn send_file(
+        &self,
+        to: &str,
+        data: Vec<u8>,
+        file_name: &str,
+        caption: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !self.is_configured() {
+            return Err("WhatsApp not configured".into());
+        }
+        let media_id = self
+            .upload_media(data, file_name, document_mime_type(file_name))
+            .await?;
+        let mut document = serde_json::json!({ "id": media_id, "filename": file_name });
+        if !caption.is_empty() {
+            document["caption"] = serde_json::Value::String(caption.to_string());
+        }
+        let payload = serde_json::json!({
+            "messaging_product": "whatsapp",
+            "to": to,
+            "type": "document",
+            "document": document
+        });
+        self.enqueue_payload(to, payload).await?;
+        Ok(())
     }
 
     pub async fn send_location_message(
@@ -723,6 +814,41 @@ impl ChannelAdapter for WhatsAppAdapter {
         !self.api_key.is_empty() && !self.phone_number_id.is_empty()
     }
 
+    /// Marks the sender's last message as read and shows the typing indicator, which
+    /// WhatsApp clears when the reply arrives or after 25 seconds.
+    async fn send_typing(
+        &self,
+        recipient: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let message_id = LAST_INBOUND
+            .get()
+            .and_then(|map| map.lock().unwrap_or_else(|e| e.into_inner()).remove(recipient))
+            .filter(|(at, _)| at.elapsed() < INBOUND_TTL);
+        let Some((_, message_id)) = message_id else {
+            return Ok(());
+        };
+        let url = format!(
+            "https://graph.facebook.com/{}/{}/messages",
+            self.api_version, self.phone_number_id
+        );
+        let response = client_builder()
+            .build()?
+            .post(&url)
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "messaging_product": "whatsapp",
+                "status": "read",
+                "message_id": message_id,
+                "typing_indicator": { "type": "text" }
+            }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(format!("typing indicator rejected: {}", response.status()).into());
+        }
+        Ok(())
+    }
+
     async fn send_message(
         &self,
         response: BotResponse,
@@ -769,6 +895,34 @@ impl ChannelAdapter for WhatsAppAdapter {
         Ok(())
     }
 
+    async fn send_rich(
+        &self,
+        rich: RichResponse,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !self.is_configured() {
+            return Err("WhatsApp not configured".into());
+        }
+        // Text and attachments share the queue, so they arrive in this order
+        let to = rich.response.user_id.clone();
+        let body = Self::sanitize_for_whatsapp(&rich.response.content);
+        let rich = rich.degrade(|a| attachment_payload(&to, a, &body).is_some());
+        // Buttons carry the text as their body, so it is not sent on its own.
+        let body = Self::sanitize_for_whatsapp(&rich.response.content);
+        let has_buttons = rich
+            .attachments
+            .iter()
+            .any(|a| matches!(a, ResponseAttachment::Buttons { .. }));
+        if !has_buttons && !body.is_empty() {
+            self.send_message(rich.response).await?;
+        }
+        for attachment in &rich.attachments {
+            if let Some(payload) = attachment_payload(&to, attachment, &body) {
+                self.enqueue_payload(&to, payload).await?;
+            }
+        }
+        Ok(())
+    }
+
     async fn receive_message(
         &self,
         payload: serde_json::Value,
@@ -1001,6 +1155,76 @@ pub fn create_interactive_buttons(text: &str, buttons: Vec<(&str, &str)>) -> ser
     })
 }
 
+/// MIME type of a document sent as a file, from its extension.
+fn document_mime_type(file_name: &str) -> &'static str {
+    let extension = file_name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("pdf") => "application/pdf",
+        Some("txt") => "text/plain",
+        Some("csv") => "text/csv",
+        Some("doc") => "application/msword",
+        Some("docx") => {
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+        }
+        Some("xls") => "application/vnd.ms-excel",
+        Some("xlsx") => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        Some("ppt") => "application/vnd.ms-powerpoint",
+        Some("pptx") => {
+            "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+        }
+        _ => "application/octet-stream",
+    }
+}
+
+/// Reply buttons allowed on one interactive message, and the length of their titles.
+const MAX_REPLY_BUTTONS: usize = 3;
+const MAX_BUTTON_TITLE: usize = 20;
+const MAX_INTERACTIVE_BODY: usize = 1024;
+
+/// The Graph API message for an attachment, or `None` when WhatsApp cannot show it and
+/// it should be sent as text. Buttons become an interactive message with `body` as its
+/// text.
+pub fn attachment_payload(
+    to: &str,
+    attachment: &ResponseAttachment,
+    body: &str,
+) -> Option<serde_json::Value> {
+    let (kind, content) = match attachment {
+        ResponseAttachment::Image { url, caption } => {
+            let mut image = serde_json::json!({ "link": url });
+            if let Some(caption) = caption {
+                image["caption"] = serde_json::Value::String(caption.clone());
+            }
+            ("image", image)
+        }
+        ResponseAttachment::File { url, name, .. } => (
+            "document",
+            serde_json::json!({ "link": url, "filename": name }),
+        ),
+        ResponseAttachment::Buttons { buttons } => {
+            let fits = !buttons.is_empty()
+                && buttons.len() <= MAX_REPLY_BUTTONS
+                && buttons.iter().all(|b| b.title.chars().count() <= MAX_BUTTON_TITLE)
+                && !body.is_empty()
+                && body.chars().count() <= MAX_INTERACTIVE_BODY;
+            if !fits {
+                return None;
+            }
+            let buttons = buttons
+                .iter()
+                .map(|b| (b.value.as_str(), b.title.as_str()))
+                .collect();
+            ("interactive", create_interactive_buttons(body, buttons))
+        }
+    };
+    Some(serde_json::json!({
+        "messaging_product": "whatsapp",
+        "to": to,
+        "type": kind,
+        kind: content
+    }))
+}
+
 pub type InteractiveListSections = Vec<(String, Vec<(String, String, Option<String>)>)>;
 
 pub fn create_interactive_list(
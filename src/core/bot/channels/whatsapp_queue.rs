@@ -18,6 +18,10 @@ pub struct QueuedWhatsAppMessage {
     pub api_key: String,
     pub phone_number_id: String,
     pub api_version: String,
+    /// A complete Graph API message, such as media or buttons, sent instead of `message`
+    /// as text. Queued with text so a recipient gets both in the order they were sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload: Option<serde_json::Value>,
 }
 
 #[derive(Debug)]
@@ -143,13 +147,15 @@ impl WhatsAppMessageQueue {
             msg.api_version, msg.phone_number_id
         );
 
-        let payload = serde_json::json!({
-            "messaging_product": "whatsapp",
-            "to": msg.to,
-            "type": "text",
-            "text": {
-                "body": msg.message
-            }
+        let payload = msg.payload.clone().unwrap_or_else(|| {
+            serde_json::json!({
+                "messaging_product": "whatsapp",
+                "to": msg.to,
+                "type": "text",
+                "text": {
+                    "body": msg.message
+                }
+            })
         });
 
         let response = client
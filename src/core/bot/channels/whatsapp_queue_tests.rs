@@ -14,6 +14,7 @@ mod tests {
             api_key: "test_key".to_string(),
             phone_number_id: "123456".to_string(),
             api_version: "v17.0".to_string(),
+            payload: None,
         };
 
         let result = queue.enqueue(msg).await;
@@ -33,6 +34,7 @@ mod tests {
             api_key: "test_key".to_string(),
             phone_number_id: "123456".to_string(),
             api_version: "v17.0".to_string(),
+            payload: None,
         };
 
         let msg2 = QueuedWhatsAppMessage {
@@ -41,6 +43,7 @@ mod tests {
             api_key: "test_key".to_string(),
             phone_number_id: "123456".to_string(),
             api_version: "v17.0".to_string(),
+            payload: None,
         };
 
         queue.enqueue(msg1).await.unwrap();
@@ -70,6 +73,7 @@ mod tests {
                 api_key: "test_key".to_string(),
                 phone_number_id: "123456".to_string(),
                 api_version: "v17.0".to_string(),
+                payload: None,
             };
             queue.enqueue(msg).await.unwrap();
         }
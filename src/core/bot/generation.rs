@@ -0,0 +1,271 @@
+//! In-flight LLM generations. Each streamed answer is registered under its session with a
+//! generation id, sent to the client as the `stream_token` of every chunk, so that the
+//! client can stop it mid-stream through `POST /api/sessions/:id/cancel` or a
+//! `{"type": "cancel"}` websocket message.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::extract::{Extension, Path};
+use axum::Json;
+use diesel::prelude::*;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, Mutex};
+use uuid::Uuid;
+
+/// Generations being streamed, by session id.
+pub type ActiveGenerations = Mutex<HashMap<String, ActiveGeneration>>;
+
+#[derive(Debug, Clone)]
+pub struct ActiveGeneration {
+    pub id: Uuid,
+    cancel: broadcast::Sender<()>,
+}
+
+impl ActiveGeneration {
+    pub fn new() -> (Self, broadcast::Receiver<()>) {
+        let (cancel, cancelled) = broadcast::channel(1);
+        (
+            Self {
+                id: Uuid::new_v4(),
+                cancel,
+            },
+            cancelled,
+        )
+    }
+
+    pub fn cancel(&self) {
+        let _ = self.cancel.send(());
+    }
+}
+
+/// Registers a new generation for the session. A generation still running there is
+/// cancelled, since only one answer streams per session.
+pub async fn start(
+    active: &ActiveGenerations,
+    session_id: Uuid,
+) -> (ActiveGeneration, broadcast::Receiver<()>) {
+    let (generation, cancelled) = ActiveGeneration::new();
+    let previous = active
+        .lock()
+        .await
+        .insert(session_id.to_string(), generation.clone());
+    if let Some(previous) = previous {
+        info!(
+            "Generation {} replaced by {} in session {}",
+            previous.id, generation.id, session_id
+        );
+        previous.cancel();
+    }
+    (generation, cancelled)
+}
+
+/// Cancels the session's generation and returns its id. With `generation_id`, only that
+/// generation is cancelled, so a stale request cannot stop a newer answer.
+pub async fn cancel(
+    active: &ActiveGenerations,
+    session_id: Uuid,
+    generation_id: Option<Uuid>,
+) -> Option<Uuid> {
+    let mut active = active.lock().await;
+    let key = session_id.to_string();
+    let current = active.get(&key)?.id;
+    if generation_id.is_some_and(|id| id != current) {
+        return None;
+    }
+    let generation = active.remove(&key)?;
+    generation.cancel();
+    Some(generation.id)
+}
+
+/// Unregisters a generation that ended, unless a newer one has replaced it.
+pub async fn finish(active: &ActiveGenerations, session_id: Uuid, generation_id: Uuid) {
+    let mut active = active.lock().await;
+    let key = session_id.to_string();
+    if active.get(&key).is_some_and(|g| g.id == generation_id) {
+        active.remove(&key);
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StreamEvent {
+    Chunk(String),
+    /// The provider closed the stream.
+    Ended,
+    Cancelled,
+}
+
+/// Waits for the next chunk from the provider. Cancellation takes precedence over chunks
+/// that are already buffered, so nothing more is relayed once it is requested.
+pub async fn next_chunk(
+    chunks: &mut mpsc::Receiver<String>,
+    cancelled: &mut broadcast::Receiver<()>,
+) -> StreamEvent {
+    tokio::select! {
+        biased;
+        _ = cancelled.recv() => StreamEvent::Cancelled,
+        chunk = chunks.recv() => chunk.map_or(StreamEvent::Ended, StreamEvent::Chunk),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ControlMessage {
+    #[serde(rename = "type")]
+    kind: String,
+    generation_id: Option<Uuid>,
+}
+
+/// Reads a `{"type": "cancel"}` websocket message, which may name the `generation_id` to
+/// stop. Anything else is left to the chat message parser.
+pub fn parse_cancel_message(text: &str) -> Option<CancelGenerationRequest> {
+    serde_json::from_str::<ControlMessage>(text)
+        .ok()
+        .filter(|msg| msg.kind == "cancel")
+        .map(|msg| CancelGenerationRequest {
+            generation_id: msg.generation_id,
+        })
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CancelGenerationRequest {
+    /// The `stream_token` of the answer to stop. Defaults to whatever is streaming.
+    pub generation_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CancelGenerationResponse {
+    pub session_id: Uuid,
+    pub generation_id: Uuid,
+}
+
+pub async fn handle_cancel_generation(
+    Extension(state): Extension<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(session_id): Path<Uuid>,
+    body: Option<Json<CancelGenerationRequest>>,
+) -> Result<Json<CancelGenerationResponse>, ApiError> {
+    if !user.is_authenticated() {
+        return Err(ApiError::unauthorized("Authentication required"));
+    }
+    let generation_id = body.and_then(|Json(req)| req.generation_id);
+
+    let pool = state.conn.clone();
+    let owner = tokio::task::spawn_blocking(move || {
+        use crate::core::shared::models::user_sessions::dsl::*;
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        user_sessions
+            .filter(id.eq(session_id))
+            .filter(deleted_at.is_null())
+            .select(user_id)
+            .first::<Uuid>(&mut conn)
+            .optional()
+            .map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??;
+    if owner.is_none() || (owner != Some(user.user_id) && !user.is_super_admin()) {
+        return Err(ApiError::not_found("Session not found"));
+    }
+
+    let cancelled = cancel(&state.active_streams, session_id, generation_id)
+        .await
+        .ok_or_else(|| ApiError::not_found("No generation in progress"))?;
+    info!(
+        "User {} cancelled generation {} in session {}",
+        user.user_id, cancelled, session_id
+    );
+    Ok(Json(CancelGenerationResponse {
+        session_id,
+        generation_id: cancelled,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancel_stops_further_tokens() {
+        let active = ActiveGenerations::default();
+        let session_id = Uuid::new_v4();
+        let (generation, mut cancelled) = start(&active, session_id).await;
+
+        let (tx, mut chunks) = mpsc::channel::<String>(100);
+        let provider = tokio::spawn(async move {
+            for i in 0.. {
+                if tx.send(format!("token{i} ")).await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        });
+
+        let mut received = Vec::new();
+        while received.len() < 3 {
+            match next_chunk(&mut chunks, &mut cancelled).await {
+                StreamEvent::Chunk(chunk) => received.push(chunk),
+                other => panic!("stream stopped early: {other:?}"),
+            }
+        }
+
+        assert_eq!(
+            cancel(&active, session_id, Some(Uuid::new_v4())).await,
+            None
+        );
+        assert_eq!(
+            cancel(&active, session_id, Some(generation.id)).await,
+            Some(generation.id)
+        );
+        // Tokens keep arriving from the provider, but none is relayed after the cancel.
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(
+            next_chunk(&mut chunks, &mut cancelled).await,
+            StreamEvent::Cancelled
+        );
+        assert_eq!(received, ["token0 ", "token1 ", "token2 "]);
+        assert!(active.lock().await.is_empty());
+
+        // Dropping the receiver, as the pipeline does when it aborts, ends the provider.
+        drop(chunks);
+        tokio::time::timeout(Duration::from_secs(1), provider)
+            .await
+            .expect("provider stopped")
+            .unwrap();
+    }
+
+    #[test]
+    fn test_parse_cancel_message() {
+        let id = Uuid::new_v4();
+        let msg = parse_cancel_message(&format!(r#"{{"type":"cancel","generation_id":"{id}"}}"#));
+        assert_eq!(msg.and_then(|m| m.generation_id), Some(id));
+        assert!(parse_cancel_message(r#"{"type":"cancel"}"#).is_some());
+        assert!(parse_cancel_message(r#"{"content":"hello","type":"text"}"#).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_new_generation_replaces_running_one() {
+        let active = ActiveGenerations::default();
+        let session_id = Uuid::new_v4();
+        let (first, mut first_cancelled) = start(&active, session_id).await;
+        let (second, _) = start(&active, session_id).await;
+
+        assert!(first_cancelled.try_recv().is_ok());
+        finish(&active, session_id, first.id).await;
+        assert_eq!(
+            active
+                .lock()
+                .await
+                .get(&session_id.to_string())
+                .map(|g| g.id),
+            Some(second.id)
+        );
+        finish(&active, session_id, second.id).await;
+        assert!(active.lock().await.is_empty());
+    }
+}
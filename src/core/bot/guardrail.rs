@@ -0,0 +1,613 @@
+//! Rules-based content filter applied to user input before it reaches the model and to
+//! model output before it reaches the user.
+//!
+//! Configured per bot in config.csv:
+//! - `guardrail-enabled` (default `false`)
+//! - `guardrail-categories`: built-in categories checked on both sides, separated by `;`
+//!   (`pii`, `credentials`)
+//! - `guardrail-input-patterns`, `guardrail-output-patterns`: case-insensitive regular
+//!   expressions separated by `;`
+//! - `guardrail-output-action`: `redact` (default) masks matches, `refuse` replaces the
+//!   whole answer
+//! - `guardrail-refusal`: reply sent instead of refused content; defaults to the translated
+//!   `chat-guardrail-refused` message
+//! - `guardrail-filter`: the [`ContentFilter`] to use; `rules` is the only built-in one
+//!
+//! Matching input is always refused. Streamed output is filtered over a window that holds
+//! back its last characters, so a match split across chunks is still caught. Decisions are
+//! logged with the rule names, never with the matched text.
+
+use crate::core::config::ConfigManager;
+use crate::core::i18n::{system_message, Locale};
+use crate::core::shared::message_types::MessageType;
+use crate::core::shared::models::BotResponse;
+use crate::core::shared::state::AppState;
+use crate::llm::redaction;
+use log::{info, warn};
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+const REDACTED: &str = "[REDACTED]";
+/// Responses buffered between the pipeline and the output filter.
+const OUTPUT_BUFFER: usize = 100;
+/// Characters of streamed output held back from the user until more text follows, so a
+/// match split across chunks is still seen whole. Longer matches are caught in the
+/// stored history, which is filtered as a whole.
+const OUTPUT_HOLDBACK: usize = 256;
+
+const CREDENTIAL_PATTERNS: &[&str] = &[
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----",
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"\bsk-[A-Za-z0-9_-]{20,}",
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    r"(?i)\b(?:password|passwd|pwd|senha)\s*[:=]\s*\S+",
+];
+
+static CACHE: LazyLock<RwLock<HashMap<Uuid, (Instant, Option<Arc<Guardrail>>)>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Input,
+    Output,
+}
+
+impl Stage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Input => "input",
+            Self::Output => "output",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    /// The matched parts were masked; the rest of the text passes.
+    Redact {
+        text: String,
+        rules: Vec<String>,
+    },
+    Refuse {
+        rules: Vec<String>,
+    },
+}
+
+/// Decides what happens to a piece of text. Implement this to plug in a classifier in
+/// place of [`RuleFilter`]. `inspect` runs for every streamed chunk, so it must be cheap.
+pub trait ContentFilter: Send + Sync {
+    fn inspect(&self, stage: Stage, text: &str) -> Verdict;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputAction {
+    #[default]
+    Redact,
+    Refuse,
+}
+
+enum Matcher {
+    Pattern(Regex),
+    /// Emails, card numbers and phone numbers, as masked in LLM logs.
+    Pii,
+}
+
+struct Rule {
+    name: String,
+    matcher: Matcher,
+}
+
+impl Rule {
+    fn mask<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match &self.matcher {
+            Matcher::Pattern(re) => re.replace_all(text, REDACTED),
+            Matcher::Pii => redaction::redact(text),
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match &self.matcher {
+            Matcher::Pattern(re) => re.is_match(text),
+            Matcher::Pii => self.mask(text) != text,
+        }
+    }
+}
+
+fn compile(pattern: &str) -> Option<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| warn!("Guardrail: ignoring invalid pattern {:?}: {}", pattern, e))
+        .ok()
+}
+
+fn category_rules(category: &str) -> Option<Vec<Rule>> {
+    let matchers = match category {
+        "pii" => vec![Matcher::Pii],
+        "credentials" => CREDENTIAL_PATTERNS
+            .iter()
+            .filter_map(|p| Regex::new(p).ok())
+            .map(Matcher::Pattern)
+            .collect(),
+        _ => return None,
+    };
+    Some(
+        matchers
+            .into_iter()
+            .map(|matcher| Rule {
+                name: category.to_string(),
+                matcher,
+            })
+            .collect(),
+    )
+}
+
+fn pattern_rules(stage: Stage, patterns: &[&str]) -> Vec<Rule> {
+    patterns
+        .iter()
+        .enumerate()
+        .filter_map(|(i, pattern)| {
+            Some(Rule {
+                name: format!("{}-pattern-{}", stage.as_str(), i + 1),
+                matcher: Matcher::Pattern(compile(pattern)?),
+            })
+        })
+        .collect()
+}
+
+/// The built-in filter: fixed categories plus operator patterns, matched with regular
+/// expressions.
+pub struct RuleFilter {
+    input: Vec<Rule>,
+    output: Vec<Rule>,
+    output_action: OutputAction,
+}
+
+impl RuleFilter {
+    /// Unknown categories and invalid patterns are logged and skipped.
+    pub fn new(
+        categories: &[&str],
+        input_patterns: &[&str],
+        output_patterns: &[&str],
+        output_action: OutputAction,
+    ) -> Self {
+        let mut input = pattern_rules(Stage::Input, input_patterns);
+        let mut output = pattern_rules(Stage::Output, output_patterns);
+        for category in categories {
+            // Rules hold compiled matchers, so each stage gets its own set.
+            let Some(for_input) = category_rules(category) else {
+                warn!("Guardrail: ignoring unknown category {:?}", category);
+                continue;
+            };
+            input.extend(for_input);
+            output.extend(category_rules(category).unwrap_or_default());
+        }
+        Self {
+            input,
+            output,
+            output_action,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty() && self.output.is_empty()
+    }
+}
+
+impl ContentFilter for RuleFilter {
+    fn inspect(&self, stage: Stage, text: &str) -> Verdict {
+        let rules = match stage {
+            Stage::Input => &self.input,
+            Stage::Output => &self.output,
+        };
+        let matched: Vec<&Rule> = rules.iter().filter(|r| r.is_match(text)).collect();
+        if matched.is_empty() {
+            return Verdict::Allow;
+        }
+        let mut names: Vec<String> = matched.iter().map(|r| r.name.clone()).collect();
+        names.dedup();
+        if stage == Stage::Input || self.output_action == OutputAction::Refuse {
+            return Verdict::Refuse { rules: names };
+        }
+        let text = matched
+            .iter()
+            .fold(text.to_string(), |text, rule| rule.mask(&text).into_owned());
+        Verdict::Redact { text, rules: names }
+    }
+}
+
+fn split_list(value: &str) -> Vec<&str> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+pub struct Guardrail {
+    bot_id: Uuid,
+    filter: Arc<dyn ContentFilter>,
+    refusal: Option<String>,
+}
+
+impl Guardrail {
+    pub fn new(bot_id: Uuid, filter: Arc<dyn ContentFilter>, refusal: Option<String>) -> Self {
+        Self {
+            bot_id,
+            filter,
+            refusal,
+        }
+    }
+
+    fn from_config(config: &ConfigManager, bot_id: Uuid) -> Option<Self> {
+        let get = |key: &str| {
+            config
+                .get_config(&bot_id, key, Some(""))
+                .unwrap_or_default()
+        };
+        if !matches!(get("guardrail-enabled").trim(), "true" | "1" | "yes") {
+            return None;
+        }
+
+        let filter: Arc<dyn ContentFilter> = match get("guardrail-filter").trim() {
+            "" | "rules" => {
+                let output_action = match get("guardrail-output-action").trim() {
+                    "refuse" => OutputAction::Refuse,
+                    _ => OutputAction::Redact,
+                };
+                let rules = RuleFilter::new(
+                    &split_list(&get("guardrail-categories")),
+                    &split_list(&get("guardrail-input-patterns")),
+                    &split_list(&get("guardrail-output-patterns")),
+                    output_action,
+                );
+                if rules.is_empty() {
+                    warn!("Guardrail enabled for bot {} without any rules", bot_id);
+                    return None;
+                }
+                Arc::new(rules)
+            }
+            other => {
+                warn!("Guardrail: unknown filter {:?} for bot {}", other, bot_id);
+                return None;
+            }
+        };
+        let refusal = Some(get("guardrail-refusal")).filter(|r| !r.trim().is_empty());
+        Some(Self::new(bot_id, filter, refusal))
+    }
+
+    /// The bot's guardrail, or `None` when it is not enabled. Blocking; cached for
+    /// `CACHE_TTL`.
+    pub fn for_bot(state: &AppState, bot_id: Uuid) -> Option<Arc<Self>> {
+        let cached = CACHE
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&bot_id)
+            .filter(|(at, _)| at.elapsed() < CACHE_TTL)
+            .map(|(_, guardrail)| guardrail.clone());
+        if let Some(guardrail) = cached {
+            return guardrail;
+        }
+
+        let config = ConfigManager::new(state.conn.clone());
+        let guardrail = Self::from_config(&config, bot_id).map(Arc::new);
+        CACHE
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(bot_id, (Instant::now(), guardrail.clone()));
+        guardrail
+    }
+
+    /// Inspects `text` and logs any decision other than allow.
+    pub fn check(&self, stage: Stage, session_id: Uuid, text: &str) -> Verdict {
+        let verdict = self.filter.inspect(stage, text);
+        let (action, rules) = match &verdict {
+            Verdict::Allow => return verdict,
+            Verdict::Redact { rules, .. } => ("redacted", rules),
+            Verdict::Refuse { rules } => ("refused", rules),
+        };
+        info!(
+            "Guardrail {} {} for bot {} session {}: rules {}",
+            action,
+            stage.as_str(),
+            self.bot_id,
+            session_id,
+            rules.join(", ")
+        );
+        verdict
+    }
+
+    pub fn refusal(&self, locale: &Locale) -> String {
+        self.refusal
+            .clone()
+            .unwrap_or_else(|| system_message(locale, "chat-guardrail-refused", &[]))
+    }
+
+    /// The answer as it should be stored in the history, without logging again.
+    pub fn filter_for_history(&self, text: &str, refusal: &str) -> String {
+        match self.filter.inspect(Stage::Output, text) {
+            Verdict::Allow => text.to_string(),
+            Verdict::Redact { text, .. } => text,
+            Verdict::Refuse { .. } => refusal.to_string(),
+        }
+    }
+
+    /// Filters the streamed text in `pending` and takes from it what can be sent now:
+    /// everything with `finish`, otherwise all but the last [`OUTPUT_HOLDBACK`] characters,
+    /// and nothing while a match may still cross that cut. `None` when refused.
+    fn release(&self, session_id: Uuid, pending: &mut String, finish: bool) -> Option<String> {
+        let whole = match self.filter.inspect(Stage::Output, pending) {
+            Verdict::Allow => None,
+            Verdict::Redact { text, .. } => Some(text),
+            Verdict::Refuse { .. } => {
+                self.check(Stage::Output, session_id, pending);
+                return None;
+            }
+        };
+        if finish {
+            let text = match whole {
+                Some(_) => self.filter_checked(session_id, pending)?,
+                None => pending.clone(),
+            };
+            pending.clear();
+            return Some(text);
+        }
+
+        let count = pending.chars().count();
+        if count <= OUTPUT_HOLDBACK {
+            return Some(String::new());
+        }
+        let cut = pending
+            .char_indices()
+            .nth(count - OUTPUT_HOLDBACK)
+            .map_or(pending.len(), |(i, _)| i);
+        let head = match whole {
+            None => pending[..cut].to_string(),
+            Some(whole) => {
+                let head = self.filter_checked(session_id, &pending[..cut])?;
+                // A match crossing the cut masks differently in the head alone; wait
+                // until the cut moves past it
+                if !whole.starts_with(&head) {
+                    return Some(String::new());
+                }
+                head
+            }
+        };
+        pending.drain(..cut);
+        Some(head)
+    }
+
+    /// `text` after the output filter, logging the decision. `None` when refused.
+    fn filter_checked(&self, session_id: Uuid, text: &str) -> Option<String> {
+        match self.check(Stage::Output, session_id, text) {
+            Verdict::Allow => Some(text.to_string()),
+            Verdict::Redact { text, .. } => Some(text),
+            Verdict::Refuse { .. } => None,
+        }
+    }
+
+    /// A sender whose responses pass through the output filter before reaching `tx`.
+    /// Streamed text is filtered over a sliding window (see [`Self::release`]), so chunks
+    /// reach `tx` slightly later and a match split across chunks is still caught. Once the
+    /// answer is refused, the refusal is sent and later chunks of the answer are sent
+    /// empty.
+    pub fn guard_output(
+        self: &Arc<Self>,
+        session_id: Uuid,
+        refusal: String,
+        tx: mpsc::Sender<BotResponse>,
+    ) -> mpsc::Sender<BotResponse> {
+        let (guarded_tx, mut rx) = mpsc::channel::<BotResponse>(OUTPUT_BUFFER);
+        let guardrail = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut pending = String::new();
+            let mut refused = false;
+            // Last text response, to flush held-back text in
+            let mut last_text: Option<BotResponse> = None;
+            while let Some(mut response) = rx.recv().await {
+                if response.message_type != MessageType::BOT_RESPONSE {
+                    let flushed = flush(
+                        &guardrail,
+                        session_id,
+                        &mut pending,
+                        &refusal,
+                        &mut refused,
+                        &last_text,
+                    );
+                    if let Some(flushed) = flushed {
+                        if tx.send(flushed).await.is_err() {
+                            return;
+                        }
+                    }
+                } else if refused {
+                    response.content = String::new();
+                } else {
+                    pending.push_str(&response.content);
+                    let released =
+                        guardrail.release(session_id, &mut pending, response.is_complete);
+                    response.content = match released {
+                        Some(text) => text,
+                        None => {
+                            refused = true;
+                            pending.clear();
+                            refusal.clone()
+                        }
+                    };
+                    last_text = Some(response.clone());
+                }
+                if tx.send(response).await.is_err() {
+                    return;
+                }
+            }
+            let flushed = flush(
+                &guardrail,
+                session_id,
+                &mut pending,
+                &refusal,
+                &mut refused,
+                &last_text,
+            );
+            if let Some(flushed) = flushed {
+                let _ = tx.send(flushed).await;
+            }
+        });
+        guarded_tx
+    }
+}
+
+/// Held-back text as a response of its own, sent before a response of another type or
+/// when the pipeline stops.
+fn flush(
+    guardrail: &Guardrail,
+    session_id: Uuid,
+    pending: &mut String,
+    refusal: &str,
+    refused: &mut bool,
+    last_text: &Option<BotResponse>,
+) -> Option<BotResponse> {
+    if pending.is_empty() || *refused {
+        return None;
+    }
+    let mut response = last_text.clone()?;
+    response.is_complete = false;
+    response.content = match guardrail.release(session_id, pending, true) {
+        Some(text) => text,
+        None => {
+            *refused = true;
+            pending.clear();
+            refusal.to_string()
+        }
+    };
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocked_input_is_refused() {
+        let filter = RuleFilter::new(
+            &["credentials"],
+            &[r"\bbuild (a|an) (bomb|explosive)"],
+            &[],
+            OutputAction::Redact,
+        );
+
+        assert_eq!(
+            filter.inspect(Stage::Input, "How do I BUILD A BOMB?"),
+            Verdict::Refuse {
+                rules: vec!["input-pattern-1".to_string()]
+            }
+        );
+        assert_eq!(
+            filter.inspect(Stage::Input, "my password: hunter2"),
+            Verdict::Refuse {
+                rules: vec!["credentials".to_string()]
+            }
+        );
+        assert_eq!(
+            filter.inspect(Stage::Input, "What is the weather today?"),
+            Verdict::Allow
+        );
+
+        let guardrail = Guardrail::new(
+            Uuid::nil(),
+            Arc::new(filter),
+            Some("I can't help with that.".to_string()),
+        );
+        assert!(matches!(
+            guardrail.check(Stage::Input, Uuid::nil(), "build an explosive"),
+            Verdict::Refuse { .. }
+        ));
+        assert_eq!(
+            guardrail.refusal(&Locale::default()),
+            "I can't help with that."
+        );
+    }
+
+    #[test]
+    fn test_blocked_output_is_redacted() {
+        let filter = RuleFilter::new(&["pii"], &[], &[r"project\s+falcon"], OutputAction::Redact);
+
+        match filter.inspect(
+            Stage::Output,
+            "Project Falcon launches soon; write to ana@example.com.",
+        ) {
+            Verdict::Redact { text, rules } => {
+                assert_eq!(text, "[REDACTED] launches soon; write to [EMAIL].");
+                assert_eq!(rules, vec!["output-pattern-1", "pii"]);
+            }
+            other => panic!("expected redaction, got {other:?}"),
+        }
+        assert_eq!(
+            filter.inspect(Stage::Output, "Nothing to hide here."),
+            Verdict::Allow
+        );
+        // Output patterns do not apply to input.
+        assert_eq!(
+            filter.inspect(Stage::Input, "tell me about project falcon"),
+            Verdict::Allow
+        );
+
+        let refusing = RuleFilter::new(&[], &[], &["falcon"], OutputAction::Refuse);
+        assert!(matches!(
+            refusing.inspect(Stage::Output, "falcon"),
+            Verdict::Refuse { .. }
+        ));
+        let guardrail = Guardrail::new(Uuid::nil(), Arc::new(refusing), None);
+        assert_eq!(guardrail.filter_for_history("falcon", "no"), "no");
+        assert_eq!(guardrail.filter_for_history("eagle", "no"), "eagle");
+    }
+
+    fn chunk(content: &str, is_complete: bool) -> BotResponse {
+        BotResponse {
+            bot_id: String::new(),
+            user_id: String::new(),
+            session_id: String::new(),
+            channel: "web".to_string(),
+            content: content.to_string(),
+            message_type: MessageType::BOT_RESPONSE,
+            stream_token: None,
+            is_complete,
+            suggestions: Vec::new(),
+            context_name: None,
+            context_length: 0,
+            context_max_length: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_match_split_across_chunks_is_redacted() {
+        let filter = RuleFilter::new(&[], &[], &[r"project\s+falcon"], OutputAction::Redact);
+        let guardrail = Arc::new(Guardrail::new(Uuid::nil(), Arc::new(filter), None));
+        let (tx, mut rx) = mpsc::channel(OUTPUT_BUFFER);
+        let guarded = guardrail.guard_output(Uuid::nil(), "no".to_string(), tx);
+
+        let filler = "a".repeat(OUTPUT_HOLDBACK);
+        for (content, done) in [(filler.as_str(), false), ("About project", false)] {
+            guarded.send(chunk(content, done)).await.unwrap();
+        }
+        guarded.send(chunk(" falcon.", true)).await.unwrap();
+        drop(guarded);
+
+        let mut streamed = String::new();
+        while let Some(response) = rx.recv().await {
+            streamed.push_str(&response.content);
+        }
+        assert_eq!(streamed, format!("{filler}About [REDACTED]."));
+    }
+
+    #[test]
+    fn test_unknown_category_and_bad_pattern_are_skipped() {
+        let filter = RuleFilter::new(&["astrology"], &["(unclosed"], &[], OutputAction::Redact);
+        assert!(filter.is_empty());
+    }
+}
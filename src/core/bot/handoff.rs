@@ -0,0 +1,436 @@
+//! Handing a conversation over to a human attendant.
+//!
+//! A session is escalated when the user's message contains one of the bot's handoff
+//! keywords, when the bot's answer contains one of its low-confidence phrases, or when a
+//! script calls TRANSFER TO HUMAN. Escalating sets `needs_human` in the session's
+//! `context_data` and notifies the attendant consoles. While it is set, the pipeline
+//! forwards the user's messages to the attendants before HEAR, direct tool calls or the
+//! model see them; resolving the conversation calls [`release`] and the bot answers again.
+//!
+//! Configured per bot in config.csv:
+//! - `handoff-keywords`: phrases in the user's message that ask for a human, separated
+//!   by `;`
+//! - `handoff-low-confidence`: phrases in the bot's answer that mean it could not help,
+//!   separated by `;`
+//! - `handoff-message`: reply sent when the session is escalated; defaults to the
+//!   translated `chat-handoff` message
+//!
+//! Phrases match whole words, ignoring case and punctuation.
+
+use crate::core::config::ConfigManager;
+use crate::core::i18n::{system_message, Locale};
+use crate::core::shared::message_bus::{publish_attendant_notification, MessageBus};
+use crate::core::shared::models::UserSession;
+use crate::core::shared::state::{AppState, AttendantNotification};
+use chrono::Utc;
+use diesel::prelude::*;
+use log::{error, info};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Priority of escalations without a TRANSFER TO HUMAN priority, on the same scale.
+pub const DEFAULT_PRIORITY: i32 = 1;
+/// How long a bot's handoff config is reused before it is read again.
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+static CACHE: LazyLock<RwLock<HashMap<Uuid, (Instant, HandoffConfig)>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandoffTrigger {
+    /// The user's message contained a handoff keyword.
+    Keyword,
+    /// The bot's answer contained a low-confidence phrase.
+    LowConfidence,
+    /// A script called TRANSFER TO HUMAN.
+    Bot,
+}
+
+impl HandoffTrigger {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::LowConfidence => "low_confidence",
+            Self::Bot => "bot",
+        }
+    }
+}
+
+/// Who handles an inbound message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    Bot,
+    /// Escalate, with the phrase that asked for it, and reply with the handoff message.
+    Escalate(HandoffTrigger, String),
+    /// The session is with the attendants: forward the message, the bot stays silent.
+    Attendant,
+}
+
+/// Lowercase words of `text` separated by single spaces, with a space on both ends.
+fn words(text: &str) -> String {
+    let mut out = String::from(" ");
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        out.push_str(&word.to_lowercase());
+        out.push(' ');
+    }
+    out
+}
+
+fn phrases(value: &str) -> Vec<String> {
+    value
+        .split(';')
+        .map(words)
+        .filter(|p| !p.trim().is_empty())
+        .collect()
+}
+
+fn find_phrase<'a>(phrases: &'a [String], text: &str) -> Option<&'a str> {
+    if phrases.is_empty() {
+        return None;
+    }
+    let text = words(text);
+    phrases
+        .iter()
+        .find(|p| text.contains(p.as_str()))
+        .map(|p| p.trim())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HandoffConfig {
+    keywords: Vec<String>,
+    low_confidence: Vec<String>,
+    message: Option<String>,
+}
+
+impl HandoffConfig {
+    pub fn new(keywords: &str, low_confidence: &str, message: Option<String>) -> Self {
+        Self {
+            keywords: phrases(keywords),
+            low_confidence: phrases(low_confidence),
+            message,
+        }
+    }
+
+    /// Blocking; cached for `CACHE_TTL`.
+    pub fn for_bot(state: &AppState, bot_id: Uuid) -> Self {
+        let cached = CACHE
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&bot_id)
+            .filter(|(at, _)| at.elapsed() < CACHE_TTL)
+            .map(|(_, config)| config.clone());
+        if let Some(config) = cached {
+            return config;
+        }
+
+        let config = Self::from_config(&ConfigManager::new(state.conn.clone()), bot_id);
+        CACHE
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(bot_id, (Instant::now(), config.clone()));
+        config
+    }
+
+    fn from_config(config: &ConfigManager, bot_id: Uuid) -> Self {
+        let get = |key: &str| {
+            config
+                .get_config(&bot_id, key, Some(""))
+                .unwrap_or_default()
+        };
+        let message = Some(get("handoff-message")).filter(|m| !m.trim().is_empty());
+        Self::new(
+            &get("handoff-keywords"),
+            &get("handoff-low-confidence"),
+            message,
+        )
+    }
+
+    pub fn route(&self, context: &Value, message: &str) -> Route {
+        if needs_human(context) {
+            return Route::Attendant;
+        }
+        match find_phrase(&self.keywords, message) {
+            Some(phrase) => Route::Escalate(HandoffTrigger::Keyword, phrase.to_string()),
+            None => Route::Bot,
+        }
+    }
+
+    /// The low-confidence phrase found in the bot's `answer`, if any.
+    pub fn low_confidence<'a>(&'a self, answer: &str) -> Option<&'a str> {
+        find_phrase(&self.low_confidence, answer)
+    }
+
+    pub fn message(&self, locale: &Locale) -> String {
+        self.message
+            .clone()
+            .unwrap_or_else(|| system_message(locale, "chat-handoff", &[]))
+    }
+}
+
+pub fn needs_human(context: &Value) -> bool {
+    context
+        .get("needs_human")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn ensure_object(context: &mut Value) {
+    if !context.is_object() {
+        *context = json!({});
+    }
+}
+
+/// Marks the session as waiting for an attendant. An attendant already assigned is kept.
+pub fn mark_escalated(context: &mut Value, trigger: HandoffTrigger, reason: &str) {
+    ensure_object(context);
+    let assigned = context.get("assigned_to").is_some_and(|a| !a.is_null());
+    context["needs_human"] = json!(true);
+    context["status"] = json!(if assigned { "assigned" } else { "queued" });
+    context["handoff_trigger"] = json!(trigger.as_str());
+    context["handoff_reason"] = json!(reason);
+    context["handoff_at"] = json!(Utc::now().to_rfc3339());
+}
+
+/// Gives the session back to the bot.
+pub fn release(context: &mut Value) {
+    ensure_object(context);
+    context["needs_human"] = json!(false);
+    context["handoff_released_at"] = json!(Utc::now().to_rfc3339());
+}
+
+/// The TRANSFER TO HUMAN priority of the session, or normal.
+pub fn priority(context: &Value) -> i32 {
+    context
+        .get("transfer_priority")
+        .and_then(Value::as_i64)
+        .map_or(DEFAULT_PRIORITY, |p| p as i32)
+}
+
+fn context_str(session: &UserSession, key: &str) -> Option<String> {
+    session
+        .context_data
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+pub fn notification(session: &UserSession, kind: &str, content: &str) -> AttendantNotification {
+    AttendantNotification {
+        notification_type: kind.to_string(),
+        session_id: session.id.to_string(),
+        user_id: session.user_id.to_string(),
+        user_name: context_str(session, "name"),
+        user_phone: context_str(session, "phone"),
+        channel: context_str(session, "channel").unwrap_or_else(|| "web".to_string()),
+        content: content.to_string(),
+        timestamp: Utc::now().to_rfc3339(),
+        assigned_to: context_str(session, "assigned_to"),
+        priority: priority(&session.context_data),
+    }
+}
+
+/// Changes the session's `context_data` under a row lock, so keys written concurrently by
+/// others are kept. Returns the new context. Blocking.
+pub fn update_context(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    change: impl FnOnce(&mut Value),
+) -> Result<Value, diesel::result::Error> {
+    try_update_context(conn, session_id, |context| {
+        change(context);
+        Ok::<(), diesel::result::Error>(())
+    })
+}
+
+/// [`update_context`] with a change that can refuse; nothing is written when it does.
+/// Two callers checking the same key cannot both see its old value.
+pub fn try_update_context<E: From<diesel::result::Error>>(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    change: impl FnOnce(&mut Value) -> Result<(), E>,
+) -> Result<Value, E> {
+    use crate::core::shared::models::schema::user_sessions;
+
+    conn.transaction(|conn| {
+        let mut context: Value = user_sessions::table
+            .find(session_id)
+            .select(user_sessions::context_data)
+            .for_update()
+            .first(conn)?;
+        change(&mut context)?;
+        diesel::update(user_sessions::table.find(session_id))
+            .set((
+                user_sessions::context_data.eq(&context),
+                user_sessions::updated_at.eq(Utc::now()),
+            ))
+            .execute(conn)?;
+        Ok(context)
+    })
+}
+
+/// Tells the attendants that `session` was escalated; `content` is the message that
+/// caused it.
+pub async fn announce(bus: &dyn MessageBus, session: &UserSession, content: &str) {
+    publish_attendant_notification(bus, &notification(session, "handoff", content)).await;
+}
+
+/// Sends a message of an escalated session to the attendants instead of the bot.
+pub async fn forward(bus: &dyn MessageBus, session: &UserSession, content: &str) {
+    publish_attendant_notification(bus, &notification(session, "new_message", content)).await;
+}
+
+/// When the session is with the attendants, saves `content` in its history and forwards it
+/// to them. Returns whether it did; the bot must then stay silent. Checked before anything
+/// else can answer the message: a waiting HEAR, a direct tool call or the LLM.
+pub async fn forward_if_escalated(
+    state: &Arc<AppState>,
+    session_id: Uuid,
+    user_id: Uuid,
+    content: &str,
+) -> bool {
+    let state_clone = Arc::clone(state);
+    let saved = content.to_string();
+    let session = tokio::task::spawn_blocking(
+        move || -> Result<Option<UserSession>, Box<dyn std::error::Error + Send + Sync>> {
+            let mut sm = state_clone.session_manager.blocking_lock();
+            let Some(session) = sm.get_session_by_id(session_id)? else {
+                return Ok(None);
+            };
+            if !needs_human(&session.context_data) {
+                return Ok(None);
+            }
+            if !saved.trim().is_empty() {
+                sm.save_message(session.id, user_id, 1, &saved, 1)?;
+            }
+            Ok(Some(session))
+        },
+    )
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r.map_err(|e| e.to_string()));
+    let session = match session {
+        Ok(Some(session)) => session,
+        Ok(None) => return false,
+        Err(e) => {
+            error!("Handoff check for session {} failed: {}", session_id, e);
+            return false;
+        }
+    };
+    if !content.trim().is_empty() {
+        forward(state.message_bus.as_ref(), &session, content).await;
+    }
+    true
+}
+
+/// Stores the handoff on the session and announces it. Returns the updated session.
+pub async fn escalate(
+    state: &AppState,
+    session: &UserSession,
+    trigger: HandoffTrigger,
+    reason: &str,
+    content: &str,
+) -> Result<UserSession, String> {
+    let conn = state.conn.clone();
+    let (session_id, reason) = (session.id, reason.to_string());
+    let context = tokio::task::spawn_blocking(move || {
+        let mut conn = conn.get().map_err(|e| e.to_string())?;
+        update_context(&mut conn, session_id, |ctx| {
+            mark_escalated(ctx, trigger, &reason)
+        })
+        .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let mut session = session.clone();
+    session.context_data = context;
+    info!(
+        "Session {} handed off to attendants ({})",
+        session.id,
+        trigger.as_str()
+    );
+    announce(state.message_bus.as_ref(), &session, content).await;
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::shared::message_bus::{InProcessBus, ATTENDANT_TOPIC};
+
+    fn session() -> UserSession {
+        UserSession {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            bot_id: Uuid::new_v4(),
+            title: "Support".to_string(),
+            context_data: json!({ "channel": "web", "name": "Ana" }),
+            current_tool: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_escalation_pauses_bot_and_notifies_attendants() {
+        let bus = InProcessBus::default();
+        let mut attendants = bus.subscribe(ATTENDANT_TOPIC).await.unwrap();
+        let config = HandoffConfig::new("talk to a human; agent", "I don't know", None);
+        let mut session = session();
+
+        assert_eq!(
+            config.route(&session.context_data, "Where is my agenda?"),
+            Route::Bot
+        );
+        let message = "Can I TALK to a human, please?";
+        let Route::Escalate(trigger, reason) = config.route(&session.context_data, message) else {
+            panic!("keyword did not escalate");
+        };
+        assert_eq!(
+            (trigger, reason.as_str()),
+            (HandoffTrigger::Keyword, "talk to a human")
+        );
+
+        // What `escalate` stores and announces.
+        mark_escalated(&mut session.context_data, trigger, &reason);
+        announce(&bus, &session, message).await;
+        let handoff = attendants.recv().await.unwrap().payload;
+        assert_eq!(handoff["type"], "handoff");
+        assert_eq!(handoff["content"], message);
+        assert_eq!(handoff["user_name"], "Ana");
+        assert_eq!(session.context_data["status"], "queued");
+
+        // The bot no longer answers; the attendants get the messages.
+        assert_eq!(
+            config.route(&session.context_data, "hello?"),
+            Route::Attendant
+        );
+        forward(&bus, &session, "hello?").await;
+        let forwarded = attendants.recv().await.unwrap().payload;
+        assert_eq!(forwarded["type"], "new_message");
+        assert_eq!(forwarded["session_id"], session.id.to_string());
+
+        release(&mut session.context_data);
+        assert_eq!(config.route(&session.context_data, "thanks"), Route::Bot);
+    }
+
+    #[test]
+    fn test_low_confidence_phrases_match_whole_words() {
+        let config = HandoffConfig::new("", "I don't know; not sure", None);
+        assert_eq!(
+            config.low_confidence("<p>Sorry, I DON'T know that.</p>"),
+            Some("i don t know")
+        );
+        assert_eq!(config.low_confidence("I'm notsure"), None);
+        assert_eq!(
+            HandoffConfig::default().route(&json!({}), "agent"),
+            Route::Bot
+        );
+    }
+}
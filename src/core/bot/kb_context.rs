@@ -7,7 +7,7 @@ use uuid::Uuid;
 
 use crate::core::kb::KnowledgeBaseManager;
 use crate::core::shared::utils::DbPool;
-use crate::core::kb::{EmbeddingConfig, KbIndexer, QdrantConfig};
+use crate::core::kb::{EmbeddingConfig, KbIndexer};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionKbAssociation {
@@ -250,25 +250,6 @@ impl KbContextManager {
         Ok(kb_contexts)
     }
 
-        async fn get_collection_dimension(&self, qdrant_config: &QdrantConfig, collection_name: &str) -> Result<Option<usize>> {
-            let http_client = crate::core::shared::utils::create_tls_client(Some(10));
-            let check_url = format!("{}/collections/{}", qdrant_config.url, collection_name);
-
-            let response = http_client.get(&check_url).send().await?;
-
-            if !response.status().is_success() {
-                debug!("Could not get collection info for '{}', using default dimension", collection_name);
-                return Ok(None);
-            }
-
-            let info_json: serde_json::Value = response.json().await?;
-            let dimension = info_json["result"]["config"]["params"]["vectors"]["size"]
-                .as_u64()
-                .map(|d| d as usize);
-
-            Ok(dimension)
-        }
-
         async fn search_single_collection(
             &self,
             collection_name: &str,
@@ -286,7 +267,7 @@ impl KbContextManager {
             let bot_id = self.get_bot_id_by_name(bot_name).await?;
 
             // Load embedding config from database for this bot
-            let mut embedding_config = EmbeddingConfig::from_bot_config(&self.db_pool, &bot_id);
+            let embedding_config = EmbeddingConfig::from_bot_config(&self.db_pool, &bot_id);
             let qdrant_config = if let Some(sm) = crate::core::shared::utils::get_secrets_manager_sync() {
                 let (url, api_key) = sm.get_vectordb_config_sync();
                 crate::core::kb::QdrantConfig {
@@ -298,26 +279,17 @@ impl KbContextManager {
                 crate::core::kb::QdrantConfig::default()
             };
 
-            // Query Qdrant to get the collection's actual vector dimension
-            let collection_dimension = self.get_collection_dimension(&qdrant_config, collection_name).await?;
-
-            // Override the embedding config dimension to match the collection
-            if let Some(dim) = collection_dimension {
-                if dim != embedding_config.dimensions {
-                    debug!(
-                        "Overriding embedding dimension from {} to {} to match collection '{}'",
-                        embedding_config.dimensions, dim, collection_name
-                    );
-                    embedding_config.dimensions = dim;
-                }
-            }
+            // The collection of the bot's current embedding model; a collection written
+            // with another model is refused by the search instead of queried with
+            // vectors it cannot compare
+            let collection_name = embedding_config.collection_name(collection_name);
 
             // Create a temporary indexer with bot-specific config
             let indexer = KbIndexer::new(embedding_config, qdrant_config);
 
         // Use the bot-specific indexer for search
         let search_results = indexer
-            .search(collection_name, query, max_results * 3)
+            .search(&collection_name, query, max_results * 3)
             .await?;
 
         let deduplicated = self.deduplicate_by_document(search_results);
@@ -353,6 +325,22 @@ impl KbContextManager {
         max_results: usize,
         max_tokens: usize,
     ) -> Result<KbContext> {
+        self.search_kb_with_candidates(bot_id, bot_name, kb_name, query, max_results, max_tokens)
+            .await
+            .map(|(_, context)| context)
+    }
+
+    /// Retrieval for one KB exactly as done for a prompt, also returning the raw search
+    /// results the context was selected from.
+    pub async fn search_kb_with_candidates(
+        &self,
+        bot_id: Uuid,
+        bot_name: &str,
+        kb_name: &str,
+        query: &str,
+        max_results: usize,
+        max_tokens: usize,
+    ) -> Result<(Vec<crate::core::kb::SearchResult>, KbContext)> {
         debug!("Searching KB '{}' with query: {}", kb_name, query);
 
         let search_results = self
@@ -360,14 +348,17 @@ impl KbContextManager {
             .search(bot_id, bot_name, kb_name, query, max_results * 3)
             .await?;
 
-        let deduplicated = self.deduplicate_by_document(search_results);
+        let deduplicated = self.deduplicate_by_document(search_results.clone());
         let kb_search_results = self.filter_by_tokens(deduplicated, max_tokens);
 
-        Ok(KbContext {
-            kb_name: kb_name.to_string(),
-            search_results: kb_search_results,
-            total_tokens: 0,
-        })
+        Ok((
+            search_results,
+            KbContext {
+                kb_name: kb_name.to_string(),
+                search_results: kb_search_results,
+                total_tokens: 0,
+            },
+        ))
     }
 
     fn deduplicate_by_document(&self, results: Vec<crate::core::kb::SearchResult>) -> Vec<crate::core::kb::SearchResult> {
@@ -438,41 +429,7 @@ impl KbContextManager {
     }
 
     pub fn build_context_string(&self, kb_contexts: &[KbContext]) -> String {
-        if kb_contexts.is_empty() {
-            return String::new();
-        }
-
-        let mut context_parts = vec!["\n--- Knowledge Base Context ---".to_string()];
-
-        for kb_context in kb_contexts {
-            if kb_context.search_results.is_empty() {
-                continue;
-            }
-
-            context_parts.push(format!(
-                "\n## From '{}':",
-                kb_context.kb_name
-            ));
-
-            for (idx, result) in kb_context.search_results.iter().enumerate() {
-                context_parts.push(format!(
-                    "\n### Result {} (relevance: {:.2}):\n{}",
-                    idx + 1,
-                    result.score,
-                    result.content
-                ));
-
-                if !result.document_path.is_empty() {
-                    context_parts.push(format!("Source: {}", result.document_path));
-                }
-            }
-        }
-
-        context_parts.push("\n--- End Knowledge Base Context ---\n".to_string());
-        let full_context = context_parts.join("\n");
-
-        // Truncate KB context to fit within token limits (max 8000 tokens for KB context)
-        crate::core::shared::utils::truncate_text_for_model(&full_context, "local", 8000)
+        format_kb_context(kb_contexts)
     }
 
     pub fn get_active_tools(&self, session_id: Uuid) -> Result<Vec<String>> {
@@ -500,6 +457,56 @@ fn estimate_tokens(text: &str) -> usize {
     text.len() / 4
 }
 
+/// Knowledge base section appended to the system prompt.
+pub fn format_kb_context(kb_contexts: &[KbContext]) -> String {
+    if kb_contexts.is_empty() {
+        return String::new();
+    }
+
+    let mut context_parts = vec!["\n--- Knowledge Base Context ---".to_string()];
+
+    for kb_context in kb_contexts {
+        if kb_context.search_results.is_empty() {
+            continue;
+        }
+
+        context_parts.push(format!(
+            "\n## From '{}':",
+            kb_context.kb_name
+        ));
+
+        for (idx, result) in kb_context.search_results.iter().enumerate() {
+            context_parts.push(format!(
+                "\n### Result {} (relevance: {:.2}):\n{}",
+                idx + 1,
+                result.score,
+                result.content
+            ));
+
+            if !result.document_path.is_empty() {
+                context_parts.push(format!("Source: {}", result.document_path));
+            }
+        }
+    }
+
+    context_parts.push("\n--- End Knowledge Base Context ---\n".to_string());
+    let full_context = context_parts.join("\n");
+
+    // Truncate KB context to fit within token limits (max 8000 tokens for KB context)
+    crate::core::shared::utils::truncate_text_for_model(&full_context, "local", 8000)
+}
+
+/// Removes UTF-16 surrogate code points, which cannot be encoded in UTF-8 requests.
+pub fn sanitize_context(context: &str) -> String {
+    context
+        .chars()
+        .filter(|c| {
+            let cp = *c as u32;
+            !(0xD800..=0xDBFF).contains(&cp) && !(0xDC00..=0xDFFF).contains(&cp)
+        })
+        .collect()
+}
+
 pub async fn inject_kb_context(
     kb_manager: Arc<KnowledgeBaseManager>,
     db_pool: DbPool,
@@ -530,13 +537,7 @@ pub async fn inject_kb_context(
     }
 
     // Sanitize context to remove UTF-16 surrogate characters that can't be encoded in UTF-8
-    let sanitized_context = context_string
-        .chars()
-        .filter(|c| {
-            let cp = *c as u32;
-            !(0xD800..=0xDBFF).contains(&cp) && !(0xDC00..=0xDFFF).contains(&cp)
-        })
-        .collect::<String>();
+    let sanitized_context = sanitize_context(&context_string);
 
     if sanitized_context.is_empty() {
         return Ok(());
@@ -17,14 +17,16 @@ use crate::llm::llm_models;
 use crate::llm::OpenAIClient;
 #[cfg(feature = "nvidia")]
 use crate::nvidia::get_system_metrics;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::message_types::MessageType;
 use crate::core::shared::models::{BotResponse, UserMessage, UserSession};
 use crate::core::shared::state::AppState;
+use crate::core::shared::ws_heartbeat::{reaped, HeartbeatConfig, Liveness};
 #[cfg(feature = "chat")]
 use crate::basic::keywords::add_suggestion::get_suggestions;
 use html2md::parse_html;
 
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
 use axum::{
     extract::{ws::WebSocketUpgrade, Extension, Path, Query, State},
     http::StatusCode,
@@ -42,7 +44,6 @@ use serde_json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tokio::sync::broadcast;
 use regex;
 #[cfg(feature = "drive")]
 #[cfg(feature = "drive")]
@@ -50,17 +51,40 @@ use tokio::sync::Mutex as AsyncMutex;
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 
+pub mod catalog;
+pub mod channel_prompt;
 pub mod channels;
+pub mod generation;
+#[cfg(feature = "llm")]
+pub mod guardrail;
+pub mod handoff;
+pub mod mount;
 pub mod multimedia;
+pub mod normalize;
+pub mod quick_replies;
 
 pub fn get_default_bot(conn: &mut PgConnection) -> (Uuid, String) {
     use crate::core::shared::models::schema::bots::dsl::*;
     use diesel::prelude::*;
 
-    // First try to get the bot named "default"
+    // A bot explicitly marked as default wins, then the bot named "default"
+    match bots
+        .filter(is_default.eq(true))
+        .filter(is_active.eq(true))
+        .filter(deleted_at.is_null())
+        .select((id, name))
+        .first::<(Uuid, String)>(conn)
+        .optional()
+    {
+        Ok(Some(found)) => return found,
+        Ok(None) => {}
+        Err(e) => warn!("Failed to query marked default bot: {}", e),
+    }
+
     match bots
         .filter(name.eq("default"))
         .filter(is_active.eq(true))
+        .filter(deleted_at.is_null())
         .select((id, name))
         .first::<(Uuid, String)>(conn)
         .optional()
@@ -71,6 +95,7 @@ pub fn get_default_bot(conn: &mut PgConnection) -> (Uuid, String) {
             // Fall back to first active bot
             match bots
                 .filter(is_active.eq(true))
+                .filter(deleted_at.is_null())
                 .select((id, name))
                 .first::<(Uuid, String)>(conn)
                 .optional()
@@ -100,6 +125,7 @@ pub fn get_bot_id_by_name(conn: &mut PgConnection, bot_name: &str) -> Result<Uui
 
     bots
         .filter(name.eq(bot_name))
+        .filter(deleted_at.is_null())
         .select(id)
         .first::<Uuid>(conn)
         .map_err(|e| format!("Bot '{}' not found: {}", bot_name, e))
@@ -244,10 +270,14 @@ impl BotOrchestrator {
         }
     }
 
-    pub fn mount_all_bots(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// Mounts every `.gbai` package found in the bot directories and records the per-bot
+    /// outcome for the mount status endpoint. A broken bot never stops the others.
+    pub fn mount_all_bots(&self) -> mount::MountReport {
         info!("Scanning drive for .gbai files to mount bots...");
 
-        let mut bots_mounted = 0;
+        let started_at = chrono::Utc::now();
+        let mut results = Vec::new();
+        let mut scan_errors = Vec::new();
 
         let directories_to_scan: Vec<std::path::PathBuf> = vec![
             self.state
@@ -268,64 +298,33 @@ impl BotOrchestrator {
                 continue;
             }
 
-            match self.scan_directory(&dir_path, &mut bots_mounted) {
-                Ok(()) => {}
+            match mount::mount_directory(&dir_path, |bot_name| {
+                self.ensure_bot_exists(bot_name).map_err(|e| e.to_string())
+            }) {
+                Ok(mut mounted) => results.append(&mut mounted),
                 Err(e) => {
                     error!("Failed to scan directory {}: {}", dir_path.display(), e);
+                    scan_errors.push(format!("{}: {}", dir_path.display(), e));
                 }
             }
         }
 
+        let report = mount::MountReport::new(started_at, results, scan_errors);
+        for failure in report.failures() {
+            error!(
+                "Failed to mount bot '{}' from {}: {}",
+                failure.bot_name,
+                failure.path,
+                failure.reason.as_deref().unwrap_or("unknown error")
+            );
+        }
         info!(
-            "BotServer ready - {} bots loaded",
-            bots_mounted
+            "BotServer ready - {} bots loaded, {} failed, {} skipped",
+            report.mounted, report.failed, report.skipped
         );
-        log::debug!(
-            "Bot mounting details: {} bots mounted",
-            bots_mounted
-        );
-
-        Ok(())
-    }
-
-    fn scan_directory(
-        &self,
-        dir_path: &std::path::Path,
-        bots_mounted: &mut i32,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let entries =
-            std::fs::read_dir(dir_path).map_err(|e| format!("Failed to read directory: {}", e))?;
-
-        for entry in entries.flatten() {
-            let name = entry.file_name();
-
-            let bot_name = match name.to_str() {
-                Some(n) if n.ends_with(".gbai") => n.trim_end_matches(".gbai"),
-                _ => continue,
-            };
-
-            info!("Found .gbai file: {}", bot_name);
-
-            match self.ensure_bot_exists(bot_name) {
-                Ok(true) => {
-                    info!("Bot '{}' already exists in database, mounting", bot_name);
-                    *bots_mounted += 1;
-                }
-                Ok(false) => {
-                    {
-                        info!(
-                            "Bot '{}' does not exist in database, skipping (run import to create)",
-                            bot_name
-                        );
-                    }
-                }
-                Err(e) => {
-                    error!("Failed to check if bot '{}' exists: {}", bot_name, e);
-                }
-            }
-        }
 
-        Ok(())
+        mount::store_report(&report);
+        report
     }
 
     fn ensure_bot_exists(
@@ -372,8 +371,51 @@ impl BotOrchestrator {
 
         let user_id = Uuid::parse_str(&message.user_id)?;
         let session_id = Uuid::parse_str(&message.session_id)?;
+
+        // Canonical inbound content, so nothing downstream depends on the channel's
+        // formatting. Direct tool invocations are passed on as sent.
+        let mut message = message;
+        let mut inbound = None;
+        let tool_call = message.message_type == MessageType::TOOL_EXEC
+            || message.content.starts_with("__TOOL__:");
+        if let (false, Ok(bot_uuid)) = (tool_call, Uuid::parse_str(&message.bot_id)) {
+            let state = self.state.clone();
+            let channel = message.channel.clone();
+            let steps = tokio::task::spawn_blocking(move || {
+                normalize::steps_for_channel(&state, bot_uuid, &channel)
+            })
+            .await
+            .unwrap_or_default();
+            let normalized = normalize::normalize(&message.content, &steps);
+            message.content = normalized.text.clone();
+            inbound = Some(normalized);
+        }
         let message_content = message.content.clone();
 
+        // A session handed off to a human attendant gets no automated answers, not even
+        // from a waiting HEAR or a direct tool call
+        if handoff::forward_if_escalated(&self.state, session_id, user_id, &message_content).await
+        {
+            let response = BotResponse {
+                bot_id: message.bot_id,
+                user_id: message.user_id,
+                session_id: message.session_id,
+                channel: message.channel,
+                content: String::new(),
+                message_type: MessageType::BOT_RESPONSE,
+                stream_token: None,
+                is_complete: true,
+                suggestions: Vec::new(),
+                context_name: None,
+                context_length: 0,
+                context_max_length: 0,
+            };
+            if let Err(e) = response_tx.send(response).await {
+                warn!("Failed to send handoff reply: {}", e);
+            }
+            return Ok(());
+        }
+
         // Handle direct tool execution via TOOL_EXEC message type (invisible to user)
         if message.message_type == MessageType::TOOL_EXEC {
             let tool_name = message_content.trim();
@@ -405,7 +447,19 @@ impl BotOrchestrator {
                 let response_content = if tool_result.success {
                     tool_result.result
                 } else {
-                    format!("Erro ao executar '{}': {}", tool_name, tool_result.error.unwrap_or_default())
+                    let locale = crate::core::i18n::chat_locale(
+                        &self.state,
+                        session_id,
+                        user_id,
+                        None,
+                    )
+                    .await;
+                    let error = tool_result.error.unwrap_or_default();
+                    crate::core::i18n::system_message(
+                        &locale,
+                        "chat-tool-failed",
+                        &[("tool", tool_name), ("error", &error)],
+                    )
                 };
 
                 // Direct tool execution — return result immediately, no LLM call
@@ -455,7 +509,19 @@ impl BotOrchestrator {
                 let response_content = if tool_result.success {
                     tool_result.result
                 } else {
-                    format!("Erro ao executar tool '{}': {}", tool_name, tool_result.error.unwrap_or_default())
+                    let locale = crate::core::i18n::chat_locale(
+                        &self.state,
+                        session_id,
+                        user_id,
+                        None,
+                    )
+                    .await;
+                    let error = tool_result.error.unwrap_or_default();
+                    crate::core::i18n::system_message(
+                        &locale,
+                        "chat-tool-failed",
+                        &[("tool", tool_name), ("error", &error)],
+                    )
                 };
                 
                 let final_response = BotResponse {
@@ -491,8 +557,9 @@ impl BotOrchestrator {
             return Ok(());
         }
 
-        let (session, context_data, history, model, key, system_prompt, bot_llm_url, explicit_llm_provider, bot_endpoint_path) = {
+        let (session, context_data, history, model, key, system_prompt, bot_llm_url, explicit_llm_provider, bot_endpoint_path, guardrail, input_refused, reply_rules) = {
             let state_clone = self.state.clone();
+            let channel = message.channel.clone();
             tokio::task::spawn_blocking(
                 move || -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
                     let mut session = {
@@ -501,6 +568,10 @@ impl BotOrchestrator {
                     }
                     .ok_or("Session not found")?;
 
+                    if let Some(inbound) = &inbound {
+                        normalize::store_on_session(&state_clone, &mut session, inbound)?;
+                    }
+
                     // Store WebSocket session_id in context for TALK routing
                     if let serde_json::Value::Object(ref mut map) = session.context_data {
                         map.insert("websocket_session_id".to_string(), serde_json::Value::String(session_id.to_string()));
@@ -510,7 +581,16 @@ impl BotOrchestrator {
                         session.context_data = serde_json::Value::Object(map);
                     }
 
-                    if !message.content.trim().is_empty() {
+                    // Refused input is neither answered nor kept in the history
+                    let guardrail = guardrail::Guardrail::for_bot(&state_clone, session.bot_id);
+                    let input_refused = guardrail.as_ref().is_some_and(|g| {
+                        matches!(
+                            g.check(guardrail::Stage::Input, session.id, &message.content),
+                            guardrail::Verdict::Refuse { .. }
+                        )
+                    });
+
+                    if !message.content.trim().is_empty() && !input_refused {
                         let mut sm = state_clone.session_manager.blocking_lock();
                         sm.save_message(session.id, user_id, 1, &message.content, 1)?;
                     }
@@ -588,9 +668,13 @@ impl BotOrchestrator {
                                 .unwrap_or_else(|_| "You are a helpful General Bots assistant.".to_string())
                         });
 
-                    info!("Loaded system-prompt for bot {}: {}", session.bot_id, system_prompt.chars().take(500).collect::<String>());
+                    let reply_rules = channel_prompt::ChannelPrompt::for_channel(&config_manager, session.bot_id, &channel);
+                    let system_prompt = reply_rules.apply(&system_prompt);
+
+                    info!("Loaded system-prompt for bot {}: {}", session.bot_id,
+                        crate::llm::redaction::for_log(&system_prompt.chars().take(500).collect::<String>()));
 
-                    Ok((session, context_data, history, model, key, system_prompt, bot_llm_url, explicit_llm_provider, bot_endpoint_path))
+                    Ok((session, context_data, history, model, key, system_prompt, bot_llm_url, explicit_llm_provider, bot_endpoint_path, guardrail, input_refused, reply_rules))
                 },
             )
             .await??
@@ -603,6 +687,108 @@ impl BotOrchestrator {
             messages.as_array().and_then(|a| a.first()).and_then(|m| m.get("role")));
 
         // Get bot name for KB and tool injection
+        let locale = crate::core::i18n::chat_locale(
+            &self.state,
+            session_id,
+            user_id,
+            Some(&session.context_data),
+        )
+        .await;
+
+        let output_refusal = guardrail.as_ref().map(|g| g.refusal(&locale));
+        if input_refused {
+            let refusal = BotResponse {
+                bot_id: message.bot_id,
+                user_id: message.user_id,
+                session_id: message.session_id,
+                channel: message.channel,
+                content: output_refusal.unwrap_or_default(),
+                message_type: MessageType::BOT_RESPONSE,
+                stream_token: None,
+                is_complete: true,
+                suggestions: Vec::new(),
+                context_name: None,
+                context_length: 0,
+                context_max_length: 0,
+            };
+            if let Err(e) = response_tx.send(refusal).await {
+                warn!("Failed to send guardrail refusal: {}", e);
+            }
+            return Ok(());
+        }
+
+        // Escalation by keyword; a session already with the attendants was forwarded above
+        let handoff_config = {
+            let state = self.state.clone();
+            let bot_id = session.bot_id;
+            tokio::task::spawn_blocking(move || handoff::HandoffConfig::for_bot(&state, bot_id))
+                .await
+                .unwrap_or_default()
+        };
+        let handoff_reply = match handoff_config.route(&session.context_data, &message_content) {
+            handoff::Route::Bot => None,
+            handoff::Route::Attendant => {
+                if !message_content.trim().is_empty() {
+                    handoff::forward(self.state.message_bus.as_ref(), &session, &message_content)
+                        .await;
+                }
+                Some(String::new())
+            }
+            handoff::Route::Escalate(trigger, reason) => {
+                match handoff::escalate(&self.state, &session, trigger, &reason, &message_content)
+                    .await
+                {
+                    Ok(_) => Some(handoff_config.message(&locale)),
+                    Err(e) => {
+                        error!("Handoff of session {} failed: {}", session.id, e);
+                        None
+                    }
+                }
+            }
+        };
+        if let Some(reply) = handoff_reply {
+            if !reply.is_empty() {
+                let state_for_save = self.state.clone();
+                let (session_id_for_save, reply_for_save) = (session.id, reply.clone());
+                let save_result = tokio::task::spawn_blocking(
+                    move || -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+                        let mut sm = state_for_save.session_manager.blocking_lock();
+                        sm.save_message(session_id_for_save, user_id, 2, &reply_for_save, 2)?;
+                        Ok(())
+                    },
+                )
+                .await;
+                if !matches!(save_result, Ok(Ok(()))) {
+                    error!("Failed to save handoff reply for session {}", session.id);
+                }
+            }
+            let response = BotResponse {
+                bot_id: message.bot_id,
+                user_id: message.user_id,
+                session_id: message.session_id,
+                channel: message.channel,
+                content: reply,
+                message_type: MessageType::BOT_RESPONSE,
+                stream_token: None,
+                is_complete: true,
+                suggestions: Vec::new(),
+                context_name: None,
+                context_length: 0,
+                context_max_length: 0,
+            };
+            if let Err(e) = response_tx.send(response).await {
+                warn!("Failed to send handoff reply: {}", e);
+            }
+            return Ok(());
+        }
+
+        // Channel rules apply to the answer as the guardrail lets it through
+        let response_tx = reply_rules.enforce_output(response_tx);
+        let response_tx = match (&guardrail, &output_refusal) {
+            (Some(g), Some(refusal)) => g.guard_output(session.id, refusal.clone(), response_tx),
+            _ => response_tx,
+        };
+
         let bot_name_for_context = {
             let conn = self.state.conn.get().ok();
             if let Some(mut db_conn) = conn {
@@ -747,8 +933,74 @@ impl BotOrchestrator {
                 return Ok(());
             }
 
-            // Inject KB context for normal messages
-            if let Some(kb_manager) = self.state.kb_manager.as_ref() {
+            // While a form is being filled, the form answers. Otherwise answer with the bot's
+            // canned response, without calling the LLM, on a trigger match.
+            let direct_reply = match crate::core::session::forms::handle_message(
+                &self.state,
+                session.bot_id,
+                session.id,
+                &message_content,
+            )
+            .await
+            {
+                Some(reply) => Some(reply),
+                None => {
+                    quick_replies::find_reply(&self.state, session.bot_id, &message_content).await
+                }
+            };
+            if let Some(reply) = direct_reply {
+                let state_for_save = self.state.clone();
+                let (session_id_for_save, reply_for_save) = (session.id, reply.clone());
+                let save_result = tokio::task::spawn_blocking(
+                    move || -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+                        let mut sm = state_for_save.session_manager.blocking_lock();
+                        sm.save_message(session_id_for_save, user_id, 2, &reply_for_save, 2)?;
+                        Ok(())
+                    },
+                )
+                .await;
+                if !matches!(save_result, Ok(Ok(()))) {
+                    error!("Failed to save direct reply for session {}", session.id);
+                }
+
+                #[cfg(feature = "chat")]
+                let suggestions = get_suggestions(
+                    self.state.cache.as_ref(),
+                    &message.bot_id,
+                    &message.session_id,
+                );
+                #[cfg(not(feature = "chat"))]
+                let suggestions: Vec<crate::core::shared::models::Suggestion> = Vec::new();
+
+                let final_response = BotResponse {
+                    bot_id: message.bot_id,
+                    user_id: message.user_id,
+                    session_id: message.session_id,
+                    channel: message.channel,
+                    content: reply,
+                    message_type: MessageType::BOT_RESPONSE,
+                    stream_token: None,
+                    is_complete: true,
+                    suggestions,
+                    context_name: None,
+                    context_length: 0,
+                    context_max_length: 0,
+                };
+
+                if let Err(e) = response_tx.send(final_response).await {
+                    warn!("Failed to send quick reply: {}", e);
+                }
+                return Ok(());
+            }
+
+            // Inject KB context for normal messages, unless KB is switched off for this bot
+            let kb_enabled = crate::core::shared::bot_features::is_enabled(
+                &self.state,
+                session.bot_id,
+                crate::core::shared::bot_features::BotFeature::Kb,
+            )
+            .await;
+            if let Some(kb_manager) = self.state.kb_manager.as_ref().filter(|_| kb_enabled) {
                 let context = crate::core::bot::kb_context::KbInjectionContext {
                     session_id,
                     bot_id: session.bot_id,
@@ -802,6 +1054,11 @@ impl BotOrchestrator {
             self.state.llm_provider.clone()
         };
 
+        // Wrap in the bot's fallback chain (a no-op pass-through when none is configured)
+        let primary_name = explicit_llm_provider.clone().unwrap_or_else(|| "primary".to_string());
+        let llm_chain = crate::llm::fallback::build_bot_chain(&self.state, &session.bot_id, &primary_name, llm).await;
+        let llm: std::sync::Arc<dyn crate::llm::LLMProvider> = llm_chain.clone();
+
         let model_clone = model.clone();
         let key_clone = key.clone();
 
@@ -821,6 +1078,54 @@ impl BotOrchestrator {
             }
         };
 
+        // Enforce the bot's max-context-tokens budget, if any
+        let budget = {
+            let config_manager = ConfigManager::new(self.state.conn.clone());
+            crate::llm::context::ContextBudget::from_lookup(|key| {
+                config_manager.get_bot_config_value(&session.bot_id, key).ok()
+            })
+        };
+        if let Some(budget) = budget {
+            let estimator = crate::llm::context::TokenEstimator::for_model(&model);
+            let summarizer = llm.clone();
+            let (summary_model, summary_key) = (model.clone(), key.clone());
+            let trimmed = budget
+                .enforce(&messages, &estimator, |transcript| async move {
+                    let prompt = serde_json::json!([{
+                        "role": "user",
+                        "content": format!(
+                            "Summarize this conversation in a few sentences, keeping facts, \
+                             names and open requests:\n\n{}",
+                            transcript
+                        )
+                    }]);
+                    match summarizer.generate("", &prompt, &summary_model, &summary_key).await {
+                        Ok(summary) => {
+                            let handler = crate::llm::llm_models::get_handler(&summary_model);
+                            Some(handler.process_content(&summary))
+                        }
+                        Err(e) => {
+                            warn!("Context overflow summary failed, dropping instead: {}", e);
+                            None
+                        }
+                    }
+                })
+                .await;
+            if trimmed.truncated() {
+                info!(
+                    "Context for session {} trimmed from {} to {} tokens \
+                     ({} messages dropped, {}, budget {})",
+                    session.id,
+                    trimmed.tokens_before,
+                    trimmed.tokens_after,
+                    trimmed.dropped,
+                    budget.strategy.as_str(),
+                    budget.max_tokens
+                );
+            }
+            messages = trimmed.messages;
+        }
+
         // Clone messages for the async task
         let messages_clone = messages.clone();
 
@@ -828,36 +1133,30 @@ impl BotOrchestrator {
         // #[cfg(feature = "drive")]
         // set_llm_streaming(true);
 
-    let stream_tx_clone = stream_tx.clone();
-
-    // Create cancellation channel for this streaming session
-    let (cancel_tx, mut cancel_rx) = broadcast::channel::<()>(1);
-    let session_id_str = session.id.to_string();
-
-    // Register this streaming session for potential cancellation
-    {
-        let mut active_streams = self.state.active_streams.lock().await;
-        active_streams.insert(session_id_str.clone(), cancel_tx);
-    }
-
-    // Wrap the LLM task in a JoinHandle so we can abort it
-    let mut cancel_rx_for_abort = cancel_rx.resubscribe();
-    let llm_task = tokio::spawn(async move {
-        if let Err(e) = llm
-        .generate_stream("", &messages_clone, stream_tx_clone, &model_clone, &key_clone, tools_for_llm.as_ref())
-        .await
-        {
-            error!("LLM streaming error: {}", e);
-        }
-    });
-
-        // Wait for cancellation to abort LLM task
-        tokio::spawn(async move {
-            if cancel_rx_for_abort.recv().await.is_ok() {
-                trace!("Aborting LLM task for session {}", session_id_str);
-                llm_task.abort();
+    // The provider task owns the only sender, so the stream ends when it finishes or is aborted
+    let stream_tx_clone = stream_tx;
+
+    // Register this generation for cancellation; its id is sent to the client as stream_token
+    let (generation, mut cancel_rx) =
+        generation::start(&self.state.active_streams, session.id).await;
+    let stream_token = Some(generation.id.to_string());
+
+    // Set by the cache when it answers instead of the model, so the call is not billed
+    let served_from_cache = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let prompt_tokens = crate::core::shared::utils::estimate_token_count(&messages_clone.to_string());
+
+    // Keep the JoinHandle so the provider request can be aborted
+    let llm_task = tokio::spawn(crate::llm::cache::with_cache_hit_flag(
+        Arc::clone(&served_from_cache),
+        async move {
+            if let Err(e) = llm
+            .generate_stream("", &messages_clone, stream_tx_clone, &model_clone, &key_clone, tools_for_llm.as_ref())
+            .await
+            {
+                error!("LLM streaming error: {}", e);
             }
-        });
+        },
+    ));
 
         let mut full_response = String::new();
         let mut analysis_buffer = String::new();
@@ -893,20 +1192,16 @@ impl BotOrchestrator {
             }
         }
 
-while let Some(chunk) = stream_rx.recv().await {
-    // Check if cancellation was requested (user sent new message)
-    match cancel_rx.try_recv() {
-        Ok(_) => {
+loop {
+    // Stop relaying as soon as the client cancels or a new message replaces this generation
+    let chunk = match generation::next_chunk(&mut stream_rx, &mut cancel_rx).await {
+        generation::StreamEvent::Chunk(chunk) => chunk,
+        generation::StreamEvent::Ended => break,
+        generation::StreamEvent::Cancelled => {
             info!("stream_exit: Cancelled for session {}", session.id);
             break;
         }
-        Err(broadcast::error::TryRecvError::Empty) => {}
-        Err(broadcast::error::TryRecvError::Closed) => {
-            info!("stream_exit: Cancel channel closed for session {}", session.id);
-            break;
-        }
-        Err(broadcast::error::TryRecvError::Lagged(_)) => {}
-    }
+    };
 
             chunk_count += 1;
             if chunk_count <= 3 || chunk_count % 50 == 0 {
@@ -966,7 +1261,7 @@ while let Some(chunk) = stream_rx.recv().await {
                                 channel: message.channel.clone(),
                                 content: regular_part.to_string(),
                                 message_type: MessageType::BOT_RESPONSE,
-                                stream_token: None,
+                                stream_token: stream_token.clone(),
                                 is_complete: false,
                                 suggestions: Vec::new(),
                                 context_name: None,
@@ -1029,7 +1324,7 @@ while let Some(chunk) = stream_rx.recv().await {
                         channel: message.channel.clone(),
                         content: execution_result.result,
                         message_type: MessageType::BOT_RESPONSE,
-                        stream_token: None,
+                        stream_token: stream_token.clone(),
                         is_complete: false,
                         suggestions: Vec::new(),
                         context_name: None,
@@ -1061,7 +1356,7 @@ while let Some(chunk) = stream_rx.recv().await {
                         channel: message.channel.clone(),
                         content: error_msg,
                         message_type: MessageType::BOT_RESPONSE,
-                        stream_token: None,
+                        stream_token: stream_token.clone(),
                         is_complete: false,
                         suggestions: Vec::new(),
                         context_name: None,
@@ -1097,7 +1392,7 @@ while let Some(chunk) = stream_rx.recv().await {
                     channel: message.channel.clone(),
                     content: tool_call_buffer.clone(),
                     message_type: MessageType::BOT_RESPONSE,
-                    stream_token: None,
+                    stream_token: stream_token.clone(),
                     is_complete: false,
                     suggestions: Vec::new(),
                     context_name: None,
@@ -1146,9 +1441,9 @@ while let Some(chunk) = stream_rx.recv().await {
                     user_id: message.user_id.clone(),
                     session_id: message.session_id.clone(),
                     channel: message.channel.clone(),
-                    content: "🤔 Pensando...".to_string(),
+                    content: crate::core::i18n::system_message(&locale, "chat-thinking", &[]),
                     message_type: MessageType::BOT_RESPONSE,
-                    stream_token: None,
+                    stream_token: stream_token.clone(),
                     is_complete: false,
                     suggestions: Vec::new(),
                     context_name: None,
@@ -1188,7 +1483,7 @@ while let Some(chunk) = stream_rx.recv().await {
                         channel: message.channel.clone(),
                         content: processed,
                         message_type: MessageType::BOT_RESPONSE,
-                        stream_token: None,
+                        stream_token: stream_token.clone(),
                         is_complete: false,
                         suggestions: Vec::new(),
                         context_name: None,
@@ -1247,7 +1542,7 @@ while let Some(chunk) = stream_rx.recv().await {
                         channel: message.channel.clone(),
                         content: content_to_send,
                         message_type: MessageType::BOT_RESPONSE,
-                        stream_token: None,
+                        stream_token: stream_token.clone(),
                         is_complete: false,
                         suggestions: Vec::new(),
                         context_name: None,
@@ -1265,6 +1560,10 @@ while let Some(chunk) = stream_rx.recv().await {
 
         info!("llm_end: Streaming loop ended for session {}, chunk_count={}, full_response_len={}", session.id, chunk_count, full_response.len());
 
+        // Drops the provider connection if the loop ended early; a no-op once it has finished
+        llm_task.abort();
+        generation::finish(&self.state.active_streams, session.id, generation.id).await;
+
         let has_html = full_response.contains("</") || full_response.contains("<!--");
         let has_div = full_response.contains("<div") || full_response.contains("</div>");
         let has_style = full_response.contains("<style");
@@ -1291,11 +1590,16 @@ while let Some(chunk) = stream_rx.recv().await {
         } else {
             full_response.clone()
         };
-        let history_preview = if content_for_save.len() > 100 {
-            format!("{}...", content_for_save.split_at(100).0)
+        let content_for_save = match (&guardrail, &output_refusal) {
+            (Some(g), Some(refusal)) => g.filter_for_history(&content_for_save, refusal),
+            _ => content_for_save,
+        };
+        let history_preview = if content_for_save.chars().count() > 100 {
+            format!("{}...", content_for_save.chars().take(100).collect::<String>())
         } else {
             content_for_save.clone()
         };
+        let history_preview = crate::llm::redaction::for_log(&history_preview);
         info!("history_save: session_id={} user_id={} full_response_len={} is_html={} content_len={} preview={}",
             session.id, user_id, full_response_len, is_html, content_for_save.len(), history_preview);
         
@@ -1325,6 +1629,40 @@ while let Some(chunk) = stream_rx.recv().await {
             }
         }
 
+        // An answer the bot is not confident about hands the session off to a human
+        let handoff_reply = match handoff_config.low_confidence(&full_response) {
+            Some(phrase) => match handoff::escalate(
+                &self.state,
+                &session,
+                handoff::HandoffTrigger::LowConfidence,
+                phrase,
+                &message_content,
+            )
+            .await
+            {
+                Ok(_) => Some(handoff_config.message(&locale)),
+                Err(e) => {
+                    error!("Handoff of session {} failed: {}", session.id, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        crate::llm::usage::record_usage(
+            &self.state,
+            crate::llm::usage::LlmUsageRecord {
+                bot_id: session.bot_id,
+                session_id: session.id,
+                model: llm_chain.served_model().unwrap_or_else(|| model.clone()),
+                provider: llm_chain.served_by().unwrap_or(primary_name),
+                prompt_tokens,
+                completion_tokens: crate::core::shared::utils::estimate_token_count(&full_response),
+                cached: served_from_cache.load(std::sync::atomic::Ordering::SeqCst),
+            },
+        )
+        .await;
+
         // Extract bot_id and session_id before moving them into BotResponse
         let bot_id_str = message.bot_id.clone();
         let session_id_str = message.session_id.clone();
@@ -1344,7 +1682,7 @@ while let Some(chunk) = stream_rx.recv().await {
                 channel: message.channel.clone(),
                 content: html_buffer.clone(),
                 message_type: MessageType::BOT_RESPONSE,
-                stream_token: None,
+                stream_token: stream_token.clone(),
                 is_complete: false,
                 suggestions: Vec::new(),
                 context_name: None,
@@ -1355,6 +1693,37 @@ while let Some(chunk) = stream_rx.recv().await {
             html_buffer.clear();
         }
 
+        if let Some(reply) = handoff_reply {
+            let state_for_save = self.state.clone();
+            let reply_for_save = reply.clone();
+            let save_result = tokio::task::spawn_blocking(
+                move || -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+                    let mut sm = state_for_save.session_manager.blocking_lock();
+                    sm.save_message(session_id_for_save, user_id_for_save, 2, &reply_for_save, 2)?;
+                    Ok(())
+                },
+            )
+            .await;
+            if !matches!(save_result, Ok(Ok(()))) {
+                error!("Failed to save handoff reply for session {}", session_id_for_save);
+            }
+            let handoff_chunk = BotResponse {
+                bot_id: message.bot_id.clone(),
+                user_id: message.user_id.clone(),
+                session_id: message.session_id.clone(),
+                channel: message.channel.clone(),
+                content: format!("\n\n{}", reply),
+                message_type: MessageType::BOT_RESPONSE,
+                stream_token: stream_token.clone(),
+                is_complete: false,
+                suggestions: Vec::new(),
+                context_name: None,
+                context_length: 0,
+                context_max_length: 0,
+            };
+            let _ = response_tx.send(handoff_chunk).await;
+        }
+
         // Content was already sent as streaming chunks.
         // Sending full_response again would duplicate it (especially for WhatsApp which accumulates buffer).
         // The final response is just a signal that streaming is complete - it should not contain content.
@@ -1367,7 +1736,7 @@ while let Some(chunk) = stream_rx.recv().await {
             channel: message.channel,
             content: final_content,
             message_type: MessageType::BOT_RESPONSE,
-            stream_token: None,
+            stream_token: stream_token.clone(),
             is_complete: true,
             suggestions,
             context_name: None,
@@ -1386,13 +1755,20 @@ while let Some(chunk) = stream_rx.recv().await {
         response_tx: mpsc::Sender<BotResponse>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         warn!("LLM feature not enabled, cannot stream response");
+        let ids = (Uuid::parse_str(&message.session_id), Uuid::parse_str(&message.user_id));
+        let locale = match ids {
+            (Ok(session_id), Ok(user_id)) => {
+                crate::core::i18n::chat_locale(&self.state, session_id, user_id, None).await
+            }
+            _ => crate::core::i18n::Locale::default(),
+        };
 
         let error_response = BotResponse {
             bot_id: message.bot_id,
             user_id: message.user_id,
             session_id: message.session_id,
             channel: message.channel,
-            content: "LLM feature is not enabled in this build".to_string(),
+            content: crate::core::i18n::system_message(&locale, "chat-llm-disabled", &[]),
             message_type: MessageType::BOT_RESPONSE,
             stream_token: None,
             is_complete: true,
@@ -1429,13 +1805,38 @@ while let Some(chunk) = stream_rx.recv().await {
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
     Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
-    info!("websocket_handler: Received request with params: {:?}", params);
+    let auth_user = match crate::security::auth_api::authenticate_ws(
+        state.auth_provider_registry.as_deref(),
+        &headers,
+        &params,
+    )
+    .await
+    {
+        Ok(user) => user,
+        Err(e) => {
+            warn!("websocket_handler: rejected unauthenticated upgrade: {:?}", e);
+            return e.into_response();
+        }
+    };
+
+    info!(
+        "websocket_handler: Received request for bot {:?}, session {:?}",
+        params.get("bot_name"),
+        params.get("session_id")
+    );
     let session_id = params
         .get("session_id")
         .and_then(|s| Uuid::parse_str(s).ok());
-    let user_id = params.get("user_id").and_then(|s| Uuid::parse_str(s).ok());
+    // The identity comes from the token; the user_id parameter is only honoured when
+    // the deployment allows anonymous access.
+    let user_id = if auth_user.is_authenticated() {
+        Some(auth_user.user_id)
+    } else {
+        params.get("user_id").and_then(|s| Uuid::parse_str(s).ok())
+    };
 
     // Extract bot_name from query params
     let bot_name = params
@@ -1476,13 +1877,25 @@ pub async fn websocket_handler(
         }
     };
 
-    ws.on_upgrade(move |socket| handle_websocket(socket, state, session_id, user_id, bot_id))
+    if let Err(expired) = crate::core::session::require_live_session(&state, session_id).await {
+        let locale = crate::core::i18n::resolve_locale(
+            None,
+            None,
+            headers.get(axum::http::header::ACCEPT_LANGUAGE).and_then(|h| h.to_str().ok()),
+        );
+        return expired.into_localized_response(&locale);
+    }
+    crate::core::i18n::remember_chat_language(session_id, &headers);
+
+    ws.protocols([crate::security::auth_api::WS_BEARER_PROTOCOL])
+        .on_upgrade(move |socket| handle_websocket(socket, state, session_id, user_id, bot_id))
         .into_response()
 }
 
 pub async fn websocket_handler_with_bot(
     ws: WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
     Path(bot_name): Path<String>,
     Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
@@ -1490,7 +1903,7 @@ pub async fn websocket_handler_with_bot(
     if !bot_name.is_empty() {
         params.insert("bot_name".to_string(), bot_name);
     }
-    websocket_handler(ws, State(state), Query(params)).await
+    websocket_handler(ws, State(state), headers, Query(params)).await
 }
 
 async fn handle_websocket(
@@ -1508,10 +1921,10 @@ async fn handle_websocket(
         .add_connection(session_id.to_string(), tx.clone())
         .await;
 
-    {
-        let mut channels = state.response_channels.lock().await;
-        channels.insert(session_id.to_string(), tx.clone());
-    }
+    state
+        .ws_router
+        .connect(&session_id.to_string(), tx.clone())
+        .await;
 
     info!(
         "WebSocket connected for session: {}, user: {}, bot: {}",
@@ -1560,12 +1973,13 @@ async fn handle_websocket(
         vec![]
     };
 
+    let locale = crate::core::i18n::chat_locale(&state, session_id, user_id, None).await;
     let welcome = serde_json::json!({
         "type": "connected",
         "session_id": session_id,
         "user_id": user_id,
         "bot_id": bot_id,
-        "message": "Connected to bot server",
+        "message": crate::core::i18n::system_message(&locale, "chat-connected", &[]),
         "tools": tools
     });
 
@@ -1735,12 +2149,41 @@ async fn handle_websocket(
 } // End of if should_execute_start_bas
 }
 
-let mut send_task = tokio::spawn(async move {
-        while let Some(response) = rx.recv().await {
-            if let Ok(json_str) = serde_json::to_string(&response) {
-                if sender.send(Message::Text(json_str)).await.is_err() {
+let heartbeat = HeartbeatConfig::default();
+    let liveness = Liveness::new();
+
+    let web_adapter = Arc::clone(&state.web_adapter);
+    let frame_session_id = session_id.to_string();
+    // Lets the receiving side close the socket once the session has expired.
+    let (close_tx, mut close_rx) = mpsc::channel::<CloseFrame<'static>>(1);
+    let mut send_task = tokio::spawn(async move {
+        let mut ping_ticker = heartbeat.ping_ticker();
+        loop {
+            let json_str = tokio::select! {
+                frame = close_rx.recv() => {
+                    if let Some(frame) = frame {
+                        let _ = sender.send(Message::Close(Some(frame))).await;
+                    }
                     break;
                 }
+                response = rx.recv() => match response {
+                    Some(response) => {
+                        match web_adapter.frame(&frame_session_id, &response).await {
+                            Ok(json_str) => json_str,
+                            Err(_) => continue,
+                        }
+                    }
+                    None => break,
+                },
+                _ = ping_ticker.tick() => {
+                    if sender.send(Message::Ping(Vec::new())).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            if sender.send(Message::Text(json_str)).await.is_err() {
+                break;
             }
         }
     });
@@ -1748,14 +2191,28 @@ let mut send_task = tokio::spawn(async move {
     let _ = send_ready_tx.send(()).await;
 
     let state_clone = state.clone();
+    let liveness_recv = liveness.clone();
     let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
+            liveness_recv.touch();
             match msg {
                 Message::Text(text) => {
-                    debug!("WebSocket received text: {}", text);
+                    debug!("WebSocket received text ({} bytes)", text.len());
                     // Add immediate trace
                     info!("Processing message for session {}", session_id);
-                    
+
+                    if let Some(request) = generation::parse_cancel_message(&text) {
+                        let active = &state_clone.active_streams;
+                        match generation::cancel(active, session_id, request.generation_id).await {
+                            Some(cancelled) => {
+                                info!("Client cancelled generation {} in session {}",
+                                    cancelled, session_id)
+                            }
+                            None => debug!("No generation to cancel in session {}", session_id),
+                        }
+                        continue;
+                    }
+
                     if let Ok(user_msg) = serde_json::from_str::<UserMessage>(&text) {
                         // Get session first, outside any lock scope
                         let session_result = {
@@ -1781,6 +2238,26 @@ let mut send_task = tokio::spawn(async move {
                             }
                         };
 
+                        let live = {
+                            let mut sm = state_clone.session_manager.lock().await;
+                            sm.enforce_timeouts(&session)
+                        };
+                        if let Err(expired) = live {
+                            info!(
+                                "Closing WebSocket for session {}: {}",
+                                session_id,
+                                expired.reason()
+                            );
+                            let frame = CloseFrame {
+                                code: close_code::POLICY,
+                                reason: expired.reason().into(),
+                            };
+                            if close_tx.send(frame).await.is_err() {
+                                break;
+                            }
+                            continue;
+                        }
+
                         // Get response channel sender out of lock scope
                         let tx_opt = {
                             let channels = state_clone.response_channels.lock().await;
@@ -1789,15 +2266,11 @@ let mut send_task = tokio::spawn(async move {
 
         if let Some(tx_clone) = tx_opt {
             // CANCEL any existing streaming for this session first
-            let session_id_str = session_id.to_string();
-            {
-                let mut active_streams = state_clone.active_streams.lock().await;
-                if let Some(cancel_tx) = active_streams.remove(&session_id_str) {
-                    info!("Cancelling existing streaming for session {}", session_id);
-                    let _ = cancel_tx.send(());
-                    // Give a moment for the streaming to stop
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                }
+            let active = &state_clone.active_streams;
+            if let Some(cancelled) = generation::cancel(active, session_id, None).await {
+                info!("Cancelling generation {} for session {}", cancelled, session_id);
+                // Give a moment for the streaming to stop
+                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
             }
                     
                     let corrected_msg = UserMessage {
@@ -1838,6 +2311,11 @@ let mut send_task = tokio::spawn(async move {
     tokio::select! {
         _ = (&mut send_task) => { recv_task.abort(); }
         _ = (&mut recv_task) => { send_task.abort(); }
+        _ = reaped(&liveness, heartbeat) => {
+            info!("Dropping unresponsive WebSocket for session: {}", session_id);
+            send_task.abort();
+            recv_task.abort();
+        }
     }
 
     state
@@ -1845,17 +2323,15 @@ let mut send_task = tokio::spawn(async move {
         .remove_connection(&session_id.to_string())
         .await;
 
-    {
-        let mut channels = state.response_channels.lock().await;
-        channels.remove(&session_id.to_string());
-    }
+    state.ws_router.disconnect(&session_id.to_string()).await;
+    crate::core::i18n::forget_chat_language(session_id);
 
     info!("WebSocket disconnected for session: {}", session_id);
 }
 
 pub fn create_bot_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let bot_name = payload
         .get("bot_name")
@@ -1863,9 +2339,7 @@ pub fn create_bot_handler(
         .unwrap_or_else(|| "default".to_string());
 
     let orchestrator = BotOrchestrator::new(state);
-    if let Err(e) = orchestrator.mount_all_bots() {
-        error!("Failed to mount bots: {}", e);
-    }
+    orchestrator.mount_all_bots();
 
     (
         StatusCode::OK,
@@ -1875,24 +2349,26 @@ pub fn create_bot_handler(
 
 pub fn mount_bot_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let bot_guid = payload.get("bot_guid").cloned().unwrap_or_default();
 
     let orchestrator = BotOrchestrator::new(state);
-    if let Err(e) = orchestrator.mount_all_bots() {
-        error!("Failed to mount bot: {}", e);
-    }
+    let report = orchestrator.mount_all_bots();
 
     (
         StatusCode::OK,
-        Json(serde_json::json!({ "status": format!("bot '{}' mounted", bot_guid) })),
+        Json(serde_json::json!({
+            "status": format!("bot '{}' mounted", bot_guid),
+            "mounted": report.mounted,
+            "failed": report.failures().collect::<Vec<_>>(),
+        })),
     )
 }
 
 pub async fn handle_user_input_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let session_id = payload.get("session_id").cloned().unwrap_or_default();
     let user_input = payload.get("input").cloned().unwrap_or_default();
@@ -1915,7 +2391,7 @@ pub async fn handle_user_input_handler(
 
 pub async fn get_user_sessions_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let user_id = payload
         .get("user_id")
@@ -1940,7 +2416,7 @@ pub async fn get_user_sessions_handler(
 
 pub async fn get_conversation_history_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let session_id = payload
         .get("session_id")
@@ -1972,7 +2448,7 @@ pub async fn get_conversation_history_handler(
 
 pub async fn send_warning_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let message = payload
         .get("message")
@@ -1,4 +1,5 @@
 use crate::core::config::ConfigManager;
+use crate::core::shared::api_json::ApiJson;
 use crate::drive::drive_monitor::DriveMonitor;
 use crate::llm::llm_models;
 use crate::llm::OpenAIClient;
@@ -386,7 +387,7 @@ async fn handle_websocket(
 
 pub async fn create_bot_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let bot_name = payload
         .get("bot_name")
@@ -406,7 +407,7 @@ pub async fn create_bot_handler(
 
 pub async fn mount_bot_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let bot_guid = payload.get("bot_guid").cloned().unwrap_or_default();
 
@@ -423,7 +424,7 @@ pub async fn mount_bot_handler(
 
 pub async fn handle_user_input_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let session_id = payload.get("session_id").cloned().unwrap_or_default();
     let user_input = payload.get("input").cloned().unwrap_or_default();
@@ -446,7 +447,7 @@ pub async fn handle_user_input_handler(
 
 pub async fn get_user_sessions_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let user_id = payload
         .get("user_id")
@@ -471,7 +472,7 @@ pub async fn get_user_sessions_handler(
 
 pub async fn get_conversation_history_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let session_id = payload
         .get("session_id")
@@ -503,7 +504,7 @@ pub async fn get_conversation_history_handler(
 
 pub async fn send_warning_handler(
     Extension(state): Extension<Arc<AppState>>,
-    Json(payload): Json<HashMap<String, String>>,
+    ApiJson(payload): ApiJson<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let message = payload
         .get("message")
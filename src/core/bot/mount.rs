@@ -0,0 +1,223 @@
+//! Per-bot results of mounting `.gbai` packages, kept for the mount status endpoint.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, LazyLock, RwLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MountStatus {
+    Mounted,
+    /// The package has no active bot in the database yet.
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BotMountResult {
+    pub bot_name: String,
+    pub path: String,
+    pub status: MountStatus,
+    pub reason: Option<String>,
+}
+
+impl BotMountResult {
+    fn new(bot_name: &str, path: &Path, status: MountStatus, reason: Option<String>) -> Self {
+        Self {
+            bot_name: bot_name.to_string(),
+            path: path.display().to_string(),
+            status,
+            reason,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MountReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub mounted: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    /// Directories that could not be scanned, with the reason.
+    pub scan_errors: Vec<String>,
+    pub bots: Vec<BotMountResult>,
+}
+
+impl MountReport {
+    pub fn new(started_at: DateTime<Utc>, bots: Vec<BotMountResult>, scan_errors: Vec<String>) -> Self {
+        let count = |status| bots.iter().filter(|b| b.status == status).count();
+        Self {
+            started_at,
+            finished_at: Utc::now(),
+            mounted: count(MountStatus::Mounted),
+            skipped: count(MountStatus::Skipped),
+            failed: count(MountStatus::Failed),
+            scan_errors,
+            bots,
+        }
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &BotMountResult> {
+        self.bots.iter().filter(|b| b.status == MountStatus::Failed)
+    }
+}
+
+static LAST_REPORT: LazyLock<RwLock<Option<MountReport>>> = LazyLock::new(|| RwLock::new(None));
+
+pub fn store_report(report: &MountReport) {
+    *LAST_REPORT.write().unwrap_or_else(|e| e.into_inner()) = Some(report.clone());
+}
+
+pub fn last_report() -> Option<MountReport> {
+    LAST_REPORT.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Checks that a `.gbai` package is readable and that its `config.csv`, when present,
+/// only contains `name,value` lines.
+pub fn validate_package(package: &Path, bot_name: &str) -> Result<(), String> {
+    if !package.is_dir() {
+        return Err("Package is not a directory".to_string());
+    }
+    let config_path = package.join(format!("{}.gbot", bot_name)).join("config.csv");
+    if !config_path.exists() {
+        return Ok(());
+    }
+    let bytes = std::fs::read(&config_path)
+        .map_err(|e| format!("Failed to read {}: {}", config_path.display(), e))?;
+    let content = String::from_utf8(bytes)
+        .map_err(|_| format!("{} is not valid UTF-8", config_path.display()))?;
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once(',') {
+            Some((key, _)) if !key.trim().is_empty() => {}
+            _ => {
+                return Err(format!(
+                    "config.csv line {}: expected 'name,value', found '{}'",
+                    index + 1,
+                    line
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Mounts every `.gbai` package in `dir`. A package that fails validation or whose
+/// database lookup fails is reported as failed without stopping the others.
+pub fn mount_directory(
+    dir: &Path,
+    mut bot_exists: impl FnMut(&str) -> Result<bool, String>,
+) -> Result<Vec<BotMountResult>, String> {
+    let entries = std::fs::read_dir(dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+    let mut paths: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+
+    let mut results = Vec::new();
+    for path in paths {
+        let Some(bot_name) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_suffix(".gbai"))
+        else {
+            continue;
+        };
+
+        let result = match validate_package(&path, bot_name).and_then(|()| bot_exists(bot_name)) {
+            Ok(true) => BotMountResult::new(bot_name, &path, MountStatus::Mounted, None),
+            Ok(false) => BotMountResult::new(
+                bot_name,
+                &path,
+                MountStatus::Skipped,
+                Some("Bot does not exist in database (run import to create)".to_string()),
+            ),
+            Err(e) => BotMountResult::new(bot_name, &path, MountStatus::Failed, Some(e)),
+        };
+        results.push(result);
+    }
+    Ok(results)
+}
+
+pub async fn handle_mount_status(
+    State(_state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+) -> Result<Json<MountReport>, ApiError> {
+    require_admin(&user)?;
+    last_report()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("Bots have not been mounted yet"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn package(root: &Path, name: &str, config: Option<&str>) {
+        let gbot = root.join(format!("{name}.gbai")).join(format!("{name}.gbot"));
+        std::fs::create_dir_all(&gbot).unwrap();
+        if let Some(config) = config {
+            std::fs::write(gbot.join("config.csv"), config).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_invalid_bot_does_not_stop_others() {
+        let root = TempDir::new().unwrap();
+        package(root.path(), "alpha", Some("name,value\ntheme-title,Alpha\n"));
+        package(root.path(), "broken", Some("name,value\nthis line has no value\n"));
+        package(root.path(), "gamma", None);
+        package(root.path(), "newbot", None);
+        std::fs::write(root.path().join("notes.txt"), "ignored").unwrap();
+
+        let results = mount_directory(root.path(), |name| Ok(name != "newbot")).unwrap();
+        let report = MountReport::new(Utc::now(), results, Vec::new());
+
+        let status = |name: &str| {
+            report
+                .bots
+                .iter()
+                .find(|b| b.bot_name == name)
+                .map(|b| b.status)
+        };
+        assert_eq!(status("alpha"), Some(MountStatus::Mounted));
+        assert_eq!(status("gamma"), Some(MountStatus::Mounted));
+        assert_eq!(status("broken"), Some(MountStatus::Failed));
+        assert_eq!(status("newbot"), Some(MountStatus::Skipped));
+        assert_eq!((report.mounted, report.skipped, report.failed), (2, 1, 1));
+
+        let failure = report.failures().next().unwrap();
+        assert!(failure.reason.as_deref().unwrap().contains("line 2"));
+    }
+
+    #[test]
+    fn test_database_error_is_reported_per_bot() {
+        let root = TempDir::new().unwrap();
+        package(root.path(), "alpha", None);
+        package(root.path(), "beta", None);
+
+        let results = mount_directory(root.path(), |name| {
+            if name == "alpha" {
+                Err("connection refused".to_string())
+            } else {
+                Ok(true)
+            }
+        })
+        .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].status, MountStatus::Failed);
+        assert_eq!(results[0].reason.as_deref(), Some("connection refused"));
+        assert_eq!(results[1].status, MountStatus::Mounted);
+    }
+}
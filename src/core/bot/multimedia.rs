@@ -11,6 +11,7 @@
 
 
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::message_types::MessageType;
 use crate::core::shared::models::{BotResponse, UserMessage};
 use anyhow::Result;
@@ -489,6 +490,7 @@ impl UserMessageMultimedia for UserMessage {
 
 
 use crate::core::shared::state::AppState;
+use crate::security::upload_policy::check_upload;
 use axum::{
     extract::{Path, State},
     http::StatusCode,
@@ -500,7 +502,7 @@ use std::sync::Arc;
 
 pub async fn upload_media_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<MediaUploadRequest>,
+    ApiJson(request): ApiJson<MediaUploadRequest>,
 ) -> impl IntoResponse {
     #[cfg(feature = "drive")]
     let handler = DefaultMultimediaHandler::new(state.drive.clone(), None);
@@ -508,6 +510,20 @@ pub async fn upload_media_handler(
     #[cfg(not(feature = "drive"))]
     let handler = DefaultMultimediaHandler::new(None, None);
 
+    if let Err(rejection) = check_upload(
+        "media-upload",
+        &request.file_name,
+        &request.content_type,
+        &request.data,
+    )
+    .await
+    {
+        return (
+            rejection.status(),
+            Json(serde_json::json!({"error": rejection.to_string()})),
+        );
+    }
+
     match handler.upload_media(request).await {
         Ok(response) => (StatusCode::OK, Json(serde_json::json!(response))),
         Err(e) => (
@@ -577,7 +593,7 @@ pub async fn generate_thumbnail_handler(
 
 pub async fn web_search_handler(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<serde_json::Value>,
+    ApiJson(payload): ApiJson<serde_json::Value>,
 ) -> impl IntoResponse {
     let query = payload.get("query").and_then(|q| q.as_str()).unwrap_or("");
     let max_results = payload
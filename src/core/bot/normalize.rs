@@ -0,0 +1,346 @@
+//! Inbound message normalization. Before a message reaches the bot, its content is put in
+//! one canonical form whatever the channel: HTML from the web and Teams is reduced to
+//! text, WhatsApp and Markdown emphasis markers are dropped, and whitespace is tidied.
+//! Mentions and a leading `/command` are extracted on the way and stored on the session
+//! under [`CONTEXT_KEY`].
+//!
+//! The steps run for a channel come from the bot's `inbound-normalize-<channel>` config,
+//! then `inbound-normalize`, then the channel default; `off` disables normalization.
+
+use super::handoff;
+use crate::core::config::ConfigManager;
+use crate::core::shared::models::UserSession;
+use crate::core::shared::state::AppState;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::LazyLock;
+use uuid::Uuid;
+
+/// Key of the session's `context_data` holding the mentions and command of the last
+/// message.
+pub const CONTEXT_KEY: &str = "inbound";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizeStep {
+    /// Reduces HTML to text: line breaks for block elements, tags removed, entities decoded.
+    Html,
+    /// Removes WhatsApp (`*bold*`, `_italic_`, `~strike~`) and Markdown formatting.
+    Markdown,
+    /// Collapses runs of spaces and blank lines and trims the message.
+    Whitespace,
+}
+
+impl NormalizeStep {
+    /// Parses a list such as `html;whitespace`, separated by `;` as in `config.csv`, or by
+    /// `,`. `off` yields no steps.
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        let mut steps = Vec::new();
+        for name in list
+            .split([';', ','])
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            let step = match name.to_ascii_lowercase().as_str() {
+                "off" => return Ok(Vec::new()),
+                "html" => Self::Html,
+                "markdown" => Self::Markdown,
+                "whitespace" => Self::Whitespace,
+                other => return Err(format!("Unknown normalization step '{other}'")),
+            };
+            if !steps.contains(&step) {
+                steps.push(step);
+            }
+        }
+        Ok(steps)
+    }
+
+    /// Steps for channels without configuration. Web messages are typed as plain text, so
+    /// `html` is opt-in there.
+    pub fn defaults_for(channel: &str) -> Vec<Self> {
+        match channel {
+            "teams" | "email" => vec![Self::Html, Self::Whitespace],
+            "whatsapp" | "telegram" | "instagram" => vec![Self::Markdown, Self::Whitespace],
+            _ => vec![Self::Whitespace],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InboundCommand {
+    pub name: String,
+    pub args: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NormalizedMessage {
+    pub text: String,
+    /// Names mentioned with `@name`, without the `@`.
+    pub mentions: Vec<String>,
+    /// A `/command args` message. The text keeps the command as typed.
+    pub command: Option<InboundCommand>,
+}
+
+impl NormalizedMessage {
+    /// What is stored under [`CONTEXT_KEY`], or `None` without mentions and command.
+    pub fn context_value(&self) -> Option<Value> {
+        if self.mentions.is_empty() && self.command.is_none() {
+            return None;
+        }
+        Some(json!({ "mentions": self.mentions, "command": self.command }))
+    }
+}
+
+/// Stores the mentions and command of `message` on `session`, replacing those of the
+/// previous message, so scripts and tools can read them from the session context. Nothing
+/// is written when both messages had none. Blocking.
+pub fn store_on_session(
+    state: &AppState,
+    session: &mut UserSession,
+    message: &NormalizedMessage,
+) -> Result<(), String> {
+    let value = message.context_value();
+    let stored = session.context_data.get(CONTEXT_KEY).filter(|v| !v.is_null());
+    if value.is_none() && stored.is_none() {
+        return Ok(());
+    }
+    let mut conn = state.conn.get().map_err(|e| e.to_string())?;
+    session.context_data = handoff::update_context(&mut conn, session.id, |context| {
+        if let Value::Object(map) = context {
+            match value {
+                Some(value) => map.insert(CONTEXT_KEY.to_string(), value),
+                None => map.remove(CONTEXT_KEY),
+            };
+        } else if let Some(value) = value {
+            *context = json!({ CONTEXT_KEY: value });
+        }
+    })
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Steps configured for `channel` on `bot_id`. An invalid value is logged and the channel
+/// default used instead.
+pub fn steps_for_channel(state: &AppState, bot_id: Uuid, channel: &str) -> Vec<NormalizeStep> {
+    let config = ConfigManager::new(state.conn.clone());
+    let configured = config
+        .get_config(&bot_id, &format!("inbound-normalize-{channel}"), None)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .or_else(|| {
+            config
+                .get_config(&bot_id, "inbound-normalize", None)
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+        });
+    match configured.map(|list| NormalizeStep::parse_list(&list)) {
+        Some(Ok(steps)) => steps,
+        Some(Err(e)) => {
+            log::warn!("Bot {bot_id}: {e}; using defaults for channel {channel}");
+            NormalizeStep::defaults_for(channel)
+        }
+        None => NormalizeStep::defaults_for(channel),
+    }
+}
+
+fn regex(pattern: &str) -> Regex {
+    Regex::new(pattern).expect("valid normalization regex")
+}
+
+static SCRIPT_STYLE: LazyLock<Regex> =
+    LazyLock::new(|| regex(r"(?is)<(script|style)\b[^>]*>.*?</(script|style)\s*>"));
+static LINE_BREAK: LazyLock<Regex> = LazyLock::new(|| regex(r"(?i)<br\s*/?>"));
+static BLOCK_END: LazyLock<Regex> =
+    LazyLock::new(|| regex(r"(?i)</(p|div|h[1-6]|blockquote|pre|tr)\s*>"));
+static LIST_ITEM: LazyLock<Regex> = LazyLock::new(|| regex(r"(?i)<li\b[^>]*>"));
+/// Only real tags and comments, so a plain `x < y and y > z` survives.
+static TAG: LazyLock<Regex> = LazyLock::new(|| regex(r"(?s)<!--.*?-->|</?[A-Za-z][^<>]*>"));
+static NUMERIC_ENTITY: LazyLock<Regex> = LazyLock::new(|| regex(r"&#(x[0-9a-fA-F]+|[0-9]+);"));
+
+fn html_to_text(html: &str) -> String {
+    let text = SCRIPT_STYLE.replace_all(html, "");
+    let text = LINE_BREAK.replace_all(&text, "\n");
+    let text = BLOCK_END.replace_all(&text, "\n\n");
+    let text = LIST_ITEM.replace_all(&text, "\n- ");
+    let text = TAG.replace_all(&text, "");
+    let text = NUMERIC_ENTITY.replace_all(&text, |caps: &regex::Captures| {
+        let code = &caps[1];
+        let value = match code.strip_prefix('x') {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => code.parse().ok(),
+        };
+        value
+            .and_then(char::from_u32)
+            .map_or_else(|| caps[0].to_string(), String::from)
+    });
+    text.replace("&nbsp;", "\u{a0}")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+static CODE_BLOCK: LazyLock<Regex> =
+    LazyLock::new(|| regex(r"(?s)```(?:[a-zA-Z0-9_-]*\n)?(.*?)```"));
+static INLINE_CODE: LazyLock<Regex> = LazyLock::new(|| regex(r"`([^`\n]+)`"));
+static LINK: LazyLock<Regex> = LazyLock::new(|| regex(r"\[([^\]\n]+)\]\(([^)\s]+)\)"));
+static HEADING: LazyLock<Regex> = LazyLock::new(|| regex(r"(?m)^#{1,6}[ \t]+"));
+/// Emphasis markers count only at word edges, so `snake_case` and `2*3*4` are left alone.
+static EMPHASIS: LazyLock<Regex> = LazyLock::new(|| {
+    regex(
+        r#"(^|[\s(\["'])(\*\*|__|~~|\*|_|~)([^\s*_~](?:[^\n]*?[^\s*_~])?)(\*\*|__|~~|\*|_|~)($|[\s)\]"'.,!?:;])"#,
+    )
+});
+
+pub(crate) fn strip_markdown(text: &str) -> String {
+    let text = CODE_BLOCK.replace_all(text, "$1");
+    let text = INLINE_CODE.replace_all(&text, "$1");
+    let text = LINK.replace_all(&text, "$1 ($2)");
+    let text = HEADING.replace_all(&text, "");
+    let mut text = text.into_owned();
+    // Nested markers (`*_both_*`) need one pass per level.
+    for _ in 0..3 {
+        let next = EMPHASIS.replace_all(&text, |caps: &regex::Captures| {
+            if caps[2] == caps[4] {
+                format!("{}{}{}", &caps[1], &caps[3], &caps[5])
+            } else {
+                caps[0].to_string()
+            }
+        });
+        if next == text {
+            break;
+        }
+        text = next.into_owned();
+    }
+    text
+}
+
+static SPACES: LazyLock<Regex> =
+    LazyLock::new(|| regex(r"[ \t\u{a0}\u{2000}-\u{200a}\u{202f}\u{3000}]+"));
+static BLANK_LINES: LazyLock<Regex> = LazyLock::new(|| regex(r"\n{3,}"));
+
+fn normalize_whitespace(text: &str) -> String {
+    let text: String = text
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .chars()
+        .filter(|c| !matches!(c, '\u{200b}' | '\u{200c}' | '\u{200d}' | '\u{feff}'))
+        .collect();
+    let text = SPACES.replace_all(&text, " ");
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    BLANK_LINES
+        .replace_all(&lines.join("\n"), "\n\n")
+        .trim()
+        .to_string()
+}
+
+static MENTION: LazyLock<Regex> =
+    LazyLock::new(|| regex(r"(?:^|\s)@([\p{L}\p{N}_][\p{L}\p{N}_.-]*)"));
+static COMMAND: LazyLock<Regex> =
+    LazyLock::new(|| regex(r"(?s)^/([A-Za-z][A-Za-z0-9_-]*)(?:\s+(.*))?$"));
+
+/// Runs `steps` over `content` in order, then extracts mentions and the command.
+pub fn normalize(content: &str, steps: &[NormalizeStep]) -> NormalizedMessage {
+    let mut text = content.to_string();
+    for step in steps {
+        text = match step {
+            NormalizeStep::Html => html_to_text(&text),
+            NormalizeStep::Markdown => strip_markdown(&text),
+            NormalizeStep::Whitespace => normalize_whitespace(&text),
+        };
+    }
+
+    let mut mentions: Vec<String> = Vec::new();
+    for caps in MENTION.captures_iter(&text) {
+        let name = caps[1].trim_end_matches(['.', '-']).to_string();
+        if !mentions.contains(&name) {
+            mentions.push(name);
+        }
+    }
+    let command = COMMAND.captures(text.trim()).map(|caps| InboundCommand {
+        name: caps[1].to_ascii_lowercase(),
+        args: caps.get(2).map_or("", |m| m.as_str()).trim().to_string(),
+    });
+
+    NormalizedMessage {
+        text,
+        mentions,
+        command,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_web_html_and_whatsapp_share_canonical_text() {
+        let html = "<p>Hello <b>team</b>,</p>\r\n<p>please check&nbsp;the <i>Q3&amp;Q4</i> \
+                    report<br/>before   Friday</p>";
+        let whatsapp = "Hello *team*,\n\n\nplease check the _Q3&Q4_ report\nbefore Friday ";
+
+        let web = normalize(html, &[NormalizeStep::Html, NormalizeStep::Whitespace]);
+        let wa = normalize(whatsapp, &NormalizeStep::defaults_for("whatsapp"));
+        assert_eq!(
+            web.text,
+            "Hello team,\n\nplease check the Q3&Q4 report\nbefore Friday"
+        );
+        assert_eq!(wa.text, web.text);
+        // Web messages keep `<tags>` unless `html` is configured.
+        assert_eq!(
+            normalize("use <b> for bold", &NormalizeStep::defaults_for("web")).text,
+            "use <b> for bold"
+        );
+    }
+
+    #[test]
+    fn test_markdown_keeps_identifiers_and_arithmetic() {
+        let steps = [NormalizeStep::Markdown, NormalizeStep::Whitespace];
+        let text = normalize(
+            "Set *max_tokens* to 2*3*4, see [docs](https://x.io) and ~old~ `user_id`",
+            &steps,
+        )
+        .text;
+        assert_eq!(
+            text,
+            "Set max_tokens to 2*3*4, see docs (https://x.io) and old user_id"
+        );
+    }
+
+    #[test]
+    fn test_extracts_mentions_and_command() {
+        let msg = normalize(
+            "/transfer  @maria.silva please, cc @joao. mail me at a@b.com",
+            &[NormalizeStep::Whitespace],
+        );
+        assert_eq!(msg.mentions, vec!["maria.silva", "joao"]);
+        assert_eq!(
+            msg.command,
+            Some(InboundCommand {
+                name: "transfer".to_string(),
+                args: "@maria.silva please, cc @joao. mail me at a@b.com".to_string(),
+            })
+        );
+        assert_eq!(
+            msg.context_value().unwrap()["command"]["name"],
+            "transfer"
+        );
+        assert_eq!(normalize("hello", &[]).command, None);
+        assert_eq!(normalize("hello", &[]).context_value(), None);
+        assert_eq!(
+            normalize("if x < y and y > z", &[NormalizeStep::Html]).text,
+            "if x < y and y > z"
+        );
+    }
+
+    #[test]
+    fn test_parse_steps() {
+        assert_eq!(
+            NormalizeStep::parse_list("html; Whitespace,html").unwrap(),
+            vec![NormalizeStep::Html, NormalizeStep::Whitespace]
+        );
+        assert!(NormalizeStep::parse_list("off").unwrap().is_empty());
+        assert!(NormalizeStep::parse_list("emoji").is_err());
+    }
+}
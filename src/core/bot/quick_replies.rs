@@ -0,0 +1,546 @@
+//! Canned responses that answer matching messages without calling the LLM.
+//!
+//! Each bot keeps rules in `bot_quick_replies`: a trigger (exact text, prefix or regex),
+//! the response and a priority. The pipeline checks them, highest priority first, before
+//! building the prompt; the first match is sent as the bot's reply.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::bot_features::bot_exists;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Integer, Nullable, Text, Timestamptz, Uuid as DieselUuid};
+use log::{info, warn};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+const MAX_PATTERN_LEN: usize = 500;
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerKind {
+    /// The whole message equals the pattern, ignoring case and extra whitespace.
+    Exact,
+    /// The message starts with the pattern, ignoring case and extra whitespace.
+    Prefix,
+    /// The pattern is a case-insensitive regular expression found anywhere in the message.
+    Regex,
+}
+
+impl TriggerKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::Prefix => "prefix",
+            Self::Regex => "regex",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "exact" => Some(Self::Exact),
+            "prefix" => Some(Self::Prefix),
+            "regex" => Some(Self::Regex),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickReply {
+    pub id: Uuid,
+    pub bot_id: Uuid,
+    pub trigger_kind: TriggerKind,
+    pub pattern: String,
+    pub response: String,
+    pub priority: i32,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+}
+
+enum Matcher {
+    Exact(String),
+    Prefix(String),
+    Regex(Regex),
+}
+
+/// An enabled rule ready for matching.
+pub struct CompiledReply {
+    id: Uuid,
+    matcher: Matcher,
+    response: String,
+}
+
+impl CompiledReply {
+    /// `None` for disabled rules and regexes that no longer compile.
+    fn new(reply: &QuickReply) -> Option<Self> {
+        if !reply.enabled {
+            return None;
+        }
+        let matcher = match reply.trigger_kind {
+            TriggerKind::Exact => Matcher::Exact(normalize(&reply.pattern)),
+            TriggerKind::Prefix => Matcher::Prefix(normalize(&reply.pattern)),
+            TriggerKind::Regex => match compile_regex(&reply.pattern) {
+                Ok(regex) => Matcher::Regex(regex),
+                Err(e) => {
+                    warn!(
+                        "Skipping quick reply {} with invalid regex: {}",
+                        reply.id, e
+                    );
+                    return None;
+                }
+            },
+        };
+        Some(Self {
+            id: reply.id,
+            matcher,
+            response: reply.response.clone(),
+        })
+    }
+
+    fn matches(&self, message: &str, normalized: &str) -> bool {
+        match &self.matcher {
+            Matcher::Exact(pattern) => normalized == pattern,
+            Matcher::Prefix(pattern) => normalized.starts_with(pattern.as_str()),
+            Matcher::Regex(regex) => regex.is_match(message.trim()),
+        }
+    }
+}
+
+/// Compiles `replies`, keeping the highest priority first; ties keep the older rule first.
+pub fn compile(replies: &[QuickReply]) -> Vec<CompiledReply> {
+    let mut ordered: Vec<&QuickReply> = replies.iter().collect();
+    ordered.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then(a.created_at.cmp(&b.created_at))
+    });
+    ordered.into_iter().filter_map(CompiledReply::new).collect()
+}
+
+/// The first rule matching `message`.
+pub fn find_match<'a>(rules: &'a [CompiledReply], message: &str) -> Option<&'a CompiledReply> {
+    let normalized = normalize(message);
+    if normalized.is_empty() {
+        return None;
+    }
+    rules.iter().find(|rule| rule.matches(message, &normalized))
+}
+
+static CACHE: LazyLock<RwLock<HashMap<Uuid, (Instant, Arc<Vec<CompiledReply>>)>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+fn cached(bot_id: Uuid) -> Option<Arc<Vec<CompiledReply>>> {
+    let cache = CACHE.read().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get(&bot_id)
+        .filter(|(at, _)| at.elapsed() < CACHE_TTL)
+        .map(|(_, rules)| Arc::clone(rules))
+}
+
+fn invalidate(bot_id: Uuid) {
+    CACHE
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&bot_id);
+}
+
+#[derive(QueryableByName)]
+struct QuickReplyRow {
+    #[diesel(sql_type = DieselUuid)]
+    id: Uuid,
+    #[diesel(sql_type = DieselUuid)]
+    bot_id: Uuid,
+    #[diesel(sql_type = Text)]
+    trigger_kind: String,
+    #[diesel(sql_type = Text)]
+    pattern: String,
+    #[diesel(sql_type = Text)]
+    response: String,
+    #[diesel(sql_type = Integer)]
+    priority: i32,
+    #[diesel(sql_type = Bool)]
+    enabled: bool,
+    #[diesel(sql_type = Timestamptz)]
+    created_at: DateTime<Utc>,
+    #[diesel(sql_type = Timestamptz)]
+    updated_at: DateTime<Utc>,
+}
+
+impl QuickReplyRow {
+    fn into_reply(self) -> Option<QuickReply> {
+        Some(QuickReply {
+            id: self.id,
+            bot_id: self.bot_id,
+            trigger_kind: TriggerKind::parse(&self.trigger_kind)?,
+            pattern: self.pattern,
+            response: self.response,
+            priority: self.priority,
+            enabled: self.enabled,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        })
+    }
+}
+
+const COLUMNS: &str =
+    "id, bot_id, trigger_kind, pattern, response, priority, enabled, created_at, updated_at";
+
+pub fn load_replies(conn: &mut PgConnection, bot_id: Uuid) -> QueryResult<Vec<QuickReply>> {
+    let rows: Vec<QuickReplyRow> = diesel::sql_query(format!(
+        "SELECT {COLUMNS} FROM bot_quick_replies WHERE bot_id = $1
+         ORDER BY priority DESC, created_at"
+    ))
+    .bind::<DieselUuid, _>(bot_id)
+    .load(conn)?;
+    Ok(rows
+        .into_iter()
+        .filter_map(QuickReplyRow::into_reply)
+        .collect())
+}
+
+/// The canned response for `message`, if one of the bot's rules matches. Lookup
+/// failures are logged and treated as no match, so the LLM still answers.
+pub async fn find_reply(state: &Arc<AppState>, bot_id: Uuid, message: &str) -> Option<String> {
+    if message.trim().is_empty() {
+        return None;
+    }
+    let rules = match cached(bot_id) {
+        Some(rules) => rules,
+        None => {
+            let pool = state.conn.clone();
+            let loaded = tokio::task::spawn_blocking(move || {
+                let mut conn = pool.get().map_err(|e| e.to_string())?;
+                load_replies(&mut conn, bot_id).map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+            match loaded {
+                Ok(replies) => {
+                    let rules = Arc::new(compile(&replies));
+                    CACHE
+                        .write()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(bot_id, (Instant::now(), Arc::clone(&rules)));
+                    rules
+                }
+                Err(e) => {
+                    warn!("Failed to load quick replies for bot {}: {}", bot_id, e);
+                    return None;
+                }
+            }
+        }
+    };
+    let rule = find_match(&rules, message)?;
+    info!(
+        "Quick reply {} answered message for bot {}",
+        rule.id, bot_id
+    );
+    Some(rule.response.clone())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuickReplyRequest {
+    pub trigger_kind: TriggerKind,
+    pub pattern: String,
+    pub response: String,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl QuickReplyRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.pattern.trim().is_empty() {
+            return Err(ApiError::bad_request("Pattern must not be empty"));
+        }
+        if self.pattern.len() > MAX_PATTERN_LEN {
+            return Err(ApiError::bad_request(format!(
+                "Pattern must be at most {} bytes",
+                MAX_PATTERN_LEN
+            )));
+        }
+        if self.response.trim().is_empty() {
+            return Err(ApiError::bad_request("Response must not be empty"));
+        }
+        if self.trigger_kind == TriggerKind::Regex {
+            compile_regex(&self.pattern)
+                .map_err(|e| ApiError::bad_request(format!("Invalid regex: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct QuickRepliesResponse {
+    pub bot_id: Uuid,
+    pub quick_replies: Vec<QuickReply>,
+}
+
+fn db_error(e: diesel::result::Error) -> ApiError {
+    ApiError::internal(e.to_string())
+}
+
+/// Runs `f` on a pooled connection after checking that the bot exists.
+async fn with_bot<T, F>(state: &Arc<AppState>, bot_id: Uuid, f: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> Result<T, ApiError> + Send + 'static,
+{
+    let pool = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        if !bot_exists(&mut conn, bot_id).map_err(db_error)? {
+            return Err(ApiError::not_found(format!("Bot {} not found", bot_id)));
+        }
+        f(&mut conn)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+}
+
+fn load_one(conn: &mut PgConnection, bot_id: Uuid, id: Uuid) -> Result<QuickReply, ApiError> {
+    let row: Option<QuickReplyRow> = diesel::sql_query(format!(
+        "SELECT {COLUMNS} FROM bot_quick_replies WHERE bot_id = $1 AND id = $2"
+    ))
+    .bind::<DieselUuid, _>(bot_id)
+    .bind::<DieselUuid, _>(id)
+    .get_result(conn)
+    .optional()
+    .map_err(db_error)?;
+    row.and_then(QuickReplyRow::into_reply)
+        .ok_or_else(|| ApiError::not_found(format!("Quick reply {} not found", id)))
+}
+
+pub async fn handle_list_quick_replies(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(bot_id): Path<Uuid>,
+) -> Result<Json<QuickRepliesResponse>, ApiError> {
+    require_admin(&user)?;
+    let quick_replies = with_bot(&state, bot_id, move |conn| {
+        load_replies(conn, bot_id).map_err(db_error)
+    })
+    .await?;
+    Ok(Json(QuickRepliesResponse {
+        bot_id,
+        quick_replies,
+    }))
+}
+
+pub async fn handle_create_quick_reply(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(bot_id): Path<Uuid>,
+    ApiJson(req): ApiJson<QuickReplyRequest>,
+) -> Result<(StatusCode, Json<QuickReply>), ApiError> {
+    require_admin(&user)?;
+    req.validate()?;
+
+    let id = Uuid::new_v4();
+    let updated_by = user.user_id;
+    let reply = with_bot(&state, bot_id, move |conn| {
+        diesel::sql_query(
+            "INSERT INTO bot_quick_replies
+             (id, bot_id, trigger_kind, pattern, response, priority, enabled, updated_by)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind::<DieselUuid, _>(id)
+        .bind::<DieselUuid, _>(bot_id)
+        .bind::<Text, _>(req.trigger_kind.as_str())
+        .bind::<Text, _>(&req.pattern)
+        .bind::<Text, _>(&req.response)
+        .bind::<Integer, _>(req.priority)
+        .bind::<Bool, _>(req.enabled)
+        .bind::<Nullable<DieselUuid>, _>(Some(updated_by))
+        .execute(conn)
+        .map_err(db_error)?;
+        load_one(conn, bot_id, id)
+    })
+    .await?;
+    invalidate(bot_id);
+
+    info!(
+        "Created quick reply {} for bot {} (by {})",
+        id, bot_id, user.user_id
+    );
+    Ok((StatusCode::CREATED, Json(reply)))
+}
+
+pub async fn handle_update_quick_reply(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path((bot_id, id)): Path<(Uuid, Uuid)>,
+    ApiJson(req): ApiJson<QuickReplyRequest>,
+) -> Result<Json<QuickReply>, ApiError> {
+    require_admin(&user)?;
+    req.validate()?;
+
+    let updated_by = user.user_id;
+    let reply = with_bot(&state, bot_id, move |conn| {
+        let updated = diesel::sql_query(
+            "UPDATE bot_quick_replies
+             SET trigger_kind = $3, pattern = $4, response = $5, priority = $6,
+                 enabled = $7, updated_by = $8, updated_at = NOW()
+             WHERE bot_id = $1 AND id = $2",
+        )
+        .bind::<DieselUuid, _>(bot_id)
+        .bind::<DieselUuid, _>(id)
+        .bind::<Text, _>(req.trigger_kind.as_str())
+        .bind::<Text, _>(&req.pattern)
+        .bind::<Text, _>(&req.response)
+        .bind::<Integer, _>(req.priority)
+        .bind::<Bool, _>(req.enabled)
+        .bind::<Nullable<DieselUuid>, _>(Some(updated_by))
+        .execute(conn)
+        .map_err(db_error)?;
+        if updated == 0 {
+            return Err(ApiError::not_found(format!("Quick reply {} not found", id)));
+        }
+        load_one(conn, bot_id, id)
+    })
+    .await?;
+    invalidate(bot_id);
+
+    info!(
+        "Updated quick reply {} for bot {} (by {})",
+        id, bot_id, user.user_id
+    );
+    Ok(Json(reply))
+}
+
+pub async fn handle_delete_quick_reply(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path((bot_id, id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, ApiError> {
+    require_admin(&user)?;
+
+    with_bot(&state, bot_id, move |conn| {
+        let deleted =
+            diesel::sql_query("DELETE FROM bot_quick_replies WHERE bot_id = $1 AND id = $2")
+                .bind::<DieselUuid, _>(bot_id)
+                .bind::<DieselUuid, _>(id)
+                .execute(conn)
+                .map_err(db_error)?;
+        if deleted == 0 {
+            return Err(ApiError::not_found(format!("Quick reply {} not found", id)));
+        }
+        Ok(())
+    })
+    .await?;
+    invalidate(bot_id);
+
+    info!(
+        "Deleted quick reply {} for bot {} (by {})",
+        id, bot_id, user.user_id
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(kind: TriggerKind, pattern: &str, response: &str, priority: i32) -> QuickReply {
+        QuickReply {
+            id: Uuid::new_v4(),
+            bot_id: Uuid::nil(),
+            trigger_kind: kind,
+            pattern: pattern.to_string(),
+            response: response.to_string(),
+            priority,
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_matching_trigger_bypasses_llm() {
+        let mut disabled = reply(TriggerKind::Prefix, "refund", "disabled", 100);
+        disabled.enabled = false;
+        let rules = compile(&[
+            reply(TriggerKind::Exact, "Hello", "Hi! How can I help?", 0),
+            reply(TriggerKind::Prefix, "refund", "See our refund policy.", 1),
+            reply(
+                TriggerKind::Regex,
+                r"\b(lawsuit|lawyer)\b",
+                "Please contact legal.",
+                5,
+            ),
+            disabled,
+        ]);
+
+        // Stands in for the model call that a quick reply must skip.
+        let llm_calls = std::sync::atomic::AtomicUsize::new(0);
+        let answer = |message: &str| match find_match(&rules, message) {
+            Some(rule) => rule.response.clone(),
+            None => {
+                llm_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                "llm".to_string()
+            }
+        };
+
+        assert_eq!(answer("  hello "), "Hi! How can I help?");
+        assert_eq!(answer("Refund my order please"), "See our refund policy.");
+        // The regex rule has the higher priority.
+        assert_eq!(
+            answer("refund or I call my LAWYER"),
+            "Please contact legal."
+        );
+        assert_eq!(llm_calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        assert_eq!(answer("hello there"), "llm");
+        assert_eq!(llm_calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_invalid_regex_is_rejected() {
+        let req = QuickReplyRequest {
+            trigger_kind: TriggerKind::Regex,
+            pattern: "(unclosed".to_string(),
+            response: "x".to_string(),
+            priority: 0,
+            enabled: true,
+        };
+        assert!(req.validate().is_err());
+    }
+}
@@ -7,7 +7,9 @@ use std::io::Write;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::basic::keywords::session_variables::json_to_dynamic;
 use crate::basic::ScriptService;
+use crate::core::session::variables::SessionVariables;
 use crate::core::shared::state::AppState;
 use crate::core::shared::models::schema::bots;
 use diesel::prelude::*;
@@ -186,7 +188,8 @@ impl ToolExecutor {
             }
 
         // Get session for ScriptService
-        let session = match state.session_manager.lock().await.get_session_by_id(*session_id) {
+        let mut session_manager = state.session_manager.lock().await;
+        let session = match session_manager.get_session_by_id(*session_id) {
             Ok(Some(sess)) => sess,
             Ok(None) => {
                 let error_msg = "Session not found".to_string();
@@ -210,6 +213,15 @@ impl ToolExecutor {
             }
         };
 
+        // Values kept from earlier turns, e.g. form fields; explicit arguments win
+        let session_variables = session_manager
+            .session_variables(*session_id)
+            .unwrap_or_else(|e| {
+                log::warn!("[TOOL_EXEC] Session variables unavailable for {}: {}", session_id, e);
+                Default::default()
+            });
+        drop(session_manager);
+
             // Execute in blocking thread for ScriptService (which is not async)
             let bot_name_clone = bot_name.to_string();
             let tool_name_clone = tool_call.tool_name.clone();
@@ -227,6 +239,7 @@ impl ToolExecutor {
                     &ast_content,
                     &tool_name_clone,
                     &arguments_clone,
+                    &session_variables,
                 )
             })
             .await;
@@ -255,6 +268,7 @@ impl ToolExecutor {
         ast_content: &str,
         tool_name: &str,
         arguments: &Value,
+        session_variables: &SessionVariables,
     ) -> ToolExecutionResult {
         let tool_call_id = format!("tool_{}", uuid::Uuid::new_v4());
         log::info!("[BASIC_EXEC] Tool '{}' starting execution (bot={}, session={})", tool_name, bot_name, session.id);
@@ -263,6 +277,13 @@ impl ToolExecutor {
         let mut script_service = ScriptService::new(state.clone(), session.clone());
         script_service.load_bot_config_params(state, bot_id);
 
+        // Session variables first, so tool parameters of the same name replace them
+        for (key, value) in session_variables.iter() {
+            script_service
+                .scope
+                .set_or_push(key.clone(), json_to_dynamic(value));
+        }
+
         // Set tool parameters as variables in the engine scope
         // Note: DATE parameters are now sent by LLM in ISO 8601 format (YYYY-MM-DD)
         // The tool schema with format="date" tells the LLM to use this agnostic format
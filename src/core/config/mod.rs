@@ -25,7 +25,450 @@ pub struct AppConfig {
     pub email: EmailConfig,
     pub site_path: String,
     pub data_dir: String,
+    pub database_pool: DatabasePoolConfig,
+    /// Optional read-replica connection string (`DATABASE_REPLICA_URL`). When unset,
+    /// read-only queries use the primary pool.
+    pub database_replica_url: Option<String>,
+    pub tls: ServerTlsConfig,
+    pub site: StaticSiteConfig,
+    pub proxy: OutboundProxyConfig,
+    pub http_timeouts: HttpTimeoutConfig,
 }
+
+fn replica_url_from_env() -> Option<String> {
+    std::env::var("DATABASE_REPLICA_URL")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Sizing for the r2d2 database pool. Read from the environment (not bot config),
+/// because the pool has to exist before the configuration table can be queried.
+///
+/// Defaults suit a single small deployment (a handful of bots, tens of concurrent
+/// users). For large deployments raise `DB_POOL_MAX_SIZE` to 50-100 and
+/// `DB_POOL_MIN_IDLE` to 10, keeping the sum across all instances below the
+/// PostgreSQL `max_connections` setting.
+#[derive(Clone, Debug)]
+pub struct DatabasePoolConfig {
+    pub max_size: u32,
+    pub min_idle: Option<u32>,
+    pub connection_timeout_secs: u64,
+    pub idle_timeout_secs: u64,
+    pub max_lifetime_secs: u64,
+    /// Checkouts that wait longer than this are logged as pool saturation.
+    pub checkout_warn_ms: u64,
+}
+
+impl Default for DatabasePoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 10,
+            min_idle: Some(1),
+            connection_timeout_secs: 5,
+            idle_timeout_secs: 300,
+            max_lifetime_secs: 1800,
+            checkout_warn_ms: 500,
+        }
+    }
+}
+
+impl DatabasePoolConfig {
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        let min_idle = match std::env::var("DB_POOL_MIN_IDLE") {
+            Ok(v) => v.trim().parse().ok(),
+            Err(_) => defaults.min_idle,
+        };
+
+        Self {
+            max_size: env_or("DB_POOL_MAX_SIZE", defaults.max_size).max(1),
+            min_idle,
+            connection_timeout_secs: env_or("DB_POOL_CONNECTION_TIMEOUT", defaults.connection_timeout_secs),
+            idle_timeout_secs: env_or("DB_POOL_IDLE_TIMEOUT", defaults.idle_timeout_secs),
+            max_lifetime_secs: env_or("DB_POOL_MAX_LIFETIME", defaults.max_lifetime_secs),
+            checkout_warn_ms: env_or("DB_POOL_CHECKOUT_WARN_MS", defaults.checkout_warn_ms),
+        }
+    }
+}
+
+/// Thread counts for the Tokio runtime. Read from the environment, because the runtime
+/// is built before anything else runs.
+///
+/// `RUNTIME_WORKER_THREADS` (or Tokio's own `TOKIO_WORKER_THREADS`) defaults to the
+/// number of CPUs. `RUNTIME_MAX_BLOCKING_THREADS` caps the pool used by
+/// `spawn_blocking`, which runs every Diesel query, IMAP session and file conversion;
+/// it defaults to Tokio's 512.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    pub worker_threads: usize,
+    pub max_blocking_threads: usize,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4),
+            max_blocking_threads: 512,
+        }
+    }
+}
+
+impl RuntimeConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let count = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| lookup(key).and_then(|v| v.trim().parse::<usize>().ok()))
+                .filter(|n| *n > 0)
+        };
+        let defaults = Self::default();
+        Self {
+            worker_threads: count(&["RUNTIME_WORKER_THREADS", "TOKIO_WORKER_THREADS"])
+                .unwrap_or(defaults.worker_threads),
+            max_blocking_threads: count(&["RUNTIME_MAX_BLOCKING_THREADS"])
+                .unwrap_or(defaults.max_blocking_threads),
+        }
+    }
+
+    pub fn build(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(self.worker_threads)
+            .max_blocking_threads(self.max_blocking_threads)
+            .enable_all()
+            .build()
+    }
+}
+
+/// Size limits for request bodies, read from the environment.
+///
+/// `HTTP_JSON_BODY_LIMIT` caps bodies read by the API's JSON extractor.
+/// `HTTP_TEXT_BODY_LIMIT` caps every other body axum reads: plain text, raw bytes, forms
+/// and multipart uploads. Both are in bytes and default to 2 MiB, axum's own default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BodyLimitConfig {
+    pub json_bytes: usize,
+    pub text_bytes: usize,
+}
+
+impl Default for BodyLimitConfig {
+    fn default() -> Self {
+        Self {
+            json_bytes: 2 * 1024 * 1024,
+            text_bytes: 2 * 1024 * 1024,
+        }
+    }
+}
+
+impl BodyLimitConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let bytes = |key: &str| {
+            lookup(key)
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .filter(|n| *n > 0)
+        };
+        let defaults = Self::default();
+        Self {
+            json_bytes: bytes("HTTP_JSON_BODY_LIMIT").unwrap_or(defaults.json_bytes),
+            text_bytes: bytes("HTTP_TEXT_BODY_LIMIT").unwrap_or(defaults.text_bytes),
+        }
+    }
+}
+
+/// Protocol constraints for the HTTPS listener, read from the environment.
+///
+/// `TLS_MIN_VERSION` is `1.2` (default) or `1.3`. `TLS_CIPHER_SUITES` is an optional
+/// comma-separated allow-list of rustls suite names such as `TLS13_AES_256_GCM_SHA384`;
+/// unset means the provider's defaults, which only include AEAD suites with forward
+/// secrecy. Certificate files are checked for changes every `TLS_CERT_RELOAD_SECS`
+/// seconds (default 60, `0` disables reloading). When `TLS_HTTP_REDIRECT_PORT` is set
+/// (usually `80`), a plain HTTP listener on that port redirects to HTTPS.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerTlsConfig {
+    pub min_version: TlsMinVersion,
+    pub cipher_suites: Option<Vec<String>>,
+    pub cert_reload_secs: u64,
+    pub http_redirect_port: Option<u16>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsMinVersion {
+    Tls12,
+    Tls13,
+}
+
+impl TlsMinVersion {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "1.2" | "tls1.2" | "tlsv1.2" => Some(Self::Tls12),
+            "1.3" | "tls1.3" | "tlsv1.3" => Some(Self::Tls13),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ServerTlsConfig {
+    fn default() -> Self {
+        Self {
+            min_version: TlsMinVersion::Tls12,
+            cipher_suites: None,
+            cert_reload_secs: 60,
+            http_redirect_port: None,
+        }
+    }
+}
+
+impl ServerTlsConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let min_version = match std::env::var("TLS_MIN_VERSION") {
+            Ok(v) => TlsMinVersion::parse(&v).unwrap_or_else(|| {
+                log::warn!("Ignoring invalid TLS_MIN_VERSION '{}', using TLS 1.2", v);
+                defaults.min_version
+            }),
+            Err(_) => defaults.min_version,
+        };
+        let cipher_suites = std::env::var("TLS_CIPHER_SUITES")
+            .ok()
+            .map(|v| {
+                v.split(',')
+                    .map(|s| s.trim().to_ascii_uppercase())
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|suites| !suites.is_empty());
+        let cert_reload_secs = std::env::var("TLS_CERT_RELOAD_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(defaults.cert_reload_secs);
+        let http_redirect_port = std::env::var("TLS_HTTP_REDIRECT_PORT")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|port| *port != 0);
+
+        Self {
+            min_version,
+            cipher_suites,
+            cert_reload_secs,
+            http_redirect_port,
+        }
+    }
+}
+
+/// How the static site under `site_path` is served, read from the environment.
+///
+/// The site is mounted at `SITE_MOUNT_PATH` (default `/static`). With `SITE_SPA_FALLBACK`
+/// enabled (the default), GET requests under the mount that match no file and do not look
+/// like a file themselves are answered with `SITE_INDEX_FILE` (default `index.html`), so
+/// deep links into client-side routed apps load the app instead of a 404.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaticSiteConfig {
+    pub mount_path: String,
+    pub spa_fallback: bool,
+    pub index_file: String,
+}
+
+impl Default for StaticSiteConfig {
+    fn default() -> Self {
+        Self {
+            mount_path: "/static".to_string(),
+            spa_fallback: true,
+            index_file: "index.html".to_string(),
+        }
+    }
+}
+
+impl StaticSiteConfig {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let mount_path = match std::env::var("SITE_MOUNT_PATH") {
+            Ok(v) => {
+                let path = format!("/{}", v.trim().trim_matches('/'));
+                if path == "/" {
+                    log::warn!(
+                        "SITE_MOUNT_PATH cannot be the root, using {}",
+                        defaults.mount_path
+                    );
+                    defaults.mount_path
+                } else {
+                    path
+                }
+            }
+            Err(_) => defaults.mount_path,
+        };
+        let spa_fallback = std::env::var("SITE_SPA_FALLBACK")
+            .map(|v| {
+                !matches!(
+                    v.trim().to_lowercase().as_str(),
+                    "false" | "0" | "no" | "off"
+                )
+            })
+            .unwrap_or(defaults.spa_fallback);
+        let index_file = std::env::var("SITE_INDEX_FILE")
+            .ok()
+            .map(|v| v.trim().trim_start_matches('/').to_string())
+            .filter(|v| !v.is_empty() && !v.split('/').any(|part| part == ".."))
+            .unwrap_or(defaults.index_file);
+
+        Self {
+            mount_path,
+            spa_fallback,
+            index_file,
+        }
+    }
+}
+
+/// Egress proxy for outbound HTTP clients built through
+/// [`crate::core::shared::outbound_proxy`].
+///
+/// Read from the standard `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` variables (upper or
+/// lower case). The `http-proxy`, `https-proxy` and `no-proxy` configuration keys take
+/// precedence when set. `NO_PROXY` is a comma-separated list of hosts, domains
+/// (`.corp.example`), IPs or CIDR ranges that are reached directly.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OutboundProxyConfig {
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+}
+
+impl OutboundProxyConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// Reads the proxy variables through `lookup`, preferring the upper-case names.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |name: &str| {
+            [name.to_string(), name.to_lowercase()]
+                .iter()
+                .find_map(|key| non_empty(lookup(key).as_deref()))
+        };
+        Self {
+            http_proxy: var("HTTP_PROXY"),
+            https_proxy: var("HTTPS_PROXY"),
+            no_proxy: var("NO_PROXY"),
+        }
+    }
+
+    pub fn with_overrides(
+        self,
+        http_proxy: Option<&str>,
+        https_proxy: Option<&str>,
+        no_proxy: Option<&str>,
+    ) -> Self {
+        Self {
+            http_proxy: non_empty(http_proxy).or(self.http_proxy),
+            https_proxy: non_empty(https_proxy).or(self.https_proxy),
+            no_proxy: non_empty(no_proxy).or(self.no_proxy),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.http_proxy.is_some() || self.https_proxy.is_some()
+    }
+}
+
+/// Deadlines for HTTP handlers, applied by
+/// [`crate::core::shared::request_timeout::request_timeout_middleware`].
+///
+/// Routes whose path starts with one of `long_prefixes` (LLM, e-mail and AI assist routes
+/// by default) get `long`; every other route gets `default`. Read from
+/// `HTTP_TIMEOUT_SECS` (default 30), `HTTP_LONG_TIMEOUT_SECS` (default 300) and the
+/// comma-separated `HTTP_LONG_TIMEOUT_PATHS`. A value of `0` disables that deadline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpTimeoutConfig {
+    pub default: std::time::Duration,
+    pub long: std::time::Duration,
+    pub long_prefixes: Vec<String>,
+}
+
+impl Default for HttpTimeoutConfig {
+    fn default() -> Self {
+        Self {
+            default: std::time::Duration::from_secs(30),
+            long: std::time::Duration::from_secs(300),
+            long_prefixes: [
+                "/api/llm/",
+                "/api/email/",
+                "/api/ui/email/",
+                "/api/attendance/llm/",
+                "/api/ai/",
+                "/api/docs/ai",
+                "/api/sheet/ai",
+                "/api/slides/ai",
+                "/api/ui/paper/ai/",
+                "/api/crm/ai/",
+                "/api/goals/ai/",
+            ]
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl HttpTimeoutConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let secs = |key: &str| {
+            lookup(key)
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(std::time::Duration::from_secs)
+        };
+        let defaults = Self::default();
+        let long_prefixes = lookup("HTTP_LONG_TIMEOUT_PATHS")
+            .map(|v| {
+                v.split(',')
+                    .filter_map(|p| non_empty(Some(p)))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|prefixes| !prefixes.is_empty())
+            .unwrap_or(defaults.long_prefixes);
+        Self {
+            default: secs("HTTP_TIMEOUT_SECS").unwrap_or(defaults.default),
+            long: secs("HTTP_LONG_TIMEOUT_SECS").unwrap_or(defaults.long),
+            long_prefixes,
+        }
+    }
+
+    /// Deadline for a request to `path`, or `None` when that group has no deadline.
+    pub fn timeout_for(&self, path: &str) -> Option<std::time::Duration> {
+        let limit = if self.long_prefixes.iter().any(|p| path.starts_with(p.as_str())) {
+            self.long
+        } else {
+            self.default
+        };
+        (!limit.is_zero()).then_some(limit)
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct DriveConfig {
     pub server: String,
@@ -321,6 +764,16 @@ impl AppConfig {
                 )?
             },
             data_dir: get_str("DATA_DIR", &format!("{}/data", crate::core::shared::utils::get_stack_path())),
+            database_pool: DatabasePoolConfig::from_env(),
+            database_replica_url: replica_url_from_env(),
+            tls: ServerTlsConfig::from_env(),
+            site: StaticSiteConfig::from_env(),
+            proxy: OutboundProxyConfig::from_env().with_overrides(
+                config_map.get("http-proxy").map(String::as_str),
+                config_map.get("https-proxy").map(String::as_str),
+                config_map.get("no-proxy").map(String::as_str),
+            ),
+            http_timeouts: HttpTimeoutConfig::from_env(),
         })
     }
     pub fn from_env() -> Result<Self, anyhow::Error> {
@@ -361,6 +814,12 @@ impl AppConfig {
 
             site_path: format!("{}/sites", crate::core::shared::utils::get_stack_path()),
             data_dir: format!("{}/data", crate::core::shared::utils::get_stack_path()),
+            database_pool: DatabasePoolConfig::from_env(),
+            database_replica_url: replica_url_from_env(),
+            tls: ServerTlsConfig::from_env(),
+            site: StaticSiteConfig::from_env(),
+            proxy: OutboundProxyConfig::from_env(),
+            http_timeouts: HttpTimeoutConfig::from_env(),
         })
     }
 }
@@ -482,63 +941,86 @@ impl ConfigManager {
         Ok(value)
     }
 
-    pub fn sync_gbot_config(&self, bot_id: &uuid::Uuid, content: &str) -> Result<usize, String> {
-        use sha2::{Digest, Sha256};
-        let mut hasher = Sha256::new();
-        hasher.update(content.as_bytes());
+    /// Replaces the bot's configuration with the entries of a `config.csv` and returns
+    /// what changed. Present keys are upserted; keys missing from the file are removed.
+    pub fn sync_gbot_config(
+        &self,
+        bot_id: &uuid::Uuid,
+        content: &str,
+    ) -> Result<ConfigDiff, String> {
+        self.apply_gbot_config(bot_id, content, false)
+    }
+
+    /// What [`Self::sync_gbot_config`] would change, without writing anything.
+    pub fn diff_gbot_config(
+        &self,
+        bot_id: &uuid::Uuid,
+        content: &str,
+    ) -> Result<ConfigDiff, String> {
+        self.apply_gbot_config(bot_id, content, true)
+    }
+
+    /// Stored configuration of a bot as `config.csv` entries, ordered by key. Encrypted
+    /// values are written as [`ENCRYPTED_PLACEHOLDER`] so secrets never end up in an
+    /// exported file, and a later sync of that file leaves them alone.
+    pub fn export_gbot_config(
+        &self,
+        target_bot_id: &uuid::Uuid,
+    ) -> Result<Vec<(String, String)>, String> {
         let mut conn = self
             .get_conn()
             .map_err(|e| format!("Failed to acquire connection: {}", e))?;
+        let mut entries = mask_encrypted(load_stored_config(&mut conn, target_bot_id)?);
+        entries.sort();
+        Ok(entries)
+    }
 
-        // Delete all existing config keys for this bot to ensure clean sync
-        diesel::sql_query("DELETE FROM bot_configuration WHERE bot_id = $1")
-            .bind::<diesel::sql_types::Uuid, _>(bot_id)
-            .execute(&mut conn)
-            .map_err(|e| format!("Failed to delete existing config: {}", e))?;
+    fn apply_gbot_config(
+        &self,
+        bot_id: &uuid::Uuid,
+        content: &str,
+        dry_run: bool,
+    ) -> Result<ConfigDiff, String> {
+        let mut conn = self
+            .get_conn()
+            .map_err(|e| format!("Failed to acquire connection: {}", e))?;
 
-        // Detect if first line is a header (has "key,value" or similar) or actual config data
-        let lines: Vec<&str> = content.lines().collect();
-        let start_idx = if lines.is_empty() {
-            return Ok(0);
-        } else {
-            let first_line = lines[0].trim().to_lowercase();
-            // Skip if first line looks like a header
-            if first_line == "key,value" 
-                || first_line == "name,value"
-                || first_line.starts_with("key,")
-                || first_line.starts_with("name,")
-                || first_line.contains("header")
-            {
-                1
-            } else {
-                0
+        let stored = load_stored_config(&mut conn, bot_id)?;
+        let (diff, entries) = plan_gbot_sync(stored, content);
+
+        if dry_run {
+            return Ok(diff);
+        }
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for key in &diff.removed {
+                diesel::sql_query(
+                    "DELETE FROM bot_configuration WHERE bot_id = $1 AND config_key = $2",
+                )
+                .bind::<diesel::sql_types::Uuid, _>(bot_id)
+                .bind::<diesel::sql_types::Text, _>(key)
+                .execute(conn)?;
             }
-        };
 
-        let mut updated = 0;
-        for line in lines.iter().skip(start_idx) {
-            let parts: Vec<&str> = line.splitn(2, ',').collect();
-            if parts.len() >= 2 {
-                let key = parts[0].trim();
-                let value = parts[1].trim();
-                
-                // Skip empty keys or lines that don't look like valid config
-                if key.is_empty() {
-                    continue;
-                }
-                
+            for (key, value) in &entries {
                 let new_id: uuid::Uuid = uuid::Uuid::new_v4();
-                diesel::sql_query("INSERT INTO bot_configuration (id, bot_id, config_key, config_value, config_type) VALUES ($1, $2, $3, $4, 'string')")
- .bind::<diesel::sql_types::Uuid, _>(new_id)
- .bind::<diesel::sql_types::Uuid, _>(bot_id)
- .bind::<diesel::sql_types::Text, _>(key)
- .bind::<diesel::sql_types::Text, _>(value)
- .execute(&mut conn)
- .map_err(|e| format!("Failed to insert config: {}", e))?;
-                updated += 1;
+                diesel::sql_query(
+                    "INSERT INTO bot_configuration (id, bot_id, config_key, config_value, config_type) \
+                     VALUES ($1, $2, $3, $4, 'string') \
+                     ON CONFLICT (bot_id, config_key) DO UPDATE SET config_value = EXCLUDED.config_value, \
+                     is_encrypted = false, updated_at = NOW()",
+                )
+                .bind::<diesel::sql_types::Uuid, _>(new_id)
+                .bind::<diesel::sql_types::Uuid, _>(bot_id)
+                .bind::<diesel::sql_types::Text, _>(key)
+                .bind::<diesel::sql_types::Text, _>(value)
+                .execute(conn)?;
             }
-        }
-        Ok(updated)
+            Ok(())
+        })
+        .map_err(|e| format!("Failed to write config: {}", e))?;
+
+        Ok(diff)
     }
 
     /// Set a single configuration value for a bot (upsert)
@@ -565,3 +1047,343 @@ impl ConfigManager {
         Ok(())
     }
 }
+
+/// Value written to an exported `config.csv` in place of an encrypted setting.
+pub const ENCRYPTED_PLACEHOLDER: &str = "<encrypted>";
+
+/// A bot's stored configuration rows as key, value and whether the value is encrypted.
+fn load_stored_config(
+    conn: &mut PgConnection,
+    target_bot_id: &uuid::Uuid,
+) -> Result<Vec<(String, String, bool)>, String> {
+    use crate::core::shared::models::schema::bot_configuration::dsl::*;
+
+    bot_configuration
+        .filter(bot_id.eq(target_bot_id))
+        .select((config_key, config_value, is_encrypted))
+        .load::<(String, String, bool)>(conn)
+        .map_err(|e| format!("Failed to load existing config: {}", e))
+}
+
+/// Stored rows as `config.csv` entries, with encrypted values replaced by
+/// [`ENCRYPTED_PLACEHOLDER`].
+fn mask_encrypted(stored: Vec<(String, String, bool)>) -> Vec<(String, String)> {
+    stored
+        .into_iter()
+        .map(|(key, value, encrypted)| {
+            let value = if encrypted {
+                ENCRYPTED_PLACEHOLDER.to_string()
+            } else {
+                value
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// What syncing `content` over the stored rows changes, and the entries to write.
+/// Encrypted settings missing from the file are kept, and [`ENCRYPTED_PLACEHOLDER`]
+/// values are never written, so syncing an exported file leaves secrets in place.
+fn plan_gbot_sync(
+    stored: Vec<(String, String, bool)>,
+    content: &str,
+) -> (ConfigDiff, Vec<(String, String)>) {
+    let encrypted: std::collections::HashSet<String> = stored
+        .iter()
+        .filter(|(_, _, encrypted)| *encrypted)
+        .map(|(key, _, _)| key.clone())
+        .collect();
+    let existing: HashMap<String, String> = mask_encrypted(stored).into_iter().collect();
+
+    let mut entries = parse_gbot_config(content);
+    entries.retain(|(key, value)| value != ENCRYPTED_PLACEHOLDER || existing.contains_key(key));
+    let mut diff = ConfigDiff::between(&existing, &entries);
+    diff.removed.retain(|key| !encrypted.contains(key));
+
+    entries.retain(|(_, value)| value != ENCRYPTED_PLACEHOLDER);
+    (diff, entries)
+}
+
+fn is_gbot_header(line: &str) -> bool {
+    let line = line.trim().to_lowercase();
+    line == "key,value"
+        || line == "name,value"
+        || line.starts_with("key,")
+        || line.starts_with("name,")
+        || line.contains("header")
+}
+
+/// Key of a `config.csv` entry line; `None` for blank, `#` comment and malformed lines.
+fn gbot_entry(line: &str) -> Option<(&str, &str)> {
+    if line.trim_start().starts_with('#') {
+        return None;
+    }
+    let (key, value) = line.split_once(',')?;
+    let key = key.trim();
+    (!key.is_empty()).then(|| (key, value.trim()))
+}
+
+/// Key/value entries of a bot `config.csv`, in file order. A leading `key,value` style
+/// header, `#` comments and lines without a key are skipped.
+pub fn parse_gbot_config(content: &str) -> Vec<(String, String)> {
+    let start_idx = usize::from(content.lines().next().is_some_and(is_gbot_header));
+    content
+        .lines()
+        .skip(start_idx)
+        .filter_map(gbot_entry)
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Writes `entries` as a `config.csv`. When `existing` is given its header, comments and
+/// line order are kept: changed values are replaced in place, keys no longer present are
+/// dropped and new keys are appended.
+pub fn render_gbot_config(existing: Option<&str>, entries: &[(String, String)]) -> String {
+    let values: HashMap<&str, &str> = entries
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    let mut written: std::collections::HashSet<&str> = std::collections::HashSet::new();
+    let mut out = String::new();
+
+    let existing = existing.filter(|content| !content.trim().is_empty());
+    match existing {
+        Some(content) => {
+            for (index, line) in content.lines().enumerate() {
+                if index == 0 && is_gbot_header(line) {
+                    out.push_str(line);
+                    out.push('\n');
+                    continue;
+                }
+                match gbot_entry(line) {
+                    None => {
+                        out.push_str(line);
+                        out.push('\n');
+                    }
+                    Some((key, old)) => {
+                        let Some((&key, &value)) = values.get_key_value(key) else {
+                            continue;
+                        };
+                        if !written.insert(key) {
+                            continue;
+                        }
+                        if old == value {
+                            out.push_str(line);
+                        } else {
+                            out.push_str(&format!("{},{}", key, value));
+                        }
+                        out.push('\n');
+                    }
+                }
+            }
+        }
+        None => out.push_str("name,value\n"),
+    }
+
+    for (key, value) in entries {
+        if written.insert(key.as_str()) {
+            out.push_str(&format!("{},{}\n", key, value));
+        }
+    }
+    out
+}
+
+/// Effect of a config sync on a bot's stored configuration, by key.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct ConfigDiff {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ConfigDiff {
+    pub fn between(existing: &HashMap<String, String>, incoming: &[(String, String)]) -> Self {
+        let incoming: HashMap<&str, &str> = incoming
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let mut diff = Self::default();
+        for (key, value) in &incoming {
+            match existing.get(*key) {
+                None => diff.added.push(key.to_string()),
+                Some(old) if old != value => diff.updated.push(key.to_string()),
+                Some(_) => diff.unchanged.push(key.to_string()),
+            }
+        }
+        diff.removed = existing
+            .keys()
+            .filter(|key| !incoming.contains_key(key.as_str()))
+            .cloned()
+            .collect();
+        for keys in [
+            &mut diff.added,
+            &mut diff.updated,
+            &mut diff.unchanged,
+            &mut diff.removed,
+        ] {
+            keys.sort();
+        }
+        diff
+    }
+
+    pub fn has_changes(&self) -> bool {
+        !(self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty())
+    }
+}
+
+impl std::fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} added, {} updated, {} unchanged, {} removed",
+            self.added.len(),
+            self.updated.len(),
+            self.unchanged.len(),
+            self.removed.len()
+        )?;
+        for (label, keys) in [
+            ("added", &self.added),
+            ("updated", &self.updated),
+            ("removed", &self.removed),
+        ] {
+            if !keys.is_empty() {
+                write!(f, "; {}: {}", label, keys.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_config_diff_classifies_keys() {
+        let existing = HashMap::from([
+            ("llm-model".to_string(), "gpt-4".to_string()),
+            ("theme-color1".to_string(), "#000".to_string()),
+            ("old-key".to_string(), "x".to_string()),
+        ]);
+        let entries = parse_gbot_config(
+            "name,value\nllm-model,gpt-5\ntheme-color1, #000\nnew-key,1\n,skip\n",
+        );
+        assert_eq!(entries.len(), 3);
+
+        let diff = ConfigDiff::between(&existing, &entries);
+        assert_eq!(diff.added, ["new-key"]);
+        assert_eq!(diff.updated, ["llm-model"]);
+        assert_eq!(diff.unchanged, ["theme-color1"]);
+        assert_eq!(diff.removed, ["old-key"]);
+        assert!(diff.has_changes());
+        assert_eq!(
+            diff.to_string(),
+            "1 added, 1 updated, 1 unchanged, 1 removed; added: new-key; updated: llm-model; removed: old-key"
+        );
+
+        let same = ConfigDiff::between(&existing, &[("llm-model".into(), "gpt-4".into())]);
+        assert!(same.has_changes());
+        assert!(!ConfigDiff::between(&HashMap::new(), &[]).has_changes());
+    }
+
+    #[test]
+    fn test_render_keeps_comments_and_round_trips() {
+        let existing =
+            "name,value\n# Model\nllm-model,gpt-4\n\n# Look\ntheme-color1 , #000\nold-key,x\n";
+        let entries = vec![
+            ("llm-model".to_string(), "gpt-5".to_string()),
+            ("theme-color1".to_string(), "#000".to_string()),
+            ("new-key".to_string(), "a,b".to_string()),
+        ];
+
+        let rendered = render_gbot_config(Some(existing), &entries);
+        assert_eq!(
+            rendered,
+            "name,value\n# Model\nllm-model,gpt-5\n\n# Look\ntheme-color1 , #000\nnew-key,a,b\n"
+        );
+        assert_eq!(parse_gbot_config(&rendered), entries);
+
+        let fresh = render_gbot_config(None, &entries[..1]);
+        assert_eq!(fresh, "name,value\nllm-model,gpt-5\n");
+    }
+
+    #[test]
+    fn test_exported_secrets_survive_a_sync() {
+        let stored = vec![
+            ("llm-key".to_string(), "c2VjcmV0".to_string(), true),
+            ("smtp-password".to_string(), "aHVudGVy".to_string(), true),
+            ("llm-model".to_string(), "gpt-4".to_string(), false),
+        ];
+        let mut exported = mask_encrypted(stored.clone());
+        exported.sort();
+        let csv = render_gbot_config(None, &exported);
+        assert!(!csv.contains("c2VjcmV0") && csv.contains("llm-key,<encrypted>"));
+
+        // Syncing the export back changes nothing and writes no placeholder.
+        let (diff, writes) = plan_gbot_sync(stored.clone(), &csv);
+        assert!(!diff.has_changes(), "{}", diff);
+        assert_eq!(writes, [("llm-model".to_string(), "gpt-4".to_string())]);
+
+        // Dropping a secret from the file keeps it; giving a new value replaces it.
+        let edited = "name,value\nllm-key,new-key\nllm-model,gpt-5\n";
+        let (diff, writes) = plan_gbot_sync(stored, edited);
+        assert_eq!(diff.updated, ["llm-key", "llm-model"]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(writes.len(), 2);
+    }
+
+    #[test]
+    fn test_runtime_config_applies_thread_counts() {
+        let defaults = RuntimeConfig::from_lookup(|_| None);
+        assert_eq!(defaults, RuntimeConfig::default());
+
+        let env = [
+            ("TOKIO_WORKER_THREADS", "8"),
+            ("RUNTIME_WORKER_THREADS", "3"),
+            ("RUNTIME_MAX_BLOCKING_THREADS", "0"),
+        ];
+        let config = RuntimeConfig::from_lookup(|key| {
+            env.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string())
+        });
+        assert_eq!(config.worker_threads, 3);
+        assert_eq!(config.max_blocking_threads, defaults.max_blocking_threads);
+
+        let runtime = config.build().unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 3);
+    }
+
+    #[test]
+    fn test_http_timeouts_pick_group_by_path() {
+        let defaults = HttpTimeoutConfig::from_lookup(|_| None);
+        assert_eq!(defaults, HttpTimeoutConfig::default());
+        assert_eq!(defaults.timeout_for("/api/sheet/list"), Some(Duration::from_secs(30)));
+        assert_eq!(defaults.timeout_for("/api/email/list"), Some(Duration::from_secs(300)));
+        for path in [
+            "/api/docs/ai",
+            "/api/docs/ai/summarize",
+            "/api/sheet/ai",
+            "/api/ai/generate-reply",
+            "/api/crm/ai/generate",
+        ] {
+            assert_eq!(
+                defaults.timeout_for(path),
+                Some(Duration::from_secs(300)),
+                "{path}"
+            );
+        }
+
+        let env = [
+            ("HTTP_TIMEOUT_SECS", "5"),
+            ("HTTP_LONG_TIMEOUT_SECS", "0"),
+            ("HTTP_LONG_TIMEOUT_PATHS", " /api/reports/ , "),
+        ];
+        let config = HttpTimeoutConfig::from_lookup(|key| {
+            env.iter().find(|(k, _)| *k == key).map(|(_, v)| v.to_string())
+        });
+        assert_eq!(config.long_prefixes, ["/api/reports/"]);
+        assert_eq!(config.timeout_for("/api/email/list"), Some(Duration::from_secs(5)));
+        assert_eq!(config.timeout_for("/api/reports/yearly"), None);
+    }
+}
@@ -134,8 +134,8 @@ impl ConfigWatcher {
                     }).await;
 
                     match sync_result {
-                        Ok(Ok(updated)) => {
-                            info!("Reloaded config for bot '{}' ({} entries updated)", bot_name_for_log, updated);
+                        Ok(Ok(diff)) => {
+                            info!("Reloaded config for bot '{}': {}", bot_name_for_log, diff);
 
                             // Trigger immediate LLM config refresh
                             if let Some(dynamic_llm) = &self.state.dynamic_llm_provider {
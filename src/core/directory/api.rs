@@ -1,4 +1,5 @@
 use crate::core::directory::{BotAccess, UserAccount, UserProvisioningService, UserRole};
+use crate::core::shared::api_json::ApiJson;
 use crate::core::urls::ApiUrls;
 use crate::core::shared::state::AppState;
 use crate::core::shared::utils::create_tls_client;
@@ -49,7 +50,7 @@ pub struct ServiceStatusResponse {
 
 pub async fn provision_user_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<CreateUserRequest>,
+    ApiJson(request): ApiJson<CreateUserRequest>,
 ) -> impl IntoResponse {
     let mut account = UserAccount {
         username: request.username.clone(),
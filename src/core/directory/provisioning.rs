@@ -100,6 +100,10 @@ impl UserProvisioningService {
 
         self.setup_oauth_config(&user_id, account)?;
 
+        if let Err(e) = self.register_tenant_route(account).await {
+            log::warn!("Proxy route registration failed: {}", e);
+        }
+
         let profile_url = self.build_profile_url(&account.username);
         log::info!(
             "User {} provisioned successfully. Profile: {}",
@@ -224,7 +228,8 @@ impl UserProvisioningService {
                 user_email_accounts::smtp_server.eq("localhost"),
                 user_email_accounts::smtp_port.eq(465),
                 user_email_accounts::username.eq(&account.username),
-                user_email_accounts::password_encrypted.eq("oauth"),
+                user_email_accounts::password_encrypted
+                    .eq(crate::security::master_key::OAUTH_PASSWORD_MARKER),
                 user_email_accounts::is_active.eq(true),
             ))
             .execute(&mut conn)?;
@@ -275,6 +280,29 @@ impl UserProvisioningService {
         Ok(())
     }
 
+    /// Proxies the organization's subdomain of `DNS_DOMAIN` to this server. Registering
+    /// an existing route again only repoints it, so every user of a tenant can trigger it.
+    async fn register_tenant_route(&self, account: &UserAccount) -> Result<()> {
+        let Some(proxy) = crate::core::proxy::caddy_routes() else {
+            log::debug!("No Caddyfile to manage, skipping proxy route");
+            return Ok(());
+        };
+        let Some(label) = tenant_label(&account.organization) else {
+            log::debug!(
+                "User {} has no organization, skipping proxy route",
+                account.username
+            );
+            return Ok(());
+        };
+
+        let domain = crate::core::dns::DnsConfig::from_env().domain;
+        let hostname = format!("{}.{}", label, domain);
+        proxy
+            .register_route(&hostname, &crate::core::proxy::tenant_upstream())
+            .await?;
+        Ok(())
+    }
+
     pub async fn deprovision_user(&self, username: &str) -> Result<()> {
         log::info!("Deprovisioning user: {}", username);
 
@@ -367,3 +395,32 @@ impl UserProvisioningService {
         Ok(())
     }
 }
+
+/// The organization name as a host name label: lowercase letters, digits and hyphens.
+fn tenant_label(organization: &str) -> Option<String> {
+    let mut label = String::new();
+    for c in organization.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() {
+            label.push(c);
+        } else if !label.is_empty() && !label.ends_with('-') {
+            label.push('-');
+        }
+    }
+    label.truncate(63);
+    let label = label.trim_end_matches('-');
+    (!label.is_empty()).then(|| label.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_label_is_a_host_name_label() {
+        assert_eq!(tenant_label("Acme Corp."), Some("acme-corp".to_string()));
+        assert_eq!(tenant_label("  tenant1 "), Some("tenant1".to_string()));
+        assert_eq!(tenant_label("--"), None);
+        assert_eq!(tenant_label(""), None);
+        assert_eq!(tenant_label(&"a".repeat(80)).map(|l| l.len()), Some(63));
+    }
+}
@@ -1,16 +1,66 @@
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Write;
 use std::fs;
-use std::net::IpAddr;
-use std::path::PathBuf;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::urls::ApiUrls;
 
+/// Names served by the core stack; neither the API nor dynamic registration may take them.
+const STATIC_RECORDS: &[&str] = &["ns1", "api", "auth", "llm", "mail", "meet"];
+
+const MANAGED_MARKER: &str = "; Managed records";
+const DYNAMIC_MARKER: &str = "; Dynamic entries";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum RecordType {
+    A,
+    Cname,
+}
+
+impl RecordType {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::Cname => "CNAME",
+        }
+    }
+}
+
+/// A record added through the management API, kept in the zone file's managed section.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsRecord {
+    /// Name relative to the zone, e.g. `tenant1` or `app.tenant1`.
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    /// IPv4 address for `A`, target host name for `CNAME`.
+    pub value: String,
+    pub ttl: u32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RecordError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("'{0}' is reserved for a core service")]
+    Reserved(String),
+    #[error("'{0}' is already registered dynamically")]
+    Conflict(String),
+    #[error("No record named '{0}'")]
+    NotFound(String),
+    #[error("Failed to write zone file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DnsEntry {
     pub hostname: String,
@@ -30,6 +80,31 @@ pub struct DnsConfig {
     pub cleanup_interval_hours: u64,
 }
 
+impl DnsConfig {
+    /// `DNS_ZONE_FILE` and `DNS_DOMAIN` override the defaults. Management is enabled when
+    /// the zone file's directory exists, i.e. when CoreDNS is installed.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let zone_file_path = std::env::var("DNS_ZONE_FILE")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .map(PathBuf::from)
+            .unwrap_or(defaults.zone_file_path);
+        let domain = std::env::var("DNS_DOMAIN")
+            .ok()
+            .map(|d| d.trim().trim_end_matches('.').to_lowercase())
+            .filter(|d| !d.is_empty())
+            .unwrap_or(defaults.domain);
+        let enabled = zone_file_path.parent().is_some_and(Path::is_dir);
+        Self {
+            enabled,
+            zone_file_path,
+            domain,
+            ..defaults
+        }
+    }
+}
+
 impl Default for DnsConfig {
     fn default() -> Self {
         Self {
@@ -47,6 +122,9 @@ pub struct DynamicDnsService {
     config: DnsConfig,
     entries: Arc<RwLock<HashMap<String, DnsEntry>>>,
     entries_by_ip: Arc<RwLock<HashMap<IpAddr, Vec<String>>>>,
+    records: RwLock<BTreeMap<String, DnsRecord>>,
+    last_serial: AtomicI64,
+    write_lock: Mutex<()>,
 }
 
 impl std::fmt::Debug for DynamicDnsService {
@@ -63,13 +141,45 @@ impl DynamicDnsService {
             config,
             entries: Arc::new(RwLock::new(HashMap::new())),
             entries_by_ip: Arc::new(RwLock::new(HashMap::new())),
+            records: RwLock::new(BTreeMap::new()),
+            last_serial: AtomicI64::new(0),
+            write_lock: Mutex::new(()),
         }
     }
 
+    /// A service with the managed records already in the zone file.
+    pub fn load(config: DnsConfig) -> Result<Self> {
+        let service = Self::new(config);
+        match fs::read_to_string(&service.config.zone_file_path) {
+            Ok(content) => {
+                let records = parse_managed_records(&content, service.config.ttl_seconds);
+                log::info!(
+                    "Loaded {} managed DNS records from {}",
+                    records.len(),
+                    service.config.zone_file_path.display()
+                );
+                *service.records.try_write()? = records
+                    .into_iter()
+                    .map(|r| (r.name.clone(), r))
+                    .collect();
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        Ok(service)
+    }
+
+    pub fn domain(&self) -> &str {
+        &self.config.domain
+    }
+
     pub async fn register_hostname(&self, hostname: &str, ip: IpAddr) -> Result<()> {
         if !Self::is_valid_hostname(hostname) {
             return Err(anyhow::anyhow!("Invalid hostname format"));
         }
+        if STATIC_RECORDS.contains(&hostname) || self.records.read().await.contains_key(hostname) {
+            return Err(anyhow::anyhow!("Hostname {} is already in use", hostname));
+        }
 
         if !self.check_rate_limit(&ip).await {
             return Err(anyhow::anyhow!("Rate limit exceeded for IP"));
@@ -166,8 +276,105 @@ impl DynamicDnsService {
         Ok(())
     }
 
+    pub async fn list_records(&self) -> Vec<DnsRecord> {
+        self.records.read().await.values().cloned().collect()
+    }
+
+    /// Adds or replaces a managed `A` or `CNAME` record and rewrites the zone file.
+    pub async fn upsert_record(
+        &self,
+        name: &str,
+        record_type: RecordType,
+        value: &str,
+        ttl: Option<u32>,
+    ) -> Result<DnsRecord, RecordError> {
+        let name = self.relative_name(name)?;
+        if STATIC_RECORDS.contains(&name.as_str()) {
+            return Err(RecordError::Reserved(name));
+        }
+        if self.entries.read().await.contains_key(&name) {
+            return Err(RecordError::Conflict(name));
+        }
+        let value = match record_type {
+            RecordType::A => value
+                .trim()
+                .parse::<Ipv4Addr>()
+                .map_err(|_| RecordError::Invalid(format!("'{}' is not an IPv4 address", value)))?
+                .to_string(),
+            RecordType::Cname => {
+                let target = value.trim().trim_end_matches('.').to_lowercase();
+                if !is_valid_name(&target) {
+                    return Err(RecordError::Invalid(format!(
+                        "'{}' is not a valid host name",
+                        value
+                    )));
+                }
+                if target == name || target == format!("{}.{}", name, self.config.domain) {
+                    return Err(RecordError::Invalid("A CNAME cannot point to itself".into()));
+                }
+                target
+            }
+        };
+        let record = DnsRecord {
+            name: name.clone(),
+            record_type,
+            value,
+            ttl: ttl.unwrap_or(self.config.ttl_seconds).clamp(30, 86400),
+        };
+
+        self.records.write().await.insert(name.clone(), record.clone());
+        self.update_zone_file().await.map_err(zone_write_error)?;
+        log::info!(
+            "DNS record {}.{} {} {}",
+            name,
+            self.config.domain,
+            record_type.as_str(),
+            record.value
+        );
+        Ok(record)
+    }
+
+    pub async fn remove_record(&self, name: &str) -> Result<DnsRecord, RecordError> {
+        let name = self.relative_name(name)?;
+        let removed = self
+            .records
+            .write()
+            .await
+            .remove(&name)
+            .ok_or_else(|| RecordError::NotFound(name.clone()))?;
+        self.update_zone_file().await.map_err(zone_write_error)?;
+        log::info!("Removed DNS record {}.{}", name, self.config.domain);
+        Ok(removed)
+    }
+
+    /// `name` lowercased and without the zone suffix, validated.
+    fn relative_name(&self, name: &str) -> Result<String, RecordError> {
+        let name = name.trim().trim_end_matches('.').to_lowercase();
+        let suffix = format!(".{}", self.config.domain);
+        let name = name.strip_suffix(&suffix).unwrap_or(&name).to_string();
+        if name == self.config.domain || name == "@" {
+            return Err(RecordError::Reserved(name));
+        }
+        if !is_valid_name(&name) {
+            return Err(RecordError::Invalid(format!("'{}' is not a valid record name", name)));
+        }
+        Ok(name)
+    }
+
+    /// A serial above the previous one, so CoreDNS sees every change even within a second.
+    fn next_serial(&self) -> i64 {
+        let now = Utc::now().timestamp();
+        let previous = self
+            .last_serial
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| Some(now.max(last + 1)))
+            .unwrap_or(now);
+        now.max(previous + 1)
+    }
+
     async fn update_zone_file(&self) -> Result<()> {
+        let _writing = self.write_lock.lock().await;
         let entries = self.entries.read().await;
+        let records = self.records.read().await;
 
         let mut zone_content = String::new();
         let _ = writeln!(
@@ -184,7 +391,7 @@ impl DynamicDnsService {
         let _ = writeln!(
             zone_content,
             "                        {}      ; Serial",
-            Utc::now().timestamp()
+            self.next_serial()
         );
         zone_content.push_str(
             "                        3600            ; Refresh\n\
@@ -201,14 +408,36 @@ impl DynamicDnsService {
         zone_content.push_str("ns1     IN      A       127.0.0.1\n\n");
 
         zone_content.push_str("; Static service entries\n");
-        zone_content.push_str("api     IN      A       127.0.0.1\n");
-        zone_content.push_str("auth    IN      A       127.0.0.1\n");
-        zone_content.push_str("llm     IN      A       127.0.0.1\n");
-        zone_content.push_str("mail    IN      A       127.0.0.1\n");
-        zone_content.push_str("meet    IN      A       127.0.0.1\n\n");
+        for name in STATIC_RECORDS.iter().filter(|n| **n != "ns1") {
+            let _ = writeln!(zone_content, "{:<8}IN      A       127.0.0.1", name);
+        }
+        zone_content.push('\n');
+
+        if !records.is_empty() {
+            zone_content.push_str(MANAGED_MARKER);
+            zone_content.push('\n');
+            for record in records.values() {
+                let value = match record.record_type {
+                    RecordType::A => record.value.clone(),
+                    // Targets with a dot are absolute; single labels stay inside the zone.
+                    RecordType::Cname if record.value.contains('.') => format!("{}.", record.value),
+                    RecordType::Cname => record.value.clone(),
+                };
+                let _ = writeln!(
+                    zone_content,
+                    "{:<16} {:<6} IN      {:<7} {}",
+                    record.name,
+                    record.ttl,
+                    record.record_type.as_str(),
+                    value
+                );
+            }
+            zone_content.push('\n');
+        }
 
         if !entries.is_empty() {
-            zone_content.push_str("; Dynamic entries\n");
+            zone_content.push_str(DYNAMIC_MARKER);
+            zone_content.push('\n');
             for (hostname, entry) in entries.iter() {
                 let _ = writeln!(
                     zone_content,
@@ -218,7 +447,10 @@ impl DynamicDnsService {
             }
         }
 
-        fs::write(&self.config.zone_file_path, zone_content)?;
+        // Write then rename, so CoreDNS never loads a half-written zone.
+        let tmp_path = self.config.zone_file_path.with_extension("zone.tmp");
+        fs::write(&tmp_path, zone_content)?;
+        fs::rename(&tmp_path, &self.config.zone_file_path)?;
         Ok(())
     }
 
@@ -260,11 +492,67 @@ impl DynamicDnsService {
     }
 }
 
+/// A host name of one or more dot-separated labels.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 253
+        && name
+            .split('.')
+            .all(DynamicDnsService::is_valid_hostname)
+}
+
+fn zone_write_error(e: anyhow::Error) -> RecordError {
+    match e.downcast::<std::io::Error>() {
+        Ok(io) => RecordError::Io(io),
+        Err(e) => RecordError::Io(std::io::Error::other(e.to_string())),
+    }
+}
+
+/// Records in the managed section of a zone file written by [`DynamicDnsService`].
+fn parse_managed_records(content: &str, default_ttl: u32) -> Vec<DnsRecord> {
+    content
+        .lines()
+        .skip_while(|line| line.trim() != MANAGED_MARKER)
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty() && !line.starts_with(';'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (name, ttl, rest) = match fields.as_slice() {
+                [name, ttl, rest @ ..] if ttl.parse::<u32>().is_ok() => {
+                    (*name, ttl.parse().ok(), rest)
+                }
+                [name, rest @ ..] => (*name, None, rest),
+                [] => return None,
+            };
+            let (record_type, value) = match rest {
+                ["IN", "A", value] => (RecordType::A, value.to_string()),
+                ["IN", "CNAME", value] => {
+                    (RecordType::Cname, value.trim_end_matches('.').to_string())
+                }
+                _ => {
+                    log::warn!("Ignoring unrecognized managed DNS record: {}", line);
+                    return None;
+                }
+            };
+            Some(DnsRecord {
+                name: name.to_string(),
+                record_type,
+                value,
+                ttl: ttl.unwrap_or(default_ttl),
+            })
+        })
+        .collect()
+}
+
+use crate::core::proxy::{CaddyRoutes, ProxyRoute, RouteError};
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::soft_delete::require_admin;
+use crate::security::auth_api::AuthenticatedUser;
 use axum::{
-    extract::{Query, State},
+    extract::{Path as UrlPath, Query, State},
     http::StatusCode,
     response::Json,
-    routing::post,
+    routing::{delete, get, post},
     Router,
 };
 
@@ -324,3 +612,195 @@ pub fn configure_dns_routes(dns_service: Arc<DynamicDnsService>) -> Router {
         .route(ApiUrls::DNS_REMOVE, post(remove_hostname_handler))
         .with_state(dns_service)
 }
+
+/// State for the record endpoints. With a proxy configured, a record created with an
+/// `upstream` also gets a Caddy site block, and deleting the record removes it.
+#[derive(Clone)]
+pub struct RecordRoutesState {
+    pub dns: Arc<DynamicDnsService>,
+    pub proxy: Option<Arc<CaddyRoutes>>,
+}
+
+impl From<RecordError> for ApiError {
+    fn from(e: RecordError) -> Self {
+        match &e {
+            RecordError::Invalid(_) => ApiError::bad_request(e.to_string()),
+            RecordError::Reserved(_) | RecordError::Conflict(_) => {
+                ApiError::conflict(e.to_string())
+            }
+            RecordError::NotFound(_) => ApiError::not_found(e.to_string()),
+            RecordError::Io(_) => ApiError::internal(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecordRequest {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    pub value: String,
+    pub ttl: Option<u32>,
+    /// `host:port` to reverse-proxy the record's host name to.
+    pub upstream: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordResponse {
+    #[serde(flatten)]
+    pub record: DnsRecord,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route: Option<ProxyRoute>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordsResponse {
+    pub domain: String,
+    pub records: Vec<DnsRecord>,
+}
+
+pub async fn list_records_handler(
+    State(state): State<RecordRoutesState>,
+    user: AuthenticatedUser,
+) -> Result<Json<RecordsResponse>, ApiError> {
+    require_admin(&user)?;
+    Ok(Json(RecordsResponse {
+        domain: state.dns.domain().to_string(),
+        records: state.dns.list_records().await,
+    }))
+}
+
+pub async fn upsert_record_handler(
+    State(state): State<RecordRoutesState>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<RecordRequest>,
+) -> Result<Json<RecordResponse>, ApiError> {
+    require_admin(&user)?;
+    if req.upstream.is_some() && state.proxy.is_none() {
+        return Err(ApiError::service_unavailable(
+            "Reverse proxy routes are not configured on this server",
+        ));
+    }
+    let record = state
+        .dns
+        .upsert_record(&req.name, req.record_type, &req.value, req.ttl)
+        .await?;
+    let route = match (&state.proxy, req.upstream.as_deref()) {
+        (Some(proxy), Some(upstream)) => {
+            let hostname = format!("{}.{}", record.name, state.dns.domain());
+            Some(proxy.register_route(&hostname, upstream).await?)
+        }
+        _ => None,
+    };
+    Ok(Json(RecordResponse { record, route }))
+}
+
+pub async fn remove_record_handler(
+    State(state): State<RecordRoutesState>,
+    user: AuthenticatedUser,
+    UrlPath(name): UrlPath<String>,
+) -> Result<StatusCode, ApiError> {
+    require_admin(&user)?;
+    let record = state.dns.remove_record(&name).await?;
+    if let Some(proxy) = &state.proxy {
+        let hostname = format!("{}.{}", record.name, state.dns.domain());
+        match proxy.remove_route(&hostname).await {
+            Ok(()) | Err(RouteError::NotFound(_)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Admin endpoints editing the records CoreDNS serves from the zone file. CoreDNS picks
+/// up the rewritten file on its next reload check.
+pub fn configure_record_routes<S>(
+    dns_service: Arc<DynamicDnsService>,
+    proxy: Option<Arc<CaddyRoutes>>,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route(
+            ApiUrls::DNS_RECORDS,
+            get(list_records_handler).post(upsert_record_handler),
+        )
+        .route(ApiUrls::DNS_RECORD, delete(remove_record_handler))
+        .with_state(RecordRoutesState {
+            dns: dns_service,
+            proxy,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(dir: &tempfile::TempDir) -> DynamicDnsService {
+        DynamicDnsService::new(DnsConfig {
+            enabled: true,
+            zone_file_path: dir.path().join("botserver.local.zone"),
+            ..DnsConfig::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_added_record_appears_in_zone_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let dns = service(&dir);
+
+        dns.upsert_record("Tenant1.botserver.local", RecordType::A, "10.0.0.7", None)
+            .await
+            .unwrap();
+        dns.upsert_record("www.tenant1", RecordType::Cname, "tenant1", Some(300))
+            .await
+            .unwrap();
+
+        let zone = fs::read_to_string(dir.path().join("botserver.local.zone")).unwrap();
+        assert!(zone.contains("tenant1          60     IN      A       10.0.0.7"));
+        assert!(zone.contains("www.tenant1      300    IN      CNAME   tenant1"));
+        assert!(zone.contains("api     IN      A       127.0.0.1"));
+
+        // Records survive a restart.
+        let reloaded = DynamicDnsService::load(DnsConfig {
+            zone_file_path: dir.path().join("botserver.local.zone"),
+            ..DnsConfig::default()
+        })
+        .unwrap();
+        assert_eq!(reloaded.list_records().await, dns.list_records().await);
+
+        dns.remove_record("tenant1").await.unwrap();
+        let zone = fs::read_to_string(dir.path().join("botserver.local.zone")).unwrap();
+        assert!(!zone.contains("10.0.0.7"));
+    }
+
+    #[tokio::test]
+    async fn test_core_entries_and_bad_names_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let dns = service(&dir);
+
+        assert!(matches!(
+            dns.upsert_record("api", RecordType::A, "10.0.0.1", None).await,
+            Err(RecordError::Reserved(_))
+        ));
+        assert!(matches!(
+            dns.upsert_record("@", RecordType::A, "10.0.0.1", None).await,
+            Err(RecordError::Reserved(_))
+        ));
+        for bad in ["-x", "a..b", "under_score", ""] {
+            assert!(matches!(
+                dns.upsert_record(bad, RecordType::A, "10.0.0.1", None).await,
+                Err(RecordError::Invalid(_))
+            ));
+        }
+        assert!(matches!(
+            dns.upsert_record("x", RecordType::A, "not-an-ip", None).await,
+            Err(RecordError::Invalid(_))
+        ));
+        assert!(dns
+            .register_hostname("mail", "10.0.0.1".parse().unwrap())
+            .await
+            .is_err());
+    }
+}
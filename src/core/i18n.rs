@@ -1,14 +1,17 @@
 use axum::{
     async_trait,
     extract::{FromRequestParts, Path, State},
-    http::{header::ACCEPT_LANGUAGE, request::Parts},
+    http::{header::ACCEPT_LANGUAGE, request::Parts, HeaderMap},
     response::IntoResponse,
     routing::get,
     Json, Router,
 };
 use botlib::i18n::{self, Locale as BotlibLocale, MessageArgs as BotlibMessageArgs};
+use diesel::prelude::*;
+use diesel::sql_types::{Jsonb, Uuid as DieselUuid};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, RwLock};
+use uuid::Uuid;
 
 use crate::core::shared::state::AppState;
 
@@ -138,26 +141,233 @@ fn parse_accept_language(header: &str) -> Vec<(String, f32)> {
 fn negotiate_locale(requested: &[(String, f32)]) -> Option<Locale> {
     for (lang, _) in requested {
         let requested_locale = Locale::new(lang)?;
+        if let Some(available) = match_available(&requested_locale) {
+            return Some(available);
+        }
+    }
 
-        for available in AVAILABLE_LOCALES {
-            let avail_locale = Locale::new(available)?;
+    Some(Locale::default())
+}
 
-            if requested_locale.language == avail_locale.language
-                && requested_locale.region == avail_locale.region
-            {
-                return Some(avail_locale);
-            }
-        }
+/// The available locale for `requested`: same language and region, else same language.
+fn match_available(requested: &Locale) -> Option<Locale> {
+    let available: Vec<Locale> = AVAILABLE_LOCALES
+        .iter()
+        .filter_map(|l| Locale::new(l))
+        .collect();
+    available
+        .iter()
+        .find(|l| l.language == requested.language && l.region == requested.region)
+        .or_else(|| available.iter().find(|l| l.language == requested.language))
+        .cloned()
+}
 
-        for available in AVAILABLE_LOCALES {
-            let avail_locale = Locale::new(available)?;
-            if requested_locale.language == avail_locale.language {
-                return Some(avail_locale);
-            }
-        }
+/// Locale for a chat user: the session's `locale` context value, then the user's `locale`
+/// preference, then the browser's Accept-Language, skipping values that are not available.
+pub fn resolve_locale(
+    session: Option<&str>,
+    profile: Option<&str>,
+    accept_language: Option<&str>,
+) -> Locale {
+    [session, profile]
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| Locale::new(tag.trim()))
+        .find_map(|l| match_available(&l))
+        .or_else(|| {
+            accept_language
+                .map(parse_accept_language)
+                .and_then(|langs| negotiate_locale(&langs))
+        })
+        .unwrap_or_default()
+}
+
+static CHAT_ACCEPT_LANGUAGE: LazyLock<RwLock<HashMap<Uuid, String>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Keeps the Accept-Language of a chat websocket upgrade for messages sent on it.
+pub fn remember_chat_language(session_id: Uuid, headers: &HeaderMap) {
+    if let Some(value) = headers.get(ACCEPT_LANGUAGE).and_then(|h| h.to_str().ok()) {
+        CHAT_ACCEPT_LANGUAGE
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(session_id, value.to_string());
     }
+}
 
-    Some(Locale::default())
+pub fn forget_chat_language(session_id: Uuid) {
+    CHAT_ACCEPT_LANGUAGE
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&session_id);
+}
+
+#[derive(QueryableByName)]
+struct PreferenceRow {
+    #[diesel(sql_type = Jsonb)]
+    preference_value: serde_json::Value,
+}
+
+fn profile_locale(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<Option<String>> {
+    let row: Option<PreferenceRow> = diesel::sql_query(
+        "SELECT preference_value FROM user_preferences
+         WHERE user_id = $1 AND preference_key = 'locale'",
+    )
+    .bind::<DieselUuid, _>(user_id)
+    .get_result(conn)
+    .optional()?;
+    Ok(row.and_then(|r| r.preference_value.as_str().map(str::to_string)))
+}
+
+/// [`resolve_locale`] for a chat session, given its context data when already loaded.
+pub async fn chat_locale(
+    state: &Arc<AppState>,
+    session_id: Uuid,
+    user_id: Uuid,
+    context: Option<&serde_json::Value>,
+) -> Locale {
+    let session = context
+        .and_then(|c| c.get("locale"))
+        .and_then(|l| l.as_str())
+        .map(str::to_string);
+    let profile = if session.is_some() {
+        None
+    } else {
+        let pool = state.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get().map_err(|e| e.to_string())?;
+            profile_locale(&mut conn, user_id).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to load locale preference of user {}: {}", user_id, e);
+            None
+        })
+    };
+    let accept_language = CHAT_ACCEPT_LANGUAGE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&session_id)
+        .cloned();
+    resolve_locale(
+        session.as_deref(),
+        profile.as_deref(),
+        accept_language.as_deref(),
+    )
+}
+
+/// Messages the server itself sends to chat users and API clients, by key and locale.
+/// These take precedence over the botlib catalog; English is the fallback.
+const SYSTEM_MESSAGES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "chat-thinking",
+        &[
+            ("en", "🤔 Thinking..."),
+            ("pt-BR", "🤔 Pensando..."),
+            ("es", "🤔 Pensando..."),
+            ("zh-CN", "🤔 思考中..."),
+        ],
+    ),
+    (
+        "chat-tool-failed",
+        &[
+            ("en", "Error running '{ $tool }': { $error }"),
+            ("pt-BR", "Erro ao executar '{ $tool }': { $error }"),
+            ("es", "Error al ejecutar '{ $tool }': { $error }"),
+            ("zh-CN", "执行 '{ $tool }' 时出错：{ $error }"),
+        ],
+    ),
+    (
+        "chat-llm-disabled",
+        &[
+            ("en", "LLM feature is not enabled in this build"),
+            ("pt-BR", "O recurso de LLM não está habilitado nesta versão"),
+            ("es", "La función de LLM no está habilitada en esta versión"),
+            ("zh-CN", "此版本未启用 LLM 功能"),
+        ],
+    ),
+    (
+        "chat-guardrail-refused",
+        &[
+            ("en", "I can't help with that request."),
+            ("pt-BR", "Não posso ajudar com essa solicitação."),
+            ("es", "No puedo ayudar con esa solicitud."),
+            ("zh-CN", "我无法协助处理该请求。"),
+        ],
+    ),
+    (
+        "chat-handoff",
+        &[
+            ("en", "I'm transferring you to a human attendant. Please wait a moment."),
+            ("pt-BR", "Estou transferindo você para um atendente humano. Aguarde um momento."),
+            ("es", "Te estoy transfiriendo a un agente humano. Espera un momento, por favor."),
+            ("zh-CN", "正在为您转接人工客服，请稍候。"),
+        ],
+    ),
+    (
+        "chat-connected",
+        &[
+            ("en", "Connected to bot server"),
+            ("pt-BR", "Conectado ao servidor do bot"),
+            ("es", "Conectado al servidor del bot"),
+            ("zh-CN", "已连接到机器人服务器"),
+        ],
+    ),
+    (
+        "error-session-idle",
+        &[
+            ("en", "Session expired after a period of inactivity"),
+            ("pt-BR", "A sessão expirou após um período de inatividade"),
+            ("es", "La sesión expiró tras un período de inactividad"),
+            ("zh-CN", "会话因长时间未活动已过期"),
+        ],
+    ),
+    (
+        "error-session-lifetime",
+        &[
+            ("en", "Session reached its maximum lifetime"),
+            ("pt-BR", "A sessão atingiu sua duração máxima"),
+            ("es", "La sesión alcanzó su duración máxima"),
+            ("zh-CN", "会话已达到最长有效期"),
+        ],
+    ),
+    (
+        "error-invalid-session-id",
+        &[
+            ("en", "Invalid session ID"),
+            ("pt-BR", "ID de sessão inválido"),
+            ("es", "ID de sesión no válido"),
+            ("zh-CN", "会话 ID 无效"),
+        ],
+    ),
+];
+
+/// Translates a server-generated message. Keys outside [`SYSTEM_MESSAGES`] go to the
+/// botlib catalog; `{ $name }` placeholders are filled from `args`.
+pub fn system_message(locale: &Locale, key: &str, args: &[(&str, &str)]) -> String {
+    let Some((_, entries)) = SYSTEM_MESSAGES.iter().find(|(k, _)| *k == key) else {
+        let args: MessageArgs = args
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect();
+        return t_with_args(locale, key, Some(&args));
+    };
+    let bcp47 = locale.to_bcp47();
+    let template = entries
+        .iter()
+        .find(|(l, _)| *l == bcp47)
+        .or_else(|| {
+            entries
+                .iter()
+                .find(|(l, _)| Locale::new(l).is_some_and(|l| l.language == locale.language))
+        })
+        .or_else(|| entries.iter().find(|(l, _)| *l == "en"))
+        .map_or(key, |(_, text)| *text);
+    args.iter().fold(template.to_string(), |text, (name, value)| {
+        text.replace(&format!("{{ ${name} }}"), value)
+    })
 }
 
 pub type MessageArgs = HashMap<String, String>;
@@ -1007,6 +1217,40 @@ mod tests {
         assert!(error.details.is_some());
     }
 
+    #[test]
+    fn test_resolve_locale_order() {
+        let accept = Some("es-MX,es;q=0.9");
+        assert_eq!(resolve_locale(Some("pt-BR"), Some("zh-CN"), accept).to_bcp47(), "pt-BR");
+        assert_eq!(resolve_locale(Some("ja"), Some("zh"), accept).to_bcp47(), "zh-CN");
+        assert_eq!(resolve_locale(None, None, accept).to_bcp47(), "es");
+        assert_eq!(resolve_locale(None, None, None).to_bcp47(), "en");
+    }
+
+    #[test]
+    fn test_system_messages_are_translated() {
+        let pt = Locale::new("pt-BR").unwrap();
+        let es = Locale::new("es-AR").unwrap();
+        let ja = Locale::new("ja").unwrap();
+
+        assert_eq!(system_message(&pt, "chat-thinking", &[]), "🤔 Pensando...");
+        assert_eq!(
+            system_message(&es, "error-session-idle", &[]),
+            "La sesión expiró tras un período de inactividad"
+        );
+        assert_eq!(
+            system_message(&pt, "chat-tool-failed", &[("tool", "weather"), ("error", "timeout")]),
+            "Erro ao executar 'weather': timeout"
+        );
+        // Locales without a translation fall back to English.
+        assert_eq!(system_message(&ja, "chat-connected", &[]), "Connected to bot server");
+
+        for (key, entries) in SYSTEM_MESSAGES {
+            for locale in AVAILABLE_LOCALES {
+                assert!(entries.iter().any(|(l, _)| l == locale), "{key} lacks {locale}");
+            }
+        }
+    }
+
     #[test]
     fn test_available_locales_without_init() {
         let locales = available_locales();
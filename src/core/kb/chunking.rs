@@ -0,0 +1,388 @@
+//! Chunking strategies for KB ingestion.
+//!
+//! A bot picks its strategy with `kb-chunking-strategy` (`fixed`, `sentence` or
+//! `markdown`), `kb-chunk-size` and `kb-chunk-overlap`, all in characters. Each key can be
+//! overridden for a single knowledge base by appending `-<kb name>`, e.g.
+//! `kb-chunking-strategy-manuals`. The [`ChunkingConfig::descriptor`] of the config used
+//! is recorded with every ingested source.
+
+use crate::core::config::ConfigManager;
+use crate::core::shared::utils::DbPool;
+use uuid::Uuid;
+
+/// Fragments shorter than this (after trimming) are not worth a chunk of their own.
+const MIN_CHUNK_CHARS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkingStrategy {
+    /// Windows of `chunk_size` characters ending at a word boundary, `chunk_overlap` apart.
+    #[default]
+    FixedSize,
+    /// Whole sentences packed up to `chunk_size`; the next chunk repeats the trailing
+    /// sentences that fit in `chunk_overlap`.
+    Sentence,
+    /// One chunk per markdown section; long sections are split by sentence and every
+    /// piece starts with the section heading.
+    MarkdownHeadings,
+}
+
+impl ChunkingStrategy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "fixed" | "fixed-size" => Some(Self::FixedSize),
+            "sentence" | "sentences" => Some(Self::Sentence),
+            "markdown" | "markdown-headings" | "headings" => Some(Self::MarkdownHeadings),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FixedSize => "fixed",
+            Self::Sentence => "sentence",
+            Self::MarkdownHeadings => "markdown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkingConfig {
+    pub strategy: ChunkingStrategy,
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ChunkingStrategy::FixedSize,
+            chunk_size: 1000,
+            chunk_overlap: 200,
+        }
+    }
+}
+
+/// A chunk and the character range of the source text it was taken from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkSpan {
+    pub start: usize,
+    pub end: usize,
+    pub content: String,
+}
+
+impl ChunkingConfig {
+    pub fn new(strategy: ChunkingStrategy, chunk_size: usize, chunk_overlap: usize) -> Self {
+        let chunk_size = chunk_size.max(MIN_CHUNK_CHARS * 10);
+        Self {
+            strategy,
+            chunk_size,
+            chunk_overlap: chunk_overlap.min(chunk_size / 2),
+        }
+    }
+
+    pub fn from_bot_config(pool: &DbPool, bot_id: &Uuid, kb_name: &str) -> Self {
+        let config_manager = ConfigManager::new(pool.clone());
+        let get = |key: &str| {
+            [format!("{}-{}", key, kb_name), key.to_string()]
+                .iter()
+                .find_map(|k| {
+                    config_manager
+                        .get_config(bot_id, k, Some(""))
+                        .ok()
+                        .filter(|v| !v.trim().is_empty())
+                })
+        };
+        let defaults = Self::default();
+
+        let strategy = get("kb-chunking-strategy")
+            .and_then(|v| ChunkingStrategy::parse(&v))
+            .unwrap_or(defaults.strategy);
+        let chunk_size = get("kb-chunk-size")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(defaults.chunk_size);
+        let chunk_overlap = get("kb-chunk-overlap")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(defaults.chunk_overlap);
+        Self::new(strategy, chunk_size, chunk_overlap)
+    }
+
+    /// Compact description such as `markdown:1000/200`, recorded with ingested sources.
+    pub fn descriptor(&self) -> String {
+        format!(
+            "{}:{}/{}",
+            self.strategy.as_str(),
+            self.chunk_size,
+            self.chunk_overlap
+        )
+    }
+
+    /// Splits `text` into at most `limit` chunks.
+    pub fn split(&self, text: &str, limit: usize) -> Vec<ChunkSpan> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut spans = match self.strategy {
+            ChunkingStrategy::FixedSize => {
+                fixed_size(&chars, 0, chars.len(), self.chunk_size, self.chunk_overlap)
+            }
+            ChunkingStrategy::Sentence => {
+                sentences(&chars, 0, chars.len(), self.chunk_size, self.chunk_overlap)
+            }
+            ChunkingStrategy::MarkdownHeadings => {
+                markdown_sections(&chars, self.chunk_size, self.chunk_overlap)
+            }
+        };
+        spans.truncate(limit);
+        spans
+    }
+}
+
+fn span(chars: &[char], start: usize, end: usize) -> Option<ChunkSpan> {
+    let content: String = chars[start..end].iter().collect();
+    if content.trim().chars().count() < MIN_CHUNK_CHARS {
+        return None;
+    }
+    Some(ChunkSpan {
+        start,
+        end,
+        content,
+    })
+}
+
+fn fixed_size(
+    chars: &[char],
+    from: usize,
+    to: usize,
+    size: usize,
+    overlap: usize,
+) -> Vec<ChunkSpan> {
+    let mut spans = Vec::new();
+    let mut start = from;
+    while start < to {
+        let end = (start + size).min(to);
+        let mut chunk_end = end;
+        if end < to {
+            // Break at the last whitespace within reasonable distance
+            let search_start = start.max(end.saturating_sub(100));
+            if let Some(i) = (search_start..end)
+                .rev()
+                .find(|&i| chars[i].is_whitespace())
+            {
+                chunk_end = i + 1;
+            }
+        }
+        spans.extend(span(chars, start, chunk_end));
+        if chunk_end >= to {
+            break;
+        }
+        start = chunk_end.saturating_sub(overlap).max(start + 1);
+    }
+    spans
+}
+
+/// Sentence ranges in `from..to`: each ends after `.`, `!` or `?` followed by whitespace,
+/// or at a line break. Leading whitespace is not part of a sentence.
+fn sentence_bounds(chars: &[char], from: usize, to: usize) -> Vec<(usize, usize)> {
+    let mut bounds = Vec::new();
+    let mut start = from;
+    for i in from..to {
+        let ends = chars[i] == '\n'
+            || (matches!(chars[i], '.' | '!' | '?')
+                && (i + 1 == to || chars[i + 1].is_whitespace()));
+        if ends {
+            bounds.push((start, i + 1));
+            start = i + 1;
+        }
+    }
+    if start < to {
+        bounds.push((start, to));
+    }
+    bounds
+        .into_iter()
+        .filter_map(|(s, e)| {
+            let s = (s..e).find(|&i| !chars[i].is_whitespace())?;
+            Some((s, e))
+        })
+        .collect()
+}
+
+fn sentences(
+    chars: &[char],
+    from: usize,
+    to: usize,
+    size: usize,
+    overlap: usize,
+) -> Vec<ChunkSpan> {
+    let bounds = sentence_bounds(chars, from, to);
+    let mut spans = Vec::new();
+    let mut first = 0;
+    while first < bounds.len() {
+        let start = bounds[first].0;
+        if bounds[first].1 - start > size {
+            // A sentence longer than a chunk is split like plain text
+            spans.extend(fixed_size(chars, start, bounds[first].1, size, overlap));
+            first += 1;
+            continue;
+        }
+        let mut last = first;
+        while last + 1 < bounds.len() && bounds[last + 1].1 - start <= size {
+            last += 1;
+        }
+        let end = bounds[last].1;
+        spans.extend(span(chars, start, end));
+        if last + 1 >= bounds.len() {
+            break;
+        }
+        // Repeat the trailing sentences that fit in the overlap, but always move forward
+        let mut next = last + 1;
+        while next - 1 > first && end - bounds[next - 1].0 <= overlap {
+            next -= 1;
+        }
+        first = next;
+    }
+    spans
+}
+
+fn is_heading(line: &[char]) -> bool {
+    let hashes = line.iter().take_while(|c| **c == '#').count();
+    (1..=6).contains(&hashes) && line.get(hashes).is_some_and(|c| *c == ' ')
+}
+
+fn markdown_sections(chars: &[char], size: usize, overlap: usize) -> Vec<ChunkSpan> {
+    // (section start, body start, heading line)
+    let mut sections: Vec<(usize, usize, String)> = vec![(0, 0, String::new())];
+    let mut line_start = 0;
+    while line_start < chars.len() {
+        let line_end = (line_start..chars.len())
+            .find(|&i| chars[i] == '\n')
+            .unwrap_or(chars.len());
+        let line = &chars[line_start..line_end];
+        let indent = line.iter().take_while(|c| c.is_whitespace()).count();
+        if is_heading(&line[indent..]) {
+            let heading: String = line[indent..].iter().collect();
+            sections.push((line_start, line_end, heading.trim_end().to_string()));
+        }
+        line_start = line_end + 1;
+    }
+
+    let mut spans = Vec::new();
+    for (i, (start, body_start, heading)) in sections.iter().enumerate() {
+        let end = sections.get(i + 1).map_or(chars.len(), |next| next.0);
+        if chars[*body_start..end].iter().all(|c| c.is_whitespace()) {
+            continue;
+        }
+        if end - start <= size {
+            spans.extend(span(chars, *start, end));
+            continue;
+        }
+        let body_size = size
+            .saturating_sub(heading.chars().count() + 1)
+            .max(size / 2);
+        for piece in sentences(chars, *body_start, end, body_size, overlap) {
+            let content = if heading.is_empty() {
+                piece.content
+            } else {
+                format!("{}\n{}", heading, piece.content)
+            };
+            spans.push(ChunkSpan { content, ..piece });
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text repeated at the end of `a` and the start of `b`.
+    fn shared_text(text: &str, a: &ChunkSpan, b: &ChunkSpan) -> String {
+        assert!(b.start < a.end, "chunks do not overlap");
+        let shared: String = text.chars().take(a.end).skip(b.start).collect();
+        assert!(a.content.ends_with(&shared));
+        assert!(b.content.starts_with(&shared));
+        shared
+    }
+
+    #[test]
+    fn test_fixed_and_sentence_chunks_overlap() {
+        let text = (0..40)
+            .map(|i| format!("Sentence number {i} is here."))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let fixed = ChunkingConfig::new(ChunkingStrategy::FixedSize, 200, 50).split(&text, 100);
+        assert!(fixed.len() > 1);
+        for pair in fixed.windows(2) {
+            let shared = shared_text(&text, &pair[0], &pair[1]);
+            assert!(!shared.is_empty() && shared.len() <= 50);
+        }
+
+        let by_sentence =
+            ChunkingConfig::new(ChunkingStrategy::Sentence, 200, 60).split(&text, 100);
+        assert!(by_sentence.len() > 1);
+        for chunk in &by_sentence {
+            assert!(chunk.content.starts_with("Sentence number"));
+            assert!(chunk.content.ends_with('.'));
+            assert!(chunk.content.chars().count() <= 200);
+        }
+        for pair in by_sentence.windows(2) {
+            // The next chunk repeats the previous chunk's trailing sentences
+            let shared = shared_text(&text, &pair[0], &pair[1]);
+            assert!(shared.starts_with("Sentence number") && shared.ends_with('.'));
+            assert!(shared.len() <= 60);
+        }
+        assert!(by_sentence
+            .last()
+            .unwrap()
+            .content
+            .ends_with("Sentence number 39 is here."));
+    }
+
+    #[test]
+    fn test_markdown_chunks_follow_headings() {
+        let long_body = (0..30)
+            .map(|i| format!("Step {i} of the install."))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let text = format!(
+            "Intro text before any heading.\n# Pricing\nPlans start at ten dollars.\n\n## Empty\n# Install\n{long_body}\n# FAQ\nAsk us anything."
+        );
+
+        let chunks =
+            ChunkingConfig::new(ChunkingStrategy::MarkdownHeadings, 200, 40).split(&text, 100);
+        assert_eq!(chunks[0].content, "Intro text before any heading.\n");
+        assert_eq!(
+            chunks[1].content,
+            "# Pricing\nPlans start at ten dollars.\n\n"
+        );
+        assert!(chunks.iter().all(|c| !c.content.contains("## Empty")));
+
+        let install: Vec<&ChunkSpan> = chunks
+            .iter()
+            .filter(|c| c.content.contains("Step"))
+            .collect();
+        assert!(install.len() > 1);
+        for chunk in &install {
+            assert!(chunk.content.starts_with("# Install\nStep"));
+            assert!(!chunk.content.contains("FAQ"));
+            assert!(chunk.content.chars().count() <= 200);
+        }
+        assert_eq!(chunks.last().unwrap().content, "# FAQ\nAsk us anything.");
+    }
+
+    #[test]
+    fn test_strategy_names() {
+        for strategy in [
+            ChunkingStrategy::FixedSize,
+            ChunkingStrategy::Sentence,
+            ChunkingStrategy::MarkdownHeadings,
+        ] {
+            assert_eq!(ChunkingStrategy::parse(strategy.as_str()), Some(strategy));
+        }
+        assert_eq!(
+            ChunkingStrategy::parse("Headings"),
+            Some(ChunkingStrategy::MarkdownHeadings)
+        );
+        assert_eq!(ChunkingStrategy::parse("semantic"), None);
+        let config = ChunkingConfig::new(ChunkingStrategy::Sentence, 500, 400);
+        assert_eq!(config.descriptor(), "sentence:500/250");
+    }
+}
@@ -0,0 +1,188 @@
+use crate::core::kb::web_crawler::WebPage;
+use diesel::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub fn content_hash(content: &str) -> String {
+    let digest = Sha256::digest(content.trim().as_bytes());
+    hex::encode(digest)
+}
+
+#[derive(Debug, Clone)]
+pub struct PageFingerprint {
+    pub url: String,
+    pub content_hash: String,
+}
+
+/// Result of comparing a fresh crawl with the hashes stored from the previous one.
+/// `changed` holds indexes into the crawled page list that need (re-)embedding.
+#[derive(Debug, Default)]
+pub struct CrawlDelta {
+    pub changed: Vec<usize>,
+    pub unchanged: usize,
+    pub fingerprints: Vec<PageFingerprint>,
+}
+
+impl CrawlDelta {
+    pub fn has_changes(&self) -> bool {
+        !self.changed.is_empty()
+    }
+}
+
+pub fn compute_crawl_delta(pages: &[WebPage], known_hashes: &HashMap<String, String>) -> CrawlDelta {
+    let mut delta = CrawlDelta::default();
+
+    for (idx, page) in pages.iter().enumerate() {
+        let hash = content_hash(&page.content);
+
+        if known_hashes.get(&page.url) == Some(&hash) {
+            delta.unchanged += 1;
+        } else {
+            delta.changed.push(idx);
+        }
+
+        delta.fingerprints.push(PageFingerprint {
+            url: page.url.clone(),
+            content_hash: hash,
+        });
+    }
+
+    delta
+}
+
+#[derive(QueryableByName)]
+struct PageHashRow {
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    page_url: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    content_hash: String,
+}
+
+pub fn load_page_hashes(
+    conn: &mut PgConnection,
+    crawl_id: &Uuid,
+) -> Result<HashMap<String, String>, diesel::result::Error> {
+    let rows: Vec<PageHashRow> = diesel::sql_query(
+        "SELECT page_url, content_hash FROM website_crawl_pages WHERE crawl_id = $1",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(crawl_id)
+    .load(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.page_url, r.content_hash))
+        .collect())
+}
+
+pub fn store_page_hashes(
+    conn: &mut PgConnection,
+    crawl_id: &Uuid,
+    fingerprints: &[PageFingerprint],
+) -> Result<(), diesel::result::Error> {
+    for fp in fingerprints {
+        diesel::sql_query(
+            "INSERT INTO website_crawl_pages (crawl_id, page_url, content_hash, last_seen_at, last_changed_at)
+             VALUES ($1, $2, $3, NOW(), NOW())
+             ON CONFLICT (crawl_id, page_url) DO UPDATE SET
+                 last_changed_at = CASE
+                     WHEN website_crawl_pages.content_hash <> EXCLUDED.content_hash THEN NOW()
+                     ELSE website_crawl_pages.last_changed_at
+                 END,
+                 content_hash = EXCLUDED.content_hash,
+                 last_seen_at = NOW()",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(crawl_id)
+        .bind::<diesel::sql_types::Text, _>(&fp.url)
+        .bind::<diesel::sql_types::Text, _>(&fp.content_hash)
+        .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, QueryableByName)]
+pub struct SourceStatus {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub url: String,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>)]
+    pub last_crawled: Option<chrono::DateTime<chrono::Utc>>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>)]
+    pub next_crawl: Option<chrono::DateTime<chrono::Utc>>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>)]
+    pub last_changed: Option<chrono::DateTime<chrono::Utc>>,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub item_count: i64,
+    #[diesel(sql_type = diesel::sql_types::Bool)]
+    pub change_detected: bool,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::SmallInt>)]
+    pub crawl_status: Option<i16>,
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    pub error_message: Option<String>,
+}
+
+pub fn list_source_status(
+    conn: &mut PgConnection,
+    bot_id: &Uuid,
+) -> Result<Vec<SourceStatus>, diesel::result::Error> {
+    diesel::sql_query(
+        "SELECT w.id, w.url, w.last_crawled, w.next_crawl, w.last_changed,
+                (SELECT COUNT(*) FROM website_crawl_pages p WHERE p.crawl_id = w.id) AS item_count,
+                w.change_detected, w.crawl_status, w.error_message
+         FROM website_crawls w
+         WHERE w.bot_id = $1
+         ORDER BY w.url",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(bot_id)
+    .load(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(url: &str, content: &str) -> WebPage {
+        WebPage {
+            url: url.to_string(),
+            title: None,
+            content: content.to_string(),
+            meta_description: None,
+            crawled_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_unchanged_source_is_skipped() {
+        let pages = vec![page("https://a.test/", "hello"), page("https://a.test/b", "world")];
+
+        let first = compute_crawl_delta(&pages, &HashMap::new());
+        assert_eq!(first.changed, vec![0, 1]);
+
+        let known: HashMap<String, String> = first
+            .fingerprints
+            .iter()
+            .map(|fp| (fp.url.clone(), fp.content_hash.clone()))
+            .collect();
+
+        let second = compute_crawl_delta(&pages, &known);
+        assert!(!second.has_changes());
+        assert_eq!(second.unchanged, 2);
+    }
+
+    #[test]
+    fn test_changed_page_is_detected() {
+        let known: HashMap<String, String> =
+            [("https://a.test/".to_string(), content_hash("old"))].into_iter().collect();
+
+        let delta = compute_crawl_delta(&[page("https://a.test/", "new")], &known);
+        assert_eq!(delta.changed, vec![0]);
+    }
+
+    #[test]
+    fn test_content_hash_ignores_surrounding_whitespace() {
+        assert_eq!(content_hash("  body\n"), content_hash("body"));
+    }
+}
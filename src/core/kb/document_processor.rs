@@ -6,6 +6,8 @@ use std::path::Path;
 use tokio::io::AsyncReadExt;
 use crate::security::command_guard::SafeCommand;
 
+use super::chunking::{ChunkingConfig, ChunkingStrategy};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DocumentFormat {
     PDF,
@@ -80,35 +82,34 @@ pub struct ChunkMetadata {
     pub page_number: Option<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct DocumentProcessor {
-    chunk_size: usize,
-    chunk_overlap: usize,
-}
-
-impl Default for DocumentProcessor {
-    fn default() -> Self {
-        Self {
-            chunk_size: 1000,
-            chunk_overlap: 200,
-        }
-    }
+    chunking: ChunkingConfig,
 }
 
 impl DocumentProcessor {
     pub fn new(chunk_size: usize, chunk_overlap: usize) -> Self {
-        Self {
+        Self::with_chunking(ChunkingConfig::new(
+            ChunkingStrategy::FixedSize,
             chunk_size,
             chunk_overlap,
-        }
+        ))
+    }
+
+    pub fn with_chunking(chunking: ChunkingConfig) -> Self {
+        Self { chunking }
+    }
+
+    pub fn chunking(&self) -> &ChunkingConfig {
+        &self.chunking
     }
 
     pub fn chunk_size(&self) -> usize {
-        self.chunk_size
+        self.chunking.chunk_size
     }
 
     pub fn chunk_overlap(&self) -> usize {
-        self.chunk_overlap
+        self.chunking.chunk_overlap
     }
 
     pub fn is_supported_file(&self, path: &Path) -> bool {
@@ -151,7 +152,11 @@ impl DocumentProcessor {
 
         let text = self.extract_text(file_path, format).await?;
 
-        let cleaned_text = Self::clean_text(&text);
+        // Heading-aware chunking needs the line structure that clean_text flattens
+        let cleaned_text = match self.chunking.strategy {
+            ChunkingStrategy::MarkdownHeadings => Self::clean_lines(&text),
+            _ => Self::clean_text(&text),
+        };
 
         let chunks = self.create_chunks(&cleaned_text, file_path);
 
@@ -413,6 +418,22 @@ impl DocumentProcessor {
             .join(" ")
     }
 
+    /// Like [`Self::clean_text`], but keeps one line per non-empty input line.
+    fn clean_lines(text: &str) -> String {
+        text.lines()
+            .map(|line| {
+                line.chars()
+                    .filter(|c| !c.is_control() || c.is_whitespace())
+                    .collect::<String>()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     fn create_chunks(&self, text: &str, file_path: &Path) -> Vec<TextChunk> {
         let mut chunks = Vec::new();
         
@@ -425,81 +446,31 @@ impl DocumentProcessor {
             text
         };
         
-        let chars: Vec<char> = text_to_process.chars().collect();
-        let total_chars = chars.len();
-
-        if total_chars == 0 {
-            return chunks;
-        }
-
-        let mut start = 0;
-        let mut chunk_index = 0;
-
-        let step_size = self.chunk_size.saturating_sub(self.chunk_overlap);
-        let total_chunks = if step_size > 0 {
-            total_chars.div_ceil(step_size)
-        } else {
-            1
-        };
-
         // Limit maximum number of chunks to prevent memory exhaustion
         const MAX_CHUNKS: usize = 1000;
-        let max_chunks_to_create = std::cmp::min(total_chunks, MAX_CHUNKS);
-
-        while start < total_chars && chunk_index < max_chunks_to_create {
-            let end = std::cmp::min(start + self.chunk_size, total_chars);
-
-            let mut chunk_end = end;
-            if end < total_chars {
-                // Find word boundary within reasonable distance
-                let search_start = std::cmp::max(start, end.saturating_sub(100));
-                for i in (search_start..end).rev() {
-                    if chars[i].is_whitespace() {
-                        chunk_end = i + 1;
-                        break;
-                    }
-                }
-            }
-
-            let chunk_content: String = chars[start..chunk_end].iter().collect();
-
-            // Skip empty or very small chunks
-            if chunk_content.trim().len() < 10 {
-                start = chunk_end;
-                continue;
-            }
+        let spans = self.chunking.split(text_to_process, MAX_CHUNKS);
+        if spans.len() >= MAX_CHUNKS {
+            warn!("Truncated chunking at {} chunks for: {}", MAX_CHUNKS, file_path.display());
+        }
 
+        let total_chunks = spans.len();
+        let document_title = file_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_string());
+        for (chunk_index, span) in spans.into_iter().enumerate() {
             chunks.push(TextChunk {
-                content: chunk_content,
+                content: span.content,
                 metadata: ChunkMetadata {
                     document_path: file_path.to_string_lossy().to_string(),
-                    document_title: file_path
-                        .file_stem()
-                        .and_then(|s| s.to_str())
-                        .map(|s| s.to_string()),
+                    document_title: document_title.clone(),
                     chunk_index,
-                    total_chunks: max_chunks_to_create,
-                    start_char: start,
-                    end_char: chunk_end,
+                    total_chunks,
+                    start_char: span.start,
+                    end_char: span.end,
                     page_number: None,
                 },
             });
-
-            chunk_index += 1;
-
-            start = if chunk_end >= self.chunk_overlap {
-                chunk_end - self.chunk_overlap
-            } else {
-                chunk_end
-            };
-
-            if start >= total_chars {
-                break;
-            }
-        }
-
-        if chunk_index >= MAX_CHUNKS {
-            warn!("Truncated chunking at {} chunks for: {}", MAX_CHUNKS, file_path.display());
         }
 
         chunks
@@ -559,7 +530,7 @@ impl DocumentProcessor {
         Ok(results)
     }
 
-    async fn collect_supported_files(&self, dir: &Path) -> Result<Vec<std::path::PathBuf>> {
+    pub(crate) async fn collect_supported_files(&self, dir: &Path) -> Result<Vec<std::path::PathBuf>> {
         let mut files = Vec::new();
         self.collect_files_recursive(dir, &mut files, 0).await?;
         Ok(files)
@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use log::{info, trace, warn};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
@@ -8,6 +8,7 @@ use std::time::Duration;
 use tokio::sync::Semaphore;
 
 use crate::core::shared::DbPool;
+use crate::llm::cache::{EmbeddingService, LocalEmbeddingService};
 use crate::core::shared::memory_monitor::{log_jemalloc_stats, MemoryStats};
 use super::document_processor::TextChunk;
 
@@ -34,6 +35,10 @@ pub struct EmbeddingConfig {
     pub timeout_seconds: u64,
     pub max_concurrent_requests: usize,
     pub connect_timeout_seconds: u64,
+    /// Keep a separate set of KB collections per embedding model
+    /// (`embedding-collection-per-model`), so switching models indexes into new
+    /// collections while the old ones stay intact.
+    pub collection_per_model: bool,
 }
 
 impl Default for EmbeddingConfig {
@@ -47,6 +52,7 @@ impl Default for EmbeddingConfig {
             timeout_seconds: 60,
             max_concurrent_requests: 1,
             connect_timeout_seconds: 10,
+            collection_per_model: false,
         }
     }
 }
@@ -104,6 +110,11 @@ impl EmbeddingConfig {
             .and_then(|v| v.parse().ok())
             .unwrap_or(1);
 
+        let collection_per_model = config_manager
+            .get_config(_bot_id, "embedding-collection-per-model", Some("false"))
+            .map(|v| v.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+
         Self {
             embedding_url,
             embedding_model,
@@ -113,7 +124,22 @@ impl EmbeddingConfig {
             timeout_seconds,
             max_concurrent_requests,
             connect_timeout_seconds: 10,
+            collection_per_model,
+        }
+    }
+
+    /// Qdrant collection holding `base` for this model: `base` itself, or `base` suffixed
+    /// with the model name when collections are kept per model.
+    pub fn collection_name(&self, base: &str) -> String {
+        if !self.collection_per_model {
+            return base.to_string();
         }
+        let slug: String = self
+            .embedding_model
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+            .collect();
+        format!("{}__{}", base, slug.trim_matches('_'))
     }
 
     fn detect_dimensions(model: &str) -> usize {
@@ -131,112 +157,6 @@ impl EmbeddingConfig {
     }
 }
 
-#[derive(Debug, Serialize)]
-struct EmbeddingRequest {
-    input: Vec<String>,
-    model: String,
-}
-
-// OpenAI/Claude/OpenAI-compatible format
-#[derive(Debug, Deserialize)]
-struct OpenAIEmbeddingResponse {
-    data: Vec<OpenAIEmbeddingData>,
-    model: String,
-    usage: Option<EmbeddingUsage>,
-}
-
-#[derive(Debug, Deserialize)]
-struct OpenAIEmbeddingData {
-    embedding: Vec<f32>,
-}
-
-// llama.cpp format
-#[derive(Debug, Deserialize)]
-struct LlamaCppEmbeddingItem {
-    embedding: Vec<Vec<f32>>,
-}
-
-// Hugging Face/SentenceTransformers format (simple array)
-type HuggingFaceEmbeddingResponse = Vec<Vec<f32>>;
-
-// Scaleway/OpenAI-compatible format (object with data array)
-#[derive(Debug, Deserialize)]
-struct ScalewayEmbeddingResponse {
-    data: Vec<ScalewayEmbeddingData>,
-    #[serde(default)]
-    model: Option<String>,
-    #[serde(default)]
-    usage: Option<EmbeddingUsage>,
-}
-
-#[derive(Debug, Deserialize)]
-struct ScalewayEmbeddingData {
-    embedding: Vec<f32>,
-}
-
-// Generic embedding service format (object with embeddings key)
-#[derive(Debug, Deserialize)]
-struct GenericEmbeddingResponse {
-    embeddings: Vec<Vec<f32>>,
-    #[serde(default)]
-    model: Option<String>,
-    #[serde(default)]
-    usage: Option<EmbeddingUsage>,
-}
-
-// Cloudflare AI Workers format
-#[derive(Debug, Serialize)]
-struct CloudflareEmbeddingRequest {
-    text: Vec<String>,
-}
-
-#[derive(Debug, Deserialize)]
-struct CloudflareEmbeddingResponse {
-    result: CloudflareResult,
-    success: bool,
-    #[serde(default)]
-    errors: Vec<CloudflareError>,
-}
-
-#[derive(Debug, Deserialize)]
-struct CloudflareResult {
-    data: Vec<Vec<f32>>,
-    #[serde(default)]
-    meta: Option<CloudflareMeta>,
-}
-
-#[derive(Debug, Deserialize)]
-struct CloudflareMeta {
-    #[serde(default)]
-    cost_metric_value_1: Option<f64>,
-}
-
-#[derive(Debug, Deserialize)]
-struct CloudflareError {
-    #[serde(default)]
-    code: i32,
-    #[serde(default)]
-    message: String,
-}
-
-// Universal response wrapper - tries formats in order of likelihood
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum EmbeddingResponse {
-    Scaleway(ScalewayEmbeddingResponse),       // Scaleway/OpenAI-compatible format
-    OpenAI(OpenAIEmbeddingResponse),           // Most common: OpenAI, Claude, etc.
-    LlamaCpp(Vec<LlamaCppEmbeddingItem>),      // llama.cpp server
-    HuggingFace(HuggingFaceEmbeddingResponse), // Simple array format
-    Generic(GenericEmbeddingResponse),         // Generic services
-    Cloudflare(CloudflareEmbeddingResponse),   // Cloudflare AI Workers
-}
-
-#[derive(Debug, Deserialize)]
-struct EmbeddingUsage {
-    #[serde(default)]
-    total_tokens: usize,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Embedding {
     pub vector: Vec<f32>,
@@ -248,6 +168,7 @@ pub struct Embedding {
 pub struct KbEmbeddingGenerator {
     config: EmbeddingConfig,
     client: Client,
+    service: LocalEmbeddingService,
     semaphore: Arc<Semaphore>,
 }
 
@@ -263,7 +184,7 @@ impl std::fmt::Debug for KbEmbeddingGenerator {
 
 impl KbEmbeddingGenerator {
     pub fn new(config: EmbeddingConfig) -> Self {
-        let client = Client::builder()
+        let client = crate::core::shared::llm_tls::llm_client_builder(&config.embedding_url)
             .timeout(Duration::from_secs(config.timeout_seconds))
             .connect_timeout(Duration::from_secs(config.connect_timeout_seconds))
             .pool_max_idle_per_host(2)
@@ -273,14 +194,21 @@ impl KbEmbeddingGenerator {
             .build()
             .unwrap_or_else(|e| {
                 warn!("Failed to create HTTP client with timeout: {}, using default", e);
-                Client::new()
+                crate::core::shared::llm_tls::llm_http_client(&config.embedding_url)
             });
 
         let semaphore = Arc::new(Semaphore::new(config.max_concurrent_requests));
+        let service = LocalEmbeddingService::new(
+            config.embedding_url.clone(),
+            config.embedding_model.clone(),
+            config.embedding_key.clone(),
+        )
+        .without_hash_fallback();
 
         Self {
             config,
             client,
+            service,
             semaphore,
         }
     }
@@ -383,6 +311,10 @@ impl KbEmbeddingGenerator {
         self.config.dimensions
     }
 
+    pub fn config(&self) -> &EmbeddingConfig {
+        &self.config
+    }
+
     pub async fn generate_embeddings(
 
 
@@ -472,295 +404,35 @@ impl KbEmbeddingGenerator {
         Ok(results)
     }
 
+    /// Embeds the chunks through [`LocalEmbeddingService::embed_batch`], which retries
+    /// texts a batch request left out one by one.
     async fn generate_batch_embeddings(&self, chunks: &[TextChunk]) -> Result<Vec<Embedding>> {
         let _permit = self.semaphore.acquire().await
             .map_err(|e| anyhow::anyhow!("Failed to acquire semaphore: {}", e))?;
 
-        let texts: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
+        let texts: Vec<String> = chunks.iter()
+            .map(|c| crate::core::shared::utils::truncate_text_for_model(&c.content, &self.config.embedding_model, 600))
+            .collect();
         let total_chars: usize = texts.iter().map(|t| t.len()).sum();
 
         trace!("generate_batch_embeddings: {} texts, {} total chars",
               texts.len(), total_chars);
 
-        match self.generate_local_embeddings(&texts).await {
-            Ok(embeddings) => {
-                trace!("Local embeddings succeeded: {} vectors", embeddings.len());
-                Ok(embeddings)
-            }
-            Err(e) => {
-                warn!("Local embedding service failed: {}", e);
-                Err(e)
-            }
-        }
-    }
-
-    async fn generate_local_embeddings(&self, texts: &[String]) -> Result<Vec<Embedding>> {
-        // Apply token-aware truncation to each text before creating request
-        let truncated_texts: Vec<String> = texts.iter()
-            .map(|text| crate::core::shared::utils::truncate_text_for_model(text, &self.config.embedding_model, 600))
-            .collect();
-
-        // Detect API format based on URL pattern
-        // Cloudflare AI: https://api.cloudflare.com/client/v4/accounts/{account_id}/ai/run/@cf/baai/bge-m3
-        // Scaleway (OpenAI-compatible): https://router.huggingface.co/scaleway/v1/embeddings
-        // HuggingFace Inference (old): https://router.huggingface.co/hf-inference/models/.../pipeline/feature-extraction
-        let is_cloudflare = self.config.embedding_url.contains("api.cloudflare.com/client/v4/accounts");
-        let is_scaleway = self.config.embedding_url.contains("/scaleway/v1/embeddings");
-        let is_hf_inference = self.config.embedding_url.contains("/hf-inference/") ||
-                             self.config.embedding_url.contains("/pipeline/feature-extraction");
-
-        let response = if is_cloudflare {
-            // Cloudflare AI Workers API format: {"text": ["text1", "text2", ...]}
-            let cf_request = CloudflareEmbeddingRequest {
-                text: truncated_texts,
-            };
-
-            let request_size = serde_json::to_string(&cf_request)
-                .map(|s| s.len())
-                .unwrap_or(0);
-            trace!("Sending Cloudflare AI request to {} (size: {} bytes)",
-                  self.config.embedding_url, request_size);
-
-            let mut request_builder = self.client
-                .post(&self.config.embedding_url)
-                .json(&cf_request);
-
-            // Add Authorization header if API key is provided
-            if let Some(ref api_key) = self.config.embedding_key {
-                request_builder = request_builder.header("Authorization", format!("Bearer {}", api_key));
-            }
+        let vectors = self.service.embed_batch(texts).await.map_err(|e| {
+            warn!("Local embedding service failed: {}", e);
+            anyhow::anyhow!("Embedding service error: {}", e)
+        })?;
+        trace!("Local embeddings succeeded: {} vectors", vectors.len());
 
-            request_builder
-                .send()
-                .await
-                .context("Failed to send request to Cloudflare AI embedding service")?
-        } else if is_hf_inference {
-            // HuggingFace Inference API (old format): {"inputs": "text"}
-            // Process one text at a time for HuggingFace Inference
-            let mut all_embeddings = Vec::new();
-
-            for text in &truncated_texts {
-                let hf_request = serde_json::json!({
-                    "inputs": text
-                });
-
-                let request_size = serde_json::to_string(&hf_request)
-                    .map(|s| s.len())
-                    .unwrap_or(0);
-                trace!("Sending HuggingFace Inference request to {} (size: {} bytes)",
-                      self.config.embedding_url, request_size);
-
-                let mut request_builder = self.client
-                    .post(&self.config.embedding_url)
-                    .json(&hf_request);
-
-                // Add Authorization header if API key is provided
-                if let Some(ref api_key) = self.config.embedding_key {
-                    request_builder = request_builder.header("Authorization", format!("Bearer {}", api_key));
-                }
-
-                let resp = request_builder
-                    .send()
-                    .await
-                    .context("Failed to send request to HuggingFace Inference embedding service")?;
-
-                let status = resp.status();
-                if !status.is_success() {
-                    let error_bytes = resp.bytes().await.unwrap_or_default();
-                    let error_text = String::from_utf8_lossy(&error_bytes[..error_bytes.len().min(1024)]);
-                    return Err(anyhow::anyhow!(
-                        "HuggingFace Inference embedding service error {}: {}",
-                        status,
-                        error_text
-                    ));
-                }
-
-                let response_bytes = resp.bytes().await
-                    .context("Failed to read HuggingFace Inference embedding response bytes")?;
-
-                trace!("Received HuggingFace Inference response: {} bytes", response_bytes.len());
-
-                if response_bytes.len() > 50 * 1024 * 1024 {
-                    return Err(anyhow::anyhow!(
-                        "Embedding response too large: {} bytes (max 50MB)",
-                        response_bytes.len()
-                    ));
-                }
-
-                // Parse HuggingFace Inference response (single array for single input)
-                let embedding_vec: Vec<f32> = serde_json::from_slice(&response_bytes)
-                    .with_context(|| {
-                        let preview = std::str::from_utf8(&response_bytes)
-                            .map(|s| if s.len() > 200 { &s[..200] } else { s })
-                            .unwrap_or("<invalid utf8>");
-                        format!("Failed to parse HuggingFace Inference embedding response. Preview: {}", preview)
-                    })?;
-
-                all_embeddings.push(Embedding {
-                    vector: embedding_vec,
-                    dimensions: self.config.dimensions,
-                    model: self.config.embedding_model.clone(),
-                    tokens_used: None,
-                });
-            }
-
-            return Ok(all_embeddings);
-        } else {
-            // Standard embedding service format (OpenAI-compatible, Scaleway, llama.cpp, local server, etc.)
-            // This includes Scaleway which uses OpenAI-compatible format: {"input": [texts], "model": "model-name"}
-            let request = EmbeddingRequest {
-                input: truncated_texts,
+        Ok(vectors
+            .into_iter()
+            .map(|vector| Embedding {
+                vector,
+                dimensions: self.config.dimensions,
                 model: self.config.embedding_model.clone(),
-            };
-
-            let request_size = serde_json::to_string(&request)
-                .map(|s| s.len())
-                .unwrap_or(0);
-
-            // Log the API format being used
-            if is_scaleway {
-                trace!("Sending Scaleway (OpenAI-compatible) request to {} (size: {} bytes)",
-                      self.config.embedding_url, request_size);
-            } else {
-                trace!("Sending standard embedding request to {} (size: {} bytes)",
-                      self.config.embedding_url, request_size);
-            }
-
-            // Build request
-            let mut request_builder = self.client
-                .post(&self.config.embedding_url)
-                .json(&request);
-
-            // Add Authorization header if API key is provided (for Scaleway, OpenAI, etc.)
-            if let Some(ref api_key) = self.config.embedding_key {
-                request_builder = request_builder.header("Authorization", format!("Bearer {}", api_key));
-            }
-
-            request_builder
-                .send()
-                .await
-                .context("Failed to send request to embedding service")?
-        };
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_bytes = response.bytes().await.unwrap_or_default();
-            let error_text = String::from_utf8_lossy(&error_bytes[..error_bytes.len().min(1024)]);
-            return Err(anyhow::anyhow!(
-                "Embedding service error {}: {}",
-                status,
-                error_text
-            ));
-        }
-
-        let response_bytes = response.bytes().await
-            .context("Failed to read embedding response bytes")?;
-
-        trace!("Received response: {} bytes", response_bytes.len());
-
-        if response_bytes.len() > 50 * 1024 * 1024 {
-            return Err(anyhow::anyhow!(
-                "Embedding response too large: {} bytes (max 50MB)",
-                response_bytes.len()
-            ));
-        }
-
-        let embedding_response: EmbeddingResponse = serde_json::from_slice(&response_bytes)
-            .with_context(|| {
-                let preview = std::str::from_utf8(&response_bytes)
-                    .map(|s| if s.len() > 200 { &s[..200] } else { s })
-                    .unwrap_or("<invalid utf8>");
-                format!("Failed to parse embedding response. Preview: {}", preview)
-            })?;
-
-        drop(response_bytes);
-
-        let embeddings = match embedding_response {
-            EmbeddingResponse::OpenAI(openai_response) => {
-                let mut embeddings = Vec::with_capacity(openai_response.data.len());
-                for data in openai_response.data {
-                    embeddings.push(Embedding {
-                        vector: data.embedding,
-                        dimensions: self.config.dimensions,
-                        model: openai_response.model.clone(),
-                        tokens_used: openai_response.usage.as_ref().map(|u| u.total_tokens),
-                    });
-                }
-                embeddings
-            }
-            EmbeddingResponse::LlamaCpp(llama_response) => {
-                let mut embeddings = Vec::new();
-                for item in llama_response {
-                    for embedding_vec in item.embedding {
-                        embeddings.push(Embedding {
-                            vector: embedding_vec,
-                            dimensions: self.config.dimensions,
-                            model: self.config.embedding_model.clone(),
-                            tokens_used: None,
-                        });
-                    }
-                }
-                embeddings
-            }
-            EmbeddingResponse::HuggingFace(hf_response) => {
-                let mut embeddings = Vec::with_capacity(hf_response.len());
-                for embedding_vec in hf_response {
-                    embeddings.push(Embedding {
-                        vector: embedding_vec,
-                        dimensions: self.config.dimensions,
-                        model: self.config.embedding_model.clone(),
-                        tokens_used: None,
-                    });
-                }
-                embeddings
-            }
-            EmbeddingResponse::Generic(generic_response) => {
-                let mut embeddings = Vec::with_capacity(generic_response.embeddings.len());
-                for embedding_vec in generic_response.embeddings {
-                    embeddings.push(Embedding {
-                        vector: embedding_vec,
-                        dimensions: self.config.dimensions,
-                        model: generic_response.model.clone().unwrap_or_else(|| self.config.embedding_model.clone()),
-                        tokens_used: generic_response.usage.as_ref().map(|u| u.total_tokens),
-                    });
-                }
-                embeddings
-            }
-            EmbeddingResponse::Scaleway(scaleway_response) => {
-                let mut embeddings = Vec::with_capacity(scaleway_response.data.len());
-                for data in scaleway_response.data {
-                    embeddings.push(Embedding {
-                        vector: data.embedding,
-                        dimensions: self.config.dimensions,
-                        model: scaleway_response.model.clone().unwrap_or_else(|| self.config.embedding_model.clone()),
-                        tokens_used: scaleway_response.usage.as_ref().map(|u| u.total_tokens),
-                    });
-                }
-                embeddings
-            }
-            EmbeddingResponse::Cloudflare(cf_response) => {
-                if !cf_response.success {
-                    let error_msg = cf_response.errors.first()
-                        .map(|e| format!("{}: {}", e.code, e.message))
-                        .unwrap_or_else(|| "Unknown Cloudflare error".to_string());
-                    return Err(anyhow::anyhow!("Cloudflare AI error: {}", error_msg));
-                }
-                let mut embeddings = Vec::with_capacity(cf_response.result.data.len());
-                for embedding_vec in cf_response.result.data {
-                    embeddings.push(Embedding {
-                        vector: embedding_vec,
-                        dimensions: self.config.dimensions,
-                        model: self.config.embedding_model.clone(),
-                        tokens_used: cf_response.result.meta.as_ref().and_then(|m| {
-                            m.cost_metric_value_1.map(|v| v as usize)
-                        }),
-                    });
-                }
-                embeddings
-            }
-        };
-
-        Ok(embeddings)
+                tokens_used: None,
+            })
+            .collect())
     }
 
     pub async fn generate_single_embedding(&self, text: &str) -> Result<Embedding> {
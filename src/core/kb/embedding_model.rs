@@ -0,0 +1,406 @@
+//! Startup verification that the embedding server serves the configured model.
+//!
+//! The server is asked for its model list (Ollama's `/api/tags`, or `/v1/models`). A model
+//! missing from an Ollama server is pulled when `embedding-model-pull` allows it. Servers
+//! without a model list are probed with a single embedding request instead. The outcome
+//! is kept for `/health/ready`; a missing model is checked again once the outcome is older
+//! than [`MISSING_TTL`], so installing it later needs no restart.
+
+use log::{error, info, warn};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{Duration, Instant};
+
+use super::embedding_generator::{
+    set_embedding_server_ready, EmbeddingConfig, KbEmbeddingGenerator,
+};
+use crate::core::config::ConfigManager;
+use crate::core::shared::llm_tls::llm_http_client;
+use crate::core::shared::state::AppState;
+
+pub const PULL_KEY: &str = "embedding-model-pull";
+pub const WAIT_KEY: &str = "embedding-model-wait";
+
+const DEFAULT_WAIT_SECS: u64 = 300;
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// How long a `Missing` outcome is trusted before the model is checked again.
+pub const MISSING_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ModelStatus {
+    /// The startup check has not run.
+    Unchecked,
+    /// No `embedding-url` is configured.
+    NotConfigured,
+    Checking {
+        model: String,
+    },
+    Available {
+        model: String,
+    },
+    /// Missing at startup and downloaded by the embedding server.
+    Pulled {
+        model: String,
+    },
+    Missing {
+        model: String,
+        reason: String,
+    },
+}
+
+impl ModelStatus {
+    pub fn is_ready(&self) -> bool {
+        !matches!(self, Self::Checking { .. } | Self::Missing { .. })
+    }
+}
+
+static MODEL_STATUS: LazyLock<RwLock<(ModelStatus, Instant)>> =
+    LazyLock::new(|| RwLock::new((ModelStatus::Unchecked, Instant::now())));
+
+/// Inputs of the last check, to repeat it when a `Missing` outcome expires.
+static LAST_CHECK: LazyLock<RwLock<Option<(EmbeddingConfig, bool, Duration)>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+fn is_expired(status: &ModelStatus, checked_at: Instant) -> bool {
+    matches!(status, ModelStatus::Missing { .. }) && checked_at.elapsed() >= MISSING_TTL
+}
+
+/// The outcome of the last check. An expired `Missing` outcome starts a new check in the
+/// background and reads as `Checking` until it finishes.
+pub fn model_status() -> ModelStatus {
+    let Some((status, checked_at)) = MODEL_STATUS.read().ok().map(|s| s.clone()) else {
+        return ModelStatus::Unchecked;
+    };
+    if !is_expired(&status, checked_at) {
+        return status;
+    }
+    let check = LAST_CHECK.read().ok().and_then(|c| c.clone());
+    match (check, tokio::runtime::Handle::try_current()) {
+        (Some((config, pull, wait)), Ok(handle)) => {
+            let checking = ModelStatus::Checking {
+                model: config.embedding_model.clone(),
+            };
+            set_model_status(checking.clone());
+            handle.spawn(run_check(config, pull, wait));
+            checking
+        }
+        _ => status,
+    }
+}
+
+fn set_model_status(status: ModelStatus) {
+    if let Ok(mut current) = MODEL_STATUS.write() {
+        *current = (status, Instant::now());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ModelApi {
+    OpenAi,
+    Ollama,
+}
+
+#[derive(Debug)]
+struct Catalog {
+    api: ModelApi,
+    models: Vec<String>,
+}
+
+/// Compares names loosely: servers report ids such as `sentence-transformers/all-MiniLM-L6-v2`,
+/// a GGUF path, or an Ollama tag, while config usually holds the bare name.
+fn model_matches(served: &str, wanted: &str) -> bool {
+    fn normalize(name: &str) -> String {
+        let name = name.trim().to_lowercase();
+        let base = name.rsplit(['/', '\\']).next().unwrap_or(&name);
+        let base = base.strip_suffix(".gguf").unwrap_or(base);
+        base.strip_suffix(":latest").unwrap_or(base).to_string()
+    }
+    let wanted = normalize(wanted);
+    !wanted.is_empty() && normalize(served) == wanted
+}
+
+/// Model names from an OpenAI-style `{"data": [{"id"}]}` or an Ollama/llama.cpp
+/// `{"models": [{"name"|"model"}]}` listing.
+fn parse_model_list(body: &Value) -> Vec<String> {
+    let openai = body
+        .get("data")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.get("id").and_then(Value::as_str));
+    let named = body
+        .get("models")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|m| {
+            m.get("name")
+                .or_else(|| m.get("model"))
+                .and_then(Value::as_str)
+        });
+    openai.chain(named).map(str::to_string).collect()
+}
+
+fn base_url(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(parsed) => format!(
+            "{}://{}{}",
+            parsed.scheme(),
+            parsed.host_str().unwrap_or("localhost"),
+            parsed.port().map(|p| format!(":{}", p)).unwrap_or_default()
+        ),
+        Err(_) => url.trim_end_matches('/').to_string(),
+    }
+}
+
+struct ModelVerifier {
+    client: Client,
+    config: EmbeddingConfig,
+    base_url: String,
+}
+
+impl ModelVerifier {
+    fn new(config: EmbeddingConfig) -> Self {
+        Self {
+            client: llm_http_client(&config.embedding_url),
+            base_url: base_url(&config.embedding_url),
+            config,
+        }
+    }
+
+    async fn get_json(&self, path: &str) -> Result<Option<Value>, String> {
+        let mut request = self
+            .client
+            .get(format!("{}{}", self.base_url, path))
+            .timeout(Duration::from_secs(self.config.connect_timeout_seconds));
+        if let Some(key) = &self.config.embedding_key {
+            request = request.bearer_auth(key);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+        Ok(response.json().await.ok())
+    }
+
+    /// `Ok(None)` when the server is reachable but publishes no model list.
+    async fn catalog(&self) -> Result<Option<Catalog>, String> {
+        // Ollama also answers /v1/models, so ask for its native listing first to know
+        // that pulling is possible.
+        if let Some(body) = self.get_json("/api/tags").await? {
+            return Ok(Some(Catalog {
+                api: ModelApi::Ollama,
+                models: parse_model_list(&body),
+            }));
+        }
+        Ok(self.get_json("/v1/models").await?.map(|body| Catalog {
+            api: ModelApi::OpenAi,
+            models: parse_model_list(&body),
+        }))
+    }
+
+    async fn pull(&self, timeout: Duration) -> Result<(), String> {
+        info!(
+            "Pulling embedding model '{}' on {}",
+            self.config.embedding_model, self.base_url
+        );
+        let response = self
+            .client
+            .post(format!("{}/api/pull", self.base_url))
+            .json(&serde_json::json!({
+                "model": self.config.embedding_model,
+                "stream": false,
+            }))
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(|e| format!("pull failed: {}", e))?;
+        if !response.status().is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("pull failed: {}", body.trim()));
+        }
+        Ok(())
+    }
+
+    async fn probe(&self) -> Result<(), String> {
+        KbEmbeddingGenerator::new(self.config.clone())
+            .generate_single_embedding("embedding model check")
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Waits up to `wait` for the server to come up, then checks for the model.
+    async fn verify(&self, pull: bool, wait: Duration) -> ModelStatus {
+        let model = self.config.embedding_model.clone();
+        let missing = |reason: String| ModelStatus::Missing {
+            model: model.clone(),
+            reason,
+        };
+        let deadline = Instant::now() + wait;
+
+        let catalog = loop {
+            match self.catalog().await {
+                Ok(catalog) => break catalog,
+                Err(e) if Instant::now() >= deadline => {
+                    return missing(format!("embedding server unreachable: {}", e));
+                }
+                Err(_) => tokio::time::sleep(RETRY_INTERVAL).await,
+            }
+        };
+
+        let Some(catalog) = catalog else {
+            return match self.probe().await {
+                Ok(()) => ModelStatus::Available { model },
+                Err(e) => missing(format!("test embedding failed: {}", e)),
+            };
+        };
+        if catalog.models.iter().any(|m| model_matches(m, &model)) {
+            return ModelStatus::Available { model };
+        }
+        if catalog.api != ModelApi::Ollama || !pull {
+            return missing(format!("server offers [{}]", catalog.models.join(", ")));
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if let Err(e) = self.pull(remaining.max(RETRY_INTERVAL)).await {
+            return missing(e);
+        }
+        match self.catalog().await {
+            Ok(Some(catalog)) if catalog.models.iter().any(|m| model_matches(m, &model)) => {
+                ModelStatus::Pulled { model }
+            }
+            Ok(_) => missing("model still not listed after pull".to_string()),
+            Err(e) => missing(format!("embedding server unreachable after pull: {}", e)),
+        }
+    }
+}
+
+/// Checks the default bot's embedding model and records the result for `/health/ready`.
+/// A missing model also marks the embedding server not ready, so KB indexing waits
+/// instead of storing unusable vectors.
+pub async fn verify_embedding_model(state: Arc<AppState>) {
+    let pool = state.conn.clone();
+    let settings = tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().ok()?;
+        let (bot_id, _) = crate::core::bot::get_default_bot(&mut conn);
+        let config_manager = ConfigManager::new(pool.clone());
+        let pull = config_manager
+            .get_config(&bot_id, PULL_KEY, Some("true"))
+            .map(|v| v.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or(true);
+        let wait = config_manager
+            .get_config(&bot_id, WAIT_KEY, None)
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_WAIT_SECS);
+        Some((EmbeddingConfig::from_bot_config(&pool, &bot_id), pull, wait))
+    })
+    .await
+    .ok()
+    .flatten();
+
+    let Some((config, pull, wait)) = settings else {
+        warn!("Embedding model check skipped: database unavailable");
+        return;
+    };
+    if config.embedding_url.trim().is_empty() {
+        set_model_status(ModelStatus::NotConfigured);
+        return;
+    }
+
+    if let Ok(mut last) = LAST_CHECK.write() {
+        *last = Some((config.clone(), pull, Duration::from_secs(wait)));
+    }
+    set_model_status(ModelStatus::Checking {
+        model: config.embedding_model.clone(),
+    });
+    run_check(config, pull, Duration::from_secs(wait)).await;
+}
+
+async fn run_check(config: EmbeddingConfig, pull: bool, wait: Duration) {
+    let url = config.embedding_url.clone();
+    let status = ModelVerifier::new(config).verify(pull, wait).await;
+    match &status {
+        ModelStatus::Available { model } => {
+            info!("Embedding model '{}' available at {}", model, url)
+        }
+        ModelStatus::Pulled { model } => info!("Embedding model '{}' pulled at {}", model, url),
+        ModelStatus::Missing { model, reason } => {
+            error!(
+                "Embedding model '{}' is not available at {}: {}. KB indexing and search \
+                 stay disabled until the model is installed.",
+                model, url, reason
+            );
+            set_embedding_server_ready(false);
+        }
+        _ => {}
+    }
+    set_model_status(status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_model_matches_server_ids() {
+        assert!(model_matches(
+            "sentence-transformers/all-MiniLM-L6-v2",
+            "all-MiniLM-L6-v2"
+        ));
+        assert!(model_matches(
+            "../../../data/llm/bge-small-en-v1.5-f32.gguf",
+            "bge-small-en-v1.5-f32.gguf"
+        ));
+        assert!(model_matches("nomic-embed-text:latest", "nomic-embed-text"));
+        assert!(!model_matches("nomic-embed-text:v1.5", "nomic-embed-text"));
+        assert!(!model_matches("all-MiniLM-L12-v2", "all-MiniLM-L6-v2"));
+        assert!(!model_matches("anything", ""));
+    }
+
+    #[test]
+    fn test_parse_model_list_formats() {
+        let openai =
+            serde_json::json!({"object": "list", "data": [{"id": "text-embedding-3-small"}]});
+        assert_eq!(parse_model_list(&openai), vec!["text-embedding-3-small"]);
+
+        let ollama = serde_json::json!({"models": [{"name": "nomic-embed-text:latest"}]});
+        assert_eq!(parse_model_list(&ollama), vec!["nomic-embed-text:latest"]);
+
+        let llama_cpp = serde_json::json!({
+            "models": [{"model": "bge.gguf"}],
+            "data": [{"id": "bge.gguf"}]
+        });
+        assert_eq!(parse_model_list(&llama_cpp), vec!["bge.gguf", "bge.gguf"]);
+    }
+
+    #[test]
+    fn test_missing_model_is_not_ready() {
+        assert!(ModelStatus::Unchecked.is_ready());
+        assert!(ModelStatus::NotConfigured.is_ready());
+        assert!(!ModelStatus::Missing {
+            model: "m".to_string(),
+            reason: "gone".to_string()
+        }
+        .is_ready());
+    }
+
+    #[test]
+    fn test_missing_outcome_expires() {
+        let missing = ModelStatus::Missing {
+            model: "m".to_string(),
+            reason: "gone".to_string(),
+        };
+        assert!(!is_expired(&missing, Instant::now()));
+        let old = Instant::now() - MISSING_TTL - Duration::from_secs(1);
+        assert!(is_expired(&missing, old));
+        let available = ModelStatus::Available {
+            model: "m".to_string(),
+        };
+        assert!(!is_expired(&available, old));
+    }
+}
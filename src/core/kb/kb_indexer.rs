@@ -1,4 +1,5 @@
 use anyhow::Result;
+use async_trait::async_trait;
 use log::{debug, info, trace, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -8,7 +9,11 @@ use uuid::Uuid;
 use crate::core::config::ConfigManager;
 use crate::core::shared::memory_monitor::{log_jemalloc_stats, MemoryStats};
 use crate::core::shared::utils::{create_tls_client, DbPool};
+use crate::vector_db::source_chunks::{
+    self, chunk_point_id, replace_source_chunks, ChunkPoint, ChunkStore, TrackedSource,
+};
 
+use super::chunking::ChunkingConfig;
 use super::document_processor::{DocumentProcessor, TextChunk};
 use super::embedding_generator::{is_embedding_server_ready, Embedding, EmbeddingConfig, KbEmbeddingGenerator};
 
@@ -77,6 +82,69 @@ pub struct SearchRequest {
     pub filter: Option<serde_json::Value>,
 }
 
+/// Embedding model and dimensions the vectors of a collection were written with. `model`
+/// is `None` for collections indexed before points were tagged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredEmbedding {
+    pub model: Option<String>,
+    pub dimensions: usize,
+}
+
+/// How long a collection's [`StoredEmbedding`] is reused before it is read again.
+const STORED_EMBEDDING_TTL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Keyed by Qdrant URL and collection name.
+type StoredEmbeddingCache = std::sync::RwLock<HashMap<(String, String), (std::time::Instant, StoredEmbedding)>>;
+
+static STORED_EMBEDDINGS: std::sync::LazyLock<StoredEmbeddingCache> =
+    std::sync::LazyLock::new(Default::default);
+
+/// A query embedded with a different model than the collection's vectors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingMismatch {
+    pub collection: String,
+    pub stored: StoredEmbedding,
+    pub query_model: String,
+    pub query_dimensions: usize,
+}
+
+impl std::fmt::Display for EmbeddingMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Collection '{}' holds vectors from {} ({} dimensions) but queries use {} ({} dimensions). \
+             Reindex the knowledge base or set embedding-model back.",
+            self.collection,
+            self.stored.model.as_deref().unwrap_or("an untagged model"),
+            self.stored.dimensions,
+            self.query_model,
+            self.query_dimensions
+        )
+    }
+}
+
+impl std::error::Error for EmbeddingMismatch {}
+
+/// Rejects a query embedding that does not match the collection's stored vectors.
+/// Untagged collections are only checked by dimension.
+pub fn check_embedding(
+    collection: &str,
+    stored: &StoredEmbedding,
+    query_model: &str,
+    query_dimensions: usize,
+) -> std::result::Result<(), EmbeddingMismatch> {
+    let model_matches = stored.model.as_deref().is_none_or(|m| m == query_model);
+    if model_matches && stored.dimensions == query_dimensions {
+        return Ok(());
+    }
+    Err(EmbeddingMismatch {
+        collection: collection.to_string(),
+        stored: stored.clone(),
+        query_model: query_model.to_string(),
+        query_dimensions,
+    })
+}
+
 pub struct KbIndexer {
     document_processor: DocumentProcessor,
     embedding_generator: KbEmbeddingGenerator,
@@ -183,7 +251,10 @@ impl KbIndexer {
         }
 
         let bot_id_short = bot_id.to_string().chars().take(8).collect::<String>();
-        let collection_name = format!("{}_{}_{}", bot_name, bot_id_short, kb_name);
+        let collection_name = self
+            .embedding_generator
+            .config()
+            .collection_name(&format!("{}_{}_{}", bot_name, bot_id_short, kb_name));
 
         self.ensure_collection_exists(&collection_name).await?;
 
@@ -191,7 +262,9 @@ impl KbIndexer {
         trace!("Before process_kb_folder RSS={}",
               MemoryStats::format_bytes(before_docs.rss_bytes));
 
-        let documents = self.document_processor.process_kb_folder(kb_path).await?;
+        let processor = self.processor_for(bot_id, kb_name).await;
+        let chunking = processor.chunking().descriptor();
+        let documents = processor.process_kb_folder(kb_path).await?;
 
         let after_docs = MemoryStats::current();
         trace!("After process_kb_folder: {} documents, RSS={} (delta={})",
@@ -217,7 +290,7 @@ impl KbIndexer {
 
             // Process batch when full
             if batch_docs.len() >= BATCH_SIZE {
-                let (processed, chunks_count) = self.process_document_batch(&collection_name, &mut batch_docs).await?;
+                let (processed, chunks_count) = self.process_document_batch(&collection_name, &chunking, &mut batch_docs).await?;
                 indexed_documents += processed;
                 total_chunks += chunks_count;
 
@@ -245,7 +318,7 @@ impl KbIndexer {
 
         // Process remaining documents in final batch
         if !batch_docs.is_empty() {
-            let (processed, chunks_count) = self.process_document_batch(&collection_name, &mut batch_docs).await?;
+            let (processed, chunks_count) = self.process_document_batch(&collection_name, &chunking, &mut batch_docs).await?;
             indexed_documents += processed;
             total_chunks += chunks_count;
         }
@@ -269,6 +342,7 @@ impl KbIndexer {
     async fn process_document_batch(
         &self,
         collection_name: &str,
+        chunking: &str,
         batch_docs: &mut Vec<(String, Vec<TextChunk>)>,
     ) -> Result<(usize, usize)> {
         let mut processed_count = 0;
@@ -297,28 +371,34 @@ impl KbIndexer {
             const CHUNK_BATCH_SIZE: usize = 20; // Process 20 chunks at a time
             let chunk_batches = chunks.chunks(CHUNK_BATCH_SIZE);
 
+            let mut embeddings = Vec::with_capacity(chunks.len());
+            let mut embedded = true;
+
             for chunk_batch in chunk_batches {
                 trace!("Processing chunk batch of {} chunks", chunk_batch.len());
 
-                let embeddings = match self
+                match self
                     .embedding_generator
                     .generate_embeddings(chunk_batch)
                     .await
                 {
-                    Ok(emb) => emb,
+                    Ok(emb) => embeddings.extend(emb),
                     Err(e) => {
-                        warn!("Embedding generation failed for {}: {}", doc_path, e);
+                        warn!("Embedding generation failed for {}, keeping its indexed version: {}", doc_path, e);
+                        embedded = false;
                         break; // Skip to next document
                     }
                 };
 
-                let points = Self::create_qdrant_points(&doc_path, embeddings)?;
-                self.upsert_points(collection_name, points).await?;
-
                 // Yield control between chunk batches
                 tokio::task::yield_now().await;
             }
 
+            if !embedded {
+                continue;
+            }
+            self.replace_document_points(collection_name, &doc_path, chunking, embeddings).await?;
+
             let after_embed = MemoryStats::current();
             trace!("After processing document: RSS={} (delta={})",
                   MemoryStats::format_bytes(after_embed.rss_bytes),
@@ -441,15 +521,26 @@ impl KbIndexer {
     }
 
     fn create_qdrant_points(
+        collection_name: &str,
         doc_path: &str,
+        chunking: &str,
+        embedding_model: &str,
         embeddings: Vec<(TextChunk, Embedding)>,
-    ) -> Result<Vec<QdrantPoint>> {
+    ) -> Result<Vec<ChunkPoint>> {
         let mut points = Vec::new();
 
         for (chunk, embedding) in embeddings {
-            let point_id = Uuid::new_v4().to_string();
+            let point_id = chunk_point_id(collection_name, doc_path, chunk.metadata.chunk_index);
 
             let mut payload = HashMap::new();
+            payload.insert(
+                "embedding_model".to_string(),
+                serde_json::Value::String(embedding_model.to_string()),
+            );
+            payload.insert(
+                "embedding_dims".to_string(),
+                serde_json::Value::Number(embedding.vector.len().into()),
+            );
             payload.insert(
                 "content".to_string(),
                 serde_json::Value::String(chunk.content),
@@ -458,6 +549,10 @@ impl KbIndexer {
                 "document_path".to_string(),
                 serde_json::Value::String(doc_path.to_string()),
             );
+            payload.insert(
+                "chunking".to_string(),
+                serde_json::Value::String(chunking.to_string()),
+            );
             payload.insert(
                 "chunk_index".to_string(),
                 serde_json::Value::Number(chunk.metadata.chunk_index.into()),
@@ -482,7 +577,7 @@ impl KbIndexer {
                 );
             }
 
-            points.push(QdrantPoint {
+            points.push(ChunkPoint {
                 id: point_id,
                 vector: embedding.vector,
                 payload,
@@ -492,6 +587,101 @@ impl KbIndexer {
         Ok(points)
     }
 
+    /// Chunker for a KB: the bot's chunking configuration when a database is available.
+    async fn processor_for(&self, bot_id: Uuid, kb_name: &str) -> DocumentProcessor {
+        let fallback = self.document_processor.chunking().clone();
+        let Some(pool) = self.db_pool.clone() else {
+            return DocumentProcessor::with_chunking(fallback);
+        };
+        let kb_name = kb_name.to_string();
+        let chunking = tokio::task::spawn_blocking(move || {
+            ChunkingConfig::from_bot_config(&pool, &bot_id, &kb_name)
+        })
+        .await
+        .unwrap_or(fallback);
+        DocumentProcessor::with_chunking(chunking)
+    }
+
+    /// Replaces the indexed chunks of `doc_path` with the new embeddings, deleting the
+    /// chunks its previous version had and this one does not. If the document was chunked
+    /// differently last time, all of its old chunks are removed.
+    async fn replace_document_points(
+        &self,
+        collection_name: &str,
+        doc_path: &str,
+        chunking: &str,
+        embeddings: Vec<(TextChunk, Embedding)>,
+    ) -> Result<()> {
+        self.replace_document_points_in(collection_name, collection_name, doc_path, chunking, embeddings)
+            .await
+    }
+
+    /// Like [`Self::replace_document_points`], writing into `collection_name` points whose
+    /// ids are derived from `id_collection`. A shadow collection built for a reindex uses
+    /// the live name, so the ids stay valid once it is swapped in.
+    async fn replace_document_points_in(
+        &self,
+        collection_name: &str,
+        id_collection: &str,
+        doc_path: &str,
+        chunking: &str,
+        embeddings: Vec<(TextChunk, Embedding)>,
+    ) -> Result<()> {
+        let model = &self.embedding_generator.config().embedding_model;
+        let points = Self::create_qdrant_points(id_collection, doc_path, chunking, model, embeddings)?;
+        let previous = match self.tracked_chunks(collection_name, doc_path).await {
+            Some(tracked) if tracked.chunking.as_deref() == Some(chunking) => Some(tracked.chunk_ids),
+            Some(tracked) => {
+                info!("Chunking of {} changed ({} -> {}), replacing all of its chunks",
+                      doc_path, tracked.chunking.as_deref().unwrap_or("unknown"), chunking);
+                None
+            }
+            None => None,
+        };
+        let ids = replace_source_chunks(self, collection_name, doc_path, previous.as_deref(), points).await?;
+
+        if let Some(pool) = self.db_pool.clone() {
+            let collection = collection_name.to_string();
+            let source = doc_path.to_string();
+            let chunking = chunking.to_string();
+            let saved = tokio::task::spawn_blocking(move || {
+                let mut conn = pool.get().map_err(|e| e.to_string())?;
+                source_chunks::save_source_chunks(&mut conn, &collection, &source, &ids, &chunking)
+                    .map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+            if let Err(e) = saved {
+                warn!("Failed to record chunk ids of {}: {}", doc_path, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// What was recorded for `doc_path`, or `None` when it is untracked or the mapping
+    /// cannot be read, in which case the document's points are cleared by filter.
+    async fn tracked_chunks(&self, collection_name: &str, doc_path: &str) -> Option<TrackedSource> {
+        let pool = self.db_pool.clone()?;
+        let collection = collection_name.to_string();
+        let source = doc_path.to_string();
+        let loaded = tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get().map_err(|e| e.to_string())?;
+            source_chunks::load_source_chunks(&mut conn, &collection, &source)
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r);
+        match loaded {
+            Ok(ids) => ids,
+            Err(e) => {
+                warn!("Failed to read chunk ids of {}: {}", doc_path, e);
+                None
+            }
+        }
+    }
+
     async fn upsert_points(&self, collection_name: &str, points: Vec<QdrantPoint>) -> Result<()> {
         if points.is_empty() {
             return Ok(());
@@ -551,7 +741,10 @@ impl KbIndexer {
         }
 
         let bot_id_short = bot_id.to_string().chars().take(8).collect::<String>();
-        let collection_name = format!("{}_{}_{}", bot_name, bot_id_short, kb_name);
+        let collection_name = self
+            .embedding_generator
+            .config()
+            .collection_name(&format!("{}_{}_{}", bot_name, bot_id_short, kb_name));
 
         self.ensure_collection_exists(&collection_name).await?;
 
@@ -561,7 +754,9 @@ impl KbIndexer {
             collection_name
         );
 
-        let chunks = self.document_processor.process_document(file_path).await?;
+        let processor = self.processor_for(bot_id, kb_name).await;
+        let chunking = processor.chunking().descriptor();
+        let chunks = processor.process_document(file_path).await?;
 
         if chunks.is_empty() {
             warn!("No chunks extracted from file: {}", file_path.display());
@@ -578,8 +773,7 @@ impl KbIndexer {
             .generate_embeddings(&chunks)
             .await?;
 
-        let points = Self::create_qdrant_points(&doc_path, embeddings)?;
-        self.upsert_points(&collection_name, points).await?;
+        self.replace_document_points(&collection_name, &doc_path, &chunking, embeddings).await?;
 
         self.update_collection_metadata(&collection_name, bot_name, kb_name, chunks.len())?;
 
@@ -597,6 +791,120 @@ impl KbIndexer {
         })
     }
 
+    /// Chunks and embeds one document into `collection_name`, the shadow collection of a
+    /// reindex of `live_collection`, and returns the number of chunks written.
+    pub async fn index_document_into(
+        &self,
+        collection_name: &str,
+        live_collection: &str,
+        bot_id: Uuid,
+        kb_name: &str,
+        file_path: &Path,
+    ) -> Result<usize> {
+        let processor = self.processor_for(bot_id, kb_name).await;
+        let chunking = processor.chunking().descriptor();
+        let doc_path = file_path.to_string_lossy().to_string();
+        // Unreadable documents are left out, as in a folder index, instead of failing it.
+        let chunks = processor.process_document(file_path).await.unwrap_or_else(|e| {
+            warn!("Failed to process document {}: {}", doc_path, e);
+            Vec::new()
+        });
+        if chunks.is_empty() {
+            self.delete_file_points(collection_name, &doc_path).await?;
+            return Ok(0);
+        }
+
+        let count = chunks.len();
+        let embeddings = self.embedding_generator.generate_embeddings(&chunks).await?;
+        self.replace_document_points_in(collection_name, live_collection, &doc_path, &chunking, embeddings)
+            .await?;
+        Ok(count)
+    }
+
+    /// Creates `collection_name` with the current embedding dimensions unless it already
+    /// exists with them.
+    pub async fn ensure_collection(&self, collection_name: &str) -> Result<()> {
+        self.ensure_collection_exists(collection_name).await
+    }
+
+    /// Supported documents below `kb_path`.
+    pub async fn list_documents(&self, kb_path: &Path) -> Result<Vec<PathBuf>> {
+        self.document_processor.collect_supported_files(kb_path).await
+    }
+
+    /// Collection the alias `name` points to, or `None` when `name` is not an alias.
+    pub async fn resolve_alias(&self, name: &str) -> Result<Option<String>> {
+        let url = format!("{}/aliases", self.qdrant_config.url);
+        let response = self.http_client.get(&url).send().await?;
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to list aliases: {}", error_text));
+        }
+        let body: serde_json::Value = response.json().await?;
+        Ok(body["result"]["aliases"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|alias| alias["alias_name"].as_str() == Some(name))
+            .and_then(|alias| alias["collection_name"].as_str())
+            .map(str::to_string))
+    }
+
+    /// Points the alias `live` at `collection_name` and returns the collection it served
+    /// before. The caller deletes that collection only after this returns, so searches
+    /// always find one of the two.
+    ///
+    /// Re-pointing an existing alias is a single atomic request. The first time, `live` is
+    /// still a plain collection and Qdrant refuses an alias under a name a collection
+    /// holds: the alias is tried first, and only when refused is the plain collection,
+    /// already rebuilt into `collection_name`, dropped and the alias retried. Should the
+    /// alias still fail, the error keeps the reindex job so the next run swaps again.
+    pub async fn swap_alias(&self, live: &str, collection_name: &str) -> Result<Option<String>> {
+        let previous = self.resolve_alias(live).await?;
+        let mut actions = Vec::new();
+        if previous.is_some() {
+            actions.push(serde_json::json!({ "delete_alias": { "alias_name": live } }));
+        }
+        actions.push(serde_json::json!({
+            "create_alias": { "collection_name": collection_name, "alias_name": live }
+        }));
+
+        if let Err(e) = self.update_aliases(&actions).await {
+            if previous.is_some() || self.get_collection_vector_dimension(live).await?.is_none() {
+                return Err(anyhow::anyhow!("Failed to point {} at {}: {}", live, collection_name, e));
+            }
+            warn!("Replacing collection {} with an alias, searches fail until it exists", live);
+            self.delete_collection(live).await?;
+            let mut attempt = 1;
+            while let Err(e) = self.update_aliases(&actions).await {
+                if attempt == 3 {
+                    return Err(anyhow::anyhow!("Failed to point {} at {}: {}", live, collection_name, e));
+                }
+                attempt += 1;
+                tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            }
+        }
+
+        self.forget_stored_embedding(live);
+        info!("Collection {} now serves {}", collection_name, live);
+        Ok(previous.filter(|p| p != collection_name))
+    }
+
+    async fn update_aliases(&self, actions: &[serde_json::Value]) -> Result<()> {
+        let url = format!("{}/collections/aliases", self.qdrant_config.url);
+        let response = self
+            .http_client
+            .post(&url)
+            .json(&serde_json::json!({ "actions": actions }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("{}", error_text));
+        }
+        Ok(())
+    }
+
     pub async fn delete_file_points(
         &self,
         collection_name: &str,
@@ -635,6 +943,22 @@ impl KbIndexer {
             document_path, collection_name
         );
 
+        if let Some(pool) = self.db_pool.clone() {
+            let collection = collection_name.to_string();
+            let source = document_path.to_string();
+            let forgotten = tokio::task::spawn_blocking(move || {
+                let mut conn = pool.get().map_err(|e| e.to_string())?;
+                source_chunks::forget_source(&mut conn, &collection, &source)
+                    .map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+            if let Err(e) = forgotten {
+                warn!("Failed to forget chunk ids of {}: {}", document_path, e);
+            }
+        }
+
         Ok(())
     }
 
@@ -660,40 +984,8 @@ impl KbIndexer {
         query: &str,
         limit: usize,
     ) -> Result<Vec<SearchResult>> {
-        // Get the collection's actual vector dimension to handle dimension mismatch
-        let collection_dimension = self.get_collection_vector_dimension(collection_name).await?;
-
-        let embedding = self
-            .embedding_generator
-            .generate_single_embedding(query)
-            .await?;
-
-        // Truncate embedding vector to match collection dimension if needed
-        let search_vector = if let Some(target_dim) = collection_dimension {
-            if embedding.vector.len() > target_dim {
-                debug!(
-                    "Truncating embedding from {} to {} dimensions for collection '{}'",
-                    embedding.vector.len(), target_dim, collection_name
-                );
-                embedding.vector[..target_dim].to_vec()
-            } else if embedding.vector.len() < target_dim {
-                warn!(
-                    "Embedding dimension ({}) is smaller than collection dimension ({}). \
-                    Search may return poor results for collection '{}'.",
-                    embedding.vector.len(), target_dim, collection_name
-                );
-                // Pad with zeros (not ideal but allows search to proceed)
-                let mut padded = embedding.vector.clone();
-                padded.resize(target_dim, 0.0);
-                padded
-            } else {
-                embedding.vector
-            }
-        } else {
-            embedding.vector
-        };
-
-        self.execute_search(collection_name, search_vector, limit).await
+        self.search_with_generator(collection_name, query, limit, &self.embedding_generator)
+            .await
     }
 
     pub async fn search_with_config(
@@ -703,35 +995,90 @@ impl KbIndexer {
         limit: usize,
         embedding_config: &EmbeddingConfig,
     ) -> Result<Vec<SearchResult>> {
-        let collection_dimension = self.get_collection_vector_dimension(collection_name).await?;
-
         let embedding_generator = KbEmbeddingGenerator::new(embedding_config.clone());
+        self.search_with_generator(collection_name, query, limit, &embedding_generator)
+            .await
+    }
+
+    /// Embeds `query` and searches the collection, refusing with [`EmbeddingMismatch`] when
+    /// the collection was written with another model or dimension.
+    async fn search_with_generator(
+        &self,
+        collection_name: &str,
+        query: &str,
+        limit: usize,
+        embedding_generator: &KbEmbeddingGenerator,
+    ) -> Result<Vec<SearchResult>> {
+        let stored = self.stored_embedding(collection_name).await?;
         let embedding = embedding_generator.generate_single_embedding(query).await?;
 
-        let search_vector = if let Some(target_dim) = collection_dimension {
-            if embedding.vector.len() > target_dim {
-                debug!(
-                    "Truncating embedding from {} to {} dimensions for collection '{}'",
-                    embedding.vector.len(), target_dim, collection_name
-                );
-                embedding.vector[..target_dim].to_vec()
-            } else if embedding.vector.len() < target_dim {
-                warn!(
-                    "Embedding dimension ({}) is smaller than collection dimension ({}). \
-                    Search may return poor results for collection '{}'.",
-                    embedding.vector.len(), target_dim, collection_name
-                );
-                let mut padded = embedding.vector.clone();
-                padded.resize(target_dim, 0.0);
-                padded
-            } else {
-                embedding.vector
+        if let Some(stored) = stored {
+            let query_model = &embedding_generator.config().embedding_model;
+            if let Err(mismatch) =
+                check_embedding(collection_name, &stored, query_model, embedding.vector.len())
+            {
+                warn!("{}", mismatch);
+                return Err(mismatch.into());
             }
+        }
+
+        self.execute_search(collection_name, embedding.vector, limit).await
+    }
+
+    /// Model and dimensions of the collection's vectors, or `None` when it does not exist.
+    /// Cached per collection for [`STORED_EMBEDDING_TTL`], so searches do not read Qdrant
+    /// twice per query.
+    pub async fn stored_embedding(&self, collection_name: &str) -> Result<Option<StoredEmbedding>> {
+        let key = (self.qdrant_config.url.clone(), collection_name.to_string());
+        if let Some((at, stored)) = STORED_EMBEDDINGS.read().ok().and_then(|c| c.get(&key).cloned()) {
+            if at.elapsed() < STORED_EMBEDDING_TTL {
+                return Ok(Some(stored));
+            }
+        }
+        let stored = self.read_stored_embedding(collection_name).await?;
+        if let (Some(stored), Ok(mut cache)) = (&stored, STORED_EMBEDDINGS.write()) {
+            cache.insert(key, (std::time::Instant::now(), stored.clone()));
+        }
+        Ok(stored)
+    }
+
+    /// Drops the cached [`StoredEmbedding`] of a collection that was deleted or re-pointed.
+    fn forget_stored_embedding(&self, collection_name: &str) {
+        if let Ok(mut cache) = STORED_EMBEDDINGS.write() {
+            cache.remove(&(self.qdrant_config.url.clone(), collection_name.to_string()));
+        }
+    }
+
+    async fn read_stored_embedding(&self, collection_name: &str) -> Result<Option<StoredEmbedding>> {
+        let Some(dimensions) = self.get_collection_vector_dimension(collection_name).await? else {
+            return Ok(None);
+        };
+
+        let scroll_url = format!(
+            "{}/collections/{}/points/scroll",
+            self.qdrant_config.url, collection_name
+        );
+        let response = self
+            .http_client
+            .post(&scroll_url)
+            .json(&serde_json::json!({
+                "limit": 1,
+                "with_payload": ["embedding_model"],
+                "with_vector": false,
+            }))
+            .send()
+            .await?;
+        let model = if response.status().is_success() {
+            let body: serde_json::Value = response.json().await?;
+            body["result"]["points"][0]["payload"]["embedding_model"]
+                .as_str()
+                .map(str::to_string)
         } else {
-            embedding.vector
+            debug!("Could not read a point of {}, checking dimensions only", collection_name);
+            None
         };
 
-        self.execute_search(collection_name, search_vector, limit).await
+        Ok(Some(StoredEmbedding { model, dimensions }))
     }
 
     async fn execute_search(
@@ -785,11 +1132,17 @@ impl KbIndexer {
                         .unwrap_or_default()
                         .to_string();
 
+                    let metadata = payload
+                        .iter()
+                        .filter(|(key, _)| !matches!(key.as_str(), "content" | "document_path"))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect();
+
                     results.push(SearchResult {
                         content,
                         document_path,
                         score: score as f32,
-                        metadata: serde_json::Map::new(),
+                        metadata,
                     });
                 }
             }
@@ -799,6 +1152,7 @@ impl KbIndexer {
     }
 
     pub async fn delete_collection(&self, collection_name: &str) -> Result<()> {
+        self.forget_stored_embedding(collection_name);
         let delete_url = format!("{}/collections/{}", self.qdrant_config.url, collection_name);
 
         let response = self.http_client.delete(&delete_url).send().await?;
@@ -893,6 +1247,47 @@ impl KbIndexer {
     }
 }
 
+#[async_trait]
+impl ChunkStore for KbIndexer {
+    async fn upsert_chunks(&self, collection: &str, points: Vec<ChunkPoint>) -> Result<()> {
+        let points = points
+            .into_iter()
+            .map(|p| QdrantPoint {
+                id: p.id.to_string(),
+                vector: p.vector,
+                payload: p.payload,
+            })
+            .collect();
+        self.upsert_points(collection, points).await
+    }
+
+    async fn delete_chunks(&self, collection: &str, ids: &[Uuid]) -> Result<()> {
+        let delete_url = format!(
+            "{}/collections/{}/points/delete?wait=true",
+            self.qdrant_config.url, collection
+        );
+
+        let response = self
+            .http_client
+            .post(&delete_url)
+            .json(&serde_json::json!({ "points": ids }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Failed to delete points: {}", error_text));
+        }
+
+        debug!("Deleted {} obsolete points from collection {}", ids.len(), collection);
+        Ok(())
+    }
+
+    async fn delete_source(&self, collection: &str, source: &str) -> Result<()> {
+        self.delete_file_points(collection, source).await
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CollectionInfo {
     pub name: String,
@@ -968,3 +1363,47 @@ impl KbFolderMonitor {
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stored(model: Option<&str>, dimensions: usize) -> StoredEmbedding {
+        StoredEmbedding {
+            model: model.map(str::to_string),
+            dimensions,
+        }
+    }
+
+    #[test]
+    fn test_query_with_other_model_is_rejected() {
+        let bge = stored(Some("BAAI/bge-m3"), 1024);
+        assert!(check_embedding("kb", &bge, "BAAI/bge-m3", 1024).is_ok());
+
+        let mismatch = check_embedding("kb", &bge, "text-embedding-3-small", 1536).unwrap_err();
+        assert_eq!(mismatch.stored, bge);
+        let message = mismatch.to_string();
+        assert!(message.contains("BAAI/bge-m3 (1024 dimensions)"));
+        assert!(message.contains("text-embedding-3-small (1536 dimensions)"));
+
+        // Same dimensions are not enough when the model differs.
+        assert!(check_embedding("kb", &bge, "intfloat/e5-large", 1024).is_err());
+
+        // Collections indexed before tagging are checked by dimension only.
+        let untagged = stored(None, 384);
+        assert!(check_embedding("kb", &untagged, "all-MiniLM-L6-v2", 384).is_ok());
+        assert!(check_embedding("kb", &untagged, "BAAI/bge-m3", 1024).is_err());
+    }
+
+    #[test]
+    fn test_collections_per_model() {
+        let mut config = EmbeddingConfig {
+            embedding_model: "BAAI/bge-m3".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(config.collection_name("bot_1a2b3c4d_faq"), "bot_1a2b3c4d_faq");
+
+        config.collection_per_model = true;
+        assert_eq!(config.collection_name("bot_1a2b3c4d_faq"), "bot_1a2b3c4d_faq__baai_bge_m3");
+    }
+}
@@ -1,10 +1,17 @@
+pub mod chunking;
+pub mod crawl_freshness;
 pub mod document_processor;
 pub mod embedding_generator;
+pub mod embedding_model;
 pub mod kb_indexer;
 pub mod permissions;
+pub mod query_debug;
+pub mod reindex;
 pub mod web_crawler;
 pub mod website_crawler_service;
 
+pub use chunking::{ChunkingConfig, ChunkingStrategy};
+pub use crawl_freshness::{list_source_status, SourceStatus};
 pub use document_processor::{DocumentFormat, DocumentProcessor, TextChunk};
 pub use embedding_generator::{
     EmailEmbeddingGenerator, EmbeddingConfig, EmbeddingGenerator, KbEmbeddingGenerator,
@@ -139,16 +146,30 @@ impl KnowledgeBaseManager {
         query: &str,
         limit: usize,
     ) -> Result<Vec<SearchResult>> {
-        let bot_id_short = bot_id.to_string().chars().take(8).collect::<String>();
-        let collection_name = format!("{}_{}_{}", bot_name, bot_id_short, kb_name);
+        let base_name = Self::base_collection_name(bot_id, bot_name, kb_name);
         
         // Use from_bot_config with state connection if available
         if let Some(pool) = self.indexer.get_db_pool() {
             let embedding_config = EmbeddingConfig::from_bot_config(pool, &bot_id);
+            let collection_name = embedding_config.collection_name(&base_name);
             self.indexer.search_with_config(&collection_name, query, limit, &embedding_config).await
         } else {
             // Fallback to default config
-            self.indexer.search(&collection_name, query, limit).await
+            self.indexer.search(&base_name, query, limit).await
+        }
+    }
+
+    fn base_collection_name(bot_id: Uuid, bot_name: &str, kb_name: &str) -> String {
+        let bot_id_short = bot_id.to_string().chars().take(8).collect::<String>();
+        format!("{}_{}_{}", bot_name, bot_id_short, kb_name)
+    }
+
+    /// Collection of a KB for the bot's current embedding model.
+    fn collection_name(&self, bot_id: Uuid, bot_name: &str, kb_name: &str) -> String {
+        let base_name = Self::base_collection_name(bot_id, bot_name, kb_name);
+        match self.indexer.get_db_pool() {
+            Some(pool) => EmbeddingConfig::from_bot_config(pool, &bot_id).collection_name(&base_name),
+            None => base_name,
         }
     }
 
@@ -255,8 +276,7 @@ impl KnowledgeBaseManager {
     }
 
     pub async fn clear_kb(&self, bot_id: Uuid, bot_name: &str, kb_name: &str) -> Result<()> {
-        let bot_id_short = bot_id.to_string().chars().take(8).collect::<String>();
-        let collection_name = format!("{}_{}_{}", bot_name, bot_id_short, kb_name);
+        let collection_name = self.collection_name(bot_id, bot_name, kb_name);
 
         warn!("Clearing knowledge base collection: {}", collection_name);
 
@@ -273,8 +293,7 @@ impl KnowledgeBaseManager {
     }
 
     pub async fn delete_file_from_kb(&self, bot_id: Uuid, bot_name: &str, kb_name: &str, file_path: &str) -> Result<()> {
-        let bot_id_short = bot_id.to_string().chars().take(8).collect::<String>();
-        let collection_name = format!("{}_{}_{}", bot_name, bot_id_short, kb_name);
+        let collection_name = self.collection_name(bot_id, bot_name, kb_name);
 
         // Use the relative path within the gbkb folder (e.g., "cartas/file.pdf")
         let relative_path = file_path
@@ -296,8 +315,7 @@ impl KnowledgeBaseManager {
     }
 
     pub async fn get_kb_stats(&self, bot_id: Uuid, bot_name: &str, kb_name: &str) -> Result<KbStatistics> {
-        let bot_id_short = bot_id.to_string().chars().take(8).collect::<String>();
-        let collection_name = format!("{}_{}_{}", bot_name, bot_id_short, kb_name);
+        let collection_name = self.collection_name(bot_id, bot_name, kb_name);
 
         let collection_info = self.indexer.get_collection_info(&collection_name).await?;
 
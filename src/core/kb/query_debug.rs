@@ -0,0 +1,250 @@
+//! Retrieval debugging: runs a query through a bot's knowledge bases the way a chat
+//! message would and returns what was retrieved and the prompt context built from it,
+//! without asking the LLM for an answer.
+
+use crate::core::bot::kb_context::{
+    format_kb_context, sanitize_context, KbContext, KbContextManager,
+};
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::state::AppState;
+use crate::core::urls::ApiUrls;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{extract::State, routing::post, Json, Router};
+use diesel::prelude::*;
+use diesel::sql_types::{Text, Uuid as DieselUuid};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::SearchResult;
+
+/// Results per KB and context token budget used for chat messages.
+const DEFAULT_TOP_K: usize = 20;
+const DEFAULT_MAX_TOKENS: usize = 8000;
+const MAX_TOP_K: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct KbQueryDebugRequest {
+    pub bot_id: Uuid,
+    pub query: String,
+    /// Only search this knowledge base instead of all of the bot's.
+    #[serde(default)]
+    pub kb_name: Option<String>,
+    #[serde(default)]
+    pub top_k: Option<usize>,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugChunk {
+    pub kb_name: String,
+    pub content: String,
+    pub document_path: String,
+    pub score: f32,
+    pub tokens: usize,
+    /// Chunk payload besides content and path: title, chunk index, chunking, ...
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    /// Whether the chunk made it into the prompt context.
+    pub included: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KbQueryDebugResponse {
+    pub bot_id: Uuid,
+    pub query: String,
+    pub knowledge_bases: Vec<String>,
+    pub chunks: Vec<DebugChunk>,
+    /// Knowledge bases whose search failed, with the error.
+    pub errors: Vec<String>,
+    pub context: String,
+    pub context_tokens: usize,
+}
+
+/// Every retrieved chunk of a KB, best first, flagged with whether the retriever kept it.
+fn debug_chunks(
+    kb_name: &str,
+    candidates: Vec<SearchResult>,
+    selected: &KbContext,
+) -> Vec<DebugChunk> {
+    let kept: HashSet<(&str, &str)> = selected
+        .search_results
+        .iter()
+        .map(|r| (r.document_path.as_str(), r.content.as_str()))
+        .collect();
+    let mut chunks: Vec<DebugChunk> = candidates
+        .into_iter()
+        .map(|c| DebugChunk {
+            included: kept.contains(&(c.document_path.as_str(), c.content.as_str())),
+            kb_name: kb_name.to_string(),
+            tokens: c.content.len() / 4,
+            content: c.content,
+            document_path: c.document_path,
+            score: c.score,
+            metadata: c.metadata,
+        })
+        .collect();
+    chunks.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    chunks
+}
+
+#[derive(QueryableByName)]
+struct NameRow {
+    #[diesel(sql_type = Text)]
+    name: String,
+}
+
+/// Name of the bot and its knowledge bases, or `None` if the bot does not exist.
+fn load_bot_kbs(
+    conn: &mut PgConnection,
+    bot_id: Uuid,
+) -> QueryResult<Option<(String, Vec<String>)>> {
+    let Some(bot) = diesel::sql_query("SELECT name FROM bots WHERE id = $1 AND deleted_at IS NULL")
+        .bind::<DieselUuid, _>(bot_id)
+        .get_result::<NameRow>(conn)
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let kbs = diesel::sql_query("SELECT name FROM kb_collections WHERE bot_id = $1 ORDER BY name")
+        .bind::<DieselUuid, _>(bot_id)
+        .load::<NameRow>(conn)?;
+    Ok(Some((
+        bot.name,
+        kbs.into_iter().map(|kb| kb.name).collect(),
+    )))
+}
+
+pub async fn handle_kb_query_debug(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<KbQueryDebugRequest>,
+) -> Result<Json<KbQueryDebugResponse>, ApiError> {
+    let query = req.query.trim().to_string();
+    if query.is_empty() {
+        return Err(ApiError::bad_request("query must not be empty"));
+    }
+    if !user.can_access_bot(&req.bot_id) {
+        return Err(ApiError::forbidden("No access to this bot"));
+    }
+    let kb_manager = state
+        .kb_manager
+        .clone()
+        .ok_or_else(|| ApiError::service_unavailable("Knowledge base is not available"))?;
+
+    let pool = state.read_pool().clone();
+    let bot_id = req.bot_id;
+    let (bot_name, mut kb_names) = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        load_bot_kbs(&mut conn, bot_id).map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??
+    .ok_or_else(|| ApiError::not_found("Bot not found"))?;
+
+    if let Some(kb_name) = req.kb_name.as_deref() {
+        if !kb_names.iter().any(|kb| kb == kb_name) {
+            return Err(ApiError::not_found(format!(
+                "Knowledge base '{}' not found",
+                kb_name
+            )));
+        }
+        kb_names = vec![kb_name.to_string()];
+    }
+
+    let top_k = req.top_k.unwrap_or(DEFAULT_TOP_K).clamp(1, MAX_TOP_K);
+    let max_tokens = req.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+    let manager = KbContextManager::new(kb_manager, state.conn.clone());
+
+    let mut chunks = Vec::new();
+    let mut contexts = Vec::new();
+    let mut errors = Vec::new();
+    for kb_name in &kb_names {
+        match manager
+            .search_kb_with_candidates(bot_id, &bot_name, kb_name, &query, top_k, max_tokens)
+            .await
+        {
+            Ok((candidates, context)) => {
+                chunks.extend(debug_chunks(kb_name, candidates, &context));
+                contexts.push(context);
+            }
+            Err(e) => {
+                warn!("KB debug query failed for '{}': {}", kb_name, e);
+                errors.push(format!("{}: {}", kb_name, e));
+            }
+        }
+    }
+
+    let context = sanitize_context(&format_kb_context(&contexts));
+    Ok(Json(KbQueryDebugResponse {
+        bot_id,
+        query,
+        knowledge_bases: kb_names,
+        chunks,
+        errors,
+        context_tokens: context.len() / 4,
+        context,
+    }))
+}
+
+pub fn configure() -> Router<Arc<AppState>> {
+    Router::new().route(ApiUrls::KB_QUERY_DEBUG, post(handle_kb_query_debug))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::bot::kb_context::KbSearchResult;
+
+    fn result(path: &str, content: &str, score: f32) -> SearchResult {
+        SearchResult {
+            content: content.to_string(),
+            document_path: path.to_string(),
+            score,
+            metadata: serde_json::Map::from_iter([(
+                "chunk_index".to_string(),
+                serde_json::json!(0),
+            )]),
+        }
+    }
+
+    #[test]
+    fn test_debug_report_shows_chunks_of_ingested_document() {
+        let refund = "Refunds are accepted within 30 days of purchase with a receipt.";
+        let candidates = vec![
+            result("policies/refunds.md", "Shipping takes five days.", 0.12),
+            result("policies/refunds.md", refund, 0.83),
+        ];
+        let selected = KbContext {
+            kb_name: "policies".to_string(),
+            search_results: vec![KbSearchResult {
+                content: refund.to_string(),
+                document_path: "policies/refunds.md".to_string(),
+                score: 0.83,
+                chunk_tokens: refund.len() / 4,
+            }],
+            total_tokens: 0,
+        };
+
+        let chunks = debug_chunks("policies", candidates, &selected);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].content, refund);
+        assert!(chunks[0].included);
+        assert_eq!(chunks[0].metadata["chunk_index"], 0);
+        assert!(!chunks[1].included);
+
+        let context = format_kb_context(&[selected]);
+        assert!(context.contains(refund));
+        assert!(context.contains("Source: policies/refunds.md"));
+        assert!(!context.contains("Shipping"));
+    }
+}
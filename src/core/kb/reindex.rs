@@ -0,0 +1,670 @@
+//! Full rebuild of a bot's knowledge bases, after its chunking or embedding model changed.
+//!
+//! Each KB is re-read from its folder and re-chunked and re-embedded into a shadow
+//! collection while searches keep using the live one. Once every document is in, the live
+//! name is pointed at the shadow collection through a Qdrant alias and the old collection
+//! is dropped. The shadow collection of an unfinished rebuild is recorded in
+//! `kb_reindex_jobs`; starting the reindex again continues into it and skips documents it
+//! already holds, unless they changed since.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::state::AppState;
+use crate::core::shared::utils::DbPool;
+use crate::core::urls::ApiUrls;
+use crate::security::auth_api::AuthenticatedUser;
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::{
+    extract::{Path as AxumPath, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Text, Timestamptz, Uuid as DieselUuid};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use uuid::Uuid;
+
+use super::embedding_generator::EmbeddingConfig;
+use super::kb_indexer::{KbIndexer, QdrantConfig};
+
+/// Passes over a KB's folder before the swap. The first rebuilds everything; the others
+/// only pick up documents changed or removed while the previous pass ran.
+const MAX_PASSES: usize = 3;
+
+/// One knowledge base to rebuild.
+#[derive(Debug, Clone)]
+pub struct KbTarget {
+    pub bot_id: Uuid,
+    pub kb_name: String,
+    pub folder: PathBuf,
+    /// Name searches use, e.g. `mybot_1a2b3c4d_faq`.
+    pub live_collection: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct KbReindexProgress {
+    pub kb_name: String,
+    pub shadow_collection: Option<String>,
+    pub sources_total: usize,
+    pub sources_done: usize,
+    pub chunks_indexed: usize,
+    /// Whether the rebuilt collection is now the live one.
+    pub swapped: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReindexStatus {
+    pub bot_id: Uuid,
+    pub running: bool,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub knowledge_bases: Vec<KbReindexProgress>,
+}
+
+/// Storage a rebuild works against: the vector store and the job and document records.
+#[async_trait]
+pub trait ReindexBackend: Send + Sync {
+    /// Shadow collection of an unfinished rebuild of this KB.
+    async fn pending_shadow(&self, bot_id: Uuid, kb_name: &str) -> Result<Option<String>>;
+    /// Records a rebuild into `shadow` and returns the shadow collection of the KB's job:
+    /// `shadow`, or the one of a job another run recorded first.
+    async fn start_job(&self, bot_id: Uuid, kb_name: &str, shadow: &str) -> Result<String>;
+    async fn finish_job(&self, bot_id: Uuid, kb_name: &str) -> Result<()>;
+    async fn create_collection(&self, name: &str) -> Result<()>;
+    /// Documents in `folder` with their modification time.
+    async fn list_sources(&self, folder: &Path) -> Result<Vec<(String, DateTime<Utc>)>>;
+    /// Documents already in `collection`, with when they were written.
+    async fn indexed_sources(&self, collection: &str) -> Result<HashMap<String, DateTime<Utc>>>;
+    /// Writes `source` into `shadow` and returns its chunk count.
+    async fn index_source(&self, shadow: &str, target: &KbTarget, source: &str) -> Result<usize>;
+    async fn remove_source(&self, shadow: &str, source: &str) -> Result<()>;
+    /// Makes `shadow` serve searches under `live` and drops what served them before.
+    async fn swap(&self, live: &str, shadow: &str) -> Result<()>;
+}
+
+/// Rebuilds one KB into its shadow collection and swaps it in, reporting progress after
+/// every document. On error the job is kept so the next run resumes it.
+pub async fn reindex_kb(
+    backend: &dyn ReindexBackend,
+    target: &KbTarget,
+    mut report: impl FnMut(&KbReindexProgress) + Send,
+) -> Result<KbReindexProgress> {
+    let shadow = match backend.pending_shadow(target.bot_id, &target.kb_name).await? {
+        Some(shadow) => {
+            info!("Resuming reindex of {} into {}", target.live_collection, shadow);
+            shadow
+        }
+        None => {
+            let new_shadow = format!("{}_r{}", target.live_collection, Utc::now().timestamp());
+            let shadow = backend.start_job(target.bot_id, &target.kb_name, &new_shadow).await?;
+            info!("Reindexing {} into {}", target.live_collection, shadow);
+            shadow
+        }
+    };
+    backend.create_collection(&shadow).await?;
+
+    let mut progress = KbReindexProgress {
+        kb_name: target.kb_name.clone(),
+        shadow_collection: Some(shadow.clone()),
+        ..Default::default()
+    };
+    for _ in 0..MAX_PASSES {
+        let sources = backend.list_sources(&target.folder).await?;
+        let indexed = backend.indexed_sources(&shadow).await?;
+        let stale: Vec<&String> = sources
+            .iter()
+            .filter(|(source, modified)| indexed.get(source).map_or(true, |at| modified > at))
+            .map(|(source, _)| source)
+            .collect();
+        let current: HashSet<&String> = sources.iter().map(|(source, _)| source).collect();
+        let removed: Vec<&String> = indexed.keys().filter(|s| !current.contains(s)).collect();
+
+        progress.sources_total = sources.len();
+        progress.sources_done = sources.len() - stale.len();
+        report(&progress);
+        if stale.is_empty() && removed.is_empty() {
+            break;
+        }
+
+        for source in stale {
+            progress.chunks_indexed += backend.index_source(&shadow, target, source).await?;
+            progress.sources_done += 1;
+            report(&progress);
+        }
+        for source in removed {
+            backend.remove_source(&shadow, source).await?;
+        }
+    }
+
+    backend.swap(&target.live_collection, &shadow).await?;
+    backend.finish_job(target.bot_id, &target.kb_name).await?;
+    progress.swapped = true;
+    report(&progress);
+    info!(
+        "Reindexed {} ({} documents, {} chunks)",
+        target.live_collection, progress.sources_total, progress.chunks_indexed
+    );
+    Ok(progress)
+}
+
+static REINDEXES: OnceLock<Mutex<HashMap<Uuid, ReindexStatus>>> = OnceLock::new();
+
+fn reindexes() -> &'static Mutex<HashMap<Uuid, ReindexStatus>> {
+    REINDEXES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn update_status(bot_id: Uuid, update: impl FnOnce(&mut ReindexStatus)) {
+    if let Some(status) = reindexes().lock().unwrap_or_else(|e| e.into_inner()).get_mut(&bot_id) {
+        update(status);
+    }
+}
+
+/// Progress of the bot's current or last reindex since the server started.
+pub fn reindex_status(bot_id: Uuid) -> Option<ReindexStatus> {
+    reindexes()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&bot_id)
+        .cloned()
+}
+
+/// Rebuilds the bot's KBs one after another. A KB that fails keeps its job for the next
+/// run and does not stop the others.
+pub async fn reindex_bot(backend: Arc<dyn ReindexBackend>, bot_id: Uuid, targets: Vec<KbTarget>) {
+    for (index, target) in targets.iter().enumerate() {
+        let result = reindex_kb(backend.as_ref(), target, |progress| {
+            let progress = progress.clone();
+            update_status(bot_id, |status| status.knowledge_bases[index] = progress);
+        })
+        .await;
+        if let Err(e) = result {
+            error!("Reindex of {} failed: {}", target.live_collection, e);
+            update_status(bot_id, |status| {
+                status.knowledge_bases[index].error = Some(e.to_string());
+            });
+        }
+    }
+    update_status(bot_id, |status| {
+        status.running = false;
+        status.finished_at = Some(Utc::now());
+    });
+}
+
+/// Starts a reindex of every KB of the bot, or returns the one already running.
+pub fn start_reindex(
+    backend: Arc<dyn ReindexBackend>,
+    bot_id: Uuid,
+    targets: Vec<KbTarget>,
+) -> ReindexStatus {
+    let mut reindexes = reindexes().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(status) = reindexes.get(&bot_id).filter(|s| s.running) {
+        return status.clone();
+    }
+    let status = ReindexStatus {
+        bot_id,
+        running: true,
+        started_at: Utc::now(),
+        finished_at: None,
+        knowledge_bases: targets
+            .iter()
+            .map(|t| KbReindexProgress {
+                kb_name: t.kb_name.clone(),
+                ..Default::default()
+            })
+            .collect(),
+    };
+    reindexes.insert(bot_id, status.clone());
+    tokio::spawn(reindex_bot(backend, bot_id, targets));
+    status
+}
+
+/// [`ReindexBackend`] over Qdrant and the `kb_source_chunks` / `kb_reindex_jobs` tables.
+pub struct QdrantReindexBackend {
+    indexer: KbIndexer,
+    pool: DbPool,
+}
+
+impl QdrantReindexBackend {
+    /// Backend using the bot's current embedding and vector DB configuration.
+    pub fn for_bot(pool: DbPool, bot_id: &Uuid) -> Self {
+        let embedding_config = EmbeddingConfig::from_bot_config(&pool, bot_id);
+        let qdrant_config = QdrantConfig::from_config(pool.clone(), bot_id);
+        Self {
+            indexer: KbIndexer::new_with_pool(embedding_config, qdrant_config, pool.clone()),
+            pool,
+        }
+    }
+
+    async fn with_conn<T: Send + 'static>(
+        &self,
+        query: impl FnOnce(&mut PgConnection) -> QueryResult<T> + Send + 'static,
+    ) -> Result<T> {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get()?;
+            Ok(query(&mut conn)?)
+        })
+        .await?
+    }
+}
+
+#[derive(QueryableByName)]
+struct ShadowRow {
+    #[diesel(sql_type = Text)]
+    shadow_collection: String,
+}
+
+#[derive(QueryableByName)]
+struct IndexedRow {
+    #[diesel(sql_type = Text)]
+    source: String,
+    #[diesel(sql_type = Timestamptz)]
+    updated_at: DateTime<Utc>,
+}
+
+#[async_trait]
+impl ReindexBackend for QdrantReindexBackend {
+    async fn pending_shadow(&self, bot_id: Uuid, kb_name: &str) -> Result<Option<String>> {
+        let kb_name = kb_name.to_string();
+        self.with_conn(move |conn| {
+            diesel::sql_query(
+                "SELECT shadow_collection FROM kb_reindex_jobs WHERE bot_id = $1 AND kb_name = $2",
+            )
+            .bind::<DieselUuid, _>(bot_id)
+            .bind::<Text, _>(kb_name)
+            .get_result::<ShadowRow>(conn)
+            .optional()
+            .map(|row| row.map(|r| r.shadow_collection))
+        })
+        .await
+    }
+
+    async fn start_job(&self, bot_id: Uuid, kb_name: &str, shadow: &str) -> Result<String> {
+        let (kb_name, shadow) = (kb_name.to_string(), shadow.to_string());
+        self.with_conn(move |conn| {
+            // The no-op update makes RETURNING yield the existing job on conflict
+            diesel::sql_query(
+                "INSERT INTO kb_reindex_jobs (bot_id, kb_name, shadow_collection) VALUES ($1, $2, $3) \
+                 ON CONFLICT (bot_id, kb_name) DO UPDATE SET kb_name = kb_reindex_jobs.kb_name \
+                 RETURNING shadow_collection",
+            )
+            .bind::<DieselUuid, _>(bot_id)
+            .bind::<Text, _>(kb_name)
+            .bind::<Text, _>(shadow)
+            .get_result::<ShadowRow>(conn)
+            .map(|row| row.shadow_collection)
+        })
+        .await
+    }
+
+    async fn finish_job(&self, bot_id: Uuid, kb_name: &str) -> Result<()> {
+        let kb_name = kb_name.to_string();
+        self.with_conn(move |conn| {
+            diesel::sql_query("DELETE FROM kb_reindex_jobs WHERE bot_id = $1 AND kb_name = $2")
+                .bind::<DieselUuid, _>(bot_id)
+                .bind::<Text, _>(kb_name)
+                .execute(conn)
+                .map(|_| ())
+        })
+        .await
+    }
+
+    async fn create_collection(&self, name: &str) -> Result<()> {
+        self.indexer.ensure_collection(name).await
+    }
+
+    async fn list_sources(&self, folder: &Path) -> Result<Vec<(String, DateTime<Utc>)>> {
+        let mut sources = Vec::new();
+        for path in self.indexer.list_documents(folder).await? {
+            let modified = tokio::fs::metadata(&path).await?.modified()?;
+            sources.push((path.to_string_lossy().to_string(), DateTime::<Utc>::from(modified)));
+        }
+        Ok(sources)
+    }
+
+    async fn indexed_sources(&self, collection: &str) -> Result<HashMap<String, DateTime<Utc>>> {
+        let collection = collection.to_string();
+        let rows = self
+            .with_conn(move |conn| {
+                diesel::sql_query(
+                    "SELECT source, updated_at FROM kb_source_chunks WHERE collection_name = $1",
+                )
+                .bind::<Text, _>(collection)
+                .load::<IndexedRow>(conn)
+            })
+            .await?;
+        Ok(rows.into_iter().map(|r| (r.source, r.updated_at)).collect())
+    }
+
+    async fn index_source(&self, shadow: &str, target: &KbTarget, source: &str) -> Result<usize> {
+        self.indexer
+            .index_document_into(
+                shadow,
+                &target.live_collection,
+                target.bot_id,
+                &target.kb_name,
+                Path::new(source),
+            )
+            .await
+    }
+
+    async fn remove_source(&self, shadow: &str, source: &str) -> Result<()> {
+        self.indexer.delete_file_points(shadow, source).await
+    }
+
+    async fn swap(&self, live: &str, shadow: &str) -> Result<()> {
+        let previous = self.indexer.swap_alias(live, shadow).await?;
+        if let Some(previous) = &previous {
+            self.indexer.delete_collection(previous).await?;
+        }
+
+        // The chunk ids recorded for the shadow collection now describe the live one.
+        let (live, shadow) = (live.to_string(), shadow.to_string());
+        self.with_conn(move |conn| {
+            conn.transaction(|conn| {
+                let mut retired = vec![live.clone()];
+                retired.extend(previous);
+                diesel::sql_query("DELETE FROM kb_source_chunks WHERE collection_name = ANY($1)")
+                    .bind::<diesel::sql_types::Array<Text>, _>(retired)
+                    .execute(conn)?;
+                diesel::sql_query(
+                    "UPDATE kb_source_chunks SET collection_name = $1 WHERE collection_name = $2",
+                )
+                .bind::<Text, _>(live)
+                .bind::<Text, _>(shadow)
+                .execute(conn)
+                .map(|_| ())
+            })
+        })
+        .await
+    }
+}
+
+#[derive(QueryableByName)]
+struct KbRow {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Text)]
+    folder_path: String,
+}
+
+#[derive(QueryableByName)]
+struct BotNameRow {
+    #[diesel(sql_type = Text)]
+    name: String,
+}
+
+/// The bot's knowledge bases, or `None` if the bot does not exist.
+fn load_targets(conn: &mut PgConnection, bot_id: Uuid) -> QueryResult<Option<Vec<KbTarget>>> {
+    let Some(bot) = diesel::sql_query("SELECT name FROM bots WHERE id = $1 AND deleted_at IS NULL")
+        .bind::<DieselUuid, _>(bot_id)
+        .get_result::<BotNameRow>(conn)
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let bot_id_short = bot_id.to_string().chars().take(8).collect::<String>();
+    let kbs = diesel::sql_query(
+        "SELECT name, folder_path FROM kb_collections WHERE bot_id = $1 ORDER BY name",
+    )
+    .bind::<DieselUuid, _>(bot_id)
+    .load::<KbRow>(conn)?;
+    Ok(Some(
+        kbs.into_iter()
+            .map(|kb| KbTarget {
+                bot_id,
+                live_collection: format!("{}_{}_{}", bot.name, bot_id_short, kb.name),
+                folder: PathBuf::from(kb.folder_path),
+                kb_name: kb.name,
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StartReindexRequest {
+    pub bot_id: Uuid,
+}
+
+pub async fn handle_start_reindex(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<StartReindexRequest>,
+) -> Result<(StatusCode, Json<ReindexStatus>), ApiError> {
+    if !user.can_access_bot(&req.bot_id) {
+        return Err(ApiError::forbidden("No access to this bot"));
+    }
+    let pool = state.conn.clone();
+    let bot_id = req.bot_id;
+    let targets = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        let targets =
+            load_targets(&mut conn, bot_id).map_err(|e| ApiError::internal(e.to_string()))?;
+        // With per-model collections, the rebuild targets the current model's collections.
+        let embedding = EmbeddingConfig::from_bot_config(&pool, &bot_id);
+        Ok::<_, ApiError>(targets.map(|targets| {
+            targets
+                .into_iter()
+                .map(|t| KbTarget {
+                    live_collection: embedding.collection_name(&t.live_collection),
+                    ..t
+                })
+                .collect::<Vec<_>>()
+        }))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??
+    .ok_or_else(|| ApiError::not_found("Bot not found"))?;
+    if targets.is_empty() {
+        warn!("Reindex requested for bot {} without knowledge bases", bot_id);
+        return Err(ApiError::not_found("Bot has no knowledge bases"));
+    }
+
+    let backend = Arc::new(QdrantReindexBackend::for_bot(state.conn.clone(), &bot_id));
+    Ok((StatusCode::ACCEPTED, Json(start_reindex(backend, bot_id, targets))))
+}
+
+pub async fn handle_reindex_status(
+    user: AuthenticatedUser,
+    AxumPath(bot_id): AxumPath<Uuid>,
+) -> Result<Json<ReindexStatus>, ApiError> {
+    if !user.can_access_bot(&bot_id) {
+        return Err(ApiError::forbidden("No access to this bot"));
+    }
+    reindex_status(bot_id)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("No reindex has run for this bot"))
+}
+
+pub fn configure() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(ApiUrls::KB_REINDEX, post(handle_start_reindex))
+        .route(ApiUrls::KB_REINDEX_STATUS, get(handle_reindex_status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collections hold source -> content; `files` is the KB folder.
+    #[derive(Default)]
+    struct MemoryState {
+        collections: HashMap<String, HashMap<String, String>>,
+        aliases: HashMap<String, String>,
+        indexed_at: HashMap<String, HashMap<String, DateTime<Utc>>>,
+        jobs: HashMap<(Uuid, String), String>,
+        files: HashMap<String, (DateTime<Utc>, String)>,
+        fail_on: Option<String>,
+        index_calls: Vec<String>,
+        /// What the live name served each time a document was written to the shadow.
+        live_during_build: Vec<HashMap<String, String>>,
+    }
+
+    impl MemoryState {
+        fn serving(&self, live: &str) -> HashMap<String, String> {
+            let name = self.aliases.get(live).map(String::as_str).unwrap_or(live);
+            self.collections.get(name).cloned().unwrap_or_default()
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryBackend(Mutex<MemoryState>);
+
+    #[async_trait]
+    impl ReindexBackend for MemoryBackend {
+        async fn pending_shadow(&self, bot_id: Uuid, kb_name: &str) -> Result<Option<String>> {
+            Ok(self.0.lock().unwrap().jobs.get(&(bot_id, kb_name.to_string())).cloned())
+        }
+
+        async fn start_job(&self, bot_id: Uuid, kb_name: &str, shadow: &str) -> Result<String> {
+            let mut state = self.0.lock().unwrap();
+            let job = state
+                .jobs
+                .entry((bot_id, kb_name.to_string()))
+                .or_insert_with(|| shadow.to_string());
+            Ok(job.clone())
+        }
+
+        async fn finish_job(&self, bot_id: Uuid, kb_name: &str) -> Result<()> {
+            self.0.lock().unwrap().jobs.remove(&(bot_id, kb_name.to_string()));
+            Ok(())
+        }
+
+        async fn create_collection(&self, name: &str) -> Result<()> {
+            self.0.lock().unwrap().collections.entry(name.to_string()).or_default();
+            Ok(())
+        }
+
+        async fn list_sources(&self, _folder: &Path) -> Result<Vec<(String, DateTime<Utc>)>> {
+            let state = self.0.lock().unwrap();
+            let mut sources: Vec<_> =
+                state.files.iter().map(|(source, (modified, _))| (source.clone(), *modified)).collect();
+            sources.sort();
+            Ok(sources)
+        }
+
+        async fn indexed_sources(&self, collection: &str) -> Result<HashMap<String, DateTime<Utc>>> {
+            Ok(self.0.lock().unwrap().indexed_at.get(collection).cloned().unwrap_or_default())
+        }
+
+        async fn index_source(&self, shadow: &str, target: &KbTarget, source: &str) -> Result<usize> {
+            let mut state = self.0.lock().unwrap();
+            if state.fail_on.as_deref() == Some(source) {
+                return Err(anyhow::anyhow!("embedding server went away"));
+            }
+            let live = state.serving(&target.live_collection);
+            state.live_during_build.push(live);
+            state.index_calls.push(source.to_string());
+            let content = format!("new:{}", state.files[source].1);
+            state.collections.get_mut(shadow).unwrap().insert(source.to_string(), content);
+            state
+                .indexed_at
+                .entry(shadow.to_string())
+                .or_default()
+                .insert(source.to_string(), Utc::now());
+            Ok(2)
+        }
+
+        async fn remove_source(&self, shadow: &str, source: &str) -> Result<()> {
+            let mut state = self.0.lock().unwrap();
+            state.collections.get_mut(shadow).unwrap().remove(source);
+            state.indexed_at.get_mut(shadow).unwrap().remove(source);
+            Ok(())
+        }
+
+        async fn swap(&self, live: &str, shadow: &str) -> Result<()> {
+            let mut state = self.0.lock().unwrap();
+            let previous = state.aliases.insert(live.to_string(), shadow.to_string());
+            state.collections.remove(previous.as_deref().unwrap_or(live));
+            Ok(())
+        }
+    }
+
+    fn backend_with_live_kb() -> (MemoryBackend, KbTarget) {
+        let target = KbTarget {
+            bot_id: Uuid::new_v4(),
+            kb_name: "faq".to_string(),
+            folder: PathBuf::from("/work/bot/bot.gbkb/faq"),
+            live_collection: "bot_1234abcd_faq".to_string(),
+        };
+        let backend = MemoryBackend::default();
+        {
+            let mut state = backend.0.lock().unwrap();
+            let old = HashMap::from([
+                ("a.md".to_string(), "old:a".to_string()),
+                ("b.md".to_string(), "old:b".to_string()),
+            ]);
+            state.collections.insert(target.live_collection.clone(), old);
+            let modified = Utc::now() - chrono::Duration::hours(1);
+            for (source, text) in [("a.md", "a"), ("b.md", "b"), ("c.md", "c")] {
+                state.files.insert(source.to_string(), (modified, text.to_string()));
+            }
+        }
+        (backend, target)
+    }
+
+    #[tokio::test]
+    async fn test_shadow_rebuild_leaves_live_serving_until_swap() {
+        let (backend, target) = backend_with_live_kb();
+        let mut reports = Vec::new();
+
+        let progress = reindex_kb(&backend, &target, |p| reports.push(p.clone())).await.unwrap();
+
+        let state = backend.0.lock().unwrap();
+        assert_eq!(state.live_during_build.len(), 3);
+        for live in &state.live_during_build {
+            assert_eq!(live.len(), 2);
+            assert_eq!(live["a.md"], "old:a");
+        }
+
+        let served = state.serving(&target.live_collection);
+        assert_eq!(served.len(), 3);
+        assert_eq!(served["c.md"], "new:c");
+        assert!(state.jobs.is_empty());
+
+        assert!(progress.swapped);
+        assert_eq!((progress.sources_done, progress.sources_total), (3, 3));
+        assert_eq!(progress.chunks_indexed, 6);
+        assert!(reports.iter().any(|p| p.sources_done == 1 && !p.swapped));
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_reindex_resumes_into_same_shadow() {
+        let (backend, target) = backend_with_live_kb();
+        backend.0.lock().unwrap().fail_on = Some("b.md".to_string());
+
+        assert!(reindex_kb(&backend, &target, |_| {}).await.is_err());
+        let shadow = {
+            let state = backend.0.lock().unwrap();
+            assert_eq!(state.serving(&target.live_collection)["a.md"], "old:a");
+            state.jobs[&(target.bot_id, "faq".to_string())].clone()
+        };
+
+        {
+            let mut state = backend.0.lock().unwrap();
+            state.fail_on = None;
+            state.index_calls.clear();
+            state.files.remove("c.md");
+        }
+        let progress = reindex_kb(&backend, &target, |_| {}).await.unwrap();
+
+        let state = backend.0.lock().unwrap();
+        assert_eq!(progress.shadow_collection.as_deref(), Some(shadow.as_str()));
+        assert_eq!(state.index_calls, ["b.md"]);
+        assert_eq!(state.aliases[&target.live_collection], shadow);
+        let mut served: Vec<String> = state.serving(&target.live_collection).into_keys().collect();
+        served.sort();
+        assert_eq!(served, ["a.md", "b.md"]);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::core::config::ConfigManager;
+use crate::core::kb::crawl_freshness::{compute_crawl_delta, load_page_hashes, store_page_hashes};
 use crate::core::kb::web_crawler::{WebCrawler, WebsiteCrawlConfig};
 use crate::core::kb::embedding_generator::EmbeddingConfig;
 use crate::core::kb::kb_indexer::{KbIndexer, QdrantConfig};
@@ -245,6 +246,49 @@ impl WebsiteCrawlerService {
             let qdrant_config = QdrantConfig::from_config(db_pool.clone(), &website.bot_id);
             let bot_indexer = KbIndexer::new(embedding_config, qdrant_config);
 
+                // Compare page hashes with the previous crawl so unchanged pages are not re-embedded
+                let known_hashes = load_page_hashes(&mut conn, &website.id).unwrap_or_default();
+                let delta = compute_crawl_delta(&pages, &known_hashes);
+
+                if !delta.has_changes() {
+                    info!(
+                        "No content changes for {} ({} pages unchanged), skipping re-indexing",
+                        website.url, delta.unchanged
+                    );
+
+                    config.calculate_next_crawl();
+
+                    diesel::sql_query(
+                        "UPDATE website_crawls
+                         SET last_crawled = NOW(),
+                             next_crawl = $1,
+                             crawl_status = 1,
+                             change_detected = FALSE,
+                             error_message = NULL
+                         WHERE id = $2",
+                    )
+                    .bind::<diesel::sql_types::Nullable<diesel::sql_types::Timestamptz>, _>(
+                        config.next_crawl,
+                    )
+                    .bind::<diesel::sql_types::Uuid, _>(&website.id)
+                    .execute(&mut conn)?;
+
+                    store_page_hashes(&mut conn, &website.id, &delta.fingerprints)?;
+                    return Ok(());
+                }
+
+                trace!(
+                    "{} changed pages, {} unchanged for {}",
+                    delta.changed.len(), delta.unchanged, website.url
+                );
+
+                // Only changed pages are written to the delta folder that gets embedded
+                let delta_path = work_path.with_extension("delta");
+                if delta_path.exists() {
+                    tokio::fs::remove_dir_all(&delta_path).await?;
+                }
+                tokio::fs::create_dir_all(&delta_path).await?;
+
                 // Process pages in small batches to prevent memory exhaustion
                 const BATCH_SIZE: usize = 5;
                 let total_pages = pages.len();
@@ -273,13 +317,17 @@ impl WebsiteCrawlerService {
                             content_preview
                         );
 
+                        if delta.changed.contains(&global_idx) {
+                            tokio::fs::write(delta_path.join(&filename), &content).await?;
+                        }
+
                         tokio::fs::write(&filepath, content).await?;
                     }
 
                     // Process this batch immediately to free memory
                     if batch_idx == 0 || (batch_idx + 1) % 2 == 0 {
                         // Index every 2 batches to prevent memory buildup
-                        match bot_indexer.index_kb_folder(website.bot_id, &bot_name, &kb_name, &work_path).await {
+                        match bot_indexer.index_kb_folder(website.bot_id, &bot_name, &kb_name, &delta_path).await {
                             Ok(result) => trace!("Indexed batch {} successfully: {} docs, {} chunks",
                                 batch_idx + 1, result.documents_processed, result.chunks_indexed),
                             Err(e) => warn!("Failed to index batch {}: {}", batch_idx + 1, e),
@@ -292,9 +340,15 @@ impl WebsiteCrawlerService {
 
                 // Final indexing for any remaining content
                 bot_indexer
-                    .index_kb_folder(website.bot_id, &bot_name, &kb_name, &work_path)
+                    .index_kb_folder(website.bot_id, &bot_name, &kb_name, &delta_path)
                     .await?;
 
+                if let Err(e) = tokio::fs::remove_dir_all(&delta_path).await {
+                    trace!("Could not remove delta folder {}: {}", delta_path.display(), e);
+                }
+
+                store_page_hashes(&mut conn, &website.id, &delta.fingerprints)?;
+
                 config.calculate_next_crawl();
 
                 diesel::sql_query(
@@ -303,6 +357,8 @@ impl WebsiteCrawlerService {
                          next_crawl = $1,
                          crawl_status = 1,
                          pages_crawled = $2,
+                         last_changed = NOW(),
+                         change_detected = TRUE,
                          error_message = NULL
                      WHERE id = $3",
                 )
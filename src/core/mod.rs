@@ -1,5 +1,6 @@
 #[cfg(feature = "automation")]
 pub mod automation;
+pub mod backup;
 pub mod bootstrap;
 pub mod bot;
 pub mod bot_database;
@@ -22,6 +23,7 @@ pub mod organization_rbac;
 pub mod package_manager;
 pub mod performance;
 pub mod product;
+pub mod proxy;
 pub mod rate_limit;
 pub mod secrets;
 pub mod session;
@@ -12,6 +12,7 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -672,7 +673,7 @@ async fn list_invitations(
 async fn create_invitation(
     State(_state): State<Arc<AppState>>,
     Path(org_id): Path<Uuid>,
-    Json(req): Json<CreateInvitationRequest>,
+    ApiJson(req): ApiJson<CreateInvitationRequest>,
 ) -> Result<Json<InvitationResponse>, (StatusCode, Json<serde_json::Value>)> {
     let service = InvitationService::new();
 
@@ -712,7 +713,7 @@ async fn create_invitation(
 async fn bulk_invite(
     State(_state): State<Arc<AppState>>,
     Path(org_id): Path<Uuid>,
-    Json(req): Json<BulkInviteRequest>,
+    ApiJson(req): ApiJson<BulkInviteRequest>,
 ) -> Result<Json<BulkInviteResponse>, (StatusCode, Json<serde_json::Value>)> {
     let service = InvitationService::new();
 
@@ -796,7 +797,7 @@ async fn revoke_invitation(
 async fn resend_invitation(
     State(_state): State<Arc<AppState>>,
     Path((_org_id, invitation_id)): Path<(Uuid, Uuid)>,
-    Json(req): Json<ResendInvitationRequest>,
+    ApiJson(req): ApiJson<ResendInvitationRequest>,
 ) -> Result<Json<InvitationResponse>, (StatusCode, Json<serde_json::Value>)> {
     let service = InvitationService::new();
 
@@ -816,7 +817,7 @@ async fn resend_invitation(
 
 async fn accept_invitation(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<AcceptInvitationRequest>,
+    ApiJson(req): ApiJson<AcceptInvitationRequest>,
 ) -> Result<Json<AcceptInvitationResponse>, (StatusCode, Json<serde_json::Value>)> {
     let service = InvitationService::new();
 
@@ -833,7 +834,7 @@ async fn accept_invitation(
 
 async fn decline_invitation(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<AcceptInvitationRequest>,
+    ApiJson(req): ApiJson<AcceptInvitationRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let service = InvitationService::new();
 
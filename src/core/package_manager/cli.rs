@@ -314,6 +314,9 @@ fn print_usage() {
     println!("  rotate-secret <comp> Rotate a component's credentials");
     println!("                      (tables, drive, cache, email, directory, encryption, jwt)");
     println!("  rotate-secrets --all Rotate ALL credentials (dangerous!)");
+    println!("  selftest             Check database, Vault, drive, cache, LLM and embeddings");
+    println!("  backup               Archive database, drive and Vault secrets");
+    println!("  restore <archive>    Verify and restore a backup archive");
     println!("  version [--all]      Show version information");
     println!("  --version, -v        Show version");
     println!("  --help, -h           Show this help");
@@ -948,25 +951,22 @@ async fn rotate_secret(component: &str) -> Result<()> {
             }
         }
         "encryption" => {
-            let new_key = generate_password(64);
-            let mut secrets: HashMap<String, String> = HashMap::new();
+            let mut keyring = crate::security::master_key::load_from_vault(&manager).await?;
+            let previous = keyring.current_version();
+            let next = keyring.add_version();
 
-            println!("⚠️  CRITICAL WARNING: Rotating encryption key will make existing encrypted data unreadable!");
-            println!("⚠️  Make sure to re-encrypt all data with the new key!");
+            println!("A new master key version {} will be added; version {} stays available.", next, previous);
+            println!("⚠️  Existing data remains encrypted with the old key until re-encrypted.");
+            println!("⚠️  Run POST /api/admin/encryption/rotate to re-encrypt and retire old versions.");
             println!();
-            println!(
-                "New master key: {}...",
-                &new_key.chars().take(8).collect::<String>()
-            );
 
-            print!("Are you ABSOLUTELY sure? Type 'ROTATE' to confirm: ");
+            print!("Add new key version? Type 'ROTATE' to confirm: ");
             std::io::Write::flush(&mut std::io::stdout())?;
             let mut input = String::new();
             std::io::stdin().read_line(&mut input)?;
             if input.trim() == "ROTATE" {
-                secrets.insert("master_key".to_string(), new_key);
-                manager.put_secret(SecretPaths::ENCRYPTION, secrets).await?;
-                println!("✓ Encryption key saved to Vault");
+                manager.put_secret(SecretPaths::ENCRYPTION, keyring.to_secrets()).await?;
+                println!("✓ Encryption key version {} saved to Vault", next);
             } else {
                 println!("✗ Aborted");
             }
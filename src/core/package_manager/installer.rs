@@ -1,3 +1,4 @@
+use crate::core::bootstrap::vault::{read_vault_init, vault_init_path, write_vault_init, VaultInitError};
 use crate::core::package_manager::component::ComponentConfig;
 use crate::core::package_manager::os::detect_os;
 use crate::core::package_manager::{InstallMode, OsType};
@@ -1469,11 +1470,11 @@ EOF"#.to_string(),
             .as_str()
             .context("No root token in output")?;
 
-        // Save init.json
-        let init_json = self.base_path.join("conf/vault/init.json");
+        // Save init.json, keeping a timestamped backup
+        let init_json = vault_init_path(&self.base_path);
         std::fs::create_dir_all(init_json.parent().unwrap())?;
-        std::fs::write(&init_json, serde_json::to_string_pretty(&init_json_val)?)?;
-        info!("Created {}", init_json.display());
+        let backup = write_vault_init(&init_json, &init_json_val)?;
+        info!("Created {} (backup {})", init_json.display(), backup.display());
 
         // Create .env file with Vault credentials
         let env_file = std::path::PathBuf::from(".env");
@@ -1812,18 +1813,16 @@ VAULT_CACERT={}
         };
 
         // Try to read existing init.json for root token
-        let init_json = self.base_path.join("conf/vault/init.json");
-        let root_token = if init_json.exists() {
-            let content = std::fs::read_to_string(&init_json)?;
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                json.get("root_token")
-                    .and_then(|v| v.as_str())
-                    .map(String::from)
-            } else {
+        let root_token = match read_vault_init(&vault_init_path(&self.base_path)) {
+            Ok(init) => Some(init.root_token),
+            Err(e @ VaultInitError::Missing(_)) => {
+                warn!("{}", e);
                 None
             }
-        } else {
-            None
+            Err(e) => {
+                error!("{}", e);
+                return Err(e.into());
+            }
         };
 
         // Unseal if we have keys
@@ -1892,29 +1891,38 @@ VAULT_CACERT={}
     fn unseal_vault(&self, vault_bin: &std::path::Path, vault_addr: &str) -> Result<()> {
         info!("Unsealing Vault...");
         let unseal_keys_file = self.base_path.join("vault-unseal-keys");
-        if unseal_keys_file.exists() {
+        let keys: Vec<String> = if unseal_keys_file.exists() {
             let content = std::fs::read_to_string(&unseal_keys_file)?;
-            let keys: Vec<String> = content
+            content
                 .lines()
                 .filter_map(|line| {
                     line.strip_prefix("VAULT_UNSEAL_KEY_")
                         .and_then(|rest| rest.split_once('='))
                         .map(|(_, key)| key.to_string())
                 })
-                .collect();
+                .collect()
+        } else {
+            match read_vault_init(&vault_init_path(&self.base_path)) {
+                Ok(init) => init.unseal_keys_b64,
+                Err(VaultInitError::Missing(_)) => Vec::new(),
+                Err(e) => {
+                    error!("{}", e);
+                    return Err(e.into());
+                }
+            }
+        };
 
-            for (i, key) in keys.iter().take(3).enumerate() {
-                let unseal_cmd = format!(
-                    "{} operator unseal -tls-skip-verify -address={} {}",
-                    vault_bin.display(),
-                    vault_addr,
-                    key
-                );
-                let unseal_output = safe_sh_command(&unseal_cmd);
-                if let Some(ref output) = unseal_output {
-                    if !output.status.success() {
-                        warn!("Unseal step {} may have failed", i + 1);
-                    }
+        for (i, key) in keys.iter().take(3).enumerate() {
+            let unseal_cmd = format!(
+                "{} operator unseal -tls-skip-verify -address={} {}",
+                vault_bin.display(),
+                vault_addr,
+                key
+            );
+            let unseal_output = safe_sh_command(&unseal_cmd);
+            if let Some(ref output) = unseal_output {
+                if !output.status.success() {
+                    warn!("Unseal step {} may have failed", i + 1);
                 }
             }
         }
@@ -1923,19 +1931,14 @@ VAULT_CACERT={}
 
     /// Ensure .env file exists with Vault credentials
     fn ensure_env_file_exists(&self) -> Result<()> {
-        let init_json = self.base_path.join("conf/vault/init.json");
         let env_file = std::path::PathBuf::from(".env");
 
-        if !init_json.exists() {
-            return Ok(()); // No init, no .env needed yet
-        }
-
-        let init_content = std::fs::read_to_string(&init_json)?;
-        let init_json_val: serde_json::Value = serde_json::from_str(&init_content)?;
-
-        let root_token = init_json_val["root_token"]
-            .as_str()
-            .context("No root_token in init.json")?;
+        let init = match read_vault_init(&vault_init_path(&self.base_path)) {
+            Ok(init) => init,
+            Err(VaultInitError::Missing(_)) => return Ok(()), // No init, no .env needed yet
+            Err(e) => return Err(e.into()),
+        };
+        let root_token = &init.root_token;
 
         let conf_path = self.base_path.join("conf");
         let ca_cert = conf_path.join("system/certificates/ca/ca.crt");
@@ -0,0 +1,469 @@
+//! Caddy site blocks for hostnames provisioned at runtime.
+//!
+//! Routes live between two marker comments at the end of the Caddyfile. Only that
+//! section is rewritten, so hand edits elsewhere in the file survive. After each change
+//! Caddy is reloaded; if the reload fails the previous file is put back.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::urls::ApiUrls;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::security::command_guard::SafeCommand;
+use axum::{
+    extract::{Path as UrlPath, State},
+    http::StatusCode,
+    response::Json,
+    routing::{delete, get},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use tokio::sync::Mutex;
+
+/// Where provisioned tenant hostnames are proxied to.
+pub const TENANT_UPSTREAM_ENV: &str = "TENANT_UPSTREAM";
+
+const BEGIN_MARKER: &str = "# BEGIN botserver managed routes";
+const END_MARKER: &str = "# END botserver managed routes";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyRoute {
+    pub hostname: String,
+    /// `host:port`, optionally with an `http://` or `https://` scheme.
+    pub upstream: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RouteError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("'{0}' already has a hand-written site block")]
+    Conflict(String),
+    #[error("No route for '{0}'")]
+    NotFound(String),
+    #[error("Failed to update Caddyfile: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Caddy reload failed, previous configuration restored: {0}")]
+    Reload(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    pub caddyfile_path: PathBuf,
+    /// Caddy binary used for `caddy reload`; `None` leaves reloading to the operator.
+    pub caddy_binary: Option<String>,
+}
+
+impl ProxyConfig {
+    /// `CADDYFILE` overrides the stack's `conf/Caddyfile`. The stack's own Caddy binary is
+    /// used for reloads when installed, otherwise `caddy` from the PATH.
+    pub fn from_env() -> Self {
+        let stack = crate::core::shared::utils::get_stack_path();
+        let caddyfile_path = std::env::var("CADDYFILE")
+            .ok()
+            .filter(|p| !p.trim().is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(format!("{stack}/conf/Caddyfile")));
+        let bundled = format!("{stack}/bin/proxy/caddy");
+        let caddy_binary = if Path::new(&bundled).exists() {
+            bundled
+        } else {
+            "caddy".to_string()
+        };
+        Self {
+            caddyfile_path,
+            caddy_binary: Some(caddy_binary),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.caddyfile_path.is_file()
+    }
+}
+
+/// Upstream for tenant routes added at provisioning: `TENANT_UPSTREAM`, or this server
+/// on loopback at `PORT` (default 8080).
+pub fn tenant_upstream() -> String {
+    std::env::var(TENANT_UPSTREAM_ENV)
+        .ok()
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .unwrap_or_else(|| {
+            let port = std::env::var("PORT")
+                .ok()
+                .and_then(|p| p.parse::<u16>().ok())
+                .unwrap_or(8080);
+            format!("127.0.0.1:{port}")
+        })
+}
+
+fn is_valid_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= 63
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+}
+
+/// A fully qualified host name: at least two labels.
+fn valid_hostname(hostname: &str) -> Result<String, RouteError> {
+    let hostname = hostname.trim().trim_end_matches('.').to_lowercase();
+    let labels: Vec<&str> = hostname.split('.').collect();
+    if hostname.len() > 253 || labels.len() < 2 || !labels.iter().all(|l| is_valid_label(l)) {
+        return Err(RouteError::Invalid(format!(
+            "'{}' is not a valid host name",
+            hostname
+        )));
+    }
+    Ok(hostname)
+}
+
+/// `host:port` with an optional http(s) scheme; the host is an IP or a host name.
+fn valid_upstream(upstream: &str) -> Result<String, RouteError> {
+    let invalid = || RouteError::Invalid(format!("'{}' is not a valid upstream", upstream));
+    let upstream = upstream.trim();
+    let address = upstream
+        .strip_prefix("http://")
+        .or_else(|| upstream.strip_prefix("https://"))
+        .unwrap_or(upstream);
+    let (host, port) = address.rsplit_once(':').ok_or_else(invalid)?;
+    port.parse::<u16>()
+        .ok()
+        .filter(|p| *p > 0)
+        .ok_or_else(invalid)?;
+    let host_ok = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .map_or_else(
+            || host.parse::<IpAddr>().is_ok() || host.split('.').all(is_valid_label),
+            |v6| v6.parse::<IpAddr>().is_ok(),
+        );
+    if !host_ok {
+        return Err(invalid());
+    }
+    Ok(upstream.to_string())
+}
+
+/// Splits a Caddyfile into the text before the managed section, the section's routes
+/// and the text after it.
+fn split_managed(content: &str) -> (String, Vec<ProxyRoute>, String) {
+    let Some(begin) = content.find(BEGIN_MARKER) else {
+        return (content.to_string(), Vec::new(), String::new());
+    };
+    let section_start = begin + BEGIN_MARKER.len();
+    let (section, after) = match content[section_start..].find(END_MARKER) {
+        Some(end) => (
+            &content[section_start..section_start + end],
+            &content[section_start + end + END_MARKER.len()..],
+        ),
+        None => (&content[section_start..], ""),
+    };
+
+    let mut routes = Vec::new();
+    let mut hostname: Option<&str> = None;
+    for line in section.lines().map(str::trim) {
+        if let Some(site) = line.strip_suffix('{') {
+            hostname = Some(site.trim());
+        } else if let (Some(host), Some(upstream)) = (hostname, line.strip_prefix("reverse_proxy"))
+        {
+            routes.push(ProxyRoute {
+                hostname: host.to_string(),
+                upstream: upstream.trim().to_string(),
+            });
+        } else if line == "}" {
+            hostname = None;
+        }
+    }
+    (
+        content[..begin].to_string(),
+        routes,
+        after.trim_start_matches('\n').to_string(),
+    )
+}
+
+/// Site addresses of the top-level blocks in hand-written parts of the file.
+fn site_addresses(text: &str) -> Vec<String> {
+    let mut depth = 0usize;
+    let mut sites = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        if depth == 0 {
+            if let Some(site) = line.strip_suffix('{') {
+                sites.extend(
+                    site.split([',', ' '])
+                        .map(|s| s.trim().to_lowercase())
+                        .filter(|s| !s.is_empty() && !s.starts_with('(')),
+                );
+            }
+        }
+        depth += line.matches('{').count();
+        depth = depth.saturating_sub(line.matches('}').count());
+    }
+    sites
+}
+
+fn render(before: &str, routes: &[ProxyRoute], after: &str) -> String {
+    let mut out = before.trim_end().to_string();
+    if routes.is_empty() {
+        out.push('\n');
+    } else {
+        // Sites get the TLS snippet when the hand-written config defines one.
+        let tls = before.contains("(tls_config)") || after.contains("(tls_config)");
+        let _ = write!(out, "\n\n{BEGIN_MARKER}\n");
+        for route in routes {
+            let _ = writeln!(out, "{} {{", route.hostname);
+            if tls {
+                out.push_str("    import tls_config\n");
+            }
+            let _ = writeln!(out, "    reverse_proxy {}\n}}", route.upstream);
+        }
+        let _ = writeln!(out, "{END_MARKER}");
+    }
+    if !after.trim().is_empty() {
+        out.push('\n');
+        out.push_str(after);
+    }
+    out
+}
+
+pub struct CaddyRoutes {
+    config: ProxyConfig,
+    write_lock: Mutex<()>,
+}
+
+impl CaddyRoutes {
+    pub fn new(config: ProxyConfig) -> Self {
+        Self {
+            config,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn list_routes(&self) -> Result<Vec<ProxyRoute>, RouteError> {
+        let content = fs::read_to_string(&self.config.caddyfile_path)?;
+        Ok(split_managed(&content).1)
+    }
+
+    /// Adds a site block proxying `hostname` to `upstream`, or repoints an existing one.
+    pub async fn register_route(
+        &self,
+        hostname: &str,
+        upstream: &str,
+    ) -> Result<ProxyRoute, RouteError> {
+        let route = ProxyRoute {
+            hostname: valid_hostname(hostname)?,
+            upstream: valid_upstream(upstream)?,
+        };
+        let added = route.clone();
+        self.update(move |before, routes, after| {
+            let hand_written = site_addresses(before)
+                .into_iter()
+                .chain(site_addresses(after))
+                .any(|site| {
+                    site == added.hostname || site.ends_with(&format!("//{}", added.hostname))
+                });
+            if hand_written {
+                return Err(RouteError::Conflict(added.hostname.clone()));
+            }
+            routes.retain(|r| r.hostname != added.hostname);
+            routes.push(added);
+            routes.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+            Ok(())
+        })
+        .await?;
+        log::info!(
+            "Caddy route {} -> {} registered",
+            route.hostname,
+            route.upstream
+        );
+        Ok(route)
+    }
+
+    pub async fn remove_route(&self, hostname: &str) -> Result<(), RouteError> {
+        let hostname = valid_hostname(hostname)?;
+        let target = hostname.clone();
+        self.update(move |_, routes, _| {
+            let count = routes.len();
+            routes.retain(|r| r.hostname != target);
+            if routes.len() == count {
+                return Err(RouteError::NotFound(target));
+            }
+            Ok(())
+        })
+        .await?;
+        log::info!("Caddy route {} removed", hostname);
+        Ok(())
+    }
+
+    async fn update<F>(&self, change: F) -> Result<(), RouteError>
+    where
+        F: FnOnce(&str, &mut Vec<ProxyRoute>, &str) -> Result<(), RouteError>,
+    {
+        let _writing = self.write_lock.lock().await;
+        let path = &self.config.caddyfile_path;
+        let original = fs::read_to_string(path)?;
+        let (before, mut routes, after) = split_managed(&original);
+        change(&before, &mut routes, &after)?;
+        write_atomically(path, &render(&before, &routes, &after))?;
+
+        if let Err(e) = self.reload().await {
+            write_atomically(path, &original)?;
+            return Err(RouteError::Reload(e));
+        }
+        Ok(())
+    }
+
+    async fn reload(&self) -> Result<(), String> {
+        let Some(binary) = &self.config.caddy_binary else {
+            return Ok(());
+        };
+        let output = SafeCommand::new(binary)
+            .and_then(|c| c.arg("reload"))
+            .and_then(|c| c.arg("--config"))
+            .and_then(|c| c.arg(self.config.caddyfile_path.to_string_lossy()))
+            .and_then(|c| c.arg("--adapter"))
+            .and_then(|c| c.arg("caddyfile"))
+            .map_err(|e| e.to_string())?
+            .execute_async()
+            .await
+            .map_err(|e| e.to_string())?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        }
+    }
+}
+
+impl From<RouteError> for ApiError {
+    fn from(e: RouteError) -> Self {
+        match &e {
+            RouteError::Invalid(_) => ApiError::bad_request(e.to_string()),
+            RouteError::Conflict(_) => ApiError::conflict(e.to_string()),
+            RouteError::NotFound(_) => ApiError::not_found(e.to_string()),
+            RouteError::Io(_) | RouteError::Reload(_) => ApiError::internal(e.to_string()),
+        }
+    }
+}
+
+pub async fn list_routes_handler(
+    State(routes): State<Arc<CaddyRoutes>>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<ProxyRoute>>, ApiError> {
+    require_admin(&user)?;
+    Ok(Json(routes.list_routes()?))
+}
+
+pub async fn register_route_handler(
+    State(routes): State<Arc<CaddyRoutes>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ProxyRoute>,
+) -> Result<Json<ProxyRoute>, ApiError> {
+    require_admin(&user)?;
+    Ok(Json(
+        routes.register_route(&req.hostname, &req.upstream).await?,
+    ))
+}
+
+pub async fn remove_route_handler(
+    State(routes): State<Arc<CaddyRoutes>>,
+    user: AuthenticatedUser,
+    UrlPath(hostname): UrlPath<String>,
+) -> Result<StatusCode, ApiError> {
+    require_admin(&user)?;
+    routes.remove_route(&hostname).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+static CADDY_ROUTES: LazyLock<Option<Arc<CaddyRoutes>>> = LazyLock::new(|| {
+    let config = ProxyConfig::from_env();
+    config.enabled().then(|| Arc::new(CaddyRoutes::new(config)))
+});
+
+/// The process-wide route manager, shared so every writer takes the same lock. `None`
+/// when there is no Caddyfile to manage.
+pub fn caddy_routes() -> Option<Arc<CaddyRoutes>> {
+    CADDY_ROUTES.clone()
+}
+
+pub fn configure_proxy_routes<S>(routes: Arc<CaddyRoutes>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    Router::new()
+        .route(
+            ApiUrls::PROXY_ROUTES,
+            get(list_routes_handler).post(register_route_handler),
+        )
+        .route(ApiUrls::PROXY_ROUTE, delete(remove_route_handler))
+        .with_state(routes)
+}
+
+fn write_atomically(path: &Path, content: &str) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAND_WRITTEN: &str = "{\n    email admin@example.com\n}\n\n(tls_config) {\n    tls internal\n}\n\napi.example.com {\n    reverse_proxy 127.0.0.1:8080\n}\n";
+
+    #[tokio::test]
+    async fn test_registered_route_is_in_caddyfile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Caddyfile");
+        fs::write(&path, HAND_WRITTEN).unwrap();
+        let routes = CaddyRoutes::new(ProxyConfig {
+            caddyfile_path: path.clone(),
+            caddy_binary: None,
+        });
+
+        routes
+            .register_route("Tenant1.Example.com", "127.0.0.1:9000")
+            .await
+            .unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(HAND_WRITTEN.trim_end()));
+        assert!(content.contains(
+            "tenant1.example.com {\n    import tls_config\n    reverse_proxy 127.0.0.1:9000\n}"
+        ));
+        assert_eq!(
+            routes.list_routes().unwrap(),
+            vec![ProxyRoute {
+                hostname: "tenant1.example.com".to_string(),
+                upstream: "127.0.0.1:9000".to_string(),
+            }]
+        );
+
+        assert!(matches!(
+            routes
+                .register_route("api.example.com", "127.0.0.1:9001")
+                .await,
+            Err(RouteError::Conflict(_))
+        ));
+        assert!(matches!(
+            routes
+                .register_route("x.example.com", "127.0.0.1:9001\n}\nevil {")
+                .await,
+            Err(RouteError::Invalid(_))
+        ));
+        assert!(routes
+            .register_route("localhost", "127.0.0.1:1")
+            .await
+            .is_err());
+
+        routes.remove_route("tenant1.example.com").await.unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content, HAND_WRITTEN);
+    }
+}
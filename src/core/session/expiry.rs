@@ -0,0 +1,219 @@
+//! Idle and absolute timeouts for conversation sessions.
+//!
+//! A session expires once it has gone longer than the idle timeout without activity, or
+//! once it is older than the maximum lifetime, whichever comes first. Activity is the
+//! session's `updated_at`, refreshed by authenticated requests and saved messages. The
+//! defaults come from the environment; a bot's `config.csv` can override both.
+//!
+//! Open chat WebSockets are checked on every message they carry; once the session has
+//! expired the socket is closed with code 1008 and the expiry reason.
+
+use crate::core::i18n::{system_message, Locale};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use std::sync::LazyLock;
+
+/// Bot config key overriding the idle timeout, in minutes.
+pub const IDLE_TIMEOUT_KEY: &str = "session-idle-timeout-minutes";
+/// Bot config key overriding the maximum lifetime, in hours.
+pub const MAX_LIFETIME_KEY: &str = "session-max-lifetime-hours";
+
+/// Session timeouts. `None` disables a limit.
+///
+/// `SESSION_IDLE_TIMEOUT_MINUTES` (default 1440) and `SESSION_MAX_LIFETIME_HOURS`
+/// (default 720) set the defaults; `0` disables the limit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionTimeouts {
+    pub idle: Option<Duration>,
+    pub max_lifetime: Option<Duration>,
+}
+
+impl Default for SessionTimeouts {
+    fn default() -> Self {
+        Self {
+            idle: Some(Duration::minutes(1440)),
+            max_lifetime: Some(Duration::hours(720)),
+        }
+    }
+}
+
+fn number(value: Option<String>) -> Option<i64> {
+    value
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|n| *n >= 0)
+}
+
+fn limit(amount: i64, unit: fn(i64) -> Duration) -> Option<Duration> {
+    // Clamped so absurd values cannot overflow the duration.
+    (amount > 0).then(|| unit(amount.min(i64::from(u32::MAX))))
+}
+
+impl SessionTimeouts {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        Self::default().with_settings(
+            number(lookup("SESSION_IDLE_TIMEOUT_MINUTES")),
+            number(lookup("SESSION_MAX_LIFETIME_HOURS")),
+        )
+    }
+
+    /// Applies a bot's overrides, looked up by config key.
+    pub fn with_bot_overrides(self, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        self.with_settings(
+            number(lookup(IDLE_TIMEOUT_KEY)),
+            number(lookup(MAX_LIFETIME_KEY)),
+        )
+    }
+
+    fn with_settings(self, idle_minutes: Option<i64>, lifetime_hours: Option<i64>) -> Self {
+        Self {
+            idle: idle_minutes.map_or(self.idle, |m| limit(m, Duration::minutes)),
+            max_lifetime: lifetime_hours.map_or(self.max_lifetime, |h| limit(h, Duration::hours)),
+        }
+    }
+
+    /// Whether a session created at `created_at` and last active at `last_activity` is
+    /// still usable at `now`. Reaching a limit exactly is still within it.
+    pub fn check(
+        &self,
+        created_at: DateTime<Utc>,
+        last_activity: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<(), SessionExpired> {
+        if self.max_lifetime.is_some_and(|max| now - created_at > max) {
+            return Err(SessionExpired::Lifetime);
+        }
+        if self.idle.is_some_and(|idle| now - last_activity > idle) {
+            return Err(SessionExpired::Idle);
+        }
+        Ok(())
+    }
+}
+
+static GLOBAL_TIMEOUTS: LazyLock<SessionTimeouts> = LazyLock::new(SessionTimeouts::from_env);
+
+/// Timeouts configured for the whole server.
+pub fn global_timeouts() -> &'static SessionTimeouts {
+    &GLOBAL_TIMEOUTS
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionExpired {
+    Idle,
+    Lifetime,
+}
+
+impl SessionExpired {
+    pub fn reason(self) -> &'static str {
+        match self {
+            Self::Idle => "session_idle_timeout",
+            Self::Lifetime => "session_lifetime_exceeded",
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::Idle => "Session expired after a period of inactivity",
+            Self::Lifetime => "Session reached its maximum lifetime",
+        }
+    }
+
+    fn message_key(self) -> &'static str {
+        match self {
+            Self::Idle => "error-session-idle",
+            Self::Lifetime => "error-session-lifetime",
+        }
+    }
+
+    /// The `401` JSON body, with the message in `locale`.
+    pub fn body(self, locale: &Locale) -> serde_json::Value {
+        serde_json::json!({
+            "error": system_message(locale, self.message_key(), &[]),
+            "reason": self.reason(),
+        })
+    }
+
+    pub fn into_localized_response(self, locale: &Locale) -> Response {
+        (StatusCode::UNAUTHORIZED, Json(self.body(locale))).into_response()
+    }
+}
+
+impl IntoResponse for SessionExpired {
+    fn into_response(self) -> Response {
+        self.into_localized_response(&Locale::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeouts() -> SessionTimeouts {
+        SessionTimeouts {
+            idle: Some(Duration::minutes(30)),
+            max_lifetime: Some(Duration::hours(8)),
+        }
+    }
+
+    #[test]
+    fn test_idle_expiry_boundary() {
+        let created = Utc::now();
+        let active = created + Duration::hours(1);
+        let t = timeouts();
+
+        assert_eq!(
+            t.check(created, active, active + Duration::minutes(30)),
+            Ok(())
+        );
+        assert_eq!(
+            t.check(
+                created,
+                active,
+                active + Duration::minutes(30) + Duration::seconds(1)
+            ),
+            Err(SessionExpired::Idle)
+        );
+    }
+
+    #[test]
+    fn test_absolute_expiry_boundary() {
+        let created = Utc::now();
+        let end = created + Duration::hours(8);
+        let t = timeouts();
+
+        // Constant activity does not extend the lifetime.
+        assert_eq!(t.check(created, end, end), Ok(()));
+        assert_eq!(
+            t.check(created, end, end + Duration::seconds(1)),
+            Err(SessionExpired::Lifetime)
+        );
+    }
+
+    #[test]
+    fn test_bot_overrides() {
+        let global = SessionTimeouts::from_lookup(|key| match key {
+            "SESSION_IDLE_TIMEOUT_MINUTES" => Some("15".to_string()),
+            _ => None,
+        });
+        assert_eq!(global.idle, Some(Duration::minutes(15)));
+        assert_eq!(global.max_lifetime, Some(Duration::hours(720)));
+
+        let bot = global.with_bot_overrides(|key| match key {
+            IDLE_TIMEOUT_KEY => Some("0".to_string()),
+            MAX_LIFETIME_KEY => Some("2".to_string()),
+            _ => None,
+        });
+        assert_eq!(bot.idle, None);
+        assert_eq!(bot.max_lifetime, Some(Duration::hours(2)));
+
+        let now = Utc::now();
+        let created = now - Duration::hours(3);
+        assert_eq!(bot.check(created, now, now), Err(SessionExpired::Lifetime));
+    }
+}
@@ -0,0 +1,407 @@
+use super::search::SearchScope;
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::models::UserSession;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::Json;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// One stored message, as copied into a fork.
+#[derive(Debug, Clone, PartialEq, Queryable)]
+pub struct HistoryRow {
+    pub user_id: Uuid,
+    pub role: i32,
+    pub content: String,
+    pub message_type: i32,
+    pub message_index: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum ForkError {
+    NotFound,
+    /// The requested index is past the end of the parent's history.
+    IndexOutOfRange {
+        index: i32,
+        len: usize,
+    },
+    Database(diesel::result::Error),
+}
+
+impl From<diesel::result::Error> for ForkError {
+    fn from(e: diesel::result::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+impl From<ForkError> for ApiError {
+    fn from(e: ForkError) -> Self {
+        match e {
+            ForkError::NotFound => ApiError::not_found("Session not found"),
+            ForkError::IndexOutOfRange { index, len } => ApiError::bad_request(format!(
+                "message_index {} is out of range, the session has {} messages",
+                index, len
+            )),
+            ForkError::Database(e) => ApiError::internal(e.to_string()),
+        }
+    }
+}
+
+/// Messages `0..=up_to_index` of `parent`, ordered and renumbered from 0. Without an
+/// index the whole history is copied.
+pub fn fork_history(
+    parent: &[HistoryRow],
+    up_to_index: Option<i32>,
+) -> Result<Vec<HistoryRow>, ForkError> {
+    let mut ordered: Vec<&HistoryRow> = parent.iter().collect();
+    ordered.sort_by_key(|row| row.message_index);
+
+    let keep = match up_to_index {
+        None => ordered.len(),
+        Some(index) if index >= 0 && (index as usize) < ordered.len() => index as usize + 1,
+        Some(index) => {
+            return Err(ForkError::IndexOutOfRange {
+                index,
+                len: ordered.len(),
+            })
+        }
+    };
+
+    Ok(ordered
+        .into_iter()
+        .take(keep)
+        .zip(0..)
+        .map(|(row, index)| HistoryRow {
+            message_index: index,
+            ..row.clone()
+        })
+        .collect())
+}
+
+fn can_fork(
+    conn: &mut PgConnection,
+    parent: &UserSession,
+    scope: SearchScope,
+) -> QueryResult<bool> {
+    use crate::core::shared::models::schema::bots::dsl::*;
+    if scope.all || parent.user_id == scope.user_id {
+        return Ok(true);
+    }
+    let Some(caller_org) = scope.org_id else {
+        return Ok(false);
+    };
+    let bot_org: Option<Option<Uuid>> = bots
+        .filter(id.eq(parent.bot_id))
+        .select(org_id)
+        .first(conn)
+        .optional()?;
+    Ok(bot_org.flatten() == Some(caller_org))
+}
+
+/// Where forked sessions and their history are persisted.
+pub trait SessionHistoryStore {
+    /// The session's messages, ordered by index.
+    fn load_history(&mut self, session_id: Uuid) -> Result<Vec<HistoryRow>, ForkError>;
+    /// A new session owned by `owner`, linked to `parent` at `forked_at`.
+    fn create_fork(
+        &mut self,
+        parent: &UserSession,
+        owner: Uuid,
+        forked_at: i32,
+    ) -> Result<UserSession, ForkError>;
+    fn append_history(&mut self, session_id: Uuid, rows: &[HistoryRow]) -> Result<(), ForkError>;
+}
+
+impl SessionHistoryStore for PgConnection {
+    fn load_history(&mut self, session_id: Uuid) -> Result<Vec<HistoryRow>, ForkError> {
+        use crate::core::shared::models::message_history::dsl as mh;
+        Ok(mh::message_history
+            .filter(mh::session_id.eq(session_id))
+            .order(mh::message_index.asc())
+            .select((
+                mh::user_id,
+                mh::role,
+                mh::content_encrypted,
+                mh::message_type,
+                mh::message_index,
+                mh::created_at,
+            ))
+            .load(self)?)
+    }
+
+    fn create_fork(
+        &mut self,
+        parent: &UserSession,
+        owner: Uuid,
+        forked_at: i32,
+    ) -> Result<UserSession, ForkError> {
+        use crate::core::shared::models::user_sessions::dsl as us;
+        let now = Utc::now();
+        Ok(diesel::insert_into(us::user_sessions)
+            .values((
+                us::id.eq(Uuid::new_v4()),
+                us::user_id.eq(owner),
+                us::bot_id.eq(parent.bot_id),
+                us::title.eq(format!("{} (fork)", parent.title)),
+                us::context_data.eq(parent.context_data.clone()),
+                us::current_tool.eq(None::<String>),
+                us::created_at.eq(now),
+                us::updated_at.eq(now),
+                us::parent_session_id.eq(Some(parent.id)),
+                us::forked_at_index.eq(Some(forked_at)),
+            ))
+            .returning(UserSession::as_returning())
+            .get_result(self)?)
+    }
+
+    fn append_history(&mut self, session_id: Uuid, rows: &[HistoryRow]) -> Result<(), ForkError> {
+        use crate::core::shared::models::message_history::dsl as mh;
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let rows: Vec<_> = rows
+            .iter()
+            .map(|row| {
+                (
+                    mh::id.eq(Uuid::new_v4()),
+                    mh::session_id.eq(session_id),
+                    mh::user_id.eq(row.user_id),
+                    mh::role.eq(row.role),
+                    mh::content_encrypted.eq(&row.content),
+                    mh::message_type.eq(row.message_type),
+                    mh::message_index.eq(row.message_index),
+                    mh::created_at.eq(row.created_at),
+                )
+            })
+            .collect();
+        diesel::insert_into(mh::message_history)
+            .values(&rows)
+            .execute(self)?;
+        Ok(())
+    }
+}
+
+/// Creates a session owned by `owner` whose history is a copy of the parent's up to
+/// `up_to_index`. The parent is left untouched; both continue independently.
+pub fn fork_into<S: SessionHistoryStore + ?Sized>(
+    store: &mut S,
+    parent: &UserSession,
+    up_to_index: Option<i32>,
+    owner: Uuid,
+) -> Result<UserSession, ForkError> {
+    let history = store.load_history(parent.id)?;
+    let copied = fork_history(&history, up_to_index)?;
+    let forked_at = copied.last().map_or(-1, |row| row.message_index);
+
+    let fork = store.create_fork(parent, owner, forked_at)?;
+    store.append_history(fork.id, &copied)?;
+    Ok(fork)
+}
+
+/// [`fork_into`] for a parent the caller may see, owned by the caller.
+pub fn fork_session(
+    conn: &mut PgConnection,
+    parent_id: Uuid,
+    up_to_index: Option<i32>,
+    scope: SearchScope,
+) -> Result<UserSession, ForkError> {
+    use crate::core::shared::models::user_sessions::dsl as us;
+
+    conn.transaction(|conn| {
+        let parent: UserSession = us::user_sessions
+            .filter(us::id.eq(parent_id))
+            .filter(us::deleted_at.is_null())
+            .select(UserSession::as_select())
+            .first(conn)
+            .optional()?
+            .ok_or(ForkError::NotFound)?;
+        if !can_fork(conn, &parent, scope)? {
+            return Err(ForkError::NotFound);
+        }
+        fork_into(conn, &parent, up_to_index, scope.user_id)
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ForkSessionRequest {
+    /// Last message copied into the fork, 0-based. Defaults to the whole history.
+    pub message_index: Option<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ForkSessionResponse {
+    pub session_id: Uuid,
+    pub parent_session_id: Uuid,
+    pub forked_at_index: i32,
+    pub bot_id: Uuid,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn handle_fork_session(
+    Extension(state): Extension<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(parent_id): Path<Uuid>,
+    body: Option<Json<ForkSessionRequest>>,
+) -> Result<(StatusCode, Json<ForkSessionResponse>), ApiError> {
+    if !user.is_authenticated() {
+        return Err(ApiError::unauthorized("Authentication required"));
+    }
+    let up_to_index = body.and_then(|Json(req)| req.message_index);
+    let scope = SearchScope::for_user(&user);
+
+    let pool = state.conn.clone();
+    let fork = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        fork_session(&mut conn, parent_id, up_to_index, scope).map_err(ApiError::from)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??;
+
+    info!(
+        "User {} forked session {} into {}",
+        user.user_id, parent_id, fork.id
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ForkSessionResponse {
+            session_id: fork.id,
+            parent_session_id: parent_id,
+            forked_at_index: fork.forked_at_index.unwrap_or(-1),
+            bot_id: fork.bot_id,
+            title: fork.title,
+            created_at: fork.created_at,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn row(index: i32, role: i32, content: &str) -> HistoryRow {
+        HistoryRow {
+            user_id: Uuid::nil(),
+            role,
+            content: content.to_string(),
+            message_type: 1,
+            message_index: index,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_fork_copies_prefix() {
+        let parent = vec![row(0, 1, "hi"), row(1, 2, "hello"), row(2, 1, "more")];
+        let fork = fork_history(&parent, Some(1)).unwrap();
+        assert_eq!(fork, parent[..2].to_vec());
+
+        assert_eq!(fork_history(&parent, None).unwrap().len(), 3);
+        assert!(matches!(
+            fork_history(&parent, Some(3)),
+            Err(ForkError::IndexOutOfRange { index: 3, len: 3 })
+        ));
+        assert!(fork_history(&parent, Some(-1)).is_err());
+    }
+
+    /// Keeps sessions and history like the two tables do.
+    #[derive(Default)]
+    struct MemoryStore {
+        sessions: Vec<UserSession>,
+        history: HashMap<Uuid, Vec<HistoryRow>>,
+    }
+
+    impl SessionHistoryStore for MemoryStore {
+        fn load_history(&mut self, session_id: Uuid) -> Result<Vec<HistoryRow>, ForkError> {
+            Ok(self.history.get(&session_id).cloned().unwrap_or_default())
+        }
+
+        fn create_fork(
+            &mut self,
+            parent: &UserSession,
+            owner: Uuid,
+            forked_at: i32,
+        ) -> Result<UserSession, ForkError> {
+            let fork = UserSession {
+                id: Uuid::new_v4(),
+                user_id: owner,
+                parent_session_id: Some(parent.id),
+                forked_at_index: Some(forked_at),
+                ..parent.clone()
+            };
+            self.sessions.push(fork.clone());
+            Ok(fork)
+        }
+
+        fn append_history(
+            &mut self,
+            session_id: Uuid,
+            rows: &[HistoryRow],
+        ) -> Result<(), ForkError> {
+            self.history
+                .entry(session_id)
+                .or_default()
+                .extend_from_slice(rows);
+            Ok(())
+        }
+    }
+
+    fn session() -> UserSession {
+        UserSession {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            bot_id: Uuid::new_v4(),
+            title: "Prompt ideas".to_string(),
+            context_data: serde_json::json!({}),
+            current_tool: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            parent_session_id: None,
+            forked_at_index: None,
+        }
+    }
+
+    #[test]
+    fn test_editing_fork_leaves_parent_history() {
+        let mut store = MemoryStore::default();
+        let parent = session();
+        let original = vec![
+            row(0, 1, "question"),
+            row(1, 2, "answer"),
+            row(2, 1, "follow-up"),
+        ];
+        store.append_history(parent.id, &original).unwrap();
+
+        let owner = Uuid::new_v4();
+        let fork = fork_into(&mut store, &parent, Some(1), owner).unwrap();
+        assert_eq!(fork.parent_session_id, Some(parent.id));
+        assert_eq!(fork.forked_at_index, Some(1));
+        assert_eq!(fork.user_id, owner);
+
+        store
+            .append_history(fork.id, &[row(2, 1, "divergent follow-up")])
+            .unwrap();
+
+        assert_eq!(store.load_history(parent.id).unwrap(), original);
+        let contents: Vec<String> = store
+            .load_history(fork.id)
+            .unwrap()
+            .into_iter()
+            .map(|row| row.content)
+            .collect();
+        assert_eq!(contents, ["question", "answer", "divergent follow-up"]);
+    }
+}
@@ -0,0 +1,820 @@
+//! Forms filled over several turns, such as a sign-up collecting name, e-mail and date.
+//!
+//! A bot declares a form in its config as `form-<name>`, a JSON schema:
+//! `{"fields": [{"name": "email", "label": "E-mail", "type": "email"}], "confirm": true}`.
+//! `START_FORM("<name>")` in BASIC makes it the session's active form. While it is active,
+//! each user message is run through the LLM in JSON mode to pick out field values, which
+//! are validated and kept in the session variable `form.<name>`. The bot asks again for
+//! missing or invalid fields and, when `confirm` is set, for a final confirmation. The
+//! user leaves the form with a cancel word, and it is given up after `max_turns` messages.
+
+use crate::core::config::ConfigManager;
+use crate::core::session::variables::{
+    authorize_session, max_bytes, set_variable, SessionVariableError, SessionVariableStore,
+};
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use async_trait::async_trait;
+use axum::extract::{Extension, Path};
+use axum::Json;
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Session variable naming the form being filled.
+pub const ACTIVE_FORM_KEY: &str = "forms.active";
+/// Messages a form takes before it is given up, unless the schema sets `max_turns`.
+const DEFAULT_MAX_TURNS: u32 = 15;
+/// Messages that leave a form, unless the schema sets `cancel_words`.
+const DEFAULT_CANCEL_WORDS: &[&str] = &[
+    "cancel", "stop", "quit", "exit", "cancelar", "parar", "sair",
+];
+
+fn state_key(form: &str) -> String {
+    format!("form.{form}")
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    #[default]
+    Text,
+    Email,
+    Date,
+    Number,
+    Phone,
+    Choice,
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn default_max_turns() -> u32 {
+    DEFAULT_MAX_TURNS
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormField {
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(rename = "type", default)]
+    pub field_type: FieldType,
+    #[serde(default = "yes")]
+    pub required: bool,
+    /// Accepted values of a `choice` field.
+    #[serde(default)]
+    pub options: Vec<String>,
+    /// Regular expression the value must match.
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Question asked for the field; defaults to "What is your <label>?".
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+impl FormField {
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    fn question(&self) -> String {
+        self.prompt
+            .clone()
+            .unwrap_or_else(|| format!("What is your {}?", self.label()))
+    }
+
+    /// The normalized value, or why `value` is not acceptable.
+    pub fn validate(&self, value: &Value) -> Result<Value, String> {
+        let text = match value {
+            Value::String(s) => s.trim().to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => return Err("expected a single value".to_string()),
+        };
+        if text.is_empty() {
+            return Err("it is empty".to_string());
+        }
+
+        let normalized = match self.field_type {
+            FieldType::Text => Value::String(text),
+            FieldType::Email => {
+                let email = text.to_lowercase();
+                let valid = !email.contains(char::is_whitespace)
+                    && email.split_once('@').is_some_and(|(local, domain)| {
+                        !local.is_empty() && domain.contains('.') && !domain.ends_with('.')
+                    });
+                if !valid {
+                    return Err("that does not look like an e-mail address".to_string());
+                }
+                Value::String(email)
+            }
+            FieldType::Date => ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y", "%d.%m.%Y"]
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(&text, format).ok())
+                .map(|date| Value::String(date.format("%Y-%m-%d").to_string()))
+                .ok_or_else(|| "use a date such as 2025-03-31".to_string())?,
+            FieldType::Number => text
+                .replace(',', ".")
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(|n| match n.as_f64() {
+                    Some(f) if f.fract() == 0.0 && f.abs() < 1e15 => Value::from(f as i64),
+                    _ => Value::Number(n),
+                })
+                .ok_or_else(|| "expected a number".to_string())?,
+            FieldType::Phone => {
+                let digits: String = text.chars().filter(char::is_ascii_digit).collect();
+                let allowed = text
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || matches!(c, '+' | ' ' | '-' | '(' | ')' | '.'));
+                if !allowed || !(8..=15).contains(&digits.len()) {
+                    return Err("expected a phone number of 8 to 15 digits".to_string());
+                }
+                let plus = if text.starts_with('+') { "+" } else { "" };
+                Value::String(format!("{plus}{digits}"))
+            }
+            FieldType::Choice => self
+                .options
+                .iter()
+                .find(|option| option.eq_ignore_ascii_case(&text))
+                .map(|option| Value::String(option.clone()))
+                .ok_or_else(|| format!("choose one of: {}", self.options.join(", ")))?,
+        };
+
+        if let Some(pattern) = &self.pattern {
+            let re = regex::Regex::new(pattern).map_err(|e| format!("invalid pattern: {e}"))?;
+            let as_text = match &normalized {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            if !re.is_match(&as_text) {
+                return Err("it is not in the expected format".to_string());
+            }
+        }
+        Ok(normalized)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormSchema {
+    #[serde(skip)]
+    pub name: String,
+    pub fields: Vec<FormField>,
+    /// Ask the user to confirm the collected values before completing.
+    #[serde(default = "yes")]
+    pub confirm: bool,
+    /// Messages the form takes before it is given up; `0` means no limit.
+    #[serde(default = "default_max_turns")]
+    pub max_turns: u32,
+    /// Messages that leave the form; defaults to [`DEFAULT_CANCEL_WORDS`].
+    #[serde(default)]
+    pub cancel_words: Vec<String>,
+}
+
+impl FormSchema {
+    pub fn parse(name: &str, json: &str) -> Result<Self, String> {
+        let mut schema: Self =
+            serde_json::from_str(json).map_err(|e| format!("Invalid form-{name}: {e}"))?;
+        if schema.fields.is_empty() {
+            return Err(format!("Form {name} has no fields"));
+        }
+        schema.name = name.to_string();
+        Ok(schema)
+    }
+
+    /// Whether `message` asks to leave the form.
+    pub fn is_cancel(&self, message: &str) -> bool {
+        let word = message.trim().trim_end_matches(['.', '!']);
+        if self.cancel_words.is_empty() {
+            DEFAULT_CANCEL_WORDS
+                .iter()
+                .any(|w| w.eq_ignore_ascii_case(word))
+        } else {
+            self.cancel_words
+                .iter()
+                .any(|w| w.trim().eq_ignore_ascii_case(word))
+        }
+    }
+
+    /// The form declared in the bot's config. Blocking.
+    pub fn for_bot(config: &ConfigManager, bot_id: Uuid, name: &str) -> Result<Self, String> {
+        let json = config
+            .get_config(&bot_id, &format!("form-{name}"), None)
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| format!("Form {name} is not defined"))?;
+        Self::parse(name, &json)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormStatus {
+    #[default]
+    Filling,
+    Confirming,
+    Complete,
+    /// Left with a cancel word, or given up after `max_turns` messages.
+    Cancelled,
+}
+
+impl FormStatus {
+    /// Whether the form no longer takes messages.
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Complete | Self::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FormState {
+    #[serde(default)]
+    pub status: FormStatus,
+    #[serde(default)]
+    pub values: BTreeMap<String, Value>,
+    /// Why the last value given for a field was rejected.
+    #[serde(default)]
+    pub errors: BTreeMap<String, String>,
+    /// User messages the form has taken.
+    #[serde(default)]
+    pub turns: u32,
+}
+
+impl FormState {
+    /// Required fields without a value, in schema order.
+    pub fn missing<'a>(&self, schema: &'a FormSchema) -> Vec<&'a FormField> {
+        schema
+            .fields
+            .iter()
+            .filter(|f| f.required && !self.values.contains_key(&f.name))
+            .collect()
+    }
+
+    /// Validates extracted values into the state. Unknown fields and nulls are ignored.
+    /// Returns whether any value changed.
+    pub fn apply(&mut self, schema: &FormSchema, extracted: &Map<String, Value>) -> bool {
+        let mut changed = false;
+        for field in &schema.fields {
+            let Some(raw) = extracted.get(&field.name).filter(|v| !v.is_null()) else {
+                continue;
+            };
+            match field.validate(raw) {
+                Ok(value) => {
+                    self.errors.remove(&field.name);
+                    if self.values.get(&field.name) != Some(&value) {
+                        self.values.insert(field.name.clone(), value);
+                        changed = true;
+                    }
+                }
+                Err(reason) => {
+                    self.errors.insert(field.name.clone(), reason);
+                }
+            }
+        }
+        changed
+    }
+
+    /// What the bot says next.
+    pub fn reply(&self, schema: &FormSchema) -> String {
+        match self.status {
+            FormStatus::Complete => "Thank you, that's everything I needed.".to_string(),
+            FormStatus::Cancelled => "Okay, I've stopped filling in the form.".to_string(),
+            FormStatus::Confirming => {
+                let lines: Vec<String> = schema
+                    .fields
+                    .iter()
+                    .filter_map(|f| {
+                        let value = self.values.get(&f.name)?;
+                        let shown = value
+                            .as_str()
+                            .map_or_else(|| value.to_string(), str::to_string);
+                        Some(format!("- {}: {}", f.label(), shown))
+                    })
+                    .collect();
+                format!("Please confirm:\n{}\nIs this correct?", lines.join("\n"))
+            }
+            FormStatus::Filling => {
+                let missing = self.missing(schema);
+                let Some(field) = missing
+                    .iter()
+                    .find(|f| self.errors.contains_key(&f.name))
+                    .or_else(|| missing.first())
+                else {
+                    return String::new();
+                };
+                match self.errors.get(&field.name) {
+                    Some(reason) => {
+                        format!(
+                            "Sorry, {} for {}. {}",
+                            reason,
+                            field.label(),
+                            field.question()
+                        )
+                    }
+                    None => field.question(),
+                }
+            }
+        }
+    }
+}
+
+/// Picks field values out of a user message.
+#[async_trait]
+pub trait FieldExtractor: Send + Sync {
+    async fn extract(
+        &self,
+        schema: &FormSchema,
+        state: &FormState,
+        message: &str,
+    ) -> Result<Map<String, Value>, String>;
+}
+
+fn is_affirmative(message: &str) -> bool {
+    let word = message.trim().trim_end_matches(['.', '!']).to_lowercase();
+    matches!(
+        word.as_str(),
+        "yes"
+            | "y"
+            | "ok"
+            | "okay"
+            | "correct"
+            | "confirm"
+            | "confirmed"
+            | "right"
+            | "sim"
+            | "s"
+            | "correto"
+            | "isso"
+            | "sí"
+            | "si"
+    )
+}
+
+/// Runs one user message through the form and returns the bot's reply.
+pub async fn advance(
+    schema: &FormSchema,
+    state: &mut FormState,
+    message: &str,
+    extractor: &dyn FieldExtractor,
+) -> String {
+    if state.status.is_finished() {
+        return state.reply(schema);
+    }
+    state.turns += 1;
+    if schema.is_cancel(message) {
+        state.status = FormStatus::Cancelled;
+        return state.reply(schema);
+    }
+    if state.status == FormStatus::Confirming && is_affirmative(message) {
+        state.status = FormStatus::Complete;
+        return state.reply(schema);
+    }
+    if schema.max_turns > 0 && state.turns > schema.max_turns {
+        warn!(
+            "Form {} given up after {} messages",
+            schema.name, schema.max_turns
+        );
+        state.status = FormStatus::Cancelled;
+        return state.reply(schema);
+    }
+
+    let extracted = match extractor.extract(schema, state, message).await {
+        Ok(extracted) => extracted,
+        Err(e) => {
+            warn!("Form {} extraction failed: {}", schema.name, e);
+            Map::new()
+        }
+    };
+    let changed = state.apply(schema, &extracted);
+
+    if !state.missing(schema).is_empty() {
+        state.status = FormStatus::Filling;
+    } else if !schema.confirm {
+        state.status = FormStatus::Complete;
+    } else if state.status == FormStatus::Confirming && !changed {
+        return "What should I change?".to_string();
+    } else {
+        state.status = FormStatus::Confirming;
+    }
+    state.reply(schema)
+}
+
+pub fn load_form<S: SessionVariableStore + ?Sized>(
+    store: &mut S,
+    session_id: Uuid,
+    form: &str,
+) -> Result<FormState, SessionVariableError> {
+    Ok(store
+        .load(session_id)?
+        .get(&state_key(form))
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default())
+}
+
+/// Saves the form state, and clears the active form once it is complete or cancelled.
+pub fn save_form<S: SessionVariableStore + ?Sized>(
+    store: &mut S,
+    session_id: Uuid,
+    form: &str,
+    state: &FormState,
+) -> Result<(), SessionVariableError> {
+    let json =
+        serde_json::to_value(state).map_err(|e| SessionVariableError::Database(e.to_string()))?;
+    set_variable(store, session_id, &state_key(form), json, max_bytes())?;
+    if state.status.is_finished() {
+        set_variable(store, session_id, ACTIVE_FORM_KEY, Value::Null, max_bytes())?;
+    }
+    Ok(())
+}
+
+/// Makes `form` the session's active form, starting from empty, and returns the first
+/// question.
+pub fn start_form<S: SessionVariableStore + ?Sized>(
+    store: &mut S,
+    session_id: Uuid,
+    schema: &FormSchema,
+) -> Result<String, SessionVariableError> {
+    let state = FormState::default();
+    save_form(store, session_id, &schema.name, &state)?;
+    set_variable(
+        store,
+        session_id,
+        ACTIVE_FORM_KEY,
+        Value::String(schema.name.clone()),
+        max_bytes(),
+    )?;
+    Ok(state.reply(schema))
+}
+
+/// Extracts with the bot's LLM in JSON mode.
+pub struct LlmExtractor {
+    state: Arc<AppState>,
+    bot_id: Uuid,
+}
+
+impl LlmExtractor {
+    pub fn new(state: Arc<AppState>, bot_id: Uuid) -> Self {
+        Self { state, bot_id }
+    }
+}
+
+#[async_trait]
+impl FieldExtractor for LlmExtractor {
+    async fn extract(
+        &self,
+        schema: &FormSchema,
+        state: &FormState,
+        message: &str,
+    ) -> Result<Map<String, Value>, String> {
+        let config = ConfigManager::new(self.state.conn.clone());
+        let bot_id = self.bot_id;
+        let (model, key) = tokio::task::spawn_blocking(move || {
+            (
+                config
+                    .get_config(&bot_id, "llm-model", None)
+                    .unwrap_or_default(),
+                config
+                    .get_config(&bot_id, "llm-key", None)
+                    .unwrap_or_default(),
+            )
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let fields: Vec<Value> = schema
+            .fields
+            .iter()
+            .map(|f| {
+                serde_json::json!({
+                    "name": f.name,
+                    "label": f.label(),
+                    "type": f.field_type,
+                    "options": f.options,
+                })
+            })
+            .collect();
+        let instructions = format!(
+            "You extract form fields from the user's last message.\n\
+             Fields: {}\n\
+             Already collected: {}\n\
+             Answer with one JSON object whose keys are field names, only for values the \
+             user stated in this message. Do not guess and do not repeat collected values \
+             unless the user changed them.",
+            Value::Array(fields),
+            serde_json::to_string(&state.values).unwrap_or_default()
+        );
+        let messages = serde_json::json!([
+            {"role": "system", "content": instructions},
+            {"role": "user", "content": message},
+        ]);
+
+        let answer = self
+            .state
+            .llm_provider
+            .generate_json(message, &messages, &model, &key)
+            .await
+            .map_err(|e| e.to_string())?;
+        let json = match (answer.find('{'), answer.rfind('}')) {
+            (Some(start), Some(end)) if start < end => &answer[start..=end],
+            _ => return Err(format!("no JSON object in answer: {answer}")),
+        };
+        match serde_json::from_str(json) {
+            Ok(Value::Object(map)) => Ok(map),
+            Ok(_) => Err("answer is not a JSON object".to_string()),
+            Err(e) => Err(format!("invalid JSON in answer: {e}")),
+        }
+    }
+}
+
+/// When the session has an active form, fills it with `message` and returns the reply.
+pub async fn handle_message(
+    state: &Arc<AppState>,
+    bot_id: Uuid,
+    session_id: Uuid,
+    message: &str,
+) -> Option<String> {
+    let pool = state.conn.clone();
+    let loaded = tokio::task::spawn_blocking(move || -> Result<Option<_>, String> {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        let variables =
+            SessionVariableStore::load(&mut *conn, session_id).map_err(|e| e.to_string())?;
+        let Some(name) = variables.get(ACTIVE_FORM_KEY).and_then(Value::as_str) else {
+            return Ok(None);
+        };
+        let schema = FormSchema::for_bot(&ConfigManager::new(pool.clone()), bot_id, name)?;
+        let form = load_form(&mut *conn, session_id, name).map_err(|e| e.to_string())?;
+        Ok(Some((schema, form)))
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+
+    let (schema, mut form) = match loaded {
+        Ok(Some(loaded)) => loaded,
+        Ok(None) => return None,
+        Err(e) => {
+            warn!("Active form for session {} unavailable: {}", session_id, e);
+            return None;
+        }
+    };
+
+    let extractor = LlmExtractor::new(Arc::clone(state), bot_id);
+    let reply = advance(&schema, &mut form, message, &extractor).await;
+
+    let pool = state.conn.clone();
+    let name = schema.name.clone();
+    let saved = tokio::task::spawn_blocking(move || -> Result<(), String> {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        conn.transaction(|conn| save_form(conn, session_id, &name, &form))
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+    if let Err(e) = saved {
+        warn!(
+            "Failed to save form {} for session {}: {}",
+            schema.name, session_id, e
+        );
+    }
+    Some(reply)
+}
+
+#[derive(Debug, Serialize)]
+pub struct FormResponse {
+    pub session_id: Uuid,
+    pub form: String,
+    pub active: bool,
+    pub status: FormStatus,
+    pub values: BTreeMap<String, Value>,
+    pub missing: Vec<String>,
+    pub errors: BTreeMap<String, String>,
+}
+
+/// A session's form, collected so far. Open to the session's owner and admins.
+pub async fn handle_get_session_form(
+    Extension(state): Extension<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path((session_id, form)): Path<(Uuid, String)>,
+) -> Result<Json<FormResponse>, ApiError> {
+    let pool = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        let bot_id = authorize_session(&mut conn, session_id, &user)?;
+        let schema = FormSchema::for_bot(&ConfigManager::new(pool.clone()), bot_id, &form)
+            .map_err(ApiError::not_found)?;
+        let variables = SessionVariableStore::load(&mut *conn, session_id)?;
+        let form_state: FormState = variables
+            .get(&state_key(&form))
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+        Ok(Json(FormResponse {
+            session_id,
+            active: variables.get(ACTIVE_FORM_KEY).and_then(Value::as_str) == Some(form.as_str()),
+            missing: form_state
+                .missing(&schema)
+                .iter()
+                .map(|f| f.name.clone())
+                .collect(),
+            form,
+            status: form_state.status,
+            values: form_state.values,
+            errors: form_state.errors,
+        }))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::session::variables::SessionVariables;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore(HashMap<Uuid, SessionVariables>);
+
+    impl SessionVariableStore for MemoryStore {
+        fn load(&mut self, session_id: Uuid) -> Result<SessionVariables, SessionVariableError> {
+            Ok(self.0.get(&session_id).cloned().unwrap_or_default())
+        }
+
+        fn save(
+            &mut self,
+            session_id: Uuid,
+            variables: &SessionVariables,
+        ) -> Result<(), SessionVariableError> {
+            self.0.insert(session_id, variables.clone());
+            Ok(())
+        }
+    }
+
+    /// Answers each turn with the next scripted extraction, like the LLM would.
+    struct Scripted(Mutex<Vec<Value>>);
+
+    #[async_trait]
+    impl FieldExtractor for Scripted {
+        async fn extract(
+            &self,
+            _schema: &FormSchema,
+            _state: &FormState,
+            _message: &str,
+        ) -> Result<Map<String, Value>, String> {
+            match self.0.lock().unwrap().remove(0) {
+                Value::Object(map) => Ok(map),
+                _ => Err("model unavailable".to_string()),
+            }
+        }
+    }
+
+    async fn turn(
+        store: &mut MemoryStore,
+        schema: &FormSchema,
+        extractor: &Scripted,
+        session: Uuid,
+        message: &str,
+    ) -> (String, FormState) {
+        let mut form = load_form(store, session, &schema.name).unwrap();
+        let reply = advance(schema, &mut form, message, extractor).await;
+        save_form(store, session, &schema.name, &form).unwrap();
+        (reply, form)
+    }
+
+    const SCHEMA: &str = r#"{"fields": [
+        {"name": "name", "label": "name"},
+        {"name": "email", "label": "e-mail", "type": "email"},
+        {"name": "date", "label": "visit date", "type": "date",
+         "prompt": "Which day would you like to visit?"},
+        {"name": "notes", "required": false}
+    ]}"#;
+
+    #[tokio::test]
+    async fn test_multi_field_form_filled_over_several_turns() {
+        let schema = FormSchema::parse("visit", SCHEMA).unwrap();
+        let mut store = MemoryStore::default();
+        let session = Uuid::new_v4();
+        let extractor = Scripted(Mutex::new(vec![
+            json!({"name": "Ana Souza"}),
+            json!({"email": "ana@"}),
+            Value::Null,
+            json!({"email": "Ana@Example.com", "date": "31/03/2025", "unknown": 1}),
+            json!({}),
+            json!({"date": "2025-04-01"}),
+        ]));
+
+        let first = start_form(&mut store, session, &schema).unwrap();
+        assert_eq!(first, "What is your name?");
+
+        let (reply, _) = turn(&mut store, &schema, &extractor, session, "I'm Ana Souza").await;
+        assert_eq!(reply, "What is your e-mail?");
+
+        let (reply, form) = turn(&mut store, &schema, &extractor, session, "ana@").await;
+        assert!(reply.starts_with("Sorry, that does not look like an e-mail address"));
+        assert!(form.errors.contains_key("email"));
+
+        // The model failing leaves the form as it was and asks again.
+        let (reply, _) = turn(&mut store, &schema, &extractor, session, "hm").await;
+        assert!(reply.contains("What is your e-mail?"));
+
+        let (reply, form) = turn(
+            &mut store,
+            &schema,
+            &extractor,
+            session,
+            "ana@example.com, visiting on 31/03/2025",
+        )
+        .await;
+        assert_eq!(form.status, FormStatus::Confirming);
+        assert!(reply.contains("- e-mail: ana@example.com"));
+        assert!(reply.contains("- visit date: 2025-03-31"));
+        assert!(!form.values.contains_key("unknown"));
+
+        let (reply, _) = turn(&mut store, &schema, &extractor, session, "no").await;
+        assert_eq!(reply, "What should I change?");
+
+        let (reply, _) = turn(
+            &mut store,
+            &schema,
+            &extractor,
+            session,
+            "make it April 1st",
+        )
+        .await;
+        assert!(reply.contains("- visit date: 2025-04-01"));
+
+        let (reply, form) = turn(&mut store, &schema, &extractor, session, "yes").await;
+        assert_eq!(form.status, FormStatus::Complete);
+        assert_eq!(reply, "Thank you, that's everything I needed.");
+
+        let variables = store.load(session).unwrap();
+        assert_eq!(variables.get(ACTIVE_FORM_KEY), None);
+        let saved: FormState =
+            serde_json::from_value(variables.get("form.visit").unwrap().clone()).unwrap();
+        assert_eq!(saved.values["name"], json!("Ana Souza"));
+        assert_eq!(saved.values["email"], json!("ana@example.com"));
+        assert_eq!(saved.values["date"], json!("2025-04-01"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_word_and_turn_limit_leave_the_form() {
+        let schema = FormSchema::parse("visit", SCHEMA).unwrap();
+        let mut store = MemoryStore::default();
+        let session = Uuid::new_v4();
+        let extractor = Scripted(Mutex::new(vec![json!({}), json!({})]));
+
+        start_form(&mut store, session, &schema).unwrap();
+        let (_, form) = turn(&mut store, &schema, &extractor, session, "hmm").await;
+        assert_eq!(form.turns, 1);
+        let (reply, form) = turn(&mut store, &schema, &extractor, session, "Cancel!").await;
+        assert_eq!(form.status, FormStatus::Cancelled);
+        assert_eq!(reply, "Okay, I've stopped filling in the form.");
+        assert_eq!(store.load(session).unwrap().get(ACTIVE_FORM_KEY), None);
+
+        let mut limited = schema.clone();
+        limited.max_turns = 1;
+        limited.cancel_words = vec!["enough".to_string()];
+        assert!(!limited.is_cancel("cancel"));
+        start_form(&mut store, session, &limited).unwrap();
+        let (_, form) = turn(&mut store, &limited, &extractor, session, "who knows").await;
+        assert_eq!(form.status, FormStatus::Filling);
+        let (_, form) = turn(&mut store, &limited, &extractor, session, "still no").await;
+        assert_eq!(form.status, FormStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_field_validation() {
+        let field = |json: Value| serde_json::from_value::<FormField>(json).unwrap();
+
+        let phone = field(json!({"name": "phone", "type": "phone"}));
+        assert_eq!(
+            phone.validate(&json!("+55 (11) 98765-4321")),
+            Ok(json!("+5511987654321"))
+        );
+        assert!(phone.validate(&json!("call me")).is_err());
+
+        let amount = field(json!({"name": "amount", "type": "number"}));
+        assert_eq!(amount.validate(&json!("12,5")), Ok(json!(12.5)));
+        assert_eq!(amount.validate(&json!(3)), Ok(json!(3)));
+
+        let plan = field(json!({"name": "plan", "type": "choice", "options": ["Basic", "Pro"]}));
+        assert_eq!(plan.validate(&json!("pro")), Ok(json!("Pro")));
+        assert!(plan.validate(&json!("Enterprise")).is_err());
+
+        let code = field(json!({"name": "code", "pattern": "^[A-Z]{3}$"}));
+        assert!(code.validate(&json!("ABC")).is_ok());
+        assert!(code.validate(&json!("abcd")).is_err());
+    }
+}
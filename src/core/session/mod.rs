@@ -1,19 +1,33 @@
 pub mod anonymous;
+pub mod expiry;
+pub mod fork;
+pub mod forms;
 pub mod migration;
+pub mod search;
+pub mod variables;
 
+use crate::core::bot::catalog::require_active_bot;
 use crate::core::bot::BotOrchestrator;
+use crate::core::i18n::{system_message, RequestLocale};
+use crate::core::shared::api_error::ApiError;
 use crate::core::shared::models::UserSession;
 use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use expiry::{global_timeouts, SessionExpired, SessionTimeouts};
 use axum::{
     extract::{Extension, Path},
     http::StatusCode,
-    response::{IntoResponse, Json},
+    response::{IntoResponse, Json, Response},
 };
 use chrono::Utc;
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, PooledConnection};
 use diesel::PgConnection;
-use log::{error, trace, warn};
+use log::{error, info, trace, warn};
+#[cfg(feature = "cache")]
+use crate::core::shared::circuit_breaker::{
+    is_redis_outage, note_redis_result, redis_connection_blocking, REDIS_BREAKER,
+};
 #[cfg(feature = "cache")]
 use redis::Client;
 use serde::{Deserialize, Serialize};
@@ -92,6 +106,12 @@ impl SessionManager {
         self.waiting_for_input.insert(session_id);
     }
 
+    /// Drops in-memory state for a session that was deleted.
+    pub fn remove_session(&mut self, session_id: Uuid) {
+        self.sessions.remove(&session_id);
+        self.waiting_for_input.remove(&session_id);
+    }
+
     pub fn get_session_by_id(
         &mut self,
         session_id: Uuid,
@@ -99,6 +119,7 @@ impl SessionManager {
         use crate::core::shared::models::user_sessions::dsl::*;
         let result = user_sessions
             .filter(id.eq(session_id))
+            .filter(deleted_at.is_null())
             .first::<UserSession>(&mut self.conn)
             .optional()?;
         Ok(result)
@@ -113,6 +134,7 @@ impl SessionManager {
         let result = user_sessions
             .filter(user_id.eq(uid))
             .filter(bot_id.eq(bid))
+            .filter(deleted_at.is_null())
             .order(created_at.desc())
             .first::<UserSession>(&mut self.conn)
             .optional()?;
@@ -252,6 +274,7 @@ impl SessionManager {
                 created_at.eq(chrono::Utc::now()),
             ))
             .execute(&mut self.conn)?;
+        self.touch_session(sess_id);
         trace!(
             "Message saved for session {} with index {}",
             sess_id,
@@ -271,8 +294,17 @@ impl SessionManager {
             use redis::Commands;
             let redis_key = format!("context:{}:{}", user_id, session_id);
             if let Some(redis_client) = &self.redis {
-                let mut conn = redis_client.get_connection()?;
-                conn.set::<_, _, ()>(&redis_key, &context_data)?;
+                // While Redis is down the session keeps running on its database state;
+                // the context is simply not cached.
+                if let Some(mut conn) = redis_connection_blocking(redis_client, &REDIS_BREAKER) {
+                    let stored = conn.set::<_, _, ()>(&redis_key, &context_data);
+                    note_redis_result(&REDIS_BREAKER, &stored);
+                    if let Err(e) = stored {
+                        if !is_redis_outage(&e) {
+                            return Err(e.into());
+                        }
+                    }
+                }
             } else {
                 warn!("No Redis client configured, context not persisted");
             }
@@ -290,15 +322,10 @@ impl SessionManager {
             use redis::Commands;
             let base_key = format!("context:{}:{}", user_id, session_id);
             if let Some(redis_client) = &self.redis {
-                let conn_option = redis_client
-                    .get_connection()
-                    .map_err(|e| {
-                        warn!("Failed to get Cache connection: {}", e);
-                        e
-                    })
-                    .ok();
-                if let Some(mut connection) = conn_option {
-                    match connection.get::<_, Option<String>>(&base_key) {
+                if let Some(mut connection) = redis_connection_blocking(redis_client, &REDIS_BREAKER) {
+                    let context_name = connection.get::<_, Option<String>>(&base_key);
+                    note_redis_result(&REDIS_BREAKER, &context_name);
+                    match context_name {
                         Ok(Some(context_name)) => {
                             let full_key =
                                 format!("context:{}:{}:{}", user_id, session_id, context_name);
@@ -332,40 +359,39 @@ impl SessionManager {
         Ok(String::new())
     }
 
+    pub fn get_session_variable(
+        &mut self,
+        session_id: Uuid,
+        key: &str,
+    ) -> Result<Option<serde_json::Value>, variables::SessionVariableError> {
+        variables::get_variable(&mut *self.conn, session_id, key)
+    }
+
+    /// Sets a session variable; a null `value` removes it. Fails once the session's
+    /// variables would exceed their size cap.
+    pub fn set_session_variable(
+        &mut self,
+        session_id: Uuid,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<(), variables::SessionVariableError> {
+        variables::set_variable_locked(&mut self.conn, session_id, key, value).map(|_| ())
+    }
+
+    pub fn session_variables(
+        &mut self,
+        session_id: Uuid,
+    ) -> Result<variables::SessionVariables, variables::SessionVariableError> {
+        variables::SessionVariableStore::load(&mut *self.conn, session_id)
+    }
+
     pub fn get_conversation_history(
         &mut self,
         sess_id: Uuid,
         _uid: Uuid,
         history_limit: Option<i64>,
     ) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
-        use crate::core::shared::models::message_history::dsl::*;
-        let limit_val = history_limit.unwrap_or(50);
-
-        // Get all messages ordered by index (chronological order)
-        let messages = message_history
-            .filter(session_id.eq(sess_id))
-            .order(message_index.asc())
-            .select((role, content_encrypted, message_index))
-            .load::<(i32, String, i32)>(&mut self.conn)?;
-
-        // Get last N message pairs to ensure user/assistant alternation
-        // Each "turn" is 2 messages (user + assistant), so we need 2 * limit_val messages
-        let total_messages_needed = (limit_val * 2) as usize;
-        let start_idx = messages.len().saturating_sub(total_messages_needed);
-        let recent_messages: Vec<_> = messages.into_iter().skip(start_idx).collect();
-
-        let mut history: Vec<(String, String)> = Vec::new();
-        for (other_role, content, _idx) in recent_messages {
-            let role_str = match other_role {
-                1 => "user".to_string(),
-                2 => "assistant".to_string(),
-                3 => "system".to_string(),
-                9 => "episodic".to_string(),
-                _ => "unknown".to_string(),
-            };
-            history.push((role_str, content));
-        }
-        Ok(history)
+        load_conversation_history(&mut self.conn, sess_id, history_limit)
     }
 
     pub fn get_user_sessions(
@@ -376,12 +402,14 @@ impl SessionManager {
 
         let sessions = if uid == Uuid::nil() {
             user_sessions
+                .filter(deleted_at.is_null())
                 .order(created_at.desc())
                 .load::<UserSession>(&mut self.conn)
                 .unwrap_or_else(|_| Vec::new())
         } else {
             user_sessions
                 .filter(user_id.eq(uid))
+                .filter(deleted_at.is_null())
                 .order(created_at.desc())
                 .load::<UserSession>(&mut self.conn)
                 .unwrap_or_else(|_| Vec::new())
@@ -389,6 +417,19 @@ impl SessionManager {
         Ok(sessions)
     }
 
+    /// Binds the session to another bot. Returns `false` when the session does not exist.
+    pub fn set_session_bot(
+        &mut self,
+        session_id: Uuid,
+        new_bot_id: Uuid,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        use crate::core::shared::models::user_sessions::dsl::*;
+        let updated_count = diesel::update(user_sessions.filter(id.eq(session_id)))
+            .set((bot_id.eq(new_bot_id), updated_at.eq(chrono::Utc::now())))
+            .execute(&mut self.conn)?;
+        Ok(updated_count > 0)
+    }
+
     pub fn update_user_id(
         &mut self,
         session_id: Uuid,
@@ -406,10 +447,51 @@ impl SessionManager {
         Ok(())
     }
 
+    /// Timeouts for sessions of a bot: the global ones with the bot's overrides applied.
+    pub fn session_timeouts(&mut self, bid: Uuid) -> SessionTimeouts {
+        use crate::core::shared::models::schema::bot_configuration::dsl::*;
+        let overrides: HashMap<String, String> = bot_configuration
+            .filter(bot_id.eq(bid))
+            .filter(config_key.eq_any([expiry::IDLE_TIMEOUT_KEY, expiry::MAX_LIFETIME_KEY]))
+            .select((config_key, config_value))
+            .load::<(String, String)>(&mut self.conn)
+            .map(|rows| rows.into_iter().collect())
+            .unwrap_or_default();
+        global_timeouts()
+            .clone()
+            .with_bot_overrides(|key| overrides.get(key).cloned())
+    }
+
+    /// Checks the session against its bot's timeouts and records the activity when it
+    /// is still live. The activity timestamp is written at most once a minute.
+    pub fn enforce_timeouts(&mut self, session: &UserSession) -> Result<(), SessionExpired> {
+        let now = Utc::now();
+        self.session_timeouts(session.bot_id)
+            .check(session.created_at, session.updated_at, now)?;
+        if now - session.updated_at > chrono::Duration::minutes(1) {
+            self.touch_session(session.id);
+        }
+        Ok(())
+    }
+
+    fn touch_session(&mut self, session_id: Uuid) {
+        use crate::core::shared::models::user_sessions::dsl::*;
+        if let Err(e) = diesel::update(user_sessions.filter(id.eq(session_id)))
+            .set(updated_at.eq(Utc::now()))
+            .execute(&mut self.conn)
+        {
+            warn!("Failed to record activity for session {}: {}", session_id, e);
+        }
+    }
+
     pub fn active_count(&self) -> usize {
         self.sessions.len()
     }
 
+    pub fn waiting_count(&self) -> usize {
+        self.waiting_for_input.len()
+    }
+
     pub fn total_count(&mut self) -> usize {
         use crate::core::shared::models::user_sessions::dsl::*;
         user_sessions
@@ -426,6 +508,7 @@ impl SessionManager {
         let since = chrono::Utc::now() - chrono::Duration::hours(hours);
         let sessions = user_sessions
             .filter(created_at.gt(since))
+            .filter(deleted_at.is_null())
             .order(created_at.desc())
             .load::<UserSession>(&mut self.conn)?;
         Ok(sessions)
@@ -460,12 +543,42 @@ impl SessionManager {
 
 /* Axum handlers */
 
-pub async fn create_session(Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
+/// Optional body of session creation and start: the bot the session should talk to.
+#[derive(Debug, Default, Deserialize)]
+pub struct SessionBotRequest {
+    pub bot_id: Option<Uuid>,
+}
+
+async fn validate_session_bot(state: &Arc<AppState>, bot_id: Uuid) -> Result<(), ApiError> {
+    let pool = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        require_active_bot(&mut conn, bot_id).map(|_| ())
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+}
+
+pub async fn create_session(
+    Extension(state): Extension<Arc<AppState>>,
+    body: Option<Json<SessionBotRequest>>,
+) -> Response {
     let temp_session_id = Uuid::new_v4();
 
+    let bot_id = match body.and_then(|Json(req)| req.bot_id) {
+        Some(requested) => {
+            if let Err(e) = validate_session_bot(&state, requested).await {
+                return e.into_response();
+            }
+            requested
+        }
+        None => Uuid::nil(),
+    };
+
     if state.conn.get().is_ok() {
         let user_id = Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap_or_default();
-        let bot_id = Uuid::nil();
 
         {
             let mut sm = state.session_manager.lock().await;
@@ -477,10 +590,12 @@ pub async fn create_session(Extension(state): Extension<Arc<AppState>>) -> impl
                     StatusCode::OK,
                     Json(serde_json::json!({
                         "session_id": session.id,
+                        "bot_id": session.bot_id,
                         "title": "New Conversation",
                         "created_at": Utc::now()
                     })),
-                );
+                )
+                    .into_response();
             }
         };
     }
@@ -489,11 +604,13 @@ pub async fn create_session(Extension(state): Extension<Arc<AppState>>) -> impl
         StatusCode::OK,
         Json(serde_json::json!({
             "session_id": temp_session_id,
+            "bot_id": bot_id,
             "title": "New Conversation",
             "created_at": Utc::now(),
             "temporary": true
         })),
     )
+        .into_response()
 }
 
 pub async fn get_sessions(Extension(state): Extension<Arc<AppState>>) -> impl IntoResponse {
@@ -511,60 +628,215 @@ pub async fn get_sessions(Extension(state): Extension<Arc<AppState>>) -> impl In
     }
 }
 
-pub async fn start_session(
+/// Soft-deletes a session. Open to the session's owner and admins.
+pub async fn delete_session(
     Extension(state): Extension<Arc<AppState>>,
+    user: AuthenticatedUser,
     Path(session_id): Path<String>,
-) -> impl IntoResponse {
-    match Uuid::parse_str(&session_id) {
-        Ok(session_uuid) => {
-            let mut sm = state.session_manager.lock().await;
-            match sm.get_session_by_id(session_uuid) {
-                Ok(Some(_)) => {
-                    sm.mark_waiting(session_uuid);
-                    (
-                        StatusCode::OK,
-                        Json(serde_json::json!({ "status": "started", "session_id": session_id })),
-                    )
-                }
-                Ok(None) => (
-                    StatusCode::NOT_FOUND,
-                    Json(serde_json::json!({ "error": "Session not found" })),
-                ),
-                Err(e) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({ "error": e.to_string() })),
-                ),
-            }
+) -> Response {
+    use crate::core::shared::soft_delete::{soft_delete, SoftDeleteTable};
+
+    let Ok(session_uuid) = Uuid::parse_str(&session_id) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "Invalid session ID" })),
+        )
+            .into_response();
+    };
+
+    let pool = state.conn.clone();
+    let deleted_by = user.user_id;
+    let result = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::internal(e.to_string()))?;
+        variables::authorize_session(&mut conn, session_uuid, &user)?;
+        soft_delete(
+            &mut conn,
+            SoftDeleteTable::UserSessions,
+            session_uuid,
+            Some(deleted_by),
+        )
+        .map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))
+    .and_then(|r| r);
+
+    match result {
+        Ok(true) => {
+            state.session_manager.lock().await.remove_session(session_uuid);
+            (
+                StatusCode::OK,
+                Json(serde_json::json!({ "status": "deleted", "session_id": session_id })),
+            )
+                .into_response()
         }
-        Err(_) => (
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "Session not found" })),
+        )
+            .into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Marks the session as waiting for input. A `bot_id` in the body switches the bot the
+/// session talks to, e.g. mid-conversation.
+pub async fn start_session(
+    Extension(state): Extension<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    body: Option<Json<SessionBotRequest>>,
+) -> Response {
+    let Ok(session_uuid) = Uuid::parse_str(&session_id) else {
+        return (
             StatusCode::BAD_REQUEST,
             Json(serde_json::json!({ "error": "Invalid session ID" })),
-        ),
+        )
+            .into_response();
+    };
+
+    let requested_bot = body.and_then(|Json(req)| req.bot_id);
+    if let Some(requested) = requested_bot {
+        if let Err(e) = validate_session_bot(&state, requested).await {
+            return e.into_response();
+        }
+    }
+
+    let mut sm = state.session_manager.lock().await;
+    let session = match sm.get_session_by_id(session_uuid) {
+        Ok(Some(session)) => session,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": "Session not found" })),
+            )
+                .into_response()
+        }
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response()
+        }
+    };
+    if let Err(expired) = sm.enforce_timeouts(&session) {
+        return expired.into_response();
+    }
+
+    let mut bound_bot = session.bot_id;
+    if let Some(requested) = requested_bot.filter(|b| *b != session.bot_id) {
+        if let Err(e) = sm.set_session_bot(session_uuid, requested) {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response();
+        }
+        info!(
+            "Session {} switched from bot {} to {}",
+            session_uuid, session.bot_id, requested
+        );
+        bound_bot = requested;
+    }
+
+    sm.mark_waiting(session_uuid);
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "status": "started",
+            "session_id": session_id,
+            "bot_id": bound_bot
+        })),
+    )
+        .into_response()
+}
+
+/// Rejects requests for a session past its idle or absolute timeout. Unknown sessions
+/// pass; callers decide whether to create them.
+pub async fn require_live_session(
+    state: &AppState,
+    session_id: Uuid,
+) -> Result<(), SessionExpired> {
+    let mut sm = state.session_manager.lock().await;
+    match sm.get_session_by_id(session_id) {
+        Ok(Some(session)) => sm.enforce_timeouts(&session).map_err(|expired| {
+            info!("Session {} rejected: {}", session_id, expired.reason());
+            expired
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Last `history_limit` turns of a session, oldest first. Read-only, so callers may pass
+/// a replica connection.
+pub fn load_conversation_history(
+    conn: &mut PgConnection,
+    sess_id: Uuid,
+    history_limit: Option<i64>,
+) -> Result<Vec<(String, String)>, Box<dyn Error + Send + Sync>> {
+    use crate::core::shared::models::message_history::dsl::*;
+    let limit_val = history_limit.unwrap_or(50);
+
+    // Get all messages ordered by index (chronological order)
+    let messages = message_history
+        .filter(session_id.eq(sess_id))
+        .order(message_index.asc())
+        .select((role, content_encrypted, message_index))
+        .load::<(i32, String, i32)>(conn)?;
+
+    // Get last N message pairs to ensure user/assistant alternation
+    // Each "turn" is 2 messages (user + assistant), so we need 2 * limit_val messages
+    let total_messages_needed = (limit_val * 2) as usize;
+    let start_idx = messages.len().saturating_sub(total_messages_needed);
+    let recent_messages: Vec<_> = messages.into_iter().skip(start_idx).collect();
+
+    let mut history: Vec<(String, String)> = Vec::new();
+    for (other_role, content, _idx) in recent_messages {
+        let role_str = match other_role {
+            1 => "user".to_string(),
+            2 => "assistant".to_string(),
+            3 => "system".to_string(),
+            9 => "episodic".to_string(),
+            _ => "unknown".to_string(),
+        };
+        history.push((role_str, content));
     }
+    Ok(history)
 }
 
 pub async fn get_session_history(
     Extension(state): Extension<Arc<AppState>>,
+    RequestLocale(locale): RequestLocale,
     Path(session_id): Path<String>,
 ) -> impl IntoResponse {
-    let user_id = Uuid::parse_str("00000000-0000-0000-0000-000000000001").unwrap_or_default();
     match Uuid::parse_str(&session_id) {
         Ok(session_uuid) => {
-            let orchestrator = BotOrchestrator::new(state.clone());
-            match orchestrator
-                .get_conversation_history(session_uuid, user_id)
-                .await
-            {
+            if let Err(expired) = require_live_session(&state, session_uuid).await {
+                return (StatusCode::UNAUTHORIZED, Json(expired.body(&locale)));
+            }
+            let pool = state.read_pool().clone();
+            let result = tokio::task::spawn_blocking(move || {
+                let mut conn = pool.get().map_err(|e| e.to_string())?;
+                load_conversation_history(&mut conn, session_uuid, None).map_err(|e| e.to_string())
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+            match result {
                 Ok(history) => (StatusCode::OK, Json(serde_json::json!(history))),
                 Err(e) => (
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({ "error": e.to_string() })),
+                    Json(serde_json::json!({ "error": e })),
                 ),
             }
         }
         Err(_) => (
             StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid session ID" })),
+            Json(serde_json::json!({
+                "error": system_message(&locale, "error-invalid-session-id", &[])
+            })),
         ),
     }
 }
@@ -0,0 +1,259 @@
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::extract::{Extension, Query};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{
+    Array, BigInt, Bool, Float4, Nullable, Text, Timestamptz, Uuid as DieselUuid,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+const MAX_QUERY_LEN: usize = 256;
+const SNIPPETS_PER_SESSION: i32 = 3;
+
+// ts_headline marks hits with these control characters, which are stripped from the
+// content first, so snippets can be escaped before the `<mark>` tags are added.
+const HIT_START: char = '\u{1}';
+const HIT_STOP: char = '\u{2}';
+
+// The to_tsvector expression must match idx_message_history_content_fts exactly,
+// otherwise Postgres will not use the index.
+const SEARCH_SQL: &str = "
+    SELECT s.id AS session_id, s.bot_id, b.name AS bot_name, s.title, s.updated_at,
+           COUNT(*) AS match_count,
+           MAX(ts_rank(to_tsvector('simple', mh.content_encrypted), q)) AS rank,
+           (ARRAY_AGG(
+               ts_headline('simple', translate(mh.content_encrypted, E'\\x01\\x02', ''), q,
+                           E'StartSel=\\x01, StopSel=\\x02, MaxWords=25, MinWords=8')
+               ORDER BY ts_rank(to_tsvector('simple', mh.content_encrypted), q) DESC
+           ))[1:$10] AS snippets,
+           COUNT(*) OVER () AS total
+    FROM message_history mh
+    CROSS JOIN websearch_to_tsquery('simple', $1) q
+    JOIN user_sessions s ON s.id = mh.session_id
+    JOIN bots b ON b.id = s.bot_id
+    WHERE to_tsvector('simple', mh.content_encrypted) @@ q
+      AND s.deleted_at IS NULL
+      AND b.deleted_at IS NULL
+      AND ($2::uuid IS NULL OR s.bot_id = $2)
+      AND ($3::timestamptz IS NULL OR mh.created_at >= $3)
+      AND ($4::timestamptz IS NULL OR mh.created_at < $4)
+      AND ($5 OR b.org_id = $6 OR s.user_id = $7)
+    GROUP BY s.id, s.bot_id, b.name, s.title, s.updated_at, q
+    ORDER BY rank DESC, s.updated_at DESC
+    LIMIT $8 OFFSET $9";
+
+#[derive(Debug, Deserialize)]
+pub struct MessageSearchQuery {
+    pub q: String,
+    pub bot_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<i64>,
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, QueryableByName)]
+struct SearchRow {
+    #[diesel(sql_type = DieselUuid)]
+    session_id: Uuid,
+    #[diesel(sql_type = DieselUuid)]
+    bot_id: Uuid,
+    #[diesel(sql_type = Text)]
+    bot_name: String,
+    #[diesel(sql_type = Text)]
+    title: String,
+    #[diesel(sql_type = Timestamptz)]
+    updated_at: DateTime<Utc>,
+    #[diesel(sql_type = BigInt)]
+    match_count: i64,
+    #[diesel(sql_type = Float4)]
+    rank: f32,
+    #[diesel(sql_type = Array<Text>)]
+    snippets: Vec<String>,
+    #[diesel(sql_type = BigInt)]
+    total: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionMatch {
+    pub session_id: Uuid,
+    pub bot_id: Uuid,
+    pub bot_name: String,
+    pub title: String,
+    pub updated_at: DateTime<Utc>,
+    pub match_count: i64,
+    pub rank: f32,
+    /// Matching excerpts with hits wrapped in `<mark>` tags, best match first.
+    pub snippets: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MessageSearchResponse {
+    pub query: String,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+    pub results: Vec<SessionMatch>,
+}
+
+/// Which sessions a caller may search: super admins see everything, other users see
+/// bots of their organization plus their own sessions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchScope {
+    pub all: bool,
+    pub org_id: Option<Uuid>,
+    pub user_id: Uuid,
+}
+
+impl SearchScope {
+    pub fn for_user(user: &AuthenticatedUser) -> Self {
+        Self {
+            all: user.is_super_admin(),
+            org_id: user.organization_id,
+            user_id: user.user_id,
+        }
+    }
+}
+
+fn page_bounds(page: Option<i64>, per_page: Option<i64>) -> (i64, i64, i64) {
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    (page, per_page, (page - 1) * per_page)
+}
+
+pub fn search_messages(
+    conn: &mut PgConnection,
+    query: &MessageSearchQuery,
+    scope: SearchScope,
+) -> QueryResult<MessageSearchResponse> {
+    let (page, per_page, offset) = page_bounds(query.page, query.per_page);
+
+    let rows: Vec<SearchRow> = diesel::sql_query(SEARCH_SQL)
+        .bind::<Text, _>(query.q.trim())
+        .bind::<Nullable<DieselUuid>, _>(query.bot_id)
+        .bind::<Nullable<Timestamptz>, _>(query.from)
+        .bind::<Nullable<Timestamptz>, _>(query.to)
+        .bind::<Bool, _>(scope.all)
+        .bind::<Nullable<DieselUuid>, _>(scope.org_id)
+        .bind::<DieselUuid, _>(scope.user_id)
+        .bind::<BigInt, _>(per_page)
+        .bind::<BigInt, _>(offset)
+        .bind::<diesel::sql_types::Integer, _>(SNIPPETS_PER_SESSION)
+        .load(conn)?;
+
+    let total = rows.first().map(|r| r.total).unwrap_or(0);
+    Ok(MessageSearchResponse {
+        query: query.q.trim().to_string(),
+        page,
+        per_page,
+        total,
+        results: rows
+            .into_iter()
+            .map(|r| SessionMatch {
+                session_id: r.session_id,
+                bot_id: r.bot_id,
+                bot_name: r.bot_name,
+                title: r.title,
+                updated_at: r.updated_at,
+                match_count: r.match_count,
+                rank: r.rank,
+                snippets: r.snippets.iter().map(|s| render_snippet(s)).collect(),
+            })
+            .collect(),
+    })
+}
+
+/// HTML-escapes a headline, then turns the hit markers into `<mark>` tags.
+fn render_snippet(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len() + 16);
+    for c in raw.chars() {
+        match c {
+            HIT_START => out.push_str("<mark>"),
+            HIT_STOP => out.push_str("</mark>"),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+pub async fn handle_search_messages(
+    Extension(state): Extension<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Query(query): Query<MessageSearchQuery>,
+) -> Result<Json<MessageSearchResponse>, ApiError> {
+    if !user.is_authenticated() {
+        return Err(ApiError::unauthorized("Authentication required"));
+    }
+    let q = query.q.trim();
+    if q.is_empty() {
+        return Err(ApiError::bad_request("Query parameter 'q' is required"));
+    }
+    if q.len() > MAX_QUERY_LEN {
+        return Err(ApiError::bad_request(format!(
+            "Query must be at most {} characters",
+            MAX_QUERY_LEN
+        )));
+    }
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Err(ApiError::bad_request("'from' must be before 'to'"));
+        }
+    }
+
+    let scope = SearchScope::for_user(&user);
+    let pool = state.read_pool().clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        search_messages(&mut conn, &query, scope).map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+    .map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_bounds() {
+        assert_eq!(page_bounds(None, None), (1, DEFAULT_PER_PAGE, 0));
+        assert_eq!(page_bounds(Some(3), Some(10)), (3, 10, 20));
+        assert_eq!(page_bounds(Some(0), Some(1000)), (1, MAX_PER_PAGE, 0));
+    }
+
+    #[test]
+    fn test_snippet_is_escaped_around_hits() {
+        let raw = "<script>alert('x')</script> \u{1}invoice\u{2} & co";
+        assert_eq!(
+            render_snippet(raw),
+            "&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; <mark>invoice</mark> &amp; co"
+        );
+    }
+
+    #[test]
+    fn test_scope_for_regular_user() {
+        let org = Uuid::new_v4();
+        let user = AuthenticatedUser::new(Uuid::new_v4(), "ana".to_string()).with_organization(org);
+        let scope = SearchScope::for_user(&user);
+        assert!(!scope.all);
+        assert_eq!(scope.org_id, Some(org));
+
+        let admin = user.with_role(crate::security::auth_api::Role::SuperAdmin);
+        assert!(SearchScope::for_user(&admin).all);
+    }
+}
@@ -0,0 +1,353 @@
+//! Typed per-session variables, such as form fields collected over several turns.
+//!
+//! Values are JSON and kept in `session_variables`, one row per session, so they survive
+//! reconnects and server restarts. BASIC reads and writes them with `SET SESSION` and
+//! `GET_SESSION`, and tool scripts start with them in scope. The serialized size of a
+//! session's variables is capped by `SESSION_VARIABLES_MAX_BYTES`.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::Json;
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+use uuid::Uuid;
+
+const DEFAULT_MAX_BYTES: usize = 64 * 1024;
+const MAX_KEY_LEN: usize = 128;
+
+/// Cap on the serialized size of one session's variables.
+pub fn max_bytes() -> usize {
+    static MAX_BYTES: OnceLock<usize> = OnceLock::new();
+    *MAX_BYTES.get_or_init(|| {
+        std::env::var("SESSION_VARIABLES_MAX_BYTES")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_MAX_BYTES)
+    })
+}
+
+#[derive(Debug)]
+pub enum SessionVariableError {
+    InvalidKey(String),
+    TooLarge { size: usize, limit: usize },
+    Database(String),
+}
+
+impl std::fmt::Display for SessionVariableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidKey(key) => write!(
+                f,
+                "Invalid session variable name {key:?}: use up to {MAX_KEY_LEN} letters, digits, '_', '.' or '-'"
+            ),
+            Self::TooLarge { size, limit } => write!(
+                f,
+                "Session variables would take {size} bytes, more than the {limit} allowed"
+            ),
+            Self::Database(e) => write!(f, "Session variables unavailable: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SessionVariableError {}
+
+impl From<diesel::result::Error> for SessionVariableError {
+    fn from(e: diesel::result::Error) -> Self {
+        Self::Database(e.to_string())
+    }
+}
+
+impl From<SessionVariableError> for ApiError {
+    fn from(e: SessionVariableError) -> Self {
+        match e {
+            SessionVariableError::InvalidKey(_) => ApiError::bad_request(e.to_string()),
+            SessionVariableError::TooLarge { .. } => ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "SESSION_VARIABLES_TOO_LARGE",
+                e.to_string(),
+            ),
+            SessionVariableError::Database(_) => ApiError::internal(e.to_string()),
+        }
+    }
+}
+
+fn validate_key(key: &str) -> Result<(), SessionVariableError> {
+    let valid = !key.is_empty()
+        && key.len() <= MAX_KEY_LEN
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(SessionVariableError::InvalidKey(key.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct SessionVariables(BTreeMap<String, Value>);
+
+impl SessionVariables {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.0.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.0.iter()
+    }
+
+    pub fn size_bytes(&self) -> usize {
+        serde_json::to_vec(&self.0).map(|v| v.len()).unwrap_or(0)
+    }
+
+    /// Sets `key`, or removes it when `value` is null. Fails without changing anything if
+    /// the result would exceed `limit` bytes; removals always succeed.
+    pub fn set(
+        &mut self,
+        key: &str,
+        value: Value,
+        limit: usize,
+    ) -> Result<(), SessionVariableError> {
+        validate_key(key)?;
+        if value.is_null() {
+            self.0.remove(key);
+            return Ok(());
+        }
+        let previous = self.0.insert(key.to_string(), value);
+        let size = self.size_bytes();
+        if size > limit {
+            match previous {
+                Some(previous) => self.0.insert(key.to_string(), previous),
+                None => self.0.remove(key),
+            };
+            return Err(SessionVariableError::TooLarge { size, limit });
+        }
+        Ok(())
+    }
+}
+
+/// Where session variables are persisted.
+pub trait SessionVariableStore {
+    fn load(&mut self, session_id: Uuid) -> Result<SessionVariables, SessionVariableError>;
+    fn save(
+        &mut self,
+        session_id: Uuid,
+        variables: &SessionVariables,
+    ) -> Result<(), SessionVariableError>;
+}
+
+#[derive(QueryableByName)]
+struct VariablesRow {
+    #[diesel(sql_type = diesel::sql_types::Jsonb)]
+    variables: Value,
+}
+
+impl SessionVariableStore for PgConnection {
+    fn load(&mut self, session_id: Uuid) -> Result<SessionVariables, SessionVariableError> {
+        // Locks the row when called inside `set_variable`'s transaction.
+        let row: Option<VariablesRow> = diesel::sql_query(
+            "SELECT variables FROM session_variables WHERE session_id = $1 FOR UPDATE",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(session_id)
+        .get_result(self)
+        .optional()?;
+        let map = match row.map(|r| r.variables) {
+            Some(Value::Object(map)) => map.into_iter().collect(),
+            _ => BTreeMap::new(),
+        };
+        Ok(SessionVariables(map))
+    }
+
+    fn save(
+        &mut self,
+        session_id: Uuid,
+        variables: &SessionVariables,
+    ) -> Result<(), SessionVariableError> {
+        let json = serde_json::to_value(variables)
+            .map_err(|e| SessionVariableError::Database(e.to_string()))?;
+        diesel::sql_query(
+            "INSERT INTO session_variables (session_id, variables, updated_at)
+             VALUES ($1, $2, NOW())
+             ON CONFLICT (session_id)
+             DO UPDATE SET variables = EXCLUDED.variables, updated_at = NOW()",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(session_id)
+        .bind::<diesel::sql_types::Jsonb, _>(json)
+        .execute(self)?;
+        Ok(())
+    }
+}
+
+pub fn get_variable<S: SessionVariableStore + ?Sized>(
+    store: &mut S,
+    session_id: Uuid,
+    key: &str,
+) -> Result<Option<Value>, SessionVariableError> {
+    Ok(store.load(session_id)?.get(key).cloned())
+}
+
+/// Sets one variable, keeping the others. A null `value` removes it.
+pub fn set_variable<S: SessionVariableStore + ?Sized>(
+    store: &mut S,
+    session_id: Uuid,
+    key: &str,
+    value: Value,
+    limit: usize,
+) -> Result<SessionVariables, SessionVariableError> {
+    let mut variables = store.load(session_id)?;
+    variables.set(key, value, limit)?;
+    store.save(session_id, &variables)?;
+    Ok(variables)
+}
+
+/// [`set_variable`] in a transaction, so concurrent writers to the same session do not
+/// lose each other's keys.
+pub fn set_variable_locked(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    key: &str,
+    value: Value,
+) -> Result<SessionVariables, SessionVariableError> {
+    conn.transaction(|conn| set_variable(conn, session_id, key, value, max_bytes()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionVariablesResponse {
+    pub session_id: Uuid,
+    pub size_bytes: usize,
+    pub max_bytes: usize,
+    pub variables: SessionVariables,
+}
+
+/// Checks that `user` may see the session's data: its owner or an admin. Returns the
+/// session's bot. Blocking.
+pub fn authorize_session(
+    conn: &mut PgConnection,
+    session_id: Uuid,
+    user: &AuthenticatedUser,
+) -> Result<Uuid, ApiError> {
+    use crate::core::shared::models::schema::user_sessions::dsl::*;
+    if !user.is_authenticated() {
+        return Err(ApiError::unauthorized("Authentication required"));
+    }
+    let (owner, bot): (Uuid, Uuid) = user_sessions
+        .filter(id.eq(session_id))
+        .select((user_id, bot_id))
+        .first(conn)
+        .optional()
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .ok_or_else(|| ApiError::not_found("Session not found"))?;
+    if owner != user.user_id && !user.is_admin() {
+        return Err(ApiError::not_found("Session not found"));
+    }
+    Ok(bot)
+}
+
+/// Variables of a session, for debugging bots. Open to the session's owner and admins.
+pub async fn handle_get_session_variables(
+    Extension(state): Extension<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(session_id): Path<Uuid>,
+) -> Result<Json<SessionVariablesResponse>, ApiError> {
+    if !user.is_authenticated() {
+        return Err(ApiError::unauthorized("Authentication required"));
+    }
+
+    let pool = state.conn.clone();
+    let variables = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        authorize_session(&mut conn, session_id, &user)?;
+        SessionVariableStore::load(&mut *conn, session_id).map_err(ApiError::from)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??;
+
+    Ok(Json(SessionVariablesResponse {
+        session_id,
+        size_bytes: variables.size_bytes(),
+        max_bytes: max_bytes(),
+        variables,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Keeps each session's variables serialized, like the database row.
+    #[derive(Default)]
+    struct MemoryStore(HashMap<Uuid, String>);
+
+    impl SessionVariableStore for MemoryStore {
+        fn load(&mut self, session_id: Uuid) -> Result<SessionVariables, SessionVariableError> {
+            let map = self
+                .0
+                .get(&session_id)
+                .map(|raw| serde_json::from_str(raw).unwrap())
+                .unwrap_or_default();
+            Ok(SessionVariables(map))
+        }
+
+        fn save(
+            &mut self,
+            session_id: Uuid,
+            variables: &SessionVariables,
+        ) -> Result<(), SessionVariableError> {
+            self.0
+                .insert(session_id, serde_json::to_string(variables).unwrap());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_value_set_in_one_turn_is_read_in_a_later_turn() {
+        let mut store = MemoryStore::default();
+        let session = Uuid::new_v4();
+        let other = Uuid::new_v4();
+
+        // First turn collects a form field.
+        set_variable(&mut store, session, "form.email", json!("ana@example.com"), 1024).unwrap();
+        set_variable(&mut store, session, "form.age", json!(31), 1024).unwrap();
+
+        // A later turn only has the session id.
+        assert_eq!(
+            get_variable(&mut store, session, "form.email").unwrap(),
+            Some(json!("ana@example.com"))
+        );
+        assert_eq!(get_variable(&mut store, session, "form.age").unwrap(), Some(json!(31)));
+        assert_eq!(get_variable(&mut store, other, "form.email").unwrap(), None);
+
+        set_variable(&mut store, session, "form.age", Value::Null, 1024).unwrap();
+        assert_eq!(get_variable(&mut store, session, "form.age").unwrap(), None);
+    }
+
+    #[test]
+    fn test_size_cap_rejects_without_changing_state() {
+        let mut store = MemoryStore::default();
+        let session = Uuid::new_v4();
+        set_variable(&mut store, session, "name", json!("Ana"), 64).unwrap();
+
+        let err = set_variable(&mut store, session, "notes", json!("x".repeat(100)), 64).unwrap_err();
+        assert!(matches!(err, SessionVariableError::TooLarge { limit: 64, .. }));
+        let kept = store.load(session).unwrap();
+        assert_eq!(kept.get("name"), Some(&json!("Ana")));
+        assert_eq!(kept.get("notes"), None);
+
+        assert!(matches!(
+            set_variable(&mut store, session, "bad key", json!(1), 64),
+            Err(SessionVariableError::InvalidKey(_))
+        ));
+    }
+}
@@ -1,5 +1,5 @@
 
-use axum::{Router, routing::{get, post}};
+use axum::{Router, routing::{get, post, put}};
 use std::sync::Arc;
 
 /// Configure admin routes
@@ -9,4 +9,56 @@ pub fn configure() -> Router<Arc<crate::core::shared::state::AppState>> {
     Router::new()
         .route("/api/admin/config", get(get_config))
         .route("/api/admin/config", post(update_config))
+        .route(
+            "/api/admin/dashboard",
+            get(super::admin_dashboard::handle_admin_dashboard),
+        )
+        .route("/api/admin/deleted/restore", post(super::soft_delete::handle_restore))
+        .route("/api/admin/deleted/purge", post(super::soft_delete::handle_purge))
+        .route(
+            "/api/admin/bots/:bot_id/features",
+            get(super::bot_features::handle_get_bot_features)
+                .put(super::bot_features::handle_update_bot_features),
+        )
+        .route(
+            "/api/admin/bots/:bot_id/config/sync",
+            post(handle_sync_bot_config),
+        )
+        .route(
+            "/api/admin/bots/:bot_id/config/export",
+            get(handle_download_bot_config).post(handle_export_bot_config),
+        )
+        .route(
+            "/api/admin/bots/:bot_id/quick-replies",
+            get(crate::core::bot::quick_replies::handle_list_quick_replies)
+                .post(crate::core::bot::quick_replies::handle_create_quick_reply),
+        )
+        .route(
+            "/api/admin/bots/:bot_id/quick-replies/:id",
+            put(crate::core::bot::quick_replies::handle_update_quick_reply)
+                .delete(crate::core::bot::quick_replies::handle_delete_quick_reply),
+        )
+        .route(
+            "/api/admin/bots/mount-status",
+            get(crate::core::bot::mount::handle_mount_status),
+        )
+        .route(
+            "/api/admin/encryption/rotate",
+            post(super::key_rotation::handle_rotate_master_key),
+        )
+        .route(
+            "/api/admin/encryption/rotation",
+            get(super::key_rotation::handle_rotation_status),
+        )
+        .route(
+            "/api/admin/logging",
+            get(super::log_control::handle_get_log_levels)
+                .put(super::log_control::handle_set_log_level)
+                .delete(super::log_control::handle_reset_log_levels),
+        )
+        .route(
+            "/api/admin/maintenance",
+            get(super::maintenance::handle_get_maintenance)
+                .put(super::maintenance::handle_set_maintenance),
+        )
 }
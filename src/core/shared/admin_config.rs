@@ -1,12 +1,19 @@
 use super::admin_types::*;
+use crate::core::config::{render_gbot_config, ConfigManager};
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::bot_features::bot_exists;
+use crate::core::shared::soft_delete::require_admin;
 use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::{IntoResponse, Json},
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
 use log::info;
 use std::sync::Arc;
+use uuid::Uuid;
 
 /// Get current configuration
 pub async fn get_config(
@@ -32,7 +39,7 @@ pub async fn get_config(
 /// Update configuration
 pub async fn update_config(
     State(_state): State<Arc<AppState>>,
-    Json(request): Json<UpdateConfigRequest>,
+    ApiJson(request): ApiJson<UpdateConfigRequest>,
 ) -> impl IntoResponse {
     info!("Updating config: {} = {}", request.key, request.value);
 
@@ -40,3 +47,197 @@ pub async fn update_config(
     // In production, this would update the database
     (StatusCode::OK, Json(serde_json::json!({"success": true}))).into_response()
 }
+
+/// Sync a bot's `config.csv` into the database, or with `dry_run` only report what would
+/// change.
+pub async fn handle_sync_bot_config(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(bot_id): Path<Uuid>,
+    ApiJson(request): ApiJson<SyncBotConfigRequest>,
+) -> Result<Json<SyncBotConfigResponse>, ApiError> {
+    require_admin(&user)?;
+
+    let pool = state.conn.clone();
+    let dry_run = request.dry_run;
+    let diff = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        if !bot_exists(&mut conn, bot_id).map_err(|e| ApiError::internal(e.to_string()))? {
+            return Err(ApiError::not_found(format!("Bot {} not found", bot_id)));
+        }
+        drop(conn);
+
+        let manager = ConfigManager::new(pool);
+        if dry_run {
+            manager.diff_gbot_config(&bot_id, &request.content)
+        } else {
+            manager.sync_gbot_config(&bot_id, &request.content)
+        }
+        .map_err(ApiError::internal)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??;
+
+    if !dry_run {
+        info!(
+            "Synced config for bot {} (by {}): {}",
+            bot_id, user.user_id, diff
+        );
+    }
+    Ok(Json(SyncBotConfigResponse {
+        bot_id,
+        dry_run,
+        diff,
+    }))
+}
+
+/// Drive location of a bot's `config.csv`.
+fn config_csv_location(bot_name: &str) -> (String, String) {
+    (
+        format!("{}.gbai", bot_name),
+        format!("{}.gbot/config.csv", bot_name),
+    )
+}
+
+#[cfg(feature = "drive")]
+async fn read_drive_text(state: &AppState, bucket: &str, key: &str) -> Option<String> {
+    let drive = state.drive.as_ref()?;
+    let object = drive
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .ok()?;
+    let bytes = object.body.collect().await.ok()?.into_bytes();
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+#[cfg(not(feature = "drive"))]
+async fn read_drive_text(_state: &AppState, _bucket: &str, _key: &str) -> Option<String> {
+    None
+}
+
+#[cfg(feature = "drive")]
+async fn write_drive_text(
+    state: &AppState,
+    bucket: &str,
+    key: &str,
+    content: String,
+) -> Result<(), ApiError> {
+    let drive = state
+        .drive
+        .as_ref()
+        .ok_or_else(|| ApiError::service_unavailable("Drive not available"))?;
+    drive
+        .put_object()
+        .bucket(bucket)
+        .key(key)
+        .body(content.into_bytes().into())
+        .content_type("text/csv")
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| ApiError::internal(format!("Failed to write {}/{}: {}", bucket, key, e)))
+}
+
+#[cfg(not(feature = "drive"))]
+async fn write_drive_text(
+    _state: &AppState,
+    _bucket: &str,
+    _key: &str,
+    _content: String,
+) -> Result<(), ApiError> {
+    Err(ApiError::service_unavailable("Drive not available"))
+}
+
+/// Name of the bot and its stored configuration rendered as `config.csv`, keeping the
+/// comments of the copy currently in the drive.
+async fn render_bot_config(
+    state: &Arc<AppState>,
+    bot_id: Uuid,
+) -> Result<(String, Vec<(String, String)>, String), ApiError> {
+    let pool = state.conn.clone();
+    let (bot_name, entries) = tokio::task::spawn_blocking(move || {
+        use crate::core::shared::models::schema::bots::dsl::*;
+        use diesel::prelude::*;
+
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        let bot_name = bots
+            .filter(id.eq(bot_id))
+            .filter(deleted_at.is_null())
+            .select(name)
+            .first::<String>(&mut conn)
+            .optional()
+            .map_err(|e| ApiError::internal(e.to_string()))?
+            .ok_or_else(|| ApiError::not_found(format!("Bot {} not found", bot_id)))?;
+        drop(conn);
+
+        let entries = ConfigManager::new(pool)
+            .export_gbot_config(&bot_id)
+            .map_err(ApiError::internal)?;
+        Ok::<_, ApiError>((bot_name, entries))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??;
+
+    let (bucket, key) = config_csv_location(&bot_name);
+    let existing = read_drive_text(state, &bucket, &key).await;
+    let content = render_gbot_config(existing.as_deref(), &entries);
+    Ok((bot_name, entries, content))
+}
+
+/// Download a bot's stored configuration as `config.csv`.
+pub async fn handle_download_bot_config(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(bot_id): Path<Uuid>,
+) -> Result<Response, ApiError> {
+    require_admin(&user)?;
+
+    let (bot_name, _, content) = render_bot_config(&state, bot_id).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}-config.csv\"", bot_name),
+            ),
+        ],
+        content,
+    )
+        .into_response())
+}
+
+/// Write a bot's stored configuration back to `<bot>.gbai/<bot>.gbot/config.csv` in the
+/// drive, so it can be versioned and synced again.
+pub async fn handle_export_bot_config(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(bot_id): Path<Uuid>,
+) -> Result<Json<ExportBotConfigResponse>, ApiError> {
+    require_admin(&user)?;
+
+    let (bot_name, entries, content) = render_bot_config(&state, bot_id).await?;
+    let (bucket, key) = config_csv_location(&bot_name);
+    write_drive_text(&state, &bucket, &key, content).await?;
+
+    info!(
+        "Exported config for bot {} to {}/{} (by {}, {} entries)",
+        bot_id,
+        bucket,
+        key,
+        user.user_id,
+        entries.len()
+    );
+    Ok(Json(ExportBotConfigResponse {
+        bot_id,
+        bucket,
+        key,
+        entries: entries.len(),
+    }))
+}
@@ -0,0 +1,268 @@
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{extract::State, Json};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Timestamptz};
+use log::warn;
+use serde::Serialize;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// The overview screen polls this; a few seconds of staleness keeps it from re-running
+/// the counts on every refresh.
+const CACHE_TTL: Duration = Duration::from_secs(10);
+
+static CACHE: LazyLock<RwLock<Option<(Instant, AdminDashboard)>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentStatus {
+    Ok,
+    Disabled,
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentSummary {
+    pub name: &'static str,
+    pub status: ComponentStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ComponentSummary {
+    fn new(name: &'static str, status: ComponentStatus) -> Self {
+        Self {
+            name,
+            status,
+            detail: None,
+        }
+    }
+
+    fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSummary {
+    pub status: ComponentStatus,
+    pub components: Vec<ComponentSummary>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct TaskQueueSummary {
+    pub due: usize,
+    pub running: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminDashboard {
+    pub generated_at: DateTime<Utc>,
+    /// The daily figures are `null` while the database cannot be queried.
+    pub active_bots: Option<i64>,
+    pub sessions_today: Option<i64>,
+    pub messages_today: Option<i64>,
+    pub llm_cost_today: Option<f64>,
+    /// Sessions held in memory by this instance.
+    pub live_sessions: usize,
+    pub waiting_for_input: usize,
+    pub api_calls_per_minute: f64,
+    pub task_queue: TaskQueueSummary,
+    pub health: HealthSummary,
+}
+
+#[derive(QueryableByName)]
+struct DailyCounts {
+    #[diesel(sql_type = BigInt)]
+    active_bots: i64,
+    #[diesel(sql_type = BigInt)]
+    sessions_today: i64,
+    #[diesel(sql_type = BigInt)]
+    messages_today: i64,
+    #[diesel(sql_type = Double)]
+    llm_cost_today: f64,
+}
+
+fn daily_counts(conn: &mut PgConnection, since: DateTime<Utc>) -> QueryResult<DailyCounts> {
+    diesel::sql_query(
+        "SELECT
+            (SELECT COUNT(*) FROM bots
+              WHERE is_active = true AND deleted_at IS NULL) AS active_bots,
+            (SELECT COUNT(*) FROM user_sessions
+              WHERE created_at >= $1 AND deleted_at IS NULL) AS sessions_today,
+            (SELECT COUNT(*) FROM message_history WHERE created_at >= $1) AS messages_today,
+            (SELECT COALESCE(SUM(cost), 0)::float8 FROM llm_usage
+              WHERE created_at >= $1) AS llm_cost_today",
+    )
+    .bind::<Timestamptz, _>(since)
+    .get_result(conn)
+}
+
+/// The worst status among components that are switched on.
+pub fn overall_status(components: &[ComponentSummary]) -> ComponentStatus {
+    components
+        .iter()
+        .map(|c| c.status)
+        .filter(|s| *s != ComponentStatus::Disabled)
+        .max()
+        .unwrap_or(ComponentStatus::Ok)
+}
+
+/// The same probes `/health/ready` uses, plus the optional services.
+fn component_health(state: &AppState, db_ok: bool, pool_saturated: bool) -> Vec<ComponentSummary> {
+    let mut components = Vec::new();
+
+    components.push(match (db_ok, pool_saturated) {
+        (false, _) => ComponentSummary::new("database", ComponentStatus::Down),
+        (true, true) => ComponentSummary::new("database", ComponentStatus::Degraded)
+            .with_detail("connection pool saturated"),
+        (true, false) => ComponentSummary::new("database", ComponentStatus::Ok),
+    });
+
+    #[cfg(feature = "cache")]
+    components.push(match &state.cache {
+        None => ComponentSummary::new("cache", ComponentStatus::Disabled),
+        Some(_) if crate::core::shared::circuit_breaker::REDIS_BREAKER.is_open() => {
+            ComponentSummary::new("cache", ComponentStatus::Down).with_detail("circuit open")
+        }
+        Some(_) => ComponentSummary::new("cache", ComponentStatus::Ok),
+    });
+
+    components.push(if state.drive.is_some() {
+        ComponentSummary::new("drive", ComponentStatus::Ok)
+    } else {
+        ComponentSummary::new("drive", ComponentStatus::Disabled)
+    });
+
+    #[cfg(any(feature = "research", feature = "llm"))]
+    {
+        let model = crate::core::kb::embedding_model::model_status();
+        components.push(if model.is_ready() {
+            ComponentSummary::new("embedding_model", ComponentStatus::Ok)
+        } else {
+            ComponentSummary::new("embedding_model", ComponentStatus::Down)
+                .with_detail(format!("{model:?}"))
+        });
+    }
+
+    components
+}
+
+async fn task_queue(state: &AppState) -> TaskQueueSummary {
+    #[cfg(feature = "tasks")]
+    if let Some(scheduler) = &state.task_scheduler {
+        let depth = scheduler.queue_depth().await;
+        return TaskQueueSummary {
+            due: depth.due,
+            running: depth.running,
+        };
+    }
+    let _ = state;
+    TaskQueueSummary::default()
+}
+
+async fn build_dashboard(state: &Arc<AppState>) -> Result<AdminDashboard, ApiError> {
+    let now = Utc::now();
+    let today_start = now
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+
+    let pool = state.read_pool().clone();
+    let primary = state.conn.clone();
+    let (counts, db_ok, pool_saturated) = tokio::task::spawn_blocking(move || {
+        let stats = crate::core::shared::db_pool::primary_pool_stats();
+        let saturated = crate::core::shared::db_pool::pool_status(&primary, &stats).saturated;
+        let counts = pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| daily_counts(&mut conn, today_start).map_err(|e| e.to_string()))
+            .map_err(|e| warn!("Admin dashboard: daily counts unavailable: {}", e))
+            .ok();
+        (counts, primary.get().is_ok(), saturated)
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?;
+
+    let (live_sessions, waiting_for_input) = {
+        let sm = state.session_manager.lock().await;
+        (sm.active_count(), sm.waiting_count())
+    };
+
+    let api_calls_per_minute = state
+        .metrics_collector
+        .get_rate("api.calls", chrono::Duration::minutes(1))
+        .await
+        * 60.0;
+
+    let components = component_health(state, db_ok, pool_saturated);
+    Ok(AdminDashboard {
+        generated_at: now,
+        active_bots: counts.as_ref().map(|c| c.active_bots),
+        sessions_today: counts.as_ref().map(|c| c.sessions_today),
+        messages_today: counts.as_ref().map(|c| c.messages_today),
+        llm_cost_today: counts.as_ref().map(|c| c.llm_cost_today),
+        live_sessions,
+        waiting_for_input,
+        api_calls_per_minute,
+        task_queue: task_queue(state).await,
+        health: HealthSummary {
+            status: overall_status(&components),
+            components,
+        },
+    })
+}
+
+pub async fn handle_admin_dashboard(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+) -> Result<Json<AdminDashboard>, ApiError> {
+    require_admin(&user)?;
+
+    let cached = CACHE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .filter(|(at, _)| at.elapsed() < CACHE_TTL)
+        .map(|(_, dashboard)| dashboard.clone());
+    if let Some(dashboard) = cached {
+        return Ok(Json(dashboard));
+    }
+
+    let dashboard = build_dashboard(&state).await?;
+    *CACHE.write().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), dashboard.clone()));
+    Ok(Json(dashboard))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overall_status_ignores_disabled() {
+        let mut components = vec![
+            ComponentSummary::new("database", ComponentStatus::Ok),
+            ComponentSummary::new("drive", ComponentStatus::Disabled),
+        ];
+        assert_eq!(overall_status(&components), ComponentStatus::Ok);
+
+        components.push(ComponentSummary::new("cache", ComponentStatus::Degraded));
+        assert_eq!(overall_status(&components), ComponentStatus::Degraded);
+
+        components.push(ComponentSummary::new(
+            "embedding_model",
+            ComponentStatus::Down,
+        ));
+        assert_eq!(overall_status(&components), ComponentStatus::Down);
+
+        assert_eq!(overall_status(&[]), ComponentStatus::Ok);
+    }
+}
@@ -1,4 +1,5 @@
 use super::admin_types::*;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::models::core::OrganizationInvitation;
 use crate::core::shared::state::AppState;
 use crate::core::urls::ApiUrls;
@@ -66,7 +67,7 @@ pub async fn list_invitations(
 pub async fn create_invitation(
     State(state): State<Arc<AppState>>,
     Path(bot_id): Path<Uuid>,
-    Json(request): Json<CreateInvitationRequest>,
+    ApiJson(request): ApiJson<CreateInvitationRequest>,
 ) -> impl IntoResponse {
     use crate::core::shared::models::schema::organization_invitations::dsl::*;
 
@@ -141,7 +142,7 @@ pub async fn create_invitation(
 
 pub async fn create_bulk_invitations(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<BulkInvitationRequest>,
+    ApiJson(request): ApiJson<BulkInvitationRequest>,
 ) -> impl IntoResponse {
     use crate::core::shared::models::schema::organization_invitations::dsl::*;
 
@@ -73,3 +73,27 @@ macro_rules! Success_response {
     };
 }
 
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncBotConfigRequest {
+    /// Contents of the bot's `config.csv`.
+    pub content: String,
+    /// Report the changes without writing them.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncBotConfigResponse {
+    pub bot_id: Uuid,
+    pub dry_run: bool,
+    pub diff: crate::core::config::ConfigDiff,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportBotConfigResponse {
+    pub bot_id: Uuid,
+    pub bucket: String,
+    pub key: String,
+    pub entries: usize,
+}
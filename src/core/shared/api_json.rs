@@ -0,0 +1,210 @@
+//! JSON body extractor for API handlers. It behaves like `axum::Json` for valid requests,
+//! but rejects bad bodies with an [`ApiError`] whose `field` names the offending field:
+//!
+//! ```json
+//! {"error": {"code": "INVALID_BODY", "message": "invalid type: string \"ten\", expected u32",
+//!            "field": "worksheet_index", "request_id": "..."}}
+//! ```
+//!
+//! Bodies are capped at `HTTP_JSON_BODY_LIMIT` bytes; see [`BodyLimitConfig`].
+
+use crate::core::config::BodyLimitConfig;
+use crate::core::shared::api_error::ApiError;
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, Request};
+use axum::http::{header, HeaderMap, StatusCode};
+use serde::de::DeserializeOwned;
+use std::sync::LazyLock;
+
+static LIMITS: LazyLock<BodyLimitConfig> = LazyLock::new(BodyLimitConfig::from_env);
+
+const DATA_ERROR_PREFIX: &str = "Failed to deserialize the JSON body into the target type: ";
+const SYNTAX_ERROR_PREFIX: &str = "Failed to parse the request body as JSON: ";
+
+/// Body size limits in effect for this process.
+pub fn body_limits() -> BodyLimitConfig {
+    *LIMITS
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApiJson<T>(pub T);
+
+fn invalid_body(message: impl Into<String>) -> ApiError {
+    ApiError::new(StatusCode::BAD_REQUEST, "INVALID_BODY", message)
+}
+
+fn is_json_content_type(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence == "application/json"
+        || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+/// Drops serde's ` at line 1 column 12` suffix, which means nothing to API clients.
+fn strip_position(message: &str) -> &str {
+    match message.rfind(" at line ") {
+        Some(at) if message[at..].contains(" column ") => &message[..at],
+        _ => message,
+    }
+}
+
+/// Splits an axum deserialization error into a message and the path of the field it is
+/// about. axum reports `path: error`, omitting the path for errors at the top level.
+/// Missing and unknown fields are reported against their parent, so their own name is
+/// appended.
+fn describe_data_error(detail: &str) -> (String, Option<String>) {
+    let detail = detail.strip_prefix(DATA_ERROR_PREFIX).unwrap_or(detail);
+    let (path, message) = match detail.split_once(": ") {
+        Some((path, message)) if !path.is_empty() && !path.contains(' ') => (Some(path), message),
+        _ => (None, detail),
+    };
+    let message = strip_position(message).to_string();
+
+    let named = ["missing field `", "unknown field `"]
+        .iter()
+        .find_map(|prefix| message.strip_prefix(prefix))
+        .and_then(|rest| rest.split('`').next());
+    let field = match (path, named) {
+        (Some(path), Some(name)) => Some(format!("{path}.{name}")),
+        (None, Some(name)) => Some(name.to_string()),
+        (path, None) => path.map(str::to_string),
+    };
+    (message, field)
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        let detail = rejection.body_text();
+        match rejection {
+            JsonRejection::JsonDataError(_) => {
+                let (message, field) = describe_data_error(&detail);
+                let error = invalid_body(message);
+                match field {
+                    Some(field) => error.with_field(field),
+                    None => error,
+                }
+            }
+            JsonRejection::JsonSyntaxError(_) => invalid_body(strip_position(
+                detail.strip_prefix(SYNTAX_ERROR_PREFIX).unwrap_or(&detail),
+            )),
+            _ => ApiError::new(rejection.status(), "INVALID_BODY", detail),
+        }
+    }
+}
+
+#[axum::async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json_content_type(req.headers()) {
+            return Err(ApiError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "INVALID_BODY",
+                "Expected request with `Content-Type: application/json`",
+            ));
+        }
+
+        let limit = LIMITS.json_bytes;
+        let bytes = axum::body::to_bytes(req.into_body(), limit)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("length limit") {
+                    ApiError::new(
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        "PAYLOAD_TOO_LARGE",
+                        format!("JSON body exceeds the limit of {limit} bytes"),
+                    )
+                } else {
+                    invalid_body(format!("Failed to read the request body: {e}"))
+                }
+            })?;
+
+        let axum::Json(value) = axum::Json::<T>::from_bytes(&bytes)?;
+        Ok(Self(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Item {
+        name: String,
+        quantity: u32,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Order {
+        customer: String,
+        items: Vec<Item>,
+    }
+
+    async fn extract(body: &str) -> Result<ApiJson<Order>, ApiError> {
+        let req = Request::builder()
+            .method("POST")
+            .uri("/orders")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        ApiJson::<Order>::from_request(req, &()).await
+    }
+
+    #[tokio::test]
+    async fn test_error_names_bad_field() {
+        let err = extract(r#"{"customer": "ana", "items": [{"name": "pen", "quantity": "ten"}]}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(err.code, "INVALID_BODY");
+        assert_eq!(err.field.as_deref(), Some("items[0].quantity"));
+        assert!(err.message.starts_with("invalid type: string \"ten\""));
+
+        let err = extract(r#"{"items": []}"#).await.unwrap_err();
+        assert_eq!(err.field.as_deref(), Some("customer"));
+        assert_eq!(err.message, "missing field `customer`");
+
+        let err = extract(r#"{"customer": "ana", "items": [}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code, "INVALID_BODY");
+        assert_eq!(err.field, None);
+    }
+
+    #[tokio::test]
+    async fn test_valid_body_is_extracted() {
+        let ApiJson(order) =
+            extract(r#"{"customer": "ana", "items": [{"name": "pen", "quantity": 2}]}"#)
+                .await
+                .unwrap();
+        assert_eq!(order.customer, "ana");
+        assert_eq!(order.items[0].quantity, 2);
+
+        let req = Request::builder()
+            .method("POST")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(Body::from("{}"))
+            .unwrap();
+        let err = ApiJson::<Order>::from_request(req, &()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
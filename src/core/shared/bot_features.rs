@@ -0,0 +1,438 @@
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{
+    extract::{Path, Request, State},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Nullable, Text, Uuid as DieselUuid};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Capabilities that can be switched per bot at runtime. A feature must also be compiled
+/// in; the flag only narrows what the build provides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BotFeature {
+    Calendar,
+    Email,
+    Kb,
+}
+
+impl BotFeature {
+    pub const ALL: [BotFeature; 3] = [BotFeature::Calendar, BotFeature::Email, BotFeature::Kb];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Calendar => "calendar",
+            Self::Email => "email",
+            Self::Kb => "kb",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.as_str() == value)
+    }
+
+    pub fn compiled_in(self) -> bool {
+        match self {
+            Self::Calendar => cfg!(feature = "calendar"),
+            Self::Email => cfg!(feature = "mail"),
+            Self::Kb => cfg!(feature = "vectordb"),
+        }
+    }
+}
+
+type Overrides = HashMap<BotFeature, bool>;
+
+static CACHE: LazyLock<RwLock<HashMap<Uuid, (Instant, Overrides)>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+fn cached(bot_id: Uuid) -> Option<Overrides> {
+    let cache = CACHE.read().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get(&bot_id)
+        .filter(|(at, _)| at.elapsed() < CACHE_TTL)
+        .map(|(_, overrides)| overrides.clone())
+}
+
+fn invalidate(bot_id: Uuid) {
+    CACHE
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&bot_id);
+}
+
+#[derive(QueryableByName)]
+struct FlagRow {
+    #[diesel(sql_type = Text)]
+    feature: String,
+    #[diesel(sql_type = Bool)]
+    enabled: bool,
+}
+
+pub fn load_overrides(conn: &mut PgConnection, bot_id: Uuid) -> QueryResult<Overrides> {
+    let rows: Vec<FlagRow> =
+        diesel::sql_query("SELECT feature, enabled FROM bot_features WHERE bot_id = $1")
+            .bind::<DieselUuid, _>(bot_id)
+            .load(conn)?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| BotFeature::parse(&row.feature).map(|f| (f, row.enabled)))
+        .collect())
+}
+
+pub fn set_override(
+    conn: &mut PgConnection,
+    bot_id: Uuid,
+    feature: BotFeature,
+    enabled: Option<bool>,
+    updated_by: Option<Uuid>,
+) -> QueryResult<()> {
+    match enabled {
+        Some(enabled) => {
+            diesel::sql_query(
+                "INSERT INTO bot_features (bot_id, feature, enabled, updated_by, updated_at)
+                 VALUES ($1, $2, $3, $4, NOW())
+                 ON CONFLICT (bot_id, feature)
+                 DO UPDATE SET enabled = $3, updated_by = $4, updated_at = NOW()",
+            )
+            .bind::<DieselUuid, _>(bot_id)
+            .bind::<Text, _>(feature.as_str())
+            .bind::<Bool, _>(enabled)
+            .bind::<Nullable<DieselUuid>, _>(updated_by)
+            .execute(conn)?;
+        }
+        None => {
+            diesel::sql_query("DELETE FROM bot_features WHERE bot_id = $1 AND feature = $2")
+                .bind::<DieselUuid, _>(bot_id)
+                .bind::<Text, _>(feature.as_str())
+                .execute(conn)?;
+        }
+    }
+    invalidate(bot_id);
+    Ok(())
+}
+
+fn effective(feature: BotFeature, overrides: &Overrides) -> bool {
+    feature.compiled_in() && overrides.get(&feature).copied().unwrap_or(true)
+}
+
+/// Features currently available to a bot with the given overrides.
+pub fn enabled_features(overrides: &Overrides) -> Vec<BotFeature> {
+    BotFeature::ALL
+        .into_iter()
+        .filter(|&feature| effective(feature, overrides))
+        .collect()
+}
+
+/// Whether `feature` is available to the bot. Lookup failures fall back to the default
+/// so a database hiccup does not switch features off.
+pub async fn is_enabled(state: &Arc<AppState>, bot_id: Uuid, feature: BotFeature) -> bool {
+    if !feature.compiled_in() {
+        return false;
+    }
+    if bot_id.is_nil() {
+        return true;
+    }
+    if let Some(overrides) = cached(bot_id) {
+        return effective(feature, &overrides);
+    }
+
+    let pool = state.conn.clone();
+    let loaded = tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        load_overrides(&mut conn, bot_id).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+
+    match loaded {
+        Ok(overrides) => {
+            let enabled = effective(feature, &overrides);
+            CACHE
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(bot_id, (Instant::now(), overrides));
+            enabled
+        }
+        Err(e) => {
+            warn!("Failed to load feature flags for bot {}: {}", bot_id, e);
+            true
+        }
+    }
+}
+
+pub(crate) fn bot_exists(conn: &mut PgConnection, bot_id: Uuid) -> QueryResult<bool> {
+    #[derive(QueryableByName)]
+    struct Exists {
+        #[diesel(sql_type = Bool)]
+        exists: bool,
+    }
+    diesel::sql_query(
+        "SELECT EXISTS (SELECT 1 FROM bots WHERE id = $1 AND deleted_at IS NULL) AS exists",
+    )
+    .bind::<DieselUuid, _>(bot_id)
+    .get_result::<Exists>(conn)
+    .map(|r| r.exists)
+}
+
+/// Route handler check: 404 if the bot does not exist, 403 if the feature is off for it.
+pub async fn ensure_enabled(
+    state: &Arc<AppState>,
+    bot_id: Uuid,
+    feature: BotFeature,
+) -> Result<(), ApiError> {
+    if !bot_id.is_nil() {
+        let pool = state.conn.clone();
+        let exists = tokio::task::spawn_blocking(move || {
+            let mut conn = pool
+                .get()
+                .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+            bot_exists(&mut conn, bot_id).map_err(|e| ApiError::internal(e.to_string()))
+        })
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))??;
+        if !exists {
+            return Err(ApiError::not_found(format!("Bot {} not found", bot_id)));
+        }
+    }
+
+    if is_enabled(state, bot_id, feature).await {
+        Ok(())
+    } else {
+        Err(ApiError::new(
+            axum::http::StatusCode::FORBIDDEN,
+            "feature_disabled",
+            format!("Feature '{}' is not enabled for this bot", feature.as_str()),
+        ))
+    }
+}
+
+fn latest_session_bot(conn: &mut PgConnection, user_id: Uuid) -> QueryResult<Option<Uuid>> {
+    #[derive(QueryableByName)]
+    struct SessionBot {
+        #[diesel(sql_type = DieselUuid)]
+        bot_id: Uuid,
+    }
+    diesel::sql_query(
+        "SELECT bot_id FROM user_sessions
+         WHERE user_id = $1 AND deleted_at IS NULL
+         ORDER BY updated_at DESC LIMIT 1",
+    )
+    .bind::<DieselUuid, _>(user_id)
+    .get_result::<SessionBot>(conn)
+    .optional()
+    .map(|row| row.map(|r| r.bot_id))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionBot {
+    /// The caller's token is bound to this bot.
+    Bound(Uuid),
+    /// The bot of this user's latest conversation.
+    LatestOf(Uuid),
+}
+
+fn session_bot(req: &Request) -> Option<SessionBot> {
+    let user = req.extensions().get::<AuthenticatedUser>()?;
+    match user.current_bot_id {
+        Some(bot_id) => Some(SessionBot::Bound(bot_id)),
+        None if user.user_id.is_nil() => None,
+        None => Some(SessionBot::LatestOf(user.user_id)),
+    }
+}
+
+/// Bot a request acts for, taken from the caller's session and never from the request
+/// itself, so a client cannot pick a bot with more features switched on: the bot the
+/// caller's token is bound to, otherwise the bot of their latest conversation. `None`
+/// for callers without either.
+pub async fn session_bot_id(
+    state: &Arc<AppState>,
+    req: &Request,
+) -> Result<Option<Uuid>, ApiError> {
+    let user_id = match session_bot(req) {
+        Some(SessionBot::Bound(bot_id)) => return Ok(Some(bot_id)),
+        Some(SessionBot::LatestOf(user_id)) => user_id,
+        None => return Ok(None),
+    };
+
+    let pool = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        latest_session_bot(&mut conn, user_id).map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+}
+
+/// Rejects email requests for bots that have the email feature switched off. Requests
+/// without a session bot, such as tracking pixels, follow the defaults.
+pub async fn require_email_feature(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let bot_id = session_bot_id(&state, &req)
+        .await?
+        .unwrap_or_else(Uuid::nil);
+    ensure_enabled(&state, bot_id, BotFeature::Email).await?;
+    Ok(next.run(req).await)
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeatureState {
+    pub feature: BotFeature,
+    pub enabled: bool,
+    pub compiled_in: bool,
+    /// Explicit per-bot setting; `None` means the default applies.
+    pub override_value: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BotFeaturesResponse {
+    pub bot_id: Uuid,
+    pub features: Vec<FeatureState>,
+}
+
+/// Feature name to `true`/`false`, or `null` to drop the override.
+#[derive(Debug, Deserialize)]
+pub struct UpdateBotFeaturesRequest {
+    pub features: HashMap<String, Option<bool>>,
+}
+
+fn describe(bot_id: Uuid, overrides: &Overrides) -> BotFeaturesResponse {
+    BotFeaturesResponse {
+        bot_id,
+        features: BotFeature::ALL
+            .into_iter()
+            .map(|feature| FeatureState {
+                feature,
+                enabled: effective(feature, overrides),
+                compiled_in: feature.compiled_in(),
+                override_value: overrides.get(&feature).copied(),
+            })
+            .collect(),
+    }
+}
+
+pub async fn handle_get_bot_features(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(bot_id): Path<Uuid>,
+) -> Result<Json<BotFeaturesResponse>, ApiError> {
+    require_admin(&user)?;
+
+    let pool = state.conn.clone();
+    let overrides = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        if !bot_exists(&mut conn, bot_id).map_err(|e| ApiError::internal(e.to_string()))? {
+            return Err(ApiError::not_found(format!("Bot {} not found", bot_id)));
+        }
+        load_overrides(&mut conn, bot_id).map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??;
+
+    Ok(Json(describe(bot_id, &overrides)))
+}
+
+pub async fn handle_update_bot_features(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(bot_id): Path<Uuid>,
+    ApiJson(req): ApiJson<UpdateBotFeaturesRequest>,
+) -> Result<Json<BotFeaturesResponse>, ApiError> {
+    require_admin(&user)?;
+
+    let updates = req
+        .features
+        .into_iter()
+        .map(|(name, enabled)| {
+            BotFeature::parse(&name)
+                .map(|feature| (feature, enabled))
+                .ok_or_else(|| ApiError::bad_request(format!("Unknown feature '{}'", name)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let pool = state.conn.clone();
+    let updated_by = Some(user.user_id);
+    let overrides = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        if !bot_exists(&mut conn, bot_id).map_err(|e| ApiError::internal(e.to_string()))? {
+            return Err(ApiError::not_found(format!("Bot {} not found", bot_id)));
+        }
+        conn.transaction::<_, diesel::result::Error, _>(|tx| {
+            for (feature, enabled) in &updates {
+                set_override(tx, bot_id, *feature, *enabled, updated_by)?;
+            }
+            load_overrides(tx, bot_id)
+        })
+        .map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))??;
+    invalidate(bot_id);
+
+    info!("Updated feature flags for bot {} (by {})", bot_id, user.user_id);
+    Ok(Json(describe(bot_id, &overrides)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_names_round_trip() {
+        for feature in BotFeature::ALL {
+            assert_eq!(BotFeature::parse(feature.as_str()), Some(feature));
+        }
+        assert_eq!(BotFeature::parse("teleport"), None);
+    }
+
+    #[test]
+    fn test_effective_defaults_to_compiled_in() {
+        let none = Overrides::new();
+        let off = Overrides::from([(BotFeature::Calendar, false)]);
+        assert_eq!(effective(BotFeature::Calendar, &none), BotFeature::Calendar.compiled_in());
+        assert!(!effective(BotFeature::Calendar, &off));
+    }
+
+    #[test]
+    fn test_session_bot_ignores_client_supplied_bot() {
+        let mut req = Request::builder()
+            .uri(format!("/api/calendar/events?bot_id={}", Uuid::new_v4()))
+            .header("x-bot-id", Uuid::new_v4().to_string())
+            .body(axum::body::Body::empty())
+            .unwrap();
+        assert_eq!(session_bot(&req), None);
+
+        let user_id = Uuid::new_v4();
+        req.extensions_mut()
+            .insert(AuthenticatedUser::new(user_id, "ana".to_string()));
+        assert_eq!(session_bot(&req), Some(SessionBot::LatestOf(user_id)));
+
+        let bound = Uuid::new_v4();
+        req.extensions_mut()
+            .insert(AuthenticatedUser::new(user_id, "ana".to_string()).with_current_bot(bound));
+        assert_eq!(session_bot(&req), Some(SessionBot::Bound(bound)));
+    }
+}
@@ -0,0 +1,336 @@
+//! Circuit breaker for optional backends such as Redis.
+//!
+//! After `failure_threshold` consecutive failures the breaker opens and callers skip the
+//! backend entirely, so an outage costs no latency. While open, a background task (or,
+//! without one, a single caller once the cooldown has passed) probes the backend and
+//! closes the breaker when it answers again. Transitions are logged once; individual
+//! failures only at debug level.
+
+use log::{debug, info, warn};
+use std::fmt::Display;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+const REDIS_FAILURE_THRESHOLD: u32 = 3;
+const REDIS_COOLDOWN: Duration = Duration::from_secs(15);
+#[cfg(feature = "cache")]
+const REDIS_CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Breaker shared by every Redis-backed path: they all talk to the same server.
+pub static REDIS_BREAKER: LazyLock<Arc<CircuitBreaker>> = LazyLock::new(|| {
+    Arc::new(CircuitBreaker::new("Redis", REDIS_FAILURE_THRESHOLD, REDIS_COOLDOWN))
+});
+
+#[derive(Default)]
+struct BreakerState {
+    failures: u32,
+    open_until: Option<Instant>,
+    probing: bool,
+    reconnecting: bool,
+}
+
+pub struct CircuitBreaker {
+    name: &'static str,
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl std::fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("name", &self.name)
+            .field("open", &self.is_open())
+            .finish()
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &'static str, failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            name,
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.lock().open_until.is_some()
+    }
+
+    /// Whether the backend should be tried. While open this is false, except for one
+    /// probing caller per cooldown when no background reconnect is running.
+    pub fn allow(&self) -> bool {
+        self.allow_at(Instant::now())
+    }
+
+    fn allow_at(&self, now: Instant) -> bool {
+        let mut state = self.lock();
+        match state.open_until {
+            None => true,
+            Some(until) if now < until || state.probing || state.reconnecting => false,
+            Some(_) => {
+                state.probing = true;
+                true
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.lock();
+        if state.open_until.is_some() {
+            info!("{} is reachable again, resuming normal operation", self.name);
+        }
+        *state = BreakerState::default();
+    }
+
+    pub fn record_failure(&self, error: impl Display) {
+        self.record_failure_at(error, Instant::now());
+    }
+
+    fn record_failure_at(&self, error: impl Display, now: Instant) {
+        let mut state = self.lock();
+        state.failures = state.failures.saturating_add(1);
+        if state.open_until.is_some() {
+            state.open_until = Some(now + self.cooldown);
+            state.probing = false;
+            debug!("{} still unavailable: {}", self.name, error);
+        } else if state.failures >= self.failure_threshold {
+            state.open_until = Some(now + self.cooldown);
+            warn!(
+                "{} unavailable after {} consecutive failures, bypassing it until it recovers: {}",
+                self.name, state.failures, error
+            );
+        } else {
+            debug!("{} call failed ({}): {}", self.name, state.failures, error);
+        }
+    }
+
+    /// Claims the probe slot for a background reconnect loop. Returns false when the
+    /// breaker is closed or another probe is already running.
+    fn begin_background_probe(&self) -> bool {
+        let mut state = self.lock();
+        if state.open_until.is_none() || state.reconnecting {
+            return false;
+        }
+        state.reconnecting = true;
+        true
+    }
+
+    fn end_background_probe(&self) {
+        self.lock().reconnecting = false;
+    }
+}
+
+/// Whether a Redis error means the server is unreachable, as opposed to e.g. a missing
+/// key or a type mismatch.
+#[cfg(feature = "cache")]
+pub fn is_redis_outage(error: &redis::RedisError) -> bool {
+    error.is_io_error()
+        || error.is_connection_dropped()
+        || error.is_connection_refusal()
+        || error.is_timeout()
+}
+
+/// Opens an async Redis connection unless the breaker is open, recording the outcome.
+#[cfg(feature = "cache")]
+pub async fn redis_connection(
+    client: &Arc<redis::Client>,
+    breaker: &Arc<CircuitBreaker>,
+) -> Option<redis::aio::MultiplexedConnection> {
+    if !breaker.allow() {
+        return None;
+    }
+    let result =
+        tokio::time::timeout(REDIS_CONNECT_TIMEOUT, client.get_multiplexed_async_connection())
+            .await;
+    match result {
+        Ok(Ok(conn)) => {
+            breaker.record_success();
+            Some(conn)
+        }
+        Ok(Err(e)) => {
+            breaker.record_failure(&e);
+            spawn_redis_reconnect(client.clone(), breaker.clone());
+            None
+        }
+        Err(_) => {
+            breaker.record_failure("connection timed out");
+            spawn_redis_reconnect(client.clone(), breaker.clone());
+            None
+        }
+    }
+}
+
+/// Blocking counterpart of [`redis_connection`].
+#[cfg(feature = "cache")]
+pub fn redis_connection_blocking(
+    client: &Arc<redis::Client>,
+    breaker: &Arc<CircuitBreaker>,
+) -> Option<redis::Connection> {
+    if !breaker.allow() {
+        return None;
+    }
+    match client.get_connection_with_timeout(REDIS_CONNECT_TIMEOUT) {
+        Ok(conn) => {
+            breaker.record_success();
+            Some(conn)
+        }
+        Err(e) => {
+            breaker.record_failure(&e);
+            spawn_redis_reconnect(client.clone(), breaker.clone());
+            None
+        }
+    }
+}
+
+/// Records the outcome of a Redis command; only outages count as failures.
+#[cfg(feature = "cache")]
+pub fn note_redis_result<T>(breaker: &CircuitBreaker, result: &redis::RedisResult<T>) {
+    if let Err(e) = result {
+        if is_redis_outage(e) {
+            breaker.record_failure(e);
+        }
+    }
+}
+
+/// Once the breaker is open, pings Redis every cooldown until it answers, so requests
+/// never wait on a dead server. Does nothing outside a Tokio runtime or when a reconnect
+/// loop is already running.
+#[cfg(feature = "cache")]
+pub fn spawn_redis_reconnect(client: Arc<redis::Client>, breaker: Arc<CircuitBreaker>) {
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    if !breaker.begin_background_probe() {
+        return;
+    }
+    handle.spawn(async move {
+        loop {
+            tokio::time::sleep(breaker.cooldown()).await;
+            let ping = async {
+                let mut conn = client.get_multiplexed_async_connection().await?;
+                let pong: redis::RedisResult<String> = redis::cmd("PING").query_async(&mut conn).await;
+                pong
+            };
+            match tokio::time::timeout(REDIS_CONNECT_TIMEOUT, ping).await {
+                Ok(Ok(_)) => {
+                    breaker.record_success();
+                    return;
+                }
+                Ok(Err(e)) => debug!("Redis reconnect attempt failed: {}", e),
+                Err(_) => debug!("Redis reconnect attempt timed out"),
+            }
+            if !breaker.is_open() {
+                breaker.end_background_probe();
+                return;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Stand-in for a Redis-backed read with a fallback to the underlying source.
+    struct Backend {
+        up: AtomicBool,
+        calls: AtomicUsize,
+    }
+
+    impl Backend {
+        fn read(&self, breaker: &CircuitBreaker, now: Instant) -> &'static str {
+            if !breaker.allow_at(now) {
+                return "fallback";
+            }
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.up.load(Ordering::SeqCst) {
+                breaker.record_success();
+                "cached"
+            } else {
+                breaker.record_failure_at("connection refused", now);
+                "fallback"
+            }
+        }
+    }
+
+    #[test]
+    fn test_redis_dropping_mid_run() {
+        let breaker = CircuitBreaker::new("Redis", 3, Duration::from_secs(10));
+        let backend = Backend {
+            up: AtomicBool::new(true),
+            calls: AtomicUsize::new(0),
+        };
+        let start = Instant::now();
+
+        assert_eq!(backend.read(&breaker, start), "cached");
+        assert_eq!(backend.read(&breaker, start), "cached");
+
+        backend.up.store(false, Ordering::SeqCst);
+        for _ in 0..3 {
+            assert_eq!(backend.read(&breaker, start), "fallback");
+        }
+        assert!(breaker.is_open());
+        let calls_when_opened = backend.calls.load(Ordering::SeqCst);
+
+        // Requests during the outage are served without touching the backend.
+        for _ in 0..50 {
+            assert_eq!(backend.read(&breaker, start + Duration::from_secs(1)), "fallback");
+        }
+        assert_eq!(backend.calls.load(Ordering::SeqCst), calls_when_opened);
+
+        // After the cooldown a single probe goes through; it fails and re-opens.
+        let later = start + Duration::from_secs(11);
+        assert_eq!(backend.read(&breaker, later), "fallback");
+        assert_eq!(backend.read(&breaker, later), "fallback");
+        assert_eq!(backend.calls.load(Ordering::SeqCst), calls_when_opened + 1);
+
+        // Redis comes back: the next probe closes the breaker.
+        backend.up.store(true, Ordering::SeqCst);
+        let recovered = later + Duration::from_secs(11);
+        assert_eq!(backend.read(&breaker, recovered), "cached");
+        assert!(!breaker.is_open());
+        assert_eq!(backend.read(&breaker, recovered), "cached");
+    }
+
+    #[test]
+    fn test_isolated_failures_do_not_open() {
+        let breaker = CircuitBreaker::new("Redis", 3, Duration::from_secs(10));
+        let now = Instant::now();
+
+        breaker.record_failure_at("timeout", now);
+        breaker.record_failure_at("timeout", now);
+        breaker.record_success();
+        breaker.record_failure_at("timeout", now);
+        breaker.record_failure_at("timeout", now);
+
+        assert!(!breaker.is_open());
+        assert!(breaker.allow_at(now));
+    }
+
+    #[test]
+    fn test_background_probe_blocks_request_probes() {
+        let breaker = CircuitBreaker::new("Redis", 1, Duration::from_secs(10));
+        let now = Instant::now();
+
+        assert!(!breaker.begin_background_probe());
+        breaker.record_failure_at("refused", now);
+        assert!(breaker.begin_background_probe());
+        assert!(!breaker.begin_background_probe());
+        assert!(!breaker.allow_at(now + Duration::from_secs(60)));
+
+        breaker.record_success();
+        assert!(breaker.allow_at(now));
+    }
+}
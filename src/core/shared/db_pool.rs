@@ -0,0 +1,180 @@
+use crate::core::config::DatabasePoolConfig;
+use crate::core::shared::utils::DbPool;
+use diesel::r2d2::event::{CheckoutEvent, TimeoutEvent};
+use diesel::r2d2::{Builder, ConnectionManager, HandleEvent, Pool};
+use diesel::PgConnection;
+use log::warn;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Checkout wait statistics shared between the pool event handler and the readiness probe.
+#[derive(Debug, Default)]
+pub struct PoolWaitStats {
+    slow_checkouts: AtomicU64,
+    timeouts: AtomicU64,
+    max_wait_ms: AtomicU64,
+}
+
+#[derive(Debug)]
+struct SaturationLogger {
+    warn_after: Duration,
+    stats: Arc<PoolWaitStats>,
+}
+
+impl HandleEvent for SaturationLogger {
+    fn handle_checkout(&self, event: CheckoutEvent) {
+        let waited = event.duration();
+        let waited_ms = waited.as_millis() as u64;
+        self.stats.max_wait_ms.fetch_max(waited_ms, Ordering::Relaxed);
+
+        if waited >= self.warn_after {
+            self.stats.slow_checkouts.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "Database pool saturated: checkout waited {}ms (threshold {}ms)",
+                waited_ms,
+                self.warn_after.as_millis()
+            );
+        }
+    }
+
+    fn handle_timeout(&self, event: TimeoutEvent) {
+        self.stats.timeouts.fetch_add(1, Ordering::Relaxed);
+        warn!(
+            "Database pool exhausted: checkout timed out after {}ms",
+            event.timeout().as_millis()
+        );
+    }
+}
+
+static PRIMARY_POOL_STATS: std::sync::OnceLock<Arc<PoolWaitStats>> = std::sync::OnceLock::new();
+
+pub fn primary_pool_stats() -> Arc<PoolWaitStats> {
+    PRIMARY_POOL_STATS
+        .get_or_init(|| Arc::new(PoolWaitStats::default()))
+        .clone()
+}
+
+static REPLICA_POOL_STATS: std::sync::OnceLock<Arc<PoolWaitStats>> = std::sync::OnceLock::new();
+
+pub fn replica_pool_stats() -> Arc<PoolWaitStats> {
+    REPLICA_POOL_STATS
+        .get_or_init(|| Arc::new(PoolWaitStats::default()))
+        .clone()
+}
+
+/// Whether a query only reads data. Only `Read` queries may go to the replica, and only
+/// when a little replication lag is acceptable to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryKind {
+    Read,
+    Write,
+}
+
+/// Picks the pool for a query: reads go to the replica when one is configured,
+/// everything else stays on the primary.
+pub fn route<'a, P>(primary: &'a P, replica: Option<&'a P>, kind: QueryKind) -> &'a P {
+    match (kind, replica) {
+        (QueryKind::Read, Some(replica)) => replica,
+        _ => primary,
+    }
+}
+
+pub fn build_pool(
+    database_url: &str,
+    config: &DatabasePoolConfig,
+    stats: Arc<PoolWaitStats>,
+) -> Result<DbPool, anyhow::Error> {
+    pool_builder(config, stats)
+        .build(ConnectionManager::<PgConnection>::new(database_url))
+        .map_err(|e| anyhow::anyhow!("Failed to create database pool: {}", e))
+}
+
+fn pool_builder(
+    config: &DatabasePoolConfig,
+    stats: Arc<PoolWaitStats>,
+) -> Builder<ConnectionManager<PgConnection>> {
+    let min_idle = config.min_idle.map(|n| n.min(config.max_size));
+
+    Pool::builder()
+        .max_size(config.max_size)
+        .min_idle(min_idle)
+        .connection_timeout(Duration::from_secs(config.connection_timeout_secs))
+        .idle_timeout(Some(Duration::from_secs(config.idle_timeout_secs)))
+        .max_lifetime(Some(Duration::from_secs(config.max_lifetime_secs)))
+        .event_handler(Box::new(SaturationLogger {
+            warn_after: Duration::from_millis(config.checkout_warn_ms),
+            stats,
+        }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoolStatus {
+    pub max_size: u32,
+    pub connections: u32,
+    pub idle: u32,
+    pub in_use: u32,
+    pub saturated: bool,
+    pub slow_checkouts: u64,
+    pub timeouts: u64,
+    pub max_wait_ms: u64,
+}
+
+pub fn pool_status(pool: &DbPool, stats: &PoolWaitStats) -> PoolStatus {
+    let state = pool.state();
+    let max_size = pool.max_size();
+    let in_use = state.connections.saturating_sub(state.idle_connections);
+
+    PoolStatus {
+        max_size,
+        connections: state.connections,
+        idle: state.idle_connections,
+        in_use,
+        saturated: in_use >= max_size,
+        slow_checkouts: stats.slow_checkouts.load(Ordering::Relaxed),
+        timeouts: stats.timeouts.load(Ordering::Relaxed),
+        max_wait_ms: stats.max_wait_ms.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_reads_to_replica() {
+        let primary = "primary";
+        let replica = "replica";
+        assert_eq!(*route(&primary, Some(&replica), QueryKind::Read), "replica");
+        assert_eq!(*route(&primary, Some(&replica), QueryKind::Write), "primary");
+    }
+
+    #[test]
+    fn test_route_falls_back_to_primary_without_replica() {
+        let primary = "primary";
+        assert_eq!(*route(&primary, None, QueryKind::Read), "primary");
+        assert_eq!(*route(&primary, None, QueryKind::Write), "primary");
+    }
+
+    #[test]
+    fn test_pool_uses_configured_sizing() {
+        let config = DatabasePoolConfig {
+            max_size: 4,
+            min_idle: Some(9),
+            connection_timeout_secs: 7,
+            idle_timeout_secs: 60,
+            max_lifetime_secs: 600,
+            checkout_warn_ms: 100,
+        };
+        let pool = pool_builder(&config, Arc::new(PoolWaitStats::default()))
+            .build_unchecked(ConnectionManager::new("postgres://127.0.0.1:1/unused"));
+
+        assert_eq!(pool.max_size(), 4);
+        // min_idle never exceeds the pool size.
+        assert_eq!(pool.min_idle(), Some(4));
+        assert_eq!(pool.connection_timeout(), Duration::from_secs(7));
+        assert_eq!(pool.idle_timeout(), Some(Duration::from_secs(60)));
+        assert_eq!(pool.max_lifetime(), Some(Duration::from_secs(600)));
+    }
+}
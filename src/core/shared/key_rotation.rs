@@ -0,0 +1,391 @@
+use crate::core::secrets::{SecretPaths, SecretsManager};
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::shared::state::AppState;
+use crate::core::shared::utils::DbPool;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::security::master_key::{
+    self, reencrypt_column, CipherColumn, CipherStore, MasterKeyring, ReencryptStats,
+    CIPHER_COLUMNS,
+};
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Integer, Nullable, Text, Timestamptz, Uuid as DieselUuid};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const BATCH_SIZE: i64 = 200;
+
+static ROTATION_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize, QueryableByName)]
+pub struct RotationStatus {
+    #[diesel(sql_type = DieselUuid)]
+    pub id: Uuid,
+    #[diesel(sql_type = Integer)]
+    pub from_version: i32,
+    #[diesel(sql_type = Integer)]
+    pub to_version: i32,
+    #[diesel(sql_type = Text)]
+    pub status: String,
+    #[diesel(sql_type = BigInt)]
+    pub total: i64,
+    #[diesel(sql_type = BigInt)]
+    pub processed: i64,
+    #[diesel(sql_type = BigInt)]
+    pub skipped: i64,
+    #[diesel(sql_type = BigInt)]
+    pub failed: i64,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub last_error: Option<String>,
+    #[diesel(sql_type = Timestamptz)]
+    pub started_at: DateTime<Utc>,
+    #[diesel(sql_type = Timestamptz)]
+    pub updated_at: DateTime<Utc>,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+const STATUS_COLUMNS: &str = "id, from_version, to_version, status, total, processed, skipped, \
+     failed, last_error, started_at, updated_at, finished_at";
+
+fn latest_rotation(conn: &mut PgConnection) -> QueryResult<Option<RotationStatus>> {
+    diesel::sql_query(format!(
+        "SELECT {} FROM encryption_key_rotations ORDER BY started_at DESC LIMIT 1",
+        STATUS_COLUMNS
+    ))
+    .get_result(conn)
+    .optional()
+}
+
+fn count_pending(conn: &mut PgConnection, target_version: u32) -> QueryResult<i64> {
+    #[derive(QueryableByName)]
+    struct Count {
+        #[diesel(sql_type = BigInt)]
+        count: i64,
+    }
+    let mut total = 0;
+    for column in CIPHER_COLUMNS {
+        let sql = format!(
+            "SELECT COUNT(*) AS count FROM {table}
+             WHERE {col} IS NOT NULL AND {col} NOT LIKE $1 AND {col} <> ALL($2)",
+            table = column.table,
+            col = column.column
+        );
+        total += diesel::sql_query(sql)
+            .bind::<Text, _>(format!("mk{}:%", target_version))
+            .bind::<Array<Text>, _>(column.markers)
+            .get_result::<Count>(conn)?
+            .count;
+    }
+    Ok(total)
+}
+
+struct DbCipherStore<'a> {
+    conn: &'a mut PgConnection,
+}
+
+impl CipherStore for DbCipherStore<'_> {
+    fn pending(
+        &mut self,
+        column: &CipherColumn,
+        target_version: u32,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> anyhow::Result<Vec<(Uuid, String)>> {
+        #[derive(QueryableByName)]
+        struct Row {
+            #[diesel(sql_type = DieselUuid)]
+            id: Uuid,
+            #[diesel(sql_type = Text)]
+            value: String,
+        }
+        let sql = format!(
+            "SELECT id, {col} AS value FROM {table}
+             WHERE {col} IS NOT NULL AND {col} NOT LIKE $1 AND {col} <> ALL($4)
+               AND ($2::uuid IS NULL OR id > $2)
+             ORDER BY id LIMIT $3",
+            table = column.table,
+            col = column.column
+        );
+        let rows: Vec<Row> = diesel::sql_query(sql)
+            .bind::<Text, _>(format!("mk{}:%", target_version))
+            .bind::<Nullable<DieselUuid>, _>(after)
+            .bind::<BigInt, _>(limit)
+            .bind::<Array<Text>, _>(column.markers)
+            .load(&mut *self.conn)?;
+        Ok(rows.into_iter().map(|r| (r.id, r.value)).collect())
+    }
+
+    fn replace(
+        &mut self,
+        column: &CipherColumn,
+        id: Uuid,
+        old: &str,
+        new: &str,
+    ) -> anyhow::Result<bool> {
+        let sql = format!(
+            "UPDATE {table} SET {col} = $3 WHERE id = $1 AND {col} = $2",
+            table = column.table,
+            col = column.column
+        );
+        let updated = diesel::sql_query(sql)
+            .bind::<DieselUuid, _>(id)
+            .bind::<Text, _>(old)
+            .bind::<Text, _>(new)
+            .execute(&mut *self.conn)?;
+        Ok(updated > 0)
+    }
+}
+
+fn record_progress(
+    conn: &mut PgConnection,
+    rotation_id: Uuid,
+    stats: &ReencryptStats,
+    last_error: Option<&str>,
+) -> QueryResult<usize> {
+    diesel::sql_query(
+        "UPDATE encryption_key_rotations
+         SET processed = $2, skipped = $3, failed = $4,
+             last_error = COALESCE($5, last_error), updated_at = NOW()
+         WHERE id = $1",
+    )
+    .bind::<DieselUuid, _>(rotation_id)
+    .bind::<BigInt, _>(stats.processed)
+    .bind::<BigInt, _>(stats.skipped)
+    .bind::<BigInt, _>(stats.failed)
+    .bind::<Nullable<Text>, _>(last_error)
+    .execute(conn)
+}
+
+fn finish(conn: &mut PgConnection, rotation_id: Uuid, status: &str, error: Option<&str>) {
+    let result = diesel::sql_query(
+        "UPDATE encryption_key_rotations
+         SET status = $2, last_error = COALESCE($3, last_error),
+             updated_at = NOW(), finished_at = NOW()
+         WHERE id = $1",
+    )
+    .bind::<DieselUuid, _>(rotation_id)
+    .bind::<Text, _>(status)
+    .bind::<Nullable<Text>, _>(error)
+    .execute(conn);
+    if let Err(e) = result {
+        error!("Failed to record key rotation {} as {}: {}", rotation_id, status, e);
+    }
+}
+
+/// Re-encrypts every cipher column under the keyring's current version, recording
+/// progress after each batch on a second connection. Returns the totals across all columns.
+fn run_reencryption(
+    pool: &DbPool,
+    keyring: &MasterKeyring,
+    rotation_id: Uuid,
+) -> anyhow::Result<ReencryptStats> {
+    let mut conn = pool.get()?;
+    let mut progress_conn = pool.get()?;
+    let mut done = ReencryptStats::default();
+
+    for column in CIPHER_COLUMNS {
+        let base = done;
+        let mut store = DbCipherStore { conn: &mut conn };
+        let stats = reencrypt_column(&mut store, keyring, column, BATCH_SIZE, |batch, err| {
+            let err = err.map(|e| e.to_string());
+            if let Err(e) =
+                record_progress(&mut progress_conn, rotation_id, &(base + *batch), err.as_deref())
+            {
+                warn!("Failed to record key rotation progress: {}", e);
+            }
+        })?;
+        done = base + stats;
+        info!(
+            "Re-encrypted {}.{}: {} updated, {} skipped, {} failed",
+            column.table, column.column, stats.processed, stats.skipped, stats.failed
+        );
+    }
+
+    record_progress(&mut progress_conn, rotation_id, &done, None)?;
+    Ok(done)
+}
+
+async fn complete_rotation(
+    pool: DbPool,
+    secrets: SecretsManager,
+    mut keyring: MasterKeyring,
+    rotation_id: Uuid,
+) {
+    let worker_keyring = keyring.clone();
+    let worker_pool = pool.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        run_reencryption(&worker_pool, &worker_keyring, rotation_id)
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("Re-encryption task failed: {}", e))
+    .and_then(|r| r);
+
+    let outcome = match result {
+        Ok(stats) if stats.failed == 0 => {
+            let retired = keyring.retire_old_versions();
+            match secrets.put_secret(SecretPaths::ENCRYPTION, keyring.to_secrets()).await {
+                Ok(()) => {
+                    master_key::install(keyring.clone());
+                    info!(
+                        "Key rotation {} complete: {} values re-encrypted, retired versions {:?}",
+                        rotation_id, stats.processed, retired
+                    );
+                    ("completed", None)
+                }
+                Err(e) => ("failed", Some(format!("Failed to retire old key versions: {}", e))),
+            }
+        }
+        Ok(stats) => (
+            "failed",
+            Some(format!(
+                "{} values could not be re-encrypted; old key versions kept",
+                stats.failed
+            )),
+        ),
+        Err(e) => ("failed", Some(e.to_string())),
+    };
+
+    if let Some(e) = &outcome.1 {
+        warn!("Key rotation {} stopped: {}", rotation_id, e);
+    }
+    let status = outcome.0;
+    let error = outcome.1;
+    let _ = tokio::task::spawn_blocking(move || {
+        if let Ok(mut conn) = pool.get() {
+            finish(&mut conn, rotation_id, status, error.as_deref());
+        }
+    })
+    .await;
+    ROTATION_ACTIVE.store(false, Ordering::SeqCst);
+}
+
+async fn with_conn<T, F>(state: &Arc<AppState>, f: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> QueryResult<T> + Send + 'static,
+{
+    let pool = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        f(&mut conn).map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+}
+
+/// Starts a master key rotation, or resumes the last one if it did not complete. The new
+/// key version is written to Vault before any value is touched and becomes the key for
+/// new writes; old versions are removed only after every value has been re-encrypted.
+pub async fn handle_rotate_master_key(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+) -> Result<(StatusCode, Json<RotationStatus>), ApiError> {
+    require_admin(&user)?;
+
+    if ROTATION_ACTIVE.swap(true, Ordering::SeqCst) {
+        return Err(ApiError::conflict("A key rotation is already in progress"));
+    }
+    let started = start_or_resume(&state, user.user_id).await;
+    if started.is_err() {
+        ROTATION_ACTIVE.store(false, Ordering::SeqCst);
+    }
+    let (status, keyring, secrets) = started?;
+
+    tokio::spawn(complete_rotation(state.conn.clone(), secrets, keyring, status.id));
+    Ok((StatusCode::ACCEPTED, Json(status)))
+}
+
+async fn start_or_resume(
+    state: &Arc<AppState>,
+    user_id: Uuid,
+) -> Result<(RotationStatus, MasterKeyring, SecretsManager), ApiError> {
+    let secrets = SecretsManager::get_clone()
+        .map_err(|e| ApiError::service_unavailable(format!("Vault unavailable: {}", e)))?;
+    secrets.clear_cache().await;
+    let mut keyring = master_key::load_from_vault(&secrets)
+        .await
+        .map_err(|e| ApiError::service_unavailable(format!("Master key unavailable: {}", e)))?;
+
+    let previous = with_conn(state, latest_rotation).await?;
+    let resumable = previous.filter(|r| {
+        r.status != "completed" && r.to_version as u32 == keyring.current_version()
+    });
+
+    let rotation_id = match resumable {
+        Some(rotation) => {
+            info!(
+                "Resuming key rotation {} to version {}",
+                rotation.id, rotation.to_version
+            );
+            let id = rotation.id;
+            with_conn(state, move |conn| {
+                diesel::sql_query(
+                    "UPDATE encryption_key_rotations
+                     SET status = 'running', finished_at = NULL, updated_at = NOW()
+                     WHERE id = $1",
+                )
+                .bind::<DieselUuid, _>(id)
+                .execute(conn)
+            })
+            .await?;
+            id
+        }
+        None => {
+            let from_version = keyring.current_version();
+            let to_version = keyring.add_version();
+            secrets
+                .put_secret(SecretPaths::ENCRYPTION, keyring.to_secrets())
+                .await
+                .map_err(|e| ApiError::service_unavailable(format!("Failed to store new key: {}", e)))?;
+            info!(
+                "Master key version {} created (previous {} kept until re-encryption completes)",
+                to_version, from_version
+            );
+
+            with_conn(state, move |conn| {
+                let total = count_pending(conn, to_version)?;
+                #[derive(QueryableByName)]
+                struct Inserted {
+                    #[diesel(sql_type = DieselUuid)]
+                    id: Uuid,
+                }
+                diesel::sql_query(
+                    "INSERT INTO encryption_key_rotations (from_version, to_version, total, started_by)
+                     VALUES ($1, $2, $3, $4) RETURNING id",
+                )
+                .bind::<Integer, _>(from_version as i32)
+                .bind::<Integer, _>(to_version as i32)
+                .bind::<BigInt, _>(total)
+                .bind::<Nullable<DieselUuid>, _>(Some(user_id))
+                .get_result::<Inserted>(conn)
+                .map(|r| r.id)
+            })
+            .await?
+        }
+    };
+
+    master_key::install(keyring.clone());
+
+    let status = with_conn(state, latest_rotation)
+        .await?
+        .filter(|r| r.id == rotation_id)
+        .ok_or_else(|| ApiError::internal("Key rotation record missing"))?;
+    Ok((status, keyring, secrets))
+}
+
+pub async fn handle_rotation_status(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+) -> Result<Json<RotationStatus>, ApiError> {
+    require_admin(&user)?;
+    with_conn(&state, latest_rotation)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("No key rotation has been started"))
+}
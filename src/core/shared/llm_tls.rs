@@ -0,0 +1,345 @@
+//! TLS for the local LLM and embedding servers.
+//!
+//! The bootstrap starts the local LLM and embedding servers with certificates issued by
+//! the stack CA, and issues botserver a client certificate. [`LlmTlsConfig`] decides
+//! whether the clients built by [`llm_client_builder`] use them:
+//!
+//! - `off` (default): plain HTTP is allowed, as in development.
+//! - `tls`: HTTPS only, with the stack CA trusted on top of the system roots.
+//! - `mtls`: as `tls`, and the client certificate is presented to servers that ask.
+//!
+//! Only the stack's own servers are covered: loopback hosts and those listed in
+//! `LLM_TLS_HOSTS`. The same clients also reach remote providers, which keep plain
+//! HTTPS with the system roots.
+
+use crate::core::shared::outbound_proxy::client_builder;
+use crate::core::shared::utils::{ca_cert_path, get_stack_path};
+use log::{debug, error, warn};
+use reqwest::{Certificate, Client, ClientBuilder, Identity};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+static LLM_TLS: LazyLock<LlmTlsConfig> = LazyLock::new(LlmTlsConfig::from_env);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LlmTlsMode {
+    #[default]
+    Off,
+    Tls,
+    Mutual,
+}
+
+impl LlmTlsMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "off" | "false" => Some(Self::Off),
+            "tls" | "on" | "true" => Some(Self::Tls),
+            "mtls" | "mutual" => Some(Self::Mutual),
+            _ => None,
+        }
+    }
+}
+
+/// Read from `LLM_TLS_MODE`, `LLM_TLS_CA_CERT`, `LLM_TLS_CLIENT_CERT`,
+/// `LLM_TLS_CLIENT_KEY` and `LLM_TLS_HOSTS`. The certificate paths default to the ones the
+/// bootstrap writes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LlmTlsConfig {
+    pub mode: LlmTlsMode,
+    pub ca_cert: PathBuf,
+    pub client_cert: PathBuf,
+    pub client_key: PathBuf,
+    /// Hosts of local servers besides loopback, lowercase.
+    pub hosts: Vec<String>,
+}
+
+impl LlmTlsConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    /// An unknown mode is treated as `tls`, so a typo never downgrades traffic to plain
+    /// HTTP.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let var = |key: &str| lookup(key).filter(|v| !v.trim().is_empty());
+        let certs = format!("{}/conf/system/certificates/botserver", get_stack_path());
+        let mode = match var("LLM_TLS_MODE") {
+            Some(value) => LlmTlsMode::parse(&value).unwrap_or_else(|| {
+                error!("Unknown LLM_TLS_MODE '{}', using tls", value);
+                LlmTlsMode::Tls
+            }),
+            None => LlmTlsMode::Off,
+        };
+        Self {
+            mode,
+            ca_cert: PathBuf::from(var("LLM_TLS_CA_CERT").unwrap_or_else(ca_cert_path)),
+            client_cert: PathBuf::from(
+                var("LLM_TLS_CLIENT_CERT").unwrap_or_else(|| format!("{certs}/client.crt")),
+            ),
+            client_key: PathBuf::from(
+                var("LLM_TLS_CLIENT_KEY").unwrap_or_else(|| format!("{certs}/client.key")),
+            ),
+            hosts: var("LLM_TLS_HOSTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|h| h.trim().to_ascii_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Whether `url` is one of the stack's servers, which this config applies to.
+    pub fn covers(&self, url: &str) -> bool {
+        let Some(host) = url::Url::parse(url)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_ascii_lowercase))
+        else {
+            return false;
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        host == "localhost"
+            || host.ends_with(".localhost")
+            || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+            || self.hosts.iter().any(|h| h == host)
+    }
+
+    /// Configures `builder` for this mode. Fails when a certificate the mode needs cannot
+    /// be read. A missing CA file leaves only the system roots, for servers with public
+    /// certificates.
+    pub fn apply(&self, builder: ClientBuilder) -> Result<ClientBuilder, String> {
+        if self.mode == LlmTlsMode::Off {
+            return Ok(builder);
+        }
+        let mut builder = builder.use_rustls_tls().https_only(true);
+
+        if self.ca_cert.exists() {
+            let pem = read(&self.ca_cert)?;
+            let ca = Certificate::from_pem(&pem)
+                .map_err(|e| format!("Invalid CA certificate {}: {}", self.ca_cert.display(), e))?;
+            builder = builder.add_root_certificate(ca);
+        } else {
+            debug!(
+                "LLM TLS CA {} not found, using system roots",
+                self.ca_cert.display()
+            );
+        }
+
+        if self.mode == LlmTlsMode::Mutual {
+            let mut pem = read(&self.client_cert)?;
+            pem.push(b'\n');
+            pem.extend(read(&self.client_key)?);
+            let identity = Identity::from_pem(&pem).map_err(|e| {
+                format!(
+                    "Invalid client certificate {}: {}",
+                    self.client_cert.display(),
+                    e
+                )
+            })?;
+            builder = builder.identity(identity);
+        }
+        Ok(builder)
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+pub fn llm_tls() -> &'static LlmTlsConfig {
+    &LLM_TLS
+}
+
+/// `reqwest` client builder for an LLM or embedding server at `url`: the outbound proxy,
+/// plus [`LlmTlsConfig`] when `url` is one of the stack's servers. If the certificates
+/// cannot be loaded, the builder still requires HTTPS, so requests fail rather than go
+/// out unencrypted.
+pub fn llm_client_builder(url: &str) -> ClientBuilder {
+    if !LLM_TLS.covers(url) {
+        return client_builder();
+    }
+    LLM_TLS.apply(client_builder()).unwrap_or_else(|e| {
+        error!("LLM TLS not applied: {}", e);
+        client_builder().https_only(true)
+    })
+}
+
+pub fn llm_http_client(url: &str) -> Client {
+    llm_client_builder(url).build().unwrap_or_else(|e| {
+        warn!("Failed to create LLM HTTP client: {}", e);
+        Client::builder()
+            .https_only(LLM_TLS.mode != LlmTlsMode::Off && LLM_TLS.covers(url))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{
+        BasicConstraints, CertificateParams, ExtendedKeyUsagePurpose, IsCa, Issuer, KeyPair,
+    };
+    use rustls::pki_types::pem::PemObject;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use rustls::server::WebPkiClientVerifier;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    struct TestPki {
+        dir: TempDir,
+        ca_pem: String,
+        server_cert: String,
+        server_key: String,
+    }
+
+    /// A CA, a `localhost` server certificate and a client certificate in `dir`.
+    fn issue_certificates() -> TestPki {
+        let dir = TempDir::new().unwrap();
+        let mut ca_params = CertificateParams::default();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca_key = KeyPair::generate().unwrap();
+        let ca_pem = ca_params.self_signed(&ca_key).unwrap().pem();
+        let issuer = Issuer::from_params(&ca_params, &ca_key);
+
+        let issue = |purpose: ExtendedKeyUsagePurpose| {
+            let mut params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+            params.extended_key_usages = vec![purpose];
+            let key = KeyPair::generate().unwrap();
+            let cert = params.signed_by(&key, &issuer).unwrap();
+            (cert.pem(), key.serialize_pem())
+        };
+        let (server_cert, server_key) = issue(ExtendedKeyUsagePurpose::ServerAuth);
+        let (client_cert, client_key) = issue(ExtendedKeyUsagePurpose::ClientAuth);
+
+        std::fs::write(dir.path().join("ca.crt"), &ca_pem).unwrap();
+        std::fs::write(dir.path().join("client.crt"), client_cert).unwrap();
+        std::fs::write(dir.path().join("client.key"), client_key).unwrap();
+        TestPki {
+            dir,
+            ca_pem,
+            server_cert,
+            server_key,
+        }
+    }
+
+    fn config(pki: &TestPki, mode: &str) -> LlmTlsConfig {
+        let dir = pki.dir.path().to_path_buf();
+        LlmTlsConfig::from_lookup(|key| {
+            let file = match key {
+                "LLM_TLS_MODE" => return Some(mode.to_string()),
+                "LLM_TLS_CA_CERT" => "ca.crt",
+                "LLM_TLS_CLIENT_CERT" => "client.crt",
+                "LLM_TLS_CLIENT_KEY" => "client.key",
+                _ => return None,
+            };
+            Some(dir.join(file).display().to_string())
+        })
+    }
+
+    /// Serves one HTTPS request on localhost, requiring a client certificate from the CA.
+    async fn mtls_server(pki: &TestPki) -> (u16, tokio::task::JoinHandle<bool>) {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut roots = rustls::RootCertStore::empty();
+        for cert in CertificateDer::pem_slice_iter(pki.ca_pem.as_bytes()) {
+            roots.add(cert.unwrap()).unwrap();
+        }
+        let verifier =
+            WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+                .build()
+                .unwrap();
+        let certs = CertificateDer::pem_slice_iter(pki.server_cert.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = PrivateKeyDer::from_pem_slice(pki.server_key.as_bytes()).unwrap();
+        let server_config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs, key)
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let acceptor = TlsAcceptor::from(Arc::new(server_config));
+        let handle = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let Ok(mut tls) = acceptor.accept(socket).await else {
+                return false;
+            };
+            let mut buf = vec![0u8; 4096];
+            let _ = tls.read(&mut buf).await;
+            tls.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok")
+                .await
+                .is_ok()
+        });
+        (port, handle)
+    }
+
+    #[tokio::test]
+    async fn test_mtls_config_builds_client_that_passes_client_auth() {
+        let pki = issue_certificates();
+        let mtls = config(&pki, "mtls");
+        assert_eq!(mtls.mode, LlmTlsMode::Mutual);
+
+        let client = mtls.apply(Client::builder()).unwrap().build().unwrap();
+        let (port, server) = mtls_server(&pki).await;
+        let response = client
+            .get(format!("https://localhost:{port}/health"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+        assert!(server.await.unwrap());
+
+        // Without the client certificate the server refuses the handshake.
+        let tls_only = config(&pki, "tls")
+            .apply(Client::builder())
+            .unwrap()
+            .build()
+            .unwrap();
+        let (port, server) = mtls_server(&pki).await;
+        assert!(tls_only
+            .get(format!("https://localhost:{port}/health"))
+            .send()
+            .await
+            .is_err());
+        assert!(!server.await.unwrap());
+
+        // Plain HTTP is refused once TLS is on.
+        assert!(client
+            .get("http://localhost:1/health")
+            .send()
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_mode_parsing_and_missing_client_cert() {
+        let pki = issue_certificates();
+        assert_eq!(LlmTlsConfig::from_lookup(|_| None).mode, LlmTlsMode::Off);
+        assert_eq!(config(&pki, "mtsl").mode, LlmTlsMode::Tls);
+
+        let mut mtls = config(&pki, "mtls");
+        mtls.client_key = pki.dir.path().join("missing.key");
+        assert!(mtls.apply(Client::builder()).is_err());
+    }
+
+    #[test]
+    fn test_only_local_servers_are_covered() {
+        let config = LlmTlsConfig::from_lookup(|key| match key {
+            "LLM_TLS_MODE" => Some("mtls".to_string()),
+            "LLM_TLS_HOSTS" => Some("llm.internal, Embed.Internal".to_string()),
+            _ => None,
+        });
+        assert!(config.covers("https://localhost:8081/v1"));
+        assert!(config.covers("https://127.0.0.1:8082"));
+        assert!(config.covers("https://[::1]:8081"));
+        assert!(config.covers("https://embed.internal:8082/embedding"));
+        assert!(!config.covers("https://api.openai.com/v1"));
+        assert!(!config.covers("https://localhost.example.com"));
+        assert!(!config.covers("not a url"));
+    }
+}
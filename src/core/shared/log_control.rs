@@ -0,0 +1,421 @@
+//! Process logging with per-module levels that can be changed at runtime.
+//!
+//! Records from the `log` facade are bridged into a `tracing_subscriber` registry whose
+//! filter sits behind a reload handle. The startup filter comes from `RUST_LOG`; module
+//! overrides set through the admin API are appended to it and take effect immediately,
+//! without a restart, until they are cleared or the process exits.
+//!
+//! Logs go to stdout and, optionally, to a file (`LOG_FILE`) and the local syslog
+//! (`LOG_SYSLOG=true`, Unix only).
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::soft_delete::require_admin;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::Json;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Registry};
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    /// Startup filter directives, `RUST_LOG` syntax.
+    pub filter: String,
+    /// Also append logs to this file.
+    pub file: Option<PathBuf>,
+    /// Also send logs to the local syslog.
+    pub syslog: bool,
+}
+
+impl LogConfig {
+    pub fn from_env() -> Self {
+        let filter = std::env::var("RUST_LOG")
+            .ok()
+            .filter(|f| !f.trim().is_empty())
+            .unwrap_or_else(|| "info".to_string());
+        let file = std::env::var("LOG_FILE")
+            .ok()
+            .filter(|f| !f.trim().is_empty())
+            .map(PathBuf::from);
+        let syslog = std::env::var("LOG_SYSLOG")
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        Self {
+            filter,
+            file,
+            syslog,
+        }
+    }
+}
+
+/// Current filter as reported by the admin API.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct LogLevels {
+    pub base: String,
+    pub overrides: BTreeMap<String, String>,
+    pub effective: String,
+}
+
+/// Owns the reload handle of the active filter and the module overrides applied on top
+/// of the startup directives.
+pub struct LogControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    base: String,
+    overrides: Mutex<BTreeMap<String, String>>,
+}
+
+impl LogControl {
+    pub fn new(handle: reload::Handle<EnvFilter, Registry>, base: String) -> Self {
+        Self {
+            handle,
+            base,
+            overrides: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn levels(&self) -> LogLevels {
+        let overrides = self.overrides.lock().map(|o| o.clone()).unwrap_or_default();
+        LogLevels {
+            effective: directives(&self.base, &overrides),
+            base: self.base.clone(),
+            overrides,
+        }
+    }
+
+    /// Sets `module`'s level, e.g. `botserver::llm` to `debug`. `None` removes the
+    /// override so the module falls back to the startup filter.
+    pub fn set_module_level(&self, module: &str, level: Option<&str>) -> Result<LogLevels, String> {
+        let module = module.trim();
+        if module.is_empty() || module.contains([',', '=', ' ']) {
+            return Err(format!("Invalid module name: {module:?}"));
+        }
+        let mut overrides = self.overrides.lock().map_err(|e| e.to_string())?;
+        let mut updated = overrides.clone();
+        match level {
+            Some(level) => {
+                let level = LevelFilter::from_str(level.trim())
+                    .map_err(|_| format!("Invalid log level: {level:?}"))?;
+                updated.insert(module.to_string(), level.to_string().to_lowercase());
+            }
+            None => {
+                updated.remove(module);
+            }
+        }
+        self.apply(&updated)?;
+        *overrides = updated;
+        drop(overrides);
+        Ok(self.levels())
+    }
+
+    /// Drops every module override, restoring the startup filter.
+    pub fn reset(&self) -> Result<LogLevels, String> {
+        let mut overrides = self.overrides.lock().map_err(|e| e.to_string())?;
+        self.apply(&BTreeMap::new())?;
+        overrides.clear();
+        drop(overrides);
+        Ok(self.levels())
+    }
+
+    fn apply(&self, overrides: &BTreeMap<String, String>) -> Result<(), String> {
+        let filter =
+            EnvFilter::try_new(directives(&self.base, overrides)).map_err(|e| e.to_string())?;
+        let max_level = filter.max_level_hint().unwrap_or(LevelFilter::TRACE);
+        self.handle.reload(filter).map_err(|e| e.to_string())?;
+        log::set_max_level(as_log_level(max_level));
+        Ok(())
+    }
+}
+
+/// Startup directives followed by the overrides; later directives for the same module win.
+fn directives(base: &str, overrides: &BTreeMap<String, String>) -> String {
+    let mut all = vec![base.to_string()];
+    all.extend(
+        overrides
+            .iter()
+            .map(|(module, level)| format!("{module}={level}")),
+    );
+    all.retain(|d| !d.is_empty());
+    all.join(",")
+}
+
+fn as_log_level(level: LevelFilter) -> log::LevelFilter {
+    match level {
+        LevelFilter::OFF => log::LevelFilter::Off,
+        LevelFilter::ERROR => log::LevelFilter::Error,
+        LevelFilter::WARN => log::LevelFilter::Warn,
+        LevelFilter::INFO => log::LevelFilter::Info,
+        LevelFilter::DEBUG => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    }
+}
+
+static LOG_CONTROL: OnceLock<LogControl> = OnceLock::new();
+
+pub fn log_control() -> Option<&'static LogControl> {
+    LOG_CONTROL.get()
+}
+
+/// Installs the process logger. Fails when the filter does not parse, the log file cannot
+/// be opened or a logger is already installed.
+pub fn init(config: &LogConfig) -> Result<(), String> {
+    let filter = EnvFilter::try_new(&config.filter).map_err(|e| e.to_string())?;
+    let max_level = filter.max_level_hint().unwrap_or(LevelFilter::TRACE);
+    let (filter_layer, handle) = reload::Layer::new(filter);
+
+    let file_layer = match &config.file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("Failed to open log file {}: {e}", path.display()))?;
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(file)),
+            )
+        }
+        None => None,
+    };
+    #[cfg(unix)]
+    let syslog_layer = match config.syslog.then(syslog::Syslog::connect) {
+        Some(Ok(writer)) => Some(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .without_time()
+                .with_level(false)
+                .with_writer(writer),
+        ),
+        Some(Err(e)) => {
+            eprintln!("Syslog unavailable, logging without it: {e}");
+            None
+        }
+        None => None,
+    };
+    #[cfg(not(unix))]
+    let syslog_layer: Option<tracing_subscriber::layer::Identity> = {
+        if config.syslog {
+            eprintln!("Syslog is only supported on Unix, logging without it");
+        }
+        None
+    };
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(tracing_subscriber::fmt::layer().compact())
+        .with(file_layer)
+        .with(syslog_layer)
+        .try_init()
+        .map_err(|e| e.to_string())?;
+    log::set_max_level(as_log_level(max_level));
+
+    let _ = LOG_CONTROL.set(LogControl::new(handle, config.filter.clone()));
+    Ok(())
+}
+
+#[cfg(unix)]
+mod syslog {
+    use std::io::{self, Write};
+    use std::os::unix::net::UnixDatagram;
+    use tracing_subscriber::fmt::MakeWriter;
+
+    const SOCKETS: [&str; 2] = ["/dev/log", "/var/run/syslog"];
+
+    /// Writes each event as one RFC 3164 datagram to the local syslog socket.
+    pub struct Syslog {
+        socket: UnixDatagram,
+    }
+
+    impl Syslog {
+        pub fn connect() -> io::Result<Self> {
+            let socket = UnixDatagram::unbound()?;
+            let mut last_err = io::Error::new(io::ErrorKind::NotFound, "no syslog socket");
+            for path in SOCKETS {
+                match socket.connect(path) {
+                    Ok(()) => return Ok(Self { socket }),
+                    Err(e) => last_err = e,
+                }
+            }
+            Err(last_err)
+        }
+    }
+
+    pub struct SyslogLine<'a> {
+        socket: &'a UnixDatagram,
+        severity: u8,
+        buf: Vec<u8>,
+    }
+
+    impl Write for SyslogLine<'_> {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.buf.extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Drop for SyslogLine<'_> {
+        fn drop(&mut self) {
+            let message = String::from_utf8_lossy(&self.buf);
+            let message = message.trim_end();
+            if message.is_empty() {
+                return;
+            }
+            // Facility "user" (1).
+            let datagram = format!("<{}>botserver: {}", 8 + self.severity, message);
+            let _ = self.socket.send(datagram.as_bytes());
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Syslog {
+        type Writer = SyslogLine<'a>;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            SyslogLine {
+                socket: &self.socket,
+                severity: 6,
+                buf: Vec::new(),
+            }
+        }
+
+        fn make_writer_for(&'a self, meta: &tracing::Metadata<'_>) -> Self::Writer {
+            let severity = match *meta.level() {
+                tracing::Level::ERROR => 3,
+                tracing::Level::WARN => 4,
+                tracing::Level::INFO => 6,
+                _ => 7,
+            };
+            SyslogLine {
+                socket: &self.socket,
+                severity,
+                buf: Vec::new(),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetLogLevelRequest {
+    pub module: String,
+    /// `trace`, `debug`, `info`, `warn`, `error` or `off`; omit to remove the override.
+    pub level: Option<String>,
+}
+
+fn active_control() -> Result<&'static LogControl, ApiError> {
+    log_control().ok_or_else(|| {
+        ApiError::service_unavailable("Runtime log levels are not available in console mode")
+    })
+}
+
+pub async fn handle_get_log_levels(user: AuthenticatedUser) -> Result<Json<LogLevels>, ApiError> {
+    require_admin(&user)?;
+    Ok(Json(active_control()?.levels()))
+}
+
+pub async fn handle_set_log_level(
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<SetLogLevelRequest>,
+) -> Result<Json<LogLevels>, ApiError> {
+    require_admin(&user)?;
+    let levels = active_control()?
+        .set_module_level(&req.module, req.level.as_deref())
+        .map_err(ApiError::bad_request)?;
+    info!(
+        "Log level of {} set to {} by {}",
+        req.module,
+        req.level.as_deref().unwrap_or("default"),
+        user.user_id
+    );
+    Ok(Json(levels))
+}
+
+pub async fn handle_reset_log_levels(user: AuthenticatedUser) -> Result<Json<LogLevels>, ApiError> {
+    require_admin(&user)?;
+    let levels = active_control()?.reset().map_err(ApiError::internal)?;
+    info!("Log level overrides cleared by {}", user.user_id);
+    Ok(Json(levels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn control(base: &str) -> (LogControl, impl tracing::Subscriber + Send + Sync) {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new(base));
+        let subscriber = tracing_subscriber::registry()
+            .with(layer)
+            .with(tracing_subscriber::fmt::layer().with_test_writer());
+        (LogControl::new(handle, base.to_string()), subscriber)
+    }
+
+    #[test]
+    fn test_toggle_module_level_through_reload_handle() {
+        let (control, subscriber) = control("info,hyper=off");
+        tracing::subscriber::with_default(subscriber, || {
+            assert!(!tracing::enabled!(target: "botserver::llm", tracing::Level::DEBUG));
+
+            let levels = control
+                .set_module_level("botserver::llm", Some("DEBUG"))
+                .unwrap();
+            assert_eq!(levels.effective, "info,hyper=off,botserver::llm=debug");
+            assert!(tracing::enabled!(target: "botserver::llm", tracing::Level::DEBUG));
+            assert!(!tracing::enabled!(target: "botserver::drive", tracing::Level::DEBUG));
+
+            control.set_module_level("botserver::llm", None).unwrap();
+            assert!(!tracing::enabled!(target: "botserver::llm", tracing::Level::DEBUG));
+            assert!(tracing::enabled!(target: "botserver::llm", tracing::Level::INFO));
+        });
+    }
+
+    #[test]
+    fn test_invalid_overrides_leave_filter_unchanged() {
+        let (control, _subscriber) = control("info");
+        assert!(control
+            .set_module_level("botserver::llm", Some("loud"))
+            .is_err());
+        assert!(control.set_module_level("a=b", Some("debug")).is_err());
+        assert!(control.set_module_level("", Some("debug")).is_err());
+        assert!(control.levels().overrides.is_empty());
+
+        control
+            .set_module_level("botserver::drive", Some("trace"))
+            .unwrap();
+        let levels = control.reset().unwrap();
+        assert_eq!(levels.effective, "info");
+        assert!(levels.overrides.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_log_level_endpoints_require_admin() {
+        use axum::http::StatusCode;
+
+        let user = AuthenticatedUser::new(uuid::Uuid::new_v4(), "operator".to_string());
+        let req = SetLogLevelRequest {
+            module: "botserver::llm".to_string(),
+            level: Some("debug".to_string()),
+        };
+        let denied = [
+            handle_get_log_levels(user.clone()).await.unwrap_err(),
+            handle_set_log_level(user.clone(), ApiJson(req))
+                .await
+                .unwrap_err(),
+            handle_reset_log_levels(user.clone()).await.unwrap_err(),
+        ];
+        assert!(denied.iter().all(|e| e.status == StatusCode::FORBIDDEN));
+
+        let admin = user.with_role(crate::security::auth_api::Role::Admin);
+        if let Err(e) = handle_get_log_levels(admin).await {
+            assert_ne!(e.status, StatusCode::FORBIDDEN);
+        }
+    }
+}
@@ -0,0 +1,442 @@
+//! Maintenance mode for upgrades and migrations.
+//!
+//! While it is on, [`maintenance_middleware`] answers new requests with `503` and a
+//! `Retry-After` header. Health checks, the maintenance endpoint itself and the paths in the
+//! allow-list keep working. Requests already being handled finish normally, and WebSocket
+//! sessions that were upgraded before maintenance started stay open; only new upgrades are
+//! refused.
+//!
+//! The flag is kept in the `server_configuration` table, so every instance sharing the
+//! database follows it and it survives the restarts an upgrade usually involves. Each
+//! instance re-reads it every few seconds.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::shared::utils::DbPool;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+const DEFAULT_RETRY_AFTER_SECS: u64 = 300;
+const CONFIG_KEY: &str = "maintenance-mode";
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Paths that are never blocked, so load balancers keep seeing the instance, admins can
+/// still sign in, and maintenance can be switched off again.
+const ALWAYS_ALLOWED: [&str; 9] = [
+    "/health",
+    "/healthz",
+    "/api/health",
+    "/api/admin/maintenance",
+    "/api/auth/login",
+    "/api/auth/refresh",
+    "/api/auth/me",
+    "/api/auth/2fa/verify",
+    "/api/auth/2fa/resend",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MaintenanceState {
+    pub enabled: bool,
+    /// Shown to clients in the `503` body.
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default = "default_retry_after")]
+    pub retry_after_secs: u64,
+    /// Extra path prefixes that stay up, typically admin endpoints.
+    #[serde(default)]
+    pub allow_paths: Vec<String>,
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated_by: Option<String>,
+}
+
+fn default_retry_after() -> u64 {
+    DEFAULT_RETRY_AFTER_SECS
+}
+
+impl Default for MaintenanceState {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            message: None,
+            retry_after_secs: DEFAULT_RETRY_AFTER_SECS,
+            allow_paths: Vec::new(),
+            since: None,
+            updated_by: None,
+        }
+    }
+}
+
+impl MaintenanceState {
+    pub fn allows(&self, path: &str) -> bool {
+        ALWAYS_ALLOWED
+            .iter()
+            .copied()
+            .chain(self.allow_paths.iter().map(String::as_str))
+            .any(|prefix| path_matches(path, prefix))
+    }
+}
+
+/// `prefix` matches itself and anything below it; a trailing `/` is optional.
+fn path_matches(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// The maintenance flag and the database it is persisted to.
+pub struct MaintenanceMode {
+    pool: Option<DbPool>,
+    state: RwLock<MaintenanceState>,
+}
+
+impl MaintenanceMode {
+    /// A flag that lives only in this process, starting as `state`.
+    pub fn in_memory(state: MaintenanceState) -> Self {
+        Self {
+            pool: None,
+            state: RwLock::new(state),
+        }
+    }
+
+    /// Loads the state saved in the database, starting switched off when there is none.
+    pub fn from_database(pool: DbPool) -> Self {
+        let state = read_state(&pool).unwrap_or_else(|e| {
+            warn!("Failed to load maintenance state, starting without it: {e}");
+            MaintenanceState::default()
+        });
+        Self {
+            pool: Some(pool),
+            state: RwLock::new(state),
+        }
+    }
+
+    pub fn state(&self) -> MaintenanceState {
+        self.state.read().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Picks up changes made through other instances.
+    pub fn refresh(&self) {
+        let Some(pool) = &self.pool else {
+            return;
+        };
+        match read_state(pool) {
+            Ok(state) => {
+                if let Ok(mut current) = self.state.write() {
+                    *current = state;
+                }
+            }
+            Err(e) => warn!("Failed to refresh maintenance state: {e}"),
+        }
+    }
+
+    /// Persists `state` and makes it current. Nothing changes if it cannot be saved.
+    pub fn update(&self, state: MaintenanceState) -> Result<MaintenanceState, String> {
+        if let Some(pool) = &self.pool {
+            save_state(pool, &state)?;
+        }
+        let mut current = self.state.write().map_err(|e| e.to_string())?;
+        *current = state.clone();
+        Ok(state)
+    }
+}
+
+#[derive(QueryableByName)]
+struct ConfigValue {
+    #[diesel(sql_type = Text)]
+    config_value: String,
+}
+
+/// Saved state; fields missing from an older value take their defaults.
+fn decode_state(raw: &str) -> Result<MaintenanceState, String> {
+    serde_json::from_str(raw).map_err(|e| format!("Unreadable maintenance state: {e}"))
+}
+
+fn read_state(pool: &DbPool) -> Result<MaintenanceState, String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    let row: Option<ConfigValue> =
+        diesel::sql_query("SELECT config_value FROM server_configuration WHERE config_key = $1")
+            .bind::<Text, _>(CONFIG_KEY)
+            .get_result(&mut conn)
+            .optional()
+            .map_err(|e| e.to_string())?;
+    match row {
+        Some(row) => decode_state(&row.config_value),
+        None => Ok(MaintenanceState::default()),
+    }
+}
+
+fn save_state(pool: &DbPool, state: &MaintenanceState) -> Result<(), String> {
+    let json = serde_json::to_string(state).map_err(|e| e.to_string())?;
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    diesel::sql_query(
+        "INSERT INTO server_configuration (id, config_key, config_value, config_type, description) \
+         VALUES ($1, $1, $2, 'json', 'Maintenance mode state') \
+         ON CONFLICT (config_key) DO UPDATE SET config_value = EXCLUDED.config_value, updated_at = NOW()",
+    )
+    .bind::<Text, _>(CONFIG_KEY)
+    .bind::<Text, _>(json)
+    .execute(&mut conn)
+    .map(|_| ())
+    .map_err(|e| format!("Failed to save maintenance state: {e}"))
+}
+
+static MAINTENANCE: OnceLock<MaintenanceMode> = OnceLock::new();
+
+/// Loads the flag from the database and re-reads it every few seconds. Called once at
+/// startup, before the server takes requests.
+pub async fn init_maintenance(pool: DbPool) -> &'static MaintenanceMode {
+    let loaded = tokio::task::spawn_blocking(move || MaintenanceMode::from_database(pool))
+        .await
+        .unwrap_or_else(|_| MaintenanceMode::in_memory(MaintenanceState::default()));
+    if MAINTENANCE.set(loaded).is_err() {
+        warn!("Maintenance mode was already initialized");
+    }
+    let mode = maintenance();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let _ = tokio::task::spawn_blocking(|| maintenance().refresh()).await;
+        }
+    });
+    mode
+}
+
+/// The process-wide maintenance flag; switched off and unsaved until
+/// [`init_maintenance`] has run.
+pub fn maintenance() -> &'static MaintenanceMode {
+    MAINTENANCE.get_or_init(|| MaintenanceMode::in_memory(MaintenanceState::default()))
+}
+
+pub async fn maintenance_middleware(request: Request<Body>, next: Next) -> Response {
+    maintenance_middleware_with(maintenance(), request, next).await
+}
+
+pub async fn maintenance_middleware_with(
+    mode: &MaintenanceMode,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let state = mode.state();
+    if !state.enabled || state.allows(request.uri().path()) {
+        return next.run(request).await;
+    }
+    unavailable(&state)
+}
+
+/// Built directly rather than through `ApiError`'s `IntoResponse`, which logs every 5xx.
+fn unavailable(state: &MaintenanceState) -> Response {
+    let message = state
+        .message
+        .clone()
+        .unwrap_or_else(|| "The service is down for maintenance".to_string());
+    let body = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "MAINTENANCE", message)
+        .envelope(crate::security::current_request_id());
+    let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(body)).into_response();
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(state.retry_after_secs),
+    );
+    response
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetMaintenanceRequest {
+    pub enabled: bool,
+    pub message: Option<String>,
+    pub retry_after_secs: Option<u64>,
+    /// Replaces the allow-list when given.
+    pub allow_paths: Option<Vec<String>>,
+}
+
+pub async fn handle_get_maintenance(
+    user: AuthenticatedUser,
+) -> Result<Json<MaintenanceState>, ApiError> {
+    require_admin(&user)?;
+    Ok(Json(maintenance().state()))
+}
+
+pub async fn handle_set_maintenance(
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<SetMaintenanceRequest>,
+) -> Result<Json<MaintenanceState>, ApiError> {
+    require_admin(&user)?;
+    if let Some(bad) = req
+        .allow_paths
+        .iter()
+        .flatten()
+        .find(|p| !p.starts_with('/'))
+    {
+        return Err(ApiError::bad_request(format!(
+            "Allowed paths must start with '/': {bad:?}"
+        )));
+    }
+
+    let current = maintenance().state();
+    let since = match (req.enabled, current.enabled) {
+        (true, true) => current.since,
+        (true, false) => Some(Utc::now()),
+        (false, _) => None,
+    };
+    let updated = MaintenanceState {
+        enabled: req.enabled,
+        message: req.message.filter(|m| !m.trim().is_empty()),
+        retry_after_secs: req.retry_after_secs.unwrap_or(current.retry_after_secs),
+        allow_paths: req.allow_paths.unwrap_or(current.allow_paths),
+        since,
+        updated_by: Some(user.user_id.to_string()),
+    };
+    let updated = tokio::task::spawn_blocking(move || maintenance().update(updated))
+        .await
+        .map_err(|e| ApiError::internal(e.to_string()))?
+        .map_err(ApiError::internal)?;
+    info!(
+        "Maintenance mode {} by {}",
+        if updated.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        },
+        user.user_id
+    );
+    Ok(Json(updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(mode: Arc<MaintenanceMode>) -> Router {
+        Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .route("/api/bots", get(|| async { "bots" }))
+            .route("/api/admin/logging", get(|| async { "levels" }))
+            .layer(axum::middleware::from_fn(move |req, next| {
+                let mode = Arc::clone(&mode);
+                async move { maintenance_middleware_with(&mode, req, next).await }
+            }))
+    }
+
+    async fn get_response(app: Router, uri: &str) -> Response {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_blocks_api_but_not_health() {
+        let mode = Arc::new(MaintenanceMode::in_memory(MaintenanceState::default()));
+
+        let response = get_response(app(Arc::clone(&mode)), "/api/bots").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        mode.update(MaintenanceState {
+            enabled: true,
+            retry_after_secs: 120,
+            allow_paths: vec!["/api/admin/logging".to_string()],
+            ..MaintenanceState::default()
+        })
+        .unwrap();
+
+        let response = get_response(app(Arc::clone(&mode)), "/api/bots").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "120");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["error"]["code"], "MAINTENANCE");
+
+        let response = get_response(app(Arc::clone(&mode)), "/health").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = get_response(app(Arc::clone(&mode)), "/api/admin/logging").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        mode.update(MaintenanceState::default()).unwrap();
+        let response = get_response(app(mode), "/api/bots").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_endpoints_require_admin() {
+        let user = AuthenticatedUser::new(uuid::Uuid::new_v4(), "operator".to_string());
+
+        let err = handle_get_maintenance(user.clone()).await.unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        let req = SetMaintenanceRequest {
+            enabled: true,
+            message: None,
+            retry_after_secs: None,
+            allow_paths: None,
+        };
+        let err = handle_set_maintenance(user.clone(), ApiJson(req))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, StatusCode::FORBIDDEN);
+        assert!(!maintenance().state().enabled);
+
+        let admin = user.with_role(crate::security::auth_api::Role::Admin);
+        assert!(handle_get_maintenance(admin).await.is_ok());
+    }
+
+    #[test]
+    fn test_saved_state_round_trips() {
+        let state = MaintenanceState {
+            enabled: true,
+            message: Some("Upgrading to 6.4".to_string()),
+            ..MaintenanceState::default()
+        };
+        let saved = serde_json::to_string(&state).unwrap();
+        assert_eq!(decode_state(&saved).unwrap(), state);
+
+        let minimal = decode_state(r#"{"enabled":true}"#).unwrap();
+        assert!(minimal.enabled);
+        assert_eq!(minimal.retry_after_secs, DEFAULT_RETRY_AFTER_SECS);
+        assert!(decode_state("not json").is_err());
+    }
+
+    #[test]
+    fn test_sign_in_stays_up() {
+        let state = MaintenanceState {
+            enabled: true,
+            ..MaintenanceState::default()
+        };
+        assert!(state.allows("/api/auth/login"));
+        assert!(state.allows("/api/auth/2fa/verify"));
+        assert!(!state.allows("/api/auth/sessions"));
+    }
+
+    #[test]
+    fn test_allow_paths_match_whole_segments() {
+        let state = MaintenanceState {
+            allow_paths: vec!["/api/admin/bots/".to_string()],
+            ..MaintenanceState::default()
+        };
+        assert!(state.allows("/health/ready"));
+        assert!(state.allows("/api/admin/bots/123"));
+        assert!(state.allows("/api/admin/bots"));
+        assert!(!state.allows("/api/admin/botsx"));
+        assert!(!state.allows("/healthcheck"));
+        assert!(!state.allows("/api/bots"));
+    }
+}
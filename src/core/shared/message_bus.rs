@@ -0,0 +1,294 @@
+//! Publish/subscribe between parts of the server and between server instances.
+//!
+//! Messages are JSON payloads on named topics. [`InProcessBus`] delivers them within this
+//! process. [`RedisBus`] sends them through Redis pub/sub, so every instance sharing the
+//! Redis server receives them; `MESSAGE_BUS=redis` selects it at startup. Delivery is
+//! best effort: a message published while nobody is subscribed to its topic is dropped.
+
+use crate::core::shared::state::{AppState, AttendantNotification};
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Notifications for attendant consoles.
+pub const ATTENDANT_TOPIC: &str = "attendant";
+
+const TOPIC_CAPACITY: usize = 1000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BusMessage {
+    pub topic: String,
+    pub payload: serde_json::Value,
+}
+
+pub type BusReceiver = broadcast::Receiver<BusMessage>;
+
+#[async_trait]
+pub trait MessageBus: Send + Sync {
+    fn backend(&self) -> &'static str;
+
+    /// Whether published messages reach other server instances.
+    fn is_shared(&self) -> bool;
+
+    async fn publish(
+        &self,
+        topic: &str,
+        payload: serde_json::Value,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    async fn subscribe(&self, topic: &str) -> Result<BusReceiver, Box<dyn Error + Send + Sync>>;
+}
+
+/// Delivers messages to subscribers in this process only.
+pub struct InProcessBus {
+    capacity: usize,
+    topics: Mutex<HashMap<String, broadcast::Sender<BusMessage>>>,
+}
+
+impl InProcessBus {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            topics: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn sender(&self, topic: &str) -> broadcast::Sender<BusMessage> {
+        let mut topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+        topics
+            .entry(topic.to_string())
+            .or_insert_with(|| broadcast::channel(self.capacity).0)
+            .clone()
+    }
+
+    fn deliver(&self, message: BusMessage) {
+        let sender = {
+            let topics = self.topics.lock().unwrap_or_else(|e| e.into_inner());
+            topics.get(&message.topic).cloned()
+        };
+        let topic = message.topic.clone();
+        if sender.map_or(true, |tx| tx.send(message).is_err()) {
+            debug!("No subscribers for bus topic {}", topic);
+        }
+    }
+}
+
+impl Default for InProcessBus {
+    fn default() -> Self {
+        Self::new(TOPIC_CAPACITY)
+    }
+}
+
+#[async_trait]
+impl MessageBus for InProcessBus {
+    fn backend(&self) -> &'static str {
+        "in-process"
+    }
+
+    fn is_shared(&self) -> bool {
+        false
+    }
+
+    async fn publish(
+        &self,
+        topic: &str,
+        payload: serde_json::Value,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.deliver(BusMessage {
+            topic: topic.to_string(),
+            payload,
+        });
+        Ok(())
+    }
+
+    async fn subscribe(&self, topic: &str) -> Result<BusReceiver, Box<dyn Error + Send + Sync>> {
+        Ok(self.sender(topic).subscribe())
+    }
+}
+
+#[cfg(feature = "cache")]
+pub use redis_bus::RedisBus;
+
+#[cfg(feature = "cache")]
+mod redis_bus {
+    use super::*;
+    use futures::StreamExt;
+    use redis::AsyncCommands;
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    const CHANNEL_PREFIX: &str = "botserver:bus:";
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+    /// Publishes through Redis pub/sub. Each instance keeps one Redis subscription per
+    /// topic it listens to and fans the messages out to its local subscribers, including
+    /// the ones it published itself.
+    pub struct RedisBus {
+        client: Arc<redis::Client>,
+        local: Arc<InProcessBus>,
+        listening: Mutex<HashSet<String>>,
+    }
+
+    impl RedisBus {
+        pub fn new(client: Arc<redis::Client>) -> Self {
+            Self {
+                client,
+                local: Arc::new(InProcessBus::default()),
+                listening: Mutex::new(HashSet::new()),
+            }
+        }
+
+        fn listen(&self, topic: &str) {
+            let first = self
+                .listening
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(topic.to_string());
+            if !first {
+                return;
+            }
+            let client = Arc::clone(&self.client);
+            let local = Arc::clone(&self.local);
+            let topic = topic.to_string();
+            tokio::spawn(async move {
+                loop {
+                    if let Err(e) = relay(&client, &local, &topic).await {
+                        warn!("Redis bus subscription for {} failed: {}", topic, e);
+                    }
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            });
+        }
+    }
+
+    async fn relay(
+        client: &redis::Client,
+        local: &InProcessBus,
+        topic: &str,
+    ) -> redis::RedisResult<()> {
+        let mut pubsub = client.get_async_pubsub().await?;
+        pubsub.subscribe(format!("{CHANNEL_PREFIX}{topic}")).await?;
+        let mut messages = pubsub.on_message();
+        while let Some(message) = messages.next().await {
+            let body: String = match message.get_payload() {
+                Ok(body) => body,
+                Err(e) => {
+                    warn!("Unreadable message on bus topic {}: {}", topic, e);
+                    continue;
+                }
+            };
+            match serde_json::from_str(&body) {
+                Ok(payload) => local.deliver(BusMessage {
+                    topic: topic.to_string(),
+                    payload,
+                }),
+                Err(e) => warn!("Invalid JSON on bus topic {}: {}", topic, e),
+            }
+        }
+        Ok(())
+    }
+
+    #[async_trait]
+    impl MessageBus for RedisBus {
+        fn backend(&self) -> &'static str {
+            "redis"
+        }
+
+        fn is_shared(&self) -> bool {
+            true
+        }
+
+        async fn publish(
+            &self,
+            topic: &str,
+            payload: serde_json::Value,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let body = serde_json::to_string(&payload)?;
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            conn.publish::<_, _, ()>(format!("{CHANNEL_PREFIX}{topic}"), body)
+                .await?;
+            Ok(())
+        }
+
+        async fn subscribe(
+            &self,
+            topic: &str,
+        ) -> Result<BusReceiver, Box<dyn Error + Send + Sync>> {
+            let receiver = self.local.sender(topic).subscribe();
+            self.listen(topic);
+            Ok(receiver)
+        }
+    }
+}
+
+/// The bus selected by `MESSAGE_BUS` (`memory`, the default, or `redis`).
+pub fn message_bus_from_env(
+    #[cfg(feature = "cache")] redis: Option<&Arc<redis::Client>>,
+) -> Arc<dyn MessageBus> {
+    let backend = std::env::var("MESSAGE_BUS").unwrap_or_default();
+    if backend.trim().eq_ignore_ascii_case("redis") {
+        #[cfg(feature = "cache")]
+        if let Some(client) = redis {
+            info!("Message bus: Redis pub/sub");
+            return Arc::new(RedisBus::new(Arc::clone(client)));
+        }
+        warn!("MESSAGE_BUS=redis but no Redis connection is available, using in-process bus");
+    }
+    Arc::new(InProcessBus::default())
+}
+
+/// Sends a notification to the attendant consoles connected to any instance.
+pub async fn notify_attendants(state: &AppState, notification: &AttendantNotification) {
+    publish_attendant_notification(state.message_bus.as_ref(), notification).await;
+}
+
+/// [`notify_attendants`] on a given bus.
+pub async fn publish_attendant_notification(
+    bus: &dyn MessageBus,
+    notification: &AttendantNotification,
+) {
+    let payload = match serde_json::to_value(notification) {
+        Ok(payload) => payload,
+        Err(e) => {
+            warn!("Failed to encode attendant notification: {}", e);
+            return;
+        }
+    };
+    match bus.publish(ATTENDANT_TOPIC, payload).await {
+        Ok(()) => debug!("Notification sent to attendants"),
+        Err(e) => warn!("Failed to notify attendants: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_in_process_publish_subscribe_round_trip() {
+        let bus = InProcessBus::default();
+        let mut attendants = bus.subscribe(ATTENDANT_TOPIC).await.unwrap();
+        let mut others = bus.subscribe("other").await.unwrap();
+
+        bus.publish(
+            ATTENDANT_TOPIC,
+            json!({"type": "new_message", "priority": 1}),
+        )
+        .await
+        .unwrap();
+
+        let message = attendants.recv().await.unwrap();
+        assert_eq!(message.topic, ATTENDANT_TOPIC);
+        assert_eq!(message.payload["type"], "new_message");
+        assert_eq!(message.payload["priority"], 1);
+        assert!(others.try_recv().is_err());
+
+        // Publishing without subscribers is not an error.
+        bus.publish("nobody", json!(null)).await.unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+use diesel::pg::Pg;
+use diesel::PgConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness, MigrationSource};
+use log::{error, info};
+use std::collections::HashSet;
+
+type MigrationResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+// Flat migrations with version-ordinal-feature naming
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// What startup does when a migration fails. Set with `DB_MIGRATIONS_ON_ERROR`
+/// (`fail` or `continue`); the default is `fail`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationFailureMode {
+    FailFast,
+    Continue,
+}
+
+impl MigrationFailureMode {
+    pub fn from_env() -> Self {
+        Self::parse(&std::env::var("DB_MIGRATIONS_ON_ERROR").unwrap_or_default())
+    }
+
+    fn parse(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "continue" | "warn" => Self::Continue,
+            _ => Self::FailFast,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationState {
+    pub name: String,
+    pub applied: bool,
+}
+
+/// Every embedded migration in order, flagged with whether it has been applied.
+pub fn migration_status(conn: &mut PgConnection) -> MigrationResult<Vec<MigrationState>> {
+    let applied: HashSet<String> = conn
+        .applied_migrations()?
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect();
+
+    let migrations = MigrationSource::<Pg>::migrations(&MIGRATIONS)?;
+    Ok(migrations
+        .iter()
+        .map(|m| MigrationState {
+            name: m.name().to_string(),
+            applied: applied.contains(&m.name().version().to_string()),
+        })
+        .collect())
+}
+
+/// Applies pending migrations one at a time so a failure names the migration that broke.
+/// Returns the names of the migrations that were applied.
+pub fn run_pending(conn: &mut PgConnection) -> MigrationResult<Vec<String>> {
+    let pending = conn.pending_migrations(MIGRATIONS)?;
+    let mut applied = Vec::with_capacity(pending.len());
+
+    for migration in pending {
+        let name = migration.name().to_string();
+        match conn.run_migration(&*migration) {
+            Ok(_) => {
+                info!("Applied migration {}", name);
+                applied.push(name);
+            }
+            Err(e) => {
+                error!("Migration {} failed: {}", name, e);
+                return Err(
+                    std::io::Error::other(format!("Migration {} failed: {}", name, e)).into(),
+                );
+            }
+        }
+    }
+
+    Ok(applied)
+}
+
+/// Reverts the last `steps` applied migrations, newest first.
+pub fn revert(conn: &mut PgConnection, steps: usize) -> MigrationResult<Vec<String>> {
+    let mut reverted = Vec::with_capacity(steps);
+    for _ in 0..steps {
+        if conn.applied_migrations()?.is_empty() {
+            break;
+        }
+        let version = conn.revert_last_migration(MIGRATIONS).map_err(|e| {
+            std::io::Error::other(format!("Failed to revert migration: {}", e))
+        })?;
+        info!("Reverted migration {}", version);
+        reverted.push(version.to_string());
+    }
+    Ok(reverted)
+}
+
+/// Entry point for `botserver migrate <status|up|down [N]>`. Returns the process exit code.
+pub fn run_cli(conn: &mut PgConnection, args: &[String]) -> i32 {
+    let subcommand = args.first().map(|s| s.as_str()).unwrap_or("status");
+    let result = match subcommand {
+        "status" => migration_status(conn).map(|states| {
+            let pending = states.iter().filter(|s| !s.applied).count();
+            println!("=== Migrations ===");
+            for state in &states {
+                println!("{} {}", if state.applied { "[applied]" } else { "[pending]" }, state.name);
+            }
+            println!("{} applied, {} pending", states.len() - pending, pending);
+        }),
+        "up" => run_pending(conn).map(|applied| {
+            if applied.is_empty() {
+                println!("No pending migrations");
+            }
+            for name in applied {
+                println!("Applied {}", name);
+            }
+        }),
+        "down" => {
+            let steps = match args.get(1).map(|s| s.parse::<usize>()) {
+                None => 1,
+                Some(Ok(n)) if n > 0 => n,
+                Some(_) => {
+                    eprintln!("Usage: botserver migrate down [N]");
+                    return 1;
+                }
+            };
+            revert(conn, steps).map(|reverted| {
+                if reverted.is_empty() {
+                    println!("No applied migrations to revert");
+                }
+                for version in reverted {
+                    println!("Reverted {}", version);
+                }
+            })
+        }
+        _ => {
+            eprintln!("Usage: botserver migrate <status|up|down [N]>");
+            return 1;
+        }
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failure_mode_defaults_to_fail_fast() {
+        assert_eq!(MigrationFailureMode::parse(""), MigrationFailureMode::FailFast);
+        assert_eq!(MigrationFailureMode::parse("fail"), MigrationFailureMode::FailFast);
+        assert_eq!(MigrationFailureMode::parse("bogus"), MigrationFailureMode::FailFast);
+        assert_eq!(MigrationFailureMode::parse(" Continue "), MigrationFailureMode::Continue);
+    }
+
+    #[test]
+    fn test_embedded_migrations_are_found() {
+        let names: Vec<String> = MigrationSource::<Pg>::migrations(&MIGRATIONS)
+            .unwrap()
+            .iter()
+            .map(|m| m.name().version().to_string())
+            .collect();
+        assert!(!names.is_empty());
+    }
+}
@@ -4,18 +4,36 @@
 pub mod admin;
 pub mod admin_types;
 pub mod admin_config;
+pub mod admin_dashboard;
 pub mod admin_email;
 pub mod analytics;
+pub mod api_error;
+pub mod api_json;
+pub mod bot_features;
+pub mod circuit_breaker;
+pub mod db_pool;
 pub mod enums;
+pub mod key_rotation;
+pub mod llm_tls;
+pub mod log_control;
+pub mod maintenance;
 pub mod memory_monitor;
+pub mod message_bus;
+pub mod migrations;
 pub mod models;
+pub mod outbound_proxy;
+pub mod request_timeout;
 pub mod schema;
+pub mod soft_delete;
 pub mod state;
 #[cfg(test)]
 pub mod test_utils;
 pub mod utils;
+pub mod ws_heartbeat;
+pub mod ws_routing;
 
 
+pub use api_error::ApiError;
 pub use enums::*;
 pub use schema::*;
 
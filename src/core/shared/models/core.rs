@@ -64,6 +64,14 @@ pub struct UserSession {
     pub current_tool: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub deleted_by: Option<Uuid>,
+    #[serde(default)]
+    pub parent_session_id: Option<Uuid>,
+    #[serde(default)]
+    pub forked_at_index: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Insertable)]
@@ -0,0 +1,226 @@
+//! Deadlines for HTTP handlers.
+//!
+//! [`request_timeout_middleware`] answers `504` when a handler runs past the deadline for
+//! its route group (see [`HttpTimeoutConfig`]). Dropping the handler future stops every
+//! `.await` it was blocked on, such as an LLM call. Work a handler hands to
+//! `tokio::spawn` or `spawn_blocking` is not part of that future, so it takes a
+//! [`RequestCancellation`] and stops when the request is abandoned, either because it
+//! timed out or because the client went away.
+
+use crate::core::config::HttpTimeoutConfig;
+use crate::core::shared::api_error::ApiError;
+use axum::{
+    body::Body,
+    extract::FromRequestParts,
+    http::{request::Parts, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use log::warn;
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Signals that the request a piece of work belongs to was abandoned.
+///
+/// Extract it in a handler and pass it to spawned work. Outside the timeout middleware,
+/// for example in unit tests, the extractor yields a handle that is never cancelled.
+#[derive(Clone, Debug)]
+pub struct RequestCancellation {
+    rx: watch::Receiver<bool>,
+}
+
+impl RequestCancellation {
+    /// A handle that is never cancelled.
+    pub fn never() -> Self {
+        let (_, rx) = watch::channel(false);
+        Self { rx }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.rx.borrow()
+    }
+
+    /// Resolves once the request is abandoned; pending forever if it completes normally.
+    pub async fn cancelled(&self) {
+        let mut rx = self.rx.clone();
+        if rx.wait_for(|cancelled| *cancelled).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// Spawns `task`, dropping it if the request is abandoned first. The handle yields
+    /// `None` in that case.
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<Option<F::Output>>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let cancellation = self.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = cancellation.cancelled() => None,
+                output = task => Some(output),
+            }
+        })
+    }
+
+    /// Runs `task` on the blocking pool unless the request is abandoned before the pool
+    /// gets to it. The handle yields `None` in that case. A task that already started runs
+    /// to completion.
+    pub fn spawn_blocking<F, T>(&self, task: F) -> JoinHandle<Option<T>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let cancellation = self.clone();
+        tokio::task::spawn_blocking(move || (!cancellation.is_cancelled()).then(task))
+    }
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for RequestCancellation {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<RequestCancellation>()
+            .cloned()
+            .unwrap_or_else(Self::never))
+    }
+}
+
+/// Cancels the request's work when dropped without [`CancelOnDrop::disarm`], which covers
+/// both the deadline and the client disconnecting mid-request.
+struct CancelOnDrop(Option<watch::Sender<bool>>);
+
+impl CancelOnDrop {
+    fn disarm(mut self) {
+        self.0.take();
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(tx) = self.0.take() {
+            let _ = tx.send(true);
+        }
+    }
+}
+
+pub async fn request_timeout_middleware(
+    config: Arc<HttpTimeoutConfig>,
+    mut request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(limit) = config.timeout_for(request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    let (tx, rx) = watch::channel(false);
+    request.extensions_mut().insert(RequestCancellation { rx });
+    let guard = CancelOnDrop(Some(tx));
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    match tokio::time::timeout(limit, next.run(request)).await {
+        Ok(response) => {
+            guard.disarm();
+            response
+        }
+        Err(_) => {
+            drop(guard);
+            warn!("{} {} timed out after {:?}", method, path, limit);
+            ApiError::new(
+                StatusCode::GATEWAY_TIMEOUT,
+                "REQUEST_TIMEOUT",
+                format!(
+                    "The request did not complete within {} seconds and was cancelled",
+                    limit.as_secs_f64()
+                ),
+            )
+            .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant};
+    use tower::ServiceExt;
+
+    fn app(config: HttpTimeoutConfig, finished: Arc<AtomicBool>) -> Router {
+        let config = Arc::new(config);
+        let slow = move |cancellation: RequestCancellation| {
+            let finished = finished.clone();
+            async move {
+                cancellation.spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    finished.store(true, Ordering::SeqCst);
+                });
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                "done"
+            }
+        };
+        Router::new()
+            .route("/api/crud/slow", get(slow.clone()))
+            .route("/api/llm/slow", get(slow))
+            .layer(axum::middleware::from_fn(move |req, next| {
+                request_timeout_middleware(config.clone(), req, next)
+            }))
+    }
+
+    fn config() -> HttpTimeoutConfig {
+        HttpTimeoutConfig {
+            default: Duration::from_millis(30),
+            long: Duration::from_secs(5),
+            long_prefixes: vec!["/api/llm/".to_string()],
+        }
+    }
+
+    async fn get_status(app: Router, uri: &str) -> StatusCode {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_slow_handler_times_out_and_cancels_spawned_work() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let started = Instant::now();
+        let status = get_status(app(config(), finished.clone()), "/api/crud/slow").await;
+
+        assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(30));
+        assert!(elapsed < Duration::from_millis(100));
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(!finished.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_long_group_completes_and_keeps_spawned_work() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let status = get_status(app(config(), finished.clone()), "/api/llm/slow").await;
+        assert_eq!(status, StatusCode::OK);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(finished.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_blocking_work_is_skipped_once_cancelled() {
+        let (tx, rx) = watch::channel(false);
+        let cancellation = RequestCancellation { rx };
+        assert_eq!(cancellation.spawn_blocking(|| 1).await.unwrap(), Some(1));
+
+        tx.send(true).unwrap();
+        assert_eq!(cancellation.spawn_blocking(|| 2).await.unwrap(), None);
+    }
+}
@@ -51,6 +51,9 @@ diesel::table! {
         updated_at -> Timestamptz,
         is_active -> Nullable<Bool>,
         database_name -> Nullable<Varchar>,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Uuid>,
+        is_default -> Bool,
     }
 }
 
@@ -79,6 +82,10 @@ diesel::table! {
         current_tool -> Nullable<Text>,
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Uuid>,
+        parent_session_id -> Nullable<Uuid>,
+        forked_at_index -> Nullable<Int4>,
     }
 }
 
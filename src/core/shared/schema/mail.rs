@@ -26,6 +26,7 @@ diesel::table! {
         smtp_port -> Int4,
         username -> Varchar,
         password_encrypted -> Text,
+        default_folder -> Varchar,
         is_primary -> Bool,
         is_active -> Bool,
         created_at -> Timestamptz,
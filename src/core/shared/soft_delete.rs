@@ -0,0 +1,273 @@
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{extract::State, Json};
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Nullable, Timestamptz, Uuid as DieselUuid};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// Tables with `deleted_at` / `deleted_by` columns. Default queries on these tables
+/// must filter `deleted_at IS NULL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoftDeleteTable {
+    Bots,
+    UserSessions,
+}
+
+impl SoftDeleteTable {
+    pub const ALL: [SoftDeleteTable; 2] = [SoftDeleteTable::Bots, SoftDeleteTable::UserSessions];
+
+    pub fn table_name(self) -> &'static str {
+        match self {
+            Self::Bots => "bots",
+            Self::UserSessions => "user_sessions",
+        }
+    }
+}
+
+/// Marks a row deleted. Returns `false` if the row does not exist or is already deleted.
+pub fn soft_delete(
+    conn: &mut PgConnection,
+    table: SoftDeleteTable,
+    id: Uuid,
+    deleted_by: Option<Uuid>,
+) -> QueryResult<bool> {
+    let sql = format!(
+        "UPDATE {} SET deleted_at = NOW(), deleted_by = $2 WHERE id = $1 AND deleted_at IS NULL",
+        table.table_name()
+    );
+    let updated = diesel::sql_query(sql)
+        .bind::<DieselUuid, _>(id)
+        .bind::<Nullable<DieselUuid>, _>(deleted_by)
+        .execute(conn)?;
+    Ok(updated > 0)
+}
+
+/// Clears the deletion marker. Returns `false` if the row is missing or not deleted.
+pub fn restore(conn: &mut PgConnection, table: SoftDeleteTable, id: Uuid) -> QueryResult<bool> {
+    let sql = format!(
+        "UPDATE {} SET deleted_at = NULL, deleted_by = NULL WHERE id = $1 AND deleted_at IS NOT NULL",
+        table.table_name()
+    );
+    let updated = diesel::sql_query(sql)
+        .bind::<DieselUuid, _>(id)
+        .execute(conn)?;
+    Ok(updated > 0)
+}
+
+/// Permanently removes rows soft-deleted before `cutoff`.
+pub fn purge(
+    conn: &mut PgConnection,
+    table: SoftDeleteTable,
+    cutoff: DateTime<Utc>,
+) -> QueryResult<usize> {
+    let sql = format!(
+        "DELETE FROM {} WHERE deleted_at IS NOT NULL AND deleted_at < $1",
+        table.table_name()
+    );
+    diesel::sql_query(sql)
+        .bind::<Timestamptz, _>(cutoff)
+        .execute(conn)
+}
+
+pub fn purge_cutoff(now: DateTime<Utc>, retention_days: i64) -> DateTime<Utc> {
+    now - Duration::days(retention_days.max(0))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RestoreRequest {
+    pub table: SoftDeleteTable,
+    pub id: Uuid,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PurgeRequest {
+    pub retention_days: Option<i64>,
+    pub tables: Option<Vec<SoftDeleteTable>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurgedTable {
+    pub table: SoftDeleteTable,
+    pub purged: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurgeResponse {
+    pub cutoff: DateTime<Utc>,
+    pub tables: Vec<PurgedTable>,
+}
+
+pub(crate) fn require_admin(user: &AuthenticatedUser) -> Result<(), ApiError> {
+    if user.is_admin() {
+        Ok(())
+    } else {
+        Err(ApiError::forbidden("Administrator role required"))
+    }
+}
+
+async fn with_conn<T, F>(state: &Arc<AppState>, f: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> QueryResult<T> + Send + 'static,
+{
+    let pool = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        f(&mut conn).map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+}
+
+pub async fn handle_restore(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<RestoreRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    require_admin(&user)?;
+
+    let (table, id) = (req.table, req.id);
+    if !with_conn(&state, move |conn| restore(conn, table, id)).await? {
+        return Err(ApiError::not_found(format!(
+            "No deleted row {} in {}",
+            id,
+            table.table_name()
+        )));
+    }
+
+    info!(
+        "Restored {} {} (by {})",
+        table.table_name(),
+        id,
+        user.user_id
+    );
+    Ok(Json(
+        serde_json::json!({ "success": true, "table": table, "id": id }),
+    ))
+}
+
+pub async fn handle_purge(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<PurgeRequest>,
+) -> Result<Json<PurgeResponse>, ApiError> {
+    require_admin(&user)?;
+
+    let retention_days = req.retention_days.unwrap_or(DEFAULT_RETENTION_DAYS);
+    if retention_days < 0 {
+        return Err(ApiError::bad_request("retention_days must not be negative"));
+    }
+    let cutoff = purge_cutoff(Utc::now(), retention_days);
+    let tables = req.tables.unwrap_or_else(|| SoftDeleteTable::ALL.to_vec());
+
+    let purged = with_conn(&state, move |conn| {
+        tables
+            .into_iter()
+            .map(|table| purge(conn, table, cutoff).map(|purged| PurgedTable { table, purged }))
+            .collect::<QueryResult<Vec<_>>>()
+    })
+    .await?;
+
+    for entry in &purged {
+        info!(
+            "Purged {} soft-deleted rows from {} older than {} (by {})",
+            entry.purged,
+            entry.table.table_name(),
+            cutoff,
+            user.user_id
+        );
+    }
+
+    Ok(Json(PurgeResponse {
+        cutoff,
+        tables: purged,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::auth_api::{
+        auth_middleware_with_providers, AuthConfig, AuthMiddlewareState, Role,
+    };
+    use crate::security::AuthProviderRegistry;
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use axum::{routing::post, Router};
+    use tower::ServiceExt;
+
+    /// Mounts an admin-gated route behind the same auth middleware the server uses.
+    fn app() -> Router {
+        let state = AuthMiddlewareState::new(
+            Arc::new(AuthConfig::default()),
+            Arc::new(AuthProviderRegistry::new()),
+        );
+        Router::new()
+            .route(
+                "/api/admin/deleted/restore",
+                post(|user: AuthenticatedUser| async move { require_admin(&user).map(|()| "ok") }),
+            )
+            .layer(axum::middleware::from_fn(move |req, next| {
+                let state = state.clone();
+                async move { auth_middleware_with_providers(req, next, state).await }
+            }))
+    }
+
+    async fn post_restore(user_id: Option<Uuid>) -> StatusCode {
+        let mut request = Request::post("/api/admin/deleted/restore");
+        if let Some(id) = user_id {
+            request = request.header("X-User-ID", id.to_string());
+        }
+        app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_gate_sees_user_from_auth_layer() {
+        assert_eq!(post_restore(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            post_restore(Some(Uuid::new_v4())).await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[test]
+    fn test_require_admin_accepts_admin_roles() {
+        let user = AuthenticatedUser::new(Uuid::new_v4(), "alice".to_string());
+        assert!(require_admin(&user).is_err());
+        assert!(require_admin(&user.clone().with_role(Role::Admin)).is_ok());
+        assert!(require_admin(&user.with_role(Role::SuperAdmin)).is_ok());
+    }
+
+    #[test]
+    fn test_purge_cutoff() {
+        let now = Utc::now();
+        assert_eq!(purge_cutoff(now, 30), now - Duration::days(30));
+        assert_eq!(purge_cutoff(now, -5), now);
+    }
+
+    #[test]
+    fn test_table_names_deserialize() {
+        let req: RestoreRequest = serde_json::from_value(serde_json::json!({
+            "table": "user_sessions",
+            "id": Uuid::nil()
+        }))
+        .unwrap();
+        assert_eq!(req.table, SoftDeleteTable::UserSessions);
+        assert_eq!(req.table.table_name(), "user_sessions");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::auto_task::TaskManifest;
 use crate::core::bot::channels::{ChannelAdapter, VoiceAdapter, WebChannelAdapter};
+use crate::core::bot::generation::ActiveGenerations;
 use crate::core::bot_database::BotDatabaseManager;
 use crate::core::config::AppConfig;
 #[cfg(any(feature = "research", feature = "llm"))]
@@ -22,6 +23,9 @@ use crate::security::auth_provider::AuthProviderRegistry;
 use crate::security::jwt::JwtManager;
 use crate::security::rbac_middleware::RbacManager;
 use crate::core::shared::models::BotResponse;
+use crate::core::shared::db_pool::{route, QueryKind};
+use crate::core::shared::message_bus::{InProcessBus, MessageBus};
+use crate::core::shared::ws_routing::SocketRouter;
 use crate::core::shared::utils::DbPool;
 #[cfg(feature = "tasks")]
 use crate::tasks::{TaskEngine, TaskScheduler};
@@ -379,11 +383,16 @@ pub struct AppState {
     #[cfg(not(feature = "drive"))]
     #[allow(non_snake_case)]
     pub drive: Option<crate::core::shared::state::NoDrive>,
+    /// Where sheets and documents are stored; `DRIVE_BACKEND` picks S3 or a local directory.
+    #[cfg(feature = "drive")]
+    pub object_store: Option<Arc<dyn crate::drive::object_store::ObjectStore>>,
     #[cfg(feature = "cache")]
     pub cache: Option<Arc<RedisClient>>,
     pub bucket_name: String,
     pub config: Option<AppConfig>,
     pub conn: DbPool,
+    /// Read-replica pool; `None` when `DATABASE_REPLICA_URL` is unset. Use `read_pool()`.
+    pub read_conn: Option<DbPool>,
     pub database_url: String,
     pub bot_database_manager: Arc<BotDatabaseManager>,
     pub session_manager: Arc<tokio::sync::Mutex<SessionManager>>,
@@ -398,8 +407,8 @@ pub struct AppState {
     pub auth_service: Arc<tokio::sync::Mutex<AuthService>>,
     pub channels: Arc<tokio::sync::Mutex<HashMap<String, Arc<dyn ChannelAdapter>>>>,
     pub response_channels: Arc<tokio::sync::Mutex<HashMap<String, mpsc::Sender<BotResponse>>>>,
-    /// Active streaming sessions for cancellation: session_id → cancellation sender
-    pub active_streams: Arc<tokio::sync::Mutex<HashMap<String, broadcast::Sender<()>>>>,
+    /// Active streaming sessions for cancellation: session_id → generation
+    pub active_streams: Arc<ActiveGenerations>,
     /// Blocking channels for HEAR: session_id → sender. Rhai thread blocks on receiver.
     pub hear_channels: Arc<std::sync::Mutex<HashMap<uuid::Uuid, std::sync::mpsc::SyncSender<String>>>>,
     pub web_adapter: Arc<WebChannelAdapter>,
@@ -409,7 +418,10 @@ pub struct AppState {
     #[cfg(feature = "tasks")]
     pub task_engine: Arc<TaskEngine>,
     pub extensions: Extensions,
-    pub attendant_broadcast: Option<broadcast::Sender<AttendantNotification>>,
+    /// Pub/sub for attendant notifications and cross-instance delivery.
+    pub message_bus: Arc<dyn MessageBus>,
+    /// Routes responses to the instance holding a session's websocket.
+    pub ws_router: Arc<SocketRouter>,
     pub task_progress_broadcast: Option<broadcast::Sender<TaskProgressEvent>>,
     pub billing_alert_broadcast: Option<broadcast::Sender<BillingAlertNotification>>,
     pub task_manifests: Arc<std::sync::RwLock<HashMap<String, TaskManifest>>>,
@@ -429,11 +441,14 @@ impl Clone for AppState {
         Self {
             #[cfg(feature = "drive")]
             drive: self.drive.clone(),
+            #[cfg(feature = "drive")]
+            object_store: self.object_store.clone(),
             #[cfg(not(feature = "drive"))]
             drive: None,
             bucket_name: self.bucket_name.clone(),
             config: self.config.clone(),
             conn: self.conn.clone(),
+            read_conn: self.read_conn.clone(),
             database_url: self.database_url.clone(),
             bot_database_manager: Arc::clone(&self.bot_database_manager),
             #[cfg(feature = "cache")]
@@ -459,7 +474,8 @@ impl Clone for AppState {
             #[cfg(feature = "tasks")]
             task_engine: Arc::clone(&self.task_engine),
             extensions: self.extensions.clone(),
-            attendant_broadcast: self.attendant_broadcast.clone(),
+            message_bus: Arc::clone(&self.message_bus),
+            ws_router: Arc::clone(&self.ws_router),
             task_progress_broadcast: self.task_progress_broadcast.clone(),
             billing_alert_broadcast: self.billing_alert_broadcast.clone(),
             task_manifests: Arc::clone(&self.task_manifests),
@@ -490,6 +506,7 @@ impl std::fmt::Debug for AppState {
             .field("bucket_name", &self.bucket_name)
             .field("config", &self.config.is_some())
             .field("conn", &"DbPool")
+            .field("read_conn", &self.read_conn.is_some())
             .field("database_url", &"[REDACTED]")
             .field("bot_database_manager", &"Arc<BotDatabaseManager>")
             .field("session_manager", &"Arc<Mutex<SessionManager>>")
@@ -521,7 +538,8 @@ impl std::fmt::Debug for AppState {
 
         debug
             .field("extensions", &self.extensions)
-            .field("attendant_broadcast", &self.attendant_broadcast.is_some())
+            .field("message_bus", &self.message_bus.backend())
+            .field("ws_router", &self.ws_router.instance_id())
             .field(
                 "task_progress_broadcast",
                 &self.task_progress_broadcast.is_some(),
@@ -537,6 +555,16 @@ impl std::fmt::Debug for AppState {
 }
 
 impl AppState {
+    /// Pool for the given query kind: the replica for reads when configured, else the primary.
+    pub fn pool_for(&self, kind: QueryKind) -> &DbPool {
+        route(&self.conn, self.read_conn.as_ref(), kind)
+    }
+
+    /// Pool for read-only queries that tolerate replication lag.
+    pub fn read_pool(&self) -> &DbPool {
+        self.pool_for(QueryKind::Read)
+    }
+
     pub fn broadcast_task_progress(&self, event: TaskProgressEvent) {
         log::info!(
             "Broadcasting: task_id={}, step={}, message={}",
@@ -639,14 +667,17 @@ impl Default for AppState {
         let conn = pool.get().expect("Failed to get test database connection");
         let session_manager = SessionManager::new(conn, None);
 
-        let (attendant_tx, _) = broadcast::channel(100);
         let (task_progress_tx, _) = broadcast::channel(100);
+        let response_channels = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+        let message_bus: Arc<dyn MessageBus> = Arc::new(InProcessBus::default());
 
         let bot_database_manager = Arc::new(BotDatabaseManager::new(pool.clone(), &database_url));
 
         Self {
             #[cfg(feature = "drive")]
             drive: None,
+            #[cfg(feature = "drive")]
+            object_store: None,
             #[cfg(not(feature = "drive"))]
             drive: None,
             #[cfg(feature = "cache")]
@@ -654,6 +685,7 @@ impl Default for AppState {
             bucket_name: "test-bucket".to_string(),
             config: None,
             conn: pool.clone(),
+            read_conn: None,
             database_url,
             bot_database_manager,
             session_manager: Arc::new(tokio::sync::Mutex::new(session_manager)),
@@ -667,8 +699,8 @@ impl Default for AppState {
             #[cfg(feature = "directory")]
             auth_service: Arc::new(tokio::sync::Mutex::new(create_mock_auth_service())),
             channels: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
-            response_channels: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
-            active_streams: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            response_channels: Arc::clone(&response_channels),
+            active_streams: Arc::new(ActiveGenerations::default()),
             hear_channels: Arc::new(std::sync::Mutex::new(HashMap::new())),
             web_adapter: Arc::new(WebChannelAdapter::new()),
             voice_adapter: Arc::new(VoiceAdapter::new()),
@@ -677,7 +709,11 @@ impl Default for AppState {
             #[cfg(feature = "tasks")]
             task_engine: Arc::new(TaskEngine::new(pool)),
             extensions: Extensions::new(),
-            attendant_broadcast: Some(attendant_tx),
+            ws_router: Arc::new(SocketRouter::local_only(
+                response_channels,
+                Arc::clone(&message_bus),
+            )),
+            message_bus,
             task_progress_broadcast: Some(task_progress_tx),
             billing_alert_broadcast: None,
             task_manifests: Arc::new(std::sync::RwLock::new(HashMap::new())),
@@ -139,11 +139,12 @@ impl ChannelAdapter for MockChannelAdapter {
     }
 }
 
-#[derive(Debug)]
 pub struct TestAppStateBuilder {
     database_url: Option<String>,
     bucket_name: String,
     config: Option<AppConfig>,
+    #[cfg(feature = "drive")]
+    object_store: Option<Arc<dyn crate::drive::object_store::ObjectStore>>,
 }
 
 impl TestAppStateBuilder {
@@ -152,6 +153,8 @@ impl TestAppStateBuilder {
             database_url: None,
             bucket_name: "test-bucket".to_string(),
             config: None,
+            #[cfg(feature = "drive")]
+            object_store: None,
         }
     }
 
@@ -170,6 +173,15 @@ impl TestAppStateBuilder {
         self
     }
 
+    #[cfg(feature = "drive")]
+    pub fn with_object_store(
+        mut self,
+        store: Arc<dyn crate::drive::object_store::ObjectStore>,
+    ) -> Self {
+        self.object_store = Some(store);
+        self
+    }
+
     pub fn build(self) -> Result<AppState, Box<dyn std::error::Error + Send + Sync>> {
         let database_url = self
             .database_url
@@ -186,9 +198,11 @@ impl TestAppStateBuilder {
         let conn = pool.get()?;
         let session_manager = SessionManager::new(conn, None);
 
-        let (attendant_tx, _) = broadcast::channel(100);
 
         let (task_progress_tx, _) = broadcast::channel(100);
+        let response_channels = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+        let message_bus: Arc<dyn crate::core::shared::message_bus::MessageBus> =
+            Arc::new(crate::core::shared::message_bus::InProcessBus::default());
 
         let bot_database_manager = Arc::new(BotDatabaseManager::new(pool.clone(), &database_url));
 
@@ -196,12 +210,13 @@ impl TestAppStateBuilder {
             #[cfg(feature = "drive")]
             drive: None,
             #[cfg(feature = "drive")]
-            s3_client: None,
+            object_store: self.object_store,
             #[cfg(feature = "cache")]
             cache: None,
             bucket_name: self.bucket_name,
             config: self.config,
             conn: pool.clone(),
+            read_conn: None,
             database_url,
             bot_database_manager,
             session_manager: Arc::new(tokio::sync::Mutex::new(session_manager)),
@@ -215,7 +230,8 @@ impl TestAppStateBuilder {
             #[cfg(feature = "directory")]
             auth_service: Arc::new(tokio::sync::Mutex::new(create_mock_auth_service())),
             channels: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
-            response_channels: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            response_channels: Arc::clone(&response_channels),
+            active_streams: Arc::new(crate::core::bot::generation::ActiveGenerations::default()),
             hear_channels: Arc::new(std::sync::Mutex::new(HashMap::new())),
             web_adapter: Arc::new(WebChannelAdapter::new()),
             voice_adapter: Arc::new(VoiceAdapter::new()),
@@ -224,7 +240,11 @@ impl TestAppStateBuilder {
             #[cfg(feature = "tasks")]
             task_engine: Arc::new(TaskEngine::new(pool)),
             extensions: Extensions::new(),
-            attendant_broadcast: Some(attendant_tx),
+            ws_router: Arc::new(crate::core::shared::ws_routing::SocketRouter::local_only(
+                response_channels,
+                Arc::clone(&message_bus),
+            )),
+            message_bus,
             task_progress_broadcast: Some(task_progress_tx),
             billing_alert_broadcast: None,
             task_manifests: Arc::new(std::sync::RwLock::new(HashMap::new())),
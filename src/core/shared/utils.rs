@@ -356,30 +356,32 @@ pub fn establish_pg_connection() -> Result<PgConnection> {
 
 pub type DbPool = Pool<ConnectionManager<PgConnection>>;
 
+/// Builds the primary pool with sizing from the environment, for callers that run before
+/// `AppConfig` is loaded.
 pub fn create_conn() -> Result<DbPool, anyhow::Error> {
+    create_conn_with(&crate::core::config::DatabasePoolConfig::from_env())
+}
+
+pub fn create_conn_with(
+    config: &crate::core::config::DatabasePoolConfig,
+) -> Result<DbPool, anyhow::Error> {
     let database_url = get_database_url_sync()?;
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    Pool::builder()
-        .max_size(10)
-        .min_idle(Some(1))
-        .connection_timeout(std::time::Duration::from_secs(5))
-        .idle_timeout(Some(std::time::Duration::from_secs(300)))
-        .max_lifetime(Some(std::time::Duration::from_secs(1800)))
-        .build(manager)
-        .map_err(|e| anyhow::anyhow!("Failed to create database pool: {}", e))
-}
-
-pub async fn create_conn_async() -> Result<DbPool, anyhow::Error> {
+    crate::core::shared::db_pool::build_pool(
+        &database_url,
+        config,
+        crate::core::shared::db_pool::primary_pool_stats(),
+    )
+}
+
+pub async fn create_conn_async(
+    config: &crate::core::config::DatabasePoolConfig,
+) -> Result<DbPool, anyhow::Error> {
     let database_url = get_database_url().await?;
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    Pool::builder()
-        .max_size(10)
-        .min_idle(Some(1))
-        .connection_timeout(std::time::Duration::from_secs(5))
-        .idle_timeout(Some(std::time::Duration::from_secs(300)))
-        .max_lifetime(Some(std::time::Duration::from_secs(1800)))
-        .build(manager)
-        .map_err(|e| anyhow::anyhow!("Failed to create database pool: {}", e))
+    crate::core::shared::db_pool::build_pool(
+        &database_url,
+        config,
+        crate::core::shared::db_pool::primary_pool_stats(),
+    )
 }
 
 pub fn parse_database_url(url: &str) -> (String, String, String, u32, String) {
@@ -419,18 +421,7 @@ pub fn run_migrations(pool: &DbPool) -> Result<(), Box<dyn std::error::Error + S
 pub fn run_migrations_on_conn(
     conn: &mut diesel::PgConnection,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-
-    // Flat migrations with version-ordinal-feature naming
-    const FLAT_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
-    conn.run_pending_migrations(FLAT_MIGRATIONS).map_err(|e| {
-        Box::new(std::io::Error::other(format!(
-            "Migration error: {}",
-            e
-        ))) as Box<dyn std::error::Error + Send + Sync>
-    })?;
-
-    Ok(())
+    crate::core::shared::migrations::run_pending(conn).map(|_| ())
 }
 
 pub use crate::security::sql_guard::sanitize_identifier;
@@ -531,7 +522,7 @@ pub fn create_tls_client(timeout_secs: Option<u64>) -> Client {
 /// A reqwest::Client configured for TLS verification
 pub fn create_tls_client_with_ca(ca_cert_path: &str, timeout_secs: Option<u64>) -> Client {
     let timeout = Duration::from_secs(timeout_secs.unwrap_or(30));
-    let mut builder = Client::builder().timeout(timeout);
+    let mut builder = super::outbound_proxy::client_builder().timeout(timeout);
 
     // Try to load local CA cert (dev stack with self-signed certs)
     // If it doesn't exist, we use system CA store (production with public certs)
@@ -0,0 +1,134 @@
+//! Server-side keepalive for websocket connections.
+//!
+//! The send loop pings the client every `interval`; the receive loop calls
+//! [`Liveness::touch`] for every frame it gets (browsers answer pings automatically).
+//! A connection that stays silent for a full interval plus `pong_timeout` is considered
+//! half-open, and [`reaped`] resolves so the handler can tear it down and run its normal
+//! disconnect cleanup.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatConfig {
+    pub interval: Duration,
+    pub pong_timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            pong_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl HeartbeatConfig {
+    /// How long a connection may go without any frame before it is dropped.
+    pub fn max_idle(&self) -> Duration {
+        self.interval + self.pong_timeout
+    }
+
+    pub fn ping_ticker(&self) -> tokio::time::Interval {
+        let mut ticker = tokio::time::interval_at(
+            tokio::time::Instant::now() + self.interval,
+            self.interval,
+        );
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker
+    }
+}
+
+/// Time of the last frame received from the peer, shared between the send and receive
+/// loops of a connection.
+#[derive(Debug, Clone)]
+pub struct Liveness {
+    started: Instant,
+    last_seen_ms: Arc<AtomicU64>,
+}
+
+impl Default for Liveness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Liveness {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            last_seen_ms: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn touch(&self) {
+        let elapsed = u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX);
+        self.last_seen_ms.store(elapsed, Ordering::Relaxed);
+    }
+
+    pub fn idle(&self) -> Duration {
+        let last_seen = Duration::from_millis(self.last_seen_ms.load(Ordering::Relaxed));
+        self.started.elapsed().saturating_sub(last_seen)
+    }
+}
+
+/// Resolves once the peer has been silent for longer than `config.max_idle()`.
+pub async fn reaped(liveness: &Liveness, config: HeartbeatConfig) {
+    loop {
+        let idle = liveness.idle();
+        let max_idle = config.max_idle();
+        if idle > max_idle {
+            return;
+        }
+        tokio::time::sleep(max_idle - idle + Duration::from_millis(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> HeartbeatConfig {
+        HeartbeatConfig {
+            interval: Duration::from_millis(40),
+            pong_timeout: Duration::from_millis(20),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_silent_client_is_reaped_after_timeout() {
+        let liveness = Liveness::new();
+        let started = Instant::now();
+
+        tokio::time::timeout(Duration::from_secs(2), reaped(&liveness, config()))
+            .await
+            .expect("silent connection should be reaped");
+        assert!(started.elapsed() >= config().max_idle());
+    }
+
+    #[tokio::test]
+    async fn test_responsive_client_is_kept() {
+        let liveness = Liveness::new();
+        let responder = {
+            let liveness = liveness.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    liveness.touch();
+                }
+            })
+        };
+
+        let result =
+            tokio::time::timeout(Duration::from_millis(300), reaped(&liveness, config())).await;
+        responder.abort();
+        assert!(result.is_err(), "answering client must not be reaped");
+
+        // Once it stops answering it is reaped.
+        tokio::time::timeout(Duration::from_secs(2), reaped(&liveness, config()))
+            .await
+            .expect("client should be reaped after it stops answering");
+    }
+}
@@ -0,0 +1,370 @@
+//! Delivers bot responses to the instance holding a session's websocket.
+//!
+//! Sockets register in `response_channels` of the process that accepted them. With a
+//! [`SocketRegistry`] each connect also records which instance owns the session, so a
+//! response produced elsewhere is published on the owner's bus topic and its relay hands
+//! it to the socket. Without a registry (no Redis) delivery stays within the process.
+
+use crate::core::shared::message_bus::MessageBus;
+use crate::core::shared::models::BotResponse;
+use async_trait::async_trait;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, Mutex};
+
+pub type ResponseChannels = Arc<Mutex<HashMap<String, mpsc::Sender<BotResponse>>>>;
+
+/// Records which instance holds each session's websocket.
+#[async_trait]
+pub trait SocketRegistry: Send + Sync {
+    async fn claim(
+        &self,
+        session_id: &str,
+        instance_id: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Drops the record, unless another instance has claimed the session since.
+    async fn release(
+        &self,
+        session_id: &str,
+        instance_id: &str,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    async fn owner(&self, session_id: &str)
+        -> Result<Option<String>, Box<dyn Error + Send + Sync>>;
+}
+
+/// `BOTSERVER_INSTANCE_ID`, or a random id for this process.
+pub fn instance_id_from_env() -> String {
+    std::env::var("BOTSERVER_INSTANCE_ID")
+        .ok()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+fn instance_topic(instance_id: &str) -> String {
+    format!("response:{instance_id}")
+}
+
+pub struct SocketRouter {
+    instance_id: String,
+    local: ResponseChannels,
+    registry: Option<Arc<dyn SocketRegistry>>,
+    bus: Arc<dyn MessageBus>,
+}
+
+impl SocketRouter {
+    pub fn new(
+        instance_id: String,
+        local: ResponseChannels,
+        registry: Option<Arc<dyn SocketRegistry>>,
+        bus: Arc<dyn MessageBus>,
+    ) -> Self {
+        Self {
+            instance_id,
+            local,
+            registry,
+            bus,
+        }
+    }
+
+    /// A router that only delivers to sockets of this process.
+    pub fn local_only(local: ResponseChannels, bus: Arc<dyn MessageBus>) -> Self {
+        Self::new(instance_id_from_env(), local, None, bus)
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    pub async fn connect(&self, session_id: &str, tx: mpsc::Sender<BotResponse>) {
+        self.local.lock().await.insert(session_id.to_string(), tx);
+        if let Some(registry) = &self.registry {
+            if let Err(e) = registry.claim(session_id, &self.instance_id).await {
+                warn!(
+                    "Failed to register socket for session {}: {}",
+                    session_id, e
+                );
+            }
+        }
+    }
+
+    pub async fn disconnect(&self, session_id: &str) {
+        self.local.lock().await.remove(session_id);
+        if let Some(registry) = &self.registry {
+            if let Err(e) = registry.release(session_id, &self.instance_id).await {
+                warn!(
+                    "Failed to unregister socket for session {}: {}",
+                    session_id, e
+                );
+            }
+        }
+    }
+
+    async fn send_local(&self, response: BotResponse) -> Option<bool> {
+        let tx = self.local.lock().await.get(&response.session_id).cloned()?;
+        Some(tx.send(response).await.is_ok())
+    }
+
+    /// Sends a response to the session's socket, on this instance or the one owning it.
+    /// Returns `false` when no instance holds the socket or forwarding failed.
+    pub async fn deliver(&self, response: BotResponse) -> bool {
+        let session_id = response.session_id.clone();
+        let Some(registry) = &self.registry else {
+            return self.send_local(response).await.unwrap_or(false);
+        };
+        let owner = match registry.owner(&session_id).await {
+            Ok(owner) => owner,
+            Err(e) => {
+                warn!(
+                    "Failed to look up socket owner of session {}: {}",
+                    session_id, e
+                );
+                None
+            }
+        };
+        match owner {
+            Some(owner) if owner != self.instance_id => {
+                let published = match serde_json::to_value(&response) {
+                    Ok(payload) => self.bus.publish(&instance_topic(&owner), payload).await,
+                    Err(e) => Err(e.into()),
+                };
+                match published {
+                    Ok(()) => {
+                        debug!("Response for session {} routed to {}", session_id, owner);
+                        true
+                    }
+                    Err(e) => {
+                        warn!("Failed to route response for session {}: {}", session_id, e);
+                        false
+                    }
+                }
+            }
+            _ => self.send_local(response).await.unwrap_or(false),
+        }
+    }
+
+    /// Delivers responses routed here by other instances. Does nothing without a registry.
+    pub async fn start_relay(self: &Arc<Self>) {
+        if self.registry.is_none() {
+            return;
+        }
+        let mut receiver = match self.bus.subscribe(&instance_topic(&self.instance_id)).await {
+            Ok(receiver) => receiver,
+            Err(e) => {
+                warn!("Cross-instance response delivery unavailable: {}", e);
+                return;
+            }
+        };
+        let router = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                let message = match receiver.recv().await {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("Response relay lagged behind by {} messages", n);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                match serde_json::from_value::<BotResponse>(message.payload) {
+                    Ok(response) => {
+                        if router.send_local(response).await.is_none() {
+                            debug!("Routed response arrived after its socket closed");
+                        }
+                    }
+                    Err(e) => warn!("Invalid routed response: {}", e),
+                }
+            }
+        });
+    }
+}
+
+/// How long a socket ownership record lives without being claimed again.
+const OWNERSHIP_TTL_SECS: u64 = 24 * 3600;
+
+/// Routes across instances when the message bus is shared and Redis is available,
+/// otherwise within this process.
+pub fn socket_router_from_env(
+    local: ResponseChannels,
+    bus: Arc<dyn MessageBus>,
+    #[cfg(feature = "cache")] redis: Option<&Arc<redis::Client>>,
+) -> SocketRouter {
+    #[cfg(feature = "cache")]
+    if let Some(client) = redis.filter(|_| bus.is_shared()) {
+        let registry = RedisSocketRegistry::new(Arc::clone(client), OWNERSHIP_TTL_SECS);
+        let router =
+            SocketRouter::new(instance_id_from_env(), local, Some(Arc::new(registry)), bus);
+        log::info!(
+            "Websocket routing across instances as {}",
+            router.instance_id()
+        );
+        return router;
+    }
+    SocketRouter::local_only(local, bus)
+}
+
+#[cfg(feature = "cache")]
+pub use redis_registry::RedisSocketRegistry;
+
+#[cfg(feature = "cache")]
+mod redis_registry {
+    use super::*;
+    use redis::AsyncCommands;
+
+    const KEY_PREFIX: &str = "botserver:ws:";
+
+    /// Ownership records as `botserver:ws:<session_id>` keys holding the instance id.
+    /// They expire after `ttl_secs`, so sessions of a crashed instance do not stay routed
+    /// to it forever.
+    pub struct RedisSocketRegistry {
+        client: Arc<redis::Client>,
+        ttl_secs: u64,
+    }
+
+    impl RedisSocketRegistry {
+        pub fn new(client: Arc<redis::Client>, ttl_secs: u64) -> Self {
+            Self { client, ttl_secs }
+        }
+    }
+
+    #[async_trait]
+    impl SocketRegistry for RedisSocketRegistry {
+        async fn claim(
+            &self,
+            session_id: &str,
+            instance_id: &str,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            conn.set_ex::<_, _, ()>(
+                format!("{KEY_PREFIX}{session_id}"),
+                instance_id,
+                self.ttl_secs,
+            )
+            .await?;
+            Ok(())
+        }
+
+        async fn release(
+            &self,
+            session_id: &str,
+            instance_id: &str,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            let _: i64 = redis::Script::new(
+                "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+                 return redis.call('DEL', KEYS[1]) else return 0 end",
+            )
+            .key(format!("{KEY_PREFIX}{session_id}"))
+            .arg(instance_id)
+            .invoke_async(&mut conn)
+            .await?;
+            Ok(())
+        }
+
+        async fn owner(
+            &self,
+            session_id: &str,
+        ) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+            let mut conn = self.client.get_multiplexed_async_connection().await?;
+            Ok(conn.get(format!("{KEY_PREFIX}{session_id}")).await?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::shared::message_bus::InProcessBus;
+    use std::time::Duration;
+
+    /// Stands in for Redis: one ownership table shared by all instances.
+    #[derive(Default)]
+    struct MockRegistry {
+        owners: std::sync::Mutex<HashMap<String, String>>,
+    }
+
+    #[async_trait]
+    impl SocketRegistry for MockRegistry {
+        async fn claim(
+            &self,
+            session_id: &str,
+            instance_id: &str,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let mut owners = self.owners.lock().unwrap();
+            owners.insert(session_id.to_string(), instance_id.to_string());
+            Ok(())
+        }
+
+        async fn release(
+            &self,
+            session_id: &str,
+            instance_id: &str,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            let mut owners = self.owners.lock().unwrap();
+            if owners.get(session_id).map(String::as_str) == Some(instance_id) {
+                owners.remove(session_id);
+            }
+            Ok(())
+        }
+
+        async fn owner(
+            &self,
+            session_id: &str,
+        ) -> Result<Option<String>, Box<dyn Error + Send + Sync>> {
+            Ok(self.owners.lock().unwrap().get(session_id).cloned())
+        }
+    }
+
+    fn response(session_id: &str, content: &str) -> BotResponse {
+        BotResponse {
+            bot_id: "bot".to_string(),
+            session_id: session_id.to_string(),
+            user_id: "user".to_string(),
+            channel: "web".to_string(),
+            content: content.to_string(),
+            message_type: botlib::MessageType::BOT_RESPONSE,
+            stream_token: None,
+            is_complete: true,
+            suggestions: vec![],
+            context_name: None,
+            context_length: 0,
+            context_max_length: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_reaches_socket_on_other_instance() {
+        // The shared bus and registry play the part of Redis between two instances.
+        let bus: Arc<dyn MessageBus> = Arc::new(InProcessBus::default());
+        let registry: Arc<dyn SocketRegistry> = Arc::new(MockRegistry::default());
+        let instance = |id: &str| {
+            Arc::new(SocketRouter::new(
+                id.to_string(),
+                ResponseChannels::default(),
+                Some(Arc::clone(&registry)),
+                Arc::clone(&bus),
+            ))
+        };
+        let (a, b) = (instance("a"), instance("b"));
+        a.start_relay().await;
+        b.start_relay().await;
+
+        let (tx, mut rx) = mpsc::channel(4);
+        a.connect("session-1", tx).await;
+
+        assert!(b.deliver(response("session-1", "from b")).await);
+        let received = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.content, "from b");
+
+        a.disconnect("session-1").await;
+        assert!(!b.deliver(response("session-1", "too late")).await);
+        assert!(registry.owner("session-1").await.unwrap().is_none());
+    }
+}
@@ -35,10 +35,15 @@ impl ApiUrls {
 
     // Sessions - JSON APIs
     pub const SESSIONS: &'static str = "/api/sessions";
+    pub const SESSIONS_SEARCH: &'static str = "/api/sessions/search";
     pub const SESSION_BY_ID: &'static str = "/api/sessions/:id";
     pub const SESSION_HISTORY: &'static str = "/api/sessions/:id/history";
+    pub const SESSION_VARIABLES: &'static str = "/api/sessions/:id/variables";
+    pub const SESSION_FORM: &'static str = "/api/sessions/:id/forms/:name";
     pub const SESSION_START: &'static str = "/api/sessions/:id/start";
     pub const SESSION_END: &'static str = "/api/sessions/:id/end";
+    pub const SESSION_FORK: &'static str = "/api/sessions/:id/fork";
+    pub const SESSION_CANCEL: &'static str = "/api/sessions/:id/cancel";
 
     // Bots - JSON APIs
     pub const BOTS: &'static str = "/api/bots";
@@ -64,6 +69,7 @@ impl ApiUrls {
     pub const EMAIL_ACCOUNT_BY_ID: &'static str = "/api/email/accounts/:id";
     pub const EMAIL_LIST: &'static str = "/api/email/list";
     pub const EMAIL_SEND: &'static str = "/api/email/send";
+    pub const EMAIL_OUTBOX_STATUS: &'static str = "/api/email/outbox/:id";
     pub const EMAIL_DRAFT: &'static str = "/api/email/draft";
     pub const EMAIL_FOLDERS: &'static str = "/api/email/folders/:account_id";
     pub const EMAIL_LATEST: &'static str = "/api/email/latest";
@@ -137,12 +143,21 @@ impl ApiUrls {
     // DNS - JSON APIs
     pub const DNS_REGISTER: &'static str = "/api/dns/register";
     pub const DNS_REMOVE: &'static str = "/api/dns/remove";
+    pub const DNS_RECORDS: &'static str = "/api/admin/dns/records";
+    pub const DNS_RECORD: &'static str = "/api/admin/dns/records/:name";
     pub const DNS_LIST: &'static str = "/api/dns/list";
     pub const DNS_UPDATE: &'static str = "/api/dns/update";
 
+    // Reverse proxy - JSON APIs
+    pub const PROXY_ROUTES: &'static str = "/api/admin/proxy/routes";
+    pub const PROXY_ROUTE: &'static str = "/api/admin/proxy/routes/:hostname";
+
     // Analytics - JSON APIs
     pub const ANALYTICS_DASHBOARD: &'static str = "/api/analytics/dashboard";
     pub const ANALYTICS_METRIC: &'static str = "/api/analytics/metric";
+    pub const ANALYTICS_TIMESERIES: &'static str = "/api/analytics/timeseries";
+    pub const ANALYTICS_LLM_COST: &'static str = "/api/analytics/llm/cost";
+    pub const ANALYTICS_EXPORT_SHEET: &'static str = "/api/analytics/export/sheet";
     pub const METRICS: &'static str = "/api/metrics";
 
     // Analytics - HTMX/HTML APIs
@@ -198,6 +213,9 @@ impl ApiUrls {
     pub const KB_DOCUMENT_BY_ID: &'static str = "/api/kb/documents/:id";
     pub const KB_INDEX: &'static str = "/api/kb/index";
     pub const KB_EMBEDDINGS: &'static str = "/api/kb/embeddings";
+    pub const KB_QUERY_DEBUG: &'static str = "/api/kb/query/debug";
+    pub const KB_REINDEX: &'static str = "/api/kb/reindex";
+    pub const KB_REINDEX_STATUS: &'static str = "/api/kb/reindex/:bot_id";
 
     // LLM - JSON APIs
     pub const LLM_CHAT: &'static str = "/api/llm/chat";
@@ -217,6 +235,14 @@ impl ApiUrls {
     pub const ATTENDANCE_RESPOND: &'static str = "/api/attendance/respond";
     pub const ATTENDANCE_KANBAN: &'static str = "/api/attendance/kanban";
     pub const ATTENDANCE_ASSIGN_BY_SKILL: &'static str = "/api/attendance/assign/by-skill";
+    pub const ATTENDANCE_HANDOFFS: &'static str = "/api/attendance/handoffs";
+    pub const ATTENDANCE_HANDOFF_CLAIM: &'static str =
+        "/api/attendance/handoffs/:session_id/claim";
+    pub const ATTENDANCE_HANDOFF_MESSAGES: &'static str =
+        "/api/attendance/handoffs/:session_id/messages";
+    pub const ATTENDANCE_HANDOFF_RELEASE: &'static str =
+        "/api/attendance/handoffs/:session_id/release";
+    pub const ATTENDANCE_PRESENCE: &'static str = "/api/attendance/presence";
     pub const ATTENDANCE_LLM_TIPS: &'static str = "/api/attendance/llm/tips";
     pub const ATTENDANCE_LLM_POLISH: &'static str = "/api/attendance/llm/polish";
     pub const ATTENDANCE_LLM_SMART_REPLIES: &'static str = "/api/attendance/llm/smart-replies";
@@ -362,6 +388,8 @@ impl ApiUrls {
     pub const PAPER_EXPORT_MD: &'static str = "/api/ui/paper/export/md";
     pub const PAPER_EXPORT_HTML: &'static str = "/api/ui/paper/export/html";
     pub const PAPER_EXPORT_TXT: &'static str = "/api/ui/paper/export/txt";
+    pub const PAPER_CITATIONS_FORMAT: &'static str = "/api/paper/citations/format";
+    pub const PAPER_BIBLIOGRAPHY: &'static str = "/api/paper/citations/bibliography";
 
     // Research - HTMX/HTML APIs
     pub const RESEARCH_COLLECTIONS: &'static str = "/api/ui/research/collections";
@@ -403,6 +431,7 @@ impl ApiUrls {
     pub const SOURCES_API_KEYS_BY_ID: &'static str = "/api/ui/sources/api-keys/:id";
     pub const SOURCES_MENTIONS: &'static str = "/api/ui/sources/mentions";
     pub const SOURCES_TOOLS: &'static str = "/api/ui/sources/tools";
+    pub const SOURCES_WEBSITES_STATUS: &'static str = "/api/ui/sources/websites/status";
 
     // Sources Knowledge Base - HTMX/HTML APIs
     pub const SOURCES_KB_UPLOAD: &'static str = "/api/ui/sources/kb/upload";
@@ -8,6 +8,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::dashboards::{dashboard_filters, dashboard_widgets, dashboards};
 use crate::core::shared::state::AppState;
 
@@ -89,7 +90,7 @@ pub async fn handle_list_dashboards(
 
 pub async fn handle_create_dashboard(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateDashboardRequest>,
+    ApiJson(req): ApiJson<CreateDashboardRequest>,
 ) -> Result<Json<Dashboard>, DashboardsError> {
     let pool = state.conn.clone();
 
@@ -179,7 +180,7 @@ pub async fn handle_get_dashboard(
 pub async fn handle_update_dashboard(
     State(state): State<Arc<AppState>>,
     Path(dashboard_id): Path<Uuid>,
-    Json(req): Json<UpdateDashboardRequest>,
+    ApiJson(req): ApiJson<UpdateDashboardRequest>,
 ) -> Result<Json<Dashboard>, DashboardsError> {
     let pool = state.conn.clone();
 
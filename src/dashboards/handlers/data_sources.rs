@@ -8,6 +8,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::dashboards::{conversational_queries, dashboard_data_sources};
 use crate::core::shared::state::AppState;
 
@@ -49,7 +50,7 @@ pub async fn handle_list_data_sources(
 
 pub async fn handle_create_data_source(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateDataSourceRequest>,
+    ApiJson(req): ApiJson<CreateDataSourceRequest>,
 ) -> Result<Json<DataSource>, DashboardsError> {
     let pool = state.conn.clone();
 
@@ -99,7 +100,7 @@ pub async fn handle_test_data_source(
 
 pub async fn handle_test_data_source_no_id(
     State(_state): State<Arc<AppState>>,
-    Json(_config): Json<serde_json::Value>,
+    ApiJson(_config): ApiJson<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>, DashboardsError> {
     Ok(Json(serde_json::json!({
         "success": true,
@@ -200,7 +201,7 @@ fn analyze_query_intent(query: &str) -> (WidgetType, String) {
 
 pub async fn handle_conversational_query(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ConversationalQueryRequest>,
+    ApiJson(req): ApiJson<ConversationalQueryRequest>,
 ) -> Result<Json<ConversationalQueryResponse>, DashboardsError> {
     let pool = state.conn.clone();
     let query_text = req.query.clone();
@@ -7,6 +7,7 @@ use diesel::prelude::*;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::dashboards::dashboard_widgets;
 use crate::core::shared::state::AppState;
 
@@ -17,7 +18,7 @@ use crate::dashboards::types::{AddWidgetRequest, UpdateWidgetRequest, Widget, Wi
 pub async fn handle_add_widget(
     State(state): State<Arc<AppState>>,
     Path(dashboard_id): Path<Uuid>,
-    Json(req): Json<AddWidgetRequest>,
+    ApiJson(req): ApiJson<AddWidgetRequest>,
 ) -> Result<Json<Widget>, DashboardsError> {
     let pool = state.conn.clone();
 
@@ -59,7 +60,7 @@ pub async fn handle_add_widget(
 pub async fn handle_update_widget(
     State(state): State<Arc<AppState>>,
     Path((dashboard_id, widget_id)): Path<(Uuid, Uuid)>,
-    Json(req): Json<UpdateWidgetRequest>,
+    ApiJson(req): ApiJson<UpdateWidgetRequest>,
 ) -> Result<Json<Widget>, DashboardsError> {
     let pool = state.conn.clone();
 
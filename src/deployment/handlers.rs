@@ -6,6 +6,7 @@ use axum::{
 };
 use std::sync::Arc;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 use super::types::*;
@@ -78,7 +79,7 @@ pub async fn get_app_types(
 /// Deploy an application to Forgejo
 pub async fn deploy_app(
     State(_state): State<Arc<AppState>>,
-    Json(request): Json<DeploymentRequest>,
+    ApiJson(request): ApiJson<DeploymentRequest>,
 ) -> Result<Json<DeploymentResponse>, DeploymentApiError> {
     log::info!(
         "Deployment request: org={:?}, app={}, type={}, env={}",
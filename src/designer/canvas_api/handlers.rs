@@ -8,6 +8,7 @@ use serde::Deserialize;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::designer::canvas_api::service::CanvasService;
 use crate::designer::canvas_api::types::*;
@@ -30,7 +31,7 @@ pub fn canvas_routes(state: Arc<AppState>) -> axum::Router<Arc<AppState>> {
 
 async fn create_canvas_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<CreateCanvasRequest>,
+    ApiJson(request): ApiJson<CreateCanvasRequest>,
 ) -> Result<Json<Canvas>, CanvasError> {
     let service = CanvasService::new(Arc::new(state.conn.clone()));
     let organization_id = Uuid::nil();
@@ -51,7 +52,7 @@ async fn get_canvas_handler(
 async fn add_element_handler(
     State(state): State<Arc<AppState>>,
     Path(canvas_id): Path<Uuid>,
-    Json(request): Json<AddElementRequest>,
+    ApiJson(request): ApiJson<AddElementRequest>,
 ) -> Result<Json<CanvasElement>, CanvasError> {
     let service = CanvasService::new(Arc::new(state.conn.clone()));
     let user_id = Uuid::nil();
@@ -62,7 +63,7 @@ async fn add_element_handler(
 async fn update_element_handler(
     State(state): State<Arc<AppState>>,
     Path((canvas_id, element_id)): Path<(Uuid, Uuid)>,
-    Json(request): Json<UpdateElementRequest>,
+    ApiJson(request): ApiJson<UpdateElementRequest>,
 ) -> Result<Json<CanvasElement>, CanvasError> {
     let service = CanvasService::new(Arc::new(state.conn.clone()));
     let user_id = Uuid::nil();
@@ -83,7 +84,7 @@ async fn delete_element_handler(
 async fn group_elements_handler(
     State(state): State<Arc<AppState>>,
     Path(canvas_id): Path<Uuid>,
-    Json(request): Json<GroupElementsRequest>,
+    ApiJson(request): ApiJson<GroupElementsRequest>,
 ) -> Result<Json<CanvasElement>, CanvasError> {
     let service = CanvasService::new(Arc::new(state.conn.clone()));
     let user_id = Uuid::nil();
@@ -94,7 +95,7 @@ async fn group_elements_handler(
 async fn add_layer_handler(
     State(state): State<Arc<AppState>>,
     Path(canvas_id): Path<Uuid>,
-    Json(request): Json<CreateLayerRequest>,
+    ApiJson(request): ApiJson<CreateLayerRequest>,
 ) -> Result<Json<Layer>, CanvasError> {
     let service = CanvasService::new(Arc::new(state.conn.clone()));
     let user_id = Uuid::nil();
@@ -105,7 +106,7 @@ async fn add_layer_handler(
 async fn export_canvas_handler(
     State(state): State<Arc<AppState>>,
     Path(canvas_id): Path<Uuid>,
-    Json(request): Json<ExportRequest>,
+    ApiJson(request): ApiJson<ExportRequest>,
 ) -> Result<Json<ExportResult>, CanvasError> {
     let service = CanvasService::new(Arc::new(state.conn.clone()));
     let result = service.export_canvas(canvas_id, request).await?;
@@ -1,6 +1,7 @@
 use super::types::*;
 use super::utils::*;
 use super::validators::validate_basic_code;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::urls::ApiUrls;
 use crate::core::shared::state::AppState;
 use axum::{
@@ -159,7 +160,7 @@ pub async fn handle_load_file(
 
 pub async fn handle_save(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<SaveRequest>,
+    ApiJson(payload): ApiJson<SaveRequest>,
 ) -> impl IntoResponse {
     let conn = state.conn.clone();
     let now = Utc::now();
@@ -219,7 +220,7 @@ pub async fn handle_save(
 
 pub async fn handle_validate(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<ValidateRequest>,
+    ApiJson(payload): ApiJson<ValidateRequest>,
 ) -> impl IntoResponse {
     let content = payload.content.unwrap_or_default();
     let validation = validate_basic_code(&content);
@@ -341,7 +342,7 @@ pub async fn handle_list_dialogs(State(state): State<Arc<AppState>>) -> impl Int
 
 pub async fn handle_create_dialog(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<SaveRequest>,
+    ApiJson(payload): ApiJson<SaveRequest>,
 ) -> impl IntoResponse {
     let conn = state.conn.clone();
     let now = Utc::now();
@@ -447,7 +448,7 @@ pub async fn handle_get_dialog(
 
 pub async fn handle_magic_suggestions(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<MagicRequest>,
+    ApiJson(request): ApiJson<MagicRequest>,
 ) -> impl IntoResponse {
     let mut suggestions = Vec::new();
     let nodes = &request.nodes;
@@ -1,5 +1,6 @@
 use super::types::*;
 use crate::auto_task::get_designer_error_context;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::core::shared::get_content_type;
 use axum::{extract::State, response::IntoResponse, Json};
@@ -9,7 +10,7 @@ use uuid::Uuid;
 
 pub async fn handle_editor_magic(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<EditorMagicRequest>,
+    ApiJson(request): ApiJson<EditorMagicRequest>,
 ) -> impl IntoResponse {
     let code = request.code;
 
@@ -126,7 +127,7 @@ If the code is already good, respond with:
 
 pub async fn handle_designer_modify(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<DesignerModifyRequest>,
+    ApiJson(request): ApiJson<DesignerModifyRequest>,
 ) -> impl IntoResponse {
     let app = &request.app_name;
     let msg_preview = &request.message[..request.message.len().min(100)];
@@ -175,7 +176,10 @@ pub fn get_designer_session(
 
     let mut conn = state.conn.get()?;
 
-    let bot_result: Result<(Uuid, String), _> = bots.select((id, name)).first(&mut conn);
+    let bot_result: Result<(Uuid, String), _> = bots
+        .filter(deleted_at.is_null())
+        .select((id, name))
+        .first(&mut conn);
 
     match bot_result {
         Ok((bot_id_val, _bot_name_val)) => Ok(UserSession {
@@ -187,6 +191,10 @@ pub fn get_designer_session(
             current_tool: None,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
+            deleted_at: None,
+            deleted_by: None,
+            parent_session_id: None,
+            forked_at_index: None,
         }),
         Err(_) => Err("No bot found for designer session".into()),
     }
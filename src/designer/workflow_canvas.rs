@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::designer::bas_analyzer::{BasFileAnalyzer, BasFileType, WorkflowMetadata};
 use axum::{
@@ -347,7 +348,7 @@ pub struct GenerateCodeRequest {
 
 pub async fn generate_workflow_code(
     State(_state): State<Arc<AppState>>,
-    Json(request): Json<GenerateCodeRequest>,
+    ApiJson(request): ApiJson<GenerateCodeRequest>,
 ) -> Result<Html<String>, StatusCode> {
     let canvas = WorkflowCanvas {
         id: Uuid::new_v4(),
@@ -376,7 +377,7 @@ pub struct AnalyzeFileResponse {
 
 pub async fn analyze_bas_file(
     State(_state): State<Arc<AppState>>,
-    Json(request): Json<AnalyzeFileRequest>,
+    ApiJson(request): ApiJson<AnalyzeFileRequest>,
 ) -> Result<Json<AnalyzeFileResponse>, StatusCode> {
     let file_type = WorkflowCanvas::detect_file_type(&request.content);
     
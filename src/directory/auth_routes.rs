@@ -2,18 +2,20 @@ use axum::{
     extract::State,
     http::{header, StatusCode},
     response::{IntoResponse, Json},
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::utils::get_stack_path;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use once_cell::sync::Lazy;
 
 use crate::core::shared::state::AppState;
+use crate::directory::{sessions, totp};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionUserData {
@@ -26,6 +28,13 @@ pub struct SessionUserData {
     pub organization_id: Option<String>,
     pub roles: Vec<String>,
     pub created_at: i64,
+    /// Public id of the session, used by the session listing endpoints.
+    #[serde(default)]
+    pub session_key: String,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub ip_address: Option<String>,
 }
 
 pub static SESSION_CACHE: Lazy<RwLock<HashMap<String, SessionUserData>>> =
@@ -87,6 +96,21 @@ pub struct TwoFactorRequest {
     pub trust_device: Option<bool>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+    /// Keeps the session making the request signed in; all others are logged out.
+    #[serde(default)]
+    pub keep_current_session: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangePasswordResponse {
+    pub success: bool,
+    pub revoked_sessions: usize,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RefreshTokenRequest {
     pub refresh_token: String,
@@ -118,14 +142,24 @@ pub fn configure() -> Router<Arc<AppState>> {
         .route("/logout", post(logout))
         .route("/me", get(get_current_user))
         .route("/refresh", post(refresh_token))
+        .route("/password", post(change_password))
+        .route("/sessions", get(sessions::list_sessions))
+        .route("/sessions/revoke-all", post(sessions::revoke_all_sessions))
+        .route("/sessions/:id", delete(sessions::revoke_session))
         .route("/2fa/verify", post(verify_2fa))
         .route("/2fa/resend", post(resend_2fa))
+        .route("/2fa/status", get(totp::totp_status))
+        .route("/2fa/setup", post(totp::setup_totp))
+        .route("/2fa/enable", post(totp::enable_totp))
+        .route("/2fa/disable", post(totp::disable_totp))
+        .route("/2fa/recovery-codes", post(totp::regenerate_recovery_codes))
         .route("/bootstrap", post(bootstrap_admin))
 }
 
 pub async fn login(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<LoginRequest>,
+    headers: axum::http::HeaderMap,
+    ApiJson(req): ApiJson<LoginRequest>,
 ) -> Result<Json<LoginResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!("Login attempt for: {}", req.email);
 
@@ -320,10 +354,6 @@ pub async fn login(
         .and_then(|s| s.as_str())
         .map(String::from);
 
-    use uuid::Uuid;
-    
-    let api_token = format!("gb_{}_{}", Uuid::new_v4(), chrono::Utc::now().timestamp());
-
     let session_user = SessionUserData {
         user_id: user_id_str.clone(),
         email: req.email.clone(),
@@ -334,20 +364,61 @@ pub async fn login(
         organization_id: None,
         roles: vec!["admin".to_string()],
         created_at: chrono::Utc::now().timestamp(),
+        session_key: String::new(),
+        user_agent: headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from),
+        ip_address: client_ip(&headers),
     };
 
-    {
-        let mut cache = SESSION_CACHE.write().await;
-        cache.insert(api_token.clone(), session_user.clone());
-        info!("Session cached for user: {} with token: {}...", req.email, &api_token[..std::cmp::min(20, api_token.len())]);
+    if totp::totp_required(&state, &user_id_str).await? {
+        let challenge = totp::create_challenge(session_user, session_id, session_token).await;
+        info!("Password accepted for {}, waiting for TOTP code", req.email);
+        return Ok(Json(LoginResponse {
+            success: false,
+            user_id: None,
+            session_id: None,
+            access_token: None,
+            refresh_token: None,
+            expires_in: None,
+            requires_2fa: true,
+            session_token: Some(challenge),
+            redirect: None,
+            message: Some("Two-factor authentication required".to_string()),
+        }));
     }
 
     info!("Login successful for: {} (user_id: {})", req.email, user_id_str);
+    Ok(Json(issue_session(session_user, session_id, session_token).await))
+}
 
-    Ok(Json(LoginResponse {
+/// Caches a session for a fully authenticated user and returns its access token.
+async fn issue_session(
+    mut session_user: SessionUserData,
+    session_id: Option<String>,
+    session_token: Option<String>,
+) -> LoginResponse {
+    use uuid::Uuid;
+
+    let api_token = format!("gb_{}_{}", Uuid::new_v4(), chrono::Utc::now().timestamp());
+    let user_id = session_user.user_id.clone();
+    session_user.session_key = Uuid::new_v4().to_string();
+
+    {
+        let mut cache = SESSION_CACHE.write().await;
+        info!(
+            "Session cached for user: {} with token: {}...",
+            session_user.email,
+            &api_token[..std::cmp::min(20, api_token.len())]
+        );
+        cache.insert(api_token.clone(), session_user);
+    }
+
+    LoginResponse {
         success: true,
-        user_id: Some(user_id_str),
-        session_id: session_id.clone(),
+        user_id: Some(user_id),
+        session_id,
         access_token: Some(api_token),
         refresh_token: None,
         expires_in: Some(3600),
@@ -355,7 +426,30 @@ pub async fn login(
         session_token,
         redirect: Some("/".to_string()),
         message: Some("Login successful".to_string()),
-    }))
+    }
+}
+
+fn client_ip(headers: &axum::http::HeaderMap) -> Option<String> {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
+        .or_else(|| {
+            headers
+                .get("x-real-ip")
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string())
+        })
+}
+
+/// The session a request's bearer token belongs to.
+pub async fn session_from_headers(headers: &axum::http::HeaderMap) -> Option<SessionUserData> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|auth| auth.strip_prefix("Bearer "))
+        .filter(|token| !token.is_empty() && !sessions::is_revoked(token))?;
+    SESSION_CACHE.read().await.get(token).cloned()
 }
 
 pub async fn logout(
@@ -369,8 +463,7 @@ pub async fn logout(
         .map(String::from);
 
     if let Some(ref token_str) = token {
-        let mut cache = SESSION_CACHE.write().await;
-        if cache.remove(token_str).is_some() {
+        if sessions::revoke_token(token_str).await.is_some() {
             info!("User logged out, session removed from cache");
         } else {
             info!("User logged out (session was not in cache)");
@@ -465,7 +558,7 @@ pub async fn get_current_user(
 
 pub async fn refresh_token(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<RefreshTokenRequest>,
+    ApiJson(req): ApiJson<RefreshTokenRequest>,
 ) -> Result<Json<LoginResponse>, (StatusCode, Json<ErrorResponse>)> {
     let client = {
         let auth_service = state.auth_service.lock().await;
@@ -558,26 +651,20 @@ pub async fn refresh_token(
 }
 
 pub async fn verify_2fa(
-    State(_state): State<Arc<AppState>>,
-    Json(req): Json<TwoFactorRequest>,
+    State(state): State<Arc<AppState>>,
+    ApiJson(req): ApiJson<TwoFactorRequest>,
 ) -> Result<Json<LoginResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let login = totp::complete_challenge(&state, &req.session_token, &req.code).await?;
     info!(
-        "2FA verification attempt for session: {}",
-        req.session_token
+        "Login successful for: {} (user_id: {}) with TOTP",
+        login.user.email, login.user.user_id
     );
-
-    Err((
-        StatusCode::NOT_IMPLEMENTED,
-        Json(ErrorResponse {
-            error: "2FA verification not yet implemented".to_string(),
-            details: Some("This feature will be available in a future update".to_string()),
-        }),
-    ))
+    Ok(Json(issue_session(login.user, login.session_id, login.session_token).await))
 }
 
 pub async fn resend_2fa(
     State(_state): State<Arc<AppState>>,
-    Json(_req): Json<serde_json::Value>,
+    ApiJson(_req): ApiJson<serde_json::Value>,
 ) -> impl IntoResponse {
     (
         StatusCode::NOT_IMPLEMENTED,
@@ -590,7 +677,7 @@ pub async fn resend_2fa(
 
 pub async fn bootstrap_admin(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<BootstrapAdminRequest>,
+    ApiJson(req): ApiJson<BootstrapAdminRequest>,
 ) -> Result<Json<BootstrapResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!("Bootstrap admin request received");
 
@@ -714,6 +801,86 @@ pub async fn bootstrap_admin(
     }))
 }
 
+/// Changes the signed-in user's password and logs out their other sessions, or all of
+/// them unless `keep_current_session` is set.
+pub async fn change_password(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    ApiJson(req): ApiJson<ChangePasswordRequest>,
+) -> Result<Json<ChangePasswordResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let user = session_from_headers(&headers).await.ok_or_else(|| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "Authentication required".to_string(),
+                details: None,
+            }),
+        )
+    })?;
+
+    let client = {
+        let auth_service = state.auth_service.lock().await;
+        auth_service.client().clone()
+    };
+
+    let url = format!("{}/v2/users/{}/password", client.api_url(), user.user_id);
+    let body = serde_json::json!({
+        "currentPassword": req.current_password,
+        "newPassword": {
+            "password": req.new_password,
+            "changeRequired": false
+        }
+    });
+
+    let response = client
+        .http_post(url)
+        .await
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| {
+            error!("Failed to change password: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Authentication service error".to_string(),
+                    details: None,
+                }),
+            )
+        })?;
+
+    if !response.status().is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        warn!("Password change rejected for {}: {}", user.user_id, error_text);
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Password change failed".to_string(),
+                details: Some(error_text),
+            }),
+        ));
+    }
+
+    let keep = if req.keep_current_session {
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|auth| auth.strip_prefix("Bearer "))
+    } else {
+        None
+    };
+    let revoked_sessions = sessions::revoke_user_sessions(&user.user_id, keep).await;
+    info!(
+        "Password changed for {}, {} session(s) logged out",
+        user.email, revoked_sessions
+    );
+
+    Ok(Json(ChangePasswordResponse {
+        success: true,
+        revoked_sessions,
+    }))
+}
+
 async fn set_user_password(
     client: &crate::directory::client::ZitadelClient,
     user_id: &str,
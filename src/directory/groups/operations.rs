@@ -9,12 +9,13 @@ use uuid::Uuid;
 use chrono;
 use serde_json;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use super::types::*;
 
 pub async fn create_group(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateGroupRequest>,
+    ApiJson(req): ApiJson<CreateGroupRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!("Creating group: {}", req.name);
 
@@ -79,7 +80,7 @@ pub async fn create_group(
 pub async fn update_group(
     State(state): State<Arc<AppState>>,
     Path(group_id): Path<String>,
-    Json(req): Json<UpdateGroupRequest>,
+    ApiJson(req): ApiJson<UpdateGroupRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!("Updating group: {}", group_id);
 
@@ -381,7 +382,7 @@ pub async fn get_group_members(
 pub async fn add_group_member(
     State(state): State<Arc<AppState>>,
     Path(group_id): Path<String>,
-    Json(req): Json<AddMemberRequest>,
+    ApiJson(req): ApiJson<AddMemberRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!("Adding user {} to group {}", req.user_id, group_id);
 
@@ -425,7 +426,7 @@ pub async fn add_group_member(
 pub async fn remove_group_member(
     State(state): State<Arc<AppState>>,
     Path(group_id): Path<String>,
-    Json(req): Json<AddMemberRequest>,
+    ApiJson(req): ApiJson<AddMemberRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!("Removing user {} from group {}", req.user_id, group_id);
 
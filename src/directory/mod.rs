@@ -1,3 +1,4 @@
+use crate::core::i18n::RequestLocale;
 use crate::core::shared::state::AppState;
 use axum::{
     extract::{Query, State},
@@ -14,6 +15,8 @@ pub mod bootstrap;
 pub mod client;
 pub mod groups;
 pub mod router;
+pub mod sessions;
+pub mod totp;
 pub mod users;
 
 // Zitadel directory service integration - v4.13.1
@@ -47,6 +50,7 @@ impl AuthService {
 
 pub async fn auth_handler(
     State(state): State<Arc<AppState>>,
+    RequestLocale(locale): RequestLocale,
     Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
     let bot_name = params.get("bot_name").cloned().unwrap_or_default();
@@ -132,6 +136,10 @@ pub async fn auth_handler(
             info!("Attempting to get existing session: {}", existing_session_id);
             match sm.get_session_by_id(existing_session_id) {
                 Ok(Some(sess)) => {
+                    if let Err(expired) = sm.enforce_timeouts(&sess) {
+                        info!("Session {} rejected: {}", sess.id, expired.reason());
+                        return (StatusCode::UNAUTHORIZED, Json(expired.body(&locale)));
+                    }
                     info!("Successfully retrieved existing session: {}", sess.id);
                     sess
                 }
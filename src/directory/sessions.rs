@@ -0,0 +1,368 @@
+//! Listing and revoking a user's login sessions.
+//!
+//! Login sessions live in [`SESSION_CACHE`], keyed by their access token. Revoking one drops
+//! it from the cache and remembers the token, so request authentication refuses it instead
+//! of treating it as an unknown session. Each session gets a public `id` for the listing
+//! endpoints; the token itself is never shown.
+//!
+//! Revocations are also written, as token hashes, to the `revoked_session_tokens` table.
+//! [`init_revocations`] loads them at startup and re-reads them every few seconds, so a
+//! revoked token stays refused after a restart and on every other instance.
+
+use super::auth_routes::{session_from_headers, ErrorResponse, SessionUserData, SESSION_CACHE};
+use crate::core::shared::utils::DbPool;
+use crate::security::session::DeviceInfo;
+use axum::{
+    extract::Path,
+    http::{header, HeaderMap, StatusCode},
+    response::Json,
+};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Text};
+use log::{error, info};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::Duration;
+
+/// How long a revoked token is remembered.
+const REVOCATION_RETENTION_SECS: i64 = 30 * 24 * 3600;
+/// How often revocations made by other instances are picked up.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Hashes of revoked access tokens and when they were revoked.
+static REVOKED_TOKENS: Lazy<RwLock<HashMap<String, i64>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Where revocations are shared; unset until [`init_revocations`] runs.
+static REVOCATION_STORE: OnceLock<DbPool> = OnceLock::new();
+
+/// Last authenticated request per access token.
+static LAST_SEEN: Lazy<Mutex<HashMap<String, i64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+type AuthError = (StatusCode, Json<ErrorResponse>);
+
+fn auth_error(status: StatusCode, error: &str) -> AuthError {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            details: None,
+        }),
+    )
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionSummary {
+    pub id: String,
+    pub user_agent: Option<String>,
+    pub device: DeviceInfo,
+    pub ip_address: Option<String>,
+    pub created_at: i64,
+    pub last_seen: i64,
+    pub current: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionListResponse {
+    pub sessions: Vec<SessionSummary>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RevokeAllRequest {
+    /// Keeps the session making the request signed in.
+    #[serde(default)]
+    pub keep_current: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeResponse {
+    pub success: bool,
+    pub revoked: usize,
+}
+
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn is_revoked(token: &str) -> bool {
+    let hash = token_hash(token);
+    REVOKED_TOKENS
+        .read()
+        .map(|revoked| revoked.contains_key(&hash))
+        .unwrap_or(false)
+}
+
+#[derive(QueryableByName)]
+struct RevokedRow {
+    #[diesel(sql_type = Text)]
+    token_hash: String,
+    #[diesel(sql_type = BigInt)]
+    revoked_at: i64,
+}
+
+/// Drops expired revocations from the table and returns the rest.
+fn load_revocations(pool: &DbPool) -> Result<Vec<RevokedRow>, String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    diesel::sql_query(
+        "DELETE FROM revoked_session_tokens
+         WHERE revoked_at < NOW() - make_interval(secs => $1)",
+    )
+    .bind::<BigInt, _>(REVOCATION_RETENTION_SECS)
+    .execute(&mut conn)
+    .map_err(|e| e.to_string())?;
+    diesel::sql_query(
+        "SELECT token_hash, EXTRACT(EPOCH FROM revoked_at)::BIGINT AS revoked_at
+         FROM revoked_session_tokens",
+    )
+    .load(&mut conn)
+    .map_err(|e| e.to_string())
+}
+
+fn save_revocations(pool: &DbPool, hashes: &[String]) -> Result<(), String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    for hash in hashes {
+        diesel::sql_query(
+            "INSERT INTO revoked_session_tokens (token_hash) VALUES ($1)
+             ON CONFLICT (token_hash) DO NOTHING",
+        )
+        .bind::<Text, _>(hash)
+        .execute(&mut conn)
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn refresh_revocations(pool: &DbPool) {
+    match load_revocations(pool) {
+        Ok(rows) => {
+            if let Ok(mut revoked) = REVOKED_TOKENS.write() {
+                for row in rows {
+                    revoked.entry(row.token_hash).or_insert(row.revoked_at);
+                }
+            }
+        }
+        Err(e) => error!("Cannot read revoked session tokens: {}", e),
+    }
+}
+
+/// Loads the revocations stored by every instance and keeps them up to date.
+pub async fn init_revocations(pool: DbPool) {
+    if REVOCATION_STORE.set(pool).is_err() {
+        return;
+    }
+    let refresh = || async {
+        let _ = tokio::task::spawn_blocking(|| {
+            if let Some(pool) = REVOCATION_STORE.get() {
+                refresh_revocations(pool);
+            }
+        })
+        .await;
+    };
+    refresh().await;
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            refresh().await;
+        }
+    });
+}
+
+/// Records an authenticated request for the session's `last_seen`.
+pub fn touch(token: &str) {
+    if let Ok(mut seen) = LAST_SEEN.lock() {
+        seen.insert(token.to_string(), chrono::Utc::now().timestamp());
+    }
+}
+
+fn last_seen(token: &str) -> Option<i64> {
+    LAST_SEEN.lock().ok()?.get(token).copied()
+}
+
+async fn mark_revoked(tokens: &[String]) {
+    let now = chrono::Utc::now().timestamp();
+    let hashes: Vec<String> = tokens.iter().map(|token| token_hash(token)).collect();
+    if let Ok(mut revoked) = REVOKED_TOKENS.write() {
+        revoked.retain(|_, at| now - *at < REVOCATION_RETENTION_SECS);
+        for hash in &hashes {
+            revoked.insert(hash.clone(), now);
+        }
+    }
+    if let Ok(mut seen) = LAST_SEEN.lock() {
+        for token in tokens {
+            seen.remove(token);
+        }
+    }
+
+    if let Some(pool) = REVOCATION_STORE.get().filter(|_| !hashes.is_empty()) {
+        let pool = pool.clone();
+        let saved = tokio::task::spawn_blocking(move || save_revocations(&pool, &hashes))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r);
+        if let Err(e) = saved {
+            error!("Cannot store revoked session tokens: {}", e);
+        }
+    }
+}
+
+/// Ends the session behind `token`, returning it if it was active.
+pub async fn revoke_token(token: &str) -> Option<SessionUserData> {
+    let removed = SESSION_CACHE.write().await.remove(token);
+    mark_revoked(&[token.to_string()]).await;
+    removed
+}
+
+/// Ends every session of a user except `keep`, returning how many were ended.
+pub async fn revoke_user_sessions(user_id: &str, keep: Option<&str>) -> usize {
+    let tokens: Vec<String> = {
+        let mut cache = SESSION_CACHE.write().await;
+        let tokens: Vec<String> = cache
+            .iter()
+            .filter(|(token, session)| session.user_id == user_id && Some(token.as_str()) != keep)
+            .map(|(token, _)| token.clone())
+            .collect();
+        for token in &tokens {
+            cache.remove(token);
+        }
+        tokens
+    };
+    mark_revoked(&tokens).await;
+    if !tokens.is_empty() {
+        info!("Revoked {} session(s) of user {}", tokens.len(), user_id);
+    }
+    tokens.len()
+}
+
+/// Active sessions of a user, most recently used first.
+pub async fn user_sessions(user_id: &str, current_token: Option<&str>) -> Vec<SessionSummary> {
+    let cache = SESSION_CACHE.read().await;
+    let mut sessions: Vec<SessionSummary> = cache
+        .iter()
+        .filter(|(_, session)| session.user_id == user_id)
+        .map(|(token, session)| SessionSummary {
+            id: session.session_key.clone(),
+            user_agent: session.user_agent.clone(),
+            device: session
+                .user_agent
+                .as_deref()
+                .map(DeviceInfo::from_user_agent)
+                .unwrap_or_default(),
+            ip_address: session.ip_address.clone(),
+            created_at: session.created_at,
+            last_seen: last_seen(token).unwrap_or(session.created_at),
+            current: Some(token.as_str()) == current_token,
+        })
+        .collect();
+    sessions.sort_by(|a, b| b.last_seen.cmp(&a.last_seen));
+    sessions
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|auth| auth.strip_prefix("Bearer "))
+        .filter(|token| !token.is_empty())
+}
+
+async fn current_user(headers: &HeaderMap) -> Result<SessionUserData, AuthError> {
+    session_from_headers(headers)
+        .await
+        .ok_or_else(|| auth_error(StatusCode::UNAUTHORIZED, "Authentication required"))
+}
+
+pub async fn list_sessions(headers: HeaderMap) -> Result<Json<SessionListResponse>, AuthError> {
+    let user = current_user(&headers).await?;
+    let sessions = user_sessions(&user.user_id, bearer_token(&headers)).await;
+    Ok(Json(SessionListResponse { sessions }))
+}
+
+pub async fn revoke_session(
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<RevokeResponse>, AuthError> {
+    let user = current_user(&headers).await?;
+    let token = SESSION_CACHE
+        .read()
+        .await
+        .iter()
+        .find(|(_, session)| session.user_id == user.user_id && session.session_key == id)
+        .map(|(token, _)| token.clone())
+        .ok_or_else(|| auth_error(StatusCode::NOT_FOUND, "Session not found"))?;
+    revoke_token(&token).await;
+    info!("User {} revoked session {}", user.user_id, id);
+    Ok(Json(RevokeResponse {
+        success: true,
+        revoked: 1,
+    }))
+}
+
+pub async fn revoke_all_sessions(
+    headers: HeaderMap,
+    body: Option<Json<RevokeAllRequest>>,
+) -> Result<Json<RevokeResponse>, AuthError> {
+    let user = current_user(&headers).await?;
+    let keep = body
+        .map(|Json(req)| req.keep_current)
+        .unwrap_or_default()
+        .then(|| bearer_token(&headers))
+        .flatten();
+    let revoked = revoke_user_sessions(&user.user_id, keep).await;
+    Ok(Json(RevokeResponse {
+        success: true,
+        revoked,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::auth_api::validate_session_sync;
+
+    fn session(user_id: &str) -> SessionUserData {
+        SessionUserData {
+            user_id: user_id.to_string(),
+            email: "ana@example.com".to_string(),
+            username: "ana".to_string(),
+            first_name: None,
+            last_name: None,
+            display_name: None,
+            organization_id: None,
+            roles: vec!["admin".to_string()],
+            created_at: chrono::Utc::now().timestamp(),
+            session_key: uuid::Uuid::new_v4().to_string(),
+            user_agent: Some("Mozilla/5.0 (X11; Linux x86_64) Firefox/128.0".to_string()),
+            ip_address: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revoked_session_token_is_rejected() {
+        let user_id = uuid::Uuid::new_v4().to_string();
+        let (laptop, phone) = ("gb_test_laptop_1", "gb_test_phone_1");
+        {
+            let mut cache = SESSION_CACHE.write().await;
+            cache.insert(laptop.to_string(), session(&user_id));
+            cache.insert(phone.to_string(), session(&user_id));
+        }
+        assert!(validate_session_sync(laptop).is_ok());
+
+        let listed = user_sessions(&user_id, Some(laptop)).await;
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed.iter().filter(|s| s.current).count(), 1);
+        assert_eq!(listed[0].device.browser.as_deref(), Some("Firefox"));
+
+        assert_eq!(revoke_user_sessions(&user_id, Some(laptop)).await, 1);
+        assert!(validate_session_sync(phone).is_err());
+        assert!(validate_session_sync(laptop).is_ok());
+
+        assert!(revoke_token(laptop).await.is_some());
+        assert!(validate_session_sync(laptop).is_err());
+        assert!(user_sessions(&user_id, None).await.is_empty());
+    }
+}
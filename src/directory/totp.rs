@@ -0,0 +1,714 @@
+//! Authenticator-app (TOTP) second factor for directory logins.
+//!
+//! Enrollment takes two steps: `/2fa/setup` stores a new secret, sealed with the master
+//! key, and returns its provisioning URI for the QR code; `/2fa/enable` switches it on once
+//! a code from the app checks out, and returns single-use recovery codes. While TOTP is on,
+//! a correct password only yields a login challenge (`requires_2fa`), and the session is
+//! issued by `/2fa/verify`.
+//!
+//! Wrong codes are limited twice over: a login challenge is dropped after
+//! [`MAX_CHALLENGE_ATTEMPTS`] tries, and a user is locked out for
+//! `lockout_duration_minutes` after `max_verification_attempts` consecutive failures, on
+//! whichever challenge or endpoint they happened.
+
+use super::auth_routes::{session_from_headers, ErrorResponse, LogoutResponse, SessionUserData};
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::state::AppState;
+use crate::security::master_key::{self, LegacyEncoding};
+use crate::security::mfa::{match_totp_step, MfaConfig, RecoveryCode, TotpEnrollment};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Integer, Jsonb, Nullable, Text, Timestamptz};
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// How long a login challenge waits for its code.
+const CHALLENGE_TTL: Duration = Duration::from_secs(300);
+/// Codes a single login challenge accepts before the user has to sign in again.
+pub const MAX_CHALLENGE_ATTEMPTS: u32 = 5;
+
+type AuthError = (StatusCode, Json<ErrorResponse>);
+
+fn auth_error(status: StatusCode, error: &str) -> AuthError {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+            details: None,
+        }),
+    )
+}
+
+fn locked_error(until: DateTime<Utc>) -> AuthError {
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ErrorResponse {
+            error: "Too many invalid codes".to_string(),
+            details: Some(format!("Try again after {}", until.to_rfc3339())),
+        }),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TotpCheck {
+    Accepted,
+    Rejected,
+    Locked(DateTime<Utc>),
+}
+
+#[derive(Debug, Clone)]
+pub struct TotpRecord {
+    pub user_id: String,
+    /// Hex-encoded key, as in [`TotpEnrollment::secret`].
+    pub secret: String,
+    pub enabled: bool,
+    pub recovery_codes: Vec<RecoveryCode>,
+    /// Last accepted time step; codes from it or earlier steps are refused.
+    pub last_step: Option<i64>,
+    pub failed_attempts: i32,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+impl TotpRecord {
+    pub fn new(user_id: &str, secret: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            secret: secret.to_string(),
+            enabled: false,
+            recovery_codes: Vec::new(),
+            last_step: None,
+            failed_attempts: 0,
+            locked_until: None,
+        }
+    }
+
+    /// Checks a code from the authenticator app, or a recovery code once TOTP is enabled,
+    /// and updates the replay and lockout state. The caller persists the record.
+    pub fn check(&mut self, code: &str, now: DateTime<Utc>, config: &MfaConfig) -> TotpCheck {
+        if let Some(until) = self.locked_until.filter(|until| *until > now) {
+            return TotpCheck::Locked(until);
+        }
+        if self.accepts(code, now, config) {
+            self.failed_attempts = 0;
+            self.locked_until = None;
+            return TotpCheck::Accepted;
+        }
+
+        self.failed_attempts += 1;
+        if self.failed_attempts as u32 >= config.max_verification_attempts {
+            let until = now + chrono::Duration::minutes(config.lockout_duration_minutes as i64);
+            warn!(
+                "User {} locked out of TOTP until {} after {} failed codes",
+                self.user_id, until, self.failed_attempts
+            );
+            self.failed_attempts = 0;
+            self.locked_until = Some(until);
+            return TotpCheck::Locked(until);
+        }
+        TotpCheck::Rejected
+    }
+
+    fn accepts(&mut self, code: &str, now: DateTime<Utc>, config: &MfaConfig) -> bool {
+        let step = match_totp_step(
+            &self.secret,
+            code,
+            now.timestamp().max(0) as u64,
+            config.totp_period,
+            config.totp_algorithm,
+            config.totp_digits,
+        );
+        if let Some(step) = step.map(|s| s as i64) {
+            if self.last_step.is_some_and(|last| step <= last) {
+                return false;
+            }
+            self.last_step = Some(step);
+            return true;
+        }
+
+        if !self.enabled {
+            return false;
+        }
+        match self
+            .recovery_codes
+            .iter_mut()
+            .find(|c| c.verify(code.trim()))
+        {
+            Some(recovery_code) => {
+                recovery_code.mark_used();
+                info!("Recovery code used by {}", self.user_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the recovery codes and returns the new ones in plain text.
+    pub fn regenerate_recovery_codes(&mut self, config: &MfaConfig) -> Vec<String> {
+        let (codes, stored) = RecoveryCode::generate_set(config.recovery_code_count);
+        self.recovery_codes = stored;
+        codes
+    }
+
+    pub fn recovery_codes_left(&self) -> usize {
+        self.recovery_codes
+            .iter()
+            .filter(|c| c.used_at.is_none())
+            .count()
+    }
+}
+
+#[derive(QueryableByName)]
+struct TotpRow {
+    #[diesel(sql_type = Text)]
+    secret_encrypted: String,
+    #[diesel(sql_type = Bool)]
+    enabled: bool,
+    #[diesel(sql_type = Jsonb)]
+    recovery_codes: serde_json::Value,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    last_step: Option<i64>,
+    #[diesel(sql_type = Integer)]
+    failed_attempts: i32,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    locked_until: Option<DateTime<Utc>>,
+}
+
+pub fn load_totp(conn: &mut PgConnection, user_id: &str) -> Result<Option<TotpRecord>, String> {
+    select_totp(conn, user_id, "")
+}
+
+/// Loads the record and locks its row until the surrounding transaction ends.
+fn lock_totp(conn: &mut PgConnection, user_id: &str) -> Result<Option<TotpRecord>, String> {
+    select_totp(conn, user_id, " FOR UPDATE")
+}
+
+fn select_totp(
+    conn: &mut PgConnection,
+    user_id: &str,
+    lock: &str,
+) -> Result<Option<TotpRecord>, String> {
+    let row: Option<TotpRow> = diesel::sql_query(format!(
+        "SELECT secret_encrypted, enabled, recovery_codes, last_step, failed_attempts,
+                locked_until
+         FROM user_totp WHERE user_id = $1{}",
+        lock
+    ))
+    .bind::<Text, _>(user_id)
+    .get_result(conn)
+    .optional()
+    .map_err(|e| e.to_string())?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+    let secret = master_key::reveal(&row.secret_encrypted, LegacyEncoding::Plain)
+        .map_err(|e| format!("Cannot read TOTP secret of {}: {}", user_id, e))?;
+    Ok(Some(TotpRecord {
+        user_id: user_id.to_string(),
+        secret,
+        enabled: row.enabled,
+        recovery_codes: serde_json::from_value(row.recovery_codes).unwrap_or_default(),
+        last_step: row.last_step,
+        failed_attempts: row.failed_attempts,
+        locked_until: row.locked_until,
+    }))
+}
+
+/// Stores a new, not yet enabled secret, replacing any unfinished enrollment.
+pub fn store_secret(conn: &mut PgConnection, user_id: &str, secret: &str) -> Result<(), String> {
+    let sealed = master_key::protect(secret, LegacyEncoding::Plain).map_err(|e| e.to_string())?;
+    diesel::sql_query(
+        "INSERT INTO user_totp (user_id, secret_encrypted) VALUES ($1, $2)
+         ON CONFLICT (user_id) DO UPDATE SET
+             secret_encrypted = EXCLUDED.secret_encrypted, enabled = FALSE,
+             recovery_codes = '[]'::jsonb, last_step = NULL, updated_at = NOW()",
+    )
+    .bind::<Text, _>(user_id)
+    .bind::<Text, _>(sealed)
+    .execute(conn)
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// Saves everything but the secret.
+pub fn save_totp(conn: &mut PgConnection, record: &TotpRecord) -> Result<(), String> {
+    let recovery_codes = serde_json::to_value(&record.recovery_codes).map_err(|e| e.to_string())?;
+    diesel::sql_query(
+        "UPDATE user_totp SET enabled = $2, recovery_codes = $3, last_step = $4,
+             failed_attempts = $5, locked_until = $6, updated_at = NOW()
+         WHERE user_id = $1",
+    )
+    .bind::<Text, _>(&record.user_id)
+    .bind::<Bool, _>(record.enabled)
+    .bind::<Jsonb, _>(recovery_codes)
+    .bind::<Nullable<BigInt>, _>(record.last_step)
+    .bind::<Integer, _>(record.failed_attempts)
+    .bind::<Nullable<Timestamptz>, _>(record.locked_until)
+    .execute(conn)
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+pub fn delete_totp(conn: &mut PgConnection, user_id: &str) -> Result<(), String> {
+    diesel::sql_query("DELETE FROM user_totp WHERE user_id = $1")
+        .bind::<Text, _>(user_id)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+async fn with_conn<T, F>(state: &AppState, f: F) -> Result<T, AuthError>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    let pool = state.conn.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        f(&mut conn)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+    result.map_err(|e| {
+        error!("TOTP storage error: {}", e);
+        auth_error(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+    })
+}
+
+pub async fn find_totp(state: &AppState, user_id: &str) -> Result<Option<TotpRecord>, AuthError> {
+    let user_id = user_id.to_string();
+    with_conn(state, move |conn| load_totp(conn, &user_id)).await
+}
+
+/// Whether logging in as `user_id` needs a second factor.
+pub async fn totp_required(state: &AppState, user_id: &str) -> Result<bool, AuthError> {
+    Ok(find_totp(state, user_id).await?.is_some_and(|r| r.enabled))
+}
+
+/// Checks `code` against the user's stored record and saves the outcome, so failures
+/// count towards the lockout even when the request fails. The row stays locked from
+/// load to save, so parallel checks are counted one after another and a code cannot be
+/// used twice. `record` is replaced with the saved state.
+pub async fn check_code(
+    state: &AppState,
+    record: &mut TotpRecord,
+    code: &str,
+) -> Result<TotpCheck, AuthError> {
+    let user_id = record.user_id.clone();
+    let code = code.to_string();
+    let checked = with_conn(state, move |conn| {
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let Some(mut current) = lock_totp(conn, &user_id).map_err(storage_error)? else {
+                return Ok(None);
+            };
+            let outcome = current.check(&code, Utc::now(), &MfaConfig::default());
+            save_totp(conn, &current).map_err(storage_error)?;
+            Ok(Some((outcome, current)))
+        })
+        .map_err(|e| e.to_string())
+    })
+    .await?;
+
+    match checked {
+        Some((outcome, current)) => {
+            *record = current;
+            Ok(outcome)
+        }
+        None => Ok(TotpCheck::Rejected),
+    }
+}
+
+fn storage_error(e: String) -> diesel::result::Error {
+    diesel::result::Error::QueryBuilderError(e.into())
+}
+
+fn outcome_result(outcome: TotpCheck) -> Result<(), AuthError> {
+    match outcome {
+        TotpCheck::Accepted => Ok(()),
+        TotpCheck::Rejected => Err(auth_error(StatusCode::UNAUTHORIZED, "Invalid code")),
+        TotpCheck::Locked(until) => Err(locked_error(until)),
+    }
+}
+
+/// A login whose password was accepted and that waits for its TOTP code.
+#[derive(Debug, Clone)]
+pub struct PendingLogin {
+    pub user: SessionUserData,
+    pub session_id: Option<String>,
+    pub session_token: Option<String>,
+    expires_at: Instant,
+    attempts: u32,
+}
+
+static PENDING_LOGINS: Lazy<RwLock<HashMap<String, PendingLogin>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Parks a login until its code is verified and returns the challenge token.
+pub async fn create_challenge(
+    user: SessionUserData,
+    session_id: Option<String>,
+    session_token: Option<String>,
+) -> String {
+    let token = format!("2fa_{}", Uuid::new_v4());
+    let now = Instant::now();
+    let mut pending = PENDING_LOGINS.write().await;
+    pending.retain(|_, login| login.expires_at > now);
+    pending.insert(
+        token.clone(),
+        PendingLogin {
+            user,
+            session_id,
+            session_token,
+            expires_at: now + CHALLENGE_TTL,
+            attempts: 0,
+        },
+    );
+    token
+}
+
+/// Completes a login challenge with `code`. The challenge is consumed on success, on
+/// lockout and after [`MAX_CHALLENGE_ATTEMPTS`] wrong codes.
+pub async fn complete_challenge(
+    state: &AppState,
+    token: &str,
+    code: &str,
+) -> Result<PendingLogin, AuthError> {
+    let expired = || {
+        auth_error(
+            StatusCode::UNAUTHORIZED,
+            "Two-factor challenge expired, sign in again",
+        )
+    };
+    let (login, attempt) =
+        reserve_attempt(&mut *PENDING_LOGINS.write().await, token, Instant::now())
+            .ok_or_else(expired)?;
+
+    let mut record = find_totp(state, &login.user.user_id)
+        .await?
+        .filter(|r| r.enabled)
+        .ok_or_else(expired)?;
+    let outcome = check_code(state, &mut record, code).await?;
+
+    if outcome != TotpCheck::Rejected || attempt >= MAX_CHALLENGE_ATTEMPTS {
+        PENDING_LOGINS.write().await.remove(token);
+    }
+
+    outcome_result(outcome).map(|()| login)
+}
+
+/// Counts an attempt against the challenge before its code is checked, so parallel
+/// requests cannot get more than [`MAX_CHALLENGE_ATTEMPTS`] codes checked. Returns the
+/// login and the attempt number, or `None` once the challenge is gone or used up.
+fn reserve_attempt(
+    pending: &mut HashMap<String, PendingLogin>,
+    token: &str,
+    now: Instant,
+) -> Option<(PendingLogin, u32)> {
+    let login = pending
+        .get_mut(token)
+        .filter(|login| login.expires_at > now)?;
+    if login.attempts >= MAX_CHALLENGE_ATTEMPTS {
+        pending.remove(token);
+        return None;
+    }
+    login.attempts += 1;
+    let attempt = login.attempts;
+    Some((login.clone(), attempt))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TotpSetupResponse {
+    /// Base32 key for entering the secret by hand.
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code.
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TotpStatusResponse {
+    pub enabled: bool,
+    pub recovery_codes_left: usize,
+}
+
+async fn current_user(headers: &HeaderMap) -> Result<SessionUserData, AuthError> {
+    session_from_headers(headers)
+        .await
+        .ok_or_else(|| auth_error(StatusCode::UNAUTHORIZED, "Authentication required"))
+}
+
+async fn enabled_record(state: &AppState, user_id: &str) -> Result<TotpRecord, AuthError> {
+    find_totp(state, user_id)
+        .await?
+        .filter(|r| r.enabled)
+        .ok_or_else(|| auth_error(StatusCode::BAD_REQUEST, "Two-factor authentication is off"))
+}
+
+pub async fn totp_status(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<TotpStatusResponse>, AuthError> {
+    let user = current_user(&headers).await?;
+    let record = find_totp(&state, &user.user_id).await?;
+    Ok(Json(TotpStatusResponse {
+        enabled: record.as_ref().is_some_and(|r| r.enabled),
+        recovery_codes_left: record
+            .filter(|r| r.enabled)
+            .map_or(0, |r| r.recovery_codes_left()),
+    }))
+}
+
+pub async fn setup_totp(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<TotpSetupResponse>, AuthError> {
+    let user = current_user(&headers).await?;
+    if totp_required(&state, &user.user_id).await? {
+        return Err(auth_error(
+            StatusCode::CONFLICT,
+            "Two-factor authentication is already on",
+        ));
+    }
+
+    // Directory user ids are not UUIDs; only the enrollment's secret and URI are used.
+    let enrollment = TotpEnrollment::new(Uuid::nil(), &user.email, &MfaConfig::default());
+    let user_id = user.user_id.clone();
+    let secret = enrollment.secret.clone();
+    with_conn(&state, move |conn| store_secret(conn, &user_id, &secret)).await?;
+
+    info!("TOTP setup started for {}", user.user_id);
+    Ok(Json(TotpSetupResponse {
+        secret: enrollment.secret_base32(),
+        otpauth_uri: enrollment.to_uri(),
+    }))
+}
+
+pub async fn enable_totp(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(req): ApiJson<TotpCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AuthError> {
+    let user = current_user(&headers).await?;
+    let mut record = find_totp(&state, &user.user_id)
+        .await?
+        .ok_or_else(|| auth_error(StatusCode::BAD_REQUEST, "Run two-factor setup first"))?;
+    if record.enabled {
+        return Err(auth_error(
+            StatusCode::CONFLICT,
+            "Two-factor authentication is already on",
+        ));
+    }
+
+    let config = MfaConfig::default();
+    let outcome = record.check(&req.code, Utc::now(), &config);
+    let recovery_codes = if outcome == TotpCheck::Accepted {
+        record.enabled = true;
+        record.regenerate_recovery_codes(&config)
+    } else {
+        Vec::new()
+    };
+    let saved = record.clone();
+    with_conn(&state, move |conn| save_totp(conn, &saved)).await?;
+    outcome_result(outcome)?;
+
+    info!("TOTP enabled for {}", user.user_id);
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+pub async fn disable_totp(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(req): ApiJson<TotpCodeRequest>,
+) -> Result<Json<LogoutResponse>, AuthError> {
+    let user = current_user(&headers).await?;
+    let mut record = enabled_record(&state, &user.user_id).await?;
+    outcome_result(check_code(&state, &mut record, &req.code).await?)?;
+
+    let user_id = user.user_id.clone();
+    with_conn(&state, move |conn| delete_totp(conn, &user_id)).await?;
+
+    info!("TOTP disabled for {}", user.user_id);
+    Ok(Json(LogoutResponse {
+        success: true,
+        message: "Two-factor authentication turned off".to_string(),
+    }))
+}
+
+pub async fn regenerate_recovery_codes(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    ApiJson(req): ApiJson<TotpCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AuthError> {
+    let user = current_user(&headers).await?;
+    let mut record = enabled_record(&state, &user.user_id).await?;
+    outcome_result(check_code(&state, &mut record, &req.code).await?)?;
+
+    let recovery_codes = record.regenerate_recovery_codes(&MfaConfig::default());
+    let saved = record.clone();
+    with_conn(&state, move |conn| save_totp(conn, &saved)).await?;
+
+    info!("Recovery codes regenerated for {}", user.user_id);
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::mfa::totp_code;
+
+    const SECRET: &str = "3132333435363738393031323334353637383930";
+
+    fn code_at(time: i64) -> String {
+        let config = MfaConfig::default();
+        totp_code(
+            SECRET,
+            time as u64 / config.totp_period,
+            config.totp_algorithm,
+            config.totp_digits,
+        )
+        .unwrap()
+    }
+
+    fn at(time: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(time, 0).unwrap()
+    }
+
+    #[test]
+    fn test_code_is_accepted_once_per_step() {
+        let config = MfaConfig::default();
+        let mut record = TotpRecord::new("user-1", SECRET);
+        let now = 1_700_000_000;
+
+        assert_eq!(
+            record.check(&code_at(now), at(now), &config),
+            TotpCheck::Accepted
+        );
+        // The same code again is a replay, even inside its validity window.
+        assert_eq!(
+            record.check(&code_at(now), at(now + 5), &config),
+            TotpCheck::Rejected
+        );
+        // A code from the previous step is older than the one just used.
+        assert_eq!(
+            record.check(&code_at(now - 30), at(now + 5), &config),
+            TotpCheck::Rejected
+        );
+        assert_eq!(
+            record.check(&code_at(now + 30), at(now + 30), &config),
+            TotpCheck::Accepted
+        );
+        assert_eq!(record.failed_attempts, 0);
+    }
+
+    #[test]
+    fn test_failed_codes_lock_out() {
+        let config = MfaConfig::default();
+        let mut record = TotpRecord::new("user-1", SECRET);
+        record.enabled = true;
+        let now = 1_700_000_000;
+
+        for _ in 1..config.max_verification_attempts {
+            assert_eq!(
+                record.check("000000", at(now), &config),
+                TotpCheck::Rejected
+            );
+        }
+        let until = at(now) + chrono::Duration::minutes(config.lockout_duration_minutes as i64);
+        assert_eq!(
+            record.check("000000", at(now), &config),
+            TotpCheck::Locked(until)
+        );
+        // Even the right code is refused until the lockout ends.
+        assert_eq!(
+            record.check(&code_at(now + 60), at(now + 60), &config),
+            TotpCheck::Locked(until)
+        );
+        let later = until.timestamp() + 1;
+        assert_eq!(
+            record.check(&code_at(later), at(later), &config),
+            TotpCheck::Accepted
+        );
+    }
+
+    #[test]
+    fn test_challenge_attempts_are_reserved_up_front() {
+        let now = Instant::now();
+        let mut pending = HashMap::new();
+        pending.insert(
+            "2fa_token".to_string(),
+            PendingLogin {
+                user: SessionUserData {
+                    user_id: "user-1".to_string(),
+                    email: "user@example.com".to_string(),
+                    username: "user".to_string(),
+                    first_name: None,
+                    last_name: None,
+                    display_name: None,
+                    organization_id: None,
+                    roles: Vec::new(),
+                    created_at: 0,
+                    session_key: String::new(),
+                    user_agent: None,
+                    ip_address: None,
+                },
+                session_id: None,
+                session_token: None,
+                expires_at: now + CHALLENGE_TTL,
+                attempts: 0,
+            },
+        );
+
+        // Every reservation counts, whether or not its code has been checked yet.
+        for expected in 1..=MAX_CHALLENGE_ATTEMPTS {
+            let (_, attempt) = reserve_attempt(&mut pending, "2fa_token", now).unwrap();
+            assert_eq!(attempt, expected);
+        }
+        assert!(reserve_attempt(&mut pending, "2fa_token", now).is_none());
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn test_recovery_codes_are_single_use() {
+        let config = MfaConfig::default();
+        let mut record = TotpRecord::new("user-1", SECRET);
+        let codes = record.regenerate_recovery_codes(&config);
+        let now = 1_700_000_000;
+
+        // Recovery codes only count once TOTP is enabled.
+        assert_eq!(
+            record.check(&codes[0], at(now), &config),
+            TotpCheck::Rejected
+        );
+        record.enabled = true;
+        assert_eq!(
+            record.check(&codes[0].to_lowercase(), at(now), &config),
+            TotpCheck::Accepted
+        );
+        assert_eq!(
+            record.check(&codes[0], at(now), &config),
+            TotpCheck::Rejected
+        );
+        assert_eq!(record.recovery_codes_left(), codes.len() - 1);
+    }
+}
@@ -8,6 +8,7 @@ use log::{error, info};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -92,7 +93,7 @@ pub struct UpdateRolesRequest {
 
 pub async fn create_user(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateUserRequest>,
+    ApiJson(req): ApiJson<CreateUserRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!("Creating user: {} ({})", req.username, req.email);
 
@@ -145,7 +146,7 @@ pub async fn create_user(
 pub async fn update_user(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
-    Json(req): Json<UpdateUserRequest>,
+    ApiJson(req): ApiJson<UpdateUserRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!("Updating user: {}", user_id);
 
@@ -476,7 +477,7 @@ pub async fn get_user_profile(
 pub async fn assign_organization(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<String>,
-    Json(req): Json<AssignOrganizationRequest>,
+    ApiJson(req): ApiJson<AssignOrganizationRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!(
         "Assigning user {} to organization {}",
@@ -586,7 +587,7 @@ pub async fn get_user_memberships(
 pub async fn update_user_roles(
     State(state): State<Arc<AppState>>,
     Path((user_id, org_id)): Path<(String, String)>,
-    Json(req): Json<UpdateRolesRequest>,
+    ApiJson(req): ApiJson<UpdateRolesRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!(
         "Updating roles for user {} in organization {}: {:?}",
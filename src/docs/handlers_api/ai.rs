@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::docs::types::{DocsAiRequest, DocsAiResponse, AiRequest, AiResponse};
 use axum::{
@@ -10,7 +11,7 @@ use std::sync::Arc;
 
 pub async fn handle_docs_ai(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<DocsAiRequest>,
+    ApiJson(req): ApiJson<DocsAiRequest>,
 ) -> impl IntoResponse {
     let command = req.command.to_lowercase();
 
@@ -43,7 +44,7 @@ pub async fn handle_docs_ai(
 }
 
 pub async fn handle_ai_summarize(
-    Json(req): Json<AiRequest>,
+    ApiJson(req): ApiJson<AiRequest>,
 ) -> Result<Json<AiResponse>, (StatusCode, Json<serde_json::Value>)> {
     let text = req.selected_text.unwrap_or_default();
     let summary = if text.len() > 200 {
@@ -60,7 +61,7 @@ pub async fn handle_ai_summarize(
 }
 
 pub async fn handle_ai_expand(
-    Json(req): Json<AiRequest>,
+    ApiJson(req): ApiJson<AiRequest>,
 ) -> Result<Json<AiResponse>, (StatusCode, Json<serde_json::Value>)> {
     let text = req.selected_text.unwrap_or_default();
     let expanded = format!("{}\n\n[Additional context and details would be added here by AI]", text);
@@ -73,7 +74,7 @@ pub async fn handle_ai_expand(
 }
 
 pub async fn handle_ai_improve(
-    Json(req): Json<AiRequest>,
+    ApiJson(req): ApiJson<AiRequest>,
 ) -> Result<Json<AiResponse>, (StatusCode, Json<serde_json::Value>)> {
     let text = req.selected_text.unwrap_or_default();
 
@@ -85,7 +86,7 @@ pub async fn handle_ai_improve(
 }
 
 pub async fn handle_ai_simplify(
-    Json(req): Json<AiRequest>,
+    ApiJson(req): ApiJson<AiRequest>,
 ) -> Result<Json<AiResponse>, (StatusCode, Json<serde_json::Value>)> {
     let text = req.selected_text.unwrap_or_default();
 
@@ -97,7 +98,7 @@ pub async fn handle_ai_simplify(
 }
 
 pub async fn handle_ai_translate(
-    Json(req): Json<AiRequest>,
+    ApiJson(req): ApiJson<AiRequest>,
 ) -> Result<Json<AiResponse>, (StatusCode, Json<serde_json::Value>)> {
     let text = req.selected_text.unwrap_or_default();
     let lang = req.translate_lang.unwrap_or_else(|| "English".to_string());
@@ -110,7 +111,7 @@ pub async fn handle_ai_translate(
 }
 
 pub async fn handle_ai_custom(
-    Json(req): Json<AiRequest>,
+    ApiJson(req): ApiJson<AiRequest>,
 ) -> Result<Json<AiResponse>, (StatusCode, Json<serde_json::Value>)> {
     let text = req.selected_text.unwrap_or_default();
 
@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::docs::storage::{get_current_user_id, load_document_from_drive, save_document};
 use crate::docs::types::{
@@ -15,7 +16,7 @@ use std::sync::Arc;
 
 pub async fn handle_add_comment(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddCommentRequest>,
+    ApiJson(req): ApiJson<AddCommentRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -63,7 +64,7 @@ pub async fn handle_add_comment(
 
 pub async fn handle_reply_comment(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ReplyCommentRequest>,
+    ApiJson(req): ApiJson<ReplyCommentRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -112,7 +113,7 @@ pub async fn handle_reply_comment(
 
 pub async fn handle_resolve_comment(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ResolveCommentRequest>,
+    ApiJson(req): ApiJson<ResolveCommentRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -154,7 +155,7 @@ pub async fn handle_resolve_comment(
 
 pub async fn handle_delete_comment(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteCommentRequest>,
+    ApiJson(req): ApiJson<DeleteCommentRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::docs::storage::{
     create_new_document, delete_document_from_drive, get_current_user_id,
@@ -14,7 +15,7 @@ use uuid::Uuid;
 
 pub async fn handle_docs_save(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DocsSaveRequest>,
+    ApiJson(req): ApiJson<DocsSaveRequest>,
 ) -> Result<Json<DocsSaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let doc_id = req.id.unwrap_or_else(|| Uuid::new_v4().to_string());
@@ -123,7 +124,7 @@ pub async fn handle_get_document(
 
 pub async fn handle_save_document(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DocsSaveRequest>,
+    ApiJson(req): ApiJson<DocsSaveRequest>,
 ) -> Result<Json<DocsSaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let doc_id = req.id.unwrap_or_else(|| Uuid::new_v4().to_string());
@@ -144,14 +145,14 @@ pub async fn handle_save_document(
 
 pub async fn handle_autosave(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DocsSaveRequest>,
+    ApiJson(req): ApiJson<DocsSaveRequest>,
 ) -> Result<Json<DocsSaveResponse>, (StatusCode, Json<serde_json::Value>)> {
-    handle_save_document(State(state), Json(req)).await
+    handle_save_document(State(state), ApiJson(req)).await
 }
 
 pub async fn handle_delete_document(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<crate::docs::types::LoadQuery>,
+    ApiJson(req): ApiJson<crate::docs::types::LoadQuery>,
 ) -> Result<Json<DocsSaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
 
@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::docs::storage::{create_new_document, get_current_user_id, load_document_from_drive, save_document};
 use crate::docs::types::{
@@ -5,6 +6,7 @@ use crate::docs::types::{
     DocumentComparison, DocumentDiff,
 };
 use crate::docs::utils::{detect_document_format, markdown_to_html, rtf_to_html};
+use crate::security::upload_policy::check_upload;
 use axum::{
     extract::State,
     http::StatusCode,
@@ -19,10 +21,12 @@ pub async fn handle_import_document(
 ) -> Result<Json<Document>, (StatusCode, Json<serde_json::Value>)> {
     let mut file_bytes: Option<Vec<u8>> = None;
     let mut filename = "import.docx".to_string();
+    let mut content_type = String::new();
 
     while let Ok(Some(field)) = multipart.next_field().await {
         if field.name() == Some("file") {
             filename = field.file_name().unwrap_or("import.docx").to_string();
+            content_type = field.content_type().unwrap_or_default().to_string();
             if let Ok(bytes) = field.bytes().await {
                 file_bytes = Some(bytes.to_vec());
             }
@@ -35,6 +39,14 @@ pub async fn handle_import_document(
             Json(serde_json::json!({ "error": "No file uploaded" })),
         )
     })?;
+    check_upload("docs-import", &filename, &content_type, &bytes)
+        .await
+        .map_err(|rejection| {
+            (
+                rejection.status(),
+                Json(serde_json::json!({ "error": rejection.to_string() })),
+            )
+        })?;
 
     let format = detect_document_format(&bytes);
     let content = match format {
@@ -75,7 +87,7 @@ pub async fn handle_import_document(
 
 pub async fn handle_compare_documents(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CompareDocumentsRequest>,
+    ApiJson(req): ApiJson<CompareDocumentsRequest>,
 ) -> Result<Json<CompareDocumentsResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
 
@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::docs::storage::{get_current_user_id, load_document_from_drive, save_document};
 use crate::docs::types::{
@@ -16,7 +17,7 @@ use std::sync::Arc;
 
 pub async fn handle_add_footnote(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddFootnoteRequest>,
+    ApiJson(req): ApiJson<AddFootnoteRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -60,7 +61,7 @@ pub async fn handle_add_footnote(
 
 pub async fn handle_update_footnote(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<UpdateFootnoteRequest>,
+    ApiJson(req): ApiJson<UpdateFootnoteRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -101,7 +102,7 @@ pub async fn handle_update_footnote(
 
 pub async fn handle_delete_footnote(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteFootnoteRequest>,
+    ApiJson(req): ApiJson<DeleteFootnoteRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -183,7 +184,7 @@ fn to_roman_numeral(num: usize) -> String {
 
 pub async fn handle_add_endnote(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddEndnoteRequest>,
+    ApiJson(req): ApiJson<AddEndnoteRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -227,7 +228,7 @@ pub async fn handle_add_endnote(
 
 pub async fn handle_update_endnote(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<UpdateEndnoteRequest>,
+    ApiJson(req): ApiJson<UpdateEndnoteRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -268,7 +269,7 @@ pub async fn handle_update_endnote(
 
 pub async fn handle_delete_endnote(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteEndnoteRequest>,
+    ApiJson(req): ApiJson<DeleteEndnoteRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::docs::storage::{get_current_user_id, load_document_from_drive};
 use crate::docs::types::{GetOutlineRequest, OutlineItem, OutlineResponse};
@@ -11,7 +12,7 @@ use std::sync::Arc;
 
 pub async fn handle_get_outline(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<GetOutlineRequest>,
+    ApiJson(req): ApiJson<GetOutlineRequest>,
 ) -> Result<Json<OutlineResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::docs::storage::{get_current_user_id, load_document_from_drive, save_document};
 use crate::docs::types::{
@@ -15,7 +16,7 @@ use std::sync::Arc;
 
 pub async fn handle_create_style(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateStyleRequest>,
+    ApiJson(req): ApiJson<CreateStyleRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -50,7 +51,7 @@ pub async fn handle_create_style(
 
 pub async fn handle_update_style(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<UpdateStyleRequest>,
+    ApiJson(req): ApiJson<UpdateStyleRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -91,7 +92,7 @@ pub async fn handle_update_style(
 
 pub async fn handle_delete_style(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteStyleRequest>,
+    ApiJson(req): ApiJson<DeleteStyleRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -153,7 +154,7 @@ pub async fn handle_list_styles(
 
 pub async fn handle_apply_style(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ApplyStyleRequest>,
+    ApiJson(req): ApiJson<ApplyStyleRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
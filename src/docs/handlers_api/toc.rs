@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::docs::storage::{get_current_user_id, load_document_from_drive, save_document};
 use crate::docs::types::{GenerateTocRequest, TableOfContents, TocEntry, TocResponse, UpdateTocRequest};
@@ -12,7 +13,7 @@ use std::sync::Arc;
 
 pub async fn handle_generate_toc(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<GenerateTocRequest>,
+    ApiJson(req): ApiJson<GenerateTocRequest>,
 ) -> Result<Json<TocResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -88,7 +89,7 @@ pub async fn handle_generate_toc(
 
 pub async fn handle_update_toc(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<UpdateTocRequest>,
+    ApiJson(req): ApiJson<UpdateTocRequest>,
 ) -> Result<Json<TocResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -123,5 +124,5 @@ pub async fn handle_update_toc(
         use_hyperlinks: existing_toc.use_hyperlinks,
     };
 
-    handle_generate_toc(State(state), Json(gen_req)).await
+    handle_generate_toc(State(state), ApiJson(gen_req)).await
 }
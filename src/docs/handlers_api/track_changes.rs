@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::docs::storage::{get_current_user_id, load_document_from_drive, save_document};
 use crate::docs::types::{
@@ -15,7 +16,7 @@ use std::sync::Arc;
 
 pub async fn handle_enable_track_changes(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<EnableTrackChangesRequest>,
+    ApiJson(req): ApiJson<EnableTrackChangesRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -49,7 +50,7 @@ pub async fn handle_enable_track_changes(
 
 pub async fn handle_accept_reject_change(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AcceptRejectChangeRequest>,
+    ApiJson(req): ApiJson<AcceptRejectChangeRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
@@ -90,7 +91,7 @@ pub async fn handle_accept_reject_change(
 
 pub async fn handle_accept_reject_all(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AcceptRejectAllRequest>,
+    ApiJson(req): ApiJson<AcceptRejectAllRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut doc = match load_document_from_drive(&state, &user_id, &req.doc_id).await {
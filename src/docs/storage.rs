@@ -1,7 +1,7 @@
 use crate::docs::ooxml::{load_docx_preserving, update_docx_text};
 use crate::docs::types::{Document, DocumentMetadata};
 use crate::core::shared::state::AppState;
-use aws_sdk_s3::primitives::ByteStream;
+use crate::drive::quota;
 use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use std::io::Cursor;
@@ -52,24 +52,16 @@ pub async fn load_docx_from_drive(
     user_identifier: &str,
     file_path: &str,
 ) -> Result<Document, String> {
-    let s3_client = state.drive.as_ref().ok_or("S3 service not available")?;
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or("Drive not available")?;
 
-    let result = s3_client
-        .get_object()
-        .bucket(&state.bucket_name)
-        .key(file_path)
-        .send()
+    let bytes = store
+        .get(&state.bucket_name, file_path)
         .await
         .map_err(|e| format!("Failed to load DOCX: {e}"))?;
 
-    let bytes = result
-        .body
-        .collect()
-        .await
-        .map_err(|e| format!("Failed to read DOCX: {e}"))?
-        .into_bytes()
-        .to_vec();
-
     load_docx_from_bytes(&bytes, user_identifier, file_path).await
 }
 
@@ -242,19 +234,23 @@ pub async fn save_document_as_docx(
         convert_html_to_docx(title, content)?
     };
 
-    let s3_client = state.drive.as_ref().ok_or("S3 service not available")?;
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or("Drive not available")?;
     let base_path = get_user_docs_path(user_identifier);
     let docx_path = format!("{base_path}/{doc_id}.docx");
 
-    s3_client
-        .put_object()
-        .bucket(&state.bucket_name)
-        .key(&docx_path)
-        .body(ByteStream::from(docx_bytes.clone()))
-        .content_type("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to save DOCX: {e}"))?;
+    quota::put_tracked(
+        state,
+        store,
+        &state.bucket_name,
+        &docx_path,
+        docx_bytes.clone(),
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    )
+    .await
+    .map_err(|e| format!("Failed to save DOCX: {e}"))?;
 
     cache_document_bytes(doc_id, docx_bytes.clone()).await;
 
@@ -339,21 +335,34 @@ pub async fn save_document_to_drive(
     title: &str,
     content: &str,
 ) -> Result<String, String> {
-    let s3_client = state.drive.as_ref().ok_or("S3 service not available")?;
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or("Drive not available")?;
 
     let base_path = get_user_docs_path(user_identifier);
     let doc_path = format!("{base_path}/{doc_id}.html");
     let meta_path = format!("{base_path}/{doc_id}.meta.json");
 
-    s3_client
-        .put_object()
-        .bucket(&state.bucket_name)
-        .key(&doc_path)
-        .body(ByteStream::from(content.as_bytes().to_vec()))
-        .content_type("text/html")
-        .send()
+    let delta = quota::check_put(
+        state,
+        store,
+        &state.bucket_name,
+        &doc_path,
+        content.len() as u64,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    store
+        .put(
+            &state.bucket_name,
+            &doc_path,
+            content.as_bytes().to_vec(),
+            "text/html",
+        )
         .await
         .map_err(|e| format!("Failed to save document: {e}"))?;
+    quota::record_change(state, &state.bucket_name, &doc_path, delta).await;
 
     let word_count = count_words(content);
 
@@ -366,15 +375,16 @@ pub async fn save_document_to_drive(
         "version": 1
     });
 
-    s3_client
-        .put_object()
-        .bucket(&state.bucket_name)
-        .key(&meta_path)
-        .body(ByteStream::from(metadata.to_string().into_bytes()))
-        .content_type("application/json")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to save metadata: {e}"))?;
+    quota::put_tracked(
+        state,
+        store,
+        &state.bucket_name,
+        &meta_path,
+        metadata.to_string().into_bytes(),
+        "application/json",
+    )
+    .await
+    .map_err(|e| format!("Failed to save metadata: {e}"))?;
 
     Ok(doc_path)
 }
@@ -392,46 +402,23 @@ pub async fn load_document_from_drive(
     user_identifier: &str,
     doc_id: &str,
 ) -> Result<Option<Document>, String> {
-    let s3_client = state.drive.as_ref().ok_or("S3 service not available")?;
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or("Drive not available")?;
 
     let base_path = get_user_docs_path(user_identifier);
     let doc_path = format!("{base_path}/{doc_id}.html");
     let meta_path = format!("{base_path}/{doc_id}.meta.json");
 
-    let content = match s3_client
-        .get_object()
-        .bucket(&state.bucket_name)
-        .key(&doc_path)
-        .send()
-        .await
-    {
-        Ok(result) => {
-            let bytes = result
-                .body
-                .collect()
-                .await
-                .map_err(|e| e.to_string())?
-                .into_bytes();
-            String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())?
-        }
+    let content = match store.get(&state.bucket_name, &doc_path).await {
+        Ok(bytes) => String::from_utf8(bytes).map_err(|e| e.to_string())?,
         Err(_) => return Ok(None),
     };
 
-    let (title, created_at, updated_at) = match s3_client
-        .get_object()
-        .bucket(&state.bucket_name)
-        .key(&meta_path)
-        .send()
-        .await
-    {
-        Ok(result) => {
-            let bytes = result
-                .body
-                .collect()
-                .await
-                .map_err(|e| e.to_string())?
-                .into_bytes();
-            let meta_str = String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())?;
+    let (title, created_at, updated_at) = match store.get(&state.bucket_name, &meta_path).await {
+        Ok(bytes) => {
+            let meta_str = String::from_utf8(bytes).map_err(|e| e.to_string())?;
             let meta: serde_json::Value = serde_json::from_str(&meta_str).unwrap_or_default();
             (
                 meta["title"].as_str().unwrap_or("Untitled").to_string(),
@@ -474,62 +461,40 @@ pub async fn list_documents_from_drive(
     state: &Arc<AppState>,
     user_identifier: &str,
 ) -> Result<Vec<DocumentMetadata>, String> {
-    let s3_client = state.drive.as_ref().ok_or("S3 service not available")?;
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or("Drive not available")?;
 
     let base_path = get_user_docs_path(user_identifier);
     let prefix = format!("{base_path}/");
     let mut documents = Vec::new();
 
-    if let Ok(result) = s3_client
-        .list_objects_v2()
-        .bucket(&state.bucket_name)
-        .prefix(&prefix)
-        .send()
-        .await
-    {
-        for obj in result.contents() {
-            if let Some(key) = obj.key() {
-                if key.ends_with(".meta.json") {
-                    if let Ok(meta_result) = s3_client
-                        .get_object()
-                        .bucket(&state.bucket_name)
-                        .key(key)
-                        .send()
-                        .await
-                    {
-                        if let Ok(bytes) = meta_result.body.collect().await {
-                            if let Ok(meta_str) = String::from_utf8(bytes.into_bytes().to_vec()) {
-                                if let Ok(meta) =
-                                    serde_json::from_str::<serde_json::Value>(&meta_str)
-                                {
-                                    let doc_meta = DocumentMetadata {
-                                        id: meta["id"].as_str().unwrap_or_default().to_string(),
-                                        title: meta["title"]
-                                            .as_str()
-                                            .unwrap_or("Untitled")
-                                            .to_string(),
-                                        owner_id: user_identifier.to_string(),
-                                        created_at: meta["created_at"]
-                                            .as_str()
-                                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                                            .map(|d| d.with_timezone(&Utc))
-                                            .unwrap_or_else(Utc::now),
-                                        updated_at: meta["updated_at"]
-                                            .as_str()
-                                            .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                                            .map(|d| d.with_timezone(&Utc))
-                                            .unwrap_or_else(Utc::now),
-                                        word_count: meta["word_count"].as_u64().unwrap_or(0)
-                                            as usize,
-                                        storage_type: "html".to_string(),
-                                    };
-                                    documents.push(doc_meta);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    if let Ok(objects) = store.list(&state.bucket_name, &prefix).await {
+        for obj in objects.iter().filter(|obj| obj.key.ends_with(".meta.json")) {
+            let Ok(bytes) = store.get(&state.bucket_name, &obj.key).await else {
+                continue;
+            };
+            let Ok(meta) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+                continue;
+            };
+            documents.push(DocumentMetadata {
+                id: meta["id"].as_str().unwrap_or_default().to_string(),
+                title: meta["title"].as_str().unwrap_or("Untitled").to_string(),
+                owner_id: user_identifier.to_string(),
+                created_at: meta["created_at"]
+                    .as_str()
+                    .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                    .map(|d| d.with_timezone(&Utc))
+                    .unwrap_or_else(Utc::now),
+                updated_at: meta["updated_at"]
+                    .as_str()
+                    .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                    .map(|d| d.with_timezone(&Utc))
+                    .unwrap_or_else(Utc::now),
+                word_count: meta["word_count"].as_u64().unwrap_or(0) as usize,
+                storage_type: "html".to_string(),
+            });
         }
     }
 
@@ -542,18 +507,16 @@ pub async fn delete_document_from_drive(
     user_identifier: &str,
     doc_id: &str,
 ) -> Result<(), String> {
-    let s3_client = state.drive.as_ref().ok_or("S3 service not available")?;
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or("Drive not available")?;
 
     let base_path = get_user_docs_path(user_identifier);
 
     for ext in &[".html", ".docx", ".meta.json"] {
         let path = format!("{base_path}/{doc_id}{ext}");
-        let _ = s3_client
-            .delete_object()
-            .bucket(&state.bucket_name)
-            .key(&path)
-            .send()
-            .await;
+        let _ = quota::delete_tracked(state, store, &state.bucket_name, &path).await;
     }
 
     remove_from_cache(doc_id).await;
@@ -0,0 +1,427 @@
+//! Paginated listing of drive files, for file-manager views.
+//!
+//! Without a bot, the listing covers the caller's own `users/{user_id}/` area of the
+//! `gbo` bucket. Sheets, papers, presentations and exports are still saved in the shared
+//! `users/default-user/` area, which only administrators may list, with `shared`. With a
+//! `bot_id` the caller may access, it covers that bot's `{bot}.gbai` bucket, leaving out
+//! the per-user areas inside it. Clients see keys relative to the listed area and can
+//! never list outside it. Listings go through the drive's [`ObjectStore`], so they work
+//! on every backend.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::models::schema::bots;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::shared::state::AppState;
+use crate::core::shared::utils::get_content_type;
+use crate::drive::object_store::ObjectStore;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+const USER_FILES_BUCKET: &str = "gbo";
+const USER_AREAS_PREFIX: &str = "users/";
+
+/// Objects returned per page when the client does not ask for a size.
+pub const DEFAULT_PAGE_SIZE: i32 = 100;
+
+/// Largest page returned, as with S3's `ListObjectsV2`.
+pub const MAX_PAGE_SIZE: i32 = 1000;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BrowseQuery {
+    /// Folder or key prefix relative to the user's area, e.g. `sheets/`.
+    #[serde(default)]
+    pub prefix: String,
+    /// Token from the previous page's `next_continuation_token`.
+    pub continuation_token: Option<String>,
+    pub limit: Option<i32>,
+    /// List every object below `prefix` instead of one folder level.
+    #[serde(default)]
+    pub recursive: bool,
+    /// Browse this bot's bucket instead of the user's own files.
+    pub bot_id: Option<Uuid>,
+    /// Browse the shared area instead of the user's own files. Administrators only.
+    #[serde(default)]
+    pub shared: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DriveObject {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub content_type: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ObjectPage {
+    pub prefix: String,
+    pub objects: Vec<DriveObject>,
+    /// Sub-folders of `prefix`; empty for recursive listings.
+    pub folders: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_continuation_token: Option<String>,
+}
+
+/// Owner of the shared user files area, the one the sheet, doc and slide handlers store
+/// under.
+pub fn get_current_user_id() -> String {
+    "default-user".to_string()
+}
+
+/// Part of the drive a listing is confined to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowseArea {
+    pub bucket: String,
+    /// Key prefix of the area; listed keys are returned relative to it.
+    pub root: String,
+}
+
+impl BrowseArea {
+    pub fn user(user_id: &str) -> Self {
+        Self {
+            bucket: USER_FILES_BUCKET.to_string(),
+            root: format!("{USER_AREAS_PREFIX}{user_id}/"),
+        }
+    }
+
+    pub fn bot(bot_name: &str) -> Self {
+        Self {
+            bucket: format!("{bot_name}.gbai"),
+            root: String::new(),
+        }
+    }
+
+    /// Keys inside a bot bucket that belong to a user area rather than the bot.
+    fn hides(&self, relative_key: &str) -> bool {
+        self.root.is_empty() && relative_key.starts_with(USER_AREAS_PREFIX)
+    }
+}
+
+/// Checks a client prefix stays inside the area.
+fn validate_prefix<'a>(area: &BrowseArea, prefix: &'a str) -> Result<&'a str, ApiError> {
+    let prefix = prefix.trim_start_matches('/');
+    if prefix
+        .split('/')
+        .any(|segment| segment == ".." || segment == ".")
+        || prefix.contains('\\')
+        || area.hides(prefix)
+    {
+        return Err(ApiError::bad_request("Invalid prefix"));
+    }
+    Ok(prefix)
+}
+
+/// The sub-folder of `prefix` that `key` lies in, if it is not directly in `prefix`.
+fn folder_of(prefix: &str, key: &str) -> Option<String> {
+    let rest = key.strip_prefix(prefix)?;
+    rest.find('/')
+        .map(|end| format!("{}{}", prefix, &rest[..=end]))
+}
+
+/// Lists one page of `user_id`'s objects below `query.prefix`.
+pub async fn list_user_objects(
+    store: &dyn ObjectStore,
+    user_id: &str,
+    query: &BrowseQuery,
+) -> Result<ObjectPage, ApiError> {
+    list_area_objects(store, &BrowseArea::user(user_id), query).await
+}
+
+/// Lists one page of the objects in `area` below `query.prefix`. Folders count towards
+/// the page size, and the continuation token is the last entry of the page.
+pub async fn list_area_objects(
+    store: &dyn ObjectStore,
+    area: &BrowseArea,
+    query: &BrowseQuery,
+) -> Result<ObjectPage, ApiError> {
+    let prefix = validate_prefix(area, &query.prefix)?;
+    let root = area.root.as_str();
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE) as usize;
+    let after = query
+        .continuation_token
+        .as_deref()
+        .filter(|t| !t.is_empty());
+
+    let listed = store
+        .list(&area.bucket, &format!("{}{}", root, prefix))
+        .await
+        .map_err(|e| ApiError::internal(format!("Failed to list files: {}", e)))?;
+
+    let mut page = ObjectPage {
+        prefix: prefix.to_string(),
+        objects: Vec::new(),
+        folders: Vec::new(),
+        next_continuation_token: None,
+    };
+    let mut last: Option<String> = None;
+    let mut entries = 0;
+    // Keys come sorted, so the keys of a folder are adjacent and entries ascend
+    for object in listed {
+        let key = object.key.strip_prefix(root).unwrap_or(&object.key);
+        if area.hides(key) {
+            continue;
+        }
+        let folder = if query.recursive {
+            None
+        } else {
+            folder_of(prefix, key)
+        };
+        let entry = folder.as_deref().unwrap_or(key);
+        if after.is_some_and(|token| entry <= token) || last.as_deref() == Some(entry) {
+            continue;
+        }
+        if entries == limit {
+            page.next_continuation_token = last;
+            break;
+        }
+        entries += 1;
+        last = Some(entry.to_string());
+        match folder {
+            Some(folder) => page.folders.push(folder),
+            None => page.objects.push(DriveObject {
+                content_type: get_content_type(key),
+                size: object.size,
+                last_modified: object.last_modified,
+                key: key.to_string(),
+            }),
+        }
+    }
+
+    Ok(page)
+}
+
+pub async fn handle_list_objects(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Query(query): Query<BrowseQuery>,
+) -> Result<Json<ObjectPage>, ApiError> {
+    if !user.is_authenticated() {
+        return Err(ApiError::unauthorized("Sign in to browse files"));
+    }
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or_else(|| ApiError::service_unavailable("Drive not available"))?;
+    let area = match query.bot_id {
+        Some(bot_id) => {
+            if !user.can_access_bot(&bot_id) {
+                return Err(ApiError::forbidden("No access to this bot"));
+            }
+            BrowseArea::bot(&bot_name(&state, bot_id).await?)
+        }
+        None => browse_user_area(&user, query.shared)?,
+    };
+    list_area_objects(store, &area, &query).await.map(Json)
+}
+
+/// The caller's own area, or the shared one for an administrator asking for it.
+fn browse_user_area(user: &AuthenticatedUser, shared: bool) -> Result<BrowseArea, ApiError> {
+    if shared {
+        require_admin(user)?;
+        return Ok(BrowseArea::user(&get_current_user_id()));
+    }
+    Ok(BrowseArea::user(&user.user_id.to_string()))
+}
+
+async fn bot_name(state: &AppState, bot_id: Uuid) -> Result<String, ApiError> {
+    let pool = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        bots::table
+            .filter(bots::id.eq(bot_id))
+            .select(bots::name)
+            .first::<String>(&mut conn)
+            .optional()
+            .map_err(|e| ApiError::internal(e.to_string()))?
+            .ok_or_else(|| ApiError::not_found("Bot not found"))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+}
+
+pub fn configure() -> Router<Arc<AppState>> {
+    Router::new().route("/api/drive/objects", get(handle_list_objects))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drive::object_store::FsObjectStore;
+
+    async fn put(store: &dyn ObjectStore, key: &str, body: &str) {
+        put_in(store, USER_FILES_BUCKET, key, body).await;
+    }
+
+    async fn put_in(store: &dyn ObjectStore, bucket: &str, key: &str, body: &str) {
+        store
+            .put(
+                bucket,
+                key,
+                body.as_bytes().to_vec(),
+                "application/octet-stream",
+            )
+            .await
+            .unwrap();
+    }
+
+    fn keys(page: &ObjectPage) -> Vec<&str> {
+        page.objects.iter().map(|o| o.key.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_listing_is_scoped_to_user_and_paginated() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsObjectStore::new(dir.path());
+        for name in ["a.json", "b.json", "c.json"] {
+            put(&store, &format!("users/alice/sheets/{name}"), "{}").await;
+        }
+        put(&store, "users/alice/papers/notes.md", "# notes").await;
+        put(&store, "users/alice2/sheets/other.json", "{}").await;
+        put(&store, "users/bob/sheets/secret.json", "{}").await;
+
+        let root = list_user_objects(&store, "alice", &BrowseQuery::default())
+            .await
+            .unwrap();
+        assert!(root.objects.is_empty());
+        assert_eq!(root.folders, vec!["papers/", "sheets/"]);
+
+        let query = BrowseQuery {
+            prefix: "sheets/".to_string(),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let first = list_user_objects(&store, "alice", &query).await.unwrap();
+        assert_eq!(keys(&first), vec!["sheets/a.json", "sheets/b.json"]);
+        assert_eq!(first.objects[0].size, 2);
+        assert_eq!(
+            first.objects[0].content_type,
+            "application/json; charset=utf-8"
+        );
+
+        let query = BrowseQuery {
+            continuation_token: first.next_continuation_token.clone(),
+            ..query
+        };
+        let second = list_user_objects(&store, "alice", &query).await.unwrap();
+        assert_eq!(keys(&second), vec!["sheets/c.json"]);
+        assert!(second.next_continuation_token.is_none());
+
+        let everything = BrowseQuery {
+            recursive: true,
+            ..Default::default()
+        };
+        let all = list_user_objects(&store, "alice", &everything)
+            .await
+            .unwrap();
+        assert_eq!(all.objects.len(), 4);
+        assert!(all
+            .objects
+            .iter()
+            .all(|o| !o.key.contains("secret") && !o.key.contains("other")));
+    }
+
+    #[tokio::test]
+    async fn test_folders_page_like_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsObjectStore::new(dir.path());
+        for key in ["a/1.txt", "a/2.txt", "b.txt", "c/1.txt", "d.txt"] {
+            put(&store, &format!("users/alice/{key}"), "x").await;
+        }
+
+        let mut query = BrowseQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut pages = Vec::new();
+        loop {
+            let page = list_user_objects(&store, "alice", &query).await.unwrap();
+            let mut entries: Vec<String> = page.folders.clone();
+            entries.extend(page.objects.iter().map(|o| o.key.clone()));
+            entries.sort();
+            pages.push(entries);
+            match page.next_continuation_token {
+                Some(token) => query.continuation_token = Some(token),
+                None => break,
+            }
+        }
+        assert_eq!(pages, vec![vec!["a/", "b.txt"], vec!["c/", "d.txt"]]);
+    }
+
+    #[tokio::test]
+    async fn test_prefix_cannot_leave_user_area() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsObjectStore::new(dir.path());
+        let query = BrowseQuery {
+            prefix: "../bob/".to_string(),
+            ..Default::default()
+        };
+        assert!(list_user_objects(&store, "alice", &query).await.is_err());
+    }
+
+    #[test]
+    fn test_shared_area_is_for_admins() {
+        let user = AuthenticatedUser::new(Uuid::new_v4(), "alice".to_string());
+        assert_eq!(
+            browse_user_area(&user, false).unwrap(),
+            BrowseArea::user(&user.user_id.to_string())
+        );
+        assert!(browse_user_area(&user, true).is_err());
+
+        let admin = user.with_role(crate::security::auth_api::Role::Admin);
+        assert_eq!(
+            browse_user_area(&admin, true).unwrap(),
+            BrowseArea::user("default-user")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bot_bucket_listing_leaves_out_user_areas() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsObjectStore::new(dir.path());
+        put_in(
+            &store,
+            "sales.gbai",
+            "sales.gbdialog/start.bas",
+            "TALK \"hi\"",
+        )
+        .await;
+        put_in(&store, "sales.gbai", "sales.gbkb/faq.md", "# FAQ").await;
+        put_in(&store, "sales.gbai", "users/alice/docs/d.html", "<p></p>").await;
+
+        let area = BrowseArea::bot("sales");
+        let root = list_area_objects(&store, &area, &BrowseQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(root.folders, vec!["sales.gbdialog/", "sales.gbkb/"]);
+
+        let everything = BrowseQuery {
+            recursive: true,
+            ..Default::default()
+        };
+        let all = list_area_objects(&store, &area, &everything).await.unwrap();
+        assert_eq!(
+            keys(&all),
+            vec!["sales.gbdialog/start.bas", "sales.gbkb/faq.md"]
+        );
+
+        let into_users = BrowseQuery {
+            prefix: "users/alice/".to_string(),
+            ..Default::default()
+        };
+        assert!(list_area_objects(&store, &area, &into_users).await.is_err());
+    }
+}
@@ -2,6 +2,7 @@ use axum::{extract::State, http::StatusCode, response::Json};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -55,7 +56,7 @@ pub struct DocumentResponse {
 
 pub async fn merge_documents(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<MergeDocumentsRequest>,
+    ApiJson(req): ApiJson<MergeDocumentsRequest>,
 ) -> Result<Json<DocumentResponse>, (StatusCode, Json<serde_json::Value>)> {
     let s3_client = state.drive.as_ref().ok_or_else(|| {
         (
@@ -141,7 +142,7 @@ pub async fn merge_documents(
 
 pub async fn convert_document(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ConvertDocumentRequest>,
+    ApiJson(req): ApiJson<ConvertDocumentRequest>,
 ) -> Result<Json<DocumentResponse>, (StatusCode, Json<serde_json::Value>)> {
     let s3_client = state.drive.as_ref().ok_or_else(|| {
         (
@@ -320,7 +321,7 @@ pub async fn convert_document(
 
 pub async fn fill_document(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<FillDocumentRequest>,
+    ApiJson(req): ApiJson<FillDocumentRequest>,
 ) -> Result<Json<DocumentResponse>, (StatusCode, Json<serde_json::Value>)> {
     let s3_client = state.drive.as_ref().ok_or_else(|| {
         (
@@ -404,7 +405,7 @@ pub async fn fill_document(
 
 pub async fn export_document(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ExportDocumentRequest>,
+    ApiJson(req): ApiJson<ExportDocumentRequest>,
 ) -> Result<Json<DocumentResponse>, (StatusCode, Json<serde_json::Value>)> {
     let s3_client = state.drive.as_ref().ok_or_else(|| {
         (
@@ -477,7 +478,7 @@ pub async fn export_document(
 
 pub async fn import_document(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ImportDocumentRequest>,
+    ApiJson(req): ApiJson<ImportDocumentRequest>,
 ) -> Result<Json<DocumentResponse>, (StatusCode, Json<serde_json::Value>)> {
     let s3_client = state.drive.as_ref().ok_or_else(|| {
         (
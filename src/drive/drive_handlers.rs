@@ -1,6 +1,7 @@
 // Drive HTTP handlers - stub for when drive feature is disabled
 #[cfg(not(feature = "drive"))]
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::drive::drive_types::*;
 use axum::{
@@ -31,7 +32,7 @@ pub async fn list_buckets(
 
 pub async fn list_files(
     State(_): State<Arc<AppState>>,
-    Json(_req): Json<SearchQuery>,
+    ApiJson(_req): ApiJson<SearchQuery>,
 ) -> Result<Json<Vec<FileItem>>, (StatusCode, Json<serde_json::Value>)> {
     Err((
         StatusCode::SERVICE_UNAVAILABLE,
@@ -51,7 +52,7 @@ pub async fn read_file(
 
 pub async fn write_file(
     State(_): State<Arc<AppState>>,
-    Json(_req): Json<WriteRequest>,
+    ApiJson(_req): ApiJson<WriteRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     Err((
         StatusCode::SERVICE_UNAVAILABLE,
@@ -71,7 +72,7 @@ pub async fn delete_file(
 
 pub async fn create_folder(
     State(_): State<Arc<AppState>>,
-    Json(_req): Json<CreateFolderRequest>,
+    ApiJson(_req): ApiJson<CreateFolderRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     Err((
         StatusCode::SERVICE_UNAVAILABLE,
@@ -89,19 +90,19 @@ pub async fn download_file(
     ))
 }
 
-pub async fn copy_file(State(_): State<Arc<AppState>>, Json(_): Json<CopyFileRequest>) -> impl axum::response::IntoResponse {
+pub async fn copy_file(State(_): State<Arc<AppState>>, ApiJson(_): ApiJson<CopyFileRequest>) -> impl axum::response::IntoResponse {
     (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"error": "Drive feature not enabled"})))
 }
 
-pub async fn upload_file_to_drive(State(_): State<Arc<AppState>>, Json(_): Json<UploadRequest>) -> impl axum::response::IntoResponse {
+pub async fn upload_file_to_drive(State(_): State<Arc<AppState>>, ApiJson(_): ApiJson<UploadRequest>) -> impl axum::response::IntoResponse {
     (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"error": "Drive feature not enabled"})))
 }
 
-pub async fn list_folder_contents(State(_): State<Arc<AppState>>, Json(_): Json<SearchQuery>) -> impl axum::response::IntoResponse {
+pub async fn list_folder_contents(State(_): State<Arc<AppState>>, ApiJson(_): ApiJson<SearchQuery>) -> impl axum::response::IntoResponse {
     (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"error": "Drive feature not enabled"})))
 }
 
-pub async fn search_files(State(_): State<Arc<AppState>>, Json(_): Json<SearchQuery>) -> impl axum::response::IntoResponse {
+pub async fn search_files(State(_): State<Arc<AppState>>, ApiJson(_): ApiJson<SearchQuery>) -> impl axum::response::IntoResponse {
     (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"error": "Drive feature not enabled"})))
 }
 
@@ -113,7 +114,7 @@ pub async fn list_favorites(State(_): State<Arc<AppState>>) -> impl axum::respon
     (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"error": "Drive feature not enabled"})))
 }
 
-pub async fn share_folder(State(_): State<Arc<AppState>>, Json(_): Json<ShareRequest>) -> impl axum::response::IntoResponse {
+pub async fn share_folder(State(_): State<Arc<AppState>>, ApiJson(_): ApiJson<ShareRequest>) -> impl axum::response::IntoResponse {
     (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({"error": "Drive feature not enabled"})))
 }
 
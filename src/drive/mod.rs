@@ -1,7 +1,11 @@
+pub mod browser;
 pub mod document_processing;
 pub mod drive_files;
 pub mod drive_monitor;
 pub mod drive_compiler;
+pub mod object_store;
+pub mod objects;
+pub mod quota;
 pub mod vectordb;
 
 // Re-exports
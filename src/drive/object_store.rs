@@ -0,0 +1,584 @@
+//! Storage backends for drive objects. Code that reads and writes whole objects goes
+//! through [`ObjectStore`], so those files can live in S3 (MinIO) or in a local directory.
+//!
+//! The backend is chosen at startup with `DRIVE_BACKEND`:
+//! - `s3` (default): the S3 client in `AppState.drive`
+//! - `fs`: files under `DRIVE_FS_ROOT`, one directory per bucket
+
+use crate::core::shared::utils::get_stack_path;
+use crate::drive::objects::{copy_object, ObjectCopyError};
+use async_trait::async_trait;
+use aws_sdk_s3::Client;
+use chrono::{DateTime, Utc};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum ObjectStoreError {
+    NotFound(String),
+    InvalidKey(String),
+    Failed(String),
+}
+
+impl std::fmt::Display for ObjectStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(location) => write!(f, "Object not found: {}", location),
+            Self::InvalidKey(key) => write!(f, "Invalid object key: {}", key),
+            Self::Failed(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for ObjectStoreError {}
+
+impl From<ObjectStoreError> for ObjectCopyError {
+    fn from(err: ObjectStoreError) -> Self {
+        match err {
+            ObjectStoreError::NotFound(location) => Self::NotFound(location),
+            other => Self::Failed(other.to_string()),
+        }
+    }
+}
+
+impl From<ObjectCopyError> for ObjectStoreError {
+    fn from(err: ObjectCopyError) -> Self {
+        match err {
+            ObjectCopyError::NotFound(location) => Self::NotFound(location),
+            other => Self::Failed(other.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectInfo {
+    pub key: String,
+    pub size: u64,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// Whole-object storage, addressed by bucket and `/`-separated key.
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// `s3` or `fs`, for logs.
+    fn backend(&self) -> &'static str;
+
+    /// Writes `data` to `bucket/key`, replacing any object there.
+    async fn put(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), ObjectStoreError>;
+
+    async fn get(&self, bucket: &str, key: &str) -> Result<Vec<u8>, ObjectStoreError>;
+
+    async fn size(&self, bucket: &str, key: &str) -> Result<u64, ObjectStoreError>;
+
+    /// Deletes `bucket/key`. Deleting a missing object succeeds.
+    async fn delete(&self, bucket: &str, key: &str) -> Result<(), ObjectStoreError>;
+
+    /// Every object in `bucket` whose key starts with `prefix`, sorted by key.
+    async fn list(&self, bucket: &str, prefix: &str) -> Result<Vec<ObjectInfo>, ObjectStoreError>;
+
+    /// Copies `src_bucket/src_key` to `dst_bucket/dst_key`, replacing any object there.
+    async fn copy(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+    ) -> Result<(), ObjectStoreError>;
+}
+
+pub struct S3ObjectStore {
+    client: Client,
+}
+
+impl S3ObjectStore {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3ObjectStore {
+    fn backend(&self) -> &'static str {
+        "s3"
+    }
+
+    async fn put(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), ObjectStoreError> {
+        self.client
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(data.into())
+            .content_type(content_type)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                ObjectStoreError::Failed(format!(
+                    "Failed to write {}/{}: {}",
+                    bucket,
+                    key,
+                    e.into_service_error()
+                ))
+            })
+    }
+
+    async fn get(&self, bucket: &str, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let result = match self
+            .client
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(result) => result,
+            Err(e) => {
+                let err = e.into_service_error();
+                return Err(if err.is_no_such_key() {
+                    ObjectStoreError::NotFound(format!("{}/{}", bucket, key))
+                } else {
+                    ObjectStoreError::Failed(format!("Failed to read {}/{}: {}", bucket, key, err))
+                });
+            }
+        };
+        result
+            .body
+            .collect()
+            .await
+            .map(|data| data.into_bytes().to_vec())
+            .map_err(|e| {
+                ObjectStoreError::Failed(format!("Failed to read {}/{}: {}", bucket, key, e))
+            })
+    }
+
+    async fn size(&self, bucket: &str, key: &str) -> Result<u64, ObjectStoreError> {
+        crate::drive::objects::object_size(&self.client, bucket, key)
+            .await
+            .map_err(ObjectStoreError::from)
+    }
+
+    async fn delete(&self, bucket: &str, key: &str) -> Result<(), ObjectStoreError> {
+        self.client
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                ObjectStoreError::Failed(format!(
+                    "Failed to delete {}/{}: {}",
+                    bucket,
+                    key,
+                    e.into_service_error()
+                ))
+            })
+    }
+
+    async fn list(&self, bucket: &str, prefix: &str) -> Result<Vec<ObjectInfo>, ObjectStoreError> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let mut request = self
+                .client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(prefix)
+                .max_keys(1000);
+            if let Some(token) = continuation_token {
+                request = request.continuation_token(token);
+            }
+            let result = request.send().await.map_err(|e| {
+                ObjectStoreError::Failed(format!(
+                    "Failed to list {}/{}: {}",
+                    bucket,
+                    prefix,
+                    e.into_service_error()
+                ))
+            })?;
+            objects.extend(result.contents().iter().filter_map(|obj| {
+                Some(ObjectInfo {
+                    key: obj.key()?.to_string(),
+                    size: obj.size().unwrap_or(0).max(0) as u64,
+                    last_modified: obj
+                        .last_modified()
+                        .and_then(|t| DateTime::from_timestamp(t.secs(), t.subsec_nanos())),
+                })
+            }));
+            if result.is_truncated.unwrap_or(false) {
+                continuation_token = result.next_continuation_token;
+            } else {
+                break;
+            }
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    async fn copy(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+    ) -> Result<(), ObjectStoreError> {
+        // CopyObject does not tell a missing source apart, so check it first.
+        self.size(src_bucket, src_key).await?;
+        copy_object(&self.client, src_bucket, src_key, dst_bucket, dst_key)
+            .await
+            .map_err(ObjectStoreError::from)
+    }
+}
+
+/// Keeps each bucket as a directory under `root` and each object as a file, its key
+/// split into directories at `/`. Writes go to a temporary file that is then renamed,
+/// so readers never see a partly written object.
+pub struct FsObjectStore {
+    root: PathBuf,
+}
+
+impl FsObjectStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn bucket_dir(&self, bucket: &str) -> Result<PathBuf, ObjectStoreError> {
+        if !is_safe_segment(bucket) || bucket.starts_with('.') {
+            return Err(ObjectStoreError::InvalidKey(bucket.to_string()));
+        }
+        Ok(self.root.join(bucket))
+    }
+
+    fn object_path(&self, bucket: &str, key: &str) -> Result<PathBuf, ObjectStoreError> {
+        let mut path = self.bucket_dir(bucket)?;
+        if key.is_empty() || !key.split('/').all(is_safe_segment) {
+            return Err(ObjectStoreError::InvalidKey(key.to_string()));
+        }
+        path.extend(key.split('/'));
+        Ok(path)
+    }
+
+    /// Writes through a temporary file under `<root>/.tmp`, outside every bucket.
+    async fn write_atomically(
+        &self,
+        path: &Path,
+        write: impl std::future::Future<Output = std::io::Result<()>>,
+        tmp: &Path,
+    ) -> Result<(), ObjectStoreError> {
+        let failed = |e: std::io::Error| {
+            ObjectStoreError::Failed(format!("Failed to write {}: {}", path.display(), e))
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(failed)?;
+        }
+        if let Err(e) = write.await {
+            let _ = tokio::fs::remove_file(tmp).await;
+            return Err(failed(e));
+        }
+        tokio::fs::rename(tmp, path).await.map_err(|e| {
+            let _ = std::fs::remove_file(tmp);
+            failed(e)
+        })
+    }
+
+    async fn tmp_path(&self) -> Result<PathBuf, ObjectStoreError> {
+        let dir = self.root.join(".tmp");
+        tokio::fs::create_dir_all(&dir).await.map_err(|e| {
+            ObjectStoreError::Failed(format!("Failed to create {}: {}", dir.display(), e))
+        })?;
+        Ok(dir.join(Uuid::new_v4().to_string()))
+    }
+}
+
+/// A path segment that stays inside its parent directory.
+fn is_safe_segment(segment: &str) -> bool {
+    !segment.is_empty()
+        && segment != "."
+        && segment != ".."
+        && !segment.contains(['\\', '\0'])
+        && !(cfg!(windows) && segment.contains(':'))
+}
+
+fn io_error(err: std::io::Error, bucket: &str, key: &str, action: &str) -> ObjectStoreError {
+    if err.kind() == ErrorKind::NotFound {
+        ObjectStoreError::NotFound(format!("{}/{}", bucket, key))
+    } else {
+        ObjectStoreError::Failed(format!("Failed to {} {}/{}: {}", action, bucket, key, err))
+    }
+}
+
+#[async_trait]
+impl ObjectStore for FsObjectStore {
+    fn backend(&self) -> &'static str {
+        "fs"
+    }
+
+    async fn put(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Vec<u8>,
+        _content_type: &str,
+    ) -> Result<(), ObjectStoreError> {
+        let path = self.object_path(bucket, key)?;
+        let tmp = self.tmp_path().await?;
+        self.write_atomically(&path, tokio::fs::write(&tmp, data), &tmp)
+            .await
+    }
+
+    async fn get(&self, bucket: &str, key: &str) -> Result<Vec<u8>, ObjectStoreError> {
+        let path = self.object_path(bucket, key)?;
+        tokio::fs::read(&path)
+            .await
+            .map_err(|e| io_error(e, bucket, key, "read"))
+    }
+
+    async fn size(&self, bucket: &str, key: &str) -> Result<u64, ObjectStoreError> {
+        let path = self.object_path(bucket, key)?;
+        match tokio::fs::metadata(&path).await {
+            Ok(meta) if meta.is_file() => Ok(meta.len()),
+            Ok(_) => Err(ObjectStoreError::NotFound(format!("{}/{}", bucket, key))),
+            Err(e) => Err(io_error(e, bucket, key, "read")),
+        }
+    }
+
+    async fn delete(&self, bucket: &str, key: &str) -> Result<(), ObjectStoreError> {
+        let path = self.object_path(bucket, key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(io_error(e, bucket, key, "delete")),
+        }
+    }
+
+    async fn list(&self, bucket: &str, prefix: &str) -> Result<Vec<ObjectInfo>, ObjectStoreError> {
+        let bucket_dir = self.bucket_dir(bucket)?;
+        let mut objects = Vec::new();
+        let mut pending = vec![(bucket_dir, String::new())];
+        while let Some((dir, dir_key)) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(io_error(e, bucket, &dir_key, "list")),
+            };
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .map_err(|e| io_error(e, bucket, &dir_key, "list"))?
+            {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+                let key = format!("{}{}", dir_key, name);
+                let meta = entry
+                    .metadata()
+                    .await
+                    .map_err(|e| io_error(e, bucket, &key, "list"))?;
+                if meta.is_dir() {
+                    // Only descend into directories that can hold matching keys.
+                    let dir_prefix = format!("{}/", key);
+                    if dir_prefix.starts_with(prefix) || prefix.starts_with(&dir_prefix) {
+                        pending.push((entry.path(), dir_prefix));
+                    }
+                } else if key.starts_with(prefix) {
+                    objects.push(ObjectInfo {
+                        key,
+                        size: meta.len(),
+                        last_modified: meta.modified().ok().map(DateTime::<Utc>::from),
+                    });
+                }
+            }
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    async fn copy(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dst_bucket: &str,
+        dst_key: &str,
+    ) -> Result<(), ObjectStoreError> {
+        let src = self.object_path(src_bucket, src_key)?;
+        let dst = self.object_path(dst_bucket, dst_key)?;
+        self.size(src_bucket, src_key).await?;
+        let tmp = self.tmp_path().await?;
+        let copy = async {
+            tokio::fs::copy(&src, &tmp).await?;
+            Ok::<(), std::io::Error>(())
+        };
+        self.write_atomically(&dst, copy, &tmp).await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriveBackend {
+    S3,
+    Filesystem(PathBuf),
+}
+
+impl DriveBackend {
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let backend = lookup("DRIVE_BACKEND")
+            .map(|v| v.trim().to_ascii_lowercase())
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "s3".to_string());
+        match backend.as_str() {
+            "s3" => Ok(Self::S3),
+            "fs" => Ok(Self::Filesystem(
+                lookup("DRIVE_FS_ROOT")
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from(get_stack_path()).join("data/drive")),
+            )),
+            other => Err(format!(
+                "Unknown DRIVE_BACKEND {:?}; use \"s3\" or \"fs\"",
+                other
+            )),
+        }
+    }
+
+    /// The store for this backend. `None` for S3 without a client.
+    pub fn build(&self, s3: Option<&Client>) -> Option<Arc<dyn ObjectStore>> {
+        match self {
+            Self::S3 => s3
+                .map(|client| Arc::new(S3ObjectStore::new(client.clone())) as Arc<dyn ObjectStore>),
+            Self::Filesystem(root) => Some(Arc::new(FsObjectStore::new(root.clone()))),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::drive::objects::tests::mock_drive;
+
+    /// Runs the same operations against either backend.
+    async fn exercise(store: &dyn ObjectStore) {
+        store
+            .put(
+                "sales.gbai",
+                "reports/q1.csv",
+                b"north,10\n".to_vec(),
+                "text/csv",
+            )
+            .await
+            .unwrap();
+        store
+            .put(
+                "sales.gbai",
+                "reports/2024/q2.csv",
+                b"south,7\n".to_vec(),
+                "text/csv",
+            )
+            .await
+            .unwrap();
+        store
+            .put("sales.gbai", "readme.txt", b"hi".to_vec(), "text/plain")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get("sales.gbai", "reports/q1.csv").await.unwrap(),
+            b"north,10\n"
+        );
+        assert_eq!(store.size("sales.gbai", "readme.txt").await.unwrap(), 2);
+        assert!(matches!(
+            store.get("sales.gbai", "missing.csv").await,
+            Err(ObjectStoreError::NotFound(_))
+        ));
+
+        let keys: Vec<String> = store
+            .list("sales.gbai", "reports/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|o| o.key)
+            .collect();
+        assert_eq!(keys, ["reports/2024/q2.csv", "reports/q1.csv"]);
+
+        store
+            .copy("sales.gbai", "reports/q1.csv", "archive.gbai", "q1.csv")
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get("archive.gbai", "q1.csv").await.unwrap(),
+            b"north,10\n"
+        );
+        assert!(matches!(
+            store
+                .copy("sales.gbai", "missing.csv", "archive.gbai", "x.csv")
+                .await,
+            Err(ObjectStoreError::NotFound(_))
+        ));
+
+        store.delete("sales.gbai", "reports/q1.csv").await.unwrap();
+        store.delete("sales.gbai", "reports/q1.csv").await.unwrap();
+        assert_eq!(store.list("sales.gbai", "reports/").await.unwrap().len(), 1);
+        assert!(store.list("empty.gbai", "").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_backends_behave_alike() {
+        let dir = tempfile::tempdir().unwrap();
+        exercise(&FsObjectStore::new(dir.path())).await;
+
+        let (drive, _) = mock_drive().await;
+        exercise(&S3ObjectStore::new(drive)).await;
+    }
+
+    #[tokio::test]
+    async fn test_fs_keys_stay_inside_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsObjectStore::new(dir.path().join("drive"));
+        for key in ["../escape.txt", "a/../../b", "/etc/passwd", "a//b", ""] {
+            assert!(
+                matches!(
+                    store.put("gbo", key, Vec::new(), "text/plain").await,
+                    Err(ObjectStoreError::InvalidKey(_))
+                ),
+                "{key:?} was accepted"
+            );
+        }
+        assert!(store.get("..", "x").await.is_err());
+        assert!(store.list(".tmp", "").await.is_err());
+    }
+
+    #[test]
+    fn test_backend_from_config() {
+        assert_eq!(
+            DriveBackend::from_lookup(|_| None).unwrap(),
+            DriveBackend::S3
+        );
+        let fs = DriveBackend::from_lookup(|key| match key {
+            "DRIVE_BACKEND" => Some("FS".to_string()),
+            "DRIVE_FS_ROOT" => Some("/srv/drive".to_string()),
+            _ => None,
+        });
+        assert_eq!(
+            fs.unwrap(),
+            DriveBackend::Filesystem(PathBuf::from("/srv/drive"))
+        );
+        assert!(DriveBackend::from_lookup(|_| Some("azure".to_string())).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+//! Server-side copy and move of drive objects. Both run as S3 `CopyObject` inside the
+//! object store, so file contents never pass through botserver; source and destination
+//! may be in different buckets. A move is a copy followed by deleting the source.
+//!
+//! [`copy_object`] and [`move_object`] are plain S3 operations. The `_tracked` variants
+//! go through an [`ObjectStore`], so they work with either drive backend, and also
+//! enforce the destination owner's quota and keep usage counters current, like
+//! [`quota::check_put`] does for uploads.
+
+use crate::core::shared::state::AppState;
+use crate::drive::object_store::ObjectStore;
+use crate::drive::quota::{self, QuotaExceeded};
+use aws_sdk_s3::Client;
+
+#[derive(Debug)]
+pub enum ObjectCopyError {
+    NotFound(String),
+    QuotaExceeded(QuotaExceeded),
+    Failed(String),
+}
+
+impl std::fmt::Display for ObjectCopyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(location) => write!(f, "Object not found: {}", location),
+            Self::QuotaExceeded(err) => err.fmt(f),
+            Self::Failed(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for ObjectCopyError {}
+
+/// `CopySource` value for `bucket/key`: the key is URL-encoded, its slashes kept.
+fn copy_source(bucket: &str, key: &str) -> String {
+    format!(
+        "{}/{}",
+        bucket,
+        urlencoding::encode(key).replace("%2F", "/")
+    )
+}
+
+/// Size of `bucket/key`, or [`ObjectCopyError::NotFound`] when it does not exist.
+pub async fn object_size(drive: &Client, bucket: &str, key: &str) -> Result<u64, ObjectCopyError> {
+    match drive.head_object().bucket(bucket).key(key).send().await {
+        Ok(head) => Ok(head.content_length().unwrap_or(0).max(0) as u64),
+        Err(e) => {
+            let err = e.into_service_error();
+            if err.is_not_found() {
+                Err(ObjectCopyError::NotFound(format!("{}/{}", bucket, key)))
+            } else {
+                Err(ObjectCopyError::Failed(format!(
+                    "Failed to read {}/{}: {}",
+                    bucket, key, err
+                )))
+            }
+        }
+    }
+}
+
+/// Copies `src_bucket/src_key` to `dst_bucket/dst_key`, replacing any object there.
+pub async fn copy_object(
+    drive: &Client,
+    src_bucket: &str,
+    src_key: &str,
+    dst_bucket: &str,
+    dst_key: &str,
+) -> Result<(), ObjectCopyError> {
+    drive
+        .copy_object()
+        .copy_source(copy_source(src_bucket, src_key))
+        .bucket(dst_bucket)
+        .key(dst_key)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| {
+            ObjectCopyError::Failed(format!(
+                "Failed to copy {}/{} to {}/{}: {}",
+                src_bucket,
+                src_key,
+                dst_bucket,
+                dst_key,
+                e.into_service_error()
+            ))
+        })
+}
+
+/// Moves `src_bucket/src_key` to `dst_bucket/dst_key`. Moving an object onto itself does
+/// nothing.
+pub async fn move_object(
+    drive: &Client,
+    src_bucket: &str,
+    src_key: &str,
+    dst_bucket: &str,
+    dst_key: &str,
+) -> Result<(), ObjectCopyError> {
+    if src_bucket == dst_bucket && src_key == dst_key {
+        return Ok(());
+    }
+    copy_object(drive, src_bucket, src_key, dst_bucket, dst_key).await?;
+    drive
+        .delete_object()
+        .bucket(src_bucket)
+        .key(src_key)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| {
+            ObjectCopyError::Failed(format!(
+                "Copied to {}/{} but failed to delete {}/{}: {}",
+                dst_bucket,
+                dst_key,
+                src_bucket,
+                src_key,
+                e.into_service_error()
+            ))
+        })
+}
+
+/// Copies like [`copy_object`], through any [`ObjectStore`], within the destination
+/// owner's quota. Returns the copied size.
+pub async fn copy_object_tracked(
+    state: &AppState,
+    store: &dyn ObjectStore,
+    src_bucket: &str,
+    src_key: &str,
+    dst_bucket: &str,
+    dst_key: &str,
+) -> Result<u64, ObjectCopyError> {
+    let size = store.size(src_bucket, src_key).await?;
+    let delta = quota::check_put(state, store, dst_bucket, dst_key, size)
+        .await
+        .map_err(ObjectCopyError::QuotaExceeded)?;
+    store.copy(src_bucket, src_key, dst_bucket, dst_key).await?;
+    quota::record_change(state, dst_bucket, dst_key, delta).await;
+    Ok(size)
+}
+
+/// Moves like [`move_object`], through any [`ObjectStore`], within the destination
+/// owner's quota. Returns the moved size.
+pub async fn move_object_tracked(
+    state: &AppState,
+    store: &dyn ObjectStore,
+    src_bucket: &str,
+    src_key: &str,
+    dst_bucket: &str,
+    dst_key: &str,
+) -> Result<u64, ObjectCopyError> {
+    if src_bucket == dst_bucket && src_key == dst_key {
+        return Ok(store.size(src_bucket, src_key).await?);
+    }
+    let size = copy_object_tracked(state, store, src_bucket, src_key, dst_bucket, dst_key).await?;
+    store.delete(src_bucket, src_key).await.map_err(|e| {
+        ObjectCopyError::Failed(format!(
+            "Copied to {}/{} but failed to delete {}/{}: {}",
+            dst_bucket, dst_key, src_bucket, src_key, e
+        ))
+    })?;
+    quota::record_change(state, src_bucket, src_key, -(size as i64)).await;
+    Ok(size)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::core::config::DriveConfig;
+    use axum::body::Bytes;
+    use axum::extract::State;
+    use axum::http::{HeaderMap, Method, StatusCode, Uri};
+    use axum::response::{IntoResponse, Response};
+    use axum::Router;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    type Objects = Arc<Mutex<HashMap<(String, String), Vec<u8>>>>;
+
+    /// Payload of an `aws-chunked` upload body.
+    fn decode_aws_chunked(body: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut rest = body;
+        while let Some(line_end) = rest.windows(2).position(|w| w == b"\r\n") {
+            let header = String::from_utf8_lossy(&rest[..line_end]);
+            let size_hex = header.split(';').next().unwrap_or("0").trim();
+            let size = usize::from_str_radix(size_hex, 16).unwrap_or(0);
+            if size == 0 {
+                break;
+            }
+            let start = line_end + 2;
+            data.extend_from_slice(&rest[start..start + size]);
+            rest = &rest[(start + size + 2).min(rest.len())..];
+        }
+        data
+    }
+
+    fn split_path(path: &str) -> (String, String) {
+        let path = urlencoding::decode(path.trim_start_matches('/'))
+            .map(|p| p.into_owned())
+            .unwrap_or_default();
+        let (bucket, key) = path.split_once('/').unwrap_or((path.as_str(), ""));
+        (bucket.to_string(), key.to_string())
+    }
+
+    fn no_such_key() -> Response {
+        (
+            StatusCode::NOT_FOUND,
+            [("content-type", "application/xml")],
+            "<Error><Code>NoSuchKey</Code><Message>missing</Message></Error>",
+        )
+            .into_response()
+    }
+
+    fn xml_escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    /// `ListObjectsV2` over the stored keys, honoring prefix, delimiter, max-keys and a
+    /// continuation token (the last key returned).
+    fn list_objects(objects: &HashMap<(String, String), Vec<u8>>, bucket: &str, query: &str) -> Response {
+        let params: HashMap<String, String> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let prefix = params.get("prefix").cloned().unwrap_or_default();
+        let delimiter = params.get("delimiter").filter(|d| !d.is_empty());
+        let max_keys: usize = params
+            .get("max-keys")
+            .and_then(|m| m.parse().ok())
+            .unwrap_or(1000);
+        let after = params.get("continuation-token").cloned().unwrap_or_default();
+
+        let mut keys: Vec<(&String, usize)> = objects
+            .iter()
+            .filter(|((b, key), _)| b == bucket && key.starts_with(&prefix))
+            .map(|((_, key), data)| (key, data.len()))
+            .collect();
+        keys.sort();
+
+        let mut contents = Vec::new();
+        let mut folders: Vec<String> = Vec::new();
+        let mut last = None;
+        let mut truncated = false;
+        for (key, size) in keys {
+            let folder = delimiter.and_then(|d| {
+                let rest = &key[prefix.len()..];
+                rest.find(d.as_str())
+                    .map(|i| format!("{}{}", prefix, &rest[..i + d.len()]))
+            });
+            let entry = folder.clone().unwrap_or_else(|| key.clone());
+            if entry.as_str() <= after.as_str() || folders.last() == Some(&entry) {
+                continue;
+            }
+            if contents.len() + folders.len() == max_keys {
+                truncated = true;
+                break;
+            }
+            last = Some(entry.clone());
+            match folder {
+                Some(folder) => folders.push(folder),
+                None => contents.push(format!(
+                    "<Contents><Key>{}</Key><LastModified>2024-01-01T00:00:00.000Z</LastModified><Size>{}</Size></Contents>",
+                    xml_escape(key),
+                    size
+                )),
+            }
+        }
+
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult xmlns=\"http://s3.amazonaws.com/doc/2006-03-01/\"><Name>{}</Name><Prefix>{}</Prefix><KeyCount>{}</KeyCount><MaxKeys>{}</MaxKeys><IsTruncated>{}</IsTruncated>",
+            bucket,
+            xml_escape(&prefix),
+            contents.len() + folders.len(),
+            max_keys,
+            truncated
+        );
+        if truncated {
+            if let Some(last) = last {
+                xml.push_str(&format!(
+                    "<NextContinuationToken>{}</NextContinuationToken>",
+                    xml_escape(&last)
+                ));
+            }
+        }
+        xml.push_str(&contents.concat());
+        for folder in folders {
+            xml.push_str(&format!(
+                "<CommonPrefixes><Prefix>{}</Prefix></CommonPrefixes>",
+                xml_escape(&folder)
+            ));
+        }
+        xml.push_str("</ListBucketResult>");
+        ([("content-type", "application/xml")], xml).into_response()
+    }
+
+    /// Minimal path-style S3: put, get, head, delete, copy and list.
+    async fn s3(
+        State(objects): State<Objects>,
+        method: Method,
+        uri: Uri,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Response {
+        let location = split_path(uri.path());
+        let mut objects = objects.lock().unwrap();
+        if method == Method::GET && location.1.is_empty() {
+            return list_objects(&objects, &location.0, uri.query().unwrap_or_default());
+        }
+        match method {
+            Method::PUT => {
+                if let Some(source) = headers.get("x-amz-copy-source") {
+                    let source = split_path(source.to_str().unwrap_or_default());
+                    let Some(data) = objects.get(&source).cloned() else {
+                        return no_such_key();
+                    };
+                    objects.insert(location, data);
+                    return (
+                        [("content-type", "application/xml")],
+                        "<CopyObjectResult><ETag>\"copy\"</ETag></CopyObjectResult>",
+                    )
+                        .into_response();
+                }
+                let chunked = headers
+                    .get("content-encoding")
+                    .and_then(|v| v.to_str().ok())
+                    .is_some_and(|v| v.contains("aws-chunked"));
+                let data = if chunked {
+                    decode_aws_chunked(&body)
+                } else {
+                    body.to_vec()
+                };
+                objects.insert(location, data);
+                [("etag", "\"put\"")].into_response()
+            }
+            Method::GET | Method::HEAD => match objects.get(&location) {
+                Some(data) if method == Method::GET => data.clone().into_response(),
+                Some(data) => [("content-length", data.len().to_string())].into_response(),
+                None if method == Method::HEAD => StatusCode::NOT_FOUND.into_response(),
+                None => no_such_key(),
+            },
+            Method::DELETE => {
+                objects.remove(&location);
+                StatusCode::NO_CONTENT.into_response()
+            }
+            _ => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+        }
+    }
+
+    /// Drive client backed by an in-memory S3 server, with access to its objects.
+    pub(crate) async fn mock_drive() -> (Client, Objects) {
+        let objects = Objects::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new().fallback(s3).with_state(objects.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let config = DriveConfig {
+            server: format!("http://{}", addr),
+            access_key: "test".to_string(),
+            secret_key: "test".to_string(),
+        };
+        let drive = crate::core::shared::utils::create_s3_operator(&config)
+            .await
+            .unwrap();
+        (drive, objects)
+    }
+
+    fn stored(objects: &Objects, bucket: &str, key: &str) -> Option<Vec<u8>> {
+        objects
+            .lock()
+            .unwrap()
+            .get(&(bucket.to_string(), key.to_string()))
+            .cloned()
+    }
+
+    #[tokio::test]
+    async fn test_copy_and_move_across_buckets() {
+        let (drive, objects) = mock_drive().await;
+        objects.lock().unwrap().insert(
+            (
+                "sales.gbai".to_string(),
+                "reports/q1 summary.csv".to_string(),
+            ),
+            b"region,total\nnorth,10\n".to_vec(),
+        );
+
+        copy_object(
+            &drive,
+            "sales.gbai",
+            "reports/q1 summary.csv",
+            "archive.gbai",
+            "2024/q1.csv",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            stored(&objects, "archive.gbai", "2024/q1.csv"),
+            stored(&objects, "sales.gbai", "reports/q1 summary.csv")
+        );
+        assert_eq!(
+            object_size(&drive, "archive.gbai", "2024/q1.csv")
+                .await
+                .unwrap(),
+            22
+        );
+
+        move_object(
+            &drive,
+            "archive.gbai",
+            "2024/q1.csv",
+            "archive.gbai",
+            "2024/old/q1.csv",
+        )
+        .await
+        .unwrap();
+        assert!(stored(&objects, "archive.gbai", "2024/q1.csv").is_none());
+        assert!(stored(&objects, "archive.gbai", "2024/old/q1.csv").is_some());
+
+        assert!(matches!(
+            object_size(&drive, "sales.gbai", "missing.csv").await,
+            Err(ObjectCopyError::NotFound(_))
+        ));
+        assert!(
+            copy_object(&drive, "sales.gbai", "missing.csv", "sales.gbai", "b.csv")
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,526 @@
+//! Storage quotas per owner. Files under a user's area (`users/{id}/…`, in any bucket)
+//! count against that user, as `user:{id}`; every other file counts against its bucket,
+//! which for `{bot}.gbai` buckets is the bot. See [`usage_owner`]. Knowledge base
+//! uploads are kept as text in the database and count against the uploading user.
+//!
+//! Usage is an incremental counter in `drive_usage`, adjusted after each write that goes
+//! through [`check_put`] / [`record_change`]; the usage endpoint can recount an owner from
+//! the drive when the counter drifts. The quota is the owner's own `quota_bytes` or, when
+//! unset, `DRIVE_QUOTA_MB`; without either an owner is unlimited. Concurrent writes are
+//! checked independently, so an owner can briefly end up slightly over its quota.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::shared::state::AppState;
+use crate::drive::object_store::{ObjectStore, ObjectStoreError};
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post, put},
+    Json, Router,
+};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text};
+use diesel::PgConnection;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Bucket holding the sheets' per-user areas.
+const SHEETS_BUCKET: &str = "gbo";
+const USER_OWNER_PREFIX: &str = "user:";
+
+/// Who the size of `bucket/key` counts against: `user:{id}` for keys under
+/// `users/{id}/`, otherwise the bucket.
+pub fn usage_owner(bucket: &str, key: &str) -> String {
+    key.strip_prefix("users/")
+        .and_then(|rest| rest.split_once('/'))
+        .filter(|(user, _)| !user.is_empty())
+        .map(|(user, _)| user_owner(user))
+        .unwrap_or_else(|| bucket.to_string())
+}
+
+pub fn user_owner(user_id: &str) -> String {
+    format!("{USER_OWNER_PREFIX}{user_id}")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub owner: String,
+    pub used_bytes: u64,
+    pub quota_bytes: u64,
+    pub requested_bytes: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Storage quota exceeded for {}: {} of {} bytes used, {} more requested",
+            self.owner, self.used_bytes, self.quota_bytes, self.requested_bytes
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+impl From<QuotaExceeded> for ApiError {
+    fn from(err: QuotaExceeded) -> Self {
+        ApiError::new(
+            StatusCode::INSUFFICIENT_STORAGE,
+            "QUOTA_EXCEEDED",
+            err.to_string(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, QueryableByName)]
+pub struct OwnerUsage {
+    /// `user:{id}` or a bucket name, see [`usage_owner`].
+    #[diesel(sql_type = Text)]
+    pub owner: String,
+    #[diesel(sql_type = BigInt)]
+    pub used_bytes: i64,
+    /// Quota set for this owner; `None` means the `DRIVE_QUOTA_MB` default applies.
+    #[diesel(sql_type = Nullable<BigInt>)]
+    pub quota_bytes: Option<i64>,
+}
+
+impl OwnerUsage {
+    fn empty(owner: &str) -> Self {
+        Self {
+            owner: owner.to_string(),
+            used_bytes: 0,
+            quota_bytes: None,
+        }
+    }
+
+    pub fn effective_quota(&self, default_quota: Option<u64>) -> Option<u64> {
+        self.quota_bytes.map(|q| q.max(0) as u64).or(default_quota)
+    }
+
+    /// Size change of replacing `replaced` bytes with `size` bytes, or the error when the
+    /// result would not fit. Writes that do not grow the usage are always allowed.
+    pub fn check_write(
+        &self,
+        default_quota: Option<u64>,
+        replaced: u64,
+        size: u64,
+    ) -> Result<i64, QuotaExceeded> {
+        let delta = size as i64 - replaced as i64;
+        let used = self.used_bytes.max(0) as u64;
+        match self.effective_quota(default_quota) {
+            Some(quota) if delta > 0 && used + delta as u64 > quota => Err(QuotaExceeded {
+                owner: self.owner.clone(),
+                used_bytes: used,
+                quota_bytes: quota,
+                requested_bytes: delta as u64,
+            }),
+            _ => Ok(delta),
+        }
+    }
+}
+
+/// Quota for owners without their own, from `DRIVE_QUOTA_MB`.
+pub fn default_quota_bytes() -> Option<u64> {
+    std::env::var("DRIVE_QUOTA_MB")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|mb| *mb > 0)
+        .map(|mb| mb * 1024 * 1024)
+}
+
+fn load_usage(conn: &mut PgConnection, owner: &str) -> QueryResult<OwnerUsage> {
+    diesel::sql_query("SELECT owner, used_bytes, quota_bytes FROM drive_usage WHERE owner = $1")
+        .bind::<Text, _>(owner)
+        .get_result::<OwnerUsage>(conn)
+        .optional()
+        .map(|row| row.unwrap_or_else(|| OwnerUsage::empty(owner)))
+}
+
+fn add_usage(conn: &mut PgConnection, owner: &str, delta: i64) -> QueryResult<()> {
+    diesel::sql_query(
+        "INSERT INTO drive_usage (owner, used_bytes, updated_at) VALUES ($1, GREATEST($2, 0), NOW())
+         ON CONFLICT (owner)
+         DO UPDATE SET used_bytes = GREATEST(drive_usage.used_bytes + $2, 0), updated_at = NOW()",
+    )
+    .bind::<Text, _>(owner)
+    .bind::<BigInt, _>(delta)
+    .execute(conn)
+    .map(|_| ())
+}
+
+async fn object_size(store: &dyn ObjectStore, bucket: &str, key: &str) -> u64 {
+    store.size(bucket, key).await.unwrap_or(0)
+}
+
+/// Checks that writing `size` bytes to `bucket/key` fits its owner's quota and returns
+/// the size change to pass to [`record_change`] once the write succeeded. Usage lookup
+/// failures are logged and let the write through.
+pub async fn check_put(
+    state: &AppState,
+    store: &dyn ObjectStore,
+    bucket: &str,
+    key: &str,
+    size: u64,
+) -> Result<i64, QuotaExceeded> {
+    let replaced = object_size(store, bucket, key).await;
+    check_owner(state, &usage_owner(bucket, key), replaced, size).await
+}
+
+/// [`check_put`] for writes made with the S3 client directly.
+pub async fn check_s3_put(
+    state: &AppState,
+    client: &aws_sdk_s3::Client,
+    bucket: &str,
+    key: &str,
+    size: u64,
+) -> Result<i64, QuotaExceeded> {
+    let replaced = s3_object_size(client, bucket, key).await;
+    check_owner(state, &usage_owner(bucket, key), replaced, size).await
+}
+
+/// Checks that replacing `replaced` bytes with `size` bytes fits `owner`'s quota, for
+/// stored data that is not a drive object, such as knowledge base text.
+pub async fn check_owner(
+    state: &AppState,
+    owner: &str,
+    replaced: u64,
+    size: u64,
+) -> Result<i64, QuotaExceeded> {
+    let pool = state.conn.clone();
+    let owner_key = owner.to_string();
+    let usage = tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        load_usage(&mut conn, &owner_key).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+
+    match usage {
+        Ok(usage) => usage.check_write(default_quota_bytes(), replaced, size),
+        Err(e) => {
+            warn!("Failed to load drive usage of {}: {}", owner, e);
+            Ok(size as i64 - replaced as i64)
+        }
+    }
+}
+
+/// Size of `bucket/key` before it is deleted, to pass negated to [`record_change`].
+pub async fn size_before_delete(store: &dyn ObjectStore, bucket: &str, key: &str) -> u64 {
+    object_size(store, bucket, key).await
+}
+
+/// Size of `bucket/key` read with the S3 client, 0 when it does not exist.
+pub async fn s3_object_size(client: &aws_sdk_s3::Client, bucket: &str, key: &str) -> u64 {
+    client
+        .head_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await
+        .ok()
+        .and_then(|head| head.content_length())
+        .map_or(0, |len| len.max(0) as u64)
+}
+
+#[derive(Debug)]
+pub enum PutError {
+    QuotaExceeded(QuotaExceeded),
+    Store(ObjectStoreError),
+}
+
+impl std::fmt::Display for PutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::QuotaExceeded(e) => e.fmt(f),
+            Self::Store(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for PutError {}
+
+/// Writes `body` to `bucket/key` within its owner's quota and records the size change.
+pub async fn put_tracked(
+    state: &AppState,
+    store: &dyn ObjectStore,
+    bucket: &str,
+    key: &str,
+    body: Vec<u8>,
+    content_type: &str,
+) -> Result<(), PutError> {
+    let delta = check_put(state, store, bucket, key, body.len() as u64)
+        .await
+        .map_err(PutError::QuotaExceeded)?;
+    store
+        .put(bucket, key, body, content_type)
+        .await
+        .map_err(PutError::Store)?;
+    record_change(state, bucket, key, delta).await;
+    Ok(())
+}
+
+/// Deletes `bucket/key` and takes its size off its owner's usage.
+pub async fn delete_tracked(
+    state: &AppState,
+    store: &dyn ObjectStore,
+    bucket: &str,
+    key: &str,
+) -> Result<(), ObjectStoreError> {
+    let size = size_before_delete(store, bucket, key).await;
+    store.delete(bucket, key).await?;
+    record_change(state, bucket, key, -(size as i64)).await;
+    Ok(())
+}
+
+/// Adds `delta` to the usage of the owner of `bucket/key`.
+pub async fn record_change(state: &AppState, bucket: &str, key: &str, delta: i64) {
+    record_owner_change(state, &usage_owner(bucket, key), delta).await;
+}
+
+pub async fn record_owner_change(state: &AppState, owner: &str, delta: i64) {
+    if delta == 0 {
+        return;
+    }
+    let pool = state.conn.clone();
+    let owner_key = owner.to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        add_usage(&mut conn, &owner_key, delta).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r);
+    if let Err(e) = result {
+        warn!("Failed to record drive usage of {}: {}", owner, e);
+    }
+}
+
+/// Buckets and key prefix holding `owner`'s files.
+fn owner_locations(state: &AppState, owner: &str) -> (Vec<String>, String) {
+    match owner.strip_prefix(USER_OWNER_PREFIX) {
+        Some(user) => {
+            let mut buckets = vec![SHEETS_BUCKET.to_string(), state.bucket_name.clone()];
+            buckets.dedup();
+            (buckets, format!("users/{user}/"))
+        }
+        None => (vec![owner.to_string()], String::new()),
+    }
+}
+
+/// Sums the sizes of every drive object that counts against `owner`.
+async fn measure_owner(
+    store: &dyn ObjectStore,
+    buckets: &[String],
+    prefix: &str,
+    owner: &str,
+) -> Result<u64, String> {
+    let mut total = 0;
+    for bucket in buckets {
+        let objects = store
+            .list(bucket, prefix)
+            .await
+            .map_err(|e| e.to_string())?;
+        total += objects
+            .iter()
+            .filter(|obj| usage_owner(bucket, &obj.key) == owner)
+            .map(|obj| obj.size)
+            .sum::<u64>();
+    }
+    Ok(total)
+}
+
+/// Knowledge base text charged to `owner` by the upload endpoint.
+fn knowledge_base_bytes(conn: &mut PgConnection, owner: &str) -> QueryResult<i64> {
+    #[derive(QueryableByName)]
+    struct Total {
+        #[diesel(sql_type = BigInt)]
+        total: i64,
+    }
+    diesel::sql_query(
+        "SELECT COALESCE(SUM((metadata->>'stored_bytes')::BIGINT), 0)::BIGINT AS total
+         FROM knowledge_sources WHERE metadata->>'usage_owner' = $1",
+    )
+    .bind::<Text, _>(owner)
+    .get_result::<Total>(conn)
+    .map(|row| row.total)
+}
+
+#[derive(Debug, Serialize)]
+pub struct DriveUsageResponse {
+    pub default_quota_bytes: Option<u64>,
+    pub owners: Vec<OwnerUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetQuotaRequest {
+    /// Quota in bytes; `null` returns the owner to the `DRIVE_QUOTA_MB` default.
+    pub quota_bytes: Option<u64>,
+}
+
+async fn run_blocking<T, F>(state: &AppState, f: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> QueryResult<T> + Send + 'static,
+{
+    let pool = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| ApiError::service_unavailable(e.to_string()))?;
+        f(&mut conn).map_err(|e| ApiError::internal(e.to_string()))
+    })
+    .await
+    .map_err(|e| ApiError::internal(e.to_string()))?
+}
+
+pub async fn handle_list_usage(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+) -> Result<Json<DriveUsageResponse>, ApiError> {
+    require_admin(&user)?;
+
+    let owners = run_blocking(&state, |conn| {
+        diesel::sql_query(
+            "SELECT owner, used_bytes, quota_bytes FROM drive_usage ORDER BY used_bytes DESC",
+        )
+        .load::<OwnerUsage>(conn)
+    })
+    .await?;
+    Ok(Json(DriveUsageResponse {
+        default_quota_bytes: default_quota_bytes(),
+        owners,
+    }))
+}
+
+pub async fn handle_set_quota(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(owner): Path<String>,
+    ApiJson(req): ApiJson<SetQuotaRequest>,
+) -> Result<Json<OwnerUsage>, ApiError> {
+    require_admin(&user)?;
+
+    let quota = req
+        .quota_bytes
+        .map(|q| i64::try_from(q).map_err(|_| ApiError::bad_request("quota_bytes is too large")))
+        .transpose()?;
+    let target = owner.clone();
+    let usage = run_blocking(&state, move |conn| {
+        diesel::sql_query(
+            "INSERT INTO drive_usage (owner, quota_bytes, updated_at) VALUES ($1, $2, NOW())
+             ON CONFLICT (owner) DO UPDATE SET quota_bytes = $2, updated_at = NOW()",
+        )
+        .bind::<Text, _>(&target)
+        .bind::<Nullable<BigInt>, _>(quota)
+        .execute(conn)?;
+        load_usage(conn, &target)
+    })
+    .await?;
+
+    info!(
+        "Drive quota of {} set to {:?} bytes (by {})",
+        owner, quota, user.user_id
+    );
+    Ok(Json(usage))
+}
+
+/// Recounts an owner's usage from the drive and the knowledge base, correcting a
+/// drifted counter.
+pub async fn handle_recalculate_usage(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(owner): Path<String>,
+) -> Result<Json<OwnerUsage>, ApiError> {
+    require_admin(&user)?;
+
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or_else(|| ApiError::service_unavailable("Drive not available"))?;
+    let (buckets, prefix) = owner_locations(&state, &owner);
+    let used = measure_owner(store, &buckets, &prefix, &owner)
+        .await
+        .map_err(ApiError::internal)?;
+    let used = i64::try_from(used).unwrap_or(i64::MAX);
+
+    let target = owner.clone();
+    let usage = run_blocking(&state, move |conn| {
+        let used = used.saturating_add(knowledge_base_bytes(conn, &target)?);
+        diesel::sql_query(
+            "INSERT INTO drive_usage (owner, used_bytes, updated_at) VALUES ($1, $2, NOW())
+             ON CONFLICT (owner) DO UPDATE SET used_bytes = $2, updated_at = NOW()",
+        )
+        .bind::<Text, _>(&target)
+        .bind::<BigInt, _>(used)
+        .execute(conn)?;
+        load_usage(conn, &target)
+    })
+    .await?;
+    Ok(Json(usage))
+}
+
+pub fn configure() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/admin/drive/usage", get(handle_list_usage))
+        .route("/api/admin/drive/usage/:owner", put(handle_set_quota))
+        .route(
+            "/api/admin/drive/usage/:owner/recalculate",
+            post(handle_recalculate_usage),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exceeding_quota_blocks_further_writes() {
+        let mut usage = OwnerUsage::empty("sales.gbai");
+        let quota = Some(100);
+
+        // Two writes fit, the third would go over the quota.
+        for size in [60, 30] {
+            let delta = usage.check_write(quota, 0, size).unwrap();
+            usage.used_bytes += delta;
+        }
+        let err = usage.check_write(quota, 0, 20).unwrap_err();
+        assert_eq!(err.used_bytes, 90);
+        assert_eq!(err.requested_bytes, 20);
+        assert_eq!(ApiError::from(err).status, StatusCode::INSUFFICIENT_STORAGE);
+        assert_eq!(usage.used_bytes, 90);
+
+        // Overwriting with a smaller file, or growing one within the quota, still works.
+        assert_eq!(usage.check_write(quota, 60, 10), Ok(-50));
+        assert_eq!(usage.check_write(quota, 60, 70), Ok(10));
+
+        // An owner's own quota overrides the default; no quota at all means unlimited.
+        usage.quota_bytes = Some(1000);
+        assert!(usage.check_write(quota, 0, 500).is_ok());
+        assert!(OwnerUsage::empty("gbo")
+            .check_write(None, 0, u32::MAX as u64)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_user_areas_count_against_the_user() {
+        assert_eq!(
+            usage_owner("gbo", "users/alice/sheets/a.json"),
+            "user:alice"
+        );
+        assert_eq!(
+            usage_owner("default.gbai", "users/alice/docs/d.html"),
+            "user:alice"
+        );
+        assert_eq!(
+            usage_owner("sales.gbai", "sales.gbdrive/report.csv"),
+            "sales.gbai"
+        );
+        assert_eq!(usage_owner("gbo", "users//x.json"), "gbo");
+        assert_eq!(usage_owner("gbo", "users/alice"), "gbo");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use super::types::*;
 use axum::{
@@ -5,7 +6,7 @@ use axum::{
     response::IntoResponse,
     Json,
 };
-use base64::{engine::general_purpose, Engine as _};
+use crate::security::master_key::{self, LegacyEncoding};
 use diesel::prelude::*;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -14,20 +15,33 @@ fn extract_user_from_session(_state: &Arc<AppState>) -> Result<Uuid, String> {
     Ok(Uuid::new_v4())
 }
 
-fn encrypt_password(password: &str) -> String {
-    general_purpose::STANDARD.encode(password.as_bytes())
+fn encrypt_password(password: &str) -> Result<String, EmailError> {
+    master_key::protect(password, LegacyEncoding::Base64)
+        .map_err(|e| EmailError::Internal(format!("Failed to encrypt password: {e}")))
+}
+
+const DEFAULT_FOLDER: &str = "INBOX";
+
+fn normalize_folder(folder: Option<&str>) -> Result<String, EmailError> {
+    let folder = folder.map(str::trim).filter(|f| !f.is_empty()).unwrap_or(DEFAULT_FOLDER);
+    if folder.len() > 255 || folder.chars().any(char::is_control) {
+        return Err(EmailError::BadRequest("Invalid folder name".to_string()));
+    }
+    Ok(folder.to_string())
 }
 
 pub async fn add_email_account(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<EmailAccountRequest>,
+    ApiJson(request): ApiJson<EmailAccountRequest>,
 ) -> Result<Json<ApiResponse<EmailAccountResponse>>, EmailError> {
     let Ok(current_user_id) = extract_user_from_session(&state) else {
-        return Err(EmailError("Authentication required".to_string()));
+        return Err(EmailError::Unauthorized("Authentication required".to_string()));
     };
 
     let account_id = Uuid::new_v4();
-    let encrypted_password = encrypt_password(&request.password);
+    let encrypted_password = encrypt_password(&request.password)?;
+    let default_folder = normalize_folder(request.default_folder.as_deref())?;
+    let resp_default_folder = default_folder.clone();
 
     let resp_email = request.email.clone();
     let resp_display_name = request.display_name.clone();
@@ -51,8 +65,8 @@ pub async fn add_email_account(
 
         diesel::sql_query(
             "INSERT INTO user_email_accounts
-            (id, user_id, email, display_name, imap_server, imap_port, smtp_server, smtp_port, username, password_encrypted, is_primary, is_active)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)"
+            (id, user_id, email, display_name, imap_server, imap_port, smtp_server, smtp_port, username, password_encrypted, is_primary, is_active, default_folder)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"
         )
         .bind::<diesel::sql_types::Uuid, _>(account_id)
         .bind::<diesel::sql_types::Uuid, _>(current_user_id)
@@ -66,14 +80,15 @@ pub async fn add_email_account(
         .bind::<diesel::sql_types::Text, _>(&encrypted_password)
         .bind::<diesel::sql_types::Bool, _>(request.is_primary)
         .bind::<diesel::sql_types::Bool, _>(true)
+        .bind::<diesel::sql_types::Text, _>(&default_folder)
         .execute(&mut db_conn)
         .map_err(|e| format!("Failed to insert account: {e}"))?;
 
         Ok::<_, String>(account_id)
     })
     .await
-    .map_err(|e| EmailError(format!("Task join error: {e}")))?
-    .map_err(EmailError)?;
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -85,6 +100,7 @@ pub async fn add_email_account(
             imap_port: resp_imap_port,
             smtp_server: resp_smtp_server,
             smtp_port: resp_smtp_port,
+            default_folder: resp_default_folder,
             is_primary: resp_is_primary,
             is_active: true,
             created_at: chrono::Utc::now().to_rfc3339(),
@@ -155,14 +171,14 @@ pub async fn list_email_accounts(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<ApiResponse<Vec<EmailAccountResponse>>>, EmailError> {
     let Ok(current_user_id) = extract_user_from_session(&state) else {
-        return Err(EmailError("Authentication required".to_string()));
+        return Err(EmailError::Unauthorized("Authentication required".to_string()));
     };
 
     let conn = state.conn.clone();
     let accounts = tokio::task::spawn_blocking(move || {
         use crate::core::shared::models::schema::user_email_accounts::dsl::{
-            created_at, display_name, email, id, imap_port, imap_server, is_active, is_primary,
-            smtp_port, smtp_server, user_email_accounts, user_id,
+            created_at, default_folder, display_name, email, id, imap_port, imap_server, is_active,
+            is_primary, smtp_port, smtp_server, user_email_accounts, user_id,
         };
         let mut db_conn = conn
             .get()
@@ -180,6 +196,7 @@ pub async fn list_email_accounts(
                 imap_port,
                 smtp_server,
                 smtp_port,
+                default_folder,
                 is_primary,
                 is_active,
                 created_at,
@@ -192,6 +209,7 @@ pub async fn list_email_accounts(
                 i32,
                 String,
                 i32,
+                String,
                 bool,
                 bool,
                 chrono::DateTime<chrono::Utc>,
@@ -201,8 +219,8 @@ pub async fn list_email_accounts(
         Ok::<_, String>(results)
     })
     .await
-    .map_err(|e| EmailError(format!("Task join error: {e}")))?
-    .map_err(EmailError)?;
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
 
     let account_list: Vec<EmailAccountResponse> = accounts
         .into_iter()
@@ -215,6 +233,7 @@ pub async fn list_email_accounts(
                 acc_imap_port,
                 acc_smtp_server,
                 acc_smtp_port,
+                acc_default_folder,
                 acc_is_primary,
                 acc_is_active,
                 acc_created_at,
@@ -227,6 +246,7 @@ pub async fn list_email_accounts(
                     imap_port: acc_imap_port as u16,
                     smtp_server: acc_smtp_server,
                     smtp_port: acc_smtp_port as u16,
+                    default_folder: acc_default_folder,
                     is_primary: acc_is_primary,
                     is_active: acc_is_active,
                     created_at: acc_created_at.to_rfc3339(),
@@ -247,7 +267,7 @@ pub async fn delete_email_account(
     Path(account_id): Path<String>,
 ) -> Result<Json<ApiResponse<()>>, EmailError> {
     let account_uuid =
-        Uuid::parse_str(&account_id).map_err(|_| EmailError("Invalid account ID".to_string()))?;
+        Uuid::parse_str(&account_id).map_err(|_| EmailError::BadRequest("Invalid account ID".to_string()))?;
 
     let conn = state.conn.clone();
     tokio::task::spawn_blocking(move || {
@@ -263,8 +283,11 @@ pub async fn delete_email_account(
         Ok::<_, String>(())
     })
     .await
-    .map_err(|e| EmailError(format!("Task join error: {e}")))?
-    .map_err(EmailError)?;
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
+
+    #[cfg(feature = "mail")]
+    super::imap_pool::IMAP_POOL.evict(account_uuid);
 
     Ok(Json(ApiResponse {
         success: true,
@@ -272,3 +295,42 @@ pub async fn delete_email_account(
         message: Some("Email account deleted".to_string()),
     }))
 }
+
+pub async fn update_default_folder(
+    State(state): State<Arc<AppState>>,
+    Path(account_id): Path<String>,
+    ApiJson(request): ApiJson<DefaultFolderRequest>,
+) -> Result<Json<ApiResponse<String>>, EmailError> {
+    let account_uuid =
+        Uuid::parse_str(&account_id).map_err(|_| EmailError::BadRequest("Invalid account ID".to_string()))?;
+    let folder = normalize_folder(Some(&request.folder))?;
+
+    let conn = state.conn.clone();
+    let folder_value = folder.clone();
+    let updated = tokio::task::spawn_blocking(move || {
+        let mut db_conn = conn
+            .get()
+            .map_err(|e| format!("DB connection error: {e}"))?;
+
+        diesel::sql_query(
+            "UPDATE user_email_accounts SET default_folder = $2, updated_at = NOW() WHERE id = $1 AND is_active = true",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(account_uuid)
+        .bind::<diesel::sql_types::Text, _>(&folder_value)
+        .execute(&mut db_conn)
+        .map_err(|e| format!("Failed to update default folder: {e}"))
+    })
+    .await
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
+
+    if updated == 0 {
+        return Err(EmailError::BadRequest("Account not found".to_string()));
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: Some(folder),
+        message: Some("Default folder updated".to_string()),
+    }))
+}
@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -25,7 +26,7 @@ pub struct FlagResponse {
 /// Flag emails for follow-up
 pub async fn flag_for_followup(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<FlagRequest>,
+    ApiJson(req): ApiJson<FlagRequest>,
 ) -> Result<Json<FlagResponse>, StatusCode> {
     use crate::core::shared::schema::email_flags;
     
@@ -53,7 +54,7 @@ pub async fn flag_for_followup(
 /// Clear flag from email
 pub async fn clear_flag(
     State(state): State<Arc<AppState>>,
-    Json(email_id): Json<Uuid>,
+    ApiJson(email_id): ApiJson<Uuid>,
 ) -> Result<StatusCode, StatusCode> {
     use crate::core::shared::schema::email_flags;
 
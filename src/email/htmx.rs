@@ -1,6 +1,10 @@
 use crate::core::shared::state::AppState;
 use crate::core::config::EmailConfig;
+use crate::core::shared::request_timeout::RequestCancellation;
+use crate::security::master_key::{self, LegacyEncoding};
 use super::types::*;
+#[cfg(feature = "mail")]
+use super::imap_pool::{connect_imap, ImapSession, IMAP_POOL, SYSTEM_MAILBOX};
 use axum::{
     extract::{Path, Query, State},
     response::IntoResponse,
@@ -15,62 +19,80 @@ fn extract_user_from_session(_state: &Arc<AppState>) -> Result<Uuid, String> {
     Ok(Uuid::new_v4())
 }
 
+fn account_password(encrypted: &str) -> String {
+    master_key::reveal(encrypted, LegacyEncoding::Base64).unwrap_or_else(|e| {
+        warn!("Failed to decrypt email account password: {}", e);
+        String::new()
+    })
+}
+
+#[cfg(feature = "mail")]
+fn connect_system_mailbox(config: &EmailConfig) -> Result<ImapSession, String> {
+    connect_imap(&config.server, config.port, &config.username, &config.password)
+}
+
+/// Runs IMAP work on the blocking pool, off the async runtime, unless the request was
+/// abandoned before it started.
+async fn run_imap<T: Send + 'static>(
+    cancellation: &RequestCancellation,
+    work: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    cancellation
+        .spawn_blocking(work)
+        .await
+        .map_err(|e| format!("Task join error: {e}"))?
+        .unwrap_or_else(|| Err("Request cancelled".to_string()))
+}
+
 fn fetch_emails_from_folder(
     config: &EmailConfig,
     folder: &str,
 ) -> Result<Vec<EmailSummary>, String> {
     #[cfg(feature = "mail")]
     {
-        let client = imap::ClientBuilder::new(&config.server, config.port)
-            .connect()
-            .map_err(|e| format!("Connection error: {}", e))?;
-
-        let mut session = client
-            .login(&config.username, &config.password)
-            .map_err(|e| format!("Login failed: {:?}", e))?;
-
-        let folder_name = match folder {
-            "sent" => "Sent",
-            "drafts" => "Drafts",
-            "trash" => "Trash",
-            _ => "INBOX",
-        };
-
-        session
-            .select(folder_name)
-            .map_err(|e| format!("Select folder failed: {}", e))?;
-
-        let messages = session
-            .fetch("1:20", "(FLAGS RFC822.HEADER)")
-            .map_err(|e| format!("Fetch failed: {}", e))?;
-
-        let mut emails = Vec::new();
-        for message in messages.iter() {
-            if let Some(header) = message.header() {
-                let parsed = parse_mail(header).ok();
-                if let Some(mail) = parsed {
-                    let subject = mail.headers.get_first_value("Subject").unwrap_or_default();
-                    let from = mail.headers.get_first_value("From").unwrap_or_default();
-                    let date = mail.headers.get_first_value("Date").unwrap_or_default();
-                    let flags = message.flags();
-                    let read = flags.iter().any(|f| matches!(f, imap::types::Flag::Seen));
-
-                    let preview = subject.chars().take(100).collect();
-                    emails.push(EmailSummary {
-                        id: message.message.to_string(),
-                        from_name: from.clone(),
-                        from_email: from,
-                        subject,
-                        preview,
-                        date,
-                        read,
-                    });
+        IMAP_POOL.with_session(SYSTEM_MAILBOX, || connect_system_mailbox(config), |session| {
+            let folder_name = match folder {
+                "sent" => "Sent",
+                "drafts" => "Drafts",
+                "trash" => "Trash",
+                _ => "INBOX",
+            };
+
+            session
+                .select(folder_name)
+                .map_err(|e| format!("Select folder failed: {}", e))?;
+
+            let messages = session
+                .fetch("1:20", "(FLAGS RFC822.HEADER)")
+                .map_err(|e| format!("Fetch failed: {}", e))?;
+
+            let mut emails = Vec::new();
+            for message in messages.iter() {
+                if let Some(header) = message.header() {
+                    let parsed = parse_mail(header).ok();
+                    if let Some(mail) = parsed {
+                        let subject = mail.headers.get_first_value("Subject").unwrap_or_default();
+                        let from = mail.headers.get_first_value("From").unwrap_or_default();
+                        let date = mail.headers.get_first_value("Date").unwrap_or_default();
+                        let flags = message.flags();
+                        let read = flags.iter().any(|f| matches!(f, imap::types::Flag::Seen));
+
+                        let preview = subject.chars().take(100).collect();
+                        emails.push(EmailSummary {
+                            id: message.message.to_string(),
+                            from_name: from.clone(),
+                            from_email: from,
+                            subject,
+                            preview,
+                            date,
+                            read,
+                        });
+                    }
                 }
             }
-        }
 
-        session.logout().ok();
-        Ok(emails)
+            Ok(emails)
+        })
     }
 
     #[cfg(not(feature = "mail"))]
@@ -86,24 +108,17 @@ fn get_folder_counts(
 
     #[cfg(feature = "mail")]
     {
-        let client = imap::ClientBuilder::new(&config.server, config.port)
-            .connect()
-            .map_err(|e| format!("Connection error: {}", e))?;
-
-        let mut session = client
-            .login(&config.username, &config.password)
-            .map_err(|e| format!("Login failed: {:?}", e))?;
+        IMAP_POOL.with_session(SYSTEM_MAILBOX, || connect_system_mailbox(config), |session| {
+            let mut counts = HashMap::new();
 
-        let mut counts = HashMap::new();
-
-        for folder in ["INBOX", "Sent", "Drafts", "Trash"] {
-            if let Ok(mailbox) = session.examine(folder) {
-                counts.insert((*folder).to_string(), mailbox.exists as usize);
+            for folder in ["INBOX", "Sent", "Drafts", "Trash"] {
+                if let Ok(mailbox) = session.examine(folder) {
+                    counts.insert((*folder).to_string(), mailbox.exists as usize);
+                }
             }
-        }
 
-        session.logout().ok();
-        Ok(counts)
+            Ok(counts)
+        })
     }
 
     #[cfg(not(feature = "mail"))]
@@ -115,58 +130,49 @@ fn get_folder_counts(
 fn fetch_email_by_id(config: &EmailConfig, id: &str) -> Result<EmailContent, String> {
     #[cfg(feature = "mail")]
     {
-        let client = imap::ClientBuilder::new(&config.server, config.port)
-            .connect()
-            .map_err(|e| format!("Connection error: {}", e))?;
-
-        let mut session = client
-            .login(&config.username, &config.password)
-            .map_err(|e| format!("Login failed: {:?}", e))?;
-
-        session
-            .select("INBOX")
-            .map_err(|e| format!("Select failed: {}", e))?;
-
-        let messages = session
-            .fetch(id, "RFC822")
-            .map_err(|e| format!("Fetch failed: {}", e))?;
-
-        if let Some(message) = messages.iter().next() {
-            if let Some(body) = message.body() {
-                let parsed = parse_mail(body).map_err(|e| format!("Parse failed: {}", e))?;
-
-                let subject = parsed
-                    .headers
-                    .get_first_value("Subject")
-                    .unwrap_or_default();
-                let from = parsed.headers.get_first_value("From").unwrap_or_default();
-                let to = parsed.headers.get_first_value("To").unwrap_or_default();
-                let date = parsed.headers.get_first_value("Date").unwrap_or_default();
-
-                let body_text = parsed
-                    .subparts
-                    .iter()
-                    .find_map(|p| p.get_body().ok())
-                    .or_else(|| parsed.get_body().ok())
-                    .unwrap_or_default();
-
-                session.logout().ok();
-
-                return Ok(EmailContent {
-                    id: id.to_string(),
-                    from_name: from.clone(),
-                    from_email: from,
-                    to,
-                    subject,
-                    body: body_text,
-                    date,
-                    read: false,
-                });
+        IMAP_POOL.with_session(SYSTEM_MAILBOX, || connect_system_mailbox(config), |session| {
+            session
+                .select("INBOX")
+                .map_err(|e| format!("Select failed: {}", e))?;
+
+            let messages = session
+                .fetch(id, "RFC822")
+                .map_err(|e| format!("Fetch failed: {}", e))?;
+
+            if let Some(message) = messages.iter().next() {
+                if let Some(body) = message.body() {
+                    let parsed = parse_mail(body).map_err(|e| format!("Parse failed: {}", e))?;
+
+                    let subject = parsed
+                        .headers
+                        .get_first_value("Subject")
+                        .unwrap_or_default();
+                    let from = parsed.headers.get_first_value("From").unwrap_or_default();
+                    let to = parsed.headers.get_first_value("To").unwrap_or_default();
+                    let date = parsed.headers.get_first_value("Date").unwrap_or_default();
+
+                    let body_text = parsed
+                        .subparts
+                        .iter()
+                        .find_map(|p| p.get_body().ok())
+                        .or_else(|| parsed.get_body().ok())
+                        .unwrap_or_default();
+
+                    return Ok(EmailContent {
+                        id: id.to_string(),
+                        from_name: from.clone(),
+                        from_email: from,
+                        to,
+                        subject,
+                        body: body_text,
+                        date,
+                        read: false,
+                    });
+                }
             }
-        }
 
-        session.logout().ok();
-        Err("Email not found".to_string())
+            Err("Email not found".to_string())
+        })
     }
 
     #[cfg(not(feature = "mail"))]
@@ -178,28 +184,21 @@ fn fetch_email_by_id(config: &EmailConfig, id: &str) -> Result<EmailContent, Str
 fn move_email_to_trash(config: &EmailConfig, id: &str) -> Result<(), String> {
     #[cfg(feature = "mail")]
     {
-        let client = imap::ClientBuilder::new(&config.server, config.port)
-            .connect()
-            .map_err(|e| format!("Connection error: {}", e))?;
-
-        let mut session = client
-            .login(&config.username, &config.password)
-            .map_err(|e| format!("Login failed: {:?}", e))?;
-
-        session
-            .select("INBOX")
-            .map_err(|e| format!("Select failed: {}", e))?;
+        IMAP_POOL.with_session(SYSTEM_MAILBOX, || connect_system_mailbox(config), |session| {
+            session
+                .select("INBOX")
+                .map_err(|e| format!("Select failed: {}", e))?;
 
-        session
-            .store(id, "+FLAGS (\\Deleted)")
-            .map_err(|e| format!("Store failed: {}", e))?;
+            session
+                .store(id, "+FLAGS (\\Deleted)")
+                .map_err(|e| format!("Store failed: {}", e))?;
 
-        session
-            .expunge()
-            .map_err(|e| format!("Expunge failed: {}", e))?;
+            session
+                .expunge()
+                .map_err(|e| format!("Expunge failed: {}", e))?;
 
-        session.logout().ok();
-        Ok(())
+            Ok(())
+        })
     }
 
     #[cfg(not(feature = "mail"))]
@@ -210,6 +209,7 @@ fn move_email_to_trash(config: &EmailConfig, id: &str) -> Result<(), String> {
 
 pub async fn list_emails_htmx(
     State(state): State<Arc<AppState>>,
+    cancellation: RequestCancellation,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> impl IntoResponse {
     let folder = params
@@ -282,7 +282,7 @@ pub async fn list_emails_htmx(
 
     let config = EmailConfig {
         username: account.username.clone(),
-        password: account.password_encrypted.clone(),
+        password: account_password(&account.password_encrypted),
         server: account.imap_server.clone(),
         port: account.imap_port as u16,
         from: account.email.clone(),
@@ -290,7 +290,12 @@ pub async fn list_emails_htmx(
         smtp_port: account.smtp_port as u16,
     };
 
-    let emails = fetch_emails_from_folder(&config, &folder).unwrap_or_default();
+    let imap_folder = folder.clone();
+    let emails = run_imap(&cancellation, move || {
+        fetch_emails_from_folder(&config, &imap_folder)
+    })
+    .await
+    .unwrap_or_default();
 
     let mut html = String::new();
     use std::fmt::Write;
@@ -328,6 +333,7 @@ pub async fn list_emails_htmx(
 
 pub async fn list_folders_htmx(
     State(state): State<Arc<AppState>>,
+    cancellation: RequestCancellation,
 ) -> impl IntoResponse {
     let user_id = match extract_user_from_session(&state) {
         Ok(id) => id,
@@ -377,7 +383,7 @@ pub async fn list_folders_htmx(
 
     let config = EmailConfig {
         username: account.username.clone(),
-        password: account.password_encrypted.clone(),
+        password: account_password(&account.password_encrypted),
         server: account.imap_server.clone(),
         port: account.imap_port as u16,
         from: account.email.clone(),
@@ -385,7 +391,9 @@ pub async fn list_folders_htmx(
         smtp_port: account.smtp_port as u16,
     };
 
-    let folder_counts = get_folder_counts(&config).unwrap_or_default();
+    let folder_counts = run_imap(&cancellation, move || get_folder_counts(&config))
+        .await
+        .unwrap_or_default();
 
     let mut html = String::new();
     for (folder_name, icon, count) in &[
@@ -472,10 +480,11 @@ pub async fn compose_email_htmx(
 
 pub async fn get_email_content_htmx(
     State(state): State<Arc<AppState>>,
+    cancellation: RequestCancellation,
     Path(id): Path<String>,
 ) -> Result<impl IntoResponse, EmailError> {
     let user_id = extract_user_from_session(&state)
-        .map_err(|_| EmailError("Authentication required".to_string()))?;
+        .map_err(|_| EmailError::Unauthorized("Authentication required".to_string()))?;
 
     let conn = state.conn.clone();
     let account = tokio::task::spawn_blocking(move || {
@@ -490,8 +499,8 @@ pub async fn get_email_content_htmx(
             .map_err(|e| format!("Failed to get email account: {}", e))
     })
     .await
-    .map_err(|e| EmailError(format!("Task join error: {e}")))?
-    .map_err(EmailError)?;
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
 
     let Some(account) = account else {
         return Ok(axum::response::Html(
@@ -504,7 +513,7 @@ pub async fn get_email_content_htmx(
 
     let config = EmailConfig {
         username: account.username.clone(),
-        password: account.password_encrypted.clone(),
+        password: account_password(&account.password_encrypted),
         server: account.imap_server.clone(),
         port: account.imap_port as u16,
         from: account.email.clone(),
@@ -512,8 +521,10 @@ pub async fn get_email_content_htmx(
         smtp_port: account.smtp_port as u16,
     };
 
-    let email_content = fetch_email_by_id(&config, &id)
-        .map_err(|e| EmailError(format!("Failed to fetch email: {}", e)))?;
+    let fetch_id = id.clone();
+    let email_content = run_imap(&cancellation, move || fetch_email_by_id(&config, &fetch_id))
+        .await
+        .map_err(|e| EmailError::Internal(format!("Failed to fetch email: {}", e)))?;
 
     let html = format!(
         r##"
@@ -558,6 +569,7 @@ pub async fn get_email_content_htmx(
 
 pub async fn delete_email_htmx(
     State(state): State<Arc<AppState>>,
+    cancellation: RequestCancellation,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
     let user_id = match extract_user_from_session(&state) {
@@ -624,7 +636,7 @@ pub async fn delete_email_htmx(
 
     let config = EmailConfig {
         username: account.username.clone(),
-        password: account.password_encrypted.clone(),
+        password: account_password(&account.password_encrypted),
         server: account.imap_server.clone(),
         port: account.imap_port as u16,
         from: account.email.clone(),
@@ -632,7 +644,12 @@ pub async fn delete_email_htmx(
         smtp_port: account.smtp_port as u16,
     };
 
-    if let Err(e) = move_email_to_trash(&config, &id) {
+    let trash_id = id.clone();
+    let trashed = run_imap(&cancellation, move || {
+        move_email_to_trash(&config, &trash_id)
+    })
+    .await;
+    if let Err(e) = trashed {
         error!("Failed to delete email: {}", e);
         return axum::response::Html(
             r#"<div class="empty-state">
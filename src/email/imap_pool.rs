@@ -0,0 +1,337 @@
+//! IMAP sessions kept open per email account and reused across requests.
+//!
+//! A session is checked out for the duration of one operation and returned afterwards.
+//! Sessions that fail an operation are logged out instead of returned, idle sessions
+//! expire after `IDLE_TIMEOUT`, and sessions idle for longer than `HEALTH_CHECK_AFTER`
+//! are probed with NOOP before reuse. The number of idle sessions is bounded per account
+//! and in total; sessions beyond the bound are closed on return.
+
+use log::debug;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const MAX_IDLE_PER_ACCOUNT: usize = 2;
+const MAX_IDLE_TOTAL: usize = 64;
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+const HEALTH_CHECK_AFTER: Duration = Duration::from_secs(30);
+
+/// Pool key for the server-wide mailbox configured through `EmailConfig`, which has no
+/// row in `user_email_accounts`.
+pub const SYSTEM_MAILBOX: Uuid = Uuid::nil();
+
+pub trait PooledSession: Send {
+    fn is_healthy(&mut self) -> bool;
+    fn close(self);
+}
+
+#[cfg(feature = "mail")]
+pub type ImapSession = imap::Session<imap::Connection>;
+
+#[cfg(feature = "mail")]
+impl PooledSession for ImapSession {
+    fn is_healthy(&mut self) -> bool {
+        self.noop().is_ok()
+    }
+
+    fn close(mut self) {
+        self.logout().ok();
+    }
+}
+
+#[cfg(feature = "mail")]
+pub static IMAP_POOL: std::sync::LazyLock<ImapPool<ImapSession>> =
+    std::sync::LazyLock::new(ImapPool::default);
+
+#[cfg(feature = "mail")]
+pub fn connect_imap(
+    server: &str,
+    port: u16,
+    username: &str,
+    password: &str,
+) -> Result<ImapSession, String> {
+    let client = imap::ClientBuilder::new(server, port)
+        .connect()
+        .map_err(|e| format!("Failed to connect to IMAP: {e:?}"))?;
+    client
+        .login(username, password)
+        .map_err(|(e, _)| format!("Login failed: {e:?}"))
+}
+
+struct IdleSession<S> {
+    session: S,
+    since: Instant,
+}
+
+pub struct ImapPool<S> {
+    idle: Mutex<HashMap<Uuid, Vec<IdleSession<S>>>>,
+    max_per_account: usize,
+    max_total: usize,
+    idle_timeout: Duration,
+    health_check_after: Duration,
+}
+
+impl<S: PooledSession> Default for ImapPool<S> {
+    fn default() -> Self {
+        Self::new(MAX_IDLE_PER_ACCOUNT, MAX_IDLE_TOTAL, IDLE_TIMEOUT, HEALTH_CHECK_AFTER)
+    }
+}
+
+impl<S: PooledSession> ImapPool<S> {
+    pub fn new(
+        max_per_account: usize,
+        max_total: usize,
+        idle_timeout: Duration,
+        health_check_after: Duration,
+    ) -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+            max_per_account,
+            max_total,
+            idle_timeout,
+            health_check_after,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, Vec<IdleSession<S>>>> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs `f` on a pooled session for the account, connecting with `connect` when none
+    /// is available. The session is returned to the pool only if `f` succeeds.
+    pub fn with_session<T, E>(
+        &self,
+        account_id: Uuid,
+        connect: impl FnOnce() -> Result<S, E>,
+        f: impl FnOnce(&mut S) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut session = match self.checkout(account_id) {
+            Some(session) => session,
+            None => connect()?,
+        };
+        match f(&mut session) {
+            Ok(value) => {
+                self.checkin(account_id, session);
+                Ok(value)
+            }
+            Err(e) => {
+                session.close();
+                Err(e)
+            }
+        }
+    }
+
+    pub fn checkout(&self, account_id: Uuid) -> Option<S> {
+        self.checkout_at(account_id, Instant::now())
+    }
+
+    fn checkout_at(&self, account_id: Uuid, now: Instant) -> Option<S> {
+        loop {
+            let (candidate, expired) = {
+                let mut idle = self.lock();
+                let sessions = idle.get_mut(&account_id)?;
+                let expired = self.drain_expired(sessions, now);
+                let candidate = sessions.pop();
+                if sessions.is_empty() {
+                    idle.remove(&account_id);
+                }
+                (candidate, expired)
+            };
+            for session in expired {
+                session.close();
+            }
+
+            let mut candidate = candidate?;
+            if now.duration_since(candidate.since) < self.health_check_after
+                || candidate.session.is_healthy()
+            {
+                return Some(candidate.session);
+            }
+            debug!("Dropping unhealthy IMAP session for account {}", account_id);
+            candidate.session.close();
+        }
+    }
+
+    pub fn checkin(&self, account_id: Uuid, session: S) {
+        self.checkin_at(account_id, session, Instant::now());
+    }
+
+    fn checkin_at(&self, account_id: Uuid, session: S, now: Instant) {
+        let (rejected, expired) = {
+            let mut idle = self.lock();
+            let mut expired = Vec::new();
+            if idle.values().map(Vec::len).sum::<usize>() >= self.max_total {
+                for sessions in idle.values_mut() {
+                    expired.extend(self.drain_expired(sessions, now));
+                }
+                idle.retain(|_, sessions| !sessions.is_empty());
+            }
+
+            let total: usize = idle.values().map(Vec::len).sum();
+            let sessions = idle.entry(account_id).or_default();
+            let rejected = if sessions.len() >= self.max_per_account || total >= self.max_total {
+                Some(session)
+            } else {
+                sessions.push(IdleSession { session, since: now });
+                None
+            };
+            if sessions.is_empty() {
+                idle.remove(&account_id);
+            }
+            (rejected, expired)
+        };
+
+        for session in expired.into_iter().chain(rejected) {
+            session.close();
+        }
+    }
+
+    /// Closes every idle session of the account, e.g. after its credentials changed or
+    /// it was removed.
+    pub fn evict(&self, account_id: Uuid) {
+        let sessions = self.lock().remove(&account_id).unwrap_or_default();
+        for idle in sessions {
+            idle.session.close();
+        }
+    }
+
+    pub fn idle_count(&self, account_id: Uuid) -> usize {
+        self.lock().get(&account_id).map_or(0, Vec::len)
+    }
+
+    fn drain_expired(&self, sessions: &mut Vec<IdleSession<S>>, now: Instant) -> Vec<S> {
+        let (expired, live): (Vec<_>, Vec<_>) = std::mem::take(sessions)
+            .into_iter()
+            .partition(|idle| now.duration_since(idle.since) >= self.idle_timeout);
+        *sessions = live;
+        expired.into_iter().map(|idle| idle.session).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Probe {
+        connects: Arc<AtomicUsize>,
+        closes: Arc<AtomicUsize>,
+        unhealthy: Arc<AtomicBool>,
+    }
+
+    struct FakeSession {
+        id: usize,
+        probe: Probe,
+    }
+
+    impl PooledSession for FakeSession {
+        fn is_healthy(&mut self) -> bool {
+            !self.probe.unhealthy.load(Ordering::SeqCst)
+        }
+
+        fn close(self) {
+            self.probe.closes.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl Probe {
+        fn connect(&self) -> Result<FakeSession, String> {
+            let id = self.connects.fetch_add(1, Ordering::SeqCst);
+            Ok(FakeSession { id, probe: self.clone() })
+        }
+    }
+
+    fn pool(max_per_account: usize, max_total: usize) -> ImapPool<FakeSession> {
+        ImapPool::new(
+            max_per_account,
+            max_total,
+            Duration::from_secs(60),
+            Duration::from_secs(10),
+        )
+    }
+
+    #[test]
+    fn test_session_is_reused() {
+        let pool = pool(2, 10);
+        let probe = Probe::default();
+        let account = Uuid::new_v4();
+
+        let first = pool
+            .with_session(account, || probe.connect(), |s| Ok::<_, String>(s.id))
+            .unwrap();
+        let second = pool
+            .with_session(account, || probe.connect(), |s| Ok::<_, String>(s.id))
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(probe.connects.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.idle_count(account), 1);
+    }
+
+    #[test]
+    fn test_failed_session_is_closed_not_returned() {
+        let pool = pool(2, 10);
+        let probe = Probe::default();
+        let account = Uuid::new_v4();
+
+        let result: Result<(), String> =
+            pool.with_session(account, || probe.connect(), |_| Err("boom".to_string()));
+        assert!(result.is_err());
+        assert_eq!(pool.idle_count(account), 0);
+        assert_eq!(probe.closes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_expired_sessions_are_evicted() {
+        let pool = pool(2, 10);
+        let probe = Probe::default();
+        let account = Uuid::new_v4();
+        let start = Instant::now();
+
+        pool.checkin_at(account, probe.connect().unwrap(), start);
+        assert!(pool
+            .checkout_at(account, start + Duration::from_secs(61))
+            .is_none());
+        assert_eq!(probe.closes.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.idle_count(account), 0);
+    }
+
+    #[test]
+    fn test_unhealthy_session_is_replaced() {
+        let pool = pool(2, 10);
+        let probe = Probe::default();
+        let account = Uuid::new_v4();
+        let start = Instant::now();
+
+        pool.checkin_at(account, probe.connect().unwrap(), start);
+        probe.unhealthy.store(true, Ordering::SeqCst);
+        assert!(pool
+            .checkout_at(account, start + Duration::from_secs(20))
+            .is_none());
+        assert_eq!(probe.closes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_pool_size_is_bounded() {
+        let pool = pool(1, 2);
+        let probe = Probe::default();
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        pool.checkin(a, probe.connect().unwrap());
+        pool.checkin(a, probe.connect().unwrap());
+        pool.checkin(b, probe.connect().unwrap());
+        pool.checkin(c, probe.connect().unwrap());
+
+        assert_eq!(pool.idle_count(a), 1);
+        assert_eq!(pool.idle_count(b), 1);
+        assert_eq!(pool.idle_count(c), 0);
+        assert_eq!(probe.closes.load(Ordering::SeqCst), 2);
+
+        pool.evict(a);
+        assert_eq!(pool.idle_count(a), 0);
+        assert_eq!(probe.closes.load(Ordering::SeqCst), 3);
+    }
+}
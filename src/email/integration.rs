@@ -8,6 +8,7 @@ use serde_json::json;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use super::integration_types::*;
 
@@ -38,7 +39,7 @@ pub async fn get_feature_flags(
 /// Extract lead information from email using AI
 pub async fn extract_lead_from_email(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<LeadExtractionRequest>,
+    ApiJson(req): ApiJson<LeadExtractionRequest>,
 ) -> Result<Json<LeadExtractionResponse>, StatusCode> {
     // Simple extraction logic (can be enhanced with LLM)
     let email = req.from.clone();
@@ -100,7 +101,7 @@ pub async fn get_crm_context_by_email(
 /// Link email to CRM contact/opportunity
 pub async fn link_email_to_crm(
     State(state): State<Arc<AppState>>,
-    Json(link): Json<EmailCrmLink>,
+    ApiJson(link): ApiJson<EmailCrmLink>,
 ) -> Result<StatusCode, StatusCode> {
     use crate::core::shared::schema::email_crm_links;
 
@@ -121,7 +122,7 @@ pub async fn link_email_to_crm(
 /// Categorize email using AI
 pub async fn categorize_email(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<LeadExtractionRequest>,
+    ApiJson(req): ApiJson<LeadExtractionRequest>,
 ) -> Result<Json<EmailCategoryResponse>, StatusCode> {
     // Simple keyword-based categorization (can be enhanced with LLM)
     let text = format!("{} {}", req.subject.to_lowercase(), req.body.to_lowercase());
@@ -145,7 +146,7 @@ pub async fn categorize_email(
 /// Generate smart reply suggestions
 pub async fn generate_smart_reply(
     State(_state): State<Arc<AppState>>,
-    Json(_req): Json<SmartReplyRequest>,
+    ApiJson(_req): ApiJson<SmartReplyRequest>,
 ) -> Result<Json<SmartReplyResponse>, StatusCode> {
     // Simple template responses (can be enhanced with LLM)
     let suggestions = vec![
@@ -0,0 +1,482 @@
+//! Local index of IMAP messages per account and folder, kept current by UID-based
+//! incremental sync.
+//!
+//! Each sync asks the server for the folder's UIDs, which is cheap next to fetching
+//! messages, drops index rows for messages no longer on the server, and fetches only UIDs
+//! above the highest one seen so far. When the folder's UIDVALIDITY changes the server may
+//! have reassigned UIDs, so the folder's index is discarded and rebuilt. Listing serves the
+//! requested page from the index and fetches from IMAP only the UIDs on that page the index
+//! does not hold yet.
+
+use super::types::EmailResponse;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Bool, Text};
+use mailparse::{parse_mail, DispositionType, MailHeaderMap};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// New messages fetched per sync. Older ones are fetched when a page reaches them.
+pub const SYNC_BATCH: usize = 200;
+/// UIDs per UID FETCH command.
+#[cfg(feature = "mail")]
+const FETCH_CHUNK: usize = 50;
+const PREVIEW_CHARS: usize = 150;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FolderSyncState {
+    pub uid_validity: u32,
+    pub highest_uid: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncPlan {
+    /// The folder was never synced or its UIDVALIDITY changed; its index is dropped first.
+    pub reset: bool,
+    /// UIDs to fetch, ascending.
+    pub fetch: Vec<u32>,
+    /// Highest UID seen once the sync completes.
+    pub highest_uid: u32,
+}
+
+/// Decides which of the folder's current UIDs to fetch: those above the highest UID seen
+/// under the same UIDVALIDITY, at most the newest `SYNC_BATCH`.
+pub fn plan_sync(
+    state: Option<FolderSyncState>,
+    uid_validity: u32,
+    server_uids: &[u32],
+) -> SyncPlan {
+    let seen = match state {
+        Some(state) if state.uid_validity == uid_validity => Some(state.highest_uid),
+        _ => None,
+    };
+    let floor = seen.unwrap_or(0);
+    let mut fetch: Vec<u32> = server_uids
+        .iter()
+        .copied()
+        .filter(|uid| *uid > floor)
+        .collect();
+    fetch.sort_unstable();
+    fetch.dedup();
+    if fetch.len() > SYNC_BATCH {
+        fetch.drain(..fetch.len() - SYNC_BATCH);
+    }
+    SyncPlan {
+        reset: seen.is_none(),
+        fetch,
+        highest_uid: server_uids.iter().copied().max().unwrap_or(0).max(floor),
+    }
+}
+
+/// The UIDs on one page of the folder, newest first.
+pub fn page_uids(server_uids: &[u32], offset: usize, limit: usize) -> Vec<u32> {
+    let mut uids = server_uids.to_vec();
+    uids.sort_unstable_by(|a, b| b.cmp(a));
+    uids.dedup();
+    uids.into_iter().skip(offset).take(limit).collect()
+}
+
+/// UIDs on the page the index does not hold, ascending.
+pub fn missing_uids(page: &[u32], indexed: &HashSet<u32>) -> Vec<u32> {
+    let mut missing: Vec<u32> = page
+        .iter()
+        .copied()
+        .filter(|uid| !indexed.contains(uid))
+        .collect();
+    missing.sort_unstable();
+    missing
+}
+
+#[derive(Debug, Clone, QueryableByName)]
+pub struct IndexedMessage {
+    #[diesel(sql_type = BigInt)]
+    pub uid: i64,
+    #[diesel(sql_type = Text)]
+    pub from_name: String,
+    #[diesel(sql_type = Text)]
+    pub from_email: String,
+    #[diesel(sql_type = Text)]
+    pub to_addrs: String,
+    #[diesel(sql_type = Text)]
+    pub subject: String,
+    #[diesel(sql_type = Text)]
+    pub preview: String,
+    #[diesel(sql_type = Text)]
+    pub body: String,
+    /// The raw `Date` header.
+    #[diesel(sql_type = Text)]
+    pub date_header: String,
+    #[diesel(sql_type = Bool)]
+    pub has_attachments: bool,
+    #[diesel(sql_type = Bool)]
+    pub seen: bool,
+}
+
+impl IndexedMessage {
+    pub fn into_response(self, folder: &str) -> EmailResponse {
+        let time = super::messages::format_email_time(&self.date_header);
+        EmailResponse {
+            id: self.uid.to_string(),
+            from_name: self.from_name,
+            from_email: self.from_email,
+            to: self.to_addrs,
+            subject: self.subject,
+            preview: self.preview,
+            body: self.body,
+            date: time.clone(),
+            time,
+            read: self.seen,
+            folder: folder.to_string(),
+            has_attachments: self.has_attachments,
+        }
+    }
+}
+
+/// Parses a fetched RFC 822 message into an index row. The HTML part is kept as the body
+/// when present, the plain text part otherwise.
+pub fn parse_message(uid: u32, raw: &[u8], seen: bool) -> Result<IndexedMessage, String> {
+    let parsed = parse_mail(raw).map_err(|e| format!("Failed to parse email: {e:?}"))?;
+    let headers = parsed.get_headers();
+    let from = headers.get_first_value("From").unwrap_or_default();
+    let (from_name, from_email) = super::messages::parse_from_field(&from);
+
+    let part_body = |mimetype: &str| {
+        parsed
+            .subparts
+            .iter()
+            .find(|p| p.ctype.mimetype == mimetype)
+            .map(|p| p.get_body().unwrap_or_default())
+    };
+    let body_text =
+        part_body("text/plain").unwrap_or_else(|| parsed.get_body().unwrap_or_default());
+    let body_html = part_body("text/html").unwrap_or_default();
+
+    let preview = body_text.lines().take(3).collect::<Vec<_>>().join(" ");
+    let preview = if preview.chars().count() > PREVIEW_CHARS {
+        format!(
+            "{}...",
+            preview.chars().take(PREVIEW_CHARS).collect::<String>()
+        )
+    } else {
+        preview
+    };
+    let has_attachments = parsed
+        .subparts
+        .iter()
+        .any(|p| p.get_content_disposition().disposition == DispositionType::Attachment);
+
+    Ok(IndexedMessage {
+        uid: i64::from(uid),
+        from_name,
+        from_email,
+        to_addrs: headers.get_first_value("To").unwrap_or_default(),
+        subject: headers.get_first_value("Subject").unwrap_or_default(),
+        preview,
+        body: if body_html.is_empty() {
+            body_text
+        } else {
+            body_html
+        },
+        date_header: headers.get_first_value("Date").unwrap_or_default(),
+        has_attachments,
+        seen,
+    })
+}
+
+#[derive(QueryableByName)]
+struct SyncStateRow {
+    #[diesel(sql_type = BigInt)]
+    uid_validity: i64,
+    #[diesel(sql_type = BigInt)]
+    highest_uid: i64,
+}
+
+#[derive(QueryableByName)]
+struct UidRow {
+    #[diesel(sql_type = BigInt)]
+    uid: i64,
+}
+
+fn to_db_uids(uids: &[u32]) -> Vec<i64> {
+    uids.iter().copied().map(i64::from).collect()
+}
+
+pub fn load_state(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    folder: &str,
+) -> QueryResult<Option<FolderSyncState>> {
+    let row: Option<SyncStateRow> = diesel::sql_query(
+        "SELECT uid_validity, highest_uid FROM email_folder_sync
+         WHERE account_id = $1 AND folder = $2",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(account_id)
+    .bind::<Text, _>(folder)
+    .get_result(conn)
+    .optional()?;
+    Ok(row.map(|row| FolderSyncState {
+        uid_validity: row.uid_validity as u32,
+        highest_uid: row.highest_uid as u32,
+    }))
+}
+
+fn save_state(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    folder: &str,
+    state: FolderSyncState,
+) -> QueryResult<usize> {
+    diesel::sql_query(
+        "INSERT INTO email_folder_sync (account_id, folder, uid_validity, highest_uid, synced_at)
+         VALUES ($1, $2, $3, $4, NOW())
+         ON CONFLICT (account_id, folder) DO UPDATE
+         SET uid_validity = EXCLUDED.uid_validity,
+             highest_uid = EXCLUDED.highest_uid,
+             synced_at = NOW()",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(account_id)
+    .bind::<Text, _>(folder)
+    .bind::<BigInt, _>(i64::from(state.uid_validity))
+    .bind::<BigInt, _>(i64::from(state.highest_uid))
+    .execute(conn)
+}
+
+/// Removes the folder's index rows, or only those whose UID is not in `keep`.
+fn prune(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    folder: &str,
+    keep: Option<&[u32]>,
+) -> QueryResult<usize> {
+    match keep {
+        None => diesel::sql_query(
+            "DELETE FROM email_message_index WHERE account_id = $1 AND folder = $2",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(account_id)
+        .bind::<Text, _>(folder)
+        .execute(conn),
+        Some(keep) => diesel::sql_query(
+            "DELETE FROM email_message_index
+             WHERE account_id = $1 AND folder = $2 AND NOT (uid = ANY($3))",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(account_id)
+        .bind::<Text, _>(folder)
+        .bind::<Array<BigInt>, _>(to_db_uids(keep))
+        .execute(conn),
+    }
+}
+
+fn store_messages(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    folder: &str,
+    messages: &[IndexedMessage],
+) -> QueryResult<()> {
+    for message in messages {
+        diesel::sql_query(
+            "INSERT INTO email_message_index
+                (account_id, folder, uid, from_name, from_email, to_addrs, subject, preview,
+                 body, date_header, has_attachments, seen)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+             ON CONFLICT (account_id, folder, uid) DO UPDATE
+             SET seen = EXCLUDED.seen, indexed_at = NOW()",
+        )
+        .bind::<diesel::sql_types::Uuid, _>(account_id)
+        .bind::<Text, _>(folder)
+        .bind::<BigInt, _>(message.uid)
+        .bind::<Text, _>(&message.from_name)
+        .bind::<Text, _>(&message.from_email)
+        .bind::<Text, _>(&message.to_addrs)
+        .bind::<Text, _>(&message.subject)
+        .bind::<Text, _>(&message.preview)
+        .bind::<Text, _>(&message.body)
+        .bind::<Text, _>(&message.date_header)
+        .bind::<Bool, _>(message.has_attachments)
+        .bind::<Bool, _>(message.seen)
+        .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn indexed_uids(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    folder: &str,
+    uids: &[u32],
+) -> QueryResult<HashSet<u32>> {
+    let rows: Vec<UidRow> = diesel::sql_query(
+        "SELECT uid FROM email_message_index
+         WHERE account_id = $1 AND folder = $2 AND uid = ANY($3)",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(account_id)
+    .bind::<Text, _>(folder)
+    .bind::<Array<BigInt>, _>(to_db_uids(uids))
+    .load(conn)?;
+    Ok(rows.into_iter().map(|row| row.uid as u32).collect())
+}
+
+fn load_messages(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    folder: &str,
+    uids: &[u32],
+) -> QueryResult<Vec<IndexedMessage>> {
+    diesel::sql_query(
+        "SELECT uid, from_name, from_email, to_addrs, subject, preview, body, date_header,
+                has_attachments, seen
+         FROM email_message_index
+         WHERE account_id = $1 AND folder = $2 AND uid = ANY($3)
+         ORDER BY uid DESC",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(account_id)
+    .bind::<Text, _>(folder)
+    .bind::<Array<BigInt>, _>(to_db_uids(uids))
+    .load(conn)
+}
+
+/// Fetches and parses the given UIDs. Messages that fail to parse are skipped.
+#[cfg(feature = "mail")]
+fn fetch_messages(
+    session: &mut super::imap_pool::ImapSession,
+    uids: &[u32],
+) -> Result<Vec<IndexedMessage>, String> {
+    let mut messages = Vec::with_capacity(uids.len());
+    for chunk in uids.chunks(FETCH_CHUNK) {
+        let uid_set = chunk
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let fetches = session
+            .uid_fetch(&uid_set, "(UID FLAGS RFC822)")
+            .map_err(|e| format!("Failed to fetch emails: {e:?}"))?;
+        for fetch in fetches.iter() {
+            let (Some(uid), Some(body)) = (fetch.uid, fetch.body()) else {
+                continue;
+            };
+            let seen = fetch
+                .flags()
+                .iter()
+                .any(|f| matches!(f, imap::types::Flag::Seen));
+            match parse_message(uid, body, seen) {
+                Ok(message) => messages.push(message),
+                Err(e) => log::warn!("Skipping UID {uid} while indexing: {e}"),
+            }
+        }
+    }
+    Ok(messages)
+}
+
+/// Brings the folder's index up to date and returns one page of it, newest first.
+#[cfg(feature = "mail")]
+pub fn sync_and_list(
+    conn: &mut PgConnection,
+    session: &mut super::imap_pool::ImapSession,
+    account_id: Uuid,
+    folder: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<EmailResponse>, String> {
+    let db_err = |e: diesel::result::Error| format!("Email index error: {e}");
+
+    let mailbox = session
+        .select(folder)
+        .map_err(|e| format!("Failed to select folder: {e:?}"))?;
+    let uid_validity = mailbox.uid_validity.unwrap_or(0);
+    let server_uids: Vec<u32> = session
+        .uid_search("ALL")
+        .map_err(|e| format!("Failed to search emails: {e:?}"))?
+        .into_iter()
+        .collect();
+
+    let state = load_state(conn, account_id, folder).map_err(db_err)?;
+    let plan = plan_sync(state, uid_validity, &server_uids);
+    if plan.reset {
+        log::info!("Rebuilding email index for account {account_id} folder {folder}");
+    }
+    let fetched = fetch_messages(session, &plan.fetch)?;
+    conn.transaction(|conn| {
+        prune(
+            conn,
+            account_id,
+            folder,
+            (!plan.reset).then_some(&server_uids[..]),
+        )?;
+        store_messages(conn, account_id, folder, &fetched)?;
+        save_state(
+            conn,
+            account_id,
+            folder,
+            FolderSyncState {
+                uid_validity,
+                highest_uid: plan.highest_uid,
+            },
+        )
+    })
+    .map_err(db_err)?;
+
+    let page = page_uids(&server_uids, offset, limit);
+    let indexed = indexed_uids(conn, account_id, folder, &page).map_err(db_err)?;
+    let missing = missing_uids(&page, &indexed);
+    if !missing.is_empty() {
+        let backfill = fetch_messages(session, &missing)?;
+        store_messages(conn, account_id, folder, &backfill).map_err(db_err)?;
+    }
+
+    Ok(load_messages(conn, account_id, folder, &page)
+        .map_err(db_err)?
+        .into_iter()
+        .map(|message| message.into_response(folder))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incremental_sync_fetches_only_new_uids() {
+        let server_uids = [3, 7, 8, 12, 13];
+
+        let first = plan_sync(None, 100, &server_uids);
+        assert!(first.reset);
+        assert_eq!(first.fetch, vec![3, 7, 8, 12, 13]);
+        assert_eq!(first.highest_uid, 13);
+
+        let synced = FolderSyncState {
+            uid_validity: 100,
+            highest_uid: 8,
+        };
+        let next = plan_sync(Some(synced), 100, &server_uids);
+        assert!(!next.reset);
+        assert_eq!(next.fetch, vec![12, 13]);
+        assert_eq!(next.highest_uid, 13);
+
+        // Expunging the newest message must not lower the high-water mark.
+        let current = FolderSyncState {
+            uid_validity: 100,
+            highest_uid: 13,
+        };
+        let idle = plan_sync(Some(current), 100, &[3, 7, 8, 12]);
+        assert!(idle.fetch.is_empty());
+        assert_eq!(idle.highest_uid, 13);
+
+        // A new UIDVALIDITY invalidates every UID seen before.
+        let resync = plan_sync(Some(current), 101, &[1, 2]);
+        assert!(resync.reset);
+        assert_eq!(resync.fetch, vec![1, 2]);
+        assert_eq!(resync.highest_uid, 2);
+    }
+
+    #[test]
+    fn test_sync_batch_and_page_gaps() {
+        let server_uids: Vec<u32> = (1..=(SYNC_BATCH as u32 + 50)).collect();
+        let plan = plan_sync(None, 1, &server_uids);
+        assert_eq!(plan.fetch.len(), SYNC_BATCH);
+        assert_eq!(plan.fetch.first(), Some(&51));
+
+        let page = page_uids(&server_uids, SYNC_BATCH - 2, 4);
+        assert_eq!(page, vec![52, 51, 50, 49]);
+        let indexed: HashSet<u32> = plan.fetch.iter().copied().collect();
+        assert_eq!(missing_uids(&page, &indexed), vec![49, 50]);
+    }
+}
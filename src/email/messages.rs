@@ -1,4 +1,8 @@
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::request_timeout::RequestCancellation;
 use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use super::outbox;
 use super::types::*;
 use axum::{
     extract::{Path, State},
@@ -6,13 +10,13 @@ use axum::{
     response::IntoResponse,
     Json,
 };
-use base64::{engine::general_purpose, Engine as _};
+use crate::security::master_key::{self, LegacyEncoding};
 use diesel::prelude::*;
 #[cfg(feature = "mail")]
-use imap::types::Seq;
-use lettre::{transport::smtp::authentication::Credentials, Message, SmtpTransport, Transport};
+use super::imap_pool::{connect_imap, IMAP_POOL};
+#[cfg(feature = "mail")]
+use super::mail_index;
 use log::info;
-use mailparse::{parse_mail, MailHeaderMap};
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -21,15 +25,11 @@ fn extract_user_from_session(_state: &Arc<AppState>) -> Result<Uuid, String> {
 }
 
 fn decrypt_password(encrypted: &str) -> Result<String, String> {
-    general_purpose::STANDARD
-        .decode(encrypted)
+    master_key::reveal(encrypted, LegacyEncoding::Base64)
         .map_err(|e| format!("Decryption failed: {e}"))
-        .and_then(|bytes| {
-            String::from_utf8(bytes).map_err(|e| format!("UTF-8 conversion failed: {e}"))
-        })
 }
 
-fn parse_from_field(from: &str) -> (String, String) {
+pub(super) fn parse_from_field(from: &str) -> (String, String) {
     if let Some(start) = from.find('<') {
         if let Some(end) = from.find('>') {
             let name = from[..start].trim().trim_matches('"').to_string();
@@ -40,7 +40,7 @@ fn parse_from_field(from: &str) -> (String, String) {
     (String::new(), from.to_string())
 }
 
-fn format_email_time(date_str: &str) -> String {
+pub(super) fn format_email_time(date_str: &str) -> String {
     if date_str.is_empty() {
         return "Unknown".to_string();
     }
@@ -83,55 +83,20 @@ fn inject_tracking_pixel(html_body: &str, tracking_id: &str, state: &Arc<AppStat
     }
 }
 
-struct EmailTrackingParams<'a> {
-    tracking_id: Uuid,
-    account_id: Uuid,
-    bot_id: Uuid,
-    from_email: &'a str,
-    to_email: &'a str,
-    cc: Option<&'a str>,
-    bcc: Option<&'a str>,
-    subject: &'a str,
-}
-
-fn save_email_tracking_record(
-    conn: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<diesel::PgConnection>>,
-    params: EmailTrackingParams,
-) -> Result<(), String> {
-    let mut db_conn = conn.get().map_err(|e| format!("DB connection error: {e}"))?;
-
-    diesel::sql_query(
-        "INSERT INTO email_tracking (id, tracking_id, bot_id, account_id, from_email, to_email, cc, bcc, subject, sent_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())"
-    )
-    .bind::<diesel::sql_types::Uuid, _>(Uuid::new_v4())
-    .bind::<diesel::sql_types::Text, _>(params.tracking_id.to_string())
-    .bind::<diesel::sql_types::Uuid, _>(params.bot_id)
-    .bind::<diesel::sql_types::Uuid, _>(params.account_id)
-    .bind::<diesel::sql_types::Text, _>(params.from_email)
-    .bind::<diesel::sql_types::Text, _>(params.to_email)
-    .bind::<diesel::sql_types::Nullable<diesel::sql_types::Text>, _>(params.cc)
-    .bind::<diesel::sql_types::Nullable<diesel::sql_types::Text>, _>(params.bcc)
-    .bind::<diesel::sql_types::Text, _>(params.subject)
-    .execute(&mut db_conn)
-    .map_err(|e| format!("Failed to save tracking record: {e}"))?;
-
-    Ok(())
-}
-
 pub async fn list_emails(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ListEmailsRequest>,
+    cancellation: RequestCancellation,
+    ApiJson(request): ApiJson<ListEmailsRequest>,
 ) -> Result<Json<ApiResponse<Vec<EmailResponse>>>, EmailError> {
     let account_uuid = Uuid::parse_str(&request.account_id)
-        .map_err(|_| EmailError("Invalid account ID".to_string()))?;
+        .map_err(|_| EmailError::BadRequest("Invalid account ID".to_string()))?;
 
     let conn = state.conn.clone();
     let account_info = tokio::task::spawn_blocking(move || {
         let mut db_conn = conn.get().map_err(|e| format!("DB connection error: {e}"))?;
 
         let result: ImapCredentialsRow = diesel::sql_query(
-            "SELECT imap_server, imap_port, username, password_encrypted FROM user_email_accounts WHERE id = $1 AND is_active = true"
+            "SELECT imap_server, imap_port, username, password_encrypted, default_folder FROM user_email_accounts WHERE id = $1 AND is_active = true"
         )
         .bind::<diesel::sql_types::Uuid, _>(account_uuid)
         .get_result(&mut db_conn)
@@ -140,8 +105,8 @@ pub async fn list_emails(
         Ok::<_, String>(result)
     })
     .await
-    .map_err(|e| EmailError(format!("Task join error: {e}")))?
-    .map_err(EmailError)?;
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
 
     let (imap_server, imap_port, username, encrypted_password) = (
         account_info.imap_server,
@@ -149,109 +114,43 @@ pub async fn list_emails(
         account_info.username,
         account_info.password_encrypted,
     );
-    let password = decrypt_password(&encrypted_password).map_err(EmailError)?;
+    let password = decrypt_password(&encrypted_password).map_err(EmailError::Internal)?;
 
     #[cfg(feature = "mail")]
     {
-        let client = imap::ClientBuilder::new(imap_server.as_str(), imap_port as u16)
-            .connect()
-            .map_err(|e| EmailError(format!("Failed to connect to IMAP: {e:?}")))?;
-
-        let mut session = client
-            .login(&username, &password)
-            .map_err(|e| EmailError(format!("Login failed: {e:?}")))?;
-
-        let folder = request.folder.unwrap_or_else(|| "INBOX".to_string());
-        session
-            .select(&folder)
-            .map_err(|e| EmailError(format!("Failed to select folder: {e:?}")))?;
-
-        let messages = session
-            .search("ALL")
-            .map_err(|e| EmailError(format!("Failed to search emails: {e:?}")))?;
-
-        let mut email_list = Vec::new();
+        let folder = request
+            .folder
+            .filter(|f| !f.trim().is_empty())
+            .unwrap_or(account_info.default_folder);
         let limit = request.limit.unwrap_or(50);
         let offset = request.offset.unwrap_or(0);
 
-        let mut recent_messages: Vec<Seq> = messages.iter().copied().collect();
-        recent_messages.sort_by(|a, b| b.cmp(a));
-        let recent_messages: Vec<Seq> = recent_messages
-            .into_iter()
-            .skip(offset)
-            .take(limit)
-            .collect();
-
-        for seq in recent_messages {
-            let fetch_result = session.fetch(seq.to_string(), "RFC822");
-            let messages =
-                fetch_result.map_err(|e| EmailError(format!("Failed to fetch email: {e:?}")))?;
-
-            for msg in messages.iter() {
-                let body = msg
-                    .body()
-                    .ok_or_else(|| EmailError("No body found".to_string()))?;
-
-                let parsed = parse_mail(body)
-                    .map_err(|e| EmailError(format!("Failed to parse email: {e:?}")))?;
-
-                let headers = parsed.get_headers();
-                let subject = headers.get_first_value("Subject").unwrap_or_default();
-                let from = headers.get_first_value("From").unwrap_or_default();
-                let to = headers.get_first_value("To").unwrap_or_default();
-                let date = headers.get_first_value("Date").unwrap_or_default();
-
-                let body_text = parsed
-                    .subparts
-                    .iter()
-                    .find(|p| p.ctype.mimetype == "text/plain")
-                    .map_or_else(
-                        || parsed.get_body().unwrap_or_default(),
-                        |body_part| body_part.get_body().unwrap_or_default(),
-                    );
-
-                let body_html = parsed
-                    .subparts
-                    .iter()
-                    .find(|p| p.ctype.mimetype == "text/html")
-                    .map_or_else(String::new, |body_part| {
-                        body_part.get_body().unwrap_or_default()
-                    });
-
-                let preview = body_text.lines().take(3).collect::<Vec<_>>().join(" ");
-                let preview_truncated = if preview.len() > 150 {
-                    format!("{}...", &preview[..150])
-                } else {
-                    preview
+        // Served from the local index, which is brought up to date by UID first.
+        let pool = state.conn.clone();
+        let email_list = cancellation
+            .spawn_blocking(move || {
+                let mut db_conn = pool
+                    .get()
+                    .map_err(|e| EmailError::Internal(format!("DB connection error: {e}")))?;
+                let connect = || {
+                    connect_imap(&imap_server, imap_port as u16, &username, &password)
+                        .map_err(EmailError::Internal)
                 };
-
-                let (from_name, from_email) = parse_from_field(&from);
-                let has_attachments = parsed.subparts.iter().any(|p| {
-                    p.get_content_disposition().disposition == mailparse::DispositionType::Attachment
-                });
-
-                email_list.push(EmailResponse {
-                    id: seq.to_string(),
-                    from_name,
-                    from_email,
-                    to,
-                    subject,
-                    preview: preview_truncated,
-                    body: if body_html.is_empty() {
-                        body_text
-                    } else {
-                        body_html
-                    },
-                    date: format_email_time(&date),
-                    time: format_email_time(&date),
-                    read: false,
-                    folder: folder.clone(),
-                    has_attachments,
-                });
-            }
-        }
-
-        session.logout().ok();
+                IMAP_POOL.with_session(account_uuid, connect, |session| {
+                    mail_index::sync_and_list(
+                        &mut db_conn,
+                        session,
+                        account_uuid,
+                        &folder,
+                        offset,
+                        limit,
+                    )
+                    .map_err(EmailError::Internal)
+                })
+            })
+            .await
+            .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+            .ok_or_else(|| EmailError::Internal("Request cancelled".to_string()))??;
 
         Ok(Json(ApiResponse {
             success: true,
@@ -262,6 +161,7 @@ pub async fn list_emails(
 
     #[cfg(not(feature = "mail"))]
     {
+        let _ = cancellation;
         Ok(Json(ApiResponse {
             success: false,
             data: Some(Vec::new()),
@@ -270,12 +170,14 @@ pub async fn list_emails(
     }
 }
 
+/// Validates the message and queues it for the outbox worker, which sends it at the
+/// account's rate. Poll `/api/email/outbox/:id` for the outcome.
 pub async fn send_email(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<SendEmailRequest>,
-) -> Result<Json<ApiResponse<()>>, EmailError> {
+    ApiJson(request): ApiJson<SendEmailRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<QueuedEmailResponse>>), EmailError> {
     let account_uuid = Uuid::parse_str(&request.account_id)
-        .map_err(|_| EmailError("Invalid account ID".to_string()))?;
+        .map_err(|_| EmailError::BadRequest("Invalid account ID".to_string()))?;
 
     let conn = state.conn.clone();
     let account_info = tokio::task::spawn_blocking(move || {
@@ -294,24 +196,10 @@ pub async fn send_email(
         Ok::<_, String>(result)
     })
     .await
-    .map_err(|e| EmailError(format!("Task join error: {e}")))?
-    .map_err(EmailError)?;
-
-    let (from_email, display_name, smtp_port, smtp_server, username, encrypted_password) = (
-        account_info.email,
-        account_info.display_name,
-        account_info.smtp_port,
-        account_info.smtp_server,
-        account_info.username,
-        account_info.password_encrypted,
-    );
-    let password = decrypt_password(&encrypted_password).map_err(EmailError)?;
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
 
-    let from_addr = if display_name.is_empty() {
-        from_email.clone()
-    } else {
-        format!("{display_name} <{from_email}>")
-    };
+    let from_addr = outbox::from_address(&account_info.email, &account_info.display_name);
 
     let pixel_enabled = is_tracking_pixel_enabled(&state, None);
     let tracking_id = Uuid::new_v4();
@@ -322,89 +210,88 @@ pub async fn send_email(
         request.body.clone()
     };
 
-    let mut email_builder = Message::builder()
-        .from(
-            from_addr
-                .parse()
-                .map_err(|e| EmailError(format!("Invalid from address: {e}")))?,
-        )
-        .to(request
-            .to
-            .parse()
-            .map_err(|e| EmailError(format!("Invalid to address: {e}")))?)
-        .subject(request.subject.clone());
-
-    if let Some(ref cc) = request.cc {
-        email_builder = email_builder.cc(cc
-            .parse()
-            .map_err(|e| EmailError(format!("Invalid cc address: {e}")))?);
-    }
+    outbox::build_message(
+        &from_addr,
+        &request.to,
+        request.cc.as_deref(),
+        request.bcc.as_deref(),
+        &request.subject,
+        final_body.clone(),
+    )
+    .map_err(EmailError::BadRequest)?;
+
+    let message = outbox::NewOutboxMessage {
+        account_id: account_uuid,
+        to: request.to,
+        cc: request.cc,
+        bcc: request.bcc,
+        subject: request.subject,
+        body: final_body,
+        tracking_id: pixel_enabled.then_some(tracking_id),
+    };
+    let conn = state.conn.clone();
+    let message_id = tokio::task::spawn_blocking(move || {
+        let mut db_conn = conn
+            .get()
+            .map_err(|e| format!("DB connection error: {e}"))?;
+        outbox::enqueue(&mut db_conn, &message).map_err(|e| format!("Failed to queue email: {e}"))
+    })
+    .await
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
 
-    if let Some(ref bcc) = request.bcc {
-        email_builder = email_builder.bcc(
-            bcc.parse()
-                .map_err(|e| EmailError(format!("Invalid bcc address: {e}")))?,
-        );
-    }
+    info!("Email {message_id} queued from account {account_uuid}");
 
-    let email = email_builder
-        .body(final_body)
-        .map_err(|e| EmailError(format!("Failed to build email: {e}")))?;
-
-    let creds = Credentials::new(username, password);
-    let mailer = SmtpTransport::relay(&smtp_server)
-        .map_err(|e| EmailError(format!("Failed to create SMTP transport: {e}")))?
-        .port(u16::try_from(smtp_port).unwrap_or(587))
-        .credentials(creds)
-        .build();
-
-    mailer
-        .send(&email)
-        .map_err(|e| EmailError(format!("Failed to send email: {e}")))?;
-
-    if pixel_enabled {
-        let conn = state.conn.clone();
-        let to_email = request.to.clone();
-        let subject = request.subject.clone();
-        let cc_clone = request.cc.clone();
-        let bcc_clone = request.bcc.clone();
-
-        let _ = tokio::task::spawn_blocking(move || {
-            save_email_tracking_record(
-                conn,
-                EmailTrackingParams {
-                    tracking_id,
-                    account_id: account_uuid,
-                    bot_id: Uuid::nil(),
-                    from_email: &from_email,
-                    to_email: &to_email,
-                    cc: cc_clone.as_deref(),
-                    bcc: bcc_clone.as_deref(),
-                    subject: &subject,
-                },
-            )
-        })
-        .await;
-    }
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ApiResponse {
+            success: true,
+            data: Some(QueuedEmailResponse {
+                message_id,
+                status: "queued".to_string(),
+            }),
+            message: Some("Email queued".to_string()),
+        }),
+    ))
+}
 
-    info!("Email sent successfully from account {account_uuid} with tracking_id {tracking_id}");
+pub async fn get_outbox_status(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<outbox::OutboxStatus>>, EmailError> {
+    if !user.is_authenticated() {
+        return Err(EmailError::Unauthorized("Authentication required".to_string()));
+    }
+    let user_id = user.user_id;
+    let conn = state.conn.clone();
+    let status = tokio::task::spawn_blocking(move || {
+        let mut db_conn = conn
+            .get()
+            .map_err(|e| format!("DB connection error: {e}"))?;
+        outbox::get_status(&mut db_conn, id, user_id).map_err(|e| format!("Failed to load email: {e}"))
+    })
+    .await
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?
+    .ok_or_else(|| EmailError::BadRequest(format!("Unknown outbox message {id}")))?;
 
     Ok(Json(ApiResponse {
         success: true,
-        data: Some(()),
-        message: Some("Email sent successfully".to_string()),
+        data: Some(status),
+        message: None,
     }))
 }
 
 pub async fn save_draft(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<SaveDraftRequest>,
+    ApiJson(request): ApiJson<SaveDraftRequest>,
 ) -> Result<Json<SaveDraftResponse>, EmailError> {
     let account_uuid = Uuid::parse_str(&request.account_id)
-        .map_err(|_| EmailError("Invalid account ID".to_string()))?;
+        .map_err(|_| EmailError::BadRequest("Invalid account ID".to_string()))?;
 
     let Ok(user_id) = extract_user_from_session(&state) else {
-        return Err(EmailError("Authentication required".to_string()));
+        return Err(EmailError::Unauthorized("Authentication required".to_string()));
     };
     let draft_id = Uuid::new_v4();
 
@@ -430,8 +317,8 @@ pub async fn save_draft(
         Ok::<_, String>(())
     })
     .await
-    .map_err(|e| EmailError(format!("Task join error: {e}")))?
-    .map_err(EmailError)?;
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
 
     Ok(Json(SaveDraftResponse {
         success: true,
@@ -442,17 +329,18 @@ pub async fn save_draft(
 
 pub async fn list_folders(
     State(state): State<Arc<AppState>>,
+    cancellation: RequestCancellation,
     Path(account_id): Path<String>,
 ) -> Result<Json<ApiResponse<Vec<FolderInfo>>>, EmailError> {
     let account_uuid =
-        Uuid::parse_str(&account_id).map_err(|_| EmailError("Invalid account ID".to_string()))?;
+        Uuid::parse_str(&account_id).map_err(|_| EmailError::BadRequest("Invalid account ID".to_string()))?;
 
     let conn = state.conn.clone();
     let account_info = tokio::task::spawn_blocking(move || {
         let mut db_conn = conn.get().map_err(|e| format!("DB connection error: {e}"))?;
 
         let result: ImapCredentialsRow = diesel::sql_query(
-            "SELECT imap_server, imap_port, username, password_encrypted FROM user_email_accounts WHERE id = $1 AND is_active = true"
+            "SELECT imap_server, imap_port, username, password_encrypted, default_folder FROM user_email_accounts WHERE id = $1 AND is_active = true"
         )
         .bind::<diesel::sql_types::Uuid, _>(account_uuid)
         .get_result(&mut db_conn)
@@ -461,8 +349,8 @@ pub async fn list_folders(
         Ok::<_, String>(result)
     })
     .await
-    .map_err(|e| EmailError(format!("Task join error: {e}")))?
-    .map_err(EmailError)?;
+    .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+    .map_err(EmailError::Internal)?;
 
     let (imap_server, imap_port, username, encrypted_password) = (
         account_info.imap_server,
@@ -470,33 +358,35 @@ pub async fn list_folders(
         account_info.username,
         account_info.password_encrypted,
     );
-    let password = decrypt_password(&encrypted_password).map_err(EmailError)?;
+    let password = decrypt_password(&encrypted_password).map_err(EmailError::Internal)?;
 
     #[cfg(feature = "mail")]
     {
-        let client = imap::ClientBuilder::new(imap_server.as_str(), imap_port as u16)
-            .connect()
-            .map_err(|e| EmailError(format!("Failed to connect to IMAP: {e:?}")))?;
-
-        let mut session = client
-            .login(&username, &password)
-            .map_err(|e| EmailError(format!("Login failed: {e:?}")))?;
-
-        let folders = session
-            .list(None, Some("*"))
-            .map_err(|e| EmailError(format!("Failed to list folders: {e:?}")))?;
-
-        let folder_list: Vec<FolderInfo> = folders
-            .iter()
-            .map(|f| FolderInfo {
-                name: f.name().to_string(),
-                path: f.name().to_string(),
-                unread_count: 0,
-                total_count: 0,
+        let folder_list = cancellation
+            .spawn_blocking(move || {
+                let connect = || {
+                    connect_imap(&imap_server, imap_port as u16, &username, &password)
+                        .map_err(EmailError::Internal)
+                };
+                IMAP_POOL.with_session(account_uuid, connect, |session| {
+                    let folders = session.list(None, Some("*")).map_err(|e| {
+                        EmailError::Internal(format!("Failed to list folders: {e:?}"))
+                    })?;
+
+                    Ok(folders
+                        .iter()
+                        .map(|f| FolderInfo {
+                            name: f.name().to_string(),
+                            path: f.name().to_string(),
+                            unread_count: 0,
+                            total_count: 0,
+                        })
+                        .collect::<Vec<_>>())
+                })
             })
-            .collect();
-
-        session.logout().ok();
+            .await
+            .map_err(|e| EmailError::Internal(format!("Task join error: {e}")))?
+            .ok_or_else(|| EmailError::Internal("Request cancelled".to_string()))??;
 
         Ok(Json(ApiResponse {
             success: true,
@@ -507,6 +397,7 @@ pub async fn list_folders(
 
     #[cfg(not(feature = "mail"))]
     {
+        let _ = cancellation;
         Ok(Json(ApiResponse {
             success: false,
             data: Some(Vec::new()),
@@ -517,7 +408,7 @@ pub async fn list_folders(
 
 pub fn get_latest_email_from(
     State(_state): State<Arc<AppState>>,
-    Json(_request): Json<serde_json::Value>,
+    ApiJson(_request): ApiJson<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>, EmailError> {
     Ok(Json(serde_json::json!({
         "success": false,
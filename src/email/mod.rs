@@ -21,6 +21,9 @@ pub mod integration;
 pub mod snooze;
 pub mod nudges;
 pub mod flags;
+pub mod imap_pool;
+pub mod mail_index;
+pub mod outbox;
 
 #[cfg(test)]
 mod integration_types_test;
@@ -79,8 +82,16 @@ pub fn configure() -> Router<Arc<AppState>> {
             &ApiUrls::EMAIL_ACCOUNT_BY_ID.replace(":id", "{account_id}"),
             axum::routing::delete(delete_email_account),
         )
+        .route(
+            &format!("{}/default-folder", ApiUrls::EMAIL_ACCOUNT_BY_ID.replace(":id", "{account_id}")),
+            axum::routing::put(update_default_folder),
+        )
         .route(ApiUrls::EMAIL_LIST, post(list_emails))
         .route(ApiUrls::EMAIL_SEND, post(send_email))
+        .route(
+            &ApiUrls::EMAIL_OUTBOX_STATUS.replace(":id", "{id}"),
+            get(get_outbox_status),
+        )
         .route(ApiUrls::EMAIL_DRAFT, post(save_draft))
         .route(
             &ApiUrls::EMAIL_FOLDERS.replace(":account_id", "{account_id}"),
@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -31,7 +32,7 @@ pub struct NudgesResponse {
 /// Check for emails that need follow-up nudges
 pub async fn check_nudges(
     State(_state): State<Arc<AppState>>,
-    Json(_req): Json<NudgeCheckRequest>,
+    ApiJson(_req): ApiJson<NudgeCheckRequest>,
 ) -> Result<Json<NudgesResponse>, StatusCode> {
     // Simple implementation - can be enhanced with actual email tracking
     let nudges = vec![];
@@ -42,7 +43,7 @@ pub async fn check_nudges(
 /// Dismiss a nudge
 pub async fn dismiss_nudge(
     State(state): State<Arc<AppState>>,
-    Json(email_id): Json<Uuid>,
+    ApiJson(email_id): ApiJson<Uuid>,
 ) -> Result<StatusCode, StatusCode> {
     use crate::core::shared::schema::email_nudges;
 
@@ -0,0 +1,545 @@
+//! Outbound mail queue. `send_email` stores a message in `email_outbox` and returns its id;
+//! a worker sends due messages, spaced per account to stay under the provider's send
+//! rate, and retries transient SMTP failures with exponential backoff.
+
+use super::tracking::{save_email_tracking_record, EmailTrackingParams};
+use super::types::SmtpCredentialsRow;
+use crate::core::shared::state::AppState;
+use crate::core::shared::utils::DbPool;
+use crate::security::master_key::{self, LegacyEncoding};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Integer, Nullable, Text, Timestamptz};
+use lettre::{transport::smtp::authentication::Credentials, Message, SmtpTransport, Transport};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+pub const MAX_ATTEMPTS: i32 = 5;
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 3600;
+/// Messages claimed per account per poll, so a slow account's claim never outlives
+/// `STALE_CLAIM_MINUTES`.
+const CLAIM_PER_ACCOUNT: i64 = 10;
+const CLAIM_BATCH: i64 = 200;
+const STALE_CLAIM_MINUTES: i64 = 60;
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+const DEFAULT_RATE_PER_MINUTE: u32 = 60;
+/// Conservative per-minute defaults for providers known to throttle bulk senders.
+/// An account's `send_rate_per_minute` overrides these.
+const PROVIDER_RATES: &[(&str, u32)] = &[
+    ("gmail.com", 20),
+    ("googlemail.com", 20),
+    ("office365.com", 30),
+    ("outlook.com", 30),
+    ("yahoo.com", 20),
+    ("zoho.com", 20),
+    ("amazonaws.com", 600),
+    ("sendgrid.net", 600),
+];
+
+/// Messages per minute for an account: its own setting, else the provider default.
+pub fn send_rate_for(smtp_server: &str, configured: Option<i32>) -> u32 {
+    if let Some(rate) = configured.filter(|r| *r > 0) {
+        return rate as u32;
+    }
+    let host = smtp_server.trim().to_ascii_lowercase();
+    PROVIDER_RATES
+        .iter()
+        .find(|(domain, _)| host == *domain || host.ends_with(&format!(".{domain}")))
+        .map(|(_, rate)| *rate)
+        .unwrap_or(DEFAULT_RATE_PER_MINUTE)
+}
+
+/// Delay before attempt `attempt + 1`, doubling from 30 seconds up to an hour.
+pub fn backoff(attempt: i32) -> chrono::Duration {
+    let exponent = attempt.saturating_sub(1).clamp(0, 16) as u32;
+    chrono::Duration::seconds((BASE_BACKOFF_SECS << exponent).min(MAX_BACKOFF_SECS))
+}
+
+/// Time between two sends from an account sending `per_minute` messages a minute.
+pub fn send_spacing(per_minute: u32) -> Duration {
+    Duration::from_secs(60) / per_minute.max(1)
+}
+
+/// Accounts with a send task running, so one poll does not claim their mail twice.
+static ACTIVE_ACCOUNTS: LazyLock<Mutex<HashSet<Uuid>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+pub struct NewOutboxMessage {
+    pub account_id: Uuid,
+    pub to: String,
+    pub cc: Option<String>,
+    pub bcc: Option<String>,
+    pub subject: String,
+    pub body: String,
+    pub tracking_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, QueryableByName)]
+pub struct OutboxStatus {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub id: Uuid,
+    /// `queued`, `sending`, `sent` or `failed`.
+    #[diesel(sql_type = Text)]
+    pub status: String,
+    #[diesel(sql_type = Integer)]
+    pub attempts: i32,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub last_error: Option<String>,
+    #[diesel(sql_type = Timestamptz)]
+    pub created_at: DateTime<Utc>,
+    #[diesel(sql_type = Timestamptz)]
+    pub next_attempt_at: DateTime<Utc>,
+    #[diesel(sql_type = Nullable<Timestamptz>)]
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+#[derive(QueryableByName)]
+struct ClaimedRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    account_id: Uuid,
+    #[diesel(sql_type = Text)]
+    to_address: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    cc: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    bcc: Option<String>,
+    #[diesel(sql_type = Text)]
+    subject: String,
+    #[diesel(sql_type = Text)]
+    body: String,
+    #[diesel(sql_type = Nullable<diesel::sql_types::Uuid>)]
+    tracking_id: Option<Uuid>,
+    #[diesel(sql_type = Integer)]
+    attempts: i32,
+}
+
+#[derive(QueryableByName)]
+struct AccountRow {
+    #[diesel(embed)]
+    credentials: SmtpCredentialsRow,
+    #[diesel(sql_type = Nullable<Integer>)]
+    send_rate_per_minute: Option<i32>,
+}
+
+pub fn enqueue(conn: &mut PgConnection, message: &NewOutboxMessage) -> QueryResult<Uuid> {
+    #[derive(QueryableByName)]
+    struct IdRow {
+        #[diesel(sql_type = diesel::sql_types::Uuid)]
+        id: Uuid,
+    }
+    diesel::sql_query(
+        "INSERT INTO email_outbox (account_id, to_address, cc, bcc, subject, body, tracking_id)
+         VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(message.account_id)
+    .bind::<Text, _>(&message.to)
+    .bind::<Nullable<Text>, _>(message.cc.as_deref())
+    .bind::<Nullable<Text>, _>(message.bcc.as_deref())
+    .bind::<Text, _>(&message.subject)
+    .bind::<Text, _>(&message.body)
+    .bind::<Nullable<diesel::sql_types::Uuid>, _>(message.tracking_id)
+    .get_result::<IdRow>(conn)
+    .map(|row| row.id)
+}
+
+/// The message's state, if it was queued from one of `user_id`'s accounts.
+pub fn get_status(
+    conn: &mut PgConnection,
+    id: Uuid,
+    user_id: Uuid,
+) -> QueryResult<Option<OutboxStatus>> {
+    diesel::sql_query(
+        "SELECT o.id, o.status, o.attempts, o.last_error, o.created_at, o.next_attempt_at,
+                o.sent_at
+         FROM email_outbox o
+         JOIN user_email_accounts a ON a.id = o.account_id
+         WHERE o.id = $1 AND a.user_id = $2",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(id)
+    .bind::<diesel::sql_types::Uuid, _>(user_id)
+    .get_result(conn)
+    .optional()
+}
+
+/// Reserves the account's next send slot and returns how long to wait for it. The
+/// slot is kept on the account row, so every server sharing the database paces the
+/// account together.
+fn reserve_slot(
+    conn: &mut PgConnection,
+    account_id: Uuid,
+    per_minute: u32,
+) -> QueryResult<Duration> {
+    #[derive(QueryableByName)]
+    struct WaitRow {
+        #[diesel(sql_type = Double)]
+        wait_secs: f64,
+    }
+    let spacing = send_spacing(per_minute).as_secs_f64();
+    diesel::sql_query(
+        "UPDATE user_email_accounts
+         SET outbox_next_send_at = GREATEST(COALESCE(outbox_next_send_at, NOW()), NOW())
+             + make_interval(secs => $2)
+         WHERE id = $1
+         RETURNING GREATEST(EXTRACT(EPOCH FROM outbox_next_send_at - NOW()) - $2, 0)::float8
+             AS wait_secs",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(account_id)
+    .bind::<Double, _>(spacing)
+    .get_result::<WaitRow>(conn)
+    .map(|row| Duration::from_secs_f64(row.wait_secs.max(0.0)))
+}
+
+/// Returns claims abandoned by a crashed worker to the queue.
+fn release_stale_claims(conn: &mut PgConnection) -> QueryResult<usize> {
+    diesel::sql_query(
+        "UPDATE email_outbox SET status = 'queued', claimed_at = NULL
+         WHERE status = 'sending' AND claimed_at < NOW() - make_interval(mins => $1)",
+    )
+    .bind::<Integer, _>(STALE_CLAIM_MINUTES as i32)
+    .execute(conn)
+}
+
+fn claim_due(conn: &mut PgConnection, skip_accounts: &[Uuid]) -> QueryResult<Vec<ClaimedRow>> {
+    diesel::sql_query(
+        "UPDATE email_outbox o
+         SET status = 'sending', claimed_at = NOW(), attempts = o.attempts + 1
+         WHERE o.id IN (
+             SELECT id FROM (
+                 SELECT id,
+                        ROW_NUMBER() OVER (PARTITION BY account_id ORDER BY next_attempt_at) AS n
+                 FROM email_outbox
+                 WHERE status = 'queued' AND next_attempt_at <= NOW()
+                   AND NOT (account_id = ANY($1))
+             ) due
+             WHERE n <= $2
+             LIMIT $3
+         )
+         AND o.status = 'queued'
+         RETURNING o.id, o.account_id, o.to_address, o.cc, o.bcc, o.subject, o.body,
+                   o.tracking_id, o.attempts",
+    )
+    .bind::<diesel::sql_types::Array<diesel::sql_types::Uuid>, _>(skip_accounts)
+    .bind::<BigInt, _>(CLAIM_PER_ACCOUNT)
+    .bind::<BigInt, _>(CLAIM_BATCH)
+    .load(conn)
+}
+
+fn load_account(conn: &mut PgConnection, account_id: Uuid) -> QueryResult<Option<AccountRow>> {
+    diesel::sql_query(
+        "SELECT email, COALESCE(display_name, '') AS display_name, smtp_port, smtp_server,
+                username, password_encrypted, send_rate_per_minute
+         FROM user_email_accounts WHERE id = $1 AND is_active = true",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(account_id)
+    .get_result(conn)
+    .optional()
+}
+
+fn mark_sent(conn: &mut PgConnection, id: Uuid) -> QueryResult<usize> {
+    diesel::sql_query(
+        "UPDATE email_outbox SET status = 'sent', sent_at = NOW(), last_error = NULL,
+                claimed_at = NULL
+         WHERE id = $1",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(id)
+    .execute(conn)
+}
+
+fn mark_retry(
+    conn: &mut PgConnection,
+    id: Uuid,
+    error: &str,
+    at: DateTime<Utc>,
+) -> QueryResult<usize> {
+    diesel::sql_query(
+        "UPDATE email_outbox SET status = 'queued', last_error = $2, next_attempt_at = $3,
+                claimed_at = NULL
+         WHERE id = $1",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(id)
+    .bind::<Text, _>(error)
+    .bind::<Timestamptz, _>(at)
+    .execute(conn)
+}
+
+fn mark_failed(conn: &mut PgConnection, id: Uuid, error: &str) -> QueryResult<usize> {
+    diesel::sql_query(
+        "UPDATE email_outbox SET status = 'failed', last_error = $2, claimed_at = NULL
+         WHERE id = $1",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(id)
+    .bind::<Text, _>(error)
+    .execute(conn)
+}
+
+pub fn from_address(email: &str, display_name: &str) -> String {
+    if display_name.is_empty() {
+        email.to_string()
+    } else {
+        format!("{display_name} <{email}>")
+    }
+}
+
+/// Builds the message, rejecting malformed addresses.
+pub fn build_message(
+    from: &str,
+    to: &str,
+    cc: Option<&str>,
+    bcc: Option<&str>,
+    subject: &str,
+    body: String,
+) -> Result<Message, String> {
+    let mut builder = Message::builder()
+        .from(
+            from.parse()
+                .map_err(|e| format!("Invalid from address: {e}"))?,
+        )
+        .to(to.parse().map_err(|e| format!("Invalid to address: {e}"))?)
+        .subject(subject);
+    if let Some(cc) = cc {
+        builder = builder.cc(cc.parse().map_err(|e| format!("Invalid cc address: {e}"))?);
+    }
+    if let Some(bcc) = bcc {
+        builder = builder.bcc(
+            bcc.parse()
+                .map_err(|e| format!("Invalid bcc address: {e}"))?,
+        );
+    }
+    builder
+        .body(body)
+        .map_err(|e| format!("Failed to build email: {e}"))
+}
+
+enum SendFailure {
+    /// Worth retrying: 4xx replies, timeouts, connection problems.
+    Transient(String),
+    Permanent(String),
+}
+
+fn deliver(account: &SmtpCredentialsRow, row: &ClaimedRow) -> Result<(), SendFailure> {
+    let from = from_address(&account.email, &account.display_name);
+    let message = build_message(
+        &from,
+        &row.to_address,
+        row.cc.as_deref(),
+        row.bcc.as_deref(),
+        &row.subject,
+        row.body.clone(),
+    )
+    .map_err(SendFailure::Permanent)?;
+    let password = master_key::reveal(&account.password_encrypted, LegacyEncoding::Base64)
+        .map_err(|e| SendFailure::Permanent(format!("Decryption failed: {e}")))?;
+
+    let mailer = SmtpTransport::relay(&account.smtp_server)
+        .map_err(|e| SendFailure::Permanent(format!("Failed to create SMTP transport: {e}")))?
+        .port(u16::try_from(account.smtp_port).unwrap_or(587))
+        .credentials(Credentials::new(account.username.clone(), password))
+        .build();
+    mailer.send(&message).map(|_| ()).map_err(|e| {
+        if e.is_permanent() {
+            SendFailure::Permanent(e.to_string())
+        } else {
+            SendFailure::Transient(e.to_string())
+        }
+    })
+}
+
+async fn blocking<T, F>(pool: &DbPool, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> QueryResult<T> + Send + 'static,
+{
+    let pool = pool.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        f(&mut conn).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Sends one account's claimed messages in order, each in its paced slot.
+async fn send_for_account(pool: DbPool, account_id: Uuid, rows: Vec<ClaimedRow>) {
+    let account = match blocking(&pool, move |conn| load_account(conn, account_id)).await {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            for row in rows {
+                let id = row.id;
+                let _ = blocking(&pool, move |conn| {
+                    mark_failed(conn, id, "Email account not found or inactive")
+                })
+                .await;
+            }
+            return;
+        }
+        Err(e) => {
+            warn!("Outbox: cannot load account {}: {}", account_id, e);
+            for row in rows {
+                let (id, at, error) = (row.id, Utc::now() + backoff(row.attempts), e.clone());
+                let _ = blocking(&pool, move |conn| mark_retry(conn, id, &error, at)).await;
+            }
+            return;
+        }
+    };
+    let rate = send_rate_for(
+        &account.credentials.smtp_server,
+        account.send_rate_per_minute,
+    );
+    let credentials = Arc::new(account.credentials);
+
+    for row in rows {
+        let wait = blocking(&pool, move |conn| reserve_slot(conn, account_id, rate))
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Outbox: cannot reserve a send slot for {}: {}", account_id, e);
+                send_spacing(rate)
+            });
+        tokio::time::sleep(wait).await;
+        let row = Arc::new(row);
+        let (creds, message) = (Arc::clone(&credentials), Arc::clone(&row));
+        let result = tokio::task::spawn_blocking(move || deliver(&creds, &message))
+            .await
+            .unwrap_or_else(|e| Err(SendFailure::Transient(e.to_string())));
+
+        let id = row.id;
+        let update = match result {
+            Ok(()) => {
+                info!("Outbox: sent {} from account {}", id, account_id);
+                if let Some(tracking_id) = row.tracking_id {
+                    record_tracking(&pool, &credentials, &row, tracking_id).await;
+                }
+                blocking(&pool, move |conn| mark_sent(conn, id)).await
+            }
+            Err(SendFailure::Transient(e)) if row.attempts < MAX_ATTEMPTS => {
+                let at = Utc::now() + backoff(row.attempts);
+                warn!(
+                    "Outbox: {} attempt {} failed, retrying at {}: {}",
+                    id, row.attempts, at, e
+                );
+                blocking(&pool, move |conn| mark_retry(conn, id, &e, at)).await
+            }
+            Err(SendFailure::Transient(e)) | Err(SendFailure::Permanent(e)) => {
+                error!(
+                    "Outbox: {} failed after {} attempt(s): {}",
+                    id, row.attempts, e
+                );
+                blocking(&pool, move |conn| mark_failed(conn, id, &e)).await
+            }
+        };
+        if let Err(e) = update {
+            error!("Outbox: cannot update {}: {}", id, e);
+        }
+    }
+}
+
+async fn record_tracking(
+    pool: &DbPool,
+    account: &Arc<SmtpCredentialsRow>,
+    row: &Arc<ClaimedRow>,
+    tracking_id: Uuid,
+) {
+    let (pool, account, row) = (pool.clone(), Arc::clone(account), Arc::clone(row));
+    let saved = tokio::task::spawn_blocking(move || {
+        save_email_tracking_record(
+            pool,
+            EmailTrackingParams {
+                tracking_id,
+                account_id: row.account_id,
+                bot_id: Uuid::nil(),
+                from_email: &account.email,
+                to_email: &row.to_address,
+                cc: row.cc.as_deref(),
+                bcc: row.bcc.as_deref(),
+                subject: &row.subject,
+            },
+        )
+    })
+    .await;
+    if let Ok(Err(e)) = saved {
+        warn!("Outbox: {}", e);
+    }
+}
+
+async fn poll(pool: &DbPool) -> Result<(), String> {
+    let skip: Vec<Uuid> = ACTIVE_ACCOUNTS
+        .lock()
+        .map(|active| active.iter().copied().collect())
+        .unwrap_or_default();
+    let claimed = blocking(pool, move |conn| {
+        release_stale_claims(conn)?;
+        claim_due(conn, &skip)
+    })
+    .await?;
+
+    let mut by_account: HashMap<Uuid, Vec<ClaimedRow>> = HashMap::new();
+    for row in claimed {
+        by_account.entry(row.account_id).or_default().push(row);
+    }
+    for (account_id, rows) in by_account {
+        if let Ok(mut active) = ACTIVE_ACCOUNTS.lock() {
+            active.insert(account_id);
+        }
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            send_for_account(pool, account_id, rows).await;
+            if let Ok(mut active) = ACTIVE_ACCOUNTS.lock() {
+                active.remove(&account_id);
+            }
+        });
+    }
+    Ok(())
+}
+
+pub fn spawn_outbox_worker(state: Arc<AppState>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = poll(&state.conn).await {
+                warn!("Outbox poll failed: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_spacing() {
+        assert_eq!(send_spacing(20), Duration::from_secs(3));
+        assert_eq!(send_spacing(600), Duration::from_millis(100));
+        assert_eq!(send_spacing(0), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_provider_rates() {
+        assert_eq!(send_rate_for("smtp.gmail.com", None), 20);
+        assert_eq!(send_rate_for("SMTP.Office365.com", None), 30);
+        assert_eq!(
+            send_rate_for("mail.example.com", None),
+            DEFAULT_RATE_PER_MINUTE
+        );
+        assert_eq!(send_rate_for("notgmail.com", None), DEFAULT_RATE_PER_MINUTE);
+        assert_eq!(send_rate_for("smtp.gmail.com", Some(5)), 5);
+        assert_eq!(send_rate_for("smtp.gmail.com", Some(0)), 20);
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert_eq!(backoff(1), chrono::Duration::seconds(30));
+        assert_eq!(backoff(2), chrono::Duration::seconds(60));
+        assert_eq!(backoff(4), chrono::Duration::seconds(240));
+        assert_eq!(backoff(20), chrono::Duration::seconds(MAX_BACKOFF_SECS));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::core::shared::state::AppState;
 use crate::core::middleware::AuthenticatedUser;
+use crate::core::shared::api_json::ApiJson;
 use super::types::*;
 use axum::{
     extract::{Path, State},
@@ -195,7 +196,7 @@ pub async fn get_signature(
 pub async fn create_signature(
     State(state): State<Arc<AppState>>,
     user: AuthenticatedUser,
-    Json(payload): Json<CreateSignatureRequest>,
+    ApiJson(payload): ApiJson<CreateSignatureRequest>,
 ) -> impl IntoResponse {
     let mut conn = match state.conn.get() {
         Ok(c) => c,
@@ -254,7 +255,7 @@ pub async fn update_signature(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
     user: AuthenticatedUser,
-    Json(payload): Json<UpdateSignatureRequest>,
+    ApiJson(payload): ApiJson<UpdateSignatureRequest>,
 ) -> impl IntoResponse {
     let signature_id = match Uuid::parse_str(&id) {
         Ok(id) => id,
@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -26,7 +27,7 @@ pub struct SnoozeResponse {
 /// Snooze emails until a specific time
 pub async fn snooze_emails(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<SnoozeRequest>,
+    ApiJson(req): ApiJson<SnoozeRequest>,
 ) -> Result<Json<SnoozeResponse>, StatusCode> {
     use crate::core::shared::schema::email_snooze;
 
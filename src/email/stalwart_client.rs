@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, NaiveDate, Utc};
+use crate::core::shared::outbound_proxy;
 use reqwest::{Client, Method};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -433,7 +434,7 @@ pub struct StalwartClient {
 
 impl StalwartClient {
     pub fn new(base_url: &str, token: &str) -> Self {
-        let http_client = Client::builder()
+        let http_client = outbound_proxy::client_builder()
             .timeout(Duration::from_secs(DEFAULT_TIMEOUT_SECS))
             .build()
             .unwrap_or_else(|_| Client::new());
@@ -446,7 +447,7 @@ impl StalwartClient {
     }
 
     pub fn with_timeout(base_url: &str, token: &str, timeout_secs: u64) -> Self {
-        let http_client = Client::builder()
+        let http_client = outbound_proxy::client_builder()
             .timeout(Duration::from_secs(timeout_secs))
             .build()
             .unwrap_or_else(|_| Client::new());
@@ -1,7 +1,7 @@
 use crate::core::shared::state::AppState;
 use super::types::*;
 use axum::{
-    extract::{Path, Query, State},
+    extract::{ConnectInfo, Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
     Json,
@@ -9,7 +9,10 @@ use axum::{
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use log::{debug, info, warn};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 const TRACKING_PIXEL: [u8; 43] = [
@@ -18,6 +21,96 @@ const TRACKING_PIXEL: [u8; 43] = [
     0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3B,
 ];
 
+/// Pixel requests allowed per client IP per window before requests are no longer recorded.
+const PIXEL_REQUESTS_PER_WINDOW: u32 = 30;
+const PIXEL_RATE_WINDOW: Duration = Duration::from_secs(60);
+/// A recipient opening the same email again within this window is not counted again.
+const OPEN_DEDUP_WINDOW_MINUTES: i32 = 60;
+
+static PIXEL_RATE_LIMITER: LazyLock<PixelRateLimiter> =
+    LazyLock::new(|| PixelRateLimiter::new(PIXEL_REQUESTS_PER_WINDOW, PIXEL_RATE_WINDOW));
+
+/// Fixed-window request counter keyed by client IP, or by tracking id when the client
+/// address is unknown.
+pub struct PixelRateLimiter {
+    limit: u32,
+    window: Duration,
+    hits: Mutex<HashMap<String, (Instant, u32)>>,
+}
+
+impl PixelRateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check(&self, key: &str, now: Instant) -> bool {
+        let mut hits = self.hits.lock().unwrap_or_else(|e| e.into_inner());
+        if hits.len() > 10_000 {
+            let window = self.window;
+            hits.retain(|_, (start, _)| now.duration_since(*start) < window);
+        }
+        let entry = hits.entry(key.to_string()).or_insert((now, 0));
+        if now.duration_since(entry.0) >= self.window {
+            *entry = (now, 0);
+        }
+        entry.1 += 1;
+        entry.1 <= self.limit
+    }
+}
+
+/// Who fetched the pixel, judged from the user agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenSource {
+    Human,
+    /// Mail provider image proxies and link scanners that fetch images on delivery
+    /// or on behalf of the reader; they never count as an open.
+    Proxy,
+    Bot,
+}
+
+impl OpenSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Human => "human",
+            Self::Proxy => "proxy",
+            Self::Bot => "bot",
+        }
+    }
+}
+
+const PROXY_USER_AGENTS: &[&str] = &[
+    "googleimageproxy",
+    "ggpht.com",
+    "yahoomailproxy",
+    "outlook-ios-linkpreview",
+    "barracuda",
+    "mimecast",
+    "proofpoint",
+];
+
+const BOT_USER_AGENTS: &[&str] = &[
+    "bot", "crawler", "spider", "preview", "curl", "wget", "python", "go-http-client",
+    "java/", "okhttp", "headless", "scanner",
+];
+
+pub fn classify_user_agent(user_agent: Option<&str>) -> OpenSource {
+    let ua = match user_agent.map(str::trim) {
+        Some(ua) if !ua.is_empty() => ua.to_ascii_lowercase(),
+        _ => return OpenSource::Bot,
+    };
+    if PROXY_USER_AGENTS.iter().any(|p| ua.contains(p)) {
+        OpenSource::Proxy
+    } else if BOT_USER_AGENTS.iter().any(|p| ua.contains(p)) {
+        OpenSource::Bot
+    } else {
+        OpenSource::Human
+    }
+}
+
 pub fn is_tracking_pixel_enabled(state: &Arc<AppState>, bot_id: Option<Uuid>) -> bool {
     let config_manager = crate::core::config::ConfigManager::new(state.conn.clone());
     let bot_id = bot_id.unwrap_or(Uuid::nil());
@@ -97,40 +190,44 @@ pub async fn serve_tracking_pixel(
     Path(tracking_id): Path<String>,
     State(state): State<Arc<AppState>>,
     Query(_query): Query<TrackingPixelQuery>,
+    peer: Option<ConnectInfo<SocketAddr>>,
     headers: axum::http::HeaderMap,
 ) -> impl IntoResponse {
-    let client_ip = headers
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
-        .or_else(|| {
-            headers
-                .get("x-real-ip")
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
-        });
+    let peer = peer.map(|ConnectInfo(addr)| addr.ip());
+    let client_ip =
+        crate::security::client_ip::client_ip(&headers, peer).map(|ip| ip.to_string());
 
     let user_agent = headers
         .get("user-agent")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    if let Ok(tracking_uuid) = Uuid::parse_str(&tracking_id) {
+    let rate_key = match &client_ip {
+        Some(ip) => ip.clone(),
+        None => format!("tracking:{}", tracking_id),
+    };
+    if !PIXEL_RATE_LIMITER.check(&rate_key, Instant::now()) {
+        debug!("Tracking pixel rate limit exceeded for {}", rate_key);
+    } else if let Ok(tracking_uuid) = Uuid::parse_str(&tracking_id) {
+        let source = classify_user_agent(user_agent.as_deref());
         let conn = state.conn.clone();
-        let ip_clone = client_ip.clone();
-        let ua_clone = user_agent.clone();
 
-        let _ = tokio::task::spawn_blocking(move || {
-            update_email_read_status(conn, tracking_uuid, ip_clone, ua_clone)
+        match tokio::task::spawn_blocking(move || {
+            record_email_open(conn, tracking_uuid, client_ip, user_agent, source)
         })
-        .await;
-
-        info!(
-            "Email read tracked: tracking_id={}, ip={:?}",
-            tracking_id, client_ip
-        );
+        .await
+        {
+            Ok(Ok(true)) => info!("Email read tracked: tracking_id={}", tracking_id),
+            Ok(Ok(false)) => debug!(
+                "Email open not counted: tracking_id={}, source={}",
+                tracking_id,
+                source.as_str()
+            ),
+            Ok(Err(e)) => warn!("Failed to record email open: {}", e),
+            Err(e) => warn!("Email open task failed: {}", e),
+        }
     } else {
-        warn!("Invalid tracking ID received: {}", tracking_id);
+        debug!("Invalid tracking ID received: {}", tracking_id);
     }
 
     (
@@ -148,38 +245,73 @@ pub async fn serve_tracking_pixel(
     )
 }
 
-fn update_email_read_status(
+/// Stores the open event and, for the first human open of a recipient within the dedup
+/// window, updates the read status. Unknown tracking ids are ignored. Returns whether the
+/// open was counted.
+fn record_email_open(
     conn: crate::core::shared::utils::DbPool,
     tracking_id: Uuid,
     client_ip: Option<String>,
     user_agent: Option<String>,
-) -> Result<(), String> {
+    source: OpenSource,
+) -> Result<bool, String> {
     let mut db_conn = conn
         .get()
         .map_err(|e| format!("DB connection error: {}", e))?;
     let now = Utc::now();
 
-    diesel::sql_query(
-        r"UPDATE sent_email_tracking
-           SET
-               is_read = true,
-               read_count = read_count + 1,
-               read_at = COALESCE(read_at, $2),
-               first_read_ip = COALESCE(first_read_ip, $3),
-               last_read_ip = $3,
-               user_agent = COALESCE(user_agent, $4),
-               updated_at = $2
-           WHERE tracking_id = $1",
-    )
-    .bind::<diesel::sql_types::Uuid, _>(tracking_id)
-    .bind::<diesel::sql_types::Timestamptz, _>(now)
-    .bind::<diesel::sql_types::Nullable<diesel::sql_types::Text>, _>(client_ip.as_deref())
-    .bind::<diesel::sql_types::Nullable<diesel::sql_types::Text>, _>(user_agent.as_deref())
-    .execute(&mut db_conn)
-    .map_err(|e| format!("Failed to update tracking record: {}", e))?;
+    #[derive(QueryableByName)]
+    struct EventRow {
+        #[diesel(sql_type = diesel::sql_types::Bool)]
+        counted: bool,
+    }
 
-    debug!("Updated email read status: tracking_id={}", tracking_id);
-    Ok(())
+    db_conn
+        .transaction::<_, diesel::result::Error, _>(|tx| {
+            let event: Option<EventRow> = diesel::sql_query(
+                r"INSERT INTO email_open_events (tracking_id, opened_at, client_ip, user_agent, source, counted)
+                   SELECT t.tracking_id, $2, $3, $4, $5,
+                          $5 = 'human' AND NOT EXISTS (
+                              SELECT 1 FROM email_open_events e
+                              WHERE e.tracking_id = t.tracking_id AND e.counted
+                                AND e.opened_at > $2 - make_interval(mins => $6))
+                   FROM sent_email_tracking t
+                   WHERE t.tracking_id = $1
+                   FOR UPDATE OF t
+                   RETURNING counted",
+            )
+            .bind::<diesel::sql_types::Uuid, _>(tracking_id)
+            .bind::<diesel::sql_types::Timestamptz, _>(now)
+            .bind::<diesel::sql_types::Nullable<diesel::sql_types::Text>, _>(client_ip.as_deref())
+            .bind::<diesel::sql_types::Nullable<diesel::sql_types::Text>, _>(user_agent.as_deref())
+            .bind::<diesel::sql_types::Text, _>(source.as_str())
+            .bind::<diesel::sql_types::Integer, _>(OPEN_DEDUP_WINDOW_MINUTES)
+            .get_result(tx)
+            .optional()?;
+
+            let counted = event.is_some_and(|e| e.counted);
+            if counted {
+                diesel::sql_query(
+                    r"UPDATE sent_email_tracking
+                       SET
+                           is_read = true,
+                           read_count = read_count + 1,
+                           read_at = COALESCE(read_at, $2),
+                           first_read_ip = COALESCE(first_read_ip, $3),
+                           last_read_ip = $3,
+                           user_agent = COALESCE(user_agent, $4),
+                           updated_at = $2
+                       WHERE tracking_id = $1",
+                )
+                .bind::<diesel::sql_types::Uuid, _>(tracking_id)
+                .bind::<diesel::sql_types::Timestamptz, _>(now)
+                .bind::<diesel::sql_types::Nullable<diesel::sql_types::Text>, _>(client_ip.as_deref())
+                .bind::<diesel::sql_types::Nullable<diesel::sql_types::Text>, _>(user_agent.as_deref())
+                .execute(tx)?;
+            }
+            Ok(counted)
+        })
+        .map_err(|e| format!("Failed to record email open: {}", e))
 }
 
 pub async fn get_tracking_status(
@@ -187,13 +319,13 @@ pub async fn get_tracking_status(
     State(state): State<Arc<AppState>>,
 ) -> Result<Json<ApiResponse<TrackingStatusResponse>>, EmailError> {
     let tracking_uuid =
-        Uuid::parse_str(&tracking_id).map_err(|_| EmailError("Invalid tracking ID".to_string()))?;
+        Uuid::parse_str(&tracking_id).map_err(|_| EmailError::BadRequest("Invalid tracking ID".to_string()))?;
 
     let conn = state.conn.clone();
     let result = tokio::task::spawn_blocking(move || get_tracking_record(conn, tracking_uuid))
         .await
-        .map_err(|e| EmailError(format!("Task join error: {}", e)))?
-        .map_err(EmailError)?;
+        .map_err(|e| EmailError::Internal(format!("Task join error: {}", e)))?
+        .map_err(EmailError::Internal)?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -254,8 +386,8 @@ pub async fn list_sent_emails_tracking(
     let conn = state.conn.clone();
     let result = tokio::task::spawn_blocking(move || list_tracking_records(conn, query))
         .await
-        .map_err(|e| EmailError(format!("Task join error: {}", e)))?
-        .map_err(EmailError)?;
+        .map_err(|e| EmailError::Internal(format!("Task join error: {}", e)))?
+        .map_err(EmailError::Internal)?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -337,8 +469,8 @@ pub async fn get_tracking_stats(
     let conn = state.conn.clone();
     let result = tokio::task::spawn_blocking(move || calculate_tracking_stats(conn))
         .await
-        .map_err(|e| EmailError(format!("Task join error: {}", e)))?
-        .map_err(EmailError)?;
+        .map_err(|e| EmailError::Internal(format!("Task join error: {}", e)))?
+        .map_err(EmailError::Internal)?;
 
     Ok(Json(ApiResponse {
         success: true,
@@ -364,12 +496,20 @@ fn calculate_tracking_stats(
         avg_time_hours: Option<f64>,
     }
 
+    // Reads come from counted open events only, so proxy prefetches, bots and repeated
+    // opens do not inflate the read rate.
     let stats: StatsRow = diesel::sql_query(
         r"SELECT
                COUNT(*) as total_sent,
-               COUNT(*) FILTER (WHERE is_read = true) as total_read,
-               AVG(EXTRACT(EPOCH FROM (read_at - sent_at)) / 3600) FILTER (WHERE is_read = true) as avg_time_hours
-           FROM sent_email_tracking",
+               COUNT(o.first_open) as total_read,
+               AVG(EXTRACT(EPOCH FROM (o.first_open - t.sent_at)) / 3600) as avg_time_hours
+           FROM sent_email_tracking t
+           LEFT JOIN (
+               SELECT tracking_id, MIN(opened_at) as first_open
+               FROM email_open_events
+               WHERE counted
+               GROUP BY tracking_id
+           ) o ON o.tracking_id = t.tracking_id",
     )
     .get_result(&mut db_conn)
     .map_err(|e| format!("Stats query failed: {}", e))?;
@@ -423,3 +563,36 @@ pub async fn get_email(
         "message": "Please use the new /api/email/list endpoint with account_id"
     })))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_user_agent() {
+        let gmail = "Mozilla/5.0 (Windows NT 5.1; rv:11.0) Gecko Firefox/11.0 (via ggpht.com GoogleImageProxy)";
+        assert_eq!(classify_user_agent(Some(gmail)), OpenSource::Proxy);
+        assert_eq!(classify_user_agent(Some("YahooMailProxy; https://help.yahoo.com")), OpenSource::Proxy);
+        assert_eq!(classify_user_agent(Some("curl/8.4.0")), OpenSource::Bot);
+        assert_eq!(classify_user_agent(Some("Googlebot/2.1")), OpenSource::Bot);
+        assert_eq!(classify_user_agent(None), OpenSource::Bot);
+        assert_eq!(classify_user_agent(Some("  ")), OpenSource::Bot);
+
+        let thunderbird = "Mozilla/5.0 (X11; Linux x86_64; rv:115.0) Gecko/20100101 Thunderbird/115.3.1";
+        assert_eq!(classify_user_agent(Some(thunderbird)), OpenSource::Human);
+
+        let outlook = "Microsoft Office/16.0 (Windows NT 10.0; Microsoft Outlook 16.0.17029; Pro)";
+        assert_eq!(classify_user_agent(Some(outlook)), OpenSource::Human);
+    }
+
+    #[test]
+    fn test_pixel_rate_limiter() {
+        let limiter = PixelRateLimiter::new(2, Duration::from_secs(60));
+        let start = Instant::now();
+        assert!(limiter.check("1.2.3.4", start));
+        assert!(limiter.check("1.2.3.4", start));
+        assert!(!limiter.check("1.2.3.4", start));
+        assert!(limiter.check("5.6.7.8", start));
+        assert!(limiter.check("1.2.3.4", start + Duration::from_secs(61)));
+    }
+}
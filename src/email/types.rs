@@ -1,7 +1,5 @@
-use axum::{
-    http::StatusCode,
-    response::{IntoResponse, Response},
-};
+use crate::core::shared::api_error::ApiError;
+use axum::response::{IntoResponse, Response};
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel::sql_types::{Bool, Integer, Nullable, Text, Timestamptz, Uuid as DieselUuid, Varchar};
@@ -31,6 +29,8 @@ pub struct ImapCredentialsRow {
     pub username: String,
     #[diesel(sql_type = Text)]
     pub password_encrypted: String,
+    #[diesel(sql_type = Text)]
+    pub default_folder: String,
 }
 
 #[derive(Debug, QueryableByName)]
@@ -181,6 +181,14 @@ pub struct EmailAccountRequest {
     pub username: String,
     pub password: String,
     pub is_primary: bool,
+    /// Folder opened when a listing does not name one. Defaults to INBOX.
+    #[serde(default)]
+    pub default_folder: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DefaultFolderRequest {
+    pub folder: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -192,6 +200,7 @@ pub struct EmailAccountResponse {
     pub imap_port: u16,
     pub smtp_server: String,
     pub smtp_port: u16,
+    pub default_folder: String,
     pub is_primary: bool,
     pub is_active: bool,
     pub created_at: String,
@@ -234,6 +243,12 @@ pub struct SendEmailRequest {
     pub is_html: bool,
 }
 
+#[derive(Debug, Serialize)]
+pub struct QueuedEmailResponse {
+    pub message_id: Uuid,
+    pub status: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SaveDraftResponse {
     pub success: bool,
@@ -286,17 +301,44 @@ pub struct EmailSignature {
     pub is_default: bool,
 }
 
-pub struct EmailError(pub String);
+#[derive(Debug)]
+pub enum EmailError {
+    Unauthorized(String),
+    BadRequest(String),
+    Internal(String),
+}
+
+impl std::fmt::Display for EmailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unauthorized(msg)
+            | Self::BadRequest(msg)
+            | Self::Internal(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for EmailError {}
+
+impl From<EmailError> for ApiError {
+    fn from(err: EmailError) -> Self {
+        match err {
+            EmailError::Unauthorized(msg) => ApiError::unauthorized(msg),
+            EmailError::BadRequest(msg) => ApiError::bad_request(msg),
+            EmailError::Internal(msg) => ApiError::internal(msg),
+        }
+    }
+}
 
 impl IntoResponse for EmailError {
     fn into_response(self) -> Response {
-        (StatusCode::INTERNAL_SERVER_ERROR, self.0).into_response()
+        ApiError::from(self).into_response()
     }
 }
 
 impl From<String> for EmailError {
     fn from(s: String) -> Self {
-        Self(s)
+        Self::Internal(s)
     }
 }
 
@@ -6,6 +6,8 @@ use std::path::PathBuf;
 use tokio::fs;
 use uuid::Uuid;
 
+use crate::core::shared::llm_tls::llm_http_client;
+
 #[cfg(feature = "vectordb")]
 use std::sync::Arc;
 #[cfg(feature = "vectordb")]
@@ -448,7 +450,7 @@ impl EmailEmbeddingGenerator {
         use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
         use serde_json::json;
 
-        let client = reqwest::Client::new();
+        let client = llm_http_client("https://api.openai.com/v1/embeddings");
         let mut headers = HeaderMap::new();
         headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
         headers.insert(
@@ -489,7 +491,7 @@ impl EmailEmbeddingGenerator {
         // Truncate text to fit within token limit (600 tokens for safety under 768 limit)
         let truncated_text = crate::core::shared::utils::truncate_text_for_model(text, "sentence-transformers/all-MiniLM-L6-v2", 600);
 
-        let client = reqwest::Client::new();
+        let client = llm_http_client(embedding_url);
         let body = json!({
             "text": truncated_text,
             "model": "sentence-transformers/all-MiniLM-L6-v2"
@@ -1,9 +1,14 @@
 pub use crate::core::bot::channels::instagram::*;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
+use crate::security::channel_webhook::{
+    verify_meta_webhook, INSTAGRAM_APP_SECRET, META_SIGNATURE_HEADER,
+};
 use axum::{
+    body::Bytes,
     extract::{Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
@@ -50,8 +55,25 @@ async fn verify_webhook(Query(query): Query<WebhookVerifyQuery>) -> impl IntoRes
 
 async fn handle_webhook(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<InstagramWebhookPayload>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> impl IntoResponse {
+    let signature = headers
+        .get(META_SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok());
+    if let Err(e) = verify_meta_webhook(None, INSTAGRAM_APP_SECRET, &body, signature).await {
+        log::warn!("Rejected Instagram webhook: {}", e);
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let payload: InstagramWebhookPayload = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::error!("Failed to deserialize Instagram webhook: {}", e);
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
     for entry in payload.entry {
         if let Some(messaging_list) = entry.messaging {
             for messaging in messaging_list {
@@ -73,7 +95,7 @@ async fn handle_webhook(
 
 async fn send_message(
     State(_state): State<Arc<AppState>>,
-    Json(request): Json<serde_json::Value>,
+    ApiJson(request): ApiJson<serde_json::Value>,
 ) -> impl IntoResponse {
     let adapter = InstagramAdapter::new();
     let recipient = request.get("to").and_then(|v| v.as_str()).unwrap_or("");
@@ -1,6 +1,7 @@
 pub mod account_deletion;
 pub mod ui;
 
+use crate::core::shared::api_json::ApiJson;
 use axum::{
     extract::{Path, Query, State},
     response::IntoResponse,
@@ -444,7 +445,7 @@ fn get_default_cookie_policy() -> CookiePolicy {
 
 pub async fn handle_record_consent(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CookieConsentRequest>,
+    ApiJson(req): ApiJson<CookieConsentRequest>,
 ) -> Result<Json<CookieConsentResponse>, LegalError> {
     let pool = state.conn.clone();
 
@@ -551,7 +552,7 @@ pub async fn handle_get_consent_by_session(
 pub async fn handle_update_consent(
     State(state): State<Arc<AppState>>,
     Path(consent_id): Path<Uuid>,
-    Json(req): Json<CookieConsentRequest>,
+    ApiJson(req): ApiJson<CookieConsentRequest>,
 ) -> Result<Json<CookieConsent>, LegalError> {
     let pool = state.conn.clone();
 
@@ -689,7 +690,7 @@ pub async fn handle_get_document(
 
 pub async fn handle_create_document(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateDocumentRequest>,
+    ApiJson(req): ApiJson<CreateDocumentRequest>,
 ) -> Result<Json<LegalDocument>, LegalError> {
     let pool = state.conn.clone();
 
@@ -733,7 +734,7 @@ pub async fn handle_create_document(
 pub async fn handle_update_document(
     State(state): State<Arc<AppState>>,
     Path(slug): Path<String>,
-    Json(req): Json<UpdateDocumentRequest>,
+    ApiJson(req): ApiJson<UpdateDocumentRequest>,
 ) -> Result<Json<LegalDocument>, LegalError> {
     let pool = state.conn.clone();
 
@@ -793,7 +794,7 @@ pub async fn handle_update_document(
 pub async fn handle_request_data_deletion(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<Uuid>,
-    Json(req): Json<DataDeletionRequest>,
+    ApiJson(req): ApiJson<DataDeletionRequest>,
 ) -> Result<Json<DataDeletionResult>, LegalError> {
     let pool = state.conn.clone();
 
@@ -852,7 +853,7 @@ pub async fn handle_request_data_deletion(
 pub async fn handle_export_user_data(
     State(state): State<Arc<AppState>>,
     Path(user_id): Path<Uuid>,
-    Json(req): Json<DataExportRequest>,
+    ApiJson(req): ApiJson<DataExportRequest>,
 ) -> Result<Json<UserDataExport>, LegalError> {
     let pool = state.conn.clone();
 
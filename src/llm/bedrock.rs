@@ -4,6 +4,7 @@ use log::{error, info, warn};
 use serde_json::Value;
 use tokio::sync::mpsc;
 
+use crate::core::shared::llm_tls::llm_http_client;
 use crate::llm::LLMProvider;
 
 #[derive(Debug)]
@@ -26,7 +27,7 @@ impl BedrockClient {
         };
 
         Self {
-            client: reqwest::Client::new(),
+            client: llm_http_client(&url),
             base_url: url,
         }
     }
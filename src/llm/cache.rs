@@ -4,6 +4,8 @@ use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
@@ -11,8 +13,23 @@ use uuid::Uuid;
 
 use super::LLMProvider;
 use crate::core::config::ConfigManager;
+use crate::core::shared::circuit_breaker::{note_redis_result, redis_connection, CircuitBreaker, REDIS_BREAKER};
 use crate::core::shared::utils::{estimate_token_count, DbPool};
 
+tokio::task_local! {
+    static CACHE_HIT: Arc<AtomicBool>;
+}
+
+/// Runs `work`, setting `hit` when a response inside it is served from the cache instead
+/// of the model, so callers can tell a hit from the lookup that produced it.
+pub async fn with_cache_hit_flag<F: Future>(hit: Arc<AtomicBool>, work: F) -> F::Output {
+    CACHE_HIT.scope(hit, work).await
+}
+
+fn note_cache_hit() {
+    let _ = CACHE_HIT.try_with(|hit| hit.store(true, Ordering::SeqCst));
+}
+
 #[derive(Clone, Debug)]
 
 pub struct CacheConfig {
@@ -55,6 +72,10 @@ pub struct CachedResponse {
     pub hit_count: u32,
 
     pub embedding: Option<Vec<f32>>,
+
+    /// Model `embedding` came from; entries from another model are never compared.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
 }
 
 impl std::fmt::Debug for CachedLLMProvider {
@@ -62,6 +83,7 @@ impl std::fmt::Debug for CachedLLMProvider {
         f.debug_struct("CachedLLMProvider")
             .field("provider", &"<dyn LLMProvider>")
             .field("cache", &self.cache)
+            .field("breaker", &self.breaker)
             .field("config", &self.config)
             .field("embedding_service", &self.embedding_service.is_some())
             .field("db_pool", &self.db_pool.is_some())
@@ -74,6 +96,9 @@ pub struct CachedLLMProvider {
 
     cache: Arc<redis::Client>,
 
+    /// Skips Redis entirely while it is unreachable; requests then go straight to `provider`.
+    breaker: Arc<CircuitBreaker>,
+
     config: CacheConfig,
 
     embedding_service: Option<Arc<dyn EmbeddingService>>,
@@ -89,6 +114,24 @@ pub trait EmbeddingService: Send + Sync {
         text: &str,
     ) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>>;
     async fn compute_similarity(&self, embedding1: &[f32], embedding2: &[f32]) -> f32;
+
+    /// Model the embeddings come from, if known.
+    fn model_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Embeddings for several texts, in input order. Services that can embed many texts
+    /// per request should override this; the default embeds them one by one.
+    async fn embed_batch(
+        &self,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in &texts {
+            embeddings.push(self.get_embedding(text).await?);
+        }
+        Ok(embeddings)
+    }
 }
 
 impl CachedLLMProvider {
@@ -107,6 +150,7 @@ impl CachedLLMProvider {
         Self {
             provider,
             cache,
+            breaker: REDIS_BREAKER.clone(),
             config,
             embedding_service,
             db_pool: None,
@@ -129,12 +173,22 @@ impl CachedLLMProvider {
         Self {
             provider,
             cache,
+            breaker: REDIS_BREAKER.clone(),
             config,
             embedding_service,
             db_pool: Some(db_pool),
         }
     }
 
+    pub fn with_breaker(mut self, breaker: Arc<CircuitBreaker>) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    async fn connection(&self) -> Option<redis::aio::MultiplexedConnection> {
+        redis_connection(&self.cache, &self.breaker).await
+    }
+
     fn generate_cache_key(&self, prompt: &str, messages: &Value, model: &str) -> String {
         let mut hasher = Sha256::new();
         hasher.update(prompt.as_bytes());
@@ -165,12 +219,8 @@ impl CachedLLMProvider {
             return cache_enabled.to_lowercase() == "true";
         }
 
-        let mut conn = match self.cache.get_multiplexed_async_connection().await {
-            Ok(conn) => conn,
-            Err(e) => {
-                debug!("Cache connection failed: {}", e);
-                return self.config.semantic_matching;
-            }
+        let Some(mut conn) = self.connection().await else {
+            return self.config.semantic_matching;
         };
 
         let config_key = format!("bot_config:{}:llm-cache", bot_id);
@@ -239,13 +289,7 @@ impl CachedLLMProvider {
         messages: &Value,
         model: &str,
     ) -> Option<CachedResponse> {
-        let mut conn = match self.cache.get_multiplexed_async_connection().await {
-            Ok(conn) => conn,
-            Err(e) => {
-                debug!("Failed to connect to cache: {}", e);
-                return None;
-            }
-        };
+        let mut conn = self.connection().await?;
 
         let actual_messages = if messages.get("messages").is_some() {
             messages.get("messages").unwrap_or(messages)
@@ -255,7 +299,9 @@ impl CachedLLMProvider {
 
         let cache_key = self.generate_cache_key(prompt, actual_messages, model);
 
-        if let Ok(cached_json) = conn.get::<_, String>(&cache_key).await {
+        let lookup = conn.get::<_, String>(&cache_key).await;
+        note_redis_result(&self.breaker, &lookup);
+        if let Ok(cached_json) = lookup {
             if let Ok(mut cached) = serde_json::from_str::<CachedResponse>(&cached_json) {
                 cached.hit_count += 1;
                 let _ = conn
@@ -336,7 +382,7 @@ impl CachedLLMProvider {
         debug!(
             "Embedding request text (len={}, using latest user question): {}",
             semantic_query.len(),
-            &semantic_query.chars().take(200).collect::<String>()
+            super::redaction::for_log(&semantic_query.chars().take(200).collect::<String>())
         );
 
         let prompt_embedding = match embedding_service.get_embedding(&semantic_query).await {
@@ -347,16 +393,12 @@ impl CachedLLMProvider {
             }
         };
 
-        let mut conn = match self.cache.get_multiplexed_async_connection().await {
-            Ok(conn) => conn,
-            Err(e) => {
-                debug!("Failed to connect to cache for semantic search: {}", e);
-                return None;
-            }
-        };
+        let mut conn = self.connection().await?;
 
         let pattern = format!("{}:{}:*", self.config.key_prefix, model);
-        let keys: Vec<String> = match conn.keys(pattern).await {
+        let keys: redis::RedisResult<Vec<String>> = conn.keys(pattern).await;
+        note_redis_result(&self.breaker, &keys);
+        let keys = match keys {
             Ok(k) => k,
             Err(e) => {
                 debug!("Failed to get cache keys: {}", e);
@@ -370,6 +412,9 @@ impl CachedLLMProvider {
         for key in keys.iter().take(check_limit) {
             if let Ok(cached_json) = conn.get::<_, String>(key).await {
                 if let Ok(cached) = serde_json::from_str::<CachedResponse>(&cached_json) {
+                    if cached.embedding_model.as_deref() != embedding_service.model_name() {
+                        continue;
+                    }
                     if let Some(ref cached_embedding) = cached.embedding {
                         let similarity = embedding_service
                             .compute_similarity(&prompt_embedding, cached_embedding)
@@ -413,12 +458,8 @@ impl CachedLLMProvider {
 
         let cache_key = self.generate_cache_key(prompt, actual_messages, model);
 
-        let mut conn = match self.cache.get_multiplexed_async_connection().await {
-            Ok(conn) => conn,
-            Err(e) => {
-                debug!("Failed to connect to cache for storing: {}", e);
-                return;
-            }
+        let Some(mut conn) = self.connection().await else {
+            return;
         };
 
         let embedding = if let Some(ref service) = self.embedding_service {
@@ -460,15 +501,22 @@ impl CachedLLMProvider {
                 .unwrap_or_default()
                 .as_secs(),
             hit_count: 0,
+            embedding_model: embedding.as_ref().and_then(|_| {
+                self.embedding_service
+                    .as_ref()
+                    .and_then(|service| service.model_name())
+                    .map(str::to_string)
+            }),
             embedding,
         };
 
         match serde_json::to_string(&cached_response) {
             Ok(json) => {
-                if let Err(e) = conn
+                let stored = conn
                     .set_ex::<_, _, ()>(&cache_key, json, self.config.ttl)
-                    .await
-                {
+                    .await;
+                note_redis_result(&self.breaker, &stored);
+                if let Err(e) = stored {
                     debug!("Failed to cache response: {}", e);
                 } else {
                     trace!(
@@ -570,6 +618,7 @@ impl LLMProvider for CachedLLMProvider {
 
         if let Some(cached) = self.get_cached_response(prompt, messages, model).await {
             info!("Cache hit (exact match) for bot {}", bot_id);
+            note_cache_hit();
             return Ok(cached.response);
         }
 
@@ -579,6 +628,7 @@ impl LLMProvider for CachedLLMProvider {
                     "Cache hit (semantic match) for bot {} with similarity threshold {}",
                     bot_id, bot_cache_config.similarity_threshold
                 );
+                note_cache_hit();
                 return Ok(cached.response);
             }
         }
@@ -611,6 +661,7 @@ impl LLMProvider for CachedLLMProvider {
         }
 
         if let Some(cached) = self.get_cached_response(prompt, messages, model).await {
+            note_cache_hit();
             for chunk in cached.response.chars().collect::<Vec<_>>().chunks(50) {
                 let chunk_str: String = chunk.iter().collect();
                 if tx.send(chunk_str).await.is_err() {
@@ -652,23 +703,105 @@ impl LLMProvider for CachedLLMProvider {
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         self.provider.cancel_job(session_id).await
     }
+
+    /// Not cached: structured extractions depend on state outside the prompt.
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        messages: &Value,
+        model: &str,
+        key: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        self.provider.generate_json(prompt, messages, model, key).await
+    }
 }
 
 #[derive(Debug)]
 
 pub struct LocalEmbeddingService {
+    client: reqwest::Client,
     embedding_url: String,
     model: String,
     api_key: Option<String>,
+    /// Answer with a hash-based vector when the service keeps failing, instead of an error.
+    hash_fallback: bool,
 }
 
+/// Texts sent per request by [`LocalEmbeddingService::embed_batch`].
+const EMBED_BATCH_SIZE: usize = 32;
+
 impl LocalEmbeddingService {
     pub fn new(embedding_url: String, model: String, api_key: Option<String>) -> Self {
         Self {
+            client: crate::core::shared::llm_tls::llm_http_client(&embedding_url),
             embedding_url,
             model,
             api_key,
+            hash_fallback: true,
+        }
+    }
+
+    /// Fails instead of answering with a hash-based vector, for callers that store the
+    /// vectors, such as KB indexing.
+    pub fn without_hash_fallback(mut self) -> Self {
+        self.hash_fallback = false;
+        self
+    }
+
+    /// HuggingFace Inference takes `{"inputs": text}`; its OpenAI-compatible routes
+    /// (`/v1/`) do not.
+    fn is_hf_inference(&self) -> bool {
+        let url = &self.embedding_url;
+        url.contains("/hf-inference/")
+            || url.contains("/pipeline/feature-extraction")
+            || (url.contains("huggingface.co") && !url.contains("/v1/"))
+    }
+
+    fn endpoint_url(&self) -> String {
+        // Determine if URL already includes endpoint path
+        if self.embedding_url.contains("/pipeline/")
+            || self.embedding_url.contains("/v1/")
+            || self.embedding_url.contains("/ai/run/")
+            || self.embedding_url.ends_with("/embeddings")
+        {
+            self.embedding_url.clone()
+        } else {
+            format!("{}/embedding", self.embedding_url)
+        }
+    }
+
+    /// HuggingFace and Cloudflare endpoints use their own request formats; everything else
+    /// is OpenAI-compatible and accepts a list of inputs.
+    fn supports_batch(&self) -> bool {
+        !self.is_hf_inference() && !self.embedding_url.contains("/ai/run/")
+    }
+
+    /// One OpenAI-style batch request. Entries missing from the response are `None`.
+    async fn request_batch(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+        texts: &[String],
+    ) -> Result<Vec<Option<Vec<f32>>>, String> {
+        let mut request = client.post(url);
+        if let Some(ref api_key) = self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+        let response = request
+            .json(&serde_json::json!({
+                "input": texts,
+                "model": self.model,
+            }))
+            .timeout(std::time::Duration::from_secs(60))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("HTTP {}", status));
         }
+        let body: Value = response.json().await.map_err(|e| e.to_string())?;
+        Ok(parse_batch_response(&body, texts.len()))
     }
 
     /// Generate a deterministic hash-based embedding for fallback
@@ -703,20 +836,11 @@ impl EmbeddingService for LocalEmbeddingService {
         &self,
         text: &str,
     ) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>> {
-        let client = reqwest::Client::new();
-
-        // Determine if URL already includes endpoint path
-        let url = if self.embedding_url.contains("/pipeline/") ||
-                   self.embedding_url.contains("/v1/") ||
-                   self.embedding_url.contains("/ai/run/") ||
-                   self.embedding_url.ends_with("/embeddings") {
-            self.embedding_url.clone()
-        } else {
-            format!("{}/embedding", self.embedding_url)
-        };
+        let client = &self.client;
+        let url = self.endpoint_url();
 
         // Determine request body format based on URL
-        let request_body = if self.embedding_url.contains("huggingface.co") {
+        let request_body = if self.is_hf_inference() {
             serde_json::json!({
                 "inputs": text,
             })
@@ -854,11 +978,19 @@ impl EmbeddingService for LocalEmbeddingService {
             }
         }
 
+        if !self.hash_fallback {
+            return Err(format!("Embedding service failed after {} attempts", MAX_RETRIES).into());
+        }
+
         // All retries exhausted - use hash-based fallback
         debug!("Embedding service failed after all retries, using hash-based fallback");
         Ok(self.hash_embedding(text))
     }
 
+    fn model_name(&self) -> Option<&str> {
+        Some(&self.model)
+    }
+
     async fn compute_similarity(&self, embedding1: &[f32], embedding2: &[f32]) -> f32 {
         if embedding1.len() != embedding2.len() {
             return 0.0;
@@ -879,4 +1011,262 @@ impl EmbeddingService for LocalEmbeddingService {
 
         dot_product / (norm1 * norm2)
     }
+
+    /// Sends the texts in batches of [`EMBED_BATCH_SIZE`]. Texts a batch request did not
+    /// return, because the request failed or the response skipped them, are retried one
+    /// by one through [`Self::get_embedding`].
+    async fn embed_batch(
+        &self,
+        texts: Vec<String>,
+    ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut embeddings: Vec<Option<Vec<f32>>> = vec![None; texts.len()];
+        if self.supports_batch() {
+            let url = self.endpoint_url();
+            for (batch_idx, batch) in texts.chunks(EMBED_BATCH_SIZE).enumerate() {
+                match self.request_batch(&self.client, &url, batch).await {
+                    Ok(vectors) => {
+                        let offset = batch_idx * EMBED_BATCH_SIZE;
+                        for (i, vector) in vectors.into_iter().enumerate() {
+                            embeddings[offset + i] = vector;
+                        }
+                    }
+                    Err(e) => debug!(
+                        "Embedding batch of {} texts failed, retrying individually: {}",
+                        batch.len(),
+                        e
+                    ),
+                }
+            }
+        }
+
+        let missing = embeddings.iter().filter(|e| e.is_none()).count();
+        if missing > 0 && self.supports_batch() {
+            debug!(
+                "Retrying {} of {} embeddings individually",
+                missing,
+                texts.len()
+            );
+        }
+        let mut result = Vec::with_capacity(texts.len());
+        for (text, embedding) in texts.iter().zip(embeddings) {
+            match embedding {
+                Some(embedding) => result.push(embedding),
+                None => result.push(self.get_embedding(text).await?),
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// Vectors of a batch response, in input order. Understands OpenAI-style
+/// `{"data": [{"index", "embedding"}]}` (placed by `index`, or position when absent),
+/// llama.cpp `[{"embedding": [[...]]}]`, plain `[[...]]` and `{"embeddings": [[...]]}`.
+/// Slots without a usable vector stay `None`.
+fn parse_batch_response(body: &Value, expected: usize) -> Vec<Option<Vec<f32>>> {
+    fn floats(value: &Value) -> Vec<f32> {
+        value
+            .as_array()
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(|v| v.as_f64().map(|f| f as f32))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    let mut vectors = vec![None; expected];
+    let entries = body
+        .get("data")
+        .or_else(|| body.get("embeddings"))
+        .unwrap_or(body);
+    let Some(entries) = entries.as_array() else {
+        return vectors;
+    };
+    for (position, entry) in entries.iter().enumerate() {
+        let index = entry
+            .get("index")
+            .and_then(Value::as_u64)
+            .map_or(position, |i| i as usize);
+        let vector = match entry.get("embedding") {
+            // llama.cpp nests the vector one level deeper
+            Some(embedding) => match embedding.get(0) {
+                Some(inner) if inner.is_array() => floats(inner),
+                _ => floats(embedding),
+            },
+            None => floats(entry),
+        };
+        if let Some(slot) = vectors.get_mut(index) {
+            if !vector.is_empty() {
+                *slot = Some(vector);
+            }
+        }
+    }
+    vectors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[derive(Debug, Default)]
+    struct CountingProvider {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMProvider for CountingProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _config: &Value,
+            _model: &str,
+            _key: &str,
+        ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok("fresh".to_string())
+        }
+
+        async fn generate_stream(
+            &self,
+            _prompt: &str,
+            _config: &Value,
+            tx: mpsc::Sender<String>,
+            _model: &str,
+            _key: &str,
+            _tools: Option<&Vec<Value>>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tx.send("fresh".to_string()).await?;
+            Ok(())
+        }
+
+        async fn cancel_job(&self, _session_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_redis_bypasses_cache() {
+        let provider = Arc::new(CountingProvider::default());
+        // Nothing listens on port 1, so every connection attempt is refused.
+        let client = Arc::new(redis::Client::open("redis://127.0.0.1:1/").unwrap());
+        let breaker = Arc::new(CircuitBreaker::new("Redis", 3, Duration::from_secs(60)));
+        let cached = CachedLLMProvider::new(provider.clone(), client, CacheConfig::default(), None)
+            .with_breaker(breaker.clone());
+        let messages = serde_json::json!([{"role": "user", "content": "hi"}]);
+
+        for _ in 0..5 {
+            let response = cached.generate("prompt", &messages, "model", "key").await.unwrap();
+            assert_eq!(response, "fresh");
+        }
+        assert!(breaker.is_open());
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 5);
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let hit = Arc::new(AtomicBool::new(false));
+        with_cache_hit_flag(
+            Arc::clone(&hit),
+            cached.generate_stream("prompt", &messages, tx, "model", "key", None),
+        )
+        .await
+        .unwrap();
+        assert_eq!(rx.recv().await.as_deref(), Some("fresh"));
+        assert!(!hit.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_flag_is_scoped_to_its_work() {
+        let hit = Arc::new(AtomicBool::new(false));
+        note_cache_hit();
+        with_cache_hit_flag(Arc::clone(&hit), async {}).await;
+        assert!(!hit.load(Ordering::SeqCst));
+
+        with_cache_hit_flag(Arc::clone(&hit), async { note_cache_hit() }).await;
+        assert!(hit.load(Ordering::SeqCst));
+    }
+
+    fn fake_vector(text: &str) -> Vec<f32> {
+        let sum: u32 = text.bytes().map(u32::from).sum();
+        vec![text.len() as f32, (sum % 97) as f32, 1.0]
+    }
+
+    /// OpenAI-style embedding server that leaves texts containing "flaky" out of batch
+    /// responses, and counts requests.
+    async fn spawn_embedding_server() -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let app = axum::Router::new().route(
+            "/embedding",
+            axum::routing::post(move |axum::Json(body): axum::Json<Value>| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    let data: Vec<Value> = match &body["input"] {
+                        Value::String(text) => {
+                            vec![serde_json::json!({"index": 0, "embedding": fake_vector(text)})]
+                        }
+                        Value::Array(texts) => texts
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(i, t)| {
+                                let text = t.as_str()?;
+                                (!text.contains("flaky")).then(|| {
+                                    serde_json::json!({"index": i, "embedding": fake_vector(text)})
+                                })
+                            })
+                            .collect(),
+                        _ => vec![],
+                    };
+                    axum::Json(serde_json::json!({ "data": data }))
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}", addr), requests)
+    }
+
+    #[tokio::test]
+    async fn test_batch_matches_single_embeddings() {
+        let (url, requests) = spawn_embedding_server().await;
+        let service = LocalEmbeddingService::new(url, "test-model".to_string(), None);
+        let texts: Vec<String> = (0..40)
+            .map(|i| {
+                if i == 7 {
+                    "a flaky chunk".to_string()
+                } else {
+                    format!("chunk number {i}")
+                }
+            })
+            .collect();
+
+        let batched = service.embed_batch(texts.clone()).await.unwrap();
+        // Two batch requests (32 + 8 texts) plus one retry for the skipped text.
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        for (text, vector) in texts.iter().zip(&batched) {
+            assert_eq!(vector, &service.get_embedding(text).await.unwrap());
+            assert_eq!(vector, &fake_vector(text));
+        }
+    }
+
+    #[test]
+    fn test_parse_batch_response_formats() {
+        let llama = serde_json::json!([
+            {"index": 1, "embedding": [[0.5, 0.25]]},
+            {"index": 0, "embedding": [[1.0, 2.0]]}
+        ]);
+        assert_eq!(
+            parse_batch_response(&llama, 2),
+            vec![Some(vec![1.0, 2.0]), Some(vec![0.5, 0.25])]
+        );
+        let plain = serde_json::json!({"embeddings": [[1.0], []]});
+        assert_eq!(parse_batch_response(&plain, 2), vec![Some(vec![1.0]), None]);
+    }
 }
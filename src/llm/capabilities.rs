@@ -0,0 +1,562 @@
+//! Capability detection for OpenAI-compatible endpoints.
+//!
+//! Not every server behind `OpenAIClient` streams, accepts `tools` or serves embeddings;
+//! small local servers often do none of these. The first request to an endpoint probes
+//! it: `GET /models` is read for advertised capabilities, and whatever it does not state
+//! is tried with a one-token request. Results are cached per endpoint and model so later
+//! requests only pick the request shape. Only a definitive refusal (400, 404 or 501)
+//! marks a capability missing; a probe that times out or fails otherwise assumes it and
+//! is repeated soon.
+
+use crate::core::shared::api_error::ApiError;
+use crate::core::shared::soft_delete::require_admin;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use axum::{routing::get, Json, Router};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+
+/// How long a successful probe is trusted before the endpoint is probed again.
+pub const CAPABILITY_TTL: Duration = Duration::from_secs(3600);
+
+/// Retry delay after a probe that could not reach the endpoint, or got no definitive answer.
+pub const UNREACHABLE_RETRY: Duration = Duration::from_secs(60);
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderCapabilities {
+    pub streaming: bool,
+    pub tools: bool,
+    pub embeddings: bool,
+    /// Model ids listed by `GET /models`; empty when the endpoint does not list them.
+    pub models: Vec<String>,
+    pub detected_at: DateTime<Utc>,
+    /// Set when the endpoint could not be probed; every capability is then assumed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probe_error: Option<String>,
+}
+
+impl ProviderCapabilities {
+    /// Capabilities assumed for an endpoint that could not be probed, matching how
+    /// requests were built before detection existed.
+    fn assumed(probe_error: String) -> Self {
+        Self {
+            streaming: true,
+            tools: true,
+            embeddings: true,
+            models: Vec::new(),
+            detected_at: Utc::now(),
+            probe_error: Some(probe_error),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointCapabilities {
+    pub endpoint: String,
+    pub model: String,
+    #[serde(flatten)]
+    pub capabilities: ProviderCapabilities,
+}
+
+struct CachedCapabilities {
+    capabilities: ProviderCapabilities,
+    expires_at: Instant,
+}
+
+/// Keyed by API root and model: one endpoint may serve models with different support.
+type CapabilityCache = RwLock<HashMap<(String, String), CachedCapabilities>>;
+
+static CAPABILITIES: OnceLock<CapabilityCache> = OnceLock::new();
+
+fn cache() -> &'static CapabilityCache {
+    CAPABILITIES.get_or_init(Default::default)
+}
+
+/// API root of a chat completions URL: `https://host/v1/chat/completions` → `https://host/v1`.
+pub fn api_root(chat_url: &str) -> String {
+    let trimmed = chat_url.trim_end_matches('/');
+    trimmed
+        .strip_suffix("/chat/completions")
+        .unwrap_or(trimmed)
+        .to_string()
+}
+
+/// Capabilities one `/models` entry states. `capabilities` may be an object of flags
+/// (`{"streaming": false}`) or a list of names (`["completion", "tools"]`); a list
+/// cannot say streaming is missing, so it leaves streaming unknown.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Advertised {
+    streaming: Option<bool>,
+    tools: Option<bool>,
+    embeddings: Option<bool>,
+}
+
+fn advertised(entry: &Value) -> Advertised {
+    match &entry["capabilities"] {
+        Value::Object(flags) => {
+            let flag = |names: &[&str]| names.iter().find_map(|n| flags.get(*n)?.as_bool());
+            Advertised {
+                streaming: flag(&["streaming", "stream"]),
+                tools: flag(&["tools", "function_calling"]),
+                embeddings: flag(&["embeddings", "embedding"]),
+            }
+        }
+        Value::Array(names) => {
+            let has = |name: &str| names.iter().any(|n| n.as_str() == Some(name));
+            Advertised {
+                streaming: has("streaming").then_some(true),
+                tools: Some(has("tools")),
+                embeddings: Some(has("embedding") || has("embeddings")),
+            }
+        }
+        _ => Advertised::default(),
+    }
+}
+
+/// Reads `GET /models`: the listed ids and what the entry for `model` (or the only
+/// entry) advertises.
+fn parse_models(body: &Value, model: &str) -> (Vec<String>, Advertised) {
+    let entries = body["data"]
+        .as_array()
+        .or_else(|| body["models"].as_array())
+        .cloned()
+        .unwrap_or_default();
+    let ids = entries
+        .iter()
+        .filter_map(|e| e["id"].as_str().or_else(|| e["name"].as_str()))
+        .map(str::to_string)
+        .collect();
+    let entry = entries
+        .iter()
+        .find(|e| e["id"].as_str() == Some(model) || e["name"].as_str() == Some(model))
+        .or_else(|| (entries.len() == 1).then(|| &entries[0]));
+    (ids, entry.map(advertised).unwrap_or_default())
+}
+
+/// Cached capabilities of `model` on the endpoint serving `chat_url`, probing it when
+/// there are none or they expired. Concurrent first requests may each probe; the last
+/// result wins.
+pub async fn detect(
+    client: &reqwest::Client,
+    chat_url: &str,
+    model: &str,
+    key: &str,
+) -> ProviderCapabilities {
+    let cache_key = (api_root(chat_url), model.to_string());
+    if let Some(cached) = cached(&cache_key) {
+        return cached;
+    }
+
+    let capabilities = probe(client, chat_url, &cache_key.0, model, key).await;
+    let ttl = if capabilities.probe_error.is_some() {
+        UNREACHABLE_RETRY
+    } else {
+        CAPABILITY_TTL
+    };
+    info!(
+        "LLM endpoint {} ({}): streaming={}, tools={}, embeddings={}",
+        cache_key.0, model, capabilities.streaming, capabilities.tools, capabilities.embeddings
+    );
+    if let Ok(mut cache) = cache().write() {
+        cache.insert(
+            cache_key,
+            CachedCapabilities {
+                capabilities: capabilities.clone(),
+                expires_at: Instant::now() + ttl,
+            },
+        );
+    }
+    capabilities
+}
+
+fn cached(key: &(String, String)) -> Option<ProviderCapabilities> {
+    let cache = cache().read().ok()?;
+    let entry = cache.get(key)?;
+    (entry.expires_at > Instant::now()).then(|| entry.capabilities.clone())
+}
+
+/// Every endpoint and model probed so far, including expired results.
+pub fn detected() -> Vec<EndpointCapabilities> {
+    let Ok(cache) = cache().read() else {
+        return Vec::new();
+    };
+    let mut endpoints: Vec<_> = cache
+        .iter()
+        .map(|((endpoint, model), entry)| EndpointCapabilities {
+            endpoint: endpoint.clone(),
+            model: model.clone(),
+            capabilities: entry.capabilities.clone(),
+        })
+        .collect();
+    endpoints.sort_by(|a, b| (&a.endpoint, &a.model).cmp(&(&b.endpoint, &b.model)));
+    endpoints
+}
+
+/// What a probe request learned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    Supported,
+    /// The endpoint refused the request shape: 400, 404 or 501, or a stream request
+    /// answered without server-sent events.
+    Unsupported,
+    /// Timeout, network error or any other status; nothing can be concluded.
+    Inconclusive,
+}
+
+impl Probe {
+    fn from_status(status: reqwest::StatusCode) -> Self {
+        match status.as_u16() {
+            _ if status.is_success() => Self::Supported,
+            400 | 404 | 501 => Self::Unsupported,
+            _ => Self::Inconclusive,
+        }
+    }
+
+    /// Inconclusive probes assume support, as before detection existed.
+    fn supported(self) -> bool {
+        self != Self::Unsupported
+    }
+}
+
+fn from_advertised(advertised: Option<bool>) -> Option<Probe> {
+    advertised.map(|supported| {
+        if supported {
+            Probe::Supported
+        } else {
+            Probe::Unsupported
+        }
+    })
+}
+
+async fn probe(
+    client: &reqwest::Client,
+    chat_url: &str,
+    root: &str,
+    model: &str,
+    key: &str,
+) -> ProviderCapabilities {
+    let auth = format!("Bearer {}", key);
+    let models_response = client
+        .get(format!("{}/models", root))
+        .header("Authorization", &auth)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await;
+    let (models, advertised) = match models_response {
+        Ok(response) if response.status().is_success() => {
+            let body = response.json::<Value>().await.unwrap_or(Value::Null);
+            parse_models(&body, model)
+        }
+        Ok(_) => (Vec::new(), Advertised::default()),
+        Err(e) => {
+            warn!("LLM endpoint {} could not be probed: {}", root, e);
+            return ProviderCapabilities::assumed(e.to_string());
+        }
+    };
+
+    let embedding_model = models
+        .iter()
+        .find(|m| m.contains("embed"))
+        .map(String::as_str);
+    let (streaming, tools, embeddings) = tokio::join!(
+        async {
+            match from_advertised(advertised.streaming) {
+                Some(probe) => probe,
+                None => probe_streaming(client, chat_url, &auth, model).await,
+            }
+        },
+        async {
+            match from_advertised(advertised.tools) {
+                Some(probe) => probe,
+                None => probe_tools(client, chat_url, &auth, model).await,
+            }
+        },
+        async {
+            match from_advertised(advertised.embeddings) {
+                Some(probe) => probe,
+                None => probe_embeddings(client, root, &auth, embedding_model).await,
+            }
+        },
+    );
+
+    let probes = [
+        ("streaming", streaming),
+        ("tools", tools),
+        ("embeddings", embeddings),
+    ];
+    let inconclusive: Vec<&str> = probes
+        .into_iter()
+        .filter(|(_, probe)| *probe == Probe::Inconclusive)
+        .map(|(name, _)| name)
+        .collect();
+
+    ProviderCapabilities {
+        streaming: streaming.supported(),
+        tools: tools.supported(),
+        embeddings: embeddings.supported(),
+        models,
+        detected_at: Utc::now(),
+        probe_error: (!inconclusive.is_empty())
+            .then(|| format!("inconclusive probes: {}", inconclusive.join(", "))),
+    }
+}
+
+async fn probe_request(
+    client: &reqwest::Client,
+    url: &str,
+    auth: &str,
+    body: &Value,
+) -> Result<reqwest::Response, Probe> {
+    let response = client
+        .post(url)
+        .header("Authorization", auth)
+        .timeout(PROBE_TIMEOUT)
+        .json(body)
+        .send()
+        .await
+        .map_err(|_| Probe::Inconclusive)?;
+    match Probe::from_status(response.status()) {
+        Probe::Supported => Ok(response),
+        probe => Err(probe),
+    }
+}
+
+/// Streaming works when a `stream: true` request is answered with server-sent events.
+async fn probe_streaming(
+    client: &reqwest::Client,
+    chat_url: &str,
+    auth: &str,
+    model: &str,
+) -> Probe {
+    let body = json!({
+        "model": model,
+        "messages": [{"role": "user", "content": "ping"}],
+        "stream": true,
+        "max_tokens": 1
+    });
+    match probe_request(client, chat_url, auth, &body).await {
+        Ok(response) => {
+            let event_stream = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.contains("text/event-stream"));
+            if event_stream {
+                Probe::Supported
+            } else {
+                Probe::Unsupported
+            }
+        }
+        Err(probe) => probe,
+    }
+}
+
+async fn probe_tools(client: &reqwest::Client, chat_url: &str, auth: &str, model: &str) -> Probe {
+    let body = json!({
+        "model": model,
+        "messages": [{"role": "user", "content": "ping"}],
+        "stream": false,
+        "max_tokens": 1,
+        "tools": [{
+            "type": "function",
+            "function": {
+                "name": "ping",
+                "description": "Capability probe",
+                "parameters": {"type": "object", "properties": {}}
+            }
+        }]
+    });
+    match probe_request(client, chat_url, auth, &body).await {
+        Ok(_) => Probe::Supported,
+        Err(probe) => probe,
+    }
+}
+
+/// Sent with an embedding model from the listing, never the chat model, which a server
+/// would rightly refuse. Without one, a 400 only says the request lacked a model: the
+/// route exists.
+async fn probe_embeddings(
+    client: &reqwest::Client,
+    root: &str,
+    auth: &str,
+    embedding_model: Option<&str>,
+) -> Probe {
+    let body = match embedding_model {
+        Some(model) => json!({"model": model, "input": "ping"}),
+        None => json!({"input": "ping"}),
+    };
+    let response = client
+        .post(format!("{}/embeddings", root))
+        .header("Authorization", auth)
+        .timeout(PROBE_TIMEOUT)
+        .json(&body)
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status() == reqwest::StatusCode::BAD_REQUEST => {
+            if embedding_model.is_some() {
+                Probe::Unsupported
+            } else {
+                Probe::Supported
+            }
+        }
+        Ok(response) => Probe::from_status(response.status()),
+        Err(_) => Probe::Inconclusive,
+    }
+}
+
+pub async fn handle_llm_capabilities(
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<EndpointCapabilities>>, ApiError> {
+    require_admin(&user)?;
+    Ok(Json(detected()))
+}
+
+pub fn configure() -> Router<Arc<AppState>> {
+    Router::new().route("/api/admin/llm/capabilities", get(handle_llm_capabilities))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LLMProvider, OpenAIClient};
+    use tokio::sync::mpsc;
+
+    /// Chat server that advertises no streaming and answers every completion with JSON.
+    async fn spawn_non_streaming_server() -> String {
+        let app = axum::Router::new()
+            .route(
+                "/v1/models",
+                axum::routing::get(|| async {
+                    axum::Json(json!({"data": [{
+                        "id": "tiny",
+                        "capabilities": {"streaming": false, "tools": false, "embeddings": false}
+                    }]}))
+                }),
+            )
+            .route(
+                "/v1/chat/completions",
+                axum::routing::post(|axum::Json(body): axum::Json<Value>| async move {
+                    let reply = if body["stream"] == json!(true) || body.get("tools").is_some() {
+                        "unsupported request"
+                    } else {
+                        "hello from tiny"
+                    };
+                    axum::Json(json!({"choices": [{"message": {"content": reply}}]}))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_api_root() {
+        assert_eq!(
+            api_root("https://api.openai.com/v1/chat/completions"),
+            "https://api.openai.com/v1"
+        );
+        assert_eq!(
+            api_root("http://localhost:8081/v1/"),
+            "http://localhost:8081/v1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_endpoint_requires_admin() {
+        let user = AuthenticatedUser::new(uuid::Uuid::new_v4(), "operator".to_string());
+        let err = handle_llm_capabilities(user.clone()).await.unwrap_err();
+        assert_eq!(err.status, axum::http::StatusCode::FORBIDDEN);
+
+        let admin = user.with_role(crate::security::auth_api::Role::Admin);
+        assert!(handle_llm_capabilities(admin).await.is_ok());
+    }
+
+    #[test]
+    fn test_parse_models_reads_both_capability_forms() {
+        let body = json!({"data": [
+            {"id": "a", "capabilities": {"streaming": false, "tools": true}},
+            {"id": "b", "capabilities": ["completion", "embedding"]},
+            {"id": "c"}
+        ]});
+        let (ids, a) = parse_models(&body, "a");
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(
+            a,
+            Advertised {
+                streaming: Some(false),
+                tools: Some(true),
+                embeddings: None
+            }
+        );
+
+        let (_, b) = parse_models(&body, "b");
+        assert_eq!(
+            b,
+            Advertised {
+                streaming: None,
+                tools: Some(false),
+                embeddings: Some(true)
+            }
+        );
+
+        let (_, c) = parse_models(&body, "c");
+        assert_eq!(c, Advertised::default());
+    }
+
+    #[test]
+    fn test_only_definitive_refusals_mark_capability_missing() {
+        use reqwest::StatusCode;
+        assert_eq!(Probe::from_status(StatusCode::OK), Probe::Supported);
+        for status in [
+            StatusCode::BAD_REQUEST,
+            StatusCode::NOT_FOUND,
+            StatusCode::NOT_IMPLEMENTED,
+        ] {
+            assert_eq!(Probe::from_status(status), Probe::Unsupported);
+        }
+        for status in [
+            StatusCode::UNAUTHORIZED,
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ] {
+            assert_eq!(Probe::from_status(status), Probe::Inconclusive);
+            assert!(Probe::from_status(status).supported());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_falls_back_when_provider_does_not_stream() {
+        let base = spawn_non_streaming_server().await;
+        let client = OpenAIClient::new(String::new(), Some(base.clone()), None);
+
+        let (tx, mut rx) = mpsc::channel(4);
+        let tools = vec![json!({"type": "function", "function": {"name": "lookup"}})];
+        client
+            .generate_stream("hi", &Value::Null, tx, "tiny", "key", Some(&tools))
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await.as_deref(), Some("hello from tiny"));
+
+        let detected = detected();
+        let endpoint = detected
+            .iter()
+            .find(|e| e.endpoint == format!("{}/v1", base) && e.model == "tiny")
+            .unwrap();
+        assert!(!endpoint.capabilities.streaming);
+        assert!(!endpoint.capabilities.tools);
+        assert_eq!(endpoint.capabilities.models, vec!["tiny"]);
+        assert!(endpoint.capabilities.probe_error.is_none());
+    }
+}
@@ -8,6 +8,7 @@ use tokio::sync::mpsc;
 use tokio::time::Instant;
 
 use super::{llm_models::get_handler, LLMProvider};
+use crate::core::shared::llm_tls::llm_client_builder;
 
 // Configuration matching Node.js proxy exactly
 const MAX_RETRIES: u32 = 5;
@@ -394,7 +395,7 @@ impl ClaudeClient {
         // Build client matching Node.js httpsAgent configuration
         // IMPORTANT: NO timeout() here - it causes premature stream closure!
         // Node.js doesn't set a timeout on the response body, only on connect/headers
-        let client = reqwest::Client::builder()
+        let client = llm_client_builder(&url)
             .connect_timeout(Duration::from_millis(TIMEOUT_MS))
             .pool_idle_timeout(Duration::from_secs(30)) // keepAliveMsecs: 30000
             .pool_max_idle_per_host(10) // maxFreeSockets: 10
@@ -662,7 +663,7 @@ impl LLMProvider for ClaudeClient {
         let url = format!("{}/v1/messages", self.base_url.trim_end_matches('/'));
 
         // Use reqwest for non-streaming
-        let client = reqwest::Client::builder()
+        let client = llm_client_builder(&url)
             .timeout(Duration::from_millis(TIMEOUT_MS))
             .build()?;
 
@@ -0,0 +1,365 @@
+//! Hard cap on the tokens of the conversation sent to the LLM.
+//!
+//! A bot sets `max-context-tokens` and, optionally, `context-truncation` in its
+//! `config.csv`. When the assembled messages go over the budget they are cut down with the
+//! chosen [`TruncationStrategy`]; the newest message, the one being answered, is always
+//! kept. Tokens are estimated per model family from character counts, since no
+//! tokenizer is bundled.
+
+use serde_json::Value;
+use std::future::Future;
+
+/// Bot config key holding the token budget. Unset or `0` means no cap.
+pub const MAX_CONTEXT_TOKENS_KEY: &str = "max-context-tokens";
+/// Bot config key selecting the [`TruncationStrategy`].
+pub const TRUNCATION_KEY: &str = "context-truncation";
+
+/// Chat formats add a few tokens of framing to every message.
+const MESSAGE_OVERHEAD: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TruncationStrategy {
+    /// Drops messages from the start, system prompt included.
+    DropOldest,
+    /// Keeps the leading system messages and as many recent messages as fit.
+    #[default]
+    KeepSystemRecent,
+    /// Like `KeepSystemRecent`, replacing the dropped messages with an LLM summary.
+    SummarizeOverflow,
+}
+
+impl TruncationStrategy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace('_', "-").as_str() {
+            "drop-oldest" => Some(Self::DropOldest),
+            "keep-system-recent" | "keep-system+recent" => Some(Self::KeepSystemRecent),
+            "summarize-overflow" => Some(Self::SummarizeOverflow),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DropOldest => "drop-oldest",
+            Self::KeepSystemRecent => "keep-system-recent",
+            Self::SummarizeOverflow => "summarize-overflow",
+        }
+    }
+}
+
+/// Approximate token counts for a model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenEstimator {
+    chars_per_token: f64,
+}
+
+impl TokenEstimator {
+    /// Ratios measured on English text with each family's tokenizer: about 4 characters
+    /// per token for OpenAI models, 3.5 for Claude, and 3.6 for the SentencePiece/BPE
+    /// vocabularies of Llama, Qwen, DeepSeek and most local models.
+    pub fn for_model(model: &str) -> Self {
+        let model = model.to_lowercase();
+        let chars_per_token = if model.contains("claude") {
+            3.5
+        } else if model.contains("gpt") || (model.starts_with('o') && model.len() <= 8) {
+            4.0
+        } else {
+            3.6
+        };
+        Self { chars_per_token }
+    }
+
+    /// CJK and similar scripts take about one token per character.
+    pub fn text(&self, text: &str) -> usize {
+        let (wide, narrow) = text.chars().fold((0, 0), |(wide, narrow), c| {
+            if is_wide(c) {
+                (wide + 1, narrow)
+            } else {
+                (wide, narrow + 1)
+            }
+        });
+        wide + (narrow as f64 / self.chars_per_token).ceil() as usize
+    }
+
+    pub fn message(&self, message: &Value) -> usize {
+        let content = match message.get("content") {
+            Some(Value::String(text)) => self.text(text),
+            Some(Value::Null) | None => 0,
+            Some(other) => self.text(&other.to_string()),
+        };
+        MESSAGE_OVERHEAD + content
+    }
+
+    pub fn messages(&self, messages: &[Value]) -> usize {
+        messages.iter().map(|m| self.message(m)).sum()
+    }
+}
+
+fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x11FF | 0x2E80..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF)
+}
+
+fn is_system(message: &Value) -> bool {
+    message.get("role").and_then(Value::as_str) == Some("system")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextBudget {
+    pub max_tokens: usize,
+    pub strategy: TruncationStrategy,
+}
+
+/// Outcome of [`ContextBudget::enforce`].
+#[derive(Debug, Clone)]
+pub struct Trimmed {
+    pub messages: Value,
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+    pub dropped: usize,
+}
+
+impl Trimmed {
+    pub fn truncated(&self) -> bool {
+        self.dropped > 0
+    }
+}
+
+impl ContextBudget {
+    /// The bot's budget, looked up by config key; `None` when it sets no cap.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let max_tokens = lookup(MAX_CONTEXT_TOKENS_KEY)?
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)?;
+        let strategy = lookup(TRUNCATION_KEY)
+            .and_then(|v| TruncationStrategy::parse(&v))
+            .unwrap_or_default();
+        Some(Self {
+            max_tokens,
+            strategy,
+        })
+    }
+
+    /// Share of the budget reserved for the summary of dropped messages.
+    fn summary_allowance(&self) -> usize {
+        self.max_tokens / 4
+    }
+
+    /// Splits `messages` into the ones kept within `budget` and the ones dropped.
+    fn split(
+        &self,
+        messages: Vec<Value>,
+        budget: usize,
+        estimator: &TokenEstimator,
+    ) -> (Vec<Value>, Vec<Value>) {
+        let costs: Vec<usize> = messages.iter().map(|m| estimator.message(m)).collect();
+        let last = messages.len().saturating_sub(1);
+
+        let keep: Vec<bool> = match self.strategy {
+            TruncationStrategy::DropOldest => {
+                let mut total: usize = costs.iter().sum();
+                let mut start = 0;
+                while total > budget && start < last {
+                    total -= costs[start];
+                    start += 1;
+                }
+                (0..messages.len()).map(|i| i >= start).collect()
+            }
+            TruncationStrategy::KeepSystemRecent | TruncationStrategy::SummarizeOverflow => {
+                let lead = messages
+                    .iter()
+                    .take_while(|m| is_system(m))
+                    .count()
+                    .min(last);
+                let mut keep = vec![false; messages.len()];
+                let mut total: usize = costs[..lead].iter().sum();
+                keep[..lead].fill(true);
+                for i in (lead..messages.len()).rev() {
+                    if i != last && total + costs[i] > budget {
+                        break;
+                    }
+                    keep[i] = true;
+                    total += costs[i];
+                }
+                keep
+            }
+        };
+
+        let mut kept = Vec::new();
+        let mut dropped = Vec::new();
+        for (message, keep) in messages.into_iter().zip(keep) {
+            if keep {
+                kept.push(message);
+            } else {
+                dropped.push(message);
+            }
+        }
+        (kept, dropped)
+    }
+
+    /// Cuts `messages` down to the budget. With `SummarizeOverflow`, `summarize` is given
+    /// a transcript of the dropped messages; when it returns nothing they are just dropped.
+    pub async fn enforce<F, Fut>(
+        &self,
+        messages: &Value,
+        estimator: &TokenEstimator,
+        summarize: F,
+    ) -> Trimmed
+    where
+        F: FnOnce(String) -> Fut,
+        Fut: Future<Output = Option<String>>,
+    {
+        let all = messages.as_array().cloned().unwrap_or_default();
+        let tokens_before = estimator.messages(&all);
+        if tokens_before <= self.max_tokens {
+            return Trimmed {
+                messages: messages.clone(),
+                tokens_before,
+                tokens_after: tokens_before,
+                dropped: 0,
+            };
+        }
+
+        let budget = match self.strategy {
+            TruncationStrategy::SummarizeOverflow => self.max_tokens - self.summary_allowance(),
+            _ => self.max_tokens,
+        };
+        let (mut kept, dropped) = self.split(all, budget, estimator);
+
+        if self.strategy == TruncationStrategy::SummarizeOverflow && !dropped.is_empty() {
+            let transcript = dropped
+                .iter()
+                .filter_map(|m| {
+                    let role = m.get("role").and_then(Value::as_str).unwrap_or("user");
+                    let content = m.get("content").and_then(Value::as_str)?;
+                    Some(format!("{role}: {content}"))
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            if let Some(summary) = summarize(transcript).await {
+                let room = self.summary_allowance().saturating_sub(MESSAGE_OVERHEAD);
+                let summary = fit_text(
+                    &format!("Summary of the earlier conversation: {}", summary.trim()),
+                    room,
+                    estimator,
+                );
+                if !summary.is_empty() {
+                    let at = kept.iter().take_while(|m| is_system(m)).count();
+                    let at = at.min(kept.len().saturating_sub(1));
+                    kept.insert(
+                        at,
+                        serde_json::json!({"role": "system", "content": summary}),
+                    );
+                }
+            }
+        }
+
+        let tokens_after = estimator.messages(&kept);
+        Trimmed {
+            messages: Value::Array(kept),
+            tokens_before,
+            tokens_after,
+            dropped: dropped.len(),
+        }
+    }
+}
+
+/// The longest prefix of `text` within `max_tokens`.
+fn fit_text(text: &str, max_tokens: usize, estimator: &TokenEstimator) -> String {
+    if estimator.text(text) <= max_tokens {
+        return text.to_string();
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let (mut low, mut high) = (0, chars.len());
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if estimator.text(&chars[..mid].iter().collect::<String>()) <= max_tokens {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    chars[..low].iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn conversation() -> Value {
+        let mut messages = vec![json!({"role": "system", "content": "You are a helpful bot."})];
+        for i in 0..20 {
+            let role = if i % 2 == 0 { "user" } else { "assistant" };
+            messages.push(json!({"role": role, "content": format!("{i} {}", "word ".repeat(20))}));
+        }
+        messages.push(json!({"role": "user", "content": "What did I ask first?"}));
+        Value::Array(messages)
+    }
+
+    async fn trimmed(strategy: TruncationStrategy, summary: Option<String>) -> Trimmed {
+        let budget = ContextBudget {
+            max_tokens: 200,
+            strategy,
+        };
+        let estimator = TokenEstimator::for_model("gpt-4o");
+        budget
+            .enforce(&conversation(), &estimator, |_| async move { summary })
+            .await
+    }
+
+    fn contents(trimmed: &Trimmed) -> Vec<String> {
+        trimmed
+            .messages
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["content"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_overflowing_history_is_trimmed_under_each_strategy() {
+        let estimator = TokenEstimator::for_model("gpt-4o");
+        let all = conversation();
+        assert!(estimator.messages(all.as_array().unwrap()) > 200);
+
+        let dropped = trimmed(TruncationStrategy::DropOldest, None).await;
+        assert!(dropped.truncated());
+        assert!(dropped.tokens_after <= 200);
+        let kept = contents(&dropped);
+        assert_eq!(kept.last().unwrap(), "What did I ask first?");
+        assert!(!kept.iter().any(|c| c.starts_with("You are")));
+
+        let recent = trimmed(TruncationStrategy::KeepSystemRecent, None).await;
+        assert!(recent.tokens_after <= 200);
+        let kept = contents(&recent);
+        assert_eq!(kept[0], "You are a helpful bot.");
+        assert_eq!(kept.last().unwrap(), "What did I ask first?");
+        assert!(kept.len() < 22);
+        assert!(kept[kept.len() - 2].starts_with("19 "));
+
+        let long_summary = "the user asked about billing ".repeat(50);
+        let summarized = trimmed(TruncationStrategy::SummarizeOverflow, Some(long_summary)).await;
+        assert!(summarized.tokens_after <= 200);
+        let kept = contents(&summarized);
+        assert_eq!(kept[0], "You are a helpful bot.");
+        assert!(kept[1].starts_with("Summary of the earlier conversation:"));
+        assert_eq!(kept.last().unwrap(), "What did I ask first?");
+    }
+
+    #[test]
+    fn test_budget_from_config() {
+        let budget = ContextBudget::from_lookup(|key| match key {
+            MAX_CONTEXT_TOKENS_KEY => Some("4000".to_string()),
+            TRUNCATION_KEY => Some("summarize-overflow".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(budget.max_tokens, 4000);
+        assert_eq!(budget.strategy, TruncationStrategy::SummarizeOverflow);
+        assert!(ContextBudget::from_lookup(|_| Some("0".to_string())).is_none());
+    }
+}
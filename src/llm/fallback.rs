@@ -0,0 +1,550 @@
+use async_trait::async_trait;
+use log::{info, trace, warn};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use super::{create_llm_provider_from_url, LLMProvider, LLMProviderType};
+use crate::core::config::ConfigManager;
+use crate::core::shared::state::AppState;
+use crate::core::shared::utils::get_secrets_manager;
+
+const DEFAULT_FIRST_TOKEN_TIMEOUT_SECS: u64 = 30;
+
+pub struct FallbackEntry {
+    pub name: String,
+    pub provider: Arc<dyn LLMProvider>,
+    /// Overrides the caller's model/key for this entry; `None` keeps the caller's value.
+    pub model: Option<String>,
+    pub key: Option<String>,
+}
+
+impl FallbackEntry {
+    pub fn new(name: impl Into<String>, provider: Arc<dyn LLMProvider>) -> Self {
+        Self {
+            name: name.into(),
+            provider,
+            model: None,
+            key: None,
+        }
+    }
+}
+
+/// Ordered provider chain: the first entry is the primary, the rest are tried in order
+/// when it errors or produces nothing within the timeout. A stream that has already
+/// emitted chunks is never retried elsewhere, so the user never sees a duplicated answer.
+pub struct FallbackLLMProvider {
+    entries: Vec<FallbackEntry>,
+    first_token_timeout: Duration,
+    /// Entry name and model of the most recent successful call.
+    served_by: Mutex<Option<(String, String)>>,
+}
+
+impl std::fmt::Debug for FallbackLLMProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FallbackLLMProvider")
+            .field("entries", &self.entries.iter().map(|e| &e.name).collect::<Vec<_>>())
+            .field("first_token_timeout", &self.first_token_timeout)
+            .finish()
+    }
+}
+
+impl FallbackLLMProvider {
+    pub fn new(entries: Vec<FallbackEntry>, first_token_timeout: Duration) -> Self {
+        Self {
+            entries,
+            first_token_timeout,
+            served_by: Mutex::new(None),
+        }
+    }
+
+    /// Name of the entry that served the most recent successful call.
+    pub fn served_by(&self) -> Option<String> {
+        self.served_by
+            .lock()
+            .ok()
+            .and_then(|g| g.as_ref().map(|(name, _)| name.clone()))
+    }
+
+    /// Model that answered the most recent successful call.
+    pub fn served_model(&self) -> Option<String> {
+        self.served_by
+            .lock()
+            .ok()
+            .and_then(|g| g.as_ref().map(|(_, model)| model.clone()))
+    }
+
+    fn mark_served(&self, name: &str, model: &str) {
+        if let Ok(mut guard) = self.served_by.lock() {
+            *guard = Some((name.to_string(), model.to_string()));
+        }
+    }
+
+    /// Streams one entry into `tx`. The timeout only covers the wait for the first chunk;
+    /// once a chunk arrives the provider may take as long as it needs. Returns the
+    /// provider's result and whether any chunk reached `tx`.
+    #[allow(clippy::too_many_arguments)]
+    async fn stream_entry(
+        &self,
+        entry: &FallbackEntry,
+        prompt: &str,
+        config: &Value,
+        tx: mpsc::Sender<String>,
+        model: &str,
+        key: &str,
+        tools: Option<&Vec<Value>>,
+    ) -> (Result<(), Box<dyn std::error::Error + Send + Sync>>, bool) {
+        let (inner_tx, mut inner_rx) = mpsc::channel::<String>(100);
+        let (first_tx, first_rx) = tokio::sync::oneshot::channel::<()>();
+        let emitted = Arc::new(AtomicBool::new(false));
+        let forward_emitted = emitted.clone();
+        let forward = tokio::spawn(async move {
+            let mut first_tx = Some(first_tx);
+            while let Some(chunk) = inner_rx.recv().await {
+                forward_emitted.store(true, Ordering::SeqCst);
+                if let Some(signal) = first_tx.take() {
+                    let _ = signal.send(());
+                }
+                if tx.send(chunk).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let result = {
+            let stream = entry
+                .provider
+                .generate_stream(prompt, config, inner_tx, model, key, tools);
+            tokio::pin!(stream);
+            let result = tokio::select! {
+                biased;
+                res = &mut stream => res,
+                _ = first_rx => stream.await,
+                _ = tokio::time::sleep(self.first_token_timeout) => {
+                    Err(format!("LLM provider '{}' timed out", entry.name).into())
+                }
+            };
+            // The provider future, and the sender it owns, is dropped here
+            result
+        };
+
+        if result.is_ok() {
+            let _ = forward.await;
+        } else {
+            // A provider that spawned its own task may still hold a sender
+            forward.abort();
+            let _ = forward.await;
+        }
+        (result, emitted.load(Ordering::SeqCst))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for FallbackLLMProvider {
+    async fn generate(
+        &self,
+        prompt: &str,
+        config: &Value,
+        model: &str,
+        key: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let mut last_error: Box<dyn std::error::Error + Send + Sync> = "No LLM providers configured".into();
+
+        for entry in &self.entries {
+            let model = entry.model.as_deref().unwrap_or(model);
+            let key = entry.key.as_deref().unwrap_or(key);
+
+            // Nothing reaches the caller until the answer is complete, so a provider that
+            // fails halfway can still be retried.
+            let (tx, mut rx) = mpsc::channel::<String>(100);
+            let collect = tokio::spawn(async move {
+                let mut response = String::new();
+                while let Some(chunk) = rx.recv().await {
+                    response.push_str(&chunk);
+                }
+                response
+            });
+            let (result, _) = self.stream_entry(entry, prompt, config, tx, model, key, None).await;
+            let response = collect.await.unwrap_or_default();
+
+            match result {
+                Ok(()) => {
+                    self.mark_served(&entry.name, model);
+                    return Ok(response);
+                }
+                Err(e) => {
+                    warn!("LLM provider '{}' failed, trying next: {}", entry.name, e);
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    async fn generate_stream(
+        &self,
+        prompt: &str,
+        config: &Value,
+        tx: mpsc::Sender<String>,
+        model: &str,
+        key: &str,
+        tools: Option<&Vec<Value>>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut last_error: Box<dyn std::error::Error + Send + Sync> = "No LLM providers configured".into();
+
+        for entry in &self.entries {
+            let model = entry.model.as_deref().unwrap_or(model);
+            let key = entry.key.as_deref().unwrap_or(key);
+
+            let (result, emitted) = self
+                .stream_entry(entry, prompt, config, tx.clone(), model, key, tools)
+                .await;
+
+            match result {
+                Ok(()) => {
+                    self.mark_served(&entry.name, model);
+                    return Ok(());
+                }
+                Err(e) if emitted => {
+                    warn!("LLM provider '{}' failed mid-stream, not retrying: {}", entry.name, e);
+                    self.mark_served(&entry.name, model);
+                    return Err(e);
+                }
+                Err(e) => {
+                    warn!("LLM provider '{}' failed before streaming, trying next: {}", entry.name, e);
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        config: &Value,
+        model: &str,
+        key: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let mut last_error: Box<dyn std::error::Error + Send + Sync> = "No LLM providers configured".into();
+
+        for entry in &self.entries {
+            let model = entry.model.as_deref().unwrap_or(model);
+            let key = entry.key.as_deref().unwrap_or(key);
+
+            match tokio::time::timeout(
+                self.first_token_timeout,
+                entry.provider.generate_json(prompt, config, model, key),
+            )
+            .await
+            {
+                Ok(Ok(response)) => {
+                    self.mark_served(&entry.name, model);
+                    return Ok(response);
+                }
+                Ok(Err(e)) => {
+                    warn!("LLM provider '{}' failed, trying next: {}", entry.name, e);
+                    last_error = e;
+                }
+                Err(_) => {
+                    warn!("LLM provider '{}' timed out, trying next", entry.name);
+                    last_error = format!("LLM provider '{}' timed out", entry.name).into();
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    async fn cancel_job(
+        &self,
+        session_id: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        for entry in &self.entries {
+            if let Err(e) = entry.provider.cancel_job(session_id).await {
+                trace!("cancel_job on '{}' failed: {}", entry.name, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds the chain for a bot: `primary` first, then every name listed in the bot's
+/// `llm-fallbacks` config (comma separated). Each fallback reads `<name>_url`,
+/// `<name>_model`, `<name>_key` and optional `<name>_provider` from the bot's Vault
+/// LLM secret, falling back to the system-wide `gbo/llm` secret.
+pub async fn build_bot_chain(
+    state: &Arc<AppState>,
+    bot_id: &Uuid,
+    primary_name: &str,
+    primary: Arc<dyn LLMProvider>,
+) -> Arc<FallbackLLMProvider> {
+    let mut entries = vec![FallbackEntry::new(primary_name, primary)];
+
+    let pool = state.conn.clone();
+    let target_bot = *bot_id;
+    let settings = tokio::task::spawn_blocking(move || {
+        use crate::core::shared::models::schema::bots::dsl::*;
+        use diesel::prelude::*;
+
+        let config_manager = ConfigManager::new(pool.clone());
+        let names = config_manager
+            .get_config(&target_bot, "llm-fallbacks", Some(""))
+            .unwrap_or_default();
+        let timeout = config_manager
+            .get_config(&target_bot, "llm-fallback-timeout", None)
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_FIRST_TOKEN_TIMEOUT_SECS);
+        let org = pool.get().ok().and_then(|mut conn| {
+            bots.filter(id.eq(target_bot))
+                .select(org_id)
+                .first::<Option<Uuid>>(&mut conn)
+                .ok()
+                .flatten()
+        });
+        (names, timeout, org)
+    })
+    .await;
+
+    let (names, timeout_secs, org) = settings.unwrap_or_else(|e| {
+        warn!("Failed to load LLM fallback config for bot {}: {}", bot_id, e);
+        (String::new(), DEFAULT_FIRST_TOKEN_TIMEOUT_SECS, None)
+    });
+
+    let names: Vec<String> = names
+        .split(',')
+        .map(|n| n.trim().to_lowercase())
+        .filter(|n| !n.is_empty())
+        .collect();
+
+    if !names.is_empty() {
+        if let Some(secrets) = get_secrets_manager().await {
+            let bot_secret = match org {
+                Some(org) => secrets
+                    .get_bot_llm_config(&org.to_string(), &bot_id.to_string())
+                    .await
+                    .ok()
+                    .flatten(),
+                None => None,
+            };
+            let system_secret = secrets
+                .get_secret(crate::core::secrets::SecretPaths::LLM)
+                .await
+                .ok();
+
+            for name in names {
+                let lookup = |field: &str| {
+                    let key = format!("{}_{}", name, field);
+                    bot_secret
+                        .as_ref()
+                        .and_then(|s| s.get(&key))
+                        .or_else(|| system_secret.as_ref().and_then(|s| s.get(&key)))
+                        .filter(|v| !v.trim().is_empty())
+                        .cloned()
+                };
+
+                let Some(url) = lookup("url") else {
+                    warn!("LLM fallback '{}' for bot {} has no {}_url in Vault, skipping", name, bot_id, name);
+                    continue;
+                };
+                let model = lookup("model");
+                let explicit = lookup("provider").map(|p| LLMProviderType::from(p.as_str()));
+                let provider = create_llm_provider_from_url(&url, model.clone(), None, explicit);
+
+                entries.push(FallbackEntry {
+                    name,
+                    provider,
+                    model,
+                    key: lookup("key"),
+                });
+            }
+        } else {
+            warn!("LLM fallbacks configured for bot {} but secrets manager is unavailable", bot_id);
+        }
+    }
+
+    if entries.len() > 1 {
+        info!(
+            "LLM provider chain for bot {}: {}",
+            bot_id,
+            entries.iter().map(|e| e.name.as_str()).collect::<Vec<_>>().join(" -> ")
+        );
+    }
+
+    Arc::new(FallbackLLMProvider::new(entries, Duration::from_secs(timeout_secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct FailingProvider;
+
+    #[async_trait]
+    impl LLMProvider for FailingProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _config: &Value,
+            _model: &str,
+            _key: &str,
+        ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+            Err("connection refused".into())
+        }
+
+        async fn generate_stream(
+            &self,
+            _prompt: &str,
+            _config: &Value,
+            _tx: mpsc::Sender<String>,
+            _model: &str,
+            _key: &str,
+            _tools: Option<&Vec<Value>>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Err("connection refused".into())
+        }
+
+        async fn cancel_job(&self, _session_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct EchoProvider(&'static str);
+
+    #[async_trait]
+    impl LLMProvider for EchoProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _config: &Value,
+            model: &str,
+            _key: &str,
+        ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(format!("{}:{}", self.0, model))
+        }
+
+        async fn generate_stream(
+            &self,
+            _prompt: &str,
+            _config: &Value,
+            tx: mpsc::Sender<String>,
+            model: &str,
+            _key: &str,
+            _tools: Option<&Vec<Value>>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            tx.send(format!("{}:{}", self.0, model)).await?;
+            Ok(())
+        }
+
+        async fn cancel_job(&self, _session_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+    }
+
+    /// Keeps its sender alive and never answers.
+    #[derive(Debug)]
+    struct HangingProvider;
+
+    #[async_trait]
+    impl LLMProvider for HangingProvider {
+        async fn generate(
+            &self,
+            _prompt: &str,
+            _config: &Value,
+            _model: &str,
+            _key: &str,
+        ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+            std::future::pending().await
+        }
+
+        async fn generate_stream(
+            &self,
+            _prompt: &str,
+            _config: &Value,
+            tx: mpsc::Sender<String>,
+            _model: &str,
+            _key: &str,
+            _tools: Option<&Vec<Value>>,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            let _tx = tx;
+            std::future::pending().await
+        }
+
+        async fn cancel_job(&self, _session_id: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Ok(())
+        }
+    }
+
+    fn chain() -> FallbackLLMProvider {
+        let mut backup = FallbackEntry::new("backup", Arc::new(EchoProvider("backup")));
+        backup.model = Some("backup-model".to_string());
+        FallbackLLMProvider::new(
+            vec![FallbackEntry::new("local", Arc::new(FailingProvider)), backup],
+            Duration::from_secs(5),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_generate_falls_back_when_primary_errors() {
+        let chain = chain();
+        let response = chain.generate("hi", &Value::Null, "local-model", "").await.unwrap();
+        assert_eq!(response, "backup:backup-model");
+        assert_eq!(chain.served_by().as_deref(), Some("backup"));
+        assert_eq!(chain.served_model().as_deref(), Some("backup-model"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_falls_back_when_primary_errors() {
+        let chain = chain();
+        let (tx, mut rx) = mpsc::channel(10);
+        chain
+            .generate_stream("hi", &Value::Null, tx, "local-model", "", None)
+            .await
+            .unwrap();
+
+        assert_eq!(rx.recv().await.as_deref(), Some("backup:backup-model"));
+        assert_eq!(chain.served_by().as_deref(), Some("backup"));
+    }
+
+    #[tokio::test]
+    async fn test_stream_falls_back_when_primary_sends_nothing() {
+        let mut backup = FallbackEntry::new("backup", Arc::new(EchoProvider("backup")));
+        backup.model = Some("backup-model".to_string());
+        let chain = FallbackLLMProvider::new(
+            vec![FallbackEntry::new("local", Arc::new(HangingProvider)), backup],
+            Duration::from_millis(50),
+        );
+        let (tx, mut rx) = mpsc::channel(10);
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            chain.generate_stream("hi", &Value::Null, tx, "local-model", "", None),
+        )
+        .await
+        .expect("timed-out provider blocked the chain")
+        .unwrap();
+
+        assert_eq!(rx.recv().await.as_deref(), Some("backup:backup-model"));
+        assert_eq!(chain.served_model().as_deref(), Some("backup-model"));
+    }
+
+    #[tokio::test]
+    async fn test_all_providers_failing_returns_error() {
+        let chain = FallbackLLMProvider::new(
+            vec![
+                FallbackEntry::new("a", Arc::new(FailingProvider)),
+                FallbackEntry::new("b", Arc::new(FailingProvider)),
+            ],
+            Duration::from_secs(5),
+        );
+        assert!(chain.generate("hi", &Value::Null, "m", "").await.is_err());
+        assert!(chain.served_by().is_none());
+    }
+}
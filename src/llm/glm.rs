@@ -6,6 +6,7 @@ use serde_json::Value;
 use tokio::sync::mpsc;
 
 use super::LLMProvider;
+use crate::core::shared::llm_tls::llm_http_client;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GLMMessage {
@@ -105,7 +106,7 @@ impl GLMClient {
         let base = base_url.trim_end_matches('/').to_string();
 
         Self {
-            client: reqwest::Client::new(),
+            client: llm_http_client(&base),
             base_url: base,
         }
     }
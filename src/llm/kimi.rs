@@ -6,6 +6,7 @@ use serde_json::Value;
 use tokio::sync::mpsc;
 
 use super::LLMProvider;
+use crate::core::shared::llm_tls::llm_http_client;
 
 // Kimi K2.5 (moonshotai) API Client
 // NVIDIA endpoint with special chat_template_kwargs for thinking mode
@@ -109,7 +110,7 @@ impl KimiClient {
         let base = base_url.trim_end_matches('/').to_string();
 
         Self {
-            client: reqwest::Client::new(),
+            client: llm_http_client(&base),
             base_url: base,
         }
     }
@@ -352,7 +352,7 @@ fn extract_base_url(url: &str) -> String {
 
 pub async fn is_server_running(url: &str) -> bool {
     let base_url = extract_base_url(url);
-    let client = reqwest::Client::builder()
+    let client = crate::core::shared::llm_tls::llm_client_builder(url)
         .timeout(std::time::Duration::from_secs(5))
         .build()
         .unwrap_or_default();
@@ -1,19 +1,24 @@
 use async_trait::async_trait;
 use futures::StreamExt;
-use log::{error, info, trace};
+use log::{debug, error, info, trace};
 use serde_json::Value;
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 
 pub mod cache;
+pub mod capabilities;
 pub mod claude;
+pub mod context;
 pub mod episodic_memory;
+pub mod fallback;
 pub mod glm;
 pub mod hallucination_detector;
 pub mod llm_models;
 pub mod local;
 pub mod rate_limiter;
 pub mod smart_router;
+pub mod redaction;
+pub mod usage;
 pub mod vertex;
 pub mod bedrock;
 
@@ -48,6 +53,18 @@ pub trait LLMProvider: Send + Sync {
         &self,
         session_id: &str,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Like `generate`, but asks for a single JSON object where the endpoint has a JSON
+    /// mode. Callers still validate the answer; by default this is plain `generate`.
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        config: &Value,
+        model: &str,
+        key: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        self.generate(prompt, config, model, key).await
+    }
 }
 
 #[derive(Debug)]
@@ -71,7 +88,7 @@ impl AzureGPT5Client {
         let api_version = api_version.unwrap_or_else(|| "2025-04-01-preview".to_string());
         let rate_limiter = Arc::new(ApiRateLimiter::unlimited());
         Self {
-            client: reqwest::Client::new(),
+            client: crate::core::shared::outbound_proxy::http_client(),
             base_url,
             api_version,
             rate_limiter,
@@ -316,7 +333,7 @@ impl OpenAIClient {
         };
 
         Self {
-            client: reqwest::Client::new(),
+            client: crate::core::shared::llm_tls::llm_http_client(&final_base),
             base_url: final_base,
             endpoint_path: final_endpoint,
             rate_limiter: Arc::new(rate_limiter),
@@ -369,15 +386,17 @@ impl OpenAIClient {
     }
 }
 
-#[async_trait]
-impl LLMProvider for OpenAIClient {
-    async fn generate(
+impl OpenAIClient {
+    /// Non-streaming completion. With `json_mode`, asks for a JSON object and returns
+    /// `None` when the endpoint rejects the option.
+    async fn complete(
         &self,
         prompt: &str,
         messages: &Value,
         model: &str,
         key: &str,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        json_mode: bool,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
         let default_messages = serde_json::json!([{"role": "user", "content": prompt}]);
 
         // Get the messages to use
@@ -421,22 +440,30 @@ impl LLMProvider for OpenAIClient {
         trace!("  API Key Last 8 chars: '...{}'", &key.chars().rev().take(8).collect::<String>());
 
         // Build the request body (no tools for non-streaming generate)
+        let mut request_body = serde_json::json!({
+            "model": model,
+            "messages": messages,
+            "stream": false
+        });
+        if json_mode {
+            request_body["response_format"] = serde_json::json!({"type": "json_object"});
+        }
         let response = self
             .client
             .post(&full_url)
             .header("Authorization", &auth_header)
-            .json(&serde_json::json!({
-                "model": model,
-                "messages": messages,
-                "stream": false
-            }))
+            .json(&request_body)
             .send()
             .await?;
 
         let status = response.status();
+        if json_mode && status == reqwest::StatusCode::BAD_REQUEST {
+            debug!("LLM endpoint rejected JSON mode for {}, retrying without it", model);
+            return Ok(None);
+        }
         if status != reqwest::StatusCode::OK {
             let error_text = response.text().await.unwrap_or_default();
-            error!("LLM generate error: {}", error_text);
+            error!("LLM generate error: {}", redaction::for_log(&error_text));
             return Err(format!("LLM request failed with status: {}", status).into());
         }
 
@@ -448,7 +475,36 @@ impl LLMProvider for OpenAIClient {
         let handler = get_handler(model);
         let content = handler.process_content(raw_content);
 
-        Ok(content)
+        Ok(Some(content))
+    }
+}
+
+#[async_trait]
+impl LLMProvider for OpenAIClient {
+    async fn generate(
+        &self,
+        prompt: &str,
+        messages: &Value,
+        model: &str,
+        key: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self
+            .complete(prompt, messages, model, key, false)
+            .await?
+            .unwrap_or_default())
+    }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        messages: &Value,
+        model: &str,
+        key: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        match self.complete(prompt, messages, model, key, true).await? {
+            Some(content) => Ok(content),
+            None => self.generate(prompt, messages, model, key).await,
+        }
     }
 
     async fn generate_stream(
@@ -465,7 +521,7 @@ impl LLMProvider for OpenAIClient {
         // Get the messages to use
         let raw_messages =
             if messages.is_array() && !messages.as_array().unwrap_or(&vec![]).is_empty() {
-                info!("Using provided messages: {:?}", messages);
+                debug!("Using provided messages: {}", redaction::for_log(&messages.to_string()));
                 messages
             } else {
                 &default_messages
@@ -499,6 +555,23 @@ impl LLMProvider for OpenAIClient {
         let full_url = format!("{}{}", self.base_url, self.endpoint_path);
         let auth_header = format!("Bearer {}", key);
 
+        // Endpoints that cannot stream get one non-streaming answer; tools are only sent
+        // to endpoints that accept them.
+        let capabilities = capabilities::detect(&self.client, &full_url, model, key).await;
+        if !capabilities.streaming {
+            debug!("LLM endpoint does not stream, sending one response for {}", model);
+            let content = self.generate(prompt, &messages, model, key).await?;
+            let _ = tx.send(content).await;
+            return Ok(());
+        }
+        let tools = match tools {
+            Some(tools) if !capabilities.tools && !tools.is_empty() => {
+                info!("LLM endpoint does not accept tools, omitting {} tools", tools.len());
+                None
+            }
+            tools => tools,
+        };
+
         // Debug logging to help troubleshoot 401 errors
         trace!("LLM Request Details:");
         trace!("  URL: {}", full_url);
@@ -557,7 +630,7 @@ impl LLMProvider for OpenAIClient {
         let status = response.status();
         if status != reqwest::StatusCode::OK {
             let error_text = response.text().await.unwrap_or_default();
-            error!("LLM generate_stream error: {}", error_text);
+            error!("LLM generate_stream error: {}", redaction::for_log(&error_text));
             return Err(format!("LLM request failed with status: {}", status).into());
         }
 
@@ -825,6 +898,19 @@ impl LLMProvider for DynamicLLMProvider {
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         self.get_provider().await.cancel_job(session_id).await
     }
+
+    async fn generate_json(
+        &self,
+        prompt: &str,
+        config: &Value,
+        model: &str,
+        key: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        self.get_provider()
+            .await
+            .generate_json(prompt, config, model, key)
+            .await
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,149 @@
+//! Masking of personal data in LLM prompts and responses before they reach the logs.
+//!
+//! Only log output is redacted. Providers, the response cache and cache keys always see
+//! the original text, so redaction never changes cache hit rates or answers.
+//!
+//! Configured from the environment:
+//! - `LLM_LOG_PROMPTS` (default `true`): set to `false` to keep prompt and response text
+//!   out of the logs entirely.
+//! - `LLM_LOG_REDACT` (default `true`): mask emails, phone numbers and card numbers in
+//!   logged text.
+
+use regex::Regex;
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9.-]+\.[a-z]{2,}\b").expect("valid regex")
+});
+
+// 13-19 digits, optionally grouped by spaces or dashes. Luhn-checked before masking.
+static CARD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").expect("valid regex"));
+
+// Numbers with a country or area code prefix, e.g. +55 11 98765-4321 or (555) 123-4567,
+// or three bare digit groups such as 011 98765 4321.
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?:\+\d{1,3}[ .-]?(?:\(\d{1,4}\)[ .-]?)?|\(\d{1,4}\)[ .-]?)\d{2,5}(?:[ .-]?\d{3,5}){1,2}\b",
+        r"|\b\d{2,5}[ .-]?\d{3,5}[ .-]?\d{3,5}\b",
+    ))
+    .expect("valid regex")
+});
+
+static POLICY: LazyLock<LogPolicy> = LazyLock::new(LogPolicy::from_env);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogPolicy {
+    pub log_prompts: bool,
+    pub redact: bool,
+}
+
+impl LogPolicy {
+    pub fn from_env() -> Self {
+        fn flag(key: &str) -> bool {
+            !matches!(
+                std::env::var(key).unwrap_or_default().trim().to_ascii_lowercase().as_str(),
+                "false" | "0" | "no" | "off"
+            )
+        }
+        Self {
+            log_prompts: flag("LLM_LOG_PROMPTS"),
+            redact: flag("LLM_LOG_REDACT"),
+        }
+    }
+
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !self.log_prompts {
+            Cow::Owned(format!("<{} chars, prompt logging disabled>", text.chars().count()))
+        } else if self.redact {
+            redact(text)
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+}
+
+fn luhn_valid(digits: &str) -> bool {
+    let mut sum = 0;
+    for (i, d) in digits.chars().rev().filter_map(|c| c.to_digit(10)).enumerate() {
+        sum += if i % 2 == 1 {
+            let doubled = d * 2;
+            if doubled > 9 { doubled - 9 } else { doubled }
+        } else {
+            d
+        };
+    }
+    sum % 10 == 0
+}
+
+/// Masks emails, card-like numbers (Luhn-valid) and phone numbers.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let mut out = EMAIL.replace_all(text, "[EMAIL]");
+
+    if CARD.is_match(&out) {
+        let replaced = CARD
+            .replace_all(&out, |caps: &regex::Captures| {
+                let digits: String = caps[0].chars().filter(char::is_ascii_digit).collect();
+                if luhn_valid(&digits) {
+                    "[CARD]".to_string()
+                } else {
+                    caps[0].to_string()
+                }
+            })
+            .into_owned();
+        out = Cow::Owned(replaced);
+    }
+
+    if PHONE.is_match(&out) {
+        out = Cow::Owned(PHONE.replace_all(&out, "[PHONE]").into_owned());
+    }
+
+    out
+}
+
+/// Text to put in a log line for a prompt or response, according to the global policy.
+pub fn for_log(text: &str) -> Cow<'_, str> {
+    POLICY.apply(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_email() {
+        assert_eq!(redact("mail john.doe+x@example.com.br now"), "mail [EMAIL] now");
+    }
+
+    #[test]
+    fn test_redacts_luhn_valid_cards_only() {
+        assert_eq!(redact("card 4111 1111 1111 1111 ok"), "card [CARD] ok");
+        assert_eq!(redact("card 4111-1111-1111-1111"), "card [CARD]");
+        assert!(!redact("id 1234567890123").contains("[CARD]"));
+    }
+
+    #[test]
+    fn test_redacts_phone_numbers() {
+        assert_eq!(redact("call +55 11 98765-4321"), "call [PHONE]");
+        assert_eq!(redact("call (555) 123-4567 please"), "call [PHONE] please");
+    }
+
+    #[test]
+    fn test_leaves_plain_text_alone() {
+        let text = "What is the weather in 2024 for room 42?";
+        assert!(matches!(redact(text), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_policy() {
+        let off = LogPolicy { log_prompts: false, redact: true };
+        assert_eq!(off.apply("secret"), "<6 chars, prompt logging disabled>");
+
+        let raw = LogPolicy { log_prompts: true, redact: false };
+        assert_eq!(raw.apply("a@b.com"), "a@b.com");
+
+        let redacted = LogPolicy { log_prompts: true, redact: true };
+        assert_eq!(redacted.apply("a@b.com"), "[EMAIL]");
+    }
+}
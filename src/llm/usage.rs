@@ -0,0 +1,355 @@
+use crate::core::config::ConfigManager;
+use crate::core::shared::state::AppState;
+use crate::core::shared::utils::DbPool;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use log::{trace, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Price per 1K tokens, in the currency the operator configures prices in.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TokenPrice {
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+/// Built-in list prices (USD per 1K tokens) used when a bot has no price configured
+/// for the model.
+/// Anything not listed, including local models, is priced at zero.
+const DEFAULT_PRICES: &[(&str, TokenPrice)] = &[
+    (
+        "gpt-4o-mini",
+        TokenPrice {
+            input_per_1k: 0.00015,
+            output_per_1k: 0.0006,
+        },
+    ),
+    (
+        "gpt-4o",
+        TokenPrice {
+            input_per_1k: 0.0025,
+            output_per_1k: 0.01,
+        },
+    ),
+    (
+        "gpt-4.1-mini",
+        TokenPrice {
+            input_per_1k: 0.0004,
+            output_per_1k: 0.0016,
+        },
+    ),
+    (
+        "gpt-4.1",
+        TokenPrice {
+            input_per_1k: 0.002,
+            output_per_1k: 0.008,
+        },
+    ),
+    (
+        "claude-3-5-haiku",
+        TokenPrice {
+            input_per_1k: 0.0008,
+            output_per_1k: 0.004,
+        },
+    ),
+    (
+        "claude-sonnet",
+        TokenPrice {
+            input_per_1k: 0.003,
+            output_per_1k: 0.015,
+        },
+    ),
+    (
+        "claude-3-5-sonnet",
+        TokenPrice {
+            input_per_1k: 0.003,
+            output_per_1k: 0.015,
+        },
+    ),
+];
+
+pub fn default_price(model: &str) -> TokenPrice {
+    let model = model.to_lowercase();
+    // Longest prefix wins so "gpt-4o-mini" is not priced as "gpt-4o".
+    DEFAULT_PRICES
+        .iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, price)| *price)
+        .unwrap_or_default()
+}
+
+/// Price of `model` for a bot. A bot's fallbacks can serve other models than its
+/// `llm-model`, so prices are configured per model, as `llm-price-input-per-1k-<model>`
+/// and `llm-price-output-per-1k-<model>`. The unsuffixed keys only price `llm-model`.
+pub fn resolve_price(config_manager: &ConfigManager, bot_id: &Uuid, model: &str) -> TokenPrice {
+    price_from(
+        |key| config_manager.get_config(bot_id, key, None).ok(),
+        model,
+    )
+}
+
+fn price_from(lookup: impl Fn(&str) -> Option<String>, model: &str) -> TokenPrice {
+    let defaults = default_price(model);
+    let is_primary = lookup("llm-model").is_some_and(|m| m.trim() == model);
+    let parse = |key: &str| lookup(key).and_then(|v| v.trim().parse::<f64>().ok());
+    let read = |base: &str, fallback: f64| {
+        parse(&format!("{base}-{model}"))
+            .or_else(|| is_primary.then(|| parse(base)).flatten())
+            .unwrap_or(fallback)
+    };
+
+    TokenPrice {
+        input_per_1k: read("llm-price-input-per-1k", defaults.input_per_1k),
+        output_per_1k: read("llm-price-output-per-1k", defaults.output_per_1k),
+    }
+}
+
+/// Cache hits are served without calling the provider, so their marginal cost is zero.
+pub fn compute_cost(
+    price: TokenPrice,
+    prompt_tokens: usize,
+    completion_tokens: usize,
+    cached: bool,
+) -> f64 {
+    if cached {
+        return 0.0;
+    }
+    (prompt_tokens as f64 / 1000.0) * price.input_per_1k
+        + (completion_tokens as f64 / 1000.0) * price.output_per_1k
+}
+
+#[derive(Debug, Clone)]
+pub struct LlmUsageRecord {
+    pub bot_id: Uuid,
+    pub session_id: Uuid,
+    pub model: String,
+    /// Name of the provider in the bot's chain that actually served the response.
+    pub provider: String,
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    pub cached: bool,
+}
+
+/// Prices and persists one LLM call. Failures are logged, never surfaced to the conversation.
+pub async fn record_usage(state: &Arc<AppState>, record: LlmUsageRecord) {
+    let pool = state.conn.clone();
+    let labels = HashMap::from([("bot_id".to_string(), record.bot_id.to_string())]);
+    let result = tokio::task::spawn_blocking(move || -> Result<f64, String> {
+        let price = resolve_price(
+            &ConfigManager::new(pool.clone()),
+            &record.bot_id,
+            &record.model,
+        );
+        let cost = compute_cost(
+            price,
+            record.prompt_tokens,
+            record.completion_tokens,
+            record.cached,
+        );
+        insert_usage(&pool, &record, cost)?;
+        Ok(cost)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(cost)) => {
+            trace!("llm_usage: recorded cost {:.6}", cost);
+            state
+                .metrics_collector
+                .record("llm.cost".to_string(), cost, labels)
+                .await;
+        }
+        Ok(Err(e)) => warn!("llm_usage: failed to record usage: {}", e),
+        Err(e) => warn!("llm_usage: record task failed: {}", e),
+    }
+}
+
+fn insert_usage(pool: &DbPool, record: &LlmUsageRecord, cost: f64) -> Result<(), String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    diesel::sql_query(
+        "INSERT INTO llm_usage (bot_id, session_id, model, provider, prompt_tokens, completion_tokens, cost, cached)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(record.bot_id)
+    .bind::<diesel::sql_types::Uuid, _>(record.session_id)
+    .bind::<diesel::sql_types::Text, _>(&record.model)
+    .bind::<diesel::sql_types::Text, _>(&record.provider)
+    .bind::<diesel::sql_types::BigInt, _>(record.prompt_tokens as i64)
+    .bind::<diesel::sql_types::BigInt, _>(record.completion_tokens as i64)
+    .bind::<diesel::sql_types::Double, _>(cost)
+    .bind::<diesel::sql_types::Bool, _>(record.cached)
+    .execute(&mut conn)
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CostReportQuery {
+    pub bot_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, QueryableByName)]
+pub struct CostReportRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    pub bot_id: Uuid,
+    #[diesel(sql_type = diesel::sql_types::Date)]
+    pub day: NaiveDate,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub model: String,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub provider: String,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub requests: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub cache_hits: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub prompt_tokens: i64,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub completion_tokens: i64,
+    #[diesel(sql_type = diesel::sql_types::Double)]
+    pub cost: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CostReport {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub total_cost: f64,
+    pub rows: Vec<CostReportRow>,
+}
+
+pub fn load_cost_report(
+    conn: &mut PgConnection,
+    bot_id: Option<Uuid>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<CostReportRow>, diesel::result::Error> {
+    diesel::sql_query(
+        "SELECT bot_id, (created_at AT TIME ZONE 'UTC')::date AS day, model, provider,
+                COUNT(*) AS requests,
+                COUNT(*) FILTER (WHERE cached) AS cache_hits,
+                COALESCE(SUM(prompt_tokens), 0)::bigint AS prompt_tokens,
+                COALESCE(SUM(completion_tokens), 0)::bigint AS completion_tokens,
+                COALESCE(SUM(cost), 0)::float8 AS cost
+         FROM llm_usage
+         WHERE created_at >= $1 AND created_at < $2
+           AND ($3::uuid IS NULL OR bot_id = $3)
+         GROUP BY bot_id, day, model, provider
+         ORDER BY day, bot_id, model, provider",
+    )
+    .bind::<diesel::sql_types::Timestamptz, _>(from)
+    .bind::<diesel::sql_types::Timestamptz, _>(to)
+    .bind::<diesel::sql_types::Nullable<diesel::sql_types::Uuid>, _>(bot_id)
+    .load(conn)
+}
+
+pub async fn handle_cost_report(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CostReportQuery>,
+) -> Result<Json<CostReport>, (StatusCode, Json<serde_json::Value>)> {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(30));
+    if from >= to {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "'from' must be before 'to'" })),
+        ));
+    }
+
+    let pool = state.read_pool().clone();
+    let bot_id = query.bot_id;
+    let rows = tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        load_cost_report(&mut conn, bot_id, from, to).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|r| r)
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({ "error": e })),
+        )
+    })?;
+
+    Ok(Json(CostReport {
+        from,
+        to,
+        total_cost: rows.iter().map(|r| r.cost).sum(),
+        rows,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cost_uses_input_and_output_prices() {
+        let price = TokenPrice {
+            input_per_1k: 0.01,
+            output_per_1k: 0.03,
+        };
+        let cost = compute_cost(price, 2000, 500, false);
+        assert!((cost - 0.035).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cache_hit_has_zero_marginal_cost() {
+        let price = TokenPrice {
+            input_per_1k: 0.01,
+            output_per_1k: 0.03,
+        };
+        assert_eq!(compute_cost(price, 2000, 500, true), 0.0);
+    }
+
+    #[test]
+    fn test_default_price_prefers_longest_prefix() {
+        assert_eq!(
+            default_price("gpt-4o-mini-2024-07-18").input_per_1k,
+            0.00015
+        );
+        assert_eq!(default_price("GPT-4o").input_per_1k, 0.0025);
+        assert_eq!(default_price("llama-3-8b-local"), TokenPrice::default());
+    }
+
+    #[test]
+    fn test_prices_are_keyed_by_model() {
+        let config = HashMap::from([
+            ("llm-model", "llama-3-8b-local"),
+            ("llm-price-input-per-1k", "0.5"),
+            ("llm-price-output-per-1k", "1.5"),
+            ("llm-price-input-per-1k-gpt-4o", "0.002"),
+        ]);
+        let lookup = |key: &str| config.get(key).map(|v| v.to_string());
+
+        let primary = price_from(lookup, "llama-3-8b-local");
+        assert_eq!(
+            primary,
+            TokenPrice {
+                input_per_1k: 0.5,
+                output_per_1k: 1.5
+            }
+        );
+
+        let fallback = price_from(lookup, "gpt-4o");
+        assert_eq!(fallback.input_per_1k, 0.002);
+        assert_eq!(
+            fallback.output_per_1k,
+            default_price("gpt-4o").output_per_1k
+        );
+
+        assert_eq!(price_from(lookup, "other-model"), TokenPrice::default());
+    }
+}
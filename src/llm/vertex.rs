@@ -4,6 +4,8 @@
 use log::{error, info, trace};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use crate::core::shared::llm_tls::llm_http_client;
+use crate::core::shared::outbound_proxy::http_client;
 use tokio::sync::RwLock;
 
 const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
@@ -114,7 +116,7 @@ impl VertexTokenManager {
             .map_err(|e| format!("Failed to encode JWT: {}", e))?;
 
         // Exchange JWT for access token
-        let client = reqwest::Client::new();
+        let client = http_client();
         let response = client
             .post(token_uri)
             .form(&[
@@ -197,7 +199,7 @@ impl VertexClient {
         });
 
         Self {
-            client: reqwest::Client::new(),
+            client: llm_http_client(&base_url),
             base_url,
             endpoint_path: endpoint,
             token_manager: Arc::new(tokio::sync::RwLock::new(None)),
@@ -145,8 +145,13 @@ use dotenvy::dotenv;
 use log::{error, info, trace, warn};
 use std::sync::Arc;
 
-#[tokio::main]
-async fn main() -> std::io::Result<()> {
+fn main() -> std::io::Result<()> {
+    dotenvy::dotenv().ok();
+    let runtime_config = crate::core::config::RuntimeConfig::from_env();
+    runtime_config.build()?.block_on(run(runtime_config))
+}
+
+async fn run(runtime_config: crate::core::config::RuntimeConfig) -> std::io::Result<()> {
     use main_module::{
         init_database, init_logging_and_i18n, load_config, parse_cli_args, run_axum_server,
         run_bootstrap, start_background_services, BootstrapProgress,
@@ -224,11 +229,42 @@ async fn main() -> std::io::Result<()> {
             );
         } else {
             info!("Secrets loaded from Vault");
+            crate::security::master_key::init_from_vault().await;
         }
     } else {
         trace!("Bootstrap not complete - skipping early SecretsManager init");
     }
 
+    // Handle `botserver migrate <status|up|down [N]>` CLI subcommands
+    if args.get(1).map(|s| s.as_str()) == Some("migrate") {
+        let code = match crate::core::shared::utils::create_conn()
+            .map_err(|e| e.to_string())
+            .and_then(|pool| pool.get().map_err(|e| e.to_string()))
+        {
+            Ok(mut conn) => crate::core::shared::migrations::run_cli(&mut conn, &args[2..]),
+            Err(e) => {
+                eprintln!("Failed to connect to database: {}", e);
+                1
+            }
+        };
+        std::process::exit(code);
+    }
+
+    // Handle `botserver backup` / `botserver restore <archive>`
+    if matches!(args.get(1).map(|s| s.as_str()), Some("backup") | Some("restore")) {
+        std::process::exit(crate::core::backup::run_cli(&args[1..]).await);
+    }
+
+    // Handle `botserver reset --confirm`: backup, stop components, delete the stack
+    if args.get(1).map(|s| s.as_str()) == Some("reset") {
+        std::process::exit(crate::core::backup::reset::run_cli(&args[1..]).await);
+    }
+
+    // Handle `botserver selftest`: checks every stack service once and exits
+    if args.get(1).map(|s| s.as_str()) == Some("selftest") {
+        std::process::exit(main_module::run_selftest().await);
+    }
+
     let noise_filters = "vaultrs=off,rustify=off,rustify_derive=off,\
          aws_sigv4=off,aws_smithy_checksums=off,aws_runtime=off,aws_smithy_http_client=off,\
          aws_smithy_runtime=off,aws_smithy_runtime_api=off,aws_sdk_s3=off,aws_config=off,\
@@ -329,7 +365,7 @@ async fn main() -> std::io::Result<()> {
     trace!("Reloading dotenv...");
     dotenv().ok();
 
-    let pool = init_database(&progress_tx).await?;
+    let pool = init_database(&cfg.database_pool, &progress_tx).await?;
     info!("Database initialized - PostgreSQL connected");
     let refreshed_cfg = load_config(&pool).await?;
     let config = std::sync::Arc::new(refreshed_cfg.clone());
@@ -376,13 +412,13 @@ async fn main() -> std::io::Result<()> {
         config.server.host, config.server.port
     );
 
-    let worker_count = std::thread::available_parallelism()
-        .map(|n| n.get())
-        .unwrap_or(4);
-
     let bot_orchestrator = crate::core::bot::BotOrchestrator::new(app_state.clone());
-    if let Err(e) = bot_orchestrator.mount_all_bots() {
-        error!("Failed to mount bots: {}", e);
+    let mount_report = bot_orchestrator.mount_all_bots();
+    if mount_report.failed > 0 {
+        warn!(
+            "{} bot(s) failed to mount, see /api/admin/bots/mount-status",
+            mount_report.failed
+        );
     }
 
     #[cfg(feature = "llm")]
@@ -421,7 +457,7 @@ async fn main() -> std::io::Result<()> {
     trace!("All system threads started, starting HTTP server...");
 
     info!("Server started on port {}", config.server.port);
-    if let Err(e) = run_axum_server(app_state, config.server.port, worker_count).await {
+    if let Err(e) = run_axum_server(app_state, config.server.port, &runtime_config).await {
         error!("Failed to start HTTP server: {}", e);
         std::process::exit(1);
     }
@@ -28,7 +28,11 @@ pub fn init_logging_and_i18n(no_console: bool, no_ui: bool) {
     use crate::core::i18n;
 
     if no_console || no_ui {
-        botlib::logging::init_compact_logger_with_style("info");
+        let config = crate::core::shared::log_control::LogConfig::from_env();
+        if let Err(e) = crate::core::shared::log_control::init(&config) {
+            eprintln!("Runtime log control unavailable ({e}), using the default logger");
+            botlib::logging::init_compact_logger_with_style("info");
+        }
         println!("Starting General Bots {}...", env!("CARGO_PKG_VERSION"));
     }
 
@@ -107,6 +111,15 @@ pub async fn run_bootstrap(
             && !vault_addr.contains("localhost")
             && !vault_addr.contains("127.0.0.1");
 
+        // A present but unusable init.json means Vault is initialized and its keys are
+        // lost; stop here instead of failing later with an unrelated unseal error.
+        if !is_remote_vault && vault_init_exists {
+            if let Err(e) = crate::core::bootstrap::vault::read_vault_init(vault_init_path) {
+                error!("{}", e);
+                return Err(std::io::Error::other(e.to_string()));
+            }
+        }
+
         let bootstrap_completed = is_remote_vault || ((env_exists || stack_env_exists) && vault_init_exists);
 
         info!(
@@ -186,6 +199,7 @@ pub async fn run_bootstrap(
             }
         };
 
+        crate::core::shared::outbound_proxy::set_outbound_proxy(cfg.proxy.clone());
         trace!("Config loaded, syncing templates to database...");
         progress_tx_clone
             .send(BootstrapProgress::UploadingTemplates)
@@ -210,7 +224,10 @@ pub async fn run_bootstrap(
                 warn!("Template drive upload error (non-blocking): {}", e);
             }
             Err(_) => {
-                warn!("Template drive upload timed out after 30s, continuing startup...");
+                warn!(
+                    "Template drive upload timed out after 30s, continuing startup; \
+                     remaining files are uploaded on next boot"
+                );
             }
         }
 
@@ -223,6 +240,7 @@ pub async fn run_bootstrap(
 
 /// Initialize database pool and run migrations
 pub async fn init_database(
+    pool_config: &crate::core::config::DatabasePoolConfig,
     progress_tx: &tokio::sync::mpsc::UnboundedSender<BootstrapProgress>,
 ) -> Result<crate::core::shared::utils::DbPool, std::io::Error> {
     use crate::core::shared::utils;
@@ -235,14 +253,30 @@ pub async fn init_database(
         .await
         .expect("Failed to initialize secrets manager");
 
-    let pool = match create_conn() {
+    let pool = match utils::create_conn_with(pool_config) {
         Ok(pool) => {
             trace!("Running database migrations...");
             info!("Running database migrations...");
             if let Err(e) = utils::run_migrations(&pool) {
                 error!("Failed to run migrations: {}", e);
 
-                warn!("Continuing despite migration errors - database might be partially migrated");
+                match crate::core::shared::migrations::MigrationFailureMode::from_env() {
+                    crate::core::shared::migrations::MigrationFailureMode::Continue => {
+                        warn!("Continuing despite migration errors (DB_MIGRATIONS_ON_ERROR=continue) - database might be partially migrated");
+                    }
+                    crate::core::shared::migrations::MigrationFailureMode::FailFast => {
+                        progress_tx
+                            .send(BootstrapProgress::BootstrapError(format!(
+                                "Database migration failed: {}",
+                                e
+                            )))
+                            .ok();
+                        return Err(std::io::Error::other(format!(
+                            "Database migration failed: {}. Fix the migration or set DB_MIGRATIONS_ON_ERROR=continue",
+                            e
+                        )));
+                    }
+                }
             } else {
                 info!("Database migrations completed successfully");
             }
@@ -281,6 +315,7 @@ pub async fn load_config(
             AppConfig::default()
         })
     });
+    crate::core::shared::outbound_proxy::set_outbound_proxy(refreshed_cfg.proxy.clone());
     let config = std::sync::Arc::new(refreshed_cfg.clone());
     info!(
         "Server configured to listen on {}:{}",
@@ -290,9 +325,10 @@ pub async fn load_config(
     Ok(refreshed_cfg)
 }
 
-/// Initialize Redis/Valkey cache with retry logic
+/// Candidate cache URLs: `CACHE_URL`, `REDIS_URL` or `VALKEY_URL`, else the Vault cache
+/// secret (without and with its password), else the local default.
 #[cfg(feature = "cache")]
-pub async fn init_redis() -> Option<Arc<redis::Client>> {
+pub async fn cache_urls() -> Vec<String> {
     use crate::core::secrets::{SecretPaths, SecretsManager};
 
     // Try environment variables first
@@ -320,6 +356,14 @@ urls.push(String::new());
         urls.push("redis://localhost:6379".to_string());
     }
 
+    urls
+}
+
+/// Initialize Redis/Valkey cache with retry logic
+#[cfg(feature = "cache")]
+pub async fn init_redis() -> Option<Arc<redis::Client>> {
+    let urls = cache_urls().await;
+
     info!("Attempting to connect to cache, trying {} URL(s)", urls.len());
 
     let max_attempts = 12;
@@ -394,58 +438,16 @@ urls.push(String::new());
     }
 }
 
-/// Create the AppState
-pub async fn create_app_state(
-    cfg: AppConfig,
-    pool: crate::core::shared::utils::DbPool,
-    #[cfg(feature = "cache")] redis_client: &Option<Arc<redis::Client>>,
-) -> Result<Arc<AppState>, std::io::Error> {
-    use std::collections::HashMap;
-
-    #[cfg(feature = "cache")]
-    let redis_client = redis_client.clone();
-    #[cfg(not(feature = "cache"))]
-    let redis_client: Option<Arc<redis::Client>> = None;
-
-    let web_adapter = Arc::new(WebChannelAdapter::new());
-    let voice_adapter = Arc::new(VoiceAdapter::new());
-
-    #[cfg(feature = "drive")]
-    let drive = match create_s3_operator(&cfg.drive).await {
-        Ok(client) => client,
-        Err(e) => {
-            return Err(std::io::Error::other(format!("Failed to initialize Drive: {}", e)));
-        }
-    };
-
-    #[cfg(feature = "drive")]
-    super::ensure_vendor_files_in_minio(&drive).await;
-
-    let session_manager = Arc::new(Mutex::new(SessionManager::new(
-        pool.get().map_err(|e| {
-            std::io::Error::other(format!("Failed to get database connection: {}", e))
-        })?,
-        #[cfg(feature = "cache")]
-        redis_client.clone(),
-    )));
-
-    #[cfg(feature = "directory")]
-    let (auth_service, zitadel_config) = init_directory_service()?;
-
-    #[cfg(feature = "directory")]
-    bootstrap_directory_admin(&zitadel_config).await;
-
-    let config_manager = ConfigManager::new(pool.clone());
-
-    let mut bot_conn = pool
-        .get()
-        .map_err(|e| std::io::Error::other(format!("Failed to get database connection: {}", e)))?;
-    let (default_bot_id, default_bot_name) = crate::core::bot::get_default_bot(&mut bot_conn);
-    info!(
-        "Using default bot: {} (id: {})",
-        default_bot_name, default_bot_id
-    );
+/// LLM connection settings of a bot: `LLM_URL`, `LLM_MODEL` and `LLM_KEY` (or
+/// `OPENAI_API_KEY`) take precedence over its `llm-*` configuration keys.
+pub struct LlmSettings {
+    pub url: String,
+    pub model: String,
+    pub key: String,
+    pub endpoint_path: String,
+}
 
+pub fn resolve_llm_settings(config_manager: &ConfigManager, bot_id: &Uuid) -> LlmSettings {
     // Check environment variables first for LLM configuration
     let llm_url_env = std::env::var("LLM_URL").ok();
     let llm_url = if let Some(url) = llm_url_env {
@@ -453,7 +455,7 @@ pub async fn create_app_state(
         url
     } else {
         config_manager
-            .get_config(&default_bot_id, "llm-url", Some(""))
+            .get_config(bot_id, "llm-url", Some(""))
             .unwrap_or_else(|_| "".to_string())
     };
     info!("LLM URL: {}", llm_url);
@@ -464,7 +466,7 @@ pub async fn create_app_state(
         model
     } else {
         config_manager
-            .get_config(&default_bot_id, "llm-model", Some(""))
+            .get_config(bot_id, "llm-model", Some(""))
             .unwrap_or_default()
     };
     if !llm_model.is_empty() {
@@ -475,7 +477,7 @@ pub async fn create_app_state(
         .or_else(|_| std::env::var("OPENAI_API_KEY"))
         .or_else(|_| {
             config_manager
-                .get_config(&default_bot_id, "llm-key", Some(""))
+                .get_config(bot_id, "llm-key", Some(""))
                 .map_err(|_| std::env::VarError::NotPresent)
         })
         .unwrap_or_default();
@@ -495,12 +497,98 @@ pub async fn create_app_state(
     // LLM endpoint path configuration
     let llm_endpoint_path = config_manager
         .get_config(
-            &default_bot_id,
+            bot_id,
             "llm-endpoint-path",
             Some("/v1/chat/completions"),
         )
         .unwrap_or_else(|_| "/v1/chat/completions".to_string());
 
+    LlmSettings {
+        url: llm_url,
+        model: llm_model,
+        key: llm_key,
+        endpoint_path: llm_endpoint_path,
+    }
+}
+
+/// Create the AppState
+pub async fn create_app_state(
+    cfg: AppConfig,
+    pool: crate::core::shared::utils::DbPool,
+    #[cfg(feature = "cache")] redis_client: &Option<Arc<redis::Client>>,
+) -> Result<Arc<AppState>, std::io::Error> {
+    use std::collections::HashMap;
+
+    #[cfg(feature = "cache")]
+    let redis_client = redis_client.clone();
+    #[cfg(not(feature = "cache"))]
+    let redis_client: Option<Arc<redis::Client>> = None;
+
+    let web_adapter = Arc::new(WebChannelAdapter::new());
+    let voice_adapter = Arc::new(VoiceAdapter::new());
+
+    #[cfg(feature = "drive")]
+    let drive_backend =
+        crate::drive::object_store::DriveBackend::from_env().map_err(std::io::Error::other)?;
+
+    // The filesystem backend runs without MinIO; features that still need S3 report
+    // the drive as unavailable.
+    #[cfg(feature = "drive")]
+    let drive = match drive_backend {
+        crate::drive::object_store::DriveBackend::Filesystem(_) => None,
+        crate::drive::object_store::DriveBackend::S3 => {
+            let client = create_s3_operator(&cfg.drive)
+                .await
+                .map_err(|e| std::io::Error::other(format!("Failed to initialize Drive: {}", e)))?;
+            super::ensure_vendor_files_in_minio(&client).await;
+            Some(client)
+        }
+    };
+
+    #[cfg(feature = "drive")]
+    let object_store = {
+        let store = drive_backend.build(drive.as_ref());
+        if let Some(store) = &store {
+            info!(
+                "Sheets and documents use the {} drive backend",
+                store.backend()
+            );
+        }
+        store
+    };
+
+    let session_manager = Arc::new(Mutex::new(SessionManager::new(
+        pool.get().map_err(|e| {
+            std::io::Error::other(format!("Failed to get database connection: {}", e))
+        })?,
+        #[cfg(feature = "cache")]
+        redis_client.clone(),
+    )));
+
+    #[cfg(feature = "directory")]
+    let (auth_service, zitadel_config) = init_directory_service()?;
+
+    #[cfg(feature = "directory")]
+    bootstrap_directory_admin(&zitadel_config).await;
+
+    let config_manager = ConfigManager::new(pool.clone());
+
+    let mut bot_conn = pool
+        .get()
+        .map_err(|e| std::io::Error::other(format!("Failed to get database connection: {}", e)))?;
+    let (default_bot_id, default_bot_name) = crate::core::bot::get_default_bot(&mut bot_conn);
+    info!(
+        "Using default bot: {} (id: {})",
+        default_bot_name, default_bot_id
+    );
+
+    let LlmSettings {
+        url: llm_url,
+        model: llm_model,
+        endpoint_path: llm_endpoint_path,
+        ..
+    } = resolve_llm_settings(&config_manager, &default_bot_id);
+
     #[cfg(feature = "llm")]
     let base_llm_provider = crate::llm::create_llm_provider_from_url(
         &llm_url,
@@ -555,8 +643,17 @@ pub async fn create_app_state(
     #[cfg(feature = "tasks")]
     let task_scheduler = None;
 
-    let (attendant_tx, _attendant_rx) =
-        tokio::sync::broadcast::channel::<crate::core::shared::state::AttendantNotification>(1000);
+    let message_bus = crate::core::shared::message_bus::message_bus_from_env(
+        #[cfg(feature = "cache")]
+        redis_client.as_ref(),
+    );
+    let response_channels = Arc::new(tokio::sync::Mutex::new(HashMap::new()));
+    let ws_router = Arc::new(crate::core::shared::ws_routing::socket_router_from_env(
+        Arc::clone(&response_channels),
+        Arc::clone(&message_bus),
+        #[cfg(feature = "cache")]
+        redis_client.as_ref(),
+    ));
 
     let (task_progress_tx, _task_progress_rx) =
         tokio::sync::broadcast::channel::<crate::core::shared::state::TaskProgressEvent>(1000);
@@ -584,13 +681,33 @@ pub async fn create_app_state(
         }
     }
 
+    let read_conn = cfg.database_replica_url.as_deref().and_then(|replica_url| {
+        match crate::core::shared::db_pool::build_pool(
+            replica_url,
+            &cfg.database_pool,
+            crate::core::shared::db_pool::replica_pool_stats(),
+        ) {
+            Ok(replica) => {
+                info!("Read-only queries will use the database replica");
+                Some(replica)
+            }
+            Err(e) => {
+                warn!("Failed to create replica pool, reads will use the primary: {}", e);
+                None
+            }
+        }
+    });
+
     let app_state = Arc::new(AppState {
         #[cfg(feature = "drive")]
-        drive: Some(drive),
+        drive,
+        #[cfg(feature = "drive")]
+        object_store,
         #[cfg(not(feature = "drive"))]
         drive: None,
         config: Some(cfg.clone()),
         conn: pool.clone(),
+        read_conn,
         database_url: database_url.clone(),
         bot_database_manager: bot_database_manager.clone(),
         bucket_name: "default.gbai".to_string(),
@@ -614,8 +731,8 @@ pub async fn create_app_state(
                 );
                 map
             })),
-            response_channels: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
-            active_streams: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
+            response_channels,
+            active_streams: Arc::new(crate::core::bot::generation::ActiveGenerations::default()),
             hear_channels: Arc::new(std::sync::Mutex::new(HashMap::new())),
         web_adapter: web_adapter.clone(),
         voice_adapter: voice_adapter.clone(),
@@ -629,7 +746,8 @@ pub async fn create_app_state(
             ext.insert_blocking(Arc::clone(&dynamic_llm_provider));
             ext
         },
-        attendant_broadcast: Some(attendant_tx),
+        message_bus,
+        ws_router: Arc::clone(&ws_router),
         task_progress_broadcast: Some(task_progress_tx),
         billing_alert_broadcast: None,
         task_manifests: Arc::new(std::sync::RwLock::new(HashMap::new())),
@@ -646,6 +764,8 @@ pub async fn create_app_state(
         rbac_manager: None,
     });
 
+    ws_router.start_relay().await;
+
     Ok(app_state)
 }
 
@@ -782,6 +902,10 @@ fn init_llm_provider(
 ) -> Arc<dyn crate::llm::LLMProvider> {
     use crate::llm::cache::{CacheConfig, CachedLLMProvider, EmbeddingService, LocalEmbeddingService};
 
+    info!(
+        "LLM/embedding TLS mode: {:?}",
+        crate::core::shared::llm_tls::llm_tls().mode
+    );
     if let Some(ref cache) = redis_client {
         let bot_id = Uuid::parse_str(default_bot_id).unwrap_or_default();
         let embedding_url = config_manager
@@ -876,9 +1000,21 @@ pub async fn start_background_services(
     info!("Memory monitor started");
     log_process_memory();
 
+    // Persist per-minute/hour/day metric rollups for historical queries
+    #[cfg(feature = "analytics")]
+    crate::analytics::rollups::spawn_rollup_worker(app_state.clone());
+
+    // Deliver queued outbound email at each account's send rate
+    #[cfg(feature = "mail")]
+    crate::email::outbox::spawn_outbox_worker(app_state.clone());
+
     let bot_orchestrator = BotOrchestrator::new(app_state.clone());
-    if let Err(e) = bot_orchestrator.mount_all_bots() {
-        error!("Failed to mount bots: {}", e);
+    let mount_report = bot_orchestrator.mount_all_bots();
+    if mount_report.failed > 0 {
+        warn!(
+            "{} bot(s) failed to mount, see /api/admin/bots/mount-status",
+            mount_report.failed
+        );
     }
 
     #[cfg(feature = "llm")]
@@ -891,6 +1027,11 @@ pub async fn start_background_services(
         trace!("ensure_llama_servers_running completed");
     }
 
+    #[cfg(any(feature = "research", feature = "llm"))]
+    tokio::spawn(crate::core::kb::embedding_model::verify_embedding_model(
+        app_state.clone(),
+    ));
+
   // Start DriveMonitor for S3/MinIO file watching and syncing
   #[cfg(feature = "drive")]
   start_drive_monitors(app_state.clone(), _pool).await;
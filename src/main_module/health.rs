@@ -4,8 +4,14 @@ use axum::extract::State;
 use axum::http::StatusCode;
 use axum::Json;
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::db_pool::{self, PoolStatus};
 use crate::core::shared::state::AppState;
+use crate::core::shared::utils::DbPool;
+
+const DB_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub async fn health_check(State(state): State<Arc<AppState>>) -> (StatusCode, Json<serde_json::Value>) {
     let db_ok = state.conn.get().is_ok();
@@ -33,6 +39,60 @@ pub async fn health_check(State(state): State<Arc<AppState>>) -> (StatusCode, Js
     )
 }
 
+/// Readiness probe: fails when the database is unreachable or the embedding model is
+/// missing, and reports pool saturation so load balancers and dashboards can see
+/// exhaustion building up.
+pub async fn health_ready(State(state): State<Arc<AppState>>) -> (StatusCode, Json<serde_json::Value>) {
+    let pool = state.conn.clone();
+    let (db_ok, pool_status) = tokio::task::spawn_blocking(move || probe_database(&pool))
+        .await
+        .unwrap_or_else(|_| {
+            let stats = db_pool::primary_pool_stats();
+            (false, db_pool::pool_status(&state.conn, &stats))
+        });
+
+    #[cfg(any(feature = "research", feature = "llm"))]
+    let embedding = crate::core::kb::embedding_model::model_status();
+    #[cfg(any(feature = "research", feature = "llm"))]
+    let embedding_ok = embedding.is_ready();
+    #[cfg(not(any(feature = "research", feature = "llm")))]
+    let embedding_ok = true;
+
+    let code = if db_ok && embedding_ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let status = match (db_ok && embedding_ok, pool_status.saturated) {
+        (false, _) => "unavailable",
+        (true, true) => "saturated",
+        (true, false) => "ready",
+    };
+
+    #[allow(unused_mut)]
+    let mut body = serde_json::json!({
+        "status": status,
+        "database": db_ok,
+        "db_pool": pool_status,
+    });
+    #[cfg(any(feature = "research", feature = "llm"))]
+    {
+        body["embedding_model"] = serde_json::json!(embedding);
+    }
+
+    (code, Json(body))
+}
+
+/// A saturated pool is reported from its state: every connection is checked out, so the
+/// database is answering, and queueing for one would hold the probe for the full
+/// `connection_timeout`. Otherwise a connection is checked out with a short timeout.
+fn probe_database(pool: &DbPool) -> (bool, PoolStatus) {
+    let stats = db_pool::primary_pool_stats();
+    let status = db_pool::pool_status(pool, &stats);
+    let db_ok = status.saturated || pool.get_timeout(DB_PROBE_TIMEOUT).is_ok();
+    (db_ok, status)
+}
+
 pub async fn health_check_simple() -> (StatusCode, Json<serde_json::Value>) {
     let commit = option_env!("BOTSERVER_COMMIT").unwrap_or("unknown");
     (
@@ -66,7 +126,7 @@ pub struct ClientErrorData {
 }
 
 pub async fn receive_client_errors(
-    Json(payload): Json<ClientErrorsRequest>,
+    ApiJson(payload): ApiJson<ClientErrorsRequest>,
 ) -> (StatusCode, Json<serde_json::Value>) {
     for error in &payload.errors {
         log::error!(
@@ -87,3 +147,24 @@ pub async fn receive_client_errors(
         })),
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel::r2d2::{ConnectionManager, Pool};
+    use std::time::Instant;
+
+    #[test]
+    fn test_probe_does_not_wait_for_connection_timeout() {
+        let pool = Pool::builder()
+            .connection_timeout(Duration::from_secs(30))
+            .build_unchecked(ConnectionManager::new("postgres://127.0.0.1:1/unused"));
+
+        let started = Instant::now();
+        let (db_ok, status) = probe_database(&pool);
+
+        assert!(!db_ok);
+        assert!(!status.saturated);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
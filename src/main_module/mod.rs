@@ -3,14 +3,18 @@
 mod bootstrap;
 mod drive_utils;
 mod health;
+mod selftest;
 mod server;
 mod shutdown;
+mod static_site;
+mod tls;
 mod types;
 
 pub use bootstrap::*;
 #[cfg(feature = "drive")]
 pub use drive_utils::*;
 pub use health::*;
+pub use selftest::run_selftest;
 pub use server::*;
 pub use shutdown::*;
 pub use types::*;
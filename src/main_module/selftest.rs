@@ -0,0 +1,307 @@
+//! `botserver selftest`: verifies that a bootstrapped stack works end to end by
+//! exercising each service the server depends on once.
+
+use crate::core::shared::utils::{create_conn, get_secrets_manager, DbPool};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+/// Longest a single check may take before it counts as failed.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+pub struct CheckOutcome {
+    pub name: &'static str,
+    /// Detail on success, reason on failure.
+    pub result: Result<String, String>,
+    pub elapsed: Duration,
+}
+
+async fn run_check<F>(name: &'static str, check: F) -> CheckOutcome
+where
+    F: Future<Output = Result<String, String>>,
+{
+    let started = Instant::now();
+    let result = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {}s", CHECK_TIMEOUT.as_secs())),
+    };
+    let outcome = CheckOutcome {
+        name,
+        result,
+        elapsed: started.elapsed(),
+    };
+    println!("{}", format_outcome(&outcome));
+    outcome
+}
+
+fn format_outcome(outcome: &CheckOutcome) -> String {
+    let (status, detail) = match &outcome.result {
+        Ok(detail) => ("PASS", detail),
+        Err(reason) => ("FAIL", reason),
+    };
+    format!(
+        "{:<10} {} ({} ms) — {}",
+        outcome.name,
+        status,
+        outcome.elapsed.as_millis(),
+        detail
+    )
+}
+
+/// Process exit code for a finished run: non-zero when any check failed.
+fn exit_code(outcomes: &[CheckOutcome]) -> i32 {
+    i32::from(outcomes.iter().any(|o| o.result.is_err()))
+}
+
+/// Entry point for `botserver selftest`. Returns the process exit code.
+pub async fn run_selftest() -> i32 {
+    println!("=== Self-test ===");
+    let pool = create_conn().map_err(|e| format!("database unavailable: {}", e));
+
+    let mut outcomes = vec![
+        run_check("database", check_database(pool.clone())).await,
+        run_check("vault", check_vault()).await,
+    ];
+    #[cfg(feature = "drive")]
+    outcomes.push(run_check("drive", check_drive(pool.clone())).await);
+    #[cfg(feature = "cache")]
+    outcomes.push(run_check("cache", check_cache()).await);
+    #[cfg(feature = "llm")]
+    outcomes.push(run_check("llm", check_llm(pool.clone())).await);
+    #[cfg(any(feature = "research", feature = "llm"))]
+    outcomes.push(run_check("embedding", check_embedding(pool)).await);
+
+    let failed = outcomes.iter().filter(|o| o.result.is_err()).count();
+    println!("{} passed, {} failed", outcomes.len() - failed, failed);
+    exit_code(&outcomes)
+}
+
+async fn blocking<T, F>(f: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("task failed: {}", e))?
+}
+
+async fn check_database(pool: Result<DbPool, String>) -> Result<String, String> {
+    let pool = pool?;
+    blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        let states = crate::core::shared::migrations::migration_status(&mut conn)
+            .map_err(|e| format!("cannot read migrations: {}", e))?;
+        let pending: Vec<&str> = states
+            .iter()
+            .filter(|s| !s.applied)
+            .map(|s| s.name.as_str())
+            .collect();
+        match pending.first() {
+            None => Ok(format!("connected, {} migrations applied", states.len())),
+            Some(first) => Err(format!(
+                "{} pending migration(s), starting with {}; run `botserver migrate up`",
+                pending.len(),
+                first
+            )),
+        }
+    })
+    .await
+}
+
+async fn check_vault() -> Result<String, String> {
+    use crate::core::secrets::SecretPaths;
+
+    let manager = get_secrets_manager()
+        .await
+        .ok_or("secrets manager not initialized; is the stack bootstrapped?")?;
+    if !manager.is_enabled() {
+        return Err("Vault is not configured".to_string());
+    }
+    if !manager.health_check().await.unwrap_or(false) {
+        return Err("Vault is sealed or unreachable".to_string());
+    }
+    let secret = manager
+        .get_secret(SecretPaths::TABLES)
+        .await
+        .map_err(|e| format!("cannot read {}: {}", SecretPaths::TABLES, e))?;
+    if secret.is_empty() {
+        return Err(format!("{} is empty", SecretPaths::TABLES));
+    }
+    Ok(format!("unsealed, {} readable", SecretPaths::TABLES))
+}
+
+/// Bot configuration when the database is reachable, else environment defaults.
+#[cfg(feature = "drive")]
+async fn load_app_config(pool: Result<DbPool, String>) -> crate::core::config::AppConfig {
+    use crate::core::config::AppConfig;
+
+    blocking(move || {
+        let from_db = pool.and_then(|p| AppConfig::from_database(&p).map_err(|e| e.to_string()));
+        Ok(from_db
+            .or_else(|_| AppConfig::from_env())
+            .unwrap_or_default())
+    })
+    .await
+    .unwrap_or_default()
+}
+
+#[cfg(feature = "drive")]
+async fn check_drive(pool: Result<DbPool, String>) -> Result<String, String> {
+    use aws_sdk_s3::primitives::ByteStream;
+
+    let config = load_app_config(pool).await;
+    let drive = crate::core::shared::utils::create_s3_operator(&config.drive)
+        .await
+        .map_err(|e| format!("cannot create client: {}", e))?;
+
+    let bucket = "default.gbai";
+    let key = format!(".selftest/{}.txt", uuid::Uuid::new_v4());
+    let payload = format!("botserver selftest {}", chrono::Utc::now().to_rfc3339());
+
+    drive
+        .put_object()
+        .bucket(bucket)
+        .key(&key)
+        .body(ByteStream::from(payload.clone().into_bytes()))
+        .send()
+        .await
+        .map_err(|e| format!("put s3://{}/{} failed: {}", bucket, key, e))?;
+
+    let read = match drive.get_object().bucket(bucket).key(&key).send().await {
+        Ok(object) => object
+            .body
+            .collect()
+            .await
+            .map(|data| data.into_bytes().to_vec())
+            .map_err(|e| format!("get failed: {}", e)),
+        Err(e) => Err(format!("get failed: {}", e)),
+    };
+    let deleted = drive.delete_object().bucket(bucket).key(&key).send().await;
+
+    if read? != payload.as_bytes() {
+        return Err("object read back differs from what was written".to_string());
+    }
+    deleted.map_err(|e| format!("delete failed: {}", e))?;
+    Ok(format!("put/get/delete round-trip on {}", bucket))
+}
+
+#[cfg(feature = "cache")]
+async fn check_cache() -> Result<String, String> {
+    let mut errors = Vec::new();
+    for url in super::cache_urls().await {
+        let host = url.split('@').next_back().unwrap_or_default().to_string();
+        let ping = blocking(move || {
+            let client = redis::Client::open(url.as_str()).map_err(|e| e.to_string())?;
+            let mut conn = client
+                .get_connection_with_timeout(Duration::from_secs(3))
+                .map_err(|e| e.to_string())?;
+            redis::cmd("PING")
+                .query::<String>(&mut conn)
+                .map_err(|e| e.to_string())
+        })
+        .await;
+        match ping {
+            Ok(reply) => return Ok(format!("{} answered {}", host, reply)),
+            Err(e) => errors.push(format!("{}: {}", host, e)),
+        }
+    }
+    Err(format!("PING failed ({})", errors.join("; ")))
+}
+
+/// Id of the default bot, whose configuration the LLM and embedding checks use.
+#[cfg(any(feature = "research", feature = "llm"))]
+async fn default_bot(pool: &DbPool) -> Result<uuid::Uuid, String> {
+    let pool = pool.clone();
+    blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        Ok(crate::core::bot::get_default_bot(&mut conn).0)
+    })
+    .await
+}
+
+#[cfg(feature = "llm")]
+async fn check_llm(pool: Result<DbPool, String>) -> Result<String, String> {
+    use crate::core::config::ConfigManager;
+
+    let pool = pool?;
+    let bot_id = default_bot(&pool).await?;
+    let settings = blocking(move || {
+        Ok(super::resolve_llm_settings(
+            &ConfigManager::new(pool),
+            &bot_id,
+        ))
+    })
+    .await?;
+    if settings.url.is_empty() {
+        return Err("llm-url is not configured".to_string());
+    }
+
+    let provider = crate::llm::create_llm_provider_from_url(
+        &settings.url,
+        (!settings.model.is_empty()).then(|| settings.model.clone()),
+        Some(settings.endpoint_path.clone()),
+        None,
+    );
+    let prompt = "Reply with the single word OK.";
+    let messages = serde_json::json!([{ "role": "user", "content": prompt }]);
+    let reply = provider
+        .generate(prompt, &messages, &settings.model, &settings.key)
+        .await
+        .map_err(|e| format!("completion from {} failed: {}", settings.url, e))?;
+    if reply.trim().is_empty() {
+        return Err(format!("{} returned an empty completion", settings.url));
+    }
+    Ok(format!(
+        "{} replied with {} chars",
+        settings.url,
+        reply.len()
+    ))
+}
+
+#[cfg(any(feature = "research", feature = "llm"))]
+async fn check_embedding(pool: Result<DbPool, String>) -> Result<String, String> {
+    use crate::core::kb::embedding_generator::{EmbeddingConfig, KbEmbeddingGenerator};
+
+    let pool = pool?;
+    let bot_id = default_bot(&pool).await?;
+    let config = blocking(move || Ok(EmbeddingConfig::from_bot_config(&pool, &bot_id))).await?;
+    if config.embedding_url.is_empty() {
+        return Err("embedding-url is not configured".to_string());
+    }
+
+    let url = config.embedding_url.clone();
+    let expected = config.dimensions;
+    let embedding = KbEmbeddingGenerator::new(config)
+        .generate_single_embedding("botserver self-test")
+        .await
+        .map_err(|e| format!("{} failed: {}", url, e))?;
+    if embedding.vector.is_empty() {
+        return Err(format!("{} returned an empty vector", url));
+    }
+    if embedding.vector.len() != expected {
+        return Err(format!(
+            "{} returned {} dimensions, embedding-dimensions is {}",
+            url,
+            embedding.vector.len(),
+            expected
+        ));
+    }
+    Ok(format!("{} returned {} dimensions", url, expected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_any_failed_check_fails_the_run() {
+        let passed = run_check("database", async { Ok("connected".to_string()) }).await;
+        assert_eq!(exit_code(std::slice::from_ref(&passed)), 0);
+        assert!(format_outcome(&passed).contains("PASS"));
+
+        let failed = run_check("cache", async { Err("PING failed".to_string()) }).await;
+        assert!(format_outcome(&failed).contains("FAIL"));
+        assert_eq!(exit_code(&[passed, failed]), 1);
+    }
+}
@@ -1,7 +1,7 @@
 //! HTTP server initialization and routing
 
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Json, Router,
 };
 use log::{error, info, warn};
@@ -10,6 +10,7 @@ use std::sync::Arc;
 use tower_http::trace::TraceLayer;
 use tower_http::services::ServeDir;
 
+use crate::core::config::RuntimeConfig;
 use crate::core::shared::state::AppState;
 use crate::core::urls::ApiUrls;
 use crate::security::{
@@ -21,13 +22,21 @@ use crate::security::{
 };
 use botlib::SystemLimits;
 
-use super::{health_check, health_check_simple, receive_client_errors, shutdown_signal};
+use super::tls::{
+    build_server_config, log_tls_settings, spawn_certificate_reloader, spawn_https_redirect,
+};
+use super::{health_check, health_check_simple, health_ready, receive_client_errors, shutdown_signal};
 
 pub async fn run_axum_server(
     app_state: Arc<AppState>,
     port: u16,
-    _worker_count: usize,
+    runtime: &RuntimeConfig,
 ) -> std::io::Result<()> {
+    info!(
+        "Tokio runtime: {} worker threads, up to {} blocking threads",
+        runtime.worker_threads, runtime.max_blocking_threads
+    );
+
     // Load CORS allowed origins from bot config database if available
     // Config key: cors-allowed-origins in config.csv
     if let Ok(mut conn) = app_state.conn.get() {
@@ -78,6 +87,7 @@ pub async fn run_axum_server(
             .add_public_path("/api/product") // For desktop UI initialization
             .add_public_path("/") // Allow all bot routes (fallback to UI)
     );
+    crate::security::auth_api::set_ws_auth_config(Arc::clone(&auth_config));
 
     let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| {
         warn!("JWT_SECRET not set, using default development secret - DO NOT USE IN PRODUCTION");
@@ -161,22 +171,55 @@ pub async fn run_axum_server(
 
     let mut api_router = Router::new()
         .route("/health", get(health_check_simple))
+        .route("/health/ready", get(health_ready))
         .route(ApiUrls::HEALTH, get(health_check))
         .route("/api/config/reload", post(crate::core::config_reload::reload_config))
         .route("/api/product", get(get_product_config))
         .route("/api/manifest", get(get_workspace_manifest))
         .route("/api/client-errors", post(receive_client_errors))
         .route("/api/bot/config", get(crate::core::bot::get_bot_config))
+        .route(ApiUrls::BOTS, get(crate::core::bot::catalog::handle_list_bots))
         .route(ApiUrls::SESSIONS, post(crate::core::session::create_session))
         .route(ApiUrls::SESSIONS, get(crate::core::session::get_sessions))
+        .route(ApiUrls::SESSIONS_SEARCH, get(crate::core::session::search::handle_search_messages))
+        .route(ApiUrls::SESSION_BY_ID, delete(crate::core::session::delete_session))
         .route(ApiUrls::SESSION_HISTORY, get(crate::core::session::get_session_history))
+        .route(
+            ApiUrls::SESSION_VARIABLES,
+            get(crate::core::session::variables::handle_get_session_variables),
+        )
+        .route(
+            ApiUrls::SESSION_FORM,
+            get(crate::core::session::forms::handle_get_session_form),
+        )
         .route(ApiUrls::SESSION_START, post(crate::core::session::start_session))
+        .route(ApiUrls::SESSION_FORK, post(crate::core::session::fork::handle_fork_session))
+        .route(
+            ApiUrls::SESSION_CANCEL,
+            post(crate::core::bot::generation::handle_cancel_generation),
+        )
         .route(ApiUrls::WS, get(crate::core::bot::websocket_handler))
         .route("/ws/:bot_name", get(crate::core::bot::websocket_handler_with_bot));
 
     #[cfg(feature = "drive")]
     {
-        api_router = // drive routes are handled by DriveMonitor, no HTTP routes needed
+        // File sync is handled by DriveMonitor; only browsing and storage administration
+        // are exposed.
+        api_router = api_router
+            .merge(crate::drive::browser::configure())
+            .merge(crate::drive::quota::configure());
+    }
+
+    #[cfg(any(feature = "research", feature = "llm"))]
+    {
+        api_router = api_router
+            .merge(crate::core::kb::query_debug::configure())
+            .merge(crate::core::kb::reindex::configure());
+    }
+
+    #[cfg(feature = "llm")]
+    {
+        api_router = api_router.merge(crate::llm::capabilities::configure());
     }
 
     #[cfg(feature = "directory")]
@@ -272,7 +315,12 @@ pub async fn run_axum_server(
 
     #[cfg(feature = "mail")]
     {
-        api_router = api_router.merge(crate::email::configure());
+        api_router = api_router.merge(crate::email::configure().route_layer(
+            axum::middleware::from_fn_with_state(
+                app_state.clone(),
+                crate::core::shared::bot_features::require_email_feature,
+            ),
+        ));
     }
 
     #[cfg(all(feature = "calendar", feature = "scripting"))]
@@ -281,9 +329,14 @@ pub async fn run_axum_server(
             app_state.conn.clone(),
         ));
 
-        api_router = api_router.merge(crate::calendar::caldav::create_caldav_router(
-            calendar_engine,
-        ));
+        api_router = api_router.merge(
+            crate::calendar::caldav::create_caldav_router(calendar_engine).route_layer(
+                axum::middleware::from_fn_with_state(
+                    app_state.clone(),
+                    crate::calendar::require_calendar_feature,
+                ),
+            ),
+        );
     }
 
     #[cfg(feature = "tasks")]
@@ -293,8 +346,15 @@ pub async fn run_axum_server(
 
     #[cfg(feature = "calendar")]
     {
-        api_router = api_router.merge(crate::calendar::configure_calendar_routes());
-        api_router = api_router.merge(crate::calendar::ui::configure_calendar_ui_routes());
+        let calendar_gate = axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            crate::calendar::require_calendar_feature,
+        );
+        api_router = api_router.merge(
+            crate::calendar::configure_calendar_routes()
+                .merge(crate::calendar::ui::configure_calendar_ui_routes())
+                .route_layer(calendar_gate),
+        );
     }
 
     #[cfg(feature = "analytics")]
@@ -376,6 +436,25 @@ pub async fn run_axum_server(
         api_router = api_router.merge(crate::auto_task::configure_autotask_routes());
     }
     api_router = api_router.merge(crate::core::shared::admin::configure());
+
+    let caddy_routes = crate::core::proxy::caddy_routes();
+    if let Some(routes) = &caddy_routes {
+        api_router =
+            api_router.merge(crate::core::proxy::configure_proxy_routes(Arc::clone(routes)));
+    }
+
+    let dns_config = crate::core::dns::DnsConfig::from_env();
+    if dns_config.enabled {
+        match crate::core::dns::DynamicDnsService::load(dns_config) {
+            Ok(dns_service) => {
+                api_router = api_router.merge(crate::core::dns::configure_record_routes(
+                    Arc::new(dns_service),
+                    caddy_routes.clone(),
+                ));
+            }
+            Err(e) => warn!("DNS record management unavailable: {}", e),
+        }
+    }
     #[cfg(feature = "workspaces")]
     {
         api_router = api_router.merge(crate::workspaces::configure_workspaces_routes());
@@ -410,7 +489,12 @@ pub async fn run_axum_server(
     }
     #[cfg(feature = "mail")]
     {
-        api_router = api_router.merge(crate::email::ui::configure_email_ui_routes());
+        api_router = api_router.merge(crate::email::ui::configure_email_ui_routes().route_layer(
+            axum::middleware::from_fn_with_state(
+                app_state.clone(),
+                crate::core::shared::bot_features::require_email_feature,
+            ),
+        ));
     }
     #[cfg(feature = "meet")]
     {
@@ -545,10 +629,50 @@ pub async fn run_axum_server(
     app_state_with_auth.rbac_manager = Some(Arc::clone(&rbac_manager));
     let app_state = Arc::new(app_state_with_auth);
 
+    let http_timeouts = Arc::new(
+        app_state
+            .config
+            .as_ref()
+            .map(|c| c.http_timeouts.clone())
+            .unwrap_or_else(crate::core::config::HttpTimeoutConfig::from_env),
+    );
+    info!(
+        "Handler timeouts: {:?} default, {:?} for {}",
+        http_timeouts.default,
+        http_timeouts.long,
+        http_timeouts.long_prefixes.join(", ")
+    );
+
+    #[cfg(feature = "directory")]
+    crate::directory::sessions::init_revocations(app_state.conn.clone()).await;
+
+    let maintenance = crate::core::shared::maintenance::init_maintenance(app_state.conn.clone())
+        .await
+        .state();
+    if maintenance.enabled {
+        warn!(
+            "Starting in maintenance mode (since {}), API requests get 503 until it is disabled",
+            maintenance
+                .since
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| "unknown".to_string())
+        );
+    }
+
+    let site_config = app_state
+        .config
+        .as_ref()
+        .map(|c| c.site.clone())
+        .unwrap_or_else(crate::core::config::StaticSiteConfig::from_env);
+    info!(
+        "Site mounted at {} (SPA fallback: {})",
+        site_config.mount_path, site_config.spa_fallback
+    );
+
     let base_router = Router::new()
         .merge(api_router.with_state(app_state.clone()))
-        // Static files fallback for legacy /apps/* paths
-        .nest_service("/static", ServeDir::new(&site_path));
+        // Static files for legacy /apps/* paths
+        .merge(super::static_site::site_router(&site_path, &site_config));
 
     // Add UI routes based on availability
     let app_with_ui = if ui_path_exists {
@@ -568,6 +692,14 @@ pub async fn run_axum_server(
 
     let app =
         app_with_ui
+            // Innermost, so only the handler itself counts against the deadline
+            .layer(axum::middleware::from_fn(move |req, next| {
+                crate::core::shared::request_timeout::request_timeout_middleware(
+                    Arc::clone(&http_timeouts),
+                    req,
+                    next,
+                )
+            }))
             // Security middleware stack (order matters - last added is outermost/runs first)
             .layer(axum::middleware::from_fn(security_headers_middleware))
             .layer(security_headers_extension)
@@ -592,6 +724,14 @@ pub async fn run_axum_server(
                     }
                 },
             ))
+            // Scheduled backups hold writes back while they copy the stack
+            .layer(axum::middleware::from_fn(
+                crate::core::backup::quiesce::write_gate_middleware,
+            ))
+            // Maintenance mode answers 503 before authentication so clients see why
+            .layer(axum::middleware::from_fn(
+                crate::core::shared::maintenance::maintenance_middleware,
+            ))
             // Panic handler catches panics and returns safe 500 responses
             .layer(axum::middleware::from_fn(move |req, next| {
                 let config = panic_config.clone();
@@ -600,6 +740,10 @@ pub async fn run_axum_server(
                 }
             }))
             .layer(axum::Extension(app_state.clone()))
+            // Caps text, form and upload bodies; ApiJson applies its own JSON limit
+            .layer(axum::extract::DefaultBodyLimit::max(
+                crate::core::shared::api_json::body_limits().text_bytes,
+            ))
             .layer(cors)
             .layer(TraceLayer::new_for_http());
 
@@ -614,10 +758,20 @@ pub async fn run_axum_server(
         .map(|v| v == "true" || v == "1")
         .unwrap_or(false);
 
-    if !disable_tls && cert_path.exists() && key_path.exists() {
-        let tls_config = axum_server::tls_rustls::RustlsConfig::from_pem_file(cert_path, key_path)
-            .await
-            .map_err(std::io::Error::other)?;
+    let served = if !disable_tls && cert_path.exists() && key_path.exists() {
+        let tls_settings = app_state
+            .config
+            .as_ref()
+            .map(|c| c.tls.clone())
+            .unwrap_or_else(crate::core::config::ServerTlsConfig::from_env);
+        log_tls_settings(&tls_settings);
+        let server_config = build_server_config(&tls_settings, &cert_path, &key_path)?;
+        let tls_config =
+            axum_server::tls_rustls::RustlsConfig::from_config(Arc::new(server_config));
+        if let Some(redirect_port) = tls_settings.http_redirect_port {
+            spawn_https_redirect(redirect_port, port);
+        }
+        spawn_certificate_reloader(tls_config.clone(), tls_settings, cert_path, key_path);
 
         info!("HTTPS server listening on {} with TLS", addr);
 
@@ -632,7 +786,7 @@ pub async fn run_axum_server(
 
         axum_server::bind_rustls(addr, tls_config)
             .handle(handle)
-            .serve(app.into_make_service())
+            .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
             .await
             .map_err(|e| {
                 error!("HTTPS server failed on {}: {}", addr, e);
@@ -656,9 +810,23 @@ pub async fn run_axum_server(
             }
         };
         info!("HTTP server listening on {}", addr);
-        axum::serve(listener, app.into_make_service())
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
             .with_graceful_shutdown(shutdown_signal())
             .await
             .map_err(std::io::Error::other)
+    };
+
+    // Sheet edits are buffered in memory; write them out before the process exits.
+    #[cfg(feature = "sheet")]
+    {
+        let flushed = crate::sheet::storage::flush_all_sheets(&app_state).await;
+        if flushed > 0 {
+            info!("Saved {} sheets with pending edits", flushed);
+        }
     }
+
+    served
 }
@@ -0,0 +1,121 @@
+//! Static site serving with an optional single-page-app fallback to `index.html`.
+
+use axum::extract::OriginalUri;
+use axum::handler::HandlerWithoutStateExt;
+use axum::http::{header, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use std::path::PathBuf;
+use tower_http::services::ServeDir;
+
+use crate::core::config::StaticSiteConfig;
+
+/// Whether a request that matched no file should get the app's index instead: only page
+/// navigations (GET/HEAD, last segment without an extension) outside the API and
+/// websocket prefixes, so missing assets and unknown endpoints still return 404.
+fn wants_index(method: &Method, path: &str) -> bool {
+    if method != Method::GET && method != Method::HEAD {
+        return false;
+    }
+    if ["/api", "/ws"]
+        .iter()
+        .any(|prefix| path == *prefix || path.starts_with(&format!("{}/", prefix)))
+    {
+        return false;
+    }
+    let last = path.rsplit('/').next().unwrap_or_default();
+    !last.contains('.')
+}
+
+async fn serve_index(index: PathBuf, method: Method, path: &str) -> Response {
+    if !wants_index(&method, path) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    match tokio::fs::read(&index).await {
+        Ok(body) => (
+            [
+                (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+                (header::CACHE_CONTROL, "no-cache"),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Router serving `site_path` at the configured mount path.
+pub fn site_router<S>(site_path: &str, config: &StaticSiteConfig) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let serve_dir = ServeDir::new(site_path);
+    if !config.spa_fallback {
+        return Router::new().nest_service(&config.mount_path, serve_dir);
+    }
+
+    let index = PathBuf::from(site_path).join(&config.index_file);
+    let fallback = move |method: Method, OriginalUri(uri): OriginalUri| {
+        let index = index.clone();
+        async move { serve_index(index, method, uri.path()).await }
+    };
+    // `fallback` keeps the index's 200; `not_found_service` would force a 404.
+    Router::new().nest_service(
+        &config.mount_path,
+        serve_dir.fallback(fallback.into_service()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_page_navigations_fall_back_to_index() {
+        assert!(wants_index(&Method::GET, "/static/dashboard/bots/42"));
+        assert!(wants_index(&Method::HEAD, "/static"));
+        assert!(!wants_index(&Method::POST, "/static/dashboard"));
+        assert!(!wants_index(&Method::GET, "/static/assets/app.3f2a.js"));
+        assert!(!wants_index(&Method::GET, "/api/unknown"));
+        assert!(!wants_index(&Method::GET, "/ws"));
+        assert!(!wants_index(&Method::GET, "/ws/sheet/1"));
+        assert!(wants_index(&Method::GET, "/static/apiary"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_index_is_not_found() {
+        let response = serve_index(
+            PathBuf::from("/nonexistent/index.html"),
+            Method::GET,
+            "/static/app",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_client_side_route_gets_index_with_ok() {
+        use axum::body::Body;
+        use axum::http::Request;
+        use tower::ServiceExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "<div id=app></div>").unwrap();
+        let app: Router = site_router(dir.path().to_str().unwrap(), &StaticSiteConfig::default());
+
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+        let response = app
+            .clone()
+            .oneshot(get("/static/dashboard/bots/42"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"<div id=app></div>");
+
+        let response = app.oneshot(get("/static/missing.js")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,265 @@
+//! rustls configuration for the HTTPS listener: protocol floor, cipher allow-list,
+//! certificate hot reload and the optional HTTP-to-HTTPS redirect listener.
+
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum_server::tls_rustls::RustlsConfig;
+use log::{error, info, warn};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, SupportedCipherSuite, SupportedProtocolVersion};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::core::config::{ServerTlsConfig, TlsMinVersion};
+
+fn protocol_versions(min: TlsMinVersion) -> &'static [&'static SupportedProtocolVersion] {
+    match min {
+        TlsMinVersion::Tls12 => rustls::ALL_VERSIONS,
+        TlsMinVersion::Tls13 => &[&rustls::version::TLS13],
+    }
+}
+
+fn suite_name(suite: &SupportedCipherSuite) -> String {
+    format!("{:?}", suite.suite())
+}
+
+/// Narrows `available` to the configured allow-list, keeping the provider's preference
+/// order. Unknown names are rejected so a typo cannot silently weaken the policy.
+fn select_cipher_suites(
+    available: &[SupportedCipherSuite],
+    allowed: Option<&[String]>,
+) -> Result<Vec<SupportedCipherSuite>, String> {
+    let Some(allowed) = allowed else {
+        return Ok(available.to_vec());
+    };
+    if let Some(unknown) = allowed
+        .iter()
+        .find(|name| !available.iter().any(|s| suite_name(s) == **name))
+    {
+        return Err(format!("Unsupported TLS cipher suite: {}", unknown));
+    }
+    Ok(available
+        .iter()
+        .filter(|s| allowed.contains(&suite_name(s)))
+        .copied()
+        .collect())
+}
+
+fn crypto_provider(settings: &ServerTlsConfig) -> Result<CryptoProvider, String> {
+    let mut provider = rustls::crypto::ring::default_provider();
+    let versions = protocol_versions(settings.min_version);
+    provider.cipher_suites =
+        select_cipher_suites(&provider.cipher_suites, settings.cipher_suites.as_deref())?
+            .into_iter()
+            .filter(|s| versions.iter().any(|v| s.version() == *v))
+            .collect();
+    if provider.cipher_suites.is_empty() {
+        return Err("No TLS cipher suite left for the configured minimum version".to_string());
+    }
+    Ok(provider)
+}
+
+pub fn build_server_config(
+    settings: &ServerTlsConfig,
+    cert_path: &Path,
+    key_path: &Path,
+) -> std::io::Result<ServerConfig> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            std::io::Error::other(format!("Failed to load {}: {}", cert_path.display(), e))
+        })?;
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| {
+        std::io::Error::other(format!("Failed to load {}: {}", key_path.display(), e))
+    })?;
+
+    let provider = crypto_provider(settings).map_err(std::io::Error::other)?;
+    let mut config = ServerConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(protocol_versions(settings.min_version))
+        .map_err(std::io::Error::other)?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(std::io::Error::other)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Polls the certificate and key files and swaps the listener's configuration when they
+/// change. A renewal that writes the two files separately may be caught half-way; that
+/// load fails, the current certificate stays in use and the next poll retries.
+pub fn spawn_certificate_reloader(
+    tls_config: RustlsConfig,
+    settings: ServerTlsConfig,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+) {
+    if settings.cert_reload_secs == 0 {
+        return;
+    }
+    let interval = Duration::from_secs(settings.cert_reload_secs);
+    tokio::spawn(async move {
+        let mut loaded = (modified(&cert_path), modified(&key_path));
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let current = (modified(&cert_path), modified(&key_path));
+            if current == loaded {
+                continue;
+            }
+            match build_server_config(&settings, &cert_path, &key_path) {
+                Ok(config) => {
+                    tls_config.reload_from_config(Arc::new(config));
+                    loaded = current;
+                    info!("Reloaded TLS certificate from {}", cert_path.display());
+                }
+                Err(e) => {
+                    warn!("TLS certificate changed but could not be loaded: {}", e);
+                }
+            }
+        }
+    });
+}
+
+pub fn log_tls_settings(settings: &ServerTlsConfig) {
+    match crypto_provider(settings) {
+        Ok(provider) => info!(
+            "TLS minimum version {:?}, cipher suites: {}",
+            settings.min_version,
+            provider
+                .cipher_suites
+                .iter()
+                .map(suite_name)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Err(e) => error!("Invalid TLS settings: {}", e),
+    }
+}
+
+/// HTTPS URL for a plain HTTP request, keeping host, path and query.
+fn https_location(host: &str, uri: &Uri, https_port: u16) -> String {
+    // Drop the HTTP port; IPv6 literals keep their brackets.
+    let host = match host.find(']') {
+        Some(end) => &host[..=end],
+        None => host.split(':').next().unwrap_or(host),
+    };
+    let path = uri.path_and_query().map_or("/", |pq| pq.as_str());
+    if https_port == 443 {
+        format!("https://{}{}", host, path)
+    } else {
+        format!("https://{}:{}{}", host, https_port, path)
+    }
+}
+
+/// Serves `301 Moved Permanently` to HTTPS on `redirect_port` until shutdown.
+pub fn spawn_https_redirect(redirect_port: u16, https_port: u16) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], redirect_port));
+    tokio::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(l) => l,
+            Err(e) => {
+                error!("Failed to bind HTTPS redirect listener on {}: {}", addr, e);
+                return;
+            }
+        };
+        let redirect = move |headers: HeaderMap, uri: Uri| async move {
+            let Some(host) = headers
+                .get(header::HOST)
+                .and_then(|h| h.to_str().ok())
+                .filter(|h| !h.is_empty())
+            else {
+                return (StatusCode::BAD_REQUEST, "Missing Host header").into_response();
+            };
+            let location = https_location(host, &uri, https_port);
+            Response::builder()
+                .status(StatusCode::MOVED_PERMANENTLY)
+                .header(header::LOCATION, location)
+                .body(axum::body::Body::empty())
+                .unwrap_or_else(|_| StatusCode::BAD_REQUEST.into_response())
+        };
+        let app = axum::Router::new().fallback(redirect);
+        info!("Redirecting HTTP on {} to HTTPS port {}", addr, https_port);
+        if let Err(e) = axum::serve(listener, app)
+            .with_graceful_shutdown(super::shutdown_signal())
+            .await
+        {
+            error!("HTTPS redirect listener failed: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(min_version: TlsMinVersion, suites: Option<&[&str]>) -> ServerTlsConfig {
+        ServerTlsConfig {
+            min_version,
+            cipher_suites: suites.map(|s| s.iter().map(|n| n.to_string()).collect()),
+            cert_reload_secs: 0,
+            http_redirect_port: None,
+        }
+    }
+
+    #[test]
+    fn test_default_allows_tls12_and_tls13_aead_suites() {
+        let provider = crypto_provider(&settings(TlsMinVersion::Tls12, None)).unwrap();
+        let names: Vec<String> = provider.cipher_suites.iter().map(suite_name).collect();
+
+        assert!(names.iter().any(|n| n.starts_with("TLS13_")));
+        assert!(names.iter().any(|n| n.starts_with("TLS_ECDHE_")));
+        assert!(names.iter().all(|n| !n.contains("CBC")));
+    }
+
+    #[test]
+    fn test_tls13_minimum_and_allow_list() {
+        let provider = crypto_provider(&settings(TlsMinVersion::Tls13, None)).unwrap();
+        assert!(provider
+            .cipher_suites
+            .iter()
+            .all(|s| s.version() == &rustls::version::TLS13));
+
+        let provider = crypto_provider(&settings(
+            TlsMinVersion::Tls12,
+            Some(&["TLS13_AES_256_GCM_SHA384"]),
+        ))
+        .unwrap();
+        assert_eq!(provider.cipher_suites.len(), 1);
+
+        // Only TLS 1.2 suites allowed, but TLS 1.3 required.
+        assert!(crypto_provider(&settings(
+            TlsMinVersion::Tls13,
+            Some(&["TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256"]),
+        ))
+        .is_err());
+        assert!(crypto_provider(&settings(TlsMinVersion::Tls12, Some(&["RC4_MD5"]))).is_err());
+        assert_eq!(TlsMinVersion::parse("TLSv1.3"), Some(TlsMinVersion::Tls13));
+        assert_eq!(TlsMinVersion::parse("1.1"), None);
+    }
+
+    #[test]
+    fn test_redirect_keeps_path_and_query() {
+        let uri: Uri = "/chat/room?x=1&y=two".parse().unwrap();
+        assert_eq!(
+            https_location("bots.example.com", &uri, 443),
+            "https://bots.example.com/chat/room?x=1&y=two"
+        );
+        assert_eq!(
+            https_location("bots.example.com:80", &uri, 8443),
+            "https://bots.example.com:8443/chat/room?x=1&y=two"
+        );
+        assert_eq!(
+            https_location("[::1]:80", &"/".parse().unwrap(), 443),
+            "https://[::1]/"
+        );
+    }
+}
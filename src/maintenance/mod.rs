@@ -14,6 +14,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 const DEFAULT_RETENTION_DAYS: i64 = 180;
@@ -1039,7 +1040,7 @@ async fn preview_cleanup_handler(
 
 async fn execute_cleanup_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ExecuteRequest>,
+    ApiJson(request): ApiJson<ExecuteRequest>,
 ) -> Result<Json<CleanupResult>, CleanupError> {
     let service = CleanupService::new(Arc::new(state.conn.clone()));
 
@@ -1103,7 +1104,7 @@ async fn get_config_handler(
 
 async fn save_config_handler(
     State(state): State<Arc<AppState>>,
-    Json(config): Json<CleanupConfig>,
+    ApiJson(config): ApiJson<CleanupConfig>,
 ) -> Result<StatusCode, CleanupError> {
     let service = CleanupService::new(Arc::new(state.conn.clone()));
     service.save_cleanup_config(&config).await?;
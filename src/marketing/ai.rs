@@ -9,6 +9,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::config::ConfigManager;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::crm_contacts;
 use crate::core::shared::state::AppState;
 
@@ -434,7 +435,7 @@ pub struct GenerateContentRequest {
 
 pub async fn generate_content_api(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<GenerateContentRequest>,
+    ApiJson(req): ApiJson<GenerateContentRequest>,
 ) -> Result<Json<ContentGenerationResult>, (StatusCode, String)> {
     let bot_id = Uuid::nil();
 
@@ -462,7 +463,7 @@ pub struct PersonalizeRequest {
 
 pub async fn personalize_api(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<PersonalizeRequest>,
+    ApiJson(req): ApiJson<PersonalizeRequest>,
 ) -> Result<Json<PersonalizationResult>, (StatusCode, String)> {
     let bot_id = Uuid::nil();
 
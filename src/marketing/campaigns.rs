@@ -12,6 +12,7 @@ use uuid::Uuid;
 use crate::core::shared::schema::marketing_campaigns;
 use crate::core::shared::state::AppState;
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable, AsChangeset)]
 #[diesel(table_name = marketing_campaigns)]
@@ -109,7 +110,7 @@ pub async fn get_campaign(
 
 pub async fn create_campaign(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateCampaignRequest>,
+    ApiJson(req): ApiJson<CreateCampaignRequest>,
 ) -> Result<Json<CrmCampaign>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -159,7 +160,7 @@ pub async fn create_campaign(
 pub async fn update_campaign(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateCampaignRequest>,
+    ApiJson(req): ApiJson<UpdateCampaignRequest>,
 ) -> Result<Json<CrmCampaign>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -315,7 +316,7 @@ async fn send_via_sms(
 pub async fn send_campaign(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<SendCampaignRequest>,
+    ApiJson(req): ApiJson<SendCampaignRequest>,
 ) -> Result<Json<CampaignSendResult>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -10,6 +10,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::config::ConfigManager;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::core::shared::schema::{
     email_tracking, marketing_campaigns, marketing_recipients,
@@ -296,7 +297,7 @@ pub struct SendEmailRequest {
 
 pub async fn send_email_api(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<SendEmailRequest>,
+    ApiJson(req): ApiJson<SendEmailRequest>,
 ) -> Result<Json<EmailSendResult>, (StatusCode, String)> {
     let bot_id = Uuid::nil();
 
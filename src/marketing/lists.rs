@@ -12,6 +12,7 @@ use uuid::Uuid;
 use crate::core::shared::schema::marketing_lists;
 use crate::core::shared::state::AppState;
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable, AsChangeset)]
 #[diesel(table_name = marketing_lists)]
@@ -97,7 +98,7 @@ pub async fn get_list(
 
 pub async fn create_list(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateListRequest>,
+    ApiJson(req): ApiJson<CreateListRequest>,
 ) -> Result<Json<MarketingList>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -130,7 +131,7 @@ pub async fn create_list(
 pub async fn update_list(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateListRequest>,
+    ApiJson(req): ApiJson<UpdateListRequest>,
 ) -> Result<Json<MarketingList>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -12,6 +12,7 @@ use uuid::Uuid;
 use crate::core::shared::schema::marketing_templates;
 use crate::core::shared::state::AppState;
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable, AsChangeset)]
 #[diesel(table_name = marketing_templates)]
@@ -111,7 +112,7 @@ pub async fn get_template(
 
 pub async fn create_template(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateTemplateRequest>,
+    ApiJson(req): ApiJson<CreateTemplateRequest>,
 ) -> Result<Json<MarketingTemplate>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -149,7 +150,7 @@ pub async fn create_template(
 pub async fn update_template(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateTemplateRequest>,
+    ApiJson(req): ApiJson<UpdateTemplateRequest>,
 ) -> Result<Json<MarketingTemplate>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -5,6 +5,7 @@ use serde::Deserialize;
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::models::TriggerKind;
 use crate::core::shared::schema::email_tracking;
 use crate::core::shared::state::AppState;
@@ -162,7 +163,7 @@ pub struct EmailOpenRequest {
 
 pub async fn track_email_open(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<EmailOpenRequest>,
+    ApiJson(req): ApiJson<EmailOpenRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -11,6 +11,7 @@ use uuid::Uuid;
 
 use crate::core::bot::channels::whatsapp::WhatsAppAdapter;
 use crate::core::bot::channels::ChannelAdapter;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::{
     marketing_campaigns, marketing_recipients,
 };
@@ -358,7 +359,7 @@ pub struct SendWhatsAppRequest {
 
 pub async fn send_whatsapp_api(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<SendWhatsAppRequest>,
+    ApiJson(req): ApiJson<SendWhatsAppRequest>,
 ) -> Result<Json<WhatsAppSendResult>, (StatusCode, String)> {
     let bot_id = Uuid::nil();
 
@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 #[derive(Debug, Deserialize)]
@@ -183,7 +184,7 @@ pub struct SuccessResponse {
 
 pub async fn create_conversation(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<CreateConversationRequest>,
+    ApiJson(req): ApiJson<CreateConversationRequest>,
 ) -> Result<Json<ConversationResponse>, (StatusCode, Json<serde_json::Value>)> {
     let conversation_id = Uuid::new_v4();
     let now = Utc::now();
@@ -209,7 +210,7 @@ pub async fn create_conversation(
 pub async fn join_conversation(
     State(_state): State<Arc<AppState>>,
     Path(conversation_id): Path<Uuid>,
-    Json(req): Json<JoinConversationRequest>,
+    ApiJson(req): ApiJson<JoinConversationRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<serde_json::Value>)> {
     Ok(Json(SuccessResponse {
         success: true,
@@ -223,7 +224,7 @@ pub async fn join_conversation(
 pub async fn leave_conversation(
     State(_state): State<Arc<AppState>>,
     Path(conversation_id): Path<Uuid>,
-    Json(req): Json<LeaveConversationRequest>,
+    ApiJson(req): ApiJson<LeaveConversationRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<serde_json::Value>)> {
     Ok(Json(SuccessResponse {
         success: true,
@@ -263,7 +264,7 @@ pub async fn get_conversation_messages(
 pub async fn send_message(
     State(_state): State<Arc<AppState>>,
     Path(conversation_id): Path<Uuid>,
-    Json(req): Json<SendMessageRequest>,
+    ApiJson(req): ApiJson<SendMessageRequest>,
 ) -> Result<Json<MessageResponse>, (StatusCode, Json<serde_json::Value>)> {
     let message_id = Uuid::new_v4();
     let sender_id = Uuid::new_v4();
@@ -291,7 +292,7 @@ pub async fn send_message(
 pub async fn edit_message(
     State(_state): State<Arc<AppState>>,
     Path((conversation_id, message_id)): Path<(Uuid, Uuid)>,
-    Json(req): Json<EditMessageRequest>,
+    ApiJson(req): ApiJson<EditMessageRequest>,
 ) -> Result<Json<MessageResponse>, (StatusCode, Json<serde_json::Value>)> {
     let now = Utc::now();
 
@@ -327,7 +328,7 @@ pub async fn delete_message(
 pub async fn react_to_message(
     State(_state): State<Arc<AppState>>,
     Path((_conversation_id, message_id)): Path<(Uuid, Uuid)>,
-    Json(req): Json<ReactToMessageRequest>,
+    ApiJson(req): ApiJson<ReactToMessageRequest>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<serde_json::Value>)> {
     Ok(Json(SuccessResponse {
         success: true,
@@ -361,7 +362,7 @@ pub async fn search_messages(
 pub async fn start_call(
     State(_state): State<Arc<AppState>>,
     Path(conversation_id): Path<Uuid>,
-    Json(req): Json<StartCallRequest>,
+    ApiJson(req): ApiJson<StartCallRequest>,
 ) -> Result<Json<CallResponse>, (StatusCode, Json<serde_json::Value>)> {
     let call_id = Uuid::new_v4();
     let starter_id = Uuid::new_v4();
@@ -426,7 +427,7 @@ pub async fn unmute_call(
 pub async fn start_screen_share(
     State(_state): State<Arc<AppState>>,
     Path(conversation_id): Path<Uuid>,
-    Json(req): Json<ScreenShareRequest>,
+    ApiJson(req): ApiJson<ScreenShareRequest>,
 ) -> Result<Json<ScreenShareResponse>, (StatusCode, Json<serde_json::Value>)> {
     let share_id = Uuid::new_v4();
     let user_id = Uuid::new_v4();
@@ -500,7 +501,7 @@ pub async fn create_whiteboard(
 pub async fn collaborate_whiteboard(
     State(_state): State<Arc<AppState>>,
     Path(_conversation_id): Path<Uuid>,
-    Json(_data): Json<serde_json::Value>,
+    ApiJson(_data): ApiJson<serde_json::Value>,
 ) -> Result<Json<SuccessResponse>, (StatusCode, Json<serde_json::Value>)> {
     Ok(Json(SuccessResponse {
         success: true,
@@ -11,6 +11,7 @@ use serde::Deserialize;
 use serde_json::Value;
 use std::sync::Arc;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::urls::ApiUrls;
 use crate::core::shared::state::AppState;
 
@@ -157,7 +158,7 @@ pub struct SendInvitesRequest {
 
 pub async fn voice_start(
     State(data): State<Arc<AppState>>,
-    Json(info): Json<Value>,
+    ApiJson(info): ApiJson<Value>,
 ) -> impl IntoResponse {
     let session_id = info
         .get("session_id")
@@ -201,7 +202,7 @@ pub async fn voice_start(
 
 pub async fn voice_stop(
     State(data): State<Arc<AppState>>,
-    Json(info): Json<Value>,
+    ApiJson(info): ApiJson<Value>,
 ) -> impl IntoResponse {
     let session_id = info
         .get("session_id")
@@ -232,7 +233,7 @@ pub async fn voice_stop(
 
 pub async fn create_meeting(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<CreateMeetingRequest>,
+    ApiJson(payload): ApiJson<CreateMeetingRequest>,
 ) -> impl IntoResponse {
     let transcription_service = Arc::new(DefaultTranscriptionService);
     let meeting_service = MeetingService::new(state.clone(), transcription_service);
@@ -310,7 +311,7 @@ pub async fn get_room(
 pub async fn join_room(
     State(state): State<Arc<AppState>>,
     Path(room_id): Path<String>,
-    Json(payload): Json<JoinRoomRequest>,
+    ApiJson(payload): ApiJson<JoinRoomRequest>,
 ) -> impl IntoResponse {
     let transcription_service = Arc::new(DefaultTranscriptionService);
     let meeting_service = MeetingService::new(state.clone(), transcription_service);
@@ -360,7 +361,7 @@ pub async fn start_transcription(
 
 pub async fn get_meeting_token(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<GetTokenRequest>,
+    ApiJson(payload): ApiJson<GetTokenRequest>,
 ) -> impl IntoResponse {
     let token = format!(
         "meet_token_{}_{}_{}",
@@ -381,7 +382,7 @@ pub async fn get_meeting_token(
 
 pub async fn send_meeting_invites(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<SendInvitesRequest>,
+    ApiJson(payload): ApiJson<SendInvitesRequest>,
 ) -> impl IntoResponse {
     info!("Sending meeting invites for room {}", payload.room_id);
 
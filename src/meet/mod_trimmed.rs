@@ -1,4 +1,5 @@
 pub mod webinar_types;
+use crate::core::shared::api_json::ApiJson;
 use axum::{
     extract::{Path, State},
     http::StatusCode,
@@ -155,7 +156,7 @@ pub struct SendInvitesRequest {
 
 pub async fn voice_start(
     State(data): State<Arc<AppState>>,
-    Json(info): Json<Value>,
+    ApiJson(info): ApiJson<Value>,
 ) -> impl IntoResponse {
     let session_id = info
         .get("session_id")
@@ -199,7 +200,7 @@ pub async fn voice_start(
 
 pub async fn voice_stop(
     State(data): State<Arc<AppState>>,
-    Json(info): Json<Value>,
+    ApiJson(info): ApiJson<Value>,
 ) -> impl IntoResponse {
     let session_id = info
         .get("session_id")
@@ -230,7 +231,7 @@ pub async fn voice_stop(
 
 pub async fn create_meeting(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<CreateMeetingRequest>,
+    ApiJson(payload): ApiJson<CreateMeetingRequest>,
 ) -> impl IntoResponse {
     let transcription_service = Arc::new(DefaultTranscriptionService);
     let meeting_service = MeetingService::new(state.clone(), transcription_service);
@@ -308,7 +309,7 @@ pub async fn get_room(
 pub async fn join_room(
     State(state): State<Arc<AppState>>,
     Path(room_id): Path<String>,
-    Json(payload): Json<JoinRoomRequest>,
+    ApiJson(payload): ApiJson<JoinRoomRequest>,
 ) -> impl IntoResponse {
     let transcription_service = Arc::new(DefaultTranscriptionService);
     let meeting_service = MeetingService::new(state.clone(), transcription_service);
@@ -358,7 +359,7 @@ pub async fn start_transcription(
 
 pub async fn get_meeting_token(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<GetTokenRequest>,
+    ApiJson(payload): ApiJson<GetTokenRequest>,
 ) -> impl IntoResponse {
     let token = format!(
         "meet_token_{}_{}_{}",
@@ -379,7 +380,7 @@ pub async fn get_meeting_token(
 
 pub async fn send_meeting_invites(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<SendInvitesRequest>,
+    ApiJson(payload): ApiJson<SendInvitesRequest>,
 ) -> impl IntoResponse {
     info!("Sending meeting invites for room {}", payload.room_id);
 
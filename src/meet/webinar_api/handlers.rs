@@ -6,6 +6,7 @@ use axum::{
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 use super::service::WebinarService;
@@ -151,7 +152,7 @@ struct RecordingRow {
 
 async fn create_webinar_handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<super::types::CreateWebinarRequest>,
+    ApiJson(request): ApiJson<super::types::CreateWebinarRequest>,
 ) -> Result<Json<Webinar>, WebinarError> {
     let service = WebinarService::new(Arc::new(state.conn.clone()));
     let organization_id = Uuid::nil();
@@ -192,7 +193,7 @@ async fn end_webinar_handler(
 async fn register_handler(
     State(state): State<Arc<AppState>>,
     Path(webinar_id): Path<Uuid>,
-    Json(request): Json<RegisterRequest>,
+    ApiJson(request): ApiJson<RegisterRequest>,
 ) -> Result<Json<WebinarRegistration>, WebinarError> {
     let service = WebinarService::new(Arc::new(state.conn.clone()));
     let registration = service.register_attendee(webinar_id, request).await?;
@@ -250,7 +251,7 @@ async fn get_questions_handler(
 async fn submit_question_handler(
     State(state): State<Arc<AppState>>,
     Path(webinar_id): Path<Uuid>,
-    Json(request): Json<SubmitQuestionRequest>,
+    ApiJson(request): ApiJson<SubmitQuestionRequest>,
 ) -> Result<Json<QAQuestion>, WebinarError> {
     let service = WebinarService::new(Arc::new(state.conn.clone()));
     let asker_id: Option<Uuid> = None;
@@ -261,7 +262,7 @@ async fn submit_question_handler(
 async fn answer_question_handler(
     State(state): State<Arc<AppState>>,
     Path((webinar_id, question_id)): Path<(Uuid, Uuid)>,
-    Json(request): Json<AnswerQuestionRequest>,
+    ApiJson(request): ApiJson<AnswerQuestionRequest>,
 ) -> Result<Json<QAQuestion>, WebinarError> {
     log::debug!("Answering question {question_id} in webinar {webinar_id}");
     let service = WebinarService::new(Arc::new(state.conn.clone()));
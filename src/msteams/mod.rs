@@ -1,8 +1,20 @@
 pub use crate::core::bot::channels::teams::TeamsAdapter;
 
 use crate::core::bot::channels::ChannelAdapter;
+use crate::core::config::ConfigManager;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
-use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use crate::security::channel_webhook::{
+    channel_secret, teams_keys, ChannelAuthError, TEAMS_APP_ID,
+};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
 use serde::Deserialize;
 use std::sync::Arc;
 use uuid::Uuid;
@@ -49,9 +61,26 @@ pub fn configure() -> Router<Arc<AppState>> {
 }
 
 async fn handle_incoming(
-    State(_state): State<Arc<AppState>>,
-    Json(activity): Json<TeamsActivity>,
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> impl IntoResponse {
+    let activity: TeamsActivity = match serde_json::from_slice(&body) {
+        Ok(activity) => activity,
+        Err(e) => {
+            log::error!("Failed to deserialize Teams activity: {}", e);
+            return (StatusCode::BAD_REQUEST, Json(serde_json::json!({})));
+        }
+    };
+
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    if let Err(e) = verify_teams_request(&state, authorization, activity.service_url.as_deref()).await {
+        log::warn!("Rejected Teams activity {}: {}", activity.id, e);
+        return (StatusCode::UNAUTHORIZED, Json(serde_json::json!({})));
+    }
+
     match activity.activity_type.as_str() {
         "message" => {
             if let Some(text) = &activity.text {
@@ -76,9 +105,36 @@ async fn handle_incoming(
     }
 }
 
+/// Checks the Bot Framework token against the app id from Vault, or from the bot's
+/// `teams-app-id` config when Vault has none.
+async fn verify_teams_request(
+    state: &Arc<AppState>,
+    authorization: Option<&str>,
+    service_url: Option<&str>,
+) -> Result<(), ChannelAuthError> {
+    let bot_id = get_default_bot_id(state).await;
+    let app_id = match channel_secret(Some(&bot_id), TEAMS_APP_ID).await {
+        Some(app_id) => app_id,
+        None => {
+            let config_manager = ConfigManager::new(state.conn.clone());
+            tokio::task::spawn_blocking(move || {
+                config_manager.get_config(&bot_id, TEAMS_APP_ID, None).ok()
+            })
+            .await
+            .ok()
+            .flatten()
+            .filter(|app_id| !app_id.trim().is_empty())
+            .ok_or(ChannelAuthError::NotConfigured(TEAMS_APP_ID))?
+        }
+    };
+    teams_keys()
+        .validate(authorization, &app_id, service_url)
+        .await
+}
+
 async fn send_message(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<serde_json::Value>,
+    ApiJson(request): ApiJson<serde_json::Value>,
 ) -> impl IntoResponse {
     let bot_id = get_default_bot_id(&state).await;
     let adapter = TeamsAdapter::new(state.conn.clone(), bot_id);
@@ -1,8 +1,8 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use axum::{
     extract::State,
     response::{Html, IntoResponse},
-    Json,
 };
 use std::sync::Arc;
 
@@ -12,7 +12,7 @@ use super::utils::format_ai_response;
 
 pub async fn handle_ai_summarize(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<AiRequest>,
+    ApiJson(payload): ApiJson<AiRequest>,
 ) -> impl IntoResponse {
     let text = payload.selected_text.unwrap_or_default();
 
@@ -40,7 +40,7 @@ pub async fn handle_ai_summarize(
 
 pub async fn handle_ai_expand(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<AiRequest>,
+    ApiJson(payload): ApiJson<AiRequest>,
 ) -> impl IntoResponse {
     let text = payload.selected_text.unwrap_or_default();
 
@@ -65,7 +65,7 @@ pub async fn handle_ai_expand(
 
 pub async fn handle_ai_improve(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<AiRequest>,
+    ApiJson(payload): ApiJson<AiRequest>,
 ) -> impl IntoResponse {
     let text = payload.selected_text.unwrap_or_default();
 
@@ -86,7 +86,7 @@ pub async fn handle_ai_improve(
 
 pub async fn handle_ai_simplify(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<AiRequest>,
+    ApiJson(payload): ApiJson<AiRequest>,
 ) -> impl IntoResponse {
     let text = payload.selected_text.unwrap_or_default();
 
@@ -110,7 +110,7 @@ pub async fn handle_ai_simplify(
 
 pub async fn handle_ai_translate(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<AiRequest>,
+    ApiJson(payload): ApiJson<AiRequest>,
 ) -> impl IntoResponse {
     let text = payload.selected_text.unwrap_or_default();
     let lang = payload.translate_lang.unwrap_or_else(|| "es".to_string());
@@ -153,7 +153,7 @@ pub async fn handle_ai_translate(
 
 pub async fn handle_ai_custom(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<AiRequest>,
+    ApiJson(payload): ApiJson<AiRequest>,
 ) -> impl IntoResponse {
     let text = payload.selected_text.unwrap_or_default();
     let prompt = payload.prompt.unwrap_or_default();
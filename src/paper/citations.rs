@@ -0,0 +1,703 @@
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::outbound_proxy::client_builder;
+use crate::core::shared::state::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const DOI_RESOLVER: &str = "https://doi.org";
+/// Most references accepted in one request.
+pub const MAX_REFERENCES: usize = 100;
+/// DOI lookups of one request in flight at once.
+const DOI_LOOKUP_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CitationStyle {
+    #[default]
+    Apa,
+    Mla,
+    Bibtex,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Reference {
+    #[serde(default)]
+    pub doi: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub year: Option<i32>,
+    #[serde(default)]
+    pub container: Option<String>,
+    #[serde(default)]
+    pub volume: Option<String>,
+    #[serde(default)]
+    pub issue: Option<String>,
+    #[serde(default)]
+    pub pages: Option<String>,
+    #[serde(default)]
+    pub publisher: Option<String>,
+}
+
+impl Reference {
+    fn needs_resolution(&self) -> bool {
+        self.doi.is_some() && (self.title.is_none() || self.authors.is_empty())
+    }
+
+    /// Fills fields missing on `self` from resolved metadata; user-provided values win.
+    fn merge_missing(&mut self, other: Reference) {
+        if self.title.is_none() {
+            self.title = other.title;
+        }
+        if self.authors.is_empty() {
+            self.authors = other.authors;
+        }
+        if self.year.is_none() {
+            self.year = other.year;
+        }
+        if self.container.is_none() {
+            self.container = other.container;
+        }
+        if self.volume.is_none() {
+            self.volume = other.volume;
+        }
+        if self.issue.is_none() {
+            self.issue = other.issue;
+        }
+        if self.pages.is_none() {
+            self.pages = other.pages;
+        }
+        if self.publisher.is_none() {
+            self.publisher = other.publisher;
+        }
+        if self.url.is_none() {
+            self.url = other.url;
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CitationRequest {
+    pub references: Vec<Reference>,
+    #[serde(default)]
+    pub style: CitationStyle,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BibliographyRequest {
+    pub references: Vec<Reference>,
+    #[serde(default)]
+    pub style: CitationStyle,
+    #[serde(default)]
+    pub heading: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CitationResponse {
+    pub style: CitationStyle,
+    pub citations: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BibliographyResponse {
+    pub style: CitationStyle,
+    pub bibliography: String,
+    pub entries: usize,
+}
+
+struct AuthorName {
+    family: String,
+    given: String,
+}
+
+fn split_author(raw: &str) -> AuthorName {
+    let raw = raw.trim();
+    if let Some((family, given)) = raw.split_once(',') {
+        return AuthorName {
+            family: family.trim().to_string(),
+            given: given.trim().to_string(),
+        };
+    }
+
+    match raw.rsplit_once(' ') {
+        Some((given, family)) => AuthorName {
+            family: family.trim().to_string(),
+            given: given.trim().to_string(),
+        },
+        None => AuthorName {
+            family: raw.to_string(),
+            given: String::new(),
+        },
+    }
+}
+
+fn initials(given: &str) -> String {
+    given
+        .split(|c: char| c.is_whitespace() || c == '-')
+        .filter_map(|part| part.chars().next())
+        .map(|c| format!("{}.", c.to_uppercase()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn doi_url(doi: &str) -> String {
+    resolver_url(DOI_RESOLVER, doi)
+}
+
+fn resolver_url(resolver: &str, doi: &str) -> String {
+    format!(
+        "{}/{}",
+        resolver,
+        doi.trim_start_matches("https://doi.org/")
+    )
+}
+
+fn apa_authors(authors: &[String]) -> String {
+    let names: Vec<String> = authors
+        .iter()
+        .map(|a| {
+            let name = split_author(a);
+            let init = initials(&name.given);
+            if init.is_empty() {
+                name.family
+            } else {
+                format!("{}, {}", name.family, init)
+            }
+        })
+        .collect();
+
+    match names.len() {
+        0 => String::new(),
+        1 => names[0].clone(),
+        2 => format!("{}, & {}", names[0], names[1]),
+        n => format!("{}, & {}", names[..n - 1].join(", "), names[n - 1]),
+    }
+}
+
+fn mla_authors(authors: &[String]) -> String {
+    let names: Vec<AuthorName> = authors.iter().map(|a| split_author(a)).collect();
+    let first = |n: &AuthorName| {
+        if n.given.is_empty() {
+            n.family.clone()
+        } else {
+            format!("{}, {}", n.family, n.given)
+        }
+    };
+
+    match names.len() {
+        0 => String::new(),
+        1 => first(&names[0]),
+        2 => format!(
+            "{}, and {} {}",
+            first(&names[0]),
+            names[1].given,
+            names[1].family
+        )
+        .replace("and  ", "and "),
+        _ => format!("{}, et al", first(&names[0])),
+    }
+}
+
+pub fn format_apa(r: &Reference) -> String {
+    let mut out = String::new();
+
+    let authors = apa_authors(&r.authors);
+    if !authors.is_empty() {
+        out.push_str(&authors);
+        out.push(' ');
+    }
+
+    match r.year {
+        Some(year) => out.push_str(&format!("({}). ", year)),
+        None => out.push_str("(n.d.). "),
+    }
+
+    if let Some(title) = &r.title {
+        out.push_str(title.trim_end_matches('.'));
+        out.push_str(". ");
+    }
+
+    if let Some(container) = &r.container {
+        out.push_str(container);
+        if let Some(volume) = &r.volume {
+            out.push_str(&format!(", {}", volume));
+            if let Some(issue) = &r.issue {
+                out.push_str(&format!("({})", issue));
+            }
+        }
+        if let Some(pages) = &r.pages {
+            out.push_str(&format!(", {}", pages));
+        }
+        out.push_str(". ");
+    } else if let Some(publisher) = &r.publisher {
+        out.push_str(publisher);
+        out.push_str(". ");
+    }
+
+    if let Some(doi) = &r.doi {
+        out.push_str(&doi_url(doi));
+    } else if let Some(url) = &r.url {
+        out.push_str(url);
+    }
+
+    out.trim_end().to_string()
+}
+
+pub fn format_mla(r: &Reference) -> String {
+    let mut parts: Vec<String> = Vec::new();
+
+    let authors = mla_authors(&r.authors);
+    let mut out = String::new();
+    if !authors.is_empty() {
+        out.push_str(authors.trim_end_matches('.'));
+        out.push_str(". ");
+    }
+
+    if let Some(title) = &r.title {
+        out.push_str(&format!("\"{}.\" ", title.trim_end_matches('.')));
+    }
+
+    if let Some(container) = &r.container {
+        parts.push(container.clone());
+    }
+    if let Some(volume) = &r.volume {
+        parts.push(format!("vol. {}", volume));
+    }
+    if let Some(issue) = &r.issue {
+        parts.push(format!("no. {}", issue));
+    }
+    if r.container.is_none() {
+        if let Some(publisher) = &r.publisher {
+            parts.push(publisher.clone());
+        }
+    }
+    if let Some(year) = r.year {
+        parts.push(year.to_string());
+    }
+    if let Some(pages) = &r.pages {
+        parts.push(format!("pp. {}", pages));
+    }
+    if let Some(doi) = &r.doi {
+        parts.push(doi_url(doi));
+    } else if let Some(url) = &r.url {
+        parts.push(url.clone());
+    }
+
+    if !parts.is_empty() {
+        out.push_str(&parts.join(", "));
+        out.push('.');
+    }
+
+    out.trim_end().to_string()
+}
+
+fn bibtex_key(r: &Reference) -> String {
+    let family = r
+        .authors
+        .first()
+        .map(|a| split_author(a).family)
+        .unwrap_or_else(|| "anon".to_string());
+    let word = r
+        .title
+        .as_deref()
+        .and_then(|t| t.split_whitespace().find(|w| w.len() > 3))
+        .unwrap_or("");
+
+    format!(
+        "{}{}{}",
+        family,
+        r.year.map(|y| y.to_string()).unwrap_or_default(),
+        word
+    )
+    .chars()
+    .filter(|c| c.is_ascii_alphanumeric())
+    .collect::<String>()
+    .to_lowercase()
+}
+
+pub fn format_bibtex(r: &Reference) -> String {
+    let entry_type = if r.container.is_some() { "article" } else { "misc" };
+    let mut fields: Vec<(&str, String)> = Vec::new();
+
+    if !r.authors.is_empty() {
+        let authors = r
+            .authors
+            .iter()
+            .map(|a| {
+                let name = split_author(a);
+                if name.given.is_empty() {
+                    name.family
+                } else {
+                    format!("{}, {}", name.family, name.given)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" and ");
+        fields.push(("author", authors));
+    }
+    if let Some(title) = &r.title {
+        fields.push(("title", title.clone()));
+    }
+    if let Some(container) = &r.container {
+        fields.push(("journal", container.clone()));
+    }
+    if let Some(year) = r.year {
+        fields.push(("year", year.to_string()));
+    }
+    if let Some(volume) = &r.volume {
+        fields.push(("volume", volume.clone()));
+    }
+    if let Some(issue) = &r.issue {
+        fields.push(("number", issue.clone()));
+    }
+    if let Some(pages) = &r.pages {
+        fields.push(("pages", pages.replace('-', "--")));
+    }
+    if let Some(publisher) = &r.publisher {
+        fields.push(("publisher", publisher.clone()));
+    }
+    if let Some(doi) = &r.doi {
+        fields.push(("doi", doi.clone()));
+    }
+    if let Some(url) = &r.url {
+        fields.push(("url", url.clone()));
+    }
+
+    let body = fields
+        .iter()
+        .map(|(k, v)| format!("  {} = {{{}}}", k, v))
+        .collect::<Vec<_>>()
+        .join(",\n");
+
+    format!("@{}{{{},\n{}\n}}", entry_type, bibtex_key(r), body)
+}
+
+pub fn format_citation(r: &Reference, style: CitationStyle) -> String {
+    match style {
+        CitationStyle::Apa => format_apa(r),
+        CitationStyle::Mla => format_mla(r),
+        CitationStyle::Bibtex => format_bibtex(r),
+    }
+}
+
+pub fn build_bibliography(references: &[Reference], style: CitationStyle, heading: Option<&str>) -> String {
+    let mut sorted: Vec<&Reference> = references.iter().collect();
+    if style != CitationStyle::Bibtex {
+        sorted.sort_by_key(|r| {
+            r.authors
+                .first()
+                .map(|a| split_author(a).family.to_lowercase())
+                .or_else(|| r.title.as_ref().map(|t| t.to_lowercase()))
+                .unwrap_or_default()
+        });
+    }
+
+    let entries: Vec<String> = sorted.iter().map(|r| format_citation(r, style)).collect();
+    let separator = if style == CitationStyle::Bibtex { "\n\n" } else { "\n" };
+
+    let default_heading = match style {
+        CitationStyle::Apa => Some("References"),
+        CitationStyle::Mla => Some("Works Cited"),
+        CitationStyle::Bibtex => None,
+    };
+
+    match heading.or(default_heading) {
+        Some(h) => format!("{}\n\n{}", h, entries.join(separator)),
+        None => entries.join(separator),
+    }
+}
+
+fn reference_from_csl(csl: &serde_json::Value) -> Reference {
+    let text = |key: &str| csl.get(key).and_then(|v| v.as_str()).map(String::from);
+
+    let authors = csl
+        .get("author")
+        .and_then(|a| a.as_array())
+        .map(|list| {
+            list.iter()
+                .filter_map(|a| {
+                    let family = a.get("family").and_then(|v| v.as_str())?;
+                    match a.get("given").and_then(|v| v.as_str()) {
+                        Some(given) => Some(format!("{}, {}", family, given)),
+                        None => Some(family.to_string()),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let year = ["issued", "published-print", "published-online"]
+        .iter()
+        .find_map(|key| {
+            csl.get(*key)?
+                .get("date-parts")?
+                .get(0)?
+                .get(0)?
+                .as_i64()
+        })
+        .map(|y| y as i32);
+
+    Reference {
+        doi: text("DOI"),
+        url: text("URL"),
+        title: text("title"),
+        authors,
+        year,
+        container: text("container-title"),
+        volume: text("volume"),
+        issue: text("issue"),
+        pages: text("page"),
+        publisher: text("publisher"),
+    }
+}
+
+async fn resolve_doi(
+    client: &reqwest::Client,
+    resolver: &str,
+    doi: &str,
+) -> Result<Reference, String> {
+    let response = client
+        .get(resolver_url(resolver, doi))
+        .header("Accept", "application/vnd.citationstyles.csl+json")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !response.status().is_success() {
+        return Err(format!("DOI resolver returned {}", response.status()));
+    }
+
+    let csl: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
+    Ok(reference_from_csl(&csl))
+}
+
+/// Completes references that only carry a DOI, up to [`DOI_LOOKUP_CONCURRENCY`] lookups
+/// at a time. Resolver failures keep the user-provided fields so formatting still
+/// produces a best-effort citation.
+pub async fn resolve_references(references: Vec<Reference>) -> Vec<Reference> {
+    let client = match client_builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+    {
+        Ok(c) => c,
+        Err(e) => {
+            log::warn!("Could not build DOI resolver client: {}", e);
+            return references;
+        }
+    };
+
+    resolve_with(&client, DOI_RESOLVER, references).await
+}
+
+/// Resolves against `resolver`, keeping the order of `references`.
+async fn resolve_with(
+    client: &reqwest::Client,
+    resolver: &str,
+    references: Vec<Reference>,
+) -> Vec<Reference> {
+    stream::iter(references)
+        .map(|mut reference| async move {
+            if reference.needs_resolution() {
+                let doi = reference.doi.clone().unwrap_or_default();
+                match resolve_doi(client, resolver, &doi).await {
+                    Ok(meta) => reference.merge_missing(meta),
+                    Err(e) => log::warn!("DOI lookup failed for {}: {}", doi, e),
+                }
+            }
+            reference
+        })
+        .buffered(DOI_LOOKUP_CONCURRENCY)
+        .collect()
+        .await
+}
+
+fn check_references(references: &[Reference]) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    let error = if references.is_empty() {
+        "At least one reference is required".to_string()
+    } else if references.len() > MAX_REFERENCES {
+        format!(
+            "At most {} references are allowed per request",
+            MAX_REFERENCES
+        )
+    } else {
+        return Ok(());
+    };
+    Err((
+        StatusCode::BAD_REQUEST,
+        Json(serde_json::json!({ "error": error })),
+    ))
+}
+
+pub async fn handle_format_citations(
+    State(_state): State<Arc<AppState>>,
+    ApiJson(req): ApiJson<CitationRequest>,
+) -> Result<Json<CitationResponse>, (StatusCode, Json<serde_json::Value>)> {
+    check_references(&req.references)?;
+
+    let references = resolve_references(req.references).await;
+    let citations = references
+        .iter()
+        .map(|r| format_citation(r, req.style))
+        .collect();
+
+    Ok(Json(CitationResponse {
+        style: req.style,
+        citations,
+    }))
+}
+
+pub async fn handle_bibliography(
+    State(_state): State<Arc<AppState>>,
+    ApiJson(req): ApiJson<BibliographyRequest>,
+) -> Result<Json<BibliographyResponse>, (StatusCode, Json<serde_json::Value>)> {
+    check_references(&req.references)?;
+
+    let references = resolve_references(req.references).await;
+    let bibliography = build_bibliography(&references, req.style, req.heading.as_deref());
+
+    Ok(Json(BibliographyResponse {
+        style: req.style,
+        bibliography,
+        entries: references.len(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Reference {
+        Reference {
+            doi: Some("10.1000/xyz123".to_string()),
+            url: None,
+            title: Some("Deep learning for chat systems".to_string()),
+            authors: vec!["Jane Ann Smith".to_string(), "Doe, John".to_string()],
+            year: Some(2021),
+            container: Some("Journal of Bots".to_string()),
+            volume: Some("12".to_string()),
+            issue: Some("3".to_string()),
+            pages: Some("45-67".to_string()),
+            publisher: None,
+        }
+    }
+
+    #[test]
+    fn test_format_apa() {
+        assert_eq!(
+            format_apa(&sample()),
+            "Smith, J. A., & Doe, J. (2021). Deep learning for chat systems. \
+             Journal of Bots, 12(3), 45-67. https://doi.org/10.1000/xyz123"
+        );
+    }
+
+    #[test]
+    fn test_format_mla() {
+        assert_eq!(
+            format_mla(&sample()),
+            "Smith, Jane Ann, and John Doe. \"Deep learning for chat systems.\" \
+             Journal of Bots, vol. 12, no. 3, 2021, pp. 45-67, https://doi.org/10.1000/xyz123."
+        );
+    }
+
+    #[test]
+    fn test_format_bibtex() {
+        let expected = "@article{smith2021deep,\n\
+                        \x20 author = {Smith, Jane Ann and Doe, John},\n\
+                        \x20 title = {Deep learning for chat systems},\n\
+                        \x20 journal = {Journal of Bots},\n\
+                        \x20 year = {2021},\n\
+                        \x20 volume = {12},\n\
+                        \x20 number = {3},\n\
+                        \x20 pages = {45--67},\n\
+                        \x20 doi = {10.1000/xyz123}\n\
+                        }";
+        assert_eq!(format_bibtex(&sample()), expected);
+    }
+
+    #[test]
+    fn test_bibliography_sorted_with_heading() {
+        let mut other = sample();
+        other.authors = vec!["Adams, Zoe".to_string()];
+        let bib = build_bibliography(&[sample(), other], CitationStyle::Apa, None);
+        assert!(bib.starts_with("References\n\nAdams, Z."));
+    }
+
+    #[test]
+    fn test_csl_metadata_fills_missing_fields_only() {
+        let csl = serde_json::json!({
+            "title": "Resolved title",
+            "author": [{"family": "Lee", "given": "Kim"}],
+            "issued": {"date-parts": [[2019, 5]]},
+            "container-title": "Resolved Journal"
+        });
+        let mut reference = Reference {
+            doi: Some("10.1/abc".to_string()),
+            year: Some(2020),
+            ..Default::default()
+        };
+        reference.merge_missing(reference_from_csl(&csl));
+
+        assert_eq!(reference.title.as_deref(), Some("Resolved title"));
+        assert_eq!(reference.authors, vec!["Lee, Kim".to_string()]);
+        assert_eq!(reference.year, Some(2020));
+    }
+
+    #[test]
+    fn test_reference_count_is_capped() {
+        assert!(check_references(&[]).is_err());
+        assert!(check_references(&vec![sample(); MAX_REFERENCES]).is_ok());
+        let (status, _) = check_references(&vec![sample(); MAX_REFERENCES + 1]).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_lookups_run_concurrently_up_to_the_limit() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (current, max) = (Arc::clone(&in_flight), Arc::clone(&peak));
+        let app = axum::Router::new().route(
+            "/10.1/:id",
+            axum::routing::get(
+                move |axum::extract::Path(id): axum::extract::Path<String>| {
+                    let (current, max) = (Arc::clone(&current), Arc::clone(&max));
+                    async move {
+                        let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                        max.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                        current.fetch_sub(1, Ordering::SeqCst);
+                        Json(serde_json::json!({ "title": format!("Paper {id}") }))
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let resolver = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let references: Vec<Reference> = (0..DOI_LOOKUP_CONCURRENCY * 3)
+            .map(|i| Reference {
+                doi: Some(format!("10.1/{i}")),
+                ..Default::default()
+            })
+            .collect();
+        let resolved = resolve_with(&reqwest::Client::new(), &resolver, references).await;
+
+        let titles: Vec<_> = resolved.iter().map(|r| r.title.clone().unwrap()).collect();
+        let expected: Vec<_> = (0..DOI_LOOKUP_CONCURRENCY * 3)
+            .map(|i| format!("Paper {i}"))
+            .collect();
+        assert_eq!(titles, expected);
+        let peak = peak.load(Ordering::SeqCst);
+        assert!(peak > 1 && peak <= DOI_LOOKUP_CONCURRENCY, "peak {peak}");
+    }
+}
@@ -1,10 +1,10 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::core::urls::ApiUrls;
 use axum::{
     extract::{Path, Query, State},
     http::HeaderMap,
     response::{Html, IntoResponse},
-    Json,
 };
 use std::sync::Arc;
 use uuid::Uuid;
@@ -179,7 +179,7 @@ pub async fn handle_get_document(
 pub async fn handle_save_document(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    Json(payload): Json<SaveRequest>,
+    ApiJson(payload): ApiJson<SaveRequest>,
 ) -> impl IntoResponse {
     let (_user_id, user_identifier) = match get_current_user(&state, &headers).await {
         Ok(u) => u,
@@ -223,7 +223,7 @@ pub async fn handle_save_document(
 pub async fn handle_autosave(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
-    Json(payload): Json<SaveRequest>,
+    ApiJson(payload): ApiJson<SaveRequest>,
 ) -> impl IntoResponse {
     let (_user_id, user_identifier) = match get_current_user(&state, &headers).await {
         Ok(u) => u,
@@ -3,6 +3,7 @@
 
 pub mod ai_handlers;
 pub mod auth;
+pub mod citations;
 pub mod export;
 pub mod handlers;
 pub mod llm;
@@ -20,6 +21,7 @@ pub use storage::{
     save_document_to_drive,
 };
 pub use llm::call_llm;
+pub use citations::{handle_bibliography, handle_format_citations};
 
 pub use handlers::{
     handle_autosave, handle_delete_document, handle_get_document, handle_list_documents,
@@ -78,4 +80,6 @@ pub fn configure_paper_routes() -> Router<Arc<AppState>> {
         .route(ApiUrls::PAPER_EXPORT_MD, get(handle_export_md))
         .route(ApiUrls::PAPER_EXPORT_HTML, get(handle_export_html))
         .route(ApiUrls::PAPER_EXPORT_TXT, get(handle_export_txt))
+        .route(ApiUrls::PAPER_CITATIONS_FORMAT, post(handle_format_citations))
+        .route(ApiUrls::PAPER_BIBLIOGRAPHY, post(handle_bibliography))
 }
@@ -1,5 +1,6 @@
 pub mod ui;
 
+use crate::core::shared::api_json::ApiJson;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -315,7 +316,7 @@ fn bd(val: f64) -> BigDecimal {
 
 pub async fn create_person(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreatePersonRequest>,
+    ApiJson(req): ApiJson<CreatePersonRequest>,
 ) -> Result<Json<Person>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -491,7 +492,7 @@ pub async fn get_person(
 pub async fn update_person(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdatePersonRequest>,
+    ApiJson(req): ApiJson<UpdatePersonRequest>,
 ) -> Result<Json<Person>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -603,7 +604,7 @@ pub async fn get_direct_reports(
 
 pub async fn create_team(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateTeamRequest>,
+    ApiJson(req): ApiJson<CreateTeamRequest>,
 ) -> Result<Json<Team>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -703,7 +704,7 @@ pub async fn get_team(
 pub async fn add_team_member(
     State(state): State<Arc<AppState>>,
     Path(team_id): Path<Uuid>,
-    Json(req): Json<AddTeamMemberRequest>,
+    ApiJson(req): ApiJson<AddTeamMemberRequest>,
 ) -> Result<Json<TeamMember>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -765,7 +766,7 @@ pub async fn delete_team(
 
 pub async fn create_department(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateDepartmentRequest>,
+    ApiJson(req): ApiJson<CreateDepartmentRequest>,
 ) -> Result<Json<Department>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -840,7 +841,7 @@ pub async fn list_skills(
 
 pub async fn create_skill(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateSkillRequest>,
+    ApiJson(req): ApiJson<CreateSkillRequest>,
 ) -> Result<Json<Skill>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -872,7 +873,7 @@ pub async fn create_skill(
 pub async fn add_person_skill(
     State(state): State<Arc<AppState>>,
     Path(person_id): Path<Uuid>,
-    Json(req): Json<AddPersonSkillRequest>,
+    ApiJson(req): ApiJson<AddPersonSkillRequest>,
 ) -> Result<Json<PersonSkill>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -902,7 +903,7 @@ pub async fn add_person_skill(
 
 pub async fn create_time_off(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateTimeOffRequest>,
+    ApiJson(req): ApiJson<CreateTimeOffRequest>,
 ) -> Result<Json<TimeOff>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -971,7 +972,7 @@ pub async fn list_time_off(
 pub async fn approve_time_off(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<ApproveTimeOffRequest>,
+    ApiJson(req): ApiJson<ApproveTimeOffRequest>,
 ) -> Result<Json<TimeOff>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
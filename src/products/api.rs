@@ -16,6 +16,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::{
     inventory_movements, price_list_items, price_lists, product_categories, product_variants,
     products, services,
@@ -344,7 +345,7 @@ fn bd_to_f64(val: &BigDecimal) -> f64 {
 
 pub async fn create_product(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateProductRequest>,
+    ApiJson(req): ApiJson<CreateProductRequest>,
 ) -> Result<Json<Product>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -463,7 +464,7 @@ pub async fn get_product(
 pub async fn update_product(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateProductRequest>,
+    ApiJson(req): ApiJson<UpdateProductRequest>,
 ) -> Result<Json<Product>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -544,7 +545,7 @@ pub async fn delete_product(
 pub async fn adjust_stock(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<AdjustStockRequest>,
+    ApiJson(req): ApiJson<AdjustStockRequest>,
 ) -> Result<Json<Product>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -602,7 +603,7 @@ pub async fn adjust_stock(
 
 pub async fn create_service(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateServiceRequest>,
+    ApiJson(req): ApiJson<CreateServiceRequest>,
 ) -> Result<Json<Service>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -701,7 +702,7 @@ pub async fn get_service(
 pub async fn update_service(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateServiceRequest>,
+    ApiJson(req): ApiJson<UpdateServiceRequest>,
 ) -> Result<Json<Service>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -787,7 +788,7 @@ pub async fn list_categories(
 
 pub async fn create_category(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateCategoryRequest>,
+    ApiJson(req): ApiJson<CreateCategoryRequest>,
 ) -> Result<Json<ProductCategory>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -848,7 +849,7 @@ pub async fn list_price_lists(
 
 pub async fn create_price_list(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreatePriceListRequest>,
+    ApiJson(req): ApiJson<CreatePriceListRequest>,
 ) -> Result<Json<PriceList>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
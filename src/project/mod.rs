@@ -11,6 +11,7 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 pub mod import;
@@ -771,7 +772,7 @@ pub struct AddDependencyRequest {
 
 async fn create_project(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateProjectRequest>,
+    ApiJson(req): ApiJson<CreateProjectRequest>,
 ) -> Result<Json<Project>, (StatusCode, Json<serde_json::Value>)> {
     let project = Project {
         id: Uuid::new_v4(),
@@ -824,7 +825,7 @@ async fn delete_project(
 async fn create_task(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<CreateTaskRequest>,
+    ApiJson(req): ApiJson<CreateTaskRequest>,
 ) -> Result<Json<ProjectTask>, (StatusCode, Json<serde_json::Value>)> {
     let end_date = req.start_date + chrono::Duration::days(req.duration_days as i64);
 
@@ -873,7 +874,7 @@ async fn get_tasks(
 async fn update_task_progress(
     State(state): State<Arc<AppState>>,
     Path(task_id): Path<Uuid>,
-    Json(req): Json<UpdateProgressRequest>,
+    ApiJson(req): ApiJson<UpdateProgressRequest>,
 ) -> Result<Json<ProjectTask>, (StatusCode, Json<serde_json::Value>)> {
     let service = state.project_service.read().await;
     match service.update_task_progress(task_id, req.percent_complete).await {
@@ -888,7 +889,7 @@ async fn update_task_progress(
 async fn add_dependency(
     State(state): State<Arc<AppState>>,
     Path(task_id): Path<Uuid>,
-    Json(req): Json<AddDependencyRequest>,
+    ApiJson(req): ApiJson<AddDependencyRequest>,
 ) -> Result<Json<ProjectTask>, (StatusCode, Json<serde_json::Value>)> {
     let service = state.project_service.read().await;
     match service
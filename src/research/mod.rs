@@ -1,12 +1,13 @@
 pub mod ui;
 pub mod web_search;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use axum::{
     extract::{Path, State},
     response::{Html, IntoResponse},
     routing::{get, post},
-    Form, Json, Router,
+    Form, Router,
 };
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -160,7 +161,7 @@ fn get_default_collections() -> Vec<(String, String, String)> {
 
 pub async fn handle_create_collection(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<NewCollectionRequest>,
+    ApiJson(payload): ApiJson<NewCollectionRequest>,
 ) -> impl IntoResponse {
     let conn = state.conn.clone();
     let id = uuid::Uuid::new_v4().to_string();
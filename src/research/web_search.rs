@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use axum::{
     extract::{Query, State},
@@ -108,7 +109,7 @@ pub fn configure_web_search_routes() -> Router<Arc<AppState>> {
 
 pub async fn handle_web_search(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<WebSearchRequest>,
+    ApiJson(payload): ApiJson<WebSearchRequest>,
 ) -> impl IntoResponse {
     let start_time = std::time::Instant::now();
 
@@ -146,7 +147,7 @@ pub async fn handle_web_search(
 
 pub async fn handle_summarize(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<SummarizeRequest>,
+    ApiJson(payload): ApiJson<SummarizeRequest>,
 ) -> impl IntoResponse {
     if payload.results.is_empty() {
         return Json(SummarizeResponse {
@@ -191,7 +192,7 @@ pub async fn handle_summarize(
 
 pub async fn handle_deep_research(
     State(_state): State<Arc<AppState>>,
-    Json(payload): Json<DeepResearchRequest>,
+    ApiJson(payload): ApiJson<DeepResearchRequest>,
 ) -> impl IntoResponse {
     let start_time = std::time::Instant::now();
 
@@ -31,6 +31,7 @@ impl Default for AuthConfig {
                 "/oauth".to_string(),
                 "/auth/callback".to_string(),
                 "/webhook/whatsapp".to_string(),
+                "/webhook/sheet".to_string(),
             ],
             public_paths: vec![
                 "/".to_string(),
@@ -10,6 +10,7 @@ pub mod middleware;
 pub mod tests;
 pub mod types;
 pub mod utils;
+pub mod websocket;
 
 // Re-export commonly used types at the module level
 pub use config::AuthConfig;
@@ -29,3 +30,4 @@ pub use utils::{
     extract_user_from_request, extract_user_with_providers, is_jwt_format,
     validate_session_sync,
 };
+pub use websocket::{authenticate_ws, set_ws_auth_config, WS_BEARER_PROTOCOL};
@@ -244,4 +244,53 @@ mod tests {
 
         assert_eq!(user.highest_role(), &Role::Admin);
     }
+
+    #[tokio::test]
+    async fn test_ws_rejects_anonymous_upgrade() {
+        let config = AuthConfig::default();
+        let headers = axum::http::HeaderMap::new();
+        let query = std::collections::HashMap::new();
+
+        let err = websocket::authenticate_ws_request(&config, None, &headers, &query)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_ws_allows_anonymous_when_auth_not_required() {
+        let config = AuthConfig::default().with_require_auth(false);
+        let headers = axum::http::HeaderMap::new();
+        let query = std::collections::HashMap::new();
+
+        let user = websocket::authenticate_ws_request(&config, None, &headers, &query)
+            .await
+            .unwrap();
+        assert!(!user.is_authenticated());
+    }
+
+    #[test]
+    fn test_ws_token_sources() {
+        let mut headers = axum::http::HeaderMap::new();
+        let mut query = std::collections::HashMap::new();
+        assert_eq!(websocket::extract_ws_token(&headers, &query, "Bearer "), None);
+
+        headers.insert("authorization", "Bearer from-header".parse().unwrap());
+        assert_eq!(
+            websocket::extract_ws_token(&headers, &query, "Bearer ").as_deref(),
+            Some("from-header")
+        );
+
+        headers.insert("sec-websocket-protocol", "bearer, from-protocol".parse().unwrap());
+        assert_eq!(
+            websocket::extract_ws_token(&headers, &query, "Bearer ").as_deref(),
+            Some("from-protocol")
+        );
+
+        query.insert("token".to_string(), "from-query".to_string());
+        assert_eq!(
+            websocket::extract_ws_token(&headers, &query, "Bearer ").as_deref(),
+            Some("from-query")
+        );
+    }
 }
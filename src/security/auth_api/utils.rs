@@ -143,11 +143,19 @@ pub fn validate_session_sync(session_id: &str) -> Result<AuthenticatedUser, Auth
         &session_id[..std::cmp::min(20, session_id.len())]
     );
 
+    // Revoked sessions must not reach the uncached fallback below
+    #[cfg(feature = "directory")]
+    if crate::directory::sessions::is_revoked(session_id) {
+        warn!("Session validation failed: session was revoked");
+        return Err(AuthError::SessionExpired);
+    }
+
     // Try to get user data from session cache first
     #[cfg(feature = "directory")]
     if let Ok(cache_guard) = crate::directory::auth_routes::SESSION_CACHE.try_read() {
         if let Some(user_data) = cache_guard.get(session_id) {
             debug!("Found user in session cache: {}", user_data.email);
+            crate::directory::sessions::touch(session_id);
 
             // Parse user_id from cached data
             let user_id = Uuid::parse_str(&user_data.user_id).unwrap_or_else(|_| Uuid::new_v4());
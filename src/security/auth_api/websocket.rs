@@ -0,0 +1,119 @@
+use super::{
+    config::AuthConfig,
+    error::AuthError,
+    types::AuthenticatedUser,
+    utils::{authenticate_with_extracted_data, validate_session_sync, ExtractedAuthData},
+};
+use crate::security::auth_provider::AuthProviderRegistry;
+use axum::http::{header, HeaderMap};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+/// Subprotocol a browser client offers together with its token, since browsers cannot
+/// set an Authorization header on a websocket: `Sec-WebSocket-Protocol: bearer, <token>`.
+/// Handlers must select it with `WebSocketUpgrade::protocols` so the handshake succeeds.
+pub const WS_BEARER_PROTOCOL: &str = "bearer";
+
+static WS_AUTH_CONFIG: OnceLock<Arc<AuthConfig>> = OnceLock::new();
+
+/// Shares the server's auth configuration with websocket handlers, which run behind an
+/// anonymous path and authenticate the upgrade request themselves.
+pub fn set_ws_auth_config(config: Arc<AuthConfig>) {
+    let _ = WS_AUTH_CONFIG.set(config);
+}
+
+fn ws_auth_config() -> Arc<AuthConfig> {
+    WS_AUTH_CONFIG
+        .get_or_init(|| Arc::new(AuthConfig::default()))
+        .clone()
+}
+
+/// Token from, in order: the `token` query parameter, the `bearer` subprotocol, or the
+/// Authorization header.
+pub fn extract_ws_token(
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+    bearer_prefix: &str,
+) -> Option<String> {
+    if let Some(token) = query.get("token").filter(|t| !t.is_empty()) {
+        return Some(token.clone());
+    }
+
+    let from_protocol = headers
+        .get(header::SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|protocols| {
+            let mut parts = protocols.split(',').map(str::trim);
+            parts
+                .by_ref()
+                .find(|p| *p == WS_BEARER_PROTOCOL)
+                .and_then(|_| parts.next())
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+        });
+    if from_protocol.is_some() {
+        return from_protocol;
+    }
+
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.strip_prefix(bearer_prefix))
+        .map(str::to_string)
+}
+
+fn session_cookie(headers: &HeaderMap, cookie_name: &str) -> Option<String> {
+    headers
+        .get(header::COOKIE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|cookies| {
+            cookies.split(';').find_map(|cookie| {
+                let (name, value) = cookie.trim().split_once('=')?;
+                (name == cookie_name).then(|| value.to_string())
+            })
+        })
+}
+
+/// Authenticates a websocket upgrade request before `on_upgrade`. Anonymous users are
+/// only returned when the deployment does not require authentication.
+pub async fn authenticate_ws_request(
+    config: &AuthConfig,
+    registry: Option<&AuthProviderRegistry>,
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+) -> Result<AuthenticatedUser, AuthError> {
+    let token = extract_ws_token(headers, query, &config.bearer_prefix);
+    let session_id = session_cookie(headers, &config.session_cookie_name);
+
+    let user = match registry {
+        Some(registry) => {
+            let data = ExtractedAuthData {
+                api_key: None,
+                bearer_token: token,
+                session_id,
+                user_id_header: None,
+                bot_id: None,
+            };
+            authenticate_with_extracted_data(data, config, registry).await?
+        }
+        None => match token.or(session_id) {
+            Some(token) => validate_session_sync(&token)?,
+            None if !config.require_auth => AuthenticatedUser::anonymous(),
+            None => return Err(AuthError::MissingToken),
+        },
+    };
+
+    if !user.is_authenticated() && config.require_auth {
+        return Err(AuthError::MissingToken);
+    }
+    Ok(user)
+}
+
+/// `authenticate_ws_request` with the server's auth configuration.
+pub async fn authenticate_ws(
+    registry: Option<&AuthProviderRegistry>,
+    headers: &HeaderMap,
+    query: &HashMap<String, String>,
+) -> Result<AuthenticatedUser, AuthError> {
+    authenticate_ws_request(&ws_auth_config(), registry, headers, query).await
+}
@@ -0,0 +1,165 @@
+//! The address a request really came from.
+//!
+//! Forwarding headers are only believed when the connection itself comes from a trusted
+//! proxy, listed in `TRUSTED_PROXIES` as comma-separated addresses or CIDR ranges
+//! (default: loopback). `X-Forwarded-For` is then read from the right, skipping further
+//! trusted proxies, so an entry the client prepended itself is never used.
+
+use axum::http::HeaderMap;
+use std::net::IpAddr;
+use std::sync::LazyLock;
+
+pub const TRUSTED_PROXIES_ENV: &str = "TRUSTED_PROXIES";
+
+const DEFAULT_TRUSTED_PROXIES: &str = "127.0.0.1,::1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    fn parse(value: &str) -> Option<Self> {
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr.trim().parse().ok()?, Some(prefix.trim().parse().ok()?)),
+            None => (value.trim().parse().ok()?, None),
+        };
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    networks: Vec<Network>,
+}
+
+impl TrustedProxies {
+    pub fn from_env() -> Self {
+        let list = std::env::var(TRUSTED_PROXIES_ENV)
+            .unwrap_or_else(|_| DEFAULT_TRUSTED_PROXIES.to_string());
+        Self::parse(&list)
+    }
+
+    /// Parses a comma-separated list; entries that are not addresses are skipped.
+    pub fn parse(list: &str) -> Self {
+        let networks = list
+            .split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .filter_map(|entry| {
+                let network = Network::parse(entry);
+                if network.is_none() {
+                    log::warn!("Ignoring invalid {} entry: {}", TRUSTED_PROXIES_ENV, entry);
+                }
+                network
+            })
+            .collect();
+        Self { networks }
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.networks.iter().any(|network| network.contains(ip))
+    }
+
+    /// The client behind `peer`, the address the connection came from.
+    pub fn client_ip(&self, headers: &HeaderMap, peer: IpAddr) -> IpAddr {
+        if !self.contains(peer) {
+            return peer;
+        }
+
+        let forwarded: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+        if forwarded.is_empty() {
+            return headers
+                .get("x-real-ip")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(peer);
+        }
+
+        let mut client = peer;
+        for hop in forwarded.iter().rev() {
+            let Ok(ip) = hop.parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !self.contains(ip) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+static TRUSTED_PROXIES: LazyLock<TrustedProxies> = LazyLock::new(TrustedProxies::from_env);
+
+/// The client address of a request that arrived from `peer`, using `TRUSTED_PROXIES`.
+/// `None` when the connection address is not known.
+pub fn client_ip(headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+    peer.map(|peer| TRUSTED_PROXIES.client_ip(headers, peer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_forwarded_for_only_from_trusted_proxies() {
+        let proxies = TrustedProxies::parse("10.0.0.0/8, ::1");
+        let headers = forwarded("6.6.6.6, 203.0.113.7, 10.0.0.2");
+
+        // The client-supplied leftmost entry is never used.
+        assert_eq!(
+            proxies.client_ip(&headers, ip("10.0.0.1")),
+            ip("203.0.113.7")
+        );
+        // A direct client cannot claim another address.
+        assert_eq!(
+            proxies.client_ip(&headers, ip("198.51.100.4")),
+            ip("198.51.100.4")
+        );
+        assert_eq!(proxies.client_ip(&HeaderMap::new(), ip("::1")), ip("::1"));
+        assert_eq!(
+            proxies.client_ip(&forwarded("garbage, 203.0.113.9"), ip("::1")),
+            ip("203.0.113.9")
+        );
+    }
+}
@@ -42,6 +42,8 @@ static ALLOWED_COMMANDS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
         "brew",
         "rustc",
         "nvcc",
+        // Reverse proxy
+        "caddy",
         // Desktop/sync commands
         "rclone",
         // Notification commands
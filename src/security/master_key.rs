@@ -0,0 +1,569 @@
+//! Versioned master key used for secrets stored in the database (email account passwords,
+//! OAuth tokens, TOTP secrets).
+//!
+//! The key material lives in Vault at `gbo/encryption`:
+//! - `master_key`: the current key (kept for tools that read it directly)
+//! - `master_key_version`: number of the current key
+//! - `master_key_v<N>`: every version still needed to read existing ciphertext
+//!
+//! Values are stored as `mk<N>:<nonce>:<ciphertext>`, so each value names the key version
+//! that sealed it and old versions keep working until re-encryption has moved every value
+//! to the current one. Values without the prefix predate the keyring and are read with
+//! their column's legacy encoding.
+
+use crate::core::secrets::{SecretPaths, SecretsManager};
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, LazyLock, RwLock};
+use uuid::Uuid;
+
+const NONCE_SIZE: usize = 12;
+const KEY_MATERIAL_LEN: usize = 64;
+const PREFIX: &str = "mk";
+
+static INSTALLED: LazyLock<RwLock<Option<Arc<MasterKeyring>>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+/// How a column stored its values before the keyring existed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyEncoding {
+    Base64,
+    Plain,
+}
+
+#[derive(Clone)]
+pub struct MasterKeyring {
+    current: u32,
+    keys: BTreeMap<u32, [u8; 32]>,
+    material: BTreeMap<u32, String>,
+}
+
+impl std::fmt::Debug for MasterKeyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MasterKeyring")
+            .field("current", &self.current)
+            .field("versions", &self.keys.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn derive(material: &str) -> [u8; 32] {
+    Sha256::digest(material.as_bytes()).into()
+}
+
+fn generate_material() -> String {
+    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+    let mut rng = rand::rng();
+    (0..KEY_MATERIAL_LEN)
+        .map(|_| CHARSET[rng.random_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
+impl MasterKeyring {
+    pub fn single(version: u32, material: &str) -> Self {
+        Self {
+            current: version,
+            keys: BTreeMap::from([(version, derive(material))]),
+            material: BTreeMap::from([(version, material.to_string())]),
+        }
+    }
+
+    /// Reads the keyring from the `gbo/encryption` secret. A secret written before
+    /// versioning (only `master_key`) becomes version 1.
+    pub fn from_secrets(secrets: &HashMap<String, String>) -> Result<Self> {
+        let mut material: BTreeMap<u32, String> = secrets
+            .iter()
+            .filter_map(|(k, v)| {
+                let version = k.strip_prefix("master_key_v")?.parse().ok()?;
+                (!v.is_empty()).then(|| (version, v.clone()))
+            })
+            .collect();
+
+        let current = match secrets.get("master_key_version") {
+            Some(v) => v
+                .parse()
+                .map_err(|_| anyhow!("Invalid master_key_version '{}'", v))?,
+            None => 1,
+        };
+        if let Some(key) = secrets.get("master_key").filter(|k| !k.is_empty()) {
+            material.entry(current).or_insert_with(|| key.clone());
+        }
+        if !material.contains_key(&current) {
+            return Err(anyhow!("Master key version {} not found in Vault", current));
+        }
+
+        Ok(Self {
+            current,
+            keys: material.iter().map(|(v, m)| (*v, derive(m))).collect(),
+            material,
+        })
+    }
+
+    pub fn to_secrets(&self) -> HashMap<String, String> {
+        let mut secrets: HashMap<String, String> = self
+            .material
+            .iter()
+            .map(|(v, m)| (format!("master_key_v{}", v), m.clone()))
+            .collect();
+        secrets.insert("master_key_version".into(), self.current.to_string());
+        if let Some(current) = self.material.get(&self.current) {
+            secrets.insert("master_key".into(), current.clone());
+        }
+        secrets
+    }
+
+    pub fn current_version(&self) -> u32 {
+        self.current
+    }
+
+    pub fn versions(&self) -> Vec<u32> {
+        self.keys.keys().copied().collect()
+    }
+
+    /// Adds a new random key version and makes it current. Older versions are kept.
+    pub fn add_version(&mut self) -> u32 {
+        self.add_version_with(&generate_material())
+    }
+
+    fn add_version_with(&mut self, material: &str) -> u32 {
+        let version = self.keys.keys().max().copied().unwrap_or(0) + 1;
+        self.keys.insert(version, derive(material));
+        self.material.insert(version, material.to_string());
+        self.current = version;
+        version
+    }
+
+    /// Drops every version except the current one.
+    pub fn retire_old_versions(&mut self) -> Vec<u32> {
+        let current = self.current;
+        let retired: Vec<u32> = self.keys.keys().copied().filter(|v| *v != current).collect();
+        self.keys.retain(|v, _| *v == current);
+        self.material.retain(|v, _| *v == current);
+        retired
+    }
+
+    pub fn seal(&self, plaintext: &str) -> Result<String> {
+        let key = self
+            .keys
+            .get(&self.current)
+            .ok_or_else(|| anyhow!("Current master key missing"))?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+        let mut rng = rand::rng();
+        let nonce_bytes: [u8; NONCE_SIZE] = std::array::from_fn(|_| rng.random());
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_bytes())
+            .map_err(|e| anyhow!("Encryption failed: {e}"))?;
+        Ok(format!(
+            "{}{}:{}:{}",
+            PREFIX,
+            self.current,
+            BASE64.encode(nonce_bytes),
+            BASE64.encode(ciphertext)
+        ))
+    }
+
+    /// Decrypts a keyring value. Returns `Ok(None)` for values not in keyring format.
+    pub fn open(&self, value: &str) -> Result<Option<String>> {
+        let Some(version) = sealed_version(value) else {
+            return Ok(None);
+        };
+        let key = self
+            .keys
+            .get(&version)
+            .ok_or_else(|| anyhow!("Master key version {} is not available", version))?;
+        let mut parts = value.splitn(3, ':').skip(1);
+        let (Some(nonce), Some(ciphertext)) = (parts.next(), parts.next()) else {
+            return Err(anyhow!("Malformed sealed value"));
+        };
+        let nonce = BASE64
+            .decode(nonce)
+            .map_err(|e| anyhow!("Invalid nonce encoding: {e}"))?;
+        if nonce.len() != NONCE_SIZE {
+            return Err(anyhow!("Invalid nonce size"));
+        }
+        let ciphertext = BASE64
+            .decode(ciphertext)
+            .map_err(|e| anyhow!("Invalid ciphertext encoding: {e}"))?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|e| anyhow!("Decryption failed: {e}"))?;
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|e| anyhow!("Invalid UTF-8: {e}"))
+    }
+
+    /// Plaintext of a keyring or legacy value.
+    pub fn reveal(&self, value: &str, legacy: LegacyEncoding) -> Result<String> {
+        match self.open(value)? {
+            Some(plaintext) => Ok(plaintext),
+            None => decode_legacy(value, legacy),
+        }
+    }
+}
+
+/// Key version of a `mk<N>:...` value.
+pub fn sealed_version(value: &str) -> Option<u32> {
+    let (head, _) = value.split_once(':')?;
+    head.strip_prefix(PREFIX)?.parse().ok()
+}
+
+fn decode_legacy(value: &str, legacy: LegacyEncoding) -> Result<String> {
+    match legacy {
+        LegacyEncoding::Plain => Ok(value.to_string()),
+        LegacyEncoding::Base64 => BASE64
+            .decode(value)
+            .map_err(|e| anyhow!("Decryption failed: {e}"))
+            .and_then(|bytes| {
+                String::from_utf8(bytes).map_err(|e| anyhow!("UTF-8 conversion failed: {e}"))
+            }),
+    }
+}
+
+fn encode_legacy(plaintext: &str, legacy: LegacyEncoding) -> String {
+    match legacy {
+        LegacyEncoding::Plain => plaintext.to_string(),
+        LegacyEncoding::Base64 => BASE64.encode(plaintext.as_bytes()),
+    }
+}
+
+/// Makes `keyring` the one used by `protect` and `reveal`.
+pub fn install(keyring: MasterKeyring) {
+    *INSTALLED.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(keyring));
+}
+
+pub fn installed() -> Option<Arc<MasterKeyring>> {
+    INSTALLED.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub async fn load_from_vault(secrets: &SecretsManager) -> Result<MasterKeyring> {
+    let data = secrets.get_secret(SecretPaths::ENCRYPTION).await?;
+    MasterKeyring::from_secrets(&data)
+}
+
+/// Loads the keyring from Vault at startup. Without one, values keep their legacy encoding.
+pub async fn init_from_vault() {
+    let Ok(secrets) = SecretsManager::get() else {
+        return;
+    };
+    match load_from_vault(secrets).await {
+        Ok(keyring) => {
+            log::info!("Master key loaded (version {})", keyring.current_version());
+            install(keyring);
+        }
+        Err(e) => log::warn!("Master key unavailable, stored secrets stay unencrypted: {}", e),
+    }
+}
+
+/// Value to store for a secret: sealed with the current master key when available.
+pub fn protect(plaintext: &str, legacy: LegacyEncoding) -> Result<String> {
+    match installed() {
+        Some(keyring) => keyring.seal(plaintext),
+        None => Ok(encode_legacy(plaintext, legacy)),
+    }
+}
+
+/// Plaintext of a stored secret written by `protect` or before the keyring existed.
+pub fn reveal(value: &str, legacy: LegacyEncoding) -> Result<String> {
+    match installed() {
+        Some(keyring) => keyring.reveal(value, legacy),
+        None if sealed_version(value).is_some() => {
+            Err(anyhow!("Value is encrypted but no master key is loaded"))
+        }
+        None => decode_legacy(value, legacy),
+    }
+}
+
+/// Stored instead of a password for mail accounts that sign in through OAuth.
+pub const OAUTH_PASSWORD_MARKER: &str = "oauth";
+
+/// A database column holding values written by `protect`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CipherColumn {
+    pub table: &'static str,
+    pub column: &'static str,
+    pub legacy: LegacyEncoding,
+    /// Values that stand for "no secret" rather than holding one; re-encryption leaves
+    /// them alone.
+    pub markers: &'static [&'static str],
+}
+
+impl CipherColumn {
+    pub fn is_marker(&self, value: &str) -> bool {
+        self.markers.contains(&value)
+    }
+}
+
+pub const CIPHER_COLUMNS: &[CipherColumn] = &[
+    CipherColumn {
+        table: "user_email_accounts",
+        column: "password_encrypted",
+        legacy: LegacyEncoding::Base64,
+        markers: &[OAUTH_PASSWORD_MARKER],
+    },
+    CipherColumn {
+        table: "connected_accounts",
+        column: "access_token",
+        legacy: LegacyEncoding::Plain,
+        markers: &[],
+    },
+    CipherColumn {
+        table: "connected_accounts",
+        column: "refresh_token",
+        legacy: LegacyEncoding::Plain,
+        markers: &[],
+    },
+    CipherColumn {
+        table: "user_totp",
+        column: "secret_encrypted",
+        legacy: LegacyEncoding::Plain,
+        markers: &[],
+    },
+];
+
+/// Row access for re-encryption, so the loop can run against the database or a test double.
+pub trait CipherStore {
+    /// Up to `limit` rows with `id > after`, ordered by id, whose value is not yet sealed
+    /// with `target_version`.
+    fn pending(
+        &mut self,
+        column: &CipherColumn,
+        target_version: u32,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<(Uuid, String)>>;
+
+    /// Replaces `old` with `new`. Returns `false` if the row changed in the meantime.
+    fn replace(&mut self, column: &CipherColumn, id: Uuid, old: &str, new: &str) -> Result<bool>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReencryptStats {
+    pub processed: i64,
+    pub skipped: i64,
+    pub failed: i64,
+}
+
+impl std::ops::Add for ReencryptStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            processed: self.processed + other.processed,
+            skipped: self.skipped + other.skipped,
+            failed: self.failed + other.failed,
+        }
+    }
+}
+
+/// Moves every value of `column` to the keyring's current version, batch by batch. Values
+/// that cannot be decrypted are left untouched and counted as failed; values changed
+/// concurrently are counted as skipped (they were rewritten with the current key anyway).
+/// Safe to re-run: already re-encrypted rows are never selected again.
+pub fn reencrypt_column<S: CipherStore>(
+    store: &mut S,
+    keyring: &MasterKeyring,
+    column: &CipherColumn,
+    batch_size: i64,
+    mut on_batch: impl FnMut(&ReencryptStats, Option<&anyhow::Error>),
+) -> Result<ReencryptStats> {
+    let mut stats = ReencryptStats::default();
+    let mut after = None;
+
+    loop {
+        let rows = store.pending(column, keyring.current_version(), after, batch_size)?;
+        let Some((last_id, _)) = rows.last() else {
+            break;
+        };
+        after = Some(*last_id);
+
+        let mut last_error = None;
+        for (id, old) in rows {
+            if column.is_marker(&old) {
+                continue;
+            }
+            let sealed = keyring
+                .reveal(&old, column.legacy)
+                .and_then(|plaintext| keyring.seal(&plaintext));
+            match sealed {
+                Ok(new) => {
+                    if store.replace(column, id, &old, &new)? {
+                        stats.processed += 1;
+                    } else {
+                        stats.skipped += 1;
+                    }
+                }
+                Err(e) => {
+                    stats.failed += 1;
+                    last_error = Some(anyhow!("{}.{} {}: {}", column.table, column.column, id, e));
+                }
+            }
+        }
+        on_batch(&stats, last_error.as_ref());
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        rows: BTreeMap<Uuid, String>,
+        fail_next_replace: bool,
+    }
+
+    impl CipherStore for MemoryStore {
+        fn pending(
+            &mut self,
+            _column: &CipherColumn,
+            target_version: u32,
+            after: Option<Uuid>,
+            limit: i64,
+        ) -> Result<Vec<(Uuid, String)>> {
+            Ok(self
+                .rows
+                .iter()
+                .filter(|(id, v)| {
+                    let after_cursor = match after {
+                        Some(a) => **id > a,
+                        None => true,
+                    };
+                    after_cursor && sealed_version(v) != Some(target_version)
+                })
+                .take(limit as usize)
+                .map(|(id, v)| (*id, v.clone()))
+                .collect())
+        }
+
+        fn replace(&mut self, _column: &CipherColumn, id: Uuid, old: &str, new: &str) -> Result<bool> {
+            if std::mem::take(&mut self.fail_next_replace) {
+                return Err(anyhow!("connection reset"));
+            }
+            match self.rows.get_mut(&id) {
+                Some(value) if value == old => {
+                    *value = new.to_string();
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
+        }
+    }
+
+    const EMAIL: CipherColumn = CIPHER_COLUMNS[0];
+
+    #[test]
+    fn test_seal_and_open_across_versions() {
+        let mut keyring = MasterKeyring::single(1, "first-key");
+        let old = keyring.seal("hunter2").unwrap();
+        assert_eq!(sealed_version(&old), Some(1));
+
+        keyring.add_version_with("second-key");
+        let new = keyring.seal("hunter2").unwrap();
+        assert_eq!(sealed_version(&new), Some(2));
+        assert_eq!(keyring.open(&old).unwrap().as_deref(), Some("hunter2"));
+        assert_eq!(keyring.open(&new).unwrap().as_deref(), Some("hunter2"));
+
+        keyring.retire_old_versions();
+        assert!(keyring.open(&old).is_err());
+    }
+
+    #[test]
+    fn test_secrets_round_trip_and_legacy_layout() {
+        let legacy = HashMap::from([("master_key".to_string(), "abc".to_string())]);
+        let keyring = MasterKeyring::from_secrets(&legacy).unwrap();
+        assert_eq!(keyring.current_version(), 1);
+
+        let mut rotated = keyring.clone();
+        rotated.add_version_with("def");
+        let restored = MasterKeyring::from_secrets(&rotated.to_secrets()).unwrap();
+        assert_eq!(restored.current_version(), 2);
+        assert_eq!(restored.versions(), vec![1, 2]);
+        assert_eq!(rotated.to_secrets().get("master_key").map(String::as_str), Some("def"));
+    }
+
+    #[test]
+    fn test_reencrypt_loop_moves_all_values_and_resumes() {
+        let mut keyring = MasterKeyring::single(1, "first-key");
+        let mut store = MemoryStore::default();
+        for i in 0..7 {
+            store.rows.insert(Uuid::new_v4(), keyring.seal(&format!("secret-{i}")).unwrap());
+        }
+        store.rows.insert(Uuid::new_v4(), BASE64.encode("legacy-secret"));
+        keyring.add_version_with("second-key");
+
+        // Interrupted run: the store fails part-way through.
+        store.fail_next_replace = true;
+        assert!(reencrypt_column(&mut store, &keyring, &EMAIL, 3, |_, _| {}).is_err());
+
+        let mut batches = 0;
+        let stats = reencrypt_column(&mut store, &keyring, &EMAIL, 3, |_, _| batches += 1).unwrap();
+        assert_eq!(stats.failed, 0);
+        assert_eq!(stats.processed, 8);
+        assert_eq!(batches, 3);
+
+        let mut plaintexts: Vec<String> = store
+            .rows
+            .values()
+            .map(|v| {
+                assert_eq!(sealed_version(v), Some(2));
+                keyring.open(v).unwrap().unwrap()
+            })
+            .collect();
+        plaintexts.sort();
+        assert!(plaintexts.contains(&"legacy-secret".to_string()));
+        assert!(plaintexts.contains(&"secret-6".to_string()));
+
+        // Nothing left to do on a second run.
+        let again = reencrypt_column(&mut store, &keyring, &EMAIL, 3, |_, _| {}).unwrap();
+        assert_eq!(again, ReencryptStats::default());
+    }
+
+    #[test]
+    fn test_reencrypt_counts_unreadable_values() {
+        let keyring = MasterKeyring::single(2, "second-key");
+        let mut store = MemoryStore::default();
+        let id = Uuid::new_v4();
+        store.rows.insert(id, "mk1:AAAA:BBBB".to_string());
+
+        let mut reported = None;
+        let stats = reencrypt_column(&mut store, &keyring, &EMAIL, 10, |_, e| {
+            reported = e.map(|e| e.to_string());
+        })
+        .unwrap();
+        assert_eq!(stats.failed, 1);
+        assert!(reported.unwrap().contains(&id.to_string()));
+        assert_eq!(store.rows[&id], "mk1:AAAA:BBBB");
+    }
+
+    #[test]
+    fn test_reencrypt_leaves_oauth_marker_alone() {
+        let mut keyring = MasterKeyring::single(1, "first-key");
+        let mut store = MemoryStore::default();
+        let oauth = Uuid::new_v4();
+        store.rows.insert(oauth, OAUTH_PASSWORD_MARKER.to_string());
+        store.rows.insert(Uuid::new_v4(), keyring.seal("secret").unwrap());
+        keyring.add_version_with("second-key");
+
+        let stats = reencrypt_column(&mut store, &keyring, &EMAIL, 10, |_, _| {}).unwrap();
+        assert_eq!(stats.failed, 0);
+        assert_eq!(stats.processed, 1);
+        assert_eq!(store.rows[&oauth], OAUTH_PASSWORD_MARKER);
+    }
+
+    #[test]
+    fn test_reveal_legacy_encodings() {
+        let keyring = MasterKeyring::single(1, "k");
+        assert_eq!(keyring.reveal("cGFzcw==", LegacyEncoding::Base64).unwrap(), "pass");
+        assert_eq!(keyring.reveal("ya29.token", LegacyEncoding::Plain).unwrap(), "ya29.token");
+    }
+}
@@ -1,18 +1,18 @@
 use anyhow::{anyhow, Result};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use chrono::{DateTime, Utc};
+use hmac::digest::KeyInit;
 use hmac::{Hmac, Mac};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-type HmacSha256 = Hmac<Sha256>;
-
 const TOTP_DIGITS: u32 = 6;
 const TOTP_PERIOD: u64 = 30;
 const TOTP_SECRET_LENGTH: usize = 20;
@@ -137,7 +137,7 @@ impl TotpEnrollment {
     pub fn to_uri(&self) -> String {
         let encoded_issuer = urlencoding::encode(&self.issuer);
         let encoded_account = urlencoding::encode(&self.account_name);
-        let encoded_secret = base32_encode(&self.secret);
+        let encoded_secret = self.secret_base32();
 
         format!(
             "otpauth://totp/{encoded_issuer}:{encoded_account}?secret={encoded_secret}&issuer={encoded_issuer}&algorithm={}&digits={}&period={}",
@@ -147,8 +147,9 @@ impl TotpEnrollment {
         )
     }
 
+    /// The secret as authenticator apps expect it: the raw key bytes in base32.
     pub fn secret_base32(&self) -> String {
-        base32_encode(&self.secret)
+        base32_encode(&hex::decode(&self.secret).unwrap_or_default())
     }
 }
 
@@ -409,7 +410,7 @@ impl MfaManager {
             .as_ref()
             .ok_or_else(|| anyhow!("No pending TOTP enrollment"))?;
 
-        let valid = verify_totp(&enrollment.secret, code, enrollment.period);
+        let valid = verify_totp(enrollment, code);
 
         if valid {
             if let Some(ref mut e) = state.totp_enrollment {
@@ -457,7 +458,7 @@ impl MfaManager {
             return Err(anyhow!("TOTP enrollment not verified"));
         }
 
-        let valid = verify_totp(&enrollment.secret, code, enrollment.period);
+        let valid = verify_totp(enrollment, code);
         state.record_attempt(valid, self.config.max_verification_attempts, self.config.lockout_duration_minutes);
 
         Ok(valid)
@@ -827,9 +828,8 @@ fn constant_time_compare(a: &str, b: &str) -> bool {
     result == 0
 }
 
-fn base32_encode(data: &str) -> String {
+fn base32_encode(bytes: &[u8]) -> String {
     const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
-    let bytes = data.as_bytes();
     let mut result = String::new();
 
     let mut buffer: u64 = 0;
@@ -854,34 +854,67 @@ fn base32_encode(data: &str) -> String {
     result
 }
 
-fn verify_totp(secret: &str, code: &str, period: u64) -> bool {
+fn verify_totp(enrollment: &TotpEnrollment, code: &str) -> bool {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs();
 
-    let counter = now / period;
+    match_totp_step(
+        &enrollment.secret,
+        code,
+        now,
+        enrollment.period,
+        enrollment.algorithm,
+        enrollment.digits,
+    )
+    .is_some()
+}
 
-    for offset in [-1i64, 0, 1] {
-        let check_counter = (counter as i64 + offset) as u64;
-        if let Ok(expected) = generate_totp_code(secret, check_counter) {
-            if constant_time_compare(&expected, code) {
-                return true;
-            }
-        }
-    }
+/// Time step whose code is `code`, looking one step either side of `unix_time` to allow
+/// for clock drift. Callers that reject steps at or below the last accepted one prevent
+/// a code from being used twice.
+pub fn match_totp_step(
+    secret: &str,
+    code: &str,
+    unix_time: u64,
+    period: u64,
+    algorithm: TotpAlgorithm,
+    digits: u32,
+) -> Option<u64> {
+    let code = code.trim();
+    let counter = unix_time / period.max(1);
+
+    [counter.checked_sub(1), Some(counter), Some(counter + 1)]
+        .into_iter()
+        .flatten()
+        .find(|step| {
+            totp_code(secret, *step, algorithm, digits)
+                .is_ok_and(|expected| constant_time_compare(&expected, code))
+        })
+}
 
-    false
+fn hmac_bytes<M: Mac + KeyInit>(key: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+    let mut mac = <M as Mac>::new_from_slice(key).map_err(|e| anyhow!("HMAC error: {e}"))?;
+    mac.update(message);
+    Ok(mac.finalize().into_bytes().to_vec())
 }
 
-fn generate_totp_code(secret: &str, counter: u64) -> Result<String> {
+/// RFC 6238 code of the hex-encoded `secret` for time step `counter`.
+pub fn totp_code(
+    secret: &str,
+    counter: u64,
+    algorithm: TotpAlgorithm,
+    digits: u32,
+) -> Result<String> {
     let secret_bytes = hex::decode(secret).map_err(|e| anyhow!("Invalid secret: {e}"))?;
+    let message = counter.to_be_bytes();
 
-    let mut mac = HmacSha256::new_from_slice(&secret_bytes)
-        .map_err(|e| anyhow!("HMAC error: {e}"))?;
-
-    mac.update(&counter.to_be_bytes());
-    let result = mac.finalize().into_bytes();
+    let result = match algorithm {
+        TotpAlgorithm::Sha1 => hmac_bytes::<Hmac<Sha1>>(&secret_bytes, &message)?,
+        TotpAlgorithm::Sha256 => hmac_bytes::<Hmac<Sha256>>(&secret_bytes, &message)?,
+        TotpAlgorithm::Sha512 => hmac_bytes::<Hmac<Sha512>>(&secret_bytes, &message)?,
+    };
 
     let offset = (result[result.len() - 1] & 0x0F) as usize;
     if offset + 4 > result.len() {
@@ -894,8 +927,8 @@ fn generate_totp_code(secret: &str, counter: u64) -> Result<String> {
         result[offset + 3],
     ]);
 
-    let otp = code % 10u32.pow(TOTP_DIGITS);
-    Ok(format!("{:0width$}", otp, width = TOTP_DIGITS as usize))
+    let otp = code % 10u32.pow(digits);
+    Ok(format!("{:0width$}", otp, width = digits as usize))
 }
 
 fn mask_destination(destination: &str, method: MfaMethod) -> String {
@@ -1067,9 +1100,60 @@ mod tests {
         assert!(!constant_time_compare("abc", "abcd"));
     }
 
+    #[test]
+    fn test_totp_rfc6238_vectors() {
+        let sha1_secret = hex::encode(b"12345678901234567890");
+        let sha256_secret = hex::encode(b"12345678901234567890123456789012");
+        let vectors = [
+            (&sha1_secret, 59, TotpAlgorithm::Sha1, "94287082"),
+            (&sha1_secret, 1111111109, TotpAlgorithm::Sha1, "07081804"),
+            (&sha1_secret, 1234567890, TotpAlgorithm::Sha1, "89005924"),
+            (&sha256_secret, 59, TotpAlgorithm::Sha256, "46119246"),
+        ];
+
+        for (secret, time, algorithm, expected) in vectors {
+            assert_eq!(
+                totp_code(secret, time / 30, algorithm, 8).unwrap(),
+                expected
+            );
+        }
+        assert_eq!(
+            totp_code(&sha1_secret, 1, TotpAlgorithm::Sha1, 6).unwrap(),
+            "287082"
+        );
+    }
+
+    #[test]
+    fn test_totp_step_window() {
+        let secret = hex::encode(b"12345678901234567890");
+        let time = 1_111_111_109;
+        let step = time / 30;
+        let code = |step| totp_code(&secret, step, TotpAlgorithm::Sha1, 6).unwrap();
+        let check = |code: &str| match_totp_step(&secret, code, time, 30, TotpAlgorithm::Sha1, 6);
+
+        assert_eq!(check(&code(step)), Some(step));
+        assert_eq!(check(&code(step - 1)), Some(step - 1));
+        assert_eq!(check(&code(step + 1)), Some(step + 1));
+        assert_eq!(check(&code(step - 2)), None);
+        assert_eq!(check(&code(step + 2)), None);
+        assert_eq!(check("000000x"), None);
+    }
+
+    #[test]
+    fn test_totp_uri_carries_raw_key() {
+        let mut enrollment =
+            TotpEnrollment::new(Uuid::new_v4(), "test@example.com", &MfaConfig::default());
+        enrollment.secret = hex::encode(b"12345678901234567890");
+
+        let base32 = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+        assert_eq!(enrollment.secret_base32(), base32);
+        assert!(enrollment.to_uri().contains(&format!("secret={base32}&")));
+        assert!(enrollment.to_uri().contains("algorithm=SHA1"));
+    }
+
     #[test]
     fn test_base32_encode() {
-        let encoded = base32_encode("test");
+        let encoded = base32_encode(b"test");
         assert!(!encoded.is_empty());
         assert!(encoded.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()));
     }
@@ -6,6 +6,8 @@ pub mod auth_api;
 pub mod auth_provider;
 pub mod ca;
 pub mod cert_pinning;
+pub mod channel_webhook;
+pub mod client_ip;
 pub mod command_guard;
 pub mod cors;
 pub mod csrf;
@@ -17,6 +19,7 @@ pub mod headers;
 pub mod integration;
 pub mod jwt;
 pub mod log_sanitizer;
+pub mod master_key;
 pub mod mfa;
 pub mod mutual_tls;
 pub mod panic_handler;
@@ -41,6 +44,7 @@ pub mod security_monitoring;
 pub mod session;
 pub mod sql_guard;
 pub mod tls;
+pub mod upload_policy;
 pub mod validation;
 pub mod webhook;
 pub mod zitadel_auth;
@@ -154,7 +158,7 @@ pub use rate_limiter::{
     simple_rate_limit_middleware, CombinedRateLimiter, HttpRateLimitConfig,
 };
 pub use request_id::{
-    generate_prefixed_request_id, generate_request_id, get_current_sequence,
+    current_request_id, generate_prefixed_request_id, generate_request_id, get_current_sequence,
     get_request_id, get_request_id_string, request_id_middleware,
     request_id_middleware_with_config, RequestId, RequestIdConfig,
     CORRELATION_ID_HEADER, REQUEST_ID_HEADER,
@@ -170,6 +174,9 @@ pub use sql_guard::{
     validate_order_direction, validate_table_name, SqlGuardError,
 };
 pub use tls::{create_https_server, ServiceTlsConfig, TlsConfig, TlsManager, TlsRegistry};
+pub use upload_policy::{
+    check_upload, upload_policy, ScanVerdict, UploadPolicy, UploadRejection, UploadScanner,
+};
 pub use validation::{
     sanitize_html, strip_html_tags, validate_alphanumeric, validate_email, validate_length,
     validate_no_html, validate_no_script_injection, validate_one_of, validate_password_strength,
@@ -1,4 +1,5 @@
 // Passkey HTTP handlers extracted from passkey.rs
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::security::passkey_types::*;
 use crate::security::passkey_service::PasskeyService;
@@ -14,7 +15,7 @@ use uuid::Uuid;
 /// Start WebAuthn registration for passkey
 pub async fn start_registration(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<StartRegistrationRequest>,
+    ApiJson(request): ApiJson<StartRegistrationRequest>,
 ) -> Result<Json<RegistrationOptions>, PasskeyError> {
     let user_id = request.user_id;
     let service = PasskeyService::new(Arc::clone(&state.conn));
@@ -27,7 +28,7 @@ pub async fn start_registration(
 /// Verify passkey registration authentication
 pub async fn verify_registration(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<VerifyAuthRequest>,
+    ApiJson(request): ApiJson<VerifyAuthRequest>,
 ) -> Result<Json<AuthenticationResponse>, PasskeyError> {
     let user_id = request.user_id;
     let service = PasskeyService::new(Arc::clone(&state.conn));
@@ -56,7 +57,7 @@ pub async fn get_credentials(
 /// Sign in with passkey
 pub async fn sign_in(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<SignInRequest>,
+    ApiJson(request): ApiJson<SignInRequest>,
 ) -> Result<Json<AuthenticationResponse>, PasskeyError> {
     let service = PasskeyService::new(Arc::clone(&state.conn));
     let response = service.sign_in(&request).await?;
@@ -77,7 +78,7 @@ pub async fn get_fallback_config(
 /// Update fallback configuration
 pub async fn set_fallback_config(
     State(state): State<Arc<AppState>>,
-    Json(config): Json<FallbackConfig>,
+    ApiJson(config): ApiJson<FallbackConfig>,
 ) -> Result<Json<serde_json::Value>, PasskeyError> {
     let service = PasskeyService::new(Arc::clone(&state.conn));
     service.set_fallback_config(&config).await?;
@@ -88,7 +89,7 @@ pub async fn set_fallback_config(
 /// Clear fallback attempts
 pub async fn clear_fallback(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ClearFallbackRequest>,
+    ApiJson(request): ApiJson<ClearFallbackRequest>,
 ) -> Result<Json<serde_json::Value>, PasskeyError> {
     let service = PasskeyService::new(Arc::clone(&state.conn));
     service.clear_fallback_attempts(&request.username).await?;
@@ -99,7 +100,7 @@ pub async fn clear_fallback(
 /// Get passkey challenges
 pub async fn get_challenges(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<GetChallengesRequest>,
+    ApiJson(request): ApiJson<GetChallengesRequest>,
 ) -> Result<Json<Vec<ChallengeResponse>>, PasskeyError> {
     let service = PasskeyService::new(Arc::clone(&state.conn));
     let challenges = service.get_challenges(&request).await?;
@@ -111,7 +112,7 @@ pub async fn get_challenges(
 pub async fn answer_challenge(
     State(state): State<Arc<AppState>>,
     Path((user_id, challenge_id)): Path<(Uuid, String)>,
-    Json(request): Json<AnswerChallengeRequest>,
+    ApiJson(request): ApiJson<AnswerChallengeRequest>,
 ) -> Result<Json<ChallengeResponse>, PasskeyError> {
     let service = PasskeyService::new(Arc::clone(&state.conn));
     let response = service.answer_challenge(&user_id, &challenge_id, &request).await?;
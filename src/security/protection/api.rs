@@ -13,6 +13,7 @@ use tracing::warn;
 
 use super::manager::{ProtectionConfig, ProtectionManager, ProtectionTool, ScanResult, ToolStatus};
 use super::security_fix::{run_security_fix, run_security_status, SecurityFixReport};
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 static PROTECTION_MANAGER: OnceLock<Arc<RwLock<ProtectionManager>>> = OnceLock::new();
@@ -323,7 +324,7 @@ async fn update_definitions(
 
 async fn toggle_auto(
     Path(tool_name): Path<String>,
-    Json(request): Json<AutoToggleRequest>,
+    ApiJson(request): ApiJson<AutoToggleRequest>,
 ) -> Result<Json<ApiResponse<ActionResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
     let tool = parse_tool(&tool_name)?;
     let manager = get_manager().write().await;
@@ -954,11 +954,14 @@ pub fn build_default_route_permissions() -> Vec<RoutePermission> {
         // WhatsApp webhook - anonymous for Meta verification and message delivery
         RoutePermission::new("/webhook/whatsapp/:bot_id", "GET", "").with_anonymous(true),
         RoutePermission::new("/webhook/whatsapp/:bot_id", "POST", "").with_anonymous(true),
+        RoutePermission::new("/webhook/sheet/:sheet_id", "POST", "").with_anonymous(true),
 
         // Auth routes - login must be anonymous
         RoutePermission::new("/api/auth", "GET", "").with_anonymous(true),
 
         RoutePermission::new("/api/auth/login", "POST", "").with_anonymous(true),
+        // Second step of a TOTP login, before any session exists
+        RoutePermission::new("/api/auth/2fa/verify", "POST", "").with_anonymous(true),
 
         // Client error reporting - anonymous to catch all JS errors
         RoutePermission::new("/api/client-errors", "POST", "").with_anonymous(true),
@@ -968,6 +971,7 @@ pub fn build_default_route_permissions() -> Vec<RoutePermission> {
         RoutePermission::new("/api/auth/me", "GET", "").with_anonymous(true),
         RoutePermission::new("/api/auth/**", "GET", ""),
         RoutePermission::new("/api/auth/**", "POST", ""),
+        RoutePermission::new("/api/auth/**", "DELETE", ""),
 
         // WebSocket - anonymous for chat support
         RoutePermission::new("/ws", "GET", "").with_anonymous(true),
@@ -981,6 +985,8 @@ pub fn build_default_route_permissions() -> Vec<RoutePermission> {
         RoutePermission::new("/api/sessions", "POST", "").with_anonymous(true),
         RoutePermission::new("/api/sessions", "GET", ""),
         RoutePermission::new("/api/sessions/**", "GET", ""),
+        RoutePermission::new("/api/sessions/:id/fork", "POST", ""),
+        RoutePermission::new("/api/sessions/:id/cancel", "POST", ""),
 
         // =====================================================================
         // AUTHENTICATED USER ROUTES (any logged-in user)
@@ -10,6 +10,10 @@ use uuid::Uuid;
 
 static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(0);
 
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: String;
+}
+
 pub const REQUEST_ID_HEADER: &str = "x-request-id";
 pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
 
@@ -167,7 +171,9 @@ pub async fn request_id_middleware_with_config(
         Span::none()
     };
 
-    let response = next.run(request).instrument(span).await;
+    let response = CURRENT_REQUEST_ID
+        .scope(request_id.id.clone(), next.run(request).instrument(span))
+        .await;
 
     if config.propagate_to_response {
         add_request_id_to_response(response, &request_id, &header_name)
@@ -245,6 +251,12 @@ pub fn get_request_id_string<B>(request: &Request<B>) -> String {
         .unwrap_or_else(|| "unknown".to_string())
 }
 
+/// Request id of the request currently being handled, for code that has no access
+/// to the `Request` (e.g. `IntoResponse` impls). `None` outside the middleware.
+pub fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
 pub fn generate_request_id() -> String {
     Uuid::new_v4().to_string()
 }
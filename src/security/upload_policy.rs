@@ -0,0 +1,514 @@
+//! Checks applied to uploaded files before they are stored or processed: an allow-list
+//! of extensions and MIME types, then an optional malware scan.
+//!
+//! Configured from the environment:
+//! - `UPLOAD_ALLOWED_EXTENSIONS`: comma-separated extensions, `*` for any (default: common
+//!   document, spreadsheet, presentation, image, audio and video formats)
+//! - `UPLOAD_ALLOWED_MIME_TYPES`: comma-separated types such as `application/pdf` or
+//!   `image/*`; empty (the default) accepts any declared type
+//! - `UPLOAD_SCAN`: `off` (default), `clamav` or `http`
+//! - `UPLOAD_SCAN_CLAMD_ADDR`: clamd TCP address (default `127.0.0.1:3310`)
+//! - `UPLOAD_SCAN_URL`: endpoint of an HTTP scanner, which receives the file as the request
+//!   body and answers `{"infected": bool, "signature": "..."}`
+//! - `UPLOAD_SCAN_TIMEOUT_SECS` (default 30)
+//!
+//! When scanning is on and the scanner cannot be reached, uploads are refused.
+
+use crate::core::shared::api_error::ApiError;
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use log::{info, warn};
+use serde::Deserialize;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const DEFAULT_EXTENSIONS: &str = "pdf,txt,md,csv,tsv,json,xml,html,htm,rtf,\
+    doc,docx,odt,xls,xlsx,xlsm,ods,ppt,pptx,odp,markdown,\
+    png,jpg,jpeg,gif,webp,bmp,tiff,mp3,wav,ogg,m4a,flac,mp4,webm,mov,eml,zip";
+const DEFAULT_CLAMD_ADDR: &str = "127.0.0.1:3310";
+const DEFAULT_SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Bytes sent to clamd per INSTREAM chunk.
+const CLAMD_CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadRejection {
+    ExtensionNotAllowed(String),
+    TypeNotAllowed(String),
+    /// Carries the scanner's signature name.
+    Infected(String),
+    ScanFailed(String),
+}
+
+impl std::fmt::Display for UploadRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExtensionNotAllowed(ext) if ext.is_empty() => {
+                write!(f, "Files without an extension are not allowed")
+            }
+            Self::ExtensionNotAllowed(ext) => write!(f, "Files of type .{ext} are not allowed"),
+            Self::TypeNotAllowed(mime) => write!(f, "Content type {mime} is not allowed"),
+            Self::Infected(signature) => {
+                write!(
+                    f,
+                    "The file was rejected by the virus scanner ({signature})"
+                )
+            }
+            Self::ScanFailed(_) => write!(f, "The file could not be scanned, try again later"),
+        }
+    }
+}
+
+impl std::error::Error for UploadRejection {}
+
+impl UploadRejection {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::ExtensionNotAllowed(_) | Self::TypeNotAllowed(_) => "UPLOAD_TYPE_NOT_ALLOWED",
+            Self::Infected(_) => "UPLOAD_INFECTED",
+            Self::ScanFailed(_) => "UPLOAD_SCAN_UNAVAILABLE",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::ExtensionNotAllowed(_) | Self::TypeNotAllowed(_) => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            Self::Infected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::ScanFailed(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+impl From<UploadRejection> for ApiError {
+    fn from(rejection: UploadRejection) -> Self {
+        ApiError::new(rejection.status(), rejection.code(), rejection.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanVerdict {
+    Clean,
+    Infected(String),
+}
+
+/// A malware scanner. Implement this to plug in a scanner other than clamd or HTTP.
+#[async_trait]
+pub trait UploadScanner: Send + Sync {
+    fn name(&self) -> &str;
+    async fn scan(&self, data: &[u8]) -> Result<ScanVerdict, String>;
+}
+
+/// Streams the file to clamd with its `INSTREAM` command.
+pub struct ClamdScanner {
+    address: String,
+    timeout: Duration,
+}
+
+impl ClamdScanner {
+    pub fn new(address: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            address: address.into(),
+            timeout,
+        }
+    }
+
+    async fn instream(&self, data: &[u8]) -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(&self.address).await?;
+        stream.write_all(b"zINSTREAM\0").await?;
+        for chunk in data.chunks(CLAMD_CHUNK) {
+            stream
+                .write_all(&(chunk.len() as u32).to_be_bytes())
+                .await?;
+            stream.write_all(chunk).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await?;
+        Ok(String::from_utf8_lossy(&reply)
+            .trim_end_matches(['\0', '\n'])
+            .to_string())
+    }
+}
+
+#[async_trait]
+impl UploadScanner for ClamdScanner {
+    fn name(&self) -> &str {
+        "clamav"
+    }
+
+    async fn scan(&self, data: &[u8]) -> Result<ScanVerdict, String> {
+        let reply = tokio::time::timeout(self.timeout, self.instream(data))
+            .await
+            .map_err(|_| format!("clamd at {} timed out", self.address))?
+            .map_err(|e| format!("clamd at {}: {e}", self.address))?;
+        // Replies look like `stream: OK` or `stream: Eicar-Signature FOUND`.
+        let status = reply.strip_prefix("stream:").unwrap_or(&reply).trim();
+        if status == "OK" {
+            Ok(ScanVerdict::Clean)
+        } else if let Some(signature) = status.strip_suffix("FOUND") {
+            Ok(ScanVerdict::Infected(signature.trim().to_string()))
+        } else {
+            Err(format!("clamd answered {reply:?}"))
+        }
+    }
+}
+
+/// Posts the file to a scanning service.
+pub struct HttpScanner {
+    url: String,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct HttpScanReply {
+    infected: bool,
+    #[serde(default)]
+    signature: Option<String>,
+}
+
+impl HttpScanner {
+    pub fn new(url: impl Into<String>, timeout: Duration) -> Self {
+        let client = crate::core::shared::outbound_proxy::client_builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        Self {
+            url: url.into(),
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl UploadScanner for HttpScanner {
+    fn name(&self) -> &str {
+        "http"
+    }
+
+    async fn scan(&self, data: &[u8]) -> Result<ScanVerdict, String> {
+        let response = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .body(data.to_vec())
+            .send()
+            .await
+            .map_err(|e| format!("scanner request failed: {e}"))?;
+        if !response.status().is_success() {
+            return Err(format!("scanner answered {}", response.status()));
+        }
+        let reply: HttpScanReply = response
+            .json()
+            .await
+            .map_err(|e| format!("invalid scanner reply: {e}"))?;
+        Ok(if reply.infected {
+            ScanVerdict::Infected(reply.signature.unwrap_or_else(|| "unknown".to_string()))
+        } else {
+            ScanVerdict::Clean
+        })
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Lower-cased extension of `filename`, empty when it has none.
+fn extension(filename: &str) -> String {
+    let name = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => ext.to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
+pub struct UploadPolicy {
+    /// `None` allows any extension.
+    allowed_extensions: Option<Vec<String>>,
+    allowed_mime_types: Vec<String>,
+    scanner: Option<Arc<dyn UploadScanner>>,
+}
+
+impl UploadPolicy {
+    pub fn new(
+        allowed_extensions: Option<Vec<String>>,
+        allowed_mime_types: Vec<String>,
+        scanner: Option<Arc<dyn UploadScanner>>,
+    ) -> Self {
+        Self {
+            allowed_extensions,
+            allowed_mime_types,
+            scanner,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let get = |key: &str| {
+            lookup(key)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let extensions = split_list(
+            &get("UPLOAD_ALLOWED_EXTENSIONS").unwrap_or_else(|| DEFAULT_EXTENSIONS.to_string()),
+        );
+        let allowed_extensions = (!extensions.iter().any(|e| e == "*")).then_some(extensions);
+        let allowed_mime_types = split_list(&get("UPLOAD_ALLOWED_MIME_TYPES").unwrap_or_default());
+
+        let timeout = get("UPLOAD_SCAN_TIMEOUT_SECS")
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0)
+            .map_or(DEFAULT_SCAN_TIMEOUT, Duration::from_secs);
+        let scanner: Option<Arc<dyn UploadScanner>> = match get("UPLOAD_SCAN").as_deref() {
+            None | Some("off") => None,
+            Some("clamav") => Some(Arc::new(ClamdScanner::new(
+                get("UPLOAD_SCAN_CLAMD_ADDR").unwrap_or_else(|| DEFAULT_CLAMD_ADDR.to_string()),
+                timeout,
+            ))),
+            Some("http") => match get("UPLOAD_SCAN_URL") {
+                Some(url) => Some(Arc::new(HttpScanner::new(url, timeout))),
+                None => {
+                    warn!("UPLOAD_SCAN=http without UPLOAD_SCAN_URL; uploads are not scanned");
+                    None
+                }
+            },
+            Some(other) => {
+                warn!("Unknown UPLOAD_SCAN {other:?}; uploads are not scanned");
+                None
+            }
+        };
+        Self::new(allowed_extensions, allowed_mime_types, scanner)
+    }
+
+    /// The allow-list check alone, cheap enough to run before reading the whole body.
+    pub fn allows(&self, filename: &str, content_type: &str) -> Result<(), UploadRejection> {
+        let ext = extension(filename);
+        if let Some(allowed) = &self.allowed_extensions {
+            if !allowed.contains(&ext) {
+                return Err(UploadRejection::ExtensionNotAllowed(ext));
+            }
+        }
+        if !self.allowed_mime_types.is_empty() {
+            let mime = content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            let allowed =
+                self.allowed_mime_types
+                    .iter()
+                    .any(|pattern| match pattern.strip_suffix("/*") {
+                        Some(family) => mime.split('/').next() == Some(family),
+                        None => *pattern == mime,
+                    });
+            if !allowed {
+                return Err(UploadRejection::TypeNotAllowed(mime));
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the allow-list, then the scanner if one is configured. `source` names the
+    /// upload path in the rejection log, e.g. `sheet-import`.
+    pub async fn check(
+        &self,
+        source: &str,
+        filename: &str,
+        content_type: &str,
+        data: &[u8],
+    ) -> Result<(), UploadRejection> {
+        let result = match self.allows(filename, content_type) {
+            Ok(()) => self.scan(data).await,
+            Err(rejection) => Err(rejection),
+        };
+        if let Err(rejection) = &result {
+            let detail = match rejection {
+                UploadRejection::ScanFailed(e) => format!("{rejection}: {e}"),
+                _ => rejection.to_string(),
+            };
+            warn!(
+                "Rejected upload {:?} ({} bytes) on {}: {}",
+                filename,
+                data.len(),
+                source,
+                detail
+            );
+        }
+        result
+    }
+
+    async fn scan(&self, data: &[u8]) -> Result<(), UploadRejection> {
+        let Some(scanner) = &self.scanner else {
+            return Ok(());
+        };
+        match scanner.scan(data).await {
+            Ok(ScanVerdict::Clean) => Ok(()),
+            Ok(ScanVerdict::Infected(signature)) => Err(UploadRejection::Infected(signature)),
+            Err(e) => Err(UploadRejection::ScanFailed(format!(
+                "{}: {e}",
+                scanner.name()
+            ))),
+        }
+    }
+}
+
+static UPLOAD_POLICY: OnceLock<UploadPolicy> = OnceLock::new();
+
+/// The process-wide policy, read from the environment on first use.
+pub fn upload_policy() -> &'static UploadPolicy {
+    UPLOAD_POLICY.get_or_init(|| {
+        let policy = UploadPolicy::from_env();
+        if let Some(scanner) = &policy.scanner {
+            info!("Uploads are scanned with {}", scanner.name());
+        }
+        policy
+    })
+}
+
+/// [`UploadPolicy::check`] with the process-wide policy.
+pub async fn check_upload(
+    source: &str,
+    filename: &str,
+    content_type: &str,
+    data: &[u8],
+) -> Result<(), UploadRejection> {
+    upload_policy()
+        .check(source, filename, content_type, data)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+
+    #[derive(Default)]
+    struct CountingScanner(AtomicUsize);
+
+    #[async_trait]
+    impl UploadScanner for CountingScanner {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn scan(&self, _data: &[u8]) -> Result<ScanVerdict, String> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(ScanVerdict::Clean)
+        }
+    }
+
+    fn policy(entries: &[(&str, &str)]) -> UploadPolicy {
+        let env: HashMap<String, String> = entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        UploadPolicy::from_lookup(|key| env.get(key).cloned())
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_extension_is_rejected_before_scan_and_storage() {
+        let scanner = Arc::new(CountingScanner::default());
+        let policy = UploadPolicy::new(
+            Some(vec!["xlsx".to_string(), "csv".to_string()]),
+            Vec::new(),
+            Some(scanner.clone()),
+        );
+        let mut stored = Vec::new();
+
+        for (name, data) in [
+            ("payroll.exe", &b"MZ\x90\x00"[..]),
+            ("q1.xlsx", &b"PK\x03\x04"[..]),
+        ] {
+            if policy
+                .check("test", name, "application/octet-stream", data)
+                .await
+                .is_ok()
+            {
+                stored.push(name);
+            }
+        }
+
+        assert_eq!(stored, vec!["q1.xlsx"]);
+        assert_eq!(scanner.0.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            policy.allows("payroll.exe", "").unwrap_err(),
+            UploadRejection::ExtensionNotAllowed("exe".to_string())
+        );
+        assert!(policy.allows("Makefile", "").is_err());
+        assert!(policy.allows("archive.tar.CSV", "").is_ok());
+    }
+
+    #[test]
+    fn test_allow_lists_from_environment() {
+        let default = policy(&[]);
+        assert!(default.allows("report.pdf", "application/pdf").is_ok());
+        assert!(default.allows("budget.xlsm", "").is_ok());
+        assert!(default.allows("setup.msi", "application/x-msi").is_err());
+        assert!(default.scanner.is_none());
+
+        let any = policy(&[("UPLOAD_ALLOWED_EXTENSIONS", "*")]);
+        assert!(any.allows("setup.msi", "").is_ok());
+
+        let images = policy(&[
+            ("UPLOAD_ALLOWED_EXTENSIONS", ".png, .PDF"),
+            ("UPLOAD_ALLOWED_MIME_TYPES", "image/*,application/pdf"),
+        ]);
+        assert!(images.allows("a.png", "image/png").is_ok());
+        assert!(images
+            .allows("a.pdf", "application/pdf; charset=binary")
+            .is_ok());
+        assert_eq!(
+            images.allows("a.png", "text/html").unwrap_err(),
+            UploadRejection::TypeNotAllowed("text/html".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_clamd_signature_rejects_the_upload() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1024];
+            // Read until the zero-length chunk that ends the stream.
+            while !received.ends_with(&[0, 0, 0, 0]) {
+                let n = socket.read(&mut buf).await.unwrap();
+                received.extend_from_slice(&buf[..n]);
+            }
+            assert!(received.starts_with(b"zINSTREAM\0"));
+            socket
+                .write_all(b"stream: Eicar-Test-Signature FOUND\0")
+                .await
+                .unwrap();
+        });
+
+        let scanner = Arc::new(ClamdScanner::new(address, Duration::from_secs(5)));
+        let policy = UploadPolicy::new(None, Vec::new(), Some(scanner));
+        let rejection = policy
+            .check("test", "eicar.txt", "text/plain", b"X5O!P%@AP")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            rejection,
+            UploadRejection::Infected("Eicar-Test-Signature".to_string())
+        );
+        assert_eq!(
+            ApiError::from(rejection).status,
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+    }
+}
@@ -8,6 +8,7 @@ pub mod rbac_kb;
 pub mod rbac_ui;
 pub mod security_admin;
 
+use crate::core::shared::api_json::ApiJson;
 use axum::{
 extract::State,
 response::{Html, Json},
@@ -72,7 +73,7 @@ Html(r##"<div class="accounts-list">
 
 async fn save_smtp_account(
 State(_state): State<Arc<AppState>>,
-Json(config): Json<serde_json::Value>,
+ApiJson(config): ApiJson<serde_json::Value>,
 ) -> Json<serde_json::Value> {
 Json(serde_json::json!({
 "success": true,
@@ -164,7 +165,7 @@ error: Option<String>,
 
 async fn save_search_settings(
 State(_state): State<Arc<AppState>>,
-Json(settings): Json<SearchSettingsRequest>,
+ApiJson(settings): ApiJson<SearchSettingsRequest>,
 ) -> Json<SearchSettingsResponse> {
 // In a real implementation, save to database
 log::info!("Saving search settings: fuzzy={:?}, limit={:?}, ai={:?}",
@@ -212,7 +213,7 @@ _use_tls: Option<bool>,
 #[cfg(feature = "mail")]
 async fn test_smtp_connection(
 State(_state): State<Arc<AppState>>,
-Json(config): Json<SmtpTestRequest>,
+ApiJson(config): ApiJson<SmtpTestRequest>,
 ) -> Json<SmtpTestResponse> {
 #[cfg(feature = "mail")]
 use lettre::SmtpTransport;
@@ -270,7 +271,7 @@ match mailer_result {
 #[cfg(not(feature = "mail"))]
 async fn test_smtp_connection(
 State(_state): State<Arc<AppState>>,
-Json(_config): Json<SmtpTestRequest>,
+ApiJson(_config): ApiJson<SmtpTestRequest>,
 ) -> Json<SmtpTestResponse> {
 Json(SmtpTestResponse {
 success: false,
@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::security::error_sanitizer::log_and_sanitize_str;
 use crate::core::shared::models::{
     NewRbacGroup, NewRbacGroupRole, NewRbacRole, NewRbacUserGroup, NewRbacUserRole, RbacGroup,
@@ -123,7 +124,7 @@ async fn get_role(State(state): State<Arc<AppState>>, Path(role_id): Path<Uuid>)
     }
 }
 
-async fn create_role(State(state): State<Arc<AppState>>, Json(req): Json<CreateRoleRequest>) -> impl IntoResponse {
+async fn create_role(State(state): State<Arc<AppState>>, ApiJson(req): ApiJson<CreateRoleRequest>) -> impl IntoResponse {
     let conn = state.conn.clone();
     let now = Utc::now();
     let new_role = NewRbacRole {
@@ -263,7 +264,7 @@ async fn get_group(State(state): State<Arc<AppState>>, Path(group_id): Path<Uuid
     }
 }
 
-async fn create_group(State(state): State<Arc<AppState>>, Json(req): Json<CreateGroupRequest>) -> impl IntoResponse {
+async fn create_group(State(state): State<Arc<AppState>>, ApiJson(req): ApiJson<CreateGroupRequest>) -> impl IntoResponse {
     let conn = state.conn.clone();
     let now = Utc::now();
     let new_group = NewRbacGroup {
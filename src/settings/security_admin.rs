@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -193,7 +194,7 @@ async fn get_tls_settings(
 
 async fn update_tls_settings(
     State(_state): State<Arc<AppState>>,
-    Json(_settings): Json<TlsSettings>,
+    ApiJson(_settings): ApiJson<TlsSettings>,
 ) -> Result<Json<TlsSettings>, SecurityError> {
     let settings = TlsSettings {
         enabled: true,
@@ -222,7 +223,7 @@ async fn get_rate_limit_settings(
 
 async fn update_rate_limit_settings(
     State(_state): State<Arc<AppState>>,
-    Json(settings): Json<RateLimitSettings>,
+    ApiJson(settings): ApiJson<RateLimitSettings>,
 ) -> Result<Json<RateLimitSettings>, SecurityError> {
     Ok(Json(settings))
 }
@@ -247,7 +248,7 @@ async fn get_cors_settings(
 
 async fn update_cors_settings(
     State(_state): State<Arc<AppState>>,
-    Json(settings): Json<CorsSettings>,
+    ApiJson(settings): ApiJson<CorsSettings>,
 ) -> Result<Json<CorsSettings>, SecurityError> {
     Ok(Json(settings))
 }
@@ -281,7 +282,7 @@ async fn list_api_keys(
 
 async fn create_api_key(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<CreateApiKeyRequest>,
+    ApiJson(req): ApiJson<CreateApiKeyRequest>,
 ) -> Result<Json<CreateApiKeyResponse>, SecurityError> {
     let response = CreateApiKeyResponse {
         id: Uuid::new_v4(),
@@ -312,7 +313,7 @@ async fn get_mfa_settings(
 
 async fn update_mfa_settings(
     State(_state): State<Arc<AppState>>,
-    Json(settings): Json<MfaSettings>,
+    ApiJson(settings): ApiJson<MfaSettings>,
 ) -> Result<Json<MfaSettings>, SecurityError> {
     Ok(Json(settings))
 }
@@ -355,7 +356,7 @@ async fn get_password_policy(
 
 async fn update_password_policy(
     State(_state): State<Arc<AppState>>,
-    Json(policy): Json<PasswordPolicy>,
+    ApiJson(policy): ApiJson<PasswordPolicy>,
 ) -> Result<Json<PasswordPolicy>, SecurityError> {
     Ok(Json(policy))
 }
@@ -0,0 +1,336 @@
+use crate::sheet::error::SheetError;
+use crate::sheet::types::{CellData, FormatRequest, Worksheet};
+
+/// Excel rejects longer format codes.
+const MAX_FORMAT_LEN: usize = 255;
+/// Positive; negative; zero; text.
+const MAX_SECTIONS: usize = 4;
+/// One call may format a whole column of a typical sheet, but not allocate millions of
+/// empty cells.
+pub const MAX_FORMAT_CELLS: u64 = 100_000;
+
+const COLORS: [&str; 8] = [
+    "black", "blue", "cyan", "green", "magenta", "red", "white", "yellow",
+];
+
+/// Characters Excel shows as-is without quoting.
+const LITERALS: &str = "0#?.,%/ $-+():!^&'~{}<>=@";
+/// Date and time codes.
+const DATE_TIME: &str = "yYmMdDhHsS";
+
+fn starts_with_ci(chars: &[char], at: usize, token: &str) -> bool {
+    let token: Vec<char> = token.chars().collect();
+    chars.len() >= at + token.len()
+        && chars[at..at + token.len()]
+            .iter()
+            .zip(&token)
+            .all(|(a, b)| a.eq_ignore_ascii_case(b))
+}
+
+fn validate_bracket(content: &str) -> Result<(), String> {
+    let lower = content.to_ascii_lowercase();
+    let valid = if COLORS.contains(&lower.as_str()) {
+        true
+    } else if let Some(n) = lower.strip_prefix("color") {
+        n.parse::<u8>().is_ok_and(|n| (1..=56).contains(&n))
+    } else if let Some(rest) = content.strip_prefix(['<', '>', '=']) {
+        let number = rest.trim_start_matches(['=', '>']);
+        number.trim().parse::<f64>().is_ok()
+    } else if content.starts_with('$') {
+        true
+    } else {
+        let mut letters = lower.chars();
+        letters
+            .next()
+            .is_some_and(|first| matches!(first, 'h' | 'm' | 's') && letters.all(|c| c == first))
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Unknown [{content}] in number format"))
+    }
+}
+
+/// Checks that `code` is a number format code Excel and LibreOffice accept, e.g.
+/// `"$#,##0.00"`, `"0.0%"`, `"yyyy-mm-dd"` or `"#,##0;[Red](#,##0)"`. Literal text
+/// must be quoted or escaped with `\`.
+pub fn validate_number_format(code: &str) -> Result<(), String> {
+    if code.trim().is_empty() {
+        return Err("Number format is empty".to_string());
+    }
+    if code.len() > MAX_FORMAT_LEN {
+        return Err(format!(
+            "Number format is longer than {MAX_FORMAT_LEN} characters"
+        ));
+    }
+
+    let chars: Vec<char> = code.chars().collect();
+    let mut sections = 1;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' => {
+                let close = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == '"')
+                    .ok_or("Unterminated quoted text in number format")?;
+                i += close + 2;
+                continue;
+            }
+            '[' => {
+                let close = chars[i + 1..]
+                    .iter()
+                    .position(|&ch| ch == ']')
+                    .ok_or("Unclosed '[' in number format")?;
+                let content: String = chars[i + 1..i + 1 + close].iter().collect();
+                validate_bracket(&content)?;
+                i += close + 2;
+                continue;
+            }
+            '\\' | '_' | '*' => {
+                if i + 1 >= chars.len() {
+                    return Err(format!("'{c}' must be followed by a character"));
+                }
+                i += 2;
+                continue;
+            }
+            ';' => {
+                sections += 1;
+                if sections > MAX_SECTIONS {
+                    return Err(format!(
+                        "Number format has more than {MAX_SECTIONS} sections"
+                    ));
+                }
+            }
+            _ if starts_with_ci(&chars, i, "general") => {
+                i += "general".len();
+                continue;
+            }
+            _ if starts_with_ci(&chars, i, "am/pm") => {
+                i += "am/pm".len();
+                continue;
+            }
+            _ if starts_with_ci(&chars, i, "a/p") => {
+                i += "a/p".len();
+                continue;
+            }
+            'E' | 'e' if matches!(chars.get(i + 1), Some('+' | '-')) => {
+                i += 2;
+                continue;
+            }
+            _ if LITERALS.contains(c) || DATE_TIME.contains(c) || c.is_ascii_digit() => {}
+            // Currency symbols and other non-ASCII characters are shown as-is.
+            _ if !c.is_ascii() => {}
+            _ => {
+                return Err(format!(
+                    "Unexpected '{c}' in number format; quote literal text"
+                ))
+            }
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+/// Applies the style and number format of `req` to every cell of its range, creating
+/// empty cells where needed. An empty `format` clears the number format. Returns the
+/// number of cells touched.
+pub fn apply_range_format(
+    worksheet: &mut Worksheet,
+    req: &FormatRequest,
+) -> Result<u64, SheetError> {
+    if req.style.is_none() && req.format.is_none() {
+        return Err(SheetError::InvalidRequest(
+            "Provide a style, a format, or both".to_string(),
+        ));
+    }
+    if req.start_row > req.end_row || req.start_col > req.end_col {
+        return Err(SheetError::InvalidRequest(
+            "Range start must not be after its end".to_string(),
+        ));
+    }
+    let cells =
+        u64::from(req.end_row - req.start_row + 1) * u64::from(req.end_col - req.start_col + 1);
+    if cells > MAX_FORMAT_CELLS {
+        return Err(SheetError::InvalidRequest(format!(
+            "Range has {cells} cells; at most {MAX_FORMAT_CELLS} can be formatted at once"
+        )));
+    }
+
+    let format = match req.format.as_deref().map(str::trim) {
+        None => None,
+        Some("") => Some(None),
+        Some(code) => {
+            validate_number_format(code).map_err(SheetError::InvalidRequest)?;
+            Some(Some(code.to_string()))
+        }
+    };
+
+    for row in req.start_row..=req.end_row {
+        for col in req.start_col..=req.end_col {
+            let cell = worksheet
+                .data
+                .entry(format!("{},{}", row, col))
+                .or_insert_with(|| CellData {
+                    value: None,
+                    formula: None,
+                    style: None,
+                    format: None,
+                    note: None,
+                    locked: None,
+                    has_comment: None,
+                    array_formula_id: None,
+                });
+            if let Some(ref style) = req.style {
+                cell.style = Some(style.clone());
+            }
+            if let Some(ref format) = format {
+                cell.format = format.clone();
+            }
+        }
+    }
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::export::export_to_xlsx;
+    use crate::sheet::storage::create_new_spreadsheet;
+    use crate::sheet::types::{CellStyle, Spreadsheet};
+    use base64::Engine;
+    use std::io::Read;
+
+    fn request(format: Option<&str>, style: Option<CellStyle>) -> FormatRequest {
+        FormatRequest {
+            sheet_id: "s1".to_string(),
+            worksheet_index: 0,
+            start_row: 0,
+            start_col: 1,
+            end_row: 2,
+            end_col: 1,
+            style,
+            format: format.map(str::to_string),
+        }
+    }
+
+    fn styles_xml(sheet: &Spreadsheet) -> String {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(export_to_xlsx(sheet).unwrap())
+            .unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut xml = String::new();
+        archive
+            .by_name("xl/styles.xml")
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        xml
+    }
+
+    #[test]
+    fn test_validate_number_format() {
+        for code in [
+            "General",
+            "0.00%",
+            "$#,##0.00",
+            "#,##0;[Red](#,##0);\"-\"",
+            "yyyy-mm-dd hh:mm AM/PM",
+            "[h]:mm:ss",
+            "[$€-407] #,##0.00",
+            "[>=1000]#,##0,\"K\";0",
+            "0.00E+00",
+            "@",
+            "\"R$\" #,##0.00",
+            "_(* #,##0_)",
+            "0\\ \\k\\g",
+        ] {
+            assert!(
+                validate_number_format(code).is_ok(),
+                "{code} should be valid"
+            );
+        }
+        for code in [
+            "",
+            "0.00 kg",
+            "\"unterminated",
+            "[Purple]0",
+            "[Color57]0",
+            "0;0;0;0;0",
+            "0\\",
+            "[h:mm",
+        ] {
+            assert!(
+                validate_number_format(code).is_err(),
+                "{code} should be invalid"
+            );
+        }
+        assert!(validate_number_format(&"0".repeat(MAX_FORMAT_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_range_format_keeps_style_and_clears() {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+        let bold = CellStyle {
+            font_weight: Some("bold".to_string()),
+            ..Default::default()
+        };
+        apply_range_format(&mut worksheet, &request(None, Some(bold))).unwrap();
+        assert_eq!(
+            apply_range_format(&mut worksheet, &request(Some("0.0%"), None)).unwrap(),
+            3
+        );
+
+        let cell = &worksheet.data["1,1"];
+        assert_eq!(cell.format.as_deref(), Some("0.0%"));
+        assert_eq!(
+            cell.style.as_ref().and_then(|s| s.font_weight.as_deref()),
+            Some("bold")
+        );
+
+        apply_range_format(&mut worksheet, &request(Some(""), None)).unwrap();
+        assert!(worksheet.data["1,1"].format.is_none());
+
+        assert!(apply_range_format(&mut worksheet, &request(None, None)).is_err());
+        assert!(apply_range_format(&mut worksheet, &request(Some("0 apples"), None)).is_err());
+        let mut huge = request(Some("0"), None);
+        huge.end_row = MAX_FORMAT_CELLS as u32;
+        assert!(apply_range_format(&mut worksheet, &huge).is_err());
+    }
+
+    #[test]
+    fn test_format_persists_and_exports() {
+        let mut sheet = create_new_spreadsheet();
+        let worksheet = &mut sheet.worksheets[0];
+        for (row, value) in ["0.125", "0.5", "1"].iter().enumerate() {
+            worksheet.data.insert(
+                format!("{row},1"),
+                CellData {
+                    value: Some(value.to_string()),
+                    formula: None,
+                    style: None,
+                    format: None,
+                    note: None,
+                    locked: None,
+                    has_comment: None,
+                    array_formula_id: None,
+                },
+            );
+        }
+        apply_range_format(worksheet, &request(Some("0.0%"), None)).unwrap();
+
+        let saved = serde_json::to_string(&sheet).unwrap();
+        let loaded: Spreadsheet = serde_json::from_str(&saved).unwrap();
+        for row in 0..3 {
+            let cell = &loaded.worksheets[0].data[&format!("{row},1")];
+            assert_eq!(cell.format.as_deref(), Some("0.0%"));
+        }
+        assert_eq!(
+            loaded.worksheets[0].data["0,1"].value.as_deref(),
+            Some("0.125")
+        );
+
+        assert!(styles_xml(&loaded).contains("formatCode=\"0.0%\""));
+    }
+}
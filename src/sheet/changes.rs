@@ -0,0 +1,747 @@
+//! Append-only change feed for mirroring sheets into other systems.
+//!
+//! Every edit appends an entry with the sheet's next sequence number. Integrations poll
+//! `GET /api/sheet/:id/changes?since=N` and apply what they get instead of diffing whole
+//! sheets. Cell entries carry the cell's new content; structural entries (sort, merge,
+//! restore, ...) name the operation and the affected worksheet, which a mirror reloads.
+//!
+//! At most [`MAX_ENTRIES`] entries are kept per sheet. When the log grows past that, the
+//! older entries are dropped and the sheet's state is kept as a snapshot instead; a client
+//! that fell behind gets a [`Resync`] pointing at it.
+
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::error::SheetError;
+use crate::sheet::formulas::parse_cell_key;
+use crate::sheet::snapshots::{
+    delete_snapshot, new_snapshot, save_snapshot, SnapshotAuthor, SYSTEM_AUTHOR_ID,
+};
+use crate::sheet::storage::load_sheet_by_id;
+use crate::sheet::types::Worksheet;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Jsonb, Nullable, Text, Timestamptz};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::sync::watch;
+
+/// Entries kept per sheet before older ones are compacted into a snapshot.
+pub const MAX_ENTRIES: i64 = 10_000;
+/// Entries allowed past [`MAX_ENTRIES`] before compacting, so it runs in batches.
+const COMPACT_SLACK: i64 = 1_000;
+/// Most entries returned by one poll.
+pub const MAX_PAGE: i64 = 500;
+/// Longest a poll waits for new entries; kept under the default 30 s request deadline.
+pub const MAX_WAIT_SECS: u64 = 25;
+
+const COMPACTION_LABEL: &str = "Change feed compaction";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SheetChange {
+    /// A cell's content after the edit. Both fields are `None` when it was cleared.
+    Cell {
+        worksheet_index: usize,
+        row: u32,
+        col: u32,
+        value: Option<String>,
+        formula: Option<String>,
+    },
+    /// An operation that moves or restyles many cells at once; mirrors reload the
+    /// worksheet, or the whole sheet when `worksheet_index` is `None`.
+    Structure {
+        action: String,
+        worksheet_index: Option<usize>,
+        #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+        detail: serde_json::Value,
+    },
+}
+
+impl SheetChange {
+    pub fn structure(
+        action: &str,
+        worksheet_index: Option<usize>,
+        detail: serde_json::Value,
+    ) -> Self {
+        Self::Structure {
+            action: action.to_string(),
+            worksheet_index,
+            detail,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeEntry {
+    pub seq: i64,
+    pub actor: String,
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub change: SheetChange,
+}
+
+/// Content of one cell as a [`SheetChange::Cell`] entry.
+pub fn cell_entry(
+    worksheet_index: usize,
+    worksheet: &Worksheet,
+    row: u32,
+    col: u32,
+) -> SheetChange {
+    let cell = worksheet.data.get(&format!("{row},{col}"));
+    SheetChange::Cell {
+        worksheet_index,
+        row,
+        col,
+        value: cell.and_then(|c| c.value.clone()),
+        formula: cell.and_then(|c| c.formula.clone()),
+    }
+}
+
+/// Values of the cells a recalculation can change: formula cells and spilled results.
+pub fn derived_values(worksheet: &Worksheet) -> HashMap<String, Option<String>> {
+    worksheet
+        .data
+        .iter()
+        .filter(|(_, cell)| cell.formula.is_some() || cell.array_formula_id.is_some())
+        .map(|(key, cell)| (key.clone(), cell.value.clone()))
+        .collect()
+}
+
+/// Cell entries for the formula and spilled cells whose value differs from `before`
+/// (taken with [`derived_values`] ahead of the edit), including cells that stopped
+/// being derived.
+pub fn derived_changes(
+    worksheet_index: usize,
+    before: &HashMap<String, Option<String>>,
+    worksheet: &Worksheet,
+) -> Vec<SheetChange> {
+    let after = derived_values(worksheet);
+    let mut cells: Vec<(u32, u32)> = before
+        .keys()
+        .chain(after.keys())
+        .filter(|key| before.get(*key) != after.get(*key))
+        .filter_map(|key| parse_cell_key(key))
+        .collect();
+    cells.sort_unstable();
+    cells.dedup();
+    cells
+        .into_iter()
+        .map(|(row, col)| cell_entry(worksheet_index, worksheet, row, col))
+        .collect()
+}
+
+/// Where a sheet's log stands.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FeedHead {
+    /// Sequence of the latest entry; 0 before the first edit.
+    pub last_seq: i64,
+    /// Entries up to this sequence were dropped.
+    pub compacted_through: i64,
+    pub snapshot_id: Option<String>,
+    pub snapshot_seq: Option<i64>,
+}
+
+/// Tells a client that fell behind the log to reload from a snapshot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Resync {
+    pub snapshot_id: String,
+    /// The snapshot holds the sheet as of at least this entry; poll again from here.
+    pub seq: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChangePage {
+    pub entries: Vec<ChangeEntry>,
+    /// Pass as `since` on the next poll.
+    pub next: i64,
+    pub head: i64,
+    pub has_more: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resync: Option<Resync>,
+}
+
+/// Where change entries are persisted.
+pub trait ChangeLog {
+    fn head(&mut self, sheet_id: &str) -> Result<FeedHead, SheetError>;
+    /// Appends `changes` in order with consecutive sequence numbers; returns the last one.
+    fn append(
+        &mut self,
+        sheet_id: &str,
+        actor: &str,
+        changes: &[SheetChange],
+    ) -> Result<i64, SheetError>;
+    /// Entries after `since`, oldest first.
+    fn entries_after(
+        &mut self,
+        sheet_id: &str,
+        since: i64,
+        limit: i64,
+    ) -> Result<Vec<ChangeEntry>, SheetError>;
+    /// Drops entries up to `through`, recording the snapshot that replaces them. Does
+    /// nothing and returns `false` when the head no longer points at `previous`, i.e.
+    /// another compaction got there first.
+    fn compact(
+        &mut self,
+        sheet_id: &str,
+        through: i64,
+        snapshot_id: &str,
+        snapshot_seq: i64,
+        previous: Option<&str>,
+    ) -> Result<bool, SheetError>;
+}
+
+/// The page of entries after `since`, or a [`Resync`] when they were compacted away.
+pub fn read_changes<L: ChangeLog + ?Sized>(
+    log: &mut L,
+    sheet_id: &str,
+    since: i64,
+    limit: i64,
+) -> Result<ChangePage, SheetError> {
+    let head = log.head(sheet_id)?;
+    if since < head.compacted_through {
+        if let (Some(snapshot_id), Some(seq)) = (head.snapshot_id, head.snapshot_seq) {
+            return Ok(ChangePage {
+                entries: Vec::new(),
+                next: seq,
+                head: head.last_seq,
+                has_more: seq < head.last_seq,
+                resync: Some(Resync { snapshot_id, seq }),
+            });
+        }
+    }
+    let entries = log.entries_after(sheet_id, since, limit.clamp(1, MAX_PAGE))?;
+    let next = entries.last().map_or(since, |e| e.seq);
+    Ok(ChangePage {
+        entries,
+        next,
+        head: head.last_seq,
+        has_more: next < head.last_seq,
+        resync: None,
+    })
+}
+
+fn db_error(e: diesel::result::Error) -> SheetError {
+    SheetError::StorageFailed(e.to_string())
+}
+
+#[derive(QueryableByName)]
+struct HeadRow {
+    #[diesel(sql_type = BigInt)]
+    last_seq: i64,
+    #[diesel(sql_type = BigInt)]
+    compacted_through: i64,
+    #[diesel(sql_type = Nullable<Text>)]
+    snapshot_id: Option<String>,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    snapshot_seq: Option<i64>,
+}
+
+#[derive(QueryableByName)]
+struct SeqRow {
+    #[diesel(sql_type = BigInt)]
+    last_seq: i64,
+}
+
+#[derive(QueryableByName)]
+struct EntryRow {
+    #[diesel(sql_type = BigInt)]
+    seq: i64,
+    #[diesel(sql_type = Text)]
+    actor: String,
+    #[diesel(sql_type = Timestamptz)]
+    created_at: DateTime<Utc>,
+    #[diesel(sql_type = Jsonb)]
+    change: serde_json::Value,
+}
+
+impl ChangeLog for PgConnection {
+    fn head(&mut self, sheet_id: &str) -> Result<FeedHead, SheetError> {
+        let row: Option<HeadRow> = diesel::sql_query(
+            "SELECT last_seq, compacted_through, snapshot_id, snapshot_seq \
+             FROM sheet_change_feeds WHERE sheet_id = $1",
+        )
+        .bind::<Text, _>(sheet_id)
+        .get_result(self)
+        .optional()
+        .map_err(db_error)?;
+        Ok(row.map_or_else(FeedHead::default, |r| FeedHead {
+            last_seq: r.last_seq,
+            compacted_through: r.compacted_through,
+            snapshot_id: r.snapshot_id,
+            snapshot_seq: r.snapshot_seq,
+        }))
+    }
+
+    fn append(
+        &mut self,
+        sheet_id: &str,
+        actor: &str,
+        changes: &[SheetChange],
+    ) -> Result<i64, SheetError> {
+        let count = changes.len() as i64;
+        self.transaction(|conn| {
+            // The upsert locks the feed row, so concurrent writers get distinct sequences.
+            let last_seq = diesel::sql_query(
+                "INSERT INTO sheet_change_feeds (sheet_id, last_seq) VALUES ($1, $2) \
+                 ON CONFLICT (sheet_id) DO UPDATE \
+                 SET last_seq = sheet_change_feeds.last_seq + EXCLUDED.last_seq \
+                 RETURNING last_seq",
+            )
+            .bind::<Text, _>(sheet_id)
+            .bind::<BigInt, _>(count)
+            .get_result::<SeqRow>(conn)?
+            .last_seq;
+
+            for (seq, change) in (last_seq - count + 1..).zip(changes) {
+                let json = serde_json::to_value(change).unwrap_or_default();
+                diesel::sql_query(
+                    "INSERT INTO sheet_changes (sheet_id, seq, actor, change) VALUES ($1, $2, $3, $4)",
+                )
+                .bind::<Text, _>(sheet_id)
+                .bind::<BigInt, _>(seq)
+                .bind::<Text, _>(actor)
+                .bind::<Jsonb, _>(json)
+                .execute(conn)?;
+            }
+            Ok(last_seq)
+        })
+        .map_err(db_error)
+    }
+
+    fn entries_after(
+        &mut self,
+        sheet_id: &str,
+        since: i64,
+        limit: i64,
+    ) -> Result<Vec<ChangeEntry>, SheetError> {
+        let rows: Vec<EntryRow> = diesel::sql_query(
+            "SELECT seq, actor, created_at, change FROM sheet_changes \
+             WHERE sheet_id = $1 AND seq > $2 ORDER BY seq LIMIT $3",
+        )
+        .bind::<Text, _>(sheet_id)
+        .bind::<BigInt, _>(since)
+        .bind::<BigInt, _>(limit)
+        .load(self)
+        .map_err(db_error)?;
+        rows.into_iter()
+            .map(|row| {
+                let change = serde_json::from_value(row.change).map_err(|e| {
+                    SheetError::StorageFailed(format!("Corrupt change {}: {e}", row.seq))
+                })?;
+                Ok(ChangeEntry {
+                    seq: row.seq,
+                    actor: row.actor,
+                    at: row.created_at,
+                    change,
+                })
+            })
+            .collect()
+    }
+
+    fn compact(
+        &mut self,
+        sheet_id: &str,
+        through: i64,
+        snapshot_id: &str,
+        snapshot_seq: i64,
+        previous: Option<&str>,
+    ) -> Result<bool, SheetError> {
+        self.transaction(|conn| {
+            let updated = diesel::sql_query(
+                "UPDATE sheet_change_feeds SET compacted_through = GREATEST(compacted_through, $2), \
+                 snapshot_id = $3, snapshot_seq = $4 \
+                 WHERE sheet_id = $1 AND snapshot_id IS NOT DISTINCT FROM $5",
+            )
+            .bind::<Text, _>(sheet_id)
+            .bind::<BigInt, _>(through)
+            .bind::<Text, _>(snapshot_id)
+            .bind::<BigInt, _>(snapshot_seq)
+            .bind::<Nullable<Text>, _>(previous)
+            .execute(conn)?;
+            if updated == 0 {
+                return Ok(false);
+            }
+            diesel::sql_query("DELETE FROM sheet_changes WHERE sheet_id = $1 AND seq <= $2")
+                .bind::<Text, _>(sheet_id)
+                .bind::<BigInt, _>(through)
+                .execute(conn)?;
+            Ok(true)
+        })
+        .map_err(db_error)
+    }
+}
+
+/// Sheets whose feed this process is compacting.
+static COMPACTING: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Latest sequence per sheet, for polls waiting on new entries in this process.
+static WATCHERS: LazyLock<Mutex<HashMap<String, watch::Sender<i64>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A receiver that changes when `sheet_id` gets new entries.
+pub fn subscribe(sheet_id: &str) -> watch::Receiver<i64> {
+    let mut watchers = WATCHERS.lock().unwrap_or_else(|e| e.into_inner());
+    watchers
+        .entry(sheet_id.to_string())
+        .or_insert_with(|| watch::channel(0).0)
+        .subscribe()
+}
+
+/// Drops the sheet's watcher once no poll is waiting on it.
+pub fn unsubscribe(sheet_id: &str, receiver: watch::Receiver<i64>) {
+    drop(receiver);
+    let mut watchers = WATCHERS.lock().unwrap_or_else(|e| e.into_inner());
+    if watchers
+        .get(sheet_id)
+        .is_some_and(|tx| tx.receiver_count() == 0)
+    {
+        watchers.remove(sheet_id);
+    }
+}
+
+fn notify(sheet_id: &str, seq: i64) {
+    let watchers = WATCHERS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(tx) = watchers.get(sheet_id) {
+        tx.send_replace(seq);
+    }
+}
+
+/// Appends `changes` to the sheet's feed after an edit was saved, compacting the log when
+/// it is due. Failures are logged: the edit itself already succeeded.
+pub async fn record_changes(
+    state: &Arc<AppState>,
+    owner_id: &str,
+    sheet_id: &str,
+    actor: &str,
+    changes: Vec<SheetChange>,
+) {
+    if changes.is_empty() {
+        return;
+    }
+    let pool = state.conn.clone();
+    let (id, actor) = (sheet_id.to_string(), actor.to_string());
+    let appended = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| SheetError::StorageFailed(e.to_string()))?;
+        let seq = ChangeLog::append(&mut *conn, &id, &actor, &changes)?;
+        let head = ChangeLog::head(&mut *conn, &id)?;
+        Ok::<_, SheetError>((seq, head))
+    })
+    .await
+    .map_err(|e| SheetError::StorageFailed(e.to_string()))
+    .and_then(|r| r);
+
+    let (seq, head) = match appended {
+        Ok(appended) => appended,
+        Err(e) => {
+            warn!("Failed to record changes for sheet {sheet_id}: {e}");
+            return;
+        }
+    };
+    notify(sheet_id, seq);
+
+    if seq - head.compacted_through > MAX_ENTRIES + COMPACT_SLACK {
+        // One compaction per sheet at a time; a sheet already compacting is left to it.
+        let started = COMPACTING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(sheet_id.to_string());
+        if started {
+            if let Err(e) = compact(state, owner_id, sheet_id, seq, head.snapshot_id).await {
+                warn!("Failed to compact change feed of sheet {sheet_id}: {e}");
+            }
+            COMPACTING
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(sheet_id);
+        }
+    }
+}
+
+/// Records one structural entry by `user`, for handlers that change a single thing.
+pub async fn record_structure(
+    state: &Arc<AppState>,
+    owner_id: &str,
+    sheet_id: &str,
+    user: &AuthenticatedUser,
+    action: &str,
+    worksheet_index: Option<usize>,
+) {
+    record_changes(
+        state,
+        owner_id,
+        sheet_id,
+        &user.user_id.to_string(),
+        vec![SheetChange::structure(
+            action,
+            worksheet_index,
+            serde_json::Value::Null,
+        )],
+    )
+    .await;
+}
+
+/// Keeps the sheet's current state as a snapshot, then drops all but the newest
+/// [`MAX_ENTRIES`] entries and the previous compaction snapshot. If another server
+/// compacted the feed meanwhile, its result stands and the new snapshot is deleted.
+async fn compact(
+    state: &Arc<AppState>,
+    owner_id: &str,
+    sheet_id: &str,
+    seq: i64,
+    previous: Option<String>,
+) -> Result<(), SheetError> {
+    // Loaded after `seq` was appended, so the state includes at least that entry.
+    let sheet = load_sheet_by_id(state, owner_id, sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    let author = SnapshotAuthor {
+        id: SYSTEM_AUTHOR_ID.to_string(),
+        name: "Change feed".to_string(),
+    };
+    let label = format!("{COMPACTION_LABEL} #{seq}");
+    let snapshot = new_snapshot(&sheet, &label, &author);
+    save_snapshot(state, owner_id, sheet_id, &snapshot).await?;
+
+    let pool = state.conn.clone();
+    let id = sheet_id.to_string();
+    let snapshot_id = snapshot.id.clone();
+    let expected = previous.clone();
+    let through = seq - MAX_ENTRIES;
+    let compacted = tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| SheetError::StorageFailed(e.to_string()))?;
+        ChangeLog::compact(
+            &mut *conn,
+            &id,
+            through,
+            &snapshot_id,
+            seq,
+            expected.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| SheetError::StorageFailed(e.to_string()))?;
+
+    match compacted {
+        Ok(true) => {
+            if let Some(previous) = previous {
+                delete_snapshot(state, owner_id, sheet_id, &previous).await;
+            }
+            info!("Compacted change feed of sheet {sheet_id} through #{through}");
+            Ok(())
+        }
+        Ok(false) => {
+            delete_snapshot(state, owner_id, sheet_id, &snapshot.id).await;
+            Ok(())
+        }
+        Err(e) => {
+            delete_snapshot(state, owner_id, sheet_id, &snapshot.id).await;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::formulas::recalculate_worksheet;
+    use crate::sheet::storage::create_new_spreadsheet;
+    use crate::sheet::types::CellData;
+
+    #[derive(Default)]
+    struct MemoryLog {
+        head: FeedHead,
+        entries: Vec<ChangeEntry>,
+    }
+
+    impl ChangeLog for MemoryLog {
+        fn head(&mut self, _sheet_id: &str) -> Result<FeedHead, SheetError> {
+            Ok(self.head.clone())
+        }
+
+        fn append(
+            &mut self,
+            _sheet_id: &str,
+            actor: &str,
+            changes: &[SheetChange],
+        ) -> Result<i64, SheetError> {
+            for change in changes {
+                self.head.last_seq += 1;
+                self.entries.push(ChangeEntry {
+                    seq: self.head.last_seq,
+                    actor: actor.to_string(),
+                    at: Utc::now(),
+                    change: change.clone(),
+                });
+            }
+            Ok(self.head.last_seq)
+        }
+
+        fn entries_after(
+            &mut self,
+            _sheet_id: &str,
+            since: i64,
+            limit: i64,
+        ) -> Result<Vec<ChangeEntry>, SheetError> {
+            Ok(self
+                .entries
+                .iter()
+                .filter(|e| e.seq > since)
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+
+        fn compact(
+            &mut self,
+            _sheet_id: &str,
+            through: i64,
+            snapshot_id: &str,
+            snapshot_seq: i64,
+            previous: Option<&str>,
+        ) -> Result<bool, SheetError> {
+            if self.head.snapshot_id.as_deref() != previous {
+                return Ok(false);
+            }
+            self.entries.retain(|e| e.seq > through);
+            self.head.compacted_through = through;
+            self.head.snapshot_id = Some(snapshot_id.to_string());
+            self.head.snapshot_seq = Some(snapshot_seq);
+            Ok(true)
+        }
+    }
+
+    fn cell(row: u32, value: &str) -> SheetChange {
+        SheetChange::Cell {
+            worksheet_index: 0,
+            row,
+            col: 0,
+            value: Some(value.to_string()),
+            formula: None,
+        }
+    }
+
+    #[test]
+    fn test_recalculated_dependents_get_entries() {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+        let set = |worksheet: &mut Worksheet, key: &str, value: &str, formula: Option<&str>| {
+            worksheet.data.insert(
+                key.to_string(),
+                CellData {
+                    value: Some(value.to_string()),
+                    formula: formula.map(str::to_string),
+                    style: None,
+                    format: None,
+                    note: None,
+                    locked: None,
+                    has_comment: None,
+                    array_formula_id: None,
+                },
+            );
+        };
+        set(&mut worksheet, "0,0", "1", None);
+        set(&mut worksheet, "1,0", "2", Some("=A1*2"));
+        set(&mut worksheet, "2,0", "0", Some("=SUM(C1:C2)"));
+
+        let before = derived_values(&worksheet);
+        set(&mut worksheet, "0,0", "5", None);
+        recalculate_worksheet(&mut worksheet);
+
+        assert_eq!(
+            derived_changes(0, &before, &worksheet),
+            vec![SheetChange::Cell {
+                worksheet_index: 0,
+                row: 1,
+                col: 0,
+                value: Some("10".to_string()),
+                formula: Some("=A1*2".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_edits_are_read_back_in_order_by_sequence() {
+        let mut log = MemoryLog::default();
+        log.append("s1", "ana", &[cell(0, "a"), cell(1, "b")])
+            .unwrap();
+        log.append(
+            "s1",
+            "bia",
+            &[SheetChange::structure(
+                "sort",
+                Some(0),
+                serde_json::Value::Null,
+            )],
+        )
+        .unwrap();
+        log.append("s1", "ana", &[cell(2, "c")]).unwrap();
+
+        let page = read_changes(&mut log, "s1", 0, 2).unwrap();
+        assert_eq!(
+            page.entries.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(page.entries[1].change, cell(1, "b"));
+        assert_eq!((page.next, page.head, page.has_more), (2, 4, true));
+
+        let page = read_changes(&mut log, "s1", page.next, 100).unwrap();
+        assert_eq!(
+            page.entries.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        assert!(
+            matches!(&page.entries[0].change, SheetChange::Structure { action, .. } if action == "sort")
+        );
+        assert_eq!(page.entries[0].actor, "bia");
+        assert!(!page.has_more);
+
+        let page = read_changes(&mut log, "s1", 4, 100).unwrap();
+        assert!(page.entries.is_empty());
+        assert_eq!(page.next, 4);
+    }
+
+    #[test]
+    fn test_client_behind_compaction_is_sent_to_the_snapshot() {
+        let mut log = MemoryLog::default();
+        for row in 0..5 {
+            log.append("s1", "ana", &[cell(row, "x")]).unwrap();
+        }
+        assert!(log.compact("s1", 3, "snap-1", 5, None).unwrap());
+
+        let page = read_changes(&mut log, "s1", 1, 100).unwrap();
+        assert_eq!(
+            page.resync,
+            Some(Resync {
+                snapshot_id: "snap-1".to_string(),
+                seq: 5
+            })
+        );
+        assert!(page.entries.is_empty());
+
+        let page = read_changes(&mut log, "s1", 3, 100).unwrap();
+        assert_eq!(page.resync, None);
+        assert_eq!(
+            page.entries.iter().map(|e| e.seq).collect::<Vec<_>>(),
+            vec![4, 5]
+        );
+    }
+
+    #[test]
+    fn test_entries_serialize_with_their_operation() {
+        let entry = ChangeEntry {
+            seq: 7,
+            actor: "ana".to_string(),
+            at: Utc::now(),
+            change: cell(3, "42"),
+        };
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(json["op"], "cell");
+        assert_eq!(json["row"], 3);
+        assert_eq!(json["value"], "42");
+    }
+}
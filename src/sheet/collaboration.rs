@@ -1,20 +1,37 @@
+use crate::core::shared::api_error::ApiError;
 use crate::core::shared::state::AppState;
-use crate::sheet::types::CollabMessage;
+use crate::core::shared::ws_heartbeat::{reaped, HeartbeatConfig, Liveness};
+use crate::security::auth_api::{authenticate_ws, AuthenticatedUser, WS_BEARER_PROTOCOL};
+use crate::sheet::error::SheetError;
+use crate::sheet::protection::ensure_cell_editable;
+use crate::sheet::snapshots::SnapshotInfo;
+use crate::sheet::storage::{can_access_sheet, flush_sheet, get_current_user_id, load_sheet_by_id};
+use crate::sheet::types::{CellComment, CollabMessage, Spreadsheet};
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        Path, State,
+        Path, Query, State,
     },
-    response::IntoResponse,
+    http::HeaderMap,
+    response::{IntoResponse, Response},
     Json,
 };
 use chrono::Utc;
 use futures_util::{SinkExt, StreamExt};
-use log::{error, info};
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+
+/// Largest message a collaborator may send; larger frames close the connection.
+pub const MAX_INBOUND_MESSAGE_BYTES: usize = 64 * 1024;
+
+/// Messages buffered per sheet. The channel keeps only the newest ones: a collaborator
+/// that falls further behind misses the oldest and is sent a full `resync` snapshot of
+/// the sheet instead, so a slow client never holds back the others.
+pub const COLLAB_CHANNEL_CAPACITY: usize = 256;
 
 pub type CollaborationChannels =
     Arc<tokio::sync::RwLock<HashMap<String, broadcast::Sender<CollabMessage>>>>;
@@ -159,28 +176,104 @@ pub async fn handle_get_mentions(
 pub async fn handle_sheet_websocket(
     ws: WebSocketUpgrade,
     Path(sheet_id): Path<String>,
-    State(_state): State<Arc<AppState>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_sheet_connection(socket, sheet_id))
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HashMap<String, String>>,
+) -> Response {
+    let user = match authenticate_ws(state.auth_provider_registry.as_deref(), &headers, &params).await {
+        Ok(user) => user,
+        Err(e) => return e.into_response(),
+    };
+
+    let sheet = match load_sheet_by_id(&state, &get_current_user_id(), &sheet_id).await {
+        Ok(sheet) => sheet,
+        Err(e) => return SheetError::SheetNotFound(e).into_response(),
+    };
+    if !can_access_sheet(&sheet, &user) {
+        return ApiError::forbidden("You don't have access to this sheet").into_response();
+    }
+
+    ws.protocols([WS_BEARER_PROTOCOL])
+        .max_message_size(MAX_INBOUND_MESSAGE_BYTES)
+        .max_frame_size(MAX_INBOUND_MESSAGE_BYTES)
+        .on_upgrade(move |socket| handle_sheet_connection(socket, state, sheet_id, user))
+}
+
+enum Outgoing {
+    Forward(CollabMessage),
+    Skip,
+    Resync(u64),
+    Close,
+}
+
+/// What to send for a message received from the sheet's broadcast channel. Own messages
+/// are not echoed back, and a lagged receiver is resynced rather than disconnected.
+fn route_broadcast(received: Result<CollabMessage, RecvError>, own_user_id: &str) -> Outgoing {
+    match received {
+        Ok(msg) if msg.user_id == own_user_id => Outgoing::Skip,
+        Ok(msg) => Outgoing::Forward(msg),
+        Err(RecvError::Lagged(missed)) => Outgoing::Resync(missed),
+        Err(RecvError::Closed) => Outgoing::Close,
+    }
 }
 
-async fn handle_sheet_connection(socket: WebSocket, sheet_id: String) {
+/// A `resync` message carrying the whole sheet as JSON, replacing the client's state.
+fn snapshot_message(sheet: &Spreadsheet) -> Option<CollabMessage> {
+    Some(CollabMessage {
+        msg_type: "resync".to_string(),
+        sheet_id: sheet.id.clone(),
+        user_id: String::new(),
+        user_name: String::new(),
+        user_color: String::new(),
+        row: None,
+        col: None,
+        value: Some(serde_json::to_string(sheet).ok()?),
+        worksheet_index: None,
+        timestamp: Utc::now(),
+    })
+}
+
+/// Rejects a collaborator's cell edit that targets a protected cell. The sheet is reloaded
+/// so protection changes made during the session apply immediately.
+async fn check_collab_edit(
+    state: &Arc<AppState>,
+    msg: &CollabMessage,
+    user: &AuthenticatedUser,
+) -> Result<(), SheetError> {
+    let (Some(row), Some(col)) = (msg.row, msg.col) else {
+        return Ok(());
+    };
+    let sheet = load_sheet_by_id(state, &get_current_user_id(), &msg.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    ensure_cell_editable(&sheet, msg.worksheet_index.unwrap_or(0), row, col, user)
+}
+
+async fn handle_sheet_connection(
+    socket: WebSocket,
+    state: Arc<AppState>,
+    sheet_id: String,
+    user: AuthenticatedUser,
+) {
+    let user_id = user.user_id.to_string();
+    let user_name = user.email.clone().unwrap_or_else(|| user.username.clone());
     let (mut sender, mut receiver) = socket.split();
+    let (direct_tx, mut direct_rx) = mpsc::unbounded_channel::<CollabMessage>();
+    let heartbeat = HeartbeatConfig::default();
+    let liveness = Liveness::new();
 
     let channels = get_collab_channels();
     let broadcast_tx = {
         let mut channels_write = channels.write().await;
         channels_write
             .entry(sheet_id.clone())
-            .or_insert_with(|| broadcast::channel(100).0)
+            .or_insert_with(|| broadcast::channel(COLLAB_CHANNEL_CAPACITY).0)
             .clone()
     };
 
     let mut broadcast_rx = broadcast_tx.subscribe();
 
-    let user_id = uuid::Uuid::new_v4().to_string();
     let user_id_for_send = user_id.clone();
-    let user_name = format!("User {}", &user_id[..8]);
     let user_color = get_random_color();
 
     {
@@ -219,9 +312,16 @@ async fn handle_sheet_connection(socket: WebSocket, sheet_id: String) {
     let sheet_id_clone = sheet_id.clone();
     let user_name_clone = user_name.clone();
     let user_color_clone = user_color.clone();
+    let liveness_recv = liveness.clone();
+    let state_for_send = Arc::clone(&state);
+    let state_for_leave = Arc::clone(&state);
+    let sheet_id_for_send = sheet_id.clone();
 
-    let receive_task = tokio::spawn(async move {
+    let mut receive_task = tokio::spawn(async move {
         while let Some(msg) = receiver.next().await {
+            if msg.is_ok() {
+                liveness_recv.touch();
+            }
             match msg {
                 Ok(Message::Text(text)) => {
                     if let Ok(mut collab_msg) = serde_json::from_str::<CollabMessage>(&text) {
@@ -232,6 +332,15 @@ async fn handle_sheet_connection(socket: WebSocket, sheet_id: String) {
                         collab_msg.timestamp = Utc::now();
 
                         match collab_msg.msg_type.as_str() {
+                            "cell_update" => {
+                                if let Err(e) = check_collab_edit(&state, &collab_msg, &user).await {
+                                    let mut rejected = collab_msg.clone();
+                                    rejected.msg_type = "edit_rejected".to_string();
+                                    rejected.value = Some(e.to_string());
+                                    let _ = direct_tx.send(rejected);
+                                    continue;
+                                }
+                            }
                             "cursor" | "cell_select" => {
                                 let mut presence = get_presence().write().await;
                                 if let Some(users) = presence.get_mut(&sheet_id_clone) {
@@ -338,11 +447,36 @@ async fn handle_sheet_connection(socket: WebSocket, sheet_id: String) {
         }
     });
 
-    let send_task = tokio::spawn(async move {
-        while let Ok(msg) = broadcast_rx.recv().await {
-            if msg.user_id == user_id_for_send {
-                continue;
-            }
+    let mut send_task = tokio::spawn(async move {
+        let mut ping_ticker = heartbeat.ping_ticker();
+        loop {
+            let msg = tokio::select! {
+                Some(direct) = direct_rx.recv() => direct,
+                received = broadcast_rx.recv() => match route_broadcast(received, &user_id_for_send) {
+                    Outgoing::Forward(msg) => msg,
+                    Outgoing::Skip => continue,
+                    Outgoing::Close => break,
+                    Outgoing::Resync(missed) => {
+                        warn!(
+                            "Sheet collaborator {} on {} missed {} messages, resyncing",
+                            user_id_for_send, sheet_id_for_send, missed
+                        );
+                        let sheet = load_sheet_by_id(&state_for_send, &get_current_user_id(), &sheet_id_for_send).await;
+                        // Without a snapshot the client's state is unknown; closing makes
+                        // it reconnect and load the sheet again.
+                        match sheet.ok().as_ref().and_then(snapshot_message) {
+                            Some(snapshot) => snapshot,
+                            None => break,
+                        }
+                    }
+                },
+                _ = ping_ticker.tick() => {
+                    if sender.send(Message::Ping(Vec::new().into())).await.is_err() {
+                        break;
+                    }
+                    continue;
+                }
+            };
             if let Ok(json) = serde_json::to_string(&msg) {
                 if sender.send(Message::Text(json.into())).await.is_err() {
                     break;
@@ -368,14 +502,24 @@ async fn handle_sheet_connection(socket: WebSocket, sheet_id: String) {
     };
 
     tokio::select! {
-        _ = receive_task => {}
-        _ = send_task => {}
+        _ = &mut receive_task => {}
+        _ = &mut send_task => {}
+        _ = reaped(&liveness, heartbeat) => {
+            info!("Dropping unresponsive sheet connection of {} on {}", user_id_leave, sheet_id_leave);
+        }
     }
+    receive_task.abort();
+    send_task.abort();
+    let _ = receive_task.await;
+    let _ = send_task.await;
 
     {
         let mut presence = get_presence().write().await;
         if let Some(users) = presence.get_mut(&sheet_id_leave) {
             users.retain(|u| u.user_id != user_id_leave);
+            if users.is_empty() {
+                presence.remove(&sheet_id_leave);
+            }
         }
     }
 
@@ -383,6 +527,9 @@ async fn handle_sheet_connection(socket: WebSocket, sheet_id: String) {
         let mut typing = get_typing().write().await;
         if let Some(indicators) = typing.get_mut(&sheet_id_leave) {
             indicators.retain(|t| t.user_id != user_id_leave);
+            if indicators.is_empty() {
+                typing.remove(&sheet_id_leave);
+            }
         }
     }
 
@@ -390,12 +537,31 @@ async fn handle_sheet_connection(socket: WebSocket, sheet_id: String) {
         let mut selections = get_selections().write().await;
         if let Some(sels) = selections.get_mut(&sheet_id_leave) {
             sels.retain(|s| s.user_id != user_id_leave);
+            if sels.is_empty() {
+                selections.remove(&sheet_id_leave);
+            }
         }
     }
 
     if let Err(e) = broadcast_tx.send(leave_msg) {
         info!("User left (broadcast may have no receivers): {}", e);
     }
+    release_channel(&sheet_id_leave).await;
+
+    if let Err(e) = flush_sheet(&state_for_leave, &get_current_user_id(), &sheet_id_leave).await {
+        warn!("Failed to save sheet {} on disconnect: {}", sheet_id_leave, e);
+    }
+}
+
+/// Drops the sheet's broadcast channel once its last subscriber has disconnected.
+async fn release_channel(sheet_id: &str) {
+    let mut channels = get_collab_channels().write().await;
+    if channels
+        .get(sheet_id)
+        .is_some_and(|tx| tx.receiver_count() == 0)
+    {
+        channels.remove(sheet_id);
+    }
 }
 
 pub async fn broadcast_sheet_change(
@@ -425,6 +591,90 @@ pub async fn broadcast_sheet_change(
     }
 }
 
+/// Notifies collaborators of a comment thread change. `event` is one of `comment_added`,
+/// `comment_replied`, `comment_resolved` or `comment_deleted`; `value` carries the comment
+/// as JSON.
+pub async fn broadcast_comment_event(
+    sheet_id: &str,
+    user: &AuthenticatedUser,
+    event: &str,
+    worksheet_index: usize,
+    row: u32,
+    col: u32,
+    comment: &CellComment,
+) {
+    let channels = get_collab_channels().read().await;
+    if let Some(tx) = channels.get(sheet_id) {
+        let msg = CollabMessage {
+            msg_type: event.to_string(),
+            sheet_id: sheet_id.to_string(),
+            user_id: user.user_id.to_string(),
+            user_name: user.email.clone().unwrap_or_else(|| user.username.clone()),
+            user_color: get_random_color(),
+            row: Some(row),
+            col: Some(col),
+            value: serde_json::to_string(comment).ok(),
+            worksheet_index: Some(worksheet_index),
+            timestamp: Utc::now(),
+        };
+        let _ = tx.send(msg);
+    }
+}
+
+/// Tells collaborators the sheet was restored from a snapshot, so they reload it.
+/// `value` carries the restored snapshot as JSON.
+pub async fn broadcast_sheet_restored(
+    sheet_id: &str,
+    user: &AuthenticatedUser,
+    snapshot: &SnapshotInfo,
+) {
+    let channels = get_collab_channels().read().await;
+    if let Some(tx) = channels.get(sheet_id) {
+        let msg = CollabMessage {
+            msg_type: "sheet_restored".to_string(),
+            sheet_id: sheet_id.to_string(),
+            user_id: user.user_id.to_string(),
+            user_name: user.email.clone().unwrap_or_else(|| user.username.clone()),
+            user_color: get_random_color(),
+            row: None,
+            col: None,
+            value: serde_json::to_string(snapshot).ok(),
+            worksheet_index: None,
+            timestamp: Utc::now(),
+        };
+        let _ = tx.send(msg);
+    }
+}
+
+/// Tells collaborators that worksheets were added, renamed, deleted or moved. `value`
+/// carries the action and the new worksheet names in order.
+pub async fn broadcast_worksheets_changed(
+    sheet_id: &str,
+    user: &AuthenticatedUser,
+    action: &str,
+    worksheet_index: Option<usize>,
+    worksheets: &[String],
+) {
+    let channels = get_collab_channels().read().await;
+    if let Some(tx) = channels.get(sheet_id) {
+        let msg = CollabMessage {
+            msg_type: "worksheets_changed".to_string(),
+            sheet_id: sheet_id.to_string(),
+            user_id: user.user_id.to_string(),
+            user_name: user.email.clone().unwrap_or_else(|| user.username.clone()),
+            user_color: get_random_color(),
+            row: None,
+            col: None,
+            value: Some(
+                serde_json::json!({ "action": action, "worksheets": worksheets }).to_string(),
+            ),
+            worksheet_index,
+            timestamp: Utc::now(),
+        };
+        let _ = tx.send(msg);
+    }
+}
+
 pub async fn mark_mention_read(user_id: &str, mention_id: &str) {
     let mut mentions = get_mentions().write().await;
     if let Some(user_mentions) = mentions.get_mut(user_id) {
@@ -450,3 +700,60 @@ fn get_random_color() -> String {
     let idx = rand::rng().random_range(0..colors.len());
     colors[idx].to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::create_new_spreadsheet;
+
+    fn message(user_id: &str) -> CollabMessage {
+        CollabMessage {
+            msg_type: "cell_update".to_string(),
+            sheet_id: "s1".to_string(),
+            user_id: user_id.to_string(),
+            user_name: user_id.to_string(),
+            user_color: String::new(),
+            row: Some(0),
+            col: Some(0),
+            value: Some("1".to_string()),
+            worksheet_index: Some(0),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lagged_receiver_is_resynced_with_snapshot() {
+        let (tx, mut rx) = broadcast::channel(2);
+        for _ in 0..5 {
+            tx.send(message("alice")).unwrap();
+        }
+
+        let missed = match route_broadcast(rx.recv().await, "bob") {
+            Outgoing::Resync(missed) => missed,
+            _ => panic!("expected a resync"),
+        };
+        assert_eq!(missed, 3);
+        // After the resync the receiver continues with the newest messages.
+        assert!(matches!(
+            route_broadcast(rx.recv().await, "bob"),
+            Outgoing::Forward(_)
+        ));
+        assert!(matches!(
+            route_broadcast(rx.recv().await, "alice"),
+            Outgoing::Skip
+        ));
+
+        let sheet = create_new_spreadsheet();
+        let snapshot = snapshot_message(&sheet).unwrap();
+        assert_eq!(snapshot.msg_type, "resync");
+        let restored: Spreadsheet = serde_json::from_str(&snapshot.value.unwrap()).unwrap();
+        assert_eq!(restored.id, sheet.id);
+        assert_eq!(restored.worksheets.len(), sheet.worksheets.len());
+
+        drop(tx);
+        assert!(matches!(
+            route_broadcast(rx.recv().await, "bob"),
+            Outgoing::Close
+        ));
+    }
+}
@@ -0,0 +1,319 @@
+//! Threaded cell comments.
+//!
+//! Each cell holds a thread: a list of top-level comments, each with its own replies and
+//! resolved flag. Sheets saved before threads stored one comment per cell, and older ones
+//! a plain `CellData.note`; both are read back as one-comment threads.
+
+use crate::sheet::error::SheetError;
+use crate::sheet::types::{CellComment, CellData, CommentReply, Spreadsheet, Worksheet};
+use chrono::Utc;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub type CommentThreads = HashMap<String, Vec<CellComment>>;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredThread {
+    Thread(Vec<CellComment>),
+    Single(Box<CellComment>),
+}
+
+/// Reads `Worksheet.comments`, accepting the former one-comment-per-cell layout.
+pub fn deserialize_threads<'de, D>(deserializer: D) -> Result<Option<CommentThreads>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let stored: Option<HashMap<String, StoredThread>> = Option::deserialize(deserializer)?;
+    Ok(stored.map(|threads| {
+        threads
+            .into_iter()
+            .map(|(key, thread)| match thread {
+                StoredThread::Thread(comments) => (key, comments),
+                StoredThread::Single(comment) => (key, vec![*comment]),
+            })
+            .collect()
+    }))
+}
+
+/// Author of a comment as shown to collaborators.
+#[derive(Debug, Clone)]
+pub struct CommentAuthor {
+    pub id: String,
+    pub name: String,
+}
+
+fn cell_key(row: u32, col: u32) -> String {
+    format!("{row},{col}")
+}
+
+fn mark_cell(worksheet: &mut Worksheet, key: &str) {
+    let has_comment = worksheet
+        .comments
+        .as_ref()
+        .and_then(|threads| threads.get(key))
+        .is_some_and(|thread| !thread.is_empty());
+    if !has_comment {
+        if let Some(cell) = worksheet.data.get_mut(key) {
+            cell.has_comment = None;
+        }
+        return;
+    }
+    let cell = worksheet
+        .data
+        .entry(key.to_string())
+        .or_insert_with(|| CellData {
+            value: None,
+            formula: None,
+            style: None,
+            format: None,
+            note: None,
+            locked: None,
+            has_comment: None,
+            array_formula_id: None,
+        });
+    cell.has_comment = Some(true);
+}
+
+fn find_comment<'a>(
+    worksheet: &'a mut Worksheet,
+    row: u32,
+    col: u32,
+    comment_id: &str,
+) -> Result<&'a mut CellComment, SheetError> {
+    worksheet
+        .comments
+        .as_mut()
+        .and_then(|threads| threads.get_mut(&cell_key(row, col)))
+        .and_then(|thread| thread.iter_mut().find(|c| c.id == comment_id))
+        .ok_or_else(|| SheetError::CommentNotFound(comment_id.to_string()))
+}
+
+/// Starts a new comment in the cell's thread.
+pub fn add_comment(
+    worksheet: &mut Worksheet,
+    row: u32,
+    col: u32,
+    author: &CommentAuthor,
+    content: String,
+) -> CellComment {
+    let now = Utc::now();
+    let comment = CellComment {
+        id: Uuid::new_v4().to_string(),
+        author_id: author.id.clone(),
+        author_name: author.name.clone(),
+        content,
+        created_at: now,
+        updated_at: now,
+        replies: vec![],
+        resolved: false,
+    };
+    let key = cell_key(row, col);
+    worksheet
+        .comments
+        .get_or_insert_with(HashMap::new)
+        .entry(key.clone())
+        .or_default()
+        .push(comment.clone());
+    mark_cell(worksheet, &key);
+    comment
+}
+
+/// Appends a reply and returns the updated comment.
+pub fn reply_to_comment(
+    worksheet: &mut Worksheet,
+    row: u32,
+    col: u32,
+    comment_id: &str,
+    author: &CommentAuthor,
+    content: String,
+) -> Result<CellComment, SheetError> {
+    let comment = find_comment(worksheet, row, col, comment_id)?;
+    let now = Utc::now();
+    comment.replies.push(CommentReply {
+        id: Uuid::new_v4().to_string(),
+        author_id: author.id.clone(),
+        author_name: author.name.clone(),
+        content,
+        created_at: now,
+    });
+    comment.updated_at = now;
+    Ok(comment.clone())
+}
+
+pub fn resolve_comment(
+    worksheet: &mut Worksheet,
+    row: u32,
+    col: u32,
+    comment_id: &str,
+    resolved: bool,
+) -> Result<CellComment, SheetError> {
+    let comment = find_comment(worksheet, row, col, comment_id)?;
+    comment.resolved = resolved;
+    comment.updated_at = Utc::now();
+    Ok(comment.clone())
+}
+
+pub fn delete_comment(
+    worksheet: &mut Worksheet,
+    row: u32,
+    col: u32,
+    comment_id: &str,
+) -> Result<CellComment, SheetError> {
+    let key = cell_key(row, col);
+    let threads = worksheet
+        .comments
+        .as_mut()
+        .ok_or_else(|| SheetError::CommentNotFound(comment_id.to_string()))?;
+    let thread = threads
+        .get_mut(&key)
+        .ok_or_else(|| SheetError::CommentNotFound(comment_id.to_string()))?;
+    let index = thread
+        .iter()
+        .position(|c| c.id == comment_id)
+        .ok_or_else(|| SheetError::CommentNotFound(comment_id.to_string()))?;
+    let removed = thread.remove(index);
+    if thread.is_empty() {
+        threads.remove(&key);
+    }
+    mark_cell(worksheet, &key);
+    Ok(removed)
+}
+
+/// Moves legacy single-string notes into one-comment threads attributed to the sheet
+/// owner. Returns whether anything changed.
+///
+/// Runs on every load until the sheet is saved again, so a migrated comment's id is
+/// derived from its sheet, worksheet and cell: replies and resolutions made before the
+/// next save still find it.
+pub fn migrate_legacy_notes(sheet: &mut Spreadsheet) -> bool {
+    let mut migrated = false;
+    for worksheet in &mut sheet.worksheets {
+        let notes: Vec<(String, String)> = worksheet
+            .data
+            .iter_mut()
+            .filter_map(|(key, cell)| Some((key.clone(), cell.note.take()?)))
+            .filter(|(_, note)| !note.trim().is_empty())
+            .collect();
+        for (key, note) in notes {
+            let now = sheet.updated_at;
+            let id = Uuid::new_v5(
+                &Uuid::NAMESPACE_OID,
+                format!("{}/{}/{}/note", sheet.id, worksheet.name, key).as_bytes(),
+            );
+            worksheet
+                .comments
+                .get_or_insert_with(HashMap::new)
+                .entry(key.clone())
+                .or_default()
+                .insert(
+                    0,
+                    CellComment {
+                        id: id.to_string(),
+                        author_id: sheet.owner_id.clone(),
+                        author_name: sheet.owner_id.clone(),
+                        content: note,
+                        created_at: now,
+                        updated_at: now,
+                        replies: vec![],
+                        resolved: false,
+                    },
+                );
+            mark_cell(worksheet, &key);
+            migrated = true;
+        }
+    }
+    migrated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::create_new_spreadsheet;
+
+    fn author(name: &str) -> CommentAuthor {
+        CommentAuthor {
+            id: format!("{name}-id"),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_add_reply_and_resolve_comment() {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+
+        let first = add_comment(&mut worksheet, 1, 2, &author("ana"), "Check this".into());
+        add_comment(&mut worksheet, 1, 2, &author("bo"), "Source?".into());
+        let thread = &worksheet.comments.as_ref().unwrap()["1,2"];
+        assert_eq!(thread.len(), 2);
+        assert_eq!(thread[0].author_name, "ana");
+        assert_eq!(worksheet.data["1,2"].has_comment, Some(true));
+
+        let replied = reply_to_comment(
+            &mut worksheet,
+            1,
+            2,
+            &first.id,
+            &author("bo"),
+            "Done".into(),
+        )
+        .unwrap();
+        assert_eq!(replied.replies.len(), 1);
+        assert_eq!(replied.replies[0].author_id, "bo-id");
+
+        let resolved = resolve_comment(&mut worksheet, 1, 2, &first.id, true).unwrap();
+        assert!(resolved.resolved);
+        assert!(!worksheet.comments.as_ref().unwrap()["1,2"][1].resolved);
+
+        let err = resolve_comment(&mut worksheet, 0, 0, &first.id, true).unwrap_err();
+        assert_eq!(err.code(), "COMMENT_NOT_FOUND");
+    }
+
+    #[test]
+    fn test_delete_last_comment_clears_marker() {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+        let comment = add_comment(&mut worksheet, 0, 0, &author("ana"), "Hi".into());
+
+        delete_comment(&mut worksheet, 0, 0, &comment.id).unwrap();
+        assert!(worksheet.comments.as_ref().unwrap().is_empty());
+        assert_eq!(worksheet.data["0,0"].has_comment, None);
+    }
+
+    #[test]
+    fn test_legacy_notes_and_single_comments_load_as_threads() {
+        let mut sheet = create_new_spreadsheet();
+        let mut worksheet = serde_json::to_value(&sheet.worksheets[0]).unwrap();
+        worksheet["data"]["0,0"] = serde_json::json!({ "value": "42", "note": "old note" });
+        worksheet["comments"] = serde_json::json!({
+            "3,1": {
+                "id": "c1",
+                "author_id": "u1",
+                "author_name": "Ana",
+                "content": "single",
+                "created_at": "2024-01-01T00:00:00Z",
+                "updated_at": "2024-01-01T00:00:00Z"
+            }
+        });
+        sheet.worksheets[0] = serde_json::from_value(worksheet).unwrap();
+        let unsaved = sheet.clone();
+
+        assert!(migrate_legacy_notes(&mut sheet));
+        let worksheet = &sheet.worksheets[0];
+        let threads = worksheet.comments.as_ref().unwrap();
+        assert_eq!(threads["3,1"][0].content, "single");
+        assert_eq!(threads["0,0"][0].content, "old note");
+        assert_eq!(worksheet.data["0,0"].note, None);
+        assert_eq!(worksheet.data["0,0"].has_comment, Some(true));
+        let migrated_id = threads["0,0"][0].id.clone();
+        assert!(!migrate_legacy_notes(&mut sheet));
+
+        // Loading the unsaved sheet again gives the migrated note the same id.
+        let mut reloaded = unsaved;
+        assert!(migrate_legacy_notes(&mut reloaded));
+        assert_eq!(
+            reloaded.worksheets[0].comments.as_ref().unwrap()["0,0"][0].id,
+            migrated_id
+        );
+    }
+}
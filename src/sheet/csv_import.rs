@@ -0,0 +1,287 @@
+//! Decoding and delimiter detection for delimited text imports (CSV, TSV, ...).
+//!
+//! Files are decoded to UTF-8 before parsing: a byte order mark wins, then valid UTF-8,
+//! then Windows-1252 (a superset of Latin-1 for printable text), which covers the
+//! spreadsheets exported by most European desktop software. The delimiter is sniffed
+//! from the first lines unless the caller provides one.
+
+use serde::Deserialize;
+
+const DELIMITER_CANDIDATES: [u8; 4] = [b',', b';', b'\t', b'|'];
+const SNIFF_LINES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+impl TextEncoding {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Self::Utf8),
+            "utf-16le" | "utf-16" => Ok(Self::Utf16Le),
+            "utf-16be" => Ok(Self::Utf16Be),
+            "windows-1252" | "cp1252" | "latin1" | "latin-1" | "iso-8859-1" => {
+                Ok(Self::Windows1252)
+            }
+            other => Err(format!("Unsupported encoding: {other}")),
+        }
+    }
+}
+
+/// Caller overrides from the `delimiter`, `encoding` and `header` query parameters.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CsvImportQuery {
+    pub delimiter: Option<String>,
+    pub encoding: Option<String>,
+    pub header: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CsvOptions {
+    pub delimiter: Option<u8>,
+    pub encoding: Option<TextEncoding>,
+    /// Marks the first row of each imported worksheet as its header.
+    pub header: bool,
+}
+
+impl CsvOptions {
+    pub fn from_query(query: &CsvImportQuery) -> Result<Self, String> {
+        let delimiter = match query.delimiter.as_deref() {
+            None | Some("") => None,
+            Some("tab" | "\\t" | "\t") => Some(b'\t'),
+            Some("comma") => Some(b','),
+            Some("semicolon") => Some(b';'),
+            Some("pipe") => Some(b'|'),
+            Some(d) if d.len() == 1 && DELIMITER_CANDIDATES.contains(&d.as_bytes()[0]) => {
+                Some(d.as_bytes()[0])
+            }
+            Some(d) => return Err(format!("Unsupported delimiter: {d}")),
+        };
+        let encoding = query
+            .encoding
+            .as_deref()
+            .filter(|e| !e.is_empty())
+            .map(TextEncoding::parse)
+            .transpose()?;
+        Ok(Self {
+            delimiter,
+            encoding,
+            header: query.header.unwrap_or(false),
+        })
+    }
+}
+
+/// Decodes `bytes` to UTF-8, returning the text and the encoding that was used.
+pub fn decode(bytes: &[u8], encoding: Option<TextEncoding>) -> (String, TextEncoding) {
+    let (encoding, body) = match (encoding, bytes) {
+        (None | Some(TextEncoding::Utf8), [0xEF, 0xBB, 0xBF, rest @ ..]) => {
+            (TextEncoding::Utf8, rest)
+        }
+        (None | Some(TextEncoding::Utf16Le), [0xFF, 0xFE, rest @ ..]) => {
+            (TextEncoding::Utf16Le, rest)
+        }
+        (None | Some(TextEncoding::Utf16Be), [0xFE, 0xFF, rest @ ..]) => {
+            (TextEncoding::Utf16Be, rest)
+        }
+        (Some(encoding), _) => (encoding, bytes),
+        (None, _) if std::str::from_utf8(bytes).is_ok() => (TextEncoding::Utf8, bytes),
+        (None, _) => (TextEncoding::Windows1252, bytes),
+    };
+
+    let text = match encoding {
+        TextEncoding::Utf8 => String::from_utf8_lossy(body).into_owned(),
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+            let units: Vec<u16> = body
+                .chunks_exact(2)
+                .map(|pair| match encoding {
+                    TextEncoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        TextEncoding::Windows1252 => body.iter().map(|&b| windows_1252_char(b)).collect(),
+    };
+    (text, encoding)
+}
+
+fn windows_1252_char(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}',
+        '\u{2021}', '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{FFFD}',
+        '\u{017D}', '\u{FFFD}', '\u{FFFD}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}',
+        '\u{2022}', '\u{2013}', '\u{2014}', '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}',
+        '\u{0153}', '\u{FFFD}', '\u{017E}', '\u{0178}',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[(byte - 0x80) as usize],
+        _ => char::from(byte),
+    }
+}
+
+fn count_outside_quotes(line: &str, delimiter: u8) -> usize {
+    let mut in_quotes = false;
+    let mut count = 0;
+    for byte in line.bytes() {
+        if byte == b'"' {
+            in_quotes = !in_quotes;
+        } else if byte == delimiter && !in_quotes {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// Picks the candidate delimiter that splits the first lines into the most consistent,
+/// non-trivial number of fields. Returns `fallback` when no candidate appears.
+pub fn sniff_delimiter(text: &str, fallback: u8) -> u8 {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+    if lines.is_empty() {
+        return fallback;
+    }
+
+    let mut best: Option<(u8, usize, usize)> = None;
+    for delimiter in DELIMITER_CANDIDATES {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|l| count_outside_quotes(l, delimiter))
+            .collect();
+        let first = counts[0];
+        if first == 0 {
+            continue;
+        }
+        let consistent = counts.iter().filter(|&&c| c == first).count();
+        let better = match best {
+            None => true,
+            Some((_, best_consistent, best_fields)) => {
+                (consistent, first) > (best_consistent, best_fields)
+            }
+        };
+        if better {
+            best = Some((delimiter, consistent, first));
+        }
+    }
+    best.map_or(fallback, |(delimiter, _, _)| delimiter)
+}
+
+/// Splits delimited text into records, honouring quoted fields (which may contain the
+/// delimiter, doubled quotes and line breaks). Unquoted fields are trimmed.
+pub fn parse_records(text: &str, delimiter: u8) -> Vec<Vec<String>> {
+    let delimiter = char::from(delimiter);
+    let mut records = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    let finish_field = |field: &mut String, quoted: &mut bool, record: &mut Vec<String>| {
+        let value = if *quoted {
+            std::mem::take(field)
+        } else {
+            let trimmed = field.trim().to_string();
+            field.clear();
+            trimmed
+        };
+        record.push(value);
+        *quoted = false;
+    };
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                in_quotes = true;
+                quoted = true;
+            }
+            c if c == delimiter => finish_field(&mut field, &mut quoted, &mut record),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                finish_field(&mut field, &mut quoted, &mut record);
+                records.push(std::mem::take(&mut record));
+            }
+            _ if quoted => {}
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || quoted || !record.is_empty() {
+        finish_field(&mut field, &mut quoted, &mut record);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semicolon_latin1_file() {
+        // "Produto;Preço;Descrição" / "Café;3,50;\"Forte; amargo\"" in Latin-1.
+        let mut bytes = b"Produto;Pre\xe7o;Descri\xe7\xe3o\r\n".to_vec();
+        bytes.extend_from_slice(b"Caf\xe9;3,50;\"Forte; amargo\"\r\n");
+
+        let (text, encoding) = decode(&bytes, None);
+        assert_eq!(encoding, TextEncoding::Windows1252);
+        assert_eq!(sniff_delimiter(&text, b','), b';');
+
+        let records = parse_records(&text, b';');
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], vec!["Produto", "Preço", "Descrição"]);
+        assert_eq!(records[1], vec!["Café", "3,50", "Forte; amargo"]);
+    }
+
+    #[test]
+    fn test_utf8_bom_file() {
+        let bytes = "\u{feff}name,city\nJosé,\"São Paulo\"\n".as_bytes();
+
+        let (text, encoding) = decode(bytes, None);
+        assert_eq!(encoding, TextEncoding::Utf8);
+        assert!(!text.starts_with('\u{feff}'));
+
+        let records = parse_records(&text, sniff_delimiter(&text, b';'));
+        assert_eq!(records[0], vec!["name", "city"]);
+        assert_eq!(records[1], vec!["José", "São Paulo"]);
+    }
+
+    #[test]
+    fn test_overrides_and_quoting() {
+        let options = CsvOptions::from_query(&CsvImportQuery {
+            delimiter: Some("pipe".to_string()),
+            encoding: Some("latin1".to_string()),
+            header: None,
+        })
+        .unwrap();
+        assert_eq!(options.delimiter, Some(b'|'));
+        assert_eq!(options.encoding, Some(TextEncoding::Windows1252));
+        assert!(CsvOptions::from_query(&CsvImportQuery {
+            delimiter: Some("::".to_string()),
+            encoding: None,
+            header: None,
+        })
+        .is_err());
+
+        let records = parse_records("a|\"b \"\"q\"\"\nline\"|c", b'|');
+        assert_eq!(records, vec![vec!["a", "b \"q\"\nline", "c"]]);
+        assert_eq!(sniff_delimiter("single column\nvalues", b'\t'), b'\t');
+    }
+}
@@ -0,0 +1,213 @@
+//! What a formula reads, found without evaluating it.
+//!
+//! The formula is scanned once, skipping string literals. A name followed by `(` is a
+//! function; a cell reference (`B7`, `$B$7`), optionally followed by `:` and a second
+//! one, is a cell or range, parsed with [`parse_cell_ref`] and [`parse_range`]. A
+//! `Sheet2!` or `'Q1 Sales'!` prefix qualifies the reference that follows it. Any other
+//! name is looked up among the sheet's named ranges.
+
+use crate::sheet::export::column_to_letter;
+use crate::sheet::formulas::{parse_cell_ref, parse_range};
+use crate::sheet::types::FormulaDependencies;
+use crate::sheet::worksheets::{quote_worksheet_name, quoted_reference};
+use std::collections::BTreeSet;
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '$'
+}
+
+/// `B7`, `$B$7` or `b7`: one to three letters and a row number, each optionally `$`-anchored.
+fn is_cell_ref(token: &str) -> bool {
+    let token = token.strip_prefix('$').unwrap_or(token);
+    let letters = token
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .count();
+    let digits = token[letters..]
+        .strip_prefix('$')
+        .unwrap_or(&token[letters..]);
+    (1..=3).contains(&letters)
+        && !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && !digits.chars().all(|c| c == '0')
+}
+
+fn is_column_ref(token: &str) -> bool {
+    let token = token.strip_prefix('$').unwrap_or(token);
+    (1..=3).contains(&token.len()) && token.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn cell_label((row, col): (u32, u32)) -> String {
+    format!("{}{}", column_to_letter(col), row + 1)
+}
+
+fn take_token(chars: &[char], start: usize) -> (String, usize) {
+    let end = chars[start..]
+        .iter()
+        .position(|&c| !is_name_char(c))
+        .map_or(chars.len(), |p| start + p);
+    (chars[start..end].iter().collect(), end)
+}
+
+#[derive(Default)]
+struct Collected {
+    cells: BTreeSet<String>,
+    ranges: BTreeSet<String>,
+    named_ranges: BTreeSet<String>,
+    functions: BTreeSet<String>,
+    unknown_names: BTreeSet<String>,
+}
+
+impl Collected {
+    /// Records the reference starting at `start` (`A1`, `A1:B2` or `A:C`), qualified by
+    /// `worksheet` when given, and returns the index after it. `None` if there is none.
+    fn reference(
+        &mut self,
+        chars: &[char],
+        start: usize,
+        worksheet: Option<&str>,
+    ) -> Option<usize> {
+        let prefix = worksheet.map_or(String::new(), |ws| format!("{}!", quote_worksheet_name(ws)));
+        let (first, mut end) = take_token(chars, start);
+        let second = (chars.get(end) == Some(&':')).then(|| take_token(chars, end + 1));
+
+        match second {
+            Some((second, second_end)) if is_cell_ref(&first) && is_cell_ref(&second) => {
+                let (a, b) = parse_range(&format!(
+                    "{}:{}",
+                    first.replace('$', ""),
+                    second.replace('$', "")
+                ))?;
+                let (top_left, bottom_right) =
+                    ((a.0.min(b.0), a.1.min(b.1)), (a.0.max(b.0), a.1.max(b.1)));
+                self.ranges.insert(format!(
+                    "{prefix}{}:{}",
+                    cell_label(top_left),
+                    cell_label(bottom_right)
+                ));
+                end = second_end;
+            }
+            Some((second, second_end)) if is_column_ref(&first) && is_column_ref(&second) => {
+                self.ranges.insert(format!(
+                    "{prefix}{}:{}",
+                    first.replace('$', "").to_uppercase(),
+                    second.replace('$', "").to_uppercase()
+                ));
+                end = second_end;
+            }
+            _ if is_cell_ref(&first) => {
+                let cell = parse_cell_ref(&first.replace('$', ""))?;
+                self.cells.insert(format!("{prefix}{}", cell_label(cell)));
+            }
+            _ => return None,
+        }
+        Some(end)
+    }
+}
+
+/// The cells, ranges, named ranges and functions `formula` refers to. `named_ranges` are
+/// the names defined for the worksheet the formula belongs to; other names that are not
+/// functions, cells or booleans are reported in `unknown_names`.
+pub fn formula_dependencies(formula: &str, named_ranges: &[&str]) -> FormulaDependencies {
+    let chars: Vec<char> = formula
+        .strip_prefix('=')
+        .unwrap_or(formula)
+        .chars()
+        .collect();
+    let mut found = Collected::default();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '"' {
+            i = chars[i + 1..]
+                .iter()
+                .position(|&c| c == '"')
+                .map_or(chars.len(), |p| i + p + 2);
+            continue;
+        }
+        if c == '\'' {
+            if let Some((worksheet, end)) = quoted_reference(&chars, i) {
+                i = found
+                    .reference(&chars, end, Some(worksheet.as_str()))
+                    .unwrap_or(end);
+                continue;
+            }
+            i += 1;
+            continue;
+        }
+        if !is_name_char(c) || (i > 0 && (is_name_char(chars[i - 1]) || chars[i - 1] == '#')) {
+            i += 1;
+            continue;
+        }
+
+        let (token, end) = take_token(&chars, i);
+        let next = chars[end..].iter().find(|c| !c.is_whitespace());
+        if chars.get(end) == Some(&'!') {
+            i = found
+                .reference(&chars, end + 1, Some(token.as_str()))
+                .unwrap_or(end + 1);
+        } else if next == Some(&'(') {
+            found.functions.insert(token.to_uppercase());
+            i = end;
+        } else if let Some(after) = found.reference(&chars, i, None) {
+            i = after;
+        } else {
+            if let Some(name) = named_ranges.iter().find(|n| n.eq_ignore_ascii_case(&token)) {
+                found.named_ranges.insert(name.to_string());
+            } else if !token.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+                && !token.eq_ignore_ascii_case("TRUE")
+                && !token.eq_ignore_ascii_case("FALSE")
+            {
+                found.unknown_names.insert(token);
+            }
+            i = end;
+        }
+    }
+
+    FormulaDependencies {
+        cells: found.cells.into_iter().collect(),
+        ranges: found.ranges.into_iter().collect(),
+        named_ranges: found.named_ranges.into_iter().collect(),
+        functions: found.functions.into_iter().collect(),
+        unknown_names: found.unknown_names.into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_functions_and_ranges() {
+        let deps = formula_dependencies(
+            "=SUM(A1:B2, MAX(c3, $D$4:E5), IF(F1>0, Sheet2!G7, 'Q1 Sales'!H3:H1))",
+            &[],
+        );
+        assert_eq!(deps.functions, vec!["IF", "MAX", "SUM"]);
+        assert_eq!(deps.cells, vec!["C3", "F1", "Sheet2!G7"]);
+        assert_eq!(deps.ranges, vec!["'Q1 Sales'!H1:H3", "A1:B2", "D4:E5"]);
+        assert!(deps.named_ranges.is_empty());
+        assert!(deps.unknown_names.is_empty());
+    }
+
+    #[test]
+    fn test_named_ranges_strings_and_unknown_names() {
+        let deps = formula_dependencies(
+            "=tax_rate * SUM(Sales) & \"A1 is not read\" & VLOOKUP(key, A:C, 2, FALSE) + 1.5",
+            &["Sales", "Tax_Rate"],
+        );
+        assert_eq!(deps.named_ranges, vec!["Sales", "Tax_Rate"]);
+        assert_eq!(deps.functions, vec!["SUM", "VLOOKUP"]);
+        assert_eq!(deps.ranges, vec!["A:C"]);
+        assert!(deps.cells.is_empty());
+        assert_eq!(deps.unknown_names, vec!["key"]);
+    }
+
+    #[test]
+    fn test_plain_values_have_no_dependencies() {
+        let deps = formula_dependencies("=1+2*3", &[]);
+        assert!(deps.cells.is_empty() && deps.ranges.is_empty() && deps.functions.is_empty());
+        assert!(formula_dependencies("=TODAY()", &[]).cells.is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::core::shared::api_error::ApiError;
+use crate::security::upload_policy::UploadRejection;
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+#[derive(Debug, Clone)]
+pub enum SheetError {
+    SheetNotFound(String),
+    InvalidWorksheet,
+    DriveUnavailable,
+    FileNotFound(String),
+    UnsupportedFormat(String),
+    InvalidRequest(String),
+    ImportFailed(String),
+    PasswordRequired,
+    InvalidPassword,
+    StorageFailed(String),
+    ExportFailed(String),
+    CellProtected(String),
+    PermissionDenied(String),
+    CommentNotFound(String),
+    QuotaExceeded(String),
+    QueryNotFound(String),
+    SnapshotNotFound(String),
+    InvalidSignature(String),
+    PayloadTooLarge(usize),
+    RateLimited,
+    UploadRejected(UploadRejection),
+}
+
+impl SheetError {
+    /// Stable, machine-readable code. Never change an existing value; clients match on it.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::SheetNotFound(_) => "SHEET_NOT_FOUND",
+            Self::InvalidWorksheet => "INVALID_WORKSHEET",
+            Self::DriveUnavailable => "DRIVE_UNAVAILABLE",
+            Self::FileNotFound(_) => "FILE_NOT_FOUND",
+            Self::UnsupportedFormat(_) => "UNSUPPORTED_FORMAT",
+            Self::InvalidRequest(_) => "INVALID_REQUEST",
+            Self::ImportFailed(_) => "IMPORT_FAILED",
+            Self::PasswordRequired => "PASSWORD_REQUIRED",
+            Self::InvalidPassword => "INVALID_PASSWORD",
+            Self::StorageFailed(_) => "STORAGE_FAILED",
+            Self::ExportFailed(_) => "EXPORT_FAILED",
+            Self::CellProtected(_) => "CELL_PROTECTED",
+            Self::PermissionDenied(_) => "PERMISSION_DENIED",
+            Self::CommentNotFound(_) => "COMMENT_NOT_FOUND",
+            Self::QuotaExceeded(_) => "QUOTA_EXCEEDED",
+            Self::QueryNotFound(_) => "QUERY_NOT_FOUND",
+            Self::SnapshotNotFound(_) => "SNAPSHOT_NOT_FOUND",
+            Self::InvalidSignature(_) => "INVALID_SIGNATURE",
+            Self::PayloadTooLarge(_) => "PAYLOAD_TOO_LARGE",
+            Self::RateLimited => "RATE_LIMITED",
+            Self::UploadRejected(rejection) => rejection.code(),
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::SheetNotFound(_)
+            | Self::FileNotFound(_)
+            | Self::CommentNotFound(_)
+            | Self::QueryNotFound(_)
+            | Self::SnapshotNotFound(_) => StatusCode::NOT_FOUND,
+            Self::InvalidWorksheet
+            | Self::UnsupportedFormat(_)
+            | Self::InvalidRequest(_)
+            | Self::ImportFailed(_) => StatusCode::BAD_REQUEST,
+            Self::PasswordRequired | Self::InvalidPassword | Self::InvalidSignature(_) => {
+                StatusCode::UNAUTHORIZED
+            }
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Self::CellProtected(_) | Self::PermissionDenied(_) => StatusCode::FORBIDDEN,
+            Self::DriveUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::QuotaExceeded(_) => StatusCode::INSUFFICIENT_STORAGE,
+            Self::StorageFailed(_) | Self::ExportFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::UploadRejected(rejection) => rejection.status(),
+        }
+    }
+}
+
+impl std::fmt::Display for SheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SheetNotFound(e) => write!(f, "{e}"),
+            Self::InvalidWorksheet => write!(f, "Invalid worksheet index"),
+            Self::DriveUnavailable => write!(f, "Drive not available"),
+            Self::FileNotFound(e) => write!(f, "File not found: {e}"),
+            Self::UnsupportedFormat(ext) if ext.is_empty() => write!(f, "Unsupported format"),
+            Self::UnsupportedFormat(ext) => write!(f, "Unsupported format: .{ext}"),
+            Self::InvalidRequest(e) => write!(f, "{e}"),
+            Self::ImportFailed(e) => write!(f, "{e}"),
+            Self::PasswordRequired => write!(f, "Password required"),
+            Self::InvalidPassword => write!(f, "Invalid password"),
+            Self::StorageFailed(e) => write!(f, "{e}"),
+            Self::ExportFailed(e) => write!(f, "{e}"),
+            Self::CellProtected(cell) => write!(f, "Cell {cell} is protected"),
+            Self::PermissionDenied(e) => write!(f, "{e}"),
+            Self::CommentNotFound(id) => write!(f, "Comment not found: {id}"),
+            Self::QuotaExceeded(e) => write!(f, "{e}"),
+            Self::QueryNotFound(id) => write!(f, "Saved query not found: {id}"),
+            Self::SnapshotNotFound(id) => write!(f, "Snapshot not found: {id}"),
+            Self::InvalidSignature(e) => write!(f, "{e}"),
+            Self::PayloadTooLarge(max) => write!(f, "Payload exceeds {max} bytes"),
+            Self::RateLimited => write!(f, "Too many pushes to this sheet, retry shortly"),
+            Self::UploadRejected(rejection) => write!(f, "{rejection}"),
+        }
+    }
+}
+
+impl std::error::Error for SheetError {}
+
+impl From<SheetError> for ApiError {
+    fn from(err: SheetError) -> Self {
+        ApiError::new(err.status(), err.code(), err.to_string())
+    }
+}
+
+impl From<UploadRejection> for SheetError {
+    fn from(rejection: UploadRejection) -> Self {
+        Self::UploadRejected(rejection)
+    }
+}
+
+impl IntoResponse for SheetError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_mapping_matches_previous_behaviour() {
+        assert_eq!(SheetError::SheetNotFound("x".into()).status(), StatusCode::NOT_FOUND);
+        assert_eq!(SheetError::InvalidWorksheet.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(SheetError::DriveUnavailable.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(SheetError::InvalidPassword.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(SheetError::StorageFailed("x".into()).status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(SheetError::InvalidWorksheet.to_string(), "Invalid worksheet index");
+        assert_eq!(SheetError::UnsupportedFormat("pdf".into()).to_string(), "Unsupported format: .pdf");
+        assert_eq!(SheetError::UnsupportedFormat(String::new()).to_string(), "Unsupported format");
+        assert_eq!(SheetError::InvalidWorksheet.code(), "INVALID_WORKSHEET");
+        assert_eq!(SheetError::CellProtected("B3".into()).to_string(), "Cell B3 is protected");
+        assert_eq!(SheetError::CellProtected("B3".into()).status(), StatusCode::FORBIDDEN);
+    }
+}
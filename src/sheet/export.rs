@@ -1,9 +1,183 @@
 use base64::Engine;
-use crate::sheet::types::{CellStyle, Spreadsheet};
-use rust_xlsxwriter::{Color, Format, FormatAlign, Workbook};
+use crate::sheet::error::SheetError;
+use crate::sheet::formulas::parse_range;
+use crate::sheet::locale::SheetLocale;
+use crate::sheet::types::{CellStyle, MergedCell, Spreadsheet, Worksheet};
+use chrono::{Datelike, NaiveDate};
+use rust_xlsxwriter::{Color, ExcelDateTime, Format, FormatAlign, Workbook};
+use std::collections::HashMap;
+
+/// Copies the cells of `worksheet` inside the 0-based `start`..=`end` corners, moved so the
+/// range starts at A1. Formulas are dropped in favor of their values, since their references
+/// would no longer point at the same cells.
+fn cut_range(worksheet: &Worksheet, start: (u32, u32), end: (u32, u32)) -> Worksheet {
+    let rows = start.0..=end.0;
+    let cols = start.1..=end.1;
+    let data = worksheet
+        .data
+        .iter()
+        .filter_map(|(key, cell)| {
+            let (row, col) = key.split_once(',')?;
+            let (row, col) = (row.parse::<u32>().ok()?, col.parse::<u32>().ok()?);
+            if !rows.contains(&row) || !cols.contains(&col) {
+                return None;
+            }
+            let mut cell = cell.clone();
+            cell.formula = None;
+            cell.array_formula_id = None;
+            Some((format!("{},{}", row - start.0, col - start.1), cell))
+        })
+        .collect();
+    let shift = |sizes: &HashMap<u32, u32>, within: &std::ops::RangeInclusive<u32>| {
+        sizes
+            .iter()
+            .filter(|(index, _)| within.contains(index))
+            .map(|(index, size)| (index - within.start(), *size))
+            .collect::<HashMap<u32, u32>>()
+    };
+    let merged_cells: Vec<MergedCell> = worksheet
+        .merged_cells
+        .iter()
+        .flatten()
+        .filter(|m| {
+            rows.contains(&m.start_row)
+                && rows.contains(&m.end_row)
+                && cols.contains(&m.start_col)
+                && cols.contains(&m.end_col)
+        })
+        .map(|m| MergedCell {
+            start_row: m.start_row - start.0,
+            start_col: m.start_col - start.1,
+            end_row: m.end_row - start.0,
+            end_col: m.end_col - start.1,
+        })
+        .collect();
+
+    Worksheet {
+        name: worksheet.name.clone(),
+        data,
+        column_widths: worksheet.column_widths.as_ref().map(|w| shift(w, &cols)),
+        row_heights: worksheet.row_heights.as_ref().map(|h| shift(h, &rows)),
+        frozen_rows: None,
+        frozen_cols: None,
+        merged_cells: (!merged_cells.is_empty()).then_some(merged_cells),
+        filters: None,
+        hidden_rows: None,
+        validations: None,
+        conditional_formats: None,
+        charts: None,
+        comments: None,
+        protection: None,
+        array_formulas: None,
+        protected_ranges: None,
+        has_header: None,
+    }
+}
+
+/// The part of `sheet` an export covers: everything, one worksheet, or a range such as
+/// `"B2:D20"` of one worksheet. A range without an index refers to the first worksheet.
+pub fn select_export(
+    sheet: &Spreadsheet,
+    worksheet_index: Option<usize>,
+    range: Option<&str>,
+) -> Result<Spreadsheet, SheetError> {
+    let range = range.map(str::trim).filter(|r| !r.is_empty());
+    if worksheet_index.is_none() && range.is_none() {
+        return Ok(sheet.clone());
+    }
+
+    let worksheet = sheet
+        .worksheets
+        .get(worksheet_index.unwrap_or(0))
+        .ok_or(SheetError::InvalidWorksheet)?;
+    let worksheet = match range {
+        None => worksheet.clone(),
+        Some(range) => {
+            let ((r1, c1), (r2, c2)) = parse_range(range)
+                .ok_or_else(|| SheetError::InvalidRequest(format!("Invalid range: {range}")))?;
+            cut_range(
+                worksheet,
+                (r1.min(r2), c1.min(c2)),
+                (r1.max(r2), c1.max(c2)),
+            )
+        }
+    };
+
+    Ok(Spreadsheet {
+        id: sheet.id.clone(),
+        name: sheet.name.clone(),
+        owner_id: sheet.owner_id.clone(),
+        worksheets: vec![worksheet],
+        created_at: sheet.created_at,
+        updated_at: sheet.updated_at,
+        named_ranges: None,
+        external_links: None,
+        locale: sheet.locale.clone(),
+        created_by: sheet.created_by.clone(),
+    })
+}
+
+/// Download name for an export, e.g. `Budget.xlsx`, `Budget - Q1.csv` or
+/// `Budget - Q1 B2-D20.csv`. Characters file systems reject are replaced with `_`.
+pub fn export_filename(
+    sheet: &Spreadsheet,
+    worksheet_index: Option<usize>,
+    range: Option<&str>,
+    extension: &str,
+) -> String {
+    let range = range.map(str::trim).filter(|r| !r.is_empty());
+    let mut name = sheet.name.clone();
+    if worksheet_index.is_some() || range.is_some() {
+        if let Some(worksheet) = sheet.worksheets.get(worksheet_index.unwrap_or(0)) {
+            name.push_str(" - ");
+            name.push_str(&worksheet.name);
+        }
+    }
+    if let Some(range) = range {
+        name.push(' ');
+        name.push_str(&range.to_uppercase().replace(':', "-"));
+    }
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|".contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    format!("{}.{extension}", name.trim())
+}
+
+/// `Content-Disposition` for a download named `filename`: an ASCII `filename` for old
+/// clients and the exact name as an RFC 5987 `filename*`.
+pub fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() && c != '"' && c != '\\' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!(
+        "attachment; filename=\"{fallback}\"; filename*=UTF-8''{}",
+        urlencoding::encode(filename)
+    )
+}
 
+/// The xlsx export, base64-encoded for JSON responses.
 pub fn export_to_xlsx(sheet: &Spreadsheet) -> Result<String, String> {
+    export_to_xlsx_bytes(sheet)
+        .map(|bytes| base64::engine::general_purpose::STANDARD.encode(bytes))
+}
+
+pub fn export_to_xlsx_bytes(sheet: &Spreadsheet) -> Result<Vec<u8>, String> {
     let mut workbook = Workbook::new();
+    let locale = SheetLocale::resolve(sheet.locale.as_deref());
 
     for ws in &sheet.worksheets {
         let worksheet = workbook.add_worksheet();
@@ -26,15 +200,33 @@ pub fn export_to_xlsx(sheet: &Spreadsheet) -> Result<String, String> {
             if let Some(ref style) = cell.style {
                 format = apply_style_to_format(format, style);
             }
+            if let Some(ref code) = cell.format {
+                format = format.set_num_format(code);
+            }
 
             if let Some(ref formula) = cell.formula {
                 worksheet
                     .write_formula_with_format(row, col, formula.as_str(), &format)
                     .map_err(|e| e.to_string())?;
             } else if let Ok(num) = value.parse::<f64>() {
+                if locale.grouping.is_some() && cell.format.is_none() {
+                    let pattern = if num.fract() == 0.0 {
+                        "#,##0"
+                    } else {
+                        "#,##0.0#####"
+                    };
+                    format = format.set_num_format(pattern);
+                }
                 worksheet
                     .write_number_with_format(row, col, num, &format)
                     .map_err(|e| e.to_string())?;
+            } else if let Some(date) = sheet.locale.as_ref().and_then(|_| excel_date(value)) {
+                if cell.format.is_none() {
+                    format = format.set_num_format(locale.excel_date_format());
+                }
+                worksheet
+                    .write_datetime_with_format(row, col, &date, &format)
+                    .map_err(|e| e.to_string())?;
             } else {
                 worksheet
                     .write_string_with_format(row, col, value, &format)
@@ -67,8 +259,20 @@ pub fn export_to_xlsx(sheet: &Spreadsheet) -> Result<String, String> {
         }
     }
 
-    let buffer = workbook.save_to_buffer().map_err(|e| e.to_string())?;
-    Ok(base64::engine::general_purpose::STANDARD.encode(&buffer))
+    workbook.save_to_buffer().map_err(|e| e.to_string())
+}
+
+fn excel_date(value: &str) -> Option<ExcelDateTime> {
+    if value.len() != 10 {
+        return None;
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    ExcelDateTime::from_ymd(
+        u16::try_from(date.year()).ok()?,
+        date.month() as u8,
+        date.day() as u8,
+    )
+    .ok()
 }
 
 fn apply_style_to_format(mut format: Format, style: &CellStyle) -> Format {
@@ -119,7 +323,10 @@ fn parse_color(color_str: &str) -> Option<Color> {
     }
 }
 
+/// CSV holds a single worksheet, the first of `sheet`; narrow it with `select_export` to
+/// export another one.
 pub fn export_to_csv(sheet: &Spreadsheet) -> String {
+    let locale = SheetLocale::resolve(sheet.locale.as_deref());
     let mut csv = String::new();
     if let Some(worksheet) = sheet.worksheets.first() {
         let mut max_row: u32 = 0;
@@ -140,7 +347,8 @@ pub fn export_to_csv(sheet: &Spreadsheet) -> String {
                 let value = worksheet
                     .data
                     .get(&key)
-                    .and_then(|c| c.value.clone())
+                    .and_then(|c| c.value.as_deref())
+                    .map(|v| locale.format_value(v))
                     .unwrap_or_default();
                 let escaped = if value.contains(',') || value.contains('"') || value.contains('\n')
                 {
@@ -248,7 +456,7 @@ pub fn export_to_html(sheet: &Spreadsheet) -> String {
     html
 }
 
-fn column_to_letter(col: u32) -> String {
+pub(crate) fn column_to_letter(col: u32) -> String {
     let mut result = String::new();
     let mut n = col + 1;
     while n > 0 {
@@ -267,7 +475,39 @@ fn html_escape(s: &str) -> String {
         .replace('\'', "&#39;")
 }
 
-pub fn export_to_ods(sheet: &Spreadsheet) -> Result<String, String> {
+const ODS_MIME_TYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const ODS_MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+<manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>"#;
+
+/// OpenDocument spreadsheet: a zip whose first entry is the uncompressed `mimetype`,
+/// followed by the manifest and the content.
+pub fn export_to_ods(sheet: &Spreadsheet) -> Result<Vec<u8>, String> {
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let mut buf = std::io::Cursor::new(Vec::new());
+    let mut zip = zip::ZipWriter::new(&mut buf);
+    let stored = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let deflated =
+        SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let entries = [
+        ("mimetype", stored, ODS_MIME_TYPE.to_string()),
+        ("META-INF/manifest.xml", deflated, ODS_MANIFEST.to_string()),
+        ("content.xml", deflated, ods_content(sheet)),
+    ];
+    for (name, options, content) in entries {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(content.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(buf.into_inner())
+}
+
+fn ods_content(sheet: &Spreadsheet) -> String {
     let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>
 <office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0"
     xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0"
@@ -279,7 +519,7 @@ pub fn export_to_ods(sheet: &Spreadsheet) -> Result<String, String> {
 "#);
 
     for ws in &sheet.worksheets {
-        xml.push_str(&format!("<table:table table:name=\"{}\">\n", ws.name));
+        xml.push_str(&format!("<table:table table:name=\"{}\">\n", html_escape(&ws.name)));
 
         let mut max_row: u32 = 0;
         let mut max_col: u32 = 0;
@@ -303,21 +543,22 @@ pub fn export_to_ods(sheet: &Spreadsheet) -> Result<String, String> {
                 let key = format!("{row},{col}");
                 let value = ws.data.get(&key).and_then(|c| c.value.clone()).unwrap_or_default();
                 let formula = ws.data.get(&key).and_then(|c| c.formula.clone());
+                let text = html_escape(&value);
 
                 if let Some(f) = formula {
                     xml.push_str(&format!(
                         "<table:table-cell table:formula=\"{}\">\n<text:p>{}</text:p>\n</table:table-cell>\n",
-                        f, value
+                        html_escape(&f), text
                     ));
                 } else if let Ok(num) = value.parse::<f64>() {
                     xml.push_str(&format!(
                         "<table:table-cell office:value-type=\"float\" office:value=\"{}\">\n<text:p>{}</text:p>\n</table:table-cell>\n",
-                        num, value
+                        num, text
                     ));
                 } else {
                     xml.push_str(&format!(
                         "<table:table-cell office:value-type=\"string\">\n<text:p>{}</text:p>\n</table:table-cell>\n",
-                        value
+                        text
                     ));
                 }
             }
@@ -327,7 +568,7 @@ pub fn export_to_ods(sheet: &Spreadsheet) -> Result<String, String> {
     }
 
     xml.push_str("</office:spreadsheet>\n</office:body>\n</office:document-content>");
-    Ok(xml)
+    xml
 }
 
 pub fn export_to_pdf_data(sheet: &Spreadsheet) -> Result<Vec<u8>, String> {
@@ -387,3 +628,128 @@ pub fn export_to_markdown(sheet: &Spreadsheet) -> String {
 
     md
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::create_new_spreadsheet;
+    use crate::sheet::types::CellData;
+    use std::io::Read;
+
+    fn set(worksheet: &mut Worksheet, row: u32, col: u32, value: &str, formula: Option<&str>) {
+        worksheet.data.insert(
+            format!("{row},{col}"),
+            CellData {
+                value: Some(value.to_string()),
+                formula: formula.map(str::to_string),
+                style: None,
+                format: None,
+                note: None,
+                locked: None,
+                has_comment: None,
+                array_formula_id: None,
+            },
+        );
+    }
+
+    fn budget() -> Spreadsheet {
+        let mut sheet = create_new_spreadsheet();
+        sheet.name = "Budget".to_string();
+        let mut q1 = sheet.worksheets.remove(0);
+        q1.data.clear();
+        q1.name = "Q1".to_string();
+        let mut q2 = q1.clone();
+        q2.name = "Q2".to_string();
+        for row in 0..4 {
+            for col in 0..4 {
+                set(&mut q1, row, col, &format!("q1-{row}{col}"), None);
+                set(&mut q2, row, col, &format!("q2-{row}{col}"), None);
+            }
+        }
+        set(&mut q2, 2, 2, "42", Some("=SUM(A1:B2)"));
+        sheet.worksheets = vec![q1, q2];
+        sheet
+    }
+
+    #[test]
+    fn test_range_limited_csv() {
+        let sheet = budget();
+        let selected = select_export(&sheet, Some(1), Some("B2:C3")).unwrap();
+        assert_eq!(export_to_csv(&selected), "q2-11,q2-12\nq2-21,42\n");
+        assert!(selected.worksheets[0].data["1,1"].formula.is_none());
+        assert_eq!(
+            export_filename(&sheet, Some(1), Some("b2:c3"), "csv"),
+            "Budget - Q2 B2-C3.csv"
+        );
+
+        // Corners may come in any order; a range alone refers to the first worksheet.
+        let reversed = select_export(&sheet, None, Some("C3:B2")).unwrap();
+        assert_eq!(export_to_csv(&reversed), "q1-11,q1-12\nq1-21,q1-22\n");
+
+        let whole = select_export(&sheet, Some(1), None).unwrap();
+        assert!(export_to_csv(&whole).starts_with("q2-00,"));
+        assert_eq!(export_filename(&sheet, None, None, "csv"), "Budget.csv");
+
+        assert!(select_export(&sheet, Some(2), None).is_err());
+        assert!(select_export(&sheet, Some(0), Some("B2")).is_err());
+    }
+
+    #[test]
+    fn test_ods_is_a_zipped_package() {
+        let mut sheet = budget();
+        sheet.worksheets[0].name = "Q1 & <Q2>".to_string();
+        let bytes = export_to_ods(&sheet).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+
+        let mut mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.name(), "mimetype");
+        assert_eq!(mimetype.compression(), zip::CompressionMethod::Stored);
+        let mut text = String::new();
+        mimetype.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "application/vnd.oasis.opendocument.spreadsheet");
+        drop(mimetype);
+
+        assert!(archive.by_name("META-INF/manifest.xml").is_ok());
+        let mut content = String::new();
+        archive
+            .by_name("content.xml")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert!(content.contains("table:name=\"Q1 &amp; &lt;Q2&gt;\""));
+    }
+
+    #[test]
+    fn test_content_disposition_keeps_unicode_names() {
+        assert_eq!(
+            content_disposition("Orçamento \"2024\".xlsx"),
+            "attachment; filename=\"Or_amento _2024_.xlsx\"; \
+             filename*=UTF-8''Or%C3%A7amento%20%222024%22.xlsx"
+        );
+    }
+
+    #[test]
+    fn test_single_worksheet_xlsx() {
+        let sheet = budget();
+        let selected = select_export(&sheet, Some(1), None).unwrap();
+        assert_eq!(selected.worksheets.len(), 1);
+
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(export_to_xlsx(&selected).unwrap())
+            .unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut workbook = String::new();
+        archive
+            .by_name("xl/workbook.xml")
+            .unwrap()
+            .read_to_string(&mut workbook)
+            .unwrap();
+        assert_eq!(workbook.matches("<sheet ").count(), 1);
+        assert!(workbook.contains("name=\"Q2\""));
+        assert!(archive.by_name("xl/worksheets/sheet2.xml").is_err());
+        assert_eq!(
+            export_filename(&sheet, Some(1), None, "xlsx"),
+            "Budget - Q2.xlsx"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::sheet::types::{FormulaResult, Worksheet};
 use chrono::{Datelike, Local, NaiveDate};
+use std::collections::{BTreeMap, HashSet, VecDeque};
 
 pub fn evaluate_formula(formula: &str, worksheet: &Worksheet) -> FormulaResult {
     if !formula.starts_with('=') {
@@ -54,6 +55,7 @@ pub fn evaluate_formula(formula: &str, worksheet: &Worksheet) -> FormulaResult {
         evaluate_month,
         evaluate_day,
         evaluate_datedif,
+        evaluate_dynamic_array,
         evaluate_arithmetic,
     ];
 
@@ -1059,3 +1061,509 @@ fn matches_criteria(value: &str, criteria: &str) -> bool {
 fn count_matching(values: &[String], criteria: &str) -> usize {
     values.iter().filter(|v| matches_criteria(v, criteria)).count()
 }
+
+const DYNAMIC_ARRAY_FUNCTIONS: [&str; 3] = ["UNIQUE(", "SORT(", "FILTER("];
+
+/// Whether the formula returns an array that spills into neighbouring cells.
+pub fn is_dynamic_array_formula(formula: &str) -> bool {
+    let Some(expr) = formula.strip_prefix('=') else {
+        return false;
+    };
+    let expr = expr.trim().to_uppercase();
+    DYNAMIC_ARRAY_FUNCTIONS
+        .iter()
+        .any(|name| expr.starts_with(name) && expr.ends_with(')'))
+}
+
+/// In a scalar context a dynamic array formula yields its top-left value.
+fn evaluate_dynamic_array(expr: &str, worksheet: &Worksheet) -> Option<String> {
+    match evaluate_array_formula(&format!("={expr}"), worksheet)? {
+        Ok(rows) => Some(rows.first()?.first().cloned().unwrap_or_default()),
+        Err(error) => Some(error),
+    }
+}
+
+/// Evaluates `UNIQUE`, `SORT` or `FILTER` to the rows it spills. Returns `None` for other
+/// formulas and `Err` with the error value (`#VALUE!`, `#CALC!`) to show in the anchor cell.
+pub fn evaluate_array_formula(
+    formula: &str,
+    worksheet: &Worksheet,
+) -> Option<Result<Vec<Vec<String>>, String>> {
+    if !is_dynamic_array_formula(formula) {
+        return None;
+    }
+    let expr = formula[1..].trim().to_uppercase();
+    let open = expr.find('(')?;
+    let name = &expr[..open];
+    let args = split_args(&expr[open + 1..expr.len() - 1]);
+    let Some(rows) = args.first().and_then(|range| range_rows(range, worksheet)) else {
+        return Some(Err("#VALUE!".to_string()));
+    };
+
+    let result = match name {
+        "UNIQUE" => Ok(unique_rows(rows)),
+        "SORT" => sort_rows(rows, &args[1..]),
+        "FILTER" => filter_rows(rows, &args[1..], worksheet),
+        _ => return None,
+    };
+    Some(result.and_then(|rows| {
+        if rows.is_empty() {
+            Err("#CALC!".to_string())
+        } else {
+            Ok(rows)
+        }
+    }))
+}
+
+/// Values of a range as rows. Trailing empty rows are dropped so that open-ended ranges
+/// such as `A1:A100` only spill the data they contain.
+fn range_rows(range: &str, worksheet: &Worksheet) -> Option<Vec<Vec<String>>> {
+    let ((start_row, start_col), (end_row, end_col)) = parse_range(range.trim())?;
+    let mut rows: Vec<Vec<String>> = (start_row..=end_row)
+        .map(|row| {
+            (start_col..=end_col)
+                .map(|col| {
+                    worksheet
+                        .data
+                        .get(&format!("{row},{col}"))
+                        .and_then(|c| c.value.clone())
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+        .collect();
+    while rows
+        .last()
+        .is_some_and(|row| row.iter().all(|v| v.is_empty()))
+    {
+        rows.pop();
+    }
+    Some(rows)
+}
+
+fn unique_rows(rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let mut unique: Vec<Vec<String>> = Vec::new();
+    for row in rows {
+        if row.iter().all(|v| v.is_empty()) {
+            continue;
+        }
+        let seen = unique.iter().any(|existing| {
+            existing.len() == row.len()
+                && existing.iter().zip(&row).all(|(a, b)| a.eq_ignore_ascii_case(b))
+        });
+        if !seen {
+            unique.push(row);
+        }
+    }
+    unique
+}
+
+/// `SORT(range, [sort_index], [sort_order])`: numbers sort before text, `-1` sorts
+/// descending.
+fn sort_rows(mut rows: Vec<Vec<String>>, args: &[&str]) -> Result<Vec<Vec<String>>, String> {
+    let number_arg = |index: usize, default: i64| -> Result<i64, String> {
+        match args.get(index).map(|a| a.trim()).filter(|a| !a.is_empty()) {
+            Some(arg) => arg.parse().map_err(|_| "#VALUE!".to_string()),
+            None => Ok(default),
+        }
+    };
+    let sort_index = number_arg(0, 1)?;
+    let descending = match number_arg(1, 1)? {
+        1 => false,
+        -1 => true,
+        _ => return Err("#VALUE!".to_string()),
+    };
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let column = usize::try_from(sort_index - 1)
+        .ok()
+        .filter(|&c| c < width.max(1))
+        .ok_or_else(|| "#VALUE!".to_string())?;
+
+    rows.retain(|row| row.iter().any(|v| !v.is_empty()));
+    rows.sort_by(|a, b| {
+        let a = a.get(column).map(String::as_str).unwrap_or("");
+        let b = b.get(column).map(String::as_str).unwrap_or("");
+        let ordering = match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(std::cmp::Ordering::Equal),
+            (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+            (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+            (Err(_), Err(_)) => a.to_lowercase().cmp(&b.to_lowercase()),
+        };
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    Ok(rows)
+}
+
+/// `FILTER(range, condition)` where the condition compares a range of the same height
+/// with a value, e.g. `FILTER(A1:A10, B1:B10>5)`.
+fn filter_rows(
+    rows: Vec<Vec<String>>,
+    args: &[&str],
+    worksheet: &Worksheet,
+) -> Result<Vec<Vec<String>>, String> {
+    let condition = args.first().map(|c| c.trim()).unwrap_or_default();
+    let re = regex::Regex::new(r"^([A-Z]+[0-9]+:[A-Z]+[0-9]+)\s*(>=|<=|<>|!=|=|>|<)\s*(.+)$")
+        .map_err(|_| "#VALUE!".to_string())?;
+    let caps = re.captures(condition).ok_or_else(|| "#VALUE!".to_string())?;
+    let ((start_row, start_col), (end_row, end_col)) =
+        parse_range(&caps[1]).ok_or_else(|| "#VALUE!".to_string())?;
+    if start_col != end_col {
+        return Err("#VALUE!".to_string());
+    }
+    let criteria = format!("{}{}", &caps[2], caps[3].trim().trim_matches('"'));
+
+    let height = (end_row - start_row + 1) as usize;
+    if rows.len() > height {
+        return Err("#VALUE!".to_string());
+    }
+    Ok(rows
+        .into_iter()
+        .enumerate()
+        .filter(|(offset, _)| {
+            let value = worksheet
+                .data
+                .get(&format!("{},{}", start_row + *offset as u32, start_col))
+                .and_then(|c| c.value.clone())
+                .unwrap_or_default();
+            matches_criteria(&value, &criteria)
+        })
+        .map(|(_, row)| row)
+        .collect())
+}
+
+/// Parses a `"row,col"` cell key as used by `Worksheet::data`.
+pub fn parse_cell_key(key: &str) -> Option<(u32, u32)> {
+    let (row, col) = key.split_once(',')?;
+    Some((row.trim().parse().ok()?, col.trim().parse().ok()?))
+}
+
+type CellRange = ((u32, u32), (u32, u32));
+
+/// Cell references and ranges a formula reads, as inclusive `(row, col)` corners.
+pub fn formula_references(formula: &str) -> Vec<CellRange> {
+    let Ok(re) = regex::Regex::new(r"\b([A-Z]{1,3})([0-9]+)\b(?::([A-Z]{1,3})([0-9]+)\b)?") else {
+        return Vec::new();
+    };
+    let formula = formula.to_uppercase();
+    re.captures_iter(&formula)
+        .filter_map(|cap| {
+            let start = parse_cell_ref(&format!("{}{}", &cap[1], &cap[2]))?;
+            let end = match (cap.get(3), cap.get(4)) {
+                (Some(col), Some(row)) => {
+                    parse_cell_ref(&format!("{}{}", col.as_str(), row.as_str()))?
+                }
+                _ => start,
+            };
+            Some((
+                (start.0.min(end.0), start.1.min(end.1)),
+                (start.0.max(end.0), start.1.max(end.1)),
+            ))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecalcOutcome {
+    pub recomputed: usize,
+    /// Cells whose formula failed to evaluate or is part of a reference cycle.
+    pub errors: Vec<(u32, u32)>,
+}
+
+/// Re-evaluates every formula cell so that each one sees the fresh values of the formula
+/// cells it references, then re-spills dynamic array formulas. Cells in a reference cycle
+/// are set to `#ERROR!`.
+pub fn recalculate_worksheet(worksheet: &mut Worksheet) -> RecalcOutcome {
+    let formulas: BTreeMap<(u32, u32), String> = worksheet
+        .data
+        .iter()
+        .filter_map(|(key, cell)| {
+            let formula = cell.formula.as_ref().filter(|f| f.starts_with('='))?;
+            Some((parse_cell_key(key)?, formula.clone()))
+        })
+        .collect();
+
+    let mut pending: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+    let mut dependents: BTreeMap<(u32, u32), Vec<(u32, u32)>> = BTreeMap::new();
+    for (&cell, formula) in &formulas {
+        let ranges = formula_references(formula);
+        let inputs: Vec<(u32, u32)> = formulas
+            .keys()
+            .filter(|&&(row, col)| {
+                ranges.iter().any(|&((r1, c1), (r2, c2))| {
+                    (r1..=r2).contains(&row) && (c1..=c2).contains(&col)
+                })
+            })
+            .copied()
+            .collect();
+        pending.insert(cell, inputs.len());
+        for input in inputs {
+            dependents.entry(input).or_default().push(cell);
+        }
+    }
+
+    let mut ready: VecDeque<(u32, u32)> = pending
+        .iter()
+        .filter(|(_, &count)| count == 0)
+        .map(|(&cell, _)| cell)
+        .collect();
+    let mut outcome = RecalcOutcome::default();
+
+    while let Some(cell) = ready.pop_front() {
+        pending.remove(&cell);
+        let result = evaluate_formula(&formulas[&cell], worksheet);
+        if result.error.is_some() {
+            outcome.errors.push(cell);
+        }
+        set_cached_value(worksheet, cell, result.value);
+        outcome.recomputed += 1;
+
+        for dependent in dependents.get(&cell).into_iter().flatten() {
+            if let Some(count) = pending.get_mut(dependent) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.push_back(*dependent);
+                }
+            }
+        }
+    }
+
+    for cell in pending.into_keys() {
+        set_cached_value(worksheet, cell, "#ERROR!".to_string());
+        outcome.recomputed += 1;
+        outcome.errors.push(cell);
+    }
+    outcome.errors.sort_unstable();
+    crate::sheet::spill::respill_worksheet(worksheet);
+    outcome
+}
+
+fn set_cached_value(worksheet: &mut Worksheet, (row, col): (u32, u32), value: String) {
+    if let Some(cell) = worksheet.data.get_mut(&format!("{row},{col}")) {
+        cell.value = Some(value);
+    }
+}
+
+/// Computed values of a worksheet as rows of cells, for read-only consumers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueGrid {
+    /// Rows and columns in use, before any truncation.
+    pub total_rows: u32,
+    pub total_cols: u32,
+    /// Empty cells are `None`.
+    pub values: Vec<Vec<Option<String>>>,
+    pub truncated: bool,
+}
+
+/// Formula cells that must be evaluated to show the cells `in_view`: the formulas in view,
+/// dynamic array anchors (their spills may land in view) and every formula those read,
+/// directly or through other formulas.
+fn formulas_needed(
+    worksheet: &Worksheet,
+    in_view: impl Fn((u32, u32)) -> bool,
+) -> HashSet<(u32, u32)> {
+    let formulas: BTreeMap<(u32, u32), &str> = worksheet
+        .data
+        .iter()
+        .filter_map(|(key, cell)| {
+            let formula = cell.formula.as_deref().filter(|f| f.starts_with('='))?;
+            Some((parse_cell_key(key)?, formula))
+        })
+        .collect();
+
+    let mut needed = HashSet::new();
+    let mut queue: Vec<(u32, u32)> = formulas
+        .iter()
+        .filter(|(&cell, formula)| in_view(cell) || is_dynamic_array_formula(formula))
+        .map(|(&cell, _)| cell)
+        .collect();
+    while let Some(cell) = queue.pop() {
+        if !needed.insert(cell) {
+            continue;
+        }
+        for ((r1, c1), (r2, c2)) in formula_references(formulas[&cell]) {
+            queue.extend(
+                formulas
+                    .range((r1, c1)..=(r2, c2))
+                    .map(|(&input, _)| input)
+                    .filter(|&(_, col)| (c1..=c2).contains(&col)),
+            );
+        }
+    }
+    needed
+}
+
+/// The worksheet's values from `A1` to its last non-empty cell, with formulas evaluated
+/// fresh on a copy; the formulas themselves are not returned. Rows past `max_cells` are
+/// dropped before evaluating, so only the formulas shown, and those they read, are
+/// recalculated.
+pub fn computed_values(worksheet: &Worksheet, max_cells: usize) -> ValueGrid {
+    let used: Vec<(u32, u32)> = worksheet
+        .data
+        .iter()
+        .filter(|(_, cell)| {
+            cell.formula.is_some() || cell.value.as_deref().is_some_and(|v| !v.is_empty())
+        })
+        .filter_map(|(key, _)| parse_cell_key(key))
+        .collect();
+    let total_rows = used.iter().map(|&(row, _)| row + 1).max().unwrap_or(0);
+    let total_cols = used.iter().map(|&(_, col)| col + 1).max().unwrap_or(0);
+
+    let cols = (total_cols as usize).min(max_cells);
+    let rows = if cols == 0 {
+        0
+    } else {
+        (total_rows as usize).min(max_cells / cols)
+    };
+    let in_view = |(row, col): (u32, u32)| (row as usize) < rows && (col as usize) < cols;
+
+    let needed = formulas_needed(worksheet, in_view);
+    let mut computed = worksheet.clone();
+    for (key, cell) in computed.data.iter_mut() {
+        if cell.formula.is_some() && !parse_cell_key(key).is_some_and(|c| needed.contains(&c)) {
+            cell.formula = None;
+        }
+    }
+    recalculate_worksheet(&mut computed);
+
+    let mut values = vec![vec![None; cols]; rows];
+    for (key, cell) in &computed.data {
+        let Some(value) = cell.value.as_deref().filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        let Some((row, col)) = parse_cell_key(key).filter(|&c| in_view(c)) else {
+            continue;
+        };
+        values[row as usize][col as usize] = Some(value.to_string());
+    }
+
+    ValueGrid {
+        total_rows,
+        total_cols,
+        values,
+        truncated: rows < total_rows as usize || cols < total_cols as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::create_new_spreadsheet;
+    use crate::sheet::types::CellData;
+
+    fn cell(worksheet: &mut Worksheet, row: u32, col: u32, value: &str, formula: Option<&str>) {
+        worksheet.data.insert(
+            format!("{row},{col}"),
+            CellData {
+                value: Some(value.to_string()),
+                formula: formula.map(str::to_string),
+                style: None,
+                format: None,
+                note: None,
+                locked: None,
+                has_comment: None,
+                array_formula_id: None,
+            },
+        );
+    }
+
+    fn value(worksheet: &Worksheet, row: u32, col: u32) -> Option<String> {
+        worksheet
+            .data
+            .get(&format!("{row},{col}"))
+            .and_then(|c| c.value.clone())
+    }
+
+    #[test]
+    fn test_recalculate_in_dependency_order() {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+        cell(&mut worksheet, 0, 0, "2", None);
+        cell(&mut worksheet, 1, 0, "3", None);
+        // A3 = A1+A2, B1 = A3*10 and C1 = SUM(A1:B3) all hold stale cached values.
+        cell(&mut worksheet, 2, 0, "stale", Some("=A1+A2"));
+        cell(&mut worksheet, 0, 1, "stale", Some("=A3*10"));
+        cell(&mut worksheet, 0, 2, "stale", Some("=SUM(A1:B3)"));
+        cell(&mut worksheet, 1, 2, "stale", Some("=FOO(A1)"));
+
+        let outcome = recalculate_worksheet(&mut worksheet);
+
+        assert_eq!(outcome.recomputed, 4);
+        assert_eq!(value(&worksheet, 2, 0).as_deref(), Some("5"));
+        assert_eq!(value(&worksheet, 0, 1).as_deref(), Some("50"));
+        assert_eq!(value(&worksheet, 0, 2).as_deref(), Some("60"));
+        assert_eq!(outcome.errors, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_reference_cycle_is_reported() {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+        cell(&mut worksheet, 0, 0, "1", Some("=B1+1"));
+        cell(&mut worksheet, 0, 1, "1", Some("=A1+1"));
+        cell(&mut worksheet, 1, 0, "0", Some("=2*3"));
+
+        let outcome = recalculate_worksheet(&mut worksheet);
+
+        assert_eq!(outcome.recomputed, 3);
+        assert_eq!(outcome.errors, vec![(0, 0), (0, 1)]);
+        assert_eq!(value(&worksheet, 0, 0).as_deref(), Some("#ERROR!"));
+        assert_eq!(value(&worksheet, 1, 0).as_deref(), Some("6"));
+    }
+
+    #[test]
+    fn test_computed_values_evaluate_formulas_without_returning_them() {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+        cell(&mut worksheet, 0, 0, "Item", None);
+        cell(&mut worksheet, 0, 1, "Price", None);
+        cell(&mut worksheet, 1, 0, "Pen", None);
+        cell(&mut worksheet, 1, 1, "4", None);
+        cell(&mut worksheet, 2, 0, "Ink", None);
+        cell(&mut worksheet, 2, 1, "6", None);
+        // Saved with stale cached values; D1 is left blank to check the gap stays empty.
+        cell(&mut worksheet, 3, 1, "stale", Some("=SUM(B2:B3)"));
+        cell(&mut worksheet, 0, 2, "", None);
+        cell(&mut worksheet, 3, 3, "stale", Some("=B4*2"));
+
+        let grid = computed_values(&worksheet, 1000);
+        let row = |cells: &[Option<&str>]| -> Vec<Option<String>> {
+            cells.iter().map(|c| c.map(str::to_string)).collect()
+        };
+        assert_eq!(
+            grid.values,
+            vec![
+                row(&[Some("Item"), Some("Price"), None, None]),
+                row(&[Some("Pen"), Some("4"), None, None]),
+                row(&[Some("Ink"), Some("6"), None, None]),
+                row(&[None, Some("10"), None, Some("20")]),
+            ]
+        );
+        assert!(!grid.truncated);
+        // The stored sheet keeps its formulas and is not modified.
+        assert_eq!(value(&worksheet, 3, 1).as_deref(), Some("stale"));
+
+        let capped = computed_values(&worksheet, 8);
+        assert_eq!((capped.total_rows, capped.total_cols), (4, 4));
+        assert_eq!(capped.values.len(), 2);
+        assert!(capped.truncated);
+    }
+
+    #[test]
+    fn test_computed_values_evaluate_inputs_outside_the_cap() {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+        cell(&mut worksheet, 0, 0, "stale", Some("=A3+1"));
+        cell(&mut worksheet, 1, 0, "5", None);
+        cell(&mut worksheet, 2, 0, "stale", Some("=A2*2"));
+        cell(&mut worksheet, 3, 0, "stale", Some("=A1"));
+
+        let grid = computed_values(&worksheet, 1);
+        assert_eq!(grid.values, vec![vec![Some("11".to_string())]]);
+        assert_eq!(grid.total_rows, 4);
+        assert!(grid.truncated);
+
+        let needed = formulas_needed(&worksheet, |(row, _)| row == 0);
+        assert!(needed.contains(&(2, 0)));
+        assert!(!needed.contains(&(3, 0)));
+    }
+}
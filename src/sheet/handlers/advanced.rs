@@ -1,15 +1,19 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
-use crate::sheet::storage::{get_current_user_id, load_sheet_by_id, save_sheet_to_drive};
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::changes::record_structure;
+use crate::sheet::error::SheetError;
+use crate::sheet::protection::{ensure_range_editable, ensure_sheet_owner};
+use crate::sheet::storage::{get_current_user_id, load_sheet_by_id, queue_sheet_save};
 use crate::sheet::types::{
     AddExternalLinkRequest, ArrayFormula, ArrayFormulaRequest, CellData,
-    CreateNamedRangeRequest, DeleteArrayFormulaRequest, DeleteNamedRangeRequest, ExternalLink,
+    ClearRangeProtectionRequest, CreateNamedRangeRequest, DeleteArrayFormulaRequest, DeleteNamedRangeRequest, ExternalLink,
     ListExternalLinksResponse, ListNamedRangesResponse, LockCellsRequest, NamedRange,
-    ProtectSheetRequest, RefreshExternalLinkRequest, RemoveExternalLinkRequest, SaveResponse,
+    ProtectRangeRequest, ProtectSheetRequest, ProtectedRange, RefreshExternalLinkRequest, RemoveExternalLinkRequest, SaveResponse,
     UnprotectSheetRequest, UpdateNamedRangeRequest,
 };
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
     Json,
 };
 use chrono::Utc;
@@ -20,24 +24,17 @@ use uuid::Uuid;
 
 pub async fn handle_protect_sheet(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ProtectSheetRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ProtectSheetRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    ensure_sheet_owner(&sheet, &user)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let mut protection = req.protection;
@@ -50,12 +47,16 @@ pub async fn handle_protect_sheet(
     sheet.worksheets[req.worksheet_index].protection = Some(protection);
     sheet.updated_at = Utc::now();
 
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "protect",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -66,24 +67,17 @@ pub async fn handle_protect_sheet(
 
 pub async fn handle_unprotect_sheet(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<UnprotectSheetRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<UnprotectSheetRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    ensure_sheet_owner(&sheet, &user)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -94,16 +88,10 @@ pub async fn handle_unprotect_sheet(
                 password.hash(&mut hasher);
                 let provided_hash = format!("{:x}", hasher.finish());
                 if &provided_hash != hash {
-                    return Err((
-                        StatusCode::UNAUTHORIZED,
-                        Json(serde_json::json!({ "error": "Invalid password" })),
-                    ));
+                    return Err(SheetError::InvalidPassword);
                 }
             } else {
-                return Err((
-                    StatusCode::UNAUTHORIZED,
-                    Json(serde_json::json!({ "error": "Password required" })),
-                ));
+                return Err(SheetError::PasswordRequired);
             }
         }
     }
@@ -111,12 +99,16 @@ pub async fn handle_unprotect_sheet(
     worksheet.protection = None;
     sheet.updated_at = Utc::now();
 
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "unprotect",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -127,24 +119,17 @@ pub async fn handle_unprotect_sheet(
 
 pub async fn handle_lock_cells(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<LockCellsRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<LockCellsRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    ensure_sheet_owner(&sheet, &user)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -167,12 +152,16 @@ pub async fn handle_lock_cells(
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "lock",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -188,20 +177,113 @@ pub async fn handle_lock_cells(
     }))
 }
 
-pub async fn handle_add_external_link(
+pub async fn handle_protect_range(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddExternalLinkRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ProtectRangeRequest>,
+) -> Result<Json<ProtectedRange>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    ensure_sheet_owner(&sheet, &user)?;
+
+    if req.worksheet_index >= sheet.worksheets.len() {
+        return Err(SheetError::InvalidWorksheet);
+    }
+    if req.start_row > req.end_row || req.start_col > req.end_col {
+        return Err(SheetError::InvalidRequest(
+            "Range start must not be after its end".to_string(),
+        ));
+    }
+
+    let range = ProtectedRange {
+        id: Uuid::new_v4().to_string(),
+        start_row: req.start_row,
+        start_col: req.start_col,
+        end_row: req.end_row,
+        end_col: req.end_col,
+        editors: req.editors,
+        description: req.description,
+        created_by: user.user_id.to_string(),
+        created_at: Utc::now(),
     };
+    sheet.worksheets[req.worksheet_index]
+        .protected_ranges
+        .get_or_insert_with(Vec::new)
+        .push(range.clone());
+
+    sheet.updated_at = Utc::now();
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "protect_range",
+        Some(req.worksheet_index),
+    )
+    .await;
+
+    Ok(Json(range))
+}
+
+pub async fn handle_clear_range_protection(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ClearRangeProtectionRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
+    let user_id = get_current_user_id();
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    ensure_sheet_owner(&sheet, &user)?;
+
+    let worksheet = sheet
+        .worksheets
+        .get_mut(req.worksheet_index)
+        .ok_or(SheetError::InvalidWorksheet)?;
+    let ranges = worksheet.protected_ranges.get_or_insert_with(Vec::new);
+    let before = ranges.len();
+    ranges.retain(|range| range.id != req.range_id);
+    if ranges.len() == before {
+        return Err(SheetError::InvalidRequest(format!(
+            "Protected range {} not found",
+            req.range_id
+        )));
+    }
+    if ranges.is_empty() {
+        worksheet.protected_ranges = None;
+    }
+
+    sheet.updated_at = Utc::now();
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "clear_range_protection",
+        Some(req.worksheet_index),
+    )
+    .await;
+
+    Ok(Json(SaveResponse {
+        id: req.sheet_id,
+        success: true,
+        message: Some("Range protection cleared".to_string()),
+    }))
+}
+
+pub async fn handle_add_external_link(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<AddExternalLinkRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
+    let user_id = get_current_user_id();
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     let link = ExternalLink {
         id: Uuid::new_v4().to_string(),
@@ -217,12 +299,8 @@ pub async fn handle_add_external_link(
     links.push(link);
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(&state, &user_id, &req.sheet_id, &user, "external_link", None).await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -233,18 +311,13 @@ pub async fn handle_add_external_link(
 
 pub async fn handle_refresh_external_link(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<RefreshExternalLinkRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<RefreshExternalLinkRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if let Some(links) = &mut sheet.external_links {
         for link in links.iter_mut() {
@@ -256,12 +329,8 @@ pub async fn handle_refresh_external_link(
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(&state, &user_id, &req.sheet_id, &user, "external_link", None).await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -272,30 +341,21 @@ pub async fn handle_refresh_external_link(
 
 pub async fn handle_remove_external_link(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<RemoveExternalLinkRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<RemoveExternalLinkRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if let Some(links) = &mut sheet.external_links {
         links.retain(|link| link.id != req.link_id);
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(&state, &user_id, &req.sheet_id, &user, "external_link", None).await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -307,18 +367,12 @@ pub async fn handle_remove_external_link(
 pub async fn handle_list_external_links(
     State(state): State<Arc<AppState>>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<ListExternalLinksResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<ListExternalLinksResponse>, SheetError> {
     let sheet_id = params.get("sheet_id").cloned().unwrap_or_default();
     let user_id = get_current_user_id();
-    let sheet = match load_sheet_by_id(&state, &user_id, &sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let sheet = load_sheet_by_id(&state, &user_id, &sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     let links = sheet.external_links.unwrap_or_default();
     Ok(Json(ListExternalLinksResponse { links }))
@@ -326,25 +380,23 @@ pub async fn handle_list_external_links(
 
 pub async fn handle_array_formula(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ArrayFormulaRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ArrayFormulaRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
-
-    if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
-    }
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+
+    ensure_range_editable(
+        &sheet,
+        req.worksheet_index,
+        req.start_row,
+        req.start_col,
+        req.end_row,
+        req.end_col,
+        &user,
+    )?;
 
     let array_formula_id = Uuid::new_v4().to_string();
     let array_formula = ArrayFormula {
@@ -382,12 +434,16 @@ pub async fn handle_array_formula(
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "array_formula",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -398,24 +454,33 @@ pub async fn handle_array_formula(
 
 pub async fn handle_delete_array_formula(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteArrayFormulaRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<DeleteArrayFormulaRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
+    }
+    let existing = sheet.worksheets[req.worksheet_index]
+        .array_formulas
+        .iter()
+        .flatten()
+        .find(|af| af.id == req.array_formula_id)
+        .map(|af| (af.start_row, af.start_col, af.end_row, af.end_col));
+    if let Some((start_row, start_col, end_row, end_col)) = existing {
+        ensure_range_editable(
+            &sheet,
+            req.worksheet_index,
+            start_row,
+            start_col,
+            end_row,
+            end_col,
+            &user,
+        )?;
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -432,12 +497,16 @@ pub async fn handle_delete_array_formula(
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "delete_array_formula",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -448,18 +517,13 @@ pub async fn handle_delete_array_formula(
 
 pub async fn handle_create_named_range(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateNamedRangeRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<CreateNamedRangeRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     let named_range = NamedRange {
         id: Uuid::new_v4().to_string(),
@@ -477,12 +541,8 @@ pub async fn handle_create_named_range(
     named_ranges.push(named_range);
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(&state, &user_id, &req.sheet_id, &user, "named_range", None).await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -493,18 +553,13 @@ pub async fn handle_create_named_range(
 
 pub async fn handle_update_named_range(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<UpdateNamedRangeRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<UpdateNamedRangeRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if let Some(named_ranges) = &mut sheet.named_ranges {
         for range in named_ranges.iter_mut() {
@@ -532,12 +587,8 @@ pub async fn handle_update_named_range(
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(&state, &user_id, &req.sheet_id, &user, "named_range", None).await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -548,30 +599,21 @@ pub async fn handle_update_named_range(
 
 pub async fn handle_delete_named_range(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteNamedRangeRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<DeleteNamedRangeRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if let Some(named_ranges) = &mut sheet.named_ranges {
         named_ranges.retain(|r| r.id != req.range_id);
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(&state, &user_id, &req.sheet_id, &user, "named_range", None).await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -583,18 +625,12 @@ pub async fn handle_delete_named_range(
 pub async fn handle_list_named_ranges(
     State(state): State<Arc<AppState>>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<ListNamedRangesResponse>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<ListNamedRangesResponse>, SheetError> {
     let sheet_id = params.get("sheet_id").cloned().unwrap_or_default();
     let user_id = get_current_user_id();
-    let sheet = match load_sheet_by_id(&state, &user_id, &sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let sheet = load_sheet_by_id(&state, &user_id, &sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     let ranges = sheet.named_ranges.unwrap_or_default();
     Ok(Json(ListNamedRangesResponse { ranges }))
@@ -1,13 +1,67 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::error::SheetError;
+use crate::sheet::protection::ensure_range_editable;
+use crate::sheet::storage::{get_current_user_id, load_sheet_by_id};
 use crate::sheet::types::{SheetAiRequest, SheetAiResponse};
-use axum::{extract::State, response::IntoResponse, Json};
+use axum::{extract::State, Json};
+use serde::Deserialize;
 use std::sync::Arc;
 
+/// Commands that change the selected cells.
+const EDIT_COMMANDS: &[&str] = &[
+    "sum", "average", "avg", "format", "currency", "percent", "bold", "italic", "merge",
+    "clear",
+];
+
+#[derive(Deserialize)]
+struct Selection {
+    #[serde(default)]
+    worksheet_index: usize,
+    start_row: u32,
+    start_col: u32,
+    end_row: u32,
+    end_col: u32,
+}
+
+/// Rejects an editing command whose selection covers cells locked for `user`.
+async fn ensure_selection_editable(
+    state: &Arc<AppState>,
+    user: &AuthenticatedUser,
+    req: &SheetAiRequest,
+    command: &str,
+) -> Result<(), SheetError> {
+    if !EDIT_COMMANDS.iter().any(|word| command.contains(word)) {
+        return Ok(());
+    }
+    let (Some(sheet_id), Some(selection)) = (&req.sheet_id, &req.selection) else {
+        return Ok(());
+    };
+    let Ok(selection) = serde_json::from_value::<Selection>(selection.clone()) else {
+        return Ok(());
+    };
+    let sheet = load_sheet_by_id(state, &get_current_user_id(), sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    ensure_range_editable(
+        &sheet,
+        selection.worksheet_index,
+        selection.start_row.min(selection.end_row),
+        selection.start_col.min(selection.end_col),
+        selection.start_row.max(selection.end_row),
+        selection.start_col.max(selection.end_col),
+        user,
+    )
+}
+
 pub async fn handle_sheet_ai(
-    State(_state): State<Arc<AppState>>,
-    Json(req): Json<SheetAiRequest>,
-) -> impl IntoResponse {
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<SheetAiRequest>,
+) -> Result<Json<SheetAiResponse>, SheetError> {
     let command = req.command.to_lowercase();
+    ensure_selection_editable(&state, &user, &req, &command).await?;
 
     let response = if command.contains("sum") {
         "I can help you sum values. Select a range and use the SUM formula, or I've added a SUM formula below your selection."
@@ -35,9 +89,9 @@ pub async fn handle_sheet_ai(
         "I understand you want help with your spreadsheet. Try commands like 'sum column B', 'format as currency', 'sort ascending', or 'create a chart'."
     };
 
-    Json(SheetAiResponse {
+    Ok(Json(SheetAiResponse {
         response: response.to_string(),
         action: None,
         data: None,
-    })
+    }))
 }
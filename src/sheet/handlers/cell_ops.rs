@@ -1,47 +1,61 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
+use crate::sheet::error::SheetError;
+use crate::sheet::cell_format::apply_range_format;
+use crate::sheet::changes::{
+    cell_entry, derived_changes, derived_values, record_changes, record_structure, SheetChange,
+};
 use crate::sheet::collaboration::broadcast_sheet_change;
-use crate::sheet::formulas::evaluate_formula;
-use crate::sheet::storage::{get_current_user_id, load_sheet_by_id, save_sheet_to_drive};
+use crate::sheet::dependencies::formula_dependencies;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::export::column_to_letter;
+use crate::sheet::formulas::{evaluate_formula, recalculate_worksheet};
+use crate::sheet::locale::SheetLocale;
+use crate::sheet::protection::{ensure_cell_editable, ensure_range_editable};
+use crate::sheet::sort_filter::set_header;
+use crate::sheet::spill::is_spill_id;
+use crate::sheet::storage::{
+    can_access_sheet, get_current_user_id, load_sheet_by_id, queue_sheet_save,
+};
 use crate::sheet::types::{
-    CellData, CellUpdateRequest, FormatRequest, FormulaRequest, FormulaResult, FreezePanesRequest,
-    MergeCellsRequest, MergedCell, SaveResponse, Worksheet,
+    CellData, CellUpdateRequest, FormatRequest, FormulaDependenciesRequest,
+    FormulaDependenciesResponse, FormulaRequest, FormulaResult, FreezePanesRequest, HeaderRequest, MergeCellsRequest, MergedCell, RecalcError, RecalcResponse, SaveResponse,
+    Worksheet,
+};
+use axum::{
+    extract::{Path, State},
+    Json,
 };
-use axum::{extract::State, http::StatusCode, Json};
 use chrono::Utc;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 pub async fn handle_update_cell(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CellUpdateRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<CellUpdateRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
 
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
-    if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
-    }
+    ensure_cell_editable(&sheet, req.worksheet_index, req.row, req.col, &user)?;
 
+    let locale = SheetLocale::resolve(sheet.locale.as_deref());
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
     let key = format!("{},{}", req.row, req.col);
+    let derived_before = derived_values(worksheet);
 
     let (value, formula) = if req.value.starts_with('=') {
         let result = evaluate_formula(&req.value, worksheet);
         (Some(result.value), Some(req.value.clone()))
     } else {
-        (Some(req.value.clone()), None)
+        let value = locale
+            .normalize_input(&req.value)
+            .unwrap_or_else(|| req.value.clone());
+        (Some(value), None)
     };
 
     let cell = worksheet.data.entry(key).or_insert_with(|| CellData {
@@ -57,15 +71,22 @@ pub async fn handle_update_cell(
 
     cell.value = value;
     cell.formula = formula;
+    // Content typed into a spilled region replaces the spilled value (and blocks the spill).
+    if cell.array_formula_id.as_deref().is_some_and(is_spill_id) {
+        cell.array_formula_id = None;
+    }
+    recalculate_worksheet(worksheet);
+
+    // The edited cell as computed, then every formula or spill the edit changed.
+    let mut changes = derived_changes(req.worksheet_index, &derived_before, worksheet);
+    changes.retain(|change| {
+        !matches!(change, SheetChange::Cell { row, col, .. } if (*row, *col) == (req.row, req.col))
+    });
+    changes.insert(0, cell_entry(req.worksheet_index, worksheet, req.row, req.col));
 
     sheet.updated_at = Utc::now();
 
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
 
     broadcast_sheet_change(
         &req.sheet_id,
@@ -77,6 +98,14 @@ pub async fn handle_update_cell(
         req.worksheet_index,
     )
     .await;
+    record_changes(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user.user_id.to_string(),
+        changes,
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -85,56 +114,94 @@ pub async fn handle_update_cell(
     }))
 }
 
-pub async fn handle_format_cells(
+/// Re-evaluates every formula in the sheet and persists the refreshed cached values,
+/// e.g. after a bulk import or for sheets saved before a formula fix.
+pub async fn handle_recalculate_sheet(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<FormatRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    Path(sheet_id): Path<String>,
+) -> Result<Json<RecalcResponse>, SheetError> {
     let user_id = get_current_user_id();
 
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+
+    let mut recomputed = 0;
+    let mut errors = Vec::new();
+    for (worksheet_index, worksheet) in sheet.worksheets.iter_mut().enumerate() {
+        let outcome = recalculate_worksheet(worksheet);
+        recomputed += outcome.recomputed;
+        errors.extend(outcome.errors.into_iter().map(|(row, col)| RecalcError {
+            worksheet_index,
+            cell: format!("{}{}", column_to_letter(col), row + 1),
+            value: worksheet
+                .data
+                .get(&format!("{row},{col}"))
+                .and_then(|c| c.value.clone())
+                .unwrap_or_default(),
+        }));
+    }
 
-    if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+    if recomputed > 0 {
+        sheet.updated_at = Utc::now();
+        queue_sheet_save(&state, &user_id, &sheet).await?;
+        record_changes(
+            &state,
+            &user_id,
+            &sheet_id,
+            &user.user_id.to_string(),
+            vec![SheetChange::structure(
+                "recalculate",
+                None,
+                serde_json::Value::Null,
+            )],
+        )
+        .await;
     }
 
-    let worksheet = &mut sheet.worksheets[req.worksheet_index];
+    Ok(Json(RecalcResponse {
+        id: sheet_id,
+        recomputed,
+        errors,
+    }))
+}
 
-    for row in req.start_row..=req.end_row {
-        for col in req.start_col..=req.end_col {
-            let key = format!("{},{}", row, col);
-            let cell = worksheet.data.entry(key).or_insert_with(|| CellData {
-                value: None,
-                formula: None,
-                style: None,
-                format: None,
-                note: None,
-                locked: None,
-                has_comment: None,
-                array_formula_id: None,
-            });
-            cell.style = Some(req.style.clone());
-        }
-    }
+pub async fn handle_format_cells(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<FormatRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
+    let user_id = get_current_user_id();
+
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+
+    ensure_range_editable(
+        &sheet,
+        req.worksheet_index,
+        req.start_row,
+        req.start_col,
+        req.end_row,
+        req.end_col,
+        &user,
+    )?;
+
+    apply_range_format(&mut sheet.worksheets[req.worksheet_index], &req)?;
 
     sheet.updated_at = Utc::now();
 
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "format",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -145,8 +212,8 @@ pub async fn handle_format_cells(
 
 pub async fn handle_evaluate_formula(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<FormulaRequest>,
-) -> Result<Json<FormulaResult>, (StatusCode, Json<serde_json::Value>)> {
+    ApiJson(req): ApiJson<FormulaRequest>,
+) -> Result<Json<FormulaResult>, SheetError> {
     let user_id = get_current_user_id();
 
     let sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
@@ -170,43 +237,95 @@ pub async fn handle_evaluate_formula(
                     comments: None,
                     protection: None,
                     array_formulas: None,
+                    protected_ranges: None,
+                    has_header: None,
                 },
             )))
         }
     };
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
-    let result = evaluate_formula(&req.formula, &sheet.worksheets[req.worksheet_index]);
+    let mut result = evaluate_formula(&req.formula, &sheet.worksheets[req.worksheet_index]);
+    result.value = SheetLocale::resolve(sheet.locale.as_deref()).format_value(&result.value);
     Ok(Json(result))
 }
 
-pub async fn handle_merge_cells(
+/// Lists what a formula, or a cell's formula, reads without evaluating it.
+pub async fn handle_formula_dependencies(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<MergeCellsRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<FormulaDependenciesRequest>,
+) -> Result<Json<FormulaDependenciesResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
+    let sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    if !can_access_sheet(&sheet, &user) {
+        return Err(SheetError::PermissionDenied(
+            "Sign in to inspect formulas".to_string(),
+        ));
+    }
+    let worksheet = sheet
+        .worksheets
+        .get(req.worksheet_index)
+        .ok_or(SheetError::InvalidWorksheet)?;
+
+    let formula = match (req.formula, req.row, req.col) {
+        (Some(formula), _, _) => formula,
+        (None, Some(row), Some(col)) => worksheet
+            .data
+            .get(&format!("{row},{col}"))
+            .and_then(|cell| cell.formula.clone())
+            .ok_or_else(|| {
+                SheetError::InvalidRequest(format!(
+                    "Cell {}{} has no formula",
+                    column_to_letter(col),
+                    row + 1
+                ))
+            })?,
+        _ => {
+            return Err(SheetError::InvalidRequest(
+                "Give a formula, or the row and col of a cell".to_string(),
             ))
         }
     };
 
-    if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
-    }
+    let names: Vec<&str> = sheet
+        .named_ranges
+        .iter()
+        .flatten()
+        .filter(|range| range.worksheet_index.is_none_or(|i| i == req.worksheet_index))
+        .map(|range| range.name.as_str())
+        .collect();
+    let dependencies = formula_dependencies(&formula, &names);
+    Ok(Json(FormulaDependenciesResponse {
+        formula,
+        dependencies,
+    }))
+}
+
+pub async fn handle_merge_cells(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<MergeCellsRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
+    let user_id = get_current_user_id();
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+
+    ensure_range_editable(
+        &sheet,
+        req.worksheet_index,
+        req.start_row,
+        req.start_col,
+        req.end_row,
+        req.end_col,
+        &user,
+    )?;
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
     let merged = MergedCell {
@@ -220,12 +339,24 @@ pub async fn handle_merge_cells(
     merged_cells.push(merged);
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_changes(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user.user_id.to_string(),
+        vec![SheetChange::structure(
+            "merge",
+            Some(req.worksheet_index),
+            serde_json::json!({
+                "start_row": req.start_row,
+                "start_col": req.start_col,
+                "end_row": req.end_row,
+                "end_col": req.end_col,
+            }),
+        )],
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -236,25 +367,23 @@ pub async fn handle_merge_cells(
 
 pub async fn handle_unmerge_cells(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<MergeCellsRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<MergeCellsRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
-    if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
-    }
+    ensure_range_editable(
+        &sheet,
+        req.worksheet_index,
+        req.start_row,
+        req.start_col,
+        req.end_row,
+        req.end_col,
+        &user,
+    )?;
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
     if let Some(ref mut merged_cells) = worksheet.merged_cells {
@@ -267,12 +396,24 @@ pub async fn handle_unmerge_cells(
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_changes(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user.user_id.to_string(),
+        vec![SheetChange::structure(
+            "unmerge",
+            Some(req.worksheet_index),
+            serde_json::json!({
+                "start_row": req.start_row,
+                "start_col": req.start_col,
+                "end_row": req.end_row,
+                "end_col": req.end_col,
+            }),
+        )],
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -283,24 +424,16 @@ pub async fn handle_unmerge_cells(
 
 pub async fn handle_freeze_panes(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<FreezePanesRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<FreezePanesRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -308,12 +441,19 @@ pub async fn handle_freeze_panes(
     worksheet.frozen_cols = Some(req.frozen_cols);
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_changes(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user.user_id.to_string(),
+        vec![SheetChange::structure(
+            "freeze",
+            Some(req.worksheet_index),
+            serde_json::json!({ "frozen_rows": req.frozen_rows, "frozen_cols": req.frozen_cols }),
+        )],
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -321,3 +461,49 @@ pub async fn handle_freeze_panes(
         message: Some("Panes frozen".to_string()),
     }))
 }
+
+/// Marks the first row as the worksheet's header and freezes it, or clears the mark.
+pub async fn handle_set_header(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<HeaderRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
+    let user_id = get_current_user_id();
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+
+    if req.worksheet_index >= sheet.worksheets.len() {
+        return Err(SheetError::InvalidWorksheet);
+    }
+
+    set_header(&mut sheet.worksheets[req.worksheet_index], req.has_header);
+
+    sheet.updated_at = Utc::now();
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_changes(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user.user_id.to_string(),
+        vec![SheetChange::structure(
+            "header",
+            Some(req.worksheet_index),
+            serde_json::json!({ "has_header": req.has_header }),
+        )],
+    )
+    .await;
+
+    Ok(Json(SaveResponse {
+        id: req.sheet_id,
+        success: true,
+        message: Some(
+            if req.has_header {
+                "Header set"
+            } else {
+                "Header cleared"
+            }
+            .to_string(),
+        ),
+    }))
+}
@@ -0,0 +1,101 @@
+use crate::core::config::HttpTimeoutConfig;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::changes::{
+    read_changes, subscribe, unsubscribe, ChangePage, MAX_PAGE, MAX_WAIT_SECS,
+};
+use crate::sheet::error::SheetError;
+use crate::sheet::storage::{can_access_sheet, get_current_user_id, load_sheet_by_id};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Left between the end of a poll's wait and the request deadline.
+const WAIT_MARGIN: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+pub struct ChangesQuery {
+    #[serde(default)]
+    pub since: i64,
+    pub limit: Option<i64>,
+    /// Seconds to wait for new entries when there are none yet.
+    #[serde(default)]
+    pub wait: u64,
+}
+
+async fn load_page(
+    state: &Arc<AppState>,
+    sheet_id: &str,
+    since: i64,
+    limit: i64,
+) -> Result<ChangePage, SheetError> {
+    let pool = state.conn.clone();
+    let id = sheet_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| SheetError::StorageFailed(e.to_string()))?;
+        read_changes(&mut *conn, &id, since, limit)
+    })
+    .await
+    .map_err(|e| SheetError::StorageFailed(e.to_string()))?
+}
+
+/// The longest `wait` that still answers before the request deadline, which otherwise
+/// turns an empty poll into a `504`.
+fn max_wait(state: &AppState) -> Duration {
+    let cap = Duration::from_secs(MAX_WAIT_SECS);
+    let deadline = state.config.as_ref().map_or_else(
+        || HttpTimeoutConfig::from_env().default,
+        |c| c.http_timeouts.default,
+    );
+    if deadline.is_zero() {
+        cap
+    } else {
+        cap.min(deadline.saturating_sub(WAIT_MARGIN))
+    }
+}
+
+/// Entries of the sheet's change feed after `since`. With `wait`, an empty poll is held
+/// open until an edit arrives or the wait runs out.
+pub async fn handle_get_sheet_changes(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(sheet_id): Path<String>,
+    Query(query): Query<ChangesQuery>,
+) -> Result<Json<ChangePage>, SheetError> {
+    let user_id = get_current_user_id();
+    let sheet = load_sheet_by_id(&state, &user_id, &sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    if !can_access_sheet(&sheet, &user) {
+        return Err(SheetError::PermissionDenied(
+            "You do not have access to this sheet".to_string(),
+        ));
+    }
+    if query.since < 0 {
+        return Err(SheetError::InvalidRequest(
+            "since must not be negative".to_string(),
+        ));
+    }
+    let limit = query.limit.unwrap_or(MAX_PAGE);
+    let wait = Duration::from_secs(query.wait).min(max_wait(&state));
+
+    // Subscribed before reading, so an edit landing in between still wakes the poll.
+    let mut receiver = subscribe(&sheet_id);
+    let mut page = load_page(&state, &sheet_id, query.since, limit).await;
+    if matches!(&page, Ok(p) if p.entries.is_empty() && p.resync.is_none()) && !wait.is_zero() {
+        let since = query.since;
+        let woke = tokio::time::timeout(wait, receiver.wait_for(|&seq| seq > since)).await;
+        if matches!(woke, Ok(Ok(_))) {
+            page = load_page(&state, &sheet_id, since, limit).await;
+        }
+    }
+    unsubscribe(&sheet_id, receiver);
+
+    page.map(Json)
+}
@@ -1,37 +1,47 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
+use crate::drive::object_store::ObjectStoreError;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::security::upload_policy::check_upload;
+use crate::sheet::changes::record_structure;
+use crate::sheet::csv_import::{CsvImportQuery, CsvOptions};
+use crate::sheet::error::SheetError;
 use crate::sheet::export::{
-    export_to_csv, export_to_html, export_to_json, export_to_markdown, export_to_ods,
-    export_to_xlsx,
+    content_disposition, export_filename, export_to_csv, export_to_html, export_to_json,
+    export_to_markdown, export_to_ods, export_to_xlsx_bytes, select_export,
 };
+use crate::sheet::formulas::computed_values;
+use crate::sheet::parse_cache::{parsed_sheets, ParseCacheKey};
+use crate::sheet::protection::ensure_worksheets_editable;
+use crate::sheet::sort_filter::set_header;
 use crate::sheet::storage::{
-    create_new_spreadsheet, delete_sheet_from_drive, get_current_user_id, import_spreadsheet_bytes,
-    list_sheets_from_drive, load_sheet_by_id, load_sheet_from_drive, parse_csv_to_worksheets,
-    parse_excel_to_worksheets, save_sheet_to_drive,
+    can_access_sheet, create_new_spreadsheet, delete_sheet_from_drive, duplicate_sheet_in_drive, flush_sheet,
+    get_current_user_id, import_spreadsheet_bytes, list_sheets_from_drive, load_sheet_by_id,
+    load_sheet_from_drive, parse_csv_to_worksheets, parse_excel_to_worksheets, save_sheet_to_drive,
 };
 use crate::sheet::types::{
     ExportRequest, LoadFromDriveRequest, LoadQuery, SaveRequest, SaveResponse, SearchQuery,
-    ShareRequest, Spreadsheet, SpreadsheetMetadata,
+    ShareRequest, Spreadsheet, SpreadsheetMetadata, ValuesQuery, ValuesResponse,
 };
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
     response::IntoResponse,
     Json,
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use log::error;
 use std::sync::Arc;
 use uuid::Uuid;
 
 pub async fn handle_new_sheet(
     State(_state): State<Arc<AppState>>,
-) -> Result<Json<Spreadsheet>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<Spreadsheet>, SheetError> {
     Ok(Json(create_new_spreadsheet()))
 }
 
 pub async fn handle_list_sheets(
     State(state): State<Arc<AppState>>,
-) -> Result<Json<Vec<SpreadsheetMetadata>>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<Vec<SpreadsheetMetadata>>, SheetError> {
     let user_id = get_current_user_id();
 
     match list_sheets_from_drive(&state, &user_id).await {
@@ -46,7 +56,7 @@ pub async fn handle_list_sheets(
 pub async fn handle_search_sheets(
     State(state): State<Arc<AppState>>,
     Query(query): Query<SearchQuery>,
-) -> Result<Json<Vec<SpreadsheetMetadata>>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<Vec<SpreadsheetMetadata>>, SheetError> {
     let user_id = get_current_user_id();
 
     let sheets = match list_sheets_from_drive(&state, &user_id).await {
@@ -70,53 +80,25 @@ pub async fn handle_search_sheets(
 pub async fn handle_load_sheet(
     State(state): State<Arc<AppState>>,
     Query(query): Query<LoadQuery>,
-) -> Result<Json<Spreadsheet>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<Spreadsheet>, SheetError> {
     let user_id = get_current_user_id();
 
-    match load_sheet_from_drive(&state, &user_id, &query.id).await {
-        Ok(sheet) => Ok(Json(sheet)),
-        Err(e) => Err((
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": e })),
-        )),
-    }
+    load_sheet_from_drive(&state, &user_id, &query.id)
+        .await
+        .map(Json)
+        .map_err(SheetError::SheetNotFound)
 }
 
 pub async fn handle_load_from_drive(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<LoadFromDriveRequest>,
-) -> Result<Json<Spreadsheet>, (StatusCode, Json<serde_json::Value>)> {
-    let drive = state.drive.as_ref().ok_or_else(|| {
-        (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({ "error": "Drive not available" })),
-        )
-    })?;
-
-    let result = drive
-        .get_object()
-        .bucket(&req.bucket)
-        .key(&req.path)
-        .send()
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": format!("File not found: {e}") })),
-            )
-        })?;
-
-    let bytes = result
-        .body
-        .collect()
-        .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({ "error": format!("Failed to read file: {e}") })),
-            )
-        })?
-        .into_bytes();
+    Query(csv_query): Query<CsvImportQuery>,
+    ApiJson(req): ApiJson<LoadFromDriveRequest>,
+) -> Result<Json<Spreadsheet>, SheetError> {
+    let csv_options = CsvOptions::from_query(&csv_query).map_err(SheetError::InvalidRequest)?;
+    let store = state
+        .object_store
+        .as_ref()
+        .ok_or(SheetError::DriveUnavailable)?;
 
     let ext = req.path.rsplit('.').next().unwrap_or("").to_lowercase();
     let file_name = req.path.rsplit('/').next().unwrap_or("Spreadsheet");
@@ -125,33 +107,58 @@ pub async fn handle_load_from_drive(
         .last()
         .unwrap_or("Spreadsheet")
         .to_string();
+    if !matches!(ext.as_str(), "csv" | "tsv" | "xlsx" | "xls" | "ods" | "xlsb" | "xlsm") {
+        return Err(SheetError::UnsupportedFormat(ext));
+    }
 
-    let worksheets = match ext.as_str() {
-        "csv" | "tsv" => {
-            let delimiter = if ext == "tsv" { b'\t' } else { b',' };
-            parse_csv_to_worksheets(&bytes, delimiter, &sheet_name).map_err(|e| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(serde_json::json!({ "error": e })),
-                )
-            })?
-        }
-        "xlsx" | "xls" | "ods" | "xlsb" | "xlsm" => {
-            parse_excel_to_worksheets(&bytes, &ext).map_err(|e| {
-                (
-                    StatusCode::BAD_REQUEST,
-                    Json(serde_json::json!({ "error": e })),
-                )
-            })?
-        }
-        _ => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({ "error": format!("Unsupported format: .{ext}") })),
-            ));
+    // A cheap listing of the key tells whether the cached parse is still current, so
+    // unchanged files are neither downloaded nor parsed again.
+    let cache_key = ParseCacheKey {
+        bucket: req.bucket.clone(),
+        key: req.path.clone(),
+        csv_options,
+    };
+    let version = store
+        .list(&req.bucket, &req.path)
+        .await
+        .ok()
+        .and_then(|objects| objects.into_iter().find(|obj| obj.key == req.path))
+        .map(|obj| object_version(obj.size, obj.last_modified));
+    let cached = version
+        .as_deref()
+        .and_then(|version| parsed_sheets().get(&cache_key, version));
+
+    let worksheets = match cached {
+        Some(worksheets) => worksheets,
+        None => {
+            let bytes = store
+                .get(&req.bucket, &req.path)
+                .await
+                .map_err(|e| match e {
+                    ObjectStoreError::NotFound(location) => SheetError::FileNotFound(location),
+                    other => SheetError::StorageFailed(format!("Failed to read file: {other}")),
+                })?;
+
+            parsed_sheets()
+                .get_or_parse(cache_key, version.as_deref(), || match ext.as_str() {
+                    "csv" | "tsv" => {
+                        let delimiter = if ext == "tsv" { b'\t' } else { b',' };
+                        parse_csv_to_worksheets(&bytes, &csv_options, delimiter, &sheet_name)
+                    }
+                    _ => parse_excel_to_worksheets(&bytes, &ext),
+                })
+                .map_err(SheetError::ImportFailed)?
         }
     };
 
+    // CSV parses are already marked; Excel and ODS files take the flag here.
+    let mut worksheets = worksheets.as_ref().clone();
+    if csv_options.header {
+        for worksheet in &mut worksheets {
+            set_header(worksheet, true);
+        }
+    }
+
     let user_id = get_current_user_id();
     let sheet = Spreadsheet {
         id: Uuid::new_v4().to_string(),
@@ -162,17 +169,39 @@ pub async fn handle_load_from_drive(
         updated_at: Utc::now(),
         named_ranges: None,
         external_links: None,
+        locale: None,
+        created_by: None,
     };
 
     Ok(Json(sheet))
 }
 
+/// Cache tag for a drive object; S3 and the filesystem both report size and mtime.
+fn object_version(size: u64, last_modified: Option<DateTime<Utc>>) -> String {
+    match last_modified {
+        Some(modified) => format!("{}-{}", size, modified.timestamp_nanos_opt().unwrap_or(0)),
+        None => size.to_string(),
+    }
+}
+
 pub async fn handle_save_sheet(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<SaveRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<SaveRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
 
+    let mut created_by = Some(user.user_id.to_string());
+    let mut locale = req.locale;
+    if let Some(id) = &req.id {
+        if let Ok(existing) = load_sheet_by_id(&state, &user_id, id).await {
+            ensure_worksheets_editable(&existing, &req.worksheets, &user)?;
+            created_by = existing.created_by;
+            // Clients that do not know about locales leave it out; keep the stored one.
+            locale = locale.or(existing.locale);
+        }
+    }
+
     let sheet_id = req.id.unwrap_or_else(|| Uuid::new_v4().to_string());
 
     let sheet = Spreadsheet {
@@ -184,14 +213,12 @@ pub async fn handle_save_sheet(
         updated_at: Utc::now(),
         named_ranges: None,
         external_links: None,
+        locale,
+        created_by,
     };
 
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    save_sheet_to_drive(&state, &user_id, &sheet).await?;
+    record_structure(&state, &user_id, &sheet_id, &user, "save", None).await;
 
     Ok(Json(SaveResponse {
         id: sheet_id,
@@ -202,16 +229,13 @@ pub async fn handle_save_sheet(
 
 pub async fn handle_delete_sheet(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<LoadQuery>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    ApiJson(req): ApiJson<LoadQuery>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
 
-    if let Err(e) = delete_sheet_from_drive(&state, &user_id, &req.id).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    delete_sheet_from_drive(&state, &user_id, &req.id)
+        .await
+        .map_err(SheetError::StorageFailed)?;
 
     Ok(Json(SaveResponse {
         id: req.id.unwrap_or_default(),
@@ -220,23 +244,84 @@ pub async fn handle_delete_sheet(
     }))
 }
 
+pub async fn handle_duplicate_sheet(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<LoadQuery>,
+) -> Result<Json<SaveResponse>, SheetError> {
+    if !user.is_authenticated() {
+        return Err(SheetError::PermissionDenied(
+            "Sign in to duplicate sheets".to_string(),
+        ));
+    }
+    let sheet_id = req
+        .id
+        .ok_or_else(|| SheetError::InvalidRequest("Sheet ID is required".to_string()))?;
+    let user_id = get_current_user_id();
+
+    let new_id = duplicate_sheet_in_drive(&state, &user_id, &sheet_id).await?;
+
+    Ok(Json(SaveResponse {
+        id: new_id,
+        success: true,
+        message: Some(format!("Duplicated sheet {}", sheet_id)),
+    }))
+}
+
 pub async fn handle_get_sheet_by_id(
     State(state): State<Arc<AppState>>,
     Path(sheet_id): Path<String>,
-) -> Result<Json<Spreadsheet>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<Spreadsheet>, SheetError> {
     let user_id = get_current_user_id();
-    match load_sheet_by_id(&state, &user_id, &sheet_id).await {
-        Ok(sheet) => Ok(Json(sheet)),
-        Err(e) => Err((
-            StatusCode::NOT_FOUND,
-            Json(serde_json::json!({ "error": e })),
-        )),
+    load_sheet_by_id(&state, &user_id, &sheet_id)
+        .await
+        .map(Json)
+        .map_err(SheetError::SheetNotFound)
+}
+
+/// Most cells returned by [`handle_get_sheet_values`].
+const MAX_VALUE_CELLS: usize = 100_000;
+
+/// Computed cell values of one worksheet, without formulas, styles or notes.
+pub async fn handle_get_sheet_values(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(sheet_id): Path<String>,
+    Query(query): Query<ValuesQuery>,
+) -> Result<Json<ValuesResponse>, SheetError> {
+    let user_id = get_current_user_id();
+    let sheet = load_sheet_by_id(&state, &user_id, &sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    if !can_access_sheet(&sheet, &user) {
+        return Err(SheetError::PermissionDenied(
+            "You do not have access to this sheet".to_string(),
+        ));
     }
+    let worksheet = sheet
+        .worksheets
+        .into_iter()
+        .nth(query.worksheet)
+        .ok_or(SheetError::InvalidWorksheet)?;
+    let name = worksheet.name.clone();
+
+    let grid = tokio::task::spawn_blocking(move || computed_values(&worksheet, MAX_VALUE_CELLS))
+        .await
+        .map_err(|e| SheetError::ExportFailed(e.to_string()))?;
+    Ok(Json(ValuesResponse {
+        id: sheet_id,
+        worksheet: query.worksheet,
+        name,
+        rows: grid.total_rows,
+        cols: grid.total_cols,
+        truncated: grid.truncated,
+        values: grid.values,
+    }))
 }
 
 pub async fn handle_share_sheet(
-    Json(req): Json<ShareRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    ApiJson(req): ApiJson<ShareRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     Ok(Json(SaveResponse {
         id: req.sheet_id,
         success: true,
@@ -246,113 +331,81 @@ pub async fn handle_share_sheet(
 
 pub async fn handle_export_sheet(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ExportRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    ApiJson(req): ApiJson<ExportRequest>,
+) -> Result<impl IntoResponse, SheetError> {
     let user_id = get_current_user_id();
+    flush_sheet(&state, &user_id, &req.id).await?;
 
-    let sheet = match load_sheet_by_id(&state, &user_id, &req.id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
-
-    match req.format.as_str() {
-        "csv" => {
-            let csv = export_to_csv(&sheet);
-            Ok(([(axum::http::header::CONTENT_TYPE, "text/csv")], csv))
-        }
-        "xlsx" => {
-            let xlsx = export_to_xlsx(&sheet).map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({ "error": e })),
-                )
-            })?;
-            Ok((
-                [(
-                    axum::http::header::CONTENT_TYPE,
-                    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
-                )],
-                xlsx,
-            ))
-        }
-        "json" => {
-            let json = export_to_json(&sheet);
-            Ok(([(axum::http::header::CONTENT_TYPE, "application/json")], json))
-        }
-        "html" => {
-            let html = export_to_html(&sheet);
-            Ok(([(axum::http::header::CONTENT_TYPE, "text/html")], html))
-        }
-        "ods" => {
-            let ods = export_to_ods(&sheet).map_err(|e| {
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({ "error": e })),
-                )
-            })?;
-            Ok((
-                [(
-                    axum::http::header::CONTENT_TYPE,
-                    "application/vnd.oasis.opendocument.spreadsheet",
-                )],
-                ods,
-            ))
-        }
-        "md" | "markdown" => {
-            let md = export_to_markdown(&sheet);
-            Ok(([(axum::http::header::CONTENT_TYPE, "text/markdown")], md))
-        }
-        _ => Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Unsupported format" })),
-        )),
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    if req.locale.is_some() {
+        sheet.locale = req.locale;
     }
+    let selected = select_export(&sheet, req.worksheet_index, req.range.as_deref())?;
+
+    let (content_type, extension, body): (_, _, Vec<u8>) = match req.format.as_str() {
+        "csv" => ("text/csv", "csv", export_to_csv(&selected).into_bytes()),
+        "xlsx" => (
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+            "xlsx",
+            export_to_xlsx_bytes(&selected).map_err(SheetError::ExportFailed)?,
+        ),
+        "json" => ("application/json", "json", export_to_json(&selected).into_bytes()),
+        "html" => ("text/html", "html", export_to_html(&selected).into_bytes()),
+        "ods" => (
+            "application/vnd.oasis.opendocument.spreadsheet",
+            "ods",
+            export_to_ods(&selected).map_err(SheetError::ExportFailed)?,
+        ),
+        "md" | "markdown" => ("text/markdown", "md", export_to_markdown(&selected).into_bytes()),
+        _ => return Err(SheetError::UnsupportedFormat(String::new())),
+    };
+    let filename = export_filename(&sheet, req.worksheet_index, req.range.as_deref(), extension);
+
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, content_type.to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, content_disposition(&filename)),
+        ],
+        body,
+    ))
 }
 
 pub async fn handle_import_sheet(
     State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Query(csv_query): Query<CsvImportQuery>,
     mut multipart: axum::extract::Multipart,
-) -> Result<Json<Spreadsheet>, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<Json<Spreadsheet>, SheetError> {
+    let csv_options = CsvOptions::from_query(&csv_query).map_err(SheetError::InvalidRequest)?;
     let mut file_bytes: Option<Vec<u8>> = None;
     let mut filename = "import.xlsx".to_string();
+    let mut content_type = String::new();
 
     while let Ok(Some(field)) = multipart.next_field().await {
         if field.name() == Some("file") {
             filename = field.file_name().unwrap_or("import.xlsx").to_string();
+            content_type = field.content_type().unwrap_or_default().to_string();
             if let Ok(bytes) = field.bytes().await {
                 file_bytes = Some(bytes.to_vec());
             }
         }
     }
 
-    let bytes = file_bytes.ok_or_else(|| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "No file uploaded" })),
-        )
-    })?;
+    let bytes =
+        file_bytes.ok_or_else(|| SheetError::InvalidRequest("No file uploaded".to_string()))?;
+    check_upload("sheet-import", &filename, &content_type, &bytes).await?;
 
-    let mut sheet = import_spreadsheet_bytes(&bytes, &filename).map_err(|e| {
-        (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": e })),
-        )
-    })?;
+    let mut sheet =
+        import_spreadsheet_bytes(&bytes, &filename, &csv_options).map_err(SheetError::ImportFailed)?;
 
     let user_id = get_current_user_id();
     sheet.owner_id = user_id.clone();
+    sheet.created_by = Some(user.user_id.to_string());
 
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    save_sheet_to_drive(&state, &user_id, &sheet).await?;
+    record_structure(&state, &user_id, &sheet.id, &user, "import", None).await;
 
     Ok(Json(sheet))
 }
@@ -1,99 +1,62 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
-use crate::sheet::storage::{get_current_user_id, load_sheet_by_id, save_sheet_to_drive};
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::changes::{record_changes, record_structure, SheetChange};
+use crate::sheet::error::SheetError;
+use crate::sheet::protection::ensure_range_editable;
+use crate::sheet::sort_filter::{apply_filters, sort_range};
+use crate::sheet::storage::{get_current_user_id, load_sheet_by_id, queue_sheet_save};
 use crate::sheet::types::{
-    CellData, ChartConfig, ChartOptions, ChartPosition, ChartRequest, ClearFilterRequest,
+    ChartConfig, ChartOptions, ChartPosition, ChartRequest, ClearFilterRequest,
     ConditionalFormatRequest, ConditionalFormatRule, DeleteChartRequest, FilterConfig,
     FilterRequest, SaveResponse, SortRequest,
 };
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, Json};
 use chrono::Utc;
 use std::sync::Arc;
 use uuid::Uuid;
 
 pub async fn handle_sort_range(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<SortRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<SortRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
-
-    if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
-    }
-
-    let worksheet = &mut sheet.worksheets[req.worksheet_index];
-
-    let mut rows: Vec<Vec<Option<CellData>>> = Vec::new();
-    for row in req.start_row..=req.end_row {
-        let mut row_data = Vec::new();
-        for col in req.start_col..=req.end_col {
-            let key = format!("{},{}", row, col);
-            row_data.push(worksheet.data.get(&key).cloned());
-        }
-        rows.push(row_data);
-    }
-
-    let sort_col_idx = (req.sort_col - req.start_col) as usize;
-    rows.sort_by(|a, b| {
-        let val_a = a
-            .get(sort_col_idx)
-            .and_then(|c| c.as_ref())
-            .and_then(|c| c.value.clone())
-            .unwrap_or_default();
-        let val_b = b
-            .get(sort_col_idx)
-            .and_then(|c| c.as_ref())
-            .and_then(|c| c.value.clone())
-            .unwrap_or_default();
-
-        let num_a = val_a.parse::<f64>().ok();
-        let num_b = val_b.parse::<f64>().ok();
-
-        let cmp = match (num_a, num_b) {
-            (Some(na), Some(nb)) => na.partial_cmp(&nb).unwrap_or(std::cmp::Ordering::Equal),
-            _ => val_a.cmp(&val_b),
-        };
-
-        if req.ascending {
-            cmp
-        } else {
-            cmp.reverse()
-        }
-    });
-
-    for (row_offset, row_data) in rows.iter().enumerate() {
-        for (col_offset, cell) in row_data.iter().enumerate() {
-            let key = format!(
-                "{},{}",
-                req.start_row + row_offset as u32,
-                req.start_col + col_offset as u32
-            );
-            if let Some(c) = cell {
-                worksheet.data.insert(key, c.clone());
-            } else {
-                worksheet.data.remove(&key);
-            }
-        }
-    }
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+
+    ensure_range_editable(
+        &sheet,
+        req.worksheet_index,
+        req.start_row,
+        req.start_col,
+        req.end_row,
+        req.end_col,
+        &user,
+    )?;
+
+    sort_range(&mut sheet.worksheets[req.worksheet_index], &req)?;
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_changes(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user.user_id.to_string(),
+        vec![SheetChange::structure(
+            "sort",
+            Some(req.worksheet_index),
+            serde_json::json!({
+                "start_row": req.start_row,
+                "start_col": req.start_col,
+                "end_row": req.end_row,
+                "end_col": req.end_col,
+            }),
+        )],
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -104,24 +67,16 @@ pub async fn handle_sort_range(
 
 pub async fn handle_filter_data(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<FilterRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<FilterRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -137,14 +92,19 @@ pub async fn handle_filter_data(
             value2: req.value2,
         },
     );
+    apply_filters(worksheet);
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "filter",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -155,24 +115,16 @@ pub async fn handle_filter_data(
 
 pub async fn handle_clear_filter(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ClearFilterRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ClearFilterRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -183,14 +135,19 @@ pub async fn handle_clear_filter(
             filters.clear();
         }
     }
+    apply_filters(worksheet);
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "clear_filter",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -201,24 +158,16 @@ pub async fn handle_clear_filter(
 
 pub async fn handle_create_chart(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ChartRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ChartRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -243,12 +192,16 @@ pub async fn handle_create_chart(
     charts.push(chart);
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "chart",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -259,24 +212,16 @@ pub async fn handle_create_chart(
 
 pub async fn handle_delete_chart(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteChartRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<DeleteChartRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -285,12 +230,16 @@ pub async fn handle_delete_chart(
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "delete_chart",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -301,24 +250,16 @@ pub async fn handle_delete_chart(
 
 pub async fn handle_conditional_format(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ConditionalFormatRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ConditionalFormatRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -338,12 +279,16 @@ pub async fn handle_conditional_format(
     formats.push(rule);
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "conditional_format",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -1,32 +1,75 @@
 pub mod advanced;
 pub mod ai;
 pub mod cell_ops;
+pub mod changes;
 pub mod crud;
 pub mod data_ops;
+pub mod queries;
+pub mod snapshots;
 pub mod validation;
+pub mod webhooks;
+pub mod worksheets;
+
+use crate::core::shared::state::AppState;
+use crate::sheet::error::SheetError;
+use diesel::PgConnection;
+use std::sync::Arc;
 
 pub use advanced::{
-    handle_add_external_link, handle_array_formula, handle_create_named_range,
-    handle_delete_array_formula, handle_delete_named_range, handle_list_external_links,
-    handle_list_named_ranges, handle_lock_cells, handle_protect_sheet,
+    handle_add_external_link, handle_array_formula, handle_clear_range_protection,
+    handle_create_named_range, handle_delete_array_formula, handle_delete_named_range,
+    handle_list_external_links,
+    handle_list_named_ranges, handle_lock_cells, handle_protect_range, handle_protect_sheet,
     handle_refresh_external_link, handle_remove_external_link, handle_unprotect_sheet,
     handle_update_named_range,
 };
 pub use ai::handle_sheet_ai;
 pub use cell_ops::{
-    handle_evaluate_formula, handle_format_cells, handle_freeze_panes, handle_merge_cells,
-    handle_unmerge_cells, handle_update_cell,
+    handle_evaluate_formula, handle_format_cells, handle_formula_dependencies, handle_freeze_panes,
+    handle_merge_cells, handle_recalculate_sheet, handle_set_header, handle_unmerge_cells,
+    handle_update_cell,
 };
 pub use crud::{
-    handle_delete_sheet, handle_export_sheet, handle_get_sheet_by_id, handle_import_sheet,
-    handle_list_sheets, handle_load_from_drive, handle_load_sheet, handle_new_sheet,
-    handle_save_sheet, handle_search_sheets, handle_share_sheet,
+    handle_delete_sheet, handle_duplicate_sheet, handle_export_sheet, handle_get_sheet_by_id,
+    handle_get_sheet_values, handle_import_sheet, handle_list_sheets, handle_load_from_drive, handle_load_sheet,
+    handle_new_sheet, handle_save_sheet, handle_search_sheets, handle_share_sheet,
 };
 pub use data_ops::{
     handle_clear_filter, handle_conditional_format, handle_create_chart, handle_delete_chart,
     handle_filter_data, handle_sort_range,
 };
+pub use queries::{
+    handle_create_saved_query, handle_delete_saved_query, handle_import_query,
+    handle_list_saved_queries,
+};
+pub use changes::handle_get_sheet_changes;
+pub use snapshots::{
+    handle_create_snapshot, handle_get_snapshot, handle_list_snapshots, handle_restore_snapshot,
+};
 pub use validation::{
     handle_add_comment, handle_add_note, handle_data_validation, handle_delete_comment,
     handle_list_comments, handle_reply_comment, handle_resolve_comment, handle_validate_cell,
 };
+pub use webhooks::{
+    handle_create_sheet_webhook, handle_delete_sheet_webhook, handle_sheet_webhook,
+};
+pub use worksheets::{
+    handle_add_worksheet, handle_delete_worksheet, handle_move_worksheet, handle_rename_worksheet,
+};
+
+/// Runs `f` on a pooled database connection off the async runtime.
+async fn with_conn<T, F>(state: &Arc<AppState>, f: F) -> Result<T, SheetError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut PgConnection) -> Result<T, SheetError> + Send + 'static,
+{
+    let pool = state.conn.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool
+            .get()
+            .map_err(|e| SheetError::StorageFailed(e.to_string()))?;
+        f(&mut conn)
+    })
+    .await
+    .map_err(|e| SheetError::StorageFailed(e.to_string()))?
+}
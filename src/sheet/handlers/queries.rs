@@ -0,0 +1,176 @@
+use super::with_conn;
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::changes::record_structure;
+use crate::sheet::error::SheetError;
+use crate::sheet::protection::{is_protected, is_sheet_owner};
+use crate::sheet::sql_import::{
+    delete_saved_query, get_saved_query, insert_saved_query, list_saved_queries, resolve_params,
+    rows_to_worksheet, run_saved_query, unique_worksheet_name, QueryImportRequest, SavedQuery,
+    SavedQueryRequest,
+};
+use crate::sheet::storage::{
+    can_access_sheet, create_new_spreadsheet, get_current_user_id, load_sheet_by_id,
+    save_sheet_to_drive,
+};
+use crate::sheet::types::Spreadsheet;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use log::info;
+use serde::Serialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+fn ensure_signed_in(user: &AuthenticatedUser) -> Result<(), SheetError> {
+    if !user.is_authenticated() {
+        return Err(SheetError::PermissionDenied(
+            "Sign in to use saved queries".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn ensure_admin(user: &AuthenticatedUser) -> Result<(), SheetError> {
+    if !user.is_admin() {
+        return Err(SheetError::PermissionDenied(
+            "Only administrators can manage saved queries".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub async fn handle_list_saved_queries(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<SavedQuery>>, SheetError> {
+    ensure_signed_in(&user)?;
+    let with_sql = user.is_admin();
+    let queries = with_conn(&state, move |conn| list_saved_queries(conn, with_sql)).await?;
+    Ok(Json(queries))
+}
+
+pub async fn handle_create_saved_query(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<SavedQueryRequest>,
+) -> Result<(StatusCode, Json<SavedQuery>), SheetError> {
+    ensure_admin(&user)?;
+    req.validate()?;
+    let created_by = user.user_id;
+    let query = with_conn(&state, move |conn| {
+        insert_saved_query(conn, &req, created_by)
+    })
+    .await?;
+    info!(
+        "Saved sheet query {} '{}' registered by {}",
+        query.id, query.name, created_by
+    );
+    Ok((StatusCode::CREATED, Json(query)))
+}
+
+pub async fn handle_delete_saved_query(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, SheetError> {
+    ensure_admin(&user)?;
+    with_conn(&state, move |conn| delete_saved_query(conn, id)).await?;
+    info!("Saved sheet query {} deleted by {}", id, user.user_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryImportResponse {
+    pub sheet: Spreadsheet,
+    pub worksheet: String,
+    pub rows: usize,
+    /// More rows matched than the query's `max_rows`.
+    pub truncated: bool,
+}
+
+/// Runs a saved query and stores the result as a new worksheet, either in a new sheet or
+/// appended to `sheet_id`.
+pub async fn handle_import_query(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<QueryImportRequest>,
+) -> Result<Json<QueryImportResponse>, SheetError> {
+    ensure_signed_in(&user)?;
+    let user_id = get_current_user_id();
+    let existing = match &req.sheet_id {
+        Some(sheet_id) => {
+            let sheet = load_sheet_by_id(&state, &user_id, sheet_id)
+                .await
+                .map_err(SheetError::SheetNotFound)?;
+            if !can_access_sheet(&sheet, &user) {
+                return Err(SheetError::PermissionDenied(
+                    "You do not have access to this sheet".to_string(),
+                ));
+            }
+            if sheet.worksheets.iter().any(is_protected) && !is_sheet_owner(&sheet, &user) {
+                return Err(SheetError::PermissionDenied(
+                    "Only the sheet owner can import into a protected sheet".to_string(),
+                ));
+            }
+            Some(sheet)
+        }
+        None => None,
+    };
+
+    let query_id = req.query_id;
+    let values = req.params;
+    let (query, result) = with_conn(&state, move |conn| {
+        let query = get_saved_query(conn, query_id, true)?;
+        let params = resolve_params(&query.params, &values)?;
+        let sql = query.sql.clone().unwrap_or_default();
+        let result = run_saved_query(conn, &sql, params, query.max_rows)?;
+        Ok((query, result))
+    })
+    .await?;
+
+    let mut sheet = existing.unwrap_or_else(|| {
+        let mut sheet = create_new_spreadsheet();
+        sheet.name = query.name.clone();
+        sheet.worksheets.clear();
+        sheet
+    });
+    let worksheet = unique_worksheet_name(
+        req.worksheet_name.as_deref().unwrap_or(&query.name),
+        &sheet.worksheets,
+    );
+    sheet
+        .worksheets
+        .push(rows_to_worksheet(&worksheet, &result.rows));
+    sheet.updated_at = Utc::now();
+    save_sheet_to_drive(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &sheet.id,
+        &user,
+        "import_query",
+        sheet.worksheets.len().checked_sub(1),
+    )
+    .await;
+
+    info!(
+        "Imported {} row(s){} from saved query '{}' into sheet {} worksheet '{}' (by {})",
+        result.rows.len(),
+        if result.truncated { ", truncated" } else { "" },
+        query.name,
+        sheet.id,
+        worksheet,
+        user.user_id
+    );
+    Ok(Json(QueryImportResponse {
+        rows: result.rows.len(),
+        truncated: result.truncated,
+        worksheet,
+        sheet,
+    }))
+}
@@ -0,0 +1,136 @@
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::changes::{record_changes, SheetChange};
+use crate::sheet::collaboration::broadcast_sheet_restored;
+use crate::sheet::error::SheetError;
+use crate::sheet::protection::ensure_worksheets_editable;
+use crate::sheet::snapshots::{
+    before_restore_label, list_snapshots, new_snapshot, read_snapshot, restored_sheet,
+    save_snapshot, validate_label, SheetSnapshot, SnapshotAuthor, SnapshotInfo,
+};
+use crate::sheet::storage::{
+    can_access_sheet, flush_sheet, get_current_user_id, load_sheet_by_id, save_sheet_to_drive,
+};
+use crate::sheet::types::{CreateSnapshotRequest, Spreadsheet};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use log::info;
+use std::sync::Arc;
+
+fn snapshot_author(user: &AuthenticatedUser) -> SnapshotAuthor {
+    SnapshotAuthor {
+        id: user.user_id.to_string(),
+        name: user.email.clone().unwrap_or_else(|| user.username.clone()),
+    }
+}
+
+/// Loads the sheet's current state, including edits still buffered for the drive.
+async fn load_accessible_sheet(
+    state: &Arc<AppState>,
+    user: &AuthenticatedUser,
+    user_id: &str,
+    sheet_id: &str,
+) -> Result<Spreadsheet, SheetError> {
+    flush_sheet(state, user_id, sheet_id).await?;
+    let sheet = load_sheet_by_id(state, user_id, sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    if !can_access_sheet(&sheet, user) {
+        return Err(SheetError::PermissionDenied(
+            "Sign in to manage sheet snapshots".to_string(),
+        ));
+    }
+    Ok(sheet)
+}
+
+pub async fn handle_create_snapshot(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(sheet_id): Path<String>,
+    ApiJson(req): ApiJson<CreateSnapshotRequest>,
+) -> Result<(StatusCode, Json<SnapshotInfo>), SheetError> {
+    let label = validate_label(&req.label)?;
+    let user_id = get_current_user_id();
+    let sheet = load_accessible_sheet(&state, &user, &user_id, &sheet_id).await?;
+    let snapshot = new_snapshot(&sheet, &label, &snapshot_author(&user));
+    let info = save_snapshot(&state, &user_id, &sheet_id, &snapshot).await?;
+
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+/// Lists the sheet's snapshots, newest first.
+pub async fn handle_list_snapshots(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(sheet_id): Path<String>,
+) -> Result<Json<Vec<SnapshotInfo>>, SheetError> {
+    let user_id = get_current_user_id();
+    load_accessible_sheet(&state, &user, &user_id, &sheet_id).await?;
+
+    let mut snapshots = list_snapshots(&state, &user_id, &sheet_id).await?;
+    snapshots.reverse();
+    Ok(Json(snapshots))
+}
+
+/// A snapshot with its full sheet state, e.g. for a change feed client that must resync.
+pub async fn handle_get_snapshot(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path((sheet_id, snapshot_id)): Path<(String, String)>,
+) -> Result<Json<SheetSnapshot>, SheetError> {
+    let user_id = get_current_user_id();
+    load_accessible_sheet(&state, &user, &user_id, &sheet_id).await?;
+
+    read_snapshot(&state, &user_id, &sheet_id, &snapshot_id)
+        .await
+        .map(Json)
+}
+
+/// Restores a snapshot as the sheet's current state. The replaced state is kept as a new
+/// snapshot, and collaborators are told to reload.
+pub async fn handle_restore_snapshot(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path((sheet_id, snapshot_id)): Path<(String, String)>,
+) -> Result<Json<Spreadsheet>, SheetError> {
+    let user_id = get_current_user_id();
+    let current = load_accessible_sheet(&state, &user, &user_id, &sheet_id).await?;
+
+    let snapshot = read_snapshot(&state, &user_id, &sheet_id, &snapshot_id).await?;
+    let restored = restored_sheet(&snapshot, &current);
+    ensure_worksheets_editable(&current, &restored.worksheets, &user)?;
+    let info = SnapshotInfo::from(&snapshot);
+
+    // History first: if saving the sheet fails, the pre-restore state is still kept.
+    let before = new_snapshot(
+        &current,
+        &before_restore_label(&snapshot),
+        &snapshot_author(&user),
+    );
+    save_snapshot(&state, &user_id, &sheet_id, &before).await?;
+    save_sheet_to_drive(&state, &user_id, &restored).await?;
+
+    info!(
+        "Sheet {} restored to snapshot {} by {}",
+        sheet_id, snapshot_id, user.user_id
+    );
+    broadcast_sheet_restored(&sheet_id, &user, &info).await;
+    record_changes(
+        &state,
+        &user_id,
+        &sheet_id,
+        &user.user_id.to_string(),
+        vec![SheetChange::structure(
+            "restore",
+            None,
+            serde_json::json!({ "snapshot_id": info.id }),
+        )],
+    )
+    .await;
+
+    Ok(Json(restored))
+}
@@ -1,36 +1,35 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
-use crate::sheet::storage::{get_current_user_id, load_sheet_by_id, save_sheet_to_drive};
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::changes::record_structure;
+use crate::sheet::collaboration::broadcast_comment_event;
+use crate::sheet::comments::{
+    add_comment, delete_comment, reply_to_comment, resolve_comment, CommentAuthor,
+};
+use crate::sheet::error::SheetError;
+use crate::sheet::formulas::parse_cell_key;
+use crate::sheet::storage::{get_current_user_id, load_sheet_by_id, queue_sheet_save};
 use crate::sheet::types::{
-    AddCommentRequest, AddNoteRequest, CellComment, CellData, CommentReply, CommentWithLocation,
-    DataValidationRequest, DeleteCommentRequest, ListCommentsRequest, ListCommentsResponse,
-    ReplyCommentRequest, ResolveCommentRequest, SaveResponse, ValidateCellRequest,
-    ValidationResult, ValidationRule,
+    AddCommentRequest, AddNoteRequest, CommentWithLocation, DataValidationRequest,
+    DeleteCommentRequest, ListCommentsRequest, ListCommentsResponse, ReplyCommentRequest,
+    ResolveCommentRequest, SaveResponse, ValidateCellRequest, ValidationResult, ValidationRule,
 };
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, Json};
 use chrono::Utc;
 use std::sync::Arc;
-use uuid::Uuid;
 
 pub async fn handle_data_validation(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DataValidationRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<DataValidationRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
@@ -59,12 +58,16 @@ pub async fn handle_data_validation(
     }
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "validation",
+        Some(req.worksheet_index),
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -75,24 +78,15 @@ pub async fn handle_data_validation(
 
 pub async fn handle_validate_cell(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ValidateCellRequest>,
-) -> Result<Json<ValidationResult>, (StatusCode, Json<serde_json::Value>)> {
+    ApiJson(req): ApiJson<ValidateCellRequest>,
+) -> Result<Json<ValidationResult>, SheetError> {
     let user_id = get_current_user_id();
-    let sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &sheet.worksheets[req.worksheet_index];
@@ -150,50 +144,59 @@ fn validate_value(value: &str, rule: &ValidationRule) -> ValidationResult {
     }
 }
 
+fn comment_author(user: &AuthenticatedUser) -> CommentAuthor {
+    CommentAuthor {
+        id: user.user_id.to_string(),
+        name: user.email.clone().unwrap_or_else(|| user.username.clone()),
+    }
+}
+
+/// Notes are single comments: new notes start a comment thread on the cell.
 pub async fn handle_add_note(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddNoteRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<AddNoteRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
-    let key = format!("{},{}", req.row, req.col);
-
-    let cell = worksheet.data.entry(key).or_insert_with(|| CellData {
-        value: None,
-        formula: None,
-        style: None,
-        format: None,
-        note: None,
-        locked: None,
-        has_comment: None,
-        array_formula_id: None,
-    });
-    cell.note = Some(req.note);
+    let comment = add_comment(
+        worksheet,
+        req.row,
+        req.col,
+        &comment_author(&user),
+        req.note,
+    );
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "note",
+        Some(req.worksheet_index),
+    )
+    .await;
+
+    broadcast_comment_event(
+        &req.sheet_id,
+        &user,
+        "comment_added",
+        req.worksheet_index,
+        req.row,
+        req.col,
+        &comment,
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -204,64 +207,49 @@ pub async fn handle_add_note(
 
 pub async fn handle_add_comment(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddCommentRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<AddCommentRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
-    let key = format!("{},{}", req.row, req.col);
-
-    let comment = CellComment {
-        id: Uuid::new_v4().to_string(),
-        author_id: user_id.clone(),
-        author_name: "User".to_string(),
-        content: req.content,
-        created_at: Utc::now(),
-        updated_at: Utc::now(),
-        replies: vec![],
-        resolved: false,
-    };
-
-    let comments = worksheet
-        .comments
-        .get_or_insert_with(std::collections::HashMap::new);
-    comments.insert(key.clone(), comment);
-
-    let cell = worksheet.data.entry(key).or_insert_with(|| CellData {
-        value: None,
-        formula: None,
-        style: None,
-        format: None,
-        note: None,
-        locked: None,
-        has_comment: None,
-        array_formula_id: None,
-    });
-    cell.has_comment = Some(true);
+    let comment = add_comment(
+        worksheet,
+        req.row,
+        req.col,
+        &comment_author(&user),
+        req.content,
+    );
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "comment",
+        Some(req.worksheet_index),
+    )
+    .await;
+
+    broadcast_comment_event(
+        &req.sheet_id,
+        &user,
+        "comment_added",
+        req.worksheet_index,
+        req.row,
+        req.col,
+        &comment,
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -272,52 +260,50 @@ pub async fn handle_add_comment(
 
 pub async fn handle_reply_comment(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ReplyCommentRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ReplyCommentRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
-    let key = format!("{},{}", req.row, req.col);
-
-    if let Some(comments) = &mut worksheet.comments {
-        if let Some(comment) = comments.get_mut(&key) {
-            if comment.id == req.comment_id {
-                let reply = CommentReply {
-                    id: Uuid::new_v4().to_string(),
-                    author_id: user_id.clone(),
-                    author_name: "User".to_string(),
-                    content: req.content,
-                    created_at: Utc::now(),
-                };
-                comment.replies.push(reply);
-                comment.updated_at = Utc::now();
-            }
-        }
-    }
+    let comment = reply_to_comment(
+        worksheet,
+        req.row,
+        req.col,
+        &req.comment_id,
+        &comment_author(&user),
+        req.content,
+    )?;
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "comment",
+        Some(req.worksheet_index),
+    )
+    .await;
+
+    broadcast_comment_event(
+        &req.sheet_id,
+        &user,
+        "comment_replied",
+        req.worksheet_index,
+        req.row,
+        req.col,
+        &comment,
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -328,45 +314,43 @@ pub async fn handle_reply_comment(
 
 pub async fn handle_resolve_comment(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ResolveCommentRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<ResolveCommentRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
-    let key = format!("{},{}", req.row, req.col);
-
-    if let Some(comments) = &mut worksheet.comments {
-        if let Some(comment) = comments.get_mut(&key) {
-            if comment.id == req.comment_id {
-                comment.resolved = req.resolved;
-                comment.updated_at = Utc::now();
-            }
-        }
-    }
+    let comment = resolve_comment(worksheet, req.row, req.col, &req.comment_id, req.resolved)?;
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "comment",
+        Some(req.worksheet_index),
+    )
+    .await;
+
+    broadcast_comment_event(
+        &req.sheet_id,
+        &user,
+        "comment_resolved",
+        req.worksheet_index,
+        req.row,
+        req.col,
+        &comment,
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -377,44 +361,43 @@ pub async fn handle_resolve_comment(
 
 pub async fn handle_delete_comment(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteCommentRequest>,
-) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<DeleteCommentRequest>,
+) -> Result<Json<SaveResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let mut sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let mut sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &mut sheet.worksheets[req.worksheet_index];
-    let key = format!("{},{}", req.row, req.col);
-
-    if let Some(comments) = &mut worksheet.comments {
-        comments.remove(&key);
-    }
-
-    if let Some(cell) = worksheet.data.get_mut(&key) {
-        cell.has_comment = Some(false);
-    }
+    let comment = delete_comment(worksheet, req.row, req.col, &req.comment_id)?;
 
     sheet.updated_at = Utc::now();
-    if let Err(e) = save_sheet_to_drive(&state, &user_id, &sheet).await {
-        return Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(serde_json::json!({ "error": e })),
-        ));
-    }
+    queue_sheet_save(&state, &user_id, &sheet).await?;
+    record_structure(
+        &state,
+        &user_id,
+        &req.sheet_id,
+        &user,
+        "comment",
+        Some(req.worksheet_index),
+    )
+    .await;
+
+    broadcast_comment_event(
+        &req.sheet_id,
+        &user,
+        "comment_deleted",
+        req.worksheet_index,
+        req.row,
+        req.col,
+        &comment,
+    )
+    .await;
 
     Ok(Json(SaveResponse {
         id: req.sheet_id,
@@ -425,43 +408,35 @@ pub async fn handle_delete_comment(
 
 pub async fn handle_list_comments(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ListCommentsRequest>,
-) -> Result<Json<ListCommentsResponse>, (StatusCode, Json<serde_json::Value>)> {
+    ApiJson(req): ApiJson<ListCommentsRequest>,
+) -> Result<Json<ListCommentsResponse>, SheetError> {
     let user_id = get_current_user_id();
-    let sheet = match load_sheet_by_id(&state, &user_id, &req.sheet_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            return Err((
-                StatusCode::NOT_FOUND,
-                Json(serde_json::json!({ "error": e })),
-            ))
-        }
-    };
+    let sheet = load_sheet_by_id(&state, &user_id, &req.sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
 
     if req.worksheet_index >= sheet.worksheets.len() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({ "error": "Invalid worksheet index" })),
-        ));
+        return Err(SheetError::InvalidWorksheet);
     }
 
     let worksheet = &sheet.worksheets[req.worksheet_index];
     let mut comments_list = vec![];
 
-    if let Some(comments) = &worksheet.comments {
-        for (key, comment) in comments {
-            let parts: Vec<&str> = key.split(',').collect();
-            if parts.len() == 2 {
-                if let (Ok(row), Ok(col)) = (parts[0].parse::<u32>(), parts[1].parse::<u32>()) {
-                    comments_list.push(CommentWithLocation {
-                        row,
-                        col,
-                        comment: comment.clone(),
-                    });
-                }
-            }
+    if let Some(threads) = &worksheet.comments {
+        for (key, thread) in threads {
+            let Some((row, col)) = parse_cell_key(key) else {
+                continue;
+            };
+            comments_list.extend(thread.iter().map(|comment| CommentWithLocation {
+                row,
+                col,
+                comment: comment.clone(),
+            }));
         }
     }
+    comments_list.sort_by(|a, b| {
+        (a.row, a.col, a.comment.created_at).cmp(&(b.row, b.col, b.comment.created_at))
+    });
 
     Ok(Json(ListCommentsResponse {
         comments: comments_list,
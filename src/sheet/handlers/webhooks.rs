@@ -0,0 +1,179 @@
+use super::with_conn;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::changes::{record_changes, SheetChange};
+use crate::sheet::collaboration::broadcast_sheet_change;
+use crate::sheet::error::SheetError;
+use crate::sheet::protection::is_sheet_owner;
+use crate::sheet::storage::{get_current_user_id, load_sheet_by_id, queue_sheet_save};
+use crate::sheet::webhook::{
+    apply_writes, check_rate_limit, delete_webhook, find_webhook, generate_secret, plan_writes,
+    resolve_worksheet, touch_webhook, upsert_webhook, verify_push, InboundPayload,
+    MAX_PAYLOAD_BYTES, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::Utc;
+use log::info;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Debug, Serialize)]
+pub struct SheetWebhookResponse {
+    pub url: String,
+    /// Only returned when the webhook is created or rotated.
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SheetWebhookPushResponse {
+    pub worksheet_index: usize,
+    pub cells_written: usize,
+}
+
+async fn load_owned_sheet(
+    state: &Arc<AppState>,
+    user: &AuthenticatedUser,
+    sheet_id: &str,
+) -> Result<(), SheetError> {
+    let sheet = load_sheet_by_id(state, &get_current_user_id(), sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    if !user.is_authenticated() || !is_sheet_owner(&sheet, user) {
+        return Err(SheetError::PermissionDenied(
+            "Only the sheet owner can manage its webhook".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Creates the sheet's inbound webhook, or rotates the secret of an existing one.
+pub async fn handle_create_sheet_webhook(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(sheet_id): Path<String>,
+) -> Result<(StatusCode, Json<SheetWebhookResponse>), SheetError> {
+    load_owned_sheet(&state, &user, &sheet_id).await?;
+    let secret = generate_secret();
+    let owner_id = get_current_user_id();
+    let created_by = user.user_id;
+    let (id, stored) = (sheet_id.clone(), secret.clone());
+    with_conn(&state, move |conn| {
+        upsert_webhook(conn, &id, &owner_id, &stored, created_by)
+    })
+    .await?;
+    info!(
+        "Inbound webhook for sheet {} set by {}",
+        sheet_id, created_by
+    );
+    Ok((
+        StatusCode::CREATED,
+        Json(SheetWebhookResponse {
+            url: format!("/webhook/sheet/{sheet_id}"),
+            secret,
+        }),
+    ))
+}
+
+pub async fn handle_delete_sheet_webhook(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    Path(sheet_id): Path<String>,
+) -> Result<StatusCode, SheetError> {
+    load_owned_sheet(&state, &user, &sheet_id).await?;
+    let id = sheet_id.clone();
+    with_conn(&state, move |conn| delete_webhook(conn, &id)).await?;
+    info!(
+        "Inbound webhook for sheet {} removed by {}",
+        sheet_id, user.user_id
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Public endpoint for signed pushes. Every write is validated before any is applied,
+/// then saved through the write buffer and broadcast to open editors.
+pub async fn handle_sheet_webhook(
+    State(state): State<Arc<AppState>>,
+    Path(sheet_id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<SheetWebhookPushResponse>, SheetError> {
+    if body.len() > MAX_PAYLOAD_BYTES {
+        return Err(SheetError::PayloadTooLarge(MAX_PAYLOAD_BYTES));
+    }
+    let id = sheet_id.clone();
+    let webhook = with_conn(&state, move |conn| find_webhook(conn, &id)).await?;
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    verify_push(
+        &body,
+        &header(SIGNATURE_HEADER),
+        &header(TIMESTAMP_HEADER),
+        &webhook.secret,
+    )
+    .await?;
+    // Only signed pushes count against the sheet's budget, so unsigned requests cannot
+    // use it up.
+    check_rate_limit(&sheet_id).await?;
+
+    let payload: InboundPayload = serde_json::from_slice(&body)
+        .map_err(|e| SheetError::InvalidRequest(format!("Invalid payload: {e}")))?;
+    let mut sheet = load_sheet_by_id(&state, &webhook.owner_id, &sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    let ws_idx = resolve_worksheet(&sheet, payload.worksheet.as_ref())?;
+    let writes = plan_writes(&sheet.worksheets[ws_idx], &payload)?;
+    apply_writes(&mut sheet, ws_idx, &writes)?;
+    sheet.updated_at = Utc::now();
+    queue_sheet_save(&state, &webhook.owner_id, &sheet).await?;
+
+    for write in &writes {
+        broadcast_sheet_change(
+            &sheet_id,
+            "webhook",
+            "Webhook",
+            write.row,
+            write.col,
+            &write.value,
+            ws_idx,
+        )
+        .await;
+    }
+    let worksheet = &sheet.worksheets[ws_idx];
+    let changes = writes
+        .iter()
+        .map(|write| {
+            let cell = worksheet.data.get(&format!("{},{}", write.row, write.col));
+            SheetChange::Cell {
+                worksheet_index: ws_idx,
+                row: write.row,
+                col: write.col,
+                value: cell.and_then(|c| c.value.clone()),
+                formula: cell.and_then(|c| c.formula.clone()),
+            }
+        })
+        .collect();
+    record_changes(&state, &webhook.owner_id, &sheet_id, "webhook", changes).await;
+    let id = sheet_id.clone();
+    with_conn(&state, move |conn| touch_webhook(conn, &id)).await?;
+
+    info!(
+        "Webhook wrote {} cell(s) to sheet {} worksheet {}",
+        writes.len(),
+        sheet_id,
+        ws_idx
+    );
+    Ok(Json(SheetWebhookPushResponse {
+        worksheet_index: ws_idx,
+        cells_written: writes.len(),
+    }))
+}
@@ -0,0 +1,178 @@
+use crate::core::shared::api_json::ApiJson;
+use crate::core::shared::state::AppState;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::changes::{record_changes, SheetChange};
+use crate::sheet::collaboration::broadcast_worksheets_changed;
+use crate::sheet::error::SheetError;
+use crate::sheet::protection::{is_protected, is_sheet_owner};
+use crate::sheet::storage::{
+    can_access_sheet, get_current_user_id, load_sheet_by_id, queue_sheet_save,
+};
+use crate::sheet::types::{
+    AddWorksheetRequest, DeleteWorksheetRequest, MoveWorksheetRequest, RenameWorksheetRequest,
+    Spreadsheet, WorksheetsResponse,
+};
+use crate::sheet::worksheets::{add_worksheet, delete_worksheet, move_worksheet, rename_worksheet};
+use axum::{extract::State, Json};
+use chrono::Utc;
+use serde_json::json;
+use std::sync::Arc;
+
+async fn load_editable_sheet(
+    state: &Arc<AppState>,
+    user: &AuthenticatedUser,
+    user_id: &str,
+    sheet_id: &str,
+) -> Result<Spreadsheet, SheetError> {
+    let sheet = load_sheet_by_id(state, user_id, sheet_id)
+        .await
+        .map_err(SheetError::SheetNotFound)?;
+    if !can_access_sheet(&sheet, user) {
+        return Err(SheetError::PermissionDenied(
+            "Sign in to manage worksheets".to_string(),
+        ));
+    }
+    Ok(sheet)
+}
+
+/// Only the owner may rename, delete or move a worksheet that has protection.
+fn ensure_worksheet_unprotected(
+    sheet: &Spreadsheet,
+    index: usize,
+    user: &AuthenticatedUser,
+) -> Result<(), SheetError> {
+    let Some(worksheet) = sheet.worksheets.get(index) else {
+        return Err(SheetError::InvalidWorksheet);
+    };
+    if is_protected(worksheet) && !is_sheet_owner(sheet, user) {
+        return Err(SheetError::PermissionDenied(
+            "Only the sheet owner can change a protected worksheet".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Saves the change, records it in the change feed and tells collaborators.
+async fn commit_structure_change(
+    state: &Arc<AppState>,
+    user: &AuthenticatedUser,
+    user_id: &str,
+    mut sheet: Spreadsheet,
+    action: &str,
+    worksheet_index: Option<usize>,
+    detail: serde_json::Value,
+) -> Result<Json<WorksheetsResponse>, SheetError> {
+    sheet.updated_at = Utc::now();
+    queue_sheet_save(state, user_id, &sheet).await?;
+    record_changes(
+        state,
+        user_id,
+        &sheet.id,
+        &user.user_id.to_string(),
+        vec![SheetChange::structure(action, worksheet_index, detail)],
+    )
+    .await;
+
+    let worksheets: Vec<String> = sheet.worksheets.iter().map(|ws| ws.name.clone()).collect();
+    broadcast_worksheets_changed(&sheet.id, user, action, worksheet_index, &worksheets).await;
+
+    Ok(Json(WorksheetsResponse {
+        id: sheet.id,
+        worksheet_index,
+        worksheets,
+    }))
+}
+
+pub async fn handle_add_worksheet(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<AddWorksheetRequest>,
+) -> Result<Json<WorksheetsResponse>, SheetError> {
+    let user_id = get_current_user_id();
+    let mut sheet = load_editable_sheet(&state, &user, &user_id, &req.sheet_id).await?;
+
+    let index = add_worksheet(&mut sheet, req.name.as_deref(), req.position)?;
+    let detail = json!({ "name": sheet.worksheets[index].name });
+    commit_structure_change(
+        &state,
+        &user,
+        &user_id,
+        sheet,
+        "worksheet_add",
+        Some(index),
+        detail,
+    )
+    .await
+}
+
+/// Renames a worksheet; formulas referring to it are rewritten to the new name.
+pub async fn handle_rename_worksheet(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<RenameWorksheetRequest>,
+) -> Result<Json<WorksheetsResponse>, SheetError> {
+    let user_id = get_current_user_id();
+    let mut sheet = load_editable_sheet(&state, &user, &user_id, &req.sheet_id).await?;
+    ensure_worksheet_unprotected(&sheet, req.worksheet_index, &user)?;
+
+    let old = rename_worksheet(&mut sheet, req.worksheet_index, &req.name)?;
+    let detail = json!({ "from": old, "to": sheet.worksheets[req.worksheet_index].name });
+    commit_structure_change(
+        &state,
+        &user,
+        &user_id,
+        sheet,
+        "worksheet_rename",
+        Some(req.worksheet_index),
+        detail,
+    )
+    .await
+}
+
+/// Deletes a worksheet; formulas referring to it become `#REF!`. The last worksheet of
+/// a sheet cannot be deleted.
+pub async fn handle_delete_worksheet(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<DeleteWorksheetRequest>,
+) -> Result<Json<WorksheetsResponse>, SheetError> {
+    let user_id = get_current_user_id();
+    let mut sheet = load_editable_sheet(&state, &user, &user_id, &req.sheet_id).await?;
+    ensure_worksheet_unprotected(&sheet, req.worksheet_index, &user)?;
+
+    let removed = delete_worksheet(&mut sheet, req.worksheet_index)?;
+    let detail = json!({ "name": removed.name });
+    commit_structure_change(
+        &state,
+        &user,
+        &user_id,
+        sheet,
+        "worksheet_delete",
+        Some(req.worksheet_index),
+        detail,
+    )
+    .await
+}
+
+pub async fn handle_move_worksheet(
+    State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
+    ApiJson(req): ApiJson<MoveWorksheetRequest>,
+) -> Result<Json<WorksheetsResponse>, SheetError> {
+    let user_id = get_current_user_id();
+    let mut sheet = load_editable_sheet(&state, &user, &user_id, &req.sheet_id).await?;
+    ensure_worksheet_unprotected(&sheet, req.worksheet_index, &user)?;
+
+    move_worksheet(&mut sheet, req.worksheet_index, req.position)?;
+    let detail = json!({ "from": req.worksheet_index, "to": req.position });
+    commit_structure_change(
+        &state,
+        &user,
+        &user_id,
+        sheet,
+        "worksheet_move",
+        Some(req.position),
+        detail,
+    )
+    .await
+}
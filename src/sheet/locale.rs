@@ -0,0 +1,223 @@
+//! Number and date display per sheet locale.
+//!
+//! Cell values are always stored in the canonical form the formula engine reads (`.` as
+//! decimal separator, no grouping, ISO dates). The locale is applied when values are shown
+//! or exported, and to normalise what users type in.
+
+use crate::core::i18n::Locale;
+use crate::sheet::formulas::format_number;
+use chrono::NaiveDate;
+
+const ISO_DATE: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SheetLocale {
+    pub decimal: char,
+    pub grouping: Option<char>,
+    /// `chrono` pattern used to display dates.
+    pub date_format: &'static str,
+}
+
+impl Default for SheetLocale {
+    fn default() -> Self {
+        Self {
+            decimal: '.',
+            grouping: None,
+            date_format: ISO_DATE,
+        }
+    }
+}
+
+impl SheetLocale {
+    const fn new(decimal: char, grouping: char, date_format: &'static str) -> Self {
+        Self {
+            decimal,
+            grouping: Some(grouping),
+            date_format,
+        }
+    }
+
+    /// Resolves a BCP 47 tag such as `pt-BR`; unknown or missing tags keep the canonical
+    /// format.
+    pub fn resolve(tag: Option<&str>) -> Self {
+        let Some(locale) = tag.and_then(Locale::new) else {
+            return Self::default();
+        };
+        match (locale.language(), locale.region()) {
+            ("en", None | Some("US")) => Self::new('.', ',', "%m/%d/%Y"),
+            ("en", _) => Self::new('.', ',', "%d/%m/%Y"),
+            ("de", _) => Self::new(',', '.', "%d.%m.%Y"),
+            ("pt" | "es" | "it" | "nl" | "id" | "tr", _) => Self::new(',', '.', "%d/%m/%Y"),
+            ("fr", _) => Self::new(',', '\u{a0}', "%d/%m/%Y"),
+            ("ru" | "pl" | "cs" | "sv" | "fi" | "nb", _) => Self::new(',', '\u{a0}', "%d.%m.%Y"),
+            ("zh" | "ja" | "ko", _) => Self::new('.', ',', "%Y/%m/%d"),
+            _ => Self::default(),
+        }
+    }
+
+    fn is_canonical(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn format_number(&self, num: f64) -> String {
+        self.localize_number(&format_number(num))
+    }
+
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(self.date_format).to_string()
+    }
+
+    /// Display form of a stored value: plain numbers and ISO dates are localised, anything
+    /// else is returned unchanged.
+    pub fn format_value(&self, value: &str) -> String {
+        if self.is_canonical() {
+            return value.to_string();
+        }
+        if is_plain_number(value) {
+            return self.localize_number(value);
+        }
+        match NaiveDate::parse_from_str(value, ISO_DATE) {
+            Ok(date) if value.len() == 10 => self.format_date(date),
+            _ => value.to_string(),
+        }
+    }
+
+    fn localize_number(&self, canonical: &str) -> String {
+        let (sign, digits) = match canonical.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", canonical),
+        };
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+
+        let mut out = String::from(sign);
+        for (i, ch) in int_part.chars().enumerate() {
+            if i > 0 && (int_part.len() - i) % 3 == 0 {
+                if let Some(grouping) = self.grouping {
+                    out.push(grouping);
+                }
+            }
+            out.push(ch);
+        }
+        if !frac_part.is_empty() {
+            out.push(self.decimal);
+            out.push_str(frac_part);
+        }
+        out
+    }
+
+    /// Parses user input as a number. Canonical `.`-decimal input is always accepted, so
+    /// formulas and API clients keep working whatever the display locale.
+    pub fn parse_number(&self, input: &str) -> Option<f64> {
+        let input = input.trim();
+        if is_plain_number(input) {
+            return input.parse().ok();
+        }
+        if self.is_canonical() {
+            return None;
+        }
+        // Plain spaces are accepted where the locale groups with a non-breaking space.
+        let is_grouping =
+            |c: char| Some(c) == self.grouping || (c == ' ' && self.grouping == Some('\u{a0}'));
+        let unsigned = input.strip_prefix('-').unwrap_or(input);
+        let (int_part, frac_part) = unsigned.split_once(self.decimal).unwrap_or((unsigned, ""));
+        if frac_part.contains(is_grouping)
+            || (int_part.contains(is_grouping) && !is_grouped(int_part, is_grouping))
+        {
+            return None;
+        }
+        let normalized: String = input
+            .chars()
+            .filter(|&c| !is_grouping(c))
+            .map(|c| if c == self.decimal { '.' } else { c })
+            .collect();
+        if is_plain_number(&normalized) {
+            normalized.parse().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Canonical form of a localised number typed by a user, or `None` when the input is
+    /// not a localised number and should be stored as typed.
+    pub fn normalize_input(&self, input: &str) -> Option<String> {
+        if is_plain_number(input.trim()) {
+            return None;
+        }
+        self.parse_number(input).map(|num| num.to_string())
+    }
+
+    /// Excel number format equivalent of `date_format`.
+    pub fn excel_date_format(&self) -> String {
+        self.date_format
+            .replace("%d", "dd")
+            .replace("%m", "mm")
+            .replace("%Y", "yyyy")
+    }
+}
+
+/// Whether `int_part` is digits grouped in threes: a leading group of one to three digits,
+/// then groups of exactly three, e.g. `1.234.567` but not `1.2.3`.
+fn is_grouped(int_part: &str, is_grouping: impl Fn(char) -> bool) -> bool {
+    let mut groups = int_part.split(is_grouping);
+    let first = groups.next().unwrap_or_default();
+    (1..=3).contains(&first.len())
+        && groups.all(|group| group.len() == 3)
+        && int_part.chars().all(|c| c.is_ascii_digit() || is_grouping(c))
+}
+
+/// Whether `value` is a number in canonical form: optional `-`, digits, optional `.` and
+/// digits.
+pub fn is_plain_number(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let (int_part, frac_part) = match digits.split_once('.') {
+        Some((int_part, frac_part)) => (int_part, Some(frac_part)),
+        None => (digits, None),
+    };
+    !int_part.is_empty()
+        && int_part.bytes().all(|b| b.is_ascii_digit())
+        && !frac_part.is_some_and(|f| f.is_empty() || !f.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comma_decimal_locale() {
+        let locale = SheetLocale::resolve(Some("pt-BR"));
+
+        assert_eq!(locale.format_number(1234567.5), "1.234.567,5");
+        assert_eq!(locale.format_number(-1000.0), "-1.000");
+        assert_eq!(locale.format_value("0.25"), "0,25");
+        assert_eq!(locale.format_value("2024-03-05"), "05/03/2024");
+        assert_eq!(locale.format_value("Total"), "Total");
+
+        assert_eq!(locale.parse_number("1.234,5"), Some(1234.5));
+        assert_eq!(locale.parse_number("3,75"), Some(3.75));
+        // Canonical decimals are always accepted.
+        assert_eq!(locale.parse_number("3.75"), Some(3.75));
+        assert_eq!(locale.normalize_input("3,75").as_deref(), Some("3.75"));
+        assert_eq!(locale.normalize_input("3.75"), None);
+        assert_eq!(locale.normalize_input("abc"), None);
+        // Grouping separators must split the integer part into thousands.
+        assert_eq!(locale.parse_number("1.2.3"), None);
+        assert_eq!(locale.parse_number("12.34,5"), None);
+        assert_eq!(locale.parse_number("1,2.3"), None);
+        assert_eq!(locale.parse_number("1.234.567"), Some(1234567.0));
+        assert_eq!(locale.parse_number("-1.000,25"), Some(-1000.25));
+    }
+
+    #[test]
+    fn test_default_keeps_canonical_format() {
+        let locale = SheetLocale::resolve(None);
+
+        assert_eq!(locale.format_number(1234567.5), "1234567.5");
+        assert_eq!(locale.format_value("2024-03-05"), "2024-03-05");
+        assert_eq!(locale.parse_number("3,75"), None);
+        assert_eq!(SheetLocale::resolve(Some("xx-YY")), SheetLocale::default());
+        assert_eq!(
+            SheetLocale::resolve(Some("de-DE")).excel_date_format(),
+            "dd.mm.yyyy"
+        );
+    }
+}
@@ -1,13 +1,29 @@
+pub mod cell_format;
+pub mod changes;
 pub mod collaboration;
+pub mod comments;
+pub mod csv_import;
+pub mod dependencies;
+pub mod error;
 pub mod export;
 pub mod formulas;
 pub mod handlers;
+pub mod locale;
+pub mod parse_cache;
+pub mod protection;
+pub mod snapshots;
+pub mod sort_filter;
+pub mod spill;
+pub mod sql_import;
 pub mod storage;
 pub mod types;
+pub mod webhook;
+pub mod worksheets;
+pub mod write_buffer;
 
 use crate::core::shared::state::AppState;
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 use std::sync::Arc;
@@ -16,25 +32,41 @@ pub use collaboration::{
     handle_get_collaborators, handle_get_mentions, handle_get_presence, handle_get_selections,
     handle_get_typing, handle_sheet_websocket,
 };
+pub use error::SheetError;
 pub use handlers::{
     handle_add_comment, handle_add_external_link, handle_add_note, handle_array_formula,
-    handle_clear_filter, handle_conditional_format, handle_create_chart, handle_create_named_range,
-    handle_data_validation, handle_delete_array_formula, handle_delete_chart, handle_delete_comment,
-    handle_delete_named_range, handle_delete_sheet, handle_evaluate_formula, handle_export_sheet,
-    handle_filter_data, handle_format_cells, handle_freeze_panes, handle_get_sheet_by_id,
-    handle_import_sheet, handle_list_comments, handle_list_external_links, handle_list_named_ranges,
-    handle_list_sheets, handle_load_from_drive, handle_load_sheet, handle_lock_cells,
-    handle_merge_cells, handle_new_sheet, handle_protect_sheet, handle_refresh_external_link,
+    handle_clear_filter, handle_clear_range_protection, handle_conditional_format,
+    handle_create_chart, handle_create_named_range, handle_create_saved_query,
+    handle_data_validation, handle_delete_array_formula, handle_delete_chart,
+    handle_delete_comment, handle_delete_named_range, handle_delete_saved_query,
+    handle_delete_sheet, handle_duplicate_sheet, handle_evaluate_formula,
+    handle_export_sheet, handle_filter_data, handle_format_cells, handle_formula_dependencies,
+    handle_freeze_panes,
+    handle_get_sheet_by_id, handle_get_sheet_values, handle_import_query, handle_import_sheet, handle_list_comments,
+    handle_list_external_links, handle_list_named_ranges, handle_list_saved_queries,
+    handle_list_sheets, handle_load_from_drive, handle_load_sheet,
+    handle_lock_cells, handle_merge_cells, handle_new_sheet, handle_protect_range,
+    handle_protect_sheet, handle_recalculate_sheet, handle_refresh_external_link,
     handle_remove_external_link, handle_reply_comment, handle_resolve_comment, handle_save_sheet,
-    handle_search_sheets, handle_share_sheet, handle_sheet_ai, handle_sort_range,
-    handle_unmerge_cells, handle_unprotect_sheet, handle_update_cell, handle_update_named_range,
-    handle_validate_cell,
+    handle_search_sheets, handle_set_header, handle_share_sheet, handle_sheet_ai,
+    handle_sort_range, handle_unmerge_cells, handle_unprotect_sheet, handle_update_cell,
+    handle_update_named_range, handle_validate_cell,
+};
+pub use handlers::{
+    handle_create_snapshot, handle_get_sheet_changes, handle_get_snapshot, handle_list_snapshots,
+    handle_restore_snapshot,
+};
+pub use handlers::{
+    handle_create_sheet_webhook, handle_delete_sheet_webhook, handle_sheet_webhook,
+};
+pub use handlers::{
+    handle_add_worksheet, handle_delete_worksheet, handle_move_worksheet, handle_rename_worksheet,
 };
 pub use types::{
     ArrayFormula, CellComment, CellData, CellStyle, ChartConfig, ChartDataset, ChartOptions,
     ChartPosition, Collaborator, CollabMessage, CommentReply, ConditionalFormatRule, ExternalLink,
-    FilterConfig, MergedCell, NamedRange, SaveResponse, SheetProtection, Spreadsheet,
-    SpreadsheetMetadata, ValidationRule, Worksheet,
+    FilterConfig, MergedCell, NamedRange, ProtectedRange, SaveResponse, SheetProtection,
+    Spreadsheet, SpreadsheetMetadata, ValidationRule, Worksheet,
 };
 
 pub fn configure_sheet_routes() -> Router<Arc<AppState>> {
@@ -45,15 +77,18 @@ pub fn configure_sheet_routes() -> Router<Arc<AppState>> {
         .route("/api/sheet/load-from-drive", post(handle_load_from_drive))
         .route("/api/sheet/save", post(handle_save_sheet))
         .route("/api/sheet/delete", post(handle_delete_sheet))
+        .route("/api/sheet/duplicate", post(handle_duplicate_sheet))
         .route("/api/sheet/cell", post(handle_update_cell))
         .route("/api/sheet/format", post(handle_format_cells))
         .route("/api/sheet/formula", post(handle_evaluate_formula))
+        .route("/api/sheet/formula/dependencies", post(handle_formula_dependencies))
         .route("/api/sheet/export", post(handle_export_sheet))
         .route("/api/sheet/share", post(handle_share_sheet))
         .route("/api/sheet/new", get(handle_new_sheet))
         .route("/api/sheet/merge", post(handle_merge_cells))
         .route("/api/sheet/unmerge", post(handle_unmerge_cells))
         .route("/api/sheet/freeze", post(handle_freeze_panes))
+        .route("/api/sheet/header", post(handle_set_header))
         .route("/api/sheet/sort", post(handle_sort_range))
         .route("/api/sheet/filter", post(handle_filter_data))
         .route("/api/sheet/filter/clear", post(handle_clear_filter))
@@ -64,9 +99,35 @@ pub fn configure_sheet_routes() -> Router<Arc<AppState>> {
         .route("/api/sheet/validate-cell", post(handle_validate_cell))
         .route("/api/sheet/note", post(handle_add_note))
         .route("/api/sheet/import", post(handle_import_sheet))
+        .route("/api/sheet/import-query", post(handle_import_query))
+        .route(
+            "/api/sheet/queries",
+            get(handle_list_saved_queries).post(handle_create_saved_query),
+        )
+        .route("/api/sheet/queries/:id", delete(handle_delete_saved_query))
         .route("/api/sheet/ai", post(handle_sheet_ai))
         .route("/api/sheet/:id", get(handle_get_sheet_by_id))
+        .route("/api/sheet/:id/values", get(handle_get_sheet_values))
+        .route("/api/sheet/:id/changes", get(handle_get_sheet_changes))
         .route("/api/sheet/:id/collaborators", get(handle_get_collaborators))
+        .route("/api/sheet/:id/recalc", post(handle_recalculate_sheet))
+        .route(
+            "/api/sheet/:id/snapshots",
+            get(handle_list_snapshots).post(handle_create_snapshot),
+        )
+        .route(
+            "/api/sheet/:id/snapshots/:snapshot_id",
+            get(handle_get_snapshot),
+        )
+        .route(
+            "/api/sheet/:id/snapshots/:snapshot_id/restore",
+            post(handle_restore_snapshot),
+        )
+        .route(
+            "/api/sheet/:sheet_id/webhook",
+            post(handle_create_sheet_webhook).delete(handle_delete_sheet_webhook),
+        )
+        .route("/webhook/sheet/:sheet_id", post(handle_sheet_webhook))
         .route("/api/sheet/comment", post(handle_add_comment))
         .route("/api/sheet/comment/reply", post(handle_reply_comment))
         .route("/api/sheet/comment/resolve", post(handle_resolve_comment))
@@ -75,6 +136,8 @@ pub fn configure_sheet_routes() -> Router<Arc<AppState>> {
         .route("/api/sheet/protect", post(handle_protect_sheet))
         .route("/api/sheet/unprotect", post(handle_unprotect_sheet))
         .route("/api/sheet/lock-cells", post(handle_lock_cells))
+        .route("/api/sheet/protect-range", post(handle_protect_range))
+        .route("/api/sheet/protect-range/clear", post(handle_clear_range_protection))
         .route("/api/sheet/external-link", post(handle_add_external_link))
         .route("/api/sheet/external-link/refresh", post(handle_refresh_external_link))
         .route("/api/sheet/external-link/remove", post(handle_remove_external_link))
@@ -85,6 +148,10 @@ pub fn configure_sheet_routes() -> Router<Arc<AppState>> {
         .route("/api/sheet/named-range/update", post(handle_update_named_range))
         .route("/api/sheet/named-range/delete", post(handle_delete_named_range))
         .route("/api/sheet/named-ranges", get(handle_list_named_ranges))
+        .route("/api/sheet/worksheet", post(handle_add_worksheet))
+        .route("/api/sheet/worksheet/rename", post(handle_rename_worksheet))
+        .route("/api/sheet/worksheet/delete", post(handle_delete_worksheet))
+        .route("/api/sheet/worksheet/move", post(handle_move_worksheet))
         .route("/api/sheet/:sheet_id/presence", get(handle_get_presence))
         .route("/api/sheet/:sheet_id/typing", get(handle_get_typing))
         .route("/api/sheet/:sheet_id/selections", get(handle_get_selections))
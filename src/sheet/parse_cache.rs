@@ -0,0 +1,165 @@
+//! In-memory cache of spreadsheet files parsed from the drive.
+//!
+//! Entries are keyed by bucket, object key and the CSV options used to parse them, and
+//! remember a version tag for the object (its size and modification time): when the file
+//! changes in the drive its tag changes and the stale parse is replaced. The cache keeps at most [`MAX_ENTRIES`] files, evicting the
+//! least recently used one.
+
+use crate::sheet::csv_import::CsvOptions;
+use crate::sheet::types::Worksheet;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+const MAX_ENTRIES: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseCacheKey {
+    pub bucket: String,
+    pub key: String,
+    pub csv_options: CsvOptions,
+}
+
+struct Entry {
+    etag: String,
+    worksheets: Arc<Vec<Worksheet>>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+pub struct ParseCache {
+    entries: Mutex<HashMap<ParseCacheKey, Entry>>,
+    clock: AtomicU64,
+}
+
+static PARSED_SHEETS: LazyLock<ParseCache> = LazyLock::new(ParseCache::default);
+
+pub fn parsed_sheets() -> &'static ParseCache {
+    &PARSED_SHEETS
+}
+
+impl ParseCache {
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Worksheets parsed from the object version identified by `etag`, if cached.
+    pub fn get(&self, key: &ParseCacheKey, etag: &str) -> Option<Arc<Vec<Worksheet>>> {
+        let mut entries = self.entries.lock().ok()?;
+        let entry = entries.get_mut(key).filter(|entry| entry.etag == etag)?;
+        entry.last_used = self.tick();
+        Some(Arc::clone(&entry.worksheets))
+    }
+
+    pub fn insert(&self, key: ParseCacheKey, etag: String, worksheets: Arc<Vec<Worksheet>>) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if !entries.contains_key(&key) && entries.len() >= MAX_ENTRIES {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key,
+            Entry {
+                etag,
+                worksheets,
+                last_used: self.tick(),
+            },
+        );
+    }
+
+    /// Returns the cached parse for `etag`, or runs `parse` and caches its result. Objects
+    /// without a version tag are never cached.
+    pub fn get_or_parse<F>(
+        &self,
+        key: ParseCacheKey,
+        etag: Option<&str>,
+        parse: F,
+    ) -> Result<Arc<Vec<Worksheet>>, String>
+    where
+        F: FnOnce() -> Result<Vec<Worksheet>, String>,
+    {
+        let Some(etag) = etag else {
+            return parse().map(Arc::new);
+        };
+        if let Some(worksheets) = self.get(&key, etag) {
+            return Ok(worksheets);
+        }
+        let worksheets = Arc::new(parse()?);
+        self.insert(key, etag.to_string(), Arc::clone(&worksheets));
+        Ok(worksheets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::create_new_spreadsheet;
+    use std::cell::Cell;
+
+    fn key(path: &str) -> ParseCacheKey {
+        ParseCacheKey {
+            bucket: "gbo".to_string(),
+            key: path.to_string(),
+            csv_options: CsvOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_unchanged_etag_skips_parsing() {
+        let cache = ParseCache::default();
+        let parses = Cell::new(0);
+        let parse = || {
+            parses.set(parses.get() + 1);
+            Ok(create_new_spreadsheet().worksheets)
+        };
+
+        cache
+            .get_or_parse(key("a.xlsx"), Some("\"v1\""), parse)
+            .unwrap();
+        let cached = cache
+            .get_or_parse(key("a.xlsx"), Some("\"v1\""), parse)
+            .unwrap();
+        assert_eq!(parses.get(), 1);
+        assert_eq!(cached.len(), 1);
+
+        // A new version of the file, or another file, is parsed again.
+        cache
+            .get_or_parse(key("a.xlsx"), Some("\"v2\""), parse)
+            .unwrap();
+        cache
+            .get_or_parse(key("b.xlsx"), Some("\"v2\""), parse)
+            .unwrap();
+        assert_eq!(parses.get(), 3);
+
+        // Without an ETag nothing is cached.
+        cache.get_or_parse(key("c.csv"), None, parse).unwrap();
+        cache.get_or_parse(key("c.csv"), None, parse).unwrap();
+        assert_eq!(parses.get(), 5);
+    }
+
+    #[test]
+    fn test_least_recently_used_entry_is_evicted() {
+        let cache = ParseCache::default();
+        let worksheets = Arc::new(create_new_spreadsheet().worksheets);
+        for i in 0..MAX_ENTRIES {
+            cache.insert(
+                key(&format!("{i}.csv")),
+                "e".into(),
+                Arc::clone(&worksheets),
+            );
+        }
+        assert!(cache.get(&key("0.csv"), "e").is_some());
+
+        cache.insert(key("new.csv"), "e".into(), worksheets);
+        assert!(cache.get(&key("0.csv"), "e").is_some());
+        assert!(cache.get(&key("1.csv"), "e").is_none());
+        assert!(cache.get(&key("new.csv"), "e").is_some());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::error::SheetError;
+use crate::sheet::export::column_to_letter;
+use crate::sheet::formulas::parse_cell_key;
+use crate::sheet::types::{CellData, ProtectedRange, Spreadsheet, Worksheet};
+use std::collections::HashSet;
+
+/// Owners (and administrators) can edit every cell and manage protection. The owner is
+/// the user who created the sheet; the shared workspace user that stores sheets is
+/// nobody's identity.
+pub fn is_sheet_owner(sheet: &Spreadsheet, user: &AuthenticatedUser) -> bool {
+    if user.is_admin() {
+        return true;
+    }
+    if !user.is_authenticated() {
+        return false;
+    }
+    let user_id = user.user_id.to_string();
+    sheet.created_by.as_deref() == Some(user_id.as_str()) || sheet.owner_id == user_id
+}
+
+pub fn ensure_sheet_owner(sheet: &Spreadsheet, user: &AuthenticatedUser) -> Result<(), SheetError> {
+    if is_sheet_owner(sheet, user) {
+        Ok(())
+    } else {
+        Err(SheetError::PermissionDenied(
+            "Only the sheet owner can manage protection".to_string(),
+        ))
+    }
+}
+
+/// Whether the worksheet has sheet protection or any protected range.
+pub fn is_protected(worksheet: &Worksheet) -> bool {
+    worksheet.protection.is_some()
+        || worksheet
+            .protected_ranges
+            .as_ref()
+            .is_some_and(|ranges| !ranges.is_empty())
+}
+
+fn cell_label(row: u32, col: u32) -> String {
+    format!("{}{}", column_to_letter(col), row + 1)
+}
+
+impl ProtectedRange {
+    pub fn contains(&self, row: u32, col: u32) -> bool {
+        (self.start_row..=self.end_row).contains(&row) && (self.start_col..=self.end_col).contains(&col)
+    }
+
+    fn intersects(&self, start_row: u32, start_col: u32, end_row: u32, end_col: u32) -> bool {
+        self.start_row <= end_row
+            && start_row <= self.end_row
+            && self.start_col <= end_col
+            && start_col <= self.end_col
+    }
+
+    pub fn allows(&self, user_id: &str) -> bool {
+        self.editors.iter().any(|editor| editor == user_id)
+    }
+}
+
+/// Whether the cell is locked for `user_id`: inside a protected range they are not an
+/// editor of, or marked locked while the worksheet itself is protected.
+fn is_locked_for(worksheet: &Worksheet, row: u32, col: u32, user_id: &str) -> bool {
+    let in_range = worksheet
+        .protected_ranges
+        .iter()
+        .flatten()
+        .any(|range| range.contains(row, col) && !range.allows(user_id));
+    if in_range {
+        return true;
+    }
+
+    match &worksheet.protection {
+        Some(protection) if protection.protected => {
+            let key = format!("{row},{col}");
+            worksheet.data.get(&key).and_then(|c| c.locked) == Some(true)
+                || protection.locked_cells.contains(&key)
+        }
+        _ => false,
+    }
+}
+
+fn worksheet_at(sheet: &Spreadsheet, worksheet_index: usize) -> Result<&Worksheet, SheetError> {
+    sheet
+        .worksheets
+        .get(worksheet_index)
+        .ok_or(SheetError::InvalidWorksheet)
+}
+
+pub fn ensure_cell_editable(
+    sheet: &Spreadsheet,
+    worksheet_index: usize,
+    row: u32,
+    col: u32,
+    user: &AuthenticatedUser,
+) -> Result<(), SheetError> {
+    ensure_range_editable(sheet, worksheet_index, row, col, row, col, user)
+}
+
+pub fn ensure_range_editable(
+    sheet: &Spreadsheet,
+    worksheet_index: usize,
+    start_row: u32,
+    start_col: u32,
+    end_row: u32,
+    end_col: u32,
+    user: &AuthenticatedUser,
+) -> Result<(), SheetError> {
+    let worksheet = worksheet_at(sheet, worksheet_index)?;
+    if is_sheet_owner(sheet, user) {
+        return Ok(());
+    }
+    let user_id = user.user_id.to_string();
+
+    if let Some(range) = worksheet.protected_ranges.iter().flatten().find(|range| {
+        range.intersects(start_row, start_col, end_row, end_col) && !range.allows(&user_id)
+    }) {
+        let row = range.start_row.max(start_row);
+        let col = range.start_col.max(start_col);
+        return Err(SheetError::CellProtected(cell_label(row, col)));
+    }
+
+    if worksheet.protection.as_ref().is_some_and(|p| p.protected) {
+        let keys = worksheet
+            .data
+            .keys()
+            .chain(worksheet.protection.iter().flat_map(|p| p.locked_cells.iter()));
+        for key in keys {
+            let Some((row, col)) = parse_cell_key(key) else {
+                continue;
+            };
+            if (start_row..=end_row).contains(&row)
+                && (start_col..=end_col).contains(&col)
+                && is_locked_for(worksheet, row, col, &user_id)
+            {
+                return Err(SheetError::CellProtected(cell_label(row, col)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn same_content(a: Option<&CellData>, b: Option<&CellData>) -> bool {
+    let content = |cell: Option<&CellData>| {
+        cell.map(|c| (c.value.clone(), c.formula.clone()))
+            .unwrap_or_default()
+    };
+    content(a) == content(b)
+}
+
+/// Checks a full save of `worksheets` over `existing`: non-owners may not change
+/// protection settings or the content of any cell locked for them.
+pub fn ensure_worksheets_editable(
+    existing: &Spreadsheet,
+    worksheets: &[Worksheet],
+    user: &AuthenticatedUser,
+) -> Result<(), SheetError> {
+    if is_sheet_owner(existing, user) {
+        return Ok(());
+    }
+    let user_id = user.user_id.to_string();
+
+    for (index, old) in existing.worksheets.iter().enumerate() {
+        let new = worksheets.get(index);
+        let protection_changed = match new {
+            Some(new) => {
+                new.protected_ranges != old.protected_ranges
+                    || serde_json::to_value(&new.protection).ok()
+                        != serde_json::to_value(&old.protection).ok()
+            }
+            None => old.protected_ranges.is_some() || old.protection.is_some(),
+        };
+        if protection_changed {
+            return Err(SheetError::PermissionDenied(
+                "Only the sheet owner can change protection".to_string(),
+            ));
+        }
+        let Some(new) = new else {
+            continue;
+        };
+
+        let keys: HashSet<&String> = old.data.keys().chain(new.data.keys()).collect();
+        for key in keys {
+            if same_content(old.data.get(key), new.data.get(key)) {
+                continue;
+            }
+            if let Some((row, col)) = parse_cell_key(key) {
+                if is_locked_for(old, row, col, &user_id) {
+                    return Err(SheetError::CellProtected(cell_label(row, col)));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::{create_new_spreadsheet, get_current_user_id};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn user(id: Uuid) -> AuthenticatedUser {
+        AuthenticatedUser::new(id, "tester".to_string())
+    }
+
+    fn protected_sheet(owner: Uuid, editor: Uuid) -> Spreadsheet {
+        let mut sheet = create_new_spreadsheet();
+        sheet.created_by = Some(owner.to_string());
+        sheet.worksheets[0].protected_ranges = Some(vec![ProtectedRange {
+            id: "r1".to_string(),
+            start_row: 0,
+            start_col: 0,
+            end_row: 2,
+            end_col: 1,
+            editors: vec![editor.to_string()],
+            description: None,
+            created_by: owner.to_string(),
+            created_at: Utc::now(),
+        }]);
+        sheet
+    }
+
+    #[test]
+    fn test_protected_cell_rejected_for_collaborator() {
+        let (owner, editor, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let sheet = protected_sheet(owner, editor);
+
+        let err = ensure_cell_editable(&sheet, 0, 1, 1, &user(other)).unwrap_err();
+        assert_eq!(err.code(), "CELL_PROTECTED");
+        assert_eq!(err.to_string(), "Cell B2 is protected");
+
+        assert!(ensure_cell_editable(&sheet, 0, 1, 1, &user(owner)).is_ok());
+        assert!(ensure_cell_editable(&sheet, 0, 1, 1, &user(editor)).is_ok());
+        assert!(ensure_cell_editable(&sheet, 0, 5, 5, &user(other)).is_ok());
+        assert!(ensure_range_editable(&sheet, 0, 2, 1, 9, 9, &user(other)).is_err());
+    }
+
+    #[test]
+    fn test_full_save_keeps_protection() {
+        let (owner, editor, other) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let sheet = protected_sheet(owner, editor);
+
+        let mut edited = sheet.worksheets.clone();
+        edited[0].data.insert(
+            "0,0".to_string(),
+            CellData {
+                value: Some("x".to_string()),
+                formula: None,
+                style: None,
+                format: None,
+                note: None,
+                locked: None,
+                has_comment: None,
+                array_formula_id: None,
+            },
+        );
+        assert!(ensure_worksheets_editable(&sheet, &edited, &user(other)).is_err());
+        assert!(ensure_worksheets_editable(&sheet, &edited, &user(owner)).is_ok());
+
+        let mut unprotected = sheet.worksheets.clone();
+        unprotected[0].protected_ranges = None;
+        let err = ensure_worksheets_editable(&sheet, &unprotected, &user(editor)).unwrap_err();
+        assert_eq!(err.code(), "PERMISSION_DENIED");
+    }
+
+    #[test]
+    fn test_storage_owner_is_not_a_sheet_owner() {
+        let (owner, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let sheet = protected_sheet(owner, owner);
+        assert_eq!(sheet.owner_id, get_current_user_id());
+
+        assert!(is_sheet_owner(&sheet, &user(owner)));
+        assert!(!is_sheet_owner(&sheet, &user(other)));
+        assert!(!is_sheet_owner(&sheet, &AuthenticatedUser::anonymous()));
+    }
+}
@@ -0,0 +1,519 @@
+//! Named sheet snapshots.
+//!
+//! A snapshot is a labeled copy of a sheet's full state ("Q1 final"). Each snapshot is its
+//! own drive object next to the user's sheets, listed by a small per-sheet index, so taking
+//! one never rewrites the others. A sheet keeps at most [`MAX_SNAPSHOTS`]; the oldest are
+//! dropped first. Restoring never drops history: the state being replaced is first kept as
+//! a snapshot of its own, so a restore can itself be undone.
+
+use crate::core::shared::state::AppState;
+use crate::drive::object_store::{ObjectStore, ObjectStoreError};
+use crate::drive::quota;
+use crate::sheet::error::SheetError;
+use crate::sheet::types::Spreadsheet;
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use uuid::Uuid;
+
+/// Longest accepted snapshot label, in characters.
+pub const MAX_LABEL_LEN: usize = 120;
+
+/// Snapshots kept per sheet. Taking one more drops the oldest.
+pub const MAX_SNAPSHOTS: usize = 50;
+
+/// Author id of snapshots the server takes itself, e.g. when compacting the change feed.
+/// They are never dropped to make room, because clients may be told to resync from them.
+pub const SYSTEM_AUTHOR_ID: &str = "system";
+
+/// Who took or restored a snapshot, as shown to collaborators.
+#[derive(Debug, Clone)]
+pub struct SnapshotAuthor {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SheetSnapshot {
+    pub id: String,
+    pub label: String,
+    pub author_id: String,
+    pub author_name: String,
+    pub created_at: DateTime<Utc>,
+    pub sheet: Spreadsheet,
+}
+
+/// A snapshot without its sheet state, as listed to clients and kept in the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: String,
+    pub label: String,
+    pub author_id: String,
+    pub author_name: String,
+    pub created_at: DateTime<Utc>,
+    pub worksheet_count: usize,
+}
+
+impl From<&SheetSnapshot> for SnapshotInfo {
+    fn from(snapshot: &SheetSnapshot) -> Self {
+        Self {
+            id: snapshot.id.clone(),
+            label: snapshot.label.clone(),
+            author_id: snapshot.author_id.clone(),
+            author_name: snapshot.author_name.clone(),
+            created_at: snapshot.created_at,
+            worksheet_count: snapshot.sheet.worksheets.len(),
+        }
+    }
+}
+
+/// Held while a sheet's snapshots are read or changed, so concurrent writers of one sheet
+/// cannot drop each other's index entries.
+static SNAPSHOT_LOCKS: LazyLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn snapshot_dir(user_id: &str, sheet_id: &str) -> String {
+    format!("users/{}/sheet-snapshots/{}", user_id, sheet_id)
+}
+
+fn index_path(user_id: &str, sheet_id: &str) -> String {
+    format!("{}/index.json", snapshot_dir(user_id, sheet_id))
+}
+
+fn snapshot_path(user_id: &str, sheet_id: &str, snapshot_id: &str) -> String {
+    format!("{}/{}.json", snapshot_dir(user_id, sheet_id), snapshot_id)
+}
+
+/// Where all of a sheet's snapshots were kept in one file before they were split up.
+fn legacy_path(user_id: &str, sheet_id: &str) -> String {
+    format!("{}.json", snapshot_dir(user_id, sheet_id))
+}
+
+/// Trims `label` and checks it is non-empty and at most [`MAX_LABEL_LEN`] characters.
+pub fn validate_label(label: &str) -> Result<String, SheetError> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err(SheetError::InvalidRequest(
+            "Snapshot label is required".to_string(),
+        ));
+    }
+    if label.chars().count() > MAX_LABEL_LEN {
+        return Err(SheetError::InvalidRequest(format!(
+            "Snapshot label exceeds {MAX_LABEL_LEN} characters"
+        )));
+    }
+    Ok(label.to_string())
+}
+
+/// A snapshot of `sheet`'s current state.
+pub fn new_snapshot(sheet: &Spreadsheet, label: &str, author: &SnapshotAuthor) -> SheetSnapshot {
+    SheetSnapshot {
+        id: Uuid::new_v4().to_string(),
+        label: label.to_string(),
+        author_id: author.id.clone(),
+        author_name: author.name.clone(),
+        created_at: Utc::now(),
+        sheet: sheet.clone(),
+    }
+}
+
+/// Returns `current` with the state of `snapshot`. The sheet keeps its id, owner and
+/// creation time.
+pub fn restored_sheet(snapshot: &SheetSnapshot, current: &Spreadsheet) -> Spreadsheet {
+    let mut restored = snapshot.sheet.clone();
+    restored.id = current.id.clone();
+    restored.owner_id = current.owner_id.clone();
+    restored.created_by = current.created_by.clone();
+    restored.created_at = current.created_at;
+    restored.updated_at = Utc::now();
+    restored
+}
+
+/// The label of the snapshot that keeps the state replaced by restoring `snapshot`.
+pub fn before_restore_label(snapshot: &SheetSnapshot) -> String {
+    format!("Before restoring \"{}\"", snapshot.label)
+}
+
+/// Removes the oldest entries beyond `max` from `index`, sparing server-taken snapshots,
+/// and returns them.
+fn prune(index: &mut Vec<SnapshotInfo>, max: usize) -> Vec<SnapshotInfo> {
+    let mut excess = index.len().saturating_sub(max);
+    let mut removed = Vec::new();
+    index.retain(|info| {
+        if excess > 0 && info.author_id != SYSTEM_AUTHOR_ID {
+            excess -= 1;
+            removed.push(info.clone());
+            false
+        } else {
+            true
+        }
+    });
+    removed
+}
+
+/// Runs `f` while holding the sheet's snapshot lock.
+async fn with_sheet_lock<T, Fut>(user_id: &str, sheet_id: &str, f: impl FnOnce() -> Fut) -> T
+where
+    Fut: std::future::Future<Output = T>,
+{
+    let key = format!("{user_id}/{sheet_id}");
+    let lock = {
+        let mut locks = SNAPSHOT_LOCKS.lock().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(locks.entry(key.clone()).or_default())
+    };
+    let result = {
+        let _guard = lock.lock().await;
+        f().await
+    };
+    if let Ok(mut locks) = SNAPSHOT_LOCKS.lock() {
+        // Only this call and the map hold the lock: nobody else is waiting on the sheet.
+        if Arc::strong_count(&lock) == 2 {
+            locks.remove(&key);
+        }
+    }
+    result
+}
+
+fn storage_error(action: &str, e: impl std::fmt::Display) -> SheetError {
+    SheetError::StorageFailed(format!("Failed to {action}: {e}"))
+}
+
+/// Writes `value` as JSON to `path`, counting its size against the drive quota.
+async fn put_json<T: Serialize + ?Sized>(
+    state: &Arc<AppState>,
+    store: &dyn ObjectStore,
+    path: &str,
+    value: &T,
+) -> Result<(), SheetError> {
+    let content = serde_json::to_vec(value)
+        .map_err(|e| SheetError::StorageFailed(format!("Serialization error: {e}")))?;
+    let delta = quota::check_put(state, store, "gbo", path, content.len() as u64)
+        .await
+        .map_err(|e| SheetError::QuotaExceeded(e.to_string()))?;
+    store
+        .put("gbo", path, content, "application/json")
+        .await
+        .map_err(|e| storage_error("save snapshots", e))?;
+    quota::record_change(state, "gbo", path, delta).await;
+    Ok(())
+}
+
+async fn delete_object(state: &Arc<AppState>, store: &dyn ObjectStore, path: &str) {
+    let size = quota::size_before_delete(store, "gbo", path).await;
+    match store.delete("gbo", path).await {
+        Ok(()) => quota::record_change(state, "gbo", path, -(size as i64)).await,
+        Err(ObjectStoreError::NotFound(_)) => {}
+        Err(e) => warn!("Failed to delete snapshot object {path}: {e}"),
+    }
+}
+
+/// Reads the sheet's snapshot index, oldest first, moving snapshots still kept in the
+/// legacy single file into their own objects. Call with the sheet's lock held.
+async fn load_index(
+    state: &Arc<AppState>,
+    store: &dyn ObjectStore,
+    user_id: &str,
+    sheet_id: &str,
+) -> Result<Vec<SnapshotInfo>, SheetError> {
+    match store.get("gbo", &index_path(user_id, sheet_id)).await {
+        Ok(bytes) => {
+            return serde_json::from_slice(&bytes).map_err(|e| storage_error("parse snapshots", e))
+        }
+        Err(ObjectStoreError::NotFound(_)) => {}
+        Err(e) => return Err(storage_error("load snapshots", e)),
+    }
+
+    let legacy = legacy_path(user_id, sheet_id);
+    let snapshots: Vec<SheetSnapshot> = match store.get("gbo", &legacy).await {
+        Ok(bytes) => {
+            serde_json::from_slice(&bytes).map_err(|e| storage_error("parse snapshots", e))?
+        }
+        Err(ObjectStoreError::NotFound(_)) => return Ok(Vec::new()),
+        Err(e) => return Err(storage_error("load snapshots", e)),
+    };
+    for snapshot in &snapshots {
+        put_json(
+            state,
+            store,
+            &snapshot_path(user_id, sheet_id, &snapshot.id),
+            snapshot,
+        )
+        .await?;
+    }
+    let index: Vec<SnapshotInfo> = snapshots.iter().map(SnapshotInfo::from).collect();
+    put_json(state, store, &index_path(user_id, sheet_id), &index).await?;
+    delete_object(state, store, &legacy).await;
+    Ok(index)
+}
+
+fn drive(state: &Arc<AppState>) -> Result<&dyn ObjectStore, SheetError> {
+    state
+        .object_store
+        .as_deref()
+        .ok_or(SheetError::DriveUnavailable)
+}
+
+/// Lists a sheet's snapshots, oldest first.
+pub async fn list_snapshots(
+    state: &Arc<AppState>,
+    user_id: &str,
+    sheet_id: &str,
+) -> Result<Vec<SnapshotInfo>, SheetError> {
+    let store = drive(state)?;
+    with_sheet_lock(user_id, sheet_id, || {
+        load_index(state, store, user_id, sheet_id)
+    })
+    .await
+}
+
+/// Reads one snapshot with its full sheet state.
+pub async fn read_snapshot(
+    state: &Arc<AppState>,
+    user_id: &str,
+    sheet_id: &str,
+    snapshot_id: &str,
+) -> Result<SheetSnapshot, SheetError> {
+    let store = drive(state)?;
+    with_sheet_lock(user_id, sheet_id, || async {
+        let index = load_index(state, store, user_id, sheet_id).await?;
+        if !index.iter().any(|info| info.id == snapshot_id) {
+            return Err(SheetError::SnapshotNotFound(snapshot_id.to_string()));
+        }
+        let bytes = match store
+            .get("gbo", &snapshot_path(user_id, sheet_id, snapshot_id))
+            .await
+        {
+            Ok(bytes) => bytes,
+            Err(ObjectStoreError::NotFound(_)) => {
+                return Err(SheetError::SnapshotNotFound(snapshot_id.to_string()))
+            }
+            Err(e) => return Err(storage_error("load snapshot", e)),
+        };
+        serde_json::from_slice(&bytes).map_err(|e| storage_error("parse snapshot", e))
+    })
+    .await
+}
+
+/// Stores `snapshot` and adds it to the sheet's index, dropping the oldest snapshots
+/// beyond [`MAX_SNAPSHOTS`].
+pub async fn save_snapshot(
+    state: &Arc<AppState>,
+    user_id: &str,
+    sheet_id: &str,
+    snapshot: &SheetSnapshot,
+) -> Result<SnapshotInfo, SheetError> {
+    let store = drive(state)?;
+    with_sheet_lock(user_id, sheet_id, || async {
+        let mut index = load_index(state, store, user_id, sheet_id).await?;
+        let path = snapshot_path(user_id, sheet_id, &snapshot.id);
+        put_json(state, store, &path, snapshot).await?;
+
+        let info = SnapshotInfo::from(snapshot);
+        index.push(info.clone());
+        let removed = prune(&mut index, MAX_SNAPSHOTS);
+
+        if let Err(e) = put_json(state, store, &index_path(user_id, sheet_id), &index).await {
+            delete_object(state, store, &path).await;
+            return Err(e);
+        }
+        for old in removed {
+            delete_object(state, store, &snapshot_path(user_id, sheet_id, &old.id)).await;
+        }
+        Ok(info)
+    })
+    .await
+}
+
+/// Deletes one snapshot, e.g. a compaction snapshot that was superseded. Failures are
+/// logged.
+pub async fn delete_snapshot(
+    state: &Arc<AppState>,
+    user_id: &str,
+    sheet_id: &str,
+    snapshot_id: &str,
+) {
+    let Ok(store) = drive(state) else {
+        return;
+    };
+    let deleted = with_sheet_lock(user_id, sheet_id, || async {
+        let mut index = load_index(state, store, user_id, sheet_id).await?;
+        let before = index.len();
+        index.retain(|info| info.id != snapshot_id);
+        if index.len() != before {
+            put_json(state, store, &index_path(user_id, sheet_id), &index).await?;
+        }
+        delete_object(state, store, &snapshot_path(user_id, sheet_id, snapshot_id)).await;
+        Ok::<_, SheetError>(())
+    })
+    .await;
+    if let Err(e) = deleted {
+        warn!("Failed to delete snapshot {snapshot_id} of sheet {sheet_id}: {e}");
+    }
+}
+
+/// Deletes a sheet's snapshots along with the sheet.
+pub async fn delete_snapshots(state: &Arc<AppState>, user_id: &str, sheet_id: &str) {
+    let Ok(store) = drive(state) else {
+        return;
+    };
+    with_sheet_lock(user_id, sheet_id, || async {
+        let prefix = format!("{}/", snapshot_dir(user_id, sheet_id));
+        match store.list("gbo", &prefix).await {
+            Ok(objects) => {
+                for object in objects {
+                    delete_object(state, store, &object.key).await;
+                }
+            }
+            Err(e) => warn!("Failed to list snapshots of sheet {sheet_id}: {e}"),
+        }
+        delete_object(state, store, &legacy_path(user_id, sheet_id)).await;
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::shared::test_utils::TestAppStateBuilder;
+    use crate::drive::object_store::FsObjectStore;
+    use crate::sheet::storage::create_new_spreadsheet;
+    use crate::sheet::types::CellData;
+
+    fn author() -> SnapshotAuthor {
+        SnapshotAuthor {
+            id: "u1".to_string(),
+            name: "ana@example.com".to_string(),
+        }
+    }
+
+    fn set_value(sheet: &mut Spreadsheet, key: &str, value: &str) {
+        sheet.worksheets[0].data.insert(
+            key.to_string(),
+            CellData {
+                value: Some(value.to_string()),
+                formula: None,
+                style: None,
+                format: None,
+                note: None,
+                locked: None,
+                has_comment: None,
+                array_formula_id: None,
+            },
+        );
+    }
+
+    fn cell_value(sheet: &Spreadsheet, key: &str) -> Option<String> {
+        sheet.worksheets[0].data.get(key)?.value.clone()
+    }
+
+    fn info(id: &str, author_id: &str) -> SnapshotInfo {
+        SnapshotInfo {
+            id: id.to_string(),
+            label: id.to_string(),
+            author_id: author_id.to_string(),
+            author_name: String::new(),
+            created_at: Utc::now(),
+            worksheet_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_create_edit_restore_returns_snapshotted_state() {
+        let mut sheet = create_new_spreadsheet();
+        sheet.name = "Budget".to_string();
+        set_value(&mut sheet, "0,0", "100");
+        let snapshot = new_snapshot(&sheet, "Q1 final", &author());
+
+        set_value(&mut sheet, "0,0", "250");
+        set_value(&mut sheet, "1,0", "extra");
+        sheet.name = "Budget v2".to_string();
+
+        let restored = restored_sheet(&snapshot, &sheet);
+        assert_eq!(restored.id, sheet.id);
+        assert_eq!(restored.name, "Budget");
+        assert_eq!(cell_value(&restored, "0,0").as_deref(), Some("100"));
+        assert_eq!(cell_value(&restored, "1,0"), None);
+        assert_eq!(
+            before_restore_label(&snapshot),
+            "Before restoring \"Q1 final\""
+        );
+    }
+
+    #[test]
+    fn test_prune_drops_oldest_but_keeps_system_snapshots() {
+        let mut index = vec![
+            info("compacted", SYSTEM_AUTHOR_ID),
+            info("a", "u1"),
+            info("b", "u1"),
+            info("c", "u1"),
+        ];
+        let removed = prune(&mut index, 2);
+        let removed: Vec<_> = removed.iter().map(|i| i.id.as_str()).collect();
+        let kept: Vec<_> = index.iter().map(|i| i.id.as_str()).collect();
+        assert_eq!(removed, ["a", "b"]);
+        assert_eq!(kept, ["compacted", "c"]);
+    }
+
+    #[test]
+    fn test_validate_label() {
+        assert_eq!(validate_label("  Q1 final ").unwrap(), "Q1 final");
+        assert!(validate_label("   ").is_err());
+        assert!(validate_label(&"x".repeat(MAX_LABEL_LEN + 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snapshots_are_separate_objects_and_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let store: Arc<dyn ObjectStore> = Arc::new(FsObjectStore::new(dir.path()));
+        let Ok(state) = TestAppStateBuilder::new()
+            .with_object_store(Arc::clone(&store))
+            .build()
+        else {
+            eprintln!("Skipping test_snapshots_are_separate_objects_and_capped: no test database");
+            return;
+        };
+        let state = Arc::new(state);
+        let user = "default-user";
+        let sheet = create_new_spreadsheet();
+        assert!(list_snapshots(&state, user, &sheet.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mut first = None;
+        for n in 0..=MAX_SNAPSHOTS {
+            let snapshot = new_snapshot(&sheet, &format!("v{n}"), &author());
+            save_snapshot(&state, user, &sheet.id, &snapshot)
+                .await
+                .unwrap();
+            first.get_or_insert(snapshot.id);
+        }
+
+        let index = list_snapshots(&state, user, &sheet.id).await.unwrap();
+        assert_eq!(index.len(), MAX_SNAPSHOTS);
+        assert_eq!(index[0].label, "v1");
+        let first = first.unwrap();
+        let err = read_snapshot(&state, user, &sheet.id, &first)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "SNAPSHOT_NOT_FOUND");
+        let newest = read_snapshot(&state, user, &sheet.id, &index[MAX_SNAPSHOTS - 1].id)
+            .await
+            .unwrap();
+        assert_eq!(newest.label, format!("v{MAX_SNAPSHOTS}"));
+
+        let objects = store
+            .list("gbo", &format!("{}/", snapshot_dir(user, &sheet.id)))
+            .await
+            .unwrap();
+        assert_eq!(objects.len(), MAX_SNAPSHOTS + 1);
+
+        delete_snapshots(&state, user, &sheet.id).await;
+        assert!(list_snapshots(&state, user, &sheet.id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
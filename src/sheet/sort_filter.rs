@@ -0,0 +1,308 @@
+use crate::sheet::error::SheetError;
+use crate::sheet::types::{CellData, FilterConfig, SortRequest, Worksheet};
+use std::cmp::Ordering;
+
+/// Number of leading rows that sort and filter leave alone.
+pub fn header_rows(worksheet: &Worksheet) -> u32 {
+    u32::from(worksheet.has_header.unwrap_or(false))
+}
+
+/// Marks row 0 as the header and freezes it, or clears the mark. Turning the header off
+/// keeps the frozen panes as they are. Filters are re-applied since the header row is
+/// never hidden.
+pub fn set_header(worksheet: &mut Worksheet, has_header: bool) {
+    if has_header {
+        worksheet.has_header = Some(true);
+        worksheet.frozen_rows = Some(worksheet.frozen_rows.unwrap_or(0).max(1));
+    } else {
+        worksheet.has_header = None;
+    }
+    apply_filters(worksheet);
+}
+
+/// Numbers compare numerically, everything else as text.
+fn compare_values(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(na), Ok(nb)) => na.partial_cmp(&nb).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+/// Sorts the rows of the request's range by `sort_col`. When the worksheet has a header,
+/// row 0 is excluded even if the range includes it.
+pub fn sort_range(worksheet: &mut Worksheet, req: &SortRequest) -> Result<(), SheetError> {
+    if req.start_row > req.end_row || req.start_col > req.end_col {
+        return Err(SheetError::InvalidRequest(
+            "Range start must not be after its end".to_string(),
+        ));
+    }
+    if !(req.start_col..=req.end_col).contains(&req.sort_col) {
+        return Err(SheetError::InvalidRequest(
+            "Sort column must be inside the range".to_string(),
+        ));
+    }
+
+    let start_row = req.start_row.max(header_rows(worksheet));
+    if start_row > req.end_row {
+        return Ok(());
+    }
+
+    let mut rows: Vec<Vec<Option<CellData>>> = Vec::new();
+    for row in start_row..=req.end_row {
+        let mut row_data = Vec::new();
+        for col in req.start_col..=req.end_col {
+            let key = format!("{},{}", row, col);
+            row_data.push(worksheet.data.get(&key).cloned());
+        }
+        rows.push(row_data);
+    }
+
+    let sort_col_idx = (req.sort_col - req.start_col) as usize;
+    let sort_value = |row: &[Option<CellData>]| {
+        row.get(sort_col_idx)
+            .and_then(|c| c.as_ref())
+            .and_then(|c| c.value.clone())
+            .unwrap_or_default()
+    };
+    rows.sort_by(|a, b| {
+        let cmp = compare_values(&sort_value(a), &sort_value(b));
+        if req.ascending {
+            cmp
+        } else {
+            cmp.reverse()
+        }
+    });
+
+    for (row_offset, row_data) in rows.iter().enumerate() {
+        for (col_offset, cell) in row_data.iter().enumerate() {
+            let key = format!(
+                "{},{}",
+                start_row + row_offset as u32,
+                req.start_col + col_offset as u32
+            );
+            if let Some(c) = cell {
+                worksheet.data.insert(key, c.clone());
+            } else {
+                worksheet.data.remove(&key);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn matches_condition(filter: &FilterConfig, value: &str) -> bool {
+    let operand = filter.value1.as_deref().unwrap_or_default();
+    let value_lower = value.to_lowercase();
+    let operand_lower = operand.to_lowercase();
+    let equals = || compare_values(&value_lower, &operand_lower) == Ordering::Equal;
+
+    match filter.condition.as_deref().unwrap_or("equals") {
+        "equals" => equals(),
+        "not_equals" => !equals(),
+        "contains" => value_lower.contains(&operand_lower),
+        "not_contains" => !value_lower.contains(&operand_lower),
+        "begins_with" => value_lower.starts_with(&operand_lower),
+        "ends_with" => value_lower.ends_with(&operand_lower),
+        "greater_than" => compare_values(value, operand) == Ordering::Greater,
+        "greater_than_or_equal" => compare_values(value, operand) != Ordering::Less,
+        "less_than" => compare_values(value, operand) == Ordering::Less,
+        "less_than_or_equal" => compare_values(value, operand) != Ordering::Greater,
+        "between" => {
+            let upper = filter.value2.as_deref().unwrap_or_default();
+            compare_values(value, operand) != Ordering::Less
+                && compare_values(value, upper) != Ordering::Greater
+        }
+        "is_empty" => value.trim().is_empty(),
+        "is_not_empty" => !value.trim().is_empty(),
+        _ => true,
+    }
+}
+
+/// Whether a cell value passes `filter`. Unknown filter types and conditions pass
+/// everything, as does a `values` filter with an empty list.
+pub fn matches_filter(filter: &FilterConfig, value: &str) -> bool {
+    match filter.filter_type.as_str() {
+        "values" => filter.values.is_empty() || filter.values.iter().any(|v| v == value),
+        "condition" => matches_condition(filter, value),
+        _ => true,
+    }
+}
+
+/// Recomputes `hidden_rows` from the worksheet's filters. A row is hidden when any
+/// filtered column fails its filter. The header row is never hidden.
+pub fn apply_filters(worksheet: &mut Worksheet) {
+    let filters = match worksheet.filters.as_ref() {
+        Some(filters) if !filters.is_empty() => filters,
+        _ => {
+            worksheet.hidden_rows = None;
+            return;
+        }
+    };
+
+    let last_row = worksheet
+        .data
+        .keys()
+        .filter_map(|key| key.split(',').next()?.parse::<u32>().ok())
+        .max();
+    let Some(last_row) = last_row else {
+        worksheet.hidden_rows = None;
+        return;
+    };
+
+    let hidden: Vec<u32> = (header_rows(worksheet)..=last_row)
+        .filter(|row| {
+            filters.iter().any(|(col, filter)| {
+                let value = worksheet
+                    .data
+                    .get(&format!("{row},{col}"))
+                    .and_then(|c| c.value.as_deref())
+                    .unwrap_or_default();
+                !matches_filter(filter, value)
+            })
+        })
+        .collect();
+    worksheet.hidden_rows = (!hidden.is_empty()).then_some(hidden);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::csv_import::CsvOptions;
+    use crate::sheet::storage::{create_new_spreadsheet, parse_csv_to_worksheets};
+    use std::collections::HashMap;
+
+    fn set(worksheet: &mut Worksheet, row: u32, col: u32, value: &str) {
+        worksheet.data.insert(
+            format!("{row},{col}"),
+            CellData {
+                value: Some(value.to_string()),
+                formula: None,
+                style: None,
+                format: None,
+                note: None,
+                locked: None,
+                has_comment: None,
+                array_formula_id: None,
+            },
+        );
+    }
+
+    fn value(worksheet: &Worksheet, row: u32, col: u32) -> &str {
+        worksheet.data[&format!("{row},{col}")]
+            .value
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    fn scores_sheet() -> Worksheet {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+        worksheet.data.clear();
+        for (row, (name, score)) in [("Name", "Score"), ("ana", "7"), ("bo", "12"), ("cy", "3")]
+            .iter()
+            .enumerate()
+        {
+            set(&mut worksheet, row as u32, 0, name);
+            set(&mut worksheet, row as u32, 1, score);
+        }
+        worksheet
+    }
+
+    fn sort_by_score(ascending: bool) -> SortRequest {
+        SortRequest {
+            sheet_id: "s1".to_string(),
+            worksheet_index: 0,
+            start_row: 0,
+            start_col: 0,
+            end_row: 3,
+            end_col: 1,
+            sort_col: 1,
+            ascending,
+        }
+    }
+
+    #[test]
+    fn test_sort_with_header_keeps_row_zero() {
+        let mut worksheet = scores_sheet();
+        set_header(&mut worksheet, true);
+        assert_eq!(worksheet.frozen_rows, Some(1));
+
+        sort_range(&mut worksheet, &sort_by_score(true)).unwrap();
+        assert_eq!(value(&worksheet, 0, 0), "Name");
+        assert_eq!(value(&worksheet, 0, 1), "Score");
+        let names: Vec<&str> = (1..=3).map(|row| value(&worksheet, row, 0)).collect();
+        assert_eq!(names, ["cy", "ana", "bo"]);
+
+        sort_range(&mut worksheet, &sort_by_score(false)).unwrap();
+        assert_eq!(value(&worksheet, 0, 0), "Name");
+        assert_eq!(value(&worksheet, 1, 0), "bo");
+
+        // Without a header, the label row sorts like any other text.
+        set_header(&mut worksheet, false);
+        sort_range(&mut worksheet, &sort_by_score(false)).unwrap();
+        assert_eq!(value(&worksheet, 0, 0), "Name");
+        assert_eq!(value(&worksheet, 3, 0), "cy");
+        sort_range(&mut worksheet, &sort_by_score(true)).unwrap();
+        assert_eq!(value(&worksheet, 3, 0), "Name");
+
+        let mut outside = sort_by_score(true);
+        outside.sort_col = 4;
+        assert!(sort_range(&mut worksheet, &outside).is_err());
+    }
+
+    #[test]
+    fn test_filters_hide_rows_but_not_header() {
+        let mut worksheet = scores_sheet();
+        set_header(&mut worksheet, true);
+        let mut filters = HashMap::new();
+        filters.insert(
+            1,
+            FilterConfig {
+                filter_type: "condition".to_string(),
+                values: vec![],
+                condition: Some("greater_than".to_string()),
+                value1: Some("5".to_string()),
+                value2: None,
+            },
+        );
+        worksheet.filters = Some(filters);
+        apply_filters(&mut worksheet);
+        assert_eq!(worksheet.hidden_rows, Some(vec![3]));
+
+        worksheet.filters.as_mut().unwrap().insert(
+            0,
+            FilterConfig {
+                filter_type: "values".to_string(),
+                values: vec!["bo".to_string()],
+                condition: None,
+                value1: None,
+                value2: None,
+            },
+        );
+        apply_filters(&mut worksheet);
+        assert_eq!(worksheet.hidden_rows, Some(vec![1, 3]));
+
+        // Without the header flag the label row fails the filters too.
+        set_header(&mut worksheet, false);
+        assert_eq!(worksheet.hidden_rows, Some(vec![0, 1, 3]));
+
+        worksheet.filters = None;
+        apply_filters(&mut worksheet);
+        assert_eq!(worksheet.hidden_rows, None);
+    }
+
+    #[test]
+    fn test_csv_import_marks_header() {
+        let csv = b"name,score\nana,7\n";
+        let options = CsvOptions {
+            header: true,
+            ..Default::default()
+        };
+        let worksheets = parse_csv_to_worksheets(csv, &options, b',', "Scores").unwrap();
+        assert_eq!(worksheets[0].has_header, Some(true));
+        assert_eq!(worksheets[0].frozen_rows, Some(1));
+
+        let plain = parse_csv_to_worksheets(csv, &CsvOptions::default(), b',', "Scores").unwrap();
+        assert_eq!(plain[0].has_header, None);
+        assert_eq!(plain[0].frozen_rows, None);
+    }
+}
@@ -0,0 +1,261 @@
+//! Spilling of dynamic array formulas (`UNIQUE`, `SORT`, `FILTER`).
+//!
+//! The formula lives in its anchor cell; the values it returns are written into the cells
+//! below and to the right of it. Each spilled region is tracked as a dynamic
+//! [`ArrayFormula`] whose id is derived from the anchor, and every cell of the region
+//! carries that id. A region that would overwrite existing content is not written and
+//! the anchor shows `#SPILL!` until the obstruction is cleared.
+
+use crate::sheet::formulas::{evaluate_array_formula, is_dynamic_array_formula, parse_cell_key};
+use crate::sheet::types::{ArrayFormula, CellData, Worksheet};
+
+pub const SPILL_ERROR: &str = "#SPILL!";
+const SPILL_ID_PREFIX: &str = "spill:";
+
+fn spill_id(row: u32, col: u32) -> String {
+    format!("{SPILL_ID_PREFIX}{row},{col}")
+}
+
+pub fn is_spill_id(id: &str) -> bool {
+    id.starts_with(SPILL_ID_PREFIX)
+}
+
+/// Anchor of the spilled region covering `(row, col)`, if any.
+pub fn spill_anchor(worksheet: &Worksheet, row: u32, col: u32) -> Option<(u32, u32)> {
+    let id = worksheet
+        .data
+        .get(&format!("{row},{col}"))?
+        .array_formula_id
+        .as_deref()
+        .filter(|id| is_spill_id(id))?;
+    parse_cell_key(&id[SPILL_ID_PREFIX.len()..])
+}
+
+fn is_empty(cell: &CellData) -> bool {
+    cell.value.is_none()
+        && cell.formula.is_none()
+        && cell.style.is_none()
+        && cell.format.is_none()
+        && cell.note.is_none()
+        && cell.locked.is_none()
+        && cell.has_comment.is_none()
+        && cell.array_formula_id.is_none()
+}
+
+/// Removes every spilled value, keeping anchors and anything users typed.
+fn clear_spills(worksheet: &mut Worksheet) {
+    if let Some(array_formulas) = &mut worksheet.array_formulas {
+        array_formulas.retain(|af| !is_spill_id(&af.id));
+    }
+    worksheet.data.retain(|key, cell| {
+        let Some(id) = cell
+            .array_formula_id
+            .as_deref()
+            .filter(|id| is_spill_id(id))
+        else {
+            return true;
+        };
+        let is_anchor = id[SPILL_ID_PREFIX.len()..] == *key;
+        cell.array_formula_id = None;
+        if !is_anchor {
+            cell.value = None;
+        }
+        !is_empty(cell)
+    });
+}
+
+fn is_blocking(cell: &CellData) -> bool {
+    cell.value.as_deref().is_some_and(|v| !v.is_empty())
+        || cell.formula.is_some()
+        || cell.array_formula_id.is_some()
+}
+
+fn spill(worksheet: &mut Worksheet, (row, col): (u32, u32), formula: &str) {
+    let anchor_key = format!("{row},{col}");
+    let set_anchor = |worksheet: &mut Worksheet, value: String| {
+        if let Some(cell) = worksheet.data.get_mut(&anchor_key) {
+            cell.value = Some(value);
+        }
+    };
+
+    let rows = match evaluate_array_formula(formula, worksheet) {
+        Some(Ok(rows)) => rows,
+        Some(Err(error)) => return set_anchor(worksheet, error),
+        None => return,
+    };
+    let height = rows.len() as u32;
+    let width = rows.iter().map(Vec::len).max().unwrap_or(1).max(1) as u32;
+
+    let blocked = (row..row + height)
+        .flat_map(|r| (col..col + width).map(move |c| (r, c)))
+        .filter(|&cell| cell != (row, col))
+        .any(|(r, c)| {
+            worksheet
+                .data
+                .get(&format!("{r},{c}"))
+                .is_some_and(is_blocking)
+        });
+    if blocked {
+        return set_anchor(worksheet, SPILL_ERROR.to_string());
+    }
+
+    let id = spill_id(row, col);
+    for (r, values) in rows.into_iter().enumerate() {
+        for c in 0..width as usize {
+            let key = format!("{},{}", row + r as u32, col + c as u32);
+            let cell = worksheet.data.entry(key).or_insert_with(|| CellData {
+                value: None,
+                formula: None,
+                style: None,
+                format: None,
+                note: None,
+                locked: None,
+                has_comment: None,
+                array_formula_id: None,
+            });
+            cell.value = Some(values.get(c).cloned().unwrap_or_default());
+            cell.array_formula_id = Some(id.clone());
+        }
+    }
+    worksheet
+        .array_formulas
+        .get_or_insert_with(Vec::new)
+        .push(ArrayFormula {
+            id,
+            formula: formula.to_string(),
+            start_row: row,
+            start_col: col,
+            end_row: row + height - 1,
+            end_col: col + width - 1,
+            is_dynamic: true,
+        });
+}
+
+/// Recomputes every spilled region of the worksheet. Call after any edit: values typed
+/// into a spilled region block it, and clearing them lets the formula spill again.
+pub fn respill_worksheet(worksheet: &mut Worksheet) {
+    clear_spills(worksheet);
+
+    let mut anchors: Vec<((u32, u32), String)> = worksheet
+        .data
+        .iter()
+        .filter_map(|(key, cell)| {
+            let formula = cell
+                .formula
+                .as_ref()
+                .filter(|f| is_dynamic_array_formula(f))?;
+            Some((parse_cell_key(key)?, formula.clone()))
+        })
+        .collect();
+    anchors.sort();
+
+    for (anchor, formula) in anchors {
+        spill(worksheet, anchor, &formula);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::create_new_spreadsheet;
+
+    fn set(worksheet: &mut Worksheet, row: u32, col: u32, value: &str) {
+        let (value, formula) = if value.starts_with('=') {
+            (None, Some(value.to_string()))
+        } else {
+            (Some(value.to_string()), None)
+        };
+        let cell = worksheet
+            .data
+            .entry(format!("{row},{col}"))
+            .or_insert_with(|| CellData {
+                value: None,
+                formula: None,
+                style: None,
+                format: None,
+                note: None,
+                locked: None,
+                has_comment: None,
+                array_formula_id: None,
+            });
+        cell.value = value;
+        cell.formula = formula;
+        cell.array_formula_id = None;
+    }
+
+    fn column(worksheet: &Worksheet, col: u32, rows: u32) -> Vec<String> {
+        (0..rows)
+            .map(|row| {
+                worksheet
+                    .data
+                    .get(&format!("{row},{col}"))
+                    .and_then(|c| c.value.clone())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    fn fruit_sheet() -> Worksheet {
+        let mut worksheet = create_new_spreadsheet().worksheets.remove(0);
+        for (row, fruit) in ["pear", "apple", "pear", "fig", "apple"].iter().enumerate() {
+            set(&mut worksheet, row as u32, 0, fruit);
+        }
+        worksheet
+    }
+
+    #[test]
+    fn test_unique_and_sort_spill_down_a_column() {
+        let mut worksheet = fruit_sheet();
+        set(&mut worksheet, 0, 2, "=UNIQUE(A1:A10)");
+        set(&mut worksheet, 0, 3, "=SORT(C1:C10, 1, -1)");
+        respill_worksheet(&mut worksheet);
+
+        assert_eq!(column(&worksheet, 2, 4), ["pear", "apple", "fig", ""]);
+        assert_eq!(column(&worksheet, 3, 4), ["pear", "fig", "apple", ""]);
+        assert_eq!(spill_anchor(&worksheet, 2, 2), Some((0, 2)));
+
+        // The source changes: both spills follow.
+        set(&mut worksheet, 5, 0, "kiwi");
+        respill_worksheet(&mut worksheet);
+        assert_eq!(
+            column(&worksheet, 2, 5),
+            ["pear", "apple", "fig", "kiwi", ""]
+        );
+        assert_eq!(
+            column(&worksheet, 3, 5),
+            ["pear", "kiwi", "fig", "apple", ""]
+        );
+    }
+
+    #[test]
+    fn test_obstructed_spill_shows_error_until_cleared() {
+        let mut worksheet = fruit_sheet();
+        set(&mut worksheet, 0, 2, "=UNIQUE(A1:A5)");
+        respill_worksheet(&mut worksheet);
+
+        // Typing into the spilled region blocks the whole spill.
+        set(&mut worksheet, 1, 2, "typed");
+        respill_worksheet(&mut worksheet);
+        assert_eq!(column(&worksheet, 2, 3), [SPILL_ERROR, "typed", ""]);
+        assert!(worksheet.array_formulas.iter().flatten().next().is_none());
+
+        worksheet.data.remove("1,2");
+        respill_worksheet(&mut worksheet);
+        assert_eq!(column(&worksheet, 2, 3), ["pear", "apple", "fig"]);
+    }
+
+    #[test]
+    fn test_filter_spills_matching_rows() {
+        let mut worksheet = fruit_sheet();
+        for (row, qty) in ["3", "10", "7", "1", "12"].iter().enumerate() {
+            set(&mut worksheet, row as u32, 1, qty);
+        }
+        set(&mut worksheet, 0, 3, "=FILTER(A1:A5, B1:B5>5)");
+        respill_worksheet(&mut worksheet);
+        assert_eq!(column(&worksheet, 3, 4), ["apple", "pear", "apple", ""]);
+
+        set(&mut worksheet, 0, 3, "=FILTER(A1:A5, B1:B5>50)");
+        respill_worksheet(&mut worksheet);
+        assert_eq!(column(&worksheet, 3, 2), ["#CALC!", ""]);
+    }
+}
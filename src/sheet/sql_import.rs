@@ -0,0 +1,569 @@
+//! Filling worksheets from saved SQL queries.
+//!
+//! Admins register SELECT statements with typed `$n` parameters in `sheet_saved_queries`.
+//! An import names a query by id and supplies parameter values, which are bound, never
+//! spliced into the SQL. The query runs in a read-only transaction with a statement
+//! timeout and a row cap, and each row becomes a worksheet row under a header of the
+//! column names.
+
+use crate::sheet::error::SheetError;
+use crate::sheet::types::{CellData, Worksheet};
+use crate::sheet::worksheets::MAX_WORKSHEET_NAME;
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::query_builder::{BoxedSqlQuery, SqlQuery};
+use diesel::sql_types::{BigInt, Bool, Date, Double, Integer, Jsonb, Nullable, Text, Timestamptz};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+/// Upper bound for any saved query's `max_rows`.
+pub const MAX_IMPORT_ROWS: i32 = 10_000;
+const STATEMENT_TIMEOUT: &str = "30s";
+const MAX_SQL_LEN: usize = 20_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    Text,
+    Integer,
+    Number,
+    Boolean,
+    Date,
+}
+
+/// Declaration of the query's `$n` parameter, where `n` is its position in the list.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryParam {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub param_type: ParamType,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedQuery {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// Only shown to admins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sql: Option<String>,
+    pub params: Vec<QueryParam>,
+    pub max_rows: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SavedQueryRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub sql: String,
+    #[serde(default)]
+    pub params: Vec<QueryParam>,
+    pub max_rows: Option<i32>,
+}
+
+impl SavedQueryRequest {
+    pub fn validate(&self) -> Result<(), SheetError> {
+        let invalid = |msg: String| Err(SheetError::InvalidRequest(msg));
+        if self.name.trim().is_empty() || self.name.len() > 128 {
+            return invalid("Query name must be 1-128 characters".to_string());
+        }
+        if let Some(max_rows) = self.max_rows {
+            if !(1..=MAX_IMPORT_ROWS).contains(&max_rows) {
+                return invalid(format!("max_rows must be between 1 and {MAX_IMPORT_ROWS}"));
+            }
+        }
+        let mut names = BTreeSet::new();
+        for param in &self.params {
+            if param.name.trim().is_empty() || !names.insert(param.name.as_str()) {
+                return invalid(format!("Invalid or duplicate parameter '{}'", param.name));
+            }
+        }
+        validate_sql(&self.sql, self.params.len()).map_err(SheetError::InvalidRequest)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueryImportRequest {
+    pub query_id: Uuid,
+    /// Values by parameter name. Strings are accepted for every type.
+    #[serde(default)]
+    pub params: Map<String, Value>,
+    /// Adds the worksheet to this sheet instead of creating a new one.
+    pub sheet_id: Option<String>,
+    pub worksheet_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamValue {
+    Text(String),
+    Integer(i64),
+    Number(f64),
+    Boolean(bool),
+    Date(NaiveDate),
+    /// NULL bound with the declared type, so comparisons type-check.
+    Null(ParamType),
+}
+
+/// Strips a trailing semicolon and checks that `sql` is one SELECT (or WITH) statement
+/// whose `$n` placeholders are exactly `$1..=$param_count`.
+fn validate_sql(sql: &str, param_count: usize) -> Result<(), String> {
+    let sql = statement_body(sql);
+    if sql.is_empty() || sql.len() > MAX_SQL_LEN {
+        return Err(format!("SQL must be 1-{MAX_SQL_LEN} characters"));
+    }
+    let first_word = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if first_word != "select" && first_word != "with" {
+        return Err("Only SELECT queries can be saved".to_string());
+    }
+
+    let mut used = BTreeSet::new();
+    let mut chars = sql.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => in_string = !in_string,
+            ';' if !in_string => return Err("SQL must be a single statement".to_string()),
+            '$' if !in_string => {
+                let mut digits = String::new();
+                while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    digits.push(*d);
+                    chars.next();
+                }
+                if let Ok(n) = digits.parse::<usize>() {
+                    used.insert(n);
+                }
+            }
+            _ => {}
+        }
+    }
+    let expected: BTreeSet<usize> = (1..=param_count).collect();
+    if used != expected {
+        return Err(format!(
+            "SQL must use placeholders $1..${param_count}, one per declared parameter"
+        ));
+    }
+    Ok(())
+}
+
+fn statement_body(sql: &str) -> &str {
+    sql.trim().trim_end_matches(';').trim_end()
+}
+
+impl ParamValue {
+    fn parse(param: &QueryParam, value: Option<&Value>) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Parameter '{}' must be a {:?}",
+                param.name, param.param_type
+            )
+        };
+        let value = match value {
+            None | Some(Value::Null) => return Ok(Self::Null(param.param_type)),
+            Some(v) => v,
+        };
+        let text = match value {
+            Value::String(s) => s.trim().to_string(),
+            other => other.to_string(),
+        };
+        Ok(match param.param_type {
+            ParamType::Text => match value {
+                Value::String(s) => Self::Text(s.clone()),
+                _ => Self::Text(text),
+            },
+            ParamType::Integer => Self::Integer(text.parse().map_err(|_| invalid())?),
+            ParamType::Number => Self::Number(text.parse().map_err(|_| invalid())?),
+            ParamType::Boolean => match text.to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" => Self::Boolean(true),
+                "false" | "0" | "no" => Self::Boolean(false),
+                _ => return Err(invalid()),
+            },
+            ParamType::Date => {
+                Self::Date(NaiveDate::parse_from_str(&text, "%Y-%m-%d").map_err(|_| invalid())?)
+            }
+        })
+    }
+}
+
+/// Values for the query's placeholders, in `$n` order. Missing parameters are NULL.
+pub fn resolve_params(
+    params: &[QueryParam],
+    values: &Map<String, Value>,
+) -> Result<Vec<ParamValue>, SheetError> {
+    if let Some(unknown) = values
+        .keys()
+        .find(|key| !params.iter().any(|p| &p.name == *key))
+    {
+        return Err(SheetError::InvalidRequest(format!(
+            "Unknown parameter '{unknown}'"
+        )));
+    }
+    params
+        .iter()
+        .map(|p| ParamValue::parse(p, values.get(&p.name)))
+        .collect::<Result<_, _>>()
+        .map_err(SheetError::InvalidRequest)
+}
+
+/// Wraps the saved statement so each row comes back as ordered `[column, value]` pairs.
+/// The row cap is the last placeholder.
+fn wrapped_sql(sql: &str, param_count: usize) -> String {
+    format!(
+        "SELECT (SELECT json_agg(json_build_array(c.key, c.value)) \
+         FROM json_each(row_to_json(q)) c)::text AS cells \
+         FROM (\n{}\n) q LIMIT ${}",
+        statement_body(sql),
+        param_count + 1
+    )
+}
+
+fn bind_param<'f>(
+    query: BoxedSqlQuery<'f, Pg, SqlQuery>,
+    value: ParamValue,
+) -> BoxedSqlQuery<'f, Pg, SqlQuery> {
+    match value {
+        ParamValue::Text(v) => query.bind::<Text, _>(v),
+        ParamValue::Integer(v) => query.bind::<BigInt, _>(v),
+        ParamValue::Number(v) => query.bind::<Double, _>(v),
+        ParamValue::Boolean(v) => query.bind::<Bool, _>(v),
+        ParamValue::Date(v) => query.bind::<Date, _>(v),
+        ParamValue::Null(ParamType::Text) => query.bind::<Nullable<Text>, _>(None::<String>),
+        ParamValue::Null(ParamType::Integer) => query.bind::<Nullable<BigInt>, _>(None::<i64>),
+        ParamValue::Null(ParamType::Number) => query.bind::<Nullable<Double>, _>(None::<f64>),
+        ParamValue::Null(ParamType::Boolean) => query.bind::<Nullable<Bool>, _>(None::<bool>),
+        ParamValue::Null(ParamType::Date) => query.bind::<Nullable<Date>, _>(None::<NaiveDate>),
+    }
+}
+
+#[derive(QueryableByName)]
+struct CellsRow {
+    #[diesel(sql_type = Nullable<Text>)]
+    cells: Option<String>,
+}
+
+/// Rows of `[column, value]` pairs and whether more rows than `max_rows` matched.
+pub struct QueryRows {
+    pub rows: Vec<Vec<(String, Value)>>,
+    pub truncated: bool,
+}
+
+/// Runs a saved query read-only. One row beyond the cap is fetched to detect truncation.
+pub fn run_saved_query(
+    conn: &mut PgConnection,
+    sql: &str,
+    values: Vec<ParamValue>,
+    max_rows: i32,
+) -> Result<QueryRows, SheetError> {
+    let max_rows = max_rows.clamp(1, MAX_IMPORT_ROWS);
+    let mut query = diesel::sql_query(wrapped_sql(sql, values.len())).into_boxed();
+    for value in values {
+        query = bind_param(query, value);
+    }
+    let query = query.bind::<BigInt, _>(i64::from(max_rows) + 1);
+
+    let loaded: Vec<CellsRow> = conn
+        .build_transaction()
+        .read_only()
+        .run(|conn| {
+            diesel::sql_query(format!(
+                "SET LOCAL statement_timeout = '{STATEMENT_TIMEOUT}'"
+            ))
+            .execute(conn)?;
+            query.load(conn)
+        })
+        .map_err(|e| SheetError::ImportFailed(format!("Query failed: {e}")))?;
+
+    let mut rows = loaded
+        .into_iter()
+        .map(|row| parse_cells(row.cells.as_deref()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let truncated = rows.len() > max_rows as usize;
+    rows.truncate(max_rows as usize);
+    Ok(QueryRows { rows, truncated })
+}
+
+fn parse_cells(cells: Option<&str>) -> Result<Vec<(String, Value)>, SheetError> {
+    let Some(cells) = cells else {
+        return Ok(Vec::new());
+    };
+    let pairs: Vec<(String, Value)> = serde_json::from_str(cells)
+        .map_err(|e| SheetError::ImportFailed(format!("Unreadable query row: {e}")))?;
+    Ok(pairs)
+}
+
+fn cell_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        Value::Bool(true) => Some("TRUE".to_string()),
+        Value::Bool(false) => Some("FALSE".to_string()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn text_cell(value: String) -> CellData {
+    CellData {
+        value: Some(value),
+        formula: None,
+        style: None,
+        format: None,
+        note: None,
+        locked: None,
+        has_comment: None,
+        array_formula_id: None,
+    }
+}
+
+/// A worksheet with the column names in row 0, frozen, and one row per result row.
+pub fn rows_to_worksheet(name: &str, rows: &[Vec<(String, Value)>]) -> Worksheet {
+    let mut data = HashMap::new();
+    if let Some(first) = rows.first() {
+        for (col, (column, _)) in first.iter().enumerate() {
+            data.insert(format!("0,{col}"), text_cell(column.clone()));
+        }
+    }
+    for (row, cells) in rows.iter().enumerate() {
+        for (col, (_, value)) in cells.iter().enumerate() {
+            if let Some(text) = cell_text(value) {
+                data.insert(format!("{},{col}", row + 1), text_cell(text));
+            }
+        }
+    }
+
+    Worksheet {
+        name: name.to_string(),
+        data,
+        column_widths: None,
+        row_heights: None,
+        frozen_rows: (!rows.is_empty()).then_some(1),
+        frozen_cols: None,
+        merged_cells: None,
+        filters: None,
+        hidden_rows: None,
+        validations: None,
+        conditional_formats: None,
+        charts: None,
+        comments: None,
+        protection: None,
+        array_formulas: None,
+        protected_ranges: None,
+        has_header: None,
+    }
+}
+
+/// `wanted`, shortened to the worksheet name limit and suffixed ` (2)`, ` (3)`... until it
+/// differs from every name in `existing`.
+pub fn unique_worksheet_name(wanted: &str, existing: &[Worksheet]) -> String {
+    let base: String = wanted.trim().chars().take(MAX_WORKSHEET_NAME).collect();
+    let base = if base.is_empty() {
+        "Query".to_string()
+    } else {
+        base
+    };
+    let taken = |name: &str| existing.iter().any(|ws| ws.name.eq_ignore_ascii_case(name));
+    if !taken(&base) {
+        return base;
+    }
+    (2..)
+        .map(|n| {
+            let suffix = format!(" ({n})");
+            let keep = MAX_WORKSHEET_NAME - suffix.chars().count();
+            format!("{}{suffix}", base.chars().take(keep).collect::<String>())
+        })
+        .find(|name| !taken(name))
+        .unwrap_or(base)
+}
+
+#[derive(QueryableByName)]
+struct SavedQueryRow {
+    #[diesel(sql_type = diesel::sql_types::Uuid)]
+    id: Uuid,
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    description: Option<String>,
+    #[diesel(sql_type = Text)]
+    sql_text: String,
+    #[diesel(sql_type = Jsonb)]
+    params: Value,
+    #[diesel(sql_type = Integer)]
+    max_rows: i32,
+    #[diesel(sql_type = Timestamptz)]
+    updated_at: DateTime<Utc>,
+}
+
+impl SavedQueryRow {
+    fn into_query(self, with_sql: bool) -> SavedQuery {
+        SavedQuery {
+            id: self.id,
+            name: self.name,
+            description: self.description,
+            sql: with_sql.then_some(self.sql_text),
+            params: serde_json::from_value(self.params).unwrap_or_default(),
+            max_rows: self.max_rows,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+const COLUMNS: &str = "id, name, description, sql_text, params, max_rows, updated_at";
+
+fn db_error(e: diesel::result::Error) -> SheetError {
+    SheetError::StorageFailed(e.to_string())
+}
+
+pub fn list_saved_queries(
+    conn: &mut PgConnection,
+    with_sql: bool,
+) -> Result<Vec<SavedQuery>, SheetError> {
+    let rows: Vec<SavedQueryRow> = diesel::sql_query(format!(
+        "SELECT {COLUMNS} FROM sheet_saved_queries ORDER BY name"
+    ))
+    .load(conn)
+    .map_err(db_error)?;
+    Ok(rows.into_iter().map(|r| r.into_query(with_sql)).collect())
+}
+
+pub fn get_saved_query(
+    conn: &mut PgConnection,
+    id: Uuid,
+    with_sql: bool,
+) -> Result<SavedQuery, SheetError> {
+    let row: Option<SavedQueryRow> = diesel::sql_query(format!(
+        "SELECT {COLUMNS} FROM sheet_saved_queries WHERE id = $1"
+    ))
+    .bind::<diesel::sql_types::Uuid, _>(id)
+    .get_result(conn)
+    .optional()
+    .map_err(db_error)?;
+    row.map(|r| r.into_query(with_sql))
+        .ok_or_else(|| SheetError::QueryNotFound(id.to_string()))
+}
+
+pub fn insert_saved_query(
+    conn: &mut PgConnection,
+    req: &SavedQueryRequest,
+    created_by: Uuid,
+) -> Result<SavedQuery, SheetError> {
+    let id = Uuid::new_v4();
+    let params = serde_json::to_value(&req.params).unwrap_or_else(|_| Value::Array(Vec::new()));
+    diesel::sql_query(
+        "INSERT INTO sheet_saved_queries
+         (id, name, description, sql_text, params, max_rows, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (name) DO NOTHING",
+    )
+    .bind::<diesel::sql_types::Uuid, _>(id)
+    .bind::<Text, _>(req.name.trim())
+    .bind::<Nullable<Text>, _>(req.description.as_deref())
+    .bind::<Text, _>(statement_body(&req.sql))
+    .bind::<Jsonb, _>(params)
+    .bind::<Integer, _>(req.max_rows.unwrap_or(1000).min(MAX_IMPORT_ROWS))
+    .bind::<diesel::sql_types::Uuid, _>(created_by)
+    .execute(conn)
+    .map_err(db_error)
+    .and_then(|inserted| match inserted {
+        0 => Err(SheetError::InvalidRequest(format!(
+            "A saved query named '{}' already exists",
+            req.name.trim()
+        ))),
+        _ => get_saved_query(conn, id, true),
+    })
+}
+
+pub fn delete_saved_query(conn: &mut PgConnection, id: Uuid) -> Result<(), SheetError> {
+    let deleted = diesel::sql_query("DELETE FROM sheet_saved_queries WHERE id = $1")
+        .bind::<diesel::sql_types::Uuid, _>(id)
+        .execute(conn)
+        .map_err(db_error)?;
+    if deleted == 0 {
+        return Err(SheetError::QueryNotFound(id.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> Vec<QueryParam> {
+        vec![
+            QueryParam {
+                name: "region".to_string(),
+                param_type: ParamType::Text,
+            },
+            QueryParam {
+                name: "min_total".to_string(),
+                param_type: ParamType::Integer,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_parameterized_saved_query_produces_cells() {
+        let sql = "SELECT name, total, active FROM orders WHERE region = $1 AND total >= $2;";
+        assert!(validate_sql(sql, 2).is_ok());
+
+        let values = serde_json::json!({"region": "south", "min_total": "100"});
+        let resolved = resolve_params(&params(), values.as_object().unwrap()).unwrap();
+        assert_eq!(
+            resolved,
+            vec![
+                ParamValue::Text("south".to_string()),
+                ParamValue::Integer(100)
+            ]
+        );
+        let wrapped = wrapped_sql(sql, resolved.len());
+        assert!(wrapped.contains("total >= $2\n) q LIMIT $3"));
+
+        // Rows as the wrapped query returns them.
+        let rows: Vec<Vec<(String, Value)>> = [
+            r#"[["name","Acme"],["total",150.5],["active",true]]"#,
+            r#"[["name","Globex"],["total",100],["active",null]]"#,
+        ]
+        .iter()
+        .map(|r| parse_cells(Some(r)).unwrap())
+        .collect();
+        let ws = rows_to_worksheet("Orders", &rows);
+
+        let cell = |key: &str| ws.data.get(key).and_then(|c| c.value.clone());
+        assert_eq!(cell("0,0").as_deref(), Some("name"));
+        assert_eq!(cell("0,1").as_deref(), Some("total"));
+        assert_eq!(cell("0,2").as_deref(), Some("active"));
+        assert_eq!(cell("1,0").as_deref(), Some("Acme"));
+        assert_eq!(cell("1,1").as_deref(), Some("150.5"));
+        assert_eq!(cell("1,2").as_deref(), Some("TRUE"));
+        assert_eq!(cell("2,1").as_deref(), Some("100"));
+        assert_eq!(cell("2,2"), None);
+        assert_eq!(ws.frozen_rows, Some(1));
+    }
+
+    #[test]
+    fn test_only_preregistered_shapes_are_accepted() {
+        assert!(validate_sql("DELETE FROM orders", 0).is_err());
+        assert!(validate_sql("SELECT 1; DROP TABLE orders", 0).is_err());
+        assert!(validate_sql("SELECT * FROM t WHERE a = $1", 0).is_err());
+        assert!(validate_sql("SELECT * FROM t WHERE a = $2", 2).is_err());
+        assert!(validate_sql("SELECT ';' || $1", 1).is_ok());
+
+        let unknown = serde_json::json!({"region": "x", "sql": "DROP"});
+        assert!(resolve_params(&params(), unknown.as_object().unwrap()).is_err());
+        let bad_int = serde_json::json!({"min_total": "1 OR 1=1"});
+        assert!(resolve_params(&params(), bad_int.as_object().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_unique_worksheet_name() {
+        let existing = vec![rows_to_worksheet("Orders", &[])];
+        assert_eq!(unique_worksheet_name("Orders", &existing), "Orders (2)");
+        assert_eq!(unique_worksheet_name("Sales", &existing), "Sales");
+        assert_eq!(unique_worksheet_name(&"x".repeat(40), &[]).len(), 31);
+    }
+}
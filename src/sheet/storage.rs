@@ -1,12 +1,27 @@
 use crate::core::shared::state::AppState;
+use crate::drive::object_store::ObjectStore;
+use crate::drive::objects::{copy_object_tracked, ObjectCopyError};
+use crate::drive::quota;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::sheet::comments::migrate_legacy_notes;
+use crate::sheet::csv_import::{self, CsvOptions};
+use crate::sheet::error::SheetError;
+use crate::sheet::snapshots::delete_snapshots;
+use crate::sheet::sort_filter::set_header;
 use crate::sheet::types::{CellData, CellStyle, MergedCell, Spreadsheet, SpreadsheetMetadata, Worksheet};
+use crate::sheet::write_buffer::sheet_writes;
 use chrono::Utc;
+use futures::future::BoxFuture;
+use log::warn;
 use std::collections::HashMap;
 use std::io::Cursor;
 use std::sync::Arc;
 use umya_spreadsheet::Spreadsheet as UmyaSpreadsheet;
 use uuid::Uuid;
 
+const XLSX_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
 pub fn get_user_sheets_path(user_id: &str) -> String {
     format!("users/{}/sheets", user_id)
 }
@@ -15,6 +30,17 @@ pub fn get_current_user_id() -> String {
     "default-user".to_string()
 }
 
+/// Whether `user` may open `sheet`. Sheets saved by the shared workspace user are open
+/// to every authenticated user; other sheets only to their owner and administrators.
+pub fn can_access_sheet(sheet: &Spreadsheet, user: &AuthenticatedUser) -> bool {
+    if !user.is_authenticated() {
+        return false;
+    }
+    user.is_admin()
+        || sheet.owner_id == user.user_id.to_string()
+        || sheet.owner_id == get_current_user_id()
+}
+
 fn extract_id_from_path(path: &str) -> String {
     path.split('/')
         .last()
@@ -24,29 +50,76 @@ fn extract_id_from_path(path: &str) -> String {
         .to_string()
 }
 
+/// Writes a sheet right away, replacing any edits still buffered for it.
 pub async fn save_sheet_to_drive(
     state: &Arc<AppState>,
     user_id: &str,
     sheet: &Spreadsheet,
-) -> Result<(), String> {
-    let drive = state
-        .drive
-        .as_ref()
-        .ok_or_else(|| "Drive not available".to_string())?;
+) -> Result<(), SheetError> {
+    sheet_writes().discard(user_id, &sheet.id);
+    write_sheet_json(state, user_id, sheet).await
+}
+
+/// Saves an edited sheet through the write buffer, which writes it to the drive once
+/// editing pauses. Loading the sheet meanwhile returns the edited copy.
+pub async fn queue_sheet_save(
+    state: &Arc<AppState>,
+    user_id: &str,
+    sheet: &Spreadsheet,
+) -> Result<(), SheetError> {
+    sheet_writes()
+        .record(user_id, sheet.clone(), drive_writer(state))
+        .await
+}
+
+/// Writes a sheet's buffered edits, if any, so the drive copy is current.
+pub async fn flush_sheet(
+    state: &Arc<AppState>,
+    user_id: &str,
+    sheet_id: &str,
+) -> Result<(), SheetError> {
+    sheet_writes()
+        .flush(user_id, sheet_id, drive_writer(state))
+        .await
+}
+
+/// Writes every buffered sheet; called on shutdown. Returns how many were written.
+pub async fn flush_all_sheets(state: &Arc<AppState>) -> usize {
+    sheet_writes().flush_all(drive_writer(state)).await
+}
+
+fn drive_writer(
+    state: &Arc<AppState>,
+) -> impl Fn(String, Spreadsheet) -> BoxFuture<'static, Result<(), SheetError>> {
+    let state = Arc::clone(state);
+    move |user_id, sheet| {
+        let state = Arc::clone(&state);
+        Box::pin(async move { write_sheet_json(&state, &user_id, &sheet).await })
+    }
+}
+
+async fn write_sheet_json(
+    state: &Arc<AppState>,
+    user_id: &str,
+    sheet: &Spreadsheet,
+) -> Result<(), SheetError> {
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or(SheetError::DriveUnavailable)?;
+
+    let path = sheet_json_path(user_id, &sheet.id);
+    let content = serde_json::to_vec_pretty(sheet)
+        .map_err(|e| SheetError::StorageFailed(format!("Serialization error: {e}")))?;
+    let delta = quota::check_put(state, store, "gbo", &path, content.len() as u64)
+        .await
+        .map_err(|e| SheetError::QuotaExceeded(e.to_string()))?;
 
-    let path = format!("{}/{}.json", get_user_sheets_path(user_id), sheet.id);
-    let content =
-        serde_json::to_string_pretty(sheet).map_err(|e| format!("Serialization error: {e}"))?;
-
-    drive
-        .put_object()
-        .bucket("gbo")
-        .key(&path)
-        .body(content.into_bytes().into())
-        .content_type("application/json")
-        .send()
+    store
+        .put("gbo", &path, content, "application/json")
         .await
-        .map_err(|e| format!("Failed to save sheet: {e}"))?;
+        .map_err(|e| SheetError::StorageFailed(format!("Failed to save sheet: {e}")))?;
+    quota::record_change(state, "gbo", &path, delta).await;
 
     Ok(())
 }
@@ -58,22 +131,23 @@ pub async fn save_sheet_as_xlsx(
 ) -> Result<Vec<u8>, String> {
     let xlsx_bytes = convert_to_xlsx(sheet)?;
 
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
-    let path = format!("{}/{}.xlsx", get_user_sheets_path(user_id), sheet.id);
+    let path = sheet_xlsx_path(user_id, &sheet.id);
 
-    drive
-        .put_object()
-        .bucket("gbo")
-        .key(&path)
-        .body(xlsx_bytes.clone().into())
-        .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to save xlsx: {e}"))?;
+    quota::put_tracked(
+        state,
+        store,
+        "gbo",
+        &path,
+        xlsx_bytes.clone(),
+        XLSX_CONTENT_TYPE,
+    )
+    .await
+    .map_err(|e| format!("Failed to save xlsx: {e}"))?;
 
     Ok(xlsx_bytes)
 }
@@ -125,6 +199,9 @@ pub fn convert_to_xlsx(sheet: &Spreadsheet) -> Result<Vec<u8>, String> {
             if let Some(ref style) = cell_data.style {
                 apply_umya_style(cell, style);
             }
+            if let Some(ref code) = cell_data.format {
+                cell.get_style_mut().get_number_format_mut().set_format_code(code);
+            }
         }
 
         if let Some(ref widths) = worksheet.column_widths {
@@ -265,26 +342,16 @@ pub async fn load_xlsx_from_drive(
     user_id: &str,
     file_path: &str,
 ) -> Result<(Spreadsheet, UmyaSpreadsheet), String> {
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
-    let result = drive
-        .get_object()
-        .bucket("gbo")
-        .key(file_path)
-        .send()
+    let bytes = store
+        .get("gbo", file_path)
         .await
         .map_err(|e| format!("Failed to load file: {e}"))?;
 
-    let bytes = result
-        .body
-        .collect()
-        .await
-        .map_err(|e| format!("Failed to read file: {e}"))?
-        .into_bytes();
-
     load_xlsx_from_bytes(&bytes, user_id, file_path)
 }
 
@@ -416,12 +483,16 @@ pub fn load_xlsx_from_bytes(
             comments: None,
             protection: None,
             array_formulas: None,
+            protected_ranges: None,
+            has_header: None,
         });
     }
 
     let spreadsheet = Spreadsheet {
         named_ranges: None,
         external_links: None,
+        locale: None,
+        created_by: None,
         id: Uuid::new_v4().to_string(),
         name: file_name.to_string(),
         owner_id: user_id.to_string(),
@@ -606,26 +677,27 @@ pub async fn save_workbook_to_drive(
     sheet_id: &str,
     workbook: &UmyaSpreadsheet,
 ) -> Result<(), String> {
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
-    let path = format!("{}/{}.xlsx", get_user_sheets_path(user_id), sheet_id);
+    let path = sheet_xlsx_path(user_id, sheet_id);
 
     let mut buf = Cursor::new(Vec::new());
     umya_spreadsheet::writer::xlsx::write_writer(workbook, &mut buf)
         .map_err(|e| format!("Failed to write xlsx: {e}"))?;
 
-    drive
-        .put_object()
-        .bucket("gbo")
-        .key(&path)
-        .body(buf.into_inner().into())
-        .content_type("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to save xlsx: {e}"))?;
+    quota::put_tracked(
+        state,
+        store,
+        "gbo",
+        &path,
+        buf.into_inner(),
+        XLSX_CONTENT_TYPE,
+    )
+    .await
+    .map_err(|e| format!("Failed to save xlsx: {e}"))?;
 
     Ok(())
 }
@@ -639,32 +711,7 @@ pub async fn load_sheet_from_drive(
         .as_ref()
         .ok_or_else(|| "Sheet ID is required".to_string())?;
 
-    let drive = state
-        .drive
-        .as_ref()
-        .ok_or_else(|| "Drive not available".to_string())?;
-
-    let path = format!("{}/{}.json", get_user_sheets_path(user_id), sheet_id);
-
-    let result = drive
-        .get_object()
-        .bucket("gbo")
-        .key(&path)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to load sheet: {e}"))?;
-
-    let bytes = result
-        .body
-        .collect()
-        .await
-        .map_err(|e| format!("Failed to read sheet: {e}"))?
-        .into_bytes();
-
-    let sheet: Spreadsheet =
-        serde_json::from_slice(&bytes).map_err(|e| format!("Failed to parse sheet: {e}"))?;
-
-    Ok(sheet)
+    load_sheet_by_id(state, user_id, sheet_id).await
 }
 
 pub async fn load_sheet_by_id(
@@ -672,72 +719,134 @@ pub async fn load_sheet_by_id(
     user_id: &str,
     sheet_id: &str,
 ) -> Result<Spreadsheet, String> {
-    let drive = state
-        .drive
-        .as_ref()
+    if sheet_writes().has_failed(user_id, sheet_id) {
+        flush_sheet(state, user_id, sheet_id)
+            .await
+            .map_err(|e| format!("Unsaved edits could not be written: {e}"))?;
+    }
+    if let Some(sheet) = sheet_writes().pending(user_id, sheet_id) {
+        return Ok(sheet);
+    }
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
-    let path = format!("{}/{}.json", get_user_sheets_path(user_id), sheet_id);
+    read_sheet(store, user_id, sheet_id).await
+}
 
-    let result = drive
-        .get_object()
-        .bucket("gbo")
-        .key(&path)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to load sheet: {e}"))?;
+fn sheet_json_path(user_id: &str, sheet_id: &str) -> String {
+    format!("{}/{}.json", get_user_sheets_path(user_id), sheet_id)
+}
 
-    let bytes = result
-        .body
-        .collect()
+fn sheet_xlsx_path(user_id: &str, sheet_id: &str) -> String {
+    format!("{}/{}.xlsx", get_user_sheets_path(user_id), sheet_id)
+}
+
+/// Reads a stored sheet. Its id is the one it is stored under, so a server-side copy of
+/// the file is a separate sheet.
+async fn read_sheet(
+    store: &dyn ObjectStore,
+    user_id: &str,
+    sheet_id: &str,
+) -> Result<Spreadsheet, String> {
+    let bytes = store
+        .get("gbo", &sheet_json_path(user_id, sheet_id))
         .await
-        .map_err(|e| format!("Failed to read sheet: {e}"))?
-        .into_bytes();
+        .map_err(|e| format!("Failed to load sheet: {e}"))?;
 
-    let sheet: Spreadsheet =
+    let mut sheet: Spreadsheet =
         serde_json::from_slice(&bytes).map_err(|e| format!("Failed to parse sheet: {e}"))?;
+    sheet.id = sheet_id.to_string();
+    migrate_legacy_notes(&mut sheet);
 
     Ok(sheet)
 }
 
+/// Ids of the sheets stored for `user_id`.
+async fn list_sheet_ids(store: &dyn ObjectStore, user_id: &str) -> Result<Vec<String>, String> {
+    let prefix = format!("{}/", get_user_sheets_path(user_id));
+    let objects = store
+        .list("gbo", &prefix)
+        .await
+        .map_err(|e| format!("Failed to list sheets: {e}"))?;
+    Ok(objects
+        .iter()
+        .filter(|obj| obj.key.ends_with(".json"))
+        .map(|obj| extract_id_from_path(&obj.key))
+        .collect())
+}
+
+/// Copies a sheet, and its `.xlsx` export when there is one, to a new id inside the drive
+/// and returns the new id. The copy keeps the original's name until it is saved.
+pub async fn duplicate_sheet_in_drive(
+    state: &Arc<AppState>,
+    user_id: &str,
+    sheet_id: &str,
+) -> Result<String, SheetError> {
+    let store = state
+        .object_store
+        .as_deref()
+        .ok_or(SheetError::DriveUnavailable)?;
+    flush_sheet(state, user_id, sheet_id).await?;
+    let new_id = Uuid::new_v4().to_string();
+
+    copy_object_tracked(
+        state,
+        store,
+        "gbo",
+        &sheet_json_path(user_id, sheet_id),
+        "gbo",
+        &sheet_json_path(user_id, &new_id),
+    )
+    .await
+    .map_err(|e| match e {
+        ObjectCopyError::NotFound(_) => SheetError::SheetNotFound(sheet_id.to_string()),
+        ObjectCopyError::QuotaExceeded(e) => SheetError::QuotaExceeded(e.to_string()),
+        ObjectCopyError::Failed(msg) => SheetError::StorageFailed(msg),
+    })?;
+
+    let xlsx_copy = copy_object_tracked(
+        state,
+        store,
+        "gbo",
+        &sheet_xlsx_path(user_id, sheet_id),
+        "gbo",
+        &sheet_xlsx_path(user_id, &new_id),
+    )
+    .await;
+    match xlsx_copy {
+        Ok(_) | Err(ObjectCopyError::NotFound(_)) => {}
+        Err(e) => warn!(
+            "Duplicated sheet {} without its xlsx export: {}",
+            sheet_id, e
+        ),
+    }
+
+    Ok(new_id)
+}
+
 pub async fn list_sheets_from_drive(
     state: &Arc<AppState>,
     user_id: &str,
 ) -> Result<Vec<SpreadsheetMetadata>, String> {
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
-    let prefix = format!("{}/", get_user_sheets_path(user_id));
-
-    let result = drive
-        .list_objects_v2()
-        .bucket("gbo")
-        .prefix(&prefix)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to list sheets: {e}"))?;
-
     let mut sheets = Vec::new();
 
-    if let Some(contents) = result.contents {
-        for obj in contents {
-            if let Some(key) = obj.key {
-                if key.ends_with(".json") {
-                    let id = extract_id_from_path(&key);
-                    if let Ok(sheet) = load_sheet_by_id(state, user_id, &id).await {
-                        sheets.push(SpreadsheetMetadata {
-                            id: sheet.id,
-                            name: sheet.name,
-                            owner_id: sheet.owner_id,
-                            created_at: sheet.created_at,
-                            updated_at: sheet.updated_at,
-                            worksheet_count: sheet.worksheets.len(),
-                        });
-                    }
-                }
-            }
+    for id in list_sheet_ids(store, user_id).await? {
+        if let Ok(sheet) = load_sheet_by_id(state, user_id, &id).await {
+            sheets.push(SpreadsheetMetadata {
+                id: sheet.id,
+                name: sheet.name,
+                owner_id: sheet.owner_id,
+                created_at: sheet.created_at,
+                updated_at: sheet.updated_at,
+                worksheet_count: sheet.worksheets.len(),
+            });
         }
     }
 
@@ -754,55 +863,52 @@ pub async fn delete_sheet_from_drive(
     let sheet_id = sheet_id
         .as_ref()
         .ok_or_else(|| "Sheet ID is required".to_string())?;
+    sheet_writes().discard(user_id, sheet_id);
 
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
-    let json_path = format!("{}/{}.json", get_user_sheets_path(user_id), sheet_id);
-    let xlsx_path = format!("{}/{}.xlsx", get_user_sheets_path(user_id), sheet_id);
+    let json_path = sheet_json_path(user_id, sheet_id);
+    let xlsx_path = sheet_xlsx_path(user_id, sheet_id);
 
-    let _ = drive
-        .delete_object()
-        .bucket("gbo")
-        .key(&json_path)
-        .send()
-        .await;
+    let json_size = quota::size_before_delete(store, "gbo", &json_path).await;
+    if store.delete("gbo", &json_path).await.is_ok() {
+        quota::record_change(state, "gbo", &json_path, -(json_size as i64)).await;
+    }
 
-    let _ = drive
-        .delete_object()
-        .bucket("gbo")
-        .key(&xlsx_path)
-        .send()
-        .await;
+    let _ = quota::delete_tracked(state, store, "gbo", &xlsx_path).await;
+
+    delete_snapshots(state, user_id, sheet_id).await;
 
     Ok(())
 }
 
+/// Parses delimited text. `options` overrides the encoding and delimiter; otherwise the
+/// encoding is detected and the delimiter sniffed, falling back to `default_delimiter`.
+/// With `options.header` the first row is marked as the header and frozen.
 pub fn parse_csv_to_worksheets(
     bytes: &[u8],
-    delimiter: u8,
+    options: &CsvOptions,
+    default_delimiter: u8,
     sheet_name: &str,
 ) -> Result<Vec<Worksheet>, String> {
-    let content = String::from_utf8_lossy(bytes);
+    let (content, _) = csv_import::decode(bytes, options.encoding);
+    let delimiter = options
+        .delimiter
+        .unwrap_or_else(|| csv_import::sniff_delimiter(&content, default_delimiter));
     let mut data: HashMap<String, CellData> = HashMap::new();
 
-    for (row_idx, line) in content.lines().enumerate() {
-        let cols: Vec<&str> = if delimiter == b'\t' {
-            line.split('\t').collect()
-        } else {
-            line.split(',').collect()
-        };
-
-        for (col_idx, value) in cols.iter().enumerate() {
-            let clean_value = value.trim().trim_matches('"').to_string();
-            if !clean_value.is_empty() {
+    let records = csv_import::parse_records(&content, delimiter);
+    for (row_idx, cols) in records.into_iter().enumerate() {
+        for (col_idx, value) in cols.into_iter().enumerate() {
+            if !value.is_empty() {
                 let key = format!("{row_idx},{col_idx}");
                 data.insert(
                     key,
                     CellData {
-                        value: Some(clean_value),
+                        value: Some(value),
                         formula: None,
                         style: None,
                         format: None,
@@ -816,7 +922,7 @@ pub fn parse_csv_to_worksheets(
         }
     }
 
-    Ok(vec![Worksheet {
+    let mut worksheet = Worksheet {
         name: sheet_name.to_string(),
         data,
         column_widths: None,
@@ -832,7 +938,13 @@ pub fn parse_csv_to_worksheets(
         comments: None,
         protection: None,
         array_formulas: None,
-    }])
+        protected_ranges: None,
+        has_header: None,
+    };
+    if options.header {
+        set_header(&mut worksheet, true);
+    }
+    Ok(vec![worksheet])
 }
 
 pub fn parse_excel_to_worksheets(bytes: &[u8], ext: &str) -> Result<Vec<Worksheet>, String> {
@@ -895,16 +1007,184 @@ pub fn parse_excel_to_worksheets(bytes: &[u8], ext: &str) -> Result<Vec<Workshee
                     comments: None,
                     protection: None,
                     array_formulas: None,
+                    protected_ranges: None,
+                    has_header: None,
                 });
             }
 
             if !worksheets.is_empty() {
+                fill_missing_formulas(bytes, &mut worksheets);
                 return Ok(worksheets);
             }
         }
     }
 
-    Err("Failed to parse spreadsheet".to_string())
+    parse_with_calamine(bytes).map_err(|e| format!("Failed to parse spreadsheet: {e}"))
+}
+
+/// Reads workbooks umya cannot open (`.xls`, `.xlsb`, `.ods`) with calamine. Formulas are
+/// kept where the format exposes them; other cells hold their values only.
+fn parse_with_calamine(bytes: &[u8]) -> Result<Vec<Worksheet>, String> {
+    use calamine::Reader;
+
+    let mut workbook =
+        calamine::open_workbook_auto_from_rs(Cursor::new(bytes.to_vec())).map_err(|e| e.to_string())?;
+    let mut worksheets = Vec::new();
+
+    for name in workbook.sheet_names() {
+        let Ok(range) = workbook.worksheet_range(&name) else {
+            continue;
+        };
+        let (first_row, first_col) = range.start().unwrap_or((0, 0));
+        let mut data: HashMap<String, CellData> = HashMap::new();
+        for (row, col, cell) in range.cells() {
+            let value = calamine_value(cell);
+            if value.is_empty() {
+                continue;
+            }
+            let key = format!("{},{}", first_row + row as u32, first_col + col as u32);
+            let cell = data.entry(key).or_insert_with(empty_cell);
+            cell.value = Some(value);
+        }
+        if let Ok(formulas) = workbook.worksheet_formula(&name) {
+            merge_formulas(&mut data, &formulas);
+        }
+
+        worksheets.push(Worksheet {
+            name,
+            data,
+            column_widths: None,
+            row_heights: None,
+            frozen_rows: None,
+            frozen_cols: None,
+            merged_cells: None,
+            filters: None,
+            hidden_rows: None,
+            validations: None,
+            conditional_formats: None,
+            charts: None,
+            comments: None,
+            protection: None,
+            array_formulas: None,
+            protected_ranges: None,
+            has_header: None,
+        });
+    }
+
+    if worksheets.is_empty() {
+        return Err("Workbook has no readable worksheets".to_string());
+    }
+    Ok(worksheets)
+}
+
+/// Adds formulas umya leaves out, such as cells filled from a shared formula, from
+/// calamine's reading of the same workbook.
+fn fill_missing_formulas(bytes: &[u8], worksheets: &mut [Worksheet]) {
+    use calamine::Reader;
+
+    let Ok(mut workbook) = calamine::open_workbook_auto_from_rs(Cursor::new(bytes.to_vec())) else {
+        return;
+    };
+    for worksheet in worksheets {
+        if let Ok(formulas) = workbook.worksheet_formula(&worksheet.name) {
+            merge_formulas(&mut worksheet.data, &formulas);
+        }
+    }
+}
+
+/// Sets the formula of every cell in `formulas` that has none yet.
+fn merge_formulas(data: &mut HashMap<String, CellData>, formulas: &calamine::Range<String>) {
+    let (first_row, first_col) = formulas.start().unwrap_or((0, 0));
+    for (row, col, formula) in formulas.cells() {
+        let formula = normalize_formula(formula);
+        if formula.is_empty() {
+            continue;
+        }
+        let key = format!("{},{}", first_row + row as u32, first_col + col as u32);
+        let cell = data.entry(key).or_insert_with(empty_cell);
+        if cell.formula.is_none() {
+            cell.formula = Some(formula);
+        }
+    }
+}
+
+fn calamine_value(cell: &calamine::Data) -> String {
+    match cell {
+        calamine::Data::Empty => String::new(),
+        calamine::Data::String(s) | calamine::Data::DateTimeIso(s) | calamine::Data::DurationIso(s) => {
+            s.clone()
+        }
+        calamine::Data::Float(f) => f.to_string(),
+        calamine::Data::Int(i) => i.to_string(),
+        calamine::Data::Bool(b) => b.to_string().to_uppercase(),
+        calamine::Data::Error(e) => e.to_string(),
+        calamine::Data::DateTime(dt) => dt.to_string(),
+    }
+}
+
+fn empty_cell() -> CellData {
+    CellData {
+        value: None,
+        formula: None,
+        style: None,
+        format: None,
+        note: None,
+        locked: None,
+        has_comment: None,
+        array_formula_id: None,
+    }
+}
+
+/// Brings a stored formula to the `=A1` form the formula engine evaluates. OpenDocument
+/// formulas (`of:=SUM([.A1:.A3];[Data.B1])`) lose their namespace, bracketed references
+/// and `;` argument separators; Excel formulas only gain the leading `=`.
+pub fn normalize_formula(formula: &str) -> String {
+    let formula = formula.trim();
+    let (formula, odf) = match formula.strip_prefix("of:") {
+        Some(rest) => (rest, true),
+        None => (formula, false),
+    };
+    let body = formula.trim_start_matches('=');
+    if body.is_empty() {
+        return String::new();
+    }
+    if !odf {
+        return format!("={body}");
+    }
+
+    let mut out = String::from("=");
+    let mut in_string = false;
+    let mut reference = String::new();
+    let mut in_reference = false;
+    for ch in body.chars() {
+        if in_reference {
+            if ch == ']' {
+                let parts: Vec<String> = reference
+                    .split(':')
+                    .map(|part| match part.strip_prefix('.') {
+                        Some(cell) => cell.to_string(),
+                        None => part.replacen('.', "!", 1),
+                    })
+                    .collect();
+                out.push_str(&parts.join(":"));
+                reference.clear();
+                in_reference = false;
+            } else {
+                reference.push(ch);
+            }
+            continue;
+        }
+        match ch {
+            '"' => {
+                in_string = !in_string;
+                out.push(ch);
+            }
+            '[' if !in_string => in_reference = true,
+            ';' if !in_string => out.push(','),
+            _ => out.push(ch),
+        }
+    }
+    out
 }
 
 pub fn parse_ods_to_worksheets(bytes: &[u8]) -> Result<Vec<Worksheet>, String> {
@@ -958,6 +1238,8 @@ pub fn parse_ods_to_worksheets(bytes: &[u8]) -> Result<Vec<Worksheet>, String> {
                         comments: None,
                         protection: None,
                         array_formulas: None,
+                        protected_ranges: None,
+                        has_header: None,
                     });
                 }
                 in_table = false;
@@ -977,7 +1259,7 @@ pub fn parse_ods_to_worksheets(bytes: &[u8]) -> Result<Vec<Worksheet>, String> {
                     if let Some(f_start) = tag.find("table:formula=\"") {
                         let f_part = &tag[f_start + 15..];
                         if let Some(f_end) = f_part.find('"') {
-                            formula = f_part[..f_end].to_string();
+                            formula = normalize_formula(&unescape_xml(&f_part[..f_end]));
                         }
                     }
                 }
@@ -1051,12 +1333,22 @@ pub fn parse_ods_to_worksheets(bytes: &[u8]) -> Result<Vec<Worksheet>, String> {
             comments: None,
             protection: None,
             array_formulas: None,
+            protected_ranges: None,
+            has_header: None,
         });
     }
 
     Ok(worksheets)
 }
 
+fn unescape_xml(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
 pub fn detect_spreadsheet_format(bytes: &[u8]) -> &'static str {
     if bytes.len() >= 4 {
         if &bytes[0..4] == b"PK\x03\x04" {
@@ -1078,28 +1370,32 @@ pub fn detect_spreadsheet_format(bytes: &[u8]) -> &'static str {
     if text.contains('\t') && text.lines().count() > 1 {
         return "tsv";
     }
-    if text.contains(',') && text.lines().count() > 1 {
+    if text.contains([',', ';', '|']) && text.lines().count() > 1 {
         return "csv";
     }
 
     "unknown"
 }
 
-pub fn import_spreadsheet_bytes(bytes: &[u8], filename: &str) -> Result<Spreadsheet, String> {
+pub fn import_spreadsheet_bytes(
+    bytes: &[u8],
+    filename: &str,
+    csv_options: &CsvOptions,
+) -> Result<Spreadsheet, String> {
     let ext = filename.rsplit('.').next().unwrap_or("").to_lowercase();
     let detected = detect_spreadsheet_format(bytes);
 
-    let worksheets = match detected {
+    let mut worksheets = match detected {
         "xlsx" | "xlsm" => parse_excel_to_worksheets(bytes, "xlsx")?,
         "xls" => parse_excel_to_worksheets(bytes, "xls")?,
         "ods" => parse_ods_to_worksheets(bytes)?,
-        "csv" => parse_csv_to_worksheets(bytes, b',', "Sheet1")?,
-        "tsv" => parse_csv_to_worksheets(bytes, b'\t', "Sheet1")?,
+        "csv" => parse_csv_to_worksheets(bytes, csv_options, b',', "Sheet1")?,
+        "tsv" => parse_csv_to_worksheets(bytes, csv_options, b'\t', "Sheet1")?,
         _ => {
             if ext == "csv" {
-                parse_csv_to_worksheets(bytes, b',', "Sheet1")?
+                parse_csv_to_worksheets(bytes, csv_options, b',', "Sheet1")?
             } else if ext == "tsv" || ext == "txt" {
-                parse_csv_to_worksheets(bytes, b'\t', "Sheet1")?
+                parse_csv_to_worksheets(bytes, csv_options, b'\t', "Sheet1")?
             } else if ext == "ods" {
                 parse_ods_to_worksheets(bytes)?
             } else {
@@ -1107,6 +1403,11 @@ pub fn import_spreadsheet_bytes(bytes: &[u8], filename: &str) -> Result<Spreadsh
             }
         }
     };
+    if csv_options.header {
+        for worksheet in &mut worksheets {
+            set_header(worksheet, true);
+        }
+    }
 
     let name = filename.rsplit('/').next().unwrap_or(filename)
         .trim_end_matches(&format!(".{ext}"))
@@ -1121,6 +1422,8 @@ pub fn import_spreadsheet_bytes(bytes: &[u8], filename: &str) -> Result<Spreadsh
         updated_at: Utc::now(),
         named_ranges: None,
         external_links: None,
+        locale: None,
+        created_by: None,
     })
 }
 
@@ -1145,10 +1448,177 @@ pub fn create_new_spreadsheet() -> Spreadsheet {
             comments: None,
             protection: None,
             array_formulas: None,
+            protected_ranges: None,
+            has_header: None,
         }],
         created_at: Utc::now(),
         updated_at: Utc::now(),
         named_ranges: None,
         external_links: None,
+        locale: None,
+        created_by: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drive::object_store::{FsObjectStore, S3ObjectStore};
+    use crate::core::shared::test_utils::TestAppStateBuilder;
+    use crate::drive::objects::tests::mock_drive;
+
+    async fn write_sheet(store: &dyn ObjectStore, user_id: &str, sheet: &Spreadsheet) {
+        store
+            .put(
+                "gbo",
+                &sheet_json_path(user_id, &sheet.id),
+                serde_json::to_vec_pretty(sheet).unwrap(),
+                "application/json",
+            )
+            .await
+            .unwrap();
+    }
+
+    fn value_cell(value: &str) -> CellData {
+        CellData {
+            value: Some(value.to_string()),
+            formula: None,
+            style: None,
+            format: None,
+            note: None,
+            locked: None,
+            has_comment: None,
+            array_formula_id: None,
+        }
+    }
+
+    fn cell_value(sheet: &Spreadsheet, key: &str) -> Option<String> {
+        sheet.worksheets[0].data.get(key)?.value.clone()
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_then_edit_is_independent() {
+        let (drive, _) = mock_drive().await;
+        let store = S3ObjectStore::new(drive);
+        let user = "default-user";
+        let mut original = create_new_spreadsheet();
+        original.name = "Budget".to_string();
+        original.worksheets[0]
+            .data
+            .insert("0,0".to_string(), value_cell("100"));
+        write_sheet(&store, user, &original).await;
+
+        let store: Arc<dyn ObjectStore> = Arc::new(store);
+        let Ok(state) = TestAppStateBuilder::new()
+            .with_object_store(Arc::clone(&store))
+            .build()
+        else {
+            eprintln!("Skipping test_duplicate_then_edit_is_independent: no test database");
+            return;
+        };
+        let state = Arc::new(state);
+        let store = store.as_ref();
+
+        let copy_id = duplicate_sheet_in_drive(&state, user, &original.id)
+            .await
+            .unwrap();
+        assert_ne!(copy_id, original.id);
+
+        let mut copy = read_sheet(store, user, &copy_id).await.unwrap();
+        assert_eq!(copy.id, copy_id);
+        assert_eq!(copy.name, "Budget");
+        assert_eq!(cell_value(&copy, "0,0").as_deref(), Some("100"));
+
+        // Saving an edit to the copy goes to the copy's own file.
+        copy.worksheets[0]
+            .data
+            .insert("0,0".to_string(), value_cell("250"));
+        write_sheet(store, user, &copy).await;
+
+        let original = read_sheet(store, user, &original.id).await.unwrap();
+        let copy = read_sheet(store, user, &copy_id).await.unwrap();
+        assert_eq!(cell_value(&original, "0,0").as_deref(), Some("100"));
+        assert_eq!(cell_value(&copy, "0,0").as_deref(), Some("250"));
+    }
+
+    #[tokio::test]
+    async fn test_sheet_round_trip_on_filesystem() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FsObjectStore::new(dir.path());
+        let user = "default-user";
+
+        let mut sheet = create_new_spreadsheet();
+        sheet.name = "Inventory".to_string();
+        sheet.worksheets[0]
+            .data
+            .insert("0,0".to_string(), value_cell("widgets"));
+        sheet.worksheets[0]
+            .data
+            .insert("0,1".to_string(), value_cell("42"));
+        write_sheet(&store, user, &sheet).await;
+        write_sheet(&store, "someone-else", &create_new_spreadsheet()).await;
+
+        assert_eq!(list_sheet_ids(&store, user).await.unwrap(), [sheet.id.clone()]);
+        let loaded = read_sheet(&store, user, &sheet.id).await.unwrap();
+        assert_eq!(loaded.name, "Inventory");
+        assert_eq!(cell_value(&loaded, "0,0").as_deref(), Some("widgets"));
+        assert_eq!(cell_value(&loaded, "0,1").as_deref(), Some("42"));
+
+        // The xlsx export round-trips through the same store.
+        let xlsx_path = sheet_xlsx_path(user, &sheet.id);
+        store
+            .put("gbo", &xlsx_path, convert_to_xlsx(&sheet).unwrap(), XLSX_CONTENT_TYPE)
+            .await
+            .unwrap();
+        let bytes = store.get("gbo", &xlsx_path).await.unwrap();
+        let (from_xlsx, _) = load_xlsx_from_bytes(&bytes, user, &xlsx_path).unwrap();
+        assert_eq!(cell_value(&from_xlsx, "0,0").as_deref(), Some("widgets"));
+
+        store
+            .delete("gbo", &sheet_json_path(user, &sheet.id))
+            .await
+            .unwrap();
+        assert!(list_sheet_ids(&store, user).await.unwrap().is_empty());
+        assert!(read_sheet(&store, user, &sheet.id).await.is_err());
+    }
+
+    fn formula_workbook() -> Vec<u8> {
+        let mut workbook = rust_xlsxwriter::Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name("Totals").unwrap();
+        worksheet.write_number(0, 0, 1.0).unwrap();
+        worksheet.write_number(1, 0, 2.0).unwrap();
+        worksheet
+            .write_formula(2, 0, rust_xlsxwriter::Formula::new("=SUM(A1:A2)").set_result("3"))
+            .unwrap();
+        workbook.save_to_buffer().unwrap()
+    }
+
+    #[test]
+    fn test_imported_formula_survives() {
+        let bytes = formula_workbook();
+
+        for worksheets in [
+            parse_excel_to_worksheets(&bytes, "xlsx").unwrap(),
+            parse_with_calamine(&bytes).unwrap(),
+        ] {
+            assert_eq!(worksheets[0].name, "Totals");
+            let total = &worksheets[0].data["2,0"];
+            assert_eq!(total.formula.as_deref(), Some("=SUM(A1:A2)"));
+            assert_eq!(total.value.as_deref(), Some("3"));
+            assert_eq!(worksheets[0].data["0,0"].formula, None);
+        }
+    }
+
+    #[test]
+    fn test_normalize_formula() {
+        assert_eq!(normalize_formula("SUM(A1:A2)"), "=SUM(A1:A2)");
+        assert_eq!(normalize_formula("=SUM(A1:A2)"), "=SUM(A1:A2)");
+        assert_eq!(normalize_formula("of:=SUM([.A1:.A3])"), "=SUM(A1:A3)");
+        assert_eq!(
+            normalize_formula("of:=IF([.$B$2]>0;\"a;b\";[Data.C1])"),
+            "=IF($B$2>0,\"a;b\",Data!C1)"
+        );
+        assert_eq!(normalize_formula(""), "");
     }
 }
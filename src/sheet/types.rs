@@ -85,6 +85,22 @@ pub struct ArrayFormula {
     pub is_dynamic: bool,
 }
 
+/// Cells that only the sheet owner and the listed editors may change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProtectedRange {
+    pub id: String,
+    pub start_row: u32,
+    pub start_col: u32,
+    pub end_row: u32,
+    pub end_col: u32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub editors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NamedRange {
     pub id: String,
@@ -140,6 +156,13 @@ pub struct Spreadsheet {
     pub named_ranges: Option<Vec<NamedRange>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub external_links: Option<Vec<ExternalLink>>,
+    /// BCP 47 tag (e.g. `pt-BR`) for displaying and exporting numbers and dates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Id of the user who created the sheet, who owns its protection. `owner_id` is the
+    /// storage owner, the shared workspace user for sheets saved from the editor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -166,12 +189,21 @@ pub struct Worksheet {
     pub conditional_formats: Option<Vec<ConditionalFormatRule>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub charts: Option<Vec<ChartConfig>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub comments: Option<HashMap<String, CellComment>>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "crate::sheet::comments::deserialize_threads"
+    )]
+    pub comments: Option<HashMap<String, Vec<CellComment>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub protection: Option<SheetProtection>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub array_formulas: Option<Vec<ArrayFormula>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protected_ranges: Option<Vec<ProtectedRange>>,
+    /// Row 0 holds column labels; sort and filter leave it in place.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub has_header: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -330,6 +362,8 @@ pub struct SaveRequest {
     pub id: Option<String>,
     pub name: String,
     pub worksheets: Vec<Worksheet>,
+    #[serde(default)]
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -365,13 +399,27 @@ pub struct FormatRequest {
     pub start_col: u32,
     pub end_row: u32,
     pub end_col: u32,
-    pub style: CellStyle,
+    #[serde(default)]
+    pub style: Option<CellStyle>,
+    /// Number format code for the range, e.g. `"$#,##0.00"` or `"0.0%"`. An empty string
+    /// clears it; leaving it out keeps the current one.
+    #[serde(default)]
+    pub format: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportRequest {
     pub id: String,
     pub format: String,
+    /// Overrides the sheet locale, e.g. with the requesting user's profile locale.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Exports only this worksheet.
+    #[serde(default)]
+    pub worksheet_index: Option<usize>,
+    /// Exports only this A1 range, e.g. `"B2:D20"`, of the selected or first worksheet.
+    #[serde(default)]
+    pub range: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -381,6 +429,11 @@ pub struct ShareRequest {
     pub permission: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateSnapshotRequest {
+    pub label: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SaveResponse {
     pub id: String,
@@ -389,6 +442,38 @@ pub struct SaveResponse {
     pub message: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecalcError {
+    pub worksheet_index: usize,
+    pub cell: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecalcResponse {
+    pub id: String,
+    pub recomputed: usize,
+    pub errors: Vec<RecalcError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValuesQuery {
+    #[serde(default)]
+    pub worksheet: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValuesResponse {
+    pub id: String,
+    pub worksheet: usize,
+    pub name: String,
+    /// Rows and columns in use, which exceed the grid when it was truncated.
+    pub rows: u32,
+    pub cols: u32,
+    pub truncated: bool,
+    pub values: Vec<Vec<Option<String>>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FormulaResult {
     pub value: String,
@@ -403,6 +488,37 @@ pub struct FormulaRequest {
     pub formula: String,
 }
 
+/// A formula to analyse: `formula` if given, otherwise the formula stored in the cell at
+/// `row`/`col`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormulaDependenciesRequest {
+    pub sheet_id: String,
+    pub worksheet_index: usize,
+    #[serde(default)]
+    pub formula: Option<String>,
+    #[serde(default)]
+    pub row: Option<u32>,
+    #[serde(default)]
+    pub col: Option<u32>,
+}
+
+/// What a formula reads, sorted. References to other worksheets keep their `Sheet!` prefix.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FormulaDependencies {
+    pub cells: Vec<String>,
+    pub ranges: Vec<String>,
+    pub named_ranges: Vec<String>,
+    pub functions: Vec<String>,
+    pub unknown_names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormulaDependenciesResponse {
+    pub formula: String,
+    #[serde(flatten)]
+    pub dependencies: FormulaDependencies,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MergeCellsRequest {
     pub sheet_id: String,
@@ -421,6 +537,52 @@ pub struct FreezePanesRequest {
     pub frozen_cols: u32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeaderRequest {
+    pub sheet_id: String,
+    pub worksheet_index: usize,
+    pub has_header: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddWorksheetRequest {
+    pub sheet_id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Index of the new worksheet; it goes last when omitted.
+    #[serde(default)]
+    pub position: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenameWorksheetRequest {
+    pub sheet_id: String,
+    pub worksheet_index: usize,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteWorksheetRequest {
+    pub sheet_id: String,
+    pub worksheet_index: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveWorksheetRequest {
+    pub sheet_id: String,
+    pub worksheet_index: usize,
+    pub position: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorksheetsResponse {
+    pub id: String,
+    /// Index of the worksheet the change applied to, after the change.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub worksheet_index: Option<usize>,
+    pub worksheets: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SortRequest {
     pub sheet_id: String,
@@ -603,6 +765,27 @@ pub struct LockCellsRequest {
     pub locked: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtectRangeRequest {
+    pub sheet_id: String,
+    pub worksheet_index: usize,
+    pub start_row: u32,
+    pub start_col: u32,
+    pub end_row: u32,
+    pub end_col: u32,
+    #[serde(default)]
+    pub editors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClearRangeProtectionRequest {
+    pub sheet_id: String,
+    pub worksheet_index: usize,
+    pub range_id: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddExternalLinkRequest {
     pub sheet_id: String,
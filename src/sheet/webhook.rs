@@ -0,0 +1,458 @@
+//! Inbound webhooks that let external systems push data into a sheet.
+//!
+//! Each sheet can have one signing secret. A push is a JSON body signed like outgoing
+//! webhooks (`X-Webhook-Signature: v1=<hex hmac>` over `<timestamp>.<body>`, with the
+//! timestamp in `X-Webhook-Timestamp`). The payload addresses one worksheet and either sets
+//! cells by A1 reference, appends rows below the last used row, or both. Values are stored
+//! as literal text; a leading `=` does not make a formula.
+
+use crate::core::rate_limit::KeyedRateLimiter;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::security::webhook::{WebhookConfig, WebhookSecurityManager};
+use crate::sheet::error::SheetError;
+use crate::sheet::formulas::parse_cell_key;
+use crate::sheet::protection::ensure_cell_editable;
+use crate::sheet::spill::{is_spill_id, respill_worksheet};
+use crate::sheet::types::{CellData, Spreadsheet, Worksheet};
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+use uuid::Uuid;
+
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+pub const MAX_PAYLOAD_BYTES: usize = 256 * 1024;
+pub const MAX_CELLS_PER_PUSH: usize = 5_000;
+const MAX_ROW: u32 = 1_048_575;
+const MAX_COL: u32 = 16_383;
+const MAX_VALUE_LEN: usize = 32_767;
+
+static SIGNATURES: LazyLock<WebhookSecurityManager> = LazyLock::new(|| {
+    WebhookSecurityManager::new(WebhookConfig {
+        max_payload_size: MAX_PAYLOAD_BYTES,
+        ..WebhookConfig::default()
+    })
+});
+
+/// Pushes per second per sheet, with bursts for catching up after an outage.
+static PUSH_LIMITER: LazyLock<KeyedRateLimiter> = LazyLock::new(|| KeyedRateLimiter::new(5, 20));
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum WorksheetRef {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InboundPayload {
+    /// Worksheet index or name; the first worksheet when omitted.
+    pub worksheet: Option<WorksheetRef>,
+    /// Values by A1 reference, e.g. `{"B3": 42}`.
+    #[serde(default)]
+    pub cells: BTreeMap<String, Value>,
+    /// Rows appended below the last used row, starting in column A.
+    #[serde(default)]
+    pub rows: Vec<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellWrite {
+    pub row: u32,
+    pub col: u32,
+    pub value: String,
+}
+
+/// The identity pushes are checked against: never the owner, so protected cells stay
+/// protected.
+pub fn webhook_actor() -> AuthenticatedUser {
+    AuthenticatedUser::service("sheet-webhook")
+}
+
+pub async fn check_rate_limit(sheet_id: &str) -> Result<(), SheetError> {
+    if PUSH_LIMITER.check(sheet_id).await {
+        Ok(())
+    } else {
+        Err(SheetError::RateLimited)
+    }
+}
+
+pub async fn verify_push(
+    body: &[u8],
+    signature: &str,
+    timestamp: &str,
+    secret: &str,
+) -> Result<(), SheetError> {
+    verify_with(&SIGNATURES, body, signature, timestamp, secret).await
+}
+
+async fn verify_with(
+    manager: &WebhookSecurityManager,
+    body: &[u8],
+    signature: &str,
+    timestamp: &str,
+    secret: &str,
+) -> Result<(), SheetError> {
+    manager
+        .validate_payload_size(body)
+        .map_err(|_| SheetError::PayloadTooLarge(MAX_PAYLOAD_BYTES))?;
+    let payload = std::str::from_utf8(body)
+        .map_err(|_| SheetError::InvalidRequest("Body must be UTF-8 JSON".to_string()))?;
+    let validation = manager
+        .verify_signature(payload, signature, timestamp, secret)
+        .await;
+    if validation.is_valid() {
+        Ok(())
+    } else {
+        Err(SheetError::InvalidSignature(
+            validation.error_message().to_string(),
+        ))
+    }
+}
+
+/// Strict A1 reference: 1-3 letters then a row number starting at 1.
+fn parse_a1(reference: &str) -> Option<(u32, u32)> {
+    let reference = reference.trim().to_ascii_uppercase();
+    let split = reference.find(|c: char| !c.is_ascii_alphabetic())?;
+    let (letters, digits) = reference.split_at(split);
+    if letters.is_empty() || letters.len() > 3 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let row: u32 = digits.parse().ok().filter(|r| *r >= 1)?;
+    let col = letters
+        .bytes()
+        .fold(0u32, |acc, b| acc * 26 + u32::from(b - b'A' + 1));
+    Some((row - 1, col - 1))
+}
+
+fn value_text(value: &Value) -> Result<Option<String>, String> {
+    let text = match value {
+        Value::Null => return Ok(None),
+        Value::String(s) => s.clone(),
+        Value::Bool(true) => "TRUE".to_string(),
+        Value::Bool(false) => "FALSE".to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::Array(_) | Value::Object(_) => {
+            return Err("Cell values must be strings, numbers, booleans or null".to_string())
+        }
+    };
+    if text.len() > MAX_VALUE_LEN {
+        return Err(format!("Cell values are limited to {MAX_VALUE_LEN} bytes"));
+    }
+    Ok(Some(text))
+}
+
+pub fn resolve_worksheet(
+    sheet: &Spreadsheet,
+    reference: Option<&WorksheetRef>,
+) -> Result<usize, SheetError> {
+    match reference {
+        None => (!sheet.worksheets.is_empty())
+            .then_some(0)
+            .ok_or(SheetError::InvalidWorksheet),
+        Some(WorksheetRef::Index(index)) => (*index < sheet.worksheets.len())
+            .then_some(*index)
+            .ok_or(SheetError::InvalidWorksheet),
+        Some(WorksheetRef::Name(name)) => sheet
+            .worksheets
+            .iter()
+            .position(|ws| ws.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or(SheetError::InvalidWorksheet),
+    }
+}
+
+/// First row below every cell that has content; 0 for an empty worksheet.
+fn next_free_row(worksheet: &Worksheet) -> u32 {
+    worksheet
+        .data
+        .iter()
+        .filter(|(_, cell)| cell.value.is_some() || cell.formula.is_some())
+        .filter_map(|(key, _)| parse_cell_key(key))
+        .map(|(row, _)| row + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Turns a payload into cell writes, checking every target is inside the sheet's bounds.
+/// `null` clears a cell set by reference and leaves an appended cell empty.
+pub fn plan_writes(
+    worksheet: &Worksheet,
+    payload: &InboundPayload,
+) -> Result<Vec<CellWrite>, SheetError> {
+    let invalid = |msg: String| SheetError::InvalidRequest(msg);
+    let total = payload.cells.len() + payload.rows.iter().map(Vec::len).sum::<usize>();
+    if total == 0 {
+        return Err(invalid("Payload has no cells or rows".to_string()));
+    }
+    if total > MAX_CELLS_PER_PUSH {
+        return Err(invalid(format!(
+            "A push may write at most {MAX_CELLS_PER_PUSH} cells"
+        )));
+    }
+
+    let mut writes = Vec::with_capacity(total);
+    for (reference, value) in &payload.cells {
+        let (row, col) = parse_a1(reference)
+            .filter(|(row, col)| *row <= MAX_ROW && *col <= MAX_COL)
+            .ok_or_else(|| invalid(format!("Invalid cell reference '{reference}'")))?;
+        let value = value_text(value).map_err(invalid)?.unwrap_or_default();
+        writes.push(CellWrite { row, col, value });
+    }
+
+    let first_row = next_free_row(worksheet);
+    for (offset, values) in payload.rows.iter().enumerate() {
+        let row = first_row + offset as u32;
+        if row > MAX_ROW || values.len() > MAX_COL as usize + 1 {
+            return Err(invalid(
+                "Appended rows exceed the worksheet size".to_string(),
+            ));
+        }
+        for (col, value) in values.iter().enumerate() {
+            if let Some(value) = value_text(value).map_err(invalid)? {
+                writes.push(CellWrite {
+                    row,
+                    col: col as u32,
+                    value,
+                });
+            }
+        }
+    }
+    Ok(writes)
+}
+
+/// Checks protection for every write before changing anything, then applies them.
+pub fn apply_writes(
+    sheet: &mut Spreadsheet,
+    worksheet_index: usize,
+    writes: &[CellWrite],
+) -> Result<(), SheetError> {
+    let actor = webhook_actor();
+    for write in writes {
+        ensure_cell_editable(sheet, worksheet_index, write.row, write.col, &actor)?;
+    }
+
+    let worksheet = sheet
+        .worksheets
+        .get_mut(worksheet_index)
+        .ok_or(SheetError::InvalidWorksheet)?;
+    for write in writes {
+        let key = format!("{},{}", write.row, write.col);
+        if write.value.is_empty() {
+            if let Some(cell) = worksheet.data.get_mut(&key) {
+                cell.value = None;
+                cell.formula = None;
+            }
+            continue;
+        }
+        let cell = worksheet.data.entry(key).or_insert_with(|| CellData {
+            value: None,
+            formula: None,
+            style: None,
+            format: None,
+            note: None,
+            locked: None,
+            has_comment: None,
+            array_formula_id: None,
+        });
+        cell.value = Some(write.value.clone());
+        cell.formula = None;
+        if cell.array_formula_id.as_deref().is_some_and(is_spill_id) {
+            cell.array_formula_id = None;
+        }
+    }
+    respill_worksheet(worksheet);
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+pub struct WebhookRow {
+    #[diesel(sql_type = Text)]
+    pub owner_id: String,
+    #[diesel(sql_type = Text)]
+    pub secret: String,
+}
+
+fn db_error(e: diesel::result::Error) -> SheetError {
+    SheetError::StorageFailed(e.to_string())
+}
+
+/// 32 random bytes, hex encoded.
+pub fn generate_secret() -> String {
+    let mut rng = rand::rng();
+    let bytes: Vec<u8> = (0..32).map(|_| rng.random()).collect();
+    hex::encode(bytes)
+}
+
+/// Creates the sheet's webhook, or rotates its secret if it already has one.
+pub fn upsert_webhook(
+    conn: &mut PgConnection,
+    sheet_id: &str,
+    owner_id: &str,
+    secret: &str,
+    created_by: Uuid,
+) -> Result<(), SheetError> {
+    diesel::sql_query(
+        "INSERT INTO sheet_webhooks (sheet_id, owner_id, secret, created_by) \
+         VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (sheet_id) DO UPDATE SET owner_id = EXCLUDED.owner_id, \
+         secret = EXCLUDED.secret, created_by = EXCLUDED.created_by, created_at = NOW()",
+    )
+    .bind::<Text, _>(sheet_id)
+    .bind::<Text, _>(owner_id)
+    .bind::<Text, _>(secret)
+    .bind::<diesel::sql_types::Uuid, _>(created_by)
+    .execute(conn)
+    .map_err(db_error)?;
+    Ok(())
+}
+
+pub fn find_webhook(conn: &mut PgConnection, sheet_id: &str) -> Result<WebhookRow, SheetError> {
+    diesel::sql_query("SELECT owner_id, secret FROM sheet_webhooks WHERE sheet_id = $1")
+        .bind::<Text, _>(sheet_id)
+        .get_result::<WebhookRow>(conn)
+        .optional()
+        .map_err(db_error)?
+        .ok_or_else(|| SheetError::SheetNotFound(sheet_id.to_string()))
+}
+
+pub fn delete_webhook(conn: &mut PgConnection, sheet_id: &str) -> Result<(), SheetError> {
+    let deleted = diesel::sql_query("DELETE FROM sheet_webhooks WHERE sheet_id = $1")
+        .bind::<Text, _>(sheet_id)
+        .execute(conn)
+        .map_err(db_error)?;
+    if deleted == 0 {
+        return Err(SheetError::SheetNotFound(sheet_id.to_string()));
+    }
+    Ok(())
+}
+
+pub fn touch_webhook(conn: &mut PgConnection, sheet_id: &str) -> Result<(), SheetError> {
+    diesel::sql_query("UPDATE sheet_webhooks SET last_received_at = NOW() WHERE sheet_id = $1")
+        .bind::<Text, _>(sheet_id)
+        .execute(conn)
+        .map_err(db_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::create_new_spreadsheet;
+    use crate::sheet::types::ProtectedRange;
+    use chrono::Utc;
+
+    fn payload(json: Value) -> InboundPayload {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_signature_verification() {
+        let manager = WebhookSecurityManager::with_defaults();
+        let body = br#"{"rows":[["a",1]]}"#;
+        let now = Utc::now();
+        let signature = manager.sign_payload(std::str::from_utf8(body).unwrap(), "s3cret", now);
+        let timestamp = now.timestamp().to_string();
+
+        assert!(matches!(
+            verify_with(&manager, body, &signature, &timestamp, "other").await,
+            Err(SheetError::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            verify_with(
+                &manager,
+                br#"{"rows":[["b",1]]}"#,
+                &signature,
+                &timestamp,
+                "s3cret"
+            )
+            .await,
+            Err(SheetError::InvalidSignature(_))
+        ));
+        assert!(
+            verify_with(&manager, body, &signature, &timestamp, "s3cret")
+                .await
+                .is_ok()
+        );
+        // The same signed request cannot be replayed.
+        assert!(
+            verify_with(&manager, body, &signature, &timestamp, "s3cret")
+                .await
+                .is_err()
+        );
+        assert!(matches!(
+            verify_with(
+                &manager,
+                &vec![b' '; MAX_PAYLOAD_BYTES + 1],
+                "v1=x",
+                &timestamp,
+                "s"
+            )
+            .await,
+            Err(SheetError::PayloadTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_rows_append_below_existing_data() {
+        let mut sheet = create_new_spreadsheet();
+        let first = payload(serde_json::json!({"rows": [["name", "total"], ["Acme", 10]]}));
+        let writes = plan_writes(&sheet.worksheets[0], &first).unwrap();
+        apply_writes(&mut sheet, 0, &writes).unwrap();
+
+        let second = payload(serde_json::json!({
+            "worksheet": "Sheet1",
+            "rows": [["Globex", 12.5, null, true]],
+            "cells": {"D1": "flag"}
+        }));
+        let writes = plan_writes(&sheet.worksheets[0], &second).unwrap();
+        apply_writes(&mut sheet, 0, &writes).unwrap();
+
+        let ws = &sheet.worksheets[0];
+        let cell = |key: &str| ws.data.get(key).and_then(|c| c.value.clone());
+        assert_eq!(cell("0,0").as_deref(), Some("name"));
+        assert_eq!(cell("1,1").as_deref(), Some("10"));
+        assert_eq!(cell("2,0").as_deref(), Some("Globex"));
+        assert_eq!(cell("2,1").as_deref(), Some("12.5"));
+        assert_eq!(cell("2,2"), None);
+        assert_eq!(cell("2,3").as_deref(), Some("TRUE"));
+        assert_eq!(cell("0,3").as_deref(), Some("flag"));
+    }
+
+    #[test]
+    fn test_target_range_is_validated() {
+        let mut sheet = create_new_spreadsheet();
+        let ws = &sheet.worksheets[0];
+        for reference in ["A0", "1A", "ZZZZ1", "XFE1", "A1048577", ""] {
+            let bad = payload(serde_json::json!({"cells": {reference: 1}}));
+            assert!(plan_writes(ws, &bad).is_err(), "{reference}");
+        }
+        assert!(plan_writes(ws, &payload(serde_json::json!({}))).is_err());
+        assert!(resolve_worksheet(&sheet, Some(&WorksheetRef::Index(3))).is_err());
+        assert!(resolve_worksheet(&sheet, Some(&WorksheetRef::Name("Nope".into()))).is_err());
+
+        sheet.worksheets[0].protected_ranges = Some(vec![ProtectedRange {
+            id: "p1".to_string(),
+            start_row: 0,
+            start_col: 0,
+            end_row: 0,
+            end_col: 0,
+            editors: Vec::new(),
+            description: None,
+            created_by: "owner".to_string(),
+            created_at: Utc::now(),
+        }]);
+        let writes = plan_writes(
+            &sheet.worksheets[0],
+            &payload(serde_json::json!({"cells": {"A1": "x"}})),
+        )
+        .unwrap();
+        assert!(matches!(
+            apply_writes(&mut sheet, 0, &writes),
+            Err(SheetError::CellProtected(_))
+        ));
+    }
+}
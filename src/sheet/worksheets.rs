@@ -0,0 +1,480 @@
+//! Adding, renaming, deleting and reordering the worksheets of a spreadsheet.
+//!
+//! Formulas refer to other worksheets by name (`Sheet2!A1`, `'Q1 Sales'!B2:B9`). A rename
+//! rewrites those references to the new name, and a delete turns them into `#REF!`, so no
+//! formula silently points at a different worksheet. Named ranges scoped to a worksheet
+//! follow it by index.
+
+use crate::sheet::error::SheetError;
+use crate::sheet::types::{Spreadsheet, Worksheet};
+use std::collections::HashMap;
+
+/// Longest worksheet name, as in Excel.
+pub const MAX_WORKSHEET_NAME: usize = 31;
+const FORBIDDEN_NAME_CHARS: &[char] = &[':', '\\', '/', '?', '*', '[', ']'];
+
+pub fn blank_worksheet(name: &str) -> Worksheet {
+    Worksheet {
+        name: name.to_string(),
+        data: HashMap::new(),
+        column_widths: None,
+        row_heights: None,
+        frozen_rows: None,
+        frozen_cols: None,
+        merged_cells: None,
+        filters: None,
+        hidden_rows: None,
+        validations: None,
+        conditional_formats: None,
+        charts: None,
+        comments: None,
+        protection: None,
+        array_formulas: None,
+        protected_ranges: None,
+        has_header: None,
+    }
+}
+
+/// The trimmed `name` if it is a valid worksheet name not used by any worksheet other than
+/// `except`. Names are compared case-insensitively.
+pub fn validate_worksheet_name(
+    sheet: &Spreadsheet,
+    name: &str,
+    except: Option<usize>,
+) -> Result<String, SheetError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(SheetError::InvalidRequest(
+            "Worksheet name cannot be empty".to_string(),
+        ));
+    }
+    if name.chars().count() > MAX_WORKSHEET_NAME {
+        return Err(SheetError::InvalidRequest(format!(
+            "Worksheet name cannot be longer than {MAX_WORKSHEET_NAME} characters"
+        )));
+    }
+    if let Some(c) = name.chars().find(|c| FORBIDDEN_NAME_CHARS.contains(c)) {
+        return Err(SheetError::InvalidRequest(format!(
+            "Worksheet name cannot contain '{c}'"
+        )));
+    }
+    if name.starts_with('\'') || name.ends_with('\'') {
+        return Err(SheetError::InvalidRequest(
+            "Worksheet name cannot start or end with an apostrophe".to_string(),
+        ));
+    }
+    let taken = sheet
+        .worksheets
+        .iter()
+        .enumerate()
+        .any(|(i, ws)| Some(i) != except && ws.name.eq_ignore_ascii_case(name));
+    if taken {
+        return Err(SheetError::InvalidRequest(format!(
+            "A worksheet named '{name}' already exists"
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// Inserts a blank worksheet at `position` (default: last) and returns its index. Without
+/// a name it is called `SheetN`, with the first free `N`.
+pub fn add_worksheet(
+    sheet: &mut Spreadsheet,
+    name: Option<&str>,
+    position: Option<usize>,
+) -> Result<usize, SheetError> {
+    let index = position.unwrap_or(sheet.worksheets.len());
+    if index > sheet.worksheets.len() {
+        return Err(SheetError::InvalidWorksheet);
+    }
+    let name = match name {
+        Some(name) => validate_worksheet_name(sheet, name, None)?,
+        None => (sheet.worksheets.len() + 1..)
+            .map(|n| format!("Sheet{n}"))
+            .find(|name| {
+                !sheet
+                    .worksheets
+                    .iter()
+                    .any(|ws| ws.name.eq_ignore_ascii_case(name))
+            })
+            .unwrap_or_default(),
+    };
+
+    sheet.worksheets.insert(index, blank_worksheet(&name));
+    remap_named_ranges(sheet, |i| Some(if i >= index { i + 1 } else { i }));
+    Ok(index)
+}
+
+/// Renames the worksheet at `index` and rewrites every formula that refers to it. Returns
+/// the old name.
+pub fn rename_worksheet(
+    sheet: &mut Spreadsheet,
+    index: usize,
+    name: &str,
+) -> Result<String, SheetError> {
+    if index >= sheet.worksheets.len() {
+        return Err(SheetError::InvalidWorksheet);
+    }
+    let name = validate_worksheet_name(sheet, name, Some(index))?;
+    let old = std::mem::replace(&mut sheet.worksheets[index].name, name.clone());
+    rewrite_sheet_formulas(sheet, &old, Some(&name));
+    Ok(old)
+}
+
+/// Removes the worksheet at `index`. References to it become `#REF!`, and named ranges
+/// scoped to it are dropped. A spreadsheet always keeps at least one worksheet.
+pub fn delete_worksheet(sheet: &mut Spreadsheet, index: usize) -> Result<Worksheet, SheetError> {
+    if index >= sheet.worksheets.len() {
+        return Err(SheetError::InvalidWorksheet);
+    }
+    if sheet.worksheets.len() == 1 {
+        return Err(SheetError::InvalidRequest(
+            "Cannot delete the only worksheet".to_string(),
+        ));
+    }
+    let removed = sheet.worksheets.remove(index);
+    rewrite_sheet_formulas(sheet, &removed.name, None);
+    remap_named_ranges(sheet, |i| match i.cmp(&index) {
+        std::cmp::Ordering::Less => Some(i),
+        std::cmp::Ordering::Equal => None,
+        std::cmp::Ordering::Greater => Some(i - 1),
+    });
+    Ok(removed)
+}
+
+/// Moves the worksheet at `from` so that it ends up at index `to`.
+pub fn move_worksheet(sheet: &mut Spreadsheet, from: usize, to: usize) -> Result<(), SheetError> {
+    let len = sheet.worksheets.len();
+    if from >= len || to >= len {
+        return Err(SheetError::InvalidWorksheet);
+    }
+    let worksheet = sheet.worksheets.remove(from);
+    sheet.worksheets.insert(to, worksheet);
+    remap_named_ranges(sheet, |i| {
+        Some(if i == from {
+            to
+        } else if from < to && i > from && i <= to {
+            i - 1
+        } else if to < from && i >= to && i < from {
+            i + 1
+        } else {
+            i
+        })
+    });
+    Ok(())
+}
+
+/// Applies `map` to the worksheet index of every scoped named range, dropping those it
+/// maps to `None`.
+fn remap_named_ranges(sheet: &mut Spreadsheet, map: impl Fn(usize) -> Option<usize>) {
+    let Some(ranges) = &mut sheet.named_ranges else {
+        return;
+    };
+    ranges.retain_mut(|range| match range.worksheet_index {
+        None => true,
+        Some(i) => match map(i) {
+            Some(new) => {
+                range.worksheet_index = Some(new);
+                true
+            }
+            None => false,
+        },
+    });
+}
+
+fn rewrite_sheet_formulas(sheet: &mut Spreadsheet, target: &str, replacement: Option<&str>) {
+    for worksheet in &mut sheet.worksheets {
+        for cell in worksheet.data.values_mut() {
+            if let Some(formula) = &mut cell.formula {
+                *formula = rewrite_references(formula, target, replacement);
+            }
+        }
+        for array_formula in worksheet.array_formulas.iter_mut().flatten() {
+            array_formula.formula = rewrite_references(&array_formula.formula, target, replacement);
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Whether `name` reads as a cell reference such as `Q1` or `AB12`: one to three column
+/// letters followed by a row number. `Sheet2` does not, its letters are too many.
+fn looks_like_cell_reference(name: &str) -> bool {
+    let letters = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    (1..=3).contains(&letters.len())
+        && letters.len() < name.len()
+        && letters.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// `name` as it must be written before `!` in a formula, quoted when needed.
+pub fn quote_worksheet_name(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(is_name_char);
+    if plain && !looks_like_cell_reference(name) {
+        name.to_string()
+    } else {
+        format!("'{}'", name.replace('\'', "''"))
+    }
+}
+
+/// Rewrites references to worksheet `target` in `formula`: to `replacement` on a rename,
+/// or to `#REF!` (dropping the cell or range) when `replacement` is `None`. String
+/// literals are left alone.
+pub fn rewrite_references(formula: &str, target: &str, replacement: Option<&str>) -> String {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut out = String::with_capacity(formula.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let reference = if c == '"' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&c| c == '"')
+                .map_or(chars.len(), |p| i + p + 2);
+            out.extend(&chars[i..end]);
+            i = end;
+            continue;
+        } else if c == '\'' {
+            quoted_reference(&chars, i)
+        } else if is_name_char(c)
+            && (i == 0 || !(is_name_char(chars[i - 1]) || chars[i - 1] == '#'))
+        {
+            let end = chars[i..]
+                .iter()
+                .position(|&c| !is_name_char(c))
+                .map_or(chars.len(), |p| i + p);
+            if chars.get(end) == Some(&'!') {
+                Some((chars[i..end].iter().collect(), end + 1))
+            } else {
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+        } else {
+            None
+        };
+
+        match reference {
+            Some((name, end)) if name.eq_ignore_ascii_case(target) => match replacement {
+                Some(new) => {
+                    out.push_str(&quote_worksheet_name(new));
+                    out.push('!');
+                    i = end;
+                }
+                None => {
+                    out.push_str("#REF!");
+                    i = chars[end..]
+                        .iter()
+                        .position(|&c| !(c.is_ascii_alphanumeric() || c == '$' || c == ':'))
+                        .map_or(chars.len(), |p| end + p);
+                }
+            },
+            Some((_, end)) => {
+                out.extend(&chars[i..end]);
+                i = end;
+            }
+            None => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Parses `'name'!` at `start`, with `''` as an escaped apostrophe. Returns the name and
+/// the index just past the `!`.
+pub(crate) fn quoted_reference(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut name = String::new();
+    let mut i = start + 1;
+    while i < chars.len() {
+        if chars[i] == '\'' {
+            if chars.get(i + 1) == Some(&'\'') {
+                name.push('\'');
+                i += 2;
+                continue;
+            }
+            return (chars.get(i + 1) == Some(&'!')).then_some((name, i + 2));
+        }
+        name.push(chars[i]);
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::create_new_spreadsheet;
+    use crate::sheet::types::{CellData, NamedRange};
+
+    fn formula_cell(formula: &str) -> CellData {
+        CellData {
+            value: None,
+            formula: Some(formula.to_string()),
+            style: None,
+            format: None,
+            note: None,
+            locked: None,
+            has_comment: None,
+            array_formula_id: None,
+        }
+    }
+
+    fn named_range(name: &str, worksheet_index: Option<usize>) -> NamedRange {
+        NamedRange {
+            id: name.to_string(),
+            name: name.to_string(),
+            scope: if worksheet_index.is_some() {
+                "worksheet"
+            } else {
+                "workbook"
+            }
+            .to_string(),
+            worksheet_index,
+            start_row: 0,
+            start_col: 0,
+            end_row: 0,
+            end_col: 0,
+            comment: None,
+        }
+    }
+
+    fn three_sheets() -> Spreadsheet {
+        let mut sheet = create_new_spreadsheet();
+        add_worksheet(&mut sheet, Some("Data"), None).unwrap();
+        add_worksheet(&mut sheet, Some("Summary"), None).unwrap();
+        sheet
+    }
+
+    fn formula(sheet: &Spreadsheet, index: usize, key: &str) -> String {
+        sheet.worksheets[index].data[key].formula.clone().unwrap()
+    }
+
+    #[test]
+    fn test_rename_rewrites_references() {
+        let mut sheet = three_sheets();
+        sheet.worksheets[2].data.insert(
+            "0,0".to_string(),
+            formula_cell("=SUM(Data!A1:A9)+data!$B$2&\"Data!A1\""),
+        );
+        sheet.worksheets[0]
+            .data
+            .insert("0,0".to_string(), formula_cell("=MyData!A1+Data!C3"));
+
+        assert_eq!(rename_worksheet(&mut sheet, 1, "Q1 Sales").unwrap(), "Data");
+
+        assert_eq!(sheet.worksheets[1].name, "Q1 Sales");
+        assert_eq!(
+            formula(&sheet, 2, "0,0"),
+            "=SUM('Q1 Sales'!A1:A9)+'Q1 Sales'!$B$2&\"Data!A1\""
+        );
+        assert_eq!(formula(&sheet, 0, "0,0"), "=MyData!A1+'Q1 Sales'!C3");
+
+        rename_worksheet(&mut sheet, 1, "Raw").unwrap();
+        assert_eq!(formula(&sheet, 0, "0,0"), "=MyData!A1+Raw!C3");
+
+        rename_worksheet(&mut sheet, 1, "Sheet2").unwrap();
+        assert_eq!(formula(&sheet, 0, "0,0"), "=MyData!A1+Sheet2!C3");
+
+        rename_worksheet(&mut sheet, 1, "AB12").unwrap();
+        assert_eq!(formula(&sheet, 0, "0,0"), "=MyData!A1+'AB12'!C3");
+    }
+
+    #[test]
+    fn test_rename_validates_names() {
+        let mut sheet = three_sheets();
+        let long = "x".repeat(32);
+        for bad in ["", "  ", "a/b", "[x]", "'quoted", long.as_str(), "summary"] {
+            assert!(
+                matches!(
+                    rename_worksheet(&mut sheet, 1, bad),
+                    Err(SheetError::InvalidRequest(_))
+                ),
+                "{bad:?} accepted"
+            );
+        }
+        assert!(rename_worksheet(&mut sheet, 1, "DATA").is_ok());
+        assert!(matches!(
+            rename_worksheet(&mut sheet, 5, "Other"),
+            Err(SheetError::InvalidWorksheet)
+        ));
+    }
+
+    #[test]
+    fn test_delete_last_worksheet_is_rejected() {
+        let mut sheet = create_new_spreadsheet();
+        assert!(matches!(
+            delete_worksheet(&mut sheet, 0),
+            Err(SheetError::InvalidRequest(_))
+        ));
+        assert_eq!(sheet.worksheets.len(), 1);
+    }
+
+    #[test]
+    fn test_delete_breaks_references_and_shifts_named_ranges() {
+        let mut sheet = three_sheets();
+        sheet.worksheets[2].data.insert(
+            "0,0".to_string(),
+            formula_cell("=SUM('Data'!A1:B2)+Sheet1!A1"),
+        );
+        sheet.named_ranges = Some(vec![
+            named_range("global", None),
+            named_range("on_data", Some(1)),
+            named_range("on_summary", Some(2)),
+        ]);
+
+        assert_eq!(delete_worksheet(&mut sheet, 1).unwrap().name, "Data");
+
+        assert_eq!(formula(&sheet, 1, "0,0"), "=SUM(#REF!)+Sheet1!A1");
+        let ranges = sheet.named_ranges.unwrap();
+        let scoped: Vec<_> = ranges
+            .iter()
+            .map(|r| (r.name.as_str(), r.worksheet_index))
+            .collect();
+        assert_eq!(scoped, vec![("global", None), ("on_summary", Some(1))]);
+    }
+
+    #[test]
+    fn test_add_and_move_worksheets() {
+        let mut sheet = three_sheets();
+        sheet.named_ranges = Some(vec![named_range("on_sheet1", Some(0))]);
+
+        assert_eq!(add_worksheet(&mut sheet, None, Some(0)).unwrap(), 0);
+        assert_eq!(sheet.worksheets[0].name, "Sheet4");
+        assert_eq!(
+            sheet.named_ranges.as_ref().unwrap()[0].worksheet_index,
+            Some(1)
+        );
+
+        move_worksheet(&mut sheet, 1, 3).unwrap();
+        let names: Vec<_> = sheet.worksheets.iter().map(|ws| ws.name.as_str()).collect();
+        assert_eq!(names, vec!["Sheet4", "Data", "Summary", "Sheet1"]);
+        assert_eq!(
+            sheet.named_ranges.as_ref().unwrap()[0].worksheet_index,
+            Some(3)
+        );
+
+        assert!(matches!(
+            add_worksheet(&mut sheet, None, Some(9)),
+            Err(SheetError::InvalidWorksheet)
+        ));
+    }
+
+    #[test]
+    fn test_quote_worksheet_name() {
+        assert_eq!(quote_worksheet_name("Data"), "Data");
+        assert_eq!(quote_worksheet_name("Q1"), "'Q1'");
+        assert_eq!(quote_worksheet_name("Sheet2"), "Sheet2");
+        assert_eq!(quote_worksheet_name("Sales2024"), "Sales2024");
+        assert_eq!(quote_worksheet_name("XFD1048576"), "'XFD1048576'");
+        assert_eq!(quote_worksheet_name("2024"), "'2024'");
+        assert_eq!(quote_worksheet_name("Bob's"), "'Bob''s'");
+    }
+}
@@ -0,0 +1,472 @@
+//! Coalesces sheet writes to the drive.
+//!
+//! Every edit replaces the sheet's pending copy in memory instead of writing it out. The
+//! pending copy is written once no edit has arrived for the autosave interval, or right
+//! away once it holds `max_pending` edits, so a burst of edits costs one drive write.
+//! Readers see the pending copy (see [`SheetWriteBuffer::pending`]); exports, collaborator
+//! disconnects and server shutdown flush it explicitly.
+//!
+//! Writes of one sheet never overlap. A failed background write keeps the copy buffered
+//! and marks it failed; the next edit or load of the sheet retries the write and reports
+//! the error if it fails again.
+
+use crate::sheet::error::SheetError;
+use crate::sheet::types::Spreadsheet;
+use log::{error, warn};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+/// Autosave settings, read from the environment.
+///
+/// `SHEET_AUTOSAVE_INTERVAL_MS` is how long a sheet must go without edits before its
+/// pending changes are written (default 2000, `0` writes every edit immediately).
+/// `SHEET_AUTOSAVE_MAX_PENDING` forces a write after that many buffered edits
+/// (default 100).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AutosaveConfig {
+    pub interval: Duration,
+    pub max_pending: usize,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(2000),
+            max_pending: 100,
+        }
+    }
+}
+
+impl AutosaveConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let number = |key: &str| lookup(key).and_then(|v| v.trim().parse::<u64>().ok());
+        let defaults = Self::default();
+        Self {
+            interval: number("SHEET_AUTOSAVE_INTERVAL_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.interval),
+            max_pending: number("SHEET_AUTOSAVE_MAX_PENDING")
+                .filter(|n| *n > 0)
+                .map(|n| n as usize)
+                .unwrap_or(defaults.max_pending),
+        }
+    }
+}
+
+/// Buffered sheets are keyed by owner and sheet id.
+type SheetKey = (String, String);
+
+struct Pending {
+    sheet: Spreadsheet,
+    edits: usize,
+    /// Changes on every edit, so an idle timer can tell whether newer edits arrived.
+    generation: u64,
+    /// Error of the last failed write of this copy.
+    failed: Option<String>,
+}
+
+pub struct SheetWriteBuffer {
+    config: AutosaveConfig,
+    pending: Mutex<HashMap<SheetKey, Pending>>,
+    generations: AtomicU64,
+    /// Held while a sheet is written, so writes of one sheet run one at a time.
+    flushing: Mutex<HashMap<SheetKey, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+static SHEET_WRITES: LazyLock<Arc<SheetWriteBuffer>> =
+    LazyLock::new(|| Arc::new(SheetWriteBuffer::new(AutosaveConfig::from_env())));
+
+pub fn sheet_writes() -> &'static Arc<SheetWriteBuffer> {
+    &SHEET_WRITES
+}
+
+impl SheetWriteBuffer {
+    pub fn new(config: AutosaveConfig) -> Self {
+        Self {
+            config,
+            pending: Mutex::new(HashMap::new()),
+            generations: AtomicU64::new(1),
+            flushing: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn key(user_id: &str, sheet_id: &str) -> SheetKey {
+        (user_id.to_string(), sheet_id.to_string())
+    }
+
+    /// The not yet written copy of a sheet, if it has buffered edits.
+    pub fn pending(&self, user_id: &str, sheet_id: &str) -> Option<Spreadsheet> {
+        let pending = self.pending.lock().ok()?;
+        pending
+            .get(&Self::key(user_id, sheet_id))
+            .map(|p| p.sheet.clone())
+    }
+
+    /// Whether the last write of the sheet's buffered copy failed.
+    pub fn has_failed(&self, user_id: &str, sheet_id: &str) -> bool {
+        self.pending
+            .lock()
+            .ok()
+            .and_then(|pending| {
+                pending
+                    .get(&Self::key(user_id, sheet_id))
+                    .map(|p| p.failed.is_some())
+            })
+            .unwrap_or(false)
+    }
+
+    /// Drops a sheet's buffered edits without writing them, e.g. because the sheet was
+    /// saved explicitly or deleted.
+    pub fn discard(&self, user_id: &str, sheet_id: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&Self::key(user_id, sheet_id));
+        }
+    }
+
+    /// Current copy and generation of a buffered sheet, restarting its edit count.
+    fn snapshot(&self, key: &SheetKey) -> Option<(Spreadsheet, u64)> {
+        let mut pending = self.pending.lock().ok()?;
+        let entry = pending.get_mut(key)?;
+        entry.edits = 0;
+        Some((entry.sheet.clone(), entry.generation))
+    }
+
+    /// Buffers an edited sheet. `write` is called with the owner and the latest copy when
+    /// the buffered edits are due; it runs before this returns when the edit fills the
+    /// buffer, otherwise from a background timer.
+    pub async fn record<W, Fut>(
+        self: &Arc<Self>,
+        user_id: &str,
+        sheet: Spreadsheet,
+        write: W,
+    ) -> Result<(), SheetError>
+    where
+        W: Fn(String, Spreadsheet) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), SheetError>> + Send + 'static,
+    {
+        let key = Self::key(user_id, &sheet.id);
+        if self.config.interval.is_zero() {
+            self.discard(&key.0, &key.1);
+            return write(key.0, sheet).await;
+        }
+
+        let generation = self.generations.fetch_add(1, Ordering::Relaxed);
+        let due = {
+            let mut pending = self
+                .pending
+                .lock()
+                .map_err(|_| SheetError::StorageFailed("Sheet write buffer poisoned".into()))?;
+            let previous = pending.get(&key);
+            let edits = previous.map_or(0, |p| p.edits) + 1;
+            let failed = previous.and_then(|p| p.failed.clone());
+            // A copy whose last write failed is written now, so the caller hears about it.
+            let due = edits >= self.config.max_pending || failed.is_some();
+            pending.insert(
+                key.clone(),
+                Pending {
+                    sheet,
+                    edits,
+                    generation,
+                    failed,
+                },
+            );
+            due
+        };
+
+        if due {
+            return self.flush_key(key, &write).await;
+        }
+
+        let buffer = Arc::clone(self);
+        let interval = self.config.interval;
+        tokio::spawn(async move {
+            tokio::time::sleep(interval).await;
+            let idle = buffer
+                .pending
+                .lock()
+                .map(|pending| {
+                    pending
+                        .get(&key)
+                        .is_some_and(|p| p.generation == generation)
+                })
+                .unwrap_or(false);
+            if idle {
+                if let Err(e) = buffer.flush_key(key.clone(), &write).await {
+                    error!("Autosave of sheet {} failed: {}", key.1, e);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    fn flush_lock(&self, key: &SheetKey) -> Arc<tokio::sync::Mutex<()>> {
+        let mut flushing = self.flushing.lock().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(flushing.entry(key.clone()).or_default())
+    }
+
+    /// Writes the sheet's buffered copy. It stays readable while the write is in flight
+    /// and is only dropped afterwards if no edit arrived meanwhile. On failure it stays
+    /// buffered and is marked failed.
+    async fn flush_key<W, Fut>(&self, key: SheetKey, write: &W) -> Result<(), SheetError>
+    where
+        W: Fn(String, Spreadsheet) -> Fut,
+        Fut: Future<Output = Result<(), SheetError>>,
+    {
+        let lock = self.flush_lock(&key);
+        let result = {
+            let _writing = lock.lock().await;
+            match self.snapshot(&key) {
+                Some((sheet, generation)) => {
+                    let result = write(key.0.clone(), sheet).await;
+                    self.finish_write(&key, generation, &result);
+                    result
+                }
+                None => Ok(()),
+            }
+        };
+        if let Ok(mut flushing) = self.flushing.lock() {
+            // Only this call and the map hold the lock: nobody else is writing the sheet.
+            if Arc::strong_count(&lock) == 2 {
+                flushing.remove(&key);
+            }
+        }
+        result
+    }
+
+    fn finish_write(&self, key: &SheetKey, generation: u64, result: &Result<(), SheetError>) {
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        match result {
+            Ok(()) => {
+                if pending.get(key).is_some_and(|p| p.generation == generation) {
+                    pending.remove(key);
+                } else if let Some(entry) = pending.get_mut(key) {
+                    entry.failed = None;
+                }
+            }
+            Err(e) => {
+                if let Some(entry) = pending.get_mut(key) {
+                    entry.failed = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Writes a sheet's buffered edits now. Does nothing when it has none.
+    pub async fn flush<W, Fut>(
+        &self,
+        user_id: &str,
+        sheet_id: &str,
+        write: W,
+    ) -> Result<(), SheetError>
+    where
+        W: Fn(String, Spreadsheet) -> Fut,
+        Fut: Future<Output = Result<(), SheetError>>,
+    {
+        self.flush_key(Self::key(user_id, sheet_id), &write).await
+    }
+
+    /// Writes every buffered sheet. Failures are logged and the sheet stays buffered.
+    /// Returns how many sheets were written.
+    pub async fn flush_all<W, Fut>(&self, write: W) -> usize
+    where
+        W: Fn(String, Spreadsheet) -> Fut,
+        Fut: Future<Output = Result<(), SheetError>>,
+    {
+        let keys: Vec<SheetKey> = self
+            .pending
+            .lock()
+            .map(|pending| pending.keys().cloned().collect())
+            .unwrap_or_default();
+        let mut written = 0;
+        for key in keys {
+            let sheet_id = key.1.clone();
+            match self.flush_key(key, &write).await {
+                Ok(()) => written += 1,
+                Err(e) => warn!("Failed to flush sheet {}: {}", sheet_id, e),
+            }
+        }
+        written
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sheet::storage::create_new_spreadsheet;
+    use crate::sheet::types::CellData;
+    use std::sync::atomic::AtomicUsize;
+
+    fn edit(sheet: &mut Spreadsheet, value: usize) {
+        sheet.worksheets[0].data.insert(
+            "0,0".to_string(),
+            CellData {
+                value: Some(value.to_string()),
+                formula: None,
+                style: None,
+                format: None,
+                note: None,
+                locked: None,
+                has_comment: None,
+                array_formula_id: None,
+            },
+        );
+    }
+
+    fn value(sheet: &Spreadsheet) -> Option<String> {
+        sheet.worksheets[0].data.get("0,0")?.value.clone()
+    }
+
+    #[tokio::test]
+    async fn test_rapid_edits_produce_one_write() {
+        let buffer = Arc::new(SheetWriteBuffer::new(AutosaveConfig {
+            interval: Duration::from_millis(100),
+            max_pending: 1000,
+        }));
+        let writes = Arc::new(AtomicUsize::new(0));
+        let written = Arc::new(Mutex::new(None));
+        let writer = {
+            let writes = Arc::clone(&writes);
+            let written = Arc::clone(&written);
+            move |_user: String, sheet: Spreadsheet| {
+                writes.fetch_add(1, Ordering::SeqCst);
+                *written.lock().unwrap() = Some(sheet);
+                async { Ok::<(), SheetError>(()) }
+            }
+        };
+
+        let mut sheet = create_new_spreadsheet();
+        for n in 1..=50 {
+            edit(&mut sheet, n);
+            buffer
+                .record("default-user", sheet.clone(), writer.clone())
+                .await
+                .unwrap();
+        }
+        assert_eq!(writes.load(Ordering::SeqCst), 0);
+        let pending = buffer.pending("default-user", &sheet.id).unwrap();
+        assert_eq!(value(&pending).as_deref(), Some("50"));
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(writes.load(Ordering::SeqCst), 1);
+        let flushed = written.lock().unwrap().take().unwrap();
+        assert_eq!(value(&flushed).as_deref(), Some("50"));
+        assert!(buffer.pending("default-user", &sheet.id).is_none());
+
+        // Edits followed by an explicit flush are written once, not again by the timer.
+        edit(&mut sheet, 51);
+        buffer
+            .record("default-user", sheet.clone(), writer.clone())
+            .await
+            .unwrap();
+        assert_eq!(buffer.flush_all(writer.clone()).await, 1);
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(writes.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_autosave_stays_buffered_and_is_reported() {
+        let buffer = Arc::new(SheetWriteBuffer::new(AutosaveConfig {
+            interval: Duration::from_millis(50),
+            max_pending: 1000,
+        }));
+        let down = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let writer = {
+            let down = Arc::clone(&down);
+            move |_user: String, _sheet: Spreadsheet| {
+                let down = down.load(Ordering::SeqCst);
+                async move {
+                    if down {
+                        Err(SheetError::StorageFailed("drive down".to_string()))
+                    } else {
+                        Ok(())
+                    }
+                }
+            }
+        };
+
+        let mut sheet = create_new_spreadsheet();
+        edit(&mut sheet, 1);
+        buffer
+            .record("default-user", sheet.clone(), writer.clone())
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(buffer.has_failed("default-user", &sheet.id));
+        let pending = buffer.pending("default-user", &sheet.id).unwrap();
+        assert_eq!(value(&pending).as_deref(), Some("1"));
+
+        // The next edit retries at once and reports the failure, keeping the edit.
+        edit(&mut sheet, 2);
+        let err = buffer
+            .record("default-user", sheet.clone(), writer.clone())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("drive down"));
+        let pending = buffer.pending("default-user", &sheet.id).unwrap();
+        assert_eq!(value(&pending).as_deref(), Some("2"));
+
+        down.store(false, Ordering::SeqCst);
+        buffer
+            .flush("default-user", &sheet.id, writer.clone())
+            .await
+            .unwrap();
+        assert!(!buffer.has_failed("default-user", &sheet.id));
+        assert!(buffer.pending("default-user", &sheet.id).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_writes_of_one_sheet_do_not_overlap() {
+        let buffer = Arc::new(SheetWriteBuffer::new(AutosaveConfig {
+            interval: Duration::from_secs(60),
+            max_pending: 1000,
+        }));
+        let active = Arc::new(AtomicUsize::new(0));
+        let overlapped = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let writer = {
+            let (active, overlapped) = (Arc::clone(&active), Arc::clone(&overlapped));
+            move |_user: String, _sheet: Spreadsheet| {
+                let (active, overlapped) = (Arc::clone(&active), Arc::clone(&overlapped));
+                async move {
+                    if active.fetch_add(1, Ordering::SeqCst) > 0 {
+                        overlapped.store(true, Ordering::SeqCst);
+                    }
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    Ok::<(), SheetError>(())
+                }
+            }
+        };
+
+        let mut sheet = create_new_spreadsheet();
+        let sheet_id = sheet.id.clone();
+        edit(&mut sheet, 1);
+        buffer
+            .record("default-user", sheet.clone(), writer.clone())
+            .await
+            .unwrap();
+        let first = buffer.flush("default-user", &sheet_id, writer.clone());
+        let second = async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            edit(&mut sheet, 2);
+            buffer
+                .record("default-user", sheet.clone(), writer.clone())
+                .await
+                .unwrap();
+            buffer.flush("default-user", &sheet_id, writer.clone()).await
+        };
+        let (first, second) = tokio::join!(first, second);
+        first.unwrap();
+        second.unwrap();
+        assert!(!overlapped.load(Ordering::SeqCst));
+        assert!(buffer.pending("default-user", &sheet.id).is_none());
+    }
+}
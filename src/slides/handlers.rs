@@ -1,4 +1,6 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
+use crate::security::upload_policy::check_upload;
 use crate::slides::collaboration::broadcast_slide_change;
 use crate::slides::storage::{
     create_new_presentation, create_slide_with_layout, delete_presentation_from_drive,
@@ -31,7 +33,7 @@ use uuid::Uuid;
 
 pub async fn handle_slides_ai(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<SlidesAiRequest>,
+    ApiJson(req): ApiJson<SlidesAiRequest>,
 ) -> impl IntoResponse {
     let command = req.command.to_lowercase();
 
@@ -131,7 +133,7 @@ pub async fn handle_load_presentation(
 
 pub async fn handle_save_presentation(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<SavePresentationRequest>,
+    ApiJson(req): ApiJson<SavePresentationRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let presentation_id = req.id.unwrap_or_else(|| Uuid::new_v4().to_string());
@@ -162,7 +164,7 @@ pub async fn handle_save_presentation(
 
 pub async fn handle_delete_presentation(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<LoadQuery>,
+    ApiJson(req): ApiJson<LoadQuery>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
 
@@ -196,7 +198,7 @@ pub async fn handle_get_presentation_by_id(
 
 pub async fn handle_add_slide(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddSlideRequest>,
+    ApiJson(req): ApiJson<AddSlideRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await
@@ -240,7 +242,7 @@ pub async fn handle_add_slide(
 
 pub async fn handle_delete_slide(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteSlideRequest>,
+    ApiJson(req): ApiJson<DeleteSlideRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await
@@ -280,7 +282,7 @@ pub async fn handle_delete_slide(
 
 pub async fn handle_duplicate_slide(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DuplicateSlideRequest>,
+    ApiJson(req): ApiJson<DuplicateSlideRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await
@@ -322,7 +324,7 @@ pub async fn handle_duplicate_slide(
 
 pub async fn handle_reorder_slides(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ReorderSlidesRequest>,
+    ApiJson(req): ApiJson<ReorderSlidesRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await
@@ -368,7 +370,7 @@ pub async fn handle_reorder_slides(
 
 pub async fn handle_update_slide_notes(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<UpdateSlideNotesRequest>,
+    ApiJson(req): ApiJson<UpdateSlideNotesRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await
@@ -408,7 +410,7 @@ pub async fn handle_update_slide_notes(
 
 pub async fn handle_add_element(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddElementRequest>,
+    ApiJson(req): ApiJson<AddElementRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await
@@ -459,7 +461,7 @@ pub async fn handle_add_element(
 
 pub async fn handle_update_element(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<UpdateElementRequest>,
+    ApiJson(req): ApiJson<UpdateElementRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await
@@ -516,7 +518,7 @@ pub async fn handle_update_element(
 
 pub async fn handle_delete_element(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteElementRequest>,
+    ApiJson(req): ApiJson<DeleteElementRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await
@@ -558,7 +560,7 @@ pub async fn handle_delete_element(
 
 pub async fn handle_apply_theme(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ApplyThemeRequest>,
+    ApiJson(req): ApiJson<ApplyThemeRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await
@@ -591,7 +593,7 @@ pub async fn handle_apply_theme(
 
 pub async fn handle_export_presentation(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ExportRequest>,
+    ApiJson(req): ApiJson<ExportRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
 
@@ -667,7 +669,7 @@ static PRESENTER_SESSIONS: LazyLock<RwLock<HashMap<String, PresenterSession>>> =
 
 pub async fn handle_update_cursor(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<UpdateCursorRequest>,
+    ApiJson(req): ApiJson<UpdateCursorRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
 
@@ -693,7 +695,7 @@ pub async fn handle_update_cursor(
 
 pub async fn handle_update_selection(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<UpdateSelectionRequest>,
+    ApiJson(req): ApiJson<UpdateSelectionRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
 
@@ -746,7 +748,7 @@ pub async fn handle_list_selections(
 
 pub async fn handle_set_transition(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<SetTransitionRequest>,
+    ApiJson(req): ApiJson<SetTransitionRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await {
@@ -785,7 +787,7 @@ pub async fn handle_set_transition(
 
 pub async fn handle_apply_transition_to_all(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<ApplyTransitionToAllRequest>,
+    ApiJson(req): ApiJson<ApplyTransitionToAllRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await {
@@ -819,7 +821,7 @@ pub async fn handle_apply_transition_to_all(
 
 pub async fn handle_remove_transition(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<RemoveTransitionRequest>,
+    ApiJson(req): ApiJson<RemoveTransitionRequest>,
 ) -> Result<Json<SaveResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await {
@@ -859,7 +861,7 @@ pub async fn handle_remove_transition(
 
 pub async fn handle_add_media(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<AddMediaRequest>,
+    ApiJson(req): ApiJson<AddMediaRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await {
@@ -895,7 +897,7 @@ pub async fn handle_add_media(
 
 pub async fn handle_update_media(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<UpdateMediaRequest>,
+    ApiJson(req): ApiJson<UpdateMediaRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await {
@@ -954,7 +956,7 @@ pub async fn handle_update_media(
 
 pub async fn handle_delete_media(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<DeleteMediaRequest>,
+    ApiJson(req): ApiJson<DeleteMediaRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
     let mut presentation = match load_presentation_by_id(&state, &user_id, &req.presentation_id).await {
@@ -1020,7 +1022,7 @@ pub async fn handle_list_media(
 
 pub async fn handle_start_presenter(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<StartPresenterRequest>,
+    ApiJson(req): ApiJson<StartPresenterRequest>,
 ) -> Result<Json<PresenterSessionResponse>, (StatusCode, Json<serde_json::Value>)> {
     let user_id = get_current_user_id();
 
@@ -1053,7 +1055,7 @@ pub async fn handle_start_presenter(
 
 pub async fn handle_update_presenter(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<UpdatePresenterRequest>,
+    ApiJson(req): ApiJson<UpdatePresenterRequest>,
 ) -> Result<Json<PresenterSessionResponse>, (StatusCode, Json<serde_json::Value>)> {
     let session = if let Ok(mut sessions) = PRESENTER_SESSIONS.write() {
         if let Some(session) = sessions.get_mut(&req.session_id) {
@@ -1085,7 +1087,7 @@ pub async fn handle_update_presenter(
 
 pub async fn handle_end_presenter(
     State(_state): State<Arc<AppState>>,
-    Json(req): Json<EndPresenterRequest>,
+    ApiJson(req): ApiJson<EndPresenterRequest>,
 ) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     if let Ok(mut sessions) = PRESENTER_SESSIONS.write() {
         sessions.remove(&req.session_id);
@@ -1140,10 +1142,12 @@ pub async fn handle_import_presentation(
 ) -> Result<Json<Presentation>, (StatusCode, Json<serde_json::Value>)> {
     let mut file_bytes: Option<Vec<u8>> = None;
     let mut filename = "import.pptx".to_string();
+    let mut content_type = String::new();
 
     while let Ok(Some(field)) = multipart.next_field().await {
         if field.name() == Some("file") {
             filename = field.file_name().unwrap_or("import.pptx").to_string();
+            content_type = field.content_type().unwrap_or_default().to_string();
             if let Ok(bytes) = field.bytes().await {
                 file_bytes = Some(bytes.to_vec());
             }
@@ -1156,6 +1160,14 @@ pub async fn handle_import_presentation(
             Json(serde_json::json!({ "error": "No file uploaded" })),
         )
     })?;
+    check_upload("slides-import", &filename, &content_type, &bytes)
+        .await
+        .map_err(|rejection| {
+            (
+                rejection.status(),
+                Json(serde_json::json!({ "error": rejection.to_string() })),
+            )
+        })?;
 
     let ext = filename.rsplit('.').next().unwrap_or("").to_lowercase();
     let theme = create_default_theme();
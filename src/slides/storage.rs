@@ -1,4 +1,5 @@
 use crate::core::shared::state::AppState;
+use crate::drive::quota;
 use crate::slides::ooxml::update_pptx_text;
 use crate::slides::types::{
     ElementContent, ElementStyle, Presentation, PresentationMetadata, Slide,
@@ -63,9 +64,9 @@ pub async fn save_presentation_to_drive(
     user_id: &str,
     presentation: &Presentation,
 ) -> Result<(), String> {
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
     let path = format!(
@@ -76,15 +77,16 @@ pub async fn save_presentation_to_drive(
     let content = serde_json::to_string_pretty(presentation)
         .map_err(|e| format!("Serialization error: {e}"))?;
 
-    drive
-        .put_object()
-        .bucket("gbo")
-        .key(&path)
-        .body(content.into_bytes().into())
-        .content_type("application/json")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to save presentation: {e}"))?;
+    quota::put_tracked(
+        state,
+        store,
+        "gbo",
+        &path,
+        content.into_bytes(),
+        "application/json",
+    )
+    .await
+    .map_err(|e| format!("Failed to save presentation: {e}"))?;
 
     Ok(())
 }
@@ -107,9 +109,9 @@ pub async fn save_presentation_as_pptx(
         convert_to_pptx(presentation)?
     };
 
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
     let path = format!(
@@ -118,15 +120,16 @@ pub async fn save_presentation_as_pptx(
         presentation.id
     );
 
-    drive
-        .put_object()
-        .bucket("gbo")
-        .key(&path)
-        .body(pptx_bytes.clone().into())
-        .content_type("application/vnd.openxmlformats-officedocument.presentationml.presentation")
-        .send()
-        .await
-        .map_err(|e| format!("Failed to save PPTX: {e}"))?;
+    quota::put_tracked(
+        state,
+        store,
+        "gbo",
+        &path,
+        pptx_bytes.clone(),
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    )
+    .await
+    .map_err(|e| format!("Failed to save PPTX: {e}"))?;
 
     Ok(pptx_bytes)
 }
@@ -503,27 +506,16 @@ pub async fn load_pptx_from_drive(
     user_id: &str,
     file_path: &str,
 ) -> Result<Presentation, String> {
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
-    let result = drive
-        .get_object()
-        .bucket("gbo")
-        .key(file_path)
-        .send()
+    let bytes = store
+        .get("gbo", file_path)
         .await
         .map_err(|e| format!("Failed to load PPTX: {e}"))?;
 
-    let bytes = result
-        .body
-        .collect()
-        .await
-        .map_err(|e| format!("Failed to read PPTX: {e}"))?
-        .into_bytes()
-        .to_vec();
-
     load_pptx_from_bytes(&bytes, user_id, file_path).await
 }
 
@@ -715,9 +707,9 @@ pub async fn load_presentation_by_id(
     user_id: &str,
     presentation_id: &str,
 ) -> Result<Presentation, String> {
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
     let path = format!(
@@ -726,21 +718,11 @@ pub async fn load_presentation_by_id(
         presentation_id
     );
 
-    let result = drive
-        .get_object()
-        .bucket("gbo")
-        .key(&path)
-        .send()
+    let bytes = store
+        .get("gbo", &path)
         .await
         .map_err(|e| format!("Failed to load presentation: {e}"))?;
 
-    let bytes = result
-        .body
-        .collect()
-        .await
-        .map_err(|e| format!("Failed to read presentation: {e}"))?
-        .into_bytes();
-
     let presentation: Presentation =
         serde_json::from_slice(&bytes).map_err(|e| format!("Failed to parse presentation: {e}"))?;
 
@@ -751,39 +733,32 @@ pub async fn list_presentations_from_drive(
     state: &Arc<AppState>,
     user_id: &str,
 ) -> Result<Vec<PresentationMetadata>, String> {
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
     let prefix = format!("{}/", get_user_presentations_path(user_id));
 
-    let result = drive
-        .list_objects_v2()
-        .bucket("gbo")
-        .prefix(&prefix)
-        .send()
+    let objects = store
+        .list("gbo", &prefix)
         .await
         .map_err(|e| format!("Failed to list presentations: {e}"))?;
 
     let mut presentations = Vec::new();
 
-    if let Some(contents) = result.contents {
-        for obj in contents {
-            if let Some(key) = obj.key {
-                if key.ends_with(".json") {
-                    let id = extract_id_from_path(&key);
-                    if let Ok(presentation) = load_presentation_by_id(state, user_id, &id).await {
-                        presentations.push(PresentationMetadata {
-                            id: presentation.id,
-                            name: presentation.name,
-                            owner_id: presentation.owner_id,
-                            slide_count: presentation.slides.len(),
-                            created_at: presentation.created_at,
-                            updated_at: presentation.updated_at,
-                        });
-                    }
-                }
+    for obj in objects {
+        if obj.key.ends_with(".json") {
+            let id = extract_id_from_path(&obj.key);
+            if let Ok(presentation) = load_presentation_by_id(state, user_id, &id).await {
+                presentations.push(PresentationMetadata {
+                    id: presentation.id,
+                    name: presentation.name,
+                    owner_id: presentation.owner_id,
+                    slide_count: presentation.slides.len(),
+                    created_at: presentation.created_at,
+                    updated_at: presentation.updated_at,
+                });
             }
         }
     }
@@ -802,9 +777,9 @@ pub async fn delete_presentation_from_drive(
         .as_ref()
         .ok_or_else(|| "Presentation ID is required".to_string())?;
 
-    let drive = state
-        .drive
-        .as_ref()
+    let store = state
+        .object_store
+        .as_deref()
         .ok_or_else(|| "Drive not available".to_string())?;
 
     let json_path = format!(
@@ -818,19 +793,9 @@ pub async fn delete_presentation_from_drive(
         presentation_id
     );
 
-    let _ = drive
-        .delete_object()
-        .bucket("gbo")
-        .key(&json_path)
-        .send()
-        .await;
-
-    let _ = drive
-        .delete_object()
-        .bucket("gbo")
-        .key(&pptx_path)
-        .send()
-        .await;
+    for path in [&json_path, &pptx_path] {
+        let _ = quota::delete_tracked(state, store, "gbo", path).await;
+    }
 
     Ok(())
 }
@@ -1,5 +1,6 @@
 pub mod ui;
 
+use crate::core::shared::api_json::ApiJson;
 use axum::{
     extract::{Form, Path, Query, State},
     response::{Html, IntoResponse},
@@ -677,7 +678,7 @@ pub async fn handle_get_post(
 pub async fn handle_update_post(
     State(state): State<Arc<AppState>>,
     Path(post_id): Path<Uuid>,
-    Json(req): Json<UpdatePostRequest>,
+    ApiJson(req): ApiJson<UpdatePostRequest>,
 ) -> Result<Json<Post>, SocialError> {
     let pool = state.conn.clone();
 
@@ -772,7 +773,7 @@ pub async fn handle_list_communities(
 
 pub async fn handle_create_community(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateCommunityRequest>,
+    ApiJson(req): ApiJson<CreateCommunityRequest>,
 ) -> Result<Json<Community>, SocialError> {
     let pool = state.conn.clone();
     let user_id = Uuid::nil();
@@ -933,7 +934,7 @@ pub async fn handle_leave_community(
 pub async fn handle_add_reaction(
     State(state): State<Arc<AppState>>,
     Path(post_id): Path<Uuid>,
-    Json(req): Json<CreateReactionRequest>,
+    ApiJson(req): ApiJson<CreateReactionRequest>,
 ) -> Result<Json<Reaction>, SocialError> {
     let pool = state.conn.clone();
     let user_id = Uuid::nil();
@@ -1030,7 +1031,7 @@ pub async fn handle_get_comments(
 pub async fn handle_add_comment(
     State(state): State<Arc<AppState>>,
     Path(post_id): Path<Uuid>,
-    Json(req): Json<CreateCommentRequest>,
+    ApiJson(req): ApiJson<CreateCommentRequest>,
 ) -> Result<Json<Comment>, SocialError> {
     let pool = state.conn.clone();
     let user_id = Uuid::nil();
@@ -1074,7 +1075,7 @@ pub async fn handle_add_comment(
 
 pub async fn handle_send_praise(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreatePraiseRequest>,
+    ApiJson(req): ApiJson<CreatePraiseRequest>,
 ) -> Result<Json<Praise>, SocialError> {
     let pool = state.conn.clone();
     let from_user_id = Uuid::nil();
@@ -1,4 +1,8 @@
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
+use crate::drive::quota;
+use crate::security::auth_api::AuthenticatedUser;
+use crate::security::upload_policy::check_upload;
 use axum::{
     extract::{Multipart, Path, Query, State},
     response::{Html, IntoResponse},
@@ -242,9 +246,11 @@ pub fn configure_knowledge_base_routes() -> Router<Arc<AppState>> {
 
 pub async fn handle_upload_document(
     State(state): State<Arc<AppState>>,
+    user: AuthenticatedUser,
     mut multipart: Multipart,
 ) -> impl IntoResponse {
     let mut file_name = String::new();
+    let mut content_type = String::new();
     let mut file_data: Vec<u8> = Vec::new();
     let mut collection = "default".to_string();
 
@@ -254,6 +260,7 @@ pub async fn handle_upload_document(
         match name.as_str() {
             "file" => {
                 file_name = field.file_name().unwrap_or("unknown").to_string();
+                content_type = field.content_type().unwrap_or_default().to_string();
                 if let Ok(data) = field.bytes().await {
                     file_data = data.to_vec();
                 }
@@ -276,6 +283,17 @@ pub async fn handle_upload_document(
         });
     }
 
+    if let Err(rejection) =
+        check_upload("kb-upload", &file_name, &content_type, &file_data).await
+    {
+        return Json(UploadResponse {
+            success: false,
+            source_id: None,
+            message: rejection.to_string(),
+            chunks_created: None,
+        });
+    }
+
     let extension = file_name.rsplit('.').next().unwrap_or("txt").to_lowercase();
     let source_type = SourceType::from(extension.as_str());
 
@@ -292,6 +310,20 @@ pub async fn handle_upload_document(
         }
     };
 
+    let usage_owner = quota::user_owner(&user.user_id.to_string());
+    let stored_bytes = content.len() as u64;
+    let delta = match quota::check_owner(&state, &usage_owner, 0, stored_bytes).await {
+        Ok(delta) => delta,
+        Err(e) => {
+            return Json(UploadResponse {
+                success: false,
+                source_id: None,
+                message: e.to_string(),
+                chunks_created: None,
+            });
+        }
+    };
+
     let content_hash = compute_content_hash(&content);
     let source_id = Uuid::new_v4().to_string();
 
@@ -305,6 +337,7 @@ pub async fn handle_upload_document(
     let content_hash_clone = content_hash.clone();
     let collection_clone = collection.clone();
     let chunks_clone = chunks.clone();
+    let owner_clone = usage_owner.clone();
 
     let result = tokio::task::spawn_blocking(move || {
         let mut db_conn = match conn.get() {
@@ -316,8 +349,8 @@ pub async fn handle_upload_document(
         };
 
         let insert_result = diesel::sql_query(
-            "INSERT INTO knowledge_sources (id, name, source_type, content_hash, chunk_count, status, collection, created_at, updated_at)
-             VALUES ($1, $2, $3, $4, $5, 'processing', $6, NOW(), NOW())
+            "INSERT INTO knowledge_sources (id, name, source_type, content_hash, chunk_count, status, collection, metadata, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, 'processing', $6, jsonb_build_object('usage_owner', $7::TEXT, 'stored_bytes', $8::BIGINT), NOW(), NOW())
              ON CONFLICT (id) DO NOTHING"
         )
         .bind::<diesel::sql_types::Text, _>(&source_id_clone)
@@ -326,6 +359,8 @@ pub async fn handle_upload_document(
         .bind::<diesel::sql_types::Text, _>(&content_hash_clone)
         .bind::<diesel::sql_types::Integer, _>(chunk_count)
         .bind::<diesel::sql_types::Text, _>(&collection_clone)
+        .bind::<diesel::sql_types::Text, _>(&owner_clone)
+        .bind::<diesel::sql_types::BigInt, _>(stored_bytes as i64)
         .execute(&mut db_conn);
 
         if let Err(e) = insert_result {
@@ -366,6 +401,7 @@ pub async fn handle_upload_document(
 
     match result {
         Ok(Ok(())) => {
+            quota::record_owner_change(&state, &usage_owner, delta).await;
             info!(
                 "Successfully ingested {} with {} chunks",
                 file_name, chunk_count
@@ -525,7 +561,7 @@ pub async fn handle_list_sources(
 
 pub async fn handle_query_knowledge_base(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<QueryRequest>,
+    ApiJson(payload): ApiJson<QueryRequest>,
 ) -> impl IntoResponse {
     let start_time = std::time::Instant::now();
 
@@ -688,6 +724,14 @@ pub async fn handle_delete_source(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
 ) -> impl IntoResponse {
+    #[derive(QueryableByName)]
+    struct ChargedSource {
+        #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+        usage_owner: Option<String>,
+        #[diesel(sql_type = diesel::sql_types::BigInt)]
+        stored_bytes: i64,
+    }
+
     let conn = state.conn.clone();
 
     let result = tokio::task::spawn_blocking(move || {
@@ -695,7 +739,7 @@ pub async fn handle_delete_source(
             Ok(c) => c,
             Err(e) => {
                 error!("DB connection error: {}", e);
-                return false;
+                return None;
             }
         };
 
@@ -703,16 +747,25 @@ pub async fn handle_delete_source(
             .bind::<diesel::sql_types::Text, _>(&id)
             .execute(&mut db_conn);
 
-        let delete_result = diesel::sql_query("DELETE FROM knowledge_sources WHERE id = $1")
-            .bind::<diesel::sql_types::Text, _>(&id)
-            .execute(&mut db_conn);
-
-        delete_result.is_ok()
+        diesel::sql_query(
+            "DELETE FROM knowledge_sources WHERE id = $1
+             RETURNING metadata->>'usage_owner' AS usage_owner,
+                       COALESCE((metadata->>'stored_bytes')::BIGINT, 0)::BIGINT AS stored_bytes",
+        )
+        .bind::<diesel::sql_types::Text, _>(&id)
+        .load::<ChargedSource>(&mut db_conn)
+        .ok()
     })
     .await
-    .unwrap_or(false);
+    .ok()
+    .flatten();
 
-    if result {
+    if let Some(deleted) = result {
+        for source in deleted {
+            if let Some(owner) = source.usage_owner {
+                quota::record_owner_change(&state, &owner, -source.stored_bytes).await;
+            }
+        }
         Html("".to_string())
     } else {
         Html("<div class=\"error\">Failed to delete source</div>".to_string())
@@ -721,7 +774,7 @@ pub async fn handle_delete_source(
 
 pub async fn handle_reindex_sources(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<ReindexRequest>,
+    ApiJson(payload): ApiJson<ReindexRequest>,
 ) -> impl IntoResponse {
     let conn = state.conn.clone();
     let source_ids = payload.source_ids.clone();
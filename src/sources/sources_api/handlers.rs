@@ -1,5 +1,6 @@
 use crate::basic::keywords::mcp_directory::McpCsvLoader;
 use crate::basic::keywords::get_all_keywords;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use super::types::{ApiResponse, SearchQuery, BotQuery, RepositoryInfo, AppInfo};
 
@@ -704,7 +705,7 @@ fn render_api_keys_html(keys: &[ApiKeyInfo]) -> String {
 
 pub async fn handle_add_api_key(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<serde_json::Value>,
+    ApiJson(payload): ApiJson<serde_json::Value>,
 ) -> impl IntoResponse {
     use crate::core::config::manager::ConfigManager;
 
@@ -741,6 +742,42 @@ pub async fn handle_delete_api_key(
     Json(serde_json::json!({ "success": true }))
 }
 
+pub async fn handle_website_sources_status(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<BotQuery>,
+) -> impl IntoResponse {
+    use crate::core::kb::list_source_status;
+
+    let Some(bot_id) = params
+        .bot_id
+        .as_deref()
+        .and_then(|id| uuid::Uuid::parse_str(id).ok())
+    else {
+        return (
+            axum::http::StatusCode::BAD_REQUEST,
+            Json(ApiResponse::<Vec<crate::core::kb::SourceStatus>>::error("Valid bot_id is required")),
+        );
+    };
+
+    let mut conn = match state.conn.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            return (
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                Json(ApiResponse::error(&e.to_string())),
+            )
+        }
+    };
+
+    match list_source_status(&mut conn, &bot_id) {
+        Ok(statuses) => (axum::http::StatusCode::OK, Json(ApiResponse::success(statuses))),
+        Err(e) => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiResponse::error(&e.to_string())),
+        ),
+    }
+}
+
 pub fn configure_sources_routes() -> axum::Router<Arc<AppState>> {
     use crate::core::urls::ApiUrls;
     use super::mcp_handlers::*;
@@ -777,4 +814,5 @@ pub fn configure_sources_routes() -> axum::Router<Arc<AppState>> {
         .route(ApiUrls::SOURCES_API_KEYS_BY_ID, delete(handle_delete_api_key))
         .route(ApiUrls::SOURCES_MENTIONS, get(handle_mentions_autocomplete))
         .route(ApiUrls::SOURCES_TOOLS, get(handle_list_all_tools))
+        .route(ApiUrls::SOURCES_WEBSITES_STATUS, get(handle_website_sources_status))
 }
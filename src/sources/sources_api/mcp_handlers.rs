@@ -1,5 +1,6 @@
 use crate::basic::keywords::mcp_directory::McpCsvLoader;
 use crate::basic::keywords::get_all_keywords;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use super::types::{ApiResponse, BotQuery, McpServerResponse, McpToolResponse, AddMcpServerRequest, McpConnectionRequest, McpAuthRequest};
 
@@ -47,7 +48,7 @@ pub async fn handle_list_mcp_servers_json(
 pub async fn handle_add_mcp_server(
     State(_state): State<Arc<AppState>>,
     Query(params): Query<BotQuery>,
-    Json(request): Json<AddMcpServerRequest>,
+    ApiJson(request): ApiJson<AddMcpServerRequest>,
 ) -> impl IntoResponse {
     let bot_id = params.bot_id.unwrap_or_else(|| "default".to_string());
     let work_path = crate::core::shared::utils::get_work_path();
@@ -155,7 +156,7 @@ pub async fn handle_update_mcp_server(
     State(_state): State<Arc<AppState>>,
     Path(name): Path<String>,
     Query(params): Query<BotQuery>,
-    Json(request): Json<AddMcpServerRequest>,
+    ApiJson(request): ApiJson<AddMcpServerRequest>,
 ) -> impl IntoResponse {
     let bot_id = params.bot_id.unwrap_or_else(|| "default".to_string());
     let work_path = crate::core::shared::utils::get_work_path();
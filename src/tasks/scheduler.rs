@@ -1,4 +1,6 @@
 use crate::security::command_guard::SafeCommand;
+use crate::core::backup::quiesce::{drain_timeout, write_gate};
+use crate::core::backup::{create_backup, BackupConfig};
 use crate::core::shared::state::AppState;
 use chrono::{DateTime, Duration, Utc};
 use cron::Schedule;
@@ -40,6 +42,12 @@ pub struct TaskExecution {
     pub duration_ms: Option<i64>,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueueDepth {
+    pub due: usize,
+    pub running: usize,
+}
+
 #[derive(Clone)]
 pub struct TaskScheduler {
     state: Arc<AppState>,
@@ -81,6 +89,12 @@ type TaskHandler = Arc<
         + Sync,
 >;
 
+/// Whether a task runs the whole-stack backup: a `backup` task whose payload `type` is
+/// `full` or left out.
+fn is_full_backup(task_type: &str, payload: &serde_json::Value) -> bool {
+    task_type == "backup" && !matches!(payload["type"].as_str(), Some("database" | "files"))
+}
+
 impl TaskScheduler {
     pub fn new(state: Arc<AppState>) -> Self {
         let scheduler = Self {
@@ -179,10 +193,24 @@ impl TaskScheduler {
                                     "backup_file": backup_file
                                 }))
                             }
-                            _ => Ok(serde_json::json!({
-                                "status": "completed",
-                                "message": "Full backup completed"
-                            })),
+                            _ => {
+                                let config = BackupConfig::from_env();
+                                config.check()?;
+
+                                // Hold API writes back so the database dump and the drive
+                                // copy match, then write buffered sheet edits to the drive.
+                                let _quiesced = write_gate().quiesce(drain_timeout()).await;
+                                #[cfg(feature = "sheet")]
+                                crate::sheet::storage::flush_all_sheets(&state).await;
+
+                                let report = create_backup(&config, state.drive.as_ref()).await?;
+                                Ok(serde_json::json!({
+                                    "status": "completed",
+                                    "backup_file": report.path,
+                                    "entries": report.entries,
+                                    "drive_changed": report.drive_changed.len()
+                                }))
+                            }
                         }
                     })
                 }),
@@ -263,6 +291,10 @@ impl TaskScheduler {
         cron_expression: String,
         payload: serde_json::Value,
     ) -> Result<ScheduledTask, Box<dyn std::error::Error + Send + Sync>> {
+        if is_full_backup(&task_type, &payload) {
+            // Every run would fail; refuse the schedule instead.
+            BackupConfig::from_env().check()?;
+        }
         let schedule = Schedule::from_str(&cron_expression)?;
         let next_run = schedule
             .upcoming(chrono::Local)
@@ -413,6 +445,23 @@ impl TaskScheduler {
         running.insert(task_id, handle);
     }
 
+    /// Enabled tasks past their run time that have not started yet, and tasks running now.
+    pub async fn queue_depth(&self) -> QueueDepth {
+        let now = Utc::now();
+        let running = self.running_tasks.read().await;
+        let due = self
+            .scheduled_tasks
+            .read()
+            .await
+            .iter()
+            .filter(|t| t.enabled && t.next_run <= now && !running.contains_key(&t.id))
+            .count();
+        QueueDepth {
+            due,
+            running: running.len(),
+        }
+    }
+
     pub async fn stop_task(
         &self,
         task_id: Uuid,
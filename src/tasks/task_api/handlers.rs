@@ -1,4 +1,5 @@
 //! HTTP handlers for task API
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::state::AppState;
 use crate::tasks::task_api::{html_renderers, utils};
 use crate::tasks::types::TaskResponse;
@@ -15,7 +16,7 @@ use uuid::Uuid;
 /// Handler for task creation
 pub async fn handle_task_create(
     State(state): State<Arc<AppState>>,
-    Json(payload): Json<crate::tasks::types::CreateTaskRequest>,
+    ApiJson(payload): ApiJson<crate::tasks::types::CreateTaskRequest>,
 ) -> Result<Json<TaskResponse>, StatusCode> {
     let task_engine = &state.task_engine;
 
@@ -32,7 +33,7 @@ pub async fn handle_task_create(
 pub async fn handle_task_update(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(payload): Json<crate::tasks::types::TaskUpdate>,
+    ApiJson(payload): ApiJson<crate::tasks::types::TaskUpdate>,
 ) -> Result<Json<TaskResponse>, StatusCode> {
     let task_engine = &state.task_engine;
 
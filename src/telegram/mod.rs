@@ -1,7 +1,11 @@
 use crate::core::bot::BotOrchestrator;
 use crate::core::bot::channels::telegram::TelegramAdapter;
+use crate::core::bot::channels::pacing::{send_paced, MessagePacing};
+use crate::core::config::ConfigManager;
 use crate::core::bot::channels::ChannelAdapter;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::models::{BotResponse, UserSession};
+use crate::core::shared::message_bus::notify_attendants;
 use crate::core::shared::state::{AppState, AttendantNotification};
 use axum::{
     extract::State,
@@ -178,7 +182,7 @@ pub fn configure() -> Router<Arc<AppState>> {
 
 pub async fn handle_webhook(
     State(state): State<Arc<AppState>>,
-    Json(update): Json<TelegramUpdate>,
+    ApiJson(update): ApiJson<TelegramUpdate>,
 ) -> impl IntoResponse {
     info!("Telegram webhook received: update_id={}", update.update_id);
 
@@ -397,7 +401,13 @@ async fn route_to_bot(
     let (tx, mut rx) = tokio::sync::mpsc::channel::<BotResponse>(10);
     let orchestrator = BotOrchestrator::new(state.clone());
 
-    let adapter = TelegramAdapter::new(state.conn.clone(), session.bot_id);
+    let adapter: Arc<dyn ChannelAdapter> =
+        Arc::new(TelegramAdapter::new(state.conn.clone(), session.bot_id));
+    let pacing = MessagePacing::for_channel(
+        &ConfigManager::new(state.conn.clone()),
+        session.bot_id,
+        "telegram",
+    );
     let chat_id_clone = chat_id.to_string();
 
     tokio::spawn(async move {
@@ -423,9 +433,7 @@ async fn route_to_bot(
                         "telegram",
                     );
 
-                    if let Err(e) = adapter.send_message(tg_response).await {
-                        error!("Failed to send Telegram response: {}", e);
-                    }
+                    send_paced(Arc::clone(&adapter), &pacing, tg_response).await;
 
                     // Reset buffer after sending
                     accumulated_content.clear();
@@ -486,13 +494,7 @@ async fn route_to_attendant(
         priority: 1,
     };
 
-    if let Some(broadcast_tx) = state.attendant_broadcast.as_ref() {
-        if let Err(e) = broadcast_tx.send(notification.clone()) {
-            debug!("No attendants listening: {}", e);
-        } else {
-            info!("Notification sent to attendants");
-        }
-    }
+    notify_attendants(state, &notification).await;
 
     Ok(())
 }
@@ -521,7 +523,7 @@ pub struct SendMessageRequest {
 
 pub async fn send_message(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<SendMessageRequest>,
+    ApiJson(request): ApiJson<SendMessageRequest>,
 ) -> impl IntoResponse {
     info!("Sending Telegram message to {}", request.to);
 
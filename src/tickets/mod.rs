@@ -1,5 +1,6 @@
 pub mod ui;
 
+use crate::core::shared::api_json::ApiJson;
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -233,7 +234,7 @@ fn generate_ticket_number(conn: &mut diesel::PgConnection, org_id: Uuid) -> Stri
 
 pub async fn create_ticket(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateTicketRequest>,
+    ApiJson(req): ApiJson<CreateTicketRequest>,
 ) -> Result<Json<SupportTicket>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -378,7 +379,7 @@ pub async fn get_ticket_with_comments(
 pub async fn update_ticket(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateTicketRequest>,
+    ApiJson(req): ApiJson<UpdateTicketRequest>,
 ) -> Result<Json<SupportTicket>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -439,7 +440,7 @@ pub async fn update_ticket(
 pub async fn assign_ticket(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<AssignTicketRequest>,
+    ApiJson(req): ApiJson<AssignTicketRequest>,
 ) -> Result<Json<SupportTicket>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -461,7 +462,7 @@ pub async fn assign_ticket(
 pub async fn change_status(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<ChangeStatusRequest>,
+    ApiJson(req): ApiJson<ChangeStatusRequest>,
 ) -> Result<Json<SupportTicket>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -501,7 +502,7 @@ pub async fn resolve_ticket(
     change_status(
         State(state),
         Path(id),
-        Json(ChangeStatusRequest {
+        ApiJson(ChangeStatusRequest {
             status: "resolved".to_string(),
             resolution: None,
         }),
@@ -516,7 +517,7 @@ pub async fn close_ticket(
     change_status(
         State(state),
         Path(id),
-        Json(ChangeStatusRequest {
+        ApiJson(ChangeStatusRequest {
             status: "closed".to_string(),
             resolution: None,
         }),
@@ -565,7 +566,7 @@ pub async fn delete_ticket(
 pub async fn add_comment(
     State(state): State<Arc<AppState>>,
     Path(ticket_id): Path<Uuid>,
-    Json(req): Json<CreateCommentRequest>,
+    ApiJson(req): ApiJson<CreateCommentRequest>,
 ) -> Result<Json<TicketComment>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -745,7 +746,7 @@ pub async fn list_canned_responses(
 
 pub async fn create_canned_response(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateCannedResponseRequest>,
+    ApiJson(req): ApiJson<CreateCannedResponseRequest>,
 ) -> Result<Json<TicketCannedResponse>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -799,7 +800,7 @@ pub async fn list_categories(
 
 pub async fn create_category(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateCategoryRequest>,
+    ApiJson(req): ApiJson<CreateCategoryRequest>,
 ) -> Result<Json<TicketCategory>, (StatusCode, String)> {
     let mut conn = state.conn.get().map_err(|e| {
         (StatusCode::INTERNAL_SERVER_ERROR, format!("DB error: {e}"))
@@ -29,6 +29,7 @@
 
 pub mod bm25_config;
 pub mod hybrid_search;
+pub mod source_chunks;
 pub mod vectordb_indexer;
 
 
@@ -0,0 +1,241 @@
+//! Vector points per ingested source document.
+//!
+//! Chunk point ids are derived from the collection, the source and the chunk index, so
+//! re-ingesting a document overwrites its chunks in place. The ids written for each
+//! source are recorded in `kb_source_chunks`; on re-ingestion the ids the new version no
+//! longer produces are deleted before the new chunks are upserted. Sources ingested
+//! before they were tracked, or with a different chunking configuration, are cleared
+//! with a payload filter instead.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, Nullable, Text, Uuid as DieselUuid};
+use diesel::PgConnection;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Point id of chunk `chunk_index` of `source` in `collection`.
+pub fn chunk_point_id(collection: &str, source: &str, chunk_index: usize) -> Uuid {
+    let name = format!("{}\n{}\n{}", collection, source, chunk_index);
+    Uuid::new_v5(&Uuid::NAMESPACE_URL, name.as_bytes())
+}
+
+#[derive(Debug, Clone)]
+pub struct ChunkPoint {
+    pub id: Uuid,
+    pub vector: Vec<f32>,
+    pub payload: HashMap<String, serde_json::Value>,
+}
+
+#[async_trait]
+pub trait ChunkStore: Send + Sync {
+    /// Inserts the points, replacing any with the same id.
+    async fn upsert_chunks(&self, collection: &str, points: Vec<ChunkPoint>) -> Result<()>;
+    async fn delete_chunks(&self, collection: &str, ids: &[Uuid]) -> Result<()>;
+    /// Deletes every point whose payload names `source` as its document.
+    async fn delete_source(&self, collection: &str, source: &str) -> Result<()>;
+}
+
+/// Replaces the indexed chunks of `source` with `points` and returns the ids now stored
+/// for it. `previous` is the tracked id list, or `None` when the source was never tracked.
+pub async fn replace_source_chunks(
+    store: &dyn ChunkStore,
+    collection: &str,
+    source: &str,
+    previous: Option<&[Uuid]>,
+    points: Vec<ChunkPoint>,
+) -> Result<Vec<Uuid>> {
+    let ids: Vec<Uuid> = points.iter().map(|p| p.id).collect();
+    match previous {
+        Some(previous) => {
+            let current: HashSet<&Uuid> = ids.iter().collect();
+            let obsolete: Vec<Uuid> = previous
+                .iter()
+                .filter(|id| !current.contains(id))
+                .copied()
+                .collect();
+            if !obsolete.is_empty() {
+                store.delete_chunks(collection, &obsolete).await?;
+            }
+        }
+        None => store.delete_source(collection, source).await?,
+    }
+    store.upsert_chunks(collection, points).await?;
+    Ok(ids)
+}
+
+/// What was recorded for a source at its last ingestion.
+#[derive(Debug, Clone, QueryableByName)]
+pub struct TrackedSource {
+    #[diesel(sql_type = Array<DieselUuid>)]
+    pub chunk_ids: Vec<Uuid>,
+    /// Chunking configuration descriptor, unset for sources tracked before it was recorded.
+    #[diesel(sql_type = Nullable<Text>)]
+    pub chunking: Option<String>,
+}
+
+/// Tracking record of `source`, or `None` if it has not been ingested since tracking
+/// started.
+pub fn load_source_chunks(
+    conn: &mut PgConnection,
+    collection: &str,
+    source: &str,
+) -> QueryResult<Option<TrackedSource>> {
+    diesel::sql_query(
+        "SELECT chunk_ids, chunking FROM kb_source_chunks \
+         WHERE collection_name = $1 AND source = $2",
+    )
+    .bind::<Text, _>(collection)
+    .bind::<Text, _>(source)
+    .get_result::<TrackedSource>(conn)
+    .optional()
+}
+
+pub fn save_source_chunks(
+    conn: &mut PgConnection,
+    collection: &str,
+    source: &str,
+    chunk_ids: &[Uuid],
+    chunking: &str,
+) -> QueryResult<()> {
+    diesel::sql_query(
+        "INSERT INTO kb_source_chunks (collection_name, source, chunk_ids, chunking, updated_at) \
+         VALUES ($1, $2, $3, $4, NOW()) \
+         ON CONFLICT (collection_name, source) \
+         DO UPDATE SET chunk_ids = EXCLUDED.chunk_ids, chunking = EXCLUDED.chunking, \
+         updated_at = NOW()",
+    )
+    .bind::<Text, _>(collection)
+    .bind::<Text, _>(source)
+    .bind::<Array<DieselUuid>, _>(chunk_ids)
+    .bind::<Text, _>(chunking)
+    .execute(conn)
+    .map(|_| ())
+}
+
+pub fn forget_source(conn: &mut PgConnection, collection: &str, source: &str) -> QueryResult<()> {
+    diesel::sql_query("DELETE FROM kb_source_chunks WHERE collection_name = $1 AND source = $2")
+        .bind::<Text, _>(collection)
+        .bind::<Text, _>(source)
+        .execute(conn)
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Point id -> source of the point.
+    #[derive(Default)]
+    struct MemoryStore {
+        points: Mutex<HashMap<Uuid, String>>,
+    }
+
+    #[async_trait]
+    impl ChunkStore for MemoryStore {
+        async fn upsert_chunks(&self, _collection: &str, points: Vec<ChunkPoint>) -> Result<()> {
+            let mut stored = self.points.lock().unwrap();
+            for point in points {
+                let source = point.payload["document_path"].as_str().unwrap().to_string();
+                stored.insert(point.id, source);
+            }
+            Ok(())
+        }
+
+        async fn delete_chunks(&self, _collection: &str, ids: &[Uuid]) -> Result<()> {
+            let mut stored = self.points.lock().unwrap();
+            for id in ids {
+                stored.remove(id);
+            }
+            Ok(())
+        }
+
+        async fn delete_source(&self, _collection: &str, source: &str) -> Result<()> {
+            self.points.lock().unwrap().retain(|_, s| s != source);
+            Ok(())
+        }
+    }
+
+    fn points(source: &str, chunks: usize) -> Vec<ChunkPoint> {
+        (0..chunks)
+            .map(|i| ChunkPoint {
+                id: chunk_point_id("kb", source, i),
+                vector: vec![i as f32],
+                payload: HashMap::from([(
+                    "document_path".to_string(),
+                    serde_json::Value::String(source.to_string()),
+                )]),
+            })
+            .collect()
+    }
+
+    fn ids_of(store: &MemoryStore, source: &str) -> HashSet<Uuid> {
+        let stored = store.points.lock().unwrap();
+        stored
+            .iter()
+            .filter(|(_, s)| s.as_str() == source)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_reingesting_changed_document_removes_obsolete_chunks() {
+        let store = MemoryStore::default();
+        // Chunks of a document indexed before tracking existed, with random ids.
+        store
+            .points
+            .lock()
+            .unwrap()
+            .insert(Uuid::new_v4(), "guide.pdf".to_string());
+        replace_source_chunks(&store, "kb", "faq.md", None, points("faq.md", 2))
+            .await
+            .unwrap();
+
+        let first = replace_source_chunks(&store, "kb", "guide.pdf", None, points("guide.pdf", 3))
+            .await
+            .unwrap();
+        assert_eq!(ids_of(&store, "guide.pdf"), first.iter().copied().collect());
+
+        // The new version of the document is shorter: its third chunk must go.
+        let second = replace_source_chunks(
+            &store,
+            "kb",
+            "guide.pdf",
+            Some(&first),
+            points("guide.pdf", 2),
+        )
+        .await
+        .unwrap();
+        assert_eq!(second.len(), 2);
+        assert_eq!(
+            ids_of(&store, "guide.pdf"),
+            second.iter().copied().collect()
+        );
+        assert!(!ids_of(&store, "guide.pdf").contains(&first[2]));
+
+        // Other sources are untouched.
+        assert_eq!(ids_of(&store, "faq.md").len(), 2);
+    }
+
+    #[test]
+    fn test_chunk_ids_are_stable_per_source_and_index() {
+        assert_eq!(
+            chunk_point_id("kb", "a.md", 0),
+            chunk_point_id("kb", "a.md", 0)
+        );
+        assert_ne!(
+            chunk_point_id("kb", "a.md", 0),
+            chunk_point_id("kb", "a.md", 1)
+        );
+        assert_ne!(
+            chunk_point_id("kb", "a.md", 0),
+            chunk_point_id("kb", "b.md", 0)
+        );
+        assert_ne!(
+            chunk_point_id("kb", "a.md", 0),
+            chunk_point_id("kb2", "a.md", 0)
+        );
+    }
+}
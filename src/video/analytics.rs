@@ -9,6 +9,7 @@ use std::sync::Arc;
 use tracing::error;
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::security::error_sanitizer::SafeErrorResponse;
 use crate::core::shared::state::AppState;
 use crate::core::shared::utils::DbPool;
@@ -325,7 +326,7 @@ pub async fn get_analytics_handler(
 
 pub async fn record_view_handler(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<RecordViewRequest>,
+    ApiJson(req): ApiJson<RecordViewRequest>,
 ) -> impl IntoResponse {
     let engine = AnalyticsEngine::new(state.conn.clone());
 
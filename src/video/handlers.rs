@@ -7,8 +7,10 @@ use std::sync::Arc;
 use tracing::{error, info};
 use uuid::Uuid;
 
+use crate::core::shared::api_json::ApiJson;
 use crate::security::error_sanitizer::SafeErrorResponse;
 use crate::core::shared::state::AppState;
+use crate::security::upload_policy::check_upload;
 
 use super::engine::VideoEngine;
 use super::models::*;
@@ -35,7 +37,7 @@ pub async fn list_projects(
 
 pub async fn create_project(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateProjectRequest>,
+    ApiJson(req): ApiJson<CreateProjectRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine.create_project(None, None, req).await {
@@ -77,7 +79,7 @@ pub async fn get_project(
 pub async fn update_project(
     State(state): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateProjectRequest>,
+    ApiJson(req): ApiJson<UpdateProjectRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine.update_project(id, req).await {
@@ -136,7 +138,7 @@ pub async fn get_clips(
 pub async fn add_clip(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<AddClipRequest>,
+    ApiJson(req): ApiJson<AddClipRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine.add_clip(project_id, req).await {
@@ -154,7 +156,7 @@ pub async fn add_clip(
 pub async fn update_clip(
     State(state): State<Arc<AppState>>,
     Path(clip_id): Path<Uuid>,
-    Json(req): Json<UpdateClipRequest>,
+    ApiJson(req): ApiJson<UpdateClipRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine.update_clip(clip_id, req).await {
@@ -193,7 +195,7 @@ pub async fn delete_clip(
 pub async fn split_clip_handler(
     State(state): State<Arc<AppState>>,
     Path(clip_id): Path<Uuid>,
-    Json(req): Json<SplitClipRequest>,
+    ApiJson(req): ApiJson<SplitClipRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine.split_clip(clip_id, req.at_ms).await {
@@ -238,7 +240,7 @@ pub async fn get_layers(
 pub async fn add_layer(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<AddLayerRequest>,
+    ApiJson(req): ApiJson<AddLayerRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine.add_layer(project_id, req).await {
@@ -259,7 +261,7 @@ pub async fn add_layer(
 pub async fn update_layer(
     State(state): State<Arc<AppState>>,
     Path(layer_id): Path<Uuid>,
-    Json(req): Json<UpdateLayerRequest>,
+    ApiJson(req): ApiJson<UpdateLayerRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine.update_layer(layer_id, req).await {
@@ -318,7 +320,7 @@ pub async fn get_audio_tracks(
 pub async fn add_audio_track(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<AddAudioRequest>,
+    ApiJson(req): ApiJson<AddAudioRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine.add_audio_track(project_id, req).await {
@@ -376,7 +378,7 @@ pub async fn get_keyframes(
 pub async fn add_keyframe(
     State(state): State<Arc<AppState>>,
     Path(layer_id): Path<Uuid>,
-    Json(req): Json<AddKeyframeRequest>,
+    ApiJson(req): ApiJson<AddKeyframeRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine.add_keyframe(layer_id, req).await {
@@ -451,6 +453,15 @@ pub async fn upload_media(
             }
         };
 
+        if let Err(rejection) =
+            check_upload("video-upload", &file_name, &content_type, &data).await
+        {
+            return (
+                rejection.status(),
+                Json(serde_json::json!({ "error": rejection.to_string() })),
+            );
+        }
+
         let file_size = data.len() as u64;
         let safe_name = format!("{}_{}", project_id, sanitize_filename(&file_name));
         let file_path = format!("{}/{}", upload_dir, safe_name);
@@ -540,7 +551,7 @@ pub async fn get_preview_frame(
 pub async fn transcribe_handler(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<TranscribeRequest>,
+    ApiJson(req): ApiJson<TranscribeRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     match engine
@@ -561,7 +572,7 @@ pub async fn transcribe_handler(
 pub async fn generate_captions_handler(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<GenerateCaptionsRequest>,
+    ApiJson(req): ApiJson<GenerateCaptionsRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
 
@@ -614,7 +625,7 @@ pub async fn generate_captions_handler(
 pub async fn tts_handler(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<TTSRequest>,
+    ApiJson(req): ApiJson<TTSRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     let output_dir =
@@ -709,7 +720,7 @@ pub async fn detect_scenes_handler(
 pub async fn auto_reframe_handler(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<AutoReframeRequest>,
+    ApiJson(req): ApiJson<AutoReframeRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
     let clips = match engine.get_clips(project_id).await {
@@ -769,7 +780,7 @@ pub async fn auto_reframe_handler(
 pub async fn remove_background_handler(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<BackgroundRemovalRequest>,
+    ApiJson(req): ApiJson<BackgroundRemovalRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
 
@@ -791,7 +802,7 @@ pub async fn remove_background_handler(
 pub async fn enhance_video_handler(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<VideoEnhanceRequest>,
+    ApiJson(req): ApiJson<VideoEnhanceRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
 
@@ -810,7 +821,7 @@ pub async fn enhance_video_handler(
 pub async fn beat_sync_handler(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<BeatSyncRequest>,
+    ApiJson(req): ApiJson<BeatSyncRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
 
@@ -832,7 +843,7 @@ pub async fn beat_sync_handler(
 pub async fn generate_waveform_handler(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<WaveformRequest>,
+    ApiJson(req): ApiJson<WaveformRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
 
@@ -896,7 +907,7 @@ pub async fn list_templates(State(_state): State<Arc<AppState>>) -> impl IntoRes
 pub async fn apply_template_handler(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<ApplyTemplateRequest>,
+    ApiJson(req): ApiJson<ApplyTemplateRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
 
@@ -923,7 +934,7 @@ pub async fn apply_template_handler(
 pub async fn add_transition_handler(
     State(state): State<Arc<AppState>>,
     Path((from_id, to_id)): Path<(Uuid, Uuid)>,
-    Json(req): Json<TransitionRequest>,
+    ApiJson(req): ApiJson<TransitionRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
 
@@ -948,7 +959,7 @@ pub async fn add_transition_handler(
 pub async fn chat_edit(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<ChatEditRequest>,
+    ApiJson(req): ApiJson<ChatEditRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
 
@@ -975,7 +986,7 @@ pub async fn chat_edit(
 pub async fn start_export(
     State(state): State<Arc<AppState>>,
     Path(project_id): Path<Uuid>,
-    Json(req): Json<ExportRequest>,
+    ApiJson(req): ApiJson<ExportRequest>,
 ) -> impl IntoResponse {
     let engine = VideoEngine::new(state.conn.clone());
 
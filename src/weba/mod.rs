@@ -1,3 +1,4 @@
+use crate::core::shared::api_json::ApiJson;
 use axum::{
     extract::{Path, Query, State},
     response::{Html, IntoResponse},
@@ -187,7 +188,7 @@ async fn list_apps(
 
 async fn create_app(
     State(state): State<Arc<WebaState>>,
-    Json(req): Json<CreateAppRequest>,
+    ApiJson(req): ApiJson<CreateAppRequest>,
 ) -> Json<WebApp> {
     let now = chrono::Utc::now();
     let id = Uuid::new_v4();
@@ -225,7 +226,7 @@ async fn get_app(
 async fn update_app(
     State(state): State<Arc<WebaState>>,
     Path(id): Path<Uuid>,
-    Json(req): Json<UpdateAppRequest>,
+    ApiJson(req): ApiJson<UpdateAppRequest>,
 ) -> Result<Json<WebApp>, axum::http::StatusCode> {
     let mut apps = state.apps.write().await;
 
@@ -281,7 +282,7 @@ async fn list_pages(
 async fn create_page(
     State(state): State<Arc<WebaState>>,
     Path(app_id): Path<Uuid>,
-    Json(req): Json<CreatePageRequest>,
+    ApiJson(req): ApiJson<CreatePageRequest>,
 ) -> Result<Json<WebAppPage>, axum::http::StatusCode> {
     let apps = state.apps.read().await;
     if !apps.contains_key(&app_id) {
@@ -326,7 +327,7 @@ async fn get_page(
 async fn update_page(
     State(state): State<Arc<WebaState>>,
     Path((app_id, page_id)): Path<(Uuid, Uuid)>,
-    Json(req): Json<CreatePageRequest>,
+    ApiJson(req): ApiJson<CreatePageRequest>,
 ) -> Result<Json<WebAppPage>, axum::http::StatusCode> {
     let mut pages = state.pages.write().await;
 
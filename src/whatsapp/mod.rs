@@ -1,17 +1,23 @@
 use crate::core::bot::{BotOrchestrator, get_default_bot};
 use crate::multimodal::BotModelsClient;
-use crate::core::bot::channels::whatsapp::WhatsAppAdapter;
+use crate::core::bot::channels::pacing::{send_paced, MessagePacing};
+use crate::core::bot::channels::whatsapp::{remember_inbound_message, WhatsAppAdapter};
 use crate::core::bot::channels::ChannelAdapter;
 use crate::core::config::ConfigManager;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::models::{BotResponse, UserMessage, UserSession};
+use crate::core::shared::message_bus::notify_attendants;
 use crate::core::shared::state::{AppState, AttendantNotification};
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
+use crate::security::channel_webhook::{
+    verify_meta_webhook, META_SIGNATURE_HEADER, WHATSAPP_APP_SECRET,
+};
 use botlib::MessageType;
 use chrono::Utc;
 use diesel::prelude::*;
@@ -247,6 +253,7 @@ pub async fn verify_webhook(
 pub async fn handle_webhook(
     State(state): State<Arc<AppState>>,
     Path(bot_id_str): Path<String>,
+    headers: HeaderMap,
     body: axum::body::Bytes,
 ) -> impl IntoResponse {
     let bot_id = match resolve_bot_id(&bot_id_str, &state).await {
@@ -254,6 +261,14 @@ pub async fn handle_webhook(
         Err(err) => return err.0,
     };
 
+    let signature = headers
+        .get(META_SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok());
+    if let Err(e) = verify_meta_webhook(Some(&bot_id), WHATSAPP_APP_SECRET, &body, signature).await {
+        warn!("Rejected WhatsApp webhook for bot {}: {}", bot_id, e);
+        return StatusCode::UNAUTHORIZED;
+    }
+
     log::info!("[BASIC_EXEC] WhatsApp webhook received for bot_id={}", bot_id);
     debug!("Raw webhook body: {}", String::from_utf8_lossy(&body));
 
@@ -507,6 +522,11 @@ async fn process_incoming_message(
         .clone()
         .unwrap_or_else(|| message.from.clone());
     let name = contact_name.clone().unwrap_or_else(|| phone.clone());
+    // Only kept for the typing indicator, which is sent when replies are paced.
+    let config = ConfigManager::new(state.conn.clone());
+    if MessagePacing::for_channel(&config, *bot_id, "whatsapp").enabled {
+        remember_inbound_message(&phone, &message.id);
+    }
 
     let mut content = extract_message_content(message);
     
@@ -991,6 +1011,11 @@ async fn route_to_bot(
 
     let phone_for_error = phone.clone();
     let adapter_for_send = WhatsAppAdapter::new(&state, session.bot_id);
+    let pacing = MessagePacing::for_channel(
+        &ConfigManager::new(state.conn.clone()),
+        session.bot_id,
+        "whatsapp",
+    );
     let bot_id_for_voice = session.bot_id;
     let state_clone = state.clone();
 
@@ -1131,6 +1156,7 @@ async fn route_to_bot(
         /// Send a WhatsApp message part
         async fn send_part(
             adapter: &crate::core::bot::channels::whatsapp::WhatsAppAdapter,
+            pacing: &MessagePacing,
             phone: &str,
             content: String,
             is_final: bool,
@@ -1153,9 +1179,7 @@ async fn route_to_bot(
                 context_max_length: 0,
             };
 
-            if let Err(e) = adapter.send_message(wa_response).await {
-                log::error!("Failed to send WhatsApp response part: {}", e);
-            }
+            send_paced(Arc::new(adapter.clone()), pacing, wa_response).await;
             // Rate limiting is handled by WhatsAppAdapter::send_whatsapp_message
         }
 
@@ -1172,7 +1196,7 @@ async fn route_to_bot(
                     // Send what we have and stop
                     if !buffer.trim().is_empty() {
                         let clean_buffer = buffer.trim_end();
-                        send_part(&adapter_for_send, &phone, clean_buffer.to_string(), true).await;
+                        send_part(&adapter_for_send, &pacing, &phone, clean_buffer.to_string(), true).await;
                     }
                     break;
                 }
@@ -1205,7 +1229,7 @@ async fn route_to_bot(
                     // Step 2: Send text before list (if not empty)
                     if !text_before.trim().is_empty() {
                         info!("WA sending text before list, len={}", text_before.len());
-                        send_part(&adapter_for_send, &phone, text_before, false).await;
+                        send_part(&adapter_for_send, &pacing, &phone, text_before, false).await;
                     }
 
                     // Step 3: Split list from text after
@@ -1214,7 +1238,7 @@ async fn route_to_bot(
                     // Step 4: Send list (isolated)
                     if !list.trim().is_empty() {
                         info!("WA sending isolated list, len={}", list.len());
-                        send_part(&adapter_for_send, &phone, list, false).await;
+                        send_part(&adapter_for_send, &pacing, &phone, list, false).await;
                     }
 
                     // Step 5: Keep text after in buffer
@@ -1229,10 +1253,10 @@ async fn route_to_bot(
                     if buffer.len() > MAX_WHATSAPP_LENGTH {
                         let parts = adapter_for_send.split_message_smart(&buffer, MAX_WHATSAPP_LENGTH);
                         for part in parts {
-                            send_part(&adapter_for_send, &phone, part, is_final).await;
+                            send_part(&adapter_for_send, &pacing, &phone, part, is_final).await;
                         }
                     } else {
-                        send_part(&adapter_for_send, &phone, buffer.clone(), is_final).await;
+                        send_part(&adapter_for_send, &pacing, &phone, buffer.clone(), is_final).await;
                     }
                     buffer.clear();
                 } else {
@@ -1257,10 +1281,10 @@ async fn route_to_bot(
                     if buffer.len() > MAX_WHATSAPP_LENGTH {
                         let parts = adapter_for_send.split_message_smart(&buffer, MAX_WHATSAPP_LENGTH);
                         for part in parts {
-                            send_part(&adapter_for_send, &phone, part, is_final).await;
+                            send_part(&adapter_for_send, &pacing, &phone, part, is_final).await;
                         }
                     } else {
-                        send_part(&adapter_for_send, &phone, buffer.clone(), is_final).await;
+                        send_part(&adapter_for_send, &pacing, &phone, buffer.clone(), is_final).await;
                     }
                     buffer.clear();
                 }
@@ -1372,13 +1396,7 @@ async fn route_to_attendant(
         priority,
     };
 
-    if let Some(broadcast_tx) = state.attendant_broadcast.as_ref() {
-        if let Err(e) = broadcast_tx.send(notification.clone()) {
-            debug!("No attendants listening: {}", e);
-        } else {
-            info!("Notification sent to attendants");
-        }
-    }
+    notify_attendants(state, &notification).await;
 
     update_queue_item(state, session, content).await?;
 
@@ -1473,7 +1491,7 @@ pub struct SendMessageRequest {
 
 pub async fn send_message(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<SendMessageRequest>,
+    ApiJson(request): ApiJson<SendMessageRequest>,
 ) -> impl IntoResponse {
     info!("Sending WhatsApp message to {}", request.to);
 
@@ -1522,7 +1540,7 @@ pub struct AttendantRespondRequest {
 
 pub async fn attendant_respond(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<AttendantRespondRequest>,
+    ApiJson(request): ApiJson<AttendantRespondRequest>,
 ) -> impl IntoResponse {
     info!(
         "Attendant {} responding to session {}",
@@ -1624,22 +1642,20 @@ pub async fn attendant_respond(
             }
         }
         _ => {
-            if let Some(broadcast_tx) = state.attendant_broadcast.as_ref() {
-                let notification = AttendantNotification {
-                    notification_type: "attendant_response".to_string(),
-                    session_id: session.id.to_string(),
-                    user_id: session.user_id.to_string(),
-                    user_name: None,
-                    user_phone: None,
-                    channel: channel.to_string(),
-                    content: request.message.clone(),
-                    timestamp: Utc::now().to_rfc3339(),
-                    assigned_to: Some(request.attendant_id.clone()),
-                    priority: 0,
-                };
+            let notification = AttendantNotification {
+                notification_type: "attendant_response".to_string(),
+                session_id: session.id.to_string(),
+                user_id: session.user_id.to_string(),
+                user_name: None,
+                user_phone: None,
+                channel: channel.to_string(),
+                content: request.message.clone(),
+                timestamp: Utc::now().to_rfc3339(),
+                assigned_to: Some(request.attendant_id.clone()),
+                priority: 0,
+            };
 
-                let _ = broadcast_tx.send(notification);
-            }
+            notify_attendants(&state, &notification).await;
 
             (
                 StatusCode::OK,
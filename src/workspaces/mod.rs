@@ -13,6 +13,7 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::core::bot::get_default_bot;
+use crate::core::shared::api_json::ApiJson;
 use crate::core::shared::schema::workspaces::{
     workspace_comments, workspace_members, workspace_page_versions, workspace_pages,
     workspaces as workspaces_table,
@@ -739,7 +740,7 @@ async fn list_workspaces(
 
 async fn create_workspace(
     State(state): State<Arc<AppState>>,
-    Json(req): Json<CreateWorkspaceRequest>,
+    ApiJson(req): ApiJson<CreateWorkspaceRequest>,
 ) -> Result<Json<Workspace>, WorkspacesError> {
     let mut conn = state
         .conn
@@ -843,7 +844,7 @@ async fn get_workspace(
 async fn update_workspace(
     State(state): State<Arc<AppState>>,
     Path(workspace_id): Path<Uuid>,
-    Json(req): Json<UpdateWorkspaceRequest>,
+    ApiJson(req): ApiJson<UpdateWorkspaceRequest>,
 ) -> Result<Json<Workspace>, WorkspacesError> {
     let mut conn = state
         .conn
@@ -991,7 +992,7 @@ async fn list_pages(
 async fn create_page(
     State(state): State<Arc<AppState>>,
     Path(workspace_id): Path<Uuid>,
-    Json(req): Json<CreatePageRequest>,
+    ApiJson(req): ApiJson<CreatePageRequest>,
 ) -> Result<Json<Page>, WorkspacesError> {
     let mut conn = state
         .conn
@@ -1073,7 +1074,7 @@ async fn get_page(
 async fn update_page(
     State(state): State<Arc<AppState>>,
     Path(page_id): Path<Uuid>,
-    Json(req): Json<UpdatePageRequest>,
+    ApiJson(req): ApiJson<UpdatePageRequest>,
 ) -> Result<Json<Page>, WorkspacesError> {
     let mut conn = state
         .conn
@@ -1149,7 +1150,7 @@ async fn delete_page(
 async fn add_member(
     State(state): State<Arc<AppState>>,
     Path(workspace_id): Path<Uuid>,
-    Json(req): Json<AddMemberRequest>,
+    ApiJson(req): ApiJson<AddMemberRequest>,
 ) -> Result<StatusCode, WorkspacesError> {
     let mut conn = state
         .conn